@@ -12,6 +12,7 @@ use uuid::Uuid;
 use chrono::Utc;
 
 use super::types::*;
+use crate::storage::{HttpClientKey, SendRetry, shared_http_client};
 
 /// API基础URL / API base URL
 const API_BASE: &str = "https://open-api.123pan.com";
@@ -113,9 +114,7 @@ pub struct ApiClient {
 impl ApiClient {
     /// 创建API客户端（使用共享的config）/ Create API client (with shared config)
     pub fn new(config: Arc<Mutex<Pan123OpenConfig>>) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
+        let client = shared_http_client(&HttpClientKey::new().timeout(30))
             .expect("Failed to create HTTP client");
 
         let mut rate_limiters = std::collections::HashMap::new();
@@ -217,7 +216,7 @@ impl ApiClient {
                 req = req.json(b);
             }
 
-            let resp = req.send().await.map_err(|e| format!("Request failed: {}", e))?;
+            let resp = req.send_retry().await.map_err(|e| format!("Request failed: {}", e))?;
             let bytes = resp.bytes().await.map_err(|e| format!("Read response failed: {}", e))?;
 
             // 先解析基础响应检查错误码 / Parse base response to check error code first
@@ -264,7 +263,7 @@ impl ApiClient {
                         ("grant_type", "refresh_token"),
                         ("refresh_token", config.refresh_token.as_str()),
                     ])
-                    .send()
+                    .send_retry()
                     .await
                     .map_err(|e| format!("Refresh token request failed: {}", e))?
                     .json()
@@ -286,7 +285,7 @@ impl ApiClient {
                         client_id: config.client_id.clone(),
                         client_secret: config.client_secret.clone(),
                     })
-                    .send()
+                    .send_retry()
                     .await
                     .map_err(|e| format!("Get access token request failed: {}", e))?
                     .json()
@@ -748,14 +748,14 @@ impl DriverFactory for Pan123OpenDriverFactory {
                 .title("客户端ID")
                 .required()
                 .help("从123云盘开发者平台获取 "),
-            ConfigItem::new("client_secret", "string")
+            ConfigItem::new("client_secret", "string").password()
                 .title("客户端密钥")
                 .required()
                 .help("从123云盘开发者平台获取"),
-            ConfigItem::new("access_token", "string")
+            ConfigItem::new("access_token", "string").password()
                 .title("访问令牌")
                 .help("可选，自动刷新"),
-            ConfigItem::new("refresh_token", "string")
+            ConfigItem::new("refresh_token", "string").password()
                 .title("刷新令牌")
                 .help("OAuth2模式使用"),
             ConfigItem::new("upload_thread", "number")
@@ -765,7 +765,7 @@ impl DriverFactory for Pan123OpenDriverFactory {
             ConfigItem::new("direct_link", "bool")
                 .title("使用直链")
                 .default("false"),
-            ConfigItem::new("direct_link_private_key", "string")
+            ConfigItem::new("direct_link_private_key", "string").password()
                 .title("直链私钥")
                 .help("URL鉴权私钥"),
             ConfigItem::new("direct_link_valid_duration", "number")
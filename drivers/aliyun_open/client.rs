@@ -8,6 +8,7 @@ use std::time::Duration;
 use tokio::sync::RwLock;
 
 use super::types::*;
+use crate::storage::{HttpClientKey, SendRetry, shared_http_client_or_fallback};
 
 /// 阿里云盘 Open 客户端
 pub struct AliyunOpenClient {
@@ -24,10 +25,7 @@ impl AliyunOpenClient {
         client_secret: String,
         refresh_token: String,
     ) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(300))
-            .build()
-            .unwrap();
+        let client = shared_http_client_or_fallback(&HttpClientKey::new().timeout(300));
 
         Self {
             client,
@@ -73,7 +71,7 @@ impl AliyunOpenClient {
         let resp = self.client
             .post(&url)
             .json(&body)
-            .send()
+            .send_retry()
             .await?;
 
         let text = resp.text().await?;
@@ -119,7 +117,7 @@ impl AliyunOpenClient {
                 req = req.json(b);
             }
 
-            let resp = req.send().await?;
+            let resp = req.send_retry().await?;
             let text = resp.text().await?;
 
             // 检查错误
@@ -473,7 +473,7 @@ impl DriverFactory for AliyunOpenDriverFactory {
 
     fn additional_items(&self) -> Vec<ConfigItem> {
         vec![
-            ConfigItem::new("refresh_token", "string")
+            ConfigItem::new("refresh_token", "string").password()
                 .title("刷新令牌")
                 .required()
                 .help("从阿里云盘开放平台获取的 refresh_token"),
@@ -493,7 +493,7 @@ impl DriverFactory for AliyunOpenDriverFactory {
             ConfigItem::new("client_id", "string")
                 .title("客户端 ID")
                 .help("自定义应用的客户端 ID（可选）"),
-            ConfigItem::new("client_secret", "string")
+            ConfigItem::new("client_secret", "string").password()
                 .title("应用密钥")
                 .help("自定义应用的密钥（可选）"),
             ConfigItem::new("remove_way", "select")
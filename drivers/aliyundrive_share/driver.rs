@@ -0,0 +1,439 @@
+//! 阿里云盘分享驱动实现
+//! 参考 OpenList 的 aliyundrive_share 驱动
+//!
+//! 列表只需分享令牌，获取下载链接需要登录（refresh_token）。下载链接校验Referer，只能中转。
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::RwLock;
+
+use crate::storage::{
+    Capability, ConfigItem, DriverConfig, DriverFactory, Entry, HttpClientKey, ProgressCallback,
+    SendRetry, SpaceInfo, StorageDriver, shared_http_client_or_fallback,
+};
+use crate::storage::http_stream::{response_reader, with_range};
+use super::super::share_url;
+
+/// API地址
+const AUTH_API: &str = "https://auth.alipan.com/v2/account/token";
+const API_BASE: &str = "https://api.alipan.com";
+const REFERER: &str = "https://www.alipan.com/";
+const CANARY: &str = "client=web,app=share,version=v2.3.1";
+
+/// 阿里云盘分享配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AliyundriveShareConfig {
+    /// 分享ID或分享链接
+    pub share_id: String,
+    /// 提取码（可选）
+    #[serde(default)]
+    pub share_pwd: String,
+    /// 登录账号的 refresh_token（下载需要）
+    pub refresh_token: String,
+    /// 根目录ID（默认root）
+    #[serde(default = "default_root_id")]
+    pub root_id: String,
+}
+
+fn default_root_id() -> String {
+    "root".to_string()
+}
+
+/// 分享中的文件
+#[derive(Debug, Clone, Deserialize)]
+struct ShareFile {
+    file_id: String,
+    name: String,
+    /// file 或 folder
+    #[serde(rename = "type")]
+    file_type: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    updated_at: Option<String>,
+}
+
+/// 文件列表响应
+#[derive(Debug, Deserialize)]
+struct ListResponse {
+    #[serde(default)]
+    items: Vec<ShareFile>,
+    #[serde(default)]
+    next_marker: String,
+}
+
+/// 令牌
+#[derive(Debug, Default)]
+struct Tokens {
+    access_token: String,
+    refresh_token: String,
+    share_token: String,
+}
+
+/// 缓存的下载链接
+#[derive(Debug, Clone)]
+struct CachedDownloadUrl {
+    url: String,
+    expire_at: DateTime<Utc>,
+}
+
+/// 阿里云盘分享驱动
+pub struct AliyundriveShareDriver {
+    config: AliyundriveShareConfig,
+    share_id: String,
+    share_pwd: String,
+    client: Client,
+    tokens: RwLock<Tokens>,
+    /// 路径到file_id的缓存
+    path_cache: RwLock<HashMap<String, String>>,
+    /// 下载链接缓存 (file_id -> cached_url)
+    download_cache: RwLock<HashMap<String, CachedDownloadUrl>>,
+}
+
+impl AliyundriveShareDriver {
+    pub fn new(config: AliyundriveShareConfig) -> Self {
+        let share_id = share_url::share_id(&config.share_id);
+        let share_pwd = share_url::share_pwd(&config.share_pwd, &config.share_id);
+        let tokens = Tokens {
+            refresh_token: config.refresh_token.trim().to_string(),
+            ..Default::default()
+        };
+        Self {
+            config,
+            share_id,
+            share_pwd,
+            client: shared_http_client_or_fallback(&HttpClientKey::new().timeout(60)),
+            tokens: RwLock::new(tokens),
+            path_cache: RwLock::new(HashMap::new()),
+            download_cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 刷新 access_token（refresh_token 每次刷新都会轮换）
+    async fn refresh_access_token(&self) -> Result<()> {
+        let refresh_token = self.tokens.read().await.refresh_token.clone();
+        if refresh_token.is_empty() {
+            return Err(anyhow!("未配置 refresh_token"));
+        }
+        let json: Value = self.client
+            .post(AUTH_API)
+            .json(&json!({"refresh_token": refresh_token, "grant_type": "refresh_token"}))
+            .send_retry()
+            .await?
+            .json()
+            .await?;
+        let access_token = json["access_token"].as_str()
+            .ok_or_else(|| anyhow!("刷新 token 失败: {}", json["message"].as_str().unwrap_or("未知错误")))?;
+        let mut tokens = self.tokens.write().await;
+        tokens.access_token = access_token.to_string();
+        if let Some(new_refresh) = json["refresh_token"].as_str() {
+            tokens.refresh_token = new_refresh.to_string();
+        }
+        Ok(())
+    }
+
+    /// 获取分享令牌（约2小时有效）
+    async fn refresh_share_token(&self) -> Result<()> {
+        let json: Value = self.client
+            .post(format!("{}/v2/share_link/get_share_token", API_BASE))
+            .header("X-Canary", CANARY)
+            .header("Referer", REFERER)
+            .json(&json!({"share_id": self.share_id, "share_pwd": self.share_pwd}))
+            .send_retry()
+            .await?
+            .json()
+            .await?;
+        let share_token = json["share_token"].as_str()
+            .ok_or_else(|| anyhow!("获取分享令牌失败: {}", json["message"].as_str().unwrap_or("分享不存在或提取码错误")))?;
+        self.tokens.write().await.share_token = share_token.to_string();
+        Ok(())
+    }
+
+    /// 发送API请求，令牌失效时刷新后重试一次；with_auth 为 true 时带登录令牌
+    async fn request(&self, uri: &str, body: Value, with_auth: bool) -> Result<Value> {
+        if self.tokens.read().await.share_token.is_empty() {
+            self.refresh_share_token().await?;
+        }
+        if with_auth && self.tokens.read().await.access_token.is_empty() {
+            self.refresh_access_token().await?;
+        }
+
+        let mut retried = false;
+        loop {
+            let (share_token, access_token) = {
+                let tokens = self.tokens.read().await;
+                (tokens.share_token.clone(), tokens.access_token.clone())
+            };
+            let mut req = self.client
+                .post(format!("{}{}", API_BASE, uri))
+                .header("X-Canary", CANARY)
+                .header("Referer", REFERER)
+                .header("x-share-token", share_token)
+                .json(&body);
+            if with_auth {
+                req = req.header("Authorization", format!("Bearer {}", access_token));
+            }
+
+            let json: Value = req.send_retry().await?.json().await?;
+            let Some(code) = json.get("code").and_then(|c| c.as_str()) else {
+                return Ok(json);
+            };
+            let message = json["message"].as_str().unwrap_or("");
+            match code {
+                "ShareLinkTokenInvalid" | "InvalidParameter.ShareToken" if !retried => self.refresh_share_token().await?,
+                "AccessTokenInvalid" | "AccessTokenExpired" if with_auth && !retried => self.refresh_access_token().await?,
+                _ => return Err(anyhow!("阿里云盘API错误: {} - {}", code, message)),
+            }
+            retried = true;
+        }
+    }
+
+    /// 列出目录中的全部文件
+    async fn list_files(&self, parent_id: &str) -> Result<Vec<ShareFile>> {
+        let mut files = Vec::new();
+        let mut marker = String::new();
+        loop {
+            let body = json!({
+                "share_id": self.share_id,
+                "parent_file_id": parent_id,
+                "limit": 200,
+                "order_by": "name",
+                "order_direction": "ASC",
+                "marker": marker,
+            });
+            let json = self.request("/adrive/v3/file/list", body, false).await?;
+            let resp: ListResponse = serde_json::from_value(json)?;
+            files.extend(resp.items);
+            if resp.next_marker.is_empty() {
+                break;
+            }
+            marker = resp.next_marker;
+        }
+        Ok(files)
+    }
+
+    /// 通过路径获取目录file_id（逐级查找并缓存）
+    async fn get_file_id(&self, path: &str) -> Result<String> {
+        let mut id = self.config.root_id.clone();
+        let mut current = String::new();
+        for name in path.split('/').filter(|s| !s.is_empty()) {
+            let parent = std::mem::take(&mut current);
+            current = if parent.is_empty() { name.to_string() } else { format!("{}/{}", parent, name) };
+            if let Some(cached) = self.path_cache.read().await.get(&current) {
+                id = cached.clone();
+                continue;
+            }
+            let files = self.list_files(&id).await?;
+            self.cache_children(&parent, &files).await;
+            id = files.into_iter()
+                .find(|f| f.name == name && f.file_type == "folder")
+                .map(|f| f.file_id)
+                .ok_or_else(|| anyhow!("目录不存在: /{}", current))?;
+        }
+        Ok(id)
+    }
+
+    /// 在父目录中查找文件
+    async fn find(&self, path: &str) -> Result<ShareFile> {
+        let path = path.trim_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let parent_id = self.get_file_id(parent).await?;
+        let files = self.list_files(&parent_id).await?;
+        self.cache_children(parent, &files).await;
+        files.into_iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| anyhow!("路径不存在: /{}", path))
+    }
+
+    /// 缓存子目录的file_id
+    async fn cache_children(&self, parent: &str, files: &[ShareFile]) {
+        let mut cache = self.path_cache.write().await;
+        for f in files.iter().filter(|f| f.file_type == "folder") {
+            let key = if parent.is_empty() { f.name.clone() } else { format!("{}/{}", parent, f.name) };
+            cache.insert(key, f.file_id.clone());
+        }
+    }
+
+    /// 获取下载链接
+    async fn get_download_url(&self, file_id: &str) -> Result<String> {
+        if let Some(cached) = self.download_cache.read().await.get(file_id) {
+            if cached.expire_at > Utc::now() {
+                return Ok(cached.url.clone());
+            }
+        }
+
+        let body = json!({
+            "share_id": self.share_id,
+            "file_id": file_id,
+            "expire_sec": 600,
+        });
+        let json = self.request("/v2/file/get_share_link_download_url", body, true).await?;
+        let url = json["download_url"].as_str()
+            .or_else(|| json["url"].as_str())
+            .ok_or_else(|| anyhow!("获取下载链接失败"))?
+            .to_string();
+
+        // 链接有效期10分钟，提前1分钟过期
+        self.download_cache.write().await.insert(file_id.to_string(), CachedDownloadUrl {
+            url: url.clone(),
+            expire_at: Utc::now() + chrono::Duration::minutes(9),
+        });
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl StorageDriver for AliyundriveShareDriver {
+    fn name(&self) -> &str {
+        "AliyundriveShare"
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> Capability {
+        Capability {
+            can_range_read: true,
+            can_direct_link: false, // 下载链接校验Referer，不支持302
+            ..Default::default()
+        }
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        tracing::debug!("AliyundriveShare: 列出目录 {}", path);
+        let dir = path.trim_matches('/');
+        let parent_id = self.get_file_id(dir).await?;
+        let files = self.list_files(&parent_id).await?;
+        self.cache_children(dir, &files).await;
+
+        Ok(files.into_iter().map(|f| Entry {
+            path: format!("/{}", if dir.is_empty() { f.name.clone() } else { format!("{}/{}", dir, f.name) }),
+            is_dir: f.file_type == "folder",
+            size: f.size,
+            modified: f.updated_at,
+            name: f.name,
+        }).collect())
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        tracing::debug!("AliyundriveShare: 读取文件 {} range={:?}", path, range);
+        let file = self.find(path).await?;
+        if file.file_type == "folder" {
+            return Err(anyhow!("不能读取目录: {}", path));
+        }
+        let url = self.get_download_url(&file.file_id).await?;
+
+        let req = self.client.get(&url).header("Referer", REFERER);
+        let response = with_range(req, range.as_ref()).send_retry().await?;
+        response_reader(response, range)
+    }
+
+    async fn open_writer(
+        &self,
+        _path: &str,
+        _size_hint: Option<u64>,
+        _progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        Err(anyhow!("阿里云盘分享不支持上传"))
+    }
+
+    async fn delete(&self, _path: &str) -> Result<()> {
+        Err(anyhow!("阿里云盘分享不支持删除"))
+    }
+
+    async fn create_dir(&self, _path: &str) -> Result<()> {
+        Err(anyhow!("阿里云盘分享不支持创建文件夹"))
+    }
+
+    async fn rename(&self, _path: &str, _new_name: &str) -> Result<()> {
+        Err(anyhow!("阿里云盘分享不支持重命名"))
+    }
+
+    async fn move_item(&self, _old_path: &str, _new_path: &str) -> Result<()> {
+        Err(anyhow!("阿里云盘分享不支持移动"))
+    }
+
+    async fn get_direct_link(&self, _path: &str) -> Result<Option<String>> {
+        // 下载链接校验Referer，浏览器直接访问会被拒绝
+        Ok(None)
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        Ok(None) // 分享不提供空间信息
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        false
+    }
+
+    fn get_updated_config(&self) -> Option<Value> {
+        // refresh_token 刷新后即失效，需要保存轮换后的值，否则重启后无法登录；
+        // 正在刷新时跳过，下次保存时再取
+        let tokens = self.tokens.try_read().ok()?;
+        if tokens.refresh_token.is_empty() || tokens.refresh_token == self.config.refresh_token.trim() {
+            return None;
+        }
+        let mut config = self.config.clone();
+        config.refresh_token = tokens.refresh_token.clone();
+        serde_json::to_value(config).ok()
+    }
+}
+
+/// 阿里云盘分享驱动工厂
+pub struct AliyundriveShareDriverFactory;
+
+impl DriverFactory for AliyundriveShareDriverFactory {
+    fn driver_type(&self) -> &'static str {
+        "AliyundriveShare"
+    }
+
+    fn driver_config(&self) -> DriverConfig {
+        DriverConfig {
+            name: "阿里云盘分享".to_string(),
+            local_sort: true,
+            only_proxy: true, // 下载链接校验Referer，必须代理
+            no_cache: false,
+            no_upload: true, // 分享不支持上传
+            default_root: Some("root".to_string()),
+        }
+    }
+
+    fn additional_items(&self) -> Vec<ConfigItem> {
+        vec![
+            ConfigItem::new("share_id", "string")
+                .title("分享ID")
+                .help("分享链接或其中的ID，如 https://www.alipan.com/s/xxxx 中的 xxxx")
+                .required(),
+            ConfigItem::new("share_pwd", "string")
+                .title("提取码")
+                .help("如果分享有提取码，请填写"),
+            ConfigItem::new("refresh_token", "string").password()
+                .title("RefreshToken")
+                .help("阿里云盘网页版登录后的 refresh_token，下载分享文件需要登录")
+                .required(),
+            ConfigItem::new("root_id", "string")
+                .title("根目录ID")
+                .help("默认为root（分享根目录）")
+                .default("root"),
+        ]
+    }
+
+    fn create_driver(&self, config: Value) -> Result<Box<dyn StorageDriver>> {
+        let config: AliyundriveShareConfig = serde_json::from_value(config)
+            .map_err(|e| anyhow!("配置解析失败: {}", e))?;
+        Ok(Box::new(AliyundriveShareDriver::new(config)))
+    }
+}
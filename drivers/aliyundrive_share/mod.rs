@@ -0,0 +1,4 @@
+//! 阿里云盘分享驱动模块
+pub mod driver;
+
+pub use driver::{AliyundriveShareDriver, AliyundriveShareDriverFactory};
@@ -0,0 +1,413 @@
+//! 百度网盘分享驱动实现
+//! 参考 OpenList 的 baidu_share 驱动
+//!
+//! 列表使用分享链接与提取码，下载需要登录（BDUSS），下载链接校验User-Agent与Cookie，只能中转。
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::RwLock;
+
+use crate::storage::{
+    Capability, ConfigItem, DriverConfig, DriverFactory, Entry, HttpClientKey, ProgressCallback,
+    SendRetry, SpaceInfo, StorageDriver, shared_http_client_or_fallback,
+};
+use crate::storage::http_stream::{response_reader, with_range};
+use super::super::share_url;
+
+const API_BASE: &str = "https://pan.baidu.com";
+/// 百度下载链接只接受网盘客户端的UA
+const USER_AGENT: &str = "netdisk";
+
+/// 百度网盘分享配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaiduShareConfig {
+    /// 分享链接或其中的surl（如 1AbCd...）
+    pub surl: String,
+    /// 提取码（链接中带 ?pwd= 时可不填）
+    #[serde(default)]
+    pub pwd: String,
+    /// 登录账号的BDUSS（下载需要）
+    pub bduss: String,
+    /// 分享内的根目录路径（默认为分享根目录）
+    #[serde(default)]
+    pub root_path: String,
+}
+
+/// 分享中的文件
+#[derive(Debug, Clone)]
+struct ShareFile {
+    fs_id: u64,
+    name: String,
+    /// 分享者网盘中的完整路径
+    path: String,
+    is_dir: bool,
+    size: u64,
+    mtime: i64,
+}
+
+/// 分享信息（下载时需要）
+#[derive(Debug, Clone)]
+struct ShareInfo {
+    uk: String,
+    share_id: String,
+    seckey: String,
+}
+
+/// 数字或数字字符串
+fn num(v: &Value) -> u64 {
+    match v {
+        Value::Number(n) => n.as_u64().unwrap_or(0),
+        Value::String(s) => s.parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// 百度网盘分享驱动
+pub struct BaiduShareDriver {
+    config: BaiduShareConfig,
+    /// 完整surl（以1开头）
+    surl: String,
+    pwd: String,
+    client: Client,
+    info: RwLock<Option<ShareInfo>>,
+    /// 路径到分享者完整路径的缓存
+    path_cache: RwLock<HashMap<String, String>>,
+}
+
+impl BaiduShareDriver {
+    pub fn new(config: BaiduShareConfig) -> Self {
+        // 兼容 /share/init?surl=xxx 形式的链接（不带开头的1）
+        let surl = match share_url::query_param(&config.surl, "surl") {
+            Some(s) => format!("1{}", s),
+            None => share_url::share_id(&config.surl),
+        };
+        let pwd = share_url::share_pwd(&config.pwd, &config.surl);
+        Self {
+            config,
+            surl,
+            pwd,
+            client: shared_http_client_or_fallback(&HttpClientKey::new().timeout(60)),
+            info: RwLock::new(None),
+            path_cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn cookie(&self) -> String {
+        format!("BDUSS={}", self.config.bduss.trim())
+    }
+
+    /// 检查百度API响应的errno
+    fn check(json: Value) -> Result<Value> {
+        match json["errno"].as_i64().unwrap_or(0) {
+            0 => Ok(json),
+            -9 => Err(anyhow!("提取码错误或分享已失效")),
+            errno => Err(anyhow!("百度网盘API错误: errno={} {}", errno, json["show_msg"].as_str().unwrap_or(""))),
+        }
+    }
+
+    /// 列出分享目录，dir 为空时列出分享根目录
+    async fn list_files(&self, dir: &str) -> Result<Vec<ShareFile>> {
+        let short_url = self.surl.strip_prefix('1').unwrap_or(&self.surl);
+        let root = if dir.is_empty() { "1" } else { "0" };
+        let mut files = Vec::new();
+        let mut page = 1;
+        loop {
+            let page_str = page.to_string();
+            let form = [
+                ("shorturl", short_url),
+                ("dir", dir),
+                ("root", root),
+                ("pwd", self.pwd.as_str()),
+                ("page", page_str.as_str()),
+                ("num", "1000"),
+                ("order", "time"),
+            ];
+            let json: Value = self.client
+                .post(format!("{}/share/wxlist?channel=weixin&version=2.2.2&clienttype=25&web=1", API_BASE))
+                .header("User-Agent", USER_AGENT)
+                .header("Cookie", self.cookie())
+                .form(&form)
+                .send_retry()
+                .await?
+                .json()
+                .await?;
+            let json = Self::check(json)?;
+            let data = &json["data"];
+
+            if self.info.read().await.is_none() {
+                *self.info.write().await = Some(ShareInfo {
+                    uk: num(&data["uk"]).to_string(),
+                    share_id: num(&data["shareid"]).to_string(),
+                    seckey: data["seckey"].as_str()
+                        .map(|s| urlencoding::decode(s).map(|s| s.into_owned()).unwrap_or_else(|_| s.to_string()))
+                        .unwrap_or_default(),
+                });
+            }
+
+            let list = data["list"].as_array().cloned().unwrap_or_default();
+            let count = list.len();
+            files.extend(list.iter().map(|f| ShareFile {
+                fs_id: num(&f["fs_id"]),
+                name: f["server_filename"].as_str().unwrap_or("").to_string(),
+                path: f["path"].as_str().unwrap_or("").to_string(),
+                is_dir: num(&f["isdir"]) == 1,
+                size: num(&f["size"]),
+                mtime: num(&f["server_mtime"]) as i64,
+            }));
+            if count < 1000 {
+                break;
+            }
+            page += 1;
+        }
+        Ok(files)
+    }
+
+    /// 通过路径获取分享者网盘中的目录路径，空字符串表示分享根目录（逐级查找并缓存）
+    async fn get_dir(&self, path: &str) -> Result<String> {
+        let mut dir = self.config.root_path.trim().trim_end_matches('/').to_string();
+        let mut current = String::new();
+        for name in path.split('/').filter(|s| !s.is_empty()) {
+            let parent = std::mem::take(&mut current);
+            current = if parent.is_empty() { name.to_string() } else { format!("{}/{}", parent, name) };
+            if let Some(cached) = self.path_cache.read().await.get(&current) {
+                dir = cached.clone();
+                continue;
+            }
+            let files = self.list_files(&dir).await?;
+            self.cache_children(&parent, &files).await;
+            dir = files.into_iter()
+                .find(|f| f.name == name && f.is_dir)
+                .map(|f| f.path)
+                .ok_or_else(|| anyhow!("目录不存在: /{}", current))?;
+        }
+        Ok(dir)
+    }
+
+    /// 在父目录中查找文件
+    async fn find(&self, path: &str) -> Result<ShareFile> {
+        let path = path.trim_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let dir = self.get_dir(parent).await?;
+        let files = self.list_files(&dir).await?;
+        self.cache_children(parent, &files).await;
+        files.into_iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| anyhow!("路径不存在: /{}", path))
+    }
+
+    /// 缓存子目录的完整路径
+    async fn cache_children(&self, parent: &str, files: &[ShareFile]) {
+        let mut cache = self.path_cache.write().await;
+        for f in files.iter().filter(|f| f.is_dir) {
+            let key = if parent.is_empty() { f.name.clone() } else { format!("{}/{}", parent, f.name) };
+            cache.insert(key, f.path.clone());
+        }
+    }
+
+    /// 获取下载链接（dlink）
+    async fn get_download_url(&self, fs_id: u64) -> Result<String> {
+        if self.info.read().await.is_none() {
+            self.list_files("").await?;
+        }
+        let info = self.info.read().await.clone().ok_or_else(|| anyhow!("获取分享信息失败"))?;
+
+        // 先获取签名
+        let json: Value = self.client
+            .get(format!("{}/share/tplconfig", API_BASE))
+            .query(&[
+                ("surl", self.surl.as_str()),
+                ("fields", "sign,timestamp"),
+                ("channel", "chunlei"),
+                ("web", "1"),
+                ("app_id", "250528"),
+                ("clienttype", "0"),
+            ])
+            .header("User-Agent", USER_AGENT)
+            .header("Cookie", self.cookie())
+            .send_retry()
+            .await?
+            .json()
+            .await?;
+        let json = Self::check(json)?;
+        let sign = json["data"]["sign"].as_str().ok_or_else(|| anyhow!("获取下载签名失败"))?.to_string();
+        let timestamp = num(&json["data"]["timestamp"]).to_string();
+
+        let extra = serde_json::json!({"sekey": info.seckey}).to_string();
+        let fid_list = format!("[{}]", fs_id);
+        let form = [
+            ("encrypt", "0"),
+            ("extra", extra.as_str()),
+            ("fid_list", fid_list.as_str()),
+            ("primaryid", info.share_id.as_str()),
+            ("product", "share"),
+            ("type", "nolimit"),
+            ("uk", info.uk.as_str()),
+        ];
+        let json: Value = self.client
+            .post(format!("{}/api/sharedownload", API_BASE))
+            .query(&[
+                ("app_id", "250528"),
+                ("channel", "chunlei"),
+                ("clienttype", "12"),
+                ("sign", sign.as_str()),
+                ("timestamp", timestamp.as_str()),
+                ("web", "1"),
+            ])
+            .header("User-Agent", USER_AGENT)
+            .header("Cookie", self.cookie())
+            .form(&form)
+            .send_retry()
+            .await?
+            .json()
+            .await?;
+        let json = Self::check(json)?;
+        json["list"][0]["dlink"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("获取下载链接失败"))
+    }
+}
+
+#[async_trait]
+impl StorageDriver for BaiduShareDriver {
+    fn name(&self) -> &str {
+        "BaiduShare"
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> Capability {
+        Capability {
+            can_range_read: true,
+            can_direct_link: false, // 下载链接校验UA与Cookie，不支持302
+            ..Default::default()
+        }
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        tracing::debug!("BaiduShare: 列出目录 {}", path);
+        let dir_path = path.trim_matches('/');
+        let dir = self.get_dir(dir_path).await?;
+        let files = self.list_files(&dir).await?;
+        self.cache_children(dir_path, &files).await;
+
+        Ok(files.into_iter().map(|f| Entry {
+            path: format!("/{}", if dir_path.is_empty() { f.name.clone() } else { format!("{}/{}", dir_path, f.name) }),
+            is_dir: f.is_dir,
+            size: f.size,
+            modified: chrono::DateTime::from_timestamp(f.mtime, 0).map(|dt| dt.to_rfc3339()),
+            name: f.name,
+        }).collect())
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        tracing::debug!("BaiduShare: 读取文件 {} range={:?}", path, range);
+        let file = self.find(path).await?;
+        if file.is_dir {
+            return Err(anyhow!("不能读取目录: {}", path));
+        }
+        let url = self.get_download_url(file.fs_id).await?;
+
+        let req = self.client
+            .get(&url)
+            .header("User-Agent", USER_AGENT)
+            .header("Cookie", self.cookie());
+        let response = with_range(req, range.as_ref()).send_retry().await?;
+        response_reader(response, range)
+    }
+
+    async fn open_writer(
+        &self,
+        _path: &str,
+        _size_hint: Option<u64>,
+        _progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        Err(anyhow!("百度网盘分享不支持上传"))
+    }
+
+    async fn delete(&self, _path: &str) -> Result<()> {
+        Err(anyhow!("百度网盘分享不支持删除"))
+    }
+
+    async fn create_dir(&self, _path: &str) -> Result<()> {
+        Err(anyhow!("百度网盘分享不支持创建文件夹"))
+    }
+
+    async fn rename(&self, _path: &str, _new_name: &str) -> Result<()> {
+        Err(anyhow!("百度网盘分享不支持重命名"))
+    }
+
+    async fn move_item(&self, _old_path: &str, _new_path: &str) -> Result<()> {
+        Err(anyhow!("百度网盘分享不支持移动"))
+    }
+
+    async fn get_direct_link(&self, _path: &str) -> Result<Option<String>> {
+        // 下载链接需要 netdisk UA 与登录Cookie，浏览器无法直接下载
+        Ok(None)
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        Ok(None) // 分享不提供空间信息
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        false
+    }
+}
+
+/// 百度网盘分享驱动工厂
+pub struct BaiduShareDriverFactory;
+
+impl DriverFactory for BaiduShareDriverFactory {
+    fn driver_type(&self) -> &'static str {
+        "BaiduShare"
+    }
+
+    fn driver_config(&self) -> DriverConfig {
+        DriverConfig {
+            name: "百度网盘分享".to_string(),
+            local_sort: true,
+            only_proxy: true, // 下载需要特定UA与Cookie，必须代理
+            no_cache: false,
+            no_upload: true, // 分享不支持上传
+            default_root: None,
+        }
+    }
+
+    fn additional_items(&self) -> Vec<ConfigItem> {
+        vec![
+            ConfigItem::new("surl", "string")
+                .title("分享链接")
+                .help("分享链接或其中的ID，如 https://pan.baidu.com/s/1xxxx?pwd=abcd 或 1xxxx")
+                .required(),
+            ConfigItem::new("pwd", "string")
+                .title("提取码")
+                .help("链接中带 ?pwd= 时可不填"),
+            ConfigItem::new("bduss", "string").password()
+                .title("BDUSS")
+                .help("登录百度网盘后Cookie中的BDUSS，下载分享文件需要登录")
+                .required(),
+            ConfigItem::new("root_path", "string")
+                .title("根目录路径")
+                .help("分享内作为根目录的完整路径，留空为分享根目录"),
+        ]
+    }
+
+    fn create_driver(&self, config: Value) -> Result<Box<dyn StorageDriver>> {
+        let config: BaiduShareConfig = serde_json::from_value(config)
+            .map_err(|e| anyhow!("配置解析失败: {}", e))?;
+        Ok(Box::new(BaiduShareDriver::new(config)))
+    }
+}
@@ -0,0 +1,4 @@
+//! 百度网盘分享驱动模块
+pub mod driver;
+
+pub use driver::{BaiduShareDriver, BaiduShareDriverFactory};
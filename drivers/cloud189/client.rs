@@ -10,6 +10,7 @@ use uuid::Uuid;
 
 use super::types::*;
 use super::utils::*;
+use crate::storage::SendRetry;
 
 /// Cloud189 HTTP client / 天翼云盘HTTP客户端
 pub struct Cloud189Client {
@@ -98,7 +99,7 @@ impl Cloud189Client {
         }
         req = req.query(&all_query);
 
-        let resp = req.send().await?;
+        let resp = req.send_retry().await?;
         let text = resp.text().await?;
 
         // Check session errors / 检查session错误
@@ -161,7 +162,7 @@ impl Cloud189Client {
         let redirect_resp = self.no_redirect_client
             .get(&download_url)
             .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-            .send()
+            .send_retry()
             .await?;
 
         if redirect_resp.status().as_u16() == 302 {
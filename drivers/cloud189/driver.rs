@@ -456,8 +456,8 @@ impl DriverFactory for Cloud189DriverFactory {
     fn additional_items(&self) -> Vec<ConfigItem> {
         vec![
             ConfigItem::new("username", "string").title("用户名").help("手机号"),
-            ConfigItem::new("password", "string").title("密码"),
-            ConfigItem::new("refresh_token", "string").title("RefreshToken").help("优先使用"),
+            ConfigItem::new("password", "string").password().title("密码"),
+            ConfigItem::new("refresh_token", "string").password().title("RefreshToken").help("优先使用"),
             ConfigItem::new("root_folder_id", "string").title("根目录ID").default("-11").help("个人云-11，家庭云留空"),
             ConfigItem::new("cloud_type", "string").title("云类型").default("personal").help("personal或family"),
             ConfigItem::new("family_id", "string").title("家庭云ID").help("家庭云模式可自动获取"),
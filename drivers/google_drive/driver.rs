@@ -21,6 +21,7 @@ use tokio_util::io::StreamReader;
 use crate::storage::{
     StorageDriver, Entry, Capability, SpaceInfo,
     DriverFactory, DriverConfig, ConfigItem,
+    HttpClientKey, shared_http_client_or_fallback,
 };
 
 // ============ 配置结构 ============
@@ -197,7 +198,7 @@ impl GoogleDriveDriver {
         
         Self {
             config,
-            client: Client::new(),
+            client: shared_http_client_or_fallback(&HttpClientKey::new()),
             access_token: Arc::new(RwLock::new(None)),
             refresh_token: Arc::new(RwLock::new(refresh_token)),
             path_cache: Arc::new(RwLock::new(path_cache)),
@@ -1107,7 +1108,7 @@ impl DriverFactory for GoogleDriveDriverFactory {
                 .title("客户端ID")
                 .required()
                 .help("Google Cloud Console 创建的 OAuth 2.0 客户端 ID"),
-            ConfigItem::new("client_secret", "string")
+            ConfigItem::new("client_secret", "string").password()
                 .title("客户端密钥")
                 .required()
                 .help("Google Cloud Console 创建的 OAuth 2.0 客户端密钥"),
@@ -1115,7 +1116,7 @@ impl DriverFactory for GoogleDriveDriverFactory {
                 .title("获取刷新令牌")
                 .link("https://accounts.google.com/o/oauth2/v2/auth?client_id={client_id}&redirect_uri={redirect_uri}&response_type=code&scope=https://www.googleapis.com/auth/drive&access_type=offline&prompt=consent")
                 .help("点击按钮跳转到 Google 授权页面。需要先在 Google Cloud Console 的 OAuth 客户端设置中，将 {当前域名}/api/oauth/google/callback 添加到「已获授权的重定向 URI」"),
-            ConfigItem::new("refresh_token", "string")
+            ConfigItem::new("refresh_token", "string").password()
                 .title("刷新令牌")
                 .required()
                 .help("OAuth 授权后获取的刷新令牌"),
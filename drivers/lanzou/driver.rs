@@ -12,6 +12,7 @@ use tokio::sync::RwLock;
 use chrono::{DateTime, Utc};
 
 use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback};
+use crate::storage::http_stream::{response_reader, with_range};
 use super::config::{LanzouConfig, LoginType};
 use super::types::*;
 use super::utils::*;
@@ -935,7 +936,7 @@ impl StorageDriver for LanzouDriver {
     async fn open_reader(
         &self,
         path: &str,
-        _range: Option<Range<u64>>,
+        range: Option<Range<u64>>,
     ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
         self.ensure_initialized().await?;
         
@@ -944,20 +945,11 @@ impl StorageDriver for LanzouDriver {
         let download_url = self.get_download_url_logged_in(&file_id).await?;
         
         let headers = build_headers(&self.config.user_agent, None);
-        let response = self.client.get(&download_url)
-            .headers(headers)
-            .send()
-            .await?;
+        let request = self.client.get(&download_url).headers(headers);
+        let response = with_range(request, range.as_ref()).send().await?;
         
         // 流式返回响应体
-        use futures::StreamExt;
-        let stream = response.bytes_stream();
-        let mapped_stream = stream.map(|result| {
-            result.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-        });
-        let reader = tokio_util::io::StreamReader::new(mapped_stream);
-        
-        Ok(Box::new(reader))
+        response_reader(response, range)
     }
     
     async fn open_writer(
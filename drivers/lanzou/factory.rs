@@ -33,7 +33,7 @@ impl DriverFactory for LanzouDriverFactory {
                 .options("cookie:Cookie登录,account:账号密码,url:分享链接")
                 .default("cookie")
                 .required(),
-            ConfigItem::new("cookie", "string")
+            ConfigItem::new("cookie", "string").password()
                 .title("Cookie")
                 .help("包含ylogin和phpdisk_info的Cookie字符串"),
             ConfigItem::new("account", "string")
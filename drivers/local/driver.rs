@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use tokio::io::{AsyncRead, AsyncWrite};
 use std::ops::Range;
 
-use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo};
+use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, HashType};
 
 pub struct LocalDriver {
     root: PathBuf,
@@ -150,6 +150,32 @@ impl StorageDriver for LocalDriver {
         Ok(Box::new(async_file))
     }
     
+    /// Continue writing at offset, discarding anything written after it / 从offset处继续写入
+    async fn open_resume_writer(
+        &self,
+        path: &str,
+        offset: u64,
+        _size_hint: Option<u64>,
+    ) -> Result<Option<Box<dyn AsyncWrite + Unpin + Send>>> {
+        let full_path = self.normalize_path(path)?;
+        
+        let file = tokio::task::spawn_blocking(move || {
+            use std::io::Seek;
+            let mut file = match std::fs::OpenOptions::new().write(true).open(&full_path) {
+                Ok(f) => f,
+                Err(_) => return Ok(None),
+            };
+            if file.metadata()?.len() < offset {
+                return Ok(None);
+            }
+            file.set_len(offset)?;
+            file.seek(std::io::SeekFrom::Start(offset))?;
+            Ok::<Option<std::fs::File>, anyhow::Error>(Some(file))
+        }).await??;
+        
+        Ok(file.map(|f| Box::new(tokio::fs::File::from_std(f)) as Box<dyn AsyncWrite + Unpin + Send>))
+    }
+    
     async fn delete(&self, path: &str) -> Result<()> {
         let full_path = self.normalize_path(path)?;
         
@@ -217,6 +243,35 @@ impl StorageDriver for LocalDriver {
         Ok(())
     }
     
+    fn hash_types(&self) -> Vec<HashType> {
+        vec![HashType::Md5]
+    }
+    
+    /// Compute checksum from disk / 从磁盘计算校验值
+    async fn get_hash(&self, path: &str, hash_type: HashType) -> Result<Option<String>> {
+        if hash_type != HashType::Md5 {
+            return Ok(None);
+        }
+        let full_path = self.normalize_path(path)?;
+        
+        let digest = tokio::task::spawn_blocking(move || {
+            use std::io::Read;
+            let mut file = std::fs::File::open(&full_path)?;
+            let mut ctx = md5::Context::new();
+            let mut buffer = vec![0u8; 1024 * 1024];
+            loop {
+                let n = file.read(&mut buffer)?;
+                if n == 0 {
+                    break;
+                }
+                ctx.consume(&buffer[..n]);
+            }
+            Ok::<String, anyhow::Error>(format!("{:x}", ctx.compute()))
+        }).await??;
+        
+        Ok(Some(digest))
+    }
+    
     fn get_local_path(&self, path: &str) -> Option<std::path::PathBuf> {
         self.normalize_path(path).ok()
     }
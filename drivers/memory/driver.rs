@@ -0,0 +1,437 @@
+//! 内存驱动实现
+//!
+//! 以规范化路径（如 `/a/b.txt`）为键保存目录与文件，根目录隐式存在。
+//! 写入时自动创建上级目录，与本地驱动的移动/复制行为一致。
+
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use parking_lot::RwLock;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::drivers::local::LocalDriver;
+use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback};
+
+/// 目录或文件
+#[derive(Clone)]
+enum Node {
+    Dir { modified: String },
+    File { data: Bytes, modified: String },
+}
+
+impl Node {
+    fn size(&self) -> u64 {
+        match self {
+            Node::Dir { .. } => 0,
+            Node::File { data, .. } => data.len() as u64,
+        }
+    }
+}
+
+/// 规范化路径：以 `/` 开头、无结尾斜杠，拒绝 `..`
+fn normalize(path: &str) -> Result<String> {
+    let mut parts = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return Err(anyhow!("Access path exceeds root directory scope")),
+            p => parts.push(p),
+        }
+    }
+    Ok(format!("/{}", parts.join("/")))
+}
+
+fn parent_of(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+fn is_within(path: &str, dir: &str) -> bool {
+    path == dir || path.starts_with(&format!("{}/", dir))
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339()
+}
+
+/// 共享的文件表
+struct Store {
+    nodes: RwLock<BTreeMap<String, Node>>,
+    /// 容量上限（字节），0 为不限制
+    max_size: u64,
+}
+
+impl Store {
+    fn used(nodes: &BTreeMap<String, Node>) -> u64 {
+        nodes.values().map(Node::size).sum()
+    }
+
+    fn is_dir(nodes: &BTreeMap<String, Node>, path: &str) -> bool {
+        path == "/" || matches!(nodes.get(path), Some(Node::Dir { .. }))
+    }
+
+    /// 创建目录及其上级目录，路径上已有同名文件时报错
+    fn mkdir_all(nodes: &mut BTreeMap<String, Node>, path: &str) -> Result<()> {
+        if path == "/" {
+            return Ok(());
+        }
+        Self::mkdir_all(nodes, parent_of(path))?;
+        match nodes.get(path) {
+            Some(Node::Dir { .. }) => Ok(()),
+            Some(Node::File { .. }) => Err(anyhow!("文件已存在: {}", path)),
+            None => {
+                nodes.insert(path.to_string(), Node::Dir { modified: now() });
+                Ok(())
+            }
+        }
+    }
+
+    /// 写入文件（覆盖已有文件）
+    fn write(&self, path: &str, data: Bytes) -> Result<()> {
+        let mut nodes = self.nodes.write();
+        if Self::is_dir(&nodes, path) {
+            return Err(anyhow!("目标是目录: {}", path));
+        }
+        if self.max_size > 0 {
+            let old = nodes.get(path).map(Node::size).unwrap_or(0);
+            if Self::used(&nodes) - old + data.len() as u64 > self.max_size {
+                return Err(anyhow!("存储空间不足"));
+            }
+        }
+        Self::mkdir_all(&mut nodes, parent_of(path))?;
+        nodes.insert(path.to_string(), Node::File { data, modified: now() });
+        Ok(())
+    }
+
+    /// 取出 `path` 及其下所有节点
+    fn subtree(nodes: &BTreeMap<String, Node>, path: &str) -> Vec<(String, Node)> {
+        nodes.range(path.to_string()..)
+            .take_while(|(k, _)| k.starts_with(path))
+            .filter(|(k, _)| is_within(k, path))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// 将 `src` 及其下所有节点复制（或移动）到 `dst`
+    fn transfer(&self, src: &str, dst: &str, remove_source: bool) -> Result<()> {
+        if src == "/" || is_within(dst, src) || is_within(src, dst) {
+            return Err(anyhow!("不能移动或复制到自身、子目录或上级目录"));
+        }
+        let mut nodes = self.nodes.write();
+        let items = Self::subtree(&nodes, src);
+        if items.is_empty() {
+            return Err(anyhow!("文件不存在: {}", src));
+        }
+        if !remove_source && self.max_size > 0 {
+            let added: u64 = items.iter().map(|(_, n)| n.size()).sum();
+            if Self::used(&nodes) + added > self.max_size {
+                return Err(anyhow!("存储空间不足"));
+            }
+        }
+        Self::mkdir_all(&mut nodes, parent_of(dst))?;
+        for (key, _) in Self::subtree(&nodes, dst) {
+            nodes.remove(&key);
+        }
+        for (key, node) in items {
+            if remove_source {
+                nodes.remove(&key);
+            }
+            nodes.insert(format!("{}{}", dst, &key[src.len()..]), node);
+        }
+        Ok(())
+    }
+}
+
+/// 内存驱动
+pub struct MemoryDriver {
+    store: Arc<Store>,
+}
+
+impl MemoryDriver {
+    /// `max_size` 为容量上限（字节），0 为不限制
+    pub fn new(max_size: u64) -> Self {
+        Self {
+            store: Arc::new(Store { nodes: RwLock::new(BTreeMap::new()), max_size }),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageDriver for MemoryDriver {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> Capability {
+        Capability {
+            can_range_read: true,
+            can_server_side_copy: true,
+            ..Default::default()
+        }
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        let path = normalize(path)?;
+        let nodes = self.store.nodes.read();
+        if !Store::is_dir(&nodes, &path) {
+            return Err(anyhow!("目录不存在: {}", path));
+        }
+        let prefix = if path == "/" { "/".to_string() } else { format!("{}/", path) };
+        let entries = nodes.range(prefix.clone()..)
+            .take_while(|(k, _)| k.starts_with(&prefix))
+            .filter(|(k, _)| !k[prefix.len()..].contains('/'))
+            .map(|(k, node)| {
+                let (is_dir, modified) = match node {
+                    Node::Dir { modified } => (true, modified),
+                    Node::File { modified, .. } => (false, modified),
+                };
+                Entry {
+                    name: k[prefix.len()..].to_string(),
+                    path: k.clone(),
+                    is_dir,
+                    size: node.size(),
+                    modified: Some(modified.clone()),
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let path = normalize(path)?;
+        let data = match self.store.nodes.read().get(&path) {
+            Some(Node::File { data, .. }) => data.clone(),
+            _ => return Err(anyhow!("文件不存在: {}", path)),
+        };
+        let data = match range {
+            Some(r) => {
+                let end = (r.end as usize).min(data.len());
+                data.slice((r.start as usize).min(end)..end)
+            }
+            None => data,
+        };
+        Ok(Box::new(io::Cursor::new(data)))
+    }
+
+    async fn open_writer(
+        &self,
+        path: &str,
+        size_hint: Option<u64>,
+        _progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        let path = normalize(path)?;
+        if Store::is_dir(&self.store.nodes.read(), &path) {
+            return Err(anyhow!("目标是目录: {}", path));
+        }
+        let capacity = size_hint.unwrap_or(0).min(64 * 1024 * 1024) as usize;
+        Ok(Box::new(MemoryWriter {
+            store: self.store.clone(),
+            path,
+            buf: Vec::with_capacity(capacity),
+            done: false,
+        }))
+    }
+
+    async fn put(&self, path: &str, data: Bytes, progress: Option<ProgressCallback>) -> Result<()> {
+        let size = data.len() as u64;
+        self.store.write(&normalize(path)?, data)?;
+        if let Some(cb) = progress {
+            cb(size, size);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let path = normalize(path)?;
+        if path == "/" {
+            return Err(anyhow!("不能删除根目录"));
+        }
+        let mut nodes = self.store.nodes.write();
+        let items = Store::subtree(&nodes, &path);
+        if items.is_empty() {
+            return Err(anyhow!("文件不存在: {}", path));
+        }
+        for (key, _) in items {
+            nodes.remove(&key);
+        }
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        let path = normalize(path)?;
+        Store::mkdir_all(&mut self.store.nodes.write(), &path)
+    }
+
+    async fn rename(&self, old_path: &str, new_name: &str) -> Result<()> {
+        let old_path = normalize(old_path)?;
+        if new_name.is_empty() || new_name.contains(['/', '\\']) || new_name == "." || new_name == ".." {
+            return Err(anyhow!("无效的名称: {}", new_name));
+        }
+        let parent = parent_of(&old_path);
+        let new_path = if parent == "/" { format!("/{}", new_name) } else { format!("{}/{}", parent, new_name) };
+        self.store.transfer(&old_path, &new_path, true)
+    }
+
+    async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.store.transfer(&normalize(old_path)?, &normalize(new_path)?, true)
+    }
+
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.store.transfer(&normalize(old_path)?, &normalize(new_path)?, false)
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        if self.store.max_size == 0 {
+            return Ok(None);
+        }
+        let used = Store::used(&self.store.nodes.read());
+        Ok(Some(SpaceInfo {
+            used,
+            total: self.store.max_size,
+            free: self.store.max_size.saturating_sub(used),
+        }))
+    }
+}
+
+/// 缓存写入内容，关闭时一次性保存
+struct MemoryWriter {
+    store: Arc<Store>,
+    path: String,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl AsyncWrite for MemoryWriter {
+    fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if this.store.max_size > 0 && this.buf.len() as u64 + buf.len() as u64 > this.store.max_size {
+            return Poll::Ready(Err(io::Error::other("存储空间不足")));
+        }
+        this.buf.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if !this.done {
+            this.done = true;
+            let data = Bytes::from(std::mem::take(&mut this.buf));
+            this.store.write(&this.path, data).map_err(io::Error::other)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// 临时目录驱动：在系统临时目录中创建专属目录，驱动卸载时删除
+pub struct TempDirDriver {
+    inner: LocalDriver,
+    // 最后释放，确保目录在内部驱动之后删除
+    _dir: tempfile::TempDir,
+}
+
+impl TempDirDriver {
+    pub fn new() -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("yaolist-memory-").tempdir()?;
+        let root = dir.path().canonicalize()?;
+        tracing::info!("Temp dir driver initialized, root: {:?}", root);
+        Ok(Self { inner: LocalDriver::new(root), _dir: dir })
+    }
+}
+
+#[async_trait]
+impl StorageDriver for TempDirDriver {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> Capability {
+        self.inner.capabilities()
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        self.inner.list(path).await
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.open_reader(path, range).await
+    }
+
+    async fn open_writer(
+        &self,
+        path: &str,
+        size_hint: Option<u64>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.inner.open_writer(path, size_hint, progress).await
+    }
+
+    async fn open_resume_writer(
+        &self,
+        path: &str,
+        offset: u64,
+        size_hint: Option<u64>,
+    ) -> Result<Option<Box<dyn AsyncWrite + Unpin + Send>>> {
+        self.inner.open_resume_writer(path, offset, size_hint).await
+    }
+
+    async fn put(&self, path: &str, data: Bytes, progress: Option<ProgressCallback>) -> Result<()> {
+        self.inner.put(path, data, progress).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.inner.delete(path).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.inner.create_dir(path).await
+    }
+
+    async fn rename(&self, old_path: &str, new_name: &str) -> Result<()> {
+        self.inner.rename(old_path, new_name).await
+    }
+
+    async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.inner.move_item(old_path, new_path).await
+    }
+
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.inner.copy_item(old_path, new_path).await
+    }
+
+    fn get_local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.get_local_path(path)
+    }
+
+    fn is_local(&self) -> bool {
+        self.inner.is_local()
+    }
+}
@@ -0,0 +1,84 @@
+//! 内存存储驱动
+//!
+//! 文件保存在内存（或进程退出即删除的临时目录）中，重启后清空。
+//! 用于集成测试、演示站点，以及解压/压缩流程的临时中转区。
+//! 需启用 `memory-driver` 特性才会在存储管理中注册。
+
+mod driver;
+
+pub use driver::{MemoryDriver, TempDirDriver};
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::storage::{StorageDriver, DriverFactory, DriverConfig, ConfigItem};
+
+/// 内存驱动配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemoryConfig {
+    /// memory（内存）或 temp_dir（临时目录）
+    #[serde(default = "default_mode")]
+    pub mode: String,
+    /// 容量上限（MB），0 为不限制，仅内存模式
+    #[serde(default, deserialize_with = "de_u64")]
+    pub max_size: u64,
+}
+
+fn default_mode() -> String {
+    "memory".to_string()
+}
+
+/// 前端表单的数字可能以字符串提交
+fn de_u64<'de, D: serde::Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    match Value::deserialize(d)? {
+        Value::Number(n) => Ok(n.as_u64().unwrap_or(0)),
+        Value::String(s) if s.trim().is_empty() => Ok(0),
+        Value::String(s) => s.trim().parse().map_err(serde::de::Error::custom),
+        _ => Ok(0),
+    }
+}
+
+/// 内存驱动工厂
+pub struct MemoryDriverFactory;
+
+impl DriverFactory for MemoryDriverFactory {
+    fn driver_type(&self) -> &'static str {
+        "memory"
+    }
+
+    fn driver_config(&self) -> DriverConfig {
+        DriverConfig {
+            name: "内存存储".to_string(),
+            local_sort: true,
+            only_proxy: true,
+            no_cache: true,
+            no_upload: false,
+            default_root: Some("/".to_string()),
+        }
+    }
+
+    fn additional_items(&self) -> Vec<ConfigItem> {
+        vec![
+            ConfigItem::new("mode", "select")
+                .title("存储位置")
+                .options("memory,temp_dir")
+                .default("memory")
+                .help("memory 保存在内存中；temp_dir 保存在临时目录，驱动卸载时删除"),
+            ConfigItem::new("max_size", "number")
+                .title("容量上限(MB)")
+                .default("0")
+                .help("0 为不限制，仅内存模式生效"),
+        ]
+    }
+
+    fn create_driver(&self, config: Value) -> Result<Box<dyn StorageDriver>> {
+        let config: MemoryConfig = serde_json::from_value(config)
+            .map_err(|e| anyhow!("配置解析失败: {}", e))?;
+        match config.mode.as_str() {
+            "memory" => Ok(Box::new(MemoryDriver::new(config.max_size.saturating_mul(1024 * 1024)))),
+            "temp_dir" => Ok(Box::new(TempDirDriver::new()?)),
+            other => Err(anyhow!("未知的存储位置: {}", other)),
+        }
+    }
+}
@@ -17,9 +17,14 @@ pub mod pan115;
 pub mod sftp;
 pub mod pan123_share;
 pub mod pan115_share;
+pub mod aliyundrive_share;
+pub mod quark_share;
+pub mod baidu_share;
 pub mod google_drive;
 pub mod thunder;
 pub mod aliyun_open;
+pub mod memory;
+pub mod share_url;
 
 use crate::storage::StorageManager;
 
@@ -59,11 +64,20 @@ pub async fn register_all(manager: &StorageManager) -> anyhow::Result<()> {
     manager.register_factory(Box::new(pan123_share::Pan123ShareDriverFactory)).await?;
     // Register 115Pan Share driver / 注册115云盘分享驱动
     manager.register_factory(Box::new(pan115_share::Pan115ShareDriverFactory)).await?;
+    // Register Aliyundrive Share driver / 注册阿里云盘分享驱动
+    manager.register_factory(Box::new(aliyundrive_share::AliyundriveShareDriverFactory)).await?;
+    // Register Quark Share driver / 注册夸克网盘分享驱动
+    manager.register_factory(Box::new(quark_share::QuarkShareDriverFactory)).await?;
+    // Register Baidu Netdisk Share driver / 注册百度网盘分享驱动
+    manager.register_factory(Box::new(baidu_share::BaiduShareDriverFactory)).await?;
     // Register Google Drive driver / 注册Google Drive驱动
     manager.register_factory(Box::new(google_drive::GoogleDriveDriverFactory)).await?;
     // Register Thunder driver / 注册迅雷驱动
     manager.register_factory(Box::new(thunder::ThunderDriverFactory)).await?;
     // Register Aliyun Open driver / 注册阿里云盘 Open 驱动
     manager.register_factory(Box::new(aliyun_open::AliyunOpenDriverFactory)).await?;
+    // Register in-memory driver for tests and demos (memory-driver feature) / 注册内存驱动（用于测试与演示）
+    #[cfg(feature = "memory-driver")]
+    manager.register_factory(Box::new(memory::MemoryDriverFactory)).await?;
     Ok(())
 }
@@ -20,7 +20,9 @@ use tokio_util::io::StreamReader;
 use crate::storage::{
     StorageDriver, Entry, Capability, SpaceInfo,
     DriverFactory, DriverConfig, ConfigItem,
+    HttpClientKey, shared_http_client, shared_http_client_or_fallback,
 };
+use crate::storage::upload_cleanup::UploadSessions;
 
 /// OneDrive region configuration / OneDrive区域配置
 struct HostConfig {
@@ -200,15 +202,33 @@ struct DriveQuota {
 /// Drive响应
 #[derive(Debug, Deserialize)]
 struct DriveResponse {
+    #[serde(default)]
+    id: String,
     quota: DriveQuota,
 }
 
+/// 服务端复制进度监视器响应
+#[derive(Debug, Deserialize)]
+struct CopyMonitorResponse {
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    id: Option<String>,
+}
+
+/// 服务端复制最长等待时间（秒）
+const COPY_MONITOR_TIMEOUT_SECS: u64 = 3600;
+
 /// OneDrive OAuth 驱动
 pub struct OneDriveDriver {
     config: OneDriveConfig,
     client: Client,
     access_token: Arc<RwLock<Option<String>>>,
     refresh_token: Arc<RwLock<String>>,
+    /// Drive ID缓存（用于识别同账号挂载）
+    drive_id: Arc<RwLock<Option<String>>>,
+    /// 未完成的上传会话（Graph API 无法列出会话，由驱动自行记录）
+    upload_sessions: UploadSessions,
 }
 
 /// OneDrive写入器 - 流式分片上传（固定内存占用）
@@ -231,6 +251,7 @@ pub struct OneDriveWriter {
     api_base: String,
     is_sharepoint: bool,
     site_id: Option<String>,
+    upload_sessions: UploadSessions,
     closed: bool,
     /// 上传过程中的错误
     error: Option<String>,
@@ -246,6 +267,7 @@ impl OneDriveWriter {
         is_sharepoint: bool,
         site_id: Option<String>,
         chunk_size: u64,
+        upload_sessions: UploadSessions,
     ) -> Self {
         let chunk_size_bytes = chunk_size * 1024 * 1024; // MB to bytes
         Self {
@@ -261,6 +283,7 @@ impl OneDriveWriter {
             api_base,
             is_sharepoint,
             site_id,
+            upload_sessions,
             closed: false,
             error: None,
         }
@@ -329,6 +352,7 @@ impl OneDriveWriter {
         let session: UploadSessionResponse = response.json().await
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
         
+        self.upload_sessions.register(&session.upload_url);
         self.upload_session_url = Some(session.upload_url);
         self.session_initialized = true;
         Ok(())
@@ -442,6 +466,7 @@ impl AsyncWrite for OneDriveWriter {
             let site_id = self.site_id.clone();
             let upload_session_url = self.upload_session_url.clone();
             let session_initialized = self.session_initialized;
+            let upload_sessions = self.upload_sessions.clone();
             let uploaded_bytes = self.uploaded_bytes;
             let total_size = self.total_size;
             let chunk_size_bytes = self.chunk_size_bytes;
@@ -463,6 +488,7 @@ impl AsyncWrite for OneDriveWriter {
                         api_base,
                         is_sharepoint,
                         site_id,
+                        upload_sessions,
                         closed: false,
                         error: None,
                     };
@@ -517,6 +543,7 @@ impl AsyncWrite for OneDriveWriter {
         let site_id = self.site_id.clone();
         let upload_session_url = self.upload_session_url.clone();
         let session_initialized = self.session_initialized;
+        let upload_sessions = self.upload_sessions.clone();
         let uploaded_bytes = self.uploaded_bytes;
         let total_size = if self.total_size == 0 { self.uploaded_bytes + chunk.len() as u64 } else { self.total_size };
         let chunk_size_bytes = self.chunk_size_bytes;
@@ -547,12 +574,17 @@ impl AsyncWrite for OneDriveWriter {
                     api_base,
                     is_sharepoint,
                     site_id,
+                    upload_sessions,
                     closed: true,
                     error: None,
                 };
                 if !chunk.is_empty() {
                     writer.upload_chunk(&chunk, true).await?;
                 }
+                // 最后一个分片上传成功，会话已结束
+                if let Some(ref url) = writer.upload_session_url {
+                    writer.upload_sessions.finish(url);
+                }
                 Ok::<_, std::io::Error>(())
             })
         }).join().map_err(|_| std::io::Error::new(std::io::ErrorKind::Other, "上传线程panic"))?;
@@ -567,9 +599,82 @@ impl OneDriveDriver {
         let refresh_token = config.refresh_token.clone();
         Self {
             config,
-            client: Client::new(),
+            client: shared_http_client_or_fallback(&HttpClientKey::new()),
             access_token: Arc::new(RwLock::new(None)),
             refresh_token: Arc::new(RwLock::new(refresh_token)),
+            drive_id: Arc::new(RwLock::new(None)),
+            upload_sessions: UploadSessions::default(),
+        }
+    }
+
+    /// 拆分目标路径为 parentReference 路径和文件名
+    fn target_reference(new_path: &str) -> Result<(String, String)> {
+        let new_parent = std::path::Path::new(new_path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| "/".to_string());
+        
+        let new_name = std::path::Path::new(new_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("无效的目标路径"))?;
+
+        let parent_path = if new_parent == "/" {
+            "/drive/root".to_string()
+        } else {
+            format!("/drive/root:/{}", new_parent.trim_start_matches('/'))
+        };
+        Ok((parent_path, new_name))
+    }
+
+    /// 服务端复制（异步任务，轮询监视器直到完成）
+    async fn server_copy(&self, old_path: &str, new_path: &str) -> Result<()> {
+        let url = format!("{}/copy", self.get_meta_url(old_path));
+        let token = self.get_access_token().await?;
+        let (parent_path, new_name) = Self::target_reference(new_path)?;
+        
+        let body = serde_json::json!({
+            "parentReference": { "path": parent_path },
+            "name": new_name
+        });
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("复制失败: HTTP {}", response.status()));
+        }
+        let monitor_url = response.headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow!("复制失败: 缺少进度监视地址"))?;
+
+        // 监视地址无需鉴权；完成后会303跳转到新文件，不跟随跳转
+        let monitor_client = shared_http_client(&HttpClientKey::new().no_redirect())?;
+        let started = std::time::Instant::now();
+        let mut interval = std::time::Duration::from_millis(500);
+        loop {
+            let response = monitor_client.get(&monitor_url).send().await?;
+            if response.status().is_redirection() {
+                return Ok(());
+            }
+            let monitor: CopyMonitorResponse = response.json().await?;
+            match monitor.status.as_deref() {
+                Some("completed") => return Ok(()),
+                Some("failed") => return Err(anyhow!("复制失败")),
+                None if monitor.id.is_some() => return Ok(()),
+                _ => {}
+            }
+            if started.elapsed().as_secs() > COPY_MONITOR_TIMEOUT_SECS {
+                return Err(anyhow!("复制超时"));
+            }
+            tokio::time::sleep(interval).await;
+            interval = (interval * 2).min(std::time::Duration::from_secs(5));
         }
     }
 
@@ -850,6 +955,7 @@ impl StorageDriver for OneDriveDriver {
             self.config.is_sharepoint,
             self.config.site_id.clone(),
             self.config.chunk_size,
+            self.upload_sessions.clone(),
         );
         
         Ok(Box::new(writer))
@@ -929,22 +1035,7 @@ impl StorageDriver for OneDriveDriver {
     async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
         let url = self.get_meta_url(old_path);
         let token = self.get_access_token().await?;
-        
-        let new_parent = std::path::Path::new(new_path)
-            .parent()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|| "/".to_string());
-        
-        let new_name = std::path::Path::new(new_path)
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .ok_or_else(|| anyhow!("无效的目标路径"))?;
-
-        let parent_path = if new_parent == "/" {
-            "/drive/root".to_string()
-        } else {
-            format!("/drive/root:/{}", new_parent.trim_start_matches('/'))
-        };
+        let (parent_path, new_name) = Self::target_reference(new_path)?;
 
         let body = serde_json::json!({
             "parentReference": { "path": parent_path },
@@ -965,6 +1056,39 @@ impl StorageDriver for OneDriveDriver {
         }
     }
 
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.server_copy(old_path, new_path).await
+    }
+
+    async fn account_key(&self) -> Option<String> {
+        {
+            let cached = self.drive_id.read().await;
+            if let Some(ref id) = *cached {
+                return Some(format!("onedrive:{}:{}", self.config.region, id));
+            }
+        }
+        let drive = self.get_drive().await.ok()?;
+        if drive.id.is_empty() {
+            return None;
+        }
+        *self.drive_id.write().await = Some(drive.id.clone());
+        Some(format!("onedrive:{}:{}", self.config.region, drive.id))
+    }
+
+    fn account_path(&self, path: &str) -> Option<String> {
+        // 路径直接相对于Drive根目录解析（见get_meta_url）
+        Some(format!("/{}", path.trim_start_matches('/')))
+    }
+
+    async fn transfer_within_account(&self, src_path: &str, dst_account_path: &str, copy: bool) -> Result<bool> {
+        if copy {
+            self.server_copy(src_path, dst_account_path).await?;
+        } else {
+            self.move_item(src_path, dst_account_path).await?;
+        }
+        Ok(true)
+    }
+
     async fn get_direct_link(&self, path: &str) -> Result<Option<String>> {
         let file = self.get_file(path).await?;
         
@@ -1001,6 +1125,19 @@ impl StorageDriver for OneDriveDriver {
     fn show_space_in_frontend(&self) -> bool {
         self.config.show_space_info
     }
+    
+    async fn abort_stale_uploads(&self, older_than: std::time::Duration) -> Result<usize> {
+        let mut aborted = 0;
+        for url in self.upload_sessions.take_stale(older_than) {
+            // 上传地址自带授权，DELETE 即取消会话；已过期的会话返回404，同样视为已清理
+            match self.client.delete(&url).send().await {
+                Ok(response) if response.status().is_success() || response.status() == 404 => aborted += 1,
+                Ok(response) => tracing::warn!(status = %response.status(), "取消OneDrive上传会话失败"),
+                Err(e) => tracing::warn!(error = %e, "取消OneDrive上传会话失败"),
+            }
+        }
+        Ok(aborted)
+    }
 }
 
 // ============ DriverFactory 实现 ============
@@ -1041,14 +1178,14 @@ impl DriverFactory for OneDriveDriverFactory {
             ConfigItem::new("client_id", "string")
                 .title("客户端 ID")
                 .required(),
-            ConfigItem::new("client_secret", "string")
+            ConfigItem::new("client_secret", "string").password()
                 .title("客户端密钥")
                 .required(),
             ConfigItem::new("redirect_uri", "string")
                 .title("回调地址")
                 .default("http://localhost:3000/api/onedrive/callback")
                 .required(),
-            ConfigItem::new("refresh_token", "string")
+            ConfigItem::new("refresh_token", "string").password()
                 .title("刷新令牌")
                 .required(),
             ConfigItem::new("site_id", "string")
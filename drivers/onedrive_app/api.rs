@@ -8,6 +8,7 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use super::types::*;
+use crate::storage::{HttpClientKey, SendRetry, shared_http_client};
 
 /// OneDrive App API client / OneDrive App API客户端
 pub struct OneDriveAppApi {
@@ -18,19 +19,9 @@ pub struct OneDriveAppApi {
 
 impl OneDriveAppApi {
     pub fn new(config: OneDriveAppConfig) -> Result<Self> {
-        let mut builder = Client::builder()
-            .redirect(reqwest::redirect::Policy::limited(10)); // 支持最多10次重定向（包括302）
-        
-        // 配置代理
-        if let Some(ref proxy_url) = config.proxy {
-            let proxy = reqwest::Proxy::http(proxy_url)
-                .or_else(|_| reqwest::Proxy::https(proxy_url))
-                .or_else(|_| reqwest::Proxy::all(proxy_url))
-                .map_err(|e| anyhow!("无效的代理URL: {} - {}", proxy_url, e))?;
-            builder = builder.proxy(proxy);
-        }
-        
-        let client = builder.build()
+        // 共享客户端（默认最多10次重定向，包括302），按代理区分连接池
+        let key = HttpClientKey::new().proxy(config.proxy.as_deref());
+        let client = shared_http_client(&key)
             .map_err(|e| anyhow!("创建HTTP客户端失败: {}", e))?;
         
         Ok(Self {
@@ -71,7 +62,7 @@ impl OneDriveAppApi {
         let response = self.client
             .post(&url)
             .form(&params)
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() {
@@ -126,7 +117,7 @@ impl OneDriveAppApi {
         let response = self.client
             .request(method.clone(), url)
             .header("Authorization", format!("Bearer {}", token))
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() {
@@ -137,7 +128,7 @@ impl OneDriveAppApi {
             let response = self.client
                 .request(method, url)
                 .header("Authorization", format!("Bearer {}", new_token))
-                .send()
+                .send_retry()
                 .await?;
 
             if response.status().is_success() {
@@ -205,7 +196,7 @@ impl OneDriveAppApi {
             .post(&url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&body)
-            .send()
+            .send_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -243,7 +234,7 @@ impl OneDriveAppApi {
             .header("Content-Range", &content_range)
             .header("Content-Length", chunk.len().to_string())
             .body(chunk)
-            .send()
+            .send_retry()
             .await?;
 
         let status = response.status();
@@ -307,7 +298,7 @@ impl OneDriveAppApi {
             .header("Content-Type", "application/octet-stream")
             .header("Content-Length", data.len().to_string())
             .body(data)
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() {
@@ -327,7 +318,7 @@ impl OneDriveAppApi {
         let response = self.client
             .delete(&url)
             .header("Authorization", format!("Bearer {}", token))
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() || response.status() == 204 {
@@ -362,7 +353,7 @@ impl OneDriveAppApi {
             .post(&url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&body)
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() {
@@ -383,7 +374,7 @@ impl OneDriveAppApi {
             .patch(&url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&body)
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() {
@@ -423,7 +414,7 @@ impl OneDriveAppApi {
             .patch(&url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&body)
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() {
@@ -461,7 +452,7 @@ impl OneDriveAppApi {
             .post(&url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&body)
-            .send()
+            .send_retry()
             .await?;
 
         if response.status().is_success() || response.status().as_u16() == 202 {
@@ -248,7 +248,7 @@ impl DriverFactory for OneDriveAppDriverFactory {
             ConfigItem::new("client_id", "string")
                 .title("客户端 ID")
                 .required(),
-            ConfigItem::new("client_secret", "string")
+            ConfigItem::new("client_secret", "string").password()
                 .title("客户端密钥")
                 .required(),
             ConfigItem::new("tenant_id", "string")
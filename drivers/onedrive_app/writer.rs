@@ -379,7 +379,7 @@ async fn upload_worker(
                     
                     // 如果是最后一个分片，标记完成
                     if task.is_last {
-                        tracing::info!("OneDrive App上传完成: path={}, total={}", path, end);
+                        tracing::info!(path = %path, total = end, "OneDrive App上传完成");
                     }
                     last_err = None;
                     break;
@@ -514,8 +514,8 @@ impl AsyncWrite for OneDriveAppWriter {
         let sent = self.sent_bytes.load(Ordering::SeqCst);
         
         if !chunk.is_empty() {
-            tracing::debug!("OneDrive App Writer shutdown: 发送最后一个分片: path={}, chunk_size={}, written={}, sent={}", 
-                self.path, chunk.len(), written, sent);
+            tracing::debug!(path = %self.path, chunk_size = chunk.len(), written, sent,
+                "OneDrive App Writer shutdown: 发送最后一个分片");
             if let Err(e) = self.send_chunk(chunk, true) {
                 return Poll::Ready(Err(e));
             }
@@ -523,7 +523,7 @@ impl AsyncWrite for OneDriveAppWriter {
             // buffer 为空，检查是否所有数据都已发送
             if written == 0 && self.total_size == 0 {
                 // 空文件：需要发送一个空分片来创建文件
-                tracing::debug!("OneDrive App Writer shutdown: 空文件，发送空分片: path={}", self.path);
+                tracing::debug!(path = %self.path, "OneDrive App Writer shutdown: 空文件，发送空分片");
                 if let Err(e) = self.send_chunk(Vec::new(), true) {
                     return Poll::Ready(Err(e));
                 }
@@ -542,8 +542,8 @@ impl AsyncWrite for OneDriveAppWriter {
                 // 所有数据都已发送，但最后一个分片可能还没有标记为 is_last
                 // 这种情况不应该发生，因为如果 buffer 为空且 sent > 0，说明所有数据都已发送
                 // 但为了安全，我们继续执行验证
-                tracing::debug!("OneDrive App Writer shutdown: buffer为空，所有数据已发送: path={}, sent={}, written={}", 
-                    self.path, sent, written);
+                tracing::debug!(path = %self.path, sent, written,
+                    "OneDrive App Writer shutdown: buffer为空，所有数据已发送");
             }
         }
 
@@ -572,8 +572,8 @@ impl AsyncWrite for OneDriveAppWriter {
         let written = self.written_bytes.load(Ordering::SeqCst);
         let sent = self.sent_bytes.load(Ordering::SeqCst);
         
-        tracing::debug!("OneDrive App Writer shutdown 验证: path={}, uploaded={}, written={}, sent={}, total_size={}", 
-            self.path, uploaded, written, sent, self.total_size);
+        tracing::debug!(path = %self.path, uploaded, written, sent, total_size = self.total_size,
+            "OneDrive App Writer shutdown 验证");
         
         if self.total_size > 0 {
             if uploaded != self.total_size {
@@ -614,8 +614,8 @@ impl AsyncWrite for OneDriveAppWriter {
             }
         }
         
-        tracing::info!("OneDrive App Writer shutdown: path={}, total_uploaded={}, total_written={}, total_sent={}", 
-            self.path, uploaded, written, sent);
+        tracing::info!(path = %self.path, total_uploaded = uploaded, total_written = written, total_sent = sent,
+            "OneDrive App Writer shutdown");
 
         Poll::Ready(Ok(()))
     }
@@ -7,6 +7,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::types::*;
 use super::crypto::*;
+use crate::storage::{HttpClientKey, SendRetry, shared_http_client};
 
 const API_FILE_LIST: &str = "https://webapi.115.com/files";
 const API_FILE_INFO: &str = "https://webapi.115.com/files/file";
@@ -38,9 +39,8 @@ pub struct Pan115Client {
 
 impl Pan115Client {
     pub fn new(cookie: &str, page_size: i64) -> Result<Self> {
-        let http = Client::builder()
-            .timeout(std::time::Duration::from_secs(120))
-            .build()?;
+        // Cookie is sent per request via header, so the pooled client can be shared
+        let http = shared_http_client(&HttpClientKey::new().timeout(120))?;
         
         Ok(Self {
             http,
@@ -97,7 +97,7 @@ impl Pan115Client {
         let resp: VersionResp = self.http
             .get(API_VERSION)
             .headers(self.build_headers())
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -117,7 +117,7 @@ impl Pan115Client {
         let resp: Value = self.http
             .get(API_USER_INFO)
             .headers(self.build_headers())
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -134,7 +134,7 @@ impl Pan115Client {
         let resp: UploadInfoResp = self.http
             .get(API_UPLOAD_INFO)
             .headers(self.build_headers())
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -163,7 +163,7 @@ impl Pan115Client {
                     ("source", ""),
                     ("format", "json"),
                 ])
-                .send()
+                .send_retry()
                 .await?
                 .json()
                 .await?;
@@ -201,7 +201,7 @@ impl Pan115Client {
             .get(API_FILE_INFO)
             .headers(self.build_headers())
             .query(&[("file_id", file_id)])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -221,7 +221,7 @@ impl Pan115Client {
             .get("https://webapi.115.com/files/file")
             .headers(self.build_headers())
             .query(&[("pick_code", pick_code)])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -263,7 +263,7 @@ impl Pan115Client {
             .post(&url)
             .headers(headers)
             .form(&[("data", &encoded)])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -295,7 +295,7 @@ impl Pan115Client {
                 ("pid", parent_id),
                 ("cname", name),
             ])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -310,7 +310,7 @@ impl Pan115Client {
             .post(API_FILE_RENAME)
             .headers(self.build_headers())
             .form(&[(key.as_str(), new_name)])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -327,7 +327,7 @@ impl Pan115Client {
                 ("pid", target_id),
                 ("fid[0]", file_id),
             ])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -344,7 +344,7 @@ impl Pan115Client {
                 ("pid", target_id),
                 ("fid[0]", file_id),
             ])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -361,7 +361,7 @@ impl Pan115Client {
                 ("fid[0]", file_id),
                 ("ignore_warn", "1"),
             ])
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -374,7 +374,7 @@ impl Pan115Client {
         let resp: OssTokenResp = self.http
             .get(API_OSS_TOKEN)
             .headers(self.build_headers())
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -390,7 +390,7 @@ impl Pan115Client {
         let resp: SpaceInfoResp = self.http
             .get(API_SPACE_INFO)
             .headers(self.build_headers())
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -460,7 +460,7 @@ impl Pan115Client {
             .post(API_UPLOAD_INIT)
             .headers(self.build_headers())
             .form(&form)
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -414,7 +414,7 @@ impl DriverFactory for Pan115DriverFactory {
     
     fn additional_items(&self) -> Vec<ConfigItem> {
         vec![
-            ConfigItem::new("cookie", "string")
+            ConfigItem::new("cookie", "string").password()
                 .title("Cookie")
                 .required()
                 .help("从浏览器获取的Cookie，包含UID、CID、SEID"),
@@ -334,6 +334,4 @@ pub struct Pan115Config {
     pub page_size: i64,
     #[serde(default)]
     pub limit_rate: f64,
-    #[serde(default)]
-    pub proxy_download: bool,
 }
@@ -16,7 +16,7 @@ use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use tokio_util::io::StreamReader;
 
-use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback, DriverFactory, DriverConfig, ConfigItem};
+use crate::storage::{StorageDriver, SendRetry, Entry, Capability, SpaceInfo, ProgressCallback, DriverFactory, DriverConfig, ConfigItem};
 use super::super::pan115::crypto::{m115_encode, m115_decode, generate_random_key};
 
 /// API地址
@@ -230,7 +230,7 @@ impl Pan115ShareDriver {
                 req = req.header("Cookie", &self.config.cookie);
             }
             
-            let response = req.send().await.context("请求分享列表失败")?;
+            let response = req.send_retry().await.context("请求分享列表失败")?;
             let status = response.status();
             let text = response.text().await.context("读取响应失败")?;
             tracing::debug!("115Share API状态: {}, 响应: {}", status, &text[..text.len().min(500)]);
@@ -329,7 +329,7 @@ impl Pan115ShareDriver {
             req = req.header("Cookie", &self.config.cookie);
         }
         
-        let response = req.send().await.context("请求下载链接失败")?;
+        let response = req.send_retry().await.context("请求下载链接失败")?;
         let text = response.text().await.context("读取响应失败")?;
         tracing::debug!("115Share 下载响应: {}", &text[..text.len().min(200)]);
         
@@ -552,7 +552,7 @@ impl StorageDriver for Pan115ShareDriver {
             req = req.header("Range", format!("bytes={}-{}", r.start, r.end - 1));
         }
         
-        let response = req.send().await.context("下载请求失败")?;
+        let response = req.send_retry().await.context("下载请求失败")?;
         
         if !response.status().is_success() && response.status().as_u16() != 206 {
             return Err(anyhow!("下载失败: HTTP {}", response.status()));
@@ -628,7 +628,7 @@ impl DriverFactory for Pan115ShareDriverFactory {
                 .title("接收码")
                 .help("分享链接中的接收码")
                 .required(),
-            ConfigItem::new("cookie", "string")
+            ConfigItem::new("cookie", "string").password()
                 .title("Cookie")
                 .help("可选，用于需要登录才能访问的分享"),
             ConfigItem::new("root_id", "string")
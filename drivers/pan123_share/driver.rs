@@ -17,7 +17,7 @@ use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use futures::TryStreamExt;
 use tokio_util::io::StreamReader;
 
-use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback, DriverFactory, DriverConfig, ConfigItem};
+use crate::storage::{StorageDriver, SendRetry, Entry, Capability, SpaceInfo, ProgressCallback, DriverFactory, DriverConfig, ConfigItem};
 
 /// API地址
 const MAIN_API: &str = "https://www.123pan.com/b/api";
@@ -229,7 +229,7 @@ impl Pan123ShareDriver {
             req = req.json(&body);
         }
         
-        let response = req.send().await.context("请求失败")?;
+        let response = req.send_retry().await.context("请求失败")?;
         let json: Value = response.json().await.context("解析响应失败")?;
         
         let code = json.get("code").and_then(|v| v.as_i64()).unwrap_or(-1);
@@ -353,7 +353,7 @@ impl Pan123ShareDriver {
         let response = self.no_redirect_client
             .get(&final_url)
             .header("Referer", "https://www.123pan.com/")
-            .send()
+            .send_retry()
             .await;
         
         if let Ok(resp) = response {
@@ -503,7 +503,7 @@ impl StorageDriver for Pan123ShareDriver {
             req = req.header("Range", format!("bytes={}-{}", r.start, r.end - 1));
         }
         
-        let response = req.send().await.context("下载请求失败")?;
+        let response = req.send_retry().await.context("下载请求失败")?;
         
         if !response.status().is_success() && response.status().as_u16() != 206 {
             return Err(anyhow!("下载失败: HTTP {}", response.status()));
@@ -582,7 +582,7 @@ impl DriverFactory for Pan123ShareDriverFactory {
                 .title("根目录ID")
                 .help("默认为0（根目录）")
                 .default("0"),
-            ConfigItem::new("access_token", "string")
+            ConfigItem::new("access_token", "string").password()
                 .title("AccessToken")
                 .help("可选，用于需要登录才能访问的分享"),
         ]
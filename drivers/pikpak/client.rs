@@ -9,6 +9,7 @@ use std::sync::{Arc, RwLock};
 
 use super::types::*;
 use super::util::*;
+use crate::storage::SendRetry;
 
 /// Platform type / 平台类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -196,7 +197,7 @@ impl PikPakClient {
             .post(api::LOGIN_URL)
             .query(&[("client_id", self.platform.client_id())])
             .json(&body)
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -239,7 +240,7 @@ impl PikPakClient {
             .post(api::TOKEN_URL)
             .query(&[("client_id", self.platform.client_id())])
             .json(&body)
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -306,7 +307,7 @@ impl PikPakClient {
             .post(api::CAPTCHA_URL)
             .query(&[("client_id", self.platform.client_id())])
             .json(&req)
-            .send()
+            .send_retry()
             .await?
             .json()
             .await?;
@@ -356,7 +357,7 @@ impl PikPakClient {
             req = req.json(&b);
         }
 
-        let resp = req.send().await?;
+        let resp = req.send_retry().await?;
         let status = resp.status();
         let text = resp.text().await?;
 
@@ -599,7 +599,7 @@ impl DriverFactory for PikPakDriverFactory {
             ConfigItem::new("password", "password")
                 .title("Password / 密码")
                 .help("PikPak账号密码"),
-            ConfigItem::new("refresh_token", "string")
+            ConfigItem::new("refresh_token", "string").password()
                 .title("Refresh Token")
                 .help("使用refresh_token认证(如果提供了用户名密码则可选)"),
             ConfigItem::new("platform", "select")
@@ -22,8 +22,9 @@ use chrono::Utc;
 
 use crate::storage::{
     Capability, ConfigItem, DriverConfig, DriverFactory, Entry, SpaceInfo,
-    StorageDriver,
+    StorageDriver, HttpClientKey, shared_http_client, shared_http_client_or_fallback,
 };
+use crate::storage::http_stream::{response_reader, with_range};
 
 /// 夸克网盘配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -138,10 +139,7 @@ impl QuarkDriver {
     pub fn new(config: QuarkConfig) -> Self {
         Self {
             config,
-            client: Client::builder()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()
-                .unwrap(),
+            client: shared_http_client_or_fallback(&HttpClientKey::new().no_redirect()),
             path_cache: Arc::new(RwLock::new(HashMap::new())),
         }
     }
@@ -336,39 +334,26 @@ impl QuarkDriver {
     }
 }
 
-/// 夸克下载读取器（使用tokio_util的ReaderStream包装）
+/// 夸克下载读取器（流式读取，支持Range）
 pub struct QuarkReader {
     inner: Pin<Box<dyn AsyncRead + Send + Unpin>>,
 }
 
 impl QuarkReader {
-    async fn new(url: &str, cookie: &str, range: Option<(u64, u64)>) -> Result<Self> {
-        let client = Client::new();
-        let mut req = client
+    async fn new(url: &str, cookie: &str, range: Option<Range<u64>>) -> Result<Self> {
+        let client = shared_http_client(&HttpClientKey::new())?;
+        let req = client
             .get(url)
             .header("Cookie", cookie)
             .header("Referer", QuarkDriver::REFERER)
             .header("User-Agent", QuarkDriver::USER_AGENT);
 
-        if let Some((start, end)) = range {
-            req = req.header("Range", format!("bytes={}-{}", start, end));
-        }
-
-        let response = req.send().await?;
-        
-        if !response.status().is_success() && response.status().as_u16() != 206 {
-            return Err(anyhow!("下载失败: HTTP {}", response.status()));
-        }
-
-        // 使用StreamReader包装响应字节流
-        let stream = response.bytes_stream();
-        let stream = stream.map(|result| {
-            result.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-        });
-        let reader = tokio_util::io::StreamReader::new(stream);
+        // 转发Range并流式读取响应，上游忽略Range时截取所需部分
+        let response = with_range(req, range.as_ref()).send().await?;
+        let reader = response_reader(response, range)?;
 
         Ok(Self {
-            inner: Box::pin(reader),
+            inner: Box::into_pin(reader),
         })
     }
 }
@@ -422,7 +407,7 @@ impl QuarkWriter {
         Self {
             buffer: Vec::new(),
             path,
-            client: Client::new(),
+            client: shared_http_client_or_fallback(&HttpClientKey::new()),
             cookie,
             root_folder_id,
             closed: false,
@@ -472,7 +457,7 @@ impl QuarkWriter {
     }
 
     async fn do_upload(&self) -> std::io::Result<()> {
-        tracing::info!("夸克上传: path={}, size={}", self.path, self.buffer.len());
+        tracing::info!(path = %self.path, size = self.buffer.len(), "夸克上传");
         
         let file_name = std::path::Path::new(&self.path).file_name().and_then(|n| n.to_str())
             .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "无效文件名"))?.to_string();
@@ -520,7 +505,7 @@ impl QuarkWriter {
         // 提交
         self.upload_commit(&pre, &etags).await?;
         self.upload_finish(&pre).await?;
-        tracing::info!("上传完成: {}", self.path);
+        tracing::info!(path = %self.path, "夸克上传完成");
         Ok(())
     }
 
@@ -677,8 +662,7 @@ impl StorageDriver for QuarkDriver {
         let fid = self.get_fid_by_path(path).await?;
         let url = self.get_download_url(&fid).await?;
         
-        let range_tuple = range.map(|r| (r.start, r.end.saturating_sub(1)));
-        let reader = QuarkReader::new(&url, &self.config.cookie, range_tuple).await?;
+        let reader = QuarkReader::new(&url, &self.config.cookie, range).await?;
         Ok(Box::new(reader))
     }
 
@@ -844,7 +828,7 @@ impl DriverFactory for QuarkDriverFactory {
 
     fn additional_items(&self) -> Vec<ConfigItem> {
         vec![
-            ConfigItem::new("cookie", "string")
+            ConfigItem::new("cookie", "string").password()
                 .title("Cookie")
                 .required()
                 .help("从浏览器获取的Cookie"),
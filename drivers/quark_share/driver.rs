@@ -0,0 +1,415 @@
+//! 夸克网盘分享驱动实现
+//!
+//! 夸克不提供分享文件的直接下载：读取文件时先将其转存到自己网盘的转存目录，再下载转存后的文件。
+//! 每个文件只转存一次，之后重复使用转存的副本；CDN 校验 Cookie，只能中转。
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::RwLock;
+
+use crate::storage::{
+    Capability, ConfigItem, DriverConfig, DriverFactory, Entry, HttpClientKey, ProgressCallback,
+    SendRetry, SpaceInfo, StorageDriver, shared_http_client_or_fallback,
+};
+use crate::storage::http_stream::{response_reader, with_range};
+use super::super::share_url;
+
+const API_BASE: &str = "https://drive-pc.quark.cn/1/clouddrive";
+const REFERER: &str = "https://pan.quark.cn";
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) quark-cloud-drive/2.5.20 Chrome/100.0.4896.160 Electron/18.3.5.4-b478491100 Safari/537.36 Channel/pckk_other_ch";
+
+/// 转存任务最多等待的轮询次数（每次500ms）
+const SAVE_POLL_LIMIT: u32 = 40;
+
+/// 夸克网盘分享配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarkShareConfig {
+    /// 分享ID（pwd_id）或分享链接
+    pub share_id: String,
+    /// 提取码（可选）
+    #[serde(default)]
+    pub passcode: String,
+    /// 自己账号的Cookie（转存与下载需要）
+    pub cookie: String,
+    /// 分享内的根目录ID（默认0）
+    #[serde(default = "default_zero")]
+    pub root_id: String,
+    /// 自己网盘中用于转存的目录ID（默认0，即根目录）
+    #[serde(default = "default_zero")]
+    pub save_dir_id: String,
+}
+
+fn default_zero() -> String {
+    "0".to_string()
+}
+
+/// 分享中的文件
+#[derive(Debug, Clone, Deserialize)]
+struct ShareFile {
+    fid: String,
+    file_name: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    dir: bool,
+    #[serde(default)]
+    updated_at: i64,
+    #[serde(default)]
+    share_fid_token: String,
+}
+
+/// 夸克网盘分享驱动
+pub struct QuarkShareDriver {
+    config: QuarkShareConfig,
+    pwd_id: String,
+    client: Client,
+    stoken: RwLock<String>,
+    /// 路径到目录fid的缓存
+    path_cache: RwLock<HashMap<String, String>>,
+    /// 分享文件fid到转存后fid的缓存
+    saved: RwLock<HashMap<String, String>>,
+}
+
+impl QuarkShareDriver {
+    pub fn new(config: QuarkShareConfig) -> Self {
+        let pwd_id = share_url::share_id(&config.share_id);
+        Self {
+            config,
+            pwd_id,
+            client: shared_http_client_or_fallback(&HttpClientKey::new().timeout(60)),
+            stoken: RwLock::new(String::new()),
+            path_cache: RwLock::new(HashMap::new()),
+            saved: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 发送API请求，返回完整响应（含metadata）
+    async fn request(&self, method: Method, path: &str, params: &[(&str, &str)], body: Option<Value>) -> Result<Value> {
+        let mut req = self.client
+            .request(method, format!("{}{}", API_BASE, path))
+            .header("Cookie", &self.config.cookie)
+            .header("Accept", "application/json, text/plain, */*")
+            .header("Referer", REFERER)
+            .header("User-Agent", USER_AGENT)
+            .query(&[("pr", "ucpro"), ("fr", "pc")])
+            .query(params);
+        if let Some(b) = body {
+            req = req.json(&b);
+        }
+        let json: Value = req.send_retry().await?.json().await?;
+        if json["code"].as_i64().unwrap_or(-1) != 0 {
+            return Err(anyhow!("夸克API错误: {} (code={})", json["message"].as_str().unwrap_or("未知错误"), json["code"]));
+        }
+        Ok(json)
+    }
+
+    /// 获取分享访问令牌 stoken
+    async fn refresh_stoken(&self) -> Result<String> {
+        let body = json!({"pwd_id": self.pwd_id, "passcode": self.config.passcode.trim()});
+        let json = self.request(Method::POST, "/share/sharepage/token", &[], Some(body)).await?;
+        let stoken = json["data"]["stoken"].as_str()
+            .ok_or_else(|| anyhow!("获取分享令牌失败，请检查分享链接与提取码"))?
+            .to_string();
+        *self.stoken.write().await = stoken.clone();
+        Ok(stoken)
+    }
+
+    async fn get_stoken(&self) -> Result<String> {
+        let stoken = self.stoken.read().await.clone();
+        if stoken.is_empty() {
+            return self.refresh_stoken().await;
+        }
+        Ok(stoken)
+    }
+
+    /// 列出分享目录中的全部文件，stoken 过期时刷新后重试一次
+    async fn list_files(&self, pdir_fid: &str) -> Result<Vec<ShareFile>> {
+        match self.list_files_with(pdir_fid, &self.get_stoken().await?).await {
+            Ok(files) => Ok(files),
+            Err(e) => {
+                tracing::debug!("QuarkShare: 列表失败，刷新stoken后重试: {}", e);
+                let stoken = self.refresh_stoken().await?;
+                self.list_files_with(pdir_fid, &stoken).await
+            }
+        }
+    }
+
+    async fn list_files_with(&self, pdir_fid: &str, stoken: &str) -> Result<Vec<ShareFile>> {
+        let mut files = Vec::new();
+        let size = 100;
+        let mut page = 1;
+        loop {
+            let page_str = page.to_string();
+            let size_str = size.to_string();
+            let params = [
+                ("pwd_id", self.pwd_id.as_str()),
+                ("stoken", stoken),
+                ("pdir_fid", pdir_fid),
+                ("force", "0"),
+                ("_page", page_str.as_str()),
+                ("_size", size_str.as_str()),
+                ("_fetch_banner", "0"),
+                ("_fetch_share", "0"),
+                ("_fetch_total", "1"),
+                ("_sort", "file_type:asc,file_name:asc"),
+            ];
+            let json = self.request(Method::GET, "/share/sharepage/detail", &params, None).await?;
+            let list: Vec<ShareFile> = serde_json::from_value(json["data"]["list"].clone()).unwrap_or_default();
+            let count = list.len();
+            files.extend(list);
+
+            let total = json["metadata"]["_total"].as_u64().unwrap_or(0) as usize;
+            if count == 0 || files.len() >= total {
+                break;
+            }
+            page += 1;
+        }
+        Ok(files)
+    }
+
+    /// 通过路径获取目录fid（逐级查找并缓存）
+    async fn get_dir_fid(&self, path: &str) -> Result<String> {
+        let mut id = self.config.root_id.clone();
+        let mut current = String::new();
+        for name in path.split('/').filter(|s| !s.is_empty()) {
+            let parent = std::mem::take(&mut current);
+            current = if parent.is_empty() { name.to_string() } else { format!("{}/{}", parent, name) };
+            if let Some(cached) = self.path_cache.read().await.get(&current) {
+                id = cached.clone();
+                continue;
+            }
+            let files = self.list_files(&id).await?;
+            self.cache_children(&parent, &files).await;
+            id = files.into_iter()
+                .find(|f| f.file_name == name && f.dir)
+                .map(|f| f.fid)
+                .ok_or_else(|| anyhow!("目录不存在: /{}", current))?;
+        }
+        Ok(id)
+    }
+
+    /// 在父目录中查找文件
+    async fn find(&self, path: &str) -> Result<ShareFile> {
+        let path = path.trim_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let parent_fid = self.get_dir_fid(parent).await?;
+        let files = self.list_files(&parent_fid).await?;
+        self.cache_children(parent, &files).await;
+        files.into_iter()
+            .find(|f| f.file_name == name)
+            .ok_or_else(|| anyhow!("路径不存在: /{}", path))
+    }
+
+    /// 缓存子目录的fid
+    async fn cache_children(&self, parent: &str, files: &[ShareFile]) {
+        let mut cache = self.path_cache.write().await;
+        for f in files.iter().filter(|f| f.dir) {
+            let key = if parent.is_empty() { f.file_name.clone() } else { format!("{}/{}", parent, f.file_name) };
+            cache.insert(key, f.fid.clone());
+        }
+    }
+
+    /// 将分享中的文件转存到自己的网盘，返回转存后的fid
+    async fn save_to_drive(&self, file: &ShareFile) -> Result<String> {
+        let body = json!({
+            "fid_list": [file.fid],
+            "fid_token_list": [file.share_fid_token],
+            "to_pdir_fid": self.config.save_dir_id,
+            "pwd_id": self.pwd_id,
+            "stoken": self.get_stoken().await?,
+            "pdir_fid": "0",
+            "scene": "link",
+        });
+        let json = self.request(Method::POST, "/share/sharepage/save", &[], Some(body)).await?;
+        let task_id = json["data"]["task_id"].as_str()
+            .ok_or_else(|| anyhow!("转存失败：响应中没有任务ID"))?
+            .to_string();
+
+        // 等待转存任务完成（status=2）
+        for retry in 0..SAVE_POLL_LIMIT {
+            let retry_str = retry.to_string();
+            let json = self.request(Method::GET, "/task", &[("task_id", task_id.as_str()), ("retry_index", retry_str.as_str())], None).await?;
+            if json["data"]["status"].as_i64() == Some(2) {
+                return json["data"]["save_as"]["save_as_top_fids"][0].as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("转存失败：响应中没有文件ID"));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        Err(anyhow!("转存超时"))
+    }
+
+    /// 转存后文件的下载链接
+    async fn download_url(&self, fid: &str) -> Result<String> {
+        let json = self.request(Method::POST, "/file/download", &[], Some(json!({"fids": [fid]}))).await?;
+        json["data"][0]["download_url"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("获取下载链接失败"))
+    }
+
+    /// 获取分享文件的下载链接，已转存的副本被删除时重新转存
+    async fn get_download_url(&self, file: &ShareFile) -> Result<String> {
+        let saved_fid = self.saved.read().await.get(&file.fid).cloned();
+        if let Some(fid) = saved_fid {
+            match self.download_url(&fid).await {
+                Ok(url) => return Ok(url),
+                Err(e) => tracing::debug!("QuarkShare: 转存的副本不可用，重新转存: {}", e),
+            }
+        }
+        let fid = self.save_to_drive(file).await?;
+        self.saved.write().await.insert(file.fid.clone(), fid.clone());
+        self.download_url(&fid).await
+    }
+}
+
+#[async_trait]
+impl StorageDriver for QuarkShareDriver {
+    fn name(&self) -> &str {
+        "QuarkShare"
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> Capability {
+        Capability {
+            can_range_read: true,
+            can_direct_link: false, // 夸克CDN需要headers验证，不支持302
+            ..Default::default()
+        }
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        tracing::debug!("QuarkShare: 列出目录 {}", path);
+        let dir = path.trim_matches('/');
+        let fid = self.get_dir_fid(dir).await?;
+        let files = self.list_files(&fid).await?;
+        self.cache_children(dir, &files).await;
+
+        Ok(files.into_iter().map(|f| Entry {
+            path: format!("/{}", if dir.is_empty() { f.file_name.clone() } else { format!("{}/{}", dir, f.file_name) }),
+            is_dir: f.dir,
+            size: f.size,
+            modified: chrono::DateTime::from_timestamp_millis(f.updated_at).map(|dt| dt.to_rfc3339()),
+            name: f.file_name,
+        }).collect())
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        tracing::debug!("QuarkShare: 读取文件 {} range={:?}", path, range);
+        let file = self.find(path).await?;
+        if file.dir {
+            return Err(anyhow!("不能读取目录: {}", path));
+        }
+        let url = self.get_download_url(&file).await?;
+
+        let req = self.client
+            .get(&url)
+            .header("Cookie", &self.config.cookie)
+            .header("Referer", REFERER)
+            .header("User-Agent", USER_AGENT);
+        let response = with_range(req, range.as_ref()).send_retry().await?;
+        response_reader(response, range)
+    }
+
+    async fn open_writer(
+        &self,
+        _path: &str,
+        _size_hint: Option<u64>,
+        _progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        Err(anyhow!("夸克网盘分享不支持上传"))
+    }
+
+    async fn delete(&self, _path: &str) -> Result<()> {
+        Err(anyhow!("夸克网盘分享不支持删除"))
+    }
+
+    async fn create_dir(&self, _path: &str) -> Result<()> {
+        Err(anyhow!("夸克网盘分享不支持创建文件夹"))
+    }
+
+    async fn rename(&self, _path: &str, _new_name: &str) -> Result<()> {
+        Err(anyhow!("夸克网盘分享不支持重命名"))
+    }
+
+    async fn move_item(&self, _old_path: &str, _new_path: &str) -> Result<()> {
+        Err(anyhow!("夸克网盘分享不支持移动"))
+    }
+
+    async fn get_direct_link(&self, _path: &str) -> Result<Option<String>> {
+        // 夸克CDN需要Cookie/Referer/User-Agent验证，不支持302重定向
+        Ok(None)
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        Ok(None) // 分享不提供空间信息
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        false
+    }
+}
+
+/// 夸克网盘分享驱动工厂
+pub struct QuarkShareDriverFactory;
+
+impl DriverFactory for QuarkShareDriverFactory {
+    fn driver_type(&self) -> &'static str {
+        "QuarkShare"
+    }
+
+    fn driver_config(&self) -> DriverConfig {
+        DriverConfig {
+            name: "夸克网盘分享".to_string(),
+            local_sort: true,
+            only_proxy: true, // 夸克CDN需要headers验证，必须代理
+            no_cache: false,
+            no_upload: true, // 分享不支持上传
+            default_root: Some("0".to_string()),
+        }
+    }
+
+    fn additional_items(&self) -> Vec<ConfigItem> {
+        vec![
+            ConfigItem::new("share_id", "string")
+                .title("分享ID")
+                .help("分享链接或其中的ID，如 https://pan.quark.cn/s/xxxx 中的 xxxx")
+                .required(),
+            ConfigItem::new("passcode", "string")
+                .title("提取码")
+                .help("如果分享有提取码，请填写"),
+            ConfigItem::new("cookie", "string").password()
+                .title("Cookie")
+                .help("自己账号的Cookie，下载前需要先将文件转存到自己的网盘")
+                .required(),
+            ConfigItem::new("root_id", "string")
+                .title("根目录ID")
+                .help("分享内的目录ID，默认为0（分享根目录）")
+                .default("0"),
+            ConfigItem::new("save_dir_id", "string")
+                .title("转存目录ID")
+                .help("自己网盘中存放转存文件的目录ID，默认为0（根目录），建议使用单独的目录并定期清理")
+                .default("0"),
+        ]
+    }
+
+    fn create_driver(&self, config: Value) -> Result<Box<dyn StorageDriver>> {
+        let config: QuarkShareConfig = serde_json::from_value(config)
+            .map_err(|e| anyhow!("配置解析失败: {}", e))?;
+        Ok(Box::new(QuarkShareDriver::new(config)))
+    }
+}
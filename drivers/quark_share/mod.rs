@@ -0,0 +1,4 @@
+//! 夸克网盘分享驱动模块
+pub mod driver;
+
+pub use driver::{QuarkShareDriver, QuarkShareDriverFactory};
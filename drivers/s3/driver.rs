@@ -22,12 +22,14 @@ use std::sync::Arc;
 use futures::stream::{FuturesUnordered, StreamExt};
 use futures::{FutureExt, Future};
 
-use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback};
+use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback, HttpClientKey, shared_http_client};
+use crate::storage::http_stream::{response_reader, with_range};
 use super::config::S3Config;
 
 const CHUNK_SIZE: usize = 8 * 1024 * 1024; // 8MB per chunk (S3最小5MB)
 const MAX_BUFFER_CHUNKS: usize = 2; // channel容量2
 const CONCURRENT_UPLOADS: usize = 2; // 2个并发上传
+const READER_URL_EXPIRE_SECS: u32 = 300; // 服务端读取用的预签名URL有效期
 
 /// S3驱动
 pub struct S3Driver {
@@ -266,22 +268,17 @@ impl StorageDriver for S3Driver {
     ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
         let key = self.get_object_key(path);
         
-        // 流式获取对象
-        let response = if let Some(r) = range {
-            self.bucket
-                .get_object_range(&key, r.start, Some(r.end))
-                .await
-                .map_err(|e| anyhow!("获取S3对象失败: {}", e))?
-        } else {
-            self.bucket
-                .get_object(&key)
-                .await
-                .map_err(|e| anyhow!("获取S3对象失败: {}", e))?
-        };
-        
-        // rust-s3返回完整响应，封装为AsyncRead
-        let data = response.bytes().to_vec();
-        Ok(Box::new(std::io::Cursor::new(data)))
+        // rust-s3的范围读取会缓冲整个响应，改用短时预签名URL流式读取并转发Range
+        let url = self.bucket
+            .presign_get(&key, READER_URL_EXPIRE_SECS, None)
+            .await
+            .map_err(|e| anyhow!("生成预签名URL失败: {}", e))?;
+        let request = shared_http_client(&HttpClientKey::new())?.get(&url);
+        let response = with_range(request, range.as_ref())
+            .send()
+            .await
+            .map_err(|e| anyhow!("获取S3对象失败: {}", e))?;
+        response_reader(response, range)
     }
     
     async fn open_writer(
@@ -422,6 +419,33 @@ impl StorageDriver for S3Driver {
     async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
         Ok(None)
     }
+    
+    async fn abort_stale_uploads(&self, older_than: std::time::Duration) -> Result<usize> {
+        // 只处理根目录下的分片上传，不影响同一存储桶的其他用途
+        let prefix = self.get_prefix("/");
+        let prefix = (!prefix.is_empty()).then_some(prefix.as_str());
+        let results = self.bucket
+            .list_multiparts_uploads(prefix, None)
+            .await
+            .map_err(|e| anyhow!("列出S3分片上传失败: {}", e))?;
+        
+        let cutoff = chrono::Utc::now() - chrono::Duration::from_std(older_than).unwrap_or(chrono::Duration::MAX);
+        let mut aborted = 0;
+        for upload in results.into_iter().flat_map(|r| r.uploads) {
+            // 无法解析时间的会话保留，避免误删进行中的上传
+            let Ok(initiated) = chrono::DateTime::parse_from_rfc3339(&upload.initiated) else {
+                continue;
+            };
+            if initiated > cutoff {
+                continue;
+            }
+            match self.bucket.abort_upload(&upload.key, &upload.id).await {
+                Ok(()) => aborted += 1,
+                Err(e) => tracing::warn!(key = %upload.key, error = %e, "中止S3分片上传失败"),
+            }
+        }
+        Ok(aborted)
+    }
 }
 
 /// 分片数据
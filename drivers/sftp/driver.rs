@@ -542,13 +542,13 @@ impl DriverFactory for SftpDriverFactory {
             ConfigItem::new("username", "string")
                 .title("用户名")
                 .required(),
-            ConfigItem::new("password", "string")
+            ConfigItem::new("password", "string").password()
                 .title("密码")
                 .help("与私钥二选一"),
-            ConfigItem::new("private_key", "string")
+            ConfigItem::new("private_key", "string").password()
                 .title("私钥路径")
                 .help("与密码二选一"),
-            ConfigItem::new("passphrase", "string")
+            ConfigItem::new("passphrase", "string").password()
                 .title("私钥密码")
                 .help("如私钥有密码则填写"),
             ConfigItem::new("root_path", "string")
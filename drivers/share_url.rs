@@ -0,0 +1,64 @@
+//! Share link parsing for share-link drivers / 分享链接驱动的链接解析
+//!
+//! Share-link drivers accept either the bare share ID or the whole link copied from the
+//! provider, e.g. `https://pan.quark.cn/s/abc123#/list/share` or
+//! `https://pan.baidu.com/s/1AbCd?pwd=x1y2`.
+//! 分享链接驱动既接受分享 ID，也接受从网盘复制的完整链接。
+
+/// Share ID from a bare ID or a link containing `/s/<id>`; query, fragment and trailing
+/// slashes are dropped
+/// 从分享 ID 或含 `/s/<id>` 的链接中取出分享 ID，去掉查询参数、锚点与末尾斜杠
+pub fn share_id(input: &str) -> String {
+    let input = input.trim();
+    let id = match input.find("/s/") {
+        Some(i) => &input[i + 3..],
+        None => input,
+    };
+    id.split(['?', '#', '/']).next().unwrap_or("").trim().to_string()
+}
+
+/// Value of a query parameter in a link, e.g. the `pwd` extraction code
+/// 链接中查询参数的值，如提取码 `pwd`
+pub fn query_param(input: &str, key: &str) -> Option<String> {
+    let query = input.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or("");
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| urlencoding::decode(v).map(|v| v.into_owned()).unwrap_or_else(|_| v.to_string()))
+        .filter(|v| !v.is_empty())
+}
+
+/// Extraction code from the config, falling back to the `pwd` parameter of the link
+/// 配置中的提取码，未填写时使用链接中的 `pwd` 参数
+pub fn share_pwd(configured: &str, link: &str) -> String {
+    let configured = configured.trim();
+    if !configured.is_empty() {
+        return configured.to_string();
+    }
+    query_param(link, "pwd").unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_id() {
+        assert_eq!(share_id("abc123"), "abc123");
+        assert_eq!(share_id(" https://pan.quark.cn/s/abc123#/list/share "), "abc123");
+        assert_eq!(share_id("https://www.alipan.com/s/Xyz9/folder/6f1a"), "Xyz9");
+        assert_eq!(share_id("https://pan.baidu.com/s/1AbCd?pwd=x1y2"), "1AbCd");
+        assert_eq!(share_id(""), "");
+    }
+
+    #[test]
+    fn test_share_pwd() {
+        let link = "https://pan.baidu.com/s/1AbCd?pwd=x1y2#list";
+        assert_eq!(query_param(link, "pwd").as_deref(), Some("x1y2"));
+        assert_eq!(query_param(link, "other"), None);
+        assert_eq!(share_pwd("", link), "x1y2");
+        assert_eq!(share_pwd(" 9z8y ", link), "9z8y");
+        assert_eq!(share_pwd("", "abc123"), "");
+    }
+}
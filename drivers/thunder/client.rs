@@ -11,6 +11,7 @@ use std::time::Duration;
 use tokio::sync::RwLock;
 
 use super::types::*;
+use crate::storage::{HttpClientKey, SendRetry, shared_http_client_or_fallback};
 
 /// 迅雷客户端
 pub struct ThunderClient {
@@ -27,10 +28,7 @@ pub struct ThunderClient {
 
 impl ThunderClient {
     pub fn new(device_id: String, captcha_token: String, credit_key: String) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(300))
-            .build()
-            .unwrap();
+        let client = shared_http_client_or_fallback(&HttpClientKey::new().timeout(300));
 
         Self {
             client,
@@ -104,7 +102,7 @@ impl ThunderClient {
             .header("x-client-id", DEFAULT_CLIENT_ID)
             .header("x-client-version", DEFAULT_CLIENT_VERSION)
             .json(&param)
-            .send()
+            .send_retry()
             .await?;
 
         let resp: CaptchaTokenResponse = resp.json().await?;
@@ -155,7 +153,7 @@ impl ThunderClient {
             .post(&url)
             .header("User-Agent", "android-ok-http-client/xl-acc-sdk/version-5.0.12.512000")
             .json(&req)
-            .send()
+            .send_retry()
             .await?;
 
         let text = resp.text().await?;
@@ -251,7 +249,7 @@ impl ThunderClient {
             .header("x-client-version", DEFAULT_CLIENT_VERSION)
             .header("X-Captcha-Token", &captcha_token)
             .json(&req)
-            .send()
+            .send_retry()
             .await?;
 
         let token: TokenResp = resp.json().await?;
@@ -278,7 +276,7 @@ impl ThunderClient {
             .get(&url)
             .header("user-agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
             .header("referer", "https://i.xunlei.com/")
-            .send()
+            .send_retry()
             .await?;
 
         let text = resp.text().await?;
@@ -349,7 +347,7 @@ impl ThunderClient {
             .header("referer", "https://i.xunlei.com/")
             .header("origin", "https://i.xunlei.com")
             .form(&form)
-            .send()
+            .send_retry()
             .await?;
 
         let text = resp.text().await?;
@@ -420,7 +418,7 @@ impl ThunderClient {
             .header("x-client-id", DEFAULT_CLIENT_ID)
             .header("x-client-version", DEFAULT_CLIENT_VERSION)
             .json(&body)
-            .send()
+            .send_retry()
             .await?;
 
         let token: TokenResp = resp.json().await?;
@@ -463,7 +461,7 @@ impl ThunderClient {
             req = req.json(b);
         }
 
-        let resp = req.send().await?;
+        let resp = req.send_retry().await?;
         let text = resp.text().await?;
 
         // 检查错误
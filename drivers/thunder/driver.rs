@@ -852,7 +852,7 @@ impl DriverFactory for ThunderDriverFactory {
             ConfigItem::new("username", "string")
                 .title("手机号")
                 .required(),
-            ConfigItem::new("password", "string")
+            ConfigItem::new("password", "string").password()
                 .title("密码")
                 .required(),
             ConfigItem::new("send_sms", "action")
@@ -864,7 +864,7 @@ impl DriverFactory for ThunderDriverFactory {
                 .title("根目录ID")
                 .default("")
                 .help("留空表示根目录"),
-            ConfigItem::new("refresh_token", "string")
+            ConfigItem::new("refresh_token", "string").password()
                 .title("Refresh Token")
                 .help("登录成功后自动保存，用于免密登录"),
         ]
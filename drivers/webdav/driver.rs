@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncRead, AsyncWrite};
 use reqwest::{Client, StatusCode};
 
-use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback};
+use crate::storage::{StorageDriver, Entry, Capability, SpaceInfo, ProgressCallback, HttpClientKey, shared_http_client};
 
 /// WebDAV 配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,24 +42,24 @@ pub struct WebDavDriver {
 
 impl WebDavDriver {
     pub fn new(config: WebDavConfig) -> Result<Self> {
-        // 普通请求客户端（较短超时）
-        let client = Client::builder()
-            .danger_accept_invalid_certs(config.tls_insecure_skip_verify)
-            .timeout(std::time::Duration::from_secs(60))
-            .connect_timeout(std::time::Duration::from_secs(30))
-            .pool_max_idle_per_host(4)
-            .build()
-            .context("创建HTTP客户端失败")?;
-        
-        // 上传专用客户端（长超时，大缓冲区）
-        let upload_client = Client::builder()
-            .danger_accept_invalid_certs(config.tls_insecure_skip_verify)
-            .timeout(std::time::Duration::from_secs(3600)) // 1小时超时
-            .connect_timeout(std::time::Duration::from_secs(60))
-            .pool_max_idle_per_host(8)
-            .tcp_nodelay(true)
-            .build()
-            .context("创建上传客户端失败")?;
+        // 普通请求客户端（较短超时），与其他相同TLS选项的挂载共享连接池
+        let client = shared_http_client(
+            &HttpClientKey::new()
+                .accept_invalid_certs(config.tls_insecure_skip_verify)
+                .timeout(60),
+        )
+        .context("创建HTTP客户端失败")?;
+        
+        // 上传专用客户端（长超时，更长的连接超时与更多空闲连接）
+        let upload_client = shared_http_client(
+            &HttpClientKey::new()
+                .accept_invalid_certs(config.tls_insecure_skip_verify)
+                .timeout(3600) // 1小时超时
+                .connect_timeout(60)
+                .pool_max_idle_per_host(8)
+                .tcp_nodelay(),
+        )
+        .context("创建上传客户端失败")?;
         
         Ok(Self { config, client, upload_client })
     }
@@ -93,6 +93,19 @@ impl WebDavDriver {
         format!("Basic {}", encoded)
     }
     
+    /// href 的路径部分（可能是完整 URL 或绝对路径），已解码且去掉首尾斜杠
+    fn href_path(href: &str) -> String {
+        let path = match url::Url::parse(href) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => href.to_string(),
+        };
+        urlencoding::decode(&path)
+            .map(|p| p.into_owned())
+            .unwrap_or(path)
+            .trim_matches('/')
+            .to_string()
+    }
+    
     /// 解析PROPFIND响应，提取文件列表
     fn parse_propfind_response(&self, xml: &str, base_path: &str) -> Result<Vec<Entry>> {
         use quick_xml::Reader;
@@ -102,6 +115,9 @@ impl WebDavDriver {
         let mut reader = Reader::from_str(xml);
         reader.trim_text(true);
         
+        // 请求目录自身的路径，服务器会在结果中返回它
+        let self_path = Self::href_path(&self.build_url(base_path));
+        
         let mut current_href = String::new();
         let mut current_is_dir = false;
         let mut current_size: u64 = 0;
@@ -147,8 +163,8 @@ impl WebDavDriver {
                                 let href_path = decoded_href.trim_end_matches('/');
                                 let file_name = href_path.split('/').last().unwrap_or("").to_string();
                                 
-                                // 跳过根目录自身
-                                if !file_name.is_empty() {
+                                // 跳过请求目录自身
+                                if !file_name.is_empty() && Self::href_path(&current_href) != self_path {
                                     let entry_path = if base_path == "/" {
                                         format!("/{}", file_name)
                                     } else {
@@ -8,6 +8,7 @@ use std::sync::{Arc, RwLock};
 
 use super::types::*;
 use super::util::*;
+use crate::storage::{HttpClientKey, SendRetry, shared_http_client_or_fallback};
 
 /// 139云盘API客户端 / 139Yun API client
 pub struct Yun139Client {
@@ -21,10 +22,7 @@ impl Yun139Client {
     /// 创建新客户端 / Create new client
     pub fn new(cloud_type: CloudType, cloud_id: String) -> Self {
         Self {
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(60))
-                .build()
-                .unwrap(),
+            client: shared_http_client_or_fallback(&HttpClientKey::new().timeout(60)),
             token_info: Arc::new(RwLock::new(TokenInfo::default())),
             cloud_type,
             cloud_id,
@@ -169,7 +167,7 @@ impl Yun139Client {
             req = req.json(&b);
         }
         
-        let resp = req.send().await?;
+        let resp = req.send_retry().await?;
         let status = resp.status();
         let text = resp.text().await?;
         
@@ -204,7 +202,7 @@ impl Yun139Client {
         }
         req = req.json(&body);
         
-        let resp = req.send().await?;
+        let resp = req.send_retry().await?;
         let status = resp.status();
         let text = resp.text().await?;
         
@@ -263,7 +261,7 @@ impl Yun139Client {
                 .post(url)
                 .header("Content-Type", "application/xml")
                 .body(req_body)
-                .send()
+                .send_retry()
                 .await?;
             
             let text = resp.text().await?;
@@ -879,17 +879,17 @@ impl DriverFactory for Yun139DriverFactory {
 
     fn additional_items(&self) -> Vec<ConfigItem> {
         vec![
-            ConfigItem::new("authorization", "text")
+            ConfigItem::new("authorization", "text").password()
                 .title("Authorization")
                 .required()
                 .help("Base64编码的认证信息"),
             ConfigItem::new("username", "string")
                 .title("用户名")
                 .help("手机号"),
-            ConfigItem::new("password", "string")
+            ConfigItem::new("password", "string").password()
                 .title("密码")
                 .help("登录密码"),
-            ConfigItem::new("mail_cookies", "text")
+            ConfigItem::new("mail_cookies", "text").password()
                 .title("邮箱Cookies")
                 .help("mail.139.com的Cookies"),
             ConfigItem::new("cloud_type", "select")
@@ -0,0 +1,216 @@
+//! Per-path capability grants / 按路径授权
+//!
+//! A user group's file permissions (`read_files`, `create_upload`, ...) apply everywhere by
+//! default. Path grants override them below a path, e.g. upload only in `/incoming` for a group
+//! that may only read elsewhere, which gives non-admin users "drop box" folders. Grant paths are
+//! full storage paths like meta rules. For a path, the most specific grant of all the user's
+//! groups wins (grants on the same path are merged); without a matching grant the merged group
+//! permissions apply. Administrators are never restricted.
+//! 用户组的文件权限（`read_files`、`create_upload` 等）默认对所有路径生效。路径授权在某个路径之下覆盖这些权限，
+//! 例如只读用户组在 `/incoming` 中只能上传，从而为非管理员用户提供“投递箱”目录。授权路径与元信息规则一样使用完整存储路径。
+//! 对某个路径，用户所有组中最具体的授权生效（同一路径的授权合并）；没有匹配的授权时使用合并后的用户组权限。管理员不受限制。
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::models::UserPermissions;
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// File operation checked against the grants / 按授权检查的文件操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// List, view and download / 列出、查看与下载
+    Read,
+    /// Upload and create files or folders / 上传与新建文件或目录
+    Upload,
+    Rename,
+    Move,
+    Copy,
+    Delete,
+}
+
+/// Capability flags, named like the user group columns / 权限标记，与用户组字段同名
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    pub read_files: bool,
+    pub create_upload: bool,
+    pub rename_files: bool,
+    pub move_files: bool,
+    pub copy_files: bool,
+    pub delete_files: bool,
+}
+
+impl Capabilities {
+    pub fn has(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Read => self.read_files,
+            Capability::Upload => self.create_upload,
+            Capability::Rename => self.rename_files,
+            Capability::Move => self.move_files,
+            Capability::Copy => self.copy_files,
+            Capability::Delete => self.delete_files,
+        }
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            read_files: self.read_files || other.read_files,
+            create_upload: self.create_upload || other.create_upload,
+            rename_files: self.rename_files || other.rename_files,
+            move_files: self.move_files || other.move_files,
+            copy_files: self.copy_files || other.copy_files,
+            delete_files: self.delete_files || other.delete_files,
+        }
+    }
+}
+
+impl From<&UserPermissions> for Capabilities {
+    fn from(p: &UserPermissions) -> Self {
+        Self {
+            read_files: p.read_files,
+            create_upload: p.create_upload,
+            rename_files: p.rename_files,
+            move_files: p.move_files,
+            copy_files: p.copy_files,
+            delete_files: p.delete_files,
+        }
+    }
+}
+
+/// Capabilities of a group below a path / 用户组在某路径之下的权限
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathGrant {
+    pub path: String,
+    #[serde(flatten)]
+    pub capabilities: Capabilities,
+}
+
+/// Whether `capability` is allowed at the full storage path `path`
+/// 判断在完整存储路径 `path` 上是否允许 `capability`
+pub fn allows(base: &Capabilities, grants: &[PathGrant], capability: Capability, path: &str) -> bool {
+    effective(base, grants, path).has(capability)
+}
+
+/// Capabilities that apply at `path` / 在 `path` 上生效的权限
+pub fn effective(base: &Capabilities, grants: &[PathGrant], path: &str) -> Capabilities {
+    let path = fix_and_clean_path(path);
+    let matching = grants.iter().filter(|g| is_sub_path(&g.path, &path));
+    let Some(depth) = matching.clone().map(|g| g.path.len()).max() else {
+        return *base;
+    };
+    matching
+        .filter(|g| g.path.len() == depth)
+        .fold(Capabilities::default(), |caps, g| caps.union(g.capabilities))
+}
+
+/// Clean grant paths and merge duplicates / 规范化授权路径并合并重复项
+pub fn normalize_grants(grants: Vec<PathGrant>) -> Vec<PathGrant> {
+    let mut merged: Vec<PathGrant> = Vec::new();
+    for mut grant in grants {
+        grant.path = fix_and_clean_path(&grant.path);
+        match merged.iter_mut().find(|g| g.path == grant.path) {
+            Some(existing) => existing.capabilities = existing.capabilities.union(grant.capabilities),
+            None => merged.push(grant),
+        }
+    }
+    merged.sort_by(|a, b| a.path.cmp(&b.path));
+    merged
+}
+
+type GrantRow = (String, bool, bool, bool, bool, bool, bool);
+
+fn from_row((path, read_files, create_upload, rename_files, move_files, copy_files, delete_files): GrantRow) -> PathGrant {
+    PathGrant {
+        path,
+        capabilities: Capabilities { read_files, create_upload, rename_files, move_files, copy_files, delete_files },
+    }
+}
+
+/// Grants of one group / 单个用户组的路径授权
+pub async fn load_group_grants(db: &SqlitePool, group_id: &str) -> Result<Vec<PathGrant>, sqlx::Error> {
+    let rows: Vec<GrantRow> = sqlx::query_as(
+        "SELECT path, read_files, create_upload, rename_files, move_files, copy_files, delete_files
+         FROM group_path_grants WHERE group_id = ? ORDER BY path"
+    )
+    .bind(group_id)
+    .fetch_all(db)
+    .await?;
+    Ok(rows.into_iter().map(from_row).collect())
+}
+
+/// Grants of all groups of a user / 用户所有用户组的路径授权
+pub async fn load_user_grants(db: &SqlitePool, user_id: &str) -> Result<Vec<PathGrant>, sqlx::Error> {
+    let rows: Vec<GrantRow> = sqlx::query_as(
+        "SELECT g.path, g.read_files, g.create_upload, g.rename_files, g.move_files, g.copy_files, g.delete_files
+         FROM group_path_grants g
+         INNER JOIN user_group_members m ON m.group_id = g.group_id
+         WHERE m.user_id = ?"
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    Ok(rows.into_iter().map(from_row).collect())
+}
+
+/// Replace the grants of a group / 替换用户组的路径授权
+pub async fn save_group_grants(db: &SqlitePool, group_id: &str, grants: &[PathGrant]) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut tx = db.begin().await?;
+    sqlx::query("DELETE FROM group_path_grants WHERE group_id = ?")
+        .bind(group_id)
+        .execute(&mut *tx)
+        .await?;
+    for grant in grants {
+        let c = &grant.capabilities;
+        sqlx::query(
+            "INSERT INTO group_path_grants (group_id, path, read_files, create_upload, rename_files, move_files, copy_files, delete_files, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(group_id)
+        .bind(&grant.path)
+        .bind(c.read_files)
+        .bind(c.create_upload)
+        .bind(c.rename_files)
+        .bind(c.move_files)
+        .bind(c.copy_files)
+        .bind(c.delete_files)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(path: &str, read_files: bool, create_upload: bool) -> PathGrant {
+        PathGrant {
+            path: path.to_string(),
+            capabilities: Capabilities { read_files, create_upload, ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn test_most_specific_grant_wins() {
+        let base = Capabilities { read_files: true, ..Default::default() };
+        let grants = normalize_grants(vec![
+            grant("/incoming/", false, true),
+            grant("/incoming/public", true, false),
+            grant("/incoming/public", false, true),
+        ]);
+        assert_eq!(grants.len(), 2);
+
+        assert!(allows(&base, &grants, Capability::Read, "/docs/a.txt"));
+        assert!(!allows(&base, &grants, Capability::Upload, "/docs"));
+        assert!(!allows(&base, &grants, Capability::Read, "/incoming/a.txt"));
+        assert!(allows(&base, &grants, Capability::Upload, "/incoming/a.txt"));
+        assert!(allows(&base, &grants, Capability::Read, "/incoming/public/b"));
+        assert!(allows(&base, &grants, Capability::Upload, "/incoming/public/b"));
+        // 仅前缀相同的目录不受影响
+        assert!(allows(&base, &grants, Capability::Read, "/incoming2"));
+        assert!(!allows(&base, &grants, Capability::Delete, "/incoming/a.txt"));
+    }
+}
@@ -0,0 +1,287 @@
+//! Account lifecycle / 账号生命周期
+//!
+//! Accounts can carry an expiry date after which logins, sessions and WebDAV access are refused,
+//! and a "must change password" flag that makes the next login set a new password first.
+//! Accounts are provisioned in bulk from CSV (header row required) or JSON (an array of objects,
+//! or `{"users": [...]}`) with the columns `username`, `password`, `email`, `phone`, `groups`,
+//! `root_path`, `expires_at` and `must_change_password`. Groups are names or ids separated by
+//! `;` or `|` in CSV, or an array in JSON.
+//! Groups can require two-factor authentication: a member without it may still sign in, but the
+//! session can only reach the routes needed to set it up until it is enabled, and WebDAV/FTP
+//! logins are refused.
+//! 账号可设置过期时间，过期后拒绝登录、会话和 WebDAV 访问；“必须修改密码”标记要求下次登录时先设置新密码。
+//! 批量导入支持 CSV（需要表头）与 JSON（对象数组或 `{"users": [...]}`），字段为 `username`、`password`、
+//! `email`、`phone`、`groups`、`root_path`、`expires_at` 和 `must_change_password`。用户组可填写名称或 ID，
+//! CSV 中用 `;` 或 `|` 分隔，JSON 中使用数组。
+//! 用户组可要求成员启用两步验证：未启用的成员仍可登录，但在启用前会话只能访问设置两步验证所需的接口，
+//! WebDAV/FTP 登录会被拒绝。
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+/// Most rows accepted by one import / 单次导入的最大行数
+pub const MAX_IMPORT_ROWS: usize = 5000;
+
+/// Normalize an expiry date to RFC 3339 UTC; a bare date expires at the end of that day (UTC),
+/// an empty string clears the expiry
+/// 将过期时间规范化为 RFC 3339 UTC；只有日期时在当天结束时（UTC）过期，空字符串表示不过期
+pub fn normalize_expiry(input: &str) -> Result<Option<String>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(Some(time.with_timezone(&Utc).to_rfc3339()));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        let end = date.and_hms_opt(23, 59, 59).expect("valid time").and_utc();
+        return Ok(Some(end.to_rfc3339()));
+    }
+    Err(format!("无效的过期时间: {}", input))
+}
+
+/// Whether an account with this expiry has expired at `now` / 账号在 `now` 时是否已过期
+pub fn is_expired(expires_at: Option<&str>, now: DateTime<Utc>) -> bool {
+    expires_at
+        .and_then(|e| DateTime::parse_from_rfc3339(e).ok())
+        .is_some_and(|e| e <= now)
+}
+
+/// API routes a session may use before required two-factor setup is done
+/// 需要先设置两步验证的会话仍可访问的接口
+pub const TWO_FACTOR_SETUP_ROUTES: &[&str] = &[
+    "/api/auth/login",
+    "/api/auth/logout",
+    "/api/auth/permissions",
+    "/api/auth/me",
+    "/api/auth/captcha",
+    "/api/auth/check-captcha",
+    "/api/auth/2fa/setup",
+    "/api/auth/2fa/enable",
+    "/api/auth/impersonate/stop",
+    "/api/settings/public",
+];
+
+/// Whether a request path stays reachable while two-factor setup is pending; only API routes are
+/// restricted / 等待设置两步验证时该路径是否仍可访问，仅限制 API 接口
+pub fn allowed_before_two_factor_setup(path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    !path.starts_with("/api/") || TWO_FACTOR_SETUP_ROUTES.contains(&path)
+}
+
+/// Whether one of the user's groups requires two-factor authentication
+/// 用户所属的用户组是否要求两步验证
+pub async fn group_requires_two_factor(db: &SqlitePool, user_id: &str) -> bool {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(
+             SELECT 1 FROM user_group_members m
+             JOIN user_groups g ON CAST(g.id AS TEXT) = m.group_id
+             WHERE m.user_id = ? AND g.require_2fa = 1
+         )"
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .unwrap_or(false)
+}
+
+/// Whether the user must set up two-factor authentication before using the site
+/// 用户是否必须先设置两步验证
+pub async fn needs_two_factor_setup(db: &SqlitePool, user_id: &str, two_factor_enabled: bool) -> bool {
+    !two_factor_enabled && group_requires_two_factor(db, user_id).await
+}
+
+/// One account to import / 待导入的账号
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ImportUser {
+    pub username: String,
+    /// Generated when missing / 为空时自动生成
+    pub password: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    /// Group names or ids / 用户组名称或 ID
+    #[serde(deserialize_with = "de_groups")]
+    pub groups: Vec<String>,
+    pub root_path: Option<String>,
+    pub expires_at: Option<String>,
+    /// Defaults to true for imported accounts / 导入的账号默认为 true
+    pub must_change_password: Option<bool>,
+}
+
+fn split_groups(value: &str) -> Vec<String> {
+    value
+        .split([';', '|'])
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn de_groups<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => split_groups(&s),
+        Value::Number(n) => vec![n.to_string()],
+        Value::Array(items) => items
+            .into_iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "y" | "是" => Some(true),
+        "0" | "false" | "no" | "n" | "否" => Some(false),
+        _ => None,
+    }
+}
+
+/// Split CSV text into records, honouring quoted fields / 将 CSV 文本拆分为记录（支持引号字段）
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    // 跳过空行
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+/// Parse CSV with a header row / 解析带表头的 CSV
+pub fn parse_csv(text: &str) -> Result<Vec<ImportUser>, String> {
+    let mut records = csv_records(text).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or("CSV为空")?
+        .iter()
+        .map(|h| h.trim().to_ascii_lowercase())
+        .collect();
+    if !header.iter().any(|h| h == "username") {
+        return Err("CSV表头缺少username列".to_string());
+    }
+
+    let mut users = Vec::new();
+    for record in records {
+        let mut user = ImportUser::default();
+        for (name, value) in header.iter().zip(record) {
+            let value = value.trim().to_string();
+            let optional = Some(value.clone()).filter(|v| !v.is_empty());
+            match name.as_str() {
+                "username" => user.username = value,
+                "password" => user.password = optional,
+                "email" => user.email = optional,
+                "phone" => user.phone = optional,
+                "groups" => user.groups = split_groups(&value),
+                "root_path" => user.root_path = optional,
+                "expires_at" => user.expires_at = optional,
+                "must_change_password" => user.must_change_password = parse_bool(&value),
+                _ => {}
+            }
+        }
+        users.push(user);
+    }
+    Ok(users)
+}
+
+/// Parse a JSON array or `{"users": [...]}` / 解析 JSON 数组或 `{"users": [...]}`
+pub fn parse_json(text: &str) -> Result<Vec<ImportUser>, String> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Import {
+        List(Vec<ImportUser>),
+        Wrapped { users: Vec<ImportUser> },
+    }
+    match serde_json::from_str(text).map_err(|e| format!("JSON解析失败: {}", e))? {
+        Import::List(users) | Import::Wrapped { users } => Ok(users),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        assert_eq!(normalize_expiry("").unwrap(), None);
+        assert_eq!(normalize_expiry("2030-01-31").unwrap().as_deref(), Some("2030-01-31T23:59:59+00:00"));
+        assert_eq!(
+            normalize_expiry("2030-01-31T08:00:00+08:00").unwrap().as_deref(),
+            Some("2030-01-31T00:00:00+00:00")
+        );
+        assert!(normalize_expiry("next week").is_err());
+
+        let now = DateTime::parse_from_rfc3339("2030-02-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert!(is_expired(Some("2030-01-31T23:59:59+00:00"), now));
+        assert!(!is_expired(Some("2030-02-01T00:00:01+00:00"), now));
+        assert!(!is_expired(None, now));
+    }
+
+    #[test]
+    fn test_parse_import() {
+        let csv = "\u{feff}username,Password,groups,must_change_password\r\n\
+                   alice,,editors;2,\r\n\
+                   \"bob, jr\",\"p\"\"w\",,no\r\n\
+                   \r\n";
+        let users = parse_csv(csv).unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].username, "alice");
+        assert_eq!(users[0].password, None);
+        assert_eq!(users[0].groups, vec!["editors", "2"]);
+        assert_eq!(users[0].must_change_password, None);
+        assert_eq!(users[1].username, "bob, jr");
+        assert_eq!(users[1].password.as_deref(), Some("p\"w"));
+        assert_eq!(users[1].must_change_password, Some(false));
+        assert!(parse_csv("email\na@b.c").is_err());
+
+        let json = r#"{"users": [{"username": "carol", "groups": ["editors", 3], "expires_at": "2030-01-01"}]}"#;
+        let users = parse_json(json).unwrap();
+        assert_eq!(users[0].groups, vec!["editors", "3"]);
+        assert_eq!(users[0].expires_at.as_deref(), Some("2030-01-01"));
+        assert_eq!(parse_json(r#"[{"username": "dave", "groups": "a|b"}]"#).unwrap()[0].groups, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_two_factor_setup_routes() {
+        assert!(allowed_before_two_factor_setup("/api/auth/2fa/setup"));
+        assert!(allowed_before_two_factor_setup("/api/auth/logout/"));
+        assert!(allowed_before_two_factor_setup("/assets/index.js"));
+        assert!(!allowed_before_two_factor_setup("/api/fs/list"));
+        assert!(!allowed_before_two_factor_setup("/api/auth/2fa/disable"));
+    }
+}
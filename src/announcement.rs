@@ -0,0 +1,223 @@
+//! Site announcements / 站点公告
+//!
+//! Admin-managed notices (maintenance windows, incidents, ...) shown as banners by the frontend.
+//! Each announcement has a severity and an optional start/end time; only enabled announcements
+//! inside their time window are returned by the public settings.
+//! 管理员维护的公告（维护窗口、故障通知等），由前端以横幅展示。
+//! 每条公告有级别和可选的起止时间，公开设置只返回已启用且处于时间窗口内的公告。
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Max title length in characters / 标题最大长度（字符）
+pub const MAX_TITLE_LEN: usize = 200;
+
+/// Announcement severity / 公告级别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "info" => Some(Severity::Info),
+            "warning" => Some(Severity::Warning),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// Announcement / 公告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Announcement {
+    pub id: String,
+    pub title: String,
+    /// Banner body (markdown) / 横幅内容（Markdown）
+    pub content: String,
+    pub severity: Severity,
+    /// Shown from this time, `None` = immediately / 开始展示时间，为空表示立即
+    pub starts_at: Option<DateTime<Utc>>,
+    /// Hidden after this time, `None` = until disabled / 结束展示时间，为空表示一直展示
+    pub ends_at: Option<DateTime<Utc>>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Announcement {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() && self.content.trim().is_empty() {
+            return Err("title or content is required".to_string());
+        }
+        if self.title.chars().count() > MAX_TITLE_LEN {
+            return Err(format!("title must be at most {} characters", MAX_TITLE_LEN));
+        }
+        if let (Some(start), Some(end)) = (self.starts_at, self.ends_at) {
+            if end <= start {
+                return Err("ends_at must be after starts_at".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the announcement is shown at `now` / 在 `now` 时是否展示
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.enabled
+            && self.starts_at.is_none_or(|start| start <= now)
+            && self.ends_at.is_none_or(|end| now < end)
+    }
+}
+
+/// `announcements` row: id, title, content, severity, starts_at, ends_at, enabled, created_at
+type AnnouncementRow = (String, String, String, String, Option<String>, Option<String>, bool, String);
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Announcement cache / 公告缓存
+pub struct AnnouncementManager {
+    announcements: RwLock<Vec<Announcement>>,
+}
+
+impl AnnouncementManager {
+    pub fn new() -> Self {
+        Self {
+            announcements: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Load announcements from database / 从数据库加载公告
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<AnnouncementRow> = sqlx::query_as(
+            "SELECT id, title, content, severity, starts_at, ends_at, enabled, created_at FROM announcements"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let announcements = rows.into_iter()
+            .map(|(id, title, content, severity, starts_at, ends_at, enabled, created_at)| Announcement {
+                id,
+                title,
+                content,
+                severity: Severity::parse(&severity).unwrap_or_default(),
+                starts_at: starts_at.as_deref().and_then(parse_time),
+                ends_at: ends_at.as_deref().and_then(parse_time),
+                enabled,
+                created_at: parse_time(&created_at).unwrap_or_else(Utc::now),
+            })
+            .collect();
+        *self.announcements.write() = announcements;
+
+        Ok(())
+    }
+
+    /// List all announcements, newest first / 列出全部公告（新的在前）
+    pub fn list(&self) -> Vec<Announcement> {
+        let mut list = self.announcements.read().clone();
+        list.sort_by_key(|a| std::cmp::Reverse(a.created_at));
+        list
+    }
+
+    pub fn get(&self, id: &str) -> Option<Announcement> {
+        self.announcements.read().iter().find(|a| a.id == id).cloned()
+    }
+
+    /// Announcements shown at `now`, most severe first / `now` 时展示的公告（级别高的在前）
+    pub fn active(&self, now: DateTime<Utc>) -> Vec<Announcement> {
+        let mut list: Vec<Announcement> = self.announcements.read().iter()
+            .filter(|a| a.is_active(now))
+            .cloned()
+            .collect();
+        list.sort_by(|a, b| b.severity.cmp(&a.severity).then(b.created_at.cmp(&a.created_at)));
+        list
+    }
+
+    /// Insert or replace an announcement in the cache / 新增或替换缓存中的公告
+    pub fn upsert(&self, announcement: Announcement) {
+        let mut announcements = self.announcements.write();
+        match announcements.iter_mut().find(|a| a.id == announcement.id) {
+            Some(existing) => *existing = announcement,
+            None => announcements.push(announcement),
+        }
+    }
+
+    /// Remove an announcement from the cache / 从缓存移除公告
+    pub fn remove(&self, id: &str) -> bool {
+        let mut announcements = self.announcements.write();
+        let before = announcements.len();
+        announcements.retain(|a| a.id != id);
+        announcements.len() != before
+    }
+}
+
+impl Default for AnnouncementManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn announcement(id: &str, severity: Severity) -> Announcement {
+        Announcement {
+            id: id.to_string(),
+            title: "Maintenance".to_string(),
+            content: String::new(),
+            severity,
+            starts_at: None,
+            ends_at: None,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_active_window() {
+        let now = Utc::now();
+        let manager = AnnouncementManager::new();
+        manager.upsert(announcement("info", Severity::Info));
+        manager.upsert(Announcement { starts_at: Some(now + Duration::hours(1)), ..announcement("future", Severity::Critical) });
+        manager.upsert(Announcement { ends_at: Some(now), ..announcement("expired", Severity::Critical) });
+        manager.upsert(Announcement { enabled: false, ..announcement("disabled", Severity::Critical) });
+        manager.upsert(Announcement {
+            starts_at: Some(now - Duration::hours(1)),
+            ends_at: Some(now + Duration::hours(1)),
+            ..announcement("window", Severity::Warning)
+        });
+
+        let ids: Vec<String> = manager.active(now).into_iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec!["window", "info"]);
+        assert_eq!(manager.active(now + Duration::hours(2)).len(), 2);
+    }
+
+    #[test]
+    fn test_validate() {
+        let now = Utc::now();
+        assert!(announcement("a", Severity::Info).validate().is_ok());
+        let empty = Announcement { title: " ".to_string(), ..announcement("a", Severity::Info) };
+        assert!(empty.validate().is_err());
+        let reversed = Announcement { starts_at: Some(now), ends_at: Some(now), ..announcement("a", Severity::Info) };
+        assert!(reversed.validate().is_err());
+        assert_eq!(serde_json::to_string(&Severity::Critical).unwrap(), "\"critical\"");
+    }
+}
@@ -0,0 +1,140 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use yaolist_backend::announcement::{Announcement, Severity};
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+#[derive(Debug, Deserialize)]
+pub struct SaveAnnouncementRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub severity: Severity,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnnouncementIdRequest {
+    pub id: String,
+}
+
+/// GET /api/admin/announcements - 获取公告列表
+pub async fn list_announcements(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let now = Utc::now();
+    let announcements: Vec<Value> = state.announcements.list().into_iter().map(|a| {
+        let active = a.is_active(now);
+        json!({
+            "id": a.id,
+            "title": a.title,
+            "content": a.content,
+            "severity": a.severity,
+            "starts_at": a.starts_at,
+            "ends_at": a.ends_at,
+            "enabled": a.enabled,
+            "created_at": a.created_at,
+            "active": active
+        })
+    }).collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": announcements
+    })))
+}
+
+/// POST /api/admin/announcements - 新建或更新公告
+pub async fn save_announcement(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveAnnouncementRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let existing = req.id.as_deref().filter(|id| !id.is_empty()).map(|id| state.announcements.get(id));
+    if let Some(None) = existing {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "公告不存在"}))));
+    }
+    let existing = existing.flatten();
+
+    let announcement = Announcement {
+        id: existing.as_ref().map(|a| a.id.clone()).unwrap_or_else(|| Uuid::new_v4().to_string()),
+        title: req.title.trim().to_string(),
+        content: req.content,
+        severity: req.severity,
+        starts_at: req.starts_at,
+        ends_at: req.ends_at,
+        enabled: req.enabled.or(existing.as_ref().map(|a| a.enabled)).unwrap_or(true),
+        created_at: existing.as_ref().map(|a| a.created_at).unwrap_or_else(Utc::now),
+    };
+    announcement.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("公告无效: {}", e)}))))?;
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO announcements (id, title, content, severity, starts_at, ends_at, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&announcement.id)
+    .bind(&announcement.title)
+    .bind(&announcement.content)
+    .bind(announcement.severity.as_str())
+    .bind(announcement.starts_at.map(|t| t.to_rfc3339()))
+    .bind(announcement.ends_at.map(|t| t.to_rfc3339()))
+    .bind(announcement.enabled)
+    .bind(announcement.created_at.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    state.announcements.upsert(announcement.clone());
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": announcement
+    })))
+}
+
+/// POST /api/admin/announcements/delete - 删除公告
+pub async fn delete_announcement(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<AnnouncementIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    sqlx::query("DELETE FROM announcements WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    if !state.announcements.remove(&req.id) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "公告不存在"}))));
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
@@ -3,25 +3,30 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use encoding_rs::GBK;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::sync::Arc;
-use std::ops::Range;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tower_cookies::Cookies;
 use tokio::io::AsyncReadExt;
 use tracing::debug;
-use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
+use yaolist_backend::archive::{self, ArchiveKind, Member};
+use yaolist_backend::storage::DriverBox;
+use yaolist_backend::path_resolver::{calculate_internal_path, get_first_mount};
+use yaolist_backend::utils::fix_and_clean_path;
+use yaolist_backend::workspace;
 
 use crate::state::AppState;
+use crate::api::file_resolver::get_user_mounts;
+use crate::api::files::{authorize_read, get_hide_rules, get_nearest_meta, is_preview_only};
 
 // 简单的缓存结构
 struct CacheEntry {
     entries: Vec<ArchiveEntry>,
     format: String,
+    encrypted: bool,
     created: Instant,
 }
 
@@ -29,13 +34,13 @@ lazy_static::lazy_static! {
     static ref ARCHIVE_CACHE: RwLock<HashMap<String, CacheEntry>> = RwLock::new(HashMap::new());
 }
 
-const CACHE_TTL: Duration = Duration::from_secs(300); // 5分钟缓存
 
 #[derive(Debug, Deserialize)]
 pub struct ArchiveListRequest {
     pub path: String,
     #[serde(default)]
     pub inner_path: String,
+    pub password: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -43,60 +48,67 @@ pub struct ArchiveEntry {
     pub name: String,
     pub path: String,
     pub is_dir: bool,
+    /// 解压后的大小（目录为 0）
+    pub size: u64,
+    /// 需要密码才能读取
+    pub encrypted: bool,
 }
 
-struct MountInfo {
-    id: String,
-    mount_path: String,
+/// 读取文件的一段字节（不支持Range的驱动可能返回更多内容，只读取需要的部分）
+async fn read_range(driver: &DriverBox, path: &str, start: u64, end: u64) -> anyhow::Result<Vec<u8>> {
+    let len = end.saturating_sub(start);
+    let mut buf = Vec::with_capacity(len as usize);
+    let reader = driver.open_reader(path, Some(start..end)).await?;
+    reader.take(len).read_to_end(&mut buf).await?;
+    Ok(buf)
 }
 
-/// 支持的压缩格式
-fn get_archive_format(filename: &str) -> Option<&'static str> {
-    let ext = filename.split('.').last()?.to_lowercase();
-    match ext.as_str() {
-        "zip" | "jar" | "war" | "apk" | "ipa" | "epub" => Some("zip"),
-        _ => None,
-    }
-}
-
-/// 根据路径找到最匹配的挂载点
-fn get_storage_by_path<'a>(path: &str, mounts: &'a [MountInfo]) -> Option<&'a MountInfo> {
-    let mut best_match: Option<&MountInfo> = None;
-    let mut best_len = 0;
-    
-    for mount in mounts {
-        let mount_path = fix_and_clean_path(&mount.mount_path);
-        if is_sub_path(&mount_path, path) {
-            if mount_path.len() > best_len {
-                best_match = Some(mount);
-                best_len = mount_path.len();
-            }
-        }
-    }
-    best_match
-}
-
-/// POST /api/fs/archive/list - 列出压缩文件内容（只读取中央目录，不读取整个文件）
+/// POST /api/fs/archive/list - 列出压缩文件内容（ZIP 只读取中央目录，TAR 只读取文件头，不读取整个文件）
 pub async fn archive_list(
     State(state): State<Arc<AppState>>,
-    _cookies: Cookies,
+    cookies: Cookies,
     Json(req): Json<ArchiveListRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let path = fix_and_clean_path(&req.path);
+    let forbidden = |message: String| (
+        StatusCode::FORBIDDEN,
+        Json(json!({ "code": 403, "message": message }))
+    );
+    // 与读取成员相同的授权：游客策略、用户根路径、路径授权与目录密码
+    let password = req.password.clone().unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &fix_and_clean_path(&req.path), &password).await
+        .map_err(|e| (e.status(), Json(json!({ "code": e.status().as_u16(), "message": e.message() }))))?;
+    let workspace = workspace::current();
+    if !workspace.path_visible(&path) {
+        return Err(forbidden("没有读取该路径的权限".to_string()));
+    }
+    // 被所在目录的隐藏规则隐藏的文件视为不存在
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("/", path.as_str()));
+    let parent = fix_and_clean_path(parent);
+    let parent_meta = get_nearest_meta(&state, &parent).await;
+    if !user_ctx.permissions.show_hidden_files && get_hide_rules(parent_meta.as_ref(), &parent).hides(name) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({ "code": 404, "message": "文件不存在" }))));
+    }
+    // 成员目录来自压缩包的原始数据，仅预览的压缩包不公开
+    if is_preview_only(&state, &user_ctx, &path).await {
+        return Err(forbidden("该路径仅允许预览，不能查看压缩包内容".to_string()));
+    }
     let inner_path = req.inner_path.trim_matches('/');
-    let cache_key = format!("{}:{}", path, inner_path);
+    // 缓存按工作区区分，避免不同工作区共用列表
+    let cache_key = format!("{}:{}:{}", workspace.id(), path, inner_path);
     
     // 检查缓存
     {
         let cache = ARCHIVE_CACHE.read().await;
         if let Some(entry) = cache.get(&cache_key) {
-            if entry.created.elapsed() < CACHE_TTL {
+            let ttl = Duration::from_secs(yaolist_backend::config::config().cache.archive_ttl_secs);
+            if entry.created.elapsed() < ttl {
                 debug!("Archive cache hit: {}", cache_key);
                 return Ok(Json(json!({
                     "code": 200,
                     "message": "success",
                     "data": {
                         "format": entry.format,
+                        "encrypted": entry.encrypted,
                         "entries": entry.entries
                     }
                 })));
@@ -107,52 +119,31 @@ pub async fn archive_list(
     let filename = path.split('/').last().unwrap_or("");
     
     // 检查是否为支持的压缩格式
-    let format = match get_archive_format(filename) {
-        Some(f) => f,
+    let kind = match archive::kind_of(filename) {
+        Some(kind) => kind,
         None => {
             return Err((
                 StatusCode::BAD_REQUEST,
                 Json(json!({
                     "code": 400,
-                    "message": "不支持的压缩格式，目前仅支持 ZIP"
+                    "message": "不支持的压缩格式，目前仅支持 ZIP 与 TAR"
                 }))
             ));
         }
     };
     
-    // 从 drivers 表读取挂载点信息
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({ "code": 500, "message": e.to_string() }))
-    ))?;
-    
-    // 构建挂载点列表
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-        })
-    }).collect();
+    let mounts = get_user_mounts(&state, &user_ctx).await
+        .map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "code": 500, "message": e.to_string() }))
+        ))?;
     
     // 找到匹配的挂载点
-    let mount = get_storage_by_path(&path, &mounts).ok_or_else(|| (
+    let mount = get_first_mount(&path, &mounts).ok_or_else(|| (
         StatusCode::NOT_FOUND,
         Json(json!({ "code": 404, "message": "未找到匹配的挂载点" }))
     ))?;
-    
-    let mount_path_str = fix_and_clean_path(&mount.mount_path);
-    let actual_path = if path.len() > mount_path_str.len() {
-        fix_and_clean_path(&path[mount_path_str.len()..])
-    } else {
-        "/".to_string()
-    };
+    let actual_path = calculate_internal_path(&mount.mount_path, &path);
     
     // 获取驱动
     let driver = state.storage_manager.get_driver(&mount.id).await
@@ -178,132 +169,44 @@ pub async fn archive_list(
     
     let file_size = file_entry.size;
     
-    // 读取文件末尾 65KB（EOCD 最大 65KB）找中央目录
-    let eocd_search_size: u64 = 65536.min(file_size);
-    let eocd_start = file_size - eocd_search_size;
-    
-    let mut eocd_reader = driver.open_reader(&actual_path, Some(Range { start: eocd_start, end: file_size })).await
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "code": 500, "message": format!("读取文件失败: {}", e) }))
-        ))?;
-    
-    let mut eocd_data = Vec::with_capacity(eocd_search_size as usize);
-    eocd_reader.read_to_end(&mut eocd_data).await
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "code": 500, "message": format!("读取 EOCD 失败: {}", e) }))
-        ))?;
-    
-    // 查找 EOCD 签名 (0x06054b50)
-    let eocd_sig: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
-    let eocd_pos = eocd_data.windows(4).rposition(|w| w == eocd_sig)
-        .ok_or_else(|| (
-            StatusCode::BAD_REQUEST,
-            Json(json!({ "code": 400, "message": "无效的 ZIP 文件：找不到 EOCD" }))
-        ))?;
-    
-    // 解析 EOCD
-    if eocd_data.len() < eocd_pos + 22 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({ "code": 400, "message": "无效的 ZIP 文件：EOCD 不完整" }))
-        ));
-    }
-    
-    let mut cd_size = u32::from_le_bytes([
-        eocd_data[eocd_pos + 12],
-        eocd_data[eocd_pos + 13],
-        eocd_data[eocd_pos + 14],
-        eocd_data[eocd_pos + 15],
-    ]) as u64;
-    
-    let mut cd_offset = u32::from_le_bytes([
-        eocd_data[eocd_pos + 16],
-        eocd_data[eocd_pos + 17],
-        eocd_data[eocd_pos + 18],
-        eocd_data[eocd_pos + 19],
-    ]) as u64;
-    
-    debug!("ZIP EOCD: cd_size={}, cd_offset={}", cd_size, cd_offset);
-    
-    // 检查是否为 ZIP64（值为 0xFFFFFFFF 表示 ZIP64）
-    if cd_size == 0xFFFFFFFF || cd_offset == 0xFFFFFFFF {
-        // 查找 ZIP64 EOCD Locator (0x07064b50)
-        let eocd64_loc_sig: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
-        if let Some(loc_pos) = eocd_data.windows(4).rposition(|w| w == eocd64_loc_sig) {
-            if loc_pos + 20 <= eocd_data.len() {
-                // 获取 ZIP64 EOCD 的绝对偏移量
-                let eocd64_offset = u64::from_le_bytes([
-                    eocd_data[loc_pos + 8],
-                    eocd_data[loc_pos + 9],
-                    eocd_data[loc_pos + 10],
-                    eocd_data[loc_pos + 11],
-                    eocd_data[loc_pos + 12],
-                    eocd_data[loc_pos + 13],
-                    eocd_data[loc_pos + 14],
-                    eocd_data[loc_pos + 15],
-                ]);
-                
-                // 读取 ZIP64 EOCD（56 字节）
-                let mut eocd64_reader = driver.open_reader(&actual_path, Some(Range { start: eocd64_offset, end: eocd64_offset + 56 })).await
-                    .map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({ "code": 500, "message": format!("读取 ZIP64 EOCD 失败: {}", e) }))
-                    ))?;
-                
-                let mut eocd64_data = Vec::with_capacity(56);
-                eocd64_reader.read_to_end(&mut eocd64_data).await
-                    .map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({ "code": 500, "message": format!("读取 ZIP64 EOCD 失败: {}", e) }))
-                    ))?;
-                
-                // 验证 ZIP64 EOCD 签名 (0x06064b50)
-                if eocd64_data.len() >= 56 && &eocd64_data[0..4] == &[0x50, 0x4b, 0x06, 0x06] {
-                    cd_size = u64::from_le_bytes([
-                        eocd64_data[40], eocd64_data[41], eocd64_data[42], eocd64_data[43],
-                        eocd64_data[44], eocd64_data[45], eocd64_data[46], eocd64_data[47],
-                    ]);
-                    cd_offset = u64::from_le_bytes([
-                        eocd64_data[48], eocd64_data[49], eocd64_data[50], eocd64_data[51],
-                        eocd64_data[52], eocd64_data[53], eocd64_data[54], eocd64_data[55],
-                    ]);
-                    debug!("ZIP64 EOCD: cd_size={}, cd_offset={}", cd_size, cd_offset);
-                }
-            }
-        }
+    // ZIP 只读取中央目录，TAR 只读取文件头
+    let read = |start: u64, end: u64| read_range(&driver, &actual_path, start, end);
+    let members: Vec<Member> = match kind {
+        ArchiveKind::Zip => archive::read_zip_members(file_size, read).await
+            .map(|m| m.into_iter().map(Member::Zip).collect()),
+        ArchiveKind::Tar => archive::read_tar_members(file_size, read).await
+            .map(|m| m.into_iter().map(Member::Tar).collect()),
     }
-    
-    debug!("Final: cd_size={}, cd_offset={}, file_size={}", cd_size, cd_offset, file_size);
-    
-    // 读取中央目录
-    let mut cd_reader = driver.open_reader(&actual_path, Some(Range { start: cd_offset, end: cd_offset + cd_size })).await
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "code": 500, "message": format!("读取中央目录失败: {}", e) }))
-        ))?;
-    
-    let mut cd_data = Vec::with_capacity(cd_size as usize);
-    cd_reader.read_to_end(&mut cd_data).await
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "code": 500, "message": format!("读取中央目录失败: {}", e) }))
-        ))?;
-    
-    debug!("Central directory data size: {} bytes", cd_data.len());
-    
-    // 解析中央目录获取文件列表
-    let entries = parse_central_directory(&cd_data, &req.inner_path)?;
+    .map_err(|e| (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "code": 400, "message": format!("无效的 {} 文件：{}", kind.name(), e) }))
+    ))?;
+
+    // 只返回指定目录下的直接子项
+    let entries = list_children(&members, &req.inner_path);
+    // 任一成员加密时，预览与解压会先尝试已保存的密码，均不正确时再提示输入
+    let encrypted = members.iter().any(|m| m.encrypted());
     
     debug!("Parsed {} entries", entries.len());
     
     // 写入缓存
     {
+        let cache_config = yaolist_backend::config::config().cache;
+        let ttl = Duration::from_secs(cache_config.archive_ttl_secs);
         let mut cache = ARCHIVE_CACHE.write().await;
+        // 清理过期条目，超过上限时淘汰最旧的
+        cache.retain(|_, e| e.created.elapsed() < ttl);
+        while cache.len() >= cache_config.archive_max_entries.max(1) {
+            let oldest = cache.iter().min_by_key(|(_, e)| e.created).map(|(k, _)| k.clone());
+            match oldest {
+                Some(key) => { cache.remove(&key); }
+                None => break,
+            }
+        }
         cache.insert(cache_key, CacheEntry {
             entries: entries.clone(),
-            format: format.to_uppercase(),
+            format: kind.name().to_string(),
+            encrypted,
             created: Instant::now(),
         });
     }
@@ -312,100 +215,56 @@ pub async fn archive_list(
         "code": 200,
         "message": "success",
         "data": {
-            "format": format.to_uppercase(),
+            "format": kind.name(),
+            "encrypted": encrypted,
             "entries": entries
         }
     })))
 }
 
-/// 解析 ZIP 中央目录
-fn parse_central_directory(data: &[u8], inner_path: &str) -> Result<Vec<ArchiveEntry>, (StatusCode, Json<Value>)> {
+/// 列出压缩包中某个目录的直接子项（没有目录条目的中间目录按成员路径补全）
+fn list_children(members: &[Member], inner_path: &str) -> Vec<ArchiveEntry> {
     let inner_path = inner_path.trim_matches('/');
     let prefix = if inner_path.is_empty() { String::new() } else { format!("{}/", inner_path) };
     
     let mut entries = Vec::new();
     let mut seen_dirs = std::collections::HashSet::new();
-    let mut pos = 0;
     
-    let cd_sig: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
-    
-    while pos + 46 <= data.len() {
-        // 检查中央目录文件头签名
-        if &data[pos..pos + 4] != cd_sig {
-            break;
-        }
-        
-        let filename_len = u16::from_le_bytes([data[pos + 28], data[pos + 29]]) as usize;
-        let extra_len = u16::from_le_bytes([data[pos + 30], data[pos + 31]]) as usize;
-        let comment_len = u16::from_le_bytes([data[pos + 32], data[pos + 33]]) as usize;
-        
-        if pos + 46 + filename_len > data.len() {
-            break;
+    for member in members {
+        let full_path = member.path();
+        let Some(relative_path) = full_path.strip_prefix(prefix.as_str()) else {
+            continue;
+        };
+        if relative_path.is_empty() {
+            continue;
         }
         
-        let filename_bytes = &data[pos + 46..pos + 46 + filename_len];
-        let is_dir = filename_bytes.last() == Some(&b'/');
-        
-        // 解码文件名：先尝试 UTF-8，失败则用 GBK
-        let full_path = match std::str::from_utf8(filename_bytes) {
-            Ok(s) => s.trim_end_matches('/').to_string(),
-            Err(_) => {
-                let (decoded, _, _) = GBK.decode(filename_bytes);
-                decoded.trim_end_matches('/').to_string()
+        match relative_path.split_once('/') {
+            // 深层子项，添加父目录
+            Some((dir_name, _)) => {
+                if seen_dirs.insert(dir_name.to_string()) {
+                    entries.push(ArchiveEntry {
+                        name: dir_name.to_string(),
+                        path: format!("{}{}", prefix, dir_name),
+                        is_dir: true,
+                        size: 0,
+                        encrypted: false,
+                    });
+                }
             }
-        };
-        
-        // 过滤：只显示指定目录下的直接子项
-        let should_include = if prefix.is_empty() {
-            true
-        } else {
-            full_path.starts_with(&prefix) || full_path == inner_path
-        };
-        
-        if should_include && !full_path.is_empty() {
-            let relative_path = if prefix.is_empty() {
-                full_path.to_string()
-            } else if full_path.len() > prefix.len() {
-                full_path[prefix.len()..].to_string()
-            } else {
-                String::new()
-            };
-            
-            if !relative_path.is_empty() {
-                let parts: Vec<&str> = relative_path.split('/').collect();
-                
-                if parts.len() > 1 {
-                    // 深层子项，添加父目录
-                    let dir_name = parts[0];
-                    if !seen_dirs.contains(dir_name) {
-                        seen_dirs.insert(dir_name.to_string());
-                        entries.push(ArchiveEntry {
-                            name: dir_name.to_string(),
-                            path: if prefix.is_empty() { 
-                                dir_name.to_string() 
-                            } else { 
-                                format!("{}{}", prefix, dir_name) 
-                            },
-                            is_dir: true,
-                        });
-                    }
-                } else {
-                    let name = parts[0].to_string();
-                    if !is_dir || !seen_dirs.contains(&name) {
-                        if is_dir {
-                            seen_dirs.insert(name.clone());
-                        }
-                        entries.push(ArchiveEntry {
-                            name,
-                            path: full_path.to_string(),
-                            is_dir,
-                        });
-                    }
+            None => {
+                if member.is_dir() && !seen_dirs.insert(relative_path.to_string()) {
+                    continue;
                 }
+                entries.push(ArchiveEntry {
+                    name: relative_path.to_string(),
+                    path: full_path.to_string(),
+                    is_dir: member.is_dir(),
+                    size: member.size(),
+                    encrypted: member.encrypted(),
+                });
             }
         }
-        
-        pos += 46 + filename_len + extra_len + comment_len;
     }
     
     // 排序：目录在前，然后按名称排序
@@ -417,5 +276,5 @@ fn parse_central_directory(data: &[u8], inner_path: &str) -> Result<Vec<ArchiveE
         }
     });
     
-    Ok(entries)
+    entries
 }
@@ -0,0 +1,121 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use yaolist_backend::archive_password::ArchivePassword;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+#[derive(Debug, Deserialize)]
+pub struct SaveArchivePasswordRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    /// 路径或通配符，如 /backups 或 /backups/**/*.zip
+    pub pattern: String,
+    /// 更新时留空表示保留原密码
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchivePasswordIdRequest {
+    pub id: String,
+}
+
+/// GET /api/admin/archive-passwords - 获取压缩包密码列表（不返回密码本身）
+pub async fn list_archive_passwords(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": state.archive_passwords.list()
+    })))
+}
+
+/// POST /api/admin/archive-passwords - 新建或更新压缩包密码
+pub async fn save_archive_password(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveArchivePasswordRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let existing = req.id.as_deref().filter(|id| !id.is_empty()).map(|id| state.archive_passwords.get(id));
+    if let Some(None) = existing {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "密码不存在"}))));
+    }
+    let existing = existing.flatten();
+
+    let password = match (req.password.is_empty(), existing.as_ref()) {
+        (true, Some(existing)) => existing.password.clone(),
+        _ => req.password,
+    };
+    let stored = ArchivePassword {
+        id: existing.as_ref().map(|p| p.id.clone()).unwrap_or_else(|| Uuid::new_v4().to_string()),
+        pattern: req.pattern.trim().to_string(),
+        password,
+        note: req.note.trim().to_string(),
+        created_at: existing.as_ref().map(|p| p.created_at).unwrap_or_else(Utc::now),
+    };
+    stored.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("密码无效: {}", e)}))))?;
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO archive_passwords (id, pattern, password, note, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&stored.id)
+    .bind(&stored.pattern)
+    .bind(&stored.password)
+    .bind(&stored.note)
+    .bind(stored.created_at.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    state.archive_passwords.upsert(stored.clone())
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("密码无效: {}", e)}))))?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": stored
+    })))
+}
+
+/// POST /api/admin/archive-passwords/delete - 删除压缩包密码
+pub async fn delete_archive_password(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<ArchivePasswordIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    sqlx::query("DELETE FROM archive_passwords WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    if !state.archive_passwords.remove(&req.id) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "密码不存在"}))));
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
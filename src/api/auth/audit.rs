@@ -0,0 +1,51 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::audit;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    #[serde(default = "default_page")]
+    page: i64,
+    #[serde(default = "default_per_page")]
+    per_page: i64,
+    action: Option<String>,
+}
+
+fn default_page() -> i64 { 1 }
+fn default_per_page() -> i64 { 20 }
+
+/// GET /api/admin/audit-logs - 审计日志（按时间倒序，可按action过滤）
+pub async fn list_audit_logs(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let per_page = query.per_page.clamp(1, 100);
+    let offset = (query.page.max(1) - 1) * per_page;
+    let action = query.action.as_deref().filter(|a| !a.is_empty());
+    let (entries, total) = audit::list(&state.db, action, per_page, offset)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "entries": entries,
+            "total": total,
+            "page": query.page.max(1),
+            "per_page": per_page
+        }
+    })))
+}
@@ -0,0 +1,243 @@
+//! 管理员模拟登录：以其他用户身份排查权限问题
+//!
+//! 模拟使用独立的短期会话，管理员无需知道用户密码，原会话保留以便结束后恢复。
+//! 开始与结束都会写入审计日志；模拟会话中不能修改密码、邮箱、手机号或两步验证。
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::{Cookies, Cookie};
+use chrono::{Duration, Utc};
+use yaolist_backend::audit::{self, AuditEvent};
+use yaolist_backend::client_ip::ClientIp;
+use yaolist_backend::workspace;
+
+use crate::state::AppState;
+use crate::auth::{SESSION_COOKIE_NAME, create_session};
+
+/// 模拟会话默认有效期（分钟）
+const DEFAULT_MINUTES: i64 = 30;
+/// 模拟会话最长有效期（分钟）
+const MAX_MINUTES: i64 = 240;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImpersonateRequest {
+    /// 有效期（分钟），默认30，最长240
+    pub minutes: Option<i64>,
+    /// 模拟原因，写入审计日志
+    pub reason: Option<String>,
+}
+
+fn session_cookie(value: String) -> Cookie<'static> {
+    let mut cookie = Cookie::new(SESSION_COOKIE_NAME, value);
+    cookie.set_path(workspace::current().cookie_path());
+    cookie.set_http_only(true);
+    cookie
+}
+
+/// 当前会话为模拟会话时拒绝操作（账号安全设置只能由用户本人修改）
+pub async fn reject_impersonation(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
+    let Some(session_id) = cookies.get(SESSION_COOKIE_NAME).map(|c| c.value().to_string()) else {
+        return Ok(());
+    };
+    let impersonator: Option<Option<String>> = sqlx::query_scalar(
+        "SELECT impersonator_id FROM sessions WHERE id = ?"
+    )
+    .bind(&session_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    if impersonator.flatten().is_some() {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "模拟登录期间不能修改账号安全设置"}))));
+    }
+    Ok(())
+}
+
+/// POST /api/users/:id/impersonate - 以该用户身份登录（需要管理员权限）
+pub async fn start_impersonation(
+    State(state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    cookies: Cookies,
+    Path(target_id): Path<String>,
+    req: Option<Json<ImpersonateRequest>>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    let session_id = cookies.get(SESSION_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
+
+    // 发起者必须是管理员本人的会话（不能在模拟会话中再次模拟）
+    let admin: Option<(String, String, bool, Option<String>)> = sqlx::query_as(
+        "SELECT u.id, u.username, u.is_admin, s.impersonator_id FROM users u
+         JOIN sessions s ON u.id = s.user_id
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+    )
+    .bind(&session_id)
+    .bind(workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    let (admin_id, admin_name, is_admin, impersonator) = admin
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话无效"}))))?;
+    if !is_admin || impersonator.is_some() {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
+    }
+    if admin_id == target_id {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "不能模拟自己"}))));
+    }
+
+    // 目标用户须属于当前工作区、已启用且不是管理员
+    let target: Option<(String, bool)> = sqlx::query_as(
+        "SELECT u.username, u.is_admin OR EXISTS(
+             SELECT 1 FROM user_group_members m
+             JOIN user_groups g ON CAST(g.id AS TEXT) = m.group_id
+             WHERE m.user_id = u.id AND g.is_admin = 1
+         )
+         FROM users u WHERE u.id = ? AND u.enabled = 1 AND u.workspace_id = ?"
+    )
+    .bind(&target_id)
+    .bind(workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    let (target_name, target_is_admin) = target
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "用户不存在或已禁用"}))))?;
+    if target_is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "不能模拟管理员"}))));
+    }
+
+    let minutes = req.minutes.unwrap_or(DEFAULT_MINUTES).clamp(1, MAX_MINUTES);
+    let mut session = create_session(&target_id);
+    session.expires_at = Utc::now() + Duration::minutes(minutes);
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query(
+        "INSERT INTO sessions (id, user_id, expires_at, created_at, workspace_id, impersonator_id, parent_session_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&session.id)
+    .bind(&session.user_id)
+    // 以 datetime() 格式保存，否则与 datetime('now') 按字符串比较时会一直有效到当天结束
+    .bind(yaolist_backend::utils::sql_datetime(session.expires_at))
+    .bind(&now)
+    .bind(workspace::current().id())
+    .bind(&admin_id)
+    .bind(&session_id)
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    let ip = client_ip.to_string();
+    let detail = json!({ "minutes": minutes, "reason": req.reason }).to_string();
+    if let Err(e) = audit::record(&state.db, AuditEvent {
+        actor_id: &admin_id,
+        actor_name: &admin_name,
+        action: audit::IMPERSONATION_START,
+        target_id: Some(&target_id),
+        target_name: Some(&target_name),
+        detail: Some(&detail),
+        ip: Some(&ip),
+    }).await {
+        // 无法留下审计记录时不允许模拟
+        tracing::error!("Failed to record impersonation audit entry: {}", e);
+        let _ = sqlx::query("DELETE FROM sessions WHERE id = ?").bind(&session.id).execute(&state.db).await;
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "写入审计日志失败"}))));
+    }
+    tracing::warn!("Admin {} started impersonating user {} for {} minutes", admin_name, target_name, minutes);
+
+    cookies.add(session_cookie(session.id));
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已切换为用户 {}", target_name),
+        "data": {
+            "user_id": target_id,
+            "username": target_name,
+            "expires_at": session.expires_at.to_rfc3339()
+        }
+    })))
+}
+
+/// 模拟会话：用户ID、用户名、管理员ID、管理员用户名、管理员原会话
+type ImpersonationRow = (String, Option<String>, Option<String>, Option<String>, Option<String>);
+
+/// POST /api/auth/impersonate/stop - 结束模拟，恢复管理员原会话
+pub async fn stop_impersonation(
+    State(state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let session_id = cookies.get(SESSION_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
+
+    let session: Option<ImpersonationRow> = sqlx::query_as(
+        "SELECT s.user_id, u.username, s.impersonator_id, a.username, s.parent_session_id FROM sessions s
+         LEFT JOIN users u ON u.id = s.user_id
+         LEFT JOIN users a ON a.id = s.impersonator_id
+         WHERE s.id = ?"
+    )
+    .bind(&session_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    let Some((target_id, target_name, Some(admin_id), admin_name, parent_session)) = session else {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "当前未处于模拟登录"}))));
+    };
+
+    sqlx::query("DELETE FROM sessions WHERE id = ?")
+        .bind(&session_id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    let ip = client_ip.to_string();
+    let admin_name = admin_name.unwrap_or_default();
+    if let Err(e) = audit::record(&state.db, AuditEvent {
+        actor_id: &admin_id,
+        actor_name: &admin_name,
+        action: audit::IMPERSONATION_STOP,
+        target_id: Some(&target_id),
+        target_name: target_name.as_deref(),
+        detail: None,
+        ip: Some(&ip),
+    }).await {
+        tracing::error!("Failed to record impersonation audit entry: {}", e);
+    }
+
+    // 原会话仍有效时恢复管理员登录，否则退出登录
+    let parent_valid: Option<String> = match parent_session {
+        Some(parent) => sqlx::query_scalar(
+            "SELECT id FROM sessions WHERE id = ? AND user_id = ? AND expires_at > datetime('now')"
+        )
+        .bind(&parent)
+        .bind(&admin_id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten(),
+        None => None,
+    };
+    let restored = parent_valid.is_some();
+    match parent_valid {
+        Some(parent) => cookies.add(session_cookie(parent)),
+        None => cookies.remove(session_cookie(String::new())),
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "已结束模拟登录",
+        "data": {
+            "restored": restored
+        }
+    })))
+}
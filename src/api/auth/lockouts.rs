@@ -0,0 +1,49 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::lockout::LockoutScope;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+/// GET /api/admin/login-lockouts - 列出登录失败计数与锁定
+pub async fn list_login_lockouts(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "policy": state.login_lockout.get_policy(),
+            "entries": state.login_lockout.list()
+        }
+    })))
+}
+
+/// POST /api/admin/login-lockouts/:scope/:key/unlock - 解除锁定（scope为ip或account）
+pub async fn unlock_login_lockout(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path((scope, key)): Path<(String, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let scope = LockoutScope::parse(&scope)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"error": "scope必须是ip或account"}))))?;
+    if !state.login_lockout.unlock(&state.db, scope, &key).await {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "记录不存在"}))));
+    }
+    tracing::info!("Login lockout cleared for {} {}", scope.as_str(), key);
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "已解除锁定"
+    })))
+}
@@ -1,20 +1,27 @@
 use axum::{
-    extract::{ConnectInfo, State},
+    extract::{Query, State},
     http::StatusCode,
     Json,
 };
 use serde_json::{json, Value};
-use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_cookies::{Cookies, Cookie};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use captcha::Captcha;
 use captcha::filters::Noise;
 use base64::prelude::*;
 use totp_rs::{Algorithm, TOTP, Secret};
+use std::net::IpAddr;
+use yaolist_backend::client_ip::ClientIp;
+use yaolist_backend::captcha::CaptchaProvider;
+use yaolist_backend::lockout::{account_key, LockoutScope};
+use yaolist_backend::accounts;
+use yaolist_backend::group_defaults;
 
 use crate::state::AppState;
 use crate::auth::{SESSION_COOKIE_NAME, create_session};
+use crate::api::files::{get_guest_permissions, is_guest_disabled};
+use crate::api::security_alert;
 use crate::models::{User, UserInfo, UserPermissions};
 use super::types::*;
 
@@ -22,18 +29,19 @@ use super::types::*;
 pub async fn generate_captcha(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<CaptchaResponse>, StatusCode> {
-    let mut captcha = Captcha::new();
-    captcha
-        .add_chars(4)
-        .apply_filter(Noise::new(0.2))
-        .view(120, 40);
-    
-    let code = captcha.chars_as_string();
-    let png_data = captcha.as_png().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Captcha 不是 Send，需在 await 之前释放
+    let (code, png_data) = {
+        let mut captcha = Captcha::new();
+        captcha
+            .add_chars(4)
+            .apply_filter(Noise::new(0.2))
+            .view(120, 40);
+        (captcha.chars_as_string(), captcha.as_png().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?)
+    };
     let base64_image = format!("data:image/png;base64,{}", BASE64_STANDARD.encode(&png_data));
     
     let captcha_id = uuid::Uuid::new_v4().to_string();
-    state.login_security.store_captcha(captcha_id.clone(), code);
+    state.login_security.store_captcha(captcha_id.clone(), code).await;
     
     Ok(Json(CaptchaResponse {
         captcha_id,
@@ -41,78 +49,159 @@ pub async fn generate_captcha(
     }))
 }
 
-/// 检查是否需要验证码（基于IP）
+/// 检查是否需要验证码（基于IP，可附带账号），并返回验证码类型
 pub async fn check_need_captcha(
     State(state): State<Arc<AppState>>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ClientIp(client_ip): ClientIp,
+    Query(query): Query<CheckCaptchaQuery>,
 ) -> Json<Value> {
-    let ip = addr.ip().to_string();
-    let needs = state.login_security.needs_captcha_by_ip(&ip);
-    Json(json!({ "need_captcha": needs }))
+    let ip = client_ip.to_string();
+    if state.cluster.is_shared() {
+        state.login_lockout.refresh(&state.db, LockoutScope::Ip, &ip).await;
+        if let Some(username) = query.username.as_deref() {
+            state.login_lockout.refresh(&state.db, LockoutScope::Account, &account_key(username)).await;
+        }
+    }
+    let needs = state.login_lockout.needs_captcha(&ip, query.username.as_deref());
+    let captcha = state.captcha.get();
+    Json(json!({
+        "need_captcha": needs,
+        "provider": captcha.provider,
+        "site_key": captcha.site_key
+    }))
+}
+
+/// 校验验证码：内置图形验证码使用 captcha_id/captcha_code，hCaptcha/Turnstile 使用 captcha_token
+pub(crate) async fn verify_captcha_input(
+    state: &AppState,
+    captcha_id: Option<&str>,
+    captcha_code: Option<&str>,
+    captcha_token: Option<&str>,
+    client_ip: IpAddr,
+) -> Result<(), &'static str> {
+    if state.captcha.get().provider == CaptchaProvider::Image {
+        return match (captcha_id, captcha_code) {
+            (Some(id), Some(code)) if !id.is_empty() && !code.is_empty() => {
+                if state.login_security.verify_captcha(id, code).await { Ok(()) } else { Err("验证码错误") }
+            }
+            _ => Err("请输入验证码"),
+        };
+    }
+    let token = captcha_token.filter(|t| !t.trim().is_empty()).ok_or("请输入验证码")?;
+    match state.captcha.verify_token(token, Some(client_ip)).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("验证码错误"),
+        Err(e) => {
+            tracing::warn!("Captcha verification failed: {}", e);
+            Err("验证码服务暂时不可用，请稍后再试")
+        }
+    }
+}
+
+/// 锁定中的响应
+fn locked_response(until: DateTime<Utc>, scope: LockoutScope) -> (StatusCode, Json<Value>) {
+    let secs = (until - Utc::now()).num_seconds().max(1);
+    let minutes = (secs + 59) / 60;
+    let error = match scope {
+        LockoutScope::Ip => format!("登录失败次数过多，请{}分钟后再试", minutes),
+        LockoutScope::Account => format!("账号已被临时锁定，请{}分钟后再试", minutes),
+    };
+    (StatusCode::TOO_MANY_REQUESTS, Json(json!({
+        "error": error,
+        "blocked": true,
+        "retry_after": secs
+    })))
+}
+
+/// 记录登录失败（IP与账号计数），推送到 Webhook 并检查告警阈值，返回是否因此被锁定
+async fn record_login_failure(state: &Arc<AppState>, ip: &str, account: &str) -> bool {
+    let locked = state.login_lockout.record_failure(&state.db, ip, account).await.is_some()
+        || state.login_lockout.locked_until(LockoutScope::Ip, ip).is_some();
+    state.webhooks.emit(yaolist_backend::webhook::WebhookEvent::LoginFailure, json!({
+        "username": account,
+        "ip": ip,
+        "blocked": locked
+    }));
+    security_alert::check_failed_login(state, ip, account, locked);
+    locked
 }
 
 pub async fn login(
     State(state): State<Arc<AppState>>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ClientIp(client_ip): ClientIp,
     cookies: Cookies,
     Json(req): Json<LoginRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let ip = addr.ip().to_string();
-    
-    // 检查IP是否被封禁
-    if state.login_security.is_ip_blocked(&ip) {
-        return Err((StatusCode::TOO_MANY_REQUESTS, Json(json!({
-            "error": "登录失败次数过多，请30分钟后再试",
-            "blocked": true
-        }))));
+    let ip = client_ip.to_string();
+    // 多实例时计数可能由其他实例写入
+    if state.cluster.is_shared() {
+        state.login_lockout.refresh(&state.db, LockoutScope::Ip, &ip).await;
     }
     
-    // 基于IP检查是否需要验证码（不能被绕过）
-    if state.login_security.needs_captcha_by_ip(&ip) {
-        match (&req.captcha_id, &req.captcha_code) {
-            (Some(id), Some(code)) if !id.is_empty() && !code.is_empty() => {
-                if !state.login_security.verify_captcha(id, code) {
-                    return Err((StatusCode::BAD_REQUEST, Json(json!({
-                        "error": "验证码错误",
-                        "need_captcha": true
-                    }))));
-                }
-            }
-            _ => {
-                return Err((StatusCode::BAD_REQUEST, Json(json!({
-                    "error": "请输入验证码",
-                    "need_captcha": true
-                }))));
-            }
-        }
+    // 检查IP是否被锁定
+    if let Some(until) = state.login_lockout.locked_until(LockoutScope::Ip, &ip) {
+        return Err(locked_response(until, LockoutScope::Ip));
     }
     
     // 支持用户名/邮箱/手机号登录
     let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE (username = ? OR email = ? OR phone = ?) AND enabled = 1"
+        "SELECT * FROM users WHERE (username = ? OR email = ? OR phone = ?) AND enabled = 1 AND workspace_id = ?"
     )
     .bind(&req.username)
     .bind(&req.username)
     .bind(&req.username)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?
-    .ok_or_else(|| {
-        state.login_security.record_failure(&ip, &req.username);
-        (StatusCode::UNAUTHORIZED, Json(json!({
-            "error": "账号或密码错误",
-            "need_captcha": state.login_security.needs_captcha_by_ip(&ip)
-        })))
-    })?;
-
-    let valid = bcrypt::verify(&req.password, &user.password_hash)
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-
-    if !valid {
-        state.login_security.record_failure(&ip, &req.username);
-        return Err((StatusCode::UNAUTHORIZED, Json(json!({
-            "error": "账号或密码错误",
-            "need_captcha": state.login_security.needs_captcha_by_ip(&ip)
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    
+    // 账号计数以用户名为准，避免换用邮箱/手机号绕过
+    let account = user.as_ref().map(|u| u.username.clone()).unwrap_or_else(|| req.username.clone());
+    if state.cluster.is_shared() {
+        state.login_lockout.refresh(&state.db, LockoutScope::Account, &account_key(&account)).await;
+    }
+    if let Some(until) = state.login_lockout.locked_until(LockoutScope::Account, &account_key(&account)) {
+        return Err(locked_response(until, LockoutScope::Account));
+    }
+    
+    // 基于IP与账号检查是否需要验证码（不能被绕过）
+    if state.login_lockout.needs_captcha(&ip, Some(&account)) {
+        verify_captcha_input(
+            &state,
+            req.captcha_id.as_deref(),
+            req.captcha_code.as_deref(),
+            req.captcha_token.as_deref(),
+            client_ip,
+        )
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({
+            "error": e,
+            "need_captcha": true
+        }))))?;
+    }
+    
+    let valid = match &user {
+        Some(user) => bcrypt::verify(&req.password, &user.password_hash)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?,
+        None => false,
+    };
+    let user = match user {
+        Some(user) if valid => user,
+        _ => {
+            let blocked = record_login_failure(&state, &ip, &account).await;
+            return Err((StatusCode::UNAUTHORIZED, Json(json!({
+                "error": "账号或密码错误",
+                "need_captcha": state.login_lockout.needs_captcha(&ip, Some(&account)),
+                "blocked": blocked
+            }))));
+        }
+    };
+    
+    // 过期账号不能登录
+    if accounts::is_expired(user.expires_at.as_deref(), Utc::now()) {
+        return Err((StatusCode::FORBIDDEN, Json(json!({
+            "error": "账号已过期",
+            "expired": true
         }))));
     }
     
@@ -136,7 +225,10 @@ pub async fn login(
                 ).map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "创建TOTP失败"}))))?;
 
                 if !totp.check_current(code).map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "验证失败"}))))? {
+                    // 两步验证码错误同样计入失败次数，防止暴力猜测
+                    let blocked = record_login_failure(&state, &ip, &account).await;
                     return Err((StatusCode::BAD_REQUEST, Json(json!({
+                        "blocked": blocked,
                         "error": "两步验证码错误",
                         "need_2fa": true
                     }))));
@@ -152,19 +244,46 @@ pub async fn login(
         }
     }
     
+    // 管理员要求修改密码（如批量导入的账号首次登录），须随登录一起提交新密码
+    if user.must_change_password {
+        let new_password = req.new_password.as_deref().unwrap_or_default();
+        if new_password.is_empty() {
+            return Err((StatusCode::FORBIDDEN, Json(json!({
+                "error": "首次登录需要修改密码",
+                "must_change_password": true
+            }))));
+        }
+        if let Err(e) = super::password::validate_new_password(new_password, &req.password) {
+            return Err((StatusCode::BAD_REQUEST, Json(json!({
+                "error": e,
+                "must_change_password": true
+            }))));
+        }
+        let password_hash = bcrypt::hash(new_password, bcrypt::DEFAULT_COST)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query("UPDATE users SET password_hash = ?, must_change_password = 0, updated_at = ? WHERE id = ?")
+            .bind(&password_hash)
+            .bind(Utc::now().to_rfc3339())
+            .bind(&user.id)
+            .execute(&state.db)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
     // 登录成功，清除失败记录
-    state.login_security.clear_failure(&ip, &req.username);
+    state.login_lockout.clear(&state.db, &ip, &account).await;
 
     let session = create_session(&user.id);
     let now = Utc::now().to_rfc3339();
     
     sqlx::query(
-        "INSERT INTO sessions (id, user_id, expires_at, created_at) VALUES (?, ?, ?, ?)"
+        "INSERT INTO sessions (id, user_id, expires_at, created_at, workspace_id) VALUES (?, ?, ?, ?, ?)"
     )
     .bind(&session.id)
     .bind(&session.user_id)
     .bind(session.expires_at.to_rfc3339())
     .bind(&now)
+    .bind(yaolist_backend::workspace::current().id())
     .execute(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -176,9 +295,13 @@ pub async fn login(
         .execute(&state.db)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    security_alert::check_login_country(&state, &user.id, &user.username, client_ip);
+
+    // 用户组要求两步验证而尚未启用时，登录后须先完成设置
+    let two_factor_setup_required = accounts::needs_two_factor_setup(&state.db, &user.id, user.two_factor_enabled).await;
 
     let mut cookie = Cookie::new(SESSION_COOKIE_NAME, session.id);
-    cookie.set_path("/");
+    cookie.set_path(yaolist_backend::workspace::current().cookie_path());
     cookie.set_http_only(true);
     cookies.add(cookie);
 
@@ -188,7 +311,8 @@ pub async fn login(
             username: user.username,
             email: user.email,
             is_admin: user.is_admin,
-        }
+        },
+        "two_factor_setup_required": two_factor_setup_required
     })))
 }
 
@@ -206,7 +330,7 @@ pub async fn logout(
     
     // 必须设置相同的 path 才能正确删除 cookie
     let mut removal_cookie = Cookie::new(SESSION_COOKIE_NAME, "");
-    removal_cookie.set_path("/");
+    removal_cookie.set_path(yaolist_backend::workspace::current().cookie_path());
     cookies.remove(removal_cookie);
     
     Ok(Json(json!({"message": "已退出登录"})))
@@ -224,9 +348,10 @@ pub async fn permissions(
         let user = sqlx::query_as::<_, User>(
             r#"SELECT u.* FROM users u 
                INNER JOIN sessions s ON u.id = s.user_id 
-               WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"#
+               WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"#
         )
         .bind(session_cookie.value())
+        .bind(yaolist_backend::workspace::current().id())
         .fetch_optional(&state.db)
         .await
         .ok()
@@ -234,7 +359,7 @@ pub async fn permissions(
         
         if let Some(user) = user {
             // 已登录用户，获取用户权限
-            let permissions = sqlx::query_as::<_, UserPermissions>(
+            let mut permissions = sqlx::query_as::<_, UserPermissions>(
                 r#"SELECT 
                     MAX(g.read_files) as read_files,
                     MAX(g.create_upload) as create_upload,
@@ -258,6 +383,11 @@ pub async fn permissions(
             .flatten()
             .unwrap_or_default();
             
+            // 继承自用户组并应用用户覆盖的设置
+            let settings = group_defaults::load_user(&state.db, &user.id).await.unwrap_or_default();
+            permissions.allow_share = settings.allow_share;
+            let two_factor_setup_required = accounts::needs_two_factor_setup(&state.db, &user.id, user.two_factor_enabled).await;
+            
             return Ok(Json(json!({
                 "is_guest": false,
                 "user": UserInfo {
@@ -266,62 +396,23 @@ pub async fn permissions(
                     email: user.email,
                     is_admin: user.is_admin,
                 },
-                "permissions": permissions
+                "permissions": permissions,
+                "settings": settings,
+                "two_factor_setup_required": two_factor_setup_required
             })));
         }
     }
     
     // 未登录或session无效，返回游客权限
-    let (guest_disabled, guest_permissions) = get_guest_permissions(&state).await;
+    let guest_disabled = is_guest_disabled(&state).await;
+    let guest_permissions = get_guest_permissions(&state).await;
     
     Ok(Json(json!({
         "is_guest": true,
         "guest_disabled": guest_disabled,
         "user": null,
-        "permissions": guest_permissions
+        "permissions": guest_permissions,
+        "guest_policy": state.guest.get()
     })))
 }
 
-/// 获取游客组权限，返回 (guest_disabled, permissions)
-async fn get_guest_permissions(state: &AppState) -> (bool, UserPermissions) {
-    // 首先检查游客用户是否启用
-    let guest_enabled: Option<(bool,)> = sqlx::query_as(
-        "SELECT enabled FROM users WHERE username = 'guest'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten();
-    
-    if let Some((enabled,)) = guest_enabled {
-        if !enabled {
-            return (true, UserPermissions::default());  // 游客被禁用
-        }
-    }
-    
-    // 查找"游客组"的权限
-    let permissions = sqlx::query_as::<_, UserPermissions>(
-        r#"SELECT 
-            read_files,
-            create_upload,
-            rename_files,
-            move_files,
-            copy_files,
-            delete_files,
-            allow_direct_link,
-            allow_share,
-            is_admin,
-            show_hidden_files,
-            extract_files
-        FROM user_groups
-        WHERE name = '游客组'"#
-    )
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten()
-    .unwrap_or_default();
-    
-    (false, permissions)
-}
-
@@ -4,9 +4,15 @@ pub mod register;
 pub mod password;
 pub mod profile;
 pub mod two_factor;
+pub mod lockouts;
+pub mod impersonate;
+pub mod audit;
 
 pub use login::*;
 pub use register::*;
 pub use password::*;
 pub use profile::*;
 pub use two_factor::*;
+pub use lockouts::*;
+pub use impersonate::*;
+pub use audit::*;
@@ -11,16 +11,25 @@ use chrono::Utc;
 use crate::state::AppState;
 use crate::auth::SESSION_COOKIE_NAME;
 use super::types::*;
+use super::impersonate::reject_impersonation;
+use yaolist_backend::client_ip::ClientIp;
 
 /// POST /api/auth/forgot-password - 发送密码重置验证码
 pub async fn forgot_password(
     State(state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
     Json(req): Json<ForgotPasswordRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 验证图形验证码
-    if !state.login_security.verify_captcha(&req.captcha_id, &req.captcha_code) {
-        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "验证码错误或已过期"}))));
-    }
+    // 验证验证码（图形验证码或 hCaptcha/Turnstile）
+    super::verify_captcha_input(
+        &state,
+        req.captcha_id.as_deref(),
+        req.captcha_code.as_deref(),
+        req.captcha_token.as_deref(),
+        client_ip,
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e}))))?;
 
     // 查找用户
     let user: Option<(String, String)> = if req.target_type == "email" {
@@ -55,7 +64,7 @@ pub async fn forgot_password(
 
     // 存储重置码（有效期10分钟）
     let reset_key = format!("{}:{}", req.target_type, req.target);
-    state.login_security.store_reset_code(reset_key, user_id.clone(), code.clone());
+    state.login_security.store_reset_code(reset_key, user_id.clone(), code.clone()).await;
 
     // 加载通知设置
     let settings = crate::api::notification::load_notification_settings(&state).await;
@@ -65,21 +74,19 @@ pub async fn forgot_password(
         if !settings.email_enabled {
             return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "邮箱通知未启用"}))));
         }
-        let subject = "YaoList 密码重置验证码";
-        let body = format!(r#"
-            <div style="font-family: Arial, sans-serif; max-width: 600px; margin: 0 auto; padding: 20px;">
-                <h2 style="color: #333;">密码重置</h2>
-                <p>您正在重置密码，验证码是：</p>
-                <div style="background: #f5f5f5; padding: 20px; border-radius: 8px; text-align: center; margin: 20px 0;">
-                    <span style="font-size: 32px; font-weight: bold; letter-spacing: 8px;">{}</span>
-                </div>
-                <p style="color: #999;">验证码有效期为10分钟，请勿泄露给他人。</p>
-            </div>
-        "#, code);
-        
-        if let Err(e) = crate::api::notification::send_smtp_email(&settings, &req.target, subject, &body).await {
-            tracing::error!("Failed to send email: {}", e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "发送验证码失败"}))));
+        let vars = json!({ "code": code, "minutes": 10, "reset": true });
+        match crate::api::notification::send_event_email(
+            &state, &settings, yaolist_backend::email_template::EmailEvent::VerificationCode,
+            req.locale.as_deref(), &req.target, vars,
+        ).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "邮件验证码已禁用"}))));
+            }
+            Err(e) => {
+                tracing::error!("Failed to send email: {}", e);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "发送验证码失败"}))));
+            }
         }
     } else {
         if !settings.sms_enabled {
@@ -90,7 +97,7 @@ pub async fn forgot_password(
             Ok(sms_code) => {
                 // 用阿里云返回的验证码更新存储
                 let reset_key = format!("{}:{}", req.target_type, req.target);
-                state.login_security.store_reset_code(reset_key, user_id.clone(), sms_code);
+                state.login_security.store_reset_code(reset_key, user_id.clone(), sms_code).await;
             }
             Err(e) => {
                 tracing::error!("Failed to send SMS: {}", e);
@@ -105,6 +112,17 @@ pub async fn forgot_password(
     })))
 }
 
+/// 校验新密码：长度至少6位且不能与当前密码相同（修改密码与首次登录改密共用）
+pub(super) fn validate_new_password(new_password: &str, current_password: &str) -> Result<(), &'static str> {
+    if new_password.len() < 6 {
+        return Err("新密码长度至少6位");
+    }
+    if new_password == current_password {
+        return Err("新密码不能与当前密码相同");
+    }
+    Ok(())
+}
+
 /// POST /api/auth/reset-password - 重置密码
 pub async fn reset_password(
     State(state): State<Arc<AppState>>,
@@ -117,7 +135,7 @@ pub async fn reset_password(
 
     // 验证重置码
     let reset_key = format!("{}:{}", req.target_type, req.target);
-    let user_id = state.login_security.verify_reset_code(&reset_key, &req.verification_code)
+    let user_id = state.login_security.verify_reset_code(&reset_key, &req.verification_code).await
         .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"error": "验证码错误或已过期"}))))?;
 
     // 更新密码并关闭2FA（找回密码后自动关闭2FA）
@@ -125,7 +143,7 @@ pub async fn reset_password(
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
     let now = Utc::now().to_rfc3339();
 
-    sqlx::query("UPDATE users SET password_hash = ?, two_factor_enabled = 0, two_factor_secret = NULL, updated_at = ? WHERE id = ?")
+    sqlx::query("UPDATE users SET password_hash = ?, two_factor_enabled = 0, two_factor_secret = NULL, must_change_password = 0, updated_at = ? WHERE id = ?")
         .bind(&password_hash)
         .bind(&now)
         .bind(&user_id)
@@ -145,6 +163,7 @@ pub async fn change_password(
     cookies: Cookies,
     Json(req): Json<ChangePasswordRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    reject_impersonation(&state, &cookies).await?;
     let session_id = cookies.get(SESSION_COOKIE_NAME)
         .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?
         .value()
@@ -153,9 +172,10 @@ pub async fn change_password(
     let user: Option<(String, String)> = sqlx::query_as(
         "SELECT u.id, u.password_hash FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -169,15 +189,14 @@ pub async fn change_password(
         return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "当前密码错误"}))));
     }
 
-    if req.new_password.len() < 6 {
-        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "新密码长度至少6位"}))));
-    }
+    validate_new_password(&req.new_password, &req.current_password)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e}))))?;
 
     let new_hash = bcrypt::hash(&req.new_password, bcrypt::DEFAULT_COST)
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
     let now = Utc::now().to_rfc3339();
 
-    sqlx::query("UPDATE users SET password_hash = ?, updated_at = ? WHERE id = ?")
+    sqlx::query("UPDATE users SET password_hash = ?, must_change_password = 0, updated_at = ? WHERE id = ?")
         .bind(&new_hash)
         .bind(&now)
         .bind(&user_id)
@@ -194,7 +213,7 @@ pub async fn change_password(
 
     // 清除当前session cookie
     let mut cookie = Cookie::new(SESSION_COOKIE_NAME, "");
-    cookie.set_path("/");
+    cookie.set_path(yaolist_backend::workspace::current().cookie_path());
     cookie.set_max_age(tower_cookies::cookie::time::Duration::seconds(0));
     cookies.remove(cookie);
 
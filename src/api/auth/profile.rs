@@ -11,6 +11,7 @@ use chrono::Utc;
 use crate::state::AppState;
 use crate::auth::SESSION_COOKIE_NAME;
 use super::types::*;
+use super::impersonate::reject_impersonation;
 
 /// GET /api/auth/me - 获取当前用户信息
 pub async fn get_current_user(
@@ -26,15 +27,40 @@ pub async fn get_current_user(
         "SELECT u.id, u.username, u.email, u.phone, u.two_factor_enabled, u.created_at, u.total_requests, u.total_traffic 
          FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
 
     let user = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话无效"}))))?;
 
+    // 模拟登录时返回发起的管理员，供前端显示提示横幅
+    let impersonator: Option<(String, Option<String>, String)> = sqlx::query_as(
+        "SELECT s.impersonator_id, a.username, s.expires_at FROM sessions s
+         LEFT JOIN users a ON a.id = s.impersonator_id
+         WHERE s.id = ? AND s.impersonator_id IS NOT NULL"
+    )
+    .bind(&session_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    let impersonation = match impersonator {
+        Some((admin_id, admin_name, expires_at)) => json!({
+            "impersonator_id": admin_id,
+            "impersonator_username": admin_name,
+            "expires_at": expires_at
+        }),
+        None => Value::Null,
+    };
+
+    // 仪表盘的最近观看（未看完的视频）
+    let recently_watched = crate::api::files::recent_progress(&state.db, &user.0, yaolist_backend::playback::DASHBOARD_RECENT, false)
+        .await
+        .unwrap_or_default();
+
     Ok(Json(json!({
         "id": user.0,
         "username": user.1,
@@ -43,7 +69,10 @@ pub async fn get_current_user(
         "two_factor_enabled": user.4,
         "created_at": user.5,
         "total_requests": user.6,
-        "total_traffic": user.7
+        "total_traffic": user.7,
+        "impersonating": impersonation.is_object(),
+        "impersonation": impersonation,
+        "recently_watched": recently_watched
     })))
 }
 /// POST /api/auth/update-email - 更新邮箱
@@ -52,6 +81,7 @@ pub async fn update_email(
     cookies: Cookies,
     Json(req): Json<UpdateEmailRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    reject_impersonation(&state, &cookies).await?;
     let session_id = cookies.get(SESSION_COOKIE_NAME)
         .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?
         .value()
@@ -60,9 +90,10 @@ pub async fn update_email(
     let user_id: Option<(String,)> = sqlx::query_as(
         "SELECT u.id FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -133,6 +164,7 @@ pub async fn update_phone(
     cookies: Cookies,
     Json(req): Json<UpdatePhoneRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    reject_impersonation(&state, &cookies).await?;
     let session_id = cookies.get(SESSION_COOKIE_NAME)
         .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?
         .value()
@@ -141,9 +173,10 @@ pub async fn update_phone(
     let user_id: Option<(String,)> = sqlx::query_as(
         "SELECT u.id FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -211,7 +244,7 @@ pub async fn update_phone(
 
     // 清除当前session cookie
     let mut cookie = Cookie::new(SESSION_COOKIE_NAME, "");
-    cookie.set_path("/");
+    cookie.set_path(yaolist_backend::workspace::current().cookie_path());
     cookie.set_max_age(tower_cookies::cookie::time::Duration::seconds(0));
     cookies.remove(cookie);
 
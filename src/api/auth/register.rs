@@ -15,12 +15,9 @@ pub async fn register(
     Json(req): Json<RegisterRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查是否允许注册
-    let allow_registration: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'allow_registration'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    let allow_registration: Option<(String,)> = yaolist_backend::workspace::read_setting(&state.db, "allow_registration")
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
     
     let registration_enabled = allow_registration
         .map(|(v,)| v == "true")
@@ -123,13 +120,10 @@ pub async fn register(
     }
 
     // 获取默认用户组（从站点设置或使用默认值）
-    let default_group_id: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'default_user_group'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten();
+    let default_group_id: Option<(String,)> = yaolist_backend::workspace::read_setting(&state.db, "default_user_group")
+        .await
+        .ok()
+        .flatten();
 
     let group_id = if let Some((gid,)) = default_group_id {
         gid
@@ -170,8 +164,8 @@ pub async fn register(
     let now = Utc::now().to_rfc3339();
 
     sqlx::query(
-        "INSERT INTO users (id, unique_id, username, password_hash, email, phone, is_admin, enabled, root_path, created_at, updated_at) 
-         VALUES (?, ?, ?, ?, ?, ?, 0, 1, ?, ?, ?)"
+        "INSERT INTO users (id, unique_id, username, password_hash, email, phone, is_admin, enabled, root_path, created_at, updated_at, workspace_id) 
+         VALUES (?, ?, ?, ?, ?, ?, 0, 1, ?, ?, ?, ?)"
     )
     .bind(&user_id)
     .bind(&unique_id)
@@ -182,6 +176,7 @@ pub async fn register(
     .bind(&group_root_path)
     .bind(&now)
     .bind(&now)
+    .bind(yaolist_backend::workspace::current().id())
     .execute(&state.db)
     .await
     .map_err(|e| {
@@ -306,13 +301,10 @@ pub async fn get_registration_config(
     State(state): State<Arc<AppState>>,
 ) -> Json<Value> {
     // 检查是否允许注册
-    let allow_registration: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'allow_registration'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten();
+    let allow_registration: Option<(String,)> = yaolist_backend::workspace::read_setting(&state.db, "allow_registration")
+        .await
+        .ok()
+        .flatten();
     
     let registration_enabled = allow_registration
         .map(|(v,)| v == "true")
@@ -1,6 +1,8 @@
 use axum::{
-    extract::State,
+    extract::{Request, State},
     http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde_json::{json, Value};
@@ -8,15 +10,55 @@ use std::sync::Arc;
 use tower_cookies::Cookies;
 use chrono::Utc;
 use totp_rs::{Algorithm, TOTP, Secret};
+use yaolist_backend::accounts;
 
 use crate::state::AppState;
 use crate::auth::SESSION_COOKIE_NAME;
 use super::types::*;
+use super::impersonate::reject_impersonation;
+
+/// 用户组要求两步验证而用户尚未启用时，会话只能访问设置两步验证所需的接口
+pub async fn two_factor_setup_middleware(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    req: Request,
+    next: Next,
+) -> Response {
+    if accounts::allowed_before_two_factor_setup(req.uri().path()) {
+        return next.run(req).await;
+    }
+    let Some(session_id) = cookies.get(SESSION_COOKIE_NAME).map(|c| c.value().to_string()) else {
+        return next.run(req).await;
+    };
+
+    // 模拟登录的会话不能设置两步验证，不做限制
+    let user: Option<(String, bool)> = sqlx::query_as(
+        "SELECT u.id, u.two_factor_enabled FROM users u
+         JOIN sessions s ON u.id = s.user_id
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND s.impersonator_id IS NULL"
+    )
+    .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten();
+    if let Some((user_id, enabled)) = user {
+        if accounts::needs_two_factor_setup(&state.db, &user_id, enabled).await {
+            return (StatusCode::FORBIDDEN, Json(json!({
+                "error": "所属用户组要求启用两步验证",
+                "two_factor_setup_required": true
+            }))).into_response();
+        }
+    }
+    next.run(req).await
+}
 
 pub async fn setup_2fa(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    reject_impersonation(&state, &cookies).await?;
     let session_id = cookies.get(SESSION_COOKIE_NAME)
         .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?
         .value()
@@ -25,9 +67,10 @@ pub async fn setup_2fa(
     let user: Option<(String, String, Option<String>)> = sqlx::query_as(
         "SELECT u.id, u.username, u.two_factor_secret FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -79,6 +122,7 @@ pub async fn enable_2fa(
     cookies: Cookies,
     Json(req): Json<Enable2FARequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    reject_impersonation(&state, &cookies).await?;
     let session_id = cookies.get(SESSION_COOKIE_NAME)
         .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?
         .value()
@@ -87,9 +131,10 @@ pub async fn enable_2fa(
     let user: Option<(String, Option<String>)> = sqlx::query_as(
         "SELECT u.id, u.two_factor_secret FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -134,6 +179,7 @@ pub async fn disable_2fa(
     cookies: Cookies,
     Json(req): Json<Verify2FARequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    reject_impersonation(&state, &cookies).await?;
     let session_id = cookies.get(SESSION_COOKIE_NAME)
         .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?
         .value()
@@ -142,9 +188,10 @@ pub async fn disable_2fa(
     let user: Option<(String, Option<String>, bool)> = sqlx::query_as(
         "SELECT u.id, u.two_factor_secret, u.two_factor_enabled FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -154,6 +201,9 @@ pub async fn disable_2fa(
     if !enabled {
         return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "2FA未启用"}))));
     }
+    if accounts::group_requires_two_factor(&state.db, &user_id).await {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "所属用户组要求两步验证，不能关闭"}))));
+    }
 
     let secret = secret.ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"error": "2FA配置错误"}))))?;
 
@@ -6,7 +6,16 @@ pub struct LoginRequest {
     pub password: String,
     pub captcha_id: Option<String>,
     pub captcha_code: Option<String>,
+    /// hCaptcha / Turnstile token
+    pub captcha_token: Option<String>,
     pub totp_code: Option<String>,
+    /// 账号要求修改密码时提交的新密码
+    pub new_password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckCaptchaQuery {
+    pub username: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -37,8 +46,15 @@ pub struct CheckUniqueRequest {
 pub struct ForgotPasswordRequest {
     pub target: String,           // 邮箱或手机号
     pub target_type: String,      // "email" 或 "sms"
-    pub captcha_id: String,
-    pub captcha_code: String,
+    #[serde(default)]
+    pub captcha_id: Option<String>,
+    #[serde(default)]
+    pub captcha_code: Option<String>,
+    /// hCaptcha / Turnstile token
+    #[serde(default)]
+    pub captcha_token: Option<String>,
+    #[serde(default)]
+    pub locale: Option<String>,   // 邮件语言，为空使用默认语言
 }
 
 #[derive(Debug, Deserialize)]
@@ -8,32 +8,10 @@ use serde_json::{json, Value};
 use std::sync::Arc;
 use tower_cookies::Cookies;
 use chrono::Utc;
+use sqlx::SqlitePool;
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
-
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<String, (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let admin_info: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    match admin_info {
-        Some((user_id, true)) => Ok(user_id),
-        Some((_, false)) => Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"})))),
-        None => Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "会话无效"})))),
-    }
-}
+use crate::auth::require_instance_admin;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BackupData {
@@ -153,15 +131,26 @@ pub async fn export_backup(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     
+    let backup = collect_backup(&state.db).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e}))))?;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": backup
+    })))
+}
+
+/// 从数据库收集备份数据（管理接口与命令行共用）
+pub async fn collect_backup(db: &SqlitePool) -> Result<BackupData, String> {
     // 导出站点设置
     let site_settings: Vec<(String, String)> = sqlx::query_as(
         "SELECT key, value FROM site_settings"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("导出站点设置失败: {}", e)}))))?;
+    .map_err(|e| format!("导出站点设置失败: {}", e))?;
     
     let site_settings: Vec<SiteSettingBackup> = site_settings.into_iter()
         .map(|(key, value)| SiteSettingBackup { key, value })
@@ -171,9 +160,9 @@ pub async fn export_backup(
     let users: Vec<UserBackup> = sqlx::query_as::<_, (String, String, String, String, Option<String>, Option<String>, Option<String>, bool, bool, bool, Option<String>, String, String)>(
         "SELECT id, unique_id, username, password_hash, email, phone, root_path, is_admin, enabled, two_factor_enabled, two_factor_secret, created_at, updated_at FROM users"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("导出用户失败: {}", e)}))))?
+    .map_err(|e| format!("导出用户失败: {}", e))?
     .into_iter()
     .map(|(id, unique_id, username, password_hash, email, phone, root_path, is_admin, enabled, two_factor_enabled, two_factor_secret, created_at, updated_at)| {
         UserBackup {
@@ -186,9 +175,9 @@ pub async fn export_backup(
     let user_groups: Vec<UserGroupBackup> = sqlx::query_as::<_, crate::models::UserGroup>(
         "SELECT * FROM user_groups"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("导出用户组失败: {}", e)}))))?
+    .map_err(|e| format!("导出用户组失败: {}", e))?
     .into_iter()
     .map(|g| UserGroupBackup {
         id: g.id, name: g.name, description: g.description, is_admin: g.is_admin,
@@ -206,9 +195,9 @@ pub async fn export_backup(
     let user_group_members: Vec<(String, String)> = sqlx::query_as(
         "SELECT user_id, group_id FROM user_group_members"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("导出用户组成员失败: {}", e)}))))?;
+    .map_err(|e| format!("导出用户组成员失败: {}", e))?;
     
     let user_group_members: Vec<UserGroupMemberBackup> = user_group_members.into_iter()
         .map(|(user_id, group_id)| UserGroupMemberBackup { user_id, group_id })
@@ -218,9 +207,9 @@ pub async fn export_backup(
     let drivers: Vec<DriverBackup> = sqlx::query_as::<_, (String, String, String, bool, Option<String>, String, String)>(
         "SELECT name, version, description, enabled, config, created_at, updated_at FROM drivers"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("导出驱动失败: {}", e)}))))?
+    .map_err(|e| format!("导出驱动失败: {}", e))?
     .into_iter()
     .map(|(name, version, description, enabled, config, created_at, updated_at)| {
         DriverBackup {
@@ -233,7 +222,7 @@ pub async fn export_backup(
     let mounts: Vec<MountBackup> = sqlx::query_as::<_, (String, String, String, String, String, bool, String, String)>(
         "SELECT id, name, driver, mount_path, config, enabled, created_at, updated_at FROM mounts"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
     .unwrap_or_default()
     .into_iter()
@@ -248,7 +237,7 @@ pub async fn export_backup(
     let metas: Vec<MetaBackup> = sqlx::query_as::<_, (i64, String, Option<String>, Option<String>, Option<String>, Option<String>, String, String)>(
         "SELECT id, path, password, hide, readme, header, created_at, updated_at FROM metas"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
     .unwrap_or_default()
     .into_iter()
@@ -263,7 +252,7 @@ pub async fn export_backup(
     let shares: Vec<ShareBackup> = sqlx::query_as::<_, (i64, String, Option<String>, Option<String>, String)>(
         "SELECT id, path, password, expire_at, created_at FROM shares"
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
     .unwrap_or_default()
     .into_iter()
@@ -287,10 +276,7 @@ pub async fn export_backup(
         shares,
     };
     
-    Ok(Json(json!({
-        "code": 200,
-        "data": backup
-    })))
+    Ok(backup)
 }
 
 #[derive(Debug, Deserialize)]
@@ -305,7 +291,7 @@ pub async fn import_backup(
     cookies: Cookies,
     Json(req): Json<RestoreRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let admin_id = require_admin(&state, &cookies).await?;
+    let admin_id = require_instance_admin(&state, &cookies).await?;
     
     let mut results = Vec::new();
     let now = Utc::now().to_rfc3339();
@@ -531,6 +517,7 @@ pub async fn import_backup(
             Err(e) => results.push(json!({"type": "driver", "name": driver.name, "status": "error", "message": e.to_string()})),
         }
     }
+    state.paths.invalidate_mounts();
     
     // 恢复元信息
     for meta in &req.data.metas {
@@ -0,0 +1,176 @@
+//! 品牌资源：Logo、网站图标、站点名称与自定义 CSS / JS
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::branding::{BrandingAsset, EffectiveBranding};
+use yaolist_backend::utils::etag_matches;
+use yaolist_backend::workspace;
+
+use crate::state::AppState;
+use crate::auth::{require_admin, require_instance_admin};
+
+fn parse_asset(name: &str) -> Result<BrandingAsset, (StatusCode, Json<Value>)> {
+    BrandingAsset::from_name(name)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "未知的品牌资源"}))))
+}
+
+/// 当前工作区的 URL 前缀（默认工作区或按域名访问时为空）
+pub fn workspace_base() -> String {
+    workspace::current().cookie_path().trim_end_matches('/').to_string()
+}
+
+/// 站点完整地址（含工作区前缀）：优先使用配置的下载域名，
+/// 未配置时仅取 Host 请求头，不信任可被伪造的 X-Forwarded-Host
+pub fn site_base(state: &AppState, headers: &HeaderMap) -> String {
+    let origin = state.download_settings.site_origin().unwrap_or_else(|| {
+        let scheme = match headers.get("x-forwarded-proto").and_then(|v| v.to_str().ok()) {
+            Some("https") => "https",
+            _ => "http",
+        };
+        let host = headers.get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("localhost");
+        format!("{}://{}", scheme, host)
+    });
+    format!("{}{}", origin, workspace_base())
+}
+
+/// 各资源的访问地址，未设置时为 null；带更新时间参数以便浏览器更新缓存
+fn branding_json(branding: &EffectiveBranding) -> Value {
+    let base = workspace_base();
+    let mut assets = Map::new();
+    for asset in BrandingAsset::ALL {
+        let url = branding.asset(asset)
+            .map(|meta| json!(format!("{}/api/branding/{}?v={}", base, asset.name(), meta.updated_at)))
+            .unwrap_or(Value::Null);
+        assets.insert(asset.name().replace('.', "_"), url);
+    }
+    json!({
+        "site_name": branding.site_name,
+        "assets": assets
+    })
+}
+
+/// GET /api/branding - 当前工作区的品牌设置（公开）
+pub async fn get_branding(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let branding = state.branding.effective(workspace::current().id()).await;
+    Json(json!({
+        "code": 200,
+        "data": branding_json(&branding)
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateBrandingRequest {
+    /// 为空表示清除，使用站点标题
+    pub site_name: Option<String>,
+}
+
+/// POST /api/branding - 设置当前工作区的站点名称（需要管理员权限）
+pub async fn update_branding(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<UpdateBrandingRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_admin(&state, &cookies).await?;
+    let workspace_id = workspace::current().id().to_string();
+    state.branding.set_site_name(&workspace_id, req.site_name).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("站点名称无效: {}", e)}))))?;
+
+    let branding = state.branding.effective(&workspace_id).await;
+    Ok(Json(json!({
+        "code": 200,
+        "message": "品牌设置已保存",
+        "data": branding_json(&branding)
+    })))
+}
+
+/// GET /api/branding/:asset - 读取品牌资源
+pub async fn get_branding_asset(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(asset) = BrandingAsset::from_name(&name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some((data, meta)) = state.branding.read(workspace::current().id(), asset).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let etag = format!("\"{}-{}\"", meta.updated_at, meta.size);
+    let not_modified = headers.get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| etag_matches(v, &etag));
+    let mut builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, "public, max-age=300");
+    if meta.content_type == "image/svg+xml" {
+        // SVG 可包含脚本，禁止其在本站源下执行
+        builder = builder.header(header::CONTENT_SECURITY_POLICY, "default-src 'none'; style-src 'unsafe-inline'");
+    }
+    if not_modified {
+        return builder.status(StatusCode::NOT_MODIFIED).body(Body::empty()).unwrap();
+    }
+    builder
+        .header(header::CONTENT_TYPE, &meta.content_type)
+        .body(Body::from(data))
+        .unwrap()
+}
+
+/// 自定义 CSS / JS 会在同域名下所有工作区页面运行，只能由实例管理员在默认工作区设置
+async fn require_asset_admin(state: &AppState, cookies: &Cookies, asset: BrandingAsset) -> Result<String, (StatusCode, Json<Value>)> {
+    if asset.is_code() {
+        require_instance_admin(state, cookies).await
+    } else {
+        require_admin(state, cookies).await
+    }
+}
+
+/// PUT /api/branding/:asset - 上传品牌资源，请求体为文件内容（需要管理员权限，CSS / JS 需要实例管理员）
+pub async fn upload_branding_asset(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(name): Path<String>,
+    body: Bytes,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let asset = parse_asset(&name)?;
+    require_asset_admin(&state, &cookies, asset).await?;
+    let meta = state.branding.save(workspace::current().id(), asset, &body).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("上传失败: {}", e)}))))?;
+
+    tracing::info!("Branding asset {} updated ({} bytes)", asset.name(), meta.size);
+    Ok(Json(json!({
+        "code": 200,
+        "message": "上传成功",
+        "data": meta
+    })))
+}
+
+/// DELETE /api/branding/:asset - 删除品牌资源，恢复内置资源（需要管理员权限，CSS / JS 需要实例管理员）
+pub async fn delete_branding_asset(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let asset = parse_asset(&name)?;
+    require_asset_admin(&state, &cookies, asset).await?;
+    let removed = state.branding.remove(workspace::current().id(), asset).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("删除失败: {}", e)}))))?;
+    if !removed {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "资源不存在"}))));
+    }
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
@@ -0,0 +1,384 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tower_cookies::Cookies;
+use yaolist_backend::dedupe::{group_duplicates, hash_key, DedupeAction, DedupeReport, DuplicateFile};
+use yaolist_backend::storage::{DriverBox, HashType};
+use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+use crate::api::file_resolver::{calculate_internal_path, get_all_mounts, get_first_mount, MountInfo};
+
+/// 每处理多少个文件更新一次进度
+const PROGRESS_INTERVAL: u64 = 100;
+
+/// 虚拟路径解析为（驱动，驱动内路径）
+async fn resolve(state: &AppState, mounts: &[MountInfo], path: &str) -> Option<(DriverBox, String)> {
+    let mount = get_first_mount(path, mounts)?;
+    let driver = state.storage_manager.get_driver(&mount.id).await?;
+    Some((driver, calculate_internal_path(&mount.mount_path, path)))
+}
+
+/// 待计算哈希的文件
+struct Candidate {
+    file: DuplicateFile,
+    driver: DriverBox,
+    internal_path: String,
+}
+
+/// 本地文件的（设备，inode），用于识别已是硬链接的文件
+#[cfg(unix)]
+fn local_file_id(path: &std::path::Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn local_file_id(_path: &std::path::Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// 读取文件内容计算MD5
+pub async fn compute_md5(driver: &DriverBox, path: &str) -> anyhow::Result<String> {
+    let mut reader = driver.open_reader(path, None).await?;
+    let mut context = md5::Context::new();
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        context.consume(&buf[..n]);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// 获取文件哈希：缓存 → 驱动提供 → 读取内容计算；返回（哈希，是否来自缓存）
+async fn file_hash(state: &AppState, candidate: &Candidate) -> Result<(String, bool), String> {
+    let file = &candidate.file;
+    let cached: Option<(i64, Option<String>, String)> = sqlx::query_as(
+        "SELECT size, modified, hash FROM file_hashes WHERE path = ?"
+    )
+    .bind(&file.path)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| format!("读取哈希缓存失败 {}: {}", file.path, e))?;
+    if let Some((size, modified, hash)) = cached {
+        if size as u64 == file.size && modified == file.modified {
+            return Ok((hash, true));
+        }
+    }
+
+    let driver = &candidate.driver;
+    let reported = if driver.hash_types().contains(&HashType::Md5) {
+        driver.get_hash(&candidate.internal_path, HashType::Md5).await.ok().flatten()
+    } else {
+        None
+    };
+    let hash = match reported {
+        Some(hash) => hash,
+        None => compute_md5(driver, &candidate.internal_path).await
+            .map_err(|e| format!("计算哈希失败 {}: {}", file.path, e))?,
+    };
+    let hash = hash_key(HashType::Md5, &hash);
+
+    if let Err(e) = sqlx::query(
+        "INSERT OR REPLACE INTO file_hashes (path, size, modified, hash, updated_at) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&file.path)
+    .bind(file.size as i64)
+    .bind(&file.modified)
+    .bind(&hash)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    {
+        tracing::warn!("Failed to cache hash of {}: {}", file.path, e);
+    }
+    Ok((hash, false))
+}
+
+/// 执行一次重复文件扫描（遍历目录 → 按大小筛选 → 计算哈希 → 分组）
+async fn run_scan(state: &AppState, report: &mut DedupeReport) {
+    let mounts = match get_all_mounts(state).await {
+        Ok(m) => m,
+        Err(e) => {
+            report.error(format!("获取挂载列表失败: {}", e));
+            return;
+        }
+    };
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    let mut local_ids: HashSet<(u64, u64)> = HashSet::new();
+    for root in report.paths.clone() {
+        let Some((driver, internal_root)) = resolve(state, &mounts, &root).await else {
+            report.error(format!("目录不在任何已加载的挂载下: {}", root));
+            continue;
+        };
+        let local = driver.is_local();
+
+        // (虚拟路径, 驱动内路径)
+        let mut stack = vec![(root, internal_root)];
+        while let Some((virtual_dir, internal_dir)) = stack.pop() {
+            let entries = match driver.list(&internal_dir).await {
+                Ok(e) => e,
+                Err(e) => {
+                    report.error(format!("列出目录失败 {}: {}", virtual_dir, e));
+                    continue;
+                }
+            };
+            for entry in entries {
+                let virtual_path = format!("{}/{}", virtual_dir.trim_end_matches('/'), entry.name);
+                let internal_path = format!("{}/{}", internal_dir.trim_end_matches('/'), entry.name);
+                if entry.is_dir {
+                    stack.push((virtual_path, internal_path));
+                    continue;
+                }
+                report.scanned += 1;
+                // 空文件不占空间，不参与去重
+                if entry.size == 0 || entry.size < report.min_size {
+                    continue;
+                }
+                // 同一文件的其他硬链接不占额外空间
+                if local {
+                    let id = driver.get_local_path(&internal_path).and_then(|p| local_file_id(&p));
+                    if id.is_some_and(|id| !local_ids.insert(id)) {
+                        report.linked += 1;
+                        continue;
+                    }
+                }
+                candidates.push(Candidate {
+                    file: DuplicateFile { path: virtual_path, size: entry.size, modified: entry.modified, local },
+                    driver: driver.clone(),
+                    internal_path,
+                });
+            }
+            if report.scanned.is_multiple_of(PROGRESS_INTERVAL) {
+                state.dedupe.progress(report);
+            }
+        }
+    }
+
+    // 只有大小相同的文件才可能重复
+    let mut sizes: HashMap<u64, usize> = HashMap::new();
+    for candidate in &candidates {
+        *sizes.entry(candidate.file.size).or_default() += 1;
+    }
+    candidates.retain(|c| sizes[&c.file.size] > 1);
+
+    let mut hashed = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        match file_hash(state, &candidate).await {
+            Ok((hash, cached)) => {
+                if cached {
+                    report.cached += 1;
+                } else {
+                    report.hashed += 1;
+                }
+                hashed.push((hash, candidate.file));
+            }
+            Err(e) => report.error(e),
+        }
+        if (report.cached + report.hashed).is_multiple_of(PROGRESS_INTERVAL) {
+            state.dedupe.progress(report);
+        }
+    }
+    report.groups = group_duplicates(hashed);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartScanRequest {
+    /// 要扫描的目录（通常为挂载路径）
+    pub paths: Vec<String>,
+    /// 忽略小于该大小的文件（字节）
+    #[serde(default)]
+    pub min_size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveGroupRequest {
+    pub hash: String,
+    /// 保留的文件
+    pub keep: String,
+    pub action: DedupeAction,
+    /// 要处理的重复文件，为空时处理分组内除保留文件外的全部文件
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+/// GET /api/admin/dedupe - 获取最近一次重复文件报告
+pub async fn get_report(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": state.dedupe.report()
+    })))
+}
+
+/// POST /api/admin/dedupe/scan - 开始扫描重复文件（后台执行）
+pub async fn start_scan(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<StartScanRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let mut paths: Vec<String> = req.paths.iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(fix_and_clean_path)
+        .collect();
+    paths.sort();
+    paths.dedup();
+    // 已被其他所选目录包含的目录不再重复扫描
+    let roots: Vec<String> = paths.iter()
+        .filter(|p| !paths.iter().any(|other| other != *p && is_sub_path(other, p)))
+        .cloned()
+        .collect();
+    if roots.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "请至少选择一个目录"}))));
+    }
+
+    if !state.dedupe.try_begin(roots.clone(), req.min_size) {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "扫描正在运行"}))));
+    }
+    tokio::spawn(async move {
+        let mut report = DedupeReport::started(roots, req.min_size);
+        // 在独立任务中运行，panic时也能清除运行标记
+        let task_state = state.clone();
+        let result = tokio::spawn(async move {
+            run_scan(&task_state, &mut report).await;
+            report
+        }).await;
+        let report = result.unwrap_or_else(|e| {
+            let mut report = DedupeReport::started(Vec::new(), 0);
+            report.error(format!("扫描异常终止: {}", e));
+            report
+        });
+        let report = state.dedupe.finish(report);
+        tracing::info!(
+            "Duplicate scan finished: scanned={}, groups={}, wasted={} bytes, errors={}",
+            report.scanned, report.groups.len(), report.wasted_bytes, report.errors.len()
+        );
+    });
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "扫描已开始运行"
+    })))
+}
+
+/// 文件是否仍存在且大小不变
+async fn still_exists(driver: &DriverBox, internal_path: &str, size: u64) -> bool {
+    let (parent, name) = internal_path.rsplit_once('/').unwrap_or(("", internal_path));
+    let parent = if parent.is_empty() { "/" } else { parent };
+    driver.list(parent).await
+        .map(|entries| entries.iter().any(|e| e.name == name && !e.is_dir && e.size == size))
+        .unwrap_or(false)
+}
+
+/// 将重复文件替换为指向保留文件的硬链接
+async fn replace_with_link(keep: &std::path::Path, target: &std::path::Path) -> std::io::Result<()> {
+    let name = target.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let tmp = target.with_file_name(format!(".{}.dedupe", name));
+    tokio::fs::hard_link(keep, &tmp).await?;
+    let result = tokio::fs::rename(&tmp, target).await;
+    // 两者已是同一文件时rename不做任何事，临时链接需要清理
+    let _ = tokio::fs::remove_file(&tmp).await;
+    result
+}
+
+/// POST /api/admin/dedupe/resolve - 删除分组中的重复文件或替换为硬链接
+pub async fn resolve_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<ResolveGroupRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    if state.dedupe.report().map(|r| r.running).unwrap_or(false) {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "扫描正在运行"}))));
+    }
+    let group = state.dedupe.group(&req.hash)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "重复分组不存在"}))))?;
+    let keep = group.files.iter().find(|f| f.path == req.keep)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"error": "保留的文件不在分组中"}))))?;
+    let targets: Vec<&DuplicateFile> = if req.paths.is_empty() {
+        group.files.iter().filter(|f| f.path != keep.path).collect()
+    } else {
+        let mut targets = Vec::new();
+        for path in &req.paths {
+            match group.files.iter().find(|f| &f.path == path && f.path != keep.path) {
+                Some(file) => targets.push(file),
+                None => return Err((StatusCode::BAD_REQUEST, Json(json!({"error": format!("文件不在分组中: {}", path)})))),
+            }
+        }
+        targets
+    };
+    if req.action == DedupeAction::Link && !targets.iter().chain(std::iter::once(&keep)).all(|f| f.local) {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "只有本地存储上的文件可以替换为链接"}))));
+    }
+
+    let mounts = get_all_mounts(&state).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    let (keep_driver, keep_internal) = resolve(&state, &mounts, &keep.path).await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "保留的文件所在存储未加载"}))))?;
+    // 保留的文件已不存在时不处理，避免删掉最后一份
+    if !still_exists(&keep_driver, &keep_internal, keep.size).await {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "保留的文件已变化，请重新扫描"}))));
+    }
+    let keep_local = keep_driver.get_local_path(&keep_internal);
+
+    let mut resolved = Vec::new();
+    let mut errors = Vec::new();
+    for file in targets {
+        let Some((driver, internal)) = resolve(&state, &mounts, &file.path).await else {
+            errors.push(format!("存储未加载: {}", file.path));
+            continue;
+        };
+        let result = match req.action {
+            DedupeAction::Delete => driver.delete(&internal).await.map_err(|e| e.to_string()),
+            DedupeAction::Link => match (&keep_local, driver.get_local_path(&internal)) {
+                (Some(keep_local), Some(target)) => replace_with_link(keep_local, &target).await
+                    .map_err(|e| e.to_string()),
+                _ => Err("不是本地存储".to_string()),
+            },
+        };
+        match result {
+            Ok(()) => {
+                if req.action == DedupeAction::Delete {
+                    let _ = sqlx::query("DELETE FROM file_hashes WHERE path = ?")
+                        .bind(&file.path)
+                        .execute(&state.db)
+                        .await;
+                }
+                resolved.push(file.path.clone());
+            }
+            Err(e) => errors.push(format!("{}: {}", file.path, e)),
+        }
+    }
+    state.dedupe.remove_files(&req.hash, &resolved);
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已处理 {} 个重复文件", resolved.len()),
+        "data": {
+            "resolved": resolved,
+            "freed_bytes": group.size * resolved.len() as u64,
+            "errors": errors
+        }
+    })))
+}
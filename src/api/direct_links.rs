@@ -8,7 +8,7 @@ use serde_json::json;
 use std::sync::Arc;
 use chrono::Utc;
 use tower_cookies::Cookies;
-use crate::auth::SESSION_COOKIE_NAME;
+use crate::auth::session_user;
 use crate::state::AppState;
 use rand::Rng;
 
@@ -66,7 +66,7 @@ pub struct ListDirectLinksQuery {
     pub search: Option<String>,
 }
 
-fn generate_sign(length: usize) -> String {
+pub fn generate_sign(length: usize) -> String {
     const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghjkmnpqrstuvwxyz23456789";
     let mut rng = rand::thread_rng();
     (0..length)
@@ -83,19 +83,8 @@ pub async fn list_direct_links(
     Query(query): Query<ListDirectLinksQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // 验证用户登录
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
@@ -232,21 +221,10 @@ pub async fn create_direct_link(
     Json(req): Json<CreateDirectLinkRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // 验证用户登录
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user_id: Option<String> = sqlx::query_scalar(
-        "SELECT u.id FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
-    let user_id = user_id.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
+    let (user_id, _) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
     // 检查用户是否有创建直链的权限
     let has_permission: bool = sqlx::query_scalar(
@@ -315,19 +293,8 @@ pub async fn update_direct_link(
     Json(req): Json<UpdateDirectLinkRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // 验证用户登录
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
@@ -387,19 +354,8 @@ pub async fn delete_direct_link(
     Path(id): Path<i64>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // 验证用户登录
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
@@ -433,19 +389,8 @@ pub async fn toggle_direct_link(
     Path(id): Path<i64>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // 验证用户登录
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
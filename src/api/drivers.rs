@@ -6,33 +6,16 @@ use axum::{
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tower_cookies::Cookies;
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
-
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
-    Ok(())
-}
+use crate::auth::require_instance_admin;
+use yaolist_backend::mount_visibility;
+use yaolist_backend::secrets;
+use yaolist_backend::storage::ConfigUpdate;
+use yaolist_backend::storage::capabilities::DriverCapabilities;
 
 /// 保存驱动更新后的配置到数据库 / Save updated driver config to database
 async fn save_driver_config(db: &sqlx::SqlitePool, id: &str, updated_config: serde_json::Value) -> Result<(), String> {
@@ -107,7 +90,7 @@ pub async fn list_drivers(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     // 从数据库获取驱动列表，包含完整配置信息
     let db_drivers: Vec<(String, String, String, bool, String)> = sqlx::query_as(
         "SELECT name, version, description, enabled, config FROM drivers"
@@ -116,13 +99,25 @@ pub async fn list_drivers(
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
     
-    // 获取所有驱动错误状态
+    // 获取所有驱动错误状态和健康状态
     let driver_errors = state.storage_manager.get_all_driver_errors().await;
+    let driver_health = state.storage_manager.get_all_driver_health().await;
+    let rate_limits = state.storage_manager.get_all_rate_limit_stats().await;
+    let visibility = mount_visibility::load_rules(&state.db).await.unwrap_or_default();
+    // 敏感字段只写不读，按驱动类型的配置项声明隐藏
+    let secret_fields: HashMap<String, Vec<String>> = state.storage_manager.get_all_factories().await
+        .iter()
+        .map(|f| (f.driver_type().to_string(), secrets::secret_fields(&f.driver_info().additional)))
+        .collect();
     
     let drivers: Vec<Value> = db_drivers.iter().map(|(name, version, description, enabled, config_str)| {
-        let config: Value = serde_json::from_str(config_str).unwrap_or(json!({}));
+        let mut config: Value = serde_json::from_str(config_str).unwrap_or(json!({}));
+        let driver_type = config.get("driver_type").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
+        let secrets_set = match (secret_fields.get(&driver_type), config.get_mut("config")) {
+            (Some(fields), Some(driver_config)) => secrets::redact_config(driver_config, fields),
+            _ => Vec::new(),
+        };
         let mount_path = config.get("mount_path").and_then(|v| v.as_str()).unwrap_or("");
-        let driver_type = config.get("driver_type").and_then(|v| v.as_str()).unwrap_or("unknown");
         
         // 获取该驱动的错误状态
         let error = driver_errors.get(name).cloned();
@@ -134,8 +129,6 @@ pub async fn list_drivers(
             "disabled"
         };
         
-        tracing::debug!("驱动 {} 配置: {}", name, config_str);
-        
         json!({
             "id": name,
             "name": mount_path,
@@ -144,13 +137,20 @@ pub async fn list_drivers(
             "description": description,
             "enabled": enabled,
             "config": config,
+            // 已保存但被隐藏的敏感字段，更新时留空即保留
+            "secrets_set": secrets_set,
             "status": status,
-            "error": error
+            "error": error,
+            "health": driver_health.get(name),
+            "rate_limit": rate_limits.get(name),
+            "visible_groups": visibility.get(name).cloned().unwrap_or_default()
         })
     }).collect();
     
     Ok(Json(json!({
-        "drivers": drivers
+        "drivers": drivers,
+        // 列表缓存预热跟踪的热门目录
+        "hot_paths": state.storage_manager.list_cache().hot_paths(20)
     })))
 }
 
@@ -159,13 +159,14 @@ pub async fn enable_driver(
     cookies: Cookies,
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     // 更新数据库启用状态
     sqlx::query("UPDATE drivers SET enabled = 1 WHERE name = ?")
         .bind(&id)
         .execute(&state.db)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    state.paths.invalidate_mounts();
     
     // 从数据库获取配置并加载驱动（验证逻辑已封装在StorageManager中）
     let driver_config: Option<(String,)> = sqlx::query_as("SELECT config FROM drivers WHERE name = ?")
@@ -211,13 +212,14 @@ pub async fn disable_driver(
     cookies: Cookies,
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     // 更新数据库禁用状态
     sqlx::query("UPDATE drivers SET enabled = 0 WHERE name = ?")
         .bind(&id)
         .execute(&state.db)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    state.paths.invalidate_mounts();
     
     // 卸载驱动实例并清除错误状态
     let _ = state.storage_manager.remove_driver(&id).await;
@@ -234,7 +236,7 @@ pub async fn delete_driver(
     cookies: Cookies,
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     // 先卸载驱动实例
     let _ = state.storage_manager.remove_driver(&id).await;
     
@@ -244,6 +246,10 @@ pub async fn delete_driver(
         .execute(&state.db)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    state.paths.invalidate_mounts();
+    
+    // 删除该存储的可见性映射
+    let _ = mount_visibility::save_driver_groups(&state.db, &id, &[]).await;
     
     // 删除该存储的索引数据库
     yaolist_backend::search::DbIndex::delete_driver_db(&id);
@@ -258,7 +264,7 @@ pub async fn reload_driver(
     cookies: Cookies,
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     // 先卸载驱动实例
     let _ = state.storage_manager.remove_driver(&id).await;
     
@@ -303,7 +309,7 @@ pub async fn list_available_drivers(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     // 从工厂获取完整的驱动信息，并转换为前端期望的格式
     let factories = state.storage_manager.get_all_factories().await;
     
@@ -320,6 +326,9 @@ pub async fn list_available_drivers(
             let display_title = item.title.as_ref().unwrap_or(&item.name);
             prop.insert("title".to_string(), json!(display_title));
             prop.insert("order".to_string(), json!(index)); // 保持驱动定义的顺序
+            if item.is_secret() {
+                prop.insert("format".to_string(), json!("password"));
+            }
             if let Some(ref help) = item.help {
                 prop.insert("description".to_string(), json!(help));
             }
@@ -343,6 +352,7 @@ pub async fn list_available_drivers(
             "driver_type": factory.driver_type(),
             "display_name": info.config.name,
             "description": format!("{} 存储驱动", info.config.name),
+            "plugin": yaolist_backend::storage::plugin::is_plugin(factory.driver_type()),
             "config_schema": {
                 "type": "object",
                 "properties": properties,
@@ -360,12 +370,25 @@ pub async fn list_available_drivers(
     })))
 }
 
+/// GET /api/admin/plugins - 获取已发现的驱动插件
+pub async fn list_plugins(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": yaolist_backend::storage::plugin::loaded_plugins()
+    })))
+}
+
 pub async fn create_driver(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Json(req): Json<CreateDriverRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = Utc::now().to_rfc3339();
     
     // 查询当前最大ID，生成新的数字ID
@@ -399,6 +422,7 @@ pub async fn create_driver(
         tracing::error!("Failed to save driver to database: {}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "保存驱动失败"})))
     })?;
+    state.paths.invalidate_mounts();
     
     // 创建驱动实例（使用唯一ID）
     if let Err(e) = state.storage_manager.create_driver(driver_id.clone(), &req.driver_type, req.config).await {
@@ -452,12 +476,32 @@ pub async fn update_driver(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Path(id): Path<String>,
-    Json(req): Json<CreateDriverRequest>,
+    Json(mut req): Json<CreateDriverRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = Utc::now().to_rfc3339();
     let display_name = req.mount_path.clone().unwrap_or_else(|| req.driver_type.clone());
     
+    // 读取旧配置（用于判断是否需要更新索引，以及保留留空的敏感字段）
+    let old_config: Option<Value> = sqlx::query_scalar::<_, String>("SELECT config FROM drivers WHERE name = ?")
+        .bind(&id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|c| serde_json::from_str::<Value>(&c).ok());
+    let old_mount_path = old_config.as_ref()
+        .and_then(|c| c.get("mount_path").and_then(|v| v.as_str()).map(|s| s.to_string()));
+    
+    if let Some(stored) = old_config.as_ref().and_then(|c| c.get("config")) {
+        let fields = state.storage_manager.get_all_factories().await
+            .iter()
+            .find(|f| f.driver_type() == req.driver_type)
+            .map(|f| secrets::secret_fields(&f.driver_info().additional))
+            .unwrap_or_default();
+        secrets::keep_blank_secrets(&mut req.config, stored, &fields);
+    }
+    
     // 更新数据库
     let result = sqlx::query(
         "UPDATE drivers SET description = ?, config = ?, updated_at = ? WHERE name = ?"
@@ -478,6 +522,7 @@ pub async fn update_driver(
         tracing::error!("Failed to update driver in database: {}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "更新驱动失败"})))
     })?;
+    state.paths.invalidate_mounts();
     
     if result.rows_affected() == 0 {
         return Ok(Json(json!({
@@ -486,17 +531,29 @@ pub async fn update_driver(
         })));
     }
     
-    // 卸载旧驱动实例
-    let _ = state.storage_manager.remove_driver(&id).await;
+    // 只有凭据等关键配置变化时才重建该驱动，其余配置原地生效
+    let update = match state.storage_manager.update_driver_config(&id, &req.driver_type, req.config).await {
+        Ok(update) => update,
+        Err(e) => {
+            let error_msg = e.to_string();
+            tracing::error!("Failed to create driver: {}", error_msg);
+            return Ok(Json(json!({
+                "code": 500,
+                "message": format!("驱动更新失败: {}", error_msg),
+                "id": id
+            })));
+        }
+    };
     
-    // 创建新驱动实例
-    if let Err(e) = state.storage_manager.create_driver(id.clone(), &req.driver_type, req.config).await {
-        let error_msg = e.to_string();
-        tracing::error!("Failed to create driver: {}", error_msg);
+    if update != ConfigUpdate::Rebuilt {
+        if old_mount_path != req.mount_path {
+            trigger_index_update_if_enabled(&state).await;
+        }
         return Ok(Json(json!({
-            "code": 500,
-            "message": format!("驱动更新失败: {}", error_msg),
-            "id": id
+            "code": 200,
+            "message": "驱动更新成功",
+            "id": id,
+            "rebuilt": false
         })));
     }
     
@@ -534,13 +591,15 @@ pub async fn update_driver(
             "code": 200,
             "message": "驱动更新成功，但验证失败",
             "id": id,
+            "rebuilt": true,
             "warning": format!("连接验证失败: {}", error)
         })))
     } else {
         Ok(Json(json!({
             "code": 200,
             "message": "驱动更新成功",
-            "id": id
+            "id": id,
+            "rebuilt": true
         })))
     }
 }
@@ -551,7 +610,7 @@ pub async fn get_driver_space(
     cookies: Cookies,
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     
     // 先检查驱动配置是否存在（使用name字段）
     let driver_exists: Option<(String,)> = sqlx::query_as("SELECT name FROM drivers WHERE name = ?")
@@ -616,13 +675,110 @@ pub async fn get_driver_space(
     }
 }
 
+/// GET /api/drivers/:id/capabilities - 获取驱动能力与生效的通用选项，前端据此隐藏不支持的操作
+pub async fn get_driver_capabilities(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let spec = state.storage_manager.get_driver_spec(&id).await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "驱动不存在"}))))?;
+    // 能力由驱动实例声明，未加载时无法获取
+    let driver = state.storage_manager.get_driver(&id).await
+        .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, Json(json!({"error": "驱动未成功加载，可能连接失败"}))))?;
+    let factory = state.storage_manager.get_factory(&spec.driver_type).await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": format!("驱动类型不存在: {}", spec.driver_type)}))))?;
+    
+    let info = factory.driver_info();
+    let capabilities = DriverCapabilities::resolve(
+        &spec.driver_type,
+        driver.capabilities(),
+        info.config,
+        &info.common,
+        &spec.config,
+        state.storage_manager.maintenance().is_enabled(),
+    );
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": capabilities
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateVisibilityRequest {
+    pub groups: Vec<String>,
+}
+
+/// GET /api/drivers/:id/visibility - 获取存储可见的用户组（为空表示对所有人可见）
+pub async fn get_driver_visibility(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    let groups = mount_visibility::load_driver_groups(&state.db, &id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": { "groups": groups }
+    })))
+}
+
+/// POST /api/drivers/:id/visibility - 设置存储可见的用户组（为空表示对所有人可见）
+pub async fn update_driver_visibility(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateVisibilityRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let driver_exists: Option<(String,)> = sqlx::query_as("SELECT name FROM drivers WHERE name = ?")
+        .bind(&id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    if driver_exists.is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "驱动不存在"}))));
+    }
+    
+    // 只接受已存在的用户组
+    let mut groups = Vec::new();
+    for group_id in req.groups.iter().map(|g| g.trim()).filter(|g| !g.is_empty()) {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM user_groups WHERE CAST(id AS TEXT) = ?")
+            .bind(group_id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        if exists.is_none() {
+            return Err((StatusCode::BAD_REQUEST, Json(json!({"error": format!("用户组 {} 不存在", group_id)}))));
+        }
+        groups.push(group_id.to_string());
+    }
+    
+    mount_visibility::save_driver_groups(&state.db, &id, &groups)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": "存储可见性已更新",
+        "data": { "groups": groups }
+    })))
+}
+
 /// POST /api/driver/thunder/send_sms - 迅雷发送短信验证码
 pub async fn thunder_send_sms(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Json(payload): Json<Value>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
 
     let username = payload.get("username")
         .and_then(|v| v.as_str())
@@ -1,7 +1,10 @@
-use std::io::Read;
+use std::io::{Read, Seek};
 use std::path::Path;
-use super::types::ArchiveFormat;
-use super::utils::decode_filename;
+use super::types::{ArchiveFormat, PASSWORD_REQUIRED};
+use super::utils::{decode_filename, entry_target};
+
+/// 7Z 密码错误的标记，换下一个候选密码重试
+const BAD_7Z_PASSWORD: &str = "bad password";
 
 
 /// 解压到本地目录（带进度回调，同步，在 spawn_blocking 中调用）
@@ -12,11 +15,12 @@ pub fn extract_to_local_with_progress(
     inner_path: &str,
     encoding: &str,
     overwrite: bool,
+    passwords: &[String],
     control: &crate::task::TaskControl,
     progress_tx: tokio::sync::mpsc::Sender<(u64, u64, String)>,
 ) -> Result<u64, String> {
     match format {
-        ArchiveFormat::Zip => extract_zip_to_local_progress(archive_path, output_dir, inner_path, encoding, overwrite, control, &progress_tx),
+        ArchiveFormat::Zip => extract_zip_to_local_progress(archive_path, output_dir, inner_path, encoding, overwrite, passwords, control, &progress_tx),
         ArchiveFormat::Tar => {
             let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
             extract_tar_to_local_progress(std::io::BufReader::new(file), output_dir, inner_path, encoding, overwrite, control, &progress_tx)
@@ -27,8 +31,22 @@ pub fn extract_to_local_with_progress(
             extract_tar_to_local_progress(std::io::BufReader::new(gz), output_dir, inner_path, encoding, overwrite, control, &progress_tx)
         }
         ArchiveFormat::TarBz2 => Err("暂不支持 tar.bz2".to_string()),
-        ArchiveFormat::SevenZip => extract_7z_to_local_progress(archive_path, output_dir, inner_path, overwrite, control, &progress_tx),
+        ArchiveFormat::SevenZip => extract_7z_to_local_progress(archive_path, output_dir, inner_path, overwrite, passwords, control, &progress_tx),
+    }
+}
+
+/// 依次尝试候选密码，返回能解开第一个加密成员的密码（没有加密成员时不需要密码）
+fn zip_password<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, passwords: &[String]) -> Result<Option<Vec<u8>>, String> {
+    let encrypted = (0..archive.len()).find(|&i| archive.by_index_raw(i).map(|f| f.encrypted()).unwrap_or(false));
+    let Some(index) = encrypted else { return Ok(None); };
+    for password in passwords {
+        match archive.by_index_decrypt(index, password.as_bytes()) {
+            Ok(_) => return Ok(Some(password.as_bytes().to_vec())),
+            Err(zip::result::ZipError::InvalidPassword) => continue,
+            Err(e) => return Err(e.to_string()),
+        }
     }
+    Err(PASSWORD_REQUIRED.to_string())
 }
 
 /// 解压 ZIP 到本地
@@ -58,8 +76,7 @@ fn extract_zip_to_local(
         };
         if rel.is_empty() { continue; }
         
-        let target = output_dir.join(rel);
-        if !target.starts_with(output_dir) { continue; }
+        let Some(target) = entry_target(output_dir, rel) else { continue; };
         
         if file.is_dir() {
             std::fs::create_dir_all(&target).ok();
@@ -101,8 +118,7 @@ fn extract_tar_to_local<R: Read>(
         };
         if rel.is_empty() { continue; }
         
-        let target = output_dir.join(rel);
-        if !target.starts_with(output_dir) { continue; }
+        let Some(target) = entry_target(output_dir, rel) else { continue; };
         
         if entry.header().entry_type().is_dir() {
             std::fs::create_dir_all(&target).ok();
@@ -147,8 +163,7 @@ fn extract_7z_to_local(
         };
         if rel.is_empty() { return Ok(true); }
         
-        let target = output_dir.join(&rel);
-        if !target.starts_with(output_dir) { return Ok(true); }
+        let Some(target) = entry_target(output_dir, &rel) else { return Ok(true); };
         
         if entry.is_directory() {
             std::fs::create_dir_all(&target).ok();
@@ -169,13 +184,16 @@ fn extract_7z_to_local(
 }
 
 /// 解压 ZIP 到本地（带进度）
+#[allow(clippy::too_many_arguments)]
 fn extract_zip_to_local_progress(
     archive_path: &Path, output_dir: &Path, inner_path: &str, encoding: &str, overwrite: bool,
+    passwords: &[String],
     control: &crate::task::TaskControl,
     progress_tx: &tokio::sync::mpsc::Sender<(u64, u64, String)>,
 ) -> Result<u64, String> {
     let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
     let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file)).map_err(|e| e.to_string())?;
+    let password = zip_password(&mut archive, passwords)?;
     let total = archive.len() as u64;
     let mut count = 0u64;
     let mut last_update = std::time::Instant::now();
@@ -187,7 +205,12 @@ fn extract_zip_to_local_progress(
             if control.is_cancelled() { return Err("任务已取消".to_string()); }
         }
         
-        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        // 未加密的成员会忽略密码
+        let file = match &password {
+            Some(password) => archive.by_index_decrypt(i, password),
+            None => archive.by_index(i),
+        };
+        let mut file = file.map_err(|e| e.to_string())?;
         let path_str = decode_filename(file.name_raw(), encoding);
         
         // 每1秒发送一次进度（try_send 不阻塞）
@@ -205,8 +228,7 @@ fn extract_zip_to_local_progress(
         };
         if rel.is_empty() { continue; }
         
-        let target = output_dir.join(rel);
-        if !target.starts_with(output_dir) { continue; }
+        let Some(target) = entry_target(output_dir, rel) else { continue; };
         
         if file.is_dir() {
             std::fs::create_dir_all(&target).ok();
@@ -261,8 +283,7 @@ fn extract_tar_to_local_progress<R: Read>(
         };
         if rel.is_empty() { continue; }
         
-        let target = output_dir.join(rel);
-        if !target.starts_with(output_dir) { continue; }
+        let Some(target) = entry_target(output_dir, rel) else { continue; };
         
         if entry.header().entry_type().is_dir() {
             std::fs::create_dir_all(&target).ok();
@@ -279,16 +300,43 @@ fn extract_tar_to_local_progress<R: Read>(
     Ok(count)
 }
 
-/// 解压 7Z 到本地（带进度）
+/// 解压 7Z 到本地（带进度），依次尝试候选密码
 fn extract_7z_to_local_progress(
     archive_path: &Path, output_dir: &Path, inner_path: &str, overwrite: bool,
+    passwords: &[String],
+    control: &crate::task::TaskControl,
+    progress_tx: &tokio::sync::mpsc::Sender<(u64, u64, String)>,
+) -> Result<u64, String> {
+    // 没有候选密码时按未加密处理
+    let candidates: Vec<&str> = if passwords.is_empty() {
+        vec![""]
+    } else {
+        passwords.iter().map(String::as_str).collect()
+    };
+    for password in candidates {
+        match extract_7z_with_password(archive_path, output_dir, inner_path, overwrite, password, control, progress_tx) {
+            Err(e) if e == BAD_7Z_PASSWORD => continue,
+            result => return result,
+        }
+    }
+    Err(PASSWORD_REQUIRED.to_string())
+}
+
+/// 用指定密码解压 7Z，密码错误时返回 BAD_7Z_PASSWORD
+fn extract_7z_with_password(
+    archive_path: &Path, output_dir: &Path, inner_path: &str, overwrite: bool,
+    password: &str,
     control: &crate::task::TaskControl,
     progress_tx: &tokio::sync::mpsc::Sender<(u64, u64, String)>,
 ) -> Result<u64, String> {
+    let password_error = |e: sevenz_rust::Error| match e {
+        sevenz_rust::Error::PasswordRequired | sevenz_rust::Error::MaybeBadPassword(_) => BAD_7Z_PASSWORD.to_string(),
+        e => e.to_string(),
+    };
     let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
     let len = file.metadata().map(|m| m.len()).unwrap_or(0);
-    let mut archive = sevenz_rust::SevenZReader::new(std::io::BufReader::new(file), len, sevenz_rust::Password::empty())
-        .map_err(|e| e.to_string())?;
+    let mut archive = sevenz_rust::SevenZReader::new(std::io::BufReader::new(file), len, sevenz_rust::Password::from(password))
+        .map_err(password_error)?;
     
     let mut count = 0u64;
     let mut processed = 0u64;
@@ -320,8 +368,7 @@ fn extract_7z_to_local_progress(
         };
         if rel.is_empty() { return Ok(true); }
         
-        let target = output_dir.join(&rel);
-        if !target.starts_with(output_dir) { return Ok(true); }
+        let Some(target) = entry_target(output_dir, &rel) else { return Ok(true); };
         
         if entry.is_directory() {
             std::fs::create_dir_all(&target).ok();
@@ -329,13 +376,21 @@ fn extract_7z_to_local_progress(
             if !overwrite && target.exists() { return Ok(true); }
             if let Some(p) = target.parent() { std::fs::create_dir_all(p).ok(); }
             if let Ok(mut out) = std::fs::File::create(&target) {
-                if std::io::copy(&mut std::io::BufReader::new(reader), &mut out).is_ok() {
-                    count += 1;
+                match std::io::copy(&mut std::io::BufReader::new(reader), &mut out) {
+                    Ok(_) => count += 1,
+                    Err(e) => {
+                        // 不留下不完整的文件；带密码时读取失败多半是密码错误
+                        drop(out);
+                        std::fs::remove_file(&target).ok();
+                        if !password.is_empty() {
+                            return Err(sevenz_rust::Error::MaybeBadPassword(e));
+                        }
+                    }
                 }
             }
         }
         Ok(true)
-    }).map_err(|e| e.to_string())?;
+    }).map_err(password_error)?;
     
     let _ = progress_tx.try_send((processed, processed, "完成".to_string()));
     if cancelled { return Err("任务已取消".to_string()); }
@@ -18,7 +18,9 @@ use super::extractors::extract_to_local_with_progress;
 use crate::state::AppState;
 use crate::models::UserPermissions;
 use crate::auth::SESSION_COOKIE_NAME;
-use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
+use crate::api::files::get_guest_permissions;
+use yaolist_backend::path_resolver::{calculate_internal_path, get_first_mount, is_sub_path};
+use yaolist_backend::utils::fix_and_clean_path;
 use super::types::*;
 
 
@@ -52,23 +54,6 @@ fn get_archive_format(filename: &str) -> Option<ArchiveFormat> {
     }
 }
 
-/// 根据路径找到最匹配的挂载点
-fn get_storage_by_path<'a>(path: &str, mounts: &'a [MountInfo]) -> Option<&'a MountInfo> {
-    let mut best_match: Option<&MountInfo> = None;
-    let mut best_len = 0;
-    
-    for mount in mounts {
-        let mount_path = fix_and_clean_path(&mount.mount_path);
-        if is_sub_path(&mount_path, path) {
-            if mount_path.len() > best_len {
-                best_match = Some(mount);
-                best_len = mount_path.len();
-            }
-        }
-    }
-    best_match
-}
-
 /// 获取用户权限
 async fn get_user_permissions(state: &AppState, cookies: &Cookies) -> UserPermissions {
     let session_id = match cookies.get(SESSION_COOKIE_NAME) {
@@ -99,9 +84,10 @@ async fn get_user_permissions(state: &AppState, cookies: &Cookies) -> UserPermis
         INNER JOIN sessions s ON u.id = s.user_id
         INNER JOIN user_group_members ugm ON u.id = ugm.user_id
         INNER JOIN user_groups g ON CAST(g.id AS TEXT) = ugm.group_id
-        WHERE s.id = ? AND s.expires_at > datetime('now')"#
+        WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')"#
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .ok()
@@ -127,9 +113,10 @@ async fn get_current_user_id(state: &AppState, cookies: &Cookies) -> Option<Stri
     let result: Option<(String,)> = sqlx::query_as(
         "SELECT u.id FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .ok()?;
@@ -137,31 +124,6 @@ async fn get_current_user_id(state: &AppState, cookies: &Cookies) -> Option<Stri
     result.map(|(id,)| id)
 }
 
-/// 获取游客权限
-async fn get_guest_permissions(state: &AppState) -> UserPermissions {
-    sqlx::query_as::<_, UserPermissions>(
-        r#"SELECT 
-            read_files,
-            create_upload,
-            rename_files,
-            move_files,
-            copy_files,
-            delete_files,
-            allow_direct_link,
-            allow_share,
-            is_admin,
-            show_hidden_files,
-            extract_files
-        FROM user_groups
-        WHERE name = '游客组'"#
-    )
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten()
-    .unwrap_or_default()
-}
-
 /// POST /api/fs/extract - 解压缩文件
 /// 
 /// 这是一个独立的解压缩API，只在用户明确触发时执行
@@ -210,30 +172,15 @@ pub async fn extract_archive(
     }
     let _archive_format = archive_format.unwrap();
     
-    // 从 drivers 表读取挂载点信息
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({ "code": 500, "message": e.to_string() }))
-    ))?;
-    
-    // 构建挂载点列表
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-        })
-    }).collect();
+    let mounts = state.paths.mounts().await
+        .map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "code": 500, "message": e.to_string() }))
+        ))?;
     
     // 找到源文件的挂载点
     debug!("查找源文件挂载点: {}", src_path);
-    let src_mount = get_storage_by_path(&src_path, &mounts).ok_or_else(|| (
+    let src_mount = get_first_mount(&src_path, &mounts).ok_or_else(|| (
         StatusCode::NOT_FOUND,
         Json(json!({ "code": 404, "message": "未找到源文件的挂载点" }))
     ))?;
@@ -241,10 +188,19 @@ pub async fn extract_archive(
     
     // 找到目标目录的挂载点
     debug!("查找目标目录挂载点: {}", dst_path);
-    let dst_mount = get_storage_by_path(&dst_path, &mounts).ok_or_else(|| (
-        StatusCode::NOT_FOUND,
-        Json(json!({ "code": 404, "message": "未找到目标目录的挂载点" }))
-    ))?;
+    let dst_mount = match req.dst_mount_id.as_deref() {
+        // 指定的挂载点必须包含目标目录
+        Some(id) => mounts.iter()
+            .find(|m| m.id == id && is_sub_path(&m.mount_path, &dst_path))
+            .ok_or_else(|| (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "code": 404, "message": "目标挂载点不存在或不包含目标目录" }))
+            ))?,
+        None => get_first_mount(&dst_path, &mounts).ok_or_else(|| (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "code": 404, "message": "未找到目标目录的挂载点" }))
+        ))?,
+    };
     debug!("目标挂载点: {} -> {}", dst_mount.id, dst_mount.mount_path);
     
     // 获取源驱动
@@ -257,12 +213,7 @@ pub async fn extract_archive(
     debug!("源驱动获取成功");
     
     // 计算驱动内部的实际路径（去掉挂载点前缀）
-    let src_mount_path = fix_and_clean_path(&src_mount.mount_path);
-    let src_actual_path = if src_path.len() > src_mount_path.len() {
-        fix_and_clean_path(&src_path[src_mount_path.len()..])
-    } else {
-        "/".to_string()
-    };
+    let src_actual_path = calculate_internal_path(&src_mount.mount_path, &src_path);
     
     // 获取压缩文件信息（使用驱动内部路径）
     let parent_path = src_actual_path.rsplitn(2, '/').nth(1).unwrap_or("/");
@@ -288,19 +239,9 @@ pub async fn extract_archive(
         ))?;
     
     // 计算实际路径
-    let src_mount_path = fix_and_clean_path(&src_mount.mount_path);
-    let src_actual_path = if src_path.len() > src_mount_path.len() {
-        fix_and_clean_path(&src_path[src_mount_path.len()..])
-    } else {
-        "/".to_string()
-    };
+    let src_actual_path = calculate_internal_path(&src_mount.mount_path, &src_path);
     
-    let dst_mount_path = fix_and_clean_path(&dst_mount.mount_path);
-    let dst_actual_path = if dst_path.len() > dst_mount_path.len() {
-        fix_and_clean_path(&dst_path[dst_mount_path.len()..])
-    } else {
-        "/".to_string()
-    };
+    let dst_actual_path = calculate_internal_path(&dst_mount.mount_path, &dst_path);
     
     // 获取当前用户ID（用于WebSocket事件过滤）
     let user_id = get_current_user_id(&state, &cookies).await;
@@ -336,12 +277,13 @@ pub async fn extract_archive(
     let put_into_new_dir = req.put_into_new_dir;
     let overwrite = req.overwrite;
     let force = req.force;
-    let password = req.password.clone();
+    // 先试用户输入的密码，再试管理员为该路径保存的密码
+    let passwords = state.archive_passwords.candidates(&src_path, req.password.as_deref());
     let inner_path = req.inner_path.clone();
     let encoding = req.encoding.clone();
     
     // 在后台执行解压缩
-    tokio::spawn(async move {
+    yaolist_backend::request_id::spawn(async move {
         let result = do_extract(
             &state_clone,
             &src_driver_id,
@@ -353,7 +295,7 @@ pub async fn extract_archive(
             put_into_new_dir,
             overwrite,
             force,
-            &password,
+            &passwords,
             &inner_path,
             &encoding,
             &task_id_clone,
@@ -404,7 +346,7 @@ async fn do_extract(
     put_into_new_dir: bool,
     overwrite: bool,
     force: bool,
-    _password: &Option<String>,
+    passwords: &[String],
     inner_path: &Option<String>,
     encoding: &str,
     task_id: &str,
@@ -434,29 +376,77 @@ async fn do_extract(
     
     let inner_path_str = inner_path.as_ref().map(|s| s.trim_matches('/')).unwrap_or("");
     
+    // 本地存储上的压缩包直接在磁盘上读取，目标为本地存储时直接解压到目标目录，
+    // 省去经由驱动接口的下载与上传
+    let src_local = src_driver.get_local_path(src_path).filter(|p| p.is_file());
+    let dst_local = match dst_driver.get_local_path(&base_dst_path) {
+        Some(p) => {
+            // 通过驱动创建目标目录，只读挂载与维护模式在这里拒绝写入
+            dst_driver.create_dir(&base_dst_path).await
+                .map_err(|e| format!("创建目标目录失败: {}", e))?;
+            Some(p)
+        }
+        None => None,
+    };
+    if src_local.is_some() || dst_local.is_some() {
+        info!("解压使用本地路径: 源={:?}, 目标={:?}", src_local, dst_local);
+    }
+    
     // 统一流程：通过 Driver 接口读取压缩包数据
     // ZIP/7Z 需要 Seek，所以先读取到内存或临时文件
-    do_extract_via_driver(
-        &src_driver, &dst_driver, src_path, &base_dst_path,
-        archive_format, put_into_new_dir, overwrite, force, inner_path_str, encoding,
+    let result = do_extract_via_driver(
+        &src_driver, &dst_driver, src_path, &base_dst_path, src_local, dst_local.clone(),
+        archive_format, put_into_new_dir, overwrite, force, passwords, inner_path_str, encoding,
         file_size, state, task_id, control
-    ).await
+    ).await;
+    
+    // 直接写入磁盘的文件没有经过驱动，列表缓存需要刷新
+    if dst_local.is_some() {
+        state.storage_manager.list_cache().invalidate(dst_driver_id).await;
+    }
+    result
+}
+
+/// 各阶段在总进度中的区间，跳过的阶段不占进度
+struct Stages {
+    download: (f32, f32),
+    extract: (f32, f32),
+    upload: (f32, f32),
+}
+
+impl Stages {
+    fn new(download: bool, upload: bool) -> Self {
+        match (download, upload) {
+            (true, true) => Self { download: (0.0, 30.0), extract: (30.0, 60.0), upload: (60.0, 100.0) },
+            (false, true) => Self { download: (0.0, 0.0), extract: (0.0, 50.0), upload: (50.0, 100.0) },
+            (true, false) => Self { download: (0.0, 50.0), extract: (50.0, 100.0), upload: (100.0, 100.0) },
+            (false, false) => Self { download: (0.0, 0.0), extract: (0.0, 100.0), upload: (100.0, 100.0) },
+        }
+    }
+}
+
+/// 阶段内完成比例对应的总进度
+fn stage_progress((start, end): (f32, f32), fraction: f32) -> f32 {
+    start + fraction.clamp(0.0, 1.0) * (end - start)
 }
 
 /// 
 /// 流程（全部本地缓存，不读入内存）：
-/// 1. 通过 src_driver.open_reader() 流式下载到临时文件
-/// 2. 从临时文件解压到临时目录
-/// 3. 通过 dst_driver.open_writer() 上传解压后的文件
+/// 1. 通过 src_driver.open_reader() 流式下载到临时文件（压缩包在本地存储上时跳过）
+/// 2. 从临时文件解压到临时目录（目标为本地存储时直接解压到目标目录）
+/// 3. 通过 dst_driver.open_writer() 上传解压后的文件（直接解压到目标目录时跳过）
 async fn do_extract_via_driver(
     src_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
     dst_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
     src_path: &str,
     base_dst_path: &str,
+    src_local: Option<std::path::PathBuf>,
+    dst_local: Option<std::path::PathBuf>,
     archive_format: ArchiveFormat,
     put_into_new_dir: bool,
     overwrite: bool,
     force: bool,
+    passwords: &[String],
     inner_path: &str,
     encoding: &str,
     file_size: u64,
@@ -464,15 +454,19 @@ async fn do_extract_via_driver(
     task_id: &str,
     control: std::sync::Arc<crate::task::TaskControl>,
 ) -> Result<u64, String> {
+    let stages = Stages::new(src_local.is_none(), dst_local.is_none());
+    
     // 创建临时目录
     let temp_dir = TempDir::new().map_err(|e| format!("创建临时目录失败: {}", e))?;
-    let temp_archive = temp_dir.path().join("archive");
-    let temp_extract = temp_dir.path().join("out");
+    let temp_archive = src_local.clone().unwrap_or_else(|| temp_dir.path().join("archive"));
+    let temp_extract = dst_local.clone().unwrap_or_else(|| temp_dir.path().join("out"));
     std::fs::create_dir_all(&temp_extract).map_err(|e| format!("创建解压目录失败: {}", e))?;
     
-    // 检查磁盘空间（预留 2.5 倍）
-    let available = fs2::available_space(temp_dir.path()).unwrap_or(0);
-    let required = (file_size as f64 * 2.5) as u64;
+    // 检查磁盘空间（下载压缩包预留 1 倍，解压预留 1.5 倍）
+    let space_dir = if dst_local.is_some() { temp_extract.as_path() } else { temp_dir.path() };
+    let available = fs2::available_space(space_dir).unwrap_or(0);
+    let factor = if src_local.is_some() { 1.5 } else { 2.5 };
+    let required = (file_size as f64 * factor) as u64;
     if available < required && !force {
         return Err(format!("DISK_SPACE_WARNING:磁盘空间可能不足: 可用 {}MB，建议 {}MB。可强制继续。", 
             available / 1024 / 1024, required / 1024 / 1024));
@@ -481,74 +475,76 @@ async fn do_extract_via_driver(
     // 记录开始时间用于ETA计算
     let start_time = std::time::Instant::now();
     
-    // ========== 阶段1: 下载文件到本地 (0-30%) ==========
-    // 先发送初始状态
-    update_extract_progress(state, task_id, 0.0, 0.0, 0, 
-        &format!("下载中... (0/{})", format_size(file_size)), 0, 0).await;
-    
-    let mut reader = src_driver.open_reader(src_path, None).await
-        .map_err(|e| format!("打开压缩包失败: {}", e))?;
-    let mut temp_file = tokio::fs::File::create(&temp_archive).await
-        .map_err(|e| format!("创建临时文件失败: {}", e))?;
-    
-    let mut downloaded = 0u64;
-    let mut buf = vec![0u8; 1024 * 1024]; // 1MB buffer
-    let mut last_update = std::time::Instant::now();
-    let mut last_downloaded = 0u64;
-    
-    loop {
-        // 检查取消
-        if control.is_cancelled() {
-            return Err("任务已取消".to_string());
-        }
-        // 检查暂停
-        while control.is_paused() {
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            if control.is_cancelled() {
-                return Err("任务已取消".to_string());
-            }
-        }
+    // ========== 阶段1: 下载文件到本地 ==========
+    if src_local.is_none() {
+        // 先发送初始状态
+        update_extract_progress(state, task_id, 0.0, 0.0, 0, 
+            &format!("下载中... (0/{})", format_size(file_size)), 0, 0).await;
         
-        let n = reader.read(&mut buf).await.map_err(|e| format!("读取失败: {}", e))?;
-        if n == 0 { break; }
+        let mut reader = src_driver.open_reader(src_path, None).await
+            .map_err(|e| format!("打开压缩包失败: {}", e))?;
+        let mut temp_file = tokio::fs::File::create(&temp_archive).await
+            .map_err(|e| format!("创建临时文件失败: {}", e))?;
         
-        temp_file.write_all(&buf[..n]).await.map_err(|e| format!("写入临时文件失败: {}", e))?;
-        downloaded += n as u64;
+        let mut downloaded = 0u64;
+        let mut buf = vec![0u8; 1024 * 1024]; // 1MB buffer
+        let mut last_update = std::time::Instant::now();
+        let mut last_downloaded = 0u64;
         
-        // 每1秒更新一次进度
-        let now = std::time::Instant::now();
-        if now.duration_since(last_update).as_millis() >= 1000 {
-            let elapsed_ms = now.duration_since(last_update).as_millis() as f64;
-            let bytes_delta = downloaded - last_downloaded;
-            let speed = (bytes_delta as f64 / elapsed_ms) * 1000.0; // bytes/sec
-            
-            let progress = (downloaded as f32 / file_size as f32) * 30.0;
-            let total_elapsed = start_time.elapsed().as_secs_f64();
-            let eta = if progress > 0.0 {
-                ((total_elapsed / progress as f64) * (100.0 - progress as f64)) as u64
-            } else { 0 };
+        loop {
+            // 检查取消
+            if control.is_cancelled() {
+                return Err("任务已取消".to_string());
+            }
+            // 检查暂停
+            while control.is_paused() {
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                if control.is_cancelled() {
+                    return Err("任务已取消".to_string());
+                }
+            }
             
-            // 格式化下载速度显示
-            let speed_str = format_speed(speed);
-            let status = format!("下载中... {} ({}/{})", speed_str, 
-                format_size(downloaded), format_size(file_size));
+            let n = reader.read(&mut buf).await.map_err(|e| format!("读取失败: {}", e))?;
+            if n == 0 { break; }
             
-            update_extract_progress(state, task_id, progress, speed, eta, &status, 0, 0).await;
+            temp_file.write_all(&buf[..n]).await.map_err(|e| format!("写入临时文件失败: {}", e))?;
+            downloaded += n as u64;
             
-            last_update = now;
-            last_downloaded = downloaded;
+            // 每1秒更新一次进度
+            let now = std::time::Instant::now();
+            if now.duration_since(last_update).as_millis() >= 1000 {
+                let elapsed_ms = now.duration_since(last_update).as_millis() as f64;
+                let bytes_delta = downloaded - last_downloaded;
+                let speed = (bytes_delta as f64 / elapsed_ms) * 1000.0; // bytes/sec
+                
+                let progress = stage_progress(stages.download, downloaded as f32 / file_size as f32);
+                let total_elapsed = start_time.elapsed().as_secs_f64();
+                let eta = if progress > 0.0 {
+                    ((total_elapsed / progress as f64) * (100.0 - progress as f64)) as u64
+                } else { 0 };
+                
+                // 格式化下载速度显示
+                let speed_str = format_speed(speed);
+                let status = format!("下载中... {} ({}/{})", speed_str, 
+                    format_size(downloaded), format_size(file_size));
+                
+                update_extract_progress(state, task_id, progress, speed, eta, &status, 0, 0).await;
+                
+                last_update = now;
+                last_downloaded = downloaded;
+            }
         }
+        temp_file.shutdown().await.ok();
+        
+        // 下载完成状态
+        let download_elapsed = start_time.elapsed().as_secs_f64();
+        update_extract_progress(state, task_id, stages.download.1, 0.0, 0, 
+            &format!("下载完成 ({:.1}s)", download_elapsed), 0, 0).await;
     }
-    temp_file.shutdown().await.ok();
     
-    // 下载完成状态
-    let download_elapsed = start_time.elapsed().as_secs_f64();
-    update_extract_progress(state, task_id, 30.0, 0.0, 0, 
-        &format!("下载完成 ({:.1}s)", download_elapsed), 0, 0).await;
-    
-    // ========== 阶段2: 解压缩 (30-60%) ==========
+    // ========== 阶段2: 解压缩 ==========
     let extract_start = std::time::Instant::now();
-    update_extract_progress(state, task_id, 30.0, 0.0, 0, "解压缩中...", 0, 0).await;
+    update_extract_progress(state, task_id, stages.extract.0, 0.0, 0, "解压缩中...", 0, 0).await;
     
     // 使用 channel 传递解压进度（非阻塞）
     let (progress_tx, mut progress_rx) = tokio::sync::mpsc::channel::<(u64, u64, String)>(1000);
@@ -559,11 +555,12 @@ async fn do_extract_via_driver(
     let inner = inner_path.to_string();
     let enc = encoding.to_string();
     let ow = overwrite;
+    let pws = passwords.to_vec();
     let ctrl = control.clone();
     
     // 启动解压任务
     let extract_handle = tokio::task::spawn_blocking(move || {
-        extract_to_local_with_progress(&arc_path, &ext_path, fmt, &inner, &enc, ow, &ctrl, progress_tx)
+        extract_to_local_with_progress(&arc_path, &ext_path, fmt, &inner, &enc, ow, &pws, &ctrl, progress_tx)
     });
     
     // 异步接收进度更新（Core 层处理进度）
@@ -571,10 +568,11 @@ async fn do_extract_via_driver(
     let task_manager = state.task_manager.clone();
     let task_id_for_progress = task_id.to_string();
     let start_time_clone = start_time;
-    let progress_handle = tokio::spawn(async move {
+    let extract_stage = stages.extract;
+    let progress_handle = yaolist_backend::request_id::spawn(async move {
         while let Some((processed, total, _current_file)) = progress_rx.recv().await {
             let extract_progress = if total > 0 { processed as f32 / total as f32 } else { 0.0 };
-            let total_progress = 30.0 + extract_progress * 30.0; // 30-60%
+            let total_progress = stage_progress(extract_stage, extract_progress);
             
             let total_elapsed = start_time_clone.elapsed().as_secs_f64();
             let eta = if total_progress > 0.0 {
@@ -595,15 +593,23 @@ async fn do_extract_via_driver(
     
     let file_count = extract_result?;
     let extract_elapsed = extract_start.elapsed().as_secs_f64();
+    
+    // 直接解压到目标目录，无需上传
+    if dst_local.is_some() {
+        let status = format!("解压完成: {} 个文件 ({:.1}s)", file_count, extract_elapsed);
+        update_extract_progress(state, task_id, 100.0, 0.0, 0, &status, file_count, file_count).await;
+        return Ok(file_count);
+    }
+    
     let total_elapsed = start_time.elapsed().as_secs_f64();
     let eta = if total_elapsed > 0.0 {
-        ((total_elapsed / 60.0) * 40.0) as u64
+        ((total_elapsed / stages.extract.1 as f64) * (100.0 - stages.extract.1 as f64)) as u64
     } else { 0 };
     
     let status = format!("解压完成: {} 个文件 ({:.1}s)", file_count, extract_elapsed);
-    update_extract_progress(state, task_id, 60.0, 0.0, eta, &status, file_count, file_count).await;
+    update_extract_progress(state, task_id, stages.extract.1, 0.0, eta, &status, file_count, file_count).await;
     
-    // ========== 阶段3: 上传到目标 (60-100%) ==========
+    // ========== 阶段3: 上传到目标 ==========
     
     if put_into_new_dir {
         dst_driver.create_dir(base_dst_path).await.ok();
@@ -660,7 +666,7 @@ async fn do_extract_via_driver(
             let speed = (bytes_delta as f64 / elapsed_ms) * 1000.0;
             
             let upload_progress = uploaded_count as f32 / total_files_to_upload as f32;
-            let total_progress = 60.0 + upload_progress * 40.0;
+            let total_progress = stage_progress(stages.upload, upload_progress);
             
             let total_elapsed = start_time.elapsed().as_secs_f64();
             let eta = if total_progress > 0.0 {
@@ -7,7 +7,10 @@ pub struct ExtractRequest {
     pub src_path: String,
     /// 目标目录路径
     pub dst_path: String,
-    /// 压缩包密码（可选）
+    /// 目标挂载点ID（可选，多个挂载点共用目标路径时指定写入哪一个，为空时按路径匹配）
+    #[serde(default)]
+    pub dst_mount_id: Option<String>,
+    /// 压缩包密码（可选，为空或不正确时尝试管理员为该路径保存的密码）
     #[serde(default)]
     pub password: Option<String>,
     /// 压缩包内部路径（可选，用于只解压部分内容）
@@ -27,6 +30,9 @@ pub struct ExtractRequest {
     pub force: bool,
 }
 
+/// 所有候选密码都无法解开压缩包时的任务错误（前缀供前端识别后提示输入密码）
+pub const PASSWORD_REQUIRED: &str = "ARCHIVE_PASSWORD_REQUIRED:压缩包已加密，请输入正确的密码";
+
 fn default_encoding() -> String {
     "utf-8".to_string()
 }
@@ -38,11 +44,6 @@ pub struct ExtractResponse {
     pub message: String,
 }
 
-pub(crate) struct MountInfo {
-    pub id: String,
-    pub mount_path: String,
-}
-
 /// 支持的压缩格式
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ArchiveFormat {
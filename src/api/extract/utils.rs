@@ -1,3 +1,4 @@
+use std::path::{Component, Path, PathBuf};
 use super::types::ArchiveFormat;
 
 /// 根据文件名获取压缩格式
@@ -74,3 +75,10 @@ pub fn decode_filename(raw_name: &[u8], encoding: &str) -> String {
     let (decoded, _, _) = decoder.decode(raw_name);
     decoded.to_string()
 }
+
+/// 压缩包条目在输出目录中的位置；含 `..` 或绝对路径的条目返回 None，防止写到输出目录之外
+pub fn entry_target(output_dir: &Path, rel: &str) -> Option<PathBuf> {
+    let rel = Path::new(rel);
+    let safe = rel.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    safe.then(|| output_dir.join(rel))
+}
@@ -0,0 +1,134 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use yaolist_backend::feed::{Feed, DEFAULT_LINK_DAYS, DEFAULT_MAX_ITEMS};
+use yaolist_backend::utils::fix_and_clean_path;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+#[derive(Debug, Deserialize)]
+pub struct SaveFeedRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    /// 订阅源列出的目录
+    pub path: String,
+    /// 为空时使用目录名
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub max_items: Option<u32>,
+    /// 包含子目录中的文件
+    #[serde(default)]
+    pub recursive: bool,
+    /// 文件链接的有效天数
+    pub link_days: Option<u32>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedIdRequest {
+    pub id: String,
+}
+
+/// GET /api/admin/feeds - 获取目录订阅源列表
+pub async fn list_feeds(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": state.feeds.list()
+    })))
+}
+
+/// POST /api/admin/feeds - 新建或更新目录订阅源（订阅地址为 /feed/:id?format=rss|atom|json）
+pub async fn save_feed(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveFeedRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let existing = req.id.as_deref().filter(|id| !id.is_empty()).map(|id| state.feeds.get(id));
+    if let Some(None) = existing {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "订阅源不存在"}))));
+    }
+    let existing = existing.flatten();
+
+    let feed = Feed {
+        id: existing.as_ref().map(|f| f.id.clone()).unwrap_or_else(|| Uuid::new_v4().simple().to_string()),
+        path: fix_and_clean_path(&req.path),
+        title: req.title.trim().to_string(),
+        description: req.description.trim().to_string(),
+        max_items: req.max_items.unwrap_or(DEFAULT_MAX_ITEMS),
+        recursive: req.recursive,
+        link_days: req.link_days.unwrap_or(DEFAULT_LINK_DAYS),
+        enabled: req.enabled.unwrap_or(true),
+        created_at: existing.as_ref().map(|f| f.created_at).unwrap_or_else(Utc::now),
+    };
+    feed.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("订阅源无效: {}", e)}))))?;
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO feeds (id, path, title, description, max_items, recursive, link_days, enabled, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&feed.id)
+    .bind(&feed.path)
+    .bind(&feed.title)
+    .bind(&feed.description)
+    .bind(feed.max_items as i64)
+    .bind(feed.recursive)
+    .bind(feed.link_days as i64)
+    .bind(feed.enabled)
+    .bind(feed.created_at.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    state.feeds.upsert(feed.clone())
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("订阅源无效: {}", e)}))))?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": feed
+    })))
+}
+
+/// POST /api/admin/feeds/delete - 删除目录订阅源（已发出的文件链接随之失效）
+pub async fn delete_feed(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<FeedIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    sqlx::query("DELETE FROM feeds WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    if !state.feeds.remove(&req.id) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "订阅源不存在"}))));
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
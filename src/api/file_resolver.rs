@@ -1,16 +1,31 @@
 //! 文件路径解析与驱动选择器
 //! 
 //! 提供通用的文件操作辅助功能：
-//! - 路径解析（用户根路径+路径穿越检查）
+//! - 路径解析（用户根路径+路径穿越检查、挂载点匹配，共用 `yaolist_backend::path_resolver`）
 //! - 驱动查找（多驱动合并支持）
 //! - 负载均衡选择（302优先+轮询）
+//! - 聚合挂载写入位置（按写入策略选择）
+//! - 负载均衡组上传放置（按剩余空间/使用率选择成员，并记录供读取时使用）
+//! - 按路径授权（用户组路径授权覆盖用户组权限）
+//! - 可见挂载点（继承自用户组默认设置，可按用户覆盖）
+//! - 存储可见性（限定用户组的存储对其他用户不存在）
+//! - 站内分享（分享给用户的项目映射到“/Shared with me”下）
 
 use std::collections::HashMap;
 use tokio::sync::RwLock;
 use once_cell::sync::Lazy;
-use serde_json::Value;
 use tower_cookies::Cookies;
 use yaolist_backend::utils::fix_and_clean_path;
+use yaolist_backend::load_balance::{choose_upload_member, LoadBalanceMode};
+use yaolist_backend::storage::aggregate::{choose_write_target, WriteCandidate, WritePolicy};
+use yaolist_backend::workspace;
+use yaolist_backend::access::{self, Capabilities, Capability, PathGrant};
+use yaolist_backend::group_defaults::{self, MemberSettings};
+use yaolist_backend::mount_visibility::{self, MountVisibility};
+use yaolist_backend::internal_share::{self, Resolved, SharedItem};
+pub use yaolist_backend::path_resolver::{
+    calculate_internal_path, get_first_mount, get_matching_mounts, is_sub_path, join_user_path, MountInfo,
+};
 
 use crate::state::AppState;
 use crate::models::UserPermissions;
@@ -28,14 +43,6 @@ async fn get_next_counter_for_path(path: &str) -> u64 {
     current
 }
 
-/// 挂载点信息
-#[derive(Debug, Clone)]
-pub struct MountInfo {
-    pub id: String,
-    pub mount_path: String,
-    pub order: i32,
-}
-
 /// 驱动匹配结果（包含驱动和302能力）
 #[derive(Debug, Clone)]
 pub struct DriverMatch {
@@ -50,6 +57,16 @@ pub struct UserContext {
     pub permissions: UserPermissions,
     pub root_path: String,
     pub is_guest: bool,
+    /// 用户组的路径授权
+    pub grants: Vec<PathGrant>,
+    /// 继承自用户组并应用用户覆盖的设置（根路径、分享、配额、限速、可见挂载点）
+    pub settings: MemberSettings,
+    /// 对该用户隐藏的存储（驱动ID）
+    pub hidden_drivers: Vec<String>,
+    /// 各挂载路径对该用户是否可见
+    pub mounts: MountVisibility,
+    /// 站内分享给该用户的项目（显示在“/Shared with me”下）
+    pub shared: Vec<SharedItem>,
 }
 
 impl Default for UserContext {
@@ -58,10 +75,119 @@ impl Default for UserContext {
             permissions: UserPermissions::default(),
             root_path: "/".to_string(),
             is_guest: true,
+            grants: Vec::new(),
+            settings: MemberSettings::default(),
+            hidden_drivers: Vec::new(),
+            mounts: MountVisibility::default(),
+            shared: Vec::new(),
         }
     }
 }
 
+impl UserContext {
+    /// 能否在完整路径 `path` 上执行操作（路径授权优先于用户组权限，不可见的挂载点中不能操作，管理员不受限制）
+    /// 站内分享给用户的项目按分享的权限放行
+    pub fn can(&self, capability: Capability, path: &str) -> bool {
+        self.permissions.is_admin
+            || internal_share::allows(&self.shared, capability, path)
+            || (self.settings.path_visible(path, capability == Capability::Read)
+                && self.mounts.path_visible(path)
+                && access::allows(&Capabilities::from(&self.permissions), &self.grants, capability, path))
+    }
+
+    /// 挂载点是否对用户可见（管理员可见全部挂载点）
+    pub fn mount_visible(&self, mount_path: &str) -> bool {
+        self.permissions.is_admin || self.settings.mount_visible(mount_path)
+    }
+
+    /// 存储是否对用户可见：挂载路径可见且存储未限定给其他用户组
+    /// 提供站内分享项目的存储对接收者可见（访问仍按分享的权限检查）
+    pub fn mount_allowed(&self, mount: &MountInfo) -> bool {
+        (self.mount_visible(&mount.mount_path) && !self.hidden_drivers.contains(&mount.id))
+            || self.shared.iter().any(|s| is_sub_path(&mount.mount_path, &s.path) || is_sub_path(&s.path, &mount.mount_path))
+    }
+
+    /// 将用户请求路径解析为完整路径：“/Shared with me/<名称>”下的路径映射到被分享的路径，其他路径与用户根路径结合
+    pub fn join_path(&self, req_path: &str) -> Result<String, String> {
+        match internal_share::resolve(&self.shared, req_path) {
+            Resolved::NotShared => join_user_path(&self.root_path, req_path),
+            Resolved::Path(path) if workspace::current().path_visible(&path) => Ok(path),
+            Resolved::Path(_) => Err("路径越权".to_string()),
+            Resolved::Root => Err("“分享给我的”是虚拟目录，不能在其中操作".to_string()),
+            Resolved::Missing => Err("分享不存在或已被取消".to_string()),
+        }
+    }
+}
+
+/// 加载对用户隐藏的存储，`user_id` 为 None 时按游客组加载（管理员不调用）
+pub async fn load_mount_visibility(state: &AppState, user_id: Option<&str>) -> (Vec<String>, MountVisibility) {
+    let result: Result<_, sqlx::Error> = async {
+        let rules = mount_visibility::load_rules(&state.db).await?;
+        if rules.is_empty() {
+            return Ok((Vec::new(), MountVisibility::default()));
+        }
+        let groups = match user_id {
+            Some(id) => mount_visibility::user_groups(&state.db, id).await?,
+            None => mount_visibility::guest_groups(&state.db).await?,
+        };
+        let hidden = mount_visibility::hidden_drivers(&rules, &groups);
+        if hidden.is_empty() {
+            return Ok((hidden, MountVisibility::default()));
+        }
+        let mounts = get_all_mounts(state).await?;
+        let visibility = MountVisibility::new(mounts.iter().map(|m| (m.id.as_str(), m.mount_path.as_str())), &hidden);
+        Ok((hidden, visibility))
+    }.await;
+    result.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load mount visibility: {}", e);
+        (Vec::new(), MountVisibility::default())
+    })
+}
+
+/// 加载用户的默认设置，`user_id` 为 None 时加载游客组的
+pub async fn load_member_settings(state: &AppState, user_id: Option<&str>) -> MemberSettings {
+    let result = match user_id {
+        Some(id) => group_defaults::load_user(&state.db, id).await,
+        None => group_defaults::load_guest(&state.db).await,
+    };
+    result.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load member settings: {}", e);
+        MemberSettings::default()
+    })
+}
+
+/// 加载用户的路径授权，`user_id` 为 None 时加载游客组的
+pub async fn load_path_grants(state: &AppState, user_id: Option<&str>) -> Vec<PathGrant> {
+    let result = match user_id {
+        Some(id) => access::load_user_grants(&state.db, id).await,
+        None => {
+            let group_id: Option<String> = sqlx::query_scalar(
+                "SELECT CAST(id AS TEXT) FROM user_groups WHERE name = '游客组'"
+            )
+            .fetch_optional(&state.db)
+            .await
+            .ok()
+            .flatten();
+            match group_id {
+                Some(id) => access::load_group_grants(&state.db, &id).await,
+                None => Ok(Vec::new()),
+            }
+        }
+    };
+    result.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load path grants: {}", e);
+        Vec::new()
+    })
+}
+
+/// 加载站内分享给用户的项目
+pub async fn load_shared_items(state: &AppState, user_id: &str) -> Vec<SharedItem> {
+    internal_share::load_for_user(&state.db, user_id).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load internal shares: {}", e);
+        Vec::new()
+    })
+}
+
 /// 获取用户上下文（权限+根路径）
 pub async fn get_user_context(state: &AppState, cookies: &Cookies) -> UserContext {
     let session_id = match cookies.get(SESSION_COOKIE_NAME) {
@@ -70,17 +196,24 @@ pub async fn get_user_context(state: &AppState, cookies: &Cookies) -> UserContex
             // 未登录时使用游客权限和根路径
             let guest_perms = get_guest_permissions(state).await;
             let guest_root = get_guest_root_path(state).await;
+            let (hidden_drivers, mounts) = load_mount_visibility(state, None).await;
             return UserContext {
                 permissions: guest_perms,
                 root_path: guest_root,
                 is_guest: true,
+                grants: load_path_grants(state, None).await,
+                settings: load_member_settings(state, None).await,
+                hidden_drivers,
+                mounts,
+                shared: Vec::new(),
             };
         }
     };
     
-    // 查询用户权限、用户根路径和用户组根路径
-    let result = sqlx::query_as::<_, (bool, bool, bool, bool, bool, bool, bool, bool, bool, bool, bool, Option<String>, Option<String>)>(
+    // 查询用户权限，根路径与分享权限取自用户组默认设置与用户覆盖
+    let result = sqlx::query_as::<_, (String, bool, bool, bool, bool, bool, bool, bool, bool, bool, bool)>(
         r#"SELECT 
+            u.id as user_id,
             MAX(g.read_files) as read_files,
             MAX(g.create_upload) as create_upload,
             MAX(g.rename_files) as rename_files,
@@ -88,34 +221,35 @@ pub async fn get_user_context(state: &AppState, cookies: &Cookies) -> UserContex
             MAX(g.copy_files) as copy_files,
             MAX(g.delete_files) as delete_files,
             MAX(g.allow_direct_link) as allow_direct_link,
-            MAX(g.allow_share) as allow_share,
             MAX(g.is_admin) as is_admin,
             MAX(g.show_hidden_files) as show_hidden_files,
-            MAX(g.extract_files) as extract_files,
-            u.root_path as user_root_path,
-            MAX(g.root_path) as group_root_path
+            MAX(g.extract_files) as extract_files
         FROM users u
         INNER JOIN sessions s ON u.id = s.user_id
         INNER JOIN user_group_members ugm ON u.id = ugm.user_id
         INNER JOIN user_groups g ON CAST(g.id AS TEXT) = ugm.group_id
-        WHERE s.id = ? AND s.expires_at > datetime('now')
+        WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')
         GROUP BY u.id"#
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .ok()
     .flatten();
     
     match result {
-        Some((read_files, create_upload, rename_files, move_files, copy_files, 
-              delete_files, allow_direct_link, allow_share, is_admin, show_hidden_files, 
-              extract_files, user_root_path, group_root_path)) => {
+        Some((user_id, read_files, create_upload, rename_files, move_files, copy_files, 
+              delete_files, allow_direct_link, is_admin, show_hidden_files, 
+              extract_files)) => {
             // 优先使用用户根路径，如果没有则使用用户组根路径
-            let root_path = user_root_path
-                .filter(|p| !p.is_empty() && p != "/")
-                .or(group_root_path)
-                .unwrap_or_else(|| "/".to_string());
+            let settings = load_member_settings(state, Some(&user_id)).await;
+            let root_path = settings.root_path.clone();
+            let (hidden_drivers, mounts) = if is_admin {
+                Default::default()
+            } else {
+                load_mount_visibility(state, Some(&user_id)).await
+            };
             
             UserContext {
                 permissions: UserPermissions {
@@ -126,23 +260,34 @@ pub async fn get_user_context(state: &AppState, cookies: &Cookies) -> UserContex
                     copy_files,
                     delete_files,
                     allow_direct_link,
-                    allow_share,
+                    allow_share: settings.allow_share,
                     is_admin,
                     show_hidden_files,
                     extract_files,
                 },
                 root_path,
                 is_guest: false,
+                grants: load_path_grants(state, Some(&user_id)).await,
+                settings,
+                hidden_drivers,
+                mounts,
+                shared: load_shared_items(state, &user_id).await,
             }
         },
         None => {
             // session无效，使用游客权限
             let guest_perms = get_guest_permissions(state).await;
             let guest_root = get_guest_root_path(state).await;
+            let (hidden_drivers, mounts) = load_mount_visibility(state, None).await;
             UserContext {
                 permissions: guest_perms,
                 root_path: guest_root,
                 is_guest: true,
+                grants: load_path_grants(state, None).await,
+                settings: load_member_settings(state, None).await,
+                hidden_drivers,
+                mounts,
+                shared: Vec::new(),
             }
         },
     }
@@ -214,96 +359,21 @@ async fn get_guest_root_path(state: &AppState) -> String {
     root
 }
 
-/// 将用户请求路径与用户根路径结合（防止路径穿越）
-pub fn join_user_path(user_root: &str, req_path: &str) -> Result<String, &'static str> {
-    let user_root = fix_and_clean_path(user_root);
-    let req_path = fix_and_clean_path(req_path);
-    
-    // 如果用户根路径是 /，直接返回请求路径
-    if user_root == "/" {
-        return Ok(req_path);
-    }
-    
-    // 组合路径
-    let combined = if req_path == "/" {
-        user_root.clone()
-    } else {
-        format!("{}{}", user_root.trim_end_matches('/'), req_path)
-    };
-    
-    let combined = fix_and_clean_path(&combined);
-    
-    // 确保组合后的路径仍在用户根路径内
-    if !combined.starts_with(&user_root) && combined != user_root {
-        return Err("路径越权");
-    }
-    
-    Ok(combined)
-}
-
-/// 从数据库获取所有启用的挂载点
+/// 从数据库获取所有启用的挂载点（仅当前工作区可见的部分）
 pub async fn get_all_mounts(state: &AppState) -> Result<Vec<MountInfo>, sqlx::Error> {
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await?;
-    
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        let order = config.get("order").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order,
-        })
-    }).collect();
-    
-    Ok(mounts)
-}
-
-/// 检查路径是否是子路径
-fn is_sub_path(parent: &str, child: &str) -> bool {
-    let parent = fix_and_clean_path(parent);
-    let child = fix_and_clean_path(child);
-    
-    if parent == "/" {
-        return true;
-    }
-    
-    child == parent || child.starts_with(&format!("{}/", parent))
+    state.paths.mounts().await
 }
 
-/// 获取所有匹配该路径的驱动（用于别名/负载均衡）
-/// 只返回相同挂载路径的驱动（最长匹配）
-pub fn get_matching_mounts<'a>(path: &str, mounts: &'a [MountInfo]) -> Vec<&'a MountInfo> {
-    let path = fix_and_clean_path(path);
-    let mut best_len = 0;
-    
-    // 先找到最长匹配长度
-    for mount in mounts {
-        let mount_path = fix_and_clean_path(&mount.mount_path);
-        if is_sub_path(&mount_path, &path) && mount_path.len() > best_len {
-            best_len = mount_path.len();
-        }
-    }
-    
-    // 返回所有具有最长匹配长度的驱动（按order排序）
-    let mut result: Vec<&MountInfo> = mounts.iter()
-        .filter(|mount| {
-            let mount_path = fix_and_clean_path(&mount.mount_path);
-            is_sub_path(&mount_path, &path) && mount_path.len() == best_len
-        })
-        .collect();
-    
-    result.sort_by_key(|m| m.order);
-    result
+/// 获取对用户可见的挂载点
+pub async fn get_user_mounts(state: &AppState, user_ctx: &UserContext) -> Result<Vec<MountInfo>, sqlx::Error> {
+    let mut mounts = get_all_mounts(state).await?;
+    mounts.retain(|m| user_ctx.mount_allowed(m));
+    Ok(mounts)
 }
 
-/// 获取第一个匹配的驱动（写操作用）
-pub fn get_first_mount<'a>(path: &str, mounts: &'a [MountInfo]) -> Option<&'a MountInfo> {
-    get_matching_mounts(path, mounts).into_iter().next()
+/// 获取驱动的挂载路径
+pub async fn get_mount_path(state: &AppState, driver_id: &str) -> Option<String> {
+    state.paths.mount_path(driver_id).await
 }
 
 /// 查找包含指定文件的所有驱动（带302能力标记）
@@ -322,11 +392,7 @@ pub async fn find_file_drivers(
     
     for mount in matching_mounts {
         let mount_path = fix_and_clean_path(&mount.mount_path);
-        let actual_parent = if parent_path.len() > mount_path.len() {
-            fix_and_clean_path(&parent_path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let actual_parent = calculate_internal_path(&mount_path, parent_path);
         
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
             // 检查文件是否存在
@@ -353,6 +419,188 @@ pub async fn find_file_drivers(
     results
 }
 
+/// 聚合挂载：包含该路径（文件或目录）的所有挂载点，按order排序
+/// 只有一个挂载点或路径就是挂载点本身时直接返回，不检查是否存在
+pub async fn find_item_mounts(state: &AppState, path: &str, mounts: &[MountInfo]) -> Vec<MountInfo> {
+    let matching = get_matching_mounts(path, mounts);
+    let is_mount_root = matching.first()
+        .is_some_and(|m| fix_and_clean_path(&m.mount_path) == fix_and_clean_path(path));
+    if matching.len() <= 1 || is_mount_root {
+        return matching.into_iter().cloned().collect();
+    }
+    find_file_drivers(state, path, mounts).await
+        .into_iter()
+        .map(|m| m.mount)
+        .collect()
+}
+
+/// 聚合挂载：批量操作的源挂载点（包含第一个所选项的挂载点）
+pub async fn find_source_mount(
+    state: &AppState,
+    src_dir: &str,
+    names: &[String],
+    mounts: &[MountInfo],
+) -> Option<MountInfo> {
+    if let Some(name) = names.first() {
+        let path = format!("{}/{}", src_dir.trim_end_matches('/'), name);
+        if let Some(mount) = find_item_mounts(state, &path, mounts).await.into_iter().next() {
+            return Some(mount);
+        }
+    }
+    get_first_mount(src_dir, mounts).cloned()
+}
+
+/// 聚合挂载：为新建的文件/目录选择写入的挂载点
+///
+/// 已存在时写回所在的挂载点（覆盖），否则在父目录已存在的挂载点中按组的写入策略选择，
+/// 避免在其他账号中重复创建目录结构。
+pub async fn select_write_mount(
+    state: &AppState,
+    path: &str,
+    mounts: &[MountInfo],
+    size: Option<u64>,
+) -> Option<MountInfo> {
+    let matching = get_matching_mounts(path, mounts);
+    let first = (*matching.first()?).clone();
+    let path = fix_and_clean_path(path);
+    if matching.len() == 1 || path == fix_and_clean_path(&first.mount_path) {
+        return Some(first);
+    }
+    
+    // 负载均衡组记录的上传成员（分片上传的后续分片、覆盖时写回同一成员）
+    if let Some((placed, _)) = get_upload_placement(state, &path).await {
+        if let Some(mount) = matching.iter().find(|m| m.id == placed) {
+            return Some((*mount).clone());
+        }
+    }
+    
+    if let Some(existing) = find_file_drivers(state, &path, mounts).await.into_iter().next() {
+        return Some(existing.mount);
+    }
+    
+    let parent = match path.rsplit_once('/') {
+        Some(("", _)) | None => "/",
+        Some((parent, _)) => parent,
+    };
+    let mut candidates = find_item_mounts(state, parent, mounts).await;
+    if candidates.is_empty() {
+        candidates = matching.into_iter().cloned().collect();
+    }
+    
+    // 跳过只读挂载；全部只读时保留，由驱动返回明确的错误
+    let mut writable = Vec::with_capacity(candidates.len());
+    for mount in &candidates {
+        if !state.storage_manager.is_read_only(&mount.id).await {
+            writable.push(mount.clone());
+        }
+    }
+    if !writable.is_empty() {
+        candidates = writable;
+    }
+    
+    // 候选属于启用了上传放置策略的负载均衡组时，只在组成员中按组的策略选择
+    let candidate_ids: Vec<&str> = candidates.iter().map(|m| m.id.as_str()).collect();
+    let group = state.load_balance.upload_group_for(&candidate_ids).await;
+    if let Some(group) = &group {
+        candidates.retain(|m| group.drivers.iter().any(|d| d.driver_id == m.id));
+        candidates.sort_by_key(|m| group.drivers.iter().position(|d| d.driver_id == m.id));
+    }
+    
+    let policy = state.storage_manager.get_driver_spec(&first.id).await
+        .map(|spec| WritePolicy::from_config(&spec.config))
+        .unwrap_or_default();
+    let need_space = group.is_some() || policy == WritePolicy::MostFreeSpace || size.is_some();
+    let mut loaded = Vec::with_capacity(candidates.len());
+    let mut infos = Vec::with_capacity(candidates.len());
+    for mount in candidates {
+        let Some(driver) = state.storage_manager.get_driver(&mount.id).await else {
+            continue;
+        };
+        let space = if need_space {
+            driver.get_space_info().await.ok().flatten()
+        } else {
+            None
+        };
+        infos.push(WriteCandidate {
+            max_file_size: driver.capabilities().max_file_size,
+            space,
+        });
+        loaded.push(mount);
+    }
+    
+    let Some(group) = group else {
+        let index = choose_write_target(policy, &infos, size)?;
+        tracing::debug!("聚合挂载写入: path={}, policy={:?}, candidates={}, 选择驱动={}",
+            path, policy, loaded.len(), loaded[index].id);
+        return loaded.into_iter().nth(index);
+    };
+    
+    let index = choose_upload_member(group.upload_policy, group.usage_threshold, &infos, size)?;
+    let chosen = loaded.into_iter().nth(index)?;
+    tracing::debug!("负载均衡组上传: group={}, path={}, policy={:?}, 选择驱动={}",
+        group.name, path, group.upload_policy, chosen.id);
+    record_upload_placement(state, &path, &chosen.id, &group.name).await;
+    Some(chosen)
+}
+
+/// 查询负载均衡组上传时记录的成员，返回 (driver_id, group_name)
+pub async fn get_upload_placement(state: &AppState, path: &str) -> Option<(String, String)> {
+    sqlx::query_as("SELECT driver_id, group_name FROM upload_placements WHERE path = ?")
+        .bind(fix_and_clean_path(path))
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+}
+
+async fn record_upload_placement(state: &AppState, path: &str, driver_id: &str, group_name: &str) {
+    let result = sqlx::query(
+        "INSERT OR REPLACE INTO upload_placements (path, driver_id, group_name, created_at) VALUES (?, ?, ?, ?)"
+    )
+    .bind(fix_and_clean_path(path))
+    .bind(driver_id)
+    .bind(group_name)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("Failed to record upload placement for {}: {}", path, e);
+    }
+}
+
+/// 删除路径（及其子路径）的上传放置记录
+pub async fn forget_upload_placement(state: &AppState, path: &str) {
+    let path = fix_and_clean_path(path);
+    let _ = sqlx::query("DELETE FROM upload_placements WHERE path = ? OR path LIKE ?")
+        .bind(&path)
+        .bind(format!("{}/%", path.trim_end_matches('/')))
+        .execute(&state.db)
+        .await;
+}
+
+/// 按上传放置记录直接定位文件所在成员，只列出该成员的父目录；记录失效时删除
+async fn find_placed_driver(state: &AppState, path: &str, mounts: &[MountInfo]) -> Option<DriverMatch> {
+    let (driver_id, _) = get_upload_placement(state, path).await?;
+    let path = fix_and_clean_path(path);
+    let found = async {
+        let mount = get_matching_mounts(&path, mounts).into_iter().find(|m| m.id == driver_id)?;
+        let driver = state.storage_manager.get_driver(&mount.id).await?;
+        let actual_path = calculate_internal_path(&mount.mount_path, &path);
+        let (parent, name) = actual_path.rsplit_once('/')?;
+        let parent = if parent.is_empty() { "/" } else { parent };
+        let files = driver.list(parent).await.ok()?;
+        files.iter().any(|f| f.name == name).then(|| DriverMatch {
+            mount: mount.clone(),
+            can_direct_link: driver.capabilities().can_direct_link,
+            actual_path,
+        })
+    }.await;
+    if found.is_none() {
+        forget_upload_placement(state, &path).await;
+    }
+    found
+}
+
 /// 多源聚合：默认驱动选择（302优先+轮询）
 /// 
 /// 策略：
@@ -389,28 +637,14 @@ pub fn select_driver_default_with_counter(drivers: &[DriverMatch], counter: u64)
     Some(candidates[index])
 }
 
-/// 计算驱动内部的实际路径
-pub fn calculate_internal_path(mount_path: &str, full_path: &str) -> String {
-    let mount_path = fix_and_clean_path(mount_path);
-    let full_path = fix_and_clean_path(full_path);
-    
-    if mount_path == "/" {
-        return full_path;
-    }
-    
-    if full_path.len() > mount_path.len() {
-        fix_and_clean_path(&full_path[mount_path.len()..])
-    } else {
-        "/".to_string()
-    }
-}
-
 /// 下载时选中的驱动信息
 #[derive(Debug, Clone)]
 pub struct SelectedDriver {
     pub driver_id: String,
     pub internal_path: String,
     pub can_direct_link: bool,
+    /// 失败时可重试下一个成员（故障转移模式的负载均衡组，或按上传记录选择的成员）
+    pub failover: bool,
 }
 
 /// 为下载/预览选择驱动（完整的多源聚合逻辑）
@@ -424,14 +658,15 @@ pub async fn select_driver_for_download(
     state: &AppState,
     file_path: &str,
 ) -> Option<SelectedDriver> {
-    select_driver_for_download_with_ip(state, file_path, None).await
+    select_driver_for_download_excluding(state, file_path, None, &std::collections::HashSet::new()).await
 }
 
-/// 为下载/预览选择驱动（带客户端IP，用于IP哈希和地区分流）
-pub async fn select_driver_for_download_with_ip(
+/// 为下载/预览选择驱动（带客户端IP，用于IP哈希和地区分流），跳过对用户隐藏的存储或本次请求中已失败的驱动（故障转移重试用）
+pub async fn select_driver_for_download_excluding(
     state: &AppState,
     file_path: &str,
     client_ip: Option<std::net::IpAddr>,
+    tried: &std::collections::HashSet<String>,
 ) -> Option<SelectedDriver> {
     // 获取所有挂载点
     let mounts = match get_all_mounts(state).await {
@@ -439,8 +674,36 @@ pub async fn select_driver_for_download_with_ip(
         Err(_) => return None,
     };
     
+    // 负载均衡组上传的文件只在记录的成员中，直接使用，失败时再查找其他成员
+    if let Some(placed) = find_placed_driver(state, file_path, &mounts).await {
+        if !tried.contains(&placed.mount.id) {
+            tracing::debug!("select_driver_for_download: 使用上传记录的成员 id={}", placed.mount.id);
+            return Some(SelectedDriver {
+                driver_id: placed.mount.id,
+                internal_path: placed.actual_path,
+                can_direct_link: placed.can_direct_link,
+                failover: true,
+            });
+        }
+    }
+    
     // 查找包含该文件的所有驱动
-    let drivers = find_file_drivers(state, file_path, &mounts).await;
+    let mut drivers: Vec<DriverMatch> = find_file_drivers(state, file_path, &mounts).await
+        .into_iter()
+        .filter(|d| !tried.contains(&d.mount.id))
+        .collect();
+    state.tiering.record_access(file_path);
+    
+    // 已分层到冷存储的文件：从冷存储读取，并在后台取回热存储
+    if drivers.is_empty() {
+        if let Some(tiered) = state.tiering.tiered(file_path) {
+            state.tiering.queue_recall(file_path);
+            drivers = find_file_drivers(state, &tiered.cold_path, &mounts).await
+                .into_iter()
+                .filter(|d| !tried.contains(&d.mount.id))
+                .collect();
+        }
+    }
     
     if drivers.is_empty() {
         tracing::debug!("select_driver_for_download: No driver found containing file path={}", file_path);
@@ -473,10 +736,11 @@ pub async fn select_driver_for_download_with_ip(
                     group.name, group.mode);
                 
                 // 使用负载均衡管理器选择驱动
-                if let Some(selected) = state.load_balance.select_from_group(
+                if let Some(selected) = state.load_balance.select_from_group_excluding(
                     &group.name, 
                     client_ip, 
-                    file_name
+                    file_name,
+                    tried,
                 ).await {
                     // 找到对应的DriverMatch获取internal_path
                     if let Some(driver_match) = drivers.iter().find(|d| d.mount.id == selected.driver_id) {
@@ -485,6 +749,7 @@ pub async fn select_driver_for_download_with_ip(
                             driver_id: selected.driver_id,
                             internal_path: driver_match.actual_path.clone(),
                             can_direct_link: driver_match.can_direct_link,
+                            failover: group.mode == LoadBalanceMode::Failover,
                         });
                     }
                 }
@@ -504,5 +769,6 @@ pub async fn select_driver_for_download_with_ip(
         driver_id: selected.mount.id.clone(),
         internal_path: selected.actual_path.clone(),
         can_direct_link: selected.can_direct_link,
+        failover: false,
     })
 }
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::time::Instant;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+};
+use bytes::Bytes;
+use futures::stream::{BoxStream, StreamExt};
+use serde::Deserialize;
+use tokio::io::AsyncReadExt;
+use tokio::sync::RwLock;
+use tokio_util::io::{ReaderStream, StreamReader};
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use crate::api::file_resolver::get_user_mounts;
+use yaolist_backend::archive::{self, ArchiveKind, Member, ZipCrypto};
+use yaolist_backend::download::TrafficCountingStream;
+use yaolist_backend::error::ApiError;
+use yaolist_backend::storage::DriverBox;
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::audio::read_range;
+use super::download::{parse_range_header, throttled_body};
+use super::gallery::{authorize_read, ensure_can_download, locate_file};
+use super::{get_hide_rules, get_nearest_meta, get_user_id, is_preview_only};
+
+/// 压缩包的成员目录（按文件路径缓存，文件大小变化时重新读取）
+struct MemberIndex {
+    size: u64,
+    members: Arc<Vec<Member>>,
+    created: Instant,
+}
+
+lazy_static::lazy_static! {
+    static ref MEMBER_INDEX: RwLock<HashMap<String, MemberIndex>> = RwLock::new(HashMap::new());
+}
+
+/// 读取压缩包的成员目录（ZIP 只读中央目录，TAR 只读文件头）
+async fn member_index(driver: &DriverBox, internal_path: &str, kind: ArchiveKind, size: u64, full_path: &str) -> Result<Arc<Vec<Member>>, ApiError> {
+    let ttl = std::time::Duration::from_secs(yaolist_backend::config::config().cache.archive_ttl_secs);
+    if let Some(index) = MEMBER_INDEX.read().await.get(full_path) {
+        if index.size == size && index.created.elapsed() < ttl {
+            return Ok(index.members.clone());
+        }
+    }
+
+    let read = |start: u64, end: u64| read_range(driver, internal_path, start, end);
+    let members: Vec<Member> = match kind {
+        ArchiveKind::Zip => archive::read_zip_members(size, read).await
+            .map(|m| m.into_iter().map(Member::Zip).collect()),
+        ArchiveKind::Tar => archive::read_tar_members(size, read).await
+            .map(|m| m.into_iter().map(Member::Tar).collect()),
+    }
+    .map_err(|e| ApiError::BadRequest(format!("无效的 {} 文件：{}", kind.name(), e)))?;
+    let members = Arc::new(members);
+
+    let max_entries = yaolist_backend::config::config().cache.archive_max_entries.max(1);
+    let mut cache = MEMBER_INDEX.write().await;
+    // 清理过期条目，超过上限时淘汰最旧的
+    cache.retain(|_, e| e.created.elapsed() < ttl);
+    while cache.len() >= max_entries {
+        let Some(oldest) = cache.iter().min_by_key(|(_, e)| e.created).map(|(k, _)| k.clone()) else {
+            break;
+        };
+        cache.remove(&oldest);
+    }
+    cache.insert(full_path.to_string(), MemberIndex { size, members: members.clone(), created: Instant::now() });
+    Ok(members)
+}
+
+/// 成员数据的读取方式
+enum Source {
+    /// 未压缩且未加密，按偏移直接读取（支持 Range）
+    Plain { start: u64 },
+    /// 需要从头解密或解压
+    Sequential { start: u64, len: u64, crypto: Option<ZipCrypto>, deflated: bool },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveMemberQuery {
+    pub path: String,
+    /// 压缩包内的成员路径
+    pub member: String,
+    pub password: Option<String>,
+    /// 压缩包密码（加密的 ZIP，为空或不正确时尝试管理员为该路径保存的密码）
+    pub archive_password: Option<String>,
+    /// 作为附件下载（默认在浏览器中预览）
+    #[serde(default)]
+    pub download: bool,
+}
+
+/// GET /api/fs/archive/member - 读取压缩包中的单个文件，无需解压整个压缩包
+/// ZIP 按中央目录定位成员后只读取其字节范围（deflate 边读边解压，ZipCrypto 边读边解密），未压缩的 TAR 按文件头定位；
+/// 未压缩且未加密的成员支持 Range；加密成员的密码都不正确时返回 403 archive_password_required
+pub async fn fs_archive_member(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    cookies: Cookies,
+    Query(query): Query<ArchiveMemberQuery>,
+) -> Result<Response, ApiError> {
+    let req_path = fix_and_clean_path(&query.path);
+    let password = query.password.unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &req_path, &password).await?;
+    let name = path.rsplit('/').next().unwrap_or("").to_string();
+    let kind = archive::kind_of(&name)
+        .ok_or_else(|| ApiError::BadRequest("不支持的压缩格式，目前仅支持 ZIP 与 TAR".to_string()))?;
+
+    // 被所在目录的隐藏规则隐藏的文件视为不存在
+    let parent = fix_and_clean_path(path.rsplit_once('/').map(|(p, _)| p).unwrap_or("/"));
+    let parent_meta = get_nearest_meta(&state, &parent).await;
+    if !user_ctx.permissions.show_hidden_files && get_hide_rules(parent_meta.as_ref(), &parent).hides(&name) {
+        return Err(ApiError::NotFound("文件不存在".to_string()));
+    }
+
+    // 读取成员内容等同于下载，遵守游客下载策略与流量配额
+    let user_id = get_user_id(&state, &cookies).await;
+    ensure_can_download(&state, &user_ctx, user_id.as_deref()).await?;
+    // 成员内容是压缩包的原始数据，仅预览的压缩包不能读取
+    if is_preview_only(&state, &user_ctx, &path).await {
+        return Err(ApiError::Forbidden("该路径仅允许预览，不能读取压缩包内的文件".to_string()));
+    }
+
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
+    let (driver, internal_path, entry) = locate_file(&state, &mounts, &path).await
+        .ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+
+    let members = member_index(&driver, &internal_path, kind, entry.size, &path).await?;
+    let member_path = query.member.trim_matches('/');
+    let member = members.iter().find(|m| m.path() == member_path && !m.is_dir())
+        .ok_or_else(|| ApiError::NotFound(format!("压缩包中不存在 {}", member_path)))?;
+    let size = member.size();
+
+    let source = match member {
+        Member::Zip(m) => {
+            if m.method != 0 && m.method != 8 && !m.is_aes() {
+                return Err(ApiError::BadRequest(format!("不支持的压缩方法 {}", m.method)));
+            }
+            if m.is_aes() {
+                return Err(ApiError::BadRequest("不支持预览 AES 加密的文件，请解压后查看".to_string()));
+            }
+            let read = |start: u64, end: u64| read_range(&driver, &internal_path, start, end);
+            let mut start = archive::zip_data_offset(m, read).await
+                .map_err(|e| ApiError::BadRequest(format!("无效的 ZIP 文件：{}", e)))?;
+            let mut len = m.compressed_size;
+            let crypto = if m.encrypted {
+                // 先试用户输入的密码，再试管理员为该路径保存的密码
+                let header_end = start + archive::ZIP_CRYPTO_HEADER_BYTES;
+                let header = read_range(&driver, &internal_path, start, header_end).await
+                    .map_err(|e| ApiError::driver(e.to_string()))?;
+                let crypto = state.archive_passwords.candidates(&path, query.archive_password.as_deref())
+                    .iter()
+                    .find_map(|password| ZipCrypto::open(password.as_bytes(), m, &header))
+                    .ok_or_else(|| ApiError::Forbidden("archive_password_required".to_string()))?;
+                start = header_end;
+                len = len.saturating_sub(archive::ZIP_CRYPTO_HEADER_BYTES);
+                Some(crypto)
+            } else {
+                None
+            };
+            match (m.method, crypto) {
+                (0, None) => Source::Plain { start },
+                (method, crypto) => Source::Sequential { start, len, crypto, deflated: method == 8 },
+            }
+        }
+        Member::Tar(m) => Source::Plain { start: m.offset },
+    };
+
+    let member_name = member_path.rsplit('/').next().unwrap_or(member_path);
+    let content_type = mime_guess::from_path(member_name).first_or_octet_stream().to_string();
+    let disposition = if query.download { "attachment" } else { "inline" };
+    let limited = !user_ctx.is_guest && !user_ctx.permissions.is_admin;
+    let speed_limit = if limited { user_ctx.settings.download_speed_limit } else { 0 };
+
+    // 只有未压缩未加密的成员支持 Range
+    let ranged = matches!(source, Source::Plain { .. });
+    let range = if ranged {
+        parse_range_header(headers.get(header::RANGE).and_then(|v| v.to_str().ok()), size)
+    } else {
+        None
+    };
+    let body = match source {
+        Source::Plain { .. } if size == 0 => Body::empty(),
+        Source::Plain { start: data_start } => {
+            let (start, end) = range.unwrap_or((0, size - 1));
+            let len = end - start + 1;
+            let from = data_start + start;
+            let reader = driver.open_reader(&internal_path, Some(from..from + len)).await
+                .map_err(|e| ApiError::driver(e.to_string()))?;
+            // 不支持Range的驱动可能返回更多内容，只读取需要的部分
+            let stream = TrafficCountingStream::new(ReaderStream::new(reader.take(len)), user_id, state.db.clone());
+            throttled_body(&state, stream, user_ctx.is_guest, speed_limit)
+        }
+        Source::Sequential { start, len, crypto, deflated } => {
+            let reader = driver.open_reader(&internal_path, Some(start..start + len)).await
+                .map_err(|e| ApiError::driver(e.to_string()))?;
+            let mut stream: BoxStream<'static, std::io::Result<Bytes>> = ReaderStream::new(reader.take(len)).boxed();
+            if let Some(mut crypto) = crypto {
+                stream = stream.map(move |chunk| chunk.map(|bytes| {
+                    let mut data = bytes.to_vec();
+                    crypto.decrypt(&mut data);
+                    Bytes::from(data)
+                })).boxed();
+            }
+            if deflated {
+                stream = archive::inflate_stream(StreamReader::new(stream), size).boxed();
+            }
+            let stream = TrafficCountingStream::new(stream, user_id, state.db.clone());
+            throttled_body(&state, stream, user_ctx.is_guest, speed_limit)
+        }
+    };
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_DISPOSITION, format!("{}; filename*=UTF-8''{}", disposition, urlencoding::encode(member_name)))
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        // 压缩包中的内容不可信，直接打开时禁止执行脚本
+        .header(header::CONTENT_SECURITY_POLICY, "sandbox; default-src 'none'; img-src 'self' data:; media-src 'self'; style-src 'self' 'unsafe-inline'; font-src 'self' data:");
+    response = match (ranged, range) {
+        (true, Some((start, end))) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, size))
+            .header(header::CONTENT_LENGTH, end - start + 1),
+        (true, None) => response
+            .status(StatusCode::OK)
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::CONTENT_LENGTH, size),
+        (false, _) => response.status(StatusCode::OK).header(header::CONTENT_LENGTH, size),
+    };
+    response.body(body).map_err(|e| ApiError::Internal(e.to_string()))
+}
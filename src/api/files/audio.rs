@@ -0,0 +1,312 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::AsyncReadExt;
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use crate::api::branding::workspace_base;
+use crate::api::file_resolver::{get_all_mounts, get_user_mounts};
+use yaolist_backend::audio::{self, AudioTags, Cover, PlaylistEntry, TagSpan};
+use yaolist_backend::client_ip::ClientIp;
+use yaolist_backend::error::ApiError;
+use yaolist_backend::storage::{DriverBox, Entry};
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::gallery::{authorize_read, ensure_can_download, locate_file, walk_files, FoundFile};
+use super::{get_hide_rules, get_nearest_meta, get_user_id, issue_download_url};
+
+/// 播放列表最多包含的曲目数
+const MAX_PLAYLIST_TRACKS: usize = 2000;
+/// 生成播放列表时同时读取标签的文件数
+const TAG_READ_CONCURRENCY: usize = 8;
+
+/// 读取文件的一段内容
+pub(super) async fn read_range(driver: &DriverBox, internal_path: &str, start: u64, end: u64) -> anyhow::Result<Vec<u8>> {
+    let len = end.saturating_sub(start);
+    let mut buf = Vec::with_capacity(len as usize);
+    let reader = driver.open_reader(internal_path, Some(start..end)).await?;
+    // 不支持Range的驱动可能返回更多内容，只读取需要的部分
+    reader.take(len).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
+/// 只读取开头的标签（按需扩大读取范围）与MP3末尾的ID3v1
+async fn read_tags(driver: &DriverBox, internal_path: &str, entry: &Entry) -> anyhow::Result<(AudioTags, Option<Cover>)> {
+    let size = entry.size;
+    let mut head = read_range(driver, internal_path, 0, audio::PROBE_BYTES.min(size)).await?;
+    loop {
+        let want = match audio::tag_span(&head) {
+            TagSpan::Complete(end) => end,
+            // 头部不完整时多读一些，避免逐块请求
+            TagSpan::NeedAtLeast(end) => end + audio::PROBE_BYTES,
+            TagSpan::None => break,
+        };
+        let want = want.min(audio::MAX_TAG_BYTES).min(size);
+        if want <= head.len() as u64 {
+            break;
+        }
+        let more = read_range(driver, internal_path, head.len() as u64, want).await?;
+        if more.is_empty() {
+            break;
+        }
+        head.extend(more);
+    }
+    let tail = if audio::may_have_id3v1(&entry.name) && size > head.len() as u64 {
+        let start = size.saturating_sub(audio::ID3V1_TAIL_BYTES).max(head.len() as u64);
+        Some(read_range(driver, internal_path, start, size).await?)
+    } else {
+        None
+    };
+    Ok(tokio::task::spawn_blocking(move || audio::parse(&head, tail.as_deref())).await?)
+}
+
+/// 文件的标签，按大小与修改时间缓存（不缓存封面）
+async fn cached_tags(state: &AppState, full_path: &str, driver: &DriverBox, internal_path: &str, entry: &Entry) -> AudioTags {
+    let cached: Option<(i64, Option<String>, String)> = sqlx::query_as(
+        "SELECT size, modified, tags FROM audio_meta WHERE path = ?"
+    )
+    .bind(full_path)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    if let Some((size, modified, tags)) = cached {
+        if size as u64 == entry.size && modified == entry.modified {
+            if let Ok(tags) = serde_json::from_str(&tags) {
+                return tags;
+            }
+        }
+    }
+
+    let tags = match read_tags(driver, internal_path, entry).await {
+        Ok((tags, _)) => tags,
+        Err(e) => {
+            tracing::debug!("Failed to read audio tags of {}: {}", full_path, e);
+            return AudioTags::default();
+        }
+    };
+    if let Err(e) = sqlx::query(
+        "INSERT OR REPLACE INTO audio_meta (path, size, modified, tags, updated_at) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(full_path)
+    .bind(entry.size as i64)
+    .bind(&entry.modified)
+    .bind(serde_json::to_string(&tags).unwrap_or_default())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    {
+        tracing::warn!("Failed to cache audio tags of {}: {}", full_path, e);
+    }
+    tags
+}
+
+/// 封面的签名链接（与相册缩略图共用签名）
+fn cover_url(state: &AppState, full_path: &str, tags: &AudioTags) -> Option<String> {
+    if !tags.has_cover {
+        return None;
+    }
+    let expires = yaolist_backend::gallery::link_expiry(Utc::now().timestamp());
+    Some(format!(
+        "{}/api/fs/audio/cover?path={}&expires={}&sign={}",
+        workspace_base(), urlencoding::encode(full_path), expires, state.thumbnails.sign(full_path, expires)
+    ))
+}
+
+fn track_json(state: &AppState, name: &str, path: &str, full_path: &str, entry: &Entry, tags: &AudioTags) -> Value {
+    let mut track = json!({
+        "name": name,
+        "path": path,
+        "size": entry.size,
+        "modified": entry.modified.clone().unwrap_or_default(),
+        "cover_url": cover_url(state, full_path, tags)
+    });
+    if let (Some(obj), Ok(Value::Object(fields))) = (track.as_object_mut(), serde_json::to_value(tags)) {
+        obj.extend(fields);
+    }
+    track
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AudioMetadataReq {
+    pub path: String,
+    pub password: Option<String>,
+}
+
+/// POST /api/fs/audio/metadata - 音频文件的标签（标题、艺术家、专辑、时长与封面链接）
+pub async fn fs_audio_metadata(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<AudioMetadataReq>,
+) -> Result<Json<Value>, ApiError> {
+    let req_path = fix_and_clean_path(&req.path);
+    let password = req.password.unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &req_path, &password).await?;
+    let name = path.rsplit('/').next().unwrap_or("").to_string();
+    if !audio::is_audio(&name) {
+        return Err(ApiError::BadRequest("不是音频文件".to_string()));
+    }
+
+    // 被所在目录的隐藏规则隐藏的文件视为不存在
+    let parent = fix_and_clean_path(path.rsplit_once('/').map(|(p, _)| p).unwrap_or("/"));
+    let parent_meta = get_nearest_meta(&state, &parent).await;
+    if !user_ctx.permissions.show_hidden_files && get_hide_rules(parent_meta.as_ref(), &parent).hides(&name) {
+        return Err(ApiError::NotFound("文件不存在".to_string()));
+    }
+
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
+    let (driver, internal_path, entry) = locate_file(&state, &mounts, &path).await
+        .ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+    let tags = cached_tags(&state, &path, &driver, &internal_path, &entry).await;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": track_json(&state, &name, &req_path, &path, &entry, &tags)
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AudioCoverQuery {
+    pub path: String,
+    pub expires: i64,
+    pub sign: String,
+}
+
+/// GET /api/fs/audio/cover - 音频内嵌封面（链接由元信息接口签名）
+pub async fn fs_audio_cover(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AudioCoverQuery>,
+) -> Result<Response, ApiError> {
+    let path = fix_and_clean_path(&query.path);
+    if !state.thumbnails.verify(&path, query.expires, &query.sign, Utc::now().timestamp()) {
+        return Err(ApiError::Forbidden("封面链接无效或已过期".to_string()));
+    }
+    let mounts = get_all_mounts(&state).await?;
+    let (driver, internal_path, entry) = locate_file(&state, &mounts, &path).await
+        .ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+    let (_, cover) = read_tags(&driver, &internal_path, &entry).await
+        .map_err(|e| ApiError::driver(e.to_string()))?;
+    let cover = cover.ok_or_else(|| ApiError::NotFound("没有内嵌封面".to_string()))?;
+
+    let max_age = (query.expires - Utc::now().timestamp()).max(0);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, cover.mime_type),
+            (header::CACHE_CONTROL, format!("private, max-age={}", max_age)),
+        ],
+        cover.data,
+    ).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AudioPlaylistReq {
+    pub path: Option<String>,
+    pub password: Option<String>,
+    /// 包含子目录中的音频
+    pub recursive: Option<bool>,
+    /// m3u（默认）或 json
+    pub format: Option<String>,
+    /// 曲目下载链接的有效期（分钟），默认使用下载设置中的有效期
+    pub expire_minutes: Option<i64>,
+}
+
+/// POST /api/fs/audio/playlist - 为目录生成播放列表（M3U 或带标签的 JSON），按目录、碟号、音轨号排序
+pub async fn fs_audio_playlist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ClientIp(client_ip): ClientIp,
+    cookies: Cookies,
+    Json(req): Json<AudioPlaylistReq>,
+) -> Result<Response, ApiError> {
+    let req_path = fix_and_clean_path(&req.path.unwrap_or_default());
+    let password = req.password.unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &req_path, &password).await?;
+
+    // 播放列表中是下载链接，遵守游客下载策略与流量配额
+    let user_id = get_user_id(&state, &cookies).await;
+    ensure_can_download(&state, &user_ctx, user_id.as_deref()).await?;
+
+    let recursive = req.recursive.unwrap_or(false);
+    let (files, truncated) = walk_files(
+        &state, &user_ctx, &path, &req_path, &password, recursive, MAX_PLAYLIST_TRACKS, audio::is_audio,
+    ).await?;
+    let files: Vec<FoundFile> = files.into_iter().take(MAX_PLAYLIST_TRACKS).collect();
+
+    // 并发读取标签（已缓存的直接返回）
+    let state_ref = &state;
+    let mut tracks: Vec<(FoundFile, AudioTags)> = futures::stream::iter(files)
+        .map(|file| async move {
+            let tags = match state_ref.storage_manager.get_driver(&file.driver_id).await {
+                Some(driver) => cached_tags(state_ref, &file.full_path, &driver, &file.internal_path, &file.entry).await,
+                None => AudioTags::default(),
+            };
+            (file, tags)
+        })
+        .buffered(TAG_READ_CONCURRENCY)
+        .collect()
+        .await;
+    tracks.sort_by(|(a, a_tags), (b, b_tags)| {
+        let a_dir = a.path.rsplit_once('/').map_or("", |(d, _)| d);
+        let b_dir = b.path.rsplit_once('/').map_or("", |(d, _)| d);
+        natord::compare_ignore_case(a_dir, b_dir)
+            .then(a_tags.disc.unwrap_or(0).cmp(&b_tags.disc.unwrap_or(0)))
+            .then(a_tags.track.unwrap_or(u32::MAX).cmp(&b_tags.track.unwrap_or(u32::MAX)))
+            .then_with(|| natord::compare_ignore_case(&a.entry.name, &b.entry.name))
+    });
+
+    let expire_minutes = req.expire_minutes
+        .unwrap_or(state.download_settings.get_link_expiry_minutes() as i64);
+    let expires_at = Utc::now() + Duration::minutes(expire_minutes);
+    let scheme = headers.get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let mut entries = Vec::with_capacity(tracks.len());
+    let mut json_tracks = Vec::with_capacity(tracks.len());
+    for (file, tags) in &tracks {
+        let Some(url) = issue_download_url(&state, &user_ctx, user_id.clone(), &file.full_path, client_ip, scheme, expires_at).await else {
+            continue;
+        };
+        entries.push(PlaylistEntry {
+            title: tags.display_name(&file.entry.name),
+            duration: tags.duration,
+            url: url.clone(),
+        });
+        let mut track = track_json(&state, &file.entry.name, &file.path, &file.full_path, &file.entry, tags);
+        track["url"] = json!(url);
+        json_tracks.push(track);
+    }
+
+    if req.format.as_deref() == Some("json") {
+        return Ok(Json(json!({
+            "code": 200,
+            "message": "success",
+            "data": {
+                "tracks": json_tracks,
+                "total": json_tracks.len(),
+                "truncated": truncated,
+                "expires_at": expires_at.to_rfc3339()
+            }
+        })).into_response());
+    }
+
+    let dir_name = req_path.rsplit('/').find(|s| !s.is_empty()).unwrap_or("playlist");
+    let disposition = format!("attachment; filename*=UTF-8''{}.m3u", urlencoding::encode(dir_name));
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "audio/x-mpegurl; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        audio::to_m3u(&entries),
+    ).into_response())
+}
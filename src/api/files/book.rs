@@ -0,0 +1,294 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::time::Instant;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use crate::api::branding::workspace_base;
+use crate::api::file_resolver::{get_all_mounts, get_user_mounts};
+use yaolist_backend::archive::{self, ZipMember};
+use yaolist_backend::book::{self, BookFormat};
+use yaolist_backend::client_ip::ClientIp;
+use yaolist_backend::error::ApiError;
+use yaolist_backend::storage::DriverBox;
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::audio::read_range;
+use super::gallery::{authorize_read, ensure_can_download, locate_file};
+use super::{get_hide_rules, get_nearest_meta, get_user_id, issue_download_url};
+
+/// 阅读位置（页码或 EPUB CFI）的最大长度
+const MAX_POSITION_LEN: usize = 1024;
+
+/// EPUB 的成员目录（按文件路径缓存，读取单个成员时无需再读中央目录）
+struct BookIndex {
+    driver: DriverBox,
+    internal_path: String,
+    members: Arc<Vec<ZipMember>>,
+    created: Instant,
+}
+
+lazy_static::lazy_static! {
+    static ref BOOK_INDEX: RwLock<HashMap<String, BookIndex>> = RwLock::new(HashMap::new());
+}
+
+/// 读取 EPUB 的中央目录并写入缓存
+async fn build_index(driver: &DriverBox, internal_path: &str, size: u64, full_path: &str) -> Result<Arc<Vec<ZipMember>>, ApiError> {
+    let read = |start: u64, end: u64| read_range(driver, internal_path, start, end);
+    let members = archive::read_zip_members(size, read).await
+        .map_err(|e| ApiError::BadRequest(format!("无效的 EPUB 文件：{}", e)))?;
+    let members = Arc::new(members);
+
+    let ttl = std::time::Duration::from_secs(yaolist_backend::config::config().cache.archive_ttl_secs);
+    let max_entries = yaolist_backend::config::config().cache.archive_max_entries.max(1);
+    let mut cache = BOOK_INDEX.write().await;
+    // 清理过期条目，超过上限时淘汰最旧的
+    cache.retain(|_, e| e.created.elapsed() < ttl);
+    while cache.len() >= max_entries {
+        let Some(oldest) = cache.iter().min_by_key(|(_, e)| e.created).map(|(k, _)| k.clone()) else {
+            break;
+        };
+        cache.remove(&oldest);
+    }
+    cache.insert(full_path.to_string(), BookIndex {
+        driver: driver.clone(),
+        internal_path: internal_path.to_string(),
+        members: members.clone(),
+        created: Instant::now(),
+    });
+    Ok(members)
+}
+
+/// 只读取成员所在的字节范围并解压
+async fn read_member(driver: &DriverBox, internal_path: &str, member: &ZipMember) -> Result<Vec<u8>, ApiError> {
+    if member.compressed_size > archive::MAX_MEMBER_BYTES {
+        return Err(ApiError::BadRequest("EPUB 中的文件过大".to_string()));
+    }
+    let start = archive::zip_data_offset(member, |start, end| read_range(driver, internal_path, start, end)).await
+        .map_err(|e| ApiError::BadRequest(format!("无效的 EPUB 文件：{}", e)))?;
+    let data = read_range(driver, internal_path, start, start + member.compressed_size).await
+        .map_err(|e| ApiError::driver(e.to_string()))?;
+    let member = member.clone();
+    tokio::task::spawn_blocking(move || archive::inflate(&member, &data))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(|e| ApiError::BadRequest(format!("解压 EPUB 内容失败: {}", e)))
+}
+
+fn find_member<'a>(members: &'a [ZipMember], name: &str) -> Result<&'a ZipMember, ApiError> {
+    members.iter().find(|m| m.name == name)
+        .ok_or_else(|| ApiError::NotFound(format!("EPUB 中不存在 {}", name)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookOpenReq {
+    pub path: String,
+    pub password: Option<String>,
+}
+
+/// POST /api/fs/book/open - 打开电子书：PDF 返回支持 Range 的分段读取链接，EPUB 返回目录与按成员读取的链接，并附带已保存的阅读位置
+pub async fn fs_book_open(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ClientIp(client_ip): ClientIp,
+    cookies: Cookies,
+    Json(req): Json<BookOpenReq>,
+) -> Result<Json<Value>, ApiError> {
+    let req_path = fix_and_clean_path(&req.path);
+    let password = req.password.unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &req_path, &password).await?;
+    let name = path.rsplit('/').next().unwrap_or("").to_string();
+    let format = book::format_of(&name)
+        .ok_or_else(|| ApiError::BadRequest("不是 PDF 或 EPUB 文件".to_string()))?;
+
+    // 被所在目录的隐藏规则隐藏的文件视为不存在
+    let parent = fix_and_clean_path(path.rsplit_once('/').map(|(p, _)| p).unwrap_or("/"));
+    let parent_meta = get_nearest_meta(&state, &parent).await;
+    if !user_ctx.permissions.show_hidden_files && get_hide_rules(parent_meta.as_ref(), &parent).hides(&name) {
+        return Err(ApiError::NotFound("文件不存在".to_string()));
+    }
+
+    // 阅读需要读取全部内容，遵守游客下载策略与流量配额
+    let user_id = get_user_id(&state, &cookies).await;
+    ensure_can_download(&state, &user_ctx, user_id.as_deref()).await?;
+
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
+    let (driver, internal_path, entry) = locate_file(&state, &mounts, &path).await
+        .ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+
+    // 游客共用一个账号，不读取也不保存阅读位置
+    let saved: Option<(String, Option<f64>, String)> = match user_id.as_ref().filter(|_| !user_ctx.is_guest) {
+        Some(id) => sqlx::query_as(
+            "SELECT position, progress, updated_at FROM reading_positions WHERE user_id = ? AND path = ?"
+        )
+        .bind(id)
+        .bind(&path)
+        .fetch_optional(&state.db)
+        .await?,
+        None => None,
+    };
+    let mut data = json!({
+        "format": format,
+        "name": name,
+        "path": req_path,
+        "size": entry.size,
+        "modified": entry.modified.clone().unwrap_or_default(),
+        "position": saved.as_ref().map(|(p, _, _)| p),
+        "progress": saved.as_ref().and_then(|(_, p, _)| *p),
+        "position_updated_at": saved.as_ref().map(|(_, _, t)| t)
+    });
+
+    match format {
+        BookFormat::Pdf => {
+            // 下载链接支持 Range，阅读器按页所需的字节范围分段读取
+            let expire_minutes = state.download_settings.get_link_expiry_minutes() as i64;
+            let expires_at = Utc::now() + Duration::minutes(expire_minutes);
+            let scheme = headers.get("x-forwarded-proto")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            let url = issue_download_url(&state, &user_ctx, user_id.clone(), &path, client_ip, scheme, expires_at).await
+                .ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+            data["url"] = json!(url);
+            data["expires_at"] = json!(expires_at.to_rfc3339());
+        }
+        BookFormat::Epub => {
+            let members = build_index(&driver, &internal_path, entry.size, &path).await?;
+            let container = read_member(&driver, &internal_path, find_member(&members, "META-INF/container.xml")?).await?;
+            let opf_path = book::container_rootfile(&container)
+                .ok_or_else(|| ApiError::BadRequest("无效的 EPUB 文件：container.xml 中没有 OPF".to_string()))?;
+            let opf = read_member(&driver, &internal_path, find_member(&members, &opf_path)?).await?;
+            let package = book::parse_package(&opf, &opf_path)
+                .ok_or_else(|| ApiError::BadRequest("无效的 EPUB 文件：无法解析 OPF".to_string()))?;
+
+            let expires = yaolist_backend::gallery::link_expiry(Utc::now().timestamp());
+            data["package"] = json!(package);
+            data["member_url"] = json!(format!(
+                "{}/api/fs/book/epub?path={}&expires={}&sign={}&member=",
+                workspace_base(), urlencoding::encode(&path), expires, state.thumbnails.sign(&path, expires)
+            ));
+            data["expires_at"] = json!(chrono::DateTime::from_timestamp(expires, 0).map(|t| t.to_rfc3339()));
+        }
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": data
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookMemberQuery {
+    pub path: String,
+    pub expires: i64,
+    pub sign: String,
+    /// EPUB 内的成员路径
+    pub member: String,
+}
+
+/// GET /api/fs/book/epub - 读取 EPUB 中的单个文件（只读取该文件所在的字节范围，链接由打开接口签名）
+pub async fn fs_book_epub_member(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BookMemberQuery>,
+) -> Result<Response, ApiError> {
+    let path = fix_and_clean_path(&query.path);
+    let now = Utc::now().timestamp();
+    if book::format_of(&path) != Some(BookFormat::Epub) || !state.thumbnails.verify(&path, query.expires, &query.sign, now) {
+        return Err(ApiError::Forbidden("阅读链接无效或已过期".to_string()));
+    }
+
+    let ttl = std::time::Duration::from_secs(yaolist_backend::config::config().cache.archive_ttl_secs);
+    let cached = BOOK_INDEX.read().await.get(&path)
+        .filter(|e| e.created.elapsed() < ttl)
+        .map(|e| (e.driver.clone(), e.internal_path.clone(), e.members.clone()));
+    let (driver, internal_path, members) = match cached {
+        Some(found) => found,
+        None => {
+            let mounts = get_all_mounts(&state).await?;
+            let (driver, internal_path, entry) = locate_file(&state, &mounts, &path).await
+                .ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+            let members = build_index(&driver, &internal_path, entry.size, &path).await?;
+            (driver, internal_path, members)
+        }
+    };
+
+    let member_name = query.member.trim_start_matches('/');
+    let member = find_member(&members, member_name)?;
+    let body = read_member(&driver, &internal_path, member).await?;
+
+    let max_age = (query.expires - now).max(0);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, book::member_content_type(member_name)),
+            (header::CACHE_CONTROL, format!("private, max-age={}", max_age)),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            // 书中的 XHTML 不可信，直接打开时禁止执行脚本
+            (header::CONTENT_SECURITY_POLICY, "sandbox; default-src 'none'; img-src 'self' data:; style-src 'self' 'unsafe-inline'; font-src 'self' data:".to_string()),
+        ],
+        body,
+    ).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookPositionReq {
+    pub path: String,
+    pub password: Option<String>,
+    /// PDF 页码或 EPUB CFI，由阅读器决定
+    pub position: String,
+    /// 阅读进度（0 到 1）
+    pub progress: Option<f64>,
+}
+
+/// POST /api/fs/book/position - 保存当前用户的阅读位置
+pub async fn fs_book_position(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<BookPositionReq>,
+) -> Result<Json<Value>, ApiError> {
+    let req_path = fix_and_clean_path(&req.path);
+    let password = req.password.unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &req_path, &password).await?;
+    if book::format_of(&path).is_none() {
+        return Err(ApiError::BadRequest("不是 PDF 或 EPUB 文件".to_string()));
+    }
+    let user_id = get_user_id(&state, &cookies).await
+        .filter(|_| !user_ctx.is_guest)
+        .ok_or_else(|| ApiError::Forbidden("登录后才能保存阅读进度".to_string()))?;
+    if req.position.len() > MAX_POSITION_LEN {
+        return Err(ApiError::BadRequest("阅读位置过长".to_string()));
+    }
+    let progress = req.progress.filter(|p| p.is_finite()).map(|p| p.clamp(0.0, 1.0));
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO reading_positions (user_id, path, position, progress, updated_at) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&user_id)
+    .bind(&path)
+    .bind(&req.position)
+    .bind(progress)
+    .bind(&now)
+    .execute(&state.db)
+    .await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": {
+            "position": req.position,
+            "progress": progress,
+            "updated_at": now
+        }
+    })))
+}
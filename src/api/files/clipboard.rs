@@ -0,0 +1,231 @@
+use std::sync::Arc;
+use std::time::Duration;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use yaolist_backend::access::Capability;
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::{fs_copy, fs_move, get_user_context, get_user_id, FsMoveReq};
+
+/// 剪贴板保留时间（存放在集群共享存储中，多实例部署时其他实例也可粘贴）
+const CLIPBOARD_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// 剪贴板内容（路径为用户视角的路径，粘贴时再与用户根路径结合）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clipboard {
+    pub action: ClipboardAction,
+    pub src_dir: String,
+    pub names: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardAction {
+    Copy,
+    Cut,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClipboardSetReq {
+    pub src_dir: String,
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClipboardPasteReq {
+    pub dst_dir: String,
+    #[serde(default)]
+    pub conflict_strategy: Option<String>,
+    #[serde(default)]
+    pub buffer_size: Option<usize>,
+    #[serde(default)]
+    pub group_id: Option<String>,
+}
+
+fn clipboard_key(user_id: &str) -> String {
+    format!("clipboard:{}", user_id)
+}
+
+fn not_logged_in() -> Json<Value> {
+    Json(json!({
+        "code": 401,
+        "message": "未登录"
+    }))
+}
+
+async fn load_clipboard(state: &AppState, user_id: &str) -> Option<Clipboard> {
+    let value = state.cluster.store().get(&clipboard_key(user_id)).await.ok()??;
+    serde_json::from_str(&value).ok()
+}
+
+async fn save_clipboard(state: &AppState, user_id: &str, clipboard: &Clipboard) -> Result<(), String> {
+    let value = serde_json::to_string(clipboard).map_err(|e| e.to_string())?;
+    state.cluster.store().set(&clipboard_key(user_id), &value, CLIPBOARD_TTL).await
+}
+
+/// POST /api/fs/clipboard/get - 获取当前剪贴板
+pub async fn fs_clipboard_get(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, StatusCode> {
+    let Some(user_id) = get_user_id(&state, &cookies).await else {
+        return Ok(not_logged_in());
+    };
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": load_clipboard(&state, &user_id).await
+    })))
+}
+
+/// POST /api/fs/clipboard/copy - 复制选择到剪贴板
+pub async fn fs_clipboard_copy(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<ClipboardSetReq>,
+) -> Result<Json<Value>, StatusCode> {
+    set_clipboard(&state, &cookies, ClipboardAction::Copy, req).await
+}
+
+/// POST /api/fs/clipboard/cut - 剪切选择到剪贴板
+pub async fn fs_clipboard_cut(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<ClipboardSetReq>,
+) -> Result<Json<Value>, StatusCode> {
+    set_clipboard(&state, &cookies, ClipboardAction::Cut, req).await
+}
+
+async fn set_clipboard(
+    state: &AppState,
+    cookies: &Cookies,
+    action: ClipboardAction,
+    req: ClipboardSetReq,
+) -> Result<Json<Value>, StatusCode> {
+    let Some(user_id) = get_user_id(state, cookies).await else {
+        return Ok(not_logged_in());
+    };
+
+    let names: Vec<String> = req.names.into_iter().filter(|n| !n.is_empty()).collect();
+    if names.is_empty() {
+        return Ok(Json(json!({
+            "code": 400,
+            "message": "未选择文件"
+        })));
+    }
+
+    // 提前检查权限（按路径授权），避免粘贴时才发现无法执行
+    let user_ctx = get_user_context(state, cookies).await;
+    let capability = match action {
+        ClipboardAction::Copy => Capability::Copy,
+        ClipboardAction::Cut => Capability::Move,
+    };
+    let allowed = user_ctx.join_path(&fix_and_clean_path(&req.src_dir)).is_ok_and(|src_dir| {
+        names.iter().all(|name| user_ctx.can(capability, &format!("{}/{}", src_dir.trim_end_matches('/'), name)))
+    });
+    if !allowed {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": match action {
+                ClipboardAction::Copy => "没有复制文件的权限",
+                ClipboardAction::Cut => "没有移动文件的权限",
+            }
+        })));
+    }
+
+    let clipboard = Clipboard {
+        action,
+        src_dir: fix_and_clean_path(&req.src_dir),
+        names,
+        created_at: Utc::now(),
+    };
+    if let Err(e) = save_clipboard(state, &user_id, &clipboard).await {
+        tracing::error!("Failed to save clipboard: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": clipboard
+    })))
+}
+
+/// POST /api/fs/clipboard/paste - 粘贴到目标目录（复制保留剪贴板，剪切粘贴后清空）
+pub async fn fs_clipboard_paste(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<ClipboardPasteReq>,
+) -> Result<Json<Value>, StatusCode> {
+    let Some(user_id) = get_user_id(&state, &cookies).await else {
+        return Ok(not_logged_in());
+    };
+
+    // 剪切的内容只能粘贴一次，原子地取出防止重复移动
+    let key = clipboard_key(&user_id);
+    let clipboard = match load_clipboard(&state, &user_id).await {
+        Some(c) if c.action == ClipboardAction::Cut => state.cluster.store().take(&key).await
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::from_str::<Clipboard>(&v).ok()),
+        other => other,
+    };
+    let Some(clipboard) = clipboard else {
+        return Ok(Json(json!({
+            "code": 404,
+            "message": "剪贴板为空"
+        })));
+    };
+
+    let move_req = FsMoveReq {
+        src_dir: clipboard.src_dir.clone(),
+        dst_dir: req.dst_dir,
+        names: clipboard.names.clone(),
+        conflict_strategy: req.conflict_strategy,
+        buffer_size: req.buffer_size,
+        group_id: req.group_id,
+    };
+    let result = match clipboard.action {
+        ClipboardAction::Copy => fs_copy(State(state.clone()), cookies, Json(move_req)).await,
+        ClipboardAction::Cut => fs_move(State(state.clone()), cookies, Json(move_req)).await,
+    };
+
+    // 移动未能开始时放回剪贴板
+    let started = matches!(&result, Ok(Json(v)) if v["code"] == 200);
+    if clipboard.action == ClipboardAction::Cut && !started {
+        if let Err(e) = save_clipboard(&state, &user_id, &clipboard).await {
+            tracing::warn!("Failed to restore clipboard: {}", e);
+        }
+    }
+    result
+}
+
+/// POST /api/fs/clipboard/clear - 清空剪贴板
+pub async fn fs_clipboard_clear(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, StatusCode> {
+    let Some(user_id) = get_user_id(&state, &cookies).await else {
+        return Ok(not_logged_in());
+    };
+
+    if let Err(e) = state.cluster.store().delete(&clipboard_key(&user_id)).await {
+        tracing::error!("Failed to clear clipboard: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success"
+    })))
+}
@@ -6,8 +6,12 @@ use tower_cookies::Cookies;
 use crate::state::AppState;
 use crate::models::{Meta, UserPermissions};
 use crate::auth::SESSION_COOKIE_NAME;
-use crate::api::file_resolver::UserContext;
+use crate::api::file_resolver::{load_member_settings, load_mount_visibility, load_path_grants, load_shared_items, MountInfo, UserContext};
+use yaolist_backend::hide_rules::HideRules;
+use yaolist_backend::link_policy::{LinkDecision, LinkRequest, MountLinkOptions};
+use yaolist_backend::upload_policy::Violation;
 use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
+use yaolist_backend::workspace;
 
 /// 生成安全的随机令牌
 pub fn generate_token() -> String {
@@ -20,67 +24,12 @@ pub fn generate_token() -> String {
 /// 获取最近的有密码的元信息（向上查找，找到有密码的就停止）
 /// "我的附庸的附庸不是我的附庸" - 只需验证最近的密码
 pub async fn get_nearest_password_meta(state: &AppState, path: &str) -> Option<Meta> {
-    let path = fix_and_clean_path(path);
-    
-    // 先尝试精确匹配
-    if let Ok(Some(meta)) = sqlx::query_as::<_, Meta>(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas WHERE path = ?"
-    )
-    .bind(&path)
-    .fetch_optional(&state.db)
-    .await {
-        // 如果当前路径有密码，直接返回
-        if meta.password.is_some() && !meta.password.as_ref().unwrap().is_empty() {
-            tracing::debug!("get_nearest_password_meta: 找到有密码的元信息 path={}", path);
-            return Some(meta);
-        }
-    }
-    
-    // 递归向上查找父目录
-    if path == "/" {
-        tracing::debug!("get_nearest_password_meta: 到达根目录，未找到有密码的元信息");
-        return None;
-    }
-    
-    let parent = std::path::Path::new(&path)
-        .parent()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| "/".to_string());
-    let parent = if parent.is_empty() { "/".to_string() } else { parent };
-    
-    tracing::debug!("get_nearest_password_meta: 向上查找 {} -> {}", path, parent);
-    Box::pin(get_nearest_password_meta(state, &parent)).await
+    state.paths.nearest_password_meta(path).await
 }
 
 /// 获取最近的元信息（向上查找父目录，用于获取其他属性如readme/header）
 pub async fn get_nearest_meta(state: &AppState, path: &str) -> Option<Meta> {
-    let path = fix_and_clean_path(path);
-    
-    // 先尝试精确匹配
-    if let Ok(Some(meta)) = sqlx::query_as::<_, Meta>(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas WHERE path = ?"
-    )
-    .bind(&path)
-    .fetch_optional(&state.db)
-    .await {
-        tracing::debug!("get_nearest_meta: 精确匹配到 path={}", path);
-        return Some(meta);
-    }
-    
-    // 递归向上查找父目录
-    if path == "/" {
-        tracing::debug!("get_nearest_meta: 到达根目录，未找到元信息");
-        return None;
-    }
-    
-    let parent = std::path::Path::new(&path)
-        .parent()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| "/".to_string());
-    let parent = if parent.is_empty() { "/".to_string() } else { parent };
-    
-    tracing::debug!("get_nearest_meta: 向上查找 {} -> {}", path, parent);
-    Box::pin(get_nearest_meta(state, &parent)).await
+    state.paths.nearest_meta(path).await
 }
 
 /// 检查路径是否匹配元信息的应用范围
@@ -99,30 +48,11 @@ pub fn is_meta_apply(meta_path: &str, req_path: &str, apply_sub: bool) -> bool {
     false
 }
 
-/// 检查隐藏规则是否应用到指定路径
-/// 隐藏规则始终应用到该目录下的直接文件，h_sub 控制是否应用到子目录的文件
-pub fn is_hide_apply(meta_path: &str, req_path: &str, h_sub: bool) -> bool {
-    let meta_path = fix_and_clean_path(meta_path);
-    let req_path = fix_and_clean_path(req_path);
-    
-    // 相同路径
-    if meta_path == req_path {
-        return true;
-    }
-    
-    // 检查是否是子路径
-    if !is_sub_path(&meta_path, &req_path) {
-        return false;
-    }
-    
-    // 计算相对路径深度
-    let relative = req_path.strip_prefix(&meta_path).unwrap_or(&req_path);
-    let relative = relative.trim_start_matches('/');
-    let depth = relative.matches('/').count();
-    
-    // depth == 0 表示直接子文件/目录，始终应用
-    // depth > 0 表示子目录中的文件，需要 h_sub 为 true
-    depth == 0 || h_sub
+/// 获取应用到 dir 目录下直接文件的隐藏规则
+/// 规则始终应用到元信息所在目录，子目录中由规则自身的 sub:/nosub: 或 h_sub 决定
+pub fn get_hide_rules(meta: Option<&Meta>, dir: &str) -> HideRules {
+    meta.map(|m| HideRules::at(&m.path, m.hide.as_deref().unwrap_or(""), m.h_sub, dir))
+        .unwrap_or_default()
 }
 
 
@@ -192,6 +122,51 @@ pub fn can_write(meta: Option<&Meta>, req_path: &str) -> bool {
     }
 }
 
+/// 元信息是否将路径设为仅预览（允许浏览和在线预览，禁止下载原文件和直链）
+pub async fn path_preview_only(state: &AppState, path: &str) -> bool {
+    state.paths.is_preview_only(path).await
+}
+
+/// 路径对该用户是否为仅预览，管理员不受限制
+pub async fn is_preview_only(state: &AppState, user_ctx: &UserContext, path: &str) -> bool {
+    !user_ctx.permissions.is_admin && path_preview_only(state, path).await
+}
+
+/// 元信息是否要求该路径下的图片与 PDF 预览加水印
+pub async fn path_watermarked(state: &AppState, path: &str) -> bool {
+    yaolist_backend::watermark::can_watermark(path)
+        && state.paths.nearest_watermark_meta(path).await
+            .is_some_and(|m| is_meta_apply(&m.path, path, m.wm_sub))
+}
+
+/// 为用户签发下载令牌时使用的水印文字（用户名），管理员与不需要水印的文件返回 None
+pub async fn watermark_viewer(state: &AppState, user_ctx: &UserContext, user_id: Option<&str>, path: &str) -> Option<String> {
+    if user_ctx.permissions.is_admin || !path_watermarked(state, path).await {
+        return None;
+    }
+    let username: Option<String> = match user_id {
+        Some(id) => sqlx::query_scalar("SELECT username FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&state.db)
+            .await
+            .ok()
+            .flatten(),
+        None => None,
+    };
+    Some(username.unwrap_or_else(|| "guest".to_string()))
+}
+
+/// 按挂载点与用户组的上传策略检查文件名和大小（大小未知时只检查文件名），返回拒绝的原因
+pub fn check_upload_policy(mount: &MountInfo, user_ctx: &UserContext, name: &str, size: Option<u64>) -> Result<(), String> {
+    let message = |scope: &str, violation: Violation| match violation {
+        Violation::NotAllowed => format!("{}不允许上传该类型的文件", scope),
+        Violation::Denied(ext) => format!("{}不允许上传 .{} 文件", scope, ext),
+        Violation::TooLarge(limit) => format!("文件大小超过{}上传上限 {} 字节", scope, limit),
+    };
+    mount.upload.check(name, size).map_err(|v| message("挂载点", v))?;
+    user_ctx.settings.upload_policy().check(name, size).map_err(|v| message("用户组", v))
+}
+
 /// 获取当前用户ID（users.id 是 TEXT 类型）
 /// 如果用户未登录，返回游客的用户ID
 pub async fn get_user_id(state: &AppState, cookies: &Cookies) -> Option<String> {
@@ -201,9 +176,10 @@ pub async fn get_user_id(state: &AppState, cookies: &Cookies) -> Option<String>
         let result: Option<(String,)> = sqlx::query_as(
             "SELECT u.id FROM users u 
              JOIN sessions s ON u.id = s.user_id 
-             WHERE s.id = ? AND s.expires_at > datetime('now')"
+             WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')"
         )
         .bind(&session_id)
+        .bind(yaolist_backend::workspace::current().id())
         .fetch_optional(&state.db)
         .await
         .ok()
@@ -226,9 +202,12 @@ pub async fn get_user_id(state: &AppState, cookies: &Cookies) -> Option<String>
     guest_id.map(|(id,)| id)
 }
 
-/// 获取游客组权限（未登录用户）
-pub async fn get_guest_permissions(state: &AppState) -> UserPermissions {
-    // 首先检查游客用户是否启用
+/// 游客是否被禁用（guest 用户已禁用，或游客策略关闭了匿名访问）
+pub async fn is_guest_disabled(state: &AppState) -> bool {
+    if !state.guest.get().allow_browse {
+        return true;
+    }
+    
     let guest_enabled: Option<(bool,)> = sqlx::query_as(
         "SELECT enabled FROM users WHERE username = 'guest'"
     )
@@ -237,12 +216,15 @@ pub async fn get_guest_permissions(state: &AppState) -> UserPermissions {
     .ok()
     .flatten();
     
-    // 如果游客用户未启用，返回默认权限（read_files=false会触发guest_disabled）
-    if let Some((enabled,)) = guest_enabled {
-        if !enabled {
-            tracing::info!("游客用户已禁用");
-            return UserPermissions::default();
-        }
+    matches!(guest_enabled, Some((false,)))
+}
+
+/// 获取游客组权限（未登录用户）
+pub async fn get_guest_permissions(state: &AppState) -> UserPermissions {
+    // 游客被禁用时返回默认权限（read_files=false会触发guest_disabled）
+    if is_guest_disabled(state).await {
+        tracing::info!("游客访问已禁用");
+        return UserPermissions::default();
     }
     
     // 直接查找"游客组"的权限
@@ -280,33 +262,12 @@ pub async fn get_guest_permissions(state: &AppState) -> UserPermissions {
     result.ok().flatten().unwrap_or_default()
 }
 
-
-/// 将用户请求路径与用户根路径结合（防止路径穿越攻击）
-pub fn join_user_path(base_path: &str, req_path: &str) -> Result<String, String> {
-    // 检测是否有相对路径攻击
-    let has_relative = req_path.contains("..");
-    let clean_req = fix_and_clean_path(req_path);
-    
-    // 如果清理后仍然包含..说明路径穿越攻击
-    if has_relative && clean_req.contains("..") {
-        return Err("路径不合法".to_string());
-    }
-    
-    let clean_base = fix_and_clean_path(base_path);
-    
-    // 如果根路径是/，直接返回清理后的请求路径
-    if clean_base == "/" {
-        return Ok(clean_req);
+/// 获取游客组的路径授权（游客被禁用时为空，不能借授权恢复访问）
+pub async fn get_guest_grants(state: &AppState) -> Vec<yaolist_backend::access::PathGrant> {
+    if is_guest_disabled(state).await {
+        return Vec::new();
     }
-    
-    // 拼接路径
-    let full_path = if clean_req == "/" {
-        clean_base.clone()
-    } else {
-        format!("{}{}", clean_base.trim_end_matches('/'), clean_req)
-    };
-    
-    Ok(fix_and_clean_path(&full_path))
+    load_path_grants(state, None).await
 }
 
 /// 获取当前用户上下文（权限+根路径）
@@ -318,18 +279,25 @@ pub async fn get_user_context(state: &AppState, cookies: &Cookies) -> UserContex
             // 未登录时使用游客权限和根路径
             let guest_perms = get_guest_permissions(state).await;
             let guest_root = get_guest_root_path(state).await;
+            let (hidden_drivers, mounts) = load_mount_visibility(state, None).await;
             return UserContext {
                 permissions: guest_perms,
-                root_path: guest_root,
+                root_path: workspace::current().scope_root(&guest_root),
                 is_guest: true,
+                grants: get_guest_grants(state).await,
+                settings: load_member_settings(state, None).await,
+                hidden_drivers,
+                mounts,
+                shared: Vec::new(),
             };
         }
     };
     
-    // 查询用户权限、用户根路径和用户组根路径
+    // 查询用户权限，根路径与分享权限取自用户组默认设置与用户覆盖
     // 如果用户没有设置根路径(NULL或空)，则使用用户组的根路径
-    let result = sqlx::query_as::<_, (bool, bool, bool, bool, bool, bool, bool, bool, bool, bool, bool, Option<String>, Option<String>)>(
+    let result = sqlx::query_as::<_, (String, bool, bool, bool, bool, bool, bool, bool, bool, bool, bool)>(
         r#"SELECT 
+            u.id as user_id,
             MAX(g.read_files) as read_files,
             MAX(g.create_upload) as create_upload,
             MAX(g.rename_files) as rename_files,
@@ -337,34 +305,37 @@ pub async fn get_user_context(state: &AppState, cookies: &Cookies) -> UserContex
             MAX(g.copy_files) as copy_files,
             MAX(g.delete_files) as delete_files,
             MAX(g.allow_direct_link) as allow_direct_link,
-            MAX(g.allow_share) as allow_share,
             MAX(g.is_admin) as is_admin,
             MAX(g.show_hidden_files) as show_hidden_files,
-            MAX(g.extract_files) as extract_files,
-            u.root_path as user_root_path,
-            MAX(g.root_path) as group_root_path
+            MAX(g.extract_files) as extract_files
         FROM users u
         INNER JOIN sessions s ON u.id = s.user_id
         INNER JOIN user_group_members ugm ON u.id = ugm.user_id
         INNER JOIN user_groups g ON CAST(g.id AS TEXT) = ugm.group_id
-        WHERE s.id = ? AND s.expires_at > datetime('now')
+        WHERE s.id = ? AND s.expires_at > datetime('now') AND s.workspace_id = ?
         GROUP BY u.id"#
     )
     .bind(&session_id)
+    .bind(workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .ok()
     .flatten();
     
     match result {
-        Some((read_files, create_upload, rename_files, move_files, copy_files, 
-              delete_files, allow_direct_link, allow_share, is_admin, show_hidden_files, 
-              extract_files, user_root_path, group_root_path)) => {
+        Some((user_id, read_files, create_upload, rename_files, move_files, copy_files, 
+              delete_files, allow_direct_link, is_admin, show_hidden_files, 
+              extract_files)) => {
             // 优先使用用户根路径，如果没有则使用用户组根路径
-            let root_path = user_root_path
-                .filter(|p| !p.is_empty() && p != "/")
-                .or(group_root_path)
-                .unwrap_or_else(|| "/".to_string());
+            let settings = load_member_settings(state, Some(&user_id)).await;
+            // 管理员可见全部存储
+            let (hidden_drivers, mounts) = if is_admin {
+                Default::default()
+            } else {
+                load_mount_visibility(state, Some(&user_id)).await
+            };
+            // 用户根路径位于所属工作区的根目录之下
+            let root_path = workspace::current().scope_root(&settings.root_path);
             
             UserContext {
                 permissions: UserPermissions {
@@ -375,23 +346,34 @@ pub async fn get_user_context(state: &AppState, cookies: &Cookies) -> UserContex
                     copy_files,
                     delete_files,
                     allow_direct_link,
-                    allow_share,
+                    allow_share: settings.allow_share,
                     is_admin,
                     show_hidden_files,
                     extract_files,
                 },
                 root_path,
                 is_guest: false,
+                grants: load_path_grants(state, Some(&user_id)).await,
+                settings,
+                hidden_drivers,
+                mounts,
+                shared: load_shared_items(state, &user_id).await,
             }
         },
         None => {
             // session无效，使用游客权限
             let guest_perms = get_guest_permissions(state).await;
             let guest_root = get_guest_root_path(state).await;
+            let (hidden_drivers, mounts) = load_mount_visibility(state, None).await;
             UserContext {
                 permissions: guest_perms,
-                root_path: guest_root,
+                root_path: workspace::current().scope_root(&guest_root),
                 is_guest: true,
+                grants: get_guest_grants(state).await,
+                settings: load_member_settings(state, None).await,
+                hidden_drivers,
+                mounts,
+                shared: Vec::new(),
             }
         }
     }
@@ -461,9 +443,10 @@ pub async fn get_user_permissions(state: &AppState, cookies: &Cookies) -> UserPe
         INNER JOIN sessions s ON u.id = s.user_id
         INNER JOIN user_group_members ugm ON u.id = ugm.user_id
         INNER JOIN user_groups g ON CAST(g.id AS TEXT) = ugm.group_id
-        WHERE s.id = ? AND s.expires_at > datetime('now')"#
+        WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')"#
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .ok()
@@ -488,7 +471,7 @@ lazy_static::lazy_static! {
     pub static ref DOWNLOAD_TOKENS: RwLock<HashMap<String, DownloadToken>> = RwLock::new(HashMap::new());
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct DownloadToken {
     pub path: String,
     pub driver_id: String,
@@ -496,27 +479,78 @@ pub struct DownloadToken {
     pub can_direct_link: bool,
     pub file_size: Option<u64>,
     pub user_id: Option<String>,  // 用于流量统计
+    pub guest: bool,  // 游客下载，受游客限速
+    /// 用户下载限速（字节每秒，0 表示不限），继承自用户组
+    #[serde(default)]
+    pub speed_limit: i64,
+    /// 仅预览：始终本地中转并以内联方式返回，附带禁止缓存与下载的响应头
+    #[serde(default)]
+    pub preview_only: bool,
+    /// 水印中的查看者，设置时图片与 PDF 加水印后返回（始终本地中转，不支持 Range）
+    #[serde(default)]
+    pub watermark: Option<String>,
+}
+
+/// Look up a file's size by listing its parent directory / 通过列出父目录获取文件大小
+pub async fn lookup_file_size(state: &AppState, driver_id: &str, path: &str) -> Option<u64> {
+    let driver = state.storage_manager.get_driver(driver_id).await?;
+    let (parent_path, filename) = path.rsplit_once('/').unwrap_or(("", path));
+    let parent_path = if parent_path.is_empty() { "/" } else { parent_path };
+    driver.list(parent_path).await
+        .ok()?
+        .into_iter()
+        .find(|e| e.name == filename && !e.is_dir)
+        .map(|e| e.size)
+}
+
+/// Decide whether a download from `driver_id` is redirected or proxied
+/// 决定从 `driver_id` 下载时走302直链还是本地中转
+pub async fn decide_download_link(
+    state: &AppState,
+    driver_id: &str,
+    can_direct_link: bool,
+    client_ip: Option<std::net::IpAddr>,
+    file_size: Option<u64>,
+    throttled: bool,
+) -> LinkDecision {
+    let mount = state.storage_manager.get_driver_spec(driver_id).await
+        .map(|spec| MountLinkOptions::from_config(&spec.config))
+        .unwrap_or_default();
+    let decision = state.download_settings.decide_link(&LinkRequest {
+        can_direct_link,
+        mount,
+        client_ip,
+        file_size,
+        throttled,
+    });
+    tracing::debug!("decide_download_link: driver={}, decision={:?}", driver_id, decision);
+    decision
 }
 
 /// Create a download token and return the token string / 创建下载令牌并返回令牌字符串
 pub async fn create_download_token(
+    state: &AppState,
     path: String,
     driver_id: String,
     expires_at: chrono::DateTime<Utc>,
     can_direct_link: bool,
     file_size: Option<u64>,
 ) -> String {
-    create_download_token_with_user(path, driver_id, expires_at, can_direct_link, file_size, None).await
+    create_download_token_with_user(state, path, driver_id, expires_at, can_direct_link, file_size, None, false, None).await
 }
 
 /// Create a download token with user_id for traffic stats / 创建带用户ID的下载令牌（用于流量统计）
+#[allow(clippy::too_many_arguments)]
 pub async fn create_download_token_with_user(
+    state: &AppState,
     path: String,
     driver_id: String,
     expires_at: chrono::DateTime<Utc>,
     can_direct_link: bool,
     file_size: Option<u64>,
     user_id: Option<String>,
+    preview_only: bool,
+    watermark: Option<String>,
 ) -> String {
     let token = generate_token();
     let download_token = DownloadToken {
@@ -526,9 +560,48 @@ pub async fn create_download_token_with_user(
         can_direct_link,
         file_size,
         user_id,
+        guest: false,
+        speed_limit: 0,
+        preview_only,
+        watermark,
     };
     
-    let mut tokens = DOWNLOAD_TOKENS.write().await;
-    tokens.insert(token.clone(), download_token);
+    store_download_token(state, &token, download_token).await;
     token
 }
+
+/// Store a download token; with shared state other replicas can redeem it
+/// 保存下载令牌；多实例共享时其他实例也可使用
+pub async fn store_download_token(state: &AppState, token: &str, download_token: DownloadToken) {
+    if state.cluster.is_shared() {
+        let ttl = (download_token.expires_at - Utc::now()).to_std().unwrap_or_default();
+        let value = serde_json::to_string(&download_token).unwrap_or_default();
+        if let Err(e) = state.cluster.store().set(&format!("download_token:{}", token), &value, ttl).await {
+            tracing::warn!("Failed to share download token: {}", e);
+        }
+    }
+    DOWNLOAD_TOKENS.write().await.insert(token.to_string(), download_token);
+}
+
+/// Find an unexpired download token, also those issued by other replicas
+/// 查找未过期的下载令牌（包括其他实例签发的）
+pub async fn find_download_token(state: &AppState, token: &str) -> Option<DownloadToken> {
+    let now = Utc::now();
+    {
+        let mut tokens = DOWNLOAD_TOKENS.write().await;
+        tokens.retain(|_, v| v.expires_at > now);
+        if let Some(t) = tokens.get(token) {
+            return Some(t.clone());
+        }
+    }
+    if !state.cluster.is_shared() {
+        return None;
+    }
+    let value = state.cluster.store().get(&format!("download_token:{}", token)).await.ok()??;
+    let download_token: DownloadToken = serde_json::from_str(&value).ok()?;
+    if download_token.expires_at <= now {
+        return None;
+    }
+    DOWNLOAD_TOKENS.write().await.insert(token.to_string(), download_token.clone());
+    Some(download_token)
+}
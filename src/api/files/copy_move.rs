@@ -9,10 +9,14 @@ use serde_json::{json, Value};
 use tower_cookies::Cookies;
 
 use crate::state::AppState;
-use crate::api::file_resolver::{MountInfo, get_first_mount};
+use crate::api::file_resolver::{calculate_internal_path, get_all_mounts, find_source_mount, select_write_mount, UserContext};
+use crate::task::VerifyResult;
+use yaolist_backend::storage::HashType;
+use yaolist_backend::transfer::{spawn_buffered_reader, DEFAULT_COPY_BUFFER_SIZE};
 use yaolist_backend::utils::{fix_and_clean_path, resolve_conflict_name, ConflictStrategy};
+use yaolist_backend::access::Capability;
 
-use super::{get_user_context, join_user_path, get_user_id, get_existing_names};
+use super::{get_user_context, get_user_id, get_existing_names};
 
 /// 跨驱动复制：Core 层控制，调用 driver 原语
 /// 支持 FTP→Local→OneDrive→夸克 等任意驱动组合
@@ -41,14 +45,14 @@ async fn cross_driver_copy(
     }
 }
 
-/// 跨驱动复制单个文件（使用大缓冲区提高速度）
-async fn cross_driver_copy_file(
+/// 跨驱动复制单个文件（后台预读，读写并行）
+pub async fn cross_driver_copy_file(
     src_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
     dst_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
     src_path: &str,
     dst_path: &str,
 ) -> anyhow::Result<()> {
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::io::AsyncWriteExt;
     
     // 获取文件大小
     let file_name = src_path.split('/').last().unwrap_or(src_path);
@@ -58,24 +62,126 @@ async fn cross_driver_copy_file(
         .and_then(|entries| entries.iter().find(|e| e.name == file_name).map(|e| e.size))
         .unwrap_or(0);
     
-    let mut reader = src_driver.open_reader(src_path, None).await?;
-    let mut writer = dst_driver.open_writer(dst_path, Some(file_size), None).await?;
-    
-    // 使用 32MB 缓冲区支持10Gbps高速传输
-    let mut buffer = vec![0u8; 32 * 1024 * 1024];
+    let hash_types = dst_driver.hash_types();
+    let mut retries = 0;
     loop {
-        let n = reader.read(&mut buffer).await?;
-        if n == 0 {
+        let reader = src_driver.open_reader(src_path, None).await?;
+        let mut writer = dst_driver.open_writer(dst_path, Some(file_size), None).await?;
+        let mut hasher = TransferHasher::new(&hash_types);
+        let mut total = 0u64;
+        
+        // 后台预读+有界通道：读写并行且内存占用有上限
+        let mut chunks = spawn_buffered_reader(reader, DEFAULT_COPY_BUFFER_SIZE);
+        while let Some(chunk) = chunks.recv().await {
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            writer.write_all(&chunk).await?;
+            total += chunk.len() as u64;
+        }
+        writer.shutdown().await?;
+        
+        match verify_copied_file(dst_driver, dst_path, file_size, total, &hasher.finish()).await {
+            Ok(_) => return Ok(()),
+            Err(e) if retries < COPY_VERIFY_RETRIES => {
+                retries += 1;
+                tracing::warn!("Copy verification failed, retrying ({}/{}): {} -> {}: {}",
+                    retries, COPY_VERIFY_RETRIES, src_path, dst_path, e);
+                let _ = dst_driver.delete(dst_path).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// 校验不一致时的最大重传次数
+const COPY_VERIFY_RETRIES: u64 = 2;
+
+/// 传输过程中计算校验值（只计算目标驱动能提供的算法）
+struct TransferHasher {
+    md5: Option<md5::Context>,
+    sha1: Option<sha1::Sha1>,
+}
+
+impl TransferHasher {
+    fn new(hash_types: &[HashType]) -> Self {
+        use sha1::Digest;
+        Self {
+            md5: hash_types.contains(&HashType::Md5).then(md5::Context::new),
+            sha1: hash_types.contains(&HashType::Sha1).then(sha1::Sha1::new),
+        }
+    }
+    
+    fn update(&mut self, data: &[u8]) {
+        use sha1::Digest;
+        if let Some(ref mut ctx) = self.md5 {
+            ctx.consume(data);
+        }
+        if let Some(ref mut ctx) = self.sha1 {
+            ctx.update(data);
+        }
+    }
+    
+    fn finish(self) -> Vec<(HashType, String)> {
+        use sha1::Digest;
+        let mut hashes = Vec::new();
+        if let Some(ctx) = self.md5 {
+            hashes.push((HashType::Md5, format!("{:x}", ctx.compute())));
+        }
+        if let Some(ctx) = self.sha1 {
+            hashes.push((HashType::Sha1, hex::encode(ctx.finalize())));
+        }
+        hashes
+    }
+}
+
+/// 校验复制结果：比较大小，目标驱动提供校验值时再比较校验值
+async fn verify_copied_file(
+    dst_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
+    dst_path: &str,
+    src_size: u64,
+    transferred: u64,
+    hashes: &[(HashType, String)],
+) -> anyhow::Result<VerifyResult> {
+    // 源文件大小已知时，读取的字节数必须一致（防止源读取被截断）
+    if src_size > 0 && transferred != src_size {
+        anyhow::bail!("读取字节数不一致: 源文件 {} 字节, 实际读取 {} 字节", src_size, transferred);
+    }
+    
+    let parent = dst_path.rsplit_once('/').map(|(p, _)| if p.is_empty() { "/" } else { p }).unwrap_or("/");
+    let file_name = dst_path.split('/').last().unwrap_or(dst_path);
+    
+    // 部分云盘上传完成后列表有延迟，找不到时稍后再试一次
+    let mut dst_size = None;
+    for attempt in 0..2 {
+        if attempt > 0 {
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        }
+        dst_size = dst_driver.list(parent).await?
+            .into_iter()
+            .find(|e| e.name == file_name && !e.is_dir)
+            .map(|e| e.size);
+        if dst_size.is_some() {
             break;
         }
-        writer.write_all(&buffer[..n]).await?;
     }
-    writer.shutdown().await?;
+    let dst_size = dst_size.ok_or_else(|| anyhow::anyhow!("目标文件不存在: {}", dst_path))?;
+    if dst_size != transferred {
+        anyhow::bail!("文件大小不一致: 已传输 {} 字节, 目标文件 {} 字节", transferred, dst_size);
+    }
     
-    Ok(())
+    for (hash_type, expected) in hashes {
+        if let Some(actual) = dst_driver.get_hash(dst_path, *hash_type).await? {
+            if !actual.eq_ignore_ascii_case(expected) {
+                anyhow::bail!("校验值不一致({:?}): 期望 {}, 目标 {}", hash_type, expected, actual);
+            }
+            return Ok(VerifyResult::Checksum);
+        }
+    }
+    
+    Ok(VerifyResult::SizeOnly)
 }
 
-/// 跨驱动复制单个文件（带详细状态：下载中/上传中）
+/// 跨驱动复制单个文件（带详细状态：下载中/上传中），完成后校验目标文件
 async fn cross_driver_copy_file_with_progress(
     src_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
     dst_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
@@ -88,13 +194,7 @@ async fn cross_driver_copy_file_with_progress(
     total_files: u64,
     total_task_size: u64,
 ) -> anyhow::Result<()> {
-    use std::time::Instant;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-    
-    // 获取任务控制
-    let control = task_manager.get_control(task_id).await;
     let file_name = src_path.split('/').last().unwrap_or(src_path);
-    let start_time = Instant::now();
     
     // 获取文件大小
     let file_size = src_driver.list(src_path.rsplit_once('/').map(|(p, _)| p).unwrap_or("/"))
@@ -103,26 +203,156 @@ async fn cross_driver_copy_file_with_progress(
         .and_then(|entries| entries.iter().find(|e| e.name == file_name).map(|e| e.size))
         .unwrap_or(0);
     
+    let hash_types = dst_driver.hash_types();
+    let mut resume_offset = copy_resume_offset(
+        src_driver, dst_driver, src_path, dst_path, file_size, task_manager, task_id,
+    ).await;
+    let mut retries = 0;
+    loop {
+        let (total, hashes) = transfer_file_with_progress(
+            src_driver, dst_driver, src_path, dst_path, file_size, resume_offset, &hash_types,
+            task_manager, task_id, base_processed_size, processed_files, total_files, total_task_size,
+        ).await?;
+        resume_offset = 0;
+        
+        task_manager.update_copy_task_progress(
+            task_id, "校验中", file_name, 100.0,
+            processed_files, total_files, base_processed_size + total, total_task_size
+        ).await;
+        
+        match verify_copied_file(dst_driver, dst_path, file_size, total, &hashes).await {
+            Ok(result) => {
+                task_manager.clear_copy_checkpoint(task_id, src_path).await;
+                task_manager.record_verification(task_id, src_path, Some(result), retries).await;
+                return Ok(());
+            }
+            Err(e) if retries < COPY_VERIFY_RETRIES => {
+                retries += 1;
+                tracing::warn!("Copy verification failed, retrying ({}/{}): {} -> {}: {}",
+                    retries, COPY_VERIFY_RETRIES, src_path, dst_path, e);
+                let _ = dst_driver.delete(dst_path).await;
+            }
+            Err(e) => {
+                task_manager.clear_copy_checkpoint(task_id, src_path).await;
+                task_manager.record_verification(task_id, src_path, None, retries).await;
+                anyhow::bail!("传输校验失败 {}: {}", src_path, e);
+            }
+        }
+    }
+}
+
+/// 计算跨驱动复制的续传位置
+/// 需要源驱动支持范围读取、任务中有该文件的断点且目标路径一致，
+/// 以目标文件当前实际大小为准（中断前缓冲未落盘的数据会重新传输）
+async fn copy_resume_offset(
+    src_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
+    dst_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
+    src_path: &str,
+    dst_path: &str,
+    file_size: u64,
+    task_manager: &crate::task::TaskManager,
+    task_id: &str,
+) -> u64 {
+    if file_size == 0 || !src_driver.capabilities().can_range_read {
+        return 0;
+    }
+    let checkpoint = match task_manager.get_copy_checkpoint(task_id, src_path).await {
+        Some(c) => c,
+        None => return 0,
+    };
+    if checkpoint.uploaded_size == 0
+        || checkpoint.size != file_size
+        || checkpoint.target_path.as_deref() != Some(dst_path) {
+        return 0;
+    }
+    
+    let parent = dst_path.rsplit_once('/').map(|(p, _)| if p.is_empty() { "/" } else { p }).unwrap_or("/");
+    let file_name = dst_path.split('/').last().unwrap_or(dst_path);
+    let partial_size = dst_driver.list(parent).await
+        .ok()
+        .and_then(|entries| entries.into_iter().find(|e| e.name == file_name && !e.is_dir))
+        .map(|e| e.size)
+        .unwrap_or(0);
+    
+    if partial_size < file_size {
+        partial_size
+    } else {
+        0
+    }
+}
+
+/// 执行一次文件传输，返回已传输字节数和传输过程中计算的校验值
+/// resume_offset > 0 时从该位置续传（目标驱动不支持续写时从头传输）
+async fn transfer_file_with_progress(
+    src_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
+    dst_driver: &std::sync::Arc<Box<dyn yaolist_backend::storage::StorageDriver>>,
+    src_path: &str,
+    dst_path: &str,
+    file_size: u64,
+    resume_offset: u64,
+    hash_types: &[HashType],
+    task_manager: &crate::task::TaskManager,
+    task_id: &str,
+    base_processed_size: u64,
+    processed_files: u64,
+    total_files: u64,
+    total_task_size: u64,
+) -> anyhow::Result<(u64, Vec<(HashType, String)>)> {
+    use std::time::Instant;
+    use tokio::io::AsyncWriteExt;
+    
+    // 获取任务控制
+    let control = task_manager.get_control(task_id).await;
+    let file_name = src_path.split('/').last().unwrap_or(src_path);
+    let start_time = Instant::now();
+    
     // 阶段1：下载（从源存储读取）
     task_manager.update_copy_task_progress(
         task_id, "下载中", file_name, 0.0,
         processed_files, total_files, base_processed_size, total_task_size
     ).await;
     
-    let mut reader = src_driver.open_reader(src_path, None).await?;
+    // 断点续传：目标驱动支持续写时从断点处读取源文件
+    let resume_writer = if resume_offset > 0 {
+        dst_driver.open_resume_writer(dst_path, resume_offset, Some(file_size)).await.unwrap_or(None)
+    } else {
+        None
+    };
+    let offset = if resume_writer.is_some() { resume_offset } else { 0 };
+    if offset > 0 {
+        tracing::info!("Resuming copy at {} bytes: {} -> {}", offset, src_path, dst_path);
+    }
+    
+    let reader = if offset > 0 {
+        src_driver.open_reader(src_path, Some(offset..file_size)).await?
+    } else {
+        src_driver.open_reader(src_path, None).await?
+    };
     
     // 阶段2：上传（写入目标存储）
     task_manager.update_copy_task_progress(
         task_id, "上传中", file_name, 0.0,
-        processed_files, total_files, base_processed_size, total_task_size
+        processed_files, total_files, base_processed_size + offset, total_task_size
     ).await;
     
     // 传递file_size作为size_hint，驱动需要知道总大小才能正确分片上传
-    let mut writer = dst_driver.open_writer(dst_path, Some(file_size), None).await?;
+    let mut writer = match resume_writer {
+        Some(writer) => writer,
+        None => dst_driver.open_writer(dst_path, Some(file_size), None).await?,
+    };
     
-    // 使用32MB缓冲区复制
-    let mut buffer = vec![0u8; 32 * 1024 * 1024];
-    let mut total = 0u64;
+    // 源驱动支持范围读取时记录字节级断点
+    let checkpoint = file_size > 0 && src_driver.capabilities().can_range_read;
+    
+    // 后台预读+有界通道：读写并行且内存占用有上限，缓冲区大小取任务设置
+    let buffer_size = task_manager.get_task(task_id).await
+        .and_then(|t| t.buffer_size)
+        .map(|s| s as usize)
+        .unwrap_or(DEFAULT_COPY_BUFFER_SIZE);
+    let mut chunks = spawn_buffered_reader(reader, buffer_size);
+    // 续传时只传输了部分数据，无法得到完整校验值，只校验大小
+    let mut hasher = TransferHasher::new(if offset > 0 { &[] } else { hash_types });
+    let mut total = offset;
     let mut last_update = std::time::Instant::now();
     
     loop {
@@ -140,17 +370,21 @@ async fn cross_driver_copy_file_with_progress(
             }
         }
         
-        let n = reader.read(&mut buffer).await?;
-        if n == 0 {
-            break;
-        }
-        writer.write_all(&buffer[..n]).await?;
-        total += n as u64;
+        let chunk = match chunks.recv().await {
+            Some(chunk) => chunk?,
+            None => break,
+        };
+        hasher.update(&chunk);
+        writer.write_all(&chunk).await?;
+        total += chunk.len() as u64;
         
         // 每100ms更新一次进度
         if last_update.elapsed().as_millis() >= 100 {
             let file_progress = if file_size > 0 { (total as f32 / file_size as f32) * 100.0 } else { 0.0 };
             let current_size = base_processed_size + total;
+            if checkpoint {
+                task_manager.update_copy_checkpoint(task_id, src_path, dst_path, file_size, total).await;
+            }
             task_manager.update_copy_task_progress(
                 task_id, "传输中", file_name, file_progress,
                 processed_files, total_files, current_size, total_task_size
@@ -165,12 +399,57 @@ async fn cross_driver_copy_file_with_progress(
     // 输出性能统计
     let elapsed = start_time.elapsed().as_secs_f64();
     if elapsed > 0.0 {
-        let speed_mbps = (total as f64 / elapsed) / (1024.0 * 1024.0);
+        let speed_mbps = ((total - offset) as f64 / elapsed) / (1024.0 * 1024.0);
         tracing::info!("File copy completed: {} -> {}, {} MB in {:.2}s, speed: {:.2} MB/s", 
             src_path, dst_path, total / 1024 / 1024, elapsed, speed_mbps);
     }
     
-    Ok(())
+    Ok((total, hasher.finish()))
+}
+
+/// 不同挂载点属于同一云盘账号时，使用服务端移动/复制（无需下载再上传）
+/// 返回false表示不适用或失败，调用方回退到跨驱动传输
+async fn transfer_within_account(
+    state: &AppState,
+    src_mount_id: &str,
+    dst_mount_id: &str,
+    src_path: &str,
+    dst_path: &str,
+    copy: bool,
+) -> bool {
+    let (Some(src_driver), Some(dst_driver)) = (
+        state.storage_manager.get_driver(src_mount_id).await,
+        state.storage_manager.get_driver(dst_mount_id).await,
+    ) else {
+        return false;
+    };
+    if src_driver.name() != dst_driver.name() {
+        return false;
+    }
+    let src_key = match src_driver.account_key().await {
+        Some(key) => key,
+        None => return false,
+    };
+    if dst_driver.account_key().await.as_deref() != Some(src_key.as_str()) {
+        return false;
+    }
+    let Some(dst_account_path) = dst_driver.account_path(dst_path) else {
+        return false;
+    };
+    
+    match src_driver.transfer_within_account(src_path, &dst_account_path, copy).await {
+        Ok(done) => {
+            if done {
+                tracing::info!("Server-side {} within account {}: {} -> {}",
+                    if copy { "copy" } else { "move" }, src_key, src_path, dst_account_path);
+            }
+            done
+        }
+        Err(e) => {
+            tracing::warn!("Server-side transfer failed, falling back to download+upload: {}", e);
+            false
+        }
+    }
 }
 
 /// 递归计算文件夹大小
@@ -304,9 +583,34 @@ pub struct FsMoveReq {
     pub names: Vec<String>,
     #[serde(default)]
     pub conflict_strategy: Option<String>, // "overwrite", "skip", "auto_rename"
+    #[serde(default)]
+    pub buffer_size: Option<usize>, // 跨驱动复制缓冲区大小（字节），为空使用全局设置
+    #[serde(default)]
+    pub group_id: Option<String>, // 加入的任务组
 }
 
 /// POST /api/fs/move - 移动文件或目录（创建任务异步执行）
+/// 源目录下的每个项目与目标目录是否都允许该操作
+fn can_transfer(user_ctx: &UserContext, capability: Capability, src_dir: &str, names: &[String], dst_dir: &str) -> bool {
+    user_ctx.can(capability, dst_dir)
+        && names.iter().all(|name| user_ctx.can(capability, &format!("{}/{}", src_dir.trim_end_matches('/'), name)))
+}
+
+/// 复制或移动是否会把仅预览的内容带出限制范围（管理员不受限制）
+async fn leaks_preview_only(state: &AppState, user_ctx: &UserContext, src_dir: &str, names: &[String], dst_dir: &str) -> bool {
+    if user_ctx.permissions.is_admin {
+        return false;
+    }
+    for name in names {
+        let from = format!("{}/{}", src_dir.trim_end_matches('/'), name);
+        let to = format!("{}/{}", dst_dir.trim_end_matches('/'), name);
+        if state.paths.leaks_preview_only(&from, &to).await {
+            return true;
+        }
+    }
+    false
+}
+
 pub async fn fs_move(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
@@ -317,18 +621,8 @@ pub async fn fs_move(
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    // 权限验证
-    if !perms.move_files && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有移动文件的权限"
-        })));
-    }
-    
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let src_dir = match join_user_path(&user_ctx.root_path, &req_src_dir) {
+    let src_dir = match user_ctx.join_path(&req_src_dir) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -337,7 +631,7 @@ pub async fn fs_move(
             })));
         }
     };
-    let dst_dir = match join_user_path(&user_ctx.root_path, &req_dst_dir) {
+    let dst_dir = match user_ctx.join_path(&req_dst_dir) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -347,6 +641,20 @@ pub async fn fs_move(
         }
     };
     
+    // 权限验证：源文件与目标目录都需要移动权限（按路径授权）
+    if !can_transfer(&user_ctx, Capability::Move, &src_dir, &req.names, &dst_dir) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有移动文件的权限"
+        })));
+    }
+    if leaks_preview_only(&state, &user_ctx, &src_dir, &req.names, &dst_dir).await {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "仅预览的文件不能移动到未设置仅预览的目录"
+        })));
+    }
+    
     let user_id = get_user_id(&state, &cookies).await;
     let names = req.names.clone();
     
@@ -365,7 +673,7 @@ pub async fn fs_move(
         format!("移动 {} 个项目", names.len())
     };
     
-    let task = crate::task::Task::new_copy_move(
+    let mut task = crate::task::Task::new_copy_move(
         crate::task::TaskType::Move,
         task_name,
         src_dir.clone(),
@@ -374,16 +682,23 @@ pub async fn fs_move(
         strategy_str.clone(),
         user_id,
     );
+    let buffer_size = req.buffer_size
+        .map(yaolist_backend::transfer::clamp_buffer_size)
+        .unwrap_or_else(|| state.transfer_settings.get_buffer_size());
+    task.buffer_size = Some(buffer_size as u64);
     let task_id = task.id.clone();
     
     state.task_manager.add_task(task).await;
+    if let Some(ref group_id) = req.group_id {
+        state.task_manager.add_to_group(group_id, std::slice::from_ref(&task_id)).await;
+    }
     state.task_manager.start_task(&task_id).await;
     
     // 异步执行移动操作
     let state_clone = state.clone();
     let task_id_clone = task_id.clone();
     
-    tokio::spawn(async move {
+    yaolist_backend::request_id::spawn(async move {
         let result = execute_move_operation(&state_clone, &src_dir, &dst_dir, &names, &task_id_clone, strategy).await;
         
         match result {
@@ -420,25 +735,12 @@ async fn execute_move_operation(
     strategy: ConflictStrategy,
 ) -> anyhow::Result<()> {
     // 获取所有挂载点
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await?;
-    
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
-    
-    let src_mount = get_first_mount(src_dir, &mounts)
+    let mounts = get_all_mounts(state).await?;
+    
+    // 聚合挂载：源为实际包含所选项的挂载点，目标按写入策略选择
+    let src_mount = find_source_mount(state, src_dir, names, &mounts).await
         .ok_or_else(|| anyhow::anyhow!("源目录不存在"))?;
-    let dst_mount = get_first_mount(dst_dir, &mounts)
+    let dst_mount = select_write_mount(state, dst_dir, &mounts, None).await
         .ok_or_else(|| anyhow::anyhow!("目标目录不存在"))?;
     
     let src_mount_path = fix_and_clean_path(&src_mount.mount_path);
@@ -454,11 +756,7 @@ async fn execute_move_operation(
         } else {
             format!("{}/{}", src_dir, name)
         };
-        let src_actual = if src_file_path.len() > src_mount_path.len() {
-            fix_and_clean_path(&src_file_path[src_mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let src_actual = calculate_internal_path(&src_mount_path, &src_file_path);
         let parent = src_actual.rsplitn(2, '/').nth(1).unwrap_or("/");
         if let Ok(entries) = src_driver.list(parent).await {
             let filename = src_actual.split('/').last().unwrap_or("");
@@ -508,11 +806,7 @@ async fn execute_move_operation(
             format!("{}/{}", dst_dir, final_name)
         };
         
-        let src_actual = if src_file_path.len() > src_mount_path.len() {
-            fix_and_clean_path(&src_file_path[src_mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let src_actual = calculate_internal_path(&src_mount_path, &src_file_path);
         
         let dst_actual = if dst_file_path.len() > dst_mount_path.len() {
             fix_and_clean_path(&dst_file_path[dst_mount_path.len()..])
@@ -537,6 +831,8 @@ async fn execute_move_operation(
             if let Some(driver) = state.storage_manager.get_driver(&src_mount.id).await {
                 driver.move_item(&src_actual, &dst_actual).await?;
             }
+        } else if transfer_within_account(state, &src_mount.id, &dst_mount.id, &src_actual, &dst_actual, false).await {
+            // 不同挂载点但同一云盘账号，已由服务端完成移动
         } else {
             let src_driver = state.storage_manager.get_driver(&src_mount.id).await
                 .ok_or_else(|| anyhow::anyhow!("源驱动不存在"))?;
@@ -599,6 +895,34 @@ async fn execute_move_operation(
     Ok(())
 }
 
+/// 获取上次中断时正在复制的文件的目标文件名（存在字节级断点时）
+async fn resumed_target_name(
+    state: &AppState,
+    task_id: &str,
+    src_dir: &str,
+    src_mount_path: &str,
+    name: &str,
+) -> Option<String> {
+    let src_file_path = if src_dir == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", src_dir, name)
+    };
+    if src_file_path.len() <= src_mount_path.len() {
+        return None;
+    }
+    let src_actual = fix_and_clean_path(&src_file_path[src_mount_path.len()..]);
+    let checkpoint = state.task_manager.get_copy_checkpoint(task_id, &src_actual).await?;
+    if checkpoint.uploaded_size == 0 {
+        return None;
+    }
+    checkpoint.target_path?
+        .rsplit('/')
+        .next()
+        .filter(|n| !n.is_empty())
+        .map(|n| n.to_string())
+}
+
 /// 执行移动操作（从断点继续）
 pub async fn execute_move_operation_resume(
     state: &AppState,
@@ -623,25 +947,12 @@ pub async fn execute_move_operation_resume(
     }
     
     // 获取所有挂载点
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await?;
-    
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
-    
-    let src_mount = get_first_mount(src_dir, &mounts)
+    let mounts = get_all_mounts(state).await?;
+    
+    // 聚合挂载：源为实际包含所选项的挂载点，目标按写入策略选择
+    let src_mount = find_source_mount(state, src_dir, names, &mounts).await
         .ok_or_else(|| anyhow::anyhow!("源目录不存在"))?;
-    let dst_mount = get_first_mount(dst_dir, &mounts)
+    let dst_mount = select_write_mount(state, dst_dir, &mounts, None).await
         .ok_or_else(|| anyhow::anyhow!("目标目录不存在"))?;
     
     let src_mount_path = fix_and_clean_path(&src_mount.mount_path);
@@ -675,16 +986,21 @@ pub async fn execute_move_operation_resume(
         }
         
         // 根据冲突策略处理文件名
-        let final_name = match strategy {
-            ConflictStrategy::Overwrite => name.clone(),
-            ConflictStrategy::Skip => {
-                if existing_names.contains(name) {
-                    processed += 1;
-                    continue;
+        // 上次中断时正在复制的文件沿用原目标文件名，以便从断点续传
+        let resumed_name = resumed_target_name(state, task_id, src_dir, &src_mount_path, name).await;
+        let final_name = match resumed_name {
+            Some(resumed) => resumed,
+            None => match strategy {
+                ConflictStrategy::Overwrite => name.clone(),
+                ConflictStrategy::Skip => {
+                    if existing_names.contains(name) {
+                        processed += 1;
+                        continue;
+                    }
+                    name.clone()
                 }
-                name.clone()
-            }
-            _ => resolve_conflict_name(name, &existing_names),
+                _ => resolve_conflict_name(name, &existing_names),
+            },
         };
         
         let src_file_path = if src_dir == "/" {
@@ -699,11 +1015,7 @@ pub async fn execute_move_operation_resume(
             format!("{}/{}", dst_dir, final_name)
         };
         
-        let src_actual = if src_file_path.len() > src_mount_path.len() {
-            fix_and_clean_path(&src_file_path[src_mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let src_actual = calculate_internal_path(&src_mount_path, &src_file_path);
         
         let dst_actual = if dst_file_path.len() > dst_mount_path.len() {
             fix_and_clean_path(&dst_file_path[dst_mount_path.len()..])
@@ -728,6 +1040,8 @@ pub async fn execute_move_operation_resume(
             if let Some(driver) = state.storage_manager.get_driver(&src_mount.id).await {
                 driver.move_item(&src_actual, &dst_actual).await?;
             }
+        } else if transfer_within_account(state, &src_mount.id, &dst_mount.id, &src_actual, &dst_actual, false).await {
+            // 不同挂载点但同一云盘账号，已由服务端完成移动
         } else {
             let src_driver = state.storage_manager.get_driver(&src_mount.id).await
                 .ok_or_else(|| anyhow::anyhow!("源驱动不存在"))?;
@@ -796,25 +1110,12 @@ pub async fn execute_copy_operation_resume(
     }
     
     // 获取所有挂载点
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await?;
-    
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
-    
-    let src_mount = get_first_mount(src_dir, &mounts)
+    let mounts = get_all_mounts(state).await?;
+    
+    // 聚合挂载：源为实际包含所选项的挂载点，目标按写入策略选择
+    let src_mount = find_source_mount(state, src_dir, names, &mounts).await
         .ok_or_else(|| anyhow::anyhow!("源目录不存在"))?;
-    let dst_mount = get_first_mount(dst_dir, &mounts)
+    let dst_mount = select_write_mount(state, dst_dir, &mounts, None).await
         .ok_or_else(|| anyhow::anyhow!("目标目录不存在"))?;
     
     let src_mount_path = fix_and_clean_path(&src_mount.mount_path);
@@ -846,16 +1147,21 @@ pub async fn execute_copy_operation_resume(
             }
         }
         
-        let final_name = match strategy {
-            ConflictStrategy::Overwrite => name.clone(),
-            ConflictStrategy::Skip => {
-                if existing_names.contains(name) {
-                    processed += 1;
-                    continue;
+        // 上次中断时正在复制的文件沿用原目标文件名，以便从断点续传
+        let resumed_name = resumed_target_name(state, task_id, src_dir, &src_mount_path, name).await;
+        let final_name = match resumed_name {
+            Some(resumed) => resumed,
+            None => match strategy {
+                ConflictStrategy::Overwrite => name.clone(),
+                ConflictStrategy::Skip => {
+                    if existing_names.contains(name) {
+                        processed += 1;
+                        continue;
+                    }
+                    name.clone()
                 }
-                name.clone()
-            }
-            _ => resolve_conflict_name(name, &existing_names),
+                _ => resolve_conflict_name(name, &existing_names),
+            },
         };
         
         let src_file_path = if src_dir == "/" {
@@ -870,11 +1176,7 @@ pub async fn execute_copy_operation_resume(
             format!("{}/{}", dst_dir, final_name)
         };
         
-        let src_actual = if src_file_path.len() > src_mount_path.len() {
-            fix_and_clean_path(&src_file_path[src_mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let src_actual = calculate_internal_path(&src_mount_path, &src_file_path);
         
         let dst_actual = if dst_file_path.len() > dst_mount_path.len() {
             fix_and_clean_path(&dst_file_path[dst_mount_path.len()..])
@@ -908,6 +1210,8 @@ pub async fn execute_copy_operation_resume(
             if let Some(driver) = state.storage_manager.get_driver(&src_mount.id).await {
                 driver.copy_item(&src_actual, &dst_actual).await?;
             }
+        } else if transfer_within_account(state, &src_mount.id, &dst_mount.id, &src_actual, &dst_actual, true).await {
+            // 不同挂载点但同一云盘账号，已由服务端完成复制
         } else {
             let src_driver = state.storage_manager.get_driver(&src_mount.id).await
                 .ok_or_else(|| anyhow::anyhow!("源驱动不存在"))?;
@@ -951,18 +1255,8 @@ pub async fn fs_copy(
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    // 权限验证
-    if !perms.copy_files && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有复制文件的权限"
-        })));
-    }
-    
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let src_dir = match join_user_path(&user_ctx.root_path, &req_src_dir) {
+    let src_dir = match user_ctx.join_path(&req_src_dir) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -971,7 +1265,7 @@ pub async fn fs_copy(
             })));
         }
     };
-    let dst_dir = match join_user_path(&user_ctx.root_path, &req_dst_dir) {
+    let dst_dir = match user_ctx.join_path(&req_dst_dir) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -981,6 +1275,20 @@ pub async fn fs_copy(
         }
     };
     
+    // 权限验证：源文件与目标目录都需要复制权限（按路径授权）
+    if !can_transfer(&user_ctx, Capability::Copy, &src_dir, &req.names, &dst_dir) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有复制文件的权限"
+        })));
+    }
+    if leaks_preview_only(&state, &user_ctx, &src_dir, &req.names, &dst_dir).await {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "仅预览的文件不能复制到未设置仅预览的目录"
+        })));
+    }
+    
     let user_id = get_user_id(&state, &cookies).await;
     let names = req.names.clone();
     
@@ -999,7 +1307,7 @@ pub async fn fs_copy(
         format!("复制 {} 个项目", names.len())
     };
     
-    let task = crate::task::Task::new_copy_move(
+    let mut task = crate::task::Task::new_copy_move(
         crate::task::TaskType::Copy,
         task_name,
         src_dir.clone(),
@@ -1008,9 +1316,16 @@ pub async fn fs_copy(
         strategy_str.clone(),
         user_id,
     );
+    let buffer_size = req.buffer_size
+        .map(yaolist_backend::transfer::clamp_buffer_size)
+        .unwrap_or_else(|| state.transfer_settings.get_buffer_size());
+    task.buffer_size = Some(buffer_size as u64);
     let task_id = task.id.clone();
     
     state.task_manager.add_task(task).await;
+    if let Some(ref group_id) = req.group_id {
+        state.task_manager.add_to_group(group_id, std::slice::from_ref(&task_id)).await;
+    }
     state.task_manager.start_task(&task_id).await;
     
     // 创建任务控制标志
@@ -1020,7 +1335,7 @@ pub async fn fs_copy(
     let state_clone = state.clone();
     let task_id_clone = task_id.clone();
     
-    tokio::spawn(async move {
+    yaolist_backend::request_id::spawn(async move {
         let result = execute_copy_operation(&state_clone, &src_dir, &dst_dir, &names, &task_id_clone, strategy, control.clone()).await;
         
         match result {
@@ -1058,25 +1373,12 @@ async fn execute_copy_operation(
     strategy: ConflictStrategy,
     _control: std::sync::Arc<crate::task::TaskControl>,
 ) -> anyhow::Result<()> {
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await?;
-    
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
-    
-    let src_mount = get_first_mount(src_dir, &mounts)
+    let mounts = get_all_mounts(state).await?;
+    
+    // 聚合挂载：源为实际包含所选项的挂载点，目标按写入策略选择
+    let src_mount = find_source_mount(state, src_dir, names, &mounts).await
         .ok_or_else(|| anyhow::anyhow!("源目录不存在"))?;
-    let dst_mount = get_first_mount(dst_dir, &mounts)
+    let dst_mount = select_write_mount(state, dst_dir, &mounts, None).await
         .ok_or_else(|| anyhow::anyhow!("目标目录不存在"))?;
     
     let src_mount_path = fix_and_clean_path(&src_mount.mount_path);
@@ -1092,11 +1394,7 @@ async fn execute_copy_operation(
         } else {
             format!("{}/{}", src_dir, name)
         };
-        let src_actual = if src_file_path.len() > src_mount_path.len() {
-            fix_and_clean_path(&src_file_path[src_mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let src_actual = calculate_internal_path(&src_mount_path, &src_file_path);
         let parent = src_actual.rsplitn(2, '/').nth(1).unwrap_or("/");
         if let Ok(entries) = src_driver.list(parent).await {
             let filename = src_actual.split('/').last().unwrap_or("");
@@ -1143,11 +1441,7 @@ async fn execute_copy_operation(
             format!("{}/{}", dst_dir, final_name)
         };
         
-        let src_actual = if src_file_path.len() > src_mount_path.len() {
-            fix_and_clean_path(&src_file_path[src_mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let src_actual = calculate_internal_path(&src_mount_path, &src_file_path);
         
         let dst_actual = if dst_file_path.len() > dst_mount_path.len() {
             fix_and_clean_path(&dst_file_path[dst_mount_path.len()..])
@@ -1180,6 +1474,8 @@ async fn execute_copy_operation(
             if let Some(driver) = state.storage_manager.get_driver(&src_mount.id).await {
                 driver.copy_item(&src_actual, &dst_actual).await?;
             }
+        } else if transfer_within_account(state, &src_mount.id, &dst_mount.id, &src_actual, &dst_actual, true).await {
+            // 不同挂载点但同一云盘账号，已由服务端完成复制
         } else {
             let src_driver = state.storage_manager.get_driver(&src_mount.id).await
                 .ok_or_else(|| anyhow::anyhow!("源驱动不存在"))?;
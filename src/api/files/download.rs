@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use std::io::Write;
 use axum::{
-    extract::{State, Path, Query, ConnectInfo},
+    extract::{State, Path, Query},
     http::{StatusCode, header, HeaderMap, Method},
     response::Response,
     body::Body,
@@ -14,16 +14,45 @@ use tokio_util::io::ReaderStream;
 use chrono::{Utc, Duration};
 
 use crate::state::AppState;
-use crate::api::file_resolver::{select_driver_for_download, select_driver_for_download_with_ip};
+use crate::api::file_resolver::{select_driver_for_download_excluding, get_mount_path, UserContext};
+use std::collections::HashSet;
 use yaolist_backend::utils::fix_and_clean_path;
+use yaolist_backend::access::Capability;
+use yaolist_backend::client_ip::ClientIp;
 use yaolist_backend::download::{ThrottledStream, TrafficCountingStream};
 
 use super::{
-    get_user_context, join_user_path, get_user_id, generate_token,
-    DownloadToken, DOWNLOAD_TOKENS,
+    get_user_context, get_user_permissions, get_user_id, generate_token, DOWNLOAD_TOKENS,
+    DownloadToken, store_download_token, find_download_token, decide_download_link, lookup_file_size,
+    is_preview_only, path_preview_only, path_watermarked, watermark_viewer,
 };
 use crate::api::stats;
 
+/// 按全局限速包装下载流，游客下载额外共享游客限速，用户限速作用于每个下载
+pub(super) fn throttled_body<S, E>(state: &AppState, stream: S, guest: bool, speed_limit: i64) -> Body
+where
+    S: futures::Stream<Item = Result<bytes::Bytes, E>> + Unpin + Send + 'static,
+    E: Into<axum::BoxError> + 'static,
+{
+    let stream = ThrottledStream::with_speed(stream, speed_limit);
+    let global = (state.download_settings.get_max_speed() > 0).then(|| state.download_settings.get_limiter());
+    let guest = guest.then(|| state.guest.get_limiter()).filter(|l| l.get_rate() > 0);
+    match (global, guest) {
+        (Some(global), Some(guest)) => Body::from_stream(ThrottledStream::new(ThrottledStream::new(stream, global), guest)),
+        (Some(limiter), None) | (None, Some(limiter)) => Body::from_stream(ThrottledStream::new(stream, limiter)),
+        (None, None) => Body::from_stream(stream),
+    }
+}
+
+/// 仅预览的响应：禁止缓存、嗅探与直接打开，并在沙箱中展示
+fn preview_only_headers(builder: axum::http::response::Builder) -> axum::http::response::Builder {
+    builder
+        .header(header::CACHE_CONTROL, "private, no-store")
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header("X-Download-Options", "noopen")
+        .header(header::CONTENT_SECURITY_POLICY, "sandbox")
+}
+
 /// 生成短一点的签名用于直链
 fn generate_sign() -> String {
     use rand::Rng;
@@ -43,6 +72,7 @@ pub struct FsDownloadReq {
 pub async fn fs_get_download_url(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    ClientIp(client_ip): ClientIp,
     cookies: Cookies,
     Json(req): Json<FsDownloadReq>,
 ) -> Result<Json<Value>, StatusCode> {
@@ -50,18 +80,20 @@ pub async fn fs_get_download_url(
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
     
-    // 权限验证
-    if !perms.read_files && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有下载文件的权限"
-        })));
+    // 游客下载受游客访问策略限制
+    let guest_policy = user_ctx.is_guest.then(|| state.guest.get());
+    if let Some(ref policy) = guest_policy {
+        if !policy.allow_download || !policy.path_browsable(&req_path) {
+            return Ok(Json(json!({
+                "code": 403,
+                "message": "游客不允许下载该文件"
+            })));
+        }
     }
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -71,70 +103,63 @@ pub async fn fs_get_download_url(
         }
     };
     
+    // 权限验证（按路径授权）
+    if !user_ctx.can(Capability::Read, &path) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有下载文件的权限"
+        })));
+    }
+    
+    // 获取用户ID用于流量统计
+    let user_id = get_user_id(&state, &cookies).await;
+    
+    // 下载流量配额与限速取自用户组默认设置（可按用户覆盖），管理员不受限制
+    let limited = !user_ctx.is_guest && !user_ctx.permissions.is_admin;
+    if let (true, Some(id)) = (limited && user_ctx.settings.traffic_quota > 0, &user_id) {
+        let used: i64 = sqlx::query_scalar("SELECT total_traffic FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .unwrap_or(0);
+        if user_ctx.settings.quota_exceeded(used) {
+            return Ok(Json(json!({
+                "code": 403,
+                "message": "下载流量配额已用完"
+            })));
+        }
+        if user_ctx.settings.quota_warning_due(used) {
+            tokio::spawn(crate::api::notification::notify_quota_warning(
+                state.clone(), id.clone(), user_ctx.settings.traffic_quota, used,
+            ));
+        }
+    }
+    
     // Use configured expiry or request param or default / 使用配置的有效期或请求参数或默认值
     let configured_expiry = state.download_settings.get_link_expiry_minutes() as i64;
     let expire_minutes = req.expire_minutes.unwrap_or(configured_expiry);
     tracing::debug!("fs_get_download_url: expiry={}min", expire_minutes);
+    let expires_at = Utc::now() + Duration::minutes(expire_minutes);
     
-    // 使用file_resolver的负载均衡选择驱动（302优先+轮询）
-    if let Some(selected) = select_driver_for_download(&state, &path).await {
-        let token = generate_token();
-        let expires_at = Utc::now() + Duration::minutes(expire_minutes);
-        
-        // 获取文件大小
-        let file_size = if let Some(driver) = state.storage_manager.get_driver(&selected.driver_id).await {
-            let parent_path = selected.internal_path.rsplitn(2, '/').nth(1).unwrap_or("/");
-            let filename = selected.internal_path.split('/').last().unwrap_or("");
-            driver.list(parent_path).await
-                .ok()
-                .and_then(|entries| {
-                    entries.iter()
-                        .find(|e| e.name == filename && !e.is_dir)
-                        .map(|e| e.size)
-                })
-        } else {
-            None
-        };
-        
-        // 获取用户ID用于流量统计
-        let user_id = get_user_id(&state, &cookies).await;
-        
-        let download_token = DownloadToken {
-            path: selected.internal_path,
-            driver_id: selected.driver_id,
-            expires_at,
-            can_direct_link: selected.can_direct_link,
-            file_size,
-            user_id,
-        };
-        
-        // 存储令牌
-        {
-            let mut tokens = DOWNLOAD_TOKENS.write().await;
-            tokens.insert(token.clone(), download_token);
-        }
-        
-        // Build download URL with configured domain if set / 如果配置了下载域名则使用配置的域名
-        // Get scheme from X-Forwarded-Proto header (reverse proxy support) / 从反代请求头获取协议
-        let scheme = headers.get("x-forwarded-proto")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("");
-        let download_path = format!("/download/{}", token);
-        let configured_domain = state.download_settings.get_download_domain();
-        tracing::debug!("fs_get_download_url: configured_domain={}, scheme={}", configured_domain, scheme);
-        let download_url = state.download_settings.build_download_url(&download_path, scheme);
-        tracing::debug!("fs_get_download_url: download_url={}", download_url);
-        
-        // 注意：流量统计移到实际下载时进行
-        // - 302重定向：统计整个文件大小
-        // - 本地中转：统计实际传输流量
-        
+    // Get scheme from X-Forwarded-Proto header (reverse proxy support) / 从反代请求头获取协议
+    let scheme = headers.get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    
+    // 注意：流量统计移到实际下载时进行
+    // - 302重定向：统计整个文件大小
+    // - 本地中转：统计实际传输流量
+    // 仅预览的路径同样签发链接，但只能在线预览（前端据此隐藏下载按钮）
+    let preview_only = is_preview_only(&state, &user_ctx, &path).await;
+    if let Some(download_url) = issue_download_url(&state, &user_ctx, user_id, &path, client_ip, scheme, expires_at).await {
         return Ok(Json(json!({
             "code": 200,
             "message": "success",
             "data": {
                 "url": download_url,
-                "expires_at": expires_at.to_rfc3339()
+                "expires_at": expires_at.to_rfc3339(),
+                "preview_only": preview_only
             }
         })));
     }
@@ -145,13 +170,72 @@ pub async fn fs_get_download_url(
     })))
 }
 
+/// 为用户签发下载令牌并返回下载链接，文件不存在时返回 None
+///
+/// 调用方负责检查读取权限、游客下载策略与流量配额。
+pub async fn issue_download_url(
+    state: &AppState,
+    user_ctx: &UserContext,
+    user_id: Option<String>,
+    path: &str,
+    client_ip: std::net::IpAddr,
+    scheme: &str,
+    expires_at: chrono::DateTime<Utc>,
+) -> Option<String> {
+    // 用户限速取自用户组默认设置（可按用户覆盖），管理员不受限制
+    let limited = !user_ctx.is_guest && !user_ctx.permissions.is_admin;
+    let speed_limit = if limited { user_ctx.settings.download_speed_limit } else { 0 };
+    
+    // 使用file_resolver的负载均衡选择驱动（302优先+轮询），跳过对用户隐藏的存储
+    let hidden: HashSet<String> = user_ctx.hidden_drivers.iter().cloned().collect();
+    let selected = select_driver_for_download_excluding(state, path, Some(client_ip), &hidden).await?;
+    let token = generate_token();
+    
+    // 获取文件大小
+    let file_size = lookup_file_size(state, &selected.driver_id, &selected.internal_path).await;
+    
+    // 游客限速与用户限速只能作用于本地中转，限速时不走302直链
+    let throttled = speed_limit > 0 || (user_ctx.is_guest && state.guest.get().download_speed_limit > 0);
+    // 仅预览的文件不能暴露存储直链，始终本地中转
+    let preview_only = is_preview_only(state, user_ctx, path).await;
+    // 需要水印的图片与 PDF 在服务器上处理，同样不走302直链
+    let watermark = watermark_viewer(state, user_ctx, user_id.as_deref(), path).await;
+    let decision = decide_download_link(
+        state, &selected.driver_id, selected.can_direct_link && !preview_only && watermark.is_none(),
+        Some(client_ip), file_size, throttled,
+    ).await;
+    let download_token = DownloadToken {
+        path: selected.internal_path,
+        driver_id: selected.driver_id,
+        expires_at,
+        can_direct_link: decision.is_redirect(),
+        file_size,
+        user_id,
+        guest: user_ctx.is_guest,
+        speed_limit,
+        preview_only,
+        watermark,
+    };
+    
+    // 存储令牌
+    store_download_token(state, &token, download_token).await;
+    
+    // Build download URL with configured domain if set / 如果配置了下载域名则使用配置的域名
+    let download_path = format!("/download/{}", token);
+    let configured_domain = state.download_settings.get_download_domain();
+    tracing::debug!("issue_download_url: configured_domain={}, scheme={}", configured_domain, scheme);
+    let download_url = state.download_settings.build_download_url(&download_path, scheme);
+    tracing::debug!("issue_download_url: download_url={}", download_url);
+    Some(download_url)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DownloadQuery {
     pub sign: Option<String>,
 }
 
 /// 解析 Range 请求头，返回 (start, end)
-fn parse_range_header(range_header: Option<&str>, file_size: u64) -> Option<(u64, u64)> {
+pub(super) fn parse_range_header(range_header: Option<&str>, file_size: u64) -> Option<(u64, u64)> {
     let range_str = range_header?;
     if !range_str.starts_with("bytes=") {
         return None;
@@ -206,38 +290,32 @@ pub async fn fs_download(
         }
     }
     
-    // 清理过期令牌
-    {
-        let mut tokens = DOWNLOAD_TOKENS.write().await;
-        let now = Utc::now();
-        tokens.retain(|_, v| v.expires_at > now);
-    }
-    
-    // 查找令牌 / Find token
-    let download_token = {
-        let tokens = DOWNLOAD_TOKENS.read().await;
-        if let Some(t) = tokens.get(&token) {
-            if t.expires_at <= Utc::now() {
-                drop(tokens);
-                let mut tokens = DOWNLOAD_TOKENS.write().await;
-                tokens.remove(&token);
-                return Err(StatusCode::NOT_FOUND);
-            }
-            t.clone()
-        } else {
-            return Err(StatusCode::NOT_FOUND);
-        }
-    };
+    // 查找令牌（同时清理过期令牌） / Find token
+    // 令牌在有效期内可重复使用，下载工具可用同一令牌并发发起多个Range请求
+    let mut download_token = find_download_token(&state, &token).await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let is_range = headers.contains_key(header::RANGE);
     
     // 获取驱动
     let driver = state.storage_manager.get_driver(&download_token.driver_id).await
         .ok_or(StatusCode::NOT_FOUND)?;
     
     // 如果驱动支持直链，尝试获取直链并302重定向（所有请求包括Range都走302）
-    if download_token.can_direct_link {
-        if let Ok(Some(direct_url)) = driver.get_direct_link(&download_token.path).await {
+    if download_token.can_direct_link && !download_token.preview_only && download_token.watermark.is_none() {
+        let direct_link = driver.get_direct_link(&download_token.path).await;
+        if let Err(ref e) = direct_link {
+            state.load_balance.report_failure(&download_token.driver_id, &e.to_string()).await;
+        }
+        if let Ok(Some(direct_url)) = direct_link {
+            // 按挂载点规则改写直链域名（CDN/Worker）
+            let direct_url = match get_mount_path(&state, &download_token.driver_id).await {
+                Some(mount_path) => state.download_settings.rewrite_direct_link(&mount_path, &download_token.path, &direct_url),
+                None => direct_url,
+            };
             tracing::debug!("fs_download: 302重定向到直链 url={}", direct_url);
             
+            state.download_transfers.record_redirect(&token, download_token.expires_at, is_range);
+            
             // 302重定向时统计整个文件大小的流量
             if let Some(ref user_id) = download_token.user_id {
                 stats::record_download(&state.db, user_id, download_token.file_size).await;
@@ -272,9 +350,20 @@ pub async fn fs_download(
     }
     
     // 不支持直链或获取失败，使用流式代理
-    // 使用缓存的文件大小（避免每次请求都调用list）
+    // 使用缓存的文件大小（避免每次请求都调用list），未知时查询一次并写回令牌，后续Range请求可直接使用
+    if download_token.file_size.is_none() {
+        download_token.file_size = lookup_file_size(&state, &download_token.driver_id, &download_token.path).await;
+        if download_token.file_size.is_some() {
+            store_download_token(&state, &token, download_token.clone()).await;
+        }
+    }
     let file_size = download_token.file_size;
     
+    if let Some(viewer) = download_token.watermark.clone() {
+        return watermarked_download(&state, &download_token, &viewer, &method).await;
+    }
+    let transfer = state.download_transfers.begin(&token, download_token.expires_at, is_range);
+    
     tracing::debug!("fs_download proxy: using cached file_size={:?}", file_size);
     
     // 获取文件名和Content-Type
@@ -304,17 +393,16 @@ pub async fn fs_download(
             
             let stream = ReaderStream::new(reader);
             // 包装流量统计（本地中转统计实际传输流量）
-            let stream = TrafficCountingStream::new(stream, download_token.user_id.clone(), state.db.clone());
+            let stream = TrafficCountingStream::new(stream, download_token.user_id.clone(), state.db.clone())
+                .with_transfer(transfer);
             // Apply global bandwidth limiting (shared across all downloads) / 应用全局带宽限制（所有下载共享）
-            let max_speed = state.download_settings.get_max_speed();
-            let body = if max_speed > 0 {
-                let limiter = state.download_settings.get_limiter();
-                Body::from_stream(ThrottledStream::new(stream, limiter))
-            } else {
-                Body::from_stream(stream)
-            };
+            let body = throttled_body(&state, stream, download_token.guest, download_token.speed_limit);
             
-            return Ok(Response::builder()
+            let mut response = Response::builder();
+            if download_token.preview_only {
+                response = preview_only_headers(response);
+            }
+            return Ok(response
                 .status(StatusCode::PARTIAL_CONTENT)
                 .header(header::CONTENT_TYPE, &content_type)
                 .header(header::ACCEPT_RANGES, "bytes")
@@ -331,24 +419,23 @@ pub async fn fs_download(
     
     let stream = ReaderStream::new(reader);
     // 包装流量统计（本地中转统计实际传输流量）
-    let stream = TrafficCountingStream::new(stream, download_token.user_id.clone(), state.db.clone());
+    let stream = TrafficCountingStream::new(stream, download_token.user_id.clone(), state.db.clone())
+        .with_transfer(transfer);
     // Apply global bandwidth limiting (shared across all downloads) / 应用全局带宽限制（所有下载共享）
     let max_speed = state.download_settings.get_max_speed();
-    tracing::info!("fs_download: max_speed={} bytes/s ({}MB/s)", max_speed, max_speed / 1024 / 1024);
-    let body = if max_speed > 0 {
-        let limiter = state.download_settings.get_limiter();
-        tracing::info!("fs_download: applying bandwidth limit, limiter rate={}", limiter.get_rate());
-        Body::from_stream(ThrottledStream::new(stream, limiter))
-    } else {
-        tracing::info!("fs_download: no bandwidth limit");
-        Body::from_stream(stream)
-    };
+    tracing::info!("fs_download: max_speed={} bytes/s ({}MB/s), guest={}", max_speed, max_speed / 1024 / 1024, download_token.guest);
+    let body = throttled_body(&state, stream, download_token.guest, download_token.speed_limit);
     
+    // 仅预览时以内联方式返回，浏览器直接展示而不是保存
+    let disposition = if download_token.preview_only { "inline" } else { "attachment" };
     let mut response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, content_type)
         .header(header::ACCEPT_RANGES, "bytes")
-        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", filename, filename_encoded));
+        .header(header::CONTENT_DISPOSITION, format!("{}; filename=\"{}\"; filename*=UTF-8''{}", disposition, filename, filename_encoded));
+    if download_token.preview_only {
+        response = preview_only_headers(response);
+    }
     
     // 如果获取到文件大小，添加Content-Length header
     if let Some(size) = file_size {
@@ -363,6 +450,104 @@ pub async fn fs_download(
     Ok(response.body(body).unwrap())
 }
 
+/// 读取整个文件并加上查看者与时间水印后返回，失败时拒绝而不是返回原文件
+async fn watermarked_download(
+    state: &AppState,
+    download_token: &DownloadToken,
+    viewer: &str,
+    method: &Method,
+) -> Result<Response, StatusCode> {
+    use tokio::io::AsyncReadExt;
+    use yaolist_backend::watermark;
+    
+    if download_token.file_size.is_some_and(|size| size > watermark::MAX_WATERMARK_SOURCE) {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let driver = state.storage_manager.get_driver(&download_token.driver_id).await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let mut data = Vec::new();
+    driver.open_reader(&download_token.path, None).await
+        .map_err(|_| StatusCode::NOT_FOUND)?
+        .take(watermark::MAX_WATERMARK_SOURCE + 1)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+    if data.len() as u64 > watermark::MAX_WATERMARK_SOURCE {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    
+    let filename = download_token.path.rsplit('/').next().unwrap_or("download").to_string();
+    let text = watermark::label(viewer, &chrono::Local::now());
+    let name = filename.clone();
+    let marked = tokio::task::spawn_blocking(move || watermark::apply(&name, &data, &text)).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            tracing::warn!("fs_download: watermark failed for {}: {}", download_token.path, e);
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+    
+    if let Some(ref user_id) = download_token.user_id {
+        stats::record_download(&state.db, user_id, Some(marked.data.len() as u64)).await;
+    }
+    
+    let disposition = if download_token.preview_only { "inline" } else { "attachment" };
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, marked.content_type)
+        .header(header::CONTENT_LENGTH, marked.data.len())
+        .header(header::CONTENT_DISPOSITION, format!(
+            "{}; filename=\"{}\"; filename*=UTF-8''{}", disposition, filename, urlencoding::encode(&filename)
+        ));
+    // 水印包含查看者，不能被共享缓存
+    response = if download_token.preview_only {
+        preview_only_headers(response)
+    } else {
+        response.header(header::CACHE_CONTROL, "private, no-store")
+    };
+    if *method == Method::HEAD {
+        return Ok(response.body(Body::empty()).unwrap());
+    }
+    Ok(response.body(Body::from(marked.data)).unwrap())
+}
+
+/// POST /api/admin/fs/download_tokens - 下载令牌使用情况（请求数、并发连接、中转流量）
+pub async fn admin_download_tokens(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, StatusCode> {
+    // 令牌与传输记录不分工作区，只对默认工作区的实例管理员开放
+    if !yaolist_backend::workspace::current().is_default() || !get_user_permissions(&state, &cookies).await.is_admin {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "需要实例管理员权限"
+        })));
+    }
+    
+    let tokens = DOWNLOAD_TOKENS.read().await;
+    let content: Vec<Value> = state.download_transfers.list().into_iter()
+        .map(|(token, stats)| {
+            let info = tokens.get(&token);
+            json!({
+                // 令牌本身即下载凭证，只返回前缀
+                "token": token.chars().take(8).collect::<String>(),
+                "path": info.map(|t| t.path.clone()),
+                "driver_id": info.map(|t| t.driver_id.clone()),
+                "user_id": info.and_then(|t| t.user_id.clone()),
+                "file_size": info.and_then(|t| t.file_size),
+                "stats": stats
+            })
+        })
+        .collect();
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "total": content.len(),
+            "content": content
+        }
+    })))
+}
+
 /// 将目录压缩为zip（使用流式写入，但最终返回完整数据）
 fn create_zip_from_dir(dir_path: &std::path::Path) -> Result<Vec<u8>, std::io::Error> {
     use std::io::Cursor;
@@ -439,8 +624,19 @@ pub async fn fs_get_direct_link(
         })));
     }
     
+    // 直链不经过游客限速，仅在游客可下载且不限速时开放
+    if user_ctx.is_guest {
+        let policy = state.guest.get();
+        if !policy.allow_download || policy.download_speed_limit > 0 || !policy.path_browsable(&req_path) {
+            return Ok(Json(json!({
+                "code": 403,
+                "message": "游客不允许创建该文件的直链"
+            })));
+        }
+    }
+    
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -450,6 +646,30 @@ pub async fn fs_get_direct_link(
         }
     };
     
+    // 投递箱等不可读取的路径不能创建直链
+    if !user_ctx.can(Capability::Read, &path) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有读取该文件的权限"
+        })));
+    }
+    
+    // 直链对所有人公开，仅预览的路径不能创建（管理员也不例外）
+    if path_preview_only(&state, &path).await {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "该路径仅允许预览，不能创建直链"
+        })));
+    }
+    
+    // 直链不经过水印处理，需要水印的文件不能创建
+    if path_watermarked(&state, &path).await {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "该文件预览需要添加水印，不能创建直链"
+        })));
+    }
+    
     // 检查是否已有直链
     let existing: Option<(String,)> = sqlx::query_as(
         "SELECT sign FROM direct_links WHERE path = ?"
@@ -561,7 +781,7 @@ pub struct DlinkQuery {
 pub async fn direct_link_download(
     State(state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
-    ConnectInfo(addr): ConnectInfo<std::net::SocketAddr>,
+    ClientIp(client_ip): ClientIp,
     Path(sign): Path<String>,
     Query(query): Query<DlinkQuery>,
 ) -> Response {
@@ -578,8 +798,8 @@ pub async fn direct_link_download(
         }
     }
     
-    // 获取真实客户端IP（支持反代/CDN）
-    let client_ip = yaolist_backend::geoip::extract_client_ip(&headers, Some(addr.ip()));
+    // 真实客户端IP（经可信代理解析）
+    let client_ip = Some(client_ip);
     // sign 可能包含 /filename 部分，需要提取
     let sign = sign.split('/').next().unwrap_or(&sign).to_string();
     let url_only = query.url_only.unwrap_or(false);
@@ -626,6 +846,14 @@ pub async fn direct_link_download(
         }
     }
     
+    // 设为仅预览之前创建的直链同样不能再下载
+    if path_preview_only(&state, &path).await {
+        return direct_link_error_response(StatusCode::FORBIDDEN, "PREVIEW_ONLY", "该路径仅允许预览，不能通过直链下载");
+    }
+    if path_watermarked(&state, &path).await {
+        return direct_link_error_response(StatusCode::FORBIDDEN, "WATERMARK_REQUIRED", "该文件需要添加水印，不能通过直链下载");
+    }
+    
     // 检查访问次数
     if let Some(max_count) = max_access_count {
         if access_count >= max_count {
@@ -644,16 +872,65 @@ pub async fn direct_link_download(
     }
     
     // 使用file_resolver的多源聚合选择驱动（支持地区分流）
-    let selected = match select_driver_for_download_with_ip(&state, &path, client_ip).await {
-        Some(s) => s,
-        None => return direct_link_error_response(StatusCode::SERVICE_UNAVAILABLE, "DRIVER_ERROR", "存储驱动故障"),
+    // 故障转移模式下，成员获取直链失败时重试下一个成员
+    let mut tried: HashSet<String> = HashSet::new();
+    let (selected, driver, file_size, direct_url) = loop {
+        let selected = match select_driver_for_download_excluding(&state, &path, client_ip, &tried).await {
+            Some(s) => s,
+            None => return direct_link_error_response(StatusCode::SERVICE_UNAVAILABLE, "DRIVER_ERROR", "存储驱动故障"),
+        };
+        let driver = match state.storage_manager.get_driver(&selected.driver_id).await {
+            Some(d) => d,
+            None if selected.failover => {
+                state.load_balance.report_failure(&selected.driver_id, "Driver not loaded").await;
+                tried.insert(selected.driver_id);
+                continue;
+            }
+            None => return direct_link_error_response(StatusCode::SERVICE_UNAVAILABLE, "DRIVER_ERROR", "存储驱动故障"),
+        };
+        
+        // 获取文件大小（通过列出父目录找到文件）
+        let parent_path = std::path::Path::new(&selected.internal_path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| "/".to_string());
+        let file_name = selected.internal_path.split('/').next_back().unwrap_or("file");
+        let file_size: Option<u64> = match driver.list(&parent_path).await {
+            Ok(entries) => entries.iter()
+                .find(|e| e.name == file_name)
+                .map(|e| e.size),
+            Err(_) => None,
+        };
+        
+        let mut direct_url = None;
+        let decision = decide_download_link(
+            &state, &selected.driver_id, selected.can_direct_link, client_ip, file_size, false,
+        ).await;
+        if decision.is_redirect() {
+            let started = std::time::Instant::now();
+            match driver.get_direct_link(&selected.internal_path).await {
+                Ok(Some(url)) => {
+                    let latency_ms = started.elapsed().as_millis() as u64;
+                    state.load_balance.report_success(&selected.driver_id, Some(latency_ms)).await;
+                    direct_url = Some(url);
+                }
+                Ok(None) => {
+                    tracing::warn!("dlink: get_direct_link returned None, path={}", selected.internal_path);
+                }
+                Err(e) => {
+                    tracing::warn!("dlink: get_direct_link failed: {}, path={}", e, selected.internal_path);
+                    state.load_balance.report_failure(&selected.driver_id, &e.to_string()).await;
+                    if selected.failover {
+                        tried.insert(selected.driver_id);
+                        continue;
+                    }
+                }
+            }
+        }
+        break (selected, driver, file_size, direct_url);
     };
     
-    let actual_path = selected.internal_path;
-    let driver = match state.storage_manager.get_driver(&selected.driver_id).await {
-        Some(d) => d,
-        None => return direct_link_error_response(StatusCode::SERVICE_UNAVAILABLE, "DRIVER_ERROR", "存储驱动故障"),
-    };
+    let actual_path = selected.internal_path.clone();
     
     let filename = actual_path.split('/').last().unwrap_or("file");
     let filename_encoded = urlencoding::encode(filename);
@@ -661,18 +938,6 @@ pub async fn direct_link_download(
         .first_or_octet_stream()
         .to_string();
     
-    // 获取文件大小（通过列出父目录找到文件）
-    let parent_path = std::path::Path::new(&actual_path)
-        .parent()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| "/".to_string());
-    let file_size: Option<u64> = match driver.list(&parent_path).await {
-        Ok(entries) => entries.iter()
-            .find(|e| e.name == filename)
-            .map(|e| e.size),
-        Err(_) => None,
-    };
-    
     // 获取直链创建者ID用于流量统计
     let link_user_id: Option<Option<String>> = sqlx::query_scalar(
         "SELECT user_id FROM direct_links WHERE sign = ?"
@@ -686,45 +951,40 @@ pub async fn direct_link_download(
     
     // 如果驱动支持直链且启用302重定向，返回302（或JSON格式URL）
     tracing::debug!("dlink: can_direct_link={}, path={}, url_only={}", selected.can_direct_link, actual_path, url_only);
-    if selected.can_direct_link {
-        match driver.get_direct_link(&actual_path).await {
-            Ok(Some(direct_url)) => {
-                // 如果请求url_only，返回JSON格式的URL（供下载器使用）
-                if url_only {
-                    tracing::info!("dlink: 返回JSON格式URL url={}", direct_url);
-                    let body = serde_json::json!({
-                        "code": 200,
-                        "url": direct_url
-                    });
-                    return Response::builder()
-                        .status(StatusCode::OK)
-                        .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
-                        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
-                        .body(Body::from(body.to_string()))
-                        .unwrap();
-                }
-                
-                // 302重定向时统计整个文件大小的流量
-                if let Some(ref uid) = link_user_id {
-                    stats::record_download(&state.db, uid, file_size).await;
-                }
-                
-                tracing::info!("dlink: 302重定向到直链 url={}", direct_url);
-                return Response::builder()
-                    .status(StatusCode::FOUND)
-                    .header(header::LOCATION, &direct_url)
-                    .header("Referrer-Policy", "no-referrer")
-                    .header(header::CACHE_CONTROL, "max-age=0, no-cache, no-store, must-revalidate")
-                    .body(Body::empty())
-                    .unwrap();
-            }
-            Ok(None) => {
-                tracing::warn!("dlink: get_direct_link returned None, path={}", actual_path);
-            }
-            Err(e) => {
-                tracing::warn!("dlink: get_direct_link failed: {}, path={}", e, actual_path);
-            }
+    if let Some(direct_url) = direct_url {
+        // 按挂载点规则改写直链域名（CDN/Worker）
+        let direct_url = match get_mount_path(&state, &selected.driver_id).await {
+            Some(mount_path) => state.download_settings.rewrite_direct_link(&mount_path, &actual_path, &direct_url),
+            None => direct_url,
+        };
+        // 如果请求url_only，返回JSON格式的URL（供下载器使用）
+        if url_only {
+            tracing::info!("dlink: 返回JSON格式URL url={}", direct_url);
+            let body = serde_json::json!({
+                "code": 200,
+                "url": direct_url
+            });
+            return Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .body(Body::from(body.to_string()))
+                .unwrap();
         }
+        
+        // 302重定向时统计整个文件大小的流量
+        if let Some(ref uid) = link_user_id {
+            stats::record_download(&state.db, uid, file_size).await;
+        }
+        
+        tracing::info!("dlink: 302重定向到直链 url={}", direct_url);
+        return Response::builder()
+            .status(StatusCode::FOUND)
+            .header(header::LOCATION, &direct_url)
+            .header("Referrer-Policy", "no-referrer")
+            .header(header::CACHE_CONTROL, "max-age=0, no-cache, no-store, must-revalidate")
+            .body(Body::empty())
+            .unwrap();
     }
     
     // 解析Range请求头
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use crate::api::file_resolver::get_user_mounts;
+use yaolist_backend::client_ip::ClientIp;
+use yaolist_backend::download_list::{DownloadItem, ListFormat};
+use yaolist_backend::error::ApiError;
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::gallery::{authorize_read, ensure_can_download, locate_file, walk_files, FoundFile};
+use super::{get_hide_rules, get_nearest_meta, get_user_id, is_preview_only, issue_download_url};
+
+/// 下载列表最多包含的文件数
+const MAX_EXPORT_FILES: usize = 10000;
+
+#[derive(Debug, Deserialize)]
+pub struct FsExportLinksReq {
+    /// 所在目录
+    pub path: Option<String>,
+    /// 选中的文件或目录名，为空时导出整个目录
+    #[serde(default)]
+    pub names: Vec<String>,
+    pub password: Option<String>,
+    /// metalink（默认）或 aria2
+    #[serde(default)]
+    pub format: ListFormat,
+    /// 下载链接的有效期（分钟），默认使用下载设置中的有效期
+    pub expire_minutes: Option<i64>,
+}
+
+/// POST /api/fs/export_links - 将选中的文件与目录（含子目录）导出为签名直链的 Metalink 或 aria2 输入文件，供下载工具批量下载
+pub async fn fs_export_links(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ClientIp(client_ip): ClientIp,
+    cookies: Cookies,
+    Json(req): Json<FsExportLinksReq>,
+) -> Result<Response, ApiError> {
+    let req_path = fix_and_clean_path(&req.path.unwrap_or_default());
+    let password = req.password.unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &req_path, &password).await?;
+
+    // 列表中是下载链接，遵守游客下载策略与流量配额
+    let user_id = get_user_id(&state, &cookies).await;
+    ensure_can_download(&state, &user_ctx, user_id.as_deref()).await?;
+
+    if req.names.iter().any(|n| n.is_empty() || n == "." || n == ".." || n.contains('/')) {
+        return Err(ApiError::BadRequest("无效的文件名".to_string()));
+    }
+    let meta = get_nearest_meta(&state, &path).await;
+    let hide_rules = get_hide_rules(meta.as_ref(), &path);
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
+
+    // (相对于所选位置的路径, 文件)
+    let mut files: Vec<(String, FoundFile)> = Vec::new();
+    let mut truncated = false;
+    if req.names.is_empty() {
+        let (found, cut) = walk_files(&state, &user_ctx, &path, &req_path, &password, true, MAX_EXPORT_FILES, |_| true).await?;
+        truncated = cut;
+        files.extend(found.into_iter().map(|f| (relative_to(&f.path, &req_path), f)));
+    }
+    for name in &req.names {
+        if files.len() >= MAX_EXPORT_FILES {
+            truncated = true;
+            break;
+        }
+        if !user_ctx.permissions.show_hidden_files && hide_rules.hides(name) {
+            continue;
+        }
+        let child_req = format!("{}/{}", req_path.trim_end_matches('/'), name);
+        let (_, child) = authorize_read(&state, &cookies, &child_req, &password).await?;
+        if let Some((_, internal_path, entry)) = locate_file(&state, &mounts, &child).await {
+            let file = FoundFile {
+                path: child_req,
+                full_path: child,
+                driver_id: String::new(),
+                internal_path,
+                entry,
+            };
+            files.push((name.clone(), file));
+            continue;
+        }
+        let (found, cut) = walk_files(
+            &state, &user_ctx, &child, &child_req, &password, true, MAX_EXPORT_FILES - files.len(), |_| true,
+        ).await?;
+        truncated |= cut;
+        files.extend(found.into_iter().map(|f| (format!("{}/{}", name, relative_to(&f.path, &child_req)), f)));
+    }
+    files.truncate(MAX_EXPORT_FILES);
+
+    let expire_minutes = req.expire_minutes
+        .unwrap_or(state.download_settings.get_link_expiry_minutes() as i64);
+    let expires_at = Utc::now() + Duration::minutes(expire_minutes);
+    let scheme = headers.get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    // 未配置下载域名时链接是相对路径，下载工具需要完整地址，使用当前访问的域名补全
+    let host = headers.get("x-forwarded-host")
+        .or_else(|| headers.get(header::HOST))
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost");
+    let mut items = Vec::with_capacity(files.len());
+    for (name, file) in &files {
+        // 仅预览的文件不提供下载链接
+        if is_preview_only(&state, &user_ctx, &file.full_path).await {
+            continue;
+        }
+        let Some(url) = issue_download_url(&state, &user_ctx, user_id.clone(), &file.full_path, client_ip, scheme, expires_at).await else {
+            continue;
+        };
+        let url = if url.starts_with('/') {
+            format!("{}://{}{}", if scheme.is_empty() { "http" } else { scheme }, host, url)
+        } else {
+            url
+        };
+        items.push(DownloadItem { name: name.clone(), url, size: file.entry.size });
+    }
+
+    let base_name = match req.names.as_slice() {
+        [single] => single.as_str(),
+        _ => req_path.rsplit('/').find(|s| !s.is_empty()).unwrap_or("download"),
+    };
+    let disposition = format!(
+        "attachment; filename*=UTF-8''{}.{}",
+        urlencoding::encode(base_name), req.format.extension(),
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, req.format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::HeaderName::from_static("x-truncated"), truncated.to_string()),
+        ],
+        req.format.render(&items),
+    ).into_response())
+}
+
+/// `path` 相对于目录 `dir` 的路径
+fn relative_to(path: &str, dir: &str) -> String {
+    path.strip_prefix(dir.trim_end_matches('/'))
+        .unwrap_or(path)
+        .trim_start_matches('/')
+        .to_string()
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::state::AppState;
+use crate::api::branding::site_base;
+use crate::api::file_resolver::UserContext;
+use yaolist_backend::client_ip::ClientIp;
+use yaolist_backend::error::ApiError;
+use yaolist_backend::feed::{self, Channel, Feed, FeedFormat, FeedItem};
+use yaolist_backend::models::UserPermissions;
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::gallery::walk_files;
+use super::{issue_download_url, path_preview_only};
+
+/// 订阅源遍历时最多收集的文件数（从中取最新的条目）
+const MAX_FEED_FILES: usize = 20000;
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    /// rss（默认）、atom 或 json
+    #[serde(default)]
+    pub format: FeedFormat,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedFileQuery {
+    pub path: String,
+    pub expires: i64,
+    pub sign: String,
+}
+
+/// 已启用的订阅源，不存在或已禁用时返回 404
+fn enabled_feed(state: &AppState, id: &str) -> Result<Feed, ApiError> {
+    state.feeds.get(id)
+        .filter(|f| f.enabled)
+        .ok_or_else(|| ApiError::NotFound("订阅源不存在".to_string()))
+}
+
+/// 订阅源以管理员开启时的授权读取目录：不受游客策略限制，但仍隐藏隐藏文件与带密码的子目录
+fn feed_reader() -> UserContext {
+    UserContext {
+        permissions: UserPermissions { read_files: true, ..Default::default() },
+        is_guest: false,
+        ..Default::default()
+    }
+}
+
+/// GET /feed/:id - 目录最近文件的 RSS / Atom / JSON Feed，文件链接带签名
+pub async fn get_feed(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Response, ApiError> {
+    let feed = enabled_feed(&state, &id)?;
+    let path = fix_and_clean_path(&feed.path);
+    let (files, _) = walk_files(&state, &feed_reader(), &path, &path, "", feed.recursive, MAX_FEED_FILES, |_| true).await?;
+
+    // 订阅源中的链接需要完整地址
+    let origin = site_base(&state, &headers);
+    let expires = feed.link_expiry(Utc::now().timestamp());
+    let all_items: Vec<FeedItem> = files.into_iter()
+        .map(|file| {
+            let sign = state.thumbnails.sign(&feed.link_subject(&file.full_path), expires);
+            FeedItem {
+                name: file.entry.name.clone(),
+                path: file.full_path.strip_prefix(path.trim_end_matches('/')).unwrap_or(&file.full_path)
+                    .trim_start_matches('/').to_string(),
+                size: file.entry.size,
+                modified: file.entry.modified.as_deref().and_then(feed::parse_modified),
+                url: format!(
+                    "{}/feed/{}/file?path={}&expires={}&sign={}",
+                    origin, urlencoding::encode(&feed.id), urlencoding::encode(&file.full_path), expires, sign,
+                ),
+            }
+        })
+        .collect();
+    // 仅预览的文件不提供下载链接
+    let mut items = Vec::new();
+    for item in feed::most_recent(all_items, feed.max_items as usize) {
+        if !path_preview_only(&state, &format!("{}/{}", path.trim_end_matches('/'), item.path)).await {
+            items.push(item);
+        }
+    }
+
+    let channel = Channel {
+        id: feed.id.clone(),
+        title: feed.display_title().to_string(),
+        description: feed.description.clone(),
+        home_url: format!("{}{}", origin, path),
+        feed_url: format!("{}/feed/{}?format={}", origin, urlencoding::encode(&feed.id), query.format.name()),
+    };
+    // 链接在当天结束前不变，允许阅读器与代理短时间缓存；
+    // 未配置下载域名时地址取自请求头，不允许共享缓存，以免缓存被污染
+    let cache_control = if state.download_settings.site_origin().is_some() {
+        "public, max-age=300"
+    } else {
+        "private, max-age=300"
+    };
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, query.format.content_type().to_string()),
+            (header::CACHE_CONTROL, cache_control.to_string()),
+        ],
+        query.format.render(&channel, &items),
+    ).into_response())
+}
+
+/// GET /feed/:id/file - 校验订阅源签名后签发临时下载链接并重定向（按游客限速）
+pub async fn get_feed_file(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<String>,
+    Query(query): Query<FeedFileQuery>,
+) -> Result<Response, ApiError> {
+    let feed = enabled_feed(&state, &id)?;
+    let path = fix_and_clean_path(&query.path);
+    let now = Utc::now();
+    if !feed.contains(&path) || !state.thumbnails.verify(&feed.link_subject(&path), query.expires, &query.sign, now.timestamp()) {
+        return Err(ApiError::Forbidden("订阅链接无效或已过期".to_string()));
+    }
+    // 签名之后才设为仅预览的文件同样不能下载
+    if path_preview_only(&state, &path).await {
+        return Err(ApiError::Forbidden("该路径仅允许预览，不能下载".to_string()));
+    }
+
+    let scheme = headers.get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let expires_at = now + chrono::Duration::minutes(state.download_settings.get_link_expiry_minutes() as i64);
+    let url = issue_download_url(&state, &UserContext::default(), None, &path, client_ip, scheme, expires_at).await
+        .ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+    Ok(Redirect::temporary(&url).into_response())
+}
@@ -0,0 +1,425 @@
+use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::AsyncReadExt;
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use crate::api::branding::workspace_base;
+use crate::api::file_resolver::{get_all_mounts, get_matching_mounts, get_user_mounts, calculate_internal_path, MountInfo, UserContext};
+use yaolist_backend::access::Capability;
+use yaolist_backend::error::ApiError;
+use yaolist_backend::gallery::{self, ImageInfo, ThumbnailSize};
+use yaolist_backend::storage::{DriverBox, Entry};
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::{
+    get_user_context, get_nearest_password_meta, can_access_password,
+    get_nearest_meta, get_hide_rules, get_virtual_files_by_path,
+};
+
+/// 递归时最多遍历的目录数
+const MAX_WALK_DIRS: usize = 2000;
+/// 相册最多收集的图片数
+const MAX_GALLERY_IMAGES: usize = 50000;
+
+#[derive(Debug, Deserialize)]
+pub struct FsGalleryReq {
+    pub path: Option<String>,
+    pub password: Option<String>,
+    /// 包含子目录中的图片
+    pub recursive: Option<bool>,
+    pub page: Option<i32>,
+    pub per_page: Option<i32>,
+    pub sort_by: Option<String>,    // modified, name, size
+    pub sort_order: Option<String>, // asc, desc（默认desc，最新的在前）
+}
+
+/// image_meta 缓存行：(大小, 修改时间, 拍摄时间, 宽, 高)
+type ImageMetaRow = (i64, Option<String>, Option<String>, Option<i64>, Option<i64>);
+
+/// 遍历目录时找到的文件
+pub(super) struct FoundFile {
+    /// 用户看到的路径
+    pub path: String,
+    /// 完整路径（含用户根路径）
+    pub full_path: String,
+    pub driver_id: String,
+    pub internal_path: String,
+    pub entry: Entry,
+}
+
+/// 检查用户能否读取 `req_path`（游客策略、路径授权与密码），返回用户上下文与完整路径
+pub(crate) async fn authorize_read(
+    state: &AppState,
+    cookies: &Cookies,
+    req_path: &str,
+    password: &str,
+) -> Result<(UserContext, String), ApiError> {
+    let user_ctx = get_user_context(state, cookies).await;
+    if !user_ctx.permissions.read_files && user_ctx.grants.is_empty() && user_ctx.shared.is_empty() {
+        return Err(ApiError::Forbidden("guest_disabled".to_string()));
+    }
+    if user_ctx.is_guest && !state.guest.get().path_browsable(req_path) {
+        return Err(ApiError::Forbidden("游客无权访问该路径".to_string()));
+    }
+    let path = user_ctx.join_path(req_path).map_err(ApiError::Forbidden)?;
+    if !user_ctx.can(Capability::Read, &path) {
+        return Err(ApiError::Forbidden("没有读取该路径的权限".to_string()));
+    }
+    let password_meta = get_nearest_password_meta(state, &path).await;
+    if !can_access_password(password_meta.as_ref(), &path, password) {
+        return Err(ApiError::Forbidden("password is incorrect or you have no permission".to_string()));
+    }
+    Ok((user_ctx, path))
+}
+
+/// 检查游客下载策略与用户流量配额（签发下载链接或流式读取前调用）
+pub(super) async fn ensure_can_download(state: &Arc<AppState>, user_ctx: &UserContext, user_id: Option<&str>) -> Result<(), ApiError> {
+    if user_ctx.is_guest && !state.guest.get().allow_download {
+        return Err(ApiError::Forbidden("游客不允许下载该文件".to_string()));
+    }
+    let limited = !user_ctx.is_guest && !user_ctx.permissions.is_admin;
+    if let (true, Some(id)) = (limited && user_ctx.settings.traffic_quota > 0, user_id) {
+        let used: i64 = sqlx::query_scalar("SELECT total_traffic FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&state.db)
+            .await?
+            .unwrap_or(0);
+        if user_ctx.settings.quota_exceeded(used) {
+            return Err(ApiError::Forbidden("下载流量配额已用完".to_string()));
+        }
+        if user_ctx.settings.quota_warning_due(used) {
+            tokio::spawn(crate::api::notification::notify_quota_warning(
+                state.clone(), id.to_string(), user_ctx.settings.traffic_quota, used,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 按列表找到文件所在的挂载（同名时优先级高的挂载优先）：(驱动, 驱动内路径, 条目)
+pub(super) async fn locate_file(state: &AppState, mounts: &[MountInfo], path: &str) -> Option<(DriverBox, String, Entry)> {
+    let (parent, name) = path.rsplit_once('/')?;
+    let parent = fix_and_clean_path(parent);
+    for mount in get_matching_mounts(&parent, mounts) {
+        let Some(driver) = state.storage_manager.get_driver(&mount.id).await else {
+            continue;
+        };
+        let internal_dir = calculate_internal_path(&mount.mount_path, &parent);
+        if let Ok(entries) = driver.list(&internal_dir).await {
+            if let Some(entry) = entries.into_iter().find(|e| e.name == name && !e.is_dir) {
+                let internal_path = format!("{}/{}", internal_dir.trim_end_matches('/'), name);
+                return Some((driver, internal_path, entry));
+            }
+        }
+    }
+    None
+}
+
+/// 列出目录中用户可见的条目：(条目, 驱动ID, 驱动内路径)，虚拟目录的驱动ID为空
+async fn list_visible(
+    state: &AppState,
+    mounts: &[MountInfo],
+    show_hidden: bool,
+    full_dir: &str,
+    user_dir: &str,
+    guest: Option<&yaolist_backend::guest::GuestPolicy>,
+) -> Vec<(Entry, String, String)> {
+    let meta = get_nearest_meta(state, full_dir).await;
+    let hide_rules = get_hide_rules(meta.as_ref(), full_dir);
+    let workspace = yaolist_backend::workspace::current();
+    let visible = |name: &str| {
+        (show_hidden || !hide_rules.hides(name))
+            && guest.is_none_or(|p| p.path_browsable(&format!("{}/{}", user_dir.trim_end_matches('/'), name)))
+            && workspace.path_visible(&format!("{}/{}", full_dir.trim_end_matches('/'), name))
+    };
+
+    let mut seen: HashMap<String, (Entry, String, String)> = HashMap::new();
+    let matching = get_matching_mounts(full_dir, mounts);
+    for mount in &matching {
+        let Some(driver) = state.storage_manager.get_driver(&mount.id).await else {
+            continue;
+        };
+        let internal_dir = calculate_internal_path(&mount.mount_path, full_dir);
+        match driver.list(&internal_dir).await {
+            Ok(entries) => {
+                for entry in entries {
+                    if !visible(&entry.name) {
+                        continue;
+                    }
+                    let internal_path = format!("{}/{}", internal_dir.trim_end_matches('/'), entry.name);
+                    // 同名条目只保留优先级高的挂载
+                    seen.entry(entry.name.clone()).or_insert((entry, mount.id.clone(), internal_path));
+                }
+            }
+            Err(e) => tracing::warn!("Gallery failed to list {} on {}: {}", full_dir, mount.id, e),
+        }
+    }
+    for vf in get_virtual_files_by_path(full_dir, mounts) {
+        let Some(name) = vf.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        if visible(name) && !seen.contains_key(name) {
+            let entry = Entry {
+                name: name.to_string(),
+                path: format!("{}/{}", full_dir.trim_end_matches('/'), name),
+                is_dir: true,
+                size: 0,
+                modified: None,
+            };
+            seen.insert(name.to_string(), (entry, String::new(), String::new()));
+        }
+    }
+    seen.into_values().collect()
+}
+
+/// 广度优先收集目录（可选递归）中 `wanted` 接受的文件，子目录需单独检查读取权限与密码
+///
+/// 返回找到的文件以及是否因数量上限提前结束。
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn walk_files(
+    state: &AppState,
+    user_ctx: &UserContext,
+    path: &str,
+    req_path: &str,
+    password: &str,
+    recursive: bool,
+    max_files: usize,
+    wanted: fn(&str) -> bool,
+) -> Result<(Vec<FoundFile>, bool), ApiError> {
+    let mounts = get_user_mounts(state, user_ctx).await?;
+    let guest_policy = user_ctx.is_guest.then(|| state.guest.get());
+    let mut files = Vec::new();
+    let mut truncated = false;
+    let mut visited = 0;
+    let mut queue = VecDeque::from([(path.to_string(), req_path.to_string())]);
+    while let Some((full_dir, user_dir)) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_WALK_DIRS || files.len() >= max_files {
+            truncated = true;
+            break;
+        }
+        let show_hidden = user_ctx.permissions.show_hidden_files;
+        let entries = list_visible(state, &mounts, show_hidden, &full_dir, &user_dir, guest_policy.as_ref()).await;
+        for (entry, driver_id, internal_path) in entries {
+            let full_path = format!("{}/{}", full_dir.trim_end_matches('/'), entry.name);
+            let user_path = format!("{}/{}", user_dir.trim_end_matches('/'), entry.name);
+            if entry.is_dir {
+                if recursive && user_ctx.can(Capability::Read, &full_path) {
+                    let sub_password_meta = get_nearest_password_meta(state, &full_path).await;
+                    if can_access_password(sub_password_meta.as_ref(), &full_path, password) {
+                        queue.push_back((full_path, user_path));
+                    }
+                }
+            } else if !driver_id.is_empty() && wanted(&entry.name) {
+                files.push(FoundFile { path: user_path, full_path, driver_id, internal_path, entry });
+            }
+        }
+    }
+    Ok((files, truncated))
+}
+
+/// 读取图片开头获取拍摄时间与尺寸，按大小与修改时间缓存
+async fn image_info(state: &AppState, image: &FoundFile) -> ImageInfo {
+    let cached: Option<ImageMetaRow> = sqlx::query_as(
+        "SELECT size, modified, taken_at, width, height FROM image_meta WHERE path = ?"
+    )
+    .bind(&image.full_path)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    if let Some((size, modified, taken_at, width, height)) = cached {
+        if size as u64 == image.entry.size && modified == image.entry.modified {
+            return ImageInfo {
+                taken_at,
+                width: width.map(|w| w as u32),
+                height: height.map(|h| h as u32),
+            };
+        }
+    }
+
+    let Some(driver) = state.storage_manager.get_driver(&image.driver_id).await else {
+        return ImageInfo::default();
+    };
+    let len = gallery::PROBE_BYTES.min(image.entry.size);
+    let mut head = Vec::with_capacity(len as usize);
+    let read = async {
+        let reader = driver.open_reader(&image.internal_path, Some(0..len)).await?;
+        // 不支持Range的驱动可能返回整个文件，只读取需要的部分
+        reader.take(len).read_to_end(&mut head).await?;
+        anyhow::Ok(())
+    };
+    if let Err(e) = read.await {
+        tracing::debug!("Gallery failed to read {}: {}", image.full_path, e);
+        return ImageInfo::default();
+    }
+    let name = image.entry.name.clone();
+    let info = tokio::task::spawn_blocking(move || gallery::probe(&name, &head))
+        .await
+        .unwrap_or_default();
+
+    if let Err(e) = sqlx::query(
+        "INSERT OR REPLACE INTO image_meta (path, size, modified, taken_at, width, height, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&image.full_path)
+    .bind(image.entry.size as i64)
+    .bind(&image.entry.modified)
+    .bind(&info.taken_at)
+    .bind(info.width.map(|w| w as i64))
+    .bind(info.height.map(|h| h as i64))
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    {
+        tracing::warn!("Failed to cache image info of {}: {}", image.full_path, e);
+    }
+    info
+}
+
+/// 各尺寸缩略图的签名链接
+fn thumbnail_urls(state: &AppState, full_path: &str, expires: i64) -> Value {
+    let sign = state.thumbnails.sign(full_path, expires);
+    let base = workspace_base();
+    let urls: serde_json::Map<String, Value> = ThumbnailSize::ALL.iter().map(|size| {
+        let url = format!(
+            "{}/api/fs/thumbnail?path={}&size={}&expires={}&sign={}",
+            base, urlencoding::encode(full_path), size.name(), expires, sign
+        );
+        (size.name().to_string(), json!(url))
+    }).collect();
+    Value::Object(urls)
+}
+
+/// POST /api/fs/gallery - 列出目录（可选递归）中的图片，分页返回缩略图链接与EXIF拍摄时间
+pub async fn fs_gallery(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<FsGalleryReq>,
+) -> Result<Json<Value>, ApiError> {
+    let req_path = fix_and_clean_path(&req.path.unwrap_or_default());
+    let password = req.password.unwrap_or_default();
+    let (user_ctx, path) = authorize_read(&state, &cookies, &req_path, &password).await?;
+
+    let page = req.page.unwrap_or(1).max(1);
+    let max_per_page = yaolist_backend::config::config().limits.max_page_size.min(i32::MAX as u32) as i32;
+    let per_page = req.per_page.unwrap_or(50).clamp(1, max_per_page);
+    let recursive = req.recursive.unwrap_or(false);
+
+    let (mut images, truncated) = walk_files(
+        &state, &user_ctx, &path, &req_path, &password, recursive, MAX_GALLERY_IMAGES, gallery::is_image,
+    ).await?;
+
+    let sort_by = req.sort_by.as_deref().unwrap_or("modified");
+    let is_desc = req.sort_order.as_deref().unwrap_or("desc") == "desc";
+    images.sort_by(|a, b| {
+        let cmp = match sort_by {
+            "name" => natord::compare_ignore_case(&a.path, &b.path),
+            "size" => a.entry.size.cmp(&b.entry.size),
+            _ => a.entry.modified.cmp(&b.entry.modified),
+        };
+        if is_desc { cmp.reverse() } else { cmp }
+    });
+
+    let total = images.len();
+    let start = ((page - 1) * per_page) as usize;
+    let end = (start + per_page as usize).min(total);
+    let expires = gallery::link_expiry(Utc::now().timestamp());
+    let mut content = Vec::new();
+    for image in images.get(start..end).unwrap_or_default() {
+        let info = image_info(&state, image).await;
+        let thumbnails = gallery::can_thumbnail(&image.entry.name)
+            .then(|| thumbnail_urls(&state, &image.full_path, expires));
+        content.push(json!({
+            "name": image.entry.name,
+            "path": image.path,
+            "size": image.entry.size,
+            "modified": image.entry.modified.clone().unwrap_or_default(),
+            "taken_at": info.taken_at,
+            "width": info.width,
+            "height": info.height,
+            "thumbnails": thumbnails
+        }));
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": {
+            "content": content,
+            "total": total,
+            "page": page,
+            "per_page": per_page,
+            "truncated": truncated
+        }
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailQuery {
+    pub path: String,
+    pub size: Option<String>,
+    pub expires: i64,
+    pub sign: String,
+}
+
+/// GET /api/fs/thumbnail - 相册签名的缩略图（首次请求时生成并缓存）
+pub async fn fs_thumbnail(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ThumbnailQuery>,
+) -> Result<Response, ApiError> {
+    let path = fix_and_clean_path(&query.path);
+    if !state.thumbnails.verify(&path, query.expires, &query.sign, Utc::now().timestamp()) {
+        return Err(ApiError::Forbidden("缩略图链接无效或已过期".to_string()));
+    }
+    let size = ThumbnailSize::from_name(query.size.as_deref().unwrap_or("small"))
+        .ok_or_else(|| ApiError::BadRequest("未知的缩略图尺寸".to_string()))?;
+    if !gallery::can_thumbnail(&path) {
+        return Err(ApiError::BadRequest("该文件类型不支持缩略图".to_string()));
+    }
+
+    let mounts = get_all_mounts(&state).await?;
+    let found = locate_file(&state, &mounts, &path).await;
+    let (driver, internal_path, entry) = found.ok_or_else(|| ApiError::NotFound("文件不存在".to_string()))?;
+
+    let modified = entry.modified.as_deref();
+    let data = match state.thumbnails.get(&path, entry.size, modified, size).await {
+        Some(data) => data,
+        None => {
+            if entry.size > gallery::MAX_THUMBNAIL_SOURCE {
+                return Err(ApiError::BadRequest("图片过大，无法生成缩略图".to_string()));
+            }
+            let mut source = Vec::with_capacity(entry.size as usize);
+            driver.open_reader(&internal_path, None).await
+                .map_err(|e| ApiError::driver(e.to_string()))?
+                .take(gallery::MAX_THUMBNAIL_SOURCE)
+                .read_to_end(&mut source)
+                .await
+                .map_err(|e| ApiError::driver(e.to_string()))?;
+            let data = state.thumbnails.render(source, size).await
+                .map_err(|e| ApiError::BadRequest(format!("无法生成缩略图: {}", e)))?;
+            if let Err(e) = state.thumbnails.put(&path, entry.size, modified, size, &data).await {
+                tracing::warn!("Failed to cache thumbnail of {}: {}", path, e);
+            }
+            data
+        }
+    };
+
+    let max_age = (query.expires - Utc::now().timestamp()).max(0);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "image/jpeg".to_string()),
+            (header::CACHE_CONTROL, format!("private, max-age={}", max_age)),
+        ],
+        data,
+    ).into_response())
+}
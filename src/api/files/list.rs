@@ -1,8 +1,9 @@
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::Deserialize;
@@ -10,14 +11,18 @@ use serde_json::{json, Value};
 use tower_cookies::Cookies;
 
 use crate::state::AppState;
-use crate::api::file_resolver::{MountInfo, get_all_mounts, get_matching_mounts, get_first_mount};
-use yaolist_backend::utils::{fix_and_clean_path, should_hide_file};
+use crate::api::file_resolver::{calculate_internal_path, get_all_mounts, get_user_mounts, get_matching_mounts, get_first_mount};
+use yaolist_backend::error::{ApiError, DriverErrorKind};
+use yaolist_backend::access::Capability;
+use yaolist_backend::internal_share::{self, Resolved, SharedItem, SHARED_ROOT};
+use yaolist_backend::storage::{Cursor, Entry, ListSort};
+use yaolist_backend::utils::{content_etag, etag_matches, fix_and_clean_path};
 
 use super::{
     FsListReq, get_virtual_files_by_path,
-    get_user_context, join_user_path, get_nearest_password_meta, can_access_password,
-    get_nearest_meta, is_hide_apply, get_readme, get_header, can_write,
-    get_user_permissions,
+    get_user_context, get_nearest_password_meta, can_access_password,
+    get_nearest_meta, get_hide_rules, get_readme, get_header, can_write,
+    get_user_permissions, is_preview_only,
 };
 
 #[derive(Debug, Deserialize)]
@@ -25,35 +30,79 @@ pub struct AdminListReq {
     pub path: Option<String>,
 }
 
-/// POST /api/fs/list - 列出目录内容
+/// POST /api/fs/list - 列出目录内容，响应带内容哈希 ETag
+///
+/// 请求的 If-None-Match 与本次内容相同时返回 304 且不带响应体，轮询的前端和移动端无需重复下载大目录。
+/// 剩余空间随时变化，不参与哈希，否则几乎每次都不同。
+pub async fn fs_list_with_etag(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    cookies: Cookies,
+    Json(req): Json<FsListReq>,
+) -> Result<Response, ApiError> {
+    let Json(body) = fs_list(State(state), cookies, Json(req)).await?;
+    let bytes = serde_json::to_vec(&body).map_err(|e| ApiError::Internal(e.to_string()))?;
+    let mut hashed = body;
+    if let Some(data) = hashed.get_mut("data").and_then(Value::as_object_mut) {
+        data.remove("space");
+    }
+    let etag = content_etag(&serde_json::to_vec(&hashed).map_err(|e| ApiError::Internal(e.to_string()))?);
+    let not_modified = headers.get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| etag_matches(v, &etag));
+    let cache_headers = [(header::ETAG, etag), (header::CACHE_CONTROL, "no-cache".to_string())];
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+    Ok((cache_headers, [(header::CONTENT_TYPE, "application/json")], bytes).into_response())
+}
+
+/// 列出目录内容（返回 JSON，供 REST 与 GraphQL 共用）
 pub async fn fs_list(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Json(req): Json<FsListReq>,
-) -> Result<Json<Value>, StatusCode> {
-    let req_path = fix_and_clean_path(&req.path.unwrap_or_default());
+) -> Result<Json<Value>, ApiError> {
+    let req_path = fix_and_clean_path(req.path.as_deref().unwrap_or_default());
     let password = req.password.clone().unwrap_or_default();
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
     let perms = &user_ctx.permissions;
     
-    // 检查是否有读取权限（游客组禁用时无权限）
-    if !perms.read_files {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "guest_disabled"
-        })));
+    // 检查是否有读取权限（游客组禁用时无权限，路径授权与站内分享在下面按路径检查）
+    if !perms.read_files && user_ctx.grants.is_empty() && user_ctx.shared.is_empty() {
+        return Err(ApiError::Forbidden("guest_disabled".to_string()));
+    }
+    
+    // 游客只能浏览访问策略允许的路径
+    let guest_policy = user_ctx.is_guest.then(|| state.guest.get());
+    if guest_policy.as_ref().is_some_and(|p| !p.path_browsable(&req_path)) {
+        return Err(ApiError::Forbidden("游客无权访问该路径".to_string()));
     }
+    let guest_hidden = |name: &str| guest_policy.as_ref()
+        .is_some_and(|p| !p.path_browsable(&format!("{}/{}", req_path.trim_end_matches('/'), name)));
+    
+    // “/Shared with me”是虚拟目录，列出站内分享给用户的项目
+    if internal_share::resolve(&user_ctx.shared, &req_path) == Resolved::Root {
+        return Ok(Json(shared_listing(&state, &user_ctx.shared, &req)));
+    }
+    // 根目录中显示“/Shared with me”
+    let shared_root = (req_path == "/" && !user_ctx.shared.is_empty()).then(|| json!({
+        "name": SHARED_ROOT.trim_start_matches('/'),
+        "size": 0,
+        "is_dir": true,
+        "type": yaolist_backend::file_type::FileType::Other,
+        "modified": "",
+        "created": "",
+        "shared": true
+    }));
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": e
-            })));
+            return Err(ApiError::Forbidden(e));
         }
     };
     
@@ -65,73 +114,81 @@ pub async fn fs_list(
     
     // 检查密码访问权限（"我的附庸的附庸不是我的附庸"）
     if !can_access_password(password_meta.as_ref(), &path, &password) {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "password is incorrect or you have no permission"
-        })));
+        return Err(ApiError::Forbidden("password is incorrect or you have no permission".to_string()));
     }
     
     // 获取最近的元信息用于其他属性（readme/header/hide等）
     let meta = get_nearest_meta(&state, &path).await;
     
     // 获取隐藏规则
-    let hide_patterns = meta.as_ref()
-        .filter(|m| is_hide_apply(&m.path, &path, m.h_sub))
-        .and_then(|m| m.hide.clone())
-        .unwrap_or_default();
+    let hide_rules = get_hide_rules(meta.as_ref(), &path);
     
-    // 分页参数：默认10/页，最高100/页
+    // 分页参数：默认10/页，上限见limits.max_page_size
     let page = req.page.unwrap_or(1).max(1);
-    let per_page = req.per_page.unwrap_or(10).clamp(1, 100);
+    let max_per_page = yaolist_backend::config::config().limits.max_page_size.min(i32::MAX as u32) as i32;
+    let per_page = req.per_page.unwrap_or(10).clamp(1, max_per_page);
     
-    // 获取所有存储挂载点（使用file_resolver）
-    let mounts = get_all_mounts(&state).await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // 投递箱：可上传但不可读取的目录返回空列表，不暴露其中的文件
+    if !user_ctx.can(Capability::Read, &path) {
+        if !user_ctx.can(Capability::Upload, &path) {
+            return Err(ApiError::Forbidden("没有读取该路径的权限".to_string()));
+        }
+        return Ok(Json(json!({
+            "code": 200,
+            "message": "success",
+            "data": {
+                "content": [],
+                "total": 0,
+                "folder_count": 0,
+                "file_count": 0,
+                "page": page,
+                "per_page": per_page,
+                "readme": get_readme(meta.as_ref(), &path),
+                "header": get_header(meta.as_ref(), &path),
+                "write": true,
+                "provider": "Virtual",
+                "drop_box": true
+            }
+        })));
+    }
+    
+    // 获取用户可见的存储挂载点（使用file_resolver）
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
     
     // 获取所有匹配的驱动（支持别名：多个驱动挂载同一路径）
     let matching_mounts = get_matching_mounts(&path, &mounts);
+    let workspace = yaolist_backend::workspace::current();
     
     if !matching_mounts.is_empty() {
         // 计算实际路径（所有驱动共用同一挂载路径）
         let mount_path = fix_and_clean_path(&matching_mounts[0].mount_path);
-        let actual_path = if path.len() > mount_path.len() {
-            fix_and_clean_path(&path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let actual_path = calculate_internal_path(&mount_path, &path);
         
         tracing::debug!("Matched {} drivers, mount point: {}, actual path: {}", 
             matching_mounts.len(), mount_path, actual_path);
         
-        // 从所有驱动获取文件列表并合并
-        let mut all_files: HashMap<String, Value> = HashMap::new();
+        // 各驱动按请求的顺序返回列表（请求未指定时使用挂载的默认排序；列表缓存保留排序视图，翻阅大目录时不重复排序）
+        let display = &matching_mounts[0].display;
+        let order = display.sort(req.sort_by.as_deref(), req.sort_order.as_deref());
+        let cursor = match req.cursor.as_deref().filter(|c| !c.is_empty()) {
+            Some(c) => Some(Cursor::decode(c, order)
+                .ok_or_else(|| ApiError::BadRequest("无效的游标或排序方式已改变".to_string()))?),
+            None => None,
+        };
+        let mut listings: Vec<Arc<Vec<Entry>>> = Vec::new();
         let mut last_error: Option<String> = None;
-        let mut has_success = false;
+        
+        // 刷新：跳过列表缓存（仅限可写用户，避免游客绕过缓存频繁请求网盘）
+        let refresh = req.refresh.unwrap_or(false)
+            && (user_ctx.can(Capability::Upload, &path) || can_write(meta.as_ref(), &path));
         
         for mount in &matching_mounts {
+            if refresh {
+                state.storage_manager.list_cache().invalidate_path(&mount.id, &actual_path).await;
+            }
             if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
-                match driver.list(&actual_path).await {
-                    Ok(files) => {
-                        has_success = true;
-                        for f in files {
-                            // 过滤隐藏文件
-                            if !perms.show_hidden_files && should_hide_file(&f.name, &hide_patterns) {
-                                continue;
-                            }
-                            
-                            let file_json = json!({
-                                "name": f.name,
-                                "size": f.size,
-                                "is_dir": f.is_dir,
-                                "modified": f.modified.clone().unwrap_or_default(),
-                                "created": ""
-                            });
-                            
-                            // 同名文件只保留第一个（按order排序，优先级高的先处理）
-                            // 不同名文件全部保留
-                            all_files.entry(f.name.clone()).or_insert(file_json);
-                        }
-                    }
+                match state.storage_manager.list_cache().sorted_list(&mount.id, driver.as_ref().as_ref(), &actual_path, order).await {
+                    Ok(entries) => listings.push(entries),
                     Err(e) => {
                         let error_msg = e.to_string();
                         tracing::error!("Driver {} list failed: {}", mount.id, error_msg);
@@ -144,93 +201,115 @@ pub async fn fs_list(
         }
         
         // 如果所有驱动都失败了，返回简单错误信息（不暴露详细信息）
-        if !has_success && last_error.is_some() {
-            return Ok(Json(json!({
-                "code": 500,
-                "message": "存储驱动故障，请联系管理员",
-                "data": null,
-                "error_type": "DRIVER_ERROR"
-            })));
+        if let (true, Some(error_msg)) = (listings.is_empty(), last_error) {
+            return Err(ApiError::Driver {
+                kind: DriverErrorKind::classify(&error_msg),
+                message: "存储驱动故障，请联系管理员".to_string(),
+            });
         }
         
-        let mut content: Vec<Value> = all_files.into_values().collect();
-        
-        // 合并虚拟目录
-        let virtual_files = get_virtual_files_by_path(&path, &mounts);
-        let existing_names: std::collections::HashSet<String> = content.iter()
-            .filter_map(|f| f.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()))
-            .collect();
-        for vf in virtual_files {
-            if let Some(name) = vf.get("name").and_then(|n| n.as_str()) {
-                if !existing_names.contains(name) && (perms.show_hidden_files || !should_hide_file(name, &hide_patterns)) {
-                    content.push(vf);
+        // 已分层到冷存储的文件（仍显示在原路径）与虚拟目录
+        let mut extras: Vec<Entry> = Vec::new();
+        let mut tiered_names: HashSet<String> = HashSet::new();
+        for tiered in state.tiering.children(&path) {
+            let name = tiered.path.rsplit('/').next().unwrap_or("").to_string();
+            tiered_names.insert(name.clone());
+            extras.push(Entry { name, path: tiered.path, is_dir: false, size: tiered.size, modified: tiered.modified });
+        }
+        let mut virtual_json: HashMap<String, Value> = HashMap::new();
+        for vf in get_virtual_files_by_path(&path, &mounts) {
+            if let Some(name) = vf.get("name").and_then(|n| n.as_str()).map(str::to_string) {
+                if !tiered_names.contains(&name) {
+                    extras.push(Entry { name: name.clone(), path: String::new(), is_dir: true, size: 0, modified: None });
+                    virtual_json.insert(name, vf);
                 }
             }
         }
+        if let Some(vf) = shared_root.clone() {
+            let name = SHARED_ROOT.trim_start_matches('/').to_string();
+            extras.push(Entry { name: name.clone(), path: String::new(), is_dir: true, size: 0, modified: None });
+            virtual_json.insert(name, vf);
+        }
         
-        // 统计文件夹和文件数量
-        let folder_count = content.iter().filter(|f| f.get("is_dir").and_then(|v| v.as_bool()).unwrap_or(false)).count();
-        let file_count = content.len() - folder_count;
-        
-        // 排序处理：目录始终在前，然后按指定字段排序
-        let sort_by = req.sort_by.as_deref().unwrap_or("name");
-        let sort_order = req.sort_order.as_deref().unwrap_or("asc");
-        let is_desc = sort_order == "desc";
-        tracing::debug!("排序参数: sort_by={}, sort_order={}, is_desc={}, 文件数={}", sort_by, sort_order, is_desc, content.len());
-        
-        content.sort_by(|a, b| {
-            let a_is_dir = a.get("is_dir").and_then(|v| v.as_bool()).unwrap_or(false);
-            let b_is_dir = b.get("is_dir").and_then(|v| v.as_bool()).unwrap_or(false);
-            
-            // 目录始终在前
-            if a_is_dir && !b_is_dir {
-                return std::cmp::Ordering::Less;
-            }
-            if !a_is_dir && b_is_dir {
-                return std::cmp::Ordering::Greater;
-            }
-            
-            let cmp = match sort_by {
-                "modified" => {
-                    let a_mod = a.get("modified").and_then(|v| v.as_str()).unwrap_or("");
-                    let b_mod = b.get("modified").and_then(|v| v.as_str()).unwrap_or("");
-                    a_mod.cmp(b_mod)
-                }
-                "size" => {
-                    let a_size = a.get("size").and_then(|v| v.as_i64()).unwrap_or(0);
-                    let b_size = b.get("size").and_then(|v| v.as_i64()).unwrap_or(0);
-                    a_size.cmp(&b_size)
+        // 只有一个驱动时直接使用其排序视图；否则合并后重新排序
+        // 同名文件只保留第一个（按order排序，优先级高的先处理），驱动中的文件优先于分层文件与虚拟目录
+        let sorted: Arc<Vec<Entry>> = if listings.len() == 1 && extras.is_empty() {
+            listings.remove(0)
+        } else {
+            let mut seen: HashSet<&str> = HashSet::new();
+            let mut merged: Vec<Entry> = Vec::new();
+            for entry in listings.iter().flat_map(|l| l.iter()) {
+                if seen.insert(&entry.name) {
+                    merged.push(entry.clone());
                 }
-                _ => {
-                    // 使用自然排序（让 "2" 排在 "10" 前面）
-                    let a_name = a.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                    let b_name = b.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                    natord::compare_ignore_case(a_name, b_name)
+            }
+            for entry in &extras {
+                if seen.insert(&entry.name) {
+                    merged.push(entry.clone());
+                } else {
+                    tiered_names.remove(&entry.name);
+                    virtual_json.remove(&entry.name);
                 }
-            };
-            
-            if is_desc { cmp.reverse() } else { cmp }
-        });
+            }
+            order.sort(&mut merged);
+            Arc::new(merged)
+        };
         
-        // 返回所有文件名用于全选
-        let all_names: Vec<String> = content.iter()
-            .filter_map(|f| f.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()))
+        let dir_prefix = path.trim_end_matches('/');
+        let visible: Vec<&Entry> = sorted.iter()
+            .filter(|f| {
+                let extra = tiered_names.contains(&f.name) || virtual_json.contains_key(&f.name);
+                // 过滤隐藏文件
+                (perms.show_hidden_files || !hide_rules.hides(&f.name))
+                    // 过滤游客不可浏览的路径
+                    && !guest_hidden(&f.name)
+                    // 过滤其他工作区的根目录
+                    && (extra || workspace.path_visible(&format!("{}/{}", dir_prefix, f.name)))
+            })
             .collect();
         
-        // 分页处理
-        let total = content.len();
-        let start = ((page - 1) * per_page) as usize;
+        // 统计文件夹和文件数量
+        let folder_count = visible.iter().filter(|f| f.is_dir).count();
+        let file_count = visible.len() - folder_count;
+        
+        // 分页处理：带游标时从游标之后开始，否则按页码
+        let total = visible.len();
+        let start = match &cursor {
+            Some(c) => c.position(&visible, |e| *e),
+            None => (page as usize - 1).saturating_mul(per_page as usize),
+        }.min(total);
         let end = (start + per_page as usize).min(total);
-        let paginated_content = if start < total {
-            content[start..end].to_vec()
-        } else {
-            vec![]
-        };
+        let next_cursor = (end > start && end < total).then(|| Cursor::after(order, visible[end - 1]).encode());
+        let paginated_content: Vec<Value> = visible[start..end].iter()
+            .map(|f| {
+                if let Some(vf) = virtual_json.get(&f.name) {
+                    return vf.clone();
+                }
+                let mut item = json!({
+                    "name": f.name,
+                    "size": f.size,
+                    "is_dir": f.is_dir,
+                    "type": state.file_types.classify(&f.name, f.is_dir),
+                    "modified": f.modified.clone().unwrap_or_default(),
+                    "created": ""
+                });
+                if tiered_names.contains(&f.name) {
+                    item["tiered"] = json!(true);
+                }
+                item
+            })
+            .collect();
+        
+        // 返回所有文件名用于全选（按游标翻页的客户端不需要，超大目录中省去这部分响应）
+        let all_names: Option<Vec<&str>> = cursor.is_none()
+            .then(|| visible.iter().map(|f| f.name.as_str()).collect());
         
         // 获取元信息内容
         let readme = get_readme(meta.as_ref(), &path);
         let header = get_header(meta.as_ref(), &path);
-        let write = perms.is_admin || perms.create_upload || can_write(meta.as_ref(), &path);
+        let write = user_ctx.can(Capability::Upload, &path) || can_write(meta.as_ref(), &path);
+        // 仅预览目录：前端据此隐藏下载/直链/分享入口
+        let preview_only = is_preview_only(&state, &user_ctx, &path).await;
         
         // 获取存储空间信息（如果驱动支持且允许前台显示）
         let mut space_info: Option<Value> = None;
@@ -262,21 +341,25 @@ pub async fn fs_list(
                 "readme": readme,
                 "header": header,
                 "write": write,
+                "preview_only": preview_only,
                 "provider": "Mixed",
                 "all_names": all_names,
-                "space": space_info
+                "next_cursor": next_cursor,
+                "space": space_info,
+                "display": display
             }
         })));
     }
     
     // 没有找到匹配的存储，显示虚拟目录
-    let virtual_files = get_virtual_files_by_path(&path, &mounts);
+    let mut virtual_files = get_virtual_files_by_path(&path, &mounts);
+    virtual_files.extend(shared_root);
     
     // 过滤隐藏的虚拟目录（有 show_hidden_files 权限的用户可以看到）
     let virtual_files: Vec<Value> = virtual_files.into_iter()
         .filter(|vf| {
             if let Some(name) = vf.get("name").and_then(|n| n.as_str()) {
-                perms.show_hidden_files || !should_hide_file(name, &hide_patterns)
+                (perms.show_hidden_files || !hide_rules.hides(name)) && !guest_hidden(name)
             } else {
                 true
             }
@@ -284,54 +367,24 @@ pub async fn fs_list(
         .collect();
     
     if virtual_files.is_empty() && path != "/" {
-        return Ok(Json(json!({
-            "code": 404,
-            "message": format!("路径不存在: {}", path),
-            "data": null
-        })));
+        return Err(ApiError::NotFound(format!("路径不存在: {}", path)));
     }
     
     // 统计文件夹和文件数量
     let folder_count = virtual_files.iter().filter(|f| f.get("is_dir").and_then(|v| v.as_bool()).unwrap_or(false)).count();
     let file_count = virtual_files.len() - folder_count;
     
-    // 虚拟目录也需要排序
-    let sort_by = req.sort_by.as_deref().unwrap_or("name");
-    let sort_order = req.sort_order.as_deref().unwrap_or("asc");
-    let is_desc = sort_order == "desc";
-    
+    // 虚拟目录也需要排序（与挂载目录相同的排序方式）
+    let order = ListSort::parse(req.sort_by.as_deref(), req.sort_order.as_deref());
+    let as_entry = |f: &Value| Entry {
+        name: f.get("name").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        path: String::new(),
+        is_dir: f.get("is_dir").and_then(|v| v.as_bool()).unwrap_or(false),
+        size: f.get("size").and_then(|v| v.as_u64()).unwrap_or(0),
+        modified: f.get("modified").and_then(|v| v.as_str()).map(|s| s.to_string()),
+    };
     let mut virtual_files = virtual_files;
-    virtual_files.sort_by(|a, b| {
-        let a_is_dir = a.get("is_dir").and_then(|v| v.as_bool()).unwrap_or(false);
-        let b_is_dir = b.get("is_dir").and_then(|v| v.as_bool()).unwrap_or(false);
-        
-        if a_is_dir && !b_is_dir {
-            return std::cmp::Ordering::Less;
-        }
-        if !a_is_dir && b_is_dir {
-            return std::cmp::Ordering::Greater;
-        }
-        
-        let cmp = match sort_by {
-            "modified" => {
-                let a_mod = a.get("modified").and_then(|v| v.as_str()).unwrap_or("");
-                let b_mod = b.get("modified").and_then(|v| v.as_str()).unwrap_or("");
-                a_mod.cmp(b_mod)
-            }
-            "size" => {
-                let a_size = a.get("size").and_then(|v| v.as_i64()).unwrap_or(0);
-                let b_size = b.get("size").and_then(|v| v.as_i64()).unwrap_or(0);
-                a_size.cmp(&b_size)
-            }
-            _ => {
-                let a_name = a.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let b_name = b.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                natord::compare_ignore_case(a_name, b_name)
-            }
-        };
-        
-        if is_desc { cmp.reverse() } else { cmp }
-    });
+    virtual_files.sort_by(|a, b| order.compare(&as_entry(a), &as_entry(b)));
     
     // 虚拟目录也需要分页
     let total = virtual_files.len();
@@ -346,7 +399,7 @@ pub async fn fs_list(
     // 获取元信息内容
     let readme = get_readme(meta.as_ref(), &path);
     let header = get_header(meta.as_ref(), &path);
-    let write = perms.is_admin || perms.create_upload || can_write(meta.as_ref(), &path);
+    let write = user_ctx.can(Capability::Upload, &path) || can_write(meta.as_ref(), &path);
     
     Ok(Json(json!({
         "code": 200,
@@ -367,6 +420,51 @@ pub async fn fs_list(
 }
 
 
+/// 列出“/Shared with me”中的站内分享项目（虚拟目录，与其他虚拟目录一样排序与分页）
+fn shared_listing(state: &AppState, shared: &[SharedItem], req: &FsListReq) -> Value {
+    let order = ListSort::parse(req.sort_by.as_deref(), req.sort_order.as_deref());
+    let mut entries: Vec<(Entry, &SharedItem)> = shared.iter()
+        .map(|item| (Entry { name: item.name.clone(), path: String::new(), is_dir: item.is_dir, size: 0, modified: None }, item))
+        .collect();
+    entries.sort_by(|a, b| order.compare(&a.0, &b.0));
+    
+    let page = req.page.unwrap_or(1).max(1);
+    let max_per_page = yaolist_backend::config::config().limits.max_page_size.min(i32::MAX as u32) as i32;
+    let per_page = req.per_page.unwrap_or(10).clamp(1, max_per_page);
+    let total = entries.len();
+    let start = ((page - 1) as usize).saturating_mul(per_page as usize).min(total);
+    let end = (start + per_page as usize).min(total);
+    let content: Vec<Value> = entries[start..end].iter()
+        .map(|(entry, item)| json!({
+            "name": entry.name,
+            "size": 0,
+            "is_dir": entry.is_dir,
+            "type": state.file_types.classify(&entry.name, entry.is_dir),
+            "modified": "",
+            "created": "",
+            "shared_access": item.access
+        }))
+        .collect();
+    let folder_count = entries.iter().filter(|(e, _)| e.is_dir).count();
+    
+    json!({
+        "code": 200,
+        "message": "success",
+        "data": {
+            "content": content,
+            "total": total,
+            "folder_count": folder_count,
+            "file_count": total - folder_count,
+            "page": page,
+            "per_page": per_page,
+            "readme": "",
+            "header": "",
+            "write": false,
+            "provider": "Virtual"
+        }
+    })
+}
+
 /// POST /api/admin/fs/list - 管理后台专用目录列表（不受密码/隐藏限制）
 /// 仅管理员可访问
 pub async fn admin_fs_list(
@@ -385,34 +483,12 @@ pub async fn admin_fs_list(
         })));
     }
     
-    // 从数据库获取所有存储挂载点
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    // 构建挂载点列表
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        let order = config.get("order").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order,
-        })
-    }).collect();
+    // 获取所有存储挂载点（仅当前工作区可见的部分）
+    let mounts = get_all_mounts(&state).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     // 尝试找到最长匹配的存储
     if let Some(mount) = get_first_mount(&path, &mounts) {
-        let mount_path = fix_and_clean_path(&mount.mount_path);
-        let actual_path = if path.len() > mount_path.len() {
-            fix_and_clean_path(&path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let actual_path = calculate_internal_path(&mount.mount_path, &path);
         
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
             match driver.list(&actual_path).await {
@@ -477,7 +553,7 @@ pub async fn fs_get(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Json(req): Json<FsListReq>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let req_path = fix_and_clean_path(&req.path.unwrap_or_default());
     let password = req.password.clone().unwrap_or_default();
     
@@ -486,13 +562,10 @@ pub async fn fs_get(
     let perms = &user_ctx.permissions;
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": e
-            })));
+            return Err(ApiError::Forbidden(e));
         }
     };
     
@@ -500,11 +573,16 @@ pub async fn fs_get(
         user_ctx.root_path, req_path, path);
     
     // 检查是否有读取权限（游客组禁用时无权限）
-    if !perms.read_files {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "guest_disabled"
-        })));
+    if !perms.read_files && user_ctx.grants.is_empty() {
+        return Err(ApiError::Forbidden("guest_disabled".to_string()));
+    }
+    if !user_ctx.can(Capability::Read, &path) {
+        return Err(ApiError::Forbidden("没有读取该路径的权限".to_string()));
+    }
+    
+    // 游客只能访问访问策略允许的路径
+    if user_ctx.is_guest && !state.guest.get().path_browsable(&req_path) {
+        return Err(ApiError::Forbidden("游客无权访问该路径".to_string()));
     }
     
     // 获取最近的有密码的元信息（只需验证这一个密码）
@@ -512,33 +590,24 @@ pub async fn fs_get(
     
     // 检查密码访问权限（"我的附庸的附庸不是我的附庸"）
     if !can_access_password(password_meta.as_ref(), &path, &password) {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "password is incorrect or you have no permission"
-        })));
+        return Err(ApiError::Forbidden("password is incorrect or you have no permission".to_string()));
     }
     
     // 获取最近的元信息用于其他属性（readme/header/hide等）
     let meta = get_nearest_meta(&state, &path).await;
     
-    // 获取隐藏规则
-    let hide_patterns = meta.as_ref()
-        .filter(|m| is_hide_apply(&m.path, &path, m.h_sub))
-        .and_then(|m| m.hide.clone())
-        .unwrap_or_default();
-    
-    // 检查文件是否被隐藏（没有 show_hidden_files 权限时）
+    // 检查文件是否被所在目录的隐藏规则隐藏（没有 show_hidden_files 权限时）
     let filename = path.split('/').last().unwrap_or("");
-    if !perms.show_hidden_files && should_hide_file(filename, &hide_patterns) {
-        return Ok(Json(json!({
-            "code": 404,
-            "message": "文件不存在"
-        })));
+    let parent = fix_and_clean_path(path.rsplit_once('/').map(|(p, _)| p).unwrap_or("/"));
+    let parent_meta = if parent == path { None } else { get_nearest_meta(&state, &parent).await };
+    if !perms.show_hidden_files && get_hide_rules(parent_meta.as_ref(), &parent).hides(filename) {
+        return Err(ApiError::NotFound("文件不存在".to_string()));
     }
     
     // 分别处理目录和文件获取元信息内容
     let readme = get_readme(meta.as_ref(), &path);
     let header = get_header(meta.as_ref(), &path);
+    let preview_only = is_preview_only(&state, &user_ctx, &path).await;
     
     // 根路径一定是目录
     if path == "/" {
@@ -558,25 +627,8 @@ pub async fn fs_get(
         })));
     }
     
-    // 从数据库获取所有存储挂载点
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    // 构建挂载点列表
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        let order = config.get("order").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order,
-        })
-    }).collect();
+    // 获取对用户可见的存储挂载点
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
     
     // 检查路径是否是某个挂载点本身
     for mount in &mounts {
@@ -628,11 +680,7 @@ pub async fn fs_get(
     // 在所有驱动中查找文件
     for mount in &matching_mounts {
         let mount_path = fix_and_clean_path(&mount.mount_path);
-        let actual_parent = if parent_path.len() > mount_path.len() {
-            fix_and_clean_path(&parent_path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let actual_parent = calculate_internal_path(&mount_path, parent_path);
         
         // 获取驱动并列出父目录
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
@@ -652,6 +700,7 @@ pub async fn fs_get(
                                     "created": "",
                                     "readme": readme,
                                     "header": header,
+                                    "preview_only": preview_only,
                                     "provider": "Local"
                                 }
                             })));
@@ -664,24 +713,37 @@ pub async fn fs_get(
                     // 记录驱动运行时错误
                     state.storage_manager.set_driver_error(&mount.id, error_msg.clone()).await;
                     // 返回简单错误信息（不暴露详细信息）
-                    return Ok(Json(json!({
-                        "code": 500,
-                        "message": "存储驱动故障，请联系管理员",
-                        "data": null,
-                        "error_type": "DRIVER_ERROR"
-                    })));
+                    return Err(ApiError::Driver {
+                        kind: DriverErrorKind::classify(&error_msg),
+                        message: "存储驱动故障，请联系管理员".to_string(),
+                    });
                 }
             }
         }
     }
     
+    // 已分层到冷存储的文件
+    if let Some(tiered) = state.tiering.tiered(&path) {
+        return Ok(Json(json!({
+            "code": 200,
+            "message": "success",
+            "data": {
+                "name": filename,
+                "size": tiered.size,
+                "is_dir": false,
+                "modified": tiered.modified.unwrap_or_default(),
+                "created": "",
+                "readme": readme,
+                "header": header,
+                "preview_only": preview_only,
+                "provider": "Tiered",
+                "tiered": true
+            }
+        })));
+    }
+    
     // 未找到文件
-    Ok(Json(json!({
-        "code": 404,
-        "message": format!("文件不存在: {}", path),
-        "data": null,
-        "error_type": "FILE_NOT_FOUND"
-    })))
+    Err(ApiError::NotFound(format!("文件不存在: {}", path)))
 }
 
 #[derive(Debug, Deserialize)]
@@ -699,18 +761,17 @@ pub async fn fs_properties(
     
     // 获取用户上下文
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
     
-    // 检查读取权限
-    if !perms.read_files {
+    // 游客只能访问访问策略允许的路径
+    if user_ctx.is_guest && !state.guest.get().path_browsable(&req_path) {
         return Ok(Json(json!({
             "code": 403,
-            "message": "没有读取权限"
+            "message": "游客无权访问该路径"
         })));
     }
     
     // 将用户请求路径与用户根路径结合
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -720,8 +781,16 @@ pub async fn fs_properties(
         }
     };
     
-    // 获取所有存储挂载点
-    let mounts = get_all_mounts(&state).await
+    // 检查读取权限（按路径授权）
+    if !user_ctx.can(Capability::Read, &path) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有读取权限"
+        })));
+    }
+    
+    // 获取用户可见的存储挂载点
+    let mounts = get_user_mounts(&state, &user_ctx).await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     // 获取匹配的驱动
@@ -756,11 +825,7 @@ pub async fn fs_properties(
     
     // 计算实际路径
     let mount_path = fix_and_clean_path(&matching_mounts[0].mount_path);
-    let actual_path = if path.len() > mount_path.len() {
-        fix_and_clean_path(&path[mount_path.len()..])
-    } else {
-        "/".to_string()
-    };
+    let actual_path = calculate_internal_path(&mount_path, &path);
     
     // 获取驱动
     let driver = match state.storage_manager.get_driver(&matching_mounts[0].id).await {
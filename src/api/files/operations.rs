@@ -1,18 +1,29 @@
 use std::sync::Arc;
 use axum::{
-    extract::State,
-    http::StatusCode,
+    body::Body,
+    extract::{Extension, Query, Request, State},
+    http::header,
     Json,
 };
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use tower_cookies::Cookies;
 
 use crate::state::AppState;
-use crate::api::file_resolver::{MountInfo, get_first_mount};
+use crate::api::file_resolver::{calculate_internal_path, get_user_mounts, find_item_mounts, forget_upload_placement, select_write_mount};
+use yaolist_backend::error::ApiError;
 use yaolist_backend::utils::fix_and_clean_path;
+use yaolist_backend::access::Capability;
+use yaolist_backend::body_limit::BodyLimit;
+use yaolist_backend::body_stream::stream_into;
 
-use super::{get_user_context, join_user_path};
+use crate::api::hooks::fire_file_hook;
+use crate::api::virus_scan::scan_after_write;
+use yaolist_backend::file_hook::FileHookEvent;
+
+use super::{check_free_space, check_upload_policy, get_user_context, get_user_id};
 
 #[derive(Debug, Deserialize)]
 pub struct FsMkdirReq {
@@ -24,57 +35,32 @@ pub async fn fs_mkdir(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Json(req): Json<FsMkdirReq>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let req_path = fix_and_clean_path(&req.path);
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    // 权限验证
-    if !perms.create_upload && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有创建目录的权限"
-        })));
-    }
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": e
-            })));
+            return Err(ApiError::Forbidden(e));
         }
     };
     
-    // 获取挂载点和实际路径
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // 权限验证（按路径授权）
+    if !user_ctx.can(Capability::Upload, &path) {
+        return Err(ApiError::Forbidden("没有创建目录的权限".to_string()));
+    }
     
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
+    // 获取挂载点和实际路径
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
     
-    if let Some(mount) = get_first_mount(&path, &mounts) {
+    // 多个挂载点共用路径时按写入策略选择
+    if let Some(mount) = select_write_mount(&state, &path, &mounts, None).await {
         let mount_path = fix_and_clean_path(&mount.mount_path);
-        let actual_path = if path.len() > mount_path.len() {
-            fix_and_clean_path(&path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let actual_path = calculate_internal_path(&mount_path, &path);
         
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
             tracing::debug!("fs_mkdir: 调用driver.create_dir, actual_path={}", actual_path);
@@ -88,10 +74,7 @@ pub async fn fs_mkdir(
                 }
                 Err(e) => {
                     tracing::error!("fs_mkdir: Failed to create directory: {}", e);
-                    return Ok(Json(json!({
-                        "code": 500,
-                        "message": format!("创建目录失败: {}", e)
-                    })));
+                    return Err(ApiError::driver(format!("创建目录失败: {}", e)));
                 }
             }
         } else {
@@ -101,98 +84,123 @@ pub async fn fs_mkdir(
         tracing::error!("fs_mkdir: Mount point not found, path={}", path);
     }
     
-    Ok(Json(json!({
-        "code": 404,
-        "message": "路径不存在"
-    })))
+    Err(ApiError::NotFound("路径不存在".to_string()))
 }
 
+/// JSON 写入请求的最大请求体（文本编辑器保存），更大的文件应使用原始请求体
+const MAX_JSON_WRITE_SIZE: usize = 16 * 1024 * 1024;
+
 #[derive(Debug, Deserialize)]
 pub struct FsWriteReq {
     pub path: String,
     pub content: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct FsWriteQuery {
+    pub path: Option<String>,
+}
+
 /// POST /api/fs/write - 创建/写入文件（Core层控制，调用driver原语）
+///
+/// JSON 请求体 `{path, content}` 用于文本编辑器；其他 Content-Type 时请求体即文件内容，
+/// 路径由 `?path=` 指定，内容边读边写入驱动。
 pub async fn fs_write(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
-    Json(req): Json<FsWriteReq>,
-) -> Result<Json<Value>, StatusCode> {
-    let req_path = fix_and_clean_path(&req.path);
+    Query(query): Query<FsWriteQuery>,
+    body_limit: Option<Extension<BodyLimit>>,
+    request: Request,
+) -> Result<Json<Value>, ApiError> {
+    let body_limit = body_limit.map(|Extension(BodyLimit(limit))| limit);
+    let is_json = request.headers().get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let (req_path, size, content): (String, Option<u64>, BoxStream<'static, Result<Bytes, axum::Error>>) = if is_json {
+        let json_limit = body_limit.map_or(MAX_JSON_WRITE_SIZE, |l| MAX_JSON_WRITE_SIZE.min(l as usize));
+        let body = axum::body::to_bytes(request.into_body(), json_limit).await
+            .map_err(|_| ApiError::PayloadTooLarge(format!("JSON 请求体超过 {} 字节", json_limit)))?;
+        let req: FsWriteReq = serde_json::from_slice(&body)
+            .map_err(|e| ApiError::BadRequest(format!("请求格式错误: {}", e)))?;
+        let content = Bytes::from(req.content);
+        (req.path, Some(content.len() as u64), stream::iter([Ok(content)]).boxed())
+    } else {
+        let path = query.path.ok_or_else(|| ApiError::BadRequest("缺少 path 参数".to_string()))?;
+        let size = request.headers().get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        (path, size, Body::into_data_stream(request.into_body()).boxed())
+    };
+    let req_path = fix_and_clean_path(&req_path);
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    // 权限验证
-    if !perms.create_upload && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有创建文件的权限"
-        })));
-    }
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": e
-            })));
+            return Err(ApiError::Forbidden(e));
         }
     };
     
-    // 获取挂载点
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // 权限验证（按路径授权）
+    if !user_ctx.can(Capability::Upload, &path) {
+        return Err(ApiError::Forbidden("没有创建文件的权限".to_string()));
+    }
     
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
+    // 用户组的单文件上传上限
+    if user_ctx.settings.upload_too_large(size.unwrap_or(0)) {
+        return Err(ApiError::PayloadTooLarge(format!("文件大小超过用户组上传上限 {} 字节", user_ctx.settings.max_upload_size)));
+    }
+    let max_bytes = [
+        size,
+        (user_ctx.settings.max_upload_size > 0).then_some(user_ctx.settings.max_upload_size as u64),
+        body_limit,
+    ].into_iter().flatten().min();
+    
+    // 获取挂载点
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
     
-    if let Some(mount) = get_first_mount(&path, &mounts) {
+    // 已存在时覆盖原文件，否则按写入策略选择挂载点
+    if let Some(mount) = select_write_mount(&state, &path, &mounts, size).await {
+        // 挂载点与用户组的上传策略（扩展名、单文件上限）
+        check_upload_policy(&mount, &user_ctx, &path, size).map_err(ApiError::Forbidden)?;
+        let max_bytes = max_bytes.into_iter().chain(mount.upload.size_limit()).min();
         let mount_path = fix_and_clean_path(&mount.mount_path);
-        let actual_path = if path.len() > mount_path.len() {
-            fix_and_clean_path(&path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let actual_path = calculate_internal_path(&mount_path, &path);
         
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
             // Core 层控制写入：获取 writer 原语，写入内容
             use tokio::io::AsyncWriteExt;
             
-            let content_bytes = req.content.as_bytes();
-            let mut writer = driver.open_writer(&actual_path, Some(content_bytes.len() as u64), None).await
+            // 空间不足时在读取请求体之前拒绝
+            check_free_space(&driver, size.unwrap_or(0)).await
+                .map_err(ApiError::driver)?;
+            
+            let mut writer = driver.open_writer(&actual_path, size, None).await
                 .map_err(|e| {
                     tracing::error!("Failed to open writer: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
+                    ApiError::driver(e.to_string())
                 })?;
             
-            writer.write_all(content_bytes).await
+            stream_into(content, &mut writer, max_bytes, |_| {}).await
                 .map_err(|e| {
                     tracing::error!("Failed to write file: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
+                    ApiError::from(e)
                 })?;
             
             writer.shutdown().await
                 .map_err(|e| {
                     tracing::error!("Failed to close writer: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
+                    ApiError::driver(e.to_string())
                 })?;
             
+            // 病毒扫描：被感染的文件已从挂载点删除
+            let user_id = get_user_id(&state, &cookies).await;
+            scan_after_write(&state, &driver, &actual_path, &path, user_id.as_deref(), size).await
+                .map_err(|message| ApiError::Forbidden(message.to_string()))?;
+            
             return Ok(Json(json!({
                 "code": 200,
                 "message": "success"
@@ -200,10 +208,7 @@ pub async fn fs_write(
         }
     }
     
-    Ok(Json(json!({
-        "code": 404,
-        "message": "路径不存在"
-    })))
+    Err(ApiError::NotFound("路径不存在".to_string()))
 }
 
 #[derive(Debug, Deserialize)]
@@ -216,83 +221,63 @@ pub async fn fs_remove(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Json(req): Json<FsRemoveReq>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let req_path = fix_and_clean_path(&req.path);
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    // 权限验证
-    if !perms.delete_files && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有删除文件的权限"
-        })));
-    }
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": e
-            })));
+            return Err(ApiError::Forbidden(e));
         }
     };
     
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // 权限验证（按路径授权）
+    if !user_ctx.can(Capability::Delete, &path) {
+        return Err(ApiError::Forbidden("没有删除文件的权限".to_string()));
+    }
     
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
     
-    if let Some(mount) = get_first_mount(&path, &mounts) {
+    // 聚合挂载中同名目录可能分布在多个挂载点，全部删除
+    let item_mounts = find_item_mounts(&state, &path, &mounts).await;
+    let mut deleted = false;
+    for mount in &item_mounts {
         let mount_path = fix_and_clean_path(&mount.mount_path);
         let actual_path = if path.len() > mount_path.len() {
             fix_and_clean_path(&path[mount_path.len()..])
         } else {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": "不能删除根目录"
-            })));
+            return Err(ApiError::Forbidden("不能删除根目录".to_string()));
         };
         
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
-            let driver = driver;
-            match driver.delete(&actual_path).await {
-                Ok(_) => {
-                    return Ok(Json(json!({
-                        "code": 200,
-                        "message": "success"
-                    })));
-                }
-                Err(e) => {
-                    return Ok(Json(json!({
-                        "code": 500,
-                        "message": format!("删除失败: {}", e)
-                    })));
-                }
-            }
+            driver.delete(&actual_path).await
+                .map_err(|e| ApiError::driver(format!("删除失败: {}", e)))?;
+            deleted = true;
         }
     }
     
-    Ok(Json(json!({
-        "code": 404,
-        "message": "路径不存在"
-    })))
+    // 已分层文件的冷存储副本一并删除
+    if crate::api::tiering::remove_tiered(&state, &path).await.map_err(ApiError::driver)? {
+        deleted = true;
+    }
+    
+    if deleted {
+        forget_upload_placement(&state, &path).await;
+        let user_id = get_user_id(&state, &cookies).await;
+        fire_file_hook(&state, FileHookEvent::Delete, &path, user_id, json!({
+            "mount_id": item_mounts.first().map(|m| m.id.clone()),
+        }));
+        return Ok(Json(json!({
+            "code": 200,
+            "message": "success"
+        })));
+    }
+    
+    Err(ApiError::NotFound("路径不存在".to_string()))
 }
 
 #[derive(Debug, Deserialize)]
@@ -306,81 +291,51 @@ pub async fn fs_rename(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
     Json(req): Json<FsRenameReq>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let req_path = fix_and_clean_path(&req.path);
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    // 权限验证
-    if !perms.rename_files && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有重命名文件的权限"
-        })));
-    }
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": e
-            })));
+            return Err(ApiError::Forbidden(e));
         }
     };
     
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // 权限验证（按路径授权）
+    if !user_ctx.can(Capability::Rename, &path) {
+        return Err(ApiError::Forbidden("没有重命名文件的权限".to_string()));
+    }
     
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
+    let mounts = get_user_mounts(&state, &user_ctx).await?;
     
-    if let Some(mount) = get_first_mount(&path, &mounts) {
+    // 聚合挂载中同名目录可能分布在多个挂载点，全部重命名
+    let mut renamed = false;
+    for mount in find_item_mounts(&state, &path, &mounts).await {
         let mount_path = fix_and_clean_path(&mount.mount_path);
         let actual_path = if path.len() > mount_path.len() {
             fix_and_clean_path(&path[mount_path.len()..])
         } else {
-            return Ok(Json(json!({
-                "code": 403,
-                "message": "不能重命名根目录"
-            })));
+            return Err(ApiError::Forbidden("不能重命名根目录".to_string()));
         };
         
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
-            let driver = driver;
-            match driver.rename(&actual_path, &req.name).await {
-                Ok(_) => {
-                    return Ok(Json(json!({
-                        "code": 200,
-                        "message": "success"
-                    })));
-                }
-                Err(e) => {
-                    return Ok(Json(json!({
-                        "code": 500,
-                        "message": format!("重命名失败: {}", e)
-                    })));
-                }
-            }
+            driver.rename(&actual_path, &req.name).await
+                .map_err(|e| ApiError::driver(format!("重命名失败: {}", e)))?;
+            renamed = true;
         }
     }
     
-    Ok(Json(json!({
-        "code": 404,
-        "message": "路径不存在"
-    })))
+    if renamed {
+        forget_upload_placement(&state, &path).await;
+        return Ok(Json(json!({
+            "code": 200,
+            "message": "success"
+        })));
+    }
+    
+    Err(ApiError::NotFound("路径不存在".to_string()))
 }
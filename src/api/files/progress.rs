@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use yaolist_backend::error::ApiError;
+use yaolist_backend::playback::{self, Progress};
+use yaolist_backend::utils::fix_and_clean_path;
+
+use super::{get_user_context, get_user_id};
+
+type ProgressRow = (String, f64, Option<f64>, bool, String);
+
+/// 当前登录用户的ID（游客共用一个账号，不记录进度）
+async fn progress_user(state: &AppState, cookies: &Cookies) -> Result<String, ApiError> {
+    let user_ctx = get_user_context(state, cookies).await;
+    if user_ctx.is_guest {
+        return Err(ApiError::Forbidden("登录后才能同步播放进度".to_string()));
+    }
+    get_user_id(state, cookies).await
+        .ok_or_else(|| ApiError::Forbidden("登录后才能同步播放进度".to_string()))
+}
+
+fn progress_json((path, position, duration, finished, updated_at): ProgressRow) -> Value {
+    let ratio = Progress::new(position, duration).and_then(|p| p.ratio());
+    json!({
+        "path": path,
+        "name": path.rsplit('/').next().unwrap_or(""),
+        "position": position,
+        "duration": duration,
+        "progress": ratio,
+        "finished": finished,
+        "updated_at": updated_at
+    })
+}
+
+/// 用户最近观看的记录（按更新时间倒序），仪表盘与最近观看列表共用
+pub async fn recent_progress(pool: &SqlitePool, user_id: &str, limit: i64, include_finished: bool) -> Result<Vec<Value>, sqlx::Error> {
+    let rows: Vec<ProgressRow> = sqlx::query_as(
+        "SELECT path, position, duration, finished, updated_at FROM playback_progress
+         WHERE user_id = ? AND (? OR finished = 0)
+         ORDER BY updated_at DESC LIMIT ?"
+    )
+    .bind(user_id)
+    .bind(include_finished)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(progress_json).collect())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressQuery {
+    pub path: String,
+}
+
+/// GET /api/fs/progress - 获取当前用户在某个文件上的播放进度（没有记录时 data 为 null）
+pub async fn fs_get_progress(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<ProgressQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let user_id = progress_user(&state, &cookies).await?;
+    let path = fix_and_clean_path(&query.path);
+    let row: Option<ProgressRow> = sqlx::query_as(
+        "SELECT path, position, duration, finished, updated_at FROM playback_progress WHERE user_id = ? AND path = ?"
+    )
+    .bind(&user_id)
+    .bind(&path)
+    .fetch_optional(&state.db)
+    .await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": row.map(progress_json)
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetProgressReq {
+    pub path: String,
+    /// 播放位置（秒）
+    pub position: f64,
+    /// 总时长（秒）
+    pub duration: Option<f64>,
+}
+
+/// POST /api/fs/progress - 保存当前用户的播放进度，多设备之间按最后一次上报为准
+pub async fn fs_set_progress(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SetProgressReq>,
+) -> Result<Json<Value>, ApiError> {
+    let user_id = progress_user(&state, &cookies).await?;
+    let path = fix_and_clean_path(&req.path);
+    if path == "/" {
+        return Err(ApiError::BadRequest("路径不能为空".to_string()));
+    }
+    let progress = Progress::new(req.position, req.duration)
+        .ok_or_else(|| ApiError::BadRequest("播放位置无效".to_string()))?;
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO playback_progress (user_id, path, position, duration, finished, updated_at) VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&user_id)
+    .bind(&path)
+    .bind(progress.position)
+    .bind(progress.duration)
+    .bind(progress.is_finished())
+    .bind(&now)
+    .execute(&state.db)
+    .await?;
+
+    // 只保留最近的记录
+    sqlx::query(
+        "DELETE FROM playback_progress WHERE user_id = ? AND path NOT IN (
+            SELECT path FROM playback_progress WHERE user_id = ? ORDER BY updated_at DESC LIMIT ?
+        )"
+    )
+    .bind(&user_id)
+    .bind(&user_id)
+    .bind(playback::MAX_ENTRIES_PER_USER)
+    .execute(&state.db)
+    .await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": progress_json((path, progress.position, progress.duration, progress.is_finished(), now))
+    })))
+}
+
+/// POST /api/fs/progress/delete - 删除播放进度（从最近观看中移除）
+pub async fn fs_delete_progress(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<ProgressQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let user_id = progress_user(&state, &cookies).await?;
+    sqlx::query("DELETE FROM playback_progress WHERE user_id = ? AND path = ?")
+        .bind(&user_id)
+        .bind(fix_and_clean_path(&req.path))
+        .execute(&state.db)
+        .await?;
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success"
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecentProgressQuery {
+    pub limit: Option<i64>,
+    /// 包含已看完的视频
+    pub include_finished: Option<bool>,
+}
+
+/// GET /api/fs/progress/recent - 当前用户最近观看的视频
+pub async fn fs_recent_progress(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<RecentProgressQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let user_id = progress_user(&state, &cookies).await?;
+    let limit = query.limit.unwrap_or(playback::DASHBOARD_RECENT).clamp(1, playback::MAX_RECENT);
+    let items = recent_progress(&state.db, &user_id, limit, query.include_finished.unwrap_or(false)).await?;
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": items
+    })))
+}
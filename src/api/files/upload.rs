@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use std::collections::HashMap;
 use axum::{
-    extract::{State, Multipart},
+    extract::{Extension, State, Multipart},
     http::StatusCode,
     Json,
 };
@@ -13,10 +13,16 @@ use tokio::io::AsyncWrite;
 
 use crate::state::AppState;
 use crate::task::{TaskType, TaskStatus, UploadFileInfo};
-use crate::api::file_resolver::{MountInfo, get_first_mount};
+use crate::api::file_resolver::{get_all_mounts, get_user_mounts, get_matching_mounts, calculate_internal_path, select_write_mount};
 use yaolist_backend::utils::{fix_and_clean_path, resolve_conflict_name, ConflictStrategy};
+use yaolist_backend::access::Capability;
+use yaolist_backend::body_limit::BodyLimit;
+use yaolist_backend::body_stream::{stream_into, BodyStreamError};
+use yaolist_backend::error::DriverErrorKind;
+use yaolist_backend::storage::DriverBox;
 
-use super::{get_user_context, join_user_path, get_user_id};
+use super::{check_upload_policy, get_user_context, get_user_id, lookup_file_size};
+use crate::api::virus_scan::{scan_after_write, scan_before_commit};
 
 /// 安全地执行进度更新任务
 /// 如果当前在 Tokio 运行时中，直接 spawn；否则使用 channel 发送到主运行时
@@ -48,45 +54,82 @@ lazy_static::lazy_static! {
     static ref STREAM_WRITERS: RwLock<HashMap<String, tokio::sync::Mutex<Box<dyn AsyncWrite + Unpin + Send>>>> = RwLock::new(HashMap::new());
 }
 
-/// 流式写入缓冲大小
-const STREAM_BUFFER_SIZE: usize = 1 * 1024 * 1024; // 1MB
-
 /// 最大内存缓冲大小（超过此大小使用流式写入）
 const MAX_MEMORY_CHUNK_SIZE: usize = 32 * 1024 * 1024; // 32MB
 
+/// 查询驱动剩余空间的超时（秒）
+const SPACE_CHECK_TIMEOUT_SECS: u64 = 10;
+
+/// 写入前检查驱动剩余空间，空间不足时在读取请求体之前拒绝
+/// 驱动不提供空间信息或查询失败时放行，由写入时的错误决定
+pub async fn check_free_space(driver: &DriverBox, size: u64) -> Result<(), String> {
+    if size == 0 {
+        return Ok(());
+    }
+    let timeout = std::time::Duration::from_secs(SPACE_CHECK_TIMEOUT_SECS);
+    match tokio::time::timeout(timeout, driver.get_space_info()).await {
+        Ok(Ok(Some(space))) if space.total > 0 && space.free < size => {
+            Err(format!("存储空间不足: 需要 {} 字节，剩余 {} 字节", size, space.free))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 流式写入中止时的响应
+fn stream_error_response(e: BodyStreamError) -> Result<Json<Value>, StatusCode> {
+    match e {
+        BodyStreamError::Body(e) => {
+            tracing::debug!("Upload body interrupted: {}", e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+        BodyStreamError::TooLarge(limit) => {
+            tracing::warn!("Upload body exceeds declared size {}", limit);
+            Err(StatusCode::PAYLOAD_TOO_LARGE)
+        }
+        BodyStreamError::Write(e) => {
+            tracing::error!("write chunk failed: {}", e);
+            let code = match DriverErrorKind::classify(&e.to_string()) {
+                DriverErrorKind::QuotaExceeded => StatusCode::INSUFFICIENT_STORAGE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            Ok(Json(json!({
+                "code": code.as_u16(),
+                "message": format!("写入失败: {}", e)
+            })))
+        }
+    }
+}
+
 /// POST /api/fs/upload - 分片上传文件（使用流式写入）
+///
+/// 元数据字段需在文件字段之前提交：收到文件字段时先完成权限与空间检查，
+/// 再将文件内容边读边写入驱动，不在内存中缓存整个分片。
 pub async fn fs_upload(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
+    body_limit: Option<Extension<BodyLimit>>,
     mut multipart: Multipart,
 ) -> Result<Json<Value>, StatusCode> {
     use tokio::io::AsyncWriteExt;
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    // 权限验证
-    if !perms.create_upload && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有上传文件的权限"
-        })));
-    }
     
-    let user_root_path = user_ctx.root_path.clone();
     let mut target_path = String::new();
     let mut filename = String::new();
     let mut chunk_index: i64 = -1;
     let mut total_chunks: i64 = 1;
     let mut total_size: u64 = 0;
     let mut task_id: Option<String> = None;
-    let mut file_data: Option<Vec<u8>> = None;
+    let mut conflict_strategy: Option<String> = None;
     
     let user_id = get_user_id(&state, &cookies).await;
     
     // 解析multipart数据 - 先解析元数据字段，保存文件字段稍后处理
-    while let Some(mut field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+    let file_field = loop {
+        let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? else {
+            break None;
+        };
         let name = field.name().unwrap_or("").to_string();
         match name.as_str() {
             "path" => target_path = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?,
@@ -95,50 +138,50 @@ pub async fn fs_upload(
             "totalChunks" => total_chunks = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?.parse().unwrap_or(1),
             "totalSize" => total_size = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?.parse().unwrap_or(0),
             "taskId" => task_id = Some(field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?),
+            "conflictStrategy" => conflict_strategy = Some(field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?),
             "file" | "files" => {
                 if filename.is_empty() {
                     filename = field.file_name().unwrap_or("unknown").to_string();
                 }
-                // 立即读取文件字段数据，避免借用问题
-                let mut data = Vec::new();
-                while let Some(chunk) = field.chunk().await.transpose() {
-                    match chunk {
-                        Ok(c) => data.extend_from_slice(&c),
-                        Err(_) => return Err(StatusCode::BAD_REQUEST),
-                    }
-                }
-                file_data = Some(data);
+                // 文件内容留到检查完成后再读取，其后的字段不再解析
+                break Some(field);
             }
             _ => {}
         }
-    }
+    };
     
-    let file_data = file_data.ok_or(StatusCode::BAD_REQUEST)?;
+    let file_field = file_field.ok_or(StatusCode::BAD_REQUEST)?;
+    // 用户组的单文件上传上限
+    if user_ctx.settings.upload_too_large(total_size) {
+        return Ok(Json(json!({
+            "code": 413,
+            "message": format!("文件大小超过用户组上传上限 {} 字节", user_ctx.settings.max_upload_size)
+        })));
+    }
+    // 文件内容不能超过声明的文件大小、用户组上限与路由上限
+    let max_bytes = [
+        (total_size > 0).then_some(total_size),
+        (user_ctx.settings.max_upload_size > 0).then_some(user_ctx.settings.max_upload_size as u64),
+        body_limit.map(|Extension(BodyLimit(limit))| limit),
+    ].into_iter().flatten().min();
     if target_path.is_empty() {
         target_path = "/".to_string();
     }
+    // 文件夹上传的文件名可带相对目录，但不允许跳出目标目录
+    if filename.split(['/', '\\']).any(|p| p == "..") {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "路径不合法"
+        })));
+    }
     
-    // 获取挂载点
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
+    // 获取用户可见的挂载点
+    let mounts = get_user_mounts(&state, &user_ctx).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
     let req_path = fix_and_clean_path(&target_path);
-    let path = match join_user_path(&user_root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -147,19 +190,85 @@ pub async fn fs_upload(
             })));
         }
     };
+    
+    // 权限验证（按路径授权）
+    if !user_ctx.can(Capability::Upload, &path) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有上传文件的权限"
+        })));
+    }
+    
+    // 首个分片按冲突策略处理同名文件，后续分片使用响应中返回的文件名
+    if chunk_index <= 0 {
+        if let Some(strategy) = conflict_strategy.as_deref().map(|s| parse_conflict_strategy(Some(s))) {
+            let (parent, name) = split_upload_name(&path, &filename);
+            let existing_names = get_existing_names(&state, &parent).await;
+            if existing_names.contains(&name) {
+                match strategy {
+                    ConflictStrategy::Overwrite => {}
+                    ConflictStrategy::AutoRename => {
+                        let new_name = resolve_conflict_name(&name, &existing_names);
+                        filename = match filename.rsplit_once('/') {
+                            Some((dir, _)) => format!("{}/{}", dir, new_name),
+                            None => new_name,
+                        };
+                    }
+                    ConflictStrategy::Skip => {
+                        // 批次任务中跳过的文件视为已完成
+                        if let Some(ref tid) = task_id {
+                            let batch_file_path = fix_and_clean_path(&format!("{}/{}", parent, name));
+                            state.task_manager.complete_file(tid, &batch_file_path).await;
+                        }
+                        return Ok(Json(json!({
+                            "code": 200,
+                            "message": "文件已存在，已跳过",
+                            "data": {
+                                "filename": filename,
+                                "completed": true,
+                                "skipped": true,
+                                "taskId": task_id
+                            }
+                        })));
+                    }
+                    ConflictStrategy::Error => {
+                        return Ok(Json(json!({
+                            "code": 409,
+                            "message": format!("文件已存在: {}", name)
+                        })));
+                    }
+                }
+            }
+        }
+    }
+    
     let file_path = if path == "/" {
         format!("/{}", filename)
     } else {
         format!("{}/{}", path, filename)
     };
     
-    let mount = get_first_mount(&file_path, &mounts)
+    // 多个挂载点共用路径时按写入策略选择（已存在的文件写回原挂载点）
+    let mount = select_write_mount(&state, &file_path, &mounts, Some(total_size)).await
         .ok_or_else(|| {
             tracing::error!("Upload failed: Mount point not found, file_path={}", file_path);
             StatusCode::NOT_FOUND
         })?;
     
     tracing::debug!("Upload: Found mount point {}, file_path={}", mount.mount_path, file_path);
+    
+    // 挂载点与用户组的上传策略（扩展名、单文件上限）
+    if let Err(message) = check_upload_policy(&mount, &user_ctx, &file_path, (total_size > 0).then_some(total_size)) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": message
+        })));
+    }
+    let max_bytes = max_bytes.into_iter().chain(mount.upload.size_limit()).min();
+    // 分片各自受 max_bytes 限制，完成时再按策略上限检查合并后的大小
+    let completed_limit = mount.upload.size_limit().into_iter()
+        .chain(user_ctx.settings.upload_policy().size_limit())
+        .min();
     let mount_path = fix_and_clean_path(&mount.mount_path);
     let actual_path = if file_path.len() > mount_path.len() {
         fix_and_clean_path(&file_path[mount_path.len()..])
@@ -175,6 +284,9 @@ pub async fn fs_upload(
     
     tracing::debug!("Upload: Driver obtained successfully, actual_path={}", actual_path);
     
+    // 病毒扫描记录中的上传者
+    let uploader = user_id.clone();
+    
     // 创建或获取任务
     let is_batch_task = task_id.is_some();
     let current_task_id = if let Some(tid) = task_id {
@@ -198,13 +310,8 @@ pub async fn fs_upload(
         tid
     };
     
-    // 批次任务中的文件路径 - 使用纯文件名匹配（批次任务只存储文件名）
-    let simple_filename = filename.split('/').last().unwrap_or(&filename);
-    let batch_file_path = if path == "/" {
-        format!("/{}", simple_filename)
-    } else {
-        format!("{}/{}", path, simple_filename)
-    };
+    // 批次任务中的文件路径（文件夹上传时包含相对目录）
+    let batch_file_path = fix_and_clean_path(&file_path);
     
     // 检查任务是否被取消
     if is_batch_task {
@@ -233,17 +340,36 @@ pub async fn fs_upload(
         let needs_local_cache = capabilities.requires_full_file_for_upload;
         
         if needs_local_cache {
-            // 需要本地缓存：使用已读取的文件数据
+            // 需要本地缓存：分片内容直接写入临时文件
             // 123云盘等：缓存分片到本地，最后合并调用put（需要完整MD5）
-            let temp_dir = std::path::PathBuf::from("data/temps");
+            let temp_dir = std::path::PathBuf::from(yaolist_backend::storage::upload_cleanup::TEMP_DIR);
             let _ = std::fs::create_dir_all(&temp_dir);
             let chunk_file = temp_dir.join(format!("{}_{}.part{}", current_task_id, filename.replace("/", "_"), chunk_index));
             
-            tokio::fs::write(&chunk_file, &file_data).await
+            if chunk_index == 0 {
+                if let Err(message) = check_free_space(&driver, total_size).await {
+                    return Ok(Json(json!({
+                        "code": 507,
+                        "message": message
+                    })));
+                }
+            }
+            
+            let mut part = tokio::fs::File::create(&chunk_file).await
                 .map_err(|e| {
                     tracing::error!("write chunk to temp failed: {}", e);
                     StatusCode::INTERNAL_SERVER_ERROR
                 })?;
+            let streamed = stream_into(file_field, &mut part, max_bytes, |_| {}).await;
+            let streamed = match streamed {
+                Ok(_) => part.flush().await.map_err(BodyStreamError::Write),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = streamed {
+                drop(part);
+                let _ = tokio::fs::remove_file(&chunk_file).await;
+                return stream_error_response(e);
+            }
             
             if is_batch_task {
                 state.task_manager.update_file_progress(
@@ -263,11 +389,13 @@ pub async fn fs_upload(
                 let batch_file_path_clone = batch_file_path.clone();
                 let is_batch = is_batch_task;
                 let filename_clone = filename.clone();
+                let state_clone = state.clone();
+                let uploader = uploader.clone();
                 
-                tokio::spawn(async move {
+                yaolist_backend::request_id::spawn(async move {
                     let upload_result = async {
                         let mut merged_data = Vec::with_capacity(total_size as usize);
-                        let temp_dir = std::path::PathBuf::from("data/temps");
+                        let temp_dir = std::path::PathBuf::from(yaolist_backend::storage::upload_cleanup::TEMP_DIR);
                         
                         for i in 0..total_chunks {
                             let part_file = temp_dir.join(format!("{}_{}.part{}", task_id_clone, filename_clone.replace("/", "_"), i));
@@ -307,6 +435,14 @@ pub async fn fs_upload(
                             });
                         }));
                         
+                        if let Some(limit) = completed_limit.filter(|limit| merged_data.len() as u64 > *limit) {
+                            anyhow::bail!("文件大小超过上传上限 {} 字节", limit);
+                        }
+                        
+                        // 提交到驱动前扫描病毒
+                        scan_before_commit(&state_clone, &merged_data, &batch_file_path_clone, uploader.as_deref()).await
+                            .map_err(anyhow::Error::msg)?;
+                        
                         driver_clone.put(&actual_path_clone, bytes::Bytes::from(merged_data), progress_callback).await?;
                         Ok::<(), anyhow::Error>(())
                     }.await;
@@ -367,6 +503,13 @@ pub async fn fs_upload(
             
             // 第一个分片：创建writer并缓存
             if chunk_index == 0 {
+                if let Err(message) = check_free_space(&driver, total_size).await {
+                    return Ok(Json(json!({
+                        "code": 507,
+                        "message": message
+                    })));
+                }
+                
                 // 创建progress callback，driver可用于报告上传进度
                 // 分片上传：只显示上传到驱动的进度（0-100%）
                 let task_manager_clone = state.task_manager.clone();
@@ -404,26 +547,17 @@ pub async fn fs_upload(
                 writers.insert(writer_key.clone(), tokio::sync::Mutex::new(writer));
             }
             
-            // 写入文件数据到writer（分块写入以模拟流式）
-            let _current_chunk_size = file_data.len() as u64;
+            // 分片内容边读边写入writer
             {
                 let readers = STREAM_WRITERS.read().await;
                 if let Some(writer_mutex) = readers.get(&writer_key) {
                     let mut writer = writer_mutex.lock().await;
-                    
-                    // 分块写入，每次最多写入1MB
-                    let mut offset = 0;
-                    while offset < file_data.len() {
-                        let end = std::cmp::min(offset + STREAM_BUFFER_SIZE, file_data.len());
-                        let chunk = &file_data[offset..end];
-                        
-                        writer.write_all(chunk).await
-                                .map_err(|e| {
-                                    tracing::error!("write chunk failed: {}", e);
-                                    StatusCode::INTERNAL_SERVER_ERROR
-                                })?;
-                        
-                        offset = end;
+                    if let Err(e) = stream_into(file_field, &mut *writer, max_bytes, |_| {}).await {
+                        // 写入中断后writer状态未知，丢弃并让客户端重新上传
+                        drop(writer);
+                        drop(readers);
+                        STREAM_WRITERS.write().await.remove(&writer_key);
+                        return stream_error_response(e);
                     }
                 } else {
                     // Writer不存在可能是任务已取消
@@ -453,6 +587,37 @@ pub async fn fs_upload(
                     }
                 }
                 
+                // 合并后超过策略上限时删除已写入的文件
+                if let Some(limit) = completed_limit {
+                    if lookup_file_size(&state, &mount.id, &actual_path).await.is_some_and(|size| size > limit) {
+                        if let Err(e) = driver.delete(&actual_path).await {
+                            tracing::error!("Failed to remove oversized upload {}: {}", batch_file_path, e);
+                        }
+                        let message = format!("文件大小超过上传上限 {} 字节", limit);
+                        if !is_batch_task {
+                            state.task_manager.fail_task(&current_task_id, message.clone()).await;
+                        }
+                        return Ok(Json(json!({
+                            "code": 413,
+                            "message": message
+                        })));
+                    }
+                }
+                
+                // 写入完成后扫描病毒，被感染的文件已从挂载点删除
+                let scanned = scan_after_write(
+                    &state, &driver, &actual_path, &batch_file_path, uploader.as_deref(), (total_size > 0).then_some(total_size),
+                ).await;
+                if let Err(message) = scanned {
+                    if !is_batch_task {
+                        state.task_manager.fail_task(&current_task_id, message.to_string()).await;
+                    }
+                    return Ok(Json(json!({
+                        "code": 403,
+                        "message": message
+                    })));
+                }
+                
                 if is_batch_task {
                     state.task_manager.update_file_progress(&current_task_id, &batch_file_path, total_size, None).await;
                     state.task_manager.complete_file(&current_task_id, &batch_file_path).await;
@@ -512,6 +677,13 @@ pub async fn fs_upload(
             });
         }));
         
+        if let Err(message) = check_free_space(&driver, total_size).await {
+            return Ok(Json(json!({
+                "code": 507,
+                "message": message
+            })));
+        }
+        
         // 创建writer
         let mut writer = driver.open_writer(&actual_path, Some(total_size), progress_callback).await
             .map_err(|e| {
@@ -524,25 +696,24 @@ pub async fn fs_upload(
         let task_id_clone = current_task_id.clone();
         let file_path_clone = batch_file_path.clone();
         let is_batch_clone = is_batch_task;
-        let total_file_size = file_data.len() as u64;
         let total_size_clone = ts;
         
-        // 使用 Arc 和 Mutex 来跟踪已写入的字节数
-        let written_bytes = Arc::new(tokio::sync::Mutex::new(0u64));
+        // 跟踪已写入的字节数
+        let written_bytes = Arc::new(std::sync::atomic::AtomicU64::new(0));
         let written_bytes_clone = written_bytes.clone();
         let stop_progress_update = Arc::new(tokio::sync::Notify::new());
         let stop_progress_update_clone = stop_progress_update.clone();
         
         // 启动定期进度更新任务（每秒更新一次）
-        let progress_update_handle = tokio::spawn(async move {
+        let progress_update_handle = yaolist_backend::request_id::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
             loop {
                 tokio::select! {
                     _ = interval.tick() => {
-                        let written = *written_bytes_clone.lock().await;
+                        let written = written_bytes_clone.load(std::sync::atomic::Ordering::Relaxed);
                         // 更新前端传输进度：0-50%（基于累计写入的字节数）
-                        let front_progress = if total_file_size > 0 {
-                            ((written as f64 / total_file_size as f64) * 0.5 * total_size_clone as f64) as u64
+                        let front_progress = if total_size_clone > 0 {
+                            ((written as f64 / total_size_clone as f64).min(1.0) * 0.5 * total_size_clone as f64) as u64
                         } else {
                             0
                         };
@@ -560,31 +731,22 @@ pub async fn fs_upload(
             }
         });
         
-        // 分块写入文件数据
-        let mut offset = 0;
-        while offset < file_data.len() {
-            let end = std::cmp::min(offset + STREAM_BUFFER_SIZE, file_data.len());
-            let chunk = &file_data[offset..end];
-            
-            writer.write_all(chunk).await
-                .map_err(|e| {
-                    tracing::error!("write chunk failed: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
-            
-            // 更新已写入字节数
-            {
-                let mut written = written_bytes.lock().await;
-                *written = end as u64;
-            }
-            
-            offset = end;
-        }
+        // 文件内容边读边写入writer
+        let streamed = stream_into(file_field, &mut writer, max_bytes, |written| {
+            written_bytes.store(written, std::sync::atomic::Ordering::Relaxed);
+        }).await;
         
         // 停止进度更新任务
         stop_progress_update.notify_one();
         progress_update_handle.await.ok();
         
+        if let Err(e) = streamed {
+            if !is_batch_task {
+                state.task_manager.fail_task(&current_task_id, e.to_string()).await;
+            }
+            return stream_error_response(e);
+        }
+        
         // 关闭writer
         writer.shutdown().await
             .map_err(|e| {
@@ -592,6 +754,20 @@ pub async fn fs_upload(
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
         
+        // 写入完成后扫描病毒，被感染的文件已从挂载点删除
+        let scanned = scan_after_write(
+            &state, &driver, &actual_path, &batch_file_path, uploader.as_deref(), (total_size > 0).then_some(total_size),
+        ).await;
+        if let Err(message) = scanned {
+            if !is_batch_task {
+                state.task_manager.fail_task(&current_task_id, message.to_string()).await;
+            }
+            return Ok(Json(json!({
+                "code": 403,
+                "message": message
+            })));
+        }
+        
         // 标记文件/任务完成
         if is_batch_task {
             state.task_manager.update_file_progress(&current_task_id, &batch_file_path, total_size, None).await;
@@ -629,17 +805,9 @@ pub async fn fs_upload_status(
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    if !perms.create_upload && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有权限"
-        })));
-    }
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -649,6 +817,13 @@ pub async fn fs_upload_status(
         }
     };
     
+    if !user_ctx.can(Capability::Upload, &path) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有权限"
+        })));
+    }
+    
     // 查询任务中的已上传分片（用于断点续传）
     let full_path = format!("{}/{}", path, req.filename);
     
@@ -688,6 +863,8 @@ pub struct CreateBatchUploadReq {
     pub files: Vec<BatchUploadFileInfo>,
     #[serde(default)]
     pub conflict_strategy: Option<String>, // "auto_rename", "overwrite", "skip", "error"
+    #[serde(default)]
+    pub group_id: Option<String>, // 加入的任务组
 }
 
 /// POST /api/fs/upload/batch - 创建批次上传任务
@@ -700,17 +877,9 @@ pub async fn fs_create_batch_upload(
     
     // 获取用户上下文（权限+根路径）
     let user_ctx = get_user_context(&state, &cookies).await;
-    let perms = &user_ctx.permissions;
-    
-    if !perms.create_upload && !perms.is_admin {
-        return Ok(Json(json!({
-            "code": 403,
-            "message": "没有上传权限"
-        })));
-    }
     
     // 将用户请求路径与用户根路径结合（防止路径穿越）
-    let target_path = match join_user_path(&user_ctx.root_path, &req_path) {
+    let target_path = match user_ctx.join_path(&req_path) {
         Ok(p) => p,
         Err(e) => {
             return Ok(Json(json!({
@@ -720,29 +889,45 @@ pub async fn fs_create_batch_upload(
         }
     };
     
+    if !user_ctx.can(Capability::Upload, &target_path) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有上传权限"
+        })));
+    }
+    
     let user_id = get_user_id(&state, &cookies).await;
     
     // 解析冲突策略
-    let strategy = match req.conflict_strategy.as_deref() {
-        Some("overwrite") => ConflictStrategy::Overwrite,
-        Some("skip") => ConflictStrategy::Skip,
-        Some("error") => ConflictStrategy::Error,
-        _ => ConflictStrategy::AutoRename,
-    };
+    let strategy = parse_conflict_strategy(req.conflict_strategy.as_deref());
     
-    // 获取目标目录已存在的文件列表（用于冲突检测）
-    let existing_names = get_existing_names(&state, &target_path).await;
+    // 各目录已存在的文件列表（用于冲突检测），每个目录只列举一次
+    let mut dir_names: HashMap<String, Vec<String>> = HashMap::new();
     
     // 处理文件名冲突
     let mut upload_files: Vec<UploadFileInfo> = Vec::new();
     let mut resolved_paths: Vec<Value> = Vec::new();
+    let mut directories: Vec<String> = Vec::new();
     
     for file in &req.files {
-        let filename = file.path.split('/').last().unwrap_or(&file.path);
+        // 文件夹上传时 path 为相对路径（如 photos/2024/a.jpg）
+        let Some(relative) = clean_relative_path(&file.path) else {
+            return Ok(Json(json!({
+                "code": 403,
+                "message": format!("路径不合法: {}", file.path)
+            })));
+        };
+        let (parent, filename) = split_upload_name(&target_path, &relative);
+        if !dir_names.contains_key(&parent) {
+            let names = get_existing_names(&state, &parent).await;
+            dir_names.insert(parent.clone(), names);
+        }
+        let existing_names = &dir_names[&parent];
+        let filename = filename.as_str();
         
         let final_name = match strategy {
             ConflictStrategy::AutoRename => {
-                resolve_conflict_name(filename, &existing_names)
+                resolve_conflict_name(filename, existing_names)
             }
             ConflictStrategy::Overwrite => filename.to_string(),
             ConflictStrategy::Skip => {
@@ -760,18 +945,23 @@ pub async fn fs_create_batch_upload(
                 if existing_names.contains(&filename.to_string()) {
                     return Ok(Json(json!({
                         "code": 409,
-                        "message": format!("文件已存在: {}", filename)
+                        "message": format!("文件已存在: {}", relative)
                     })));
                 }
                 filename.to_string()
             }
         };
         
-        let full_path = if target_path == "/" {
-            format!("/{}", final_name)
-        } else {
-            format!("{}/{}", target_path, final_name)
-        };
+        let full_path = fix_and_clean_path(&format!("{}/{}", parent, final_name));
+        
+        // 记录需要创建的中间目录（含所有上级目录）
+        let mut dir = parent.as_str();
+        while dir.len() > target_path.len() && dir.starts_with(target_path.as_str()) {
+            if !directories.iter().any(|d| d == dir) {
+                directories.push(dir.to_string());
+            }
+            dir = dir.rsplit_once('/').map(|(p, _)| p).unwrap_or("/");
+        }
         
         upload_files.push(UploadFileInfo {
             path: full_path.clone(),
@@ -779,6 +969,7 @@ pub async fn fs_create_batch_upload(
             uploaded_size: 0,
             uploaded_chunks: vec![],
             status: TaskStatus::Pending,
+            target_path: None,
         });
         
         resolved_paths.push(json!({
@@ -799,6 +990,18 @@ pub async fn fs_create_batch_upload(
         })));
     }
     
+    // 按层级顺序创建中间目录，父目录先于子目录
+    directories.sort_by(|a, b| a.matches('/').count().cmp(&b.matches('/').count()).then_with(|| a.cmp(b)));
+    for dir in &directories {
+        if let Err(e) = ensure_dir(&state, dir, &mut dir_names).await {
+            tracing::error!("Batch upload: failed to create directory {}: {}", dir, e);
+            return Ok(Json(json!({
+                "code": 500,
+                "message": format!("创建目录失败: {}: {}", dir, e)
+            })));
+        }
+    }
+    
     // 创建批次上传任务
     let task_name = if upload_files.len() == 1 {
         upload_files[0].path.split('/').last().unwrap_or("上传").to_string()
@@ -812,6 +1015,9 @@ pub async fn fs_create_batch_upload(
         upload_files,
         user_id,
     ).await;
+    if let Some(ref group_id) = req.group_id {
+        state.task_manager.add_to_group(group_id, std::slice::from_ref(&task_id)).await;
+    }
     
     // 启动任务
     state.task_manager.start_task(&task_id).await;
@@ -821,46 +1027,158 @@ pub async fn fs_create_batch_upload(
         "message": "success",
         "data": {
             "taskId": task_id,
-            "files": resolved_paths
+            "files": resolved_paths,
+            "directories": directories
         }
     })))
 }
 
-/// 获取目录中已存在的文件名列表
-pub async fn get_existing_names(state: &AppState, path: &str) -> Vec<String> {
-    let db_drivers: Vec<(String, String)> = sqlx::query_as(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await
-    .unwrap_or_default();
-    
-    let mounts: Vec<MountInfo> = db_drivers.iter().filter_map(|(id, config_str)| {
-        let config: Value = serde_json::from_str(config_str).ok()?;
-        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-        Some(MountInfo {
-            id: id.clone(),
-            mount_path: mount_path.to_string(),
-            order: 0,
-        })
-    }).collect();
-    
-    if let Some(mount) = get_first_mount(path, &mounts) {
-        let mount_path = fix_and_clean_path(&mount.mount_path);
-        let actual_path = if path.len() > mount_path.len() {
-            fix_and_clean_path(&path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
-        
-        if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
-            if let Ok(entries) = driver.list(&actual_path).await {
-                return entries.iter().map(|e| e.name.clone()).collect();
+/// 清理文件夹上传中的相对路径，包含 .. 或为空时返回 None
+fn clean_relative_path(path: &str) -> Option<String> {
+    let parts: Vec<&str> = path.split(['/', '\\'])
+        .filter(|p| !p.is_empty() && *p != ".")
+        .collect();
+    if parts.is_empty() || parts.contains(&"..") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// 确保目录存在，不存在时通过驱动创建（上级目录需已存在）
+async fn ensure_dir(state: &AppState, dir: &str, dir_names: &mut HashMap<String, Vec<String>>) -> Result<(), String> {
+    let (parent, name) = split_upload_name(dir, "");
+    if !dir_names.contains_key(&parent) {
+        let names = get_existing_names(state, &parent).await;
+        dir_names.insert(parent.clone(), names);
+    }
+    if dir_names[&parent].contains(&name) {
+        return Ok(());
+    }
+    let mounts = get_all_mounts(state).await.map_err(|e| e.to_string())?;
+    let mount = select_write_mount(state, dir, &mounts, None).await.ok_or_else(|| "挂载点不存在".to_string())?;
+    let driver = state.storage_manager.get_driver(&mount.id).await.ok_or_else(|| "驱动未加载".to_string())?;
+    let actual_path = calculate_internal_path(&mount.mount_path, dir);
+    driver.create_dir(&actual_path).await.map_err(|e| e.to_string())?;
+    if let Some(names) = dir_names.get_mut(&parent) {
+        names.push(name);
+    }
+    // 新目录为空，无需列举
+    dir_names.entry(dir.to_string()).or_default();
+    Ok(())
+}
+
+/// 解析冲突策略，默认自动重命名
+fn parse_conflict_strategy(value: Option<&str>) -> ConflictStrategy {
+    match value {
+        Some("overwrite") => ConflictStrategy::Overwrite,
+        Some("skip") => ConflictStrategy::Skip,
+        Some("error") => ConflictStrategy::Error,
+        _ => ConflictStrategy::AutoRename,
+    }
+}
+
+/// 拆分上传文件的父目录与文件名（文件名可带子目录，如文件夹上传）
+fn split_upload_name(dir: &str, filename: &str) -> (String, String) {
+    let full_path = fix_and_clean_path(&format!("{}/{}", dir, filename));
+    match full_path.rsplit_once('/') {
+        Some((parent, name)) => {
+            let parent = if parent.is_empty() { "/".to_string() } else { parent.to_string() };
+            (parent, name.to_string())
+        }
+        None => ("/".to_string(), full_path),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsExistsReq {
+    pub path: String,
+    pub names: Vec<String>,
+}
+
+/// POST /api/fs/exists - 上传前批量检查同名文件
+pub async fn fs_exists(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<FsExistsReq>,
+) -> Result<Json<Value>, StatusCode> {
+    let req_path = fix_and_clean_path(&req.path);
+    
+    // 获取用户上下文（权限+根路径）
+    let user_ctx = get_user_context(&state, &cookies).await;
+    
+    // 将用户请求路径与用户根路径结合（防止路径穿越）
+    let path = match user_ctx.join_path(&req_path) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(Json(json!({
+                "code": 403,
+                "message": e
+            })));
+        }
+    };
+    
+    if !user_ctx.can(Capability::Upload, &path) {
+        return Ok(Json(json!({
+            "code": 403,
+            "message": "没有权限"
+        })));
+    }
+    
+    // 同一目录只列举一次
+    let mut dir_names: HashMap<String, Vec<String>> = HashMap::new();
+    let mut files = Vec::with_capacity(req.names.len());
+    let mut conflicts = 0;
+    for name in &req.names {
+        let (parent, file_name) = split_upload_name(&path, name);
+        if !dir_names.contains_key(&parent) {
+            let existing_names = get_existing_names(&state, &parent).await;
+            dir_names.insert(parent.clone(), existing_names);
+        }
+        let existing_names = &dir_names[&parent];
+        let exists = existing_names.contains(&file_name);
+        // 建议名与上传时自动重命名的结果一致（保留子目录）
+        let suggested = exists.then(|| {
+            let new_name = resolve_conflict_name(&file_name, existing_names);
+            match name.rsplit_once('/') {
+                Some((dir, _)) => format!("{}/{}", dir, new_name),
+                None => new_name,
             }
+        });
+        if exists {
+            conflicts += 1;
         }
+        files.push(json!({
+            "name": name,
+            "exists": exists,
+            "suggested": suggested
+        }));
     }
     
-    vec![]
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": {
+            "files": files,
+            "conflicts": conflicts
+        }
+    })))
+}
+
+/// 获取目录中已存在的文件名列表（聚合挂载时合并所有挂载点）
+pub async fn get_existing_names(state: &AppState, path: &str) -> Vec<String> {
+    let mounts = get_all_mounts(state).await.unwrap_or_default();
+    let mut names = Vec::new();
+    for mount in get_matching_mounts(path, &mounts) {
+        let Some(driver) = state.storage_manager.get_driver(&mount.id).await else {
+            continue;
+        };
+        if let Ok(entries) = driver.list(&calculate_internal_path(&mount.mount_path, path)).await {
+            names.extend(entries.into_iter().map(|e| e.name));
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,365 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::dedupe::hash_key;
+use yaolist_backend::storage::{DriverBox, HashType};
+use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+use crate::api::dedupe::compute_md5;
+use crate::api::file_resolver::{calculate_internal_path, get_all_mounts, get_first_mount};
+use crate::task::{FsckIssueKind, FsckReport, Task, TaskControl, TaskType};
+
+/// 目录树中的文件
+struct TreeFile {
+    size: u64,
+    modified: Option<String>,
+    internal_path: String,
+}
+
+/// 已解析的目录：驱动与（相对路径 → 文件）
+struct Tree {
+    root: String,
+    driver: DriverBox,
+    files: BTreeMap<String, TreeFile>,
+}
+
+impl Tree {
+    fn virtual_path(&self, rel: &str) -> String {
+        format!("{}/{}", self.root.trim_end_matches('/'), rel)
+    }
+}
+
+/// 检查取消与暂停
+pub(crate) async fn check_control(control: &TaskControl) -> Result<(), String> {
+    if control.is_cancelled() {
+        return Err("任务已取消".to_string());
+    }
+    while control.is_paused() {
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        if control.is_cancelled() {
+            return Err("任务已取消".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// 遍历目录，列出失败时整个任务失败（不完整的列表会误报文件缺失）
+async fn walk(state: &AppState, root: &str, control: &TaskControl) -> Result<Tree, String> {
+    let mounts = get_all_mounts(state).await.map_err(|e| format!("获取挂载列表失败: {}", e))?;
+    let mount = get_first_mount(root, &mounts)
+        .ok_or_else(|| format!("目录不在任何挂载下: {}", root))?;
+    let driver = state.storage_manager.get_driver(&mount.id).await
+        .ok_or_else(|| format!("驱动未加载: {}", mount.id))?;
+
+    let mut files = BTreeMap::new();
+    // (相对路径, 驱动内路径)
+    let mut stack = vec![(String::new(), calculate_internal_path(&mount.mount_path, root))];
+    while let Some((rel_dir, internal_dir)) = stack.pop() {
+        check_control(control).await?;
+        let entries = driver.list(&internal_dir).await
+            .map_err(|e| format!("列出目录失败 {}/{}: {}", root.trim_end_matches('/'), rel_dir, e))?;
+        for entry in entries {
+            let rel = if rel_dir.is_empty() { entry.name.clone() } else { format!("{}/{}", rel_dir, entry.name) };
+            let internal_path = format!("{}/{}", internal_dir.trim_end_matches('/'), entry.name);
+            if entry.is_dir {
+                stack.push((rel, internal_path));
+            } else {
+                files.insert(rel, TreeFile { size: entry.size, modified: entry.modified, internal_path });
+            }
+        }
+    }
+    Ok(Tree { root: root.to_string(), driver, files })
+}
+
+/// 文件的当前校验值：驱动提供的MD5，本地存储读取内容计算；都没有时为None
+async fn current_hash(driver: &DriverBox, internal_path: &str) -> Result<Option<String>, String> {
+    if driver.hash_types().contains(&HashType::Md5) {
+        if let Some(hash) = driver.get_hash(internal_path, HashType::Md5).await.map_err(|e| e.to_string())? {
+            return Ok(Some(hash_key(HashType::Md5, &hash)));
+        }
+    }
+    if driver.is_local() {
+        let hash = compute_md5(driver, internal_path).await.map_err(|e| e.to_string())?;
+        return Ok(Some(hash_key(HashType::Md5, &hash)));
+    }
+    Ok(None)
+}
+
+/// 记录文件的校验值（与重复文件扫描共用缓存）
+async fn save_hash(state: &AppState, path: &str, file: &TreeFile, hash: &str) {
+    if let Err(e) = sqlx::query(
+        "INSERT OR REPLACE INTO file_hashes (path, size, modified, hash, updated_at) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(path)
+    .bind(file.size as i64)
+    .bind(&file.modified)
+    .bind(hash)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    {
+        tracing::warn!("Failed to save hash of {}: {}", path, e);
+    }
+}
+
+/// 与参照目录对比：参照目录中的每个文件都必须存在于被检查目录，且大小、校验值一致
+async fn compare_trees(
+    state: &AppState,
+    task_id: &str,
+    control: &TaskControl,
+    checked: &Tree,
+    reference: &Tree,
+    report: &mut FsckReport,
+) -> Result<(), String> {
+    let total_size = reference.files.values().map(|f| f.size).sum();
+    state.task_manager.update_task_size(task_id, total_size, reference.files.len() as u64).await;
+
+    let mut processed_size = 0;
+    for (index, (rel, expected)) in reference.files.iter().enumerate() {
+        check_control(control).await?;
+        let path = checked.virtual_path(rel);
+        processed_size += expected.size;
+        state.task_manager.update_task_progress_with_size(task_id, index as u64 + 1, processed_size, Some(path.clone())).await;
+
+        let Some(actual) = checked.files.get(rel) else {
+            report.issue(&path, FsckIssueKind::Missing, format!("参照文件 {} 在此处不存在", reference.virtual_path(rel)));
+            continue;
+        };
+        if actual.size != expected.size {
+            report.issue(&path, FsckIssueKind::SizeMismatch, format!("期望 {} 字节, 实际 {} 字节", expected.size, actual.size));
+            continue;
+        }
+        let expected_hash = match current_hash(&reference.driver, &expected.internal_path).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                report.issue(&reference.virtual_path(rel), FsckIssueKind::Unreadable, e);
+                continue;
+            }
+        };
+        let actual_hash = match current_hash(&checked.driver, &actual.internal_path).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                report.issue(&path, FsckIssueKind::Unreadable, e);
+                continue;
+            }
+        };
+        match (expected_hash, actual_hash) {
+            (Some(expected_hash), Some(actual_hash)) if expected_hash != actual_hash => {
+                report.issue(&path, FsckIssueKind::HashMismatch, format!("期望 {}, 实际 {}", expected_hash, actual_hash));
+            }
+            (Some(expected_hash), Some(_)) => {
+                save_hash(state, &path, actual, &expected_hash).await;
+                report.checksum_verified += 1;
+            }
+            _ => report.size_verified += 1,
+        }
+    }
+    Ok(())
+}
+
+/// 与记录的校验值对比：内容未修改（大小与修改时间不变）但校验值变化即为损坏
+async fn check_against_records(
+    state: &AppState,
+    task_id: &str,
+    control: &TaskControl,
+    checked: &Tree,
+    report: &mut FsckReport,
+) -> Result<(), String> {
+    let total_size = checked.files.values().map(|f| f.size).sum();
+    state.task_manager.update_task_size(task_id, total_size, checked.files.len() as u64).await;
+
+    let mut seen = HashSet::new();
+    let mut processed_size = 0;
+    for (index, (rel, file)) in checked.files.iter().enumerate() {
+        check_control(control).await?;
+        let path = checked.virtual_path(rel);
+        processed_size += file.size;
+        state.task_manager.update_task_progress_with_size(task_id, index as u64 + 1, processed_size, Some(path.clone())).await;
+        seen.insert(path.clone());
+
+        let hash = match current_hash(&checked.driver, &file.internal_path).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                report.issue(&path, FsckIssueKind::Unreadable, e);
+                continue;
+            }
+        };
+        let recorded: Option<(i64, Option<String>, String)> = sqlx::query_as(
+            "SELECT size, modified, hash FROM file_hashes WHERE path = ?"
+        )
+        .bind(&path)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| format!("读取校验记录失败: {}", e))?;
+
+        match (recorded, hash) {
+            (Some((size, modified, recorded_hash)), Some(hash)) if size as u64 == file.size && modified == file.modified => {
+                if recorded_hash == hash {
+                    report.checksum_verified += 1;
+                } else {
+                    // 保留原记录，重新校验时仍能发现
+                    report.issue(&path, FsckIssueKind::HashMismatch, format!("记录 {}, 实际 {}", recorded_hash, hash));
+                }
+            }
+            (Some(_), Some(hash)) => {
+                save_hash(state, &path, file, &hash).await;
+                report.changed += 1;
+            }
+            (None, Some(hash)) => {
+                save_hash(state, &path, file, &hash).await;
+                report.baselined += 1;
+            }
+            (_, None) => report.size_verified += 1,
+        }
+    }
+
+    // 有记录但已找不到的文件，报告一次后删除记录
+    let prefix = format!("{}/", checked.root.trim_end_matches('/'));
+    let recorded: Vec<(String,)> = sqlx::query_as("SELECT path FROM file_hashes WHERE substr(path, 1, ?) = ?")
+        .bind(prefix.chars().count() as i64)
+        .bind(&prefix)
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| format!("读取校验记录失败: {}", e))?;
+    for (path,) in recorded {
+        if seen.contains(&path) {
+            continue;
+        }
+        report.issue(&path, FsckIssueKind::Missing, "有校验记录但文件已不存在".to_string());
+        let _ = sqlx::query("DELETE FROM file_hashes WHERE path = ?")
+            .bind(&path)
+            .execute(&state.db)
+            .await;
+    }
+    Ok(())
+}
+
+/// 执行存储校验任务
+async fn run_fsck(
+    state: &AppState,
+    task_id: &str,
+    control: &TaskControl,
+    path: &str,
+    compare_path: Option<&str>,
+) -> Result<FsckReport, String> {
+    let mut report = FsckReport::default();
+    let checked = walk(state, path, control).await?;
+    match compare_path {
+        Some(compare_path) => {
+            let reference = walk(state, compare_path, control).await?;
+            compare_trees(state, task_id, control, &checked, &reference, &mut report).await?;
+        }
+        None => check_against_records(state, task_id, control, &checked, &mut report).await?,
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsckRequest {
+    /// 要校验的目录（通常为挂载路径）
+    pub path: String,
+    /// 参照目录（如迁移的源目录），为空时与记录的校验值对比
+    pub compare_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsckReportQuery {
+    pub task_id: String,
+}
+
+/// POST /api/admin/fsck - 创建存储校验任务
+pub async fn create_fsck_task(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<FsckRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let user_id = require_instance_admin(&state, &cookies).await?;
+
+    let path = fix_and_clean_path(req.path.trim());
+    let compare_path = req.compare_path.as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(fix_and_clean_path);
+    if let Some(compare_path) = &compare_path {
+        if is_sub_path(compare_path, &path) || is_sub_path(&path, compare_path) {
+            return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "校验目录和参照目录不能相互包含"}))));
+        }
+    }
+
+    let name = match &compare_path {
+        Some(compare_path) => format!("校验 {} (参照 {})", path, compare_path),
+        None => format!("校验 {}", path),
+    };
+    let task = Task::new(TaskType::Fsck, name, path.clone(), compare_path.clone(), 0, 0, Some(user_id));
+    let task_id = task.id.clone();
+    state.task_manager.add_task(task).await;
+    state.task_manager.start_task(&task_id).await;
+    let control = state.task_manager.create_control(&task_id).await;
+
+    let task_state = state.clone();
+    let id = task_id.clone();
+    tokio::spawn(async move {
+        match run_fsck(&task_state, &id, &control, &path, compare_path.as_deref()).await {
+            Ok(report) => {
+                tracing::info!(
+                    "Fsck of {} finished: verified={}, size_only={}, issues={}",
+                    path, report.checksum_verified, report.size_verified, report.issues.len() as u64 + report.issues_truncated
+                );
+                task_state.task_manager.set_fsck_report(&id, report).await;
+                task_state.task_manager.complete_task(&id).await;
+            }
+            Err(e) => {
+                if !e.contains("已取消") {
+                    tracing::warn!("Fsck of {} failed: {}", path, e);
+                    task_state.task_manager.fail_task(&id, e).await;
+                }
+            }
+        }
+        task_state.task_manager.remove_control(&id).await;
+    });
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "校验任务已创建",
+        "data": {
+            "task_id": task_id
+        }
+    })))
+}
+
+/// GET /api/admin/fsck?task_id= - 获取存储校验任务的报告
+pub async fn get_fsck_report(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<FsckReportQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let task = state.task_manager.get_task(&query.task_id).await
+        .filter(|t| t.task_type == TaskType::Fsck)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "校验任务不存在"}))))?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "task_id": task.id,
+            "status": task.status,
+            "path": task.source_path,
+            "compare_path": task.target_path,
+            "processed_files": task.processed_files,
+            "total_files": task.total_files,
+            "error": task.error,
+            "report": task.fsck
+        }
+    })))
+}
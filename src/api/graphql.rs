@@ -0,0 +1,435 @@
+//! GraphQL API (`graphql` feature) / GraphQL 接口（`graphql` 特性）
+//!
+//! Read-only queries over the mount tree, directory entries, tasks and shares. Resolvers call the
+//! REST handlers with the caller's cookies, so permissions, guest policy, hidden files, folder
+//! passwords and workspaces behave exactly as in the REST API; GraphQL only adds field selection
+//! and nesting (e.g. a directory and its subdirectories in one request).
+//! 只读查询挂载树、目录条目、任务与分享。解析器携带调用者的 Cookie 调用 REST 处理器，因此权限、
+//! 游客策略、隐藏文件、目录密码与工作区的行为与 REST 接口一致；GraphQL 只增加字段选择与嵌套查询
+//! （例如一次请求取回目录及其子目录）。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, ErrorExtensions, Object, Schema, SimpleObject,
+};
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use tower_cookies::Cookies;
+use yaolist_backend::error::ApiError;
+use yaolist_backend::i18n::{self, Locale};
+
+use crate::state::AppState;
+use crate::api::{drivers, files, shares, tasks};
+
+/// Max query depth / 最大查询深度
+const MAX_DEPTH: usize = 12;
+/// Max query complexity / 最大查询复杂度
+const MAX_COMPLEXITY: usize = 2000;
+/// Max directory listings per request, bounds nested `children` fan-out
+/// 单次请求最多列出的目录数，限制嵌套 `children` 的展开
+const MAX_LISTINGS: usize = 50;
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+fn schema() -> &'static ApiSchema {
+    static SCHEMA: OnceLock<ApiSchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .limit_depth(MAX_DEPTH)
+            .limit_complexity(MAX_COMPLEXITY)
+            .finish()
+    })
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/graphql", post(graphql_handler))
+        .route("/api/graphql/schema", get(graphql_schema))
+}
+
+/// Per-request resolver context / 单次请求的解析上下文
+struct RequestContext {
+    state: Arc<AppState>,
+    cookies: Cookies,
+    locale: Locale,
+    listings: AtomicUsize,
+}
+
+/// POST /api/graphql - 执行 GraphQL 查询
+pub async fn graphql_handler(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Json(req): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let ctx = RequestContext {
+        state,
+        cookies,
+        locale: Locale::from_headers(&headers),
+        listings: AtomicUsize::new(0),
+    };
+    Json(schema().execute(req.data(ctx)).await)
+}
+
+/// GET /api/graphql/schema - 获取 SDL
+pub async fn graphql_schema() -> String {
+    schema().sdl()
+}
+
+/// Build a GraphQL error from a REST error, with `status` and `code` extensions
+/// 由 REST 错误构造 GraphQL 错误，附带 `status` 与 `code` 扩展字段
+fn rest_error(status: StatusCode, message: &str, locale: Locale) -> async_graphql::Error {
+    let (code, message) = i18n::localize(message, locale)
+        .map(|(code, text)| (Some(code), text))
+        .unwrap_or_else(|| (None, message.to_string()));
+    async_graphql::Error::new(message).extend_with(|_, e| {
+        e.set("status", status.as_u16());
+        if let Some(code) = code {
+            e.set("code", code);
+        }
+    })
+}
+
+fn body_error(status: StatusCode, body: &Value, locale: Locale) -> async_graphql::Error {
+    let message = body.get("message")
+        .or_else(|| body.get("error"))
+        .and_then(Value::as_str)
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or("error"));
+    rest_error(status, message, locale)
+}
+
+/// REST handler result that can be turned into a GraphQL value / 可转换为 GraphQL 值的 REST 处理器结果
+trait RestResult {
+    fn into_body(self, locale: Locale) -> async_graphql::Result<Value>;
+}
+
+/// Successful REST bodies may still carry an error `code` / 成功响应体中也可能带有错误 `code`
+fn check_body(body: Value, locale: Locale) -> async_graphql::Result<Value> {
+    match body.get("code").and_then(Value::as_u64) {
+        Some(code) if code >= 400 => {
+            let status = StatusCode::from_u16(code as u16).unwrap_or(StatusCode::BAD_REQUEST);
+            Err(body_error(status, &body, locale))
+        }
+        _ => Ok(body),
+    }
+}
+
+impl RestResult for Result<Json<Value>, ApiError> {
+    fn into_body(self, locale: Locale) -> async_graphql::Result<Value> {
+        match self {
+            Ok(Json(body)) => check_body(body, locale),
+            Err(e) => {
+                let error_type = e.error_type();
+                Err(rest_error(e.status(), e.message(), locale).extend_with(|_, ext| ext.set("type", error_type)))
+            }
+        }
+    }
+}
+
+impl RestResult for Result<Json<Value>, StatusCode> {
+    fn into_body(self, locale: Locale) -> async_graphql::Result<Value> {
+        match self {
+            Ok(Json(body)) => check_body(body, locale),
+            Err(status) => Err(rest_error(status, status.canonical_reason().unwrap_or("error"), locale)),
+        }
+    }
+}
+
+impl RestResult for Result<Json<Value>, (StatusCode, Json<Value>)> {
+    fn into_body(self, locale: Locale) -> async_graphql::Result<Value> {
+        match self {
+            Ok(Json(body)) => check_body(body, locale),
+            Err((status, Json(body))) => Err(body_error(status, &body, locale)),
+        }
+    }
+}
+
+fn decode<T: DeserializeOwned>(value: Value) -> async_graphql::Result<T> {
+    serde_json::from_value(value).map_err(|e| async_graphql::Error::new(format!("Unexpected response: {}", e)))
+}
+
+fn data(mut body: Value) -> Value {
+    body.get_mut("data").map(Value::take).unwrap_or(Value::Null)
+}
+
+/// List a directory through `fs_list` / 通过 `fs_list` 列出目录
+async fn list_directory(
+    ctx: &Context<'_>,
+    path: String,
+    password: Option<String>,
+    page: Option<i32>,
+    per_page: Option<i32>,
+    sort_by: Option<String>,
+    sort_order: Option<String>,
+) -> async_graphql::Result<Directory> {
+    let rc = ctx.data::<RequestContext>()?;
+    if rc.listings.fetch_add(1, Ordering::Relaxed) >= MAX_LISTINGS {
+        return Err(async_graphql::Error::new(format!("Too many directory listings in one request (max {})", MAX_LISTINGS))
+            .extend_with(|_, e| e.set("status", StatusCode::TOO_MANY_REQUESTS.as_u16())));
+    }
+    let req = files::FsListReq {
+        path: Some(path.clone()),
+        password: password.clone(),
+        page,
+        per_page,
+        refresh: None,
+        sort_by,
+        sort_order,
+        cursor: None,
+    };
+    let body = files::fs_list(State(rc.state.clone()), rc.cookies.clone(), Json(req)).await.into_body(rc.locale)?;
+    let mut dir: Directory = decode(data(body))?;
+    dir.path = path;
+    for entry in &mut dir.entries {
+        entry.path = join_path(&dir.path, &entry.name);
+        entry.password = password.clone();
+    }
+    Ok(dir)
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    format!("{}/{}", parent.trim_end_matches('/'), name)
+}
+
+/// File or directory / 文件或目录
+#[derive(Debug, Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct Entry {
+    pub name: String,
+    /// Full path as seen by the caller / 调用者视角下的完整路径
+    #[serde(skip)]
+    pub path: String,
+    #[serde(default)]
+    pub size: i64,
+    #[serde(default)]
+    pub is_dir: bool,
+    #[serde(default)]
+    pub modified: String,
+    #[serde(default)]
+    pub created: String,
+    /// Folder password inherited from the query / 从查询继承的目录密码
+    #[serde(skip)]
+    #[graphql(skip)]
+    password: Option<String>,
+}
+
+#[ComplexObject]
+impl Entry {
+    /// Directory contents, `null` for files / 目录内容，文件为 `null`
+    async fn children(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<i32>,
+        per_page: Option<i32>,
+        sort_by: Option<String>,
+        sort_order: Option<String>,
+    ) -> async_graphql::Result<Option<Directory>> {
+        if !self.is_dir {
+            return Ok(None);
+        }
+        list_directory(ctx, self.path.clone(), self.password.clone(), page, per_page, sort_by, sort_order)
+            .await
+            .map(Some)
+    }
+}
+
+/// Directory listing page / 目录列表分页
+#[derive(Debug, Deserialize, SimpleObject)]
+pub struct Directory {
+    #[serde(skip)]
+    pub path: String,
+    #[serde(rename = "content", default)]
+    pub entries: Vec<Entry>,
+    #[serde(default)]
+    pub total: i64,
+    #[serde(default)]
+    pub page: i32,
+    #[serde(default)]
+    pub per_page: i32,
+    pub readme: Option<String>,
+    pub header: Option<String>,
+    /// Whether the caller may write here / 调用者是否可在此写入
+    #[serde(default)]
+    pub write: bool,
+}
+
+/// Storage mount / 存储挂载点
+#[derive(Debug, Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct Mount {
+    pub id: String,
+    #[serde(rename = "name")]
+    pub mount_path: String,
+    pub driver_type: String,
+    pub enabled: bool,
+    /// running / disabled / error
+    pub status: String,
+    pub error: Option<String>,
+}
+
+#[ComplexObject]
+impl Mount {
+    /// Contents of the mount root / 挂载点根目录内容
+    async fn root(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<i32>,
+        per_page: Option<i32>,
+    ) -> async_graphql::Result<Directory> {
+        list_directory(ctx, self.mount_path.clone(), None, page, per_page, None, None).await
+    }
+}
+
+/// Background task / 后台任务
+#[derive(Debug, Deserialize, SimpleObject)]
+pub struct Task {
+    pub id: String,
+    pub task_type: String,
+    pub status: String,
+    pub name: String,
+    pub source_path: String,
+    pub target_path: Option<String>,
+    pub total_size: u64,
+    pub processed_size: u64,
+    pub total_files: u64,
+    pub processed_files: u64,
+    pub progress: f32,
+    pub speed: f64,
+    pub eta_seconds: Option<u64>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub error: Option<String>,
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub current_file: Option<String>,
+}
+
+#[derive(Debug, Deserialize, SimpleObject)]
+pub struct TaskPage {
+    #[serde(rename = "tasks")]
+    pub items: Vec<Task>,
+    pub total: u64,
+    pub page: u32,
+    pub page_size: u32,
+    pub total_pages: u32,
+}
+
+/// Share link / 分享链接
+#[derive(Debug, Deserialize, SimpleObject)]
+pub struct Share {
+    pub id: i64,
+    pub user_id: Option<String>,
+    pub short_id: String,
+    pub path: String,
+    pub name: String,
+    pub is_dir: bool,
+    pub has_password: bool,
+    pub expires_at: Option<String>,
+    pub max_access_count: Option<i64>,
+    pub access_count: i64,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+    pub creator_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, SimpleObject)]
+pub struct SharePage {
+    #[serde(rename = "data")]
+    pub items: Vec<Share>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// List a directory / 列出目录
+    #[allow(clippy::too_many_arguments)]
+    async fn directory(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = "/")] path: String,
+        password: Option<String>,
+        page: Option<i32>,
+        per_page: Option<i32>,
+        sort_by: Option<String>,
+        sort_order: Option<String>,
+    ) -> async_graphql::Result<Directory> {
+        list_directory(ctx, path, password, page, per_page, sort_by, sort_order).await
+    }
+
+    /// Get a single file or directory / 获取单个文件或目录
+    async fn entry(
+        &self,
+        ctx: &Context<'_>,
+        path: String,
+        password: Option<String>,
+    ) -> async_graphql::Result<Entry> {
+        let rc = ctx.data::<RequestContext>()?;
+        let req = files::FsListReq {
+            path: Some(path.clone()),
+            password: password.clone(),
+            page: None,
+            per_page: None,
+            refresh: None,
+            sort_by: None,
+            sort_order: None,
+            cursor: None,
+        };
+        let body = files::fs_get(State(rc.state.clone()), rc.cookies.clone(), Json(req)).await.into_body(rc.locale)?;
+        let mut entry: Entry = decode(data(body))?;
+        entry.path = path;
+        entry.password = password;
+        Ok(entry)
+    }
+
+    /// Storage mounts, admin only / 存储挂载点（仅管理员）
+    async fn mounts(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Mount>> {
+        let rc = ctx.data::<RequestContext>()?;
+        let mut body = drivers::list_drivers(State(rc.state.clone()), rc.cookies.clone()).await.into_body(rc.locale)?;
+        let mut mounts: Vec<Mount> = decode(body.get_mut("drivers").map(Value::take).unwrap_or_default())?;
+        mounts.sort_by(|a, b| a.mount_path.cmp(&b.mount_path));
+        Ok(mounts)
+    }
+
+    /// Tasks of the caller (all tasks for admins) / 调用者的任务（管理员为全部任务）
+    async fn tasks(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        task_type: Option<String>,
+        status: Option<String>,
+    ) -> async_graphql::Result<TaskPage> {
+        let rc = ctx.data::<RequestContext>()?;
+        let query = tasks::ListTasksQuery { page, page_size, task_type, status, user_id: None };
+        let body = tasks::list_tasks(State(rc.state.clone()), rc.cookies.clone(), Json(query)).await.into_body(rc.locale)?;
+        decode(data(body))
+    }
+
+    /// Shares of the caller (all shares of the workspace for admins) / 调用者的分享（管理员为工作区全部分享）
+    async fn shares(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<i64>,
+        per_page: Option<i64>,
+        search: Option<String>,
+    ) -> async_graphql::Result<SharePage> {
+        let rc = ctx.data::<RequestContext>()?;
+        let query = shares::types::ListSharesQuery { page, per_page, search };
+        let body = shares::list_shares(State(rc.state.clone()), rc.cookies.clone(), Query(query)).await.into_body(rc.locale)?;
+        decode(body)
+    }
+}
@@ -8,35 +8,15 @@ use serde_json::{json, Value};
 use std::sync::Arc;
 use chrono::Utc;
 use tower_cookies::Cookies;
+use yaolist_backend::access::{self, PathGrant};
+use yaolist_backend::{group_defaults, upload_policy};
 
 use crate::{
     models::UserGroup,
     state::AppState,
-    auth::SESSION_COOKIE_NAME,
+    auth::require_instance_admin,
 };
 
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
-    Ok(())
-}
-
 #[derive(Debug, Deserialize)]
 pub struct ListGroupsQuery {
     #[serde(default = "default_page")]
@@ -86,6 +66,22 @@ pub struct CreateGroupRequest {
     #[serde(default)]
     ftp_enabled: bool,
     root_path: Option<String>,
+    /// 成员默认设置：下载流量配额（字节）、下载限速（字节每秒），0 表示不限
+    #[serde(default)]
+    traffic_quota: i64,
+    #[serde(default)]
+    download_speed_limit: i64,
+    /// 单个上传文件的最大字节数，0 表示不限
+    #[serde(default)]
+    max_upload_size: i64,
+    /// 允许/拒绝上传的扩展名，逗号分隔，为空表示不限
+    upload_allow_ext: Option<String>,
+    upload_deny_ext: Option<String>,
+    /// 成员可见的挂载路径，每行一个，为空表示全部
+    visible_mounts: Option<String>,
+    /// 要求成员启用两步验证，未启用的成员下次登录后须先完成设置
+    #[serde(default)]
+    require_2fa: bool,
 }
 
 fn default_true() -> bool { true }
@@ -111,6 +107,15 @@ pub struct UpdateGroupRequest {
     webdav_enabled: Option<bool>,
     ftp_enabled: Option<bool>,
     root_path: Option<String>,
+    traffic_quota: Option<i64>,
+    download_speed_limit: Option<i64>,
+    max_upload_size: Option<i64>,
+    /// 空字符串表示不限
+    upload_allow_ext: Option<String>,
+    upload_deny_ext: Option<String>,
+    /// 空字符串表示可见全部挂载点
+    visible_mounts: Option<String>,
+    require_2fa: Option<bool>,
 }
 
 pub async fn list_groups(
@@ -118,7 +123,7 @@ pub async fn list_groups(
     cookies: Cookies,
     Query(query): Query<ListGroupsQuery>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let offset = (query.page - 1) * query.page_size;
     
     let mut sql = "SELECT * FROM user_groups".to_string();
@@ -138,7 +143,7 @@ pub async fn list_groups(
         .fetch_all(&state.db)
         .await
         .map_err(|e| {
-            eprintln!("Error fetching groups: {:?}", e);
+            tracing::error!("Error fetching groups: {:?}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "获取用户组失败"})))
         })?;
     
@@ -163,7 +168,7 @@ pub async fn create_group(
     cookies: Cookies,
     Json(req): Json<CreateGroupRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = Utc::now().to_rfc3339();
 
     let result = sqlx::query(
@@ -172,8 +177,10 @@ pub async fn create_group(
             allow_direct_link, allow_share, show_hidden_files, no_password_access,
             add_offline_download, create_upload, rename_files, move_files,
             copy_files, delete_files, read_files, read_compressed, extract_files,
-            webdav_enabled, ftp_enabled, root_path, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            webdav_enabled, ftp_enabled, root_path, created_at, updated_at,
+            traffic_quota, download_speed_limit, visible_mounts, max_upload_size, require_2fa,
+            upload_allow_ext, upload_deny_ext
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&req.name)
     .bind(&req.description)
@@ -196,10 +203,17 @@ pub async fn create_group(
     .bind(&req.root_path)
     .bind(&now)
     .bind(&now)
+    .bind(req.traffic_quota.max(0))
+    .bind(req.download_speed_limit.max(0))
+    .bind(req.visible_mounts.as_deref().and_then(group_defaults::normalize_mounts))
+    .bind(req.max_upload_size.max(0))
+    .bind(req.require_2fa)
+    .bind(req.upload_allow_ext.as_deref().and_then(upload_policy::normalize_extensions))
+    .bind(req.upload_deny_ext.as_deref().and_then(upload_policy::normalize_extensions))
     .execute(&state.db)
     .await
     .map_err(|e| {
-        eprintln!("Error creating group: {:?}", e);
+        tracing::error!("Error creating group: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "创建用户组失败"})))
     })?;
 
@@ -214,7 +228,7 @@ pub async fn get_group(
     cookies: Cookies,
     Path(id): Path<i64>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let group = sqlx::query_as::<_, UserGroup>("SELECT * FROM user_groups WHERE id = ?")
         .bind(id)
         .fetch_optional(&state.db)
@@ -233,7 +247,7 @@ pub async fn update_group(
     Path(id): Path<i64>,
     Json(req): Json<UpdateGroupRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = Utc::now().to_rfc3339();
     
     // 获取当前用户组信息
@@ -264,6 +278,22 @@ pub async fn update_group(
     let webdav_enabled = req.webdav_enabled.unwrap_or(current.webdav_enabled);
     let ftp_enabled = req.ftp_enabled.unwrap_or(current.ftp_enabled);
     let root_path = if req.root_path.is_some() { req.root_path } else { current.root_path };
+    let traffic_quota = req.traffic_quota.unwrap_or(current.traffic_quota).max(0);
+    let download_speed_limit = req.download_speed_limit.unwrap_or(current.download_speed_limit).max(0);
+    let max_upload_size = req.max_upload_size.unwrap_or(current.max_upload_size).max(0);
+    let visible_mounts = match req.visible_mounts {
+        Some(mounts) => group_defaults::normalize_mounts(&mounts),
+        None => current.visible_mounts,
+    };
+    let require_2fa = req.require_2fa.unwrap_or(current.require_2fa);
+    let upload_allow_ext = match req.upload_allow_ext {
+        Some(extensions) => upload_policy::normalize_extensions(&extensions),
+        None => current.upload_allow_ext,
+    };
+    let upload_deny_ext = match req.upload_deny_ext {
+        Some(extensions) => upload_policy::normalize_extensions(&extensions),
+        None => current.upload_deny_ext,
+    };
     
     sqlx::query(
         "UPDATE user_groups SET 
@@ -271,7 +301,9 @@ pub async fn update_group(
             allow_direct_link = ?, allow_share = ?, show_hidden_files = ?, no_password_access = ?,
             add_offline_download = ?, create_upload = ?, rename_files = ?, move_files = ?,
             copy_files = ?, delete_files = ?, read_files = ?, read_compressed = ?, extract_files = ?,
-            webdav_enabled = ?, ftp_enabled = ?, root_path = ?, updated_at = ?
+            webdav_enabled = ?, ftp_enabled = ?, root_path = ?, updated_at = ?,
+            traffic_quota = ?, download_speed_limit = ?, visible_mounts = ?, max_upload_size = ?,
+            require_2fa = ?, upload_allow_ext = ?, upload_deny_ext = ?
          WHERE id = ?"
     )
     .bind(&name)
@@ -294,11 +326,18 @@ pub async fn update_group(
     .bind(ftp_enabled)
     .bind(&root_path)
     .bind(&now)
+    .bind(traffic_quota)
+    .bind(download_speed_limit)
+    .bind(&visible_mounts)
+    .bind(max_upload_size)
+    .bind(require_2fa)
+    .bind(&upload_allow_ext)
+    .bind(&upload_deny_ext)
     .bind(id)
     .execute(&state.db)
     .await
     .map_err(|e| {
-        eprintln!("Error updating group: {:?}", e);
+        tracing::error!("Error updating group: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "更新用户组失败"})))
     })?;
 
@@ -312,18 +351,76 @@ pub async fn delete_group(
     cookies: Cookies,
     Path(id): Path<i64>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     sqlx::query("DELETE FROM user_groups WHERE id = ?")
         .bind(id)
         .execute(&state.db)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    sqlx::query("DELETE FROM group_path_grants WHERE group_id = ?")
+        .bind(id.to_string())
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    // 存储可见性映射保留该用户组ID，避免只对该组可见的存储在删除后变为对所有人可见
 
     Ok(Json(json!({
         "message": "用户组删除成功"
     })))
 }
 
+/// GET /api/groups/:id/grants - 获取用户组的路径授权
+pub async fn get_group_grants(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    let grants = access::load_group_grants(&state.db, &id.to_string())
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    Ok(Json(json!({
+        "grants": grants
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGrantsRequest {
+    grants: Vec<PathGrant>,
+}
+
+/// POST /api/groups/:id/grants - 替换用户组的路径授权
+pub async fn update_group_grants(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<i64>,
+    Json(req): Json<UpdateGrantsRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM user_groups WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    if exists.is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "用户组不存在"}))));
+    }
+
+    let grants = access::normalize_grants(req.grants);
+    access::save_group_grants(&state.db, &id.to_string(), &grants)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error saving path grants: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "保存路径授权失败"})))
+        })?;
+
+    Ok(Json(json!({
+        "message": "路径授权已保存",
+        "grants": grants
+    })))
+}
+
 pub async fn list_permissions(
     State(_state): State<Arc<AppState>>,
     _cookies: Cookies,
@@ -0,0 +1,246 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use yaolist_backend::file_hook::{
+    FileHook, FileHookAction, FileHookContext, FileHookEvent, DEFAULT_HOOK_TIMEOUT_SECS, MAX_HOOK_TIMEOUT_SECS,
+};
+
+use crate::state::AppState;
+use crate::task::{TaskStatus, TaskSummary, TaskType};
+use crate::auth::require_instance_admin;
+
+/// 触发文件事件钩子（后台执行，自动补全用户名）
+pub fn fire_file_hook(
+    state: &Arc<AppState>,
+    event: FileHookEvent,
+    path: &str,
+    user_id: Option<String>,
+    extra: Value,
+) {
+    if !state.file_hooks.list().iter().any(|h| h.accepts(event, path)) {
+        return;
+    }
+    let db = state.db.clone();
+    let hooks = state.file_hooks.clone();
+    let path = path.to_string();
+    tokio::spawn(async move {
+        let username: Option<String> = match user_id.as_deref() {
+            Some(id) => sqlx::query_scalar("SELECT username FROM users WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&db)
+                .await
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        hooks.fire(FileHookContext::new(event, &path, user_id, username, extra));
+    });
+}
+
+/// 上传任务完成后触发upload_complete钩子（批次任务逐个文件触发）
+pub async fn fire_upload_hooks(state: Arc<AppState>, task: TaskSummary) {
+    if task.task_type != TaskType::Upload {
+        return;
+    }
+    let paths: Vec<String> = match state.task_manager.get_task(&task.id).await.and_then(|t| t.files) {
+        Some(files) => files.into_iter()
+            .filter(|f| f.status == TaskStatus::Completed)
+            .map(|f| f.path)
+            .collect(),
+        None => vec![task.source_path.clone()],
+    };
+    for path in paths.iter().filter(|p| !p.is_empty()) {
+        fire_file_hook(&state, FileHookEvent::UploadComplete, path, task.user_id.clone(), json!({
+            "task_id": task.id,
+        }));
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveHookRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub events: Vec<FileHookEvent>,
+    pub path_prefix: Option<String>,
+    pub action: FileHookAction,
+    pub timeout_secs: Option<u64>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HookIdRequest {
+    pub id: String,
+}
+
+/// GET /api/admin/hooks - 获取文件事件钩子列表及可订阅事件
+pub async fn list_hooks(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let hooks: Vec<Value> = state.file_hooks.list().into_iter().map(|hook| {
+        let last_run = state.file_hooks.last_status(&hook.id);
+        json!({
+            "id": hook.id,
+            "name": hook.name,
+            "events": hook.events,
+            "path_prefix": hook.path_prefix,
+            "action": hook.action,
+            "timeout_secs": hook.timeout_secs,
+            "enabled": hook.enabled,
+            "created_at": hook.created_at,
+            "last_run": last_run
+        })
+    }).collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "hooks": hooks,
+            "events": FileHookEvent::all()
+        }
+    })))
+}
+
+/// POST /api/admin/hooks - 新建或更新文件事件钩子
+pub async fn save_hook(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveHookRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let action = match req.action {
+        FileHookAction::Command { command } => {
+            let command = command.trim().to_string();
+            if command.is_empty() {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "命令不能为空"}))));
+            }
+            FileHookAction::Command { command }
+        }
+        FileHookAction::Http { url, method, headers, body } => {
+            let url = url.trim().to_string();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "URL必须以http://或https://开头"}))));
+            }
+            if reqwest::Method::from_bytes(method.to_uppercase().as_bytes()).is_err() {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "无效的HTTP方法"}))));
+            }
+            FileHookAction::Http { url, method: method.to_uppercase(), headers, body }
+        }
+    };
+    if req.events.contains(&FileHookEvent::Test) {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "不支持订阅test事件"}))));
+    }
+
+    let existing = req.id.as_deref().and_then(|id| state.file_hooks.get(id));
+    if req.id.is_some() && existing.is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "钩子不存在"}))));
+    }
+
+    let path_prefix = req.path_prefix
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty() && p != "/")
+        .map(|p| if p.starts_with('/') { p } else { format!("/{}", p) });
+    let hook = FileHook {
+        id: existing.as_ref().map(|h| h.id.clone()).unwrap_or_else(|| Uuid::new_v4().to_string()),
+        name: req.name,
+        events: req.events,
+        path_prefix,
+        action,
+        timeout_secs: req.timeout_secs
+            .or(existing.as_ref().map(|h| h.timeout_secs))
+            .unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS)
+            .clamp(1, MAX_HOOK_TIMEOUT_SECS),
+        enabled: req.enabled.or(existing.as_ref().map(|h| h.enabled)).unwrap_or(true),
+        created_at: existing.as_ref().map(|h| h.created_at).unwrap_or_else(Utc::now),
+    };
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO file_hooks (id, name, events, path_prefix, action, timeout_secs, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&hook.id)
+    .bind(&hook.name)
+    .bind(serde_json::to_string(&hook.events).unwrap_or_else(|_| "[]".to_string()))
+    .bind(&hook.path_prefix)
+    .bind(serde_json::to_string(&hook.action).unwrap_or_default())
+    .bind(hook.timeout_secs as i64)
+    .bind(hook.enabled)
+    .bind(hook.created_at.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    state.file_hooks.upsert(hook.clone());
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": hook
+    })))
+}
+
+/// POST /api/admin/hooks/delete - 删除文件事件钩子
+pub async fn delete_hook(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<HookIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    sqlx::query("DELETE FROM file_hooks WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    if !state.file_hooks.remove(&req.id) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "钩子不存在"}))));
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
+
+/// POST /api/admin/hooks/test - 以test事件运行钩子并返回结果
+pub async fn test_hook(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<HookIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let hook = state.file_hooks.get(&req.id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "钩子不存在"}))))?;
+    let path = hook.path_prefix.clone().unwrap_or_else(|| "/".to_string());
+    let ctx = FileHookContext::new(FileHookEvent::Test, &path, None, None, json!({ "test": true }));
+    let result = state.file_hooks.run(&hook, &ctx).await;
+
+    if result.success {
+        Ok(Json(json!({
+            "code": 200,
+            "message": "钩子执行成功",
+            "data": result
+        })))
+    } else {
+        Ok(Json(json!({
+            "code": 500,
+            "message": format!("钩子执行失败: {}", result.error.clone().unwrap_or_default()),
+            "data": result
+        })))
+    }
+}
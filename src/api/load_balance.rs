@@ -9,39 +9,13 @@ use std::sync::Arc;
 use tower_cookies::Cookies;
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
-use yaolist_backend::models::UserPermissions;
-use yaolist_backend::load_balance::{BalanceGroupConfig, LoadBalanceMode, BalanceDriver, DriverCapability};
+use crate::auth::require_instance_admin;
+use yaolist_backend::load_balance::{BalanceGroupConfig, LoadBalanceMode, BalanceDriver, DriverCapability, MemberHealth, UploadPolicy, DEFAULT_USAGE_THRESHOLD};
 use yaolist_backend::geoip::{lookup_ip, GeoInfo};
 
+/// 负载均衡组属于全局存储配置，仅默认工作区的管理员可管理
 async fn check_admin(state: &AppState, cookies: &Cookies) -> bool {
-    let session_id = match cookies.get(SESSION_COOKIE_NAME) {
-        Some(c) => c.value().to_string(),
-        None => return false,
-    };
-    
-    let perms = sqlx::query_as::<_, UserPermissions>(
-        r#"SELECT 
-            MAX(g.read_files) as read_files, MAX(g.create_upload) as create_upload,
-            MAX(g.rename_files) as rename_files, MAX(g.move_files) as move_files,
-            MAX(g.copy_files) as copy_files, MAX(g.delete_files) as delete_files,
-            MAX(g.allow_direct_link) as allow_direct_link, MAX(g.allow_share) as allow_share,
-            MAX(g.is_admin) as is_admin, MAX(g.show_hidden_files) as show_hidden_files,
-            MAX(g.extract_files) as extract_files
-        FROM users u
-        INNER JOIN sessions s ON u.id = s.user_id
-        INNER JOIN user_group_members ugm ON u.id = ugm.user_id
-        INNER JOIN user_groups g ON CAST(g.id AS TEXT) = ugm.group_id
-        WHERE s.id = ? AND s.expires_at > datetime('now')
-        GROUP BY u.id"#
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten();
-    
-    perms.map(|p| p.is_admin).unwrap_or(false)
+    require_instance_admin(state, cookies).await.is_ok()
 }
 
 #[derive(Serialize)]
@@ -68,10 +42,18 @@ pub struct CreateGroupRequest {
     pub drivers: Vec<DriverConfig>,
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// 上传放置策略：none / most_free_space / usage_threshold
+    #[serde(default)]
+    pub upload_policy: String,
+    /// usage_threshold 策略的使用率上限（1-100）
+    #[serde(default = "default_usage_threshold")]
+    pub usage_threshold: u8,
 }
 
 fn default_true() -> bool { true }
 
+fn default_usage_threshold() -> u8 { DEFAULT_USAGE_THRESHOLD }
+
 #[derive(Deserialize, Serialize)]
 pub struct DriverConfig {
     pub driver_id: String,
@@ -92,6 +74,8 @@ fn default_weight() -> u32 { 1 }
 #[derive(Serialize)]
 pub struct GroupListResponse {
     pub groups: Vec<BalanceGroupConfig>,
+    /// 成员健康状态（driver_id -> health）
+    pub member_health: std::collections::HashMap<String, MemberHealth>,
 }
 
 #[derive(Serialize)]
@@ -111,7 +95,8 @@ pub async fn list_groups(
     }
     
     let groups = state.load_balance.get_all_groups().await;
-    Json(ApiResponse::success(GroupListResponse { groups }))
+    let member_health = state.load_balance.get_member_health().await;
+    Json(ApiResponse::success(GroupListResponse { groups, member_health }))
 }
 
 /// 创建负载均衡组
@@ -148,6 +133,8 @@ pub async fn create_group(
         mode,
         drivers,
         enabled: req.enabled,
+        upload_policy: UploadPolicy::from(req.upload_policy.as_str()),
+        usage_threshold: req.usage_threshold.clamp(1, 100),
     };
     
     // 保存到数据库
@@ -205,6 +192,8 @@ pub async fn update_group(
         mode,
         drivers,
         enabled: req.enabled,
+        upload_policy: UploadPolicy::from(req.upload_policy.as_str()),
+        usage_threshold: req.usage_threshold.clamp(1, 100),
     };
     
     let config_json = serde_json::to_string(&config).unwrap_or_default();
@@ -287,6 +276,9 @@ pub async fn list_modes() -> Json<ApiResponse<Vec<ModeInfo>>> {
     let modes = vec![
         ModeInfo { id: "weighted_round_robin".to_string(), name: "加权轮询".to_string(), description: "按权重比例轮询分配请求".to_string() },
         ModeInfo { id: "geo_region".to_string(), name: "地区分流".to_string(), description: "中国大陆/海外用户分流到不同驱动".to_string() },
+        ModeInfo { id: "ip_hash".to_string(), name: "IP哈希".to_string(), description: "同一客户端IP固定分配到同一驱动".to_string() },
+        ModeInfo { id: "lowest_latency".to_string(), name: "最低延迟".to_string(), description: "根据记录的驱动响应时间，优先选择最快的驱动".to_string() },
+        ModeInfo { id: "failover".to_string(), name: "主备切换".to_string(), description: "始终使用排序最靠前的可用驱动，失败时自动切换到下一个".to_string() },
     ];
     Json(ApiResponse::success(modes))
 }
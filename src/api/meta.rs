@@ -10,29 +10,11 @@ use tower_cookies::Cookies;
 
 use crate::models::{CreateMetaRequest, Meta, UpdateMetaRequest};
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
-
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
-    Ok(())
-}
+use crate::auth::require_instance_admin;
+use crate::api::file_resolver::{calculate_internal_path, get_all_mounts, get_matching_mounts};
+use crate::api::files::{get_nearest_meta, get_virtual_files_by_path};
+use yaolist_backend::hide_rules::{HideRule, HideRules};
+use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
 
 #[derive(Debug, Deserialize)]
 pub struct ListMetasQuery {
@@ -45,7 +27,7 @@ pub async fn list_metas(
     cookies: Cookies,
     Query(query): Query<ListMetasQuery>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let page = query.page.unwrap_or(1).max(1);
     let per_page = query.per_page.unwrap_or(10).clamp(1, 100);
     let offset = (page - 1) * per_page;
@@ -57,7 +39,7 @@ pub async fn list_metas(
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
 
     let metas: Vec<Meta> = sqlx::query_as(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas ORDER BY path LIMIT ? OFFSET ?"
+        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, preview_only, po_sub, watermark, wm_sub, created_at, updated_at FROM metas ORDER BY path LIMIT ? OFFSET ?"
     )
     .bind(per_page)
     .bind(offset)
@@ -84,9 +66,9 @@ pub async fn get_meta(
     cookies: Cookies,
     Path(id): Path<i64>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let meta: Option<Meta> = sqlx::query_as(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas WHERE id = ?"
+        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, preview_only, po_sub, watermark, wm_sub, created_at, updated_at FROM metas WHERE id = ?"
     )
     .bind(id)
     .fetch_optional(&state.db)
@@ -110,11 +92,11 @@ pub async fn create_meta(
     cookies: Cookies,
     Json(req): Json<CreateMetaRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     
     let result = sqlx::query(
-        "INSERT INTO metas (path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        "INSERT INTO metas (path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, preview_only, po_sub, watermark, wm_sub, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&req.path)
     .bind(&req.password)
@@ -127,6 +109,10 @@ pub async fn create_meta(
     .bind(req.r_sub.unwrap_or(false))
     .bind(&req.header)
     .bind(req.header_sub.unwrap_or(false))
+    .bind(req.preview_only.unwrap_or(false))
+    .bind(req.po_sub.unwrap_or(false))
+    .bind(req.watermark.unwrap_or(false))
+    .bind(req.wm_sub.unwrap_or(false))
     .bind(&now)
     .bind(&now)
     .execute(&state.db)
@@ -138,7 +124,7 @@ pub async fn create_meta(
             "message": "Meta created successfully"
         }))),
         Err(e) => {
-            eprintln!("Failed to create meta: {:?}", e);
+            tracing::error!("Failed to create meta: {:?}", e);
             Ok(Json(json!({
                 "code": 500,
                 "message": "Failed to create meta"
@@ -153,12 +139,12 @@ pub async fn update_meta(
     Path(id): Path<i64>,
     Json(req): Json<UpdateMetaRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     
     // 获取现有的 meta
     let existing: Option<Meta> = sqlx::query_as(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas WHERE id = ?"
+        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, preview_only, po_sub, watermark, wm_sub, created_at, updated_at FROM metas WHERE id = ?"
     )
     .bind(id)
     .fetch_optional(&state.db)
@@ -174,7 +160,7 @@ pub async fn update_meta(
     };
 
     let result = sqlx::query(
-        "UPDATE metas SET path = ?, password = ?, p_sub = ?, write = ?, w_sub = ?, hide = ?, h_sub = ?, readme = ?, r_sub = ?, header = ?, header_sub = ?, updated_at = ? WHERE id = ?"
+        "UPDATE metas SET path = ?, password = ?, p_sub = ?, write = ?, w_sub = ?, hide = ?, h_sub = ?, readme = ?, r_sub = ?, header = ?, header_sub = ?, preview_only = ?, po_sub = ?, watermark = ?, wm_sub = ?, updated_at = ? WHERE id = ?"
     )
     .bind(req.path.unwrap_or(existing.path))
     .bind(req.password.or(existing.password))
@@ -187,6 +173,10 @@ pub async fn update_meta(
     .bind(req.r_sub.unwrap_or(existing.r_sub))
     .bind(req.header.or(existing.header))
     .bind(req.header_sub.unwrap_or(existing.header_sub))
+    .bind(req.preview_only.unwrap_or(existing.preview_only))
+    .bind(req.po_sub.unwrap_or(existing.po_sub))
+    .bind(req.watermark.unwrap_or(existing.watermark))
+    .bind(req.wm_sub.unwrap_or(existing.wm_sub))
     .bind(&now)
     .bind(id)
     .execute(&state.db)
@@ -198,7 +188,7 @@ pub async fn update_meta(
             "message": "Meta updated successfully"
         }))),
         Err(e) => {
-            eprintln!("Failed to update meta: {:?}", e);
+            tracing::error!("Failed to update meta: {:?}", e);
             Ok(Json(json!({
                 "code": 500,
                 "message": "Failed to update meta"
@@ -212,7 +202,7 @@ pub async fn delete_meta(
     cookies: Cookies,
     Path(id): Path<i64>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let result = sqlx::query("DELETE FROM metas WHERE id = ?")
         .bind(id)
         .execute(&state.db)
@@ -224,7 +214,7 @@ pub async fn delete_meta(
             "message": "Meta deleted successfully"
         }))),
         Err(e) => {
-            eprintln!("Failed to delete meta: {:?}", e);
+            tracing::error!("Failed to delete meta: {:?}", e);
             Ok(Json(json!({
                 "code": 500,
                 "message": "Failed to delete meta"
@@ -233,6 +223,115 @@ pub async fn delete_meta(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TestHideRequest {
+    /// 要预览的目录
+    pub path: String,
+    /// 要试用的规则，缺省时使用该目录最近元信息的规则
+    pub hide: Option<String>,
+    /// 试用规则所在的元信息路径，缺省为 path
+    pub meta_path: Option<String>,
+    pub h_sub: Option<bool>,
+}
+
+/// POST /api/metas/test_hide - 预览隐藏规则在指定目录下会隐藏哪些文件
+pub async fn test_hide(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TestHideRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    let path = fix_and_clean_path(&req.path);
+    
+    let (meta_path, hide, h_sub) = match req.hide {
+        Some(hide) => (
+            fix_and_clean_path(req.meta_path.as_deref().unwrap_or(&path)),
+            hide,
+            req.h_sub.unwrap_or(false),
+        ),
+        None => match get_nearest_meta(&state, &path).await {
+            Some(m) => (m.path, m.hide.unwrap_or_default(), m.h_sub),
+            None => (path.clone(), String::new(), false),
+        },
+    };
+    if path != meta_path && !is_sub_path(&meta_path, &path) {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "目录不在元信息路径范围内"}))));
+    }
+    let in_sub = path != meta_path;
+    
+    // 逐条解析规则，标出不合法和在该目录不生效的规则
+    let rules: Vec<Value> = hide.lines()
+        .filter_map(|line| match HideRule::parse(line) {
+            Ok(Some(rule)) => Some(json!({
+                "rule": rule.rule,
+                "syntax": rule.syntax,
+                "sub": rule.applies_to_subfolders(h_sub),
+                "applies": !in_sub || rule.applies_to_subfolders(h_sub),
+                "error": null
+            })),
+            Ok(None) => None,
+            Err(e) => Some(json!({
+                "rule": line.trim(),
+                "syntax": null,
+                "sub": null,
+                "applies": false,
+                "error": e
+            })),
+        })
+        .collect();
+    let hide_rules = HideRules::at(&meta_path, &hide, h_sub, &path);
+    
+    // 列出目录（合并别名挂载与虚拟目录）
+    let mounts = get_all_mounts(&state)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    let mut entries: Vec<(String, bool)> = Vec::new();
+    let matching_mounts = get_matching_mounts(&path, &mounts);
+    if let Some(first) = matching_mounts.first() {
+        let mount_path = fix_and_clean_path(&first.mount_path);
+        let actual_path = calculate_internal_path(&mount_path, &path);
+        for mount in &matching_mounts {
+            if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
+                if let Ok(files) = driver.list(&actual_path).await {
+                    entries.extend(files.into_iter().map(|f| (f.name, f.is_dir)));
+                }
+            }
+        }
+    }
+    for vf in get_virtual_files_by_path(&path, &mounts) {
+        if let Some(name) = vf.get("name").and_then(|n| n.as_str()) {
+            entries.push((name.to_string(), true));
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    entries.retain(|(name, _)| seen.insert(name.clone()));
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| natord::compare_ignore_case(&a.0, &b.0)));
+    
+    let files: Vec<Value> = entries.iter().map(|(name, is_dir)| {
+        let rule = hide_rules.matching(name).map(|r| r.rule.clone());
+        json!({
+            "name": name,
+            "is_dir": is_dir,
+            "hidden": rule.is_some(),
+            "rule": rule
+        })
+    }).collect();
+    let hidden_count = files.iter().filter(|f| f["hidden"] == true).count();
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "path": path,
+            "meta_path": meta_path,
+            "h_sub": h_sub,
+            "rules": rules,
+            "files": files,
+            "total": files.len(),
+            "hidden_count": hidden_count
+        }
+    })))
+}
+
 // 获取指定路径的元信息（用于前台）
 pub async fn get_meta_for_path(
     State(state): State<Arc<AppState>>,
@@ -242,7 +341,7 @@ pub async fn get_meta_for_path(
     
     // 查找匹配的元信息
     let metas: Vec<Meta> = sqlx::query_as(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas ORDER BY length(path) DESC"
+        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, preview_only, po_sub, watermark, wm_sub, created_at, updated_at FROM metas ORDER BY length(path) DESC"
     )
     .fetch_all(&state.db)
     .await
@@ -256,6 +355,8 @@ pub async fn get_meta_for_path(
     let mut hide_patterns: Vec<String> = vec![];
     let mut readme: Option<String> = None;
     let mut header: Option<String> = None;
+    let mut preview_only = false;
+    let mut watermark = false;
 
     for meta in metas {
         let meta_path = meta.path.trim_end_matches('/');
@@ -318,6 +419,16 @@ pub async fn get_meta_for_path(
             }
         }
 
+        // 仅预览
+        if meta.preview_only && (!is_sub || meta.po_sub) {
+            preview_only = true;
+        }
+
+        // 预览水印
+        if meta.watermark && (!is_sub || meta.wm_sub) {
+            watermark = true;
+        }
+
         if result_meta.is_none() {
             result_meta = Some(meta);
         }
@@ -331,7 +442,9 @@ pub async fn get_meta_for_path(
             "can_write": can_write,
             "hide_patterns": hide_patterns,
             "readme": readme,
-            "header": header
+            "header": header,
+            "preview_only": preview_only,
+            "watermark": watermark
         }
     })))
 }
@@ -345,7 +458,7 @@ pub async fn verify_meta_password(
     let password = req.get("password").and_then(|v| v.as_str()).unwrap_or("");
 
     let metas: Vec<Meta> = sqlx::query_as(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas ORDER BY length(path) DESC"
+        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, preview_only, po_sub, watermark, wm_sub, created_at, updated_at FROM metas ORDER BY length(path) DESC"
     )
     .fetch_all(&state.db)
     .await
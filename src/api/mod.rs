@@ -1,50 +1,51 @@
+pub mod announcements;
 pub mod archive;
+pub mod archive_passwords;
 pub mod auth;
 pub mod backup;
+pub mod branding;
 pub mod direct_links;
 pub mod shares;
 pub mod drivers;
 pub mod extract;
+pub mod feeds;
 pub mod file_resolver;
 pub mod files;
+#[cfg(feature = "graphql")]
+pub mod graphql;
 pub mod groups;
+pub mod hooks;
 pub mod load_balance;
 pub mod meta;
 pub mod mounts;
 pub mod notification;
 pub mod oauth;
+pub mod scheduler;
 pub mod search;
 pub mod server;
+pub mod server_config;
 pub mod settings;
 pub mod stats;
+pub mod storage_usage;
+pub mod strm;
+pub mod tiering;
+pub mod upload_cleanup;
+pub mod virus_scan;
+pub mod dedupe;
+pub mod fsck;
+pub mod static_site;
+pub mod security_alert;
 pub mod tasks;
 pub mod users;
 pub mod webdav;
+pub mod workspaces;
 
-use serde::Serialize;
+pub use yaolist_backend::error::ApiResponse;
 
-#[derive(Serialize)]
-pub struct ApiResponse<T> {
-    pub code: i32,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<T>,
-}
-
-impl<T> ApiResponse<T> {
-    pub fn success(data: T) -> Self {
-        Self {
-            code: 200,
-            message: "success".to_string(),
-            data: Some(data),
-        }
-    }
-    
-    pub fn error(message: &str) -> Self {
-        Self {
-            code: 400,
-            message: message.to_string(),
-            data: None,
-        }
-    }
+/// GraphQL routes, empty without the `graphql` feature / GraphQL 路由，未启用 `graphql` 特性时为空
+pub fn graphql_routes() -> axum::Router<std::sync::Arc<crate::state::AppState>> {
+    #[cfg(feature = "graphql")]
+    return graphql::routes();
+    #[cfg(not(feature = "graphql"))]
+    axum::Router::new()
 }
@@ -9,35 +9,13 @@ use uuid::Uuid;
 use chrono::Utc;
 use tower_cookies::Cookies;
 
-use crate::{models::{CreateMountRequest, UpdateMountRequest, Mount}, state::AppState, auth::SESSION_COOKIE_NAME};
-
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
-    Ok(())
-}
+use crate::{models::{CreateMountRequest, UpdateMountRequest, Mount}, state::AppState, auth::require_instance_admin};
 
 pub async fn list_mounts(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let mounts = sqlx::query_as::<_, Mount>("SELECT * FROM mounts ORDER BY created_at DESC")
         .fetch_all(&state.db)
         .await
@@ -53,7 +31,7 @@ pub async fn create_mount(
     cookies: Cookies,
     Json(req): Json<CreateMountRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let id = Uuid::new_v4().to_string();
     let now = Utc::now();
     let config_str = serde_json::to_string(&req.config)
@@ -85,7 +63,7 @@ pub async fn get_mount(
     cookies: Cookies,
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let mount = sqlx::query_as::<_, Mount>("SELECT * FROM mounts WHERE id = ?")
         .bind(&id)
         .fetch_optional(&state.db)
@@ -104,7 +82,7 @@ pub async fn update_mount(
     Path(id): Path<String>,
     Json(req): Json<UpdateMountRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = Utc::now();
     
     if let Some(name) = req.name {
@@ -149,7 +127,7 @@ pub async fn delete_mount(
     cookies: Cookies,
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     sqlx::query("DELETE FROM mounts WHERE id = ?")
         .bind(&id)
         .execute(&state.db)
@@ -17,31 +17,11 @@ use hmac::{Hmac, Mac};
 use sha1::Sha1;
 use std::collections::BTreeMap;
 use tower_cookies::Cookies;
+use yaolist_backend::client_ip::ClientIp;
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
-
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
-    Ok(())
-}
+use crate::auth::require_instance_admin;
+use yaolist_backend::email_template::{EmailEvent, EmailTemplate, EmailTemplateConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotificationSettings {
@@ -137,7 +117,7 @@ pub async fn get_notification_settings(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let settings = load_notification_settings(&state).await;
     
     Ok(Json(json!({
@@ -152,7 +132,7 @@ pub async fn save_notification_settings(
     cookies: Cookies,
     Json(req): Json<NotificationSettings>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     save_setting(&state, "notification_email_enabled", &req.email_enabled.to_string()).await.ok();
     save_setting(&state, "notification_email_host", &req.email_host).await.ok();
     save_setting(&state, "notification_email_port", &req.email_port.to_string()).await.ok();
@@ -180,11 +160,29 @@ pub async fn send_smtp_email(
     subject: &str,
     body: &str,
 ) -> Result<(), String> {
-    let email = Message::builder()
+    send_smtp_message(settings, to_email, &[], subject, body).await
+}
+
+/// 发送SMTP邮件（带密送）
+pub async fn send_smtp_message(
+    settings: &NotificationSettings,
+    to_email: &str,
+    bcc: &[String],
+    subject: &str,
+    body: &str,
+) -> Result<(), String> {
+    let mut builder = Message::builder()
         .from(format!("{} <{}>", settings.email_from_name, settings.email_from_email)
             .parse()
             .map_err(|e| format!("发件人地址格式错误: {}", e))?)
-        .to(to_email.parse().map_err(|e| format!("收件人地址格式错误: {}", e))?)
+        .to(to_email.parse().map_err(|e| format!("收件人地址格式错误: {}", e))?);
+    for addr in bcc {
+        match addr.parse() {
+            Ok(mailbox) => builder = builder.bcc(mailbox),
+            Err(e) => tracing::warn!("Invalid BCC address {}: {}", addr, e),
+        }
+    }
+    let email = builder
         .subject(subject)
         .header(ContentType::TEXT_HTML)
         .body(body.to_string())
@@ -211,6 +209,130 @@ pub async fn send_smtp_email(
     Ok(())
 }
 
+/// 按事件模板发送邮件，事件未启用时返回Ok(false)
+/// vars中未提供site_title时自动填充站点标题
+pub async fn send_event_email(
+    state: &AppState,
+    settings: &NotificationSettings,
+    event: EmailEvent,
+    locale: Option<&str>,
+    to_email: &str,
+    mut vars: Value,
+) -> Result<bool, String> {
+    if !settings.email_enabled {
+        return Err("邮箱通知未启用".to_string());
+    }
+    if let Some(obj) = vars.as_object_mut() {
+        if !obj.contains_key("site_title") {
+            obj.insert("site_title".to_string(), json!(load_site_title(state).await));
+        }
+    }
+    
+    let Some(mail) = state.email_templates.render(event, locale, &vars) else {
+        return Ok(false);
+    };
+    send_smtp_message(settings, to_email, &mail.bcc, &mail.subject, &mail.body).await?;
+    Ok(true)
+}
+
+async fn load_site_title(state: &AppState) -> String {
+    sqlx::query_scalar::<_, String>("SELECT value FROM site_settings WHERE key = 'site_title'")
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "YaoList".to_string())
+}
+
+/// 任务完成后给任务所属用户发送邮件（任务完成邮件未启用或用户无邮箱时跳过）
+pub async fn notify_task_completed(state: Arc<AppState>, task: crate::task::TaskSummary) {
+    if !state.email_templates.is_enabled(EmailEvent::TaskCompleted) {
+        return;
+    }
+    let Some(ref user_id) = task.user_id else {
+        return;
+    };
+    let user: Option<(String, Option<String>)> = sqlx::query_as(
+        "SELECT username, email FROM users WHERE id = ?"
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten();
+    let Some((username, Some(email))) = user else {
+        return;
+    };
+    if email.is_empty() {
+        return;
+    }
+    
+    let settings = load_notification_settings(&state).await;
+    if !settings.email_enabled {
+        return;
+    }
+    let vars = json!({
+        "username": username,
+        "task_name": task.name,
+        "task_type": task.task_type,
+        "total_files": task.total_files,
+        "total_size": crate::api::extract::utils::format_size(task.total_size),
+        "finished_at": task.finished_at.unwrap_or_else(Utc::now).format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    });
+    if let Err(e) = send_event_email(&state, &settings, EmailEvent::TaskCompleted, None, &email, vars).await {
+        tracing::warn!("Failed to send task completion email to {}: {}", email, e);
+    }
+}
+
+/// 用户下载流量达到配额告警比例时发送提醒邮件，同一配额只提醒一次
+pub async fn notify_quota_warning(state: Arc<AppState>, user_id: String, quota: i64, used: i64) {
+    if !state.email_templates.is_enabled(EmailEvent::QuotaWarning) {
+        return;
+    }
+    let settings = load_notification_settings(&state).await;
+    if !settings.email_enabled {
+        return;
+    }
+    let user: Option<(String, Option<String>)> = sqlx::query_as(
+        "SELECT username, email FROM users WHERE id = ?"
+    )
+    .bind(&user_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten();
+    let Some((username, Some(email))) = user else {
+        return;
+    };
+    if email.is_empty() {
+        return;
+    }
+    // 先记录已提醒的配额，并发下载时只有一个请求发送邮件
+    let claimed = sqlx::query(
+        "UPDATE users SET quota_warned_for = ? WHERE id = ? AND (quota_warned_for IS NULL OR quota_warned_for != ?)"
+    )
+    .bind(quota)
+    .bind(&user_id)
+    .bind(quota)
+    .execute(&state.db)
+    .await
+    .map(|r| r.rows_affected() == 1)
+    .unwrap_or(false);
+    if !claimed {
+        return;
+    }
+    
+    let vars = json!({
+        "username": username,
+        "used": crate::api::extract::utils::format_size(used.max(0) as u64),
+        "total": crate::api::extract::utils::format_size(quota as u64),
+        "percent": (used as i128 * 100 / quota as i128).min(100)
+    });
+    if let Err(e) = send_event_email(&state, &settings, EmailEvent::QuotaWarning, None, &email, vars).await {
+        tracing::warn!("Failed to send quota warning email to {}: {}", email, e);
+    }
+}
+
 /// 发送阿里云融合认证短信（号码认证服务）
 pub async fn send_aliyun_sms(
     settings: &NotificationSettings,
@@ -305,7 +427,7 @@ pub async fn test_email(
     cookies: Cookies,
     Json(req): Json<TestRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let settings = load_notification_settings(&state).await;
     
     if !settings.email_enabled {
@@ -342,7 +464,7 @@ pub async fn test_sms(
     cookies: Cookies,
     Json(req): Json<TestRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let settings = load_notification_settings(&state).await;
     
     if !settings.sms_enabled {
@@ -367,24 +489,21 @@ pub async fn test_sms(
 /// POST /api/notifications/send-code - 发送验证码（需要图形验证码）
 pub async fn send_verification_code(
     State(state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
     Json(req): Json<SendCodeRequest>,
 ) -> Result<Json<Value>, StatusCode> {
-    // 验证图形验证码
-    match (&req.captcha_id, &req.captcha_code) {
-        (Some(id), Some(code)) if !id.is_empty() && !code.is_empty() => {
-            if !state.login_security.verify_captcha(id, code) {
-                return Ok(Json(json!({
-                    "code": 400,
-                    "message": "图形验证码错误"
-                })));
-            }
-        }
-        _ => {
-            return Ok(Json(json!({
-                "code": 400,
-                "message": "请输入图形验证码"
-            })));
-        }
+    // 验证验证码（图形验证码或 hCaptcha/Turnstile）
+    if let Err(e) = crate::api::auth::verify_captcha_input(
+        &state,
+        req.captcha_id.as_deref(),
+        req.captcha_code.as_deref(),
+        req.captcha_token.as_deref(),
+        client_ip,
+    ).await {
+        return Ok(Json(json!({
+            "code": 400,
+            "message": e
+        })));
     }
 
     let settings = load_notification_settings(&state).await;
@@ -405,20 +524,11 @@ pub async fn send_verification_code(
 
             // 邮件验证码由本地生成
             let code = format!("{:06}", rand::random::<u32>() % 1000000);
-            let subject = "YaoList 验证码";
-            let body = format!(r#"
-                <div style="font-family: Arial, sans-serif; max-width: 600px; margin: 0 auto; padding: 20px;">
-                    <h2 style="color: #333;">验证码</h2>
-                    <p>您的验证码是：</p>
-                    <div style="background: #f5f5f5; padding: 20px; border-radius: 8px; text-align: center; margin: 20px 0;">
-                        <span style="font-size: 32px; font-weight: bold; letter-spacing: 8px;">{}</span>
-                    </div>
-                    <p style="color: #999;">验证码有效期为10分钟，请勿泄露给他人。</p>
-                </div>
-            "#, code);
-
-            match send_smtp_email(&settings, &req.target, subject, &body).await {
-                Ok(_) => (code, None),
+            let vars = json!({ "code": code, "minutes": 10 });
+
+            match send_event_email(&state, &settings, EmailEvent::VerificationCode, req.locale.as_deref(), &req.target, vars).await {
+                Ok(true) => (code, None),
+                Ok(false) => (String::new(), Some("邮件验证码已禁用".to_string())),
                 Err(e) => (String::new(), Some(e))
             }
         }
@@ -475,6 +585,10 @@ pub struct SendCodeRequest {
     pub send_type: String,
     pub captcha_id: Option<String>,
     pub captcha_code: Option<String>,
+    /// hCaptcha / Turnstile 令牌
+    pub captcha_token: Option<String>,
+    /// 邮件语言（如 en-US），为空使用默认语言
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -535,3 +649,252 @@ pub async fn verify_code(
         })))
     }
 }
+
+// ========== 邮件模板 ==========
+
+/// GET /api/notifications/email-templates - 获取邮件模板设置（含内置模板和可用变量）
+pub async fn get_email_templates(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let events: Vec<Value> = EmailEvent::all().iter().map(|event| {
+        json!({
+            "event": event,
+            "enabled": state.email_templates.is_enabled(*event),
+            "variables": event.variables(),
+            "builtin": {
+                "zh-CN": yaolist_backend::email_template::builtin_template(*event, "zh-CN"),
+                "en-US": yaolist_backend::email_template::builtin_template(*event, "en-US")
+            }
+        })
+    }).collect();
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "config": state.email_templates.get_config(),
+            "events": events
+        }
+    })))
+}
+
+/// POST /api/notifications/email-templates - 保存邮件模板设置
+pub async fn save_email_templates(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<EmailTemplateConfig>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let value = serde_json::to_string(&req)
+        .map_err(|_| (StatusCode::BAD_REQUEST, Json(json!({"error": "模板格式错误"}))))?;
+    save_setting(&state, "email_templates", &value).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    state.email_templates.set_config(req);
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功"
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewEmailRequest {
+    pub event: EmailEvent,
+    pub locale: Option<String>,
+    /// 未保存的模板，为空时预览当前生效的模板
+    pub template: Option<EmailTemplate>,
+    #[serde(default)]
+    pub vars: Value,
+}
+
+/// POST /api/notifications/email-templates/preview - 预览邮件模板
+pub async fn preview_email_template(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<PreviewEmailRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let template = req.template
+        .unwrap_or_else(|| state.email_templates.resolve(req.event, req.locale.as_deref()));
+    // 未提供的变量用变量名占位
+    let mut vars = serde_json::Map::new();
+    for name in req.event.variables() {
+        vars.insert(name.to_string(), json!(format!("[{}]", name)));
+    }
+    vars.insert("site_title".to_string(), json!(load_site_title(&state).await));
+    if let Value::Object(custom) = req.vars {
+        vars.extend(custom);
+    }
+    let vars = Value::Object(vars);
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "subject": yaolist_backend::email_template::render_template(&template.subject, &vars),
+            "body": yaolist_backend::email_template::render_template(&template.body, &vars)
+        }
+    })))
+}
+
+// ========== Webhook ==========
+
+#[derive(Debug, Deserialize)]
+pub struct SaveWebhookRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    pub name: String,
+    pub url: String,
+    /// 为空时自动生成（新建）或保持不变（更新）
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub events: Vec<yaolist_backend::webhook::WebhookEvent>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookIdRequest {
+    pub id: String,
+}
+
+/// GET /api/notifications/webhooks - 获取Webhook列表及可订阅事件
+pub async fn list_webhooks(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let webhooks: Vec<Value> = state.webhooks.list().into_iter().map(|hook| {
+        let last_delivery = state.webhooks.last_status(&hook.id);
+        json!({
+            "id": hook.id,
+            "name": hook.name,
+            "url": hook.url,
+            "secret": hook.secret,
+            "events": hook.events,
+            "enabled": hook.enabled,
+            "created_at": hook.created_at,
+            "last_delivery": last_delivery
+        })
+    }).collect();
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "webhooks": webhooks,
+            "events": yaolist_backend::webhook::WebhookEvent::all()
+        }
+    })))
+}
+
+/// POST /api/notifications/webhooks - 新建或更新Webhook
+pub async fn save_webhook(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveWebhookRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let url = req.url.trim().to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "URL必须以http://或https://开头"}))));
+    }
+    
+    let existing = req.id.as_deref().and_then(|id| state.webhooks.get(id));
+    if req.id.is_some() && existing.is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "Webhook不存在"}))));
+    }
+    
+    let secret = match req.secret.filter(|s| !s.is_empty()) {
+        Some(s) => s,
+        None => existing.as_ref()
+            .map(|h| h.secret.clone())
+            .unwrap_or_else(|| hex::encode(rand::random::<[u8; 32]>())),
+    };
+    let hook = yaolist_backend::webhook::Webhook {
+        id: existing.as_ref().map(|h| h.id.clone()).unwrap_or_else(|| Uuid::new_v4().to_string()),
+        name: req.name,
+        url,
+        secret,
+        events: req.events,
+        enabled: req.enabled.or(existing.as_ref().map(|h| h.enabled)).unwrap_or(true),
+        created_at: existing.as_ref().map(|h| h.created_at).unwrap_or_else(Utc::now),
+    };
+    
+    sqlx::query(
+        "INSERT OR REPLACE INTO webhooks (id, name, url, secret, events, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&hook.id)
+    .bind(&hook.name)
+    .bind(&hook.url)
+    .bind(&hook.secret)
+    .bind(serde_json::to_string(&hook.events).unwrap_or_else(|_| "[]".to_string()))
+    .bind(hook.enabled)
+    .bind(hook.created_at.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    
+    state.webhooks.upsert(hook.clone());
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": hook
+    })))
+}
+
+/// POST /api/notifications/webhooks/delete - 删除Webhook
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<WebhookIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    
+    if !state.webhooks.remove(&req.id) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "Webhook不存在"}))));
+    }
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
+
+/// POST /api/notifications/webhooks/test - 发送测试事件（ping）
+pub async fn test_webhook(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<WebhookIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+    
+    let hook = state.webhooks.get(&req.id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "Webhook不存在"}))))?;
+    let result = state.webhooks.ping(&hook).await;
+    
+    if result.success {
+        Ok(Json(json!({
+            "code": 200,
+            "message": "测试事件发送成功",
+            "data": result
+        })))
+    } else {
+        Ok(Json(json!({
+            "code": 500,
+            "message": format!("测试事件发送失败: {}", result.error.clone().unwrap_or_default()),
+            "data": result
+        })))
+    }
+}
@@ -0,0 +1,109 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateJobRequest {
+    pub id: String,
+    pub cron: Option<String>,
+    pub enabled: Option<bool>,
+    pub jitter_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobIdRequest {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobHistoryRequest {
+    pub id: Option<String>,
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+/// GET /api/admin/jobs - 获取定时任务列表
+pub async fn list_jobs(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": state.scheduler.list()
+    })))
+}
+
+/// POST /api/admin/jobs/update - 修改定时任务（Cron、启用、随机延迟）
+pub async fn update_job(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<UpdateJobRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let cron = req.cron.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    match state.scheduler.update(&req.id, cron, req.enabled, req.jitter_secs).await {
+        Ok(job) => Ok(Json(json!({
+            "code": 200,
+            "message": "保存成功",
+            "data": job
+        }))),
+        Err(e) => Err((StatusCode::BAD_REQUEST, Json(json!({"error": e})))),
+    }
+}
+
+/// POST /api/admin/jobs/trigger - 立即运行定时任务
+pub async fn trigger_job(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<JobIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    if state.scheduler.get(&req.id).is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "任务不存在"}))));
+    }
+    match state.scheduler.trigger(&req.id) {
+        Ok(()) => Ok(Json(json!({
+            "code": 200,
+            "message": "任务已开始运行"
+        }))),
+        Err(e) => Err((StatusCode::CONFLICT, Json(json!({"error": e})))),
+    }
+}
+
+/// POST /api/admin/jobs/history - 获取定时任务运行历史
+pub async fn job_history(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<JobHistoryRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let page = req.page.unwrap_or(1);
+    let page_size = req.page_size.unwrap_or(20);
+    let (runs, total) = state.scheduler
+        .history(req.id.as_deref().filter(|id| !id.is_empty()), page, page_size)
+        .await;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "runs": runs,
+            "total": total,
+            "page": page.max(1),
+            "page_size": page_size.clamp(1, 100)
+        }
+    })))
+}
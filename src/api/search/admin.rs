@@ -5,37 +5,16 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use serde_json::{json, Value};
+use serde_json::Value;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tower_cookies::Cookies;
 use futures::stream::{self, StreamExt};
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
+use crate::auth::{require_instance_admin, SESSION_COOKIE_NAME};
 use super::types::*;
-
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
-    Ok(())
-}
+pub use yaolist_backend::error::ApiResponse;
 
 
 /// 获取搜索用的用户权限
@@ -61,9 +40,10 @@ async fn get_search_permissions(state: &AppState, cookies: &Cookies) -> SearchUs
         INNER JOIN sessions s ON u.id = s.user_id
         INNER JOIN user_group_members ugm ON u.id = ugm.user_id
         INNER JOIN user_groups g ON CAST(g.id AS TEXT) = ugm.group_id
-        WHERE s.id = ? AND s.expires_at > datetime('now')"#
+        WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')"#
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .ok()
@@ -88,36 +68,11 @@ pub struct IndexStatus {
     pub error_message: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub code: i32,
-    pub message: String,
-    pub data: Option<T>,
-}
-
-impl<T> ApiResponse<T> {
-    pub fn success(data: T) -> Self {
-        Self {
-            code: 200,
-            message: "success".to_string(),
-            data: Some(data),
-        }
-    }
-
-    pub fn error(message: &str) -> Self {
-        Self {
-            code: 500,
-            message: message.to_string(),
-            data: None,
-        }
-    }
-}
-
 pub async fn get_search_settings(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<ApiResponse<SearchSettings>>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let result = sqlx::query_as::<_, (bool, bool, String, i32)>(
         "SELECT enabled, auto_update_index, ignore_paths, max_index_depth FROM search_settings WHERE id = 1"
     )
@@ -171,7 +126,7 @@ pub async fn update_search_settings(
     cookies: Cookies,
     Json(settings): Json<SearchSettings>,
 ) -> Result<Json<ApiResponse<SearchSettings>>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let now = Utc::now().to_rfc3339();
     
     let result = sqlx::query(
@@ -210,7 +165,7 @@ pub async fn get_index_status(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<ApiResponse<IndexStatus>>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     let progress = state.index_state.get_progress();
     
     // 检查是否有任何存储的索引数据库
@@ -295,7 +250,7 @@ pub async fn rebuild_index(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     
     // 检查是否已经在运行
     if state.index_state.is_running() {
@@ -311,32 +266,15 @@ pub async fn rebuild_index(
 async fn do_rebuild_index(state: Arc<AppState>) -> Result<(), String> {
     tracing::info!("开始构建索引");
     
-    let drivers_result = sqlx::query_as::<_, (String, String)>(
-        "SELECT name, config FROM drivers WHERE enabled = 1"
-    )
-    .fetch_all(&state.db)
-    .await;
-
-    let drivers = match drivers_result {
-        Ok(d) => d,
+    // 索引覆盖所有工作区的挂载点
+    let driver_mounts: Vec<(String, String)> = match state.paths.all_mounts().await {
+        Ok(mounts) => mounts.into_iter().map(|m| (m.id, m.mount_path)).collect(),
         Err(e) => {
             tracing::error!("Failed to get driver list: {}", e);
             return Err(format!("获取驱动列表失败: {}", e));
         }
     };
 
-    // 解析驱动配置，提取 mount_path
-    let mut driver_mounts: Vec<(String, String)> = Vec::new();
-    for (driver_name, config_str) in drivers {
-        if let Ok(config) = serde_json::from_str::<serde_json::Value>(&config_str) {
-            let mount_path = config.get("mount_path")
-                .and_then(|v| v.as_str())
-                .unwrap_or("/")
-                .to_string();
-            driver_mounts.push((driver_name, mount_path));
-        }
-    }
-
     if driver_mounts.is_empty() {
         tracing::warn!("没有启用的驱动");
         return Err("没有启用的驱动".to_string());
@@ -528,7 +466,7 @@ pub async fn clear_index(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     
     if state.index_state.is_running() {
         return Ok(Json(ApiResponse::error("索引正在构建中，请先停止")));
@@ -548,7 +486,7 @@ pub async fn stop_indexing(
     State(state): State<Arc<AppState>>,
     cookies: Cookies,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<Value>)> {
-    require_admin(&state, &cookies).await?;
+    require_instance_admin(&state, &cookies).await?;
     
     if !state.index_state.is_running() {
         return Ok(Json(ApiResponse::error("没有正在运行的索引任务")));
@@ -8,7 +8,9 @@ use tower_cookies::Cookies;
 
 use crate::state::AppState;
 use crate::models::Meta;
-use yaolist_backend::utils::should_hide_file;
+use yaolist_backend::path_resolver::is_sub_path;
+use yaolist_backend::hide_rules::HideRules;
+use yaolist_backend::file_type::FileType;
 use super::types::*;
 use super::admin::ApiResponse;
 
@@ -23,7 +25,7 @@ pub struct SearchRequest {
     #[serde(default)]
     pub current_path: Option<String>,
     #[serde(default)]
-    pub filter_type: Option<String>, // "file" or "folder"
+    pub filter_type: Option<String>, // "file"、"folder" 或文件类型（video、audio、image、doc、archive）
 }
 
 fn default_limit() -> usize { 50 }
@@ -34,6 +36,9 @@ pub struct SearchResultItem {
     pub path: String,
     pub name: String,
     pub is_dir: bool,
+    /// video、audio、image、doc、archive、other
+    #[serde(rename = "type")]
+    pub file_type: FileType,
     pub size: i64,
     pub modified: i64,
 }
@@ -79,17 +84,25 @@ pub async fn search(
     // 获取用户上下文（权限+根路径）- 直接使用files模块的函数确保一致性
     let user_ctx = crate::api::files::get_user_context(&state, &cookies).await;
     let user_root = yaolist_backend::utils::fix_and_clean_path(&user_ctx.root_path);
+    let workspace = yaolist_backend::workspace::current();
     let perms = SearchUserPermissions { show_hidden_files: user_ctx.permissions.show_hidden_files };
     
+    // 游客搜索受游客访问策略限制
+    let guest_policy = user_ctx.is_guest.then(|| state.guest.get());
+    if guest_policy.as_ref().is_some_and(|p| !p.allow_search) {
+        return Json(ApiResponse::error("游客不允许搜索"));
+    }
+    
     tracing::debug!("搜索：用户根路径={}", user_root);
     
     // 获取所有元信息的隐藏规则
-    let metas: Vec<Meta> = sqlx::query_as(
-        "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas"
-    )
-    .fetch_all(&state.db)
-    .await
-    .unwrap_or_default();
+    let metas = state.paths.metas().await.unwrap_or_default();
+    let hide_rules: Vec<(Meta, HideRules)> = metas.into_iter()
+        .filter_map(|m| {
+            let rules = HideRules::parse(m.hide.as_deref().unwrap_or(""));
+            (!rules.is_empty()).then_some((m, rules))
+        })
+        .collect();
 
     // 获取当前路径（用于优先排序）
     let current_path = req.current_path.unwrap_or_else(|| "/".to_string());
@@ -99,6 +112,10 @@ pub async fn search(
     let mut all_hits: Vec<yaolist_backend::search::SearchHit> = Vec::new();
     
     for driver_id in &driver_dbs {
+        // 对用户隐藏的存储不参与搜索
+        if user_ctx.hidden_drivers.contains(driver_id) {
+            continue;
+        }
         // 为每个存储打开数据库
         let db_index = match yaolist_backend::search::DbIndex::new_for_driver(driver_id).await {
             Ok(idx) => idx,
@@ -134,14 +151,24 @@ pub async fn search(
     let mut filtered: Vec<_> = all_hits.iter()
         .filter(|h| {
             // 首先检查是否在用户根路径下
-            // 用户根路径为"/"时允许所有路径，否则路径必须以用户根路径开头
-            if user_root != "/" {
-                let user_root_with_slash = if user_root.ends_with('/') {
-                    user_root.clone()
-                } else {
-                    format!("{}/", user_root)
-                };
-                if !h.path.starts_with(&user_root_with_slash) && h.path != user_root {
+            if !is_sub_path(&user_root, &h.path) {
+                return false;
+            }
+            
+            // 其他工作区的目录不可见
+            if !workspace.path_visible(&h.path) {
+                return false;
+            }
+            
+            // 用户不可见的挂载点中的结果不返回
+            if !user_ctx.mount_visible(&h.path) {
+                return false;
+            }
+            
+            // 游客只能搜到可浏览路径下的结果（策略路径相对游客根路径）
+            if let Some(ref policy) = guest_policy {
+                let relative = if user_root == "/" { &h.path[..] } else { &h.path[user_root.len()..] };
+                if !policy.path_browsable(relative) {
                     return false;
                 }
             }
@@ -151,25 +178,9 @@ pub async fn search(
                 return true;
             }
             
-            // 检查是否被任何元信息规则隐藏
-            let filename = h.path.split('/').last().unwrap_or(&h.name);
-            for meta in &metas {
-                // 检查路径是否在元信息范围内
-                if h.path.starts_with(&meta.path) || h.path == meta.path {
-                    let hide_str: &str = meta.hide.as_deref().unwrap_or("");
-                    if !hide_str.is_empty() {
-                        // 检查是否应用子目录规则
-                        let is_sub = h.path.len() > meta.path.len() && 
-                            h.path[meta.path.len()..].contains('/');
-                        if !is_sub || meta.h_sub {
-                            if should_hide_file(filename, hide_str) {
-                                return false;
-                            }
-                        }
-                    }
-                }
-            }
-            true
+            // 检查是否被任何元信息规则隐藏（按结果所在目录判断规则是否生效）
+            let (dir, filename) = h.path.rsplit_once('/').unwrap_or(("/", &h.name));
+            !hide_rules.iter().any(|(meta, rules)| rules.hides_at(&meta.path, meta.h_sub, dir, filename))
         })
         .collect();
     
@@ -179,7 +190,10 @@ pub async fn search(
             match filter_type.as_str() {
                 "file" => !h.is_dir,
                 "folder" => h.is_dir,
-                _ => true,
+                other => match FileType::from_name(other) {
+                    Some(file_type) => state.file_types.classify(&h.name, h.is_dir) == file_type,
+                    None => true,
+                },
             }
         });
     }
@@ -220,6 +234,7 @@ pub async fn search(
                 path: display_path,
                 name: h.name.clone(),
                 is_dir: h.is_dir,
+                file_type: state.file_types.classify(&h.name, h.is_dir),
                 size: h.size,
                 modified: h.modified,
             }
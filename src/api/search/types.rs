@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use yaolist_backend::file_type::FileType;
 
 /// 用户权限（仅搜索需要的字段）
 #[derive(Debug, Clone, Default, sqlx::FromRow)]
@@ -22,32 +23,6 @@ impl Default for SearchUserContext {
     }
 }
 
-/// API响应结构
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
-impl<T> ApiResponse<T> {
-    pub fn success(data: T) -> Self {
-        Self {
-            success: true,
-            data: Some(data),
-            error: None,
-        }
-    }
-
-    pub fn error(message: &str) -> Self {
-        Self {
-            success: false,
-            data: None,
-            error: Some(message.to_string()),
-        }
-    }
-}
-
 /// 索引状态
 #[derive(Debug, Serialize)]
 pub struct IndexStatus {
@@ -78,7 +53,7 @@ pub struct SearchRequest {
     #[serde(default)]
     pub current_path: Option<String>,
     #[serde(default)]
-    pub filter_type: Option<String>, // "file" or "folder"
+    pub filter_type: Option<String>, // "file"、"folder" 或文件类型（video、audio、image、doc、archive）
 }
 
 fn default_limit() -> usize { 50 }
@@ -90,6 +65,9 @@ pub struct SearchResultItem {
     pub path: String,
     pub name: String,
     pub is_dir: bool,
+    /// video、audio、image、doc、archive、other
+    #[serde(rename = "type")]
+    pub file_type: FileType,
     pub size: i64,
     pub modified: i64,
 }
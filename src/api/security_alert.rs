@@ -0,0 +1,174 @@
+//! 安全告警：多次登录失败、从新国家登录、管理员账号变更
+
+use chrono::Utc;
+use serde_json::json;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::email_template::EmailEvent;
+use yaolist_backend::geoip;
+use yaolist_backend::lockout::{account_key, LockoutScope};
+use yaolist_backend::security_alert::{
+    is_new_country, reached_threshold, send_telegram, SecurityAlert, SecurityAlertConfig, SECURITY_ALERT_KEY,
+};
+use yaolist_backend::webhook::WebhookEvent;
+
+use crate::state::AppState;
+use crate::auth::SESSION_COOKIE_NAME;
+use crate::api::notification::{load_notification_settings, send_event_email};
+
+/// 从数据库加载安全告警设置
+pub async fn load_security_alert_config(state: &AppState) -> SecurityAlertConfig {
+    sqlx::query_scalar::<_, String>("SELECT value FROM site_settings WHERE key = ?")
+        .bind(SECURITY_ALERT_KEY)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+/// 推送安全告警（Webhook、Telegram，以及按设置发送邮件给管理员）
+async fn send_security_alert(state: &AppState, config: &SecurityAlertConfig, alert: SecurityAlert) {
+    tracing::warn!("Security alert: {} - {}", alert.title(), alert.details());
+    state.webhooks.emit(WebhookEvent::SecurityAlert, alert.to_json());
+
+    let text = format!("{}\n{}", alert.title(), alert.details());
+    if let Err(e) = send_telegram(config, &text).await {
+        tracing::warn!("Failed to send security alert to Telegram: {}", e);
+    }
+
+    if !config.email_admins || !state.email_templates.is_enabled(EmailEvent::SecurityAlert) {
+        return;
+    }
+    let settings = load_notification_settings(state).await;
+    if !settings.email_enabled {
+        return;
+    }
+    let emails: Vec<String> = sqlx::query_scalar(
+        "SELECT email FROM users WHERE is_admin = 1 AND enabled = 1 AND email IS NOT NULL AND email != ''"
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+    let vars = json!({
+        "title": alert.title(),
+        "details": alert.details(),
+        "time": Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    });
+    for email in emails {
+        if let Err(e) = send_event_email(state, &settings, EmailEvent::SecurityAlert, None, &email, vars.clone()).await {
+            tracing::warn!("Failed to send security alert email to {}: {}", email, e);
+        }
+    }
+}
+
+/// 登录失败后检查账号或IP的失败次数是否刚好达到告警阈值
+pub fn check_failed_login(state: &Arc<AppState>, ip: &str, account: &str, blocked: bool) {
+    let account_failures = state.login_lockout.fail_count(LockoutScope::Account, &account_key(account));
+    let ip_failures = state.login_lockout.fail_count(LockoutScope::Ip, ip);
+    let state = state.clone();
+    let (ip, account) = (ip.to_string(), account.to_string());
+    tokio::spawn(async move {
+        let config = load_security_alert_config(&state).await;
+        let threshold = config.failed_login_threshold;
+        if !reached_threshold(account_failures, threshold) && !reached_threshold(ip_failures, threshold) {
+            return;
+        }
+        let alert = SecurityAlert::FailedLogins {
+            username: account,
+            ip,
+            failures: account_failures.max(ip_failures),
+            blocked,
+        };
+        send_security_alert(&state, &config, alert).await;
+    });
+}
+
+/// 登录成功后记录登录国家，账号从新国家登录时告警
+pub fn check_login_country(state: &Arc<AppState>, user_id: &str, username: &str, ip: IpAddr) {
+    if geoip::is_private_ip(&ip) {
+        return;
+    }
+    let Some(country) = geoip::lookup_country(ip) else {
+        return;
+    };
+    let state = state.clone();
+    let (user_id, username) = (user_id.to_string(), username.to_string());
+    tokio::spawn(async move {
+        let known: Vec<String> = sqlx::query_scalar("SELECT country FROM user_login_countries WHERE user_id = ? ORDER BY first_seen")
+            .bind(&user_id)
+            .fetch_all(&state.db)
+            .await
+            .unwrap_or_default();
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            "INSERT INTO user_login_countries (user_id, country, first_seen, last_seen) VALUES (?, ?, ?, ?)
+             ON CONFLICT(user_id, country) DO UPDATE SET last_seen = excluded.last_seen"
+        )
+        .bind(&user_id)
+        .bind(&country)
+        .bind(&now)
+        .bind(&now)
+        .execute(&state.db)
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to record login country of {}: {}", username, e);
+        }
+
+        if !is_new_country(&known, &country) {
+            return;
+        }
+        let config = load_security_alert_config(&state).await;
+        if config.new_country {
+            let alert = SecurityAlert::NewCountry { username, ip: ip.to_string(), country, known };
+            send_security_alert(&state, &config, alert).await;
+        }
+    });
+}
+
+/// 用户是否为管理员（用户标记或属于管理员用户组）
+pub async fn is_admin_account(state: &AppState, user_id: &str) -> bool {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT u.is_admin OR EXISTS(
+             SELECT 1 FROM user_group_members m
+             JOIN user_groups g ON CAST(g.id AS TEXT) = m.group_id
+             WHERE m.user_id = u.id AND g.is_admin = 1
+         )
+         FROM users u WHERE u.id = ?"
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten()
+    .unwrap_or(false)
+}
+
+/// 管理员账号被创建、修改或删除时告警，actor 为当前会话的用户
+pub async fn admin_account_changed(state: &Arc<AppState>, cookies: &Cookies, username: &str, action: &str) {
+    let actor: Option<String> = match cookies.get(SESSION_COOKIE_NAME) {
+        Some(cookie) => sqlx::query_scalar(
+            "SELECT u.username FROM users u JOIN sessions s ON u.id = s.user_id WHERE s.id = ?"
+        )
+        .bind(cookie.value())
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten(),
+        None => None,
+    };
+    let state = state.clone();
+    let alert = SecurityAlert::AdminChanged {
+        actor: actor.unwrap_or_else(|| "unknown".to_string()),
+        username: username.to_string(),
+        action: action.to_string(),
+    };
+    tokio::spawn(async move {
+        let config = load_security_alert_config(&state).await;
+        if config.admin_changes {
+            send_security_alert(&state, &config, alert).await;
+        }
+    });
+}
@@ -1,10 +1,16 @@
 use axum::{
     extract::State,
+    http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
+use yaolist_backend::config;
+use yaolist_backend::storage::ListSort;
 
 use crate::state::AppState;
 use crate::api::ApiResponse;
@@ -17,6 +23,85 @@ pub async fn health_check() -> Json<Value> {
     }))
 }
 
+/// GET /api/health/ready - 就绪检查
+///
+/// Answers 503 until the database responds, the data directory is writable and the saved drivers
+/// have finished loading. Drivers that failed to load are counted but do not block readiness.
+/// 数据库可用、数据目录可写且已保存的驱动加载完成前返回 503。加载失败的驱动只计数，不影响就绪。
+pub async fn readiness_check(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<Value>) {
+    let database = match tokio::time::timeout(
+        Duration::from_secs(3),
+        sqlx::query_scalar::<_, i64>("SELECT 1").fetch_one(&state.db),
+    ).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            tracing::warn!("Readiness check: database error: {}", e);
+            false
+        }
+        Err(_) => {
+            tracing::warn!("Readiness check: database timeout");
+            false
+        }
+    };
+    let data_dir = data_dir_writable(&config::config().get_data_dir()).await;
+
+    let enabled: Vec<String> = sqlx::query_scalar("SELECT name FROM drivers WHERE enabled = 1")
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+    let loading = state.drivers_loading.load(Ordering::SeqCst);
+    let errors = state.storage_manager.get_all_driver_errors().await;
+    let instances = state.storage_manager.list_drivers().await;
+    let loaded = enabled.iter()
+        .filter(|id| instances.contains(*id) && !errors.contains_key(*id))
+        .count();
+    // 启动加载结束后仍未成功的驱动均视为失败
+    let failed = enabled.iter()
+        .filter(|id| errors.contains_key(*id) || (loading == 0 && !instances.contains(*id)))
+        .count();
+
+    let ready = database && data_dir && loading == 0;
+    let status = if !ready {
+        "not_ready"
+    } else if failed > 0 {
+        "degraded"
+    } else {
+        "ready"
+    };
+    let code = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (code, Json(json!({
+        "status": status,
+        "checks": {
+            "database": database,
+            "data_dir": data_dir,
+            "drivers": {
+                "enabled": enabled.len(),
+                "loaded": loaded,
+                "failed": failed,
+                "loading": loading
+            }
+        }
+    })))
+}
+
+/// Write and remove a probe file / 写入并删除探测文件
+async fn data_dir_writable(dir: &Path) -> bool {
+    let probe = dir.join(".ready_probe");
+    match tokio::fs::write(&probe, b"ok").await {
+        Ok(()) => {
+            let _ = tokio::fs::remove_file(&probe).await;
+            true
+        }
+        Err(e) => {
+            tracing::warn!("Readiness check: data directory {:?} not writable: {}", dir, e);
+            false
+        }
+    }
+}
+
 /// WebDAV服务器状态
 #[derive(Debug, Clone, Serialize)]
 pub struct ServerStatus {
@@ -42,6 +127,10 @@ pub struct UpdateWebDavConfigRequest {
     pub enabled: Option<bool>,
     pub listen: Option<String>,
     pub prefix: Option<String>,
+    /// 目录列表排序：name、pinyin、modified、size
+    pub sort_by: Option<String>,
+    /// asc、desc
+    pub sort_order: Option<String>,
 }
 
 /// 更新WebDAV配置
@@ -60,6 +149,12 @@ pub async fn update_webdav_config(
     if let Some(prefix) = req.prefix {
         config.prefix = prefix;
     }
+    if req.sort_by.is_some() || req.sort_order.is_some() {
+        let current = config.sort;
+        let field = req.sort_by.as_deref().map_or(current.field, |s| ListSort::parse(Some(s), None).field);
+        let desc = req.sort_order.as_deref().map_or(current.desc, |o| o == "desc");
+        config.sort = ListSort { field, desc };
+    }
     
     // TODO: 保存配置到数据库
     // TODO: 重启WebDAV服务器
@@ -0,0 +1,78 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::config::{self, AppConfig};
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+/// 配置变更结果（restart_required为需重启才生效的配置项）
+fn change_response(message: &str, restart: Vec<&'static str>) -> Json<Value> {
+    Json(json!({
+        "code": 200,
+        "message": message,
+        "data": {
+            "config": config::config(),
+            "restart_required": restart
+        }
+    }))
+}
+
+/// GET /api/admin/config - 获取服务器配置
+pub async fn get_config(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "config": config::config(),
+            "config_path": config::get_config_path(),
+            "restart_fields": ["server.host", "server.port", "server.http2", "server.unix_socket", "database", "search", "geoip.db_dir", "tls", "cluster"]
+        }
+    })))
+}
+
+/// POST /api/admin/config - 更新服务器配置（部分字段合并），写入配置文件
+pub async fn update_config(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(patch): Json<Value>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    if !patch.is_object() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "配置必须是JSON对象"}))));
+    }
+    let mut merged = serde_json::to_value(config::config())
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    config::merge_json(&mut merged, patch);
+    let new: AppConfig = serde_json::from_value(merged)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("配置格式错误: {}", e)}))))?;
+
+    let restart = config::update_config(new)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e}))))?;
+    tracing::info!("Configuration updated via API, restart required for: {:?}", restart);
+
+    Ok(change_response("保存成功", restart))
+}
+
+/// POST /api/admin/config/reload - 从配置文件重新加载（同SIGHUP）
+pub async fn reload_config(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let restart = config::reload_config()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e}))))?;
+
+    Ok(change_response("重新加载成功", restart))
+}
@@ -12,7 +12,7 @@ use tokio::io::AsyncWriteExt;
 use tower_cookies::Cookies;
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
+use crate::auth::require_instance_admin;
 use yaolist_backend::geoip::get_geoip_manager;
 
 /// GET /api/settings/geoip/status - 获取GeoIP数据库状态
@@ -21,23 +21,7 @@ pub async fn get_geoip_status(
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 验证管理员权限
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
+    require_instance_admin(&state, &cookies).await?;
     let data_dir = std::env::current_dir()
         .map(|p| p.join("data"))
         .unwrap_or_else(|_| PathBuf::from("data"));
@@ -74,23 +58,7 @@ pub async fn download_geoip_db(
     Json(req): Json<DownloadGeoIpRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 验证管理员权限
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
+    require_instance_admin(&state, &cookies).await?;
     let data_dir = std::env::current_dir()
         .map(|p| p.join("data"))
         .unwrap_or_else(|_| PathBuf::from("data"));
@@ -207,23 +175,7 @@ pub async fn reload_geoip_db(
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 验证管理员权限
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
+    require_instance_admin(&state, &cookies).await?;
     let data_dir = std::env::current_dir()
         .map(|p| p.join("data"))
         .unwrap_or_else(|_| PathBuf::from("data"));
@@ -256,23 +208,7 @@ pub async fn get_geoip_config(
     cookies: Cookies,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 验证管理员权限
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
+    require_instance_admin(&state, &cookies).await?;
     let enabled: Option<(String,)> = sqlx::query_as(
         "SELECT value FROM site_settings WHERE key = 'geoip_auto_update'"
     )
@@ -323,23 +259,7 @@ pub async fn save_geoip_config(
     Json(req): Json<GeoIpConfigRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 验证管理员权限
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
-    }
+    require_instance_admin(&state, &cookies).await?;
     let now = Utc::now().to_rfc3339();
     
     tracing::info!("Saving GeoIP config: enabled={}, url={}, interval={}", req.enabled, req.url, req.update_interval);
@@ -10,54 +10,37 @@ use chrono::Utc;
 use tower_cookies::Cookies;
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
+use crate::auth::require_admin;
+use yaolist_backend::workspace::{read_setting, settings_key};
 use super::types::*;
 
 /// GET /api/settings/public - 获取公开站点设置
 pub async fn get_public_settings(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<Value>, StatusCode> {
-    let site_title: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'site_title'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let site_title: Option<(String,)> = read_setting(&state.db, "site_title")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let site_description: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'site_description'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let site_description: Option<(String,)> = read_setting(&state.db, "site_description")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let allow_registration: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'allow_registration'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allow_registration: Option<(String,)> = read_setting(&state.db, "allow_registration")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let site_announcement: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'site_announcement'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let site_announcement: Option<(String,)> = read_setting(&state.db, "site_announcement")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let site_icon: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'site_icon'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let site_icon: Option<(String,)> = read_setting(&state.db, "site_icon")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let robots_txt: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'robots_txt'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let robots_txt: Option<(String,)> = read_setting(&state.db, "robots_txt")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     let preview_encrypted_audio: Option<(String,)> = sqlx::query_as(
         "SELECT value FROM site_settings WHERE key = 'preview_encrypted_audio'"
@@ -66,40 +49,25 @@ pub async fn get_public_settings(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let background_image: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'background_image'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let background_image: Option<(String,)> = read_setting(&state.db, "background_image")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let glass_effect: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'glass_effect'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let glass_effect: Option<(String,)> = read_setting(&state.db, "glass_effect")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let glass_blur: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'glass_blur'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let glass_blur: Option<(String,)> = read_setting(&state.db, "glass_blur")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let glass_opacity: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'glass_opacity'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let glass_opacity: Option<(String,)> = read_setting(&state.db, "glass_opacity")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let default_user_group: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM site_settings WHERE key = 'default_user_group'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let default_user_group: Option<(String,)> = read_setting(&state.db, "default_user_group")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     // Proxy and download settings / 代理和下载设置
     let proxy_max_speed: Option<(String,)> = sqlx::query_as(
@@ -149,7 +117,44 @@ pub async fn get_public_settings(
         // Download domain / 下载域名 (empty = use current / 空表示使用当前域名)
         "download_domain": download_domain.map(|(v,)| v).unwrap_or_else(|| "".to_string()),
         // Link expiry / 链接有效期
-        "link_expiry_minutes": link_expiry_minutes.map(|(v,)| v.parse::<i32>().unwrap_or(15)).unwrap_or(15)
+        "link_expiry_minutes": link_expiry_minutes.map(|(v,)| v.parse::<i32>().unwrap_or(15)).unwrap_or(15),
+        // Direct link rewrite rules / 直链改写规则
+        "download_link_rewrites": state.download_settings.get_link_rewrites().iter()
+            .map(|r| r.redacted()).collect::<Vec<_>>(),
+        // Direct link vs proxy policy / 直链与代理决策策略
+        "download_link_policy": state.download_settings.get_link_policy(),
+        // Copy buffer size / 复制缓冲区大小
+        "copy_buffer_size": state.transfer_settings.get_buffer_size(),
+        // Task retention / 任务保留策略
+        "task_retention_days": state.task_manager.get_retention().0,
+        "task_memory_retention_hours": state.task_manager.get_retention().1,
+        // CORS and security headers / 跨域与安全响应头
+        "http_security": *state.http_security.get(),
+        // Response compression / 响应压缩
+        "response_compression": state.compression.get(),
+        // Login lockout / 登录锁定策略
+        "login_lockout": state.login_lockout.get_policy(),
+        // Captcha provider, secret is never returned / 验证码提供方（不返回密钥）
+        "captcha_provider": state.captcha.get().provider,
+        "captcha_site_key": state.captcha.get().site_key,
+        "captcha_secret_set": !state.captcha.get().secret_key.is_empty(),
+        // Guest access policy / 游客访问策略
+        "guest_policy": state.guest.get(),
+        // File type categories / 文件类型分类
+        "file_types": state.file_types.get(),
+        // Directory listing cache / 目录列表缓存
+        "list_cache": state.storage_manager.list_cache().get_config(),
+        "list_cache_shared": state.storage_manager.list_cache().is_shared(),
+        // Storage space alerts / 存储空间告警
+        "storage_alert": crate::api::storage_usage::load_storage_alert_config(&state).await,
+        // Interrupted upload cleanup / 中断上传清理
+        "upload_cleanup": crate::api::upload_cleanup::load_upload_cleanup_config(&state).await,
+        // Maintenance mode / 维护模式
+        "maintenance": state.storage_manager.maintenance().get(),
+        // Security alerts, bot token hidden / 安全告警（不返回机器人令牌）
+        "security_alert": crate::api::security_alert::load_security_alert_config(&state).await.redacted(),
+        // Active announcement banners / 当前展示的公告
+        "announcements": state.announcements.active(Utc::now())
     })))
 }
 
@@ -160,22 +165,58 @@ pub async fn update_settings(
     Json(req): Json<UpdateSettingsRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 验证管理员权限
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    require_admin(&state, &cookies).await?;
     
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
+    if !yaolist_backend::workspace::current().is_default() && req.has_instance_settings() {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "实例级设置仅能在默认工作区中修改"}))));
+    }
+    if let Some(ref http_security) = req.http_security {
+        http_security.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("安全设置无效: {}", e)}))))?;
+    }
+    if let Some(ref policy) = req.login_lockout {
+        policy.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("登录锁定策略无效: {}", e)}))))?;
+    }
+    let captcha = req.captcha.map(|c| state.captcha.merge_secret(c));
+    if let Some(ref captcha) = captcha {
+        captcha.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("验证码设置无效: {}", e)}))))?;
+    }
+    if let Some(ref policy) = req.guest_policy {
+        policy.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("游客访问策略无效: {}", e)}))))?;
+    }
+    let file_types = req.file_types.map(|mut map| {
+        map.normalize();
+        map
+    });
+    if let Some(ref map) = file_types {
+        map.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("文件类型分类无效: {}", e)}))))?;
+    }
+    if let Some(ref storage_alert) = req.storage_alert {
+        storage_alert.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("空间告警设置无效: {}", e)}))))?;
+    }
+    if let Some(ref upload_cleanup) = req.upload_cleanup {
+        upload_cleanup.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("上传清理设置无效: {}", e)}))))?;
+    }
+    let download_link_rewrites = req.download_link_rewrites.map(|rules| {
+        yaolist_backend::download::merge_rewrite_secrets(rules, &state.download_settings.get_link_rewrites())
+    });
+    let security_alert = match req.security_alert {
+        Some(config) => Some(config.merge_secret(&crate::api::security_alert::load_security_alert_config(&state).await)),
+        None => None,
+    };
+    if let Some(ref security_alert) = security_alert {
+        security_alert.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("安全告警设置无效: {}", e)}))))?;
+    }
+    if let Some(ref virus_scan) = req.virus_scan {
+        virus_scan.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("病毒扫描设置无效: {}", e)}))))?;
     }
     
     let now = Utc::now().to_rfc3339();
@@ -184,7 +225,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("site_title")
+        .bind(settings_key("site_title"))
         .bind(&site_title)
         .bind(&now)
         .execute(&state.db)
@@ -196,7 +237,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("site_description")
+        .bind(settings_key("site_description"))
         .bind(&site_description)
         .bind(&now)
         .execute(&state.db)
@@ -208,7 +249,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("site_icon")
+        .bind(settings_key("site_icon"))
         .bind(&site_icon)
         .bind(&now)
         .execute(&state.db)
@@ -220,7 +261,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("allow_registration")
+        .bind(settings_key("allow_registration"))
         .bind(if allow_registration { "true" } else { "false" })
         .bind(&now)
         .execute(&state.db)
@@ -232,7 +273,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("default_user_group")
+        .bind(settings_key("default_user_group"))
         .bind(&default_user_group)
         .bind(&now)
         .execute(&state.db)
@@ -244,7 +285,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("site_announcement")
+        .bind(settings_key("site_announcement"))
         .bind(&site_announcement)
         .bind(&now)
         .execute(&state.db)
@@ -256,7 +297,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("robots_txt")
+        .bind(settings_key("robots_txt"))
         .bind(&robots_txt)
         .bind(&now)
         .execute(&state.db)
@@ -280,7 +321,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("background_image")
+        .bind(settings_key("background_image"))
         .bind(&background_image)
         .bind(&now)
         .execute(&state.db)
@@ -292,7 +333,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("glass_effect")
+        .bind(settings_key("glass_effect"))
         .bind(if glass_effect { "true" } else { "false" })
         .bind(&now)
         .execute(&state.db)
@@ -304,7 +345,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("glass_blur")
+        .bind(settings_key("glass_blur"))
         .bind(glass_blur.to_string())
         .bind(&now)
         .execute(&state.db)
@@ -316,7 +357,7 @@ pub async fn update_settings(
         sqlx::query(
             "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
         )
-        .bind("glass_opacity")
+        .bind(settings_key("glass_opacity"))
         .bind(glass_opacity.to_string())
         .bind(&now)
         .execute(&state.db)
@@ -376,9 +417,258 @@ pub async fn update_settings(
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
     }
     
+    // Direct link rewrite rules / 直链改写规则
+    if let Some(ref rewrites) = download_link_rewrites {
+        let value = serde_json::to_string(rewrites)
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(json!({"error": "直链改写规则格式错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("download_link_rewrites")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
+    // Direct link policy / 直链策略
+    if let Some(ref policy) = req.download_link_policy {
+        let value = serde_json::to_string(policy)
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(json!({"error": "直链策略格式错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("download_link_policy")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
+    // Copy buffer size / 复制缓冲区大小
+    if let Some(copy_buffer_size) = req.copy_buffer_size {
+        state.transfer_settings.set_buffer_size(copy_buffer_size);
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("copy_buffer_size")
+        .bind(state.transfer_settings.get_buffer_size().to_string())
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
+    // CORS and security headers / 跨域与安全响应头
+    if let Some(http_security) = req.http_security {
+        let value = serde_json::to_string(&http_security)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("http_security")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        let _ = state.http_security.set(http_security);
+    }
+    
+    // Response compression / 响应压缩
+    if let Some(compression) = req.response_compression {
+        let value = serde_json::to_string(&compression)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind(yaolist_backend::compression::RESPONSE_COMPRESSION_KEY)
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        state.compression.set(compression);
+    }
+    
+    // Login lockout policy / 登录锁定策略
+    if let Some(policy) = req.login_lockout {
+        let value = serde_json::to_string(&policy)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("login_lockout_policy")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        let _ = state.login_lockout.set_policy(policy);
+    }
+    
+    // Captcha provider / 验证码提供方
+    if let Some(captcha) = captcha {
+        let value = serde_json::to_string(&captcha)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("captcha_config")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        let _ = state.captcha.set(captcha);
+    }
+    
+    // Guest access policy / 游客访问策略
+    if let Some(policy) = req.guest_policy {
+        let value = serde_json::to_string(&policy)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("guest_policy")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        let _ = state.guest.set(policy);
+    }
+    
+    // File type categories / 文件类型分类
+    if let Some(map) = file_types {
+        let value = serde_json::to_string(&map)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("file_types")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        let _ = state.file_types.set(map);
+    }
+    
+    // Task retention / 任务保留策略
+    if req.task_retention_days.is_some() || req.task_memory_retention_hours.is_some() {
+        state.task_manager.set_retention(req.task_retention_days, req.task_memory_retention_hours);
+        let (days, hours) = state.task_manager.get_retention();
+        for (key, value) in [("task_retention_days", days), ("task_memory_retention_hours", hours)] {
+            sqlx::query(
+                "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+            )
+            .bind(key)
+            .bind(value.to_string())
+            .bind(&now)
+            .execute(&state.db)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        }
+    }
+    
+    // Directory listing cache / 目录列表缓存
+    if let Some(list_cache) = req.list_cache {
+        let value = serde_json::to_string(&list_cache)
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(json!({"error": "列表缓存设置格式错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind("list_cache")
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        state.storage_manager.list_cache().set_config(list_cache);
+    }
+    
+    // Storage space alerts / 存储空间告警
+    if let Some(storage_alert) = req.storage_alert {
+        let value = serde_json::to_string(&storage_alert)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind(yaolist_backend::storage::usage::STORAGE_ALERT_KEY)
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
+    // Security alerts / 安全告警
+    if let Some(security_alert) = security_alert {
+        let value = serde_json::to_string(&security_alert)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind(yaolist_backend::security_alert::SECURITY_ALERT_KEY)
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
+    // Upload virus scanning / 上传病毒扫描
+    if let Some(virus_scan) = req.virus_scan {
+        let value = serde_json::to_string(&virus_scan)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind(yaolist_backend::virus_scan::VIRUS_SCAN_KEY)
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
+    // Interrupted upload cleanup / 中断上传清理
+    if let Some(upload_cleanup) = req.upload_cleanup {
+        let value = serde_json::to_string(&upload_cleanup)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind(yaolist_backend::storage::upload_cleanup::UPLOAD_CLEANUP_KEY)
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    }
+    
+    // Maintenance mode / 维护模式
+    if let Some(maintenance) = req.maintenance {
+        let value = serde_json::to_string(&maintenance)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)"
+        )
+        .bind(yaolist_backend::storage::read_only::MAINTENANCE_KEY)
+        .bind(value)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+        state.storage_manager.maintenance().set(maintenance);
+    }
+    
     // Update download settings cache / 更新下载设置缓存
     if req.proxy_max_speed.is_some() || req.proxy_max_concurrent.is_some() || req.download_domain.is_some() 
-        || req.link_expiry_minutes.is_some() {
+        || req.link_expiry_minutes.is_some() || download_link_rewrites.is_some() || req.download_link_policy.is_some() {
         if let Some(speed) = req.proxy_max_speed {
             state.download_settings.set_max_speed(speed);
         }
@@ -391,6 +681,12 @@ pub async fn update_settings(
         if let Some(expiry) = req.link_expiry_minutes {
             state.download_settings.set_link_expiry_minutes(expiry);
         }
+        if let Some(rewrites) = download_link_rewrites {
+            state.download_settings.set_link_rewrites(rewrites);
+        }
+        if let Some(policy) = req.download_link_policy {
+            state.download_settings.set_link_policy(policy);
+        }
         tracing::info!("Download settings cache updated: expiry={}min", 
             state.download_settings.get_link_expiry_minutes());
     }
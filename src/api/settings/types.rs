@@ -1,4 +1,17 @@
 use serde::{Deserialize, Serialize};
+use yaolist_backend::download::LinkRewriteRule;
+use yaolist_backend::link_policy::LinkPolicyConfig;
+use yaolist_backend::http_security::HttpSecurityConfig;
+use yaolist_backend::compression::CompressionConfig;
+use yaolist_backend::lockout::LockoutPolicy;
+use yaolist_backend::captcha::CaptchaConfig;
+use yaolist_backend::guest::GuestPolicy;
+use yaolist_backend::file_type::FileTypeMap;
+use yaolist_backend::storage::{ListCacheConfig, MaintenanceConfig};
+use yaolist_backend::storage::usage::StorageAlertConfig;
+use yaolist_backend::storage::upload_cleanup::UploadCleanupConfig;
+use yaolist_backend::security_alert::SecurityAlertConfig;
+use yaolist_backend::virus_scan::VirusScanConfig;
 
 /// Site settings update request / 站点设置更新请求
 #[derive(Debug, Deserialize, Serialize)]
@@ -23,6 +36,68 @@ pub struct UpdateSettingsRequest {
     pub download_domain: Option<String>,
     /// Download link expiry in minutes (default 15)
     pub link_expiry_minutes: Option<i32>,
+    /// Per-mount direct link rewrite rules (CDN / worker host)
+    pub download_link_rewrites: Option<Vec<LinkRewriteRule>>,
+    /// When downloads redirect to the provider or are proxied (regions, small files)
+    pub download_link_policy: Option<LinkPolicyConfig>,
+    /// Cross-driver copy buffer size in bytes, 0 or null means default
+    pub copy_buffer_size: Option<usize>,
+    /// Days to keep finished tasks in history, 0 means forever
+    pub task_retention_days: Option<u32>,
+    /// Hours to keep finished tasks in the task list
+    pub task_memory_retention_hours: Option<u32>,
+    /// CORS and security headers
+    pub http_security: Option<HttpSecurityConfig>,
+    /// Gzip / Brotli compression of JSON and text responses
+    pub response_compression: Option<CompressionConfig>,
+    /// Login lockout policy
+    pub login_lockout: Option<LockoutPolicy>,
+    /// Captcha provider, an empty secret_key keeps the saved one
+    pub captcha: Option<CaptchaConfig>,
+    /// Guest browsing, download, search and anonymous WebDAV policy
+    pub guest_policy: Option<GuestPolicy>,
+    /// Extensions of each file type category
+    pub file_types: Option<FileTypeMap>,
+    /// Directory listing cache
+    pub list_cache: Option<ListCacheConfig>,
+    /// Storage usage history and space alert threshold
+    pub storage_alert: Option<StorageAlertConfig>,
+    /// Age after which interrupted uploads are cleaned up
+    pub upload_cleanup: Option<UploadCleanupConfig>,
+    /// Maintenance mode, rejects every file change while enabled
+    pub maintenance: Option<MaintenanceConfig>,
+    /// Failed login, new country and admin change alerts; an empty bot token keeps the saved one
+    pub security_alert: Option<SecurityAlertConfig>,
+    /// clamd scanning of uploads
+    pub virus_scan: Option<VirusScanConfig>,
+}
+
+impl UpdateSettingsRequest {
+    /// Whether any instance-wide setting is changed; workspaces only own the site appearance
+    pub fn has_instance_settings(&self) -> bool {
+        self.preview_encrypted_audio.is_some()
+            || self.proxy_max_speed.is_some()
+            || self.proxy_max_concurrent.is_some()
+            || self.download_domain.is_some()
+            || self.link_expiry_minutes.is_some()
+            || self.download_link_rewrites.is_some()
+            || self.download_link_policy.is_some()
+            || self.copy_buffer_size.is_some()
+            || self.task_retention_days.is_some()
+            || self.task_memory_retention_hours.is_some()
+            || self.http_security.is_some()
+            || self.response_compression.is_some()
+            || self.login_lockout.is_some()
+            || self.captcha.is_some()
+            || self.guest_policy.is_some()
+            || self.file_types.is_some()
+            || self.list_cache.is_some()
+            || self.storage_alert.is_some()
+            || self.upload_cleanup.is_some()
+            || self.maintenance.is_some()
+            || self.security_alert.is_some()
+            || self.virus_scan.is_some()
+    }
 }
 
 /// GeoIP配置请求
@@ -10,7 +10,13 @@ use chrono::Utc;
 use rand::Rng;
 
 use crate::state::AppState;
-use crate::auth::SESSION_COOKIE_NAME;
+use crate::auth::{session_user, SESSION_COOKIE_NAME};
+use crate::api::hooks::fire_file_hook;
+use crate::api::files::{get_user_context, is_preview_only};
+use yaolist_backend::access::Capability;
+use yaolist_backend::file_hook::FileHookEvent;
+use yaolist_backend::share_link;
+use yaolist_backend::workspace;
 use super::types::*;
 
 fn generate_short_id(length: usize) -> String {
@@ -26,19 +32,7 @@ fn generate_short_id(length: usize) -> String {
 
 /// 获取用户ID
 async fn get_user_id(state: &AppState, cookies: &Cookies) -> Option<String> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)?.value().to_string();
-    
-    let result: Option<(String,)> = sqlx::query_as(
-        "SELECT u.id FROM users u 
-         INNER JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .ok()?;
-    
-    result.map(|(id,)| id)
+    session_user(state, cookies).await.ok().flatten().map(|(id, _)| id)
 }
 
 /// GET /api/shares - 获取分享列表
@@ -47,19 +41,8 @@ pub async fn list_shares(
     cookies: Cookies,
     Query(query): Query<ListSharesQuery>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
@@ -75,12 +58,13 @@ pub async fn list_shares(
             "SELECT s.id, s.user_id, s.short_id, s.path, s.name, s.is_dir, s.password, s.expires_at, s.max_access_count, s.access_count, s.enabled, s.created_at, s.updated_at, u.username as creator_name
              FROM shares s
              LEFT JOIN users u ON s.user_id = u.id
-             WHERE s.path LIKE ? OR s.name LIKE ? OR s.short_id LIKE ?
+             WHERE s.workspace_id = ? AND (s.path LIKE ? OR s.name LIKE ? OR s.short_id LIKE ?)
              ORDER BY s.created_at DESC LIMIT ? OFFSET ?"
         } else {
             "SELECT s.id, s.user_id, s.short_id, s.path, s.name, s.is_dir, s.password, s.expires_at, s.max_access_count, s.access_count, s.enabled, s.created_at, s.updated_at, u.username as creator_name
              FROM shares s
              LEFT JOIN users u ON s.user_id = u.id
+             WHERE s.workspace_id = ?
              ORDER BY s.created_at DESC LIMIT ? OFFSET ?"
         }
     } else {
@@ -99,9 +83,11 @@ pub async fn list_shares(
         }
     };
 
+    let workspace = workspace::current();
     let shares: Vec<ShareWithCreator> = if is_admin {
         if let Some(ref pattern) = search_pattern {
             sqlx::query_as(base_query)
+                .bind(workspace.id())
                 .bind(pattern)
                 .bind(pattern)
                 .bind(pattern)
@@ -112,6 +98,7 @@ pub async fn list_shares(
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?
         } else {
             sqlx::query_as(base_query)
+                .bind(workspace.id())
                 .bind(per_page)
                 .bind(offset)
                 .fetch_all(&state.db)
@@ -144,9 +131,9 @@ pub async fn list_shares(
     // 获取总数
     let count_query = if is_admin {
         if search_pattern.is_some() {
-            "SELECT COUNT(*) FROM shares WHERE path LIKE ? OR name LIKE ? OR short_id LIKE ?"
+            "SELECT COUNT(*) FROM shares WHERE workspace_id = ? AND (path LIKE ? OR name LIKE ? OR short_id LIKE ?)"
         } else {
-            "SELECT COUNT(*) FROM shares"
+            "SELECT COUNT(*) FROM shares WHERE workspace_id = ?"
         }
     } else {
         if search_pattern.is_some() {
@@ -159,6 +146,7 @@ pub async fn list_shares(
     let total: i64 = if is_admin {
         if let Some(ref pattern) = search_pattern {
             sqlx::query_scalar(count_query)
+                .bind(workspace.id())
                 .bind(pattern)
                 .bind(pattern)
                 .bind(pattern)
@@ -167,6 +155,7 @@ pub async fn list_shares(
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?
         } else {
             sqlx::query_scalar(count_query)
+                .bind(workspace.id())
                 .fetch_one(&state.db)
                 .await
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?
@@ -226,6 +215,12 @@ pub async fn create_share(
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let user_id = get_user_id(&state, &cookies).await;
     
+    // 分享权限继承自用户组，可按用户覆盖
+    let user_ctx = get_user_context(&state, &cookies).await;
+    if !user_ctx.permissions.is_admin && !user_ctx.permissions.allow_share {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "没有创建分享的权限"}))));
+    }
+    
     let path = req.path.trim();
     if path.is_empty() {
         return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "路径不能为空"}))));
@@ -236,11 +231,47 @@ pub async fn create_share(
     // 检查路径是否为目录（简单检查：路径不含扩展名或以/结尾）
     let is_dir = !name.contains('.') || path.ends_with('/');
     
-    let short_id = generate_short_id(8);
+    // 工作区内的路径映射到其根目录下
+    let workspace = workspace::current();
+    let scoped_path;
+    let path = if workspace.is_default() {
+        path
+    } else {
+        scoped_path = workspace.scope_root(path);
+        scoped_path.as_str()
+    };
+    if !workspace.path_visible(path) {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "路径越权"}))));
+    }
+    // 投递箱等不可读取的路径不能分享
+    if !user_ctx.can(Capability::Read, path) {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "没有读取该路径的权限"}))));
+    }
+    if is_preview_only(&state, &user_ctx, path).await {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "该路径仅允许预览，不能分享"}))));
+    }
+    
+    let short_id = match req.slug.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(slug) => {
+            share_link::validate_slug(slug)
+                .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("短链接无效: {}", e)}))))?;
+            // 短 ID 区分大小写，但仅大小写不同的自定义短链接容易混淆，同样视为已占用
+            let taken: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shares WHERE short_id = ? COLLATE NOCASE")
+                .bind(slug)
+                .fetch_one(&state.db)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+            if taken > 0 {
+                return Err((StatusCode::CONFLICT, Json(json!({"error": "该短链接已被使用"}))));
+            }
+            slug.to_string()
+        }
+        None => generate_short_id(8),
+    };
     let now = Utc::now().to_rfc3339();
     
     sqlx::query(
-        "INSERT INTO shares (user_id, short_id, path, name, is_dir, password, expires_at, max_access_count, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)"
+        "INSERT INTO shares (user_id, short_id, path, name, is_dir, password, expires_at, max_access_count, enabled, created_at, updated_at, workspace_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?, ?)"
     )
     .bind(&user_id)
     .bind(&short_id)
@@ -252,13 +283,25 @@ pub async fn create_share(
     .bind(&req.max_access_count)
     .bind(&now)
     .bind(&now)
+    .bind(workspace.id())
     .execute(&state.db)
     .await
     .map_err(|e| {
+        // 并发创建同一自定义短链接时由唯一约束兜底
+        if e.as_database_error().is_some_and(|d| d.is_unique_violation()) {
+            return (StatusCode::CONFLICT, Json(json!({"error": "该短链接已被使用"})));
+        }
         tracing::error!("Failed to create share: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "创建分享失败"})))
     })?;
     
+    fire_file_hook(&state, FileHookEvent::ShareCreate, path, user_id, json!({
+        "short_id": short_id,
+        "is_dir": is_dir,
+        "has_password": req.password.is_some(),
+        "expires_at": req.expires_at,
+    }));
+    
     Ok(Json(json!({
         "code": 200,
         "message": "success",
@@ -276,27 +319,17 @@ pub async fn update_share(
     Path(id): Path<i64>,
     Json(req): Json<UpdateShareRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
     // 检查分享是否存在且属于当前用户
     let share_user_id: Option<Option<String>> = sqlx::query_scalar(
-        "SELECT user_id FROM shares WHERE id = ?"
+        "SELECT user_id FROM shares WHERE id = ? AND workspace_id = ?"
     )
     .bind(id)
+    .bind(workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
@@ -340,27 +373,17 @@ pub async fn delete_share(
     cookies: Cookies,
     Path(id): Path<i64>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
     // 检查分享是否存在且属于当前用户
     let share_user_id: Option<Option<String>> = sqlx::query_scalar(
-        "SELECT user_id FROM shares WHERE id = ?"
+        "SELECT user_id FROM shares WHERE id = ? AND workspace_id = ?"
     )
     .bind(id)
+    .bind(workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
@@ -389,27 +412,17 @@ pub async fn toggle_share(
     cookies: Cookies,
     Path(id): Path<i64>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let user: Option<(String, bool)> = sqlx::query_as(
-        "SELECT u.id, u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     
     let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
     
     // 检查分享是否存在且属于当前用户
     let share: Option<(Option<String>, bool)> = sqlx::query_as(
-        "SELECT user_id, enabled FROM shares WHERE id = ?"
+        "SELECT user_id, enabled FROM shares WHERE id = ? AND workspace_id = ?"
     )
     .bind(id)
+    .bind(workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
@@ -437,3 +450,147 @@ pub async fn toggle_share(
         "enabled": new_enabled
     })))
 }
+
+/// GET /api/shares/:id/link - 获取分享的公开地址及其二维码（PNG，data URI）
+pub async fn get_share_link(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let user = session_user(&state, &cookies).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    
+    let (user_id, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
+    
+    let share: Option<(Option<String>, String)> = sqlx::query_as(
+        "SELECT user_id, short_id FROM shares WHERE id = ? AND workspace_id = ?"
+    )
+    .bind(id)
+    .bind(workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    
+    let (share_user_id, short_id) = share.ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "分享不存在"}))))?;
+    
+    if !is_admin && share_user_id.as_ref() != Some(&user_id) {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "无权操作此分享"}))));
+    }
+    
+    let url = share_link::share_url(&crate::api::branding::site_base(&state, &headers), &short_id);
+    let png = share_link::qr_png(&url)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("生成二维码失败: {}", e)}))))?;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": "success",
+        "data": {
+            "short_id": short_id,
+            "url": url,
+            "qr_code": format!("data:image/png;base64,{}", base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png))
+        }
+    })))
+}
+
+/// 分享邀请最多收件人数
+const MAX_INVITE_RECIPIENTS: usize = 20;
+
+/// POST /api/shares/:id/invite - 通过邮件邀请他人访问分享
+pub async fn invite_share(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<i64>,
+    Json(req): Json<InviteShareRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let session_id = cookies.get(SESSION_COOKIE_NAME)
+        .map(|c| c.value().to_string())
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
+    
+    let user: Option<(String, String, bool)> = sqlx::query_as(
+        "SELECT u.id, u.username, u.is_admin FROM users u 
+         JOIN sessions s ON u.id = s.user_id 
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')"
+    )
+    .bind(&session_id)
+    .bind(workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    
+    let (user_id, username, is_admin) = user.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "会话已过期"}))))?;
+    
+    let share: Option<Share> = sqlx::query_as(
+        "SELECT id, user_id, short_id, path, name, is_dir, password, expires_at, max_access_count, access_count, enabled, created_at, updated_at
+         FROM shares WHERE id = ? AND workspace_id = ?"
+    )
+    .bind(id)
+    .bind(workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    
+    let share = share.ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "分享不存在"}))))?;
+    
+    if !is_admin && share.user_id.as_ref() != Some(&user_id) {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "无权操作此分享"}))));
+    }
+    if !share.enabled {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "分享已被禁用"}))));
+    }
+    
+    let emails: Vec<String> = req.emails.iter()
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .collect();
+    if emails.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "收件人不能为空"}))));
+    }
+    if emails.len() > MAX_INVITE_RECIPIENTS {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": format!("收件人最多{}个", MAX_INVITE_RECIPIENTS)}))));
+    }
+    
+    // 邮件中的链接不能取自请求头，否则可伪造域名借站点名义发出钓鱼链接
+    let origin = state.download_settings.site_origin()
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"error": "请先在站点设置中配置下载域名，邮件中的分享链接需要完整地址"}))))?;
+    
+    let settings = crate::api::notification::load_notification_settings(&state).await;
+    
+    let share_url = share_link::share_url(&format!("{}{}", origin, crate::api::branding::workspace_base()), &share.short_id);
+    
+    let vars = json!({
+        "inviter": username,
+        "share_name": share.name,
+        "share_url": share_url,
+        "password": if req.include_password { share.password.clone() } else { None },
+        "expires_at": share.expires_at,
+        "message": req.message
+    });
+    
+    let mut sent = 0;
+    let mut failed = Vec::new();
+    for email in &emails {
+        match crate::api::notification::send_event_email(
+            &state, &settings, yaolist_backend::email_template::EmailEvent::ShareInvitation,
+            req.locale.as_deref(), email, vars.clone(),
+        ).await {
+            Ok(true) => sent += 1,
+            Ok(false) => {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "分享邀请邮件已禁用"}))));
+            }
+            Err(e) => {
+                tracing::warn!("Failed to send share invitation to {}: {}", email, e);
+                failed.push(json!({ "email": email, "error": e }));
+            }
+        }
+    }
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已发送 {} 封邀请邮件", sent),
+        "data": {
+            "sent": sent,
+            "failed": failed
+        }
+    })))
+}
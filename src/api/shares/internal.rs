@@ -0,0 +1,284 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::state::AppState;
+use crate::api::files::{get_user_context, get_user_id};
+use crate::api::file_resolver::{calculate_internal_path, get_matching_mounts, get_user_mounts, UserContext};
+use yaolist_backend::access::Capability;
+use yaolist_backend::internal_share::{self, ShareAccess, TargetType, SHARED_ROOT};
+use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
+use yaolist_backend::workspace;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInternalShareRequest {
+    /// 用户看到的路径
+    pub path: String,
+    /// user 或 group
+    pub target_type: String,
+    /// 用户ID或用户组ID
+    pub target_id: String,
+    /// read（默认）或 read_write
+    pub access: Option<String>,
+    /// 接收者看到的名称，默认使用文件名
+    pub name: Option<String>,
+}
+
+/// internal_shares 列表行：id, path, name, is_dir, target_type, target_id, access, created_at, 创建者, 接收者名称
+type InternalShareRow = (String, String, String, bool, String, String, String, String, Option<String>, Option<String>);
+
+/// 路径是否为目录（挂载点及其上级目录视为目录），路径不存在时返回 None
+async fn item_is_dir(state: &AppState, user_ctx: &UserContext, path: &str) -> Option<bool> {
+    let mounts = get_user_mounts(state, user_ctx).await.ok()?;
+    if mounts.iter().any(|m| is_sub_path(path, &m.mount_path)) {
+        return Some(true);
+    }
+    let (parent, name) = path.rsplit_once('/')?;
+    let parent = fix_and_clean_path(parent);
+    for mount in get_matching_mounts(&parent, &mounts) {
+        let Some(driver) = state.storage_manager.get_driver(&mount.id).await else {
+            continue;
+        };
+        if let Ok(entries) = driver.list(&calculate_internal_path(&mount.mount_path, &parent)).await {
+            if let Some(entry) = entries.into_iter().find(|e| e.name == name) {
+                return Some(entry.is_dir);
+            }
+        }
+    }
+    None
+}
+
+/// GET /api/internal-shares - 获取自己创建的站内分享（管理员获取全部）
+pub async fn list_internal_shares(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let user_id = get_user_id(&state, &cookies).await
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
+    let user_ctx = get_user_context(&state, &cookies).await;
+
+    let rows: Vec<InternalShareRow> = sqlx::query_as(
+        "SELECT s.id, s.path, s.name, s.is_dir, s.target_type, s.target_id, s.access, s.created_at, o.username,
+                CASE s.target_type WHEN 'user' THEN tu.username ELSE g.name END
+         FROM internal_shares s
+         LEFT JOIN users o ON o.id = s.owner_id
+         LEFT JOIN users tu ON s.target_type = 'user' AND tu.id = s.target_id
+         LEFT JOIN user_groups g ON s.target_type = 'group' AND CAST(g.id AS TEXT) = s.target_id
+         WHERE s.workspace_id = ? AND (? OR s.owner_id = ?)
+         ORDER BY s.created_at DESC"
+    )
+    .bind(workspace::current().id())
+    .bind(user_ctx.permissions.is_admin)
+    .bind(&user_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+
+    let shares: Vec<Value> = rows.into_iter()
+        .map(|(id, path, name, is_dir, target_type, target_id, access, created_at, creator_name, target_name)| json!({
+            "id": id,
+            "path": path,
+            "name": name,
+            "is_dir": is_dir,
+            "target_type": target_type,
+            "target_id": target_id,
+            "target_name": target_name,
+            "access": access,
+            "created_at": created_at,
+            "creator_name": creator_name
+        }))
+        .collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": shares
+    })))
+}
+
+/// GET /api/internal-shares/received - 获取分享给自己的项目（位于“/Shared with me”下）
+pub async fn list_received_shares(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let user_ctx = get_user_context(&state, &cookies).await;
+    if user_ctx.is_guest {
+        return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))));
+    }
+
+    // 只返回接收者看到的路径，不暴露被分享项目的存储路径
+    let items: Vec<Value> = user_ctx.shared.iter()
+        .map(|item| json!({
+            "name": item.name,
+            "path": format!("{}/{}", SHARED_ROOT, item.name),
+            "is_dir": item.is_dir,
+            "access": item.access
+        }))
+        .collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": items
+    })))
+}
+
+/// POST /api/internal-shares - 将文件或目录分享给指定用户或用户组（同一接收者重复分享时更新权限与名称）
+pub async fn create_internal_share(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<CreateInternalShareRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let user_id = get_user_id(&state, &cookies).await
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
+
+    // 分享权限继承自用户组，可按用户覆盖
+    let user_ctx = get_user_context(&state, &cookies).await;
+    if !user_ctx.permissions.is_admin && !user_ctx.permissions.allow_share {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "没有创建分享的权限"}))));
+    }
+
+    let target_type = TargetType::parse(&req.target_type)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"error": "接收者类型只能是 user 或 group"}))))?;
+    let access = match req.access.as_deref() {
+        None => ShareAccess::Read,
+        Some(a) => ShareAccess::parse(a)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"error": "权限只能是 read 或 read_write"}))))?,
+    };
+
+    let path = user_ctx.join_path(req.path.trim())
+        .map_err(|e| (StatusCode::FORBIDDEN, Json(json!({"error": e}))))?;
+    if path == "/" {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "不能分享根目录"}))));
+    }
+    // 不能授予超出自己的权限
+    if !user_ctx.can(Capability::Read, &path) {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "没有读取该路径的权限"}))));
+    }
+    let is_dir = item_is_dir(&state, &user_ctx, &path).await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "文件不存在"}))))?;
+    // 读写分享授予的每一项权限（见 SharedItem::allows）创建者都必须拥有
+    if access == ShareAccess::ReadWrite {
+        let granted = [Capability::Rename, Capability::Move, Capability::Copy, Capability::Delete];
+        let upload = is_dir.then_some(Capability::Upload);
+        if upload.into_iter().chain(granted).any(|c| !user_ctx.can(c, &path)) {
+            return Err((StatusCode::FORBIDDEN, Json(json!({"error": "没有写入、重命名、移动、复制或删除该路径的权限，只能只读分享"}))));
+        }
+    }
+
+    let name = req.name.as_deref().map(str::trim).filter(|n| !n.is_empty())
+        .unwrap_or_else(|| path.rsplit('/').next().unwrap_or(""))
+        .to_string();
+    internal_share::validate_name(&name)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("名称无效: {}", e)}))))?;
+
+    // 接收者必须属于当前工作区（用户组需有当前工作区的成员）
+    let workspace = workspace::current();
+    let target_exists: i64 = match target_type {
+        TargetType::User => sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id = ? AND workspace_id = ?"),
+        TargetType::Group => sqlx::query_scalar(
+            "SELECT COUNT(*) FROM user_groups g WHERE CAST(g.id AS TEXT) = ? AND EXISTS (
+                SELECT 1 FROM user_group_members m JOIN users u ON u.id = m.user_id
+                WHERE m.group_id = CAST(g.id AS TEXT) AND u.workspace_id = ?)"
+        ),
+    }
+    .bind(&req.target_id)
+    .bind(workspace.id())
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    if target_exists == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "接收者不存在"}))));
+    }
+    if target_type == TargetType::User && req.target_id == user_id {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "不能分享给自己"}))));
+    }
+
+    let existing: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM internal_shares WHERE owner_id = ? AND path = ? AND target_type = ? AND target_id = ? AND workspace_id = ?"
+    )
+    .bind(&user_id)
+    .bind(&path)
+    .bind(target_type.as_str())
+    .bind(&req.target_id)
+    .bind(workspace.id())
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+
+    let id = existing.unwrap_or_else(|| Uuid::new_v4().to_string());
+    sqlx::query(
+        "INSERT INTO internal_shares (id, owner_id, path, name, is_dir, target_type, target_id, access, workspace_id, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, is_dir = excluded.is_dir, access = excluded.access"
+    )
+    .bind(&id)
+    .bind(&user_id)
+    .bind(&path)
+    .bind(&name)
+    .bind(is_dir)
+    .bind(target_type.as_str())
+    .bind(&req.target_id)
+    .bind(access.as_str())
+    .bind(workspace.id())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create internal share: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "创建分享失败"})))
+    })?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "分享成功",
+        "data": {
+            "id": id,
+            "name": name,
+            "is_dir": is_dir,
+            "access": access
+        }
+    })))
+}
+
+/// POST /api/internal-shares/:id/delete - 取消站内分享
+pub async fn delete_internal_share(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let user_id = get_user_id(&state, &cookies).await
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
+    let user_ctx = get_user_context(&state, &cookies).await;
+
+    let owner_id: Option<String> = sqlx::query_scalar(
+        "SELECT owner_id FROM internal_shares WHERE id = ? AND workspace_id = ?"
+    )
+    .bind(&id)
+    .bind(workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+
+    let owner_id = owner_id.ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "分享不存在"}))))?;
+    if !user_ctx.permissions.is_admin && owner_id != user_id {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "无权删除此分享"}))));
+    }
+
+    sqlx::query("DELETE FROM internal_shares WHERE id = ?")
+        .bind(&id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
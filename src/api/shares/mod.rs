@@ -1,6 +1,8 @@
 pub mod types;
 pub mod admin;
 pub mod public;
+pub mod internal;
 
 pub use admin::*;
 pub use public::*;
+pub use internal::*;
@@ -4,13 +4,21 @@ use axum::{
     Json,
 };
 use serde_json::{json, Value};
+use std::collections::HashSet;
 use std::sync::Arc;
 use chrono::Utc;
 use serde::Deserialize;
+use yaolist_backend::client_ip::ClientIp;
 
 use crate::state::AppState;
-use crate::api::file_resolver::{get_all_mounts, get_matching_mounts, get_first_mount};
-use crate::api::files::create_download_token_with_user;
+use crate::api::file_resolver::{calculate_internal_path, get_all_mounts, get_matching_mounts, get_first_mount};
+use crate::api::files::{
+    create_download_token_with_user, decide_download_link, get_header, get_hide_rules, get_nearest_meta,
+    get_readme, get_virtual_files_by_path, path_preview_only, path_watermarked,
+};
+use yaolist_backend::share_link::{breadcrumbs, normalize_sub_path};
+use yaolist_backend::storage::{Cursor, Entry, ListSort};
+use yaolist_backend::utils::fix_and_clean_path;
 use super::types::*;
 use rand::Rng;
 
@@ -39,6 +47,8 @@ pub struct ShareFileRequest {
     pub per_page: Option<i64>,
     pub sort_by: Option<String>,    // name, modified, size
     pub sort_order: Option<String>, // asc, desc
+    /// 上一页返回的 next_cursor，与 fs_list 相同
+    pub cursor: Option<String>,
 }
 
 /// GET /api/s/:short_id/info - 获取分享信息（公开）
@@ -50,9 +60,10 @@ pub async fn get_share_info(
         "SELECT s.id, s.user_id, s.short_id, s.path, s.name, s.is_dir, s.password, s.expires_at, s.max_access_count, s.access_count, s.enabled, s.created_at, s.updated_at, u.username as creator_name
          FROM shares s
          LEFT JOIN users u ON s.user_id = u.id
-         WHERE s.short_id = ?"
+         WHERE s.short_id = ? AND s.workspace_id = ?"
     )
     .bind(&short_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
@@ -109,9 +120,10 @@ pub async fn verify_share(
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let share: Option<Share> = sqlx::query_as(
         "SELECT id, user_id, short_id, path, name, is_dir, password, expires_at, max_access_count, access_count, enabled, created_at, updated_at
-         FROM shares WHERE short_id = ?"
+         FROM shares WHERE short_id = ? AND workspace_id = ?"
     )
     .bind(&short_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
@@ -159,9 +171,10 @@ pub async fn get_share_files(
     
     let share: Option<Share> = sqlx::query_as(
         "SELECT id, user_id, short_id, path, name, is_dir, password, expires_at, max_access_count, access_count, enabled, created_at, updated_at
-         FROM shares WHERE short_id = ?"
+         FROM shares WHERE short_id = ? AND workspace_id = ?"
     )
     .bind(&short_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|e| {
@@ -209,11 +222,7 @@ pub async fn get_share_files(
         })?;
         
         let mount_path = yaolist_backend::utils::fix_and_clean_path(&mount.mount_path);
-        let relative_parent = if parent_path.len() > mount_path.len() {
-            yaolist_backend::utils::fix_and_clean_path(&parent_path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let relative_parent = calculate_internal_path(&mount_path, &parent_path);
         
         let driver = state.storage_manager.get_driver(&mount.id).await
             .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, Json(json!({"code": "DRIVER_ERROR", "message": "存储驱动故障"}))))?;
@@ -252,121 +261,148 @@ pub async fn get_share_files(
         })));
     }
     
-    // 目录分享：构建实际路径
-    let actual_path = if let Some(ref sub) = req.sub_path {
-        let sub = sub.trim_start_matches('/');
-        if sub.is_empty() {
-            base_path.clone()
-        } else {
-            format!("{}/{}", base_path, sub)
-        }
-    } else {
-        base_path.clone()
-    };
-    
-    // 安全检查：确保不能访问分享路径之外的文件
-    let actual_clean = yaolist_backend::utils::fix_and_clean_path(&actual_path);
-    if !actual_clean.starts_with(&base_path) && actual_clean != base_path {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"code": "FORBIDDEN", "message": "无权访问此路径"}))));
+    // 目录分享：子路径相对分享目录规范化，`..` 无法越出分享目录
+    let sub_path = normalize_sub_path(req.sub_path.as_deref().unwrap_or_default());
+    let actual_clean = fix_and_clean_path(&format!("{}{}", base_path, sub_path));
+    if share_path_hidden(&state, &base_path, &sub_path).await {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"code": "NOT_FOUND", "message": "目录不存在"}))));
     }
     
     tracing::debug!("get_share_files: 目录分享模式, actual_clean={}", actual_clean);
-    let mount = get_first_mount(&actual_clean, &mounts).ok_or_else(|| {
+    let matching_mounts = get_matching_mounts(&actual_clean, &mounts);
+    let virtual_dirs = get_virtual_files_by_path(&actual_clean, &mounts);
+    if matching_mounts.is_empty() && virtual_dirs.is_empty() {
         tracing::error!("get_share_files: 目录分享未找到驱动 actual_clean={}", actual_clean);
-        (StatusCode::SERVICE_UNAVAILABLE, Json(json!({"code": "DRIVER_ERROR", "message": "存储驱动故障"})))
-    })?;
+        return Err((StatusCode::SERVICE_UNAVAILABLE, Json(json!({"code": "DRIVER_ERROR", "message": "存储驱动故障"}))));
+    }
     
-    let mount_path = yaolist_backend::utils::fix_and_clean_path(&mount.mount_path);
-    let relative_path = if actual_clean.len() > mount_path.len() {
-        yaolist_backend::utils::fix_and_clean_path(&actual_clean[mount_path.len()..])
-    } else {
-        "/".to_string()
+    // 与主文件列表相同的排序与游标
+    let order = ListSort::parse(req.sort_by.as_deref(), req.sort_order.as_deref());
+    let cursor = match req.cursor.as_deref().filter(|c| !c.is_empty()) {
+        Some(c) => Some(Cursor::decode(c, order)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({"code": "BAD_REQUEST", "message": "无效的游标或排序方式已改变"}))))?),
+        None => None,
     };
     
-    let driver = state.storage_manager.get_driver(&mount.id).await
-        .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, Json(json!({"code": "DRIVER_ERROR", "message": "存储驱动故障"}))))?;
-    
-    let mut entries = driver.list(&relative_path).await
-        .map_err(|e| {
-            tracing::error!("Share directory listing failed: {}", e);
-            (StatusCode::SERVICE_UNAVAILABLE, Json(json!({"code": "DRIVER_ERROR", "message": "存储驱动故障"})))
-        })?;
-    
-    // 排序处理：目录始终在前，然后按指定字段排序
-    let sort_by = req.sort_by.as_deref().unwrap_or("name");
-    let sort_order = req.sort_order.as_deref().unwrap_or("asc");
-    let is_desc = sort_order == "desc";
+    let mut listings: Vec<Arc<Vec<Entry>>> = Vec::new();
+    let mut failed = false;
+    for mount in &matching_mounts {
+        let Some(driver) = state.storage_manager.get_driver(&mount.id).await else {
+            continue;
+        };
+        let relative_path = calculate_internal_path(&mount.mount_path, &actual_clean);
+        match state.storage_manager.list_cache().sorted_list(&mount.id, driver.as_ref().as_ref(), &relative_path, order).await {
+            Ok(entries) => listings.push(entries),
+            Err(e) => {
+                tracing::error!("Share directory listing failed: {}", e);
+                failed = true;
+            }
+        }
+    }
+    if listings.is_empty() && failed {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, Json(json!({"code": "DRIVER_ERROR", "message": "存储驱动故障"}))));
+    }
     
-    entries.sort_by(|a, b| {
-        // 目录始终在前
-        if a.is_dir && !b.is_dir {
-            return std::cmp::Ordering::Less;
+    // 合并多个驱动与挂载在其下的虚拟目录，同名只保留第一个
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut entries: Vec<Entry> = Vec::new();
+    for entry in listings.iter().flat_map(|l| l.iter()) {
+        if seen.insert(entry.name.clone()) {
+            entries.push(entry.clone());
         }
-        if !a.is_dir && b.is_dir {
-            return std::cmp::Ordering::Greater;
+    }
+    for vf in &virtual_dirs {
+        if let Some(name) = vf.get("name").and_then(|n| n.as_str()) {
+            if seen.insert(name.to_string()) {
+                entries.push(Entry { name: name.to_string(), path: String::new(), is_dir: true, size: 0, modified: None });
+            }
         }
-        
-        let cmp = match sort_by {
-            "modified" => a.modified.cmp(&b.modified),
-            "size" => a.size.cmp(&b.size),
-            // 使用自然排序（让 "2" 排在 "10" 前面）
-            _ => natord::compare_ignore_case(&a.name, &b.name),
-        };
-        
-        if is_desc { cmp.reverse() } else { cmp }
-    });
+    }
+    if listings.len() != 1 || !virtual_dirs.is_empty() {
+        order.sort(&mut entries);
+    }
+    
+    // 访客不能查看隐藏文件
+    let meta = get_nearest_meta(&state, &actual_clean).await;
+    let hide_rules = get_hide_rules(meta.as_ref(), &actual_clean);
+    let visible: Vec<&Entry> = entries.iter().filter(|e| !hide_rules.hides(&e.name)).collect();
+    let folder_count = visible.iter().filter(|e| e.is_dir).count();
+    let file_count = visible.len() - folder_count;
     
-    // 分页处理
-    let total = entries.len();
+    // 分页处理：带游标时从游标之后开始，否则按页码
+    let total = visible.len();
     let page = req.page.unwrap_or(1).max(1) as usize;
-    let per_page = req.per_page.unwrap_or(50).clamp(1, 100) as usize;
-    let start = (page - 1) * per_page;
+    let max_per_page = yaolist_backend::config::config().limits.max_page_size as i64;
+    let per_page = req.per_page.unwrap_or(50).clamp(1, max_per_page) as usize;
+    let start = match &cursor {
+        Some(c) => c.position(&visible, |e| *e),
+        None => (page - 1).saturating_mul(per_page),
+    }.min(total);
     let end = (start + per_page).min(total);
+    let next_cursor = (end > start && end < total).then(|| Cursor::after(order, visible[end - 1]).encode());
     
-    let paged_entries = if start < total {
-        &entries[start..end]
-    } else {
-        &entries[0..0]
-    };
-    
-    let files: Vec<Value> = paged_entries.iter().map(|e| {
+    let files: Vec<Value> = visible[start..end].iter().map(|e| {
         json!({
             "name": e.name,
             "size": e.size,
             "is_dir": e.is_dir,
+            "type": state.file_types.classify(&e.name, e.is_dir),
             "modified": e.modified
         })
     }).collect();
     
-    // 返回所有文件名用于全选
-    let all_names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+    // 返回所有文件名用于全选（按游标翻页时省略）
+    let all_names: Option<Vec<&str>> = cursor.is_none()
+        .then(|| visible.iter().map(|e| e.name.as_str()).collect());
     
     Ok(Json(json!({
         "code": 200,
         "data": {
             "files": files,
             "total": total,
+            "folder_count": folder_count,
+            "file_count": file_count,
             "page": page,
             "per_page": per_page,
-            "path": req.sub_path.unwrap_or_default(),
+            "next_cursor": next_cursor,
+            "path": sub_path,
+            "breadcrumbs": breadcrumbs(&share.name, &sub_path),
+            "readme": get_readme(meta.as_ref(), &actual_clean),
+            "header": get_header(meta.as_ref(), &actual_clean),
             "all_names": all_names
         }
     })))
 }
 
+/// 子路径中是否有被元信息隐藏的目录（访客不能通过拼接路径进入隐藏目录）
+async fn share_path_hidden(state: &AppState, base_path: &str, sub_path: &str) -> bool {
+    let mut dir = base_path.trim_end_matches('/').to_string();
+    for segment in sub_path.split('/').filter(|s| !s.is_empty()) {
+        let parent = if dir.is_empty() { "/".to_string() } else { dir.clone() };
+        let meta = get_nearest_meta(state, &parent).await;
+        if get_hide_rules(meta.as_ref(), &parent).hides(segment) {
+            return true;
+        }
+        dir = format!("{}/{}", dir, segment);
+    }
+    false
+}
+
 /// GET /api/share/:short_id/download/:filename - 生成临时下载链接
 /// Generate temporary download link for shared file
 pub async fn get_share_download(
     State(state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
     headers: axum::http::HeaderMap,
     Path((short_id, filename)): Path<(String, String)>,
     Query(query): Query<ShareFileRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let share: Option<Share> = sqlx::query_as(
         "SELECT id, user_id, short_id, path, name, is_dir, password, expires_at, max_access_count, access_count, enabled, created_at, updated_at
-         FROM shares WHERE short_id = ?"
+         FROM shares WHERE short_id = ? AND workspace_id = ?"
     )
     .bind(&short_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
@@ -409,19 +445,27 @@ pub async fn get_share_download(
         .await
         .ok();
     
+    state.webhooks.emit(yaolist_backend::webhook::WebhookEvent::ShareAccessed, json!({
+        "short_id": share.short_id,
+        "name": share.name,
+        "path": share.path,
+        "file": filename,
+        "access_count": share.access_count + 1,
+        "ip": client_ip.to_string()
+    }));
+    
     // 安全检查：验证文件名是否在分享范围内
-    let base_path = yaolist_backend::utils::fix_and_clean_path(&share.path);
-    let file_path = if share.is_dir {
-        if let Some(ref sub) = query.sub_path {
-            let sub = sub.trim_start_matches('/');
-            if sub.is_empty() {
-                format!("{}/{}", base_path, filename)
-            } else {
-                format!("{}/{}/{}", base_path, sub, filename)
-            }
-        } else {
-            format!("{}/{}", base_path, filename)
+    let base_path = fix_and_clean_path(&share.path);
+    let file_path_clean = if share.is_dir {
+        if filename.is_empty() || filename == "." || filename == ".." || filename.contains(['/', '\\']) {
+            return Err((StatusCode::FORBIDDEN, Json(json!({"code": "FORBIDDEN", "message": "无权下载此文件"}))));
+        }
+        let sub_path = normalize_sub_path(&format!("{}/{}", query.sub_path.as_deref().unwrap_or_default(), filename));
+        // 隐藏的文件与目录对访客不可下载
+        if share_path_hidden(&state, &base_path, &sub_path).await {
+            return Err((StatusCode::NOT_FOUND, Json(json!({"code": "FILE_NOT_FOUND", "message": "文件不存在"}))));
         }
+        fix_and_clean_path(&format!("{}{}", base_path, sub_path))
     } else {
         // 单文件分享，只能下载这个文件
         if filename != share.name {
@@ -430,12 +474,6 @@ pub async fn get_share_download(
         base_path.clone()
     };
     
-    // 安全检查：确保路径在分享范围内
-    let file_path_clean = yaolist_backend::utils::fix_and_clean_path(&file_path);
-    if share.is_dir && !file_path_clean.starts_with(&base_path) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"code": "FORBIDDEN", "message": "无权下载此文件"}))));
-    }
-    
     // Get scheme from X-Forwarded-Proto header / 从反代请求头获取协议
     let scheme = headers.get("x-forwarded-proto")
         .and_then(|v| v.to_str().ok())
@@ -462,11 +500,7 @@ pub async fn get_share_download(
     
     for mount in &matching_mounts {
         let mount_path = yaolist_backend::utils::fix_and_clean_path(&mount.mount_path);
-        let actual_parent = if parent_path.len() > mount_path.len() {
-            yaolist_backend::utils::fix_and_clean_path(&parent_path[mount_path.len()..])
-        } else {
-            "/".to_string()
-        };
+        let actual_parent = calculate_internal_path(&mount_path, parent_path);
         
         if let Some(driver) = state.storage_manager.get_driver(&mount.id).await {
             if let Ok(files) = driver.list(&actual_parent).await {
@@ -475,8 +509,7 @@ pub async fn get_share_download(
                     found_internal_path = yaolist_backend::utils::fix_and_clean_path(&found_internal_path);
                     found_driver_id = Some(mount.id.clone());
                     found_file_size = Some(file.size);
-                    // Let fs_download decide if direct link is available / 让 fs_download 决定是否使用直链
-                    can_direct_link = true;
+                    can_direct_link = driver.capabilities().can_direct_link;
                     break;
                 }
             }
@@ -486,6 +519,17 @@ pub async fn get_share_download(
     let driver_id = found_driver_id.ok_or_else(|| 
         (StatusCode::NOT_FOUND, Json(json!({"code": "FILE_NOT_FOUND", "message": "文件不存在"}))))?;
     
+    // 仅预览的文件只能内联预览，始终本地中转
+    let preview_only = path_preview_only(&state, &file_path_clean).await;
+    // 需要水印的文件以分享短链接标识来源
+    let watermark = path_watermarked(&state, &file_path_clean).await
+        .then(|| format!("share {}", share.short_id));
+    
+    // Redirect or proxy per the direct link policy / 按直链策略决定302或中转
+    let can_direct_link = decide_download_link(
+        &state, &driver_id, can_direct_link && !preview_only && watermark.is_none(), Some(client_ip), found_file_size, false,
+    ).await.is_redirect();
+    
     // Use configured link expiry / 使用配置的链接有效期
     let expiry_minutes = state.download_settings.get_link_expiry_minutes() as i64;
     let expires_at = Utc::now() + chrono::Duration::minutes(expiry_minutes);
@@ -493,12 +537,15 @@ pub async fn get_share_download(
     // Create download token with user_id for traffic stats / 创建带用户ID的下载令牌（用于流量统计）
     // 流量统计在实际下载时进行：302统计整个文件，本地中转统计实际传输
     let token = create_download_token_with_user(
+        &state,
         found_internal_path,
         driver_id,
         expires_at,
         can_direct_link,
         found_file_size,
         share.user_id.clone(),
+        preview_only,
+        watermark,
     ).await;
     
     // Build download URL with configured domain / 使用配置的下载域名生成下载链接
@@ -509,7 +556,8 @@ pub async fn get_share_download(
         "code": 200,
         "data": {
             "url": download_url,
-            "expires_at": expires_at.to_rfc3339()
+            "expires_at": expires_at.to_rfc3339(),
+            "preview_only": preview_only
         }
     })))
 }
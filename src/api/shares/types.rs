@@ -41,6 +41,19 @@ pub struct CreateShareRequest {
     pub password: Option<String>,
     pub expires_at: Option<String>,
     pub max_access_count: Option<i64>,
+    /// 自定义短 ID（个性化短链接），为空时随机生成
+    pub slug: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteShareRequest {
+    pub emails: Vec<String>,
+    pub message: Option<String>,
+    /// 是否在邮件中附带提取码
+    #[serde(default)]
+    pub include_password: bool,
+    /// 邮件语言，为空使用默认语言
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
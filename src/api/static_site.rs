@@ -0,0 +1,182 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::static_site::{render_index, SiteDir, SiteFile};
+use yaolist_backend::strm::{normalize_base_url, strm_content, DriverStrmSink, StrmSink};
+use yaolist_backend::utils::{fix_and_clean_path, is_sub_path};
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+use crate::api::file_resolver::get_all_mounts;
+use crate::api::files::{can_access_password, get_hide_rules, get_nearest_meta, get_nearest_password_meta};
+use crate::api::fsck::check_control;
+use crate::api::strm::{ensure_direct_link, load_permanent_links, resolve_mount};
+use crate::task::{Task, TaskControl, TaskType};
+
+/// 遍历源目录，为每个文件创建永久直链；隐藏文件与带密码的子目录不导出
+async fn collect_tree(
+    state: &AppState,
+    control: &TaskControl,
+    source: &str,
+    base_url: &str,
+) -> Result<BTreeMap<String, SiteDir>, String> {
+    let mounts = get_all_mounts(state).await.map_err(|e| format!("获取挂载列表失败: {}", e))?;
+    let (mount, internal_root) = resolve_mount(source, &mounts)
+        .ok_or_else(|| format!("源目录不在任何挂载下: {}", source))?;
+    let driver = state.storage_manager.get_driver(&mount.id).await
+        .ok_or_else(|| format!("驱动未加载: {}", mount.id))?;
+    let mut signs = load_permanent_links(state).await;
+
+    let mut dirs = BTreeMap::new();
+    // (相对路径, 虚拟路径, 驱动内路径)
+    let mut stack = vec![(String::new(), source.to_string(), internal_root)];
+    while let Some((rel_dir, virtual_dir, internal_dir)) = stack.pop() {
+        check_control(control).await?;
+        let entries = driver.list(&internal_dir).await
+            .map_err(|e| format!("列出目录失败 {}: {}", virtual_dir, e))?;
+        let meta = get_nearest_meta(state, &virtual_dir).await;
+        let hide_rules = get_hide_rules(meta.as_ref(), &virtual_dir);
+
+        let mut dir = SiteDir::new(&rel_dir);
+        for entry in entries {
+            if hide_rules.hides(&entry.name) {
+                continue;
+            }
+            let virtual_path = format!("{}/{}", virtual_dir.trim_end_matches('/'), entry.name);
+            if entry.is_dir {
+                let password_meta = get_nearest_password_meta(state, &virtual_path).await;
+                if !can_access_password(password_meta.as_ref(), &virtual_path, "") {
+                    continue;
+                }
+                let rel = if rel_dir.is_empty() { entry.name.clone() } else { format!("{}/{}", rel_dir, entry.name) };
+                let internal_path = format!("{}/{}", internal_dir.trim_end_matches('/'), entry.name);
+                dir.dirs.push(entry.name);
+                stack.push((rel, virtual_path, internal_path));
+            } else {
+                let sign = ensure_direct_link(state, &mut signs, &virtual_path, &entry.name).await?;
+                dir.files.push(SiteFile {
+                    url: strm_content(base_url, &sign, &entry.name),
+                    name: entry.name,
+                    size: entry.size,
+                    modified: entry.modified,
+                });
+            }
+        }
+        dirs.insert(rel_dir, dir);
+    }
+    Ok(dirs)
+}
+
+/// 执行一次静态站点导出，返回写入的页面数
+async fn run_export(
+    state: &AppState,
+    task_id: &str,
+    control: &TaskControl,
+    req: &StaticSiteRequest,
+    base_url: &str,
+) -> Result<u64, String> {
+    let password_meta = get_nearest_password_meta(state, &req.source_path).await;
+    if !can_access_password(password_meta.as_ref(), &req.source_path, "") {
+        return Err("源目录受密码保护，不能公开导出".to_string());
+    }
+    let dirs = collect_tree(state, control, &req.source_path, base_url).await?;
+    state.task_manager.update_task_size(task_id, 0, dirs.len() as u64).await;
+
+    let mounts = get_all_mounts(state).await.map_err(|e| format!("获取挂载列表失败: {}", e))?;
+    let (mount, internal_target) = resolve_mount(&req.target_path, &mounts)
+        .ok_or_else(|| format!("目标路径不在任何挂载下: {}", req.target_path))?;
+    let driver = state.storage_manager.get_driver(&mount.id).await
+        .ok_or_else(|| format!("驱动未加载: {}", mount.id))?;
+    let sink = DriverStrmSink::new(driver, &internal_target);
+
+    let title = req.title.as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| req.source_path.rsplit('/').find(|s| !s.is_empty()).unwrap_or("/"));
+    for (index, dir) in dirs.values().enumerate() {
+        check_control(control).await?;
+        let page = dir.page_path();
+        sink.write(&page, &render_index(title, dir)).await
+            .map_err(|e| format!("写入页面失败 {}: {}", page, e))?;
+        state.task_manager.update_task_progress_files(task_id, index as u64 + 1, Some(page)).await;
+    }
+    Ok(dirs.len() as u64)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaticSiteRequest {
+    /// 要导出的目录
+    pub source_path: String,
+    /// 写入页面的目录（位于某个挂载内，如对象存储）
+    pub target_path: String,
+    /// 直链使用的外部访问地址，为空时使用下载域名
+    pub base_url: Option<String>,
+    /// 页面标题，为空时使用源目录名
+    pub title: Option<String>,
+}
+
+/// POST /api/admin/static-site - 创建静态站点导出任务（每个目录生成 index.html，文件链接为永久直链）
+pub async fn create_static_site_task(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(mut req): Json<StaticSiteRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let user_id = require_instance_admin(&state, &cookies).await?;
+
+    req.source_path = fix_and_clean_path(req.source_path.trim());
+    req.target_path = fix_and_clean_path(req.target_path.trim());
+    if req.target_path == "/" {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "目标路径不能为根目录"}))));
+    }
+    if is_sub_path(&req.source_path, &req.target_path) || is_sub_path(&req.target_path, &req.source_path) {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "源目录和目标目录不能相互包含"}))));
+    }
+    let base_url = match req.base_url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        Some(url) => normalize_base_url(url).ok_or_else(|| {
+            (StatusCode::BAD_REQUEST, Json(json!({"error": "外部访问地址必须以http://或https://开头"})))
+        })?,
+        None => normalize_base_url(&state.download_settings.build_download_url("", "http")).ok_or_else(|| {
+            (StatusCode::BAD_REQUEST, Json(json!({"error": "未配置外部访问地址（base_url或下载域名）"})))
+        })?,
+    };
+
+    let name = format!("导出静态站点 {} → {}", req.source_path, req.target_path);
+    let task = Task::new(TaskType::StaticSite, name, req.source_path.clone(), Some(req.target_path.clone()), 0, 0, Some(user_id));
+    let task_id = task.id.clone();
+    state.task_manager.add_task(task).await;
+    state.task_manager.start_task(&task_id).await;
+    let control = state.task_manager.create_control(&task_id).await;
+
+    let task_state = state.clone();
+    let id = task_id.clone();
+    tokio::spawn(async move {
+        match run_export(&task_state, &id, &control, &req, &base_url).await {
+            Ok(pages) => {
+                tracing::info!("Static site export of {} to {} finished: {} pages", req.source_path, req.target_path, pages);
+                task_state.task_manager.complete_task(&id).await;
+            }
+            Err(e) => {
+                if !e.contains("已取消") {
+                    tracing::warn!("Static site export of {} failed: {}", req.source_path, e);
+                    task_state.task_manager.fail_task(&id, e).await;
+                }
+            }
+        }
+        task_state.task_manager.remove_control(&id).await;
+    });
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "静态站点导出任务已创建",
+        "data": {
+            "task_id": task_id
+        }
+    })))
+}
@@ -0,0 +1,239 @@
+//! 存储用量报表与空间告警
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_cookies::Cookies;
+use yaolist_backend::path_resolver::MountInfo;
+use yaolist_backend::email_template::EmailEvent;
+use yaolist_backend::storage::SpaceInfo;
+use yaolist_backend::storage::usage::{
+    crossed_threshold, usage_percent, usage_trend, StorageAlertConfig, UsageSample, STORAGE_ALERT_KEY,
+};
+use yaolist_backend::webhook::WebhookEvent;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+use crate::api::extract::utils::format_size;
+use crate::api::notification::{load_notification_settings, send_event_email};
+
+/// 单个驱动查询空间的超时（秒）
+const SPACE_INFO_TIMEOUT_SECS: u64 = 30;
+/// 报表默认天数
+const DEFAULT_REPORT_DAYS: u32 = 7;
+
+/// 从数据库加载空间告警设置
+pub async fn load_storage_alert_config(state: &AppState) -> StorageAlertConfig {
+    sqlx::query_scalar::<_, String>("SELECT value FROM site_settings WHERE key = ?")
+        .bind(STORAGE_ALERT_KEY)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+/// 采集所有启用挂载点的空间并记录历史，使用率升过阈值时告警（定时任务 storage_usage）
+pub async fn collect_storage_usage(state: Arc<AppState>) -> Result<String, String> {
+    let config = load_storage_alert_config(&state).await;
+    let mounts = state.paths.all_mounts().await.map_err(|e| e.to_string())?;
+
+    let now = Utc::now().to_rfc3339();
+    let mut recorded = 0;
+    let mut alerts = 0;
+    for MountInfo { id, mount_path, .. } in mounts {
+        let Some(driver) = state.storage_manager.get_driver(&id).await else {
+            continue;
+        };
+        let space = match tokio::time::timeout(Duration::from_secs(SPACE_INFO_TIMEOUT_SECS), driver.get_space_info()).await {
+            Ok(Ok(Some(space))) => space,
+            Ok(Ok(None)) => continue,
+            Ok(Err(e)) => {
+                tracing::debug!("Storage usage: failed to get space of {}: {}", id, e);
+                continue;
+            }
+            Err(_) => {
+                tracing::debug!("Storage usage: space query of {} timed out", id);
+                continue;
+            }
+        };
+
+        let previous: Option<(i64, i64)> = sqlx::query_as(
+            "SELECT used, total FROM storage_usage_history WHERE driver_id = ? ORDER BY recorded_at DESC LIMIT 1"
+        )
+        .bind(&id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        sqlx::query(
+            "INSERT INTO storage_usage_history (driver_id, mount_path, used, total, free, recorded_at) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&id)
+        .bind(&mount_path)
+        .bind(space.used as i64)
+        .bind(space.total as i64)
+        .bind(space.free as i64)
+        .bind(&now)
+        .execute(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+        recorded += 1;
+
+        let Some(percent) = usage_percent(&space) else {
+            continue;
+        };
+        let previous_percent = previous.and_then(|(used, total)| usage_percent(&SpaceInfo {
+            used: used as u64,
+            total: total as u64,
+            free: 0,
+        }));
+        if crossed_threshold(previous_percent, percent, config.threshold_percent) {
+            alerts += 1;
+            send_storage_alert(&state, &config, &id, &mount_path, &space, percent).await;
+        }
+    }
+
+    let cutoff = (Utc::now() - chrono::Duration::days(config.retention_days as i64)).to_rfc3339();
+    let removed = sqlx::query("DELETE FROM storage_usage_history WHERE recorded_at < ?")
+        .bind(&cutoff)
+        .execute(&state.db)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+
+    Ok(format!("Recorded {} mounts, {} alerts, removed {} old samples", recorded, alerts, removed))
+}
+
+/// 推送空间告警（Webhook，以及按设置发送邮件给管理员）
+async fn send_storage_alert(
+    state: &AppState,
+    config: &StorageAlertConfig,
+    driver_id: &str,
+    mount_path: &str,
+    space: &SpaceInfo,
+    percent: f64,
+) {
+    let percent = format!("{:.1}", percent);
+    tracing::warn!("Storage alert: {} ({}) is {}% full", mount_path, driver_id, percent);
+    state.webhooks.emit(WebhookEvent::StorageAlert, json!({
+        "driver_id": driver_id,
+        "mount_path": mount_path,
+        "used": space.used,
+        "total": space.total,
+        "free": space.free,
+        "percent": percent,
+        "threshold": config.threshold_percent
+    }));
+
+    if !config.email_admins || !state.email_templates.is_enabled(EmailEvent::StorageAlert) {
+        return;
+    }
+    let settings = load_notification_settings(state).await;
+    if !settings.email_enabled {
+        return;
+    }
+    let emails: Vec<String> = sqlx::query_scalar(
+        "SELECT email FROM users WHERE is_admin = 1 AND enabled = 1 AND email IS NOT NULL AND email != ''"
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+    let vars = json!({
+        "mount_path": mount_path,
+        "used": format_size(space.used),
+        "total": format_size(space.total),
+        "percent": percent,
+        "threshold": config.threshold_percent
+    });
+    for email in emails {
+        if let Err(e) = send_event_email(state, &settings, EmailEvent::StorageAlert, None, &email, vars.clone()).await {
+            tracing::warn!("Failed to send storage alert email to {}: {}", email, e);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StorageUsageQuery {
+    pub days: Option<u32>,
+    pub driver_id: Option<String>,
+}
+
+/// GET /api/admin/storage/usage - 各挂载点的空间历史与增长趋势
+pub async fn admin_storage_usage(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<StorageUsageQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let config = load_storage_alert_config(&state).await;
+    let days = query.days.unwrap_or(DEFAULT_REPORT_DAYS).clamp(1, 365);
+    let since = (Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+    let driver_id = query.driver_id.filter(|id| !id.is_empty());
+    let rows: Vec<(String, String, i64, i64, i64, String)> = sqlx::query_as(
+        "SELECT driver_id, mount_path, used, total, free, recorded_at FROM storage_usage_history
+         WHERE recorded_at >= ? AND (? IS NULL OR driver_id = ?)
+         ORDER BY recorded_at"
+    )
+    .bind(&since)
+    .bind(&driver_id)
+    .bind(&driver_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    let mut by_driver: BTreeMap<String, (String, Vec<UsageSample>)> = BTreeMap::new();
+    for (id, mount_path, used, total, free, recorded_at) in rows {
+        let Ok(recorded_at) = DateTime::parse_from_rfc3339(&recorded_at) else {
+            continue;
+        };
+        let entry = by_driver.entry(id).or_default();
+        entry.0 = mount_path;
+        entry.1.push(UsageSample {
+            used: used as u64,
+            total: total as u64,
+            free: free as u64,
+            recorded_at: recorded_at.with_timezone(&Utc),
+        });
+    }
+
+    let mut mounts: Vec<Value> = by_driver.into_iter().map(|(id, (mount_path, history))| {
+        let latest = history.last().cloned();
+        let percent = latest.as_ref().and_then(|s| usage_percent(&SpaceInfo {
+            used: s.used,
+            total: s.total,
+            free: s.free,
+        }));
+        let alert = config.threshold_percent > 0
+            && percent.is_some_and(|p| p >= config.threshold_percent as f64);
+        json!({
+            "driver_id": id,
+            "mount_path": mount_path,
+            "latest": latest,
+            "percent": percent,
+            "alert": alert,
+            "trend": usage_trend(&history),
+            "history": history
+        })
+    }).collect();
+    mounts.sort_by(|a, b| a["mount_path"].as_str().cmp(&b["mount_path"].as_str()));
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "days": days,
+            "config": config,
+            "mounts": mounts
+        }
+    })))
+}
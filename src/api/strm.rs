@@ -0,0 +1,399 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use yaolist_backend::strm::{
+    normalize_base_url, strm_content, strm_relative_path, sync_strm, validate_local_dir,
+    DriverStrmSink, LocalStrmSink, StrmExport, StrmExportStatus, StrmSink, StrmTarget,
+};
+use yaolist_backend::utils::fix_and_clean_path;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+use crate::api::file_resolver::{calculate_internal_path, get_all_mounts, get_first_mount, MountInfo};
+
+/// 虚拟路径转换为（挂载，驱动内路径）
+pub(crate) fn resolve_mount<'a>(path: &str, mounts: &'a [MountInfo]) -> Option<(&'a MountInfo, String)> {
+    let mount = get_first_mount(path, mounts)?;
+    let internal = calculate_internal_path(&mount.mount_path, path);
+    Some((mount, internal))
+}
+
+/// 已有的永久直链（path → sign），重复运行时复用，保证生成的内容稳定
+pub(crate) async fn load_permanent_links(state: &AppState) -> HashMap<String, String> {
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT path, sign FROM direct_links
+         WHERE enabled = 1 AND expires_at IS NULL AND max_access_count IS NULL ORDER BY id"
+    )
+    .fetch_all(&state.db)
+    .await
+    .map(|rows| rows.into_iter().collect())
+    .unwrap_or_default()
+}
+
+/// 查找或创建永久直链，返回sign
+pub(crate) async fn ensure_direct_link(
+    state: &AppState,
+    signs: &mut HashMap<String, String>,
+    path: &str,
+    filename: &str,
+) -> Result<String, String> {
+    if let Some(sign) = signs.get(path) {
+        return Ok(sign.clone());
+    }
+    let sign = crate::api::direct_links::generate_sign(16);
+    let now = Utc::now().to_rfc3339();
+    sqlx::query(
+        "INSERT INTO direct_links (user_id, sign, path, filename, expires_at, max_access_count, access_count, enabled, created_at, updated_at)
+         VALUES (NULL, ?, ?, ?, NULL, NULL, 0, 1, ?, ?)"
+    )
+    .bind(&sign)
+    .bind(path)
+    .bind(filename)
+    .bind(&now)
+    .bind(&now)
+    .execute(&state.db)
+    .await
+    .map_err(|e| format!("创建直链失败 {}: {}", path, e))?;
+    signs.insert(path.to_string(), sign.clone());
+    Ok(sign)
+}
+
+/// 执行一次STRM导出（遍历源目录 → 生成直链 → 同步.strm文件）
+pub async fn run_export(state: &AppState, export: &StrmExport) -> StrmExportStatus {
+    let mut status = StrmExportStatus::started();
+
+    let base_url = export.base_url.as_deref()
+        .and_then(normalize_base_url)
+        .or_else(|| normalize_base_url(&state.download_settings.build_download_url("", "http")));
+    let base_url = match base_url {
+        Some(u) => u,
+        None => {
+            status.error("未配置外部访问地址（导出的base_url或下载域名）".to_string());
+            return status;
+        }
+    };
+    let mounts = match get_all_mounts(state).await {
+        Ok(m) => m,
+        Err(e) => {
+            status.error(format!("获取挂载列表失败: {}", e));
+            return status;
+        }
+    };
+
+    let mut signs = load_permanent_links(state).await;
+
+    let target_virtual = match &export.target {
+        StrmTarget::Mount { path } => Some(fix_and_clean_path(path)),
+        StrmTarget::Local { .. } => None,
+    };
+
+    let mut files: BTreeMap<String, String> = BTreeMap::new();
+    let mut complete = true;
+    for source in &export.source_paths {
+        let source = fix_and_clean_path(source);
+        let Some((mount, internal_root)) = resolve_mount(&source, &mounts) else {
+            status.error(format!("源目录不在任何挂载下: {}", source));
+            complete = false;
+            continue;
+        };
+        let Some(driver) = state.storage_manager.get_driver(&mount.id).await else {
+            status.error(format!("驱动未加载: {}", mount.id));
+            complete = false;
+            continue;
+        };
+
+        // (虚拟路径, 驱动内路径)
+        let mut stack = vec![(source.clone(), internal_root)];
+        while let Some((virtual_dir, internal_dir)) = stack.pop() {
+            let entries = match driver.list(&internal_dir).await {
+                Ok(e) => e,
+                Err(e) => {
+                    status.error(format!("列出目录失败 {}: {}", virtual_dir, e));
+                    complete = false;
+                    continue;
+                }
+            };
+            for entry in entries {
+                let virtual_path = format!("{}/{}", virtual_dir.trim_end_matches('/'), entry.name);
+                if entry.is_dir {
+                    // 不遍历导出目标自身
+                    if target_virtual.as_deref() != Some(virtual_path.as_str()) {
+                        stack.push((virtual_path, format!("{}/{}", internal_dir.trim_end_matches('/'), entry.name)));
+                    }
+                    continue;
+                }
+                if !export.is_video(&entry.name) {
+                    continue;
+                }
+                let Some(rel) = strm_relative_path(&source, &virtual_path) else {
+                    continue;
+                };
+                match ensure_direct_link(state, &mut signs, &virtual_path, &entry.name).await {
+                    Ok(sign) => {
+                        files.insert(rel, strm_content(&base_url, &sign, &entry.name));
+                    }
+                    Err(e) => {
+                        status.error(e);
+                        complete = false;
+                    }
+                }
+            }
+        }
+    }
+
+    let sink: Box<dyn StrmSink> = match &export.target {
+        StrmTarget::Local { dir } => Box::new(LocalStrmSink::new(dir.trim())),
+        StrmTarget::Mount { path } => {
+            let path = fix_and_clean_path(path);
+            let resolved = resolve_mount(&path, &mounts);
+            let driver = match resolved {
+                Some((mount, _)) => state.storage_manager.get_driver(&mount.id).await,
+                None => None,
+            };
+            match (driver, resolved) {
+                (Some(driver), Some((_, internal))) => Box::new(DriverStrmSink::new(driver, &internal)),
+                _ => {
+                    status.error(format!("目标路径不在任何已加载的挂载下: {}", path));
+                    return status;
+                }
+            }
+        }
+    };
+    sync_strm(sink.as_ref(), &files, complete, &mut status).await;
+    status
+}
+
+/// 运行导出并记录结果；已在运行时返回错误
+pub async fn run_export_tracked(state: Arc<AppState>, id: String) -> Result<StrmExportStatus, String> {
+    let export = state.strm.get(&id).ok_or_else(|| "导出不存在".to_string())?;
+    if !state.strm.try_begin(&id) {
+        return Err("导出正在运行".to_string());
+    }
+    // 在独立任务中运行，panic时也能清除运行标记
+    let task_state = state.clone();
+    let result = tokio::spawn(async move { run_export(&task_state, &export).await }).await;
+    let status = result.unwrap_or_else(|e| {
+        let mut status = StrmExportStatus::started();
+        status.error(format!("导出异常终止: {}", e));
+        status
+    });
+    let status = state.strm.finish(&id, status);
+    tracing::info!(
+        "STRM export {} finished: written={}, unchanged={}, removed={}, errors={}",
+        id, status.written, status.unchanged, status.removed, status.errors.len()
+    );
+    Ok(status)
+}
+
+/// 定时任务：刷新所有启用的导出
+pub async fn run_all_exports(state: Arc<AppState>) -> Result<String, String> {
+    let exports: Vec<StrmExport> = state.strm.list().into_iter().filter(|e| e.enabled).collect();
+    let mut written = 0;
+    let mut failed = Vec::new();
+    for export in &exports {
+        match run_export_tracked(state.clone(), export.id.clone()).await {
+            Ok(status) => {
+                written += status.written;
+                if !status.success {
+                    failed.push(export.name.clone());
+                }
+            }
+            Err(e) => failed.push(format!("{} ({})", export.name, e)),
+        }
+    }
+    if failed.is_empty() {
+        Ok(format!("{} exports refreshed, {} files written", exports.len(), written))
+    } else {
+        Err(format!("Failed exports: {}", failed.join(", ")))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveStrmExportRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    pub name: String,
+    pub source_paths: Vec<String>,
+    pub target: StrmTarget,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    pub base_url: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StrmExportIdRequest {
+    pub id: String,
+}
+
+/// GET /api/admin/strm - 获取STRM导出列表
+pub async fn list_exports(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let exports: Vec<Value> = state.strm.list().into_iter().map(|export| {
+        let last_run = state.strm.last_status(&export.id);
+        json!({
+            "id": export.id,
+            "name": export.name,
+            "source_paths": export.source_paths,
+            "target": export.target,
+            "extensions": export.extensions,
+            "base_url": export.base_url,
+            "enabled": export.enabled,
+            "created_at": export.created_at,
+            "last_run": last_run
+        })
+    }).collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "exports": exports,
+            "default_extensions": yaolist_backend::strm::DEFAULT_VIDEO_EXTENSIONS
+        }
+    })))
+}
+
+/// POST /api/admin/strm - 新建或更新STRM导出
+pub async fn save_export(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveStrmExportRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let source_paths: Vec<String> = req.source_paths.iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(fix_and_clean_path)
+        .collect();
+    if source_paths.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "请至少选择一个视频目录"}))));
+    }
+    let target = match req.target {
+        StrmTarget::Local { dir } => {
+            if validate_local_dir(&dir).is_err() {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "目标目录不能为空或根目录"}))));
+            }
+            StrmTarget::Local { dir: dir.trim().to_string() }
+        }
+        StrmTarget::Mount { path } => {
+            let path = fix_and_clean_path(path.trim());
+            if path == "/" {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "目标路径不能为根目录"}))));
+            }
+            StrmTarget::Mount { path }
+        }
+    };
+    let base_url = match req.base_url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        Some(url) => Some(normalize_base_url(url).ok_or_else(|| {
+            (StatusCode::BAD_REQUEST, Json(json!({"error": "外部访问地址必须以http://或https://开头"})))
+        })?),
+        None => None,
+    };
+
+    let existing = req.id.as_deref().and_then(|id| state.strm.get(id));
+    if req.id.is_some() && existing.is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "导出不存在"}))));
+    }
+
+    let export = StrmExport {
+        id: existing.as_ref().map(|e| e.id.clone()).unwrap_or_else(|| Uuid::new_v4().to_string()),
+        name: req.name,
+        source_paths,
+        target,
+        extensions: req.extensions.iter()
+            .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect(),
+        base_url,
+        enabled: req.enabled.or(existing.as_ref().map(|e| e.enabled)).unwrap_or(true),
+        created_at: existing.as_ref().map(|e| e.created_at).unwrap_or_else(Utc::now),
+    };
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO strm_exports (id, name, source_paths, target, extensions, base_url, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&export.id)
+    .bind(&export.name)
+    .bind(serde_json::to_string(&export.source_paths).unwrap_or_else(|_| "[]".to_string()))
+    .bind(serde_json::to_string(&export.target).unwrap_or_default())
+    .bind(serde_json::to_string(&export.extensions).unwrap_or_else(|_| "[]".to_string()))
+    .bind(&export.base_url)
+    .bind(export.enabled)
+    .bind(export.created_at.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    state.strm.upsert(export.clone());
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": export
+    })))
+}
+
+/// POST /api/admin/strm/delete - 删除STRM导出（已生成的文件保留）
+pub async fn delete_export(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<StrmExportIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    sqlx::query("DELETE FROM strm_exports WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    if !state.strm.remove(&req.id) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "导出不存在"}))));
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
+
+/// POST /api/admin/strm/run - 立即运行STRM导出（后台执行）
+pub async fn run_export_now(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<StrmExportIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    if state.strm.get(&req.id).is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "导出不存在"}))));
+    }
+    if state.strm.last_status(&req.id).map(|s| s.running).unwrap_or(false) {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "导出正在运行"}))));
+    }
+    tokio::spawn(async move {
+        if let Err(e) = run_export_tracked(state, req.id).await {
+            tracing::warn!("STRM export not started: {}", e);
+        }
+    });
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "导出已开始运行"
+    })))
+}
@@ -18,9 +18,10 @@ async fn get_current_user_id(state: &AppState, cookies: &Cookies) -> Option<Stri
     let result: Option<(String,)> = sqlx::query_as(
         "SELECT u.id FROM users u 
          JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now')"
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now')"
     )
     .bind(&session_id)
+    .bind(yaolist_backend::workspace::current().id())
     .fetch_optional(&state.db)
     .await
     .ok()?;
@@ -208,6 +209,109 @@ pub async fn list_tasks(
     })))
 }
 
+/// POST /api/tasks/history - 分页查询任务历史（数据库，包含已从任务列表清理的任务）
+pub async fn task_history(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(query): Json<ListTasksQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let current_user_id = get_current_user_id(&state, &cookies).await;
+    
+    let is_admin = if let Some(ref uid) = current_user_id {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT is_admin FROM users WHERE id = ?"
+        )
+        .bind(uid)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+    } else {
+        false
+    };
+    
+    // 游客没有历史记录
+    if current_user_id.is_none() {
+        return Ok(Json(json!({
+            "code": 401,
+            "message": "未登录"
+        })));
+    }
+    
+    // 普通用户只能查看自己的任务
+    let user_id = if is_admin { query.user_id.clone() } else { current_user_id.clone() };
+    let filter = crate::task::TaskHistoryFilter {
+        user_id,
+        task_type: query.task_type.clone().filter(|s| !s.is_empty()),
+        status: query.status.clone().filter(|s| !s.is_empty()),
+        page: query.page.unwrap_or(1).max(1),
+        page_size: query.page_size.unwrap_or(20).clamp(1, 100),
+    };
+    let (tasks, total) = state.task_manager.query_history(&filter).await;
+    let total_pages = (total as f64 / filter.page_size as f64).ceil() as u32;
+    
+    // 获取用户名映射
+    let mut user_names: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    for task in &tasks {
+        if let Some(ref uid) = task.user_id {
+            if !user_names.contains_key(uid) {
+                if let Ok(Some((username,))) = sqlx::query_as::<_, (String,)>(
+                    "SELECT username FROM users WHERE id = ?"
+                )
+                .bind(uid)
+                .fetch_optional(&state.db)
+                .await {
+                    user_names.insert(uid.clone(), username);
+                }
+            }
+        }
+    }
+    
+    let task_list: Vec<Value> = tasks.iter().map(|task| {
+        let username = task.user_id.as_ref()
+            .and_then(|uid| user_names.get(uid))
+            .cloned()
+            .unwrap_or_else(|| "游客".to_string());
+        json!({
+            "id": task.id,
+            "task_type": task.task_type,
+            "status": task.status,
+            "name": task.name,
+            "source_path": task.source_path,
+            "target_path": task.target_path,
+            "total_size": task.total_size,
+            "processed_size": task.processed_size,
+            "total_files": task.total_files,
+            "processed_files": task.processed_files,
+            "progress": task.progress,
+            "created_at": task.created_at,
+            "started_at": task.started_at,
+            "finished_at": task.finished_at,
+            "error": task.error,
+            "user_id": task.user_id,
+            "username": username,
+            "verification": task.verification,
+            "fsck": task.fsck
+        })
+    }).collect();
+    
+    let (retention_days, _) = state.task_manager.get_retention();
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "tasks": task_list,
+            "total": total,
+            "page": filter.page,
+            "page_size": filter.page_size,
+            "total_pages": total_pages,
+            "retention_days": retention_days,
+            "is_admin": is_admin
+        }
+    })))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetTaskReq {
     pub task_id: String,
@@ -231,6 +335,24 @@ pub async fn get_task(
     }
 }
 
+/// POST /api/tasks/directories - 获取文件夹上传任务各目录的进度
+pub async fn get_task_directories(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<GetTaskReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Some(directories) = state.task_manager.get_directory_progress(&req.task_id).await {
+        Ok(Json(json!({
+            "code": 200,
+            "data": directories
+        })))
+    } else {
+        Ok(Json(json!({
+            "code": 404,
+            "message": "任务不存在"
+        })))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CancelTaskReq {
     pub task_id: String,
@@ -437,89 +559,19 @@ pub async fn restart_task(
     Json(req): Json<RetryTaskReq>,
 ) -> Result<Json<Value>, StatusCode> {
     if let Some(task) = state.task_manager.get_task(&req.task_id).await {
-        // 只能重启失败、取消或中断的任务
-        if task.status != crate::task::TaskStatus::Failed 
-            && task.status != crate::task::TaskStatus::Cancelled 
-            && task.status != crate::task::TaskStatus::Interrupted {
-            return Ok(Json(json!({
-                "code": 400,
-                "message": "只能重启失败、已取消或已中断的任务"
-            })));
-        }
-        
-        // 上传任务需要用户重新选择文件，不能直接重启
-        if task.task_type == crate::task::TaskType::Upload {
-            return Ok(Json(json!({
-                "code": 400,
-                "message": "上传任务请使用'继续上传'功能"
-            })));
-        }
-        
-        // 获取任务执行上下文
-        let items = task.items.clone().unwrap_or_default();
-        let conflict_strategy = task.conflict_strategy.clone().unwrap_or_else(|| "auto_rename".to_string());
-        let source_path = task.source_path.clone();
-        let target_path = task.target_path.clone().unwrap_or_default();
-        let task_type = task.task_type.clone();
-        let task_id = task.id.clone();
-        let processed_files = task.processed_files;
-        
-        if items.is_empty() {
-            return Ok(Json(json!({
+        match spawn_restart(&state, task).await {
+            Ok(task_id) => Ok(Json(json!({
+                "code": 200,
+                "message": "任务已重新启动",
+                "data": {
+                    "task_id": task_id
+                }
+            }))),
+            Err(message) => Ok(Json(json!({
                 "code": 400,
-                "message": "任务上下文丢失，无法重启"
-            })));
+                "message": message
+            }))),
         }
-        
-        // 重置任务状态（保留已处理进度）
-        state.task_manager.restart_task_resume(&task_id, processed_files).await;
-        
-        // 克隆task_id用于返回
-        let task_id_return = task_id.clone();
-        
-        // 异步重新执行任务
-        let state_clone = state.clone();
-        tokio::spawn(async move {
-            let result = match task_type {
-                crate::task::TaskType::Move => {
-                    crate::api::files::execute_move_operation_resume(
-                        &state_clone, &source_path, &target_path, &items, &task_id, 
-                        &conflict_strategy, processed_files
-                    ).await
-                }
-                crate::task::TaskType::Copy => {
-                    crate::api::files::execute_copy_operation_resume(
-                        &state_clone, &source_path, &target_path, &items, &task_id,
-                        &conflict_strategy, processed_files
-                    ).await
-                }
-                _ => {
-                    Err(anyhow::anyhow!("不支持重启此类型任务"))
-                }
-            };
-            
-            match result {
-                Ok(()) => {
-                    state_clone.task_manager.complete_task(&task_id).await;
-                }
-                Err(e) => {
-                    let err_msg = e.to_string();
-                    if err_msg.contains("cancelled") || err_msg.contains("取消") {
-                        state_clone.task_manager.cancel_task(&task_id).await;
-                    } else {
-                        state_clone.task_manager.fail_task(&task_id, err_msg).await;
-                    }
-                }
-            }
-        });
-        
-        Ok(Json(json!({
-            "code": 200,
-            "message": "任务已重新启动",
-            "data": {
-                "task_id": task_id_return
-            }
-        })))
     } else {
         Ok(Json(json!({
             "code": 404,
@@ -527,3 +579,343 @@ pub async fn restart_task(
         })))
     }
 }
+
+/// 从断点重新执行任务（复制/移动），返回任务ID或无法重启的原因
+async fn spawn_restart(state: &Arc<AppState>, task: crate::task::Task) -> Result<String, &'static str> {
+    // 只能重启失败、取消或中断的任务
+    if task.status != crate::task::TaskStatus::Failed 
+        && task.status != crate::task::TaskStatus::Cancelled 
+        && task.status != crate::task::TaskStatus::Interrupted {
+        return Err("只能重启失败、已取消或已中断的任务");
+    }
+    
+    // 上传任务需要用户重新选择文件，不能直接重启
+    if task.task_type == crate::task::TaskType::Upload {
+        return Err("上传任务请使用'继续上传'功能");
+    }
+    
+    // 获取任务执行上下文
+    let items = task.items.clone().unwrap_or_default();
+    let conflict_strategy = task.conflict_strategy.clone().unwrap_or_else(|| "auto_rename".to_string());
+    let source_path = task.source_path.clone();
+    let target_path = task.target_path.clone().unwrap_or_default();
+    let task_type = task.task_type.clone();
+    let task_id = task.id.clone();
+    let processed_files = task.processed_files;
+    
+    if items.is_empty() {
+        return Err("任务上下文丢失，无法重启");
+    }
+    
+    // 重置任务状态（保留已处理进度）
+    state.task_manager.restart_task_resume(&task_id, processed_files).await;
+    
+    // 克隆task_id用于返回
+    let task_id_return = task_id.clone();
+    
+    // 异步重新执行任务
+    let state_clone = state.clone();
+    tokio::spawn(async move {
+        let result = match task_type {
+            crate::task::TaskType::Move => {
+                crate::api::files::execute_move_operation_resume(
+                    &state_clone, &source_path, &target_path, &items, &task_id, 
+                    &conflict_strategy, processed_files
+                ).await
+            }
+            crate::task::TaskType::Copy => {
+                crate::api::files::execute_copy_operation_resume(
+                    &state_clone, &source_path, &target_path, &items, &task_id,
+                    &conflict_strategy, processed_files
+                ).await
+            }
+            _ => {
+                Err(anyhow::anyhow!("不支持重启此类型任务"))
+            }
+        };
+        
+        match result {
+            Ok(()) => {
+                state_clone.task_manager.complete_task(&task_id).await;
+            }
+            Err(e) => {
+                let err_msg = e.to_string();
+                if err_msg.contains("cancelled") || err_msg.contains("取消") {
+                    state_clone.task_manager.cancel_task(&task_id).await;
+                } else {
+                    state_clone.task_manager.fail_task(&task_id, err_msg).await;
+                }
+            }
+        }
+    });
+    
+    Ok(task_id_return)
+}
+
+/// 任务组需要登录：游客没有独立账号，共用的组会互相看到和操作
+async fn require_group_user(state: &AppState, cookies: &Cookies) -> Result<String, Json<Value>> {
+    get_current_user_id(state, cookies).await.ok_or_else(|| Json(json!({
+        "code": 401,
+        "message": "登录后才能使用任务组"
+    })))
+}
+
+/// 检查当前用户是否可以操作任务组（组的创建者或管理员）
+async fn check_group_access(
+    state: &AppState,
+    cookies: &Cookies,
+    group_id: &str,
+) -> Result<crate::task::TaskGroup, Json<Value>> {
+    let user_id = Some(require_group_user(state, cookies).await?);
+    let Some(group) = state.task_manager.get_group(group_id).await else {
+        return Err(Json(json!({
+            "code": 404,
+            "message": "任务组不存在"
+        })));
+    };
+    
+    if group.user_id == user_id {
+        return Ok(group);
+    }
+    let is_admin = if let Some(ref uid) = user_id {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT is_admin FROM users WHERE id = ?"
+        )
+        .bind(uid)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+    } else {
+        false
+    };
+    if is_admin {
+        Ok(group)
+    } else {
+        Err(Json(json!({
+            "code": 403,
+            "message": "无权操作此任务组"
+        })))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTaskGroupReq {
+    pub name: String,
+    #[serde(default)]
+    pub task_ids: Vec<String>,
+}
+
+/// POST /api/tasks/group/create - 创建任务组，可同时加入已有任务
+pub async fn create_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<CreateTaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    let user_id = match require_group_user(&state, &cookies).await {
+        Ok(id) => Some(id),
+        Err(resp) => return Ok(resp),
+    };
+    let name = if req.name.trim().is_empty() { "任务组".to_string() } else { req.name.trim().to_string() };
+    let group = state.task_manager.create_group(name, user_id).await;
+    let added = state.task_manager.add_to_group(&group.id, &req.task_ids).await;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": "任务组已创建",
+        "data": {
+            "group_id": group.id,
+            "added": added
+        }
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskGroupReq {
+    pub group_id: String,
+    #[serde(default)]
+    pub task_ids: Vec<String>,
+}
+
+/// POST /api/tasks/group/add - 将任务加入任务组
+pub async fn add_to_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(resp) = check_group_access(&state, &cookies, &req.group_id).await {
+        return Ok(resp);
+    }
+    let added = state.task_manager.add_to_group(&req.group_id, &req.task_ids).await;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已加入 {} 个任务", added),
+        "data": {
+            "added": added
+        }
+    })))
+}
+
+/// POST /api/tasks/group/list - 获取任务组列表（含聚合进度）
+pub async fn list_task_groups(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, StatusCode> {
+    let user_id = match require_group_user(&state, &cookies).await {
+        Ok(id) => Some(id),
+        Err(resp) => return Ok(resp),
+    };
+    let groups = state.task_manager.list_groups(Some(user_id)).await;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": groups
+    })))
+}
+
+/// POST /api/tasks/group/get - 获取任务组详情（聚合进度和子任务）
+pub async fn get_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(resp) = check_group_access(&state, &cookies, &req.group_id).await {
+        return Ok(resp);
+    }
+    let Some(summary) = state.task_manager.get_group_summary(&req.group_id).await else {
+        return Ok(Json(json!({
+            "code": 404,
+            "message": "任务组不存在"
+        })));
+    };
+    let mut tasks: Vec<crate::task::TaskSummary> = state.task_manager.get_group_tasks(&req.group_id).await
+        .iter()
+        .map(crate::task::TaskSummary::from)
+        .collect();
+    tasks.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "group": summary,
+            "tasks": tasks
+        }
+    })))
+}
+
+/// POST /api/tasks/group/pause - 暂停组内所有任务
+pub async fn pause_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(resp) = check_group_access(&state, &cookies, &req.group_id).await {
+        return Ok(resp);
+    }
+    let count = state.task_manager.pause_group(&req.group_id).await;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已暂停 {} 个任务", count),
+        "data": {
+            "affected": count
+        }
+    })))
+}
+
+/// POST /api/tasks/group/resume - 继续组内所有暂停的任务
+pub async fn resume_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(resp) = check_group_access(&state, &cookies, &req.group_id).await {
+        return Ok(resp);
+    }
+    let count = state.task_manager.resume_group(&req.group_id).await;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已继续 {} 个任务", count),
+        "data": {
+            "affected": count
+        }
+    })))
+}
+
+/// POST /api/tasks/group/cancel - 取消组内所有未结束的任务
+pub async fn cancel_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(resp) = check_group_access(&state, &cookies, &req.group_id).await {
+        return Ok(resp);
+    }
+    let count = state.task_manager.cancel_group(&req.group_id).await;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已取消 {} 个任务", count),
+        "data": {
+            "affected": count
+        }
+    })))
+}
+
+/// POST /api/tasks/group/retry - 重启组内失败/取消/中断的任务
+/// 上传任务需要用户重新选择文件，返回在need_file_selection中
+pub async fn retry_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(resp) = check_group_access(&state, &cookies, &req.group_id).await {
+        return Ok(resp);
+    }
+    
+    let mut restarted = Vec::new();
+    let mut need_file_selection = Vec::new();
+    for task in state.task_manager.get_group_tasks(&req.group_id).await {
+        if !matches!(task.status, crate::task::TaskStatus::Failed 
+            | crate::task::TaskStatus::Cancelled 
+            | crate::task::TaskStatus::Interrupted) {
+            continue;
+        }
+        if task.task_type == crate::task::TaskType::Upload {
+            need_file_selection.push(task.id.clone());
+            continue;
+        }
+        if let Ok(task_id) = spawn_restart(&state, task).await {
+            restarted.push(task_id);
+        }
+    }
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": format!("已重启 {} 个任务", restarted.len()),
+        "data": {
+            "restarted": restarted,
+            "need_file_selection": need_file_selection
+        }
+    })))
+}
+
+/// POST /api/tasks/group/remove - 解散任务组（子任务保留）
+pub async fn remove_task_group(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TaskGroupReq>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(resp) = check_group_access(&state, &cookies, &req.group_id).await {
+        return Ok(resp);
+    }
+    state.task_manager.remove_group(&req.group_id).await;
+    
+    Ok(Json(json!({
+        "code": 200,
+        "message": "任务组已解散"
+    })))
+}
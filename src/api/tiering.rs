@@ -0,0 +1,573 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use yaolist_backend::storage::{DriverBox, Entry};
+use yaolist_backend::tiering::{validate_paths, TieredFile, TieringPolicy, TieringStatus};
+use yaolist_backend::utils::fix_and_clean_path;
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+use crate::api::file_resolver::{calculate_internal_path, find_file_drivers, get_all_mounts, get_first_mount, MountInfo};
+use crate::api::files::cross_driver_copy_file;
+
+/// 访问时间写入数据库的间隔
+const ACCESS_FLUSH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// 虚拟路径解析为（驱动，驱动内路径）
+async fn resolve(state: &AppState, mounts: &[MountInfo], path: &str) -> Option<(DriverBox, String)> {
+    let mount = get_first_mount(path, mounts)?;
+    let driver = state.storage_manager.get_driver(&mount.id).await?;
+    Some((driver, calculate_internal_path(&mount.mount_path, path)))
+}
+
+/// 逐级创建父目录，已存在时的错误可忽略
+async fn create_parents(driver: &DriverBox, internal_path: &str) {
+    let Some((parents, _)) = internal_path.trim_start_matches('/').rsplit_once('/') else {
+        return;
+    };
+    let mut dir = String::new();
+    for segment in parents.split('/') {
+        dir = format!("{}/{}", dir, segment);
+        let _ = driver.create_dir(&dir).await;
+    }
+}
+
+/// 将内存中的访问时间写入数据库
+pub async fn flush_accesses(state: &AppState) {
+    for (path, accessed_at) in state.tiering.take_accesses() {
+        if let Err(e) = sqlx::query("INSERT OR REPLACE INTO file_access (path, accessed_at) VALUES (?, ?)")
+            .bind(&path)
+            .bind(accessed_at.to_rfc3339())
+            .execute(&state.db)
+            .await
+        {
+            tracing::warn!("Failed to save access time of {}: {}", path, e);
+        }
+    }
+}
+
+/// 热目录下文件的最近访问时间
+async fn load_accesses(state: &AppState, hot_path: &str) -> HashMap<String, DateTime<Utc>> {
+    let prefix = format!("{}/", hot_path.trim_end_matches('/'));
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT path, accessed_at FROM file_access WHERE substr(path, 1, ?) = ?"
+    )
+    .bind(prefix.chars().count() as i64)
+    .bind(&prefix)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|(path, at)| Some((path, DateTime::parse_from_rfc3339(&at).ok()?.with_timezone(&Utc))))
+    .collect()
+}
+
+/// 删除分层映射（数据库与缓存）
+async fn forget_tiered(state: &AppState, path: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM tiered_files WHERE path = ?")
+        .bind(path)
+        .execute(&state.db)
+        .await
+        .map_err(|e| format!("删除分层记录失败 {}: {}", path, e))?;
+    state.tiering.remove_file(path);
+    Ok(())
+}
+
+/// 将一个热存储文件移到冷目录并记录映射
+async fn tier_file(
+    state: &AppState,
+    policy: &TieringPolicy,
+    mounts: &[MountInfo],
+    hot_driver: &DriverBox,
+    virtual_path: &str,
+    internal_path: &str,
+    entry: &Entry,
+) -> Result<(), String> {
+    let cold_path = policy.cold_target(virtual_path)
+        .ok_or_else(|| format!("文件不在热目录下: {}", virtual_path))?;
+    let (cold_driver, cold_internal) = resolve(state, mounts, &cold_path).await
+        .ok_or_else(|| format!("冷目录不在任何已加载的挂载下: {}", cold_path))?;
+
+    create_parents(&cold_driver, &cold_internal).await;
+    cross_driver_copy_file(hot_driver, &cold_driver, internal_path, &cold_internal).await
+        .map_err(|e| format!("复制到冷存储失败 {}: {}", virtual_path, e))?;
+
+    let file = TieredFile {
+        path: virtual_path.to_string(),
+        policy_id: policy.id.clone(),
+        cold_path,
+        size: entry.size,
+        modified: entry.modified.clone(),
+        tiered_at: Utc::now(),
+    };
+    sqlx::query(
+        "INSERT OR REPLACE INTO tiered_files (path, policy_id, cold_path, size, modified, tiered_at) VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&file.path)
+    .bind(&file.policy_id)
+    .bind(&file.cold_path)
+    .bind(file.size as i64)
+    .bind(&file.modified)
+    .bind(file.tiered_at.to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|e| format!("保存分层记录失败 {}: {}", virtual_path, e))?;
+    state.tiering.insert_file(file);
+
+    // 删除失败时撤销分层，文件保留在热存储
+    if let Err(e) = hot_driver.delete(internal_path).await {
+        let _ = forget_tiered(state, virtual_path).await;
+        let _ = cold_driver.delete(&cold_internal).await;
+        return Err(format!("删除热存储文件失败 {}: {}", virtual_path, e));
+    }
+    let _ = sqlx::query("DELETE FROM file_access WHERE path = ?")
+        .bind(virtual_path)
+        .execute(&state.db)
+        .await;
+    Ok(())
+}
+
+/// 删除已过时的冷存储副本（文件分层后又被重新上传）
+async fn drop_cold_copy(state: &AppState, mounts: &[MountInfo], file: &TieredFile) -> Result<(), String> {
+    if let Some((cold_driver, cold_internal)) = resolve(state, mounts, &file.cold_path).await {
+        if let Err(e) = cold_driver.delete(&cold_internal).await {
+            tracing::warn!("Failed to delete cold copy {}: {}", file.cold_path, e);
+        }
+    }
+    forget_tiered(state, &file.path).await
+}
+
+/// 执行一次分层策略（遍历热目录 → 移动闲置文件到冷目录）
+pub async fn run_policy(state: &AppState, policy: &TieringPolicy) -> TieringStatus {
+    let mut status = TieringStatus::started();
+    flush_accesses(state).await;
+
+    let mounts = match get_all_mounts(state).await {
+        Ok(m) => m,
+        Err(e) => {
+            status.error(format!("获取挂载列表失败: {}", e));
+            return status;
+        }
+    };
+    let hot_path = fix_and_clean_path(&policy.hot_path);
+    let Some((hot_driver, hot_internal)) = resolve(state, &mounts, &hot_path).await else {
+        status.error(format!("热目录不在任何已加载的挂载下: {}", hot_path));
+        return status;
+    };
+    let accessed = load_accesses(state, &hot_path).await;
+    let now = Utc::now();
+
+    // (虚拟路径, 驱动内路径)
+    let mut stack = vec![(hot_path, hot_internal)];
+    while let Some((virtual_dir, internal_dir)) = stack.pop() {
+        let entries = match hot_driver.list(&internal_dir).await {
+            Ok(e) => e,
+            Err(e) => {
+                status.error(format!("列出目录失败 {}: {}", virtual_dir, e));
+                continue;
+            }
+        };
+        for entry in entries {
+            let virtual_path = format!("{}/{}", virtual_dir.trim_end_matches('/'), entry.name);
+            let internal_path = format!("{}/{}", internal_dir.trim_end_matches('/'), entry.name);
+            if entry.is_dir {
+                stack.push((virtual_path, internal_path));
+                continue;
+            }
+            if let Some(stale) = state.tiering.tiered(&virtual_path) {
+                if let Err(e) = drop_cold_copy(state, &mounts, &stale).await {
+                    status.error(e);
+                }
+            }
+            if entry.size < policy.min_size
+                || !policy.is_idle(accessed.get(&virtual_path).copied(), entry.modified.as_deref(), now)
+            {
+                status.kept += 1;
+                continue;
+            }
+            match tier_file(state, policy, &mounts, &hot_driver, &virtual_path, &internal_path, &entry).await {
+                Ok(()) => {
+                    status.tiered += 1;
+                    status.tiered_bytes += entry.size;
+                }
+                Err(e) => status.error(e),
+            }
+        }
+    }
+    status
+}
+
+/// 运行策略并记录结果；已在运行时返回错误
+pub async fn run_policy_tracked(state: Arc<AppState>, id: String) -> Result<TieringStatus, String> {
+    let policy = state.tiering.get(&id).ok_or_else(|| "分层策略不存在".to_string())?;
+    if !state.tiering.try_begin(&id) {
+        return Err("分层策略正在运行".to_string());
+    }
+    // 在独立任务中运行，panic时也能清除运行标记
+    let task_state = state.clone();
+    let result = tokio::spawn(async move { run_policy(&task_state, &policy).await }).await;
+    let status = result.unwrap_or_else(|e| {
+        let mut status = TieringStatus::started();
+        status.error(format!("分层异常终止: {}", e));
+        status
+    });
+    let status = state.tiering.finish(&id, status);
+    tracing::info!(
+        "Tiering policy {} finished: tiered={}, bytes={}, kept={}, errors={}",
+        id, status.tiered, status.tiered_bytes, status.kept, status.errors.len()
+    );
+    Ok(status)
+}
+
+/// 删除已取回文件的冷存储副本（链接有效期已过）
+async fn purge_recalled(state: &AppState) -> Result<u64, String> {
+    let expiry = chrono::Duration::minutes(state.download_settings.get_link_expiry_minutes().max(0) as i64);
+    let before = (Utc::now() - expiry).to_rfc3339();
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT path, cold_path FROM tiered_files WHERE recalled_at IS NOT NULL AND recalled_at < ?"
+    )
+    .bind(&before)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| format!("读取分层记录失败: {}", e))?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    let mounts = get_all_mounts(state).await.map_err(|e| format!("获取挂载列表失败: {}", e))?;
+    let mut purged = 0;
+    for (path, cold_path) in rows {
+        if let Some((cold_driver, cold_internal)) = resolve(state, &mounts, &cold_path).await {
+            if let Err(e) = cold_driver.delete(&cold_internal).await {
+                tracing::warn!("Failed to delete cold copy {}: {}", cold_path, e);
+                continue;
+            }
+        }
+        sqlx::query("DELETE FROM tiered_files WHERE path = ? AND recalled_at IS NOT NULL")
+            .bind(&path)
+            .execute(&state.db)
+            .await
+            .map_err(|e| format!("删除分层记录失败 {}: {}", path, e))?;
+        purged += 1;
+    }
+    Ok(purged)
+}
+
+/// 定时任务：删除已取回文件的冷存储副本，并运行所有启用的分层策略
+pub async fn run_all_policies(state: Arc<AppState>) -> Result<String, String> {
+    let purged = purge_recalled(&state).await?;
+    let policies: Vec<TieringPolicy> = state.tiering.list().into_iter().filter(|p| p.enabled).collect();
+    let mut tiered = 0;
+    let mut failed = Vec::new();
+    for policy in &policies {
+        match run_policy_tracked(state.clone(), policy.id.clone()).await {
+            Ok(status) => {
+                tiered += status.tiered;
+                if !status.success {
+                    failed.push(policy.name.clone());
+                }
+            }
+            Err(e) => failed.push(format!("{} ({})", policy.name, e)),
+        }
+    }
+    if failed.is_empty() {
+        Ok(format!("{} policies run, {} files tiered, {} recalled copies removed", policies.len(), tiered, purged))
+    } else {
+        Err(format!("Failed policies: {}", failed.join(", ")))
+    }
+}
+
+/// 将已分层文件移回热存储；不是已分层文件时返回 false
+pub async fn recall_file(state: &AppState, path: &str) -> Result<bool, String> {
+    let Some(file) = state.tiering.tiered(path) else {
+        return Ok(false);
+    };
+    let mounts = get_all_mounts(state).await.map_err(|e| format!("获取挂载列表失败: {}", e))?;
+
+    // 热存储中已有同名文件（分层后重新上传），冷存储副本已过时
+    if !find_file_drivers(state, &file.path, &mounts).await.is_empty() {
+        drop_cold_copy(state, &mounts, &file).await?;
+        return Ok(true);
+    }
+
+    let (hot_driver, hot_internal) = resolve(state, &mounts, &file.path).await
+        .ok_or_else(|| format!("热目录不在任何已加载的挂载下: {}", file.path))?;
+    let (cold_driver, cold_internal) = resolve(state, &mounts, &file.cold_path).await
+        .ok_or_else(|| format!("冷目录不在任何已加载的挂载下: {}", file.cold_path))?;
+
+    create_parents(&hot_driver, &hot_internal).await;
+    cross_driver_copy_file(&cold_driver, &hot_driver, &cold_internal, &hot_internal).await
+        .map_err(|e| format!("取回文件失败 {}: {}", file.path, e))?;
+    // 已发出的下载链接仍指向冷存储副本，副本由定时任务在链接过期后删除
+    sqlx::query("UPDATE tiered_files SET recalled_at = ? WHERE path = ?")
+        .bind(Utc::now().to_rfc3339())
+        .bind(&file.path)
+        .execute(&state.db)
+        .await
+        .map_err(|e| format!("保存分层记录失败 {}: {}", file.path, e))?;
+    state.tiering.remove_file(&file.path);
+    // 取回即视为一次访问，避免下次运行时再次分层
+    state.tiering.record_access(&file.path);
+    Ok(true)
+}
+
+/// 删除路径本身及其下已分层文件的冷存储副本与映射；没有已分层文件时返回 false
+pub async fn remove_tiered(state: &AppState, path: &str) -> Result<bool, String> {
+    let files = state.tiering.under(path);
+    if files.is_empty() {
+        return Ok(false);
+    }
+    let mounts = get_all_mounts(state).await.map_err(|e| format!("获取挂载列表失败: {}", e))?;
+    for file in &files {
+        if let Some((cold_driver, cold_internal)) = resolve(state, &mounts, &file.cold_path).await {
+            cold_driver.delete(&cold_internal).await
+                .map_err(|e| format!("删除冷存储副本失败 {}: {}", file.cold_path, e))?;
+        }
+        forget_tiered(state, &file.path).await?;
+    }
+    Ok(true)
+}
+
+/// 后台任务：取回被访问的已分层文件，并定期保存访问时间
+pub fn start_recall_worker(state: Arc<AppState>) {
+    let worker_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            for path in worker_state.tiering.next_recalls().await {
+                match recall_file(&worker_state, &path).await {
+                    Ok(true) => tracing::info!("Tiered file recalled: {}", path),
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Tiered file recall failed: {}", e),
+                }
+            }
+        }
+    });
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ACCESS_FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            flush_accesses(&state).await;
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveTieringPolicyRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    pub name: String,
+    pub hot_path: String,
+    pub cold_path: String,
+    pub idle_days: u32,
+    #[serde(default)]
+    pub min_size: u64,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TieringPolicyIdRequest {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TieredFilesQuery {
+    pub policy_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecallRequest {
+    pub path: String,
+}
+
+/// GET /api/admin/tiering - 获取分层策略列表
+pub async fn list_policies(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let files = state.tiering.files();
+    let policies: Vec<Value> = state.tiering.list().into_iter().map(|policy| {
+        let (count, bytes) = files.iter()
+            .filter(|f| f.policy_id == policy.id)
+            .fold((0u64, 0u64), |(n, b), f| (n + 1, b + f.size));
+        json!({
+            "id": policy.id,
+            "name": policy.name,
+            "hot_path": policy.hot_path,
+            "cold_path": policy.cold_path,
+            "idle_days": policy.idle_days,
+            "min_size": policy.min_size,
+            "enabled": policy.enabled,
+            "created_at": policy.created_at,
+            "tiered_files": count,
+            "tiered_bytes": bytes,
+            "last_run": state.tiering.last_status(&policy.id)
+        })
+    }).collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": policies
+    })))
+}
+
+/// POST /api/admin/tiering - 新建或更新分层策略
+pub async fn save_policy(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveTieringPolicyRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let hot_path = fix_and_clean_path(req.hot_path.trim());
+    let cold_path = fix_and_clean_path(req.cold_path.trim());
+    validate_paths(&hot_path, &cold_path)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e}))))?;
+    if req.idle_days == 0 {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "闲置天数必须大于0"}))));
+    }
+    // 与其他策略的目录重叠时，文件可能在冷热目录间来回移动
+    let overlaps = state.tiering.list().into_iter()
+        .filter(|p| Some(&p.id) != req.id.as_ref())
+        .any(|p| validate_paths(&p.hot_path, &hot_path).is_err() || validate_paths(&p.hot_path, &cold_path).is_err()
+            || validate_paths(&p.cold_path, &hot_path).is_err());
+    if overlaps {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "目录与其他分层策略重叠"}))));
+    }
+
+    let existing = req.id.as_deref().and_then(|id| state.tiering.get(id));
+    if req.id.is_some() && existing.is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "分层策略不存在"}))));
+    }
+
+    let policy = TieringPolicy {
+        id: existing.as_ref().map(|p| p.id.clone()).unwrap_or_else(|| Uuid::new_v4().to_string()),
+        name: req.name,
+        hot_path,
+        cold_path,
+        idle_days: req.idle_days,
+        min_size: req.min_size,
+        enabled: req.enabled.or(existing.as_ref().map(|p| p.enabled)).unwrap_or(true),
+        created_at: existing.as_ref().map(|p| p.created_at).unwrap_or_else(Utc::now),
+    };
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO tiering_policies (id, name, hot_path, cold_path, idle_days, min_size, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&policy.id)
+    .bind(&policy.name)
+    .bind(&policy.hot_path)
+    .bind(&policy.cold_path)
+    .bind(policy.idle_days as i64)
+    .bind(policy.min_size as i64)
+    .bind(policy.enabled)
+    .bind(policy.created_at.to_rfc3339())
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    state.tiering.upsert(policy.clone());
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": policy
+    })))
+}
+
+/// POST /api/admin/tiering/delete - 删除分层策略（已分层的文件仍可访问并取回）
+pub async fn delete_policy(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TieringPolicyIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    sqlx::query("DELETE FROM tiering_policies WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
+    if !state.tiering.remove(&req.id) {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "分层策略不存在"}))));
+    }
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
+
+/// POST /api/admin/tiering/run - 立即运行分层策略（后台执行）
+pub async fn run_policy_now(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<TieringPolicyIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    if state.tiering.get(&req.id).is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "分层策略不存在"}))));
+    }
+    if state.tiering.last_status(&req.id).map(|s| s.running).unwrap_or(false) {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "分层策略正在运行"}))));
+    }
+    tokio::spawn(async move {
+        if let Err(e) = run_policy_tracked(state, req.id).await {
+            tracing::warn!("Tiering policy not started: {}", e);
+        }
+    });
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "分层策略已开始运行"
+    })))
+}
+
+/// GET /api/admin/tiering/files - 已分层文件列表
+pub async fn list_tiered_files(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<TieredFilesQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let files: Vec<TieredFile> = state.tiering.files().into_iter()
+        .filter(|f| query.policy_id.as_ref().is_none_or(|id| &f.policy_id == id))
+        .collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": files
+    })))
+}
+
+/// POST /api/admin/tiering/recall - 立即将已分层文件取回热存储
+pub async fn recall_now(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<RecallRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    match recall_file(&state, &fix_and_clean_path(&req.path)).await {
+        Ok(true) => Ok(Json(json!({
+            "code": 200,
+            "message": "文件已取回"
+        }))),
+        Ok(false) => Err((StatusCode::NOT_FOUND, Json(json!({"error": "文件未分层"})))),
+        Err(e) => Err((StatusCode::BAD_GATEWAY, Json(json!({"error": e})))),
+    }
+}
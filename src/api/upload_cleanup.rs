@@ -0,0 +1,50 @@
+//! 中断上传清理（定时任务 upload_cleanup）
+
+use std::path::Path;
+use std::sync::Arc;
+use yaolist_backend::storage::upload_cleanup::{
+    purge_temp_files, UploadCleanupConfig, TEMP_DIR, UPLOAD_CLEANUP_KEY,
+};
+
+use crate::state::AppState;
+
+/// 从数据库加载上传清理设置
+pub async fn load_upload_cleanup_config(state: &AppState) -> UploadCleanupConfig {
+    sqlx::query_scalar::<_, String>("SELECT value FROM site_settings WHERE key = ?")
+        .bind(UPLOAD_CLEANUP_KEY)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+/// 删除过期的分片临时文件，并中止各驱动过期的分片上传会话
+pub async fn cleanup_stale_uploads(state: Arc<AppState>) -> Result<String, String> {
+    let config = load_upload_cleanup_config(&state).await;
+    let max_age = config.max_age();
+
+    let files = purge_temp_files(Path::new(TEMP_DIR), max_age)
+        .await
+        .map_err(|e| format!("Failed to purge {}: {}", TEMP_DIR, e))?;
+
+    let mut sessions = 0;
+    let mut failed = 0;
+    for (id, driver) in state.storage_manager.get_all_drivers().await {
+        match driver.abort_stale_uploads(max_age).await {
+            Ok(n) => sessions += n,
+            Err(e) => {
+                // 单个驱动失败不影响其他驱动
+                tracing::warn!(driver_id = %id, error = %e, "Failed to abort stale uploads");
+                failed += 1;
+            }
+        }
+    }
+
+    let mut summary = format!("Removed {} temp files, aborted {} upload sessions", files, sessions);
+    if failed > 0 {
+        summary.push_str(&format!(", {} drivers failed", failed));
+    }
+    Ok(summary)
+}
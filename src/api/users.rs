@@ -11,33 +11,57 @@ use chrono::Utc;
 use tower_cookies::Cookies;
 
 use tower_cookies::Cookie;
+use yaolist_backend::accounts;
+use yaolist_backend::group_defaults;
 
 use crate::{
     models::{CreateUserRequest, UpdateUserRequest, User, UserGroup},
     state::AppState,
-    auth::SESSION_COOKIE_NAME,
+    auth::{require_admin, SESSION_COOKIE_NAME},
 };
+use crate::api::security_alert::{admin_account_changed, is_admin_account};
 
-/// 验证管理员权限
-async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<(), (StatusCode, Json<Value>)> {
-    let session_id = cookies.get(SESSION_COOKIE_NAME)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))))?;
-    
-    let is_admin: Option<bool> = sqlx::query_scalar(
-        "SELECT u.is_admin FROM users u 
-         JOIN sessions s ON u.id = s.user_id 
-         WHERE s.id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
-    )
-    .bind(&session_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
-    
-    if !is_admin.unwrap_or(false) {
-        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"}))));
+/// 要管理的工作区：默认工作区的管理员可指定任意工作区，其他工作区只能管理自身
+fn managed_workspace(requested: Option<&str>) -> String {
+    let current = yaolist_backend::workspace::current();
+    match requested.filter(|w| !w.is_empty()) {
+        Some(requested) if current.is_default() => requested.to_string(),
+        _ => current.id().to_string(),
+    }
+}
+
+/// 确认用户属于当前可管理的工作区
+async fn require_user_in_workspace(state: &AppState, id: &str) -> Result<(), (StatusCode, Json<Value>)> {
+    let workspace_id: Option<String> = sqlx::query_scalar("SELECT workspace_id FROM users WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    let current = yaolist_backend::workspace::current();
+    match workspace_id {
+        Some(w) if current.is_default() || w == current.id() => Ok(()),
+        _ => Err((StatusCode::NOT_FOUND, Json(json!({"error": "用户不存在"})))),
+    }
+}
+
+/// 校验并规范化过期时间，空值表示不过期
+fn parse_expiry(input: Option<&str>) -> Result<Option<String>, (StatusCode, Json<Value>)> {
+    match input {
+        Some(input) => accounts::normalize_expiry(input)
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e})))),
+        None => Ok(None),
+    }
+}
+
+/// 删除用户的所有会话，使禁用、过期等变更立即生效
+async fn revoke_sessions(state: &AppState, user_id: &str) {
+    if let Err(e) = sqlx::query("DELETE FROM sessions WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+    {
+        tracing::warn!("Failed to revoke sessions of user {}: {}", user_id, e);
     }
-    Ok(())
 }
 
 #[derive(Debug, Deserialize)]
@@ -48,6 +72,9 @@ pub struct PaginationQuery {
     page_size: i64,
     #[serde(default)]
     search: Option<String>,
+    /// 工作区ID，默认为当前工作区
+    #[serde(default)]
+    workspace_id: Option<String>,
 }
 
 fn default_page() -> i64 { 1 }
@@ -61,12 +88,14 @@ pub async fn list_users(
     require_admin(&state, &cookies).await?;
     
     let offset = (params.page - 1) * params.page_size;
+    let workspace_id = managed_workspace(params.workspace_id.as_deref());
     
     let (users, total) = if let Some(search) = params.search {
         let search_pattern = format!("%{}%", search);
         let users = sqlx::query_as::<_, User>(
-            "SELECT * FROM users WHERE username LIKE ? OR email LIKE ? OR phone LIKE ? OR unique_id LIKE ? ORDER BY created_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM users WHERE workspace_id = ? AND (username LIKE ? OR email LIKE ? OR phone LIKE ? OR unique_id LIKE ?) ORDER BY created_at DESC LIMIT ? OFFSET ?"
         )
+        .bind(&workspace_id)
         .bind(&search_pattern)
         .bind(&search_pattern)
         .bind(&search_pattern)
@@ -78,8 +107,9 @@ pub async fn list_users(
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
         
         let total: i64 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM users WHERE username LIKE ? OR email LIKE ? OR phone LIKE ? OR unique_id LIKE ?"
+            "SELECT COUNT(*) FROM users WHERE workspace_id = ? AND (username LIKE ? OR email LIKE ? OR phone LIKE ? OR unique_id LIKE ?)"
         )
+        .bind(&workspace_id)
         .bind(&search_pattern)
         .bind(&search_pattern)
         .bind(&search_pattern)
@@ -91,15 +121,17 @@ pub async fn list_users(
         (users, total)
     } else {
         let users = sqlx::query_as::<_, User>(
-            "SELECT * FROM users ORDER BY created_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM users WHERE workspace_id = ? ORDER BY created_at DESC LIMIT ? OFFSET ?"
         )
+        .bind(&workspace_id)
         .bind(params.page_size)
         .bind(offset)
         .fetch_all(&state.db)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
         
-        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE workspace_id = ?")
+            .bind(&workspace_id)
             .fetch_one(&state.db)
             .await
             .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -123,6 +155,11 @@ pub async fn create_user(
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     require_admin(&state, &cookies).await?;
     
+    let workspace_id = managed_workspace(req.workspace_id.as_deref());
+    if state.workspaces.get(&workspace_id).is_none() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "工作区不存在"}))));
+    }
+    
     // 获取下一个整数ID（与注册逻辑保持一致）
     let max_id: Option<(i64,)> = sqlx::query_as("SELECT MAX(CAST(id AS INTEGER)) FROM users WHERE id GLOB '[0-9]*'")
         .fetch_optional(&state.db)
@@ -134,12 +171,14 @@ pub async fn create_user(
     let unique_id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
     
+    let expires_at = parse_expiry(req.expires_at.as_deref())?;
     let password_hash = bcrypt::hash(&req.password, bcrypt::DEFAULT_COST)
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
 
     sqlx::query(
-        "INSERT INTO users (id, unique_id, username, password_hash, email, phone, is_admin, enabled, two_factor_enabled, created_at, updated_at) 
-         VALUES (?, ?, ?, ?, ?, ?, 0, 1, 0, ?, ?)"
+        "INSERT INTO users (id, unique_id, username, password_hash, email, phone, is_admin, enabled, two_factor_enabled, created_at, updated_at, workspace_id, expires_at, must_change_password,
+            allow_share, traffic_quota, download_speed_limit, visible_mounts) 
+         VALUES (?, ?, ?, ?, ?, ?, 0, 1, 0, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&id)
     .bind(&unique_id)
@@ -149,6 +188,13 @@ pub async fn create_user(
     .bind(&req.phone)
     .bind(&now)
     .bind(&now)
+    .bind(&workspace_id)
+    .bind(&expires_at)
+    .bind(req.must_change_password)
+    .bind(req.allow_share)
+    .bind(req.traffic_quota.map(|q| q.max(0)))
+    .bind(req.download_speed_limit.map(|s| s.max(0)))
+    .bind(req.visible_mounts.as_deref().map(|m| group_defaults::parse_mounts(m).join("\n")))
     .execute(&state.db)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
@@ -164,6 +210,9 @@ pub async fn create_user(
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
     }
+    if is_admin_account(&state, &id).await {
+        admin_account_changed(&state, &cookies, &req.username, "was created").await;
+    }
 
     Ok(Json(json!({
         "id": id,
@@ -177,6 +226,7 @@ pub async fn get_user(
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     require_admin(&state, &cookies).await?;
+    require_user_in_workspace(&state, &id).await?;
     
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
         .bind(&id)
@@ -195,9 +245,15 @@ pub async fn get_user(
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
 
+    // 继承用户组默认设置并应用用户覆盖后的实际设置
+    let settings = group_defaults::load_user(&state.db, &id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+
     Ok(Json(json!({
         "user": user,
-        "groups": groups
+        "groups": groups,
+        "settings": settings
     })))
 }
 
@@ -208,6 +264,7 @@ pub async fn update_user(
     body: String,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     require_admin(&state, &cookies).await?;
+    require_user_in_workspace(&state, &id).await?;
     
     tracing::debug!("update_user called with id: {}, body: {}", id, body);
     
@@ -217,6 +274,7 @@ pub async fn update_user(
             (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": format!("JSON parse error: {}", e)})))
         })?;
     
+    let was_admin = is_admin_account(&state, &id).await;
     let now = Utc::now().to_rfc3339();
     
     if let Some(username) = &req.username {
@@ -307,6 +365,81 @@ pub async fn update_user(
             .execute(&state.db)
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+        // 禁用后立即退出所有会话
+        if !enabled {
+            revoke_sessions(&state, &id).await;
+        }
+    }
+    
+    if let Some(expires_at) = &req.expires_at {
+        let expires_at = parse_expiry(Some(expires_at))?;
+        sqlx::query("UPDATE users SET expires_at = ?, updated_at = ? WHERE id = ?")
+            .bind(&expires_at)
+            .bind(&now)
+            .bind(&id)
+            .execute(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+        if accounts::is_expired(expires_at.as_deref(), Utc::now()) {
+            revoke_sessions(&state, &id).await;
+        }
+    }
+    
+    if let Some(must_change) = req.must_change_password {
+        sqlx::query("UPDATE users SET must_change_password = ?, updated_at = ? WHERE id = ?")
+            .bind(must_change)
+            .bind(&now)
+            .bind(&id)
+            .execute(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+        // 已登录的会话退出，下次登录时修改密码
+        if must_change {
+            revoke_sessions(&state, &id).await;
+        }
+    }
+    
+    // 覆盖用户组默认设置，null 表示恢复继承
+    if let Some(allow_share) = req.allow_share {
+        sqlx::query("UPDATE users SET allow_share = ?, updated_at = ? WHERE id = ?")
+            .bind(allow_share)
+            .bind(&now)
+            .bind(&id)
+            .execute(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    }
+    
+    if let Some(traffic_quota) = req.traffic_quota {
+        sqlx::query("UPDATE users SET traffic_quota = ?, updated_at = ? WHERE id = ?")
+            .bind(traffic_quota.map(|q| q.max(0)))
+            .bind(&now)
+            .bind(&id)
+            .execute(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    }
+    
+    if let Some(speed_limit) = req.download_speed_limit {
+        sqlx::query("UPDATE users SET download_speed_limit = ?, updated_at = ? WHERE id = ?")
+            .bind(speed_limit.map(|s| s.max(0)))
+            .bind(&now)
+            .bind(&id)
+            .execute(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+    }
+    
+    if let Some(visible_mounts) = &req.visible_mounts {
+        // 空字符串表示可见全部挂载点，与继承不同
+        let visible_mounts = visible_mounts.as_deref().map(|m| group_defaults::parse_mounts(m).join("\n"));
+        sqlx::query("UPDATE users SET visible_mounts = ?, updated_at = ? WHERE id = ?")
+            .bind(visible_mounts)
+            .bind(&now)
+            .bind(&id)
+            .execute(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
     }
     
     if let Some(two_factor) = req.two_factor_enabled {
@@ -339,10 +472,38 @@ pub async fn update_user(
         }
     }
     
+    // 管理员账号的权限与登录相关字段变更时告警
+    let is_admin = is_admin_account(&state, &id).await;
+    let changed: Vec<&str> = [
+        ("username", req.username.is_some()),
+        ("email", req.email.is_some()),
+        ("phone", req.phone.is_some()),
+        ("password", req.password.is_some()),
+        ("enabled", req.enabled.is_some()),
+        ("two-factor", req.two_factor_enabled.is_some()),
+        ("root path", req.root_path.is_some()),
+    ].into_iter().filter(|(_, set)| *set).map(|(name, _)| name).collect();
+    let action = match (was_admin, is_admin) {
+        (false, true) => Some("was granted administrator rights".to_string()),
+        (true, false) => Some("lost administrator rights".to_string()),
+        (true, true) if !changed.is_empty() => Some(format!("was changed: {}", changed.join(", "))),
+        _ => None,
+    };
+    if let Some(action) = action {
+        let username: String = sqlx::query_scalar("SELECT username FROM users WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(&state.db)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| id.clone());
+        admin_account_changed(&state, &cookies, &username, &action).await;
+    }
+    
     // 如果修改了自己的密码，清除cookie并返回logout标志
     if self_password_changed {
         let mut cookie = Cookie::new(SESSION_COOKIE_NAME, "");
-        cookie.set_path("/");
+        cookie.set_path(yaolist_backend::workspace::current().cookie_path());
         cookie.set_max_age(tower_cookies::cookie::time::Duration::seconds(0));
         cookies.remove(cookie);
         
@@ -363,14 +524,187 @@ pub async fn delete_user(
     Path(id): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     require_admin(&state, &cookies).await?;
+    require_user_in_workspace(&state, &id).await?;
+    
+    let admin_name: Option<String> = if is_admin_account(&state, &id).await {
+        sqlx::query_scalar("SELECT username FROM users WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(&state.db)
+            .await
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
     
     sqlx::query("DELETE FROM users WHERE id = ?")
         .bind(&id)
         .execute(&state.db)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    if let Some(username) = admin_name {
+        admin_account_changed(&state, &cookies, &username, "was deleted").await;
+    }
 
     Ok(Json(json!({
         "message": "用户删除成功"
     })))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// csv 或 json，默认按 Content-Type 与内容判断
+    #[serde(default)]
+    format: Option<String>,
+    /// 工作区ID，默认为当前工作区
+    #[serde(default)]
+    workspace_id: Option<String>,
+}
+
+/// 按名称或ID解析用户组，返回用户组ID
+async fn resolve_group(state: &AppState, group: &str) -> Option<String> {
+    sqlx::query_scalar("SELECT CAST(id AS TEXT) FROM user_groups WHERE CAST(id AS TEXT) = ? OR name = ? ORDER BY name = ? LIMIT 1")
+        .bind(group)
+        .bind(group)
+        .bind(group)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+}
+
+/// 创建单个导入的账号，返回用户ID与自动生成的密码
+async fn import_user(
+    state: &AppState,
+    workspace_id: &str,
+    user: accounts::ImportUser,
+) -> Result<(String, Option<String>), String> {
+    let username = user.username.trim().to_string();
+    if username.is_empty() {
+        return Err("用户名不能为空".to_string());
+    }
+    let exists: Option<String> = sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+        .bind(&username)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists.is_some() {
+        return Err("用户名已存在".to_string());
+    }
+
+    let mut group_ids = Vec::with_capacity(user.groups.len());
+    for group in &user.groups {
+        let id = resolve_group(state, group).await.ok_or_else(|| format!("用户组不存在: {}", group))?;
+        group_ids.push(id);
+    }
+    let expires_at = accounts::normalize_expiry(user.expires_at.as_deref().unwrap_or_default())?;
+    let generated = user.password.is_none().then(|| crate::db::generate_random_password(12));
+    let password = user.password.or_else(|| generated.clone()).unwrap_or_default();
+    if password.len() < 6 {
+        return Err("密码长度至少6位".to_string());
+    }
+    let password_hash = bcrypt::hash(&password, bcrypt::DEFAULT_COST).map_err(|e| e.to_string())?;
+
+    let max_id: Option<i64> = sqlx::query_scalar("SELECT MAX(CAST(id AS INTEGER)) FROM users WHERE id GLOB '[0-9]*'")
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    let id = (max_id.unwrap_or(0) + 1).to_string();
+    let now = Utc::now().to_rfc3339();
+
+    let mut tx = state.db.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query(
+        "INSERT INTO users (id, unique_id, username, password_hash, email, phone, root_path, is_admin, enabled, two_factor_enabled, created_at, updated_at, workspace_id, expires_at, must_change_password) 
+         VALUES (?, ?, ?, ?, ?, ?, ?, 0, 1, 0, ?, ?, ?, ?, ?)"
+    )
+    .bind(&id)
+    .bind(Uuid::new_v4().to_string())
+    .bind(&username)
+    .bind(&password_hash)
+    .bind(&user.email)
+    .bind(&user.phone)
+    .bind(user.root_path.as_deref().unwrap_or("/"))
+    .bind(&now)
+    .bind(&now)
+    .bind(workspace_id)
+    .bind(&expires_at)
+    .bind(user.must_change_password.unwrap_or(true))
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    for group_id in &group_ids {
+        sqlx::query("INSERT OR IGNORE INTO user_group_members (user_id, group_id, created_at) VALUES (?, ?, ?)")
+            .bind(&id)
+            .bind(group_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok((id, generated))
+}
+
+/// POST /api/users/import - 从 CSV 或 JSON 批量创建用户（需要管理员权限）
+///
+/// 逐行创建，失败的行不影响其他行；未提供密码时自动生成并在结果中返回，导入的账号默认首次登录须修改密码。
+pub async fn import_users(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_admin(&state, &cookies).await?;
+
+    let workspace_id = managed_workspace(query.workspace_id.as_deref());
+    if state.workspaces.get(&workspace_id).is_none() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "工作区不存在"}))));
+    }
+
+    let content_type = headers.get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let is_json = match query.format.as_deref() {
+        Some(format) => format.eq_ignore_ascii_case("json"),
+        None if content_type.contains("csv") => false,
+        None => content_type.contains("json") || body.trim_start().starts_with(['[', '{']),
+    };
+    let users = if is_json { accounts::parse_json(&body) } else { accounts::parse_csv(&body) }
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e}))))?;
+    if users.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "没有可导入的用户"}))));
+    }
+    if users.len() > accounts::MAX_IMPORT_ROWS {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": format!("单次最多导入{}个用户", accounts::MAX_IMPORT_ROWS)
+        }))));
+    }
+
+    let mut created = Vec::new();
+    let mut errors = Vec::new();
+    for (index, user) in users.into_iter().enumerate() {
+        let username = user.username.clone();
+        match import_user(&state, &workspace_id, user).await {
+            Ok((id, password)) => created.push(json!({
+                "row": index + 1,
+                "id": id,
+                "username": username,
+                "password": password
+            })),
+            Err(error) => errors.push(json!({
+                "row": index + 1,
+                "username": username,
+                "error": error
+            })),
+        }
+    }
+    tracing::info!("Imported {} users into workspace {} ({} failed)", created.len(), workspace_id, errors.len());
+
+    Ok(Json(json!({
+        "message": format!("已导入{}个用户，失败{}个", created.len(), errors.len()),
+        "created": created,
+        "errors": errors
+    })))
+}
@@ -0,0 +1,108 @@
+//! 上传病毒扫描（clamd）：扫描上传的文件、记录结果、拒绝被感染的上传
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use yaolist_backend::storage::DriverBox;
+use yaolist_backend::virus_scan::{self, VirusScanConfig};
+
+use crate::state::AppState;
+use crate::auth::require_instance_admin;
+
+/// 从数据库加载病毒扫描设置
+pub async fn load_virus_scan_config(state: &AppState) -> VirusScanConfig {
+    virus_scan::load_config(&state.db).await
+}
+
+/// 缓存在服务器上、尚未提交到驱动的上传：提交前扫描
+pub async fn scan_before_commit(
+    state: &AppState,
+    data: &[u8],
+    path: &str,
+    user_id: Option<&str>,
+) -> Result<(), &'static str> {
+    virus_scan::scan_before_commit(&state.db, data, path, user_id, data.len() as u64).await
+}
+
+/// 已写入驱动的流式上传：读回扫描，被感染或按设置拒绝时从挂载点删除
+pub async fn scan_after_write(
+    state: &AppState,
+    driver: &DriverBox,
+    actual_path: &str,
+    path: &str,
+    user_id: Option<&str>,
+    size: Option<u64>,
+) -> Result<(), &'static str> {
+    virus_scan::scan_after_write(&state.db, driver, actual_path, path, user_id, size).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanResultQuery {
+    #[serde(default = "default_page")]
+    page: i64,
+    #[serde(default = "default_per_page")]
+    per_page: i64,
+    status: Option<String>,
+}
+
+fn default_page() -> i64 { 1 }
+fn default_per_page() -> i64 { 20 }
+
+/// GET /api/admin/virus-scans - 上传扫描记录（按时间倒序，可按状态过滤）
+pub async fn list_scan_results(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<ScanResultQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let per_page = query.per_page.clamp(1, 100);
+    let offset = (query.page.max(1) - 1) * per_page;
+    let status = query.status.as_deref().filter(|s| !s.is_empty());
+    let (entries, total) = virus_scan::list(&state.db, status, per_page, offset)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": {
+            "entries": entries,
+            "total": total,
+            "page": query.page.max(1),
+            "per_page": per_page
+        }
+    })))
+}
+
+/// POST /api/admin/virus-scans/test - 测试与 clamd 的连接（可传入未保存的设置）
+pub async fn test_scanner(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    body: Option<Json<VirusScanConfig>>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let config = match body {
+        Some(Json(config)) => config,
+        None => load_virus_scan_config(&state).await,
+    };
+    config.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("病毒扫描设置无效: {}", e)}))))?;
+
+    match virus_scan::ping(&config).await {
+        Ok(()) => Ok(Json(json!({
+            "code": 200,
+            "message": "clamd 连接正常"
+        }))),
+        Err(e) => Ok(Json(json!({
+            "code": 500,
+            "message": format!("无法连接 clamd: {}", e)
+        }))),
+    }
+}
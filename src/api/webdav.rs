@@ -13,7 +13,25 @@ use std::sync::Arc;
 use base64::Engine;
 
 use crate::state::AppState;
-use yaolist_backend::server::{WebDavFs, UserAuthenticator};
+use yaolist_backend::server::{WebDavFs, UserAuthenticator, AuthenticatedUser};
+
+/// 游客访问策略允许匿名WebDAV时返回只读的游客身份（下载还需策略允许游客下载）
+async fn anonymous_user(state: &AppState, method: &Method) -> Option<AuthenticatedUser> {
+    let policy = state.guest.get();
+    let allowed = match method.as_str() {
+        "HEAD" | "PROPFIND" => true,
+        "GET" => policy.allow_download,
+        _ => false,
+    };
+    if !allowed || !policy.allow_browse || !policy.allow_webdav {
+        return None;
+    }
+    let user = UserAuthenticator::new(state.db.clone()).guest(policy.browse_paths).await;
+    if let Some(ref u) = user {
+        tracing::debug!("WebDAV anonymous read as {}", u.username);
+    }
+    user
+}
 
 /// WebDAV请求处理器
 /// 处理所有/dav/*路径的请求
@@ -71,6 +89,12 @@ pub async fn webdav_handler(
         None
     };
 
+    // 未携带认证信息的只读请求按游客访问策略以游客身份处理
+    let user = match user {
+        None if auth_header.is_none() => anonymous_user(&state, &method).await,
+        user => user,
+    };
+
     // 未认证返回401
     let user = match user {
         Some(u) => {
@@ -93,9 +117,11 @@ pub async fn webdav_handler(
     // 创建带用户的文件系统
     let fs = WebDavFs::with_user(
         state.storage_manager.clone(),
-        state.db.clone(),
+        state.paths.clone(),
         user,
-    );
+    )
+    .with_sort(state.webdav_config.read().await.sort)
+    .with_request_path(req.uri().path(), "/dav");
     
     // 创建WebDAV处理器
     let handler = dav_server::DavHandler::builder()
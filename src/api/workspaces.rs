@@ -0,0 +1,272 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use yaolist_backend::workspace::{self, Workspace, DEFAULT_WORKSPACE_ID};
+
+use crate::state::AppState;
+use crate::auth::{require_instance_admin, SESSION_COOKIE_NAME};
+
+/// 去掉属于其他工作区的会话Cookie，返回新的Cookie头（无需修改时返回None）
+async fn filter_session_cookies(state: &AppState, cookie_header: &str, workspace_id: &str) -> Option<String> {
+    let mut changed = false;
+    let mut kept = Vec::new();
+    for pair in cookie_header.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+        if let Some(session_id) = pair.strip_prefix(SESSION_COOKIE_NAME).and_then(|v| v.strip_prefix('=')) {
+            let owner: Option<String> = sqlx::query_scalar("SELECT workspace_id FROM sessions WHERE id = ?")
+                .bind(session_id)
+                .fetch_optional(&state.db)
+                .await
+                .ok()
+                .flatten();
+            if owner.is_some_and(|owner| owner != workspace_id) {
+                changed = true;
+                continue;
+            }
+        }
+        kept.push(pair);
+    }
+    changed.then(|| kept.join("; "))
+}
+
+/// 工作区中间件：按域名或 /w/<slug> 前缀确定工作区，去掉前缀后在该工作区内处理请求
+pub async fn workspace_middleware(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let host = req.headers().get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| req.uri().host())
+        .map(|h| h.to_string());
+    let path = req.uri().path().to_string();
+
+    let Some(context) = state.workspaces.resolve(host.as_deref(), &path) else {
+        return (StatusCode::NOT_FOUND, Json(json!({"code": 404, "message": "工作区不存在"}))).into_response();
+    };
+
+    // 去掉 /w/<slug> 前缀，后续路由与未加前缀时一致
+    if context.via_prefix {
+        if let Some((_, rest)) = workspace::split_prefix(&path) {
+            let path_and_query = match req.uri().query() {
+                Some(query) => format!("{}?{}", rest, query),
+                None => rest.to_string(),
+            };
+            let mut parts = req.uri().clone().into_parts();
+            parts.path_and_query = path_and_query.parse().ok();
+            if let Ok(uri) = axum::http::Uri::from_parts(parts) {
+                *req.uri_mut() = uri;
+            }
+        }
+    }
+
+    if !context.is_default() && workspace::is_instance_route(req.uri().path()) {
+        return (StatusCode::FORBIDDEN, Json(json!({"code": 403, "message": "该功能仅能在默认工作区中管理"}))).into_response();
+    }
+
+    // 会话只在创建它的工作区内有效
+    let cookie_headers: Vec<String> = req.headers().get_all(header::COOKIE).iter()
+        .filter_map(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+        .collect();
+    if cookie_headers.iter().any(|v| v.contains(SESSION_COOKIE_NAME)) {
+        let mut filtered = Vec::with_capacity(cookie_headers.len());
+        let mut changed = false;
+        for value in &cookie_headers {
+            match filter_session_cookies(&state, value, context.id()).await {
+                Some(new_value) => {
+                    changed = true;
+                    filtered.push(new_value);
+                }
+                None => filtered.push(value.clone()),
+            }
+        }
+        if changed {
+            req.headers_mut().remove(header::COOKIE);
+            for value in filtered.into_iter().filter(|v| !v.is_empty()) {
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    req.headers_mut().append(header::COOKIE, value);
+                }
+            }
+        }
+    }
+
+    workspace::scope(Arc::new(context), next.run(req)).await
+}
+
+/// GET /api/admin/workspaces - 列出工作区
+pub async fn list_workspaces(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let counts: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT workspace_id, COUNT(*) FROM users GROUP BY workspace_id"
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+    let user_count = |id: &str| counts.iter().find(|(w, _)| w == id).map(|(_, c)| *c).unwrap_or(0);
+
+    let workspaces: Vec<Value> = std::iter::once(Workspace::default_workspace())
+        .chain(state.workspaces.list())
+        .map(|w| json!({
+            "id": w.id,
+            "name": w.name,
+            "slug": w.slug,
+            "domains": w.domains,
+            "root_path": w.root_path,
+            "enabled": w.enabled,
+            "is_default": w.is_default(),
+            "user_count": user_count(&w.id)
+        }))
+        .collect();
+
+    Ok(Json(json!({
+        "code": 200,
+        "data": workspaces
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveWorkspaceRequest {
+    /// 为空时新建
+    pub id: Option<String>,
+    pub name: String,
+    pub slug: String,
+    #[serde(default)]
+    pub domains: Vec<String>,
+    pub root_path: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// POST /api/admin/workspaces - 新建或更新工作区
+pub async fn save_workspace(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveWorkspaceRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    let id = match req.id.filter(|id| !id.is_empty()) {
+        Some(id) if id == DEFAULT_WORKSPACE_ID => {
+            return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "默认工作区不可修改"}))));
+        }
+        Some(id) => {
+            if state.workspaces.get(&id).is_none() {
+                return Err((StatusCode::NOT_FOUND, Json(json!({"error": "工作区不存在"}))));
+            }
+            id
+        }
+        None => Uuid::new_v4().to_string(),
+    };
+
+    let mut workspace = Workspace {
+        id,
+        name: req.name,
+        slug: req.slug,
+        domains: req.domains,
+        root_path: req.root_path,
+        enabled: req.enabled,
+    };
+    workspace.normalize();
+    workspace.validate()
+        .and_then(|_| state.workspaces.check_conflicts(&workspace))
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": format!("工作区设置无效: {}", e)}))))?;
+
+    let now = Utc::now().to_rfc3339();
+    let domains = serde_json::to_string(&workspace.domains)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    sqlx::query(
+        "INSERT INTO workspaces (id, name, slug, domains, root_path, enabled, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, slug = excluded.slug, domains = excluded.domains,
+             root_path = excluded.root_path, enabled = excluded.enabled, updated_at = excluded.updated_at"
+    )
+    .bind(&workspace.id)
+    .bind(&workspace.name)
+    .bind(&workspace.slug)
+    .bind(&domains)
+    .bind(&workspace.root_path)
+    .bind(workspace.enabled)
+    .bind(&now)
+    .bind(&now)
+    .execute(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))))?;
+
+    let id = workspace.id.clone();
+    state.workspaces.upsert(workspace);
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "保存成功",
+        "data": { "id": id }
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceIdRequest {
+    pub id: String,
+}
+
+/// POST /api/admin/workspaces/delete - 删除工作区（需先删除其中的用户）
+pub async fn delete_workspace(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<WorkspaceIdRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    require_instance_admin(&state, &cookies).await?;
+
+    if req.id == DEFAULT_WORKSPACE_ID {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "默认工作区不可删除"}))));
+    }
+    if state.workspaces.get(&req.id).is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({"error": "工作区不存在"}))));
+    }
+    let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE workspace_id = ?")
+        .bind(&req.id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    if users > 0 {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "请先删除该工作区中的用户"}))));
+    }
+
+    sqlx::query("DELETE FROM shares WHERE workspace_id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    sqlx::query("DELETE FROM site_settings WHERE key LIKE ?")
+        .bind(format!("workspace:{}:%", req.id))
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    sqlx::query("DELETE FROM workspaces WHERE id = ?")
+        .bind(&req.id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    state.workspaces.remove(&req.id);
+
+    Ok(Json(json!({
+        "code": 200,
+        "message": "删除成功"
+    })))
+}
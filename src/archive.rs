@@ -0,0 +1,740 @@
+//! Reading archives by byte range / 按字节范围读取压缩包
+//!
+//! This module handles:
+//! - Locating the ZIP central directory from the file tail, so an archive is never downloaded whole / 从文件末尾定位 ZIP 中央目录，无需下载整个压缩包
+//! - Walking TAR headers, reading only the blocks around each header / 遍历 TAR 文件头，只读取文件头附近的数据块
+//! - Unpacking a single member from its byte range, in memory or as a stream / 按字节范围解压单个成员（整体读入或流式输出）
+//!
+//! Reads go through a `read(start, end)` callback, so any driver that serves ranges can be used.
+//! 通过 `read(start, end)` 回调读取，任何支持按范围读取的驱动都可使用。
+
+use std::future::Future;
+use std::io::Read;
+use bytes::Bytes;
+use encoding_rs::GBK;
+use flate2::{Decompress, FlushDecompress, Status};
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Bytes read from the end to find the end of central directory record (22 bytes plus a 64KB comment)
+/// 查找中央目录结束记录时从末尾读取的字节数（22 字节加最长 64KB 注释）
+pub const EOCD_SEARCH_BYTES: u64 = 22 + 65535;
+
+/// Size of the ZIP64 end of central directory record / ZIP64 中央目录结束记录的大小
+pub const ZIP64_EOCD_BYTES: u64 = 56;
+
+/// Largest central directory read / 读取中央目录的上限
+pub const MAX_DIRECTORY_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Largest member unpacked, compressed or not / 解压成员的上限（压缩前后均受限）
+pub const MAX_MEMBER_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Fixed part of a local file header / 本地文件头的固定部分
+pub const LOCAL_HEADER_BYTES: u64 = 30;
+
+const EOCD_SIG: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
+const ZIP64_LOCATOR_SIG: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
+const ZIP64_EOCD_SIG: [u8; 4] = [0x50, 0x4b, 0x06, 0x06];
+const CENTRAL_SIG: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
+const LOCAL_SIG: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
+
+
+/// TAR block size / TAR 块大小
+const TAR_BLOCK: u64 = 512;
+
+/// Bytes read at a time while walking TAR headers / 遍历 TAR 文件头时每次读取的字节数
+const TAR_WINDOW: u64 = 1024 * 1024;
+
+/// Largest number of TAR entries indexed / 索引 TAR 条目的上限
+pub const MAX_TAR_ENTRIES: usize = 100_000;
+
+/// Longest GNU long name or PAX header read / 读取 GNU 长文件名或 PAX 头的上限
+const MAX_TAR_NAME_BYTES: u64 = 64 * 1024;
+
+/// Archive formats that can be read by range / 可按范围读取的压缩包格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    /// Uncompressed TAR only, compressed TARs have no random access / 仅未压缩的 TAR（压缩的 TAR 无法随机访问）
+    Tar,
+}
+
+impl ArchiveKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Zip => "ZIP",
+            Self::Tar => "TAR",
+        }
+    }
+}
+
+/// Archive format by extension / 按扩展名判断压缩包格式
+pub fn kind_of(name: &str) -> Option<ArchiveKind> {
+    let ext = name.rsplit_once('.')?.1.to_lowercase();
+    match ext.as_str() {
+        "zip" | "jar" | "war" | "apk" | "ipa" | "epub" => Some(ArchiveKind::Zip),
+        "tar" => Some(ArchiveKind::Tar),
+        _ => None,
+    }
+}
+
+/// A file or directory inside an archive, with where its bytes are
+/// 压缩包中的文件或目录，以及其数据所在位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Member {
+    Zip(ZipMember),
+    Tar(TarMember),
+}
+
+impl Member {
+    /// Path inside the archive, without a trailing slash / 压缩包内的路径（不带结尾斜杠）
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Zip(m) => m.name.trim_end_matches('/'),
+            Self::Tar(m) => m.name.trim_end_matches('/'),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        match self {
+            Self::Zip(m) => m.is_dir(),
+            Self::Tar(m) => m.is_dir,
+        }
+    }
+
+    /// Unpacked size / 解压后的大小
+    pub fn size(&self) -> u64 {
+        match self {
+            Self::Zip(m) => m.size,
+            Self::Tar(m) => m.size,
+        }
+    }
+
+    /// Needs a password, TAR has no encryption / 需要密码（TAR 没有加密）
+    pub fn encrypted(&self) -> bool {
+        match self {
+            Self::Zip(m) => m.encrypted,
+            Self::Tar(_) => false,
+        }
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn u64_at(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Where the central directory is / 中央目录的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Directory {
+    /// Offset and size of the central directory / 中央目录的偏移与大小
+    At { offset: u64, size: u64 },
+    /// ZIP64: the real record is at this offset, see [`parse_zip64_eocd`] / ZIP64：真正的记录在该偏移处
+    Zip64(u64),
+}
+
+/// Find the central directory from the tail of the file / 从文件末尾找到中央目录
+pub fn locate_directory(tail: &[u8]) -> Option<Directory> {
+    let pos = tail.windows(4).rposition(|w| w == EOCD_SIG)?;
+    if tail.len() < pos + 22 {
+        return None;
+    }
+    let size = u32_at(tail, pos + 12);
+    let offset = u32_at(tail, pos + 16);
+    if size == u32::MAX || offset == u32::MAX {
+        // ZIP64 定位记录紧挨在结束记录之前
+        let loc = pos.checked_sub(20)?;
+        if tail[loc..loc + 4] != ZIP64_LOCATOR_SIG {
+            return None;
+        }
+        return Some(Directory::Zip64(u64_at(tail, loc + 8)));
+    }
+    Some(Directory::At { offset: offset as u64, size: size as u64 })
+}
+
+/// Central directory offset and size from a ZIP64 record / 从 ZIP64 记录读取中央目录的偏移与大小
+pub fn parse_zip64_eocd(data: &[u8]) -> Option<(u64, u64)> {
+    if data.len() < ZIP64_EOCD_BYTES as usize || data[0..4] != ZIP64_EOCD_SIG {
+        return None;
+    }
+    Some((u64_at(data, 48), u64_at(data, 40)))
+}
+
+/// A file inside a ZIP archive / ZIP 中的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipMember {
+    pub name: String,
+    /// 0 stored, 8 deflate / 0 为存储，8 为 deflate
+    pub method: u16,
+    pub compressed_size: u64,
+    pub size: u64,
+    /// Offset of the local file header / 本地文件头的偏移
+    pub header_offset: u64,
+    /// Encrypted with a password / 已加密
+    pub encrypted: bool,
+    /// Last byte of a valid ZipCrypto header / ZipCrypto 加密头正确时的最后一个字节
+    pub check_byte: u8,
+}
+
+impl ZipMember {
+    /// Directory entries end with a slash / 目录条目以斜杠结尾
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    /// Encrypted with AES (method 99) instead of ZipCrypto / 使用 AES（方法 99）而非 ZipCrypto 加密
+    pub fn is_aes(&self) -> bool {
+        self.encrypted && self.method == 99
+    }
+}
+
+/// Parse the central directory, directories included / 解析中央目录（包含目录条目）
+pub fn parse_members(data: &[u8]) -> Vec<ZipMember> {
+    let mut members = Vec::new();
+    let mut pos = 0;
+    while pos + 46 <= data.len() && data[pos..pos + 4] == CENTRAL_SIG {
+        let flags = u16_at(data, pos + 8);
+        let encrypted = flags & 1 != 0;
+        // 使用数据描述符时加密头校验修改时间的高字节，否则校验 CRC 的高字节
+        let check_byte = if flags & 0x08 != 0 { data[pos + 13] } else { data[pos + 19] };
+        let method = u16_at(data, pos + 10);
+        let mut compressed_size = u32_at(data, pos + 20) as u64;
+        let mut size = u32_at(data, pos + 24) as u64;
+        let name_len = u16_at(data, pos + 28) as usize;
+        let extra_len = u16_at(data, pos + 30) as usize;
+        let comment_len = u16_at(data, pos + 32) as usize;
+        let mut header_offset = u32_at(data, pos + 42) as u64;
+        let name_end = pos + 46 + name_len;
+        let extra_end = name_end + extra_len;
+        if extra_end > data.len() {
+            break;
+        }
+
+        // 先尝试 UTF-8，失败则用 GBK
+        let name_bytes = &data[pos + 46..name_end];
+        let name = match std::str::from_utf8(name_bytes) {
+            Ok(s) => s.to_string(),
+            Err(_) => GBK.decode(name_bytes).0.into_owned(),
+        };
+
+        // ZIP64 扩展字段只包含值为 0xFFFFFFFF 的字段，按固定顺序排列
+        let mut extra = &data[name_end..extra_end];
+        while extra.len() >= 4 {
+            let id = u16_at(extra, 0);
+            let len = (u16_at(extra, 2) as usize).min(extra.len() - 4);
+            if id == 0x0001 {
+                let mut field = &extra[4..4 + len];
+                for value in [&mut size, &mut compressed_size, &mut header_offset] {
+                    if *value == u32::MAX as u64 && field.len() >= 8 {
+                        *value = u64_at(field, 0);
+                        field = &field[8..];
+                    }
+                }
+            }
+            extra = &extra[4 + len..];
+        }
+
+        members.push(ZipMember { name, method, compressed_size, size, header_offset, encrypted, check_byte });
+        pos = extra_end + comment_len;
+    }
+    members
+}
+
+/// Length of a local file header, the member data follows it / 本地文件头的长度，成员数据紧随其后
+pub fn local_header_len(header: &[u8]) -> Option<u64> {
+    if header.len() < LOCAL_HEADER_BYTES as usize || header[0..4] != LOCAL_SIG {
+        return None;
+    }
+    Some(LOCAL_HEADER_BYTES + u16_at(header, 26) as u64 + u16_at(header, 28) as u64)
+}
+
+/// Unpack the compressed bytes of a member / 解压成员的压缩数据
+pub fn inflate(member: &ZipMember, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if member.size > MAX_MEMBER_BYTES {
+        anyhow::bail!("member too large: {} bytes", member.size);
+    }
+    match member.method {
+        0 => Ok(data.to_vec()),
+        8 => {
+            let mut out = Vec::with_capacity(member.size as usize);
+            flate2::read::DeflateDecoder::new(data)
+                .take(MAX_MEMBER_BYTES)
+                .read_to_end(&mut out)?;
+            Ok(out)
+        }
+        method => anyhow::bail!("unsupported compression method {}", method),
+    }
+}
+
+/// Read the ZIP central directory / 读取 ZIP 中央目录
+pub async fn read_zip_members<F, Fut>(size: u64, mut read: F) -> anyhow::Result<Vec<ZipMember>>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+{
+    let tail = read(size.saturating_sub(EOCD_SEARCH_BYTES), size).await?;
+    let (offset, len) = match locate_directory(&tail) {
+        Some(Directory::At { offset, size }) => (offset, size),
+        Some(Directory::Zip64(at)) => {
+            let record = read(at, at + ZIP64_EOCD_BYTES).await?;
+            parse_zip64_eocd(&record).ok_or_else(|| anyhow::anyhow!("broken ZIP64 directory"))?
+        }
+        None => anyhow::bail!("central directory not found"),
+    };
+    if len > MAX_DIRECTORY_BYTES || offset + len > size {
+        anyhow::bail!("central directory too large");
+    }
+    Ok(parse_members(&read(offset, offset + len).await?))
+}
+
+/// Where the data of a ZIP member starts, from its local header
+/// 根据本地文件头得到 ZIP 成员数据的起始位置
+pub async fn zip_data_offset<F, Fut>(member: &ZipMember, mut read: F) -> anyhow::Result<u64>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+{
+    let header = read(member.header_offset, member.header_offset + LOCAL_HEADER_BYTES).await?;
+    let len = local_header_len(&header).ok_or_else(|| anyhow::anyhow!("broken local file header"))?;
+    Ok(member.header_offset + len)
+}
+
+/// A file or directory inside a TAR archive / TAR 中的文件或目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TarMember {
+    pub name: String,
+    /// Offset of the member data / 成员数据的偏移
+    pub offset: u64,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// Parse an octal or base-256 number field / 解析八进制或 base-256 数字字段
+fn tar_number(field: &[u8]) -> Option<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        // GNU base-256：首字节最高位为 1，其余按大端存储
+        let mut value: u64 = (field[0] & 0x7f) as u64;
+        for &b in &field[1..] {
+            value = value.checked_mul(256)?.checked_add(b as u64)?;
+        }
+        return Some(value);
+    }
+    let text = std::str::from_utf8(field).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+/// Text of a NUL-terminated field, UTF-8 or GBK / 以 NUL 结尾的文本字段（UTF-8 或 GBK）
+fn tar_text(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    let bytes = &field[..end];
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => GBK.decode(bytes).0.into_owned(),
+    }
+}
+
+/// Header checksum: sum of all bytes with the checksum field as spaces / 文件头校验和（校验和字段按空格计算）
+fn tar_checksum_ok(header: &[u8]) -> bool {
+    let Some(expected) = tar_number(&header[148..156]) else {
+        return false;
+    };
+    let sum: u64 = header.iter().enumerate()
+        .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 })
+        .sum();
+    sum == expected
+}
+
+/// `path` record of a PAX extended header / PAX 扩展头中的 `path` 记录
+fn pax_path(data: &[u8]) -> Option<String> {
+    let mut rest = data;
+    while !rest.is_empty() {
+        // 每条记录形如 "<长度> <键>=<值>\n"，长度包含整条记录
+        let space = rest.iter().position(|&b| b == b' ')?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        if len <= space || len > rest.len() {
+            return None;
+        }
+        let record = &rest[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Some(value) = record.strip_prefix(b"path=") {
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+/// Reads a TAR in windows, so walking small members does not cost one request per header
+/// 按窗口读取 TAR，遍历小文件时不必每个文件头请求一次
+struct Window<F> {
+    read: F,
+    size: u64,
+    start: u64,
+    data: Vec<u8>,
+}
+
+impl<F, Fut> Window<F>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+{
+    async fn bytes(&mut self, start: u64, len: u64) -> anyhow::Result<&[u8]> {
+        let end = start + len;
+        if end > self.size {
+            anyhow::bail!("truncated archive");
+        }
+        if start < self.start || end > self.start + self.data.len() as u64 {
+            self.start = start;
+            self.data = (self.read)(start, end.max(start + TAR_WINDOW).min(self.size)).await?;
+            if (self.data.len() as u64) < len {
+                anyhow::bail!("truncated archive");
+            }
+        }
+        let from = (start - self.start) as usize;
+        Ok(&self.data[from..from + len as usize])
+    }
+}
+
+/// Walk the headers of an uncompressed TAR / 遍历未压缩 TAR 的文件头
+pub async fn read_tar_members<F, Fut>(size: u64, read: F) -> anyhow::Result<Vec<TarMember>>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+{
+    let mut window = Window { read, size, start: 0, data: Vec::new() };
+    let mut members = Vec::new();
+    let mut long_name: Option<String> = None;
+    let mut pos = 0;
+    while pos + TAR_BLOCK <= size {
+        let header = window.bytes(pos, TAR_BLOCK).await?.to_vec();
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        if !tar_checksum_ok(&header) {
+            anyhow::bail!("broken TAR header at {}", pos);
+        }
+        let entry_size = tar_number(&header[124..136])
+            .ok_or_else(|| anyhow::anyhow!("broken TAR size at {}", pos))?;
+        let data = pos + TAR_BLOCK;
+        let next = data.checked_add(entry_size.div_ceil(TAR_BLOCK) * TAR_BLOCK)
+            .ok_or_else(|| anyhow::anyhow!("broken TAR size at {}", pos))?;
+
+        match header[156] {
+            // GNU 长文件名与 PAX 扩展头作用于下一个条目
+            b'L' | b'x' => {
+                if entry_size > MAX_TAR_NAME_BYTES {
+                    anyhow::bail!("TAR extended header too large");
+                }
+                let body = window.bytes(data, entry_size).await?;
+                long_name = if header[156] == b'L' { Some(tar_text(body)) } else { pax_path(body) }.or(long_name);
+            }
+            flag @ (b'0' | 0 | b'5') => {
+                let name = match long_name.take() {
+                    Some(name) => name,
+                    None => {
+                        let name = tar_text(&header[0..100]);
+                        // ustar 格式的路径前缀
+                        let prefix = if &header[257..262] == b"ustar" { tar_text(&header[345..500]) } else { String::new() };
+                        if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+                    }
+                };
+                let name = name.trim_start_matches("./").to_string();
+                let is_dir = flag == b'5' || name.ends_with('/');
+                if !name.is_empty() {
+                    members.push(TarMember { name, offset: data, size: if is_dir { 0 } else { entry_size }, is_dir });
+                    if members.len() >= MAX_TAR_ENTRIES {
+                        break;
+                    }
+                }
+            }
+            // 链接、设备文件等没有可读取的内容
+            _ => long_name = None,
+        }
+        pos = next;
+    }
+    Ok(members)
+}
+
+/// Size of the ZipCrypto encryption header before the member data / 成员数据前 ZipCrypto 加密头的大小
+pub const ZIP_CRYPTO_HEADER_BYTES: u64 = 12;
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+fn crc32_byte(crc: u32, b: u8) -> u32 {
+    (crc >> 8) ^ CRC32_TABLE[((crc ^ b as u32) & 0xff) as usize]
+}
+
+/// Legacy ZIP encryption (ZipCrypto), a byte-wise stream cipher / 传统 ZIP 加密（ZipCrypto），逐字节的流密码
+#[derive(Debug, Clone)]
+pub struct ZipCrypto {
+    keys: [u32; 3],
+}
+
+impl ZipCrypto {
+    pub fn new(password: &[u8]) -> Self {
+        let mut crypto = Self { keys: [0x1234_5678, 0x2345_6789, 0x3456_7890] };
+        for &b in password {
+            crypto.update(b);
+        }
+        crypto
+    }
+
+    fn update(&mut self, plain: u8) {
+        self.keys[0] = crc32_byte(self.keys[0], plain);
+        self.keys[1] = self.keys[1].wrapping_add(self.keys[0] & 0xff).wrapping_mul(134_775_813).wrapping_add(1);
+        self.keys[2] = crc32_byte(self.keys[2], (self.keys[1] >> 24) as u8);
+    }
+
+    fn stream_byte(&self) -> u8 {
+        let temp = (self.keys[2] | 2) & 0xffff;
+        (temp.wrapping_mul(temp ^ 1) >> 8) as u8
+    }
+
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        for b in data {
+            let plain = *b ^ self.stream_byte();
+            self.update(plain);
+            *b = plain;
+        }
+    }
+
+    /// Check a password against the encryption header of a member, ready to decrypt the data that follows when it fits.
+    /// Only one byte is checked, so about one wrong password in 256 passes.
+    /// 用成员的加密头校验密码，正确时返回可继续解密后续数据的状态。只校验一个字节，约 1/256 的错误密码会通过。
+    pub fn open(password: &[u8], member: &ZipMember, header: &[u8]) -> Option<Self> {
+        if header.len() < ZIP_CRYPTO_HEADER_BYTES as usize {
+            return None;
+        }
+        let mut crypto = Self::new(password);
+        let mut header = header[..ZIP_CRYPTO_HEADER_BYTES as usize].to_vec();
+        crypto.decrypt(&mut header);
+        (header[11] == member.check_byte).then_some(crypto)
+    }
+}
+
+/// Stream the unpacked bytes of a deflate member, stopping at `size` bytes
+/// 流式解压 deflate 成员，输出 `size` 字节后结束
+pub fn inflate_stream<R>(reader: R, size: u64) -> impl Stream<Item = std::io::Result<Bytes>> + Send
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    struct State<R> {
+        reader: R,
+        inflater: Decompress,
+        input: Vec<u8>,
+        pos: usize,
+        eof: bool,
+        done: bool,
+    }
+    let state = State {
+        reader,
+        inflater: Decompress::new(false),
+        input: Vec::new(),
+        pos: 0,
+        eof: false,
+        done: false,
+    };
+    futures::stream::unfold(state, move |mut s| async move {
+        if s.done {
+            return None;
+        }
+        let mut out = vec![0u8; 64 * 1024];
+        loop {
+            if s.pos == s.input.len() && !s.eof {
+                s.input.resize(64 * 1024, 0);
+                match s.reader.read(&mut s.input).await {
+                    Ok(n) => {
+                        s.input.truncate(n);
+                        s.pos = 0;
+                        s.eof = n == 0;
+                    }
+                    Err(e) => {
+                        s.done = true;
+                        return Some((Err(e), s));
+                    }
+                }
+            }
+            let (before_in, before_out) = (s.inflater.total_in(), s.inflater.total_out());
+            let status = s.inflater.decompress(&s.input[s.pos..], &mut out, FlushDecompress::None);
+            s.pos += (s.inflater.total_in() - before_in) as usize;
+            // 超出声明大小的内容丢弃，保证与 Content-Length 一致
+            let produced = (s.inflater.total_out() - before_out).min(size.saturating_sub(before_out)) as usize;
+            let status = match status {
+                Ok(status) => status,
+                Err(e) => {
+                    s.done = true;
+                    return Some((Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)), s));
+                }
+            };
+            if status == Status::StreamEnd || s.inflater.total_out() >= size {
+                s.done = true;
+            } else if produced == 0 && s.eof && s.pos == s.input.len() {
+                s.done = true;
+                return Some((Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated deflate data")), s));
+            }
+            if produced > 0 {
+                out.truncate(produced);
+                return Some((Ok(Bytes::from(out)), s));
+            }
+            if s.done {
+                return None;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn build_zip(files: &[(&str, &[u8], zip::CompressionMethod)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, data, method) in files {
+            let options = zip::write::SimpleFileOptions::default().compression_method(*method);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_read_member_by_range() {
+        let chapter = "<html>第一章</html>".repeat(200);
+        let file = build_zip(&[
+            ("mimetype", b"application/epub+zip", zip::CompressionMethod::Stored),
+            ("OEBPS/ch1.xhtml", chapter.as_bytes(), zip::CompressionMethod::Deflated),
+        ]);
+
+        let tail_start = file.len().saturating_sub(EOCD_SEARCH_BYTES as usize);
+        let Some(Directory::At { offset, size }) = locate_directory(&file[tail_start..]) else {
+            panic!("central directory not found");
+        };
+        let members = parse_members(&file[offset as usize..(offset + size) as usize]);
+        assert_eq!(members.len(), 2);
+
+        let member = members.iter().find(|m| m.name == "OEBPS/ch1.xhtml").unwrap();
+        assert_eq!(member.method, 8);
+        let start = member.header_offset as usize;
+        let data_start = start + local_header_len(&file[start..]).unwrap() as usize;
+        let data = &file[data_start..data_start + member.compressed_size as usize];
+        assert_eq!(inflate(member, data).unwrap(), chapter.as_bytes());
+    }
+
+    fn reader(file: &[u8]) -> impl FnMut(u64, u64) -> std::future::Ready<anyhow::Result<Vec<u8>>> + '_ {
+        move |start, end| std::future::ready(Ok(file[start as usize..end as usize].to_vec()))
+    }
+
+    #[tokio::test]
+    async fn test_read_zip_members() {
+        let file = build_zip(&[
+            ("docs/", b"", zip::CompressionMethod::Stored),
+            ("docs/a.txt", b"aaaa", zip::CompressionMethod::Stored),
+        ]);
+        let members = read_zip_members(file.len() as u64, reader(&file)).await.unwrap();
+        // 目录条目也会列出
+        assert!(members[0].is_dir() && !members[0].encrypted);
+        let member = &members[1];
+        let start = zip_data_offset(member, reader(&file)).await.unwrap() as usize;
+        assert_eq!(&file[start..start + member.size as usize], b"aaaa");
+    }
+
+    #[tokio::test]
+    async fn test_read_tar_members() {
+        let long_name = format!("{}/movie.txt", "很长的目录".repeat(20));
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        builder.append_data(&mut header, "docs/", std::io::empty()).unwrap();
+        for (name, data) in [("docs/readme.md", b"hello".as_slice()), (long_name.as_str(), b"long".as_slice())] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            builder.append_data(&mut header, name, data).unwrap();
+        }
+        let file = builder.into_inner().unwrap();
+
+        let members = read_tar_members(file.len() as u64, reader(&file)).await.unwrap();
+        assert_eq!(members.len(), 3);
+        assert!(members[0].is_dir);
+        assert_eq!(members[1].name, "docs/readme.md");
+        let m = &members[2];
+        assert_eq!(m.name, long_name);
+        assert_eq!(&file[m.offset as usize..(m.offset + m.size) as usize], b"long");
+    }
+
+    #[test]
+    fn test_zip_crypto() {
+        let member = ZipMember {
+            name: "secret.txt".to_string(),
+            method: 0,
+            compressed_size: 0,
+            size: 0,
+            header_offset: 0,
+            encrypted: true,
+            check_byte: 0xA7,
+        };
+        // 按 ZipCrypto 的方式加密：密文 = 明文 ^ 密钥流，再用明文更新密钥
+        let mut plain = b"0123456789a\xA7hello, world".to_vec();
+        let mut crypto = ZipCrypto::new(b"correct");
+        for b in plain.iter_mut() {
+            let p = *b;
+            *b ^= crypto.stream_byte();
+            crypto.update(p);
+        }
+        let (header, data) = plain.split_at(ZIP_CRYPTO_HEADER_BYTES as usize);
+
+        assert!(ZipCrypto::open(b"wrong", &member, header).is_none());
+        let mut crypto = ZipCrypto::open(b"correct", &member, header).unwrap();
+        let mut data = data.to_vec();
+        crypto.decrypt(&mut data);
+        assert_eq!(data, b"hello, world");
+    }
+
+    #[tokio::test]
+    async fn test_inflate_stream() {
+        use futures::StreamExt;
+        let text = "第一行\n".repeat(50_000);
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let chunks: Vec<_> = inflate_stream(std::io::Cursor::new(data.clone()), text.len() as u64).collect().await;
+        let out: Vec<u8> = chunks.into_iter().flat_map(|c| c.unwrap().to_vec()).collect();
+        assert_eq!(out, text.as_bytes());
+
+        // 数据被截断时报错
+        let cut = data[..data.len() / 2].to_vec();
+        let chunks: Vec<_> = inflate_stream(std::io::Cursor::new(cut), text.len() as u64).collect().await;
+        assert!(chunks.last().unwrap().is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Stored archive passwords / 压缩包密码库
+//!
+//! Admins store passwords for encrypted archives against a path pattern. Listing members,
+//! previewing and extracting an archive try the password given by the user first and then every
+//! stored password whose pattern matches the archive path, most specific pattern first, so users
+//! are only asked when none of them fits.
+//! 管理员按路径模式保存加密压缩包的密码。预览与解压压缩包时先尝试用户输入的密码，再按模式从具体到宽泛依次尝试
+//! 与压缩包路径匹配的已保存密码，均不正确时才需要用户输入。
+//!
+//! A pattern is a glob over the full path (`/backups/**/*.zip`, `*` stays within one folder);
+//! a pattern without glob characters matches that file and everything below that folder.
+//! 模式为匹配完整路径的通配符（如 `/backups/**/*.zip`，`*` 不跨目录）；不含通配符的模式匹配该文件及该目录下的全部文件。
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::hide_rules::glob_to_regex;
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Max pattern length in characters / 模式最大长度（字符）
+pub const MAX_PATTERN_LEN: usize = 1024;
+
+/// Max password length in bytes / 密码最大长度（字节）
+pub const MAX_PASSWORD_LEN: usize = 256;
+
+/// How a pattern matches paths / 模式的匹配方式
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// The path itself and everything below it / 路径本身及其下的全部内容
+    Prefix(String),
+    Glob(Regex),
+}
+
+/// Parse a pattern / 解析模式
+fn matcher(pattern: &str) -> Result<Matcher, String> {
+    if pattern.contains(['*', '?', '[', '{']) {
+        let glob = if pattern.starts_with('/') { pattern.to_string() } else { format!("/{}", pattern) };
+        Regex::new(&glob_to_regex(&glob)).map(Matcher::Glob).map_err(|e| e.to_string())
+    } else {
+        Ok(Matcher::Prefix(fix_and_clean_path(pattern)))
+    }
+}
+
+/// A stored password / 已保存的密码
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivePassword {
+    pub id: String,
+    pub pattern: String,
+    /// Never returned by the API / 不通过接口返回
+    #[serde(skip)]
+    pub password: String,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ArchivePassword {
+    pub fn validate(&self) -> Result<(), String> {
+        let pattern = self.pattern.trim();
+        if pattern.is_empty() {
+            return Err("pattern is required".to_string());
+        }
+        if pattern.chars().count() > MAX_PATTERN_LEN {
+            return Err(format!("pattern must be at most {} characters", MAX_PATTERN_LEN));
+        }
+        if self.password.is_empty() {
+            return Err("password is required".to_string());
+        }
+        if self.password.len() > MAX_PASSWORD_LEN {
+            return Err(format!("password must be at most {} bytes", MAX_PASSWORD_LEN));
+        }
+        matcher(pattern).map(|_| ())
+    }
+}
+
+/// `archive_passwords` row: id, pattern, password, note, created_at
+type ArchivePasswordRow = (String, String, String, String, String);
+
+struct Entry {
+    stored: ArchivePassword,
+    matcher: Matcher,
+}
+
+impl Entry {
+    fn new(stored: ArchivePassword) -> Option<Self> {
+        let matcher = matcher(stored.pattern.trim()).ok()?;
+        Some(Self { stored, matcher })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match &self.matcher {
+            Matcher::Prefix(prefix) => is_sub_path(prefix, path),
+            Matcher::Glob(re) => re.is_match(path),
+        }
+    }
+}
+
+/// Stored password cache / 已保存密码缓存
+pub struct ArchivePasswordManager {
+    entries: RwLock<Vec<Entry>>,
+}
+
+impl ArchivePasswordManager {
+    pub fn new() -> Self {
+        Self {
+            entries: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Load passwords from database / 从数据库加载密码
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<ArchivePasswordRow> = sqlx::query_as(
+            "SELECT id, pattern, password, note, created_at FROM archive_passwords"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let entries = rows.into_iter()
+            .filter_map(|(id, pattern, password, note, created_at)| Entry::new(ArchivePassword {
+                id,
+                pattern,
+                password,
+                note,
+                created_at: DateTime::parse_from_rfc3339(&created_at)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+            }))
+            .collect();
+        *self.entries.write() = entries;
+
+        Ok(())
+    }
+
+    /// List all passwords, newest first / 列出全部密码（新的在前）
+    pub fn list(&self) -> Vec<ArchivePassword> {
+        let mut list: Vec<ArchivePassword> = self.entries.read().iter().map(|e| e.stored.clone()).collect();
+        list.sort_by_key(|p| std::cmp::Reverse(p.created_at));
+        list
+    }
+
+    pub fn get(&self, id: &str) -> Option<ArchivePassword> {
+        self.entries.read().iter().find(|e| e.stored.id == id).map(|e| e.stored.clone())
+    }
+
+    /// Insert or replace a password in the cache / 新增或替换缓存中的密码
+    pub fn upsert(&self, stored: ArchivePassword) -> Result<(), String> {
+        stored.validate()?;
+        let entry = Entry::new(stored).ok_or("invalid pattern")?;
+        let mut entries = self.entries.write();
+        match entries.iter_mut().find(|e| e.stored.id == entry.stored.id) {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+        Ok(())
+    }
+
+    /// Remove a password from the cache / 从缓存移除密码
+    pub fn remove(&self, id: &str) -> bool {
+        let mut entries = self.entries.write();
+        let before = entries.len();
+        entries.retain(|e| e.stored.id != id);
+        entries.len() != before
+    }
+
+    /// Passwords to try for an archive: the user's first, then stored ones, most specific pattern first
+    /// 压缩包依次尝试的密码：先用户输入的，再按模式从具体到宽泛的已保存密码
+    pub fn candidates(&self, path: &str, given: Option<&str>) -> Vec<String> {
+        let path = fix_and_clean_path(path);
+        let entries = self.entries.read();
+        let mut matched: Vec<&ArchivePassword> = entries.iter()
+            .filter(|e| e.matches(&path))
+            .map(|e| &e.stored)
+            .collect();
+        matched.sort_by(|a, b| b.pattern.len().cmp(&a.pattern.len()).then(a.created_at.cmp(&b.created_at)));
+
+        let mut candidates: Vec<String> = given.filter(|p| !p.is_empty()).map(str::to_string).into_iter().collect();
+        for stored in matched {
+            if !candidates.contains(&stored.password) {
+                candidates.push(stored.password.clone());
+            }
+        }
+        candidates
+    }
+}
+
+impl Default for ArchivePasswordManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(id: &str, pattern: &str, password: &str) -> ArchivePassword {
+        ArchivePassword {
+            id: id.to_string(),
+            pattern: pattern.to_string(),
+            password: password.to_string(),
+            note: String::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let manager = ArchivePasswordManager::new();
+        manager.upsert(stored("a", "/share", "common")).unwrap();
+        manager.upsert(stored("b", "/share/backups/*.zip", "backup")).unwrap();
+        manager.upsert(stored("c", "/share/backups/*.7z", "common")).unwrap();
+        manager.upsert(stored("d", "/other", "other")).unwrap();
+
+        assert_eq!(manager.candidates("/share/backups/2024.zip", None), vec!["backup", "common"]);
+        assert_eq!(manager.candidates("/share/backups/2024.7z", Some("typed")), vec!["typed", "common"]);
+        // `*` 不跨目录，前缀模式不匹配同名前缀的其他目录
+        assert_eq!(manager.candidates("/share/backups/old/2023.zip", None), vec!["common"]);
+        assert!(manager.candidates("/shared/a.zip", Some("")).is_empty());
+
+        assert!(manager.remove("a"));
+        assert_eq!(manager.candidates("/share/backups/old/2023.zip", None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(stored("a", "/x", "pw").validate().is_ok());
+        assert!(stored("a", " ", "pw").validate().is_err());
+        assert!(stored("a", "/x", "").validate().is_err());
+        assert!(ArchivePasswordManager::new().upsert(stored("a", "/x", "")).is_err());
+        // 密码不会被序列化
+        assert!(!serde_json::to_string(&stored("a", "/x", "secret")).unwrap().contains("secret"));
+    }
+}
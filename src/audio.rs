@@ -0,0 +1,420 @@
+//! Audio tags and playlists / 音频标签与播放列表
+//!
+//! This module handles:
+//! - Telling audio files apart by extension / 按扩展名识别音频文件
+//! - How many leading bytes hold the tags, so only those are fetched / 计算标签所在的开头字节数，只读取这部分
+//! - ID3v2 / ID3v1 (MP3) and FLAC metadata blocks: tags, duration and cover art / 解析 ID3v2 / ID3v1（MP3）与 FLAC 元数据块：标签、时长与封面
+//! - Extended M3U playlists / 扩展 M3U 播放列表
+
+use std::io::Cursor;
+use serde::{Deserialize, Serialize};
+
+/// Audio extensions / 音频扩展名
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "flac", "m4a", "aac", "ogg", "oga", "opus", "wav", "wma", "ape", "alac", "aiff",
+];
+
+/// Bytes fetched first when probing tags / 探测标签时首次读取的字节数
+pub const PROBE_BYTES: u64 = 64 * 1024;
+
+/// Largest tag read, embedded covers can be big / 读取标签的上限（内嵌封面可能较大）
+pub const MAX_TAG_BYTES: u64 = 16 * 1024 * 1024;
+
+/// ID3v1 tag plus the enhanced ID3v1 block before it / ID3v1 标签及其前面的增强块
+pub const ID3V1_TAIL_BYTES: u64 = 128 + 227;
+
+/// Largest cover art returned / 返回的封面大小上限
+const MAX_COVER_BYTES: usize = 8 * 1024 * 1024;
+
+fn extension(name: &str) -> Option<String> {
+    let (_, ext) = name.rsplit_once('.')?;
+    Some(ext.to_ascii_lowercase())
+}
+
+/// Whether a file name is audio / 文件名是否为音频
+pub fn is_audio(name: &str) -> bool {
+    extension(name).is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Whether ID3v1 at the end of the file is worth fetching / 是否值得读取文件末尾的 ID3v1
+pub fn may_have_id3v1(name: &str) -> bool {
+    extension(name).as_deref() == Some("mp3")
+}
+
+/// Embedded cover art / 内嵌封面
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cover {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Tags of an audio file / 音频文件的标签
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    /// Seconds, from FLAC stream info or the ID3 TLEN frame / 时长（秒），来自 FLAC 流信息或 ID3 TLEN 帧
+    pub duration: Option<f64>,
+    pub has_cover: bool,
+}
+
+impl AudioTags {
+    /// `Artist - Title` for playlists, the file name without extension when untagged
+    /// 播放列表中显示的 `艺术家 - 标题`，没有标签时使用去掉扩展名的文件名
+    pub fn display_name(&self, file_name: &str) -> String {
+        let stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+        match (&self.artist, &self.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            _ => stem.to_string(),
+        }
+    }
+}
+
+/// Leading bytes needed to read the tags / 读取标签所需的开头字节数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagSpan {
+    /// The tags end at this offset / 标签在此偏移处结束
+    Complete(u64),
+    /// More bytes are needed to know, fetch at least this many / 需要更多字节才能确定，至少读取这么多
+    NeedAtLeast(u64),
+    /// No leading tags / 开头没有标签
+    None,
+}
+
+/// Find where the leading tags end / 查找开头标签的结束位置
+pub fn tag_span(head: &[u8]) -> TagSpan {
+    if head.len() < 10 {
+        return TagSpan::NeedAtLeast(10);
+    }
+    if &head[..3] == b"ID3" {
+        // 28位同步安全整数，不含10字节头；标志位0x10表示带10字节尾部
+        let size = head[6..10].iter().fold(0u64, |acc, b| (acc << 7) | (*b & 0x7f) as u64);
+        let footer = if head[5] & 0x10 != 0 { 10 } else { 0 };
+        return TagSpan::Complete(10 + size + footer);
+    }
+    if &head[..4] == b"fLaC" {
+        let mut offset = 4usize;
+        loop {
+            let Some(header) = head.get(offset..offset + 4) else {
+                return TagSpan::NeedAtLeast(offset as u64 + 4);
+            };
+            let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            offset += 4 + len;
+            if header[0] & 0x80 != 0 {
+                return TagSpan::Complete(offset as u64);
+            }
+        }
+    }
+    TagSpan::None
+}
+
+/// Parse the leading tags and the optional last bytes of the file (for ID3v1)
+/// 解析开头的标签与可选的文件末尾字节（用于 ID3v1）
+pub fn parse(head: &[u8], tail: Option<&[u8]>) -> (AudioTags, Option<Cover>) {
+    let (mut tags, cover) = if head.starts_with(b"fLaC") {
+        parse_flac(head)
+    } else if head.starts_with(b"ID3") {
+        parse_id3v2(head)
+    } else {
+        (AudioTags::default(), None)
+    };
+    if let Some(tail) = tail {
+        fill_from_id3v1(&mut tags, tail);
+    }
+    tags.has_cover = cover.is_some();
+    (tags, cover)
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+fn parse_id3v2(head: &[u8]) -> (AudioTags, Option<Cover>) {
+    use id3::TagLike;
+
+    let tag = match id3::Tag::read_from2(Cursor::new(head)) {
+        Ok(tag) => tag,
+        Err(e) => {
+            tracing::debug!("Invalid ID3v2 tag: {}", e);
+            return (AudioTags::default(), None);
+        }
+    };
+    let tags = AudioTags {
+        title: tag.title().and_then(non_empty),
+        artist: tag.artist().and_then(non_empty),
+        album: tag.album().and_then(non_empty),
+        album_artist: tag.album_artist().and_then(non_empty),
+        track: tag.track(),
+        disc: tag.disc(),
+        year: tag.year().or_else(|| tag.date_recorded().map(|d| d.year)),
+        genre: tag.genre_parsed().as_deref().and_then(non_empty),
+        duration: tag.duration().map(|ms| ms as f64 / 1000.0),
+        has_cover: false,
+    };
+    let cover = tag.pictures()
+        .find(|p| p.picture_type == id3::frame::PictureType::CoverFront)
+        .or_else(|| tag.pictures().next())
+        .filter(|p| p.data.len() <= MAX_COVER_BYTES)
+        .map(|p| Cover { mime_type: cover_mime(&p.mime_type, &p.data), data: p.data.clone() });
+    (tags, cover)
+}
+
+/// Fill fields ID3v2 left empty from ID3v1 / 用 ID3v1 补充 ID3v2 中缺少的字段
+fn fill_from_id3v1(tags: &mut AudioTags, tail: &[u8]) {
+    let Ok(v1) = id3::v1::Tag::read_from(Cursor::new(tail)) else {
+        return;
+    };
+    tags.title = tags.title.take().or_else(|| non_empty(&v1.title));
+    tags.artist = tags.artist.take().or_else(|| non_empty(&v1.artist));
+    tags.album = tags.album.take().or_else(|| non_empty(&v1.album));
+    tags.year = tags.year.or_else(|| v1.year.trim().parse().ok().filter(|y| *y > 0));
+    tags.track = tags.track.or(v1.track.filter(|t| *t > 0).map(u32::from));
+    tags.genre = tags.genre.take().or_else(|| v1.genre().and_then(non_empty));
+}
+
+/// FLAC metadata blocks: STREAMINFO, VORBIS_COMMENT and PICTURE / FLAC 元数据块
+fn parse_flac(head: &[u8]) -> (AudioTags, Option<Cover>) {
+    let mut tags = AudioTags::default();
+    let mut cover: Option<(u32, Cover)> = None;
+    let mut offset = 4usize;
+    while let Some(header) = head.get(offset..offset + 4) {
+        let block_type = header[0] & 0x7f;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let Some(block) = head.get(offset + 4..offset + 4 + len) else {
+            break;
+        };
+        match block_type {
+            0 => tags.duration = flac_duration(block),
+            4 => read_vorbis_comments(block, &mut tags),
+            6 => {
+                if let Some((picture_type, picture)) = read_flac_picture(block) {
+                    // 优先使用封面（类型3）
+                    let better = cover.as_ref().is_none_or(|(t, _)| *t != 3 && picture_type == 3);
+                    if better {
+                        cover = Some((picture_type, picture));
+                    }
+                }
+            }
+            _ => {}
+        }
+        offset += 4 + len;
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    (tags, cover.map(|(_, c)| c))
+}
+
+fn flac_duration(info: &[u8]) -> Option<f64> {
+    let bits = info.get(10..18)?;
+    let packed = u64::from_be_bytes(bits.try_into().ok()?);
+    let sample_rate = packed >> 44;
+    let total_samples = packed & 0xf_ffff_ffff;
+    (sample_rate > 0 && total_samples > 0).then(|| total_samples as f64 / sample_rate as f64)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    fn u32_le(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u32_be(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+}
+
+fn read_vorbis_comments(block: &[u8], tags: &mut AudioTags) {
+    let mut reader = Reader { data: block, pos: 0 };
+    let parsed = (|| {
+        let vendor_len = reader.u32_le()? as usize;
+        reader.bytes(vendor_len)?;
+        let count = reader.u32_le()?;
+        for _ in 0..count {
+            let len = reader.u32_le()? as usize;
+            let comment = String::from_utf8_lossy(reader.bytes(len)?);
+            let Some((key, value)) = comment.split_once('=') else {
+                continue;
+            };
+            let value = non_empty(value);
+            // 同一字段出现多次时保留第一个
+            let number = || value.as_deref().and_then(|v| v.split('/').next()?.trim().parse().ok());
+            match key.to_ascii_uppercase().as_str() {
+                "TITLE" if tags.title.is_none() => tags.title = value,
+                "ARTIST" if tags.artist.is_none() => tags.artist = value,
+                "ALBUM" if tags.album.is_none() => tags.album = value,
+                "ALBUMARTIST" | "ALBUM ARTIST" if tags.album_artist.is_none() => tags.album_artist = value,
+                "TRACKNUMBER" if tags.track.is_none() => tags.track = number(),
+                "DISCNUMBER" if tags.disc.is_none() => tags.disc = number(),
+                "GENRE" if tags.genre.is_none() => tags.genre = value,
+                "DATE" | "YEAR" if tags.year.is_none() => {
+                    tags.year = value.as_deref().and_then(|v| v.get(..4)?.parse().ok());
+                }
+                _ => {}
+            }
+        }
+        Some(())
+    })();
+    if parsed.is_none() {
+        tracing::debug!("Truncated FLAC vorbis comment block");
+    }
+}
+
+fn read_flac_picture(block: &[u8]) -> Option<(u32, Cover)> {
+    let mut reader = Reader { data: block, pos: 0 };
+    let picture_type = reader.u32_be()?;
+    let mime_len = reader.u32_be()? as usize;
+    let mime_type = String::from_utf8_lossy(reader.bytes(mime_len)?).to_string();
+    let desc_len = reader.u32_be()? as usize;
+    reader.bytes(desc_len)?;
+    // 宽、高、色深、索引颜色数
+    reader.bytes(16)?;
+    let data_len = reader.u32_be()? as usize;
+    if data_len > MAX_COVER_BYTES {
+        return None;
+    }
+    let data = reader.bytes(data_len)?.to_vec();
+    Some((picture_type, Cover { mime_type: cover_mime(&mime_type, &data), data }))
+}
+
+/// Cover MIME type, sniffed when the tag leaves it out / 封面 MIME 类型，标签中缺少时按内容判断
+fn cover_mime(declared: &str, data: &[u8]) -> String {
+    match declared.trim().to_ascii_lowercase().as_str() {
+        "" | "-->" | "image/" => crate::branding::sniff_image(data).unwrap_or("application/octet-stream").to_string(),
+        "jpg" | "image/jpg" => "image/jpeg".to_string(),
+        "png" => "image/png".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// A playlist entry / 播放列表条目
+#[derive(Debug, Clone)]
+pub struct PlaylistEntry {
+    pub title: String,
+    pub duration: Option<f64>,
+    pub url: String,
+}
+
+/// Extended M3U playlist, unknown durations are written as -1 / 扩展 M3U 播放列表，未知时长写为 -1
+pub fn to_m3u(entries: &[PlaylistEntry]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for entry in entries {
+        let duration = entry.duration.map_or(-1, |d| d.round() as i64);
+        // 标题中的换行会破坏格式
+        let title = entry.title.replace(['\r', '\n'], " ");
+        out.push_str(&format!("#EXTINF:{},{}\n{}\n", duration, title, entry.url));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flac_block(block_type: u8, last: bool, data: &[u8]) -> Vec<u8> {
+        let len = (data.len() as u32).to_be_bytes();
+        let mut out = vec![block_type | if last { 0x80 } else { 0 }, len[1], len[2], len[3]];
+        out.extend_from_slice(data);
+        out
+    }
+
+    #[test]
+    fn test_flac_tags() {
+        // 44100Hz, 441000 个采样 = 10 秒
+        let mut info = vec![0u8; 34];
+        let packed: u64 = (44100u64 << 44) | 441000;
+        info[10..18].copy_from_slice(&packed.to_be_bytes());
+
+        let mut comments = Vec::new();
+        comments.extend_from_slice(&3u32.to_le_bytes());
+        comments.extend_from_slice(b"lib");
+        let fields = ["TITLE=Song", "ARTIST=Band", "TRACKNUMBER=3/12", "DATE=2019-05-01"];
+        comments.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+        for field in fields {
+            comments.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            comments.extend_from_slice(field.as_bytes());
+        }
+
+        let mut picture = Vec::new();
+        picture.extend_from_slice(&3u32.to_be_bytes());
+        picture.extend_from_slice(&9u32.to_be_bytes());
+        picture.extend_from_slice(b"image/png");
+        picture.extend_from_slice(&0u32.to_be_bytes());
+        picture.extend_from_slice(&[0u8; 16]);
+        picture.extend_from_slice(&4u32.to_be_bytes());
+        picture.extend_from_slice(b"\x89PNG");
+
+        let mut file = b"fLaC".to_vec();
+        file.extend(flac_block(0, false, &info));
+        file.extend(flac_block(4, false, &comments));
+        file.extend(flac_block(6, true, &picture));
+        let end = file.len() as u64;
+        file.extend_from_slice(b"audio frames");
+
+        assert_eq!(tag_span(&file[..20]), TagSpan::NeedAtLeast(4 + 4 + 34 + 4));
+        assert_eq!(tag_span(&file), TagSpan::Complete(end));
+
+        let (tags, cover) = parse(&file, None);
+        assert_eq!(tags.title.as_deref(), Some("Song"));
+        assert_eq!(tags.display_name("x.flac"), "Band - Song");
+        assert_eq!((tags.track, tags.year), (Some(3), Some(2019)));
+        assert_eq!(tags.duration, Some(10.0));
+        assert!(tags.has_cover);
+        assert_eq!(cover.unwrap().mime_type, "image/png");
+    }
+
+    #[test]
+    fn test_id3() {
+        use id3::TagLike;
+
+        let mut tag = id3::Tag::new();
+        tag.set_title("Title");
+        tag.set_artist("Artist");
+        tag.set_duration(61500);
+        let mut file = Vec::new();
+        tag.write_to(&mut file, id3::Version::Id3v24).unwrap();
+        let end = file.len() as u64;
+        file.extend_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+        assert_eq!(tag_span(&file), TagSpan::Complete(end));
+
+        let mut tail = vec![0u8; 128];
+        tail[..3].copy_from_slice(b"TAG");
+        tail[63..68].copy_from_slice(b"Album");
+        tail[93..97].copy_from_slice(b"2001");
+        let (tags, cover) = parse(&file, Some(&tail));
+        assert_eq!(tags.display_name("a.mp3"), "Artist - Title");
+        assert_eq!(tags.album.as_deref(), Some("Album"));
+        assert_eq!((tags.year, tags.duration), (Some(2001), Some(61.5)));
+        assert!(cover.is_none());
+
+        assert_eq!(tag_span(b"\xff\xfb\x90\x00plain mp3"), TagSpan::None);
+        assert_eq!(AudioTags::default().display_name("01 Intro.mp3"), "01 Intro");
+    }
+
+    #[test]
+    fn test_m3u() {
+        let entries = vec![
+            PlaylistEntry { title: "A - B".to_string(), duration: Some(61.5), url: "/download/x".to_string() },
+            PlaylistEntry { title: "C\nD".to_string(), duration: None, url: "/download/y".to_string() },
+        ];
+        assert_eq!(to_m3u(&entries), "#EXTM3U\n#EXTINF:62,A - B\n/download/x\n#EXTINF:-1,C D\n/download/y\n");
+    }
+}
@@ -0,0 +1,95 @@
+//! Audit log / 审计日志
+//!
+//! Sensitive administrative actions, such as an administrator acting as another user, are
+//! recorded in `audit_logs` together with the acting administrator, the affected user, the
+//! client IP and the workspace. Entries are append-only; they are listed for administrators and
+//! never edited.
+//! 敏感的管理操作（例如管理员以其他用户身份操作）记录在 `audit_logs` 中，包含操作的管理员、受影响的用户、
+//! 客户端 IP 和工作区。记录只追加，仅供管理员查看，不可修改。
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::workspace;
+
+/// An administrator started acting as another user / 管理员开始以其他用户身份操作
+pub const IMPERSONATION_START: &str = "impersonation_start";
+/// The administrator returned to their own account / 管理员结束模拟，返回自己的账号
+pub const IMPERSONATION_STOP: &str = "impersonation_stop";
+
+/// Stored audit entry / 已记录的审计条目
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor_id: String,
+    pub actor_name: String,
+    pub action: String,
+    pub target_id: Option<String>,
+    pub target_name: Option<String>,
+    pub detail: Option<String>,
+    pub ip: Option<String>,
+    pub workspace_id: String,
+    pub created_at: String,
+}
+
+/// Audit event to record / 待记录的审计事件
+#[derive(Debug, Clone, Default)]
+pub struct AuditEvent<'a> {
+    pub actor_id: &'a str,
+    pub actor_name: &'a str,
+    pub action: &'a str,
+    pub target_id: Option<&'a str>,
+    pub target_name: Option<&'a str>,
+    pub detail: Option<&'a str>,
+    pub ip: Option<&'a str>,
+}
+
+/// Append an entry in the current workspace / 在当前工作区追加一条审计记录
+pub async fn record(db: &SqlitePool, event: AuditEvent<'_>) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO audit_logs (actor_id, actor_name, action, target_id, target_name, detail, ip, workspace_id, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(event.actor_id)
+    .bind(event.actor_name)
+    .bind(event.action)
+    .bind(event.target_id)
+    .bind(event.target_name)
+    .bind(event.detail)
+    .bind(event.ip)
+    .bind(workspace::current().id())
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Newest entries first, optionally filtered by action, with the total count
+/// 按时间倒序列出审计记录（可按操作过滤），并返回总数
+pub async fn list(
+    db: &SqlitePool,
+    action: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<AuditEntry>, i64), sqlx::Error> {
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_logs WHERE (? IS NULL OR action = ?)"
+    )
+    .bind(action)
+    .bind(action)
+    .fetch_one(db)
+    .await?;
+
+    let entries = sqlx::query_as::<_, AuditEntry>(
+        "SELECT * FROM audit_logs WHERE (? IS NULL OR action = ?)
+         ORDER BY id DESC LIMIT ? OFFSET ?"
+    )
+    .bind(action)
+    .bind(action)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(db)
+    .await?;
+
+    Ok((entries, total))
+}
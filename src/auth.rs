@@ -1,6 +1,12 @@
 use rand::Rng;
 use sha2::{Sha256, Digest};
 use chrono::{DateTime, Utc, Duration};
+use axum::{http::StatusCode, Json};
+use serde_json::{json, Value};
+use tower_cookies::Cookies;
+use yaolist_backend::workspace;
+
+use crate::state::AppState;
 
 pub const SESSION_COOKIE_NAME: &str = "yaolist_session";
 
@@ -26,3 +32,40 @@ pub fn create_session(user_id: &str) -> Session {
         expires_at: Utc::now() + Duration::days(7),
     }
 }
+
+/// 当前工作区内会话对应的用户ID与是否为管理员；其他工作区创建的会话视为无效
+pub async fn session_user(state: &AppState, cookies: &Cookies) -> Result<Option<(String, bool)>, sqlx::Error> {
+    let Some(session_id) = cookies.get(SESSION_COOKIE_NAME).map(|c| c.value().to_string()) else {
+        return Ok(None);
+    };
+    sqlx::query_as(
+        "SELECT u.id, u.is_admin FROM users u
+         JOIN sessions s ON u.id = s.user_id
+         WHERE s.id = ? AND s.workspace_id = ? AND s.expires_at > datetime('now') AND u.enabled = 1"
+    )
+    .bind(&session_id)
+    .bind(workspace::current().id())
+    .fetch_optional(&state.db)
+    .await
+}
+
+/// 验证管理员权限，返回管理员的用户ID：会话必须属于当前工作区
+pub async fn require_admin(state: &AppState, cookies: &Cookies) -> Result<String, (StatusCode, Json<Value>)> {
+    if cookies.get(SESSION_COOKIE_NAME).is_none() {
+        return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "未登录"}))));
+    }
+    let user = session_user(state, cookies).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "服务器错误"}))))?;
+    match user {
+        Some((user_id, true)) => Ok(user_id),
+        _ => Err((StatusCode::FORBIDDEN, Json(json!({"error": "需要管理员权限"})))),
+    }
+}
+
+/// 验证管理全局资源（存储、用户组、实例级设置）的权限：还必须在默认工作区
+pub async fn require_instance_admin(state: &AppState, cookies: &Cookies) -> Result<String, (StatusCode, Json<Value>)> {
+    if !workspace::current().is_default() {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "该功能仅能在默认工作区中管理"}))));
+    }
+    require_admin(state, cookies).await
+}
@@ -0,0 +1,110 @@
+//! Request body size limits / 请求体大小限制
+//!
+//! `body_limit_middleware` picks the limit of a route from `limits` in the config (live): a
+//! `route_body_limits` entry with the longest matching prefix, else `max_upload_size` for upload
+//! routes and `max_body_size` for the rest. A declared `Content-Length` above the limit is
+//! rejected with 413 before the body is read. Other bodies are capped while being read, except
+//! on the streaming upload routes which get the limit as a [`BodyLimit`] extension and stop the
+//! copy themselves, so they can also apply the per-group upload size.
+//! `body_limit_middleware` 按配置中的 `limits`（即时生效）确定路由的上限：`route_body_limits` 中最长匹配前缀的条目，
+//! 否则上传接口使用 `max_upload_size`，其余使用 `max_body_size`。声明的 `Content-Length` 超过上限时在读取请求体之前
+//! 返回 413。其余请求在读取时截断；流式上传接口例外，它们从 [`BodyLimit`] 扩展取得上限并自行中止写入，
+//! 以便同时应用用户组的上传上限。
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http_body_util::Limited;
+
+use crate::config::LimitsConfig;
+use crate::error::ApiError;
+
+/// Upload routes, limited by `max_upload_size` / 上传接口，受 `max_upload_size` 限制
+const UPLOAD_ROUTES: &[&str] = &["/api/fs/upload", "/api/fs/write", "/dav"];
+
+/// Upload routes that stream the body and enforce the limit themselves / 流式读取请求体并自行执行上限的上传接口
+const STREAMING_ROUTES: &[&str] = &["/api/fs/upload", "/api/fs/write"];
+
+/// Body limit of the current request in bytes / 当前请求的请求体上限（字节）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimit(pub u64);
+
+/// Whether `path` is `prefix` or below it / `path` 是否为 `prefix` 或其子路径
+fn under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Limit of a route, `None` when unlimited / 路由的请求体上限，不限时为 `None`
+pub fn route_limit(limits: &LimitsConfig, path: &str) -> Option<u64> {
+    let limit = limits.route_body_limits.iter()
+        .filter(|(prefix, _)| under(path, prefix))
+        .max_by_key(|(prefix, _)| prefix.trim_end_matches('/').len())
+        .map(|(_, limit)| *limit)
+        .unwrap_or_else(|| {
+            if UPLOAD_ROUTES.iter().any(|route| under(path, route)) {
+                limits.max_upload_size
+            } else {
+                limits.max_body_size
+            }
+        });
+    (limit > 0).then_some(limit)
+}
+
+/// 413 response naming the limit / 说明上限的 413 响应
+pub fn too_large(limit: u64) -> ApiError {
+    ApiError::PayloadTooLarge(format!("请求体超过上限 {} 字节", limit))
+}
+
+/// Enforce the body limit of each route / 执行各路由的请求体上限
+pub async fn body_limit_middleware(mut req: Request, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    let Some(limit) = route_limit(&crate::config::config().limits, &path) else {
+        return next.run(req).await;
+    };
+
+    let declared = req.headers().get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > limit) {
+        return too_large(limit).into_response();
+    }
+
+    req.extensions_mut().insert(BodyLimit(limit));
+    if !STREAMING_ROUTES.iter().any(|route| under(&path, route)) {
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+        req = req.map(|body| Body::new(Limited::new(body, limit)));
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_limit() {
+        let mut limits = LimitsConfig {
+            max_body_size: 100,
+            max_upload_size: 0,
+            route_body_limits: [("/api/admin", 1000), ("/api/admin/backup/", 0), ("/dav/photos", 5000)]
+                .into_iter()
+                .map(|(prefix, limit)| (prefix.to_string(), limit))
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(route_limit(&limits, "/api/auth/login"), Some(100));
+        assert_eq!(route_limit(&limits, "/api/fs/upload"), None);
+        assert_eq!(route_limit(&limits, "/api/fs/uploads"), Some(100));
+        assert_eq!(route_limit(&limits, "/dav/docs/a.txt"), None);
+        assert_eq!(route_limit(&limits, "/dav/photos/a.jpg"), Some(5000));
+        assert_eq!(route_limit(&limits, "/api/admin/users"), Some(1000));
+        assert_eq!(route_limit(&limits, "/api/admin/backup/import"), None);
+
+        limits.max_upload_size = 10;
+        assert_eq!(route_limit(&limits, "/api/fs/write"), Some(10));
+    }
+}
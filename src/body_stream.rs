@@ -0,0 +1,168 @@
+//! Streaming request bodies into driver writers / 将请求体流式写入驱动 writer
+//!
+//! Upload handlers copy the body straight into `open_writer` instead of collecting it first.
+//! At most [`WRITE_BUFFER_SIZE`] bytes (plus one incoming frame) are held in memory: the next
+//! frame is only pulled after the previous batch was written, so a slow storage backend slows
+//! down the client instead of filling up the server's memory. A failed write or a body larger
+//! than the declared size stops reading immediately.
+//! 上传接口将请求体直接写入 `open_writer`，不先读入内存。内存中最多保留 [`WRITE_BUFFER_SIZE`]
+//! 字节（外加一个到达的数据帧）：上一批写入完成后才读取下一帧，存储端较慢时减慢客户端发送而
+//! 不是占满服务器内存。写入失败或请求体超过声明大小时立即停止读取。
+
+use std::fmt;
+
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Bytes batched before each write to the driver / 每次写入驱动前累积的字节数
+pub const WRITE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Why streaming a body stopped / 请求体流式写入中止的原因
+#[derive(Debug)]
+pub enum BodyStreamError {
+    /// Reading the request body failed (client disconnected) / 读取请求体失败（客户端断开）
+    Body(String),
+    /// The driver writer failed / 驱动 writer 写入失败
+    Write(std::io::Error),
+    /// The body exceeded this many bytes / 请求体超过该字节数
+    TooLarge(u64),
+}
+
+impl fmt::Display for BodyStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyStreamError::Body(e) => write!(f, "读取请求体失败: {}", e),
+            BodyStreamError::Write(e) => write!(f, "写入失败: {}", e),
+            BodyStreamError::TooLarge(limit) => write!(f, "请求体超过 {} 字节", limit),
+        }
+    }
+}
+
+impl std::error::Error for BodyStreamError {}
+
+impl From<BodyStreamError> for crate::error::ApiError {
+    fn from(e: BodyStreamError) -> Self {
+        use crate::error::ApiError;
+        match e {
+            BodyStreamError::Body(_) => ApiError::BadRequest(e.to_string()),
+            BodyStreamError::TooLarge(_) => ApiError::PayloadTooLarge(e.to_string()),
+            BodyStreamError::Write(_) => ApiError::driver(e.to_string()),
+        }
+    }
+}
+
+/// Copy `body` into `writer`, calling `on_written` with the running total after each write
+/// The writer is not shut down so callers can keep writing chunks of the same file.
+/// 将 `body` 写入 `writer`，每次写入后以累计字节数调用 `on_written`。
+/// 不关闭 writer，便于调用方继续写入同一文件的后续分片。
+pub async fn stream_into<S, E, W>(
+    body: S,
+    writer: &mut W,
+    max_bytes: Option<u64>,
+    mut on_written: impl FnMut(u64),
+) -> Result<u64, BodyStreamError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: fmt::Display,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut body = std::pin::pin!(body);
+    let mut buffer = BytesMut::with_capacity(WRITE_BUFFER_SIZE);
+    let mut received: u64 = 0;
+    let mut written: u64 = 0;
+
+    while let Some(frame) = body.next().await {
+        let frame = frame.map_err(|e| BodyStreamError::Body(e.to_string()))?;
+        received += frame.len() as u64;
+        if let Some(limit) = max_bytes {
+            if received > limit {
+                return Err(BodyStreamError::TooLarge(limit));
+            }
+        }
+        buffer.extend_from_slice(&frame);
+        if buffer.len() >= WRITE_BUFFER_SIZE {
+            writer.write_all(&buffer).await.map_err(BodyStreamError::Write)?;
+            written += buffer.len() as u64;
+            buffer.clear();
+            on_written(written);
+        }
+    }
+
+    if !buffer.is_empty() {
+        writer.write_all(&buffer).await.map_err(BodyStreamError::Write)?;
+        written += buffer.len() as u64;
+        on_written(written);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+
+    fn frames(count: usize, size: usize) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+        futures::stream::iter((0..count).map(move |i| Ok(Bytes::from(vec![i as u8; size]))))
+    }
+
+    /// Writer that fails once it has accepted `capacity` bytes / 写满 `capacity` 字节后报错的 writer
+    struct FullDisk {
+        capacity: usize,
+        written: usize,
+    }
+
+    impl AsyncWrite for FullDisk {
+        fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+            if self.written + buf.len() > self.capacity {
+                return Poll::Ready(Err(std::io::Error::other("no space left")));
+            }
+            self.written += buf.len();
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_into_batches_writes() {
+        let mut out = Vec::new();
+        let mut reports = Vec::new();
+        let total = stream_into(frames(40, 64 * 1024), &mut out, None, |n| reports.push(n)).await.unwrap();
+        assert_eq!(total, 40 * 64 * 1024);
+        assert_eq!(out.len(), 40 * 64 * 1024);
+        assert_eq!(&out[64 * 1024..64 * 1024 + 3], [1, 1, 1]);
+        // 16 frames per 1 MiB batch, then the remainder / 每 16 帧写入一次，最后写入剩余部分
+        assert_eq!(reports, [1024 * 1024, 2 * 1024 * 1024, 40 * 64 * 1024]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_into_rejects_oversized_body() {
+        let mut out = Vec::new();
+        let err = stream_into(frames(4, 100), &mut out, Some(250), |_| {}).await.unwrap_err();
+        assert!(matches!(err, BodyStreamError::TooLarge(250)));
+        assert!(out.is_empty());
+        assert_eq!(stream_into(frames(4, 100), &mut out, Some(400), |_| {}).await.unwrap(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_stream_into_stops_reading_after_write_failure() {
+        let pulled = AtomicUsize::new(0);
+        let body = frames(64, 64 * 1024).inspect(|_| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+        });
+        let mut disk = FullDisk { capacity: 1024 * 1024, written: 0 };
+        let err = stream_into(body, &mut disk, None, |_| {}).await.unwrap_err();
+        assert!(matches!(err, BodyStreamError::Write(_)));
+        // The second batch failed, the rest of the body was never read / 第二批写入失败，其余请求体未被读取
+        assert_eq!(pulled.load(Ordering::SeqCst), 32);
+    }
+}
@@ -0,0 +1,232 @@
+//! E-books: PDF and EPUB / 电子书：PDF 与 EPUB
+//!
+//! This module handles:
+//! - Telling books apart by extension / 按扩展名识别电子书
+//! - Parsing container.xml and the OPF package: metadata, manifest and reading order / 解析 container.xml 与 OPF：元数据、清单与阅读顺序
+//!
+//! EPUB members are read by byte range through [`crate::archive`], so a book is never downloaded whole.
+//! EPUB 的成员通过 [`crate::archive`] 按字节范围读取，无需下载整本书。
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+
+/// Book format / 电子书格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookFormat {
+    Pdf,
+    Epub,
+}
+
+/// Book format of a file name / 文件名对应的电子书格式
+pub fn format_of(name: &str) -> Option<BookFormat> {
+    let (_, ext) = name.rsplit_once('.')?;
+    match ext.to_ascii_lowercase().as_str() {
+        "pdf" => Some(BookFormat::Pdf),
+        "epub" => Some(BookFormat::Epub),
+        _ => None,
+    }
+}
+
+/// Resolve an href against the directory of the document it appears in / 相对所在文档的目录解析链接
+pub fn resolve_href(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or("");
+    let href = urlencoding::decode(href).map(|s| s.into_owned()).unwrap_or_else(|_| href.to_string());
+    let mut parts: Vec<&str> = match base.rsplit_once('/') {
+        Some((dir, _)) if !href.starts_with('/') => dir.split('/').collect(),
+        _ => Vec::new(),
+    };
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// Path of the OPF package from META-INF/container.xml / 从 META-INF/container.xml 读取 OPF 路径
+pub fn container_rootfile(xml: &[u8]) -> Option<String> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf).ok()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"rootfile" => {
+                return attr(&e, b"full-path").map(|p| p.trim_start_matches('/').to_string());
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// A manifest item, href resolved to the member name / 清单条目（链接已解析为成员名）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestItem {
+    pub id: String,
+    pub href: String,
+    pub media_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<String>,
+}
+
+/// EPUB package: metadata, manifest and reading order / EPUB 包：元数据、清单与阅读顺序
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EpubPackage {
+    /// Member name of the OPF / OPF 的成员名
+    pub package: String,
+    pub title: Option<String>,
+    pub creator: Option<String>,
+    pub language: Option<String>,
+    pub manifest: Vec<ManifestItem>,
+    /// Member names in reading order / 按阅读顺序排列的成员名
+    pub spine: Vec<String>,
+    /// EPUB 3 navigation document or EPUB 2 NCX / EPUB 3 导航文档或 EPUB 2 NCX
+    pub toc: Option<String>,
+    pub cover: Option<String>,
+}
+
+/// Parse the OPF package at member `package` / 解析成员 `package` 处的 OPF
+pub fn parse_package(xml: &[u8], package: &str) -> Option<EpubPackage> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    let mut pkg = EpubPackage { package: package.to_string(), ..Default::default() };
+    let mut spine_ids = Vec::new();
+    let mut ncx_id = None;
+    let mut cover_id = None;
+    let mut field: Option<&'static str> = None;
+    loop {
+        match reader.read_event_into(&mut buf).ok()? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"title" => field = Some("title"),
+                b"creator" => field = Some("creator"),
+                b"language" => field = Some("language"),
+                b"meta" if attr(&e, b"name").as_deref() == Some("cover") => cover_id = attr(&e, b"content"),
+                b"item" => {
+                    if let (Some(id), Some(href)) = (attr(&e, b"id"), attr(&e, b"href")) {
+                        pkg.manifest.push(ManifestItem {
+                            id,
+                            href: resolve_href(package, &href),
+                            media_type: attr(&e, b"media-type").unwrap_or_default(),
+                            properties: attr(&e, b"properties"),
+                        });
+                    }
+                }
+                b"spine" => ncx_id = attr(&e, b"toc"),
+                b"itemref" if attr(&e, b"linear").as_deref() != Some("no") => {
+                    if let Some(id) = attr(&e, b"idref") {
+                        spine_ids.push(id);
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(t) => {
+                if let Some(name) = field.take() {
+                    let text = t.unescape().map(|s| s.trim().to_string()).unwrap_or_default();
+                    let slot = match name {
+                        "title" => &mut pkg.title,
+                        "creator" => &mut pkg.creator,
+                        _ => &mut pkg.language,
+                    };
+                    // 多个同名元素时保留第一个
+                    if slot.is_none() && !text.is_empty() {
+                        *slot = Some(text);
+                    }
+                }
+            }
+            Event::End(_) => field = None,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    let has_property = |item: &ManifestItem, name: &str| {
+        item.properties.as_deref().is_some_and(|p| p.split_whitespace().any(|p| p == name))
+    };
+    let href_of = |id: &str| pkg.manifest.iter().find(|i| i.id == id).map(|i| i.href.clone());
+    pkg.spine = spine_ids.iter().filter_map(|id| href_of(id)).collect();
+    pkg.toc = pkg.manifest.iter().find(|i| has_property(i, "nav")).map(|i| i.href.clone())
+        .or_else(|| ncx_id.as_deref().and_then(href_of));
+    pkg.cover = pkg.manifest.iter().find(|i| has_property(i, "cover-image")).map(|i| i.href.clone())
+        .or_else(|| cover_id.as_deref().and_then(href_of));
+    Some(pkg)
+}
+
+/// Content type of a member, XHTML is served as XHTML / 成员的内容类型（XHTML 按 XHTML 返回）
+pub fn member_content_type(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    if lower.ends_with(".xhtml") || lower.ends_with(".xht") {
+        return "application/xhtml+xml".to_string();
+    }
+    if lower.ends_with(".ncx") {
+        return "application/x-dtbncx+xml".to_string();
+    }
+    if lower.ends_with(".opf") {
+        return "application/oebps-package+xml".to_string();
+    }
+    mime_guess::from_path(name).first_or_octet_stream().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package() {
+        let container = br#"<?xml version="1.0"?>
+            <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+              <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+            </container>"#;
+        let opf_path = container_rootfile(container).unwrap();
+        assert_eq!(opf_path, "OEBPS/content.opf");
+
+        let opf = br#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:title>Rust &amp; Me</dc:title>
+                <dc:creator>Ferris</dc:creator>
+                <dc:language>zh</dc:language>
+              </metadata>
+              <manifest>
+                <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+                <item id="c1" href="text/ch%201.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c2" href="text/ch2.xhtml" media-type="application/xhtml+xml"/>
+                <item id="img" href="../images/cover.jpg" media-type="image/jpeg" properties="cover-image"/>
+              </manifest>
+              <spine>
+                <itemref idref="c1"/>
+                <itemref idref="nav" linear="no"/>
+                <itemref idref="c2"/>
+              </spine>
+            </package>"#;
+        let pkg = parse_package(opf, &opf_path).unwrap();
+        assert_eq!(pkg.title.as_deref(), Some("Rust & Me"));
+        assert_eq!(pkg.creator.as_deref(), Some("Ferris"));
+        assert_eq!(pkg.spine, vec!["OEBPS/text/ch 1.xhtml", "OEBPS/text/ch2.xhtml"]);
+        assert_eq!(pkg.toc.as_deref(), Some("OEBPS/nav.xhtml"));
+        assert_eq!(pkg.cover.as_deref(), Some("images/cover.jpg"));
+    }
+
+    #[test]
+    fn test_format_and_href() {
+        assert_eq!(format_of("Book.PDF"), Some(BookFormat::Pdf));
+        assert_eq!(format_of("novel.epub"), Some(BookFormat::Epub));
+        assert_eq!(format_of("notes.txt"), None);
+        assert_eq!(resolve_href("a/b/c.xhtml", "../img/x.png#top"), "a/img/x.png");
+        assert_eq!(resolve_href("content.opf", "./ch1.xhtml"), "ch1.xhtml");
+        assert_eq!(member_content_type("OEBPS/ch1.xhtml"), "application/xhtml+xml");
+    }
+}
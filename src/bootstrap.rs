@@ -0,0 +1,493 @@
+//! Declarative bootstrap file / 声明式初始化配置
+//!
+//! `bootstrap.yaml` (next to `config.json`, or the path in `YAOLIST_BOOTSTRAP`) lists settings,
+//! groups, users, mounts and metas. It is reconciled into the database on every start before
+//! anything is loaded: missing records are created and listed fields are updated, records that are
+//! not listed are left alone and nothing is deleted. The whole file is applied in one transaction.
+//! `bootstrap.yaml`（与 `config.json` 同目录，或 `YAOLIST_BOOTSTRAP` 指定的路径）声明站点设置、
+//! 用户组、用户、挂载与元信息，每次启动时在加载任何数据之前同步到数据库：不存在的记录会被创建，
+//! 文件中列出的字段会被更新，未列出的记录保持不变，从不删除。整个文件在一个事务中应用。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+use yaolist_backend::utils::fix_and_clean_path;
+use yaolist_backend::workspace::DEFAULT_WORKSPACE_ID;
+
+/// Environment variable overriding the bootstrap file path / 覆盖初始化配置路径的环境变量
+pub const BOOTSTRAP_ENV: &str = "YAOLIST_BOOTSTRAP";
+
+/// Bootstrap file / 初始化配置文件
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BootstrapFile {
+    /// Site settings, key to scalar value / 站点设置，键到标量值
+    #[serde(default)]
+    pub settings: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub groups: Vec<GroupSpec>,
+    #[serde(default)]
+    pub users: Vec<UserSpec>,
+    #[serde(default)]
+    pub mounts: Vec<MountSpec>,
+    #[serde(default)]
+    pub metas: Vec<MetaSpec>,
+}
+
+/// User group, matched by name. Omitted permissions keep their current value
+/// 用户组，按名称匹配。未填写的权限保持原值
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupSpec {
+    pub name: String,
+    pub description: Option<String>,
+    pub root_path: Option<String>,
+    pub is_admin: Option<bool>,
+    pub allow_direct_link: Option<bool>,
+    pub allow_share: Option<bool>,
+    pub show_hidden_files: Option<bool>,
+    pub no_password_access: Option<bool>,
+    pub add_offline_download: Option<bool>,
+    pub create_upload: Option<bool>,
+    pub rename_files: Option<bool>,
+    pub move_files: Option<bool>,
+    pub copy_files: Option<bool>,
+    pub delete_files: Option<bool>,
+    pub read_files: Option<bool>,
+    pub read_compressed: Option<bool>,
+    pub extract_files: Option<bool>,
+    pub webdav_enabled: Option<bool>,
+    pub ftp_enabled: Option<bool>,
+}
+
+/// User, matched by username. The password is only rewritten when it no longer matches,
+/// listed groups are joined but other memberships are kept
+/// 用户，按用户名匹配。密码仅在不一致时重写；加入列出的用户组，保留其他组成员关系
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserSpec {
+    pub username: String,
+    /// Required when the user does not exist yet / 用户不存在时必填
+    pub password: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub root_path: Option<String>,
+    pub is_admin: Option<bool>,
+    pub enabled: Option<bool>,
+    /// Workspace of a new user / 新用户所属工作区
+    pub workspace_id: Option<String>,
+    /// Group names / 用户组名称
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+/// Storage mount, matched by mount path / 存储挂载，按挂载路径匹配
+#[derive(Debug, Deserialize)]
+pub struct MountSpec {
+    pub mount_path: String,
+    pub driver_type: String,
+    #[serde(default)]
+    pub order: Option<i32>,
+    #[serde(default)]
+    pub remark: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub config: Value,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Path meta, matched by path. Omitted fields keep their current value
+/// 路径元信息，按路径匹配。未填写的字段保持原值
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetaSpec {
+    pub path: String,
+    pub password: Option<String>,
+    pub p_sub: Option<bool>,
+    pub write: Option<bool>,
+    pub w_sub: Option<bool>,
+    pub hide: Option<String>,
+    pub h_sub: Option<bool>,
+    pub readme: Option<String>,
+    pub r_sub: Option<bool>,
+    pub header: Option<String>,
+    pub header_sub: Option<bool>,
+    pub preview_only: Option<bool>,
+    pub po_sub: Option<bool>,
+    pub watermark: Option<bool>,
+    pub wm_sub: Option<bool>,
+}
+
+/// Whether a reconcile step created or updated a record / 同步时创建还是更新了记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applied {
+    Created,
+    Updated,
+}
+
+/// Bootstrap file path / 初始化配置文件路径
+pub fn bootstrap_path() -> PathBuf {
+    match std::env::var(BOOTSTRAP_ENV) {
+        Ok(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+        _ => std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join("bootstrap.yaml"),
+    }
+}
+
+/// Reconcile the bootstrap file if present / 存在初始化配置时同步到数据库
+pub async fn apply_if_present(pool: &SqlitePool) -> Result<()> {
+    let path = bootstrap_path();
+    if !path.exists() {
+        if std::env::var(BOOTSTRAP_ENV).is_ok_and(|p| !p.trim().is_empty()) {
+            bail!("Bootstrap file {} not found", path.display());
+        }
+        return Ok(());
+    }
+    let file = load(&path)?;
+    apply(pool, &file).await
+        .with_context(|| format!("Failed to apply bootstrap file {}", path.display()))?;
+    tracing::info!(
+        "Bootstrap file {} applied: {} settings, {} groups, {} users, {} mounts, {} metas",
+        path.display(), file.settings.len(), file.groups.len(), file.users.len(), file.mounts.len(), file.metas.len()
+    );
+    Ok(())
+}
+
+/// Parse a bootstrap file / 解析初始化配置文件
+pub fn load(path: &Path) -> Result<BootstrapFile> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_yaml::from_str(&text).with_context(|| format!("Invalid bootstrap file {}", path.display()))
+}
+
+/// Reconcile all sections in one transaction / 在一个事务中同步全部内容
+pub async fn apply(pool: &SqlitePool, file: &BootstrapFile) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let now = Utc::now().to_rfc3339();
+
+    for (key, value) in &file.settings {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            Value::Bool(_) | Value::Number(_) => value.to_string(),
+            _ => bail!("Setting {} must be a string, number or boolean", key),
+        };
+        sqlx::query(
+            "INSERT INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at"
+        )
+        .bind(key)
+        .bind(&value)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for group in &file.groups {
+        let applied = upsert_group(&mut tx, group).await?;
+        tracing::info!("Bootstrap: {:?} group {}", applied, group.name);
+    }
+    for user in &file.users {
+        let applied = upsert_user(&mut tx, user).await?;
+        tracing::info!("Bootstrap: {:?} user {}", applied, user.username);
+    }
+    for mount in &file.mounts {
+        let (id, applied) = upsert_mount(&mut tx, mount).await?;
+        tracing::info!("Bootstrap: {:?} mount {} (id {})", applied, mount.mount_path, id);
+    }
+    for meta in &file.metas {
+        let applied = upsert_meta(&mut tx, meta).await?;
+        tracing::info!("Bootstrap: {:?} meta {}", applied, meta.path);
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn upsert_group(conn: &mut SqliteConnection, group: &GroupSpec) -> Result<Applied> {
+    let name = group.name.trim();
+    if name.is_empty() {
+        bail!("Group name must not be empty");
+    }
+    let now = Utc::now().to_rfc3339();
+    let root_path = group.root_path.as_deref().map(fix_and_clean_path);
+    let exists: Option<(i64,)> = sqlx::query_as("SELECT id FROM user_groups WHERE name = ?")
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    // 新建时未填写的权限默认关闭（读取默认开启），更新时保持原值
+    let sql = if exists.is_some() {
+        "UPDATE user_groups SET
+            description = COALESCE(?, description), root_path = COALESCE(?, root_path),
+            is_admin = COALESCE(?, is_admin), allow_direct_link = COALESCE(?, allow_direct_link),
+            allow_share = COALESCE(?, allow_share), show_hidden_files = COALESCE(?, show_hidden_files),
+            no_password_access = COALESCE(?, no_password_access), add_offline_download = COALESCE(?, add_offline_download),
+            create_upload = COALESCE(?, create_upload), rename_files = COALESCE(?, rename_files),
+            move_files = COALESCE(?, move_files), copy_files = COALESCE(?, copy_files),
+            delete_files = COALESCE(?, delete_files), read_files = COALESCE(?, read_files),
+            read_compressed = COALESCE(?, read_compressed), extract_files = COALESCE(?, extract_files),
+            webdav_enabled = COALESCE(?, webdav_enabled), ftp_enabled = COALESCE(?, ftp_enabled),
+            updated_at = ?
+         WHERE name = ?"
+    } else {
+        "INSERT INTO user_groups (
+            description, root_path, is_admin,
+            allow_direct_link, allow_share, show_hidden_files, no_password_access,
+            add_offline_download, create_upload, rename_files, move_files,
+            copy_files, delete_files, read_files, read_compressed, extract_files,
+            webdav_enabled, ftp_enabled, created_at, updated_at, name
+        ) VALUES (?, COALESCE(?, '/'), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0),
+            COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0),
+            COALESCE(?, 1), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0), COALESCE(?, 0), ?, ?, ?)"
+    };
+    let mut query = sqlx::query(sql)
+        .bind(&group.description)
+        .bind(&root_path)
+        .bind(group.is_admin)
+        .bind(group.allow_direct_link)
+        .bind(group.allow_share)
+        .bind(group.show_hidden_files)
+        .bind(group.no_password_access)
+        .bind(group.add_offline_download)
+        .bind(group.create_upload)
+        .bind(group.rename_files)
+        .bind(group.move_files)
+        .bind(group.copy_files)
+        .bind(group.delete_files)
+        .bind(group.read_files)
+        .bind(group.read_compressed)
+        .bind(group.extract_files)
+        .bind(group.webdav_enabled)
+        .bind(group.ftp_enabled);
+    if exists.is_none() {
+        query = query.bind(&now);
+    }
+    query.bind(&now).bind(name).execute(&mut *conn).await?;
+
+    Ok(if exists.is_some() { Applied::Updated } else { Applied::Created })
+}
+
+async fn upsert_user(conn: &mut SqliteConnection, user: &UserSpec) -> Result<Applied> {
+    let username = user.username.trim();
+    if username.is_empty() {
+        bail!("Username must not be empty");
+    }
+    let now = Utc::now().to_rfc3339();
+    let root_path = user.root_path.as_deref().map(fix_and_clean_path);
+    let existing: Option<(String, String)> = sqlx::query_as("SELECT id, password_hash FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let (user_id, applied) = match existing {
+        Some((id, password_hash)) => {
+            // 密码未变化时不重新哈希，避免每次启动都改写
+            let password_hash = match &user.password {
+                Some(password) if !bcrypt::verify(password, &password_hash).unwrap_or(false) => {
+                    Some(bcrypt::hash(password, bcrypt::DEFAULT_COST)?)
+                }
+                _ => None,
+            };
+            sqlx::query(
+                "UPDATE users SET password_hash = COALESCE(?, password_hash), email = COALESCE(?, email),
+                    phone = COALESCE(?, phone), root_path = COALESCE(?, root_path),
+                    is_admin = COALESCE(?, is_admin), enabled = COALESCE(?, enabled), updated_at = ?
+                 WHERE id = ?"
+            )
+            .bind(&password_hash)
+            .bind(&user.email)
+            .bind(&user.phone)
+            .bind(&root_path)
+            .bind(user.is_admin)
+            .bind(user.enabled)
+            .bind(&now)
+            .bind(&id)
+            .execute(&mut *conn)
+            .await?;
+            (id, Applied::Updated)
+        }
+        None => {
+            let Some(password) = &user.password else {
+                bail!("password is required to create user {}", username);
+            };
+            let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)?;
+            // 与管理接口一致的整数ID
+            let max_id: Option<(i64,)> = sqlx::query_as("SELECT MAX(CAST(id AS INTEGER)) FROM users WHERE id GLOB '[0-9]*'")
+                .fetch_optional(&mut *conn)
+                .await?;
+            let id = (max_id.map(|(id,)| id).unwrap_or(0) + 1).to_string();
+            sqlx::query(
+                "INSERT INTO users (id, unique_id, username, password_hash, email, phone, root_path, is_admin, enabled, two_factor_enabled, created_at, updated_at, workspace_id)
+                 VALUES (?, ?, ?, ?, ?, ?, COALESCE(?, '/'), ?, ?, 0, ?, ?, ?)"
+            )
+            .bind(&id)
+            .bind(Uuid::new_v4().to_string())
+            .bind(username)
+            .bind(&password_hash)
+            .bind(&user.email)
+            .bind(&user.phone)
+            .bind(&root_path)
+            .bind(user.is_admin.unwrap_or(false))
+            .bind(user.enabled.unwrap_or(true))
+            .bind(&now)
+            .bind(&now)
+            .bind(user.workspace_id.as_deref().unwrap_or(DEFAULT_WORKSPACE_ID))
+            .execute(&mut *conn)
+            .await?;
+            (id, Applied::Created)
+        }
+    };
+
+    for group in &user.groups {
+        let group_id: Option<(i64,)> = sqlx::query_as("SELECT id FROM user_groups WHERE name = ?")
+            .bind(group.trim())
+            .fetch_optional(&mut *conn)
+            .await?;
+        let Some((group_id,)) = group_id else {
+            bail!("Group {} of user {} does not exist", group, username);
+        };
+        sqlx::query("INSERT OR IGNORE INTO user_group_members (user_id, group_id, created_at) VALUES (?, ?, ?)")
+            .bind(&user_id)
+            .bind(group_id.to_string())
+            .bind(&now)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(applied)
+}
+
+/// Create or update a mount, returns the driver id / 创建或更新挂载，返回驱动ID
+pub async fn upsert_mount(conn: &mut SqliteConnection, mount: &MountSpec) -> Result<(String, Applied)> {
+    let mount_path = fix_and_clean_path(mount.mount_path.trim());
+    if mount.driver_type.trim().is_empty() {
+        bail!("driver_type is required for mount {}", mount_path);
+    }
+    let existing: Vec<(String, String)> = sqlx::query_as("SELECT name, config FROM drivers")
+        .fetch_all(&mut *conn)
+        .await?;
+    let existing_id = existing.into_iter()
+        .find(|(_, config)| {
+            serde_json::from_str::<Value>(config).ok()
+                .and_then(|c| c["mount_path"].as_str().map(fix_and_clean_path))
+                .is_some_and(|p| p == mount_path)
+        })
+        .map(|(id, _)| id);
+
+    let config = serde_json::to_string(&json!({
+        "driver_type": mount.driver_type,
+        "mount_path": mount_path,
+        "order": mount.order,
+        "remark": mount.remark,
+        "config": mount.config
+    }))?;
+    let now = Utc::now().to_rfc3339();
+
+    match existing_id {
+        Some(id) => {
+            sqlx::query("UPDATE drivers SET config = ?, enabled = ?, description = ?, updated_at = ? WHERE name = ?")
+                .bind(&config)
+                .bind(mount.enabled)
+                .bind(&mount_path)
+                .bind(&now)
+                .bind(&id)
+                .execute(&mut *conn)
+                .await?;
+            Ok((id, Applied::Updated))
+        }
+        None => {
+            // 与管理接口一致的数字驱动ID
+            let max_id: Option<(i64,)> = sqlx::query_as("SELECT COALESCE(MAX(CAST(name AS INTEGER)), 0) FROM drivers WHERE name GLOB '[0-9]*'")
+                .fetch_optional(&mut *conn)
+                .await?;
+            let id = (max_id.map(|r| r.0).unwrap_or(0) + 1).to_string();
+            sqlx::query(
+                "INSERT INTO drivers (name, version, description, enabled, config, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(&id)
+            .bind("1.0.0")
+            .bind(&mount_path)
+            .bind(mount.enabled)
+            .bind(&config)
+            .bind(&now)
+            .bind(&now)
+            .execute(&mut *conn)
+            .await?;
+            Ok((id, Applied::Created))
+        }
+    }
+}
+
+async fn upsert_meta(conn: &mut SqliteConnection, meta: &MetaSpec) -> Result<Applied> {
+    let path = fix_and_clean_path(meta.path.trim());
+    // 与元信息接口一致的时间格式
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let exists: Option<(i64,)> = sqlx::query_as("SELECT id FROM metas WHERE path = ? ORDER BY id LIMIT 1")
+        .bind(&path)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let query = match exists {
+        Some((id,)) => sqlx::query(
+            "UPDATE metas SET password = COALESCE(?, password), p_sub = COALESCE(?, p_sub),
+                write = COALESCE(?, write), w_sub = COALESCE(?, w_sub), hide = COALESCE(?, hide),
+                h_sub = COALESCE(?, h_sub), readme = COALESCE(?, readme), r_sub = COALESCE(?, r_sub),
+                header = COALESCE(?, header), header_sub = COALESCE(?, header_sub),
+                preview_only = COALESCE(?, preview_only), po_sub = COALESCE(?, po_sub),
+                watermark = COALESCE(?, watermark), wm_sub = COALESCE(?, wm_sub), updated_at = ?
+             WHERE id = ?"
+        )
+        .bind(&meta.password)
+        .bind(meta.p_sub)
+        .bind(meta.write)
+        .bind(meta.w_sub)
+        .bind(&meta.hide)
+        .bind(meta.h_sub)
+        .bind(&meta.readme)
+        .bind(meta.r_sub)
+        .bind(&meta.header)
+        .bind(meta.header_sub)
+        .bind(meta.preview_only)
+        .bind(meta.po_sub)
+        .bind(meta.watermark)
+        .bind(meta.wm_sub)
+        .bind(&now)
+        .bind(id),
+        None => sqlx::query(
+            "INSERT INTO metas (path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub,
+                preview_only, po_sub, watermark, wm_sub, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&path)
+        .bind(&meta.password)
+        .bind(meta.p_sub.unwrap_or(false))
+        .bind(meta.write.unwrap_or(false))
+        .bind(meta.w_sub.unwrap_or(false))
+        .bind(&meta.hide)
+        .bind(meta.h_sub.unwrap_or(false))
+        .bind(&meta.readme)
+        .bind(meta.r_sub.unwrap_or(false))
+        .bind(&meta.header)
+        .bind(meta.header_sub.unwrap_or(false))
+        .bind(meta.preview_only.unwrap_or(false))
+        .bind(meta.po_sub.unwrap_or(false))
+        .bind(meta.watermark.unwrap_or(false))
+        .bind(meta.wm_sub.unwrap_or(false))
+        .bind(&now)
+        .bind(&now),
+    };
+    query.execute(&mut *conn).await?;
+
+    Ok(if exists.is_some() { Applied::Updated } else { Applied::Created })
+}
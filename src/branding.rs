@@ -0,0 +1,360 @@
+//! Branding assets / 品牌资源
+//!
+//! Admins can replace the logo and favicon, set a site name and add custom CSS / JS snippets
+//! without rebuilding the embedded frontend. Everything is kept on disk below
+//! `<data_dir>/branding/<workspace id>/`, with `branding.json` holding the site name and the type,
+//! size and time of each asset. A workspace without its own asset uses the default workspace's.
+//! 管理员无需重新构建内嵌前端即可替换 Logo、网站图标，设置站点名称并添加自定义 CSS / JS 片段。
+//! 所有内容保存在 `<data_dir>/branding/<工作区 ID>/` 下，`branding.json` 记录站点名称以及各资源的类型、大小与更新时间。
+//! 工作区没有自己的资源时使用默认工作区的。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::workspace::DEFAULT_WORKSPACE_ID;
+
+/// Metadata file in each branding directory / 各品牌目录中的元数据文件
+const META_FILE: &str = "branding.json";
+/// Largest logo or favicon / Logo 与网站图标的大小上限
+pub const MAX_IMAGE_SIZE: usize = 2 * 1024 * 1024;
+/// Largest CSS or JS snippet / CSS 与 JS 片段的大小上限
+pub const MAX_SNIPPET_SIZE: usize = 256 * 1024;
+/// Longest site name / 站点名称的最大长度
+const MAX_SITE_NAME_CHARS: usize = 100;
+
+/// Replaceable asset / 可替换的资源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrandingAsset {
+    Logo,
+    Favicon,
+    CustomCss,
+    CustomJs,
+}
+
+impl BrandingAsset {
+    pub const ALL: [BrandingAsset; 4] = [Self::Logo, Self::Favicon, Self::CustomCss, Self::CustomJs];
+
+    /// Parse the name used in URLs / 解析 URL 中的资源名
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// Name in URLs and the file name on disk / URL 中的名称，也是磁盘上的文件名
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Logo => "logo",
+            Self::Favicon => "favicon",
+            Self::CustomCss => "custom.css",
+            Self::CustomJs => "custom.js",
+        }
+    }
+
+    fn is_image(&self) -> bool {
+        matches!(self, Self::Logo | Self::Favicon)
+    }
+
+    /// CSS / JS run on every page of the host, including other workspaces' `/w/<slug>` pages
+    /// and the instance admin's session, so only the default workspace may set them.
+    /// CSS / JS 会在同一域名的所有页面运行（包括其他 `/w/<slug>` 工作区与实例管理员的会话），仅默认工作区可设置
+    pub fn is_code(&self) -> bool {
+        matches!(self, Self::CustomCss | Self::CustomJs)
+    }
+
+    pub fn max_size(&self) -> usize {
+        if self.is_image() { MAX_IMAGE_SIZE } else { MAX_SNIPPET_SIZE }
+    }
+
+    /// Check an upload and return its content type / 校验上传内容并返回其内容类型
+    pub fn content_type_of(&self, data: &[u8]) -> Result<&'static str, String> {
+        if data.is_empty() {
+            return Err("file is empty".to_string());
+        }
+        if data.len() > self.max_size() {
+            return Err(format!("file is larger than {} KiB", self.max_size() / 1024));
+        }
+        match self {
+            Self::Logo | Self::Favicon => sniff_image(data)
+                .ok_or_else(|| "unsupported image, use PNG, JPEG, GIF, WebP, ICO or SVG".to_string()),
+            Self::CustomCss | Self::CustomJs => {
+                std::str::from_utf8(data).map_err(|_| "snippet must be UTF-8 text".to_string())?;
+                Ok(if *self == Self::CustomCss { "text/css; charset=utf-8" } else { "text/javascript; charset=utf-8" })
+            }
+        }
+    }
+}
+
+/// Image type from the first bytes / 根据文件头判断图片类型
+pub fn sniff_image(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if data.starts_with(&[0, 0, 1, 0]) {
+        return Some("image/x-icon");
+    }
+    let head = String::from_utf8_lossy(&data[..data.len().min(1024)]).to_ascii_lowercase();
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    if head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg")) {
+        return Some("image/svg+xml");
+    }
+    None
+}
+
+/// Stored asset / 已保存的资源
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetMeta {
+    pub content_type: String,
+    pub size: u64,
+    /// Unix seconds, also used to bust browser caches / Unix 秒，也用于让浏览器缓存失效
+    pub updated_at: i64,
+}
+
+/// Contents of `branding.json` / `branding.json` 的内容
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrandingMeta {
+    pub site_name: Option<String>,
+    /// Keyed by asset name / 以资源名为键
+    pub assets: BTreeMap<String, AssetMeta>,
+}
+
+/// Branding as seen by one workspace, after falling back to the default workspace
+/// 某工作区实际使用的品牌设置（已回退到默认工作区）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EffectiveBranding {
+    pub site_name: Option<String>,
+    /// Asset name to (owning workspace, metadata) / 资源名 → (所属工作区, 元数据)
+    pub assets: BTreeMap<String, (String, AssetMeta)>,
+}
+
+impl EffectiveBranding {
+    pub fn asset(&self, asset: BrandingAsset) -> Option<&AssetMeta> {
+        self.assets.get(asset.name()).map(|(_, meta)| meta)
+    }
+}
+
+/// Branding files on disk / 磁盘上的品牌文件
+pub struct BrandingStore {
+    root: PathBuf,
+    /// Serializes changes to `branding.json` / 串行化对 `branding.json` 的修改
+    write_lock: Mutex<()>,
+}
+
+impl BrandingStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            root: data_dir.join("branding"),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    fn dir(&self, workspace_id: &str) -> Result<PathBuf, String> {
+        let safe = !workspace_id.is_empty()
+            && workspace_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !safe {
+            return Err(format!("invalid workspace id '{}'", workspace_id));
+        }
+        Ok(self.root.join(workspace_id))
+    }
+
+    /// Metadata of one workspace's own branding / 某工作区自己的品牌元数据
+    pub async fn meta(&self, workspace_id: &str) -> BrandingMeta {
+        let Ok(dir) = self.dir(workspace_id) else {
+            return BrandingMeta::default();
+        };
+        match tokio::fs::read(dir.join(META_FILE)).await {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                tracing::warn!("Invalid branding metadata of workspace {}: {}", workspace_id, e);
+                BrandingMeta::default()
+            }),
+            Err(_) => BrandingMeta::default(),
+        }
+    }
+
+    async fn write_meta(&self, workspace_id: &str, meta: &BrandingMeta) -> Result<(), String> {
+        let dir = self.dir(workspace_id)?;
+        let data = serde_json::to_vec_pretty(meta).map_err(|e| e.to_string())?;
+        write_file(&dir, META_FILE, &data).await
+    }
+
+    /// Branding of a workspace with the default workspace filling the gaps
+    /// 工作区的品牌设置，缺少的部分使用默认工作区的
+    pub async fn effective(&self, workspace_id: &str) -> EffectiveBranding {
+        let own = self.meta(workspace_id).await;
+        let is_default = workspace_id == DEFAULT_WORKSPACE_ID;
+        let mut effective = EffectiveBranding {
+            site_name: own.site_name,
+            assets: own.assets.into_iter()
+                // 旧版本允许工作区上传的 CSS / JS 不再使用
+                .filter(|(k, _)| is_default || !BrandingAsset::from_name(k).is_some_and(|a| a.is_code()))
+                .map(|(k, v)| (k, (workspace_id.to_string(), v)))
+                .collect(),
+        };
+        if !is_default {
+            let default = self.meta(DEFAULT_WORKSPACE_ID).await;
+            if effective.site_name.is_none() {
+                effective.site_name = default.site_name;
+            }
+            for (name, meta) in default.assets {
+                effective.assets.entry(name).or_insert_with(|| (DEFAULT_WORKSPACE_ID.to_string(), meta));
+            }
+        }
+        effective
+    }
+
+    /// Read an asset as the workspace sees it / 按工作区可见的内容读取资源
+    pub async fn read(&self, workspace_id: &str, asset: BrandingAsset) -> Option<(Vec<u8>, AssetMeta)> {
+        let effective = self.effective(workspace_id).await;
+        let (owner, meta) = effective.assets.get(asset.name())?;
+        let data = tokio::fs::read(self.dir(owner).ok()?.join(asset.name())).await.ok()?;
+        Some((data, meta.clone()))
+    }
+
+    /// Validate and save an asset / 校验并保存资源
+    pub async fn save(&self, workspace_id: &str, asset: BrandingAsset, data: &[u8]) -> Result<AssetMeta, String> {
+        if asset.is_code() && workspace_id != DEFAULT_WORKSPACE_ID {
+            return Err(format!("{} can only be set on the default workspace", asset.name()));
+        }
+        let content_type = asset.content_type_of(data)?;
+        let dir = self.dir(workspace_id)?;
+        let _guard = self.write_lock.lock().await;
+        write_file(&dir, asset.name(), data).await?;
+        let meta = AssetMeta {
+            content_type: content_type.to_string(),
+            size: data.len() as u64,
+            updated_at: chrono::Utc::now().timestamp(),
+        };
+        let mut all = self.meta(workspace_id).await;
+        all.assets.insert(asset.name().to_string(), meta.clone());
+        self.write_meta(workspace_id, &all).await?;
+        Ok(meta)
+    }
+
+    /// Remove an asset, returns whether it existed / 删除资源，返回是否存在
+    pub async fn remove(&self, workspace_id: &str, asset: BrandingAsset) -> Result<bool, String> {
+        let dir = self.dir(workspace_id)?;
+        let _guard = self.write_lock.lock().await;
+        let mut all = self.meta(workspace_id).await;
+        if all.assets.remove(asset.name()).is_none() {
+            return Ok(false);
+        }
+        self.write_meta(workspace_id, &all).await?;
+        if let Err(e) = tokio::fs::remove_file(dir.join(asset.name())).await {
+            tracing::debug!("Failed to remove branding file {}: {}", asset.name(), e);
+        }
+        Ok(true)
+    }
+
+    /// Set or clear (empty / None) the site name / 设置或清除（为空 / None）站点名称
+    pub async fn set_site_name(&self, workspace_id: &str, site_name: Option<String>) -> Result<(), String> {
+        let site_name = site_name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        if site_name.as_ref().is_some_and(|n| n.chars().count() > MAX_SITE_NAME_CHARS || n.contains(['<', '>'])) {
+            return Err(format!("site name must be at most {} characters without '<' or '>'", MAX_SITE_NAME_CHARS));
+        }
+        let _guard = self.write_lock.lock().await;
+        let mut all = self.meta(workspace_id).await;
+        all.site_name = site_name;
+        self.write_meta(workspace_id, &all).await
+    }
+}
+
+/// Write through a temporary file so readers never see half a file / 先写临时文件再改名，读取方不会看到写了一半的文件
+async fn write_file(dir: &Path, name: &str, data: &[u8]) -> Result<(), String> {
+    tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+    let tmp = dir.join(format!(".{}.tmp", name));
+    tokio::fs::write(&tmp, data).await.map_err(|e| e.to_string())?;
+    tokio::fs::rename(&tmp, dir.join(name)).await.map_err(|e| e.to_string())
+}
+
+/// Add the branding to the frontend's `index.html`: title, favicon, custom CSS and JS
+/// 将品牌设置注入前端 `index.html`：标题、网站图标、自定义 CSS 与 JS
+///
+/// `base` is the URL prefix of the workspace (`""` or `/w/<slug>`) / `base` 为工作区的 URL 前缀
+pub fn inject_into_index(html: &str, branding: &EffectiveBranding, base: &str) -> String {
+    let mut html = html.to_string();
+    if let Some(name) = &branding.site_name {
+        if let (Some(start), Some(end)) = (html.find("<title>"), html.find("</title>")) {
+            if start < end {
+                html.replace_range(start + "<title>".len()..end, name);
+            }
+        }
+    }
+    let url = |asset: BrandingAsset, meta: &AssetMeta| format!("{}/api/branding/{}?v={}", base, asset.name(), meta.updated_at);
+    let mut tags = String::new();
+    if let Some(meta) = branding.asset(BrandingAsset::Favicon) {
+        tags.push_str(&format!("<link rel=\"icon\" href=\"{}\">", url(BrandingAsset::Favicon, meta)));
+    }
+    if let Some(meta) = branding.asset(BrandingAsset::CustomCss) {
+        tags.push_str(&format!("<link rel=\"stylesheet\" href=\"{}\">", url(BrandingAsset::CustomCss, meta)));
+    }
+    if let Some(meta) = branding.asset(BrandingAsset::CustomJs) {
+        tags.push_str(&format!("<script src=\"{}\" defer></script>", url(BrandingAsset::CustomJs, meta)));
+    }
+    if !tags.is_empty() {
+        if let Some(pos) = html.find("</head>") {
+            html.insert_str(pos, &tags);
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_validation() {
+        assert_eq!(BrandingAsset::from_name("custom.css"), Some(BrandingAsset::CustomCss));
+        assert_eq!(BrandingAsset::from_name("../logo"), None);
+        assert_eq!(BrandingAsset::Logo.content_type_of(b"\x89PNG\r\n\x1a\nxxxx"), Ok("image/png"));
+        assert_eq!(BrandingAsset::Favicon.content_type_of(b"  <svg xmlns=\"\"></svg>"), Ok("image/svg+xml"));
+        assert!(BrandingAsset::Logo.content_type_of(b"body { color: red }").is_err());
+        assert!(BrandingAsset::CustomJs.content_type_of(&[0xff, 0xfe]).is_err());
+        assert!(BrandingAsset::CustomCss.content_type_of(&vec![b' '; MAX_SNIPPET_SIZE + 1]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_code_assets_default_workspace_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BrandingStore::new(dir.path());
+        assert!(store.save("team", BrandingAsset::CustomJs, b"alert(1)").await.is_err());
+        assert!(store.save("team", BrandingAsset::CustomCss, b"body {}").await.is_err());
+        assert!(store.save("team", BrandingAsset::Favicon, b"<svg></svg>").await.is_ok());
+        store.save(DEFAULT_WORKSPACE_ID, BrandingAsset::CustomJs, b"alert(1)").await.unwrap();
+
+        // 工作区自己保存过的脚本（旧版本）不再生效，仍使用默认工作区的
+        let mut legacy = store.meta("team").await;
+        legacy.assets.insert("custom.js".to_string(), AssetMeta { content_type: "text/javascript".to_string(), size: 1, updated_at: 1 });
+        store.write_meta("team", &legacy).await.unwrap();
+        let effective = store.effective("team").await;
+        assert_eq!(effective.assets["custom.js"].0, DEFAULT_WORKSPACE_ID);
+        assert!(effective.assets.contains_key("favicon"));
+    }
+
+    #[test]
+    fn test_inject_into_index() {
+        let meta = AssetMeta { content_type: "text/css".to_string(), size: 1, updated_at: 7 };
+        let mut branding = EffectiveBranding {
+            site_name: Some("My Files".to_string()),
+            ..Default::default()
+        };
+        branding.assets.insert("custom.css".to_string(), ("default".to_string(), meta));
+        let html = inject_into_index("<html><head><title>YaoList</title></head></html>", &branding, "/w/team");
+        assert_eq!(
+            html,
+            "<html><head><title>My Files</title><link rel=\"stylesheet\" href=\"/w/team/api/branding/custom.css?v=7\"></head></html>"
+        );
+        let plain = inject_into_index("<title>YaoList</title>", &EffectiveBranding::default(), "");
+        assert_eq!(plain, "<title>YaoList</title>");
+    }
+}
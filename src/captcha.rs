@@ -0,0 +1,187 @@
+//! CAPTCHA providers / 验证码服务
+//!
+//! The built-in image captcha is answered with `captcha_id` + `captcha_code`; hCaptcha and
+//! Cloudflare Turnstile render a widget with the site key in the browser and send back a token
+//! (`captcha_token`) that is checked against the provider's `siteverify` endpoint.
+//! 内置图形验证码提交 `captcha_id` + `captcha_code`；hCaptcha 与 Turnstile 在浏览器中用站点密钥渲染组件，
+//! 提交的令牌（`captcha_token`）由服务端向提供方 `siteverify` 接口校验。
+
+use std::net::IpAddr;
+use std::time::Duration;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Timeout of a siteverify call / 校验请求超时
+const VERIFY_TIMEOUT_SECS: u64 = 10;
+
+/// Captcha provider / 验证码提供方
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    /// Built-in image captcha / 内置图形验证码
+    #[default]
+    Image,
+    Hcaptcha,
+    Turnstile,
+}
+
+impl CaptchaProvider {
+    fn verify_url(&self) -> Option<&'static str> {
+        match self {
+            CaptchaProvider::Image => None,
+            CaptchaProvider::Hcaptcha => Some("https://api.hcaptcha.com/siteverify"),
+            CaptchaProvider::Turnstile => Some("https://challenges.cloudflare.com/turnstile/v0/siteverify"),
+        }
+    }
+}
+
+/// Captcha settings / 验证码设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptchaConfig {
+    pub provider: CaptchaProvider,
+    /// Public key used by the browser widget / 浏览器组件使用的站点密钥
+    pub site_key: String,
+    /// Server-side secret, never returned by the API / 服务端密钥，接口不返回
+    pub secret_key: String,
+}
+
+impl CaptchaConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.provider != CaptchaProvider::Image
+            && (self.site_key.trim().is_empty() || self.secret_key.trim().is_empty())
+        {
+            return Err("site_key and secret_key are required for hCaptcha / Turnstile".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+/// Captcha settings cache and verifier / 验证码设置缓存与校验
+pub struct CaptchaSettings {
+    config: RwLock<CaptchaConfig>,
+    client: reqwest::Client,
+}
+
+impl CaptchaSettings {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(VERIFY_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        Self {
+            config: RwLock::new(CaptchaConfig::default()),
+            client,
+        }
+    }
+
+    /// Load settings from database / 从数据库加载设置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let value: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'captcha_config'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((json,)) = value {
+            let config: CaptchaConfig = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            config.validate()?;
+            *self.config.write() = config;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self) -> CaptchaConfig {
+        self.config.read().clone()
+    }
+
+    /// Fill an empty secret from the current settings, so clients can save without resending it
+    /// 新设置的密钥为空时沿用当前密钥，客户端保存时无需回传密钥
+    pub fn merge_secret(&self, mut config: CaptchaConfig) -> CaptchaConfig {
+        if config.secret_key.trim().is_empty() {
+            config.secret_key = self.config.read().secret_key.clone();
+        }
+        config
+    }
+
+    /// Validate and apply new settings / 校验并应用新设置
+    pub fn set(&self, config: CaptchaConfig) -> Result<(), String> {
+        config.validate()?;
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    /// Check a widget token with the provider; `Ok(false)` means the token was rejected
+    /// 向提供方校验令牌，`Ok(false)` 表示令牌无效
+    pub async fn verify_token(&self, token: &str, remote_ip: Option<IpAddr>) -> Result<bool, String> {
+        let config = self.get();
+        let Some(url) = config.provider.verify_url() else {
+            return Err("provider does not use tokens".to_string());
+        };
+        if token.trim().is_empty() {
+            return Ok(false);
+        }
+
+        let mut form = vec![("secret", config.secret_key.clone()), ("response", token.trim().to_string())];
+        if let Some(ip) = remote_ip {
+            form.push(("remoteip", ip.to_string()));
+        }
+        let resp: SiteVerifyResponse = self.client.post(url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !resp.success {
+            tracing::debug!("Captcha token rejected: {:?}", resp.error_codes);
+        }
+        Ok(resp.success)
+    }
+}
+
+impl Default for CaptchaSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        assert!(CaptchaConfig::default().validate().is_ok());
+        let mut config: CaptchaConfig = serde_json::from_str(r#"{"provider":"turnstile","site_key":"0x4AAA"}"#).unwrap();
+        assert_eq!(config.provider, CaptchaProvider::Turnstile);
+        assert!(config.validate().is_err());
+
+        let settings = CaptchaSettings::new();
+        settings.set(CaptchaConfig { secret_key: "0x4SECRET".to_string(), ..config.clone() }).unwrap();
+        config = settings.merge_secret(config);
+        assert_eq!(config.secret_key, "0x4SECRET");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_siteverify_response() {
+        let resp: SiteVerifyResponse = serde_json::from_str(
+            r#"{"success":false,"error-codes":["invalid-input-response"],"hostname":""}"#
+        ).unwrap();
+        assert!(!resp.success);
+        assert_eq!(resp.error_codes, vec!["invalid-input-response"]);
+        let resp: SiteVerifyResponse = serde_json::from_str(r#"{"success":true}"#).unwrap();
+        assert!(resp.success);
+    }
+}
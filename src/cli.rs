@@ -0,0 +1,503 @@
+//! Headless administration subcommands / 命令行管理子命令
+//!
+//! Account and mount commands work on the database directly, so they also run before the first
+//! start (e.g. in a container entrypoint). Index and job commands need the running server: the CLI
+//! signs in as an administrator by writing a short-lived session into the database and calls the
+//! admin API on the local listener. Service and data migration commands don't open the database.
+//! 账号与挂载命令直接操作数据库，服务未启动时（如容器入口脚本中）也可执行。索引与定时任务命令需要
+//! 服务正在运行：命令行向数据库写入一个短期管理员会话，再调用本机监听地址上的管理接口。服务安装与
+//! 数据迁移命令不打开数据库。
+
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration, Utc};
+use clap::{Args, Parser, Subcommand};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+use yaolist_backend::config::{self, AppConfig};
+use yaolist_backend::lockout::{account_key, LockoutScope, LoginLockout};
+use yaolist_backend::service::{self, ServiceSpec, DEFAULT_SERVICE_NAME};
+use yaolist_backend::utils::fix_and_clean_path;
+
+use crate::auth::{create_session, SESSION_COOKIE_NAME};
+use crate::bootstrap::{self, Applied, MountSpec};
+use crate::{api, db};
+
+/// Lifetime of the session used for API calls / 调用接口所用会话的有效期
+const CLI_SESSION_MINUTES: i64 = 5;
+
+#[derive(Debug, Parser)]
+#[command(name = "yaolist-backend", version, about = "YaoList server and headless administration")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the server (default)
+    Serve,
+    /// Manage administrator accounts
+    #[command(subcommand)]
+    Admin(AdminCommand),
+    /// Manage storage mounts
+    #[command(subcommand)]
+    Mount(MountCommand),
+    /// Export settings, users, groups, drivers, metas and shares as JSON
+    Backup {
+        /// Output file, stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Search index operations (requires a running server)
+    #[command(subcommand)]
+    Index(IndexCommand),
+    /// Scheduled jobs (requires a running server)
+    #[command(subcommand)]
+    Job(JobCommand),
+    /// Start YaoList with the machine from the current directory (systemd on Linux, a startup task on Windows)
+    InstallService {
+        /// Service name
+        #[arg(long, default_value = DEFAULT_SERVICE_NAME)]
+        name: String,
+        /// Account to run as (Linux), root when omitted
+        #[arg(long)]
+        user: Option<String>,
+        /// Print the systemd unit instead of installing it
+        #[arg(long)]
+        print: bool,
+    },
+    /// Copy an existing data directory into the configured one (the server must be stopped)
+    MigrateData {
+        old_dir: PathBuf,
+        /// New data directory, written to config.json; defaults to the configured one
+        #[arg(long)]
+        to: Option<PathBuf>,
+        /// Overwrite a database already in the target
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminCommand {
+    /// Create an administrator in the default workspace
+    Create {
+        username: String,
+        /// Password, generated and printed when omitted
+        #[arg(long)]
+        password: Option<String>,
+        #[arg(long)]
+        email: Option<String>,
+    },
+    /// Reset a user's password, sign out their sessions and clear their login lockout
+    ResetPassword {
+        username: String,
+        /// New password, generated and printed when omitted
+        #[arg(long)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MountCommand {
+    /// List mounts
+    List,
+    /// Create or update mounts from a YAML file, matched by mount path
+    Apply {
+        file: PathBuf,
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum IndexCommand {
+    /// Rebuild the search index
+    Rebuild {
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum JobCommand {
+    /// List scheduled jobs
+    List {
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+    /// Run a scheduled job now
+    Trigger {
+        id: String,
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ServerArgs {
+    /// Base URL of the running server, defaults to the configured port on the loopback address
+    #[arg(long)]
+    pub server: Option<String>,
+}
+
+/// Mount file / 挂载配置文件
+#[derive(Debug, Deserialize)]
+struct MountFile {
+    mounts: Vec<MountSpec>,
+}
+
+/// Run a subcommand / 执行子命令
+pub async fn run(command: Command, app_config: &AppConfig) -> Result<()> {
+    let command = match command {
+        Command::InstallService { name, user, print } => return install_service(&name, user, print),
+        Command::MigrateData { old_dir, to, force } => return migrate_data(app_config, &old_dir, to, force).await,
+        command => command,
+    };
+
+    let data_dir = app_config.get_data_dir();
+    std::fs::create_dir_all(&data_dir)?;
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| app_config.get_database_url());
+    let pool = db::connect(&database_url, &app_config.database).await?;
+    db::run_migrations(&pool).await?;
+
+    match command {
+        Command::Serve => bail!("serve is handled by main"),
+        Command::InstallService { .. } | Command::MigrateData { .. } => unreachable!(),
+        Command::Admin(AdminCommand::Create { username, password, email }) => {
+            create_admin(&pool, &username, password, email).await
+        }
+        Command::Admin(AdminCommand::ResetPassword { username, password }) => {
+            reset_password(&pool, &username, password).await
+        }
+        Command::Mount(MountCommand::List) => list_mounts(&pool).await,
+        Command::Mount(MountCommand::Apply { file, server }) => {
+            apply_mounts(&pool, app_config, &file, &server).await
+        }
+        Command::Backup { output } => {
+            let backup = api::backup::collect_backup(&pool).await.map_err(|e| anyhow!(e))?;
+            let text = serde_json::to_string_pretty(&json!({ "code": 200, "data": backup }))?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!("Backup written to {}", path.display());
+                }
+                None => println!("{}", text),
+            }
+            Ok(())
+        }
+        Command::Index(IndexCommand::Rebuild { server }) => {
+            let client = ServerClient::connect(&pool, app_config, &server).await?;
+            let result = client.post("/api/admin/search/index/rebuild", json!({})).await;
+            client.close().await;
+            println!("{}", message_of(&result?));
+            Ok(())
+        }
+        Command::Job(JobCommand::List { server }) => {
+            let client = ServerClient::connect(&pool, app_config, &server).await?;
+            let result = client.get("/api/admin/jobs").await;
+            client.close().await;
+            let result = result?;
+            for job in result.get("data").and_then(Value::as_array).into_iter().flatten() {
+                println!(
+                    "{:<20} {:<8} {:<16} {}",
+                    job["id"].as_str().unwrap_or(""),
+                    if job["enabled"].as_bool().unwrap_or(false) { "enabled" } else { "disabled" },
+                    job["cron"].as_str().unwrap_or(""),
+                    job["last_status"].as_str().unwrap_or("-"),
+                );
+            }
+            Ok(())
+        }
+        Command::Job(JobCommand::Trigger { id, server }) => {
+            let client = ServerClient::connect(&pool, app_config, &server).await?;
+            let result = client.post("/api/admin/jobs/trigger", json!({ "id": id })).await;
+            client.close().await;
+            println!("{}", message_of(&result?));
+            Ok(())
+        }
+    }
+}
+
+fn install_service(name: &str, user: Option<String>, print: bool) -> Result<()> {
+    let spec = ServiceSpec::current(name, user)?;
+    if print {
+        print!("{}", spec.systemd_unit());
+        return Ok(());
+    }
+    println!("{}", spec.install()?);
+    Ok(())
+}
+
+async fn migrate_data(app_config: &AppConfig, old_dir: &Path, to: Option<PathBuf>, force: bool) -> Result<()> {
+    // 服务运行时数据库仍在写入，复制结果可能不一致
+    let probe = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(std::time::Duration::from_secs(3))
+        .build()?;
+    if probe.get(format!("{}/api/settings/public", default_server_url(app_config))).send().await.is_ok() {
+        bail!("The server is running, stop it before migrating its data");
+    }
+
+    let target = to.clone().unwrap_or_else(|| app_config.get_data_dir());
+    service::check_migration(old_dir, &target, &app_config.database.db_file, force)?;
+    let report = service::copy_dir(old_dir, &target)?;
+    println!("Copied {} files ({} bytes) from {} to {}", report.files, report.bytes, old_dir.display(), target.display());
+
+    if let Some(to) = to {
+        let mut updated = app_config.clone();
+        updated.database.data_dir = to.to_string_lossy().to_string();
+        config::save_config(&updated).map_err(|e| anyhow!(e))?;
+        println!("config.json now uses {} as data directory", to.display());
+    }
+    println!("{} was left in place, remove it once the server starts correctly", old_dir.display());
+    Ok(())
+}
+
+/// Use the given password or generate one / 使用给定密码或生成随机密码
+fn password_or_generate(password: Option<String>) -> (String, bool) {
+    match password {
+        Some(p) => (p, false),
+        None => (db::generate_random_password(16), true),
+    }
+}
+
+async fn create_admin(pool: &SqlitePool, username: &str, password: Option<String>, email: Option<String>) -> Result<()> {
+    let username = username.trim();
+    if username.is_empty() {
+        bail!("Username must not be empty");
+    }
+    let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await?;
+    if exists.is_some() {
+        bail!("User {} already exists", username);
+    }
+
+    let (password, generated) = password_or_generate(password);
+    let password_hash = bcrypt::hash(&password, bcrypt::DEFAULT_COST)?;
+    // 与管理接口一致的整数ID
+    let max_id: Option<(i64,)> = sqlx::query_as("SELECT MAX(CAST(id AS INTEGER)) FROM users WHERE id GLOB '[0-9]*'")
+        .fetch_optional(pool)
+        .await?;
+    let id = (max_id.map(|(id,)| id).unwrap_or(0) + 1).to_string();
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query(
+        "INSERT INTO users (id, unique_id, username, password_hash, email, is_admin, enabled, two_factor_enabled, created_at, updated_at, workspace_id)
+         VALUES (?, ?, ?, ?, ?, 1, 1, 0, ?, ?, ?)"
+    )
+    .bind(&id)
+    .bind(Uuid::new_v4().to_string())
+    .bind(username)
+    .bind(&password_hash)
+    .bind(&email)
+    .bind(&now)
+    .bind(&now)
+    .bind(yaolist_backend::workspace::DEFAULT_WORKSPACE_ID)
+    .execute(pool)
+    .await?;
+
+    let admin_group: Option<(i64,)> = sqlx::query_as("SELECT id FROM user_groups WHERE is_admin = 1 ORDER BY id LIMIT 1")
+        .fetch_optional(pool)
+        .await?;
+    if let Some((group_id,)) = admin_group {
+        sqlx::query("INSERT INTO user_group_members (user_id, group_id, created_at) VALUES (?, ?, ?)")
+            .bind(&id)
+            .bind(group_id.to_string())
+            .bind(&now)
+            .execute(pool)
+            .await?;
+    }
+
+    println!("Created administrator {} (id {})", username, id);
+    if generated {
+        println!("Password: {}", password);
+    }
+    Ok(())
+}
+
+async fn reset_password(pool: &SqlitePool, username: &str, password: Option<String>) -> Result<()> {
+    let user: Option<(String,)> = sqlx::query_as("SELECT id FROM users WHERE username = ?")
+        .bind(username.trim())
+        .fetch_optional(pool)
+        .await?;
+    let (user_id,) = user.ok_or_else(|| anyhow!("User {} not found", username))?;
+
+    let (password, generated) = password_or_generate(password);
+    let password_hash = bcrypt::hash(&password, bcrypt::DEFAULT_COST)?;
+    sqlx::query("UPDATE users SET password_hash = ?, updated_at = ? WHERE id = ?")
+        .bind(&password_hash)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&user_id)
+        .execute(pool)
+        .await?;
+    sqlx::query("DELETE FROM sessions WHERE user_id = ?")
+        .bind(&user_id)
+        .execute(pool)
+        .await?;
+    LoginLockout::new().unlock(pool, LockoutScope::Account, &account_key(username)).await;
+
+    println!("Password of {} has been reset", username.trim());
+    if generated {
+        println!("Password: {}", password);
+    }
+    Ok(())
+}
+
+async fn list_mounts(pool: &SqlitePool) -> Result<()> {
+    let drivers: Vec<(String, bool, String)> = sqlx::query_as("SELECT name, enabled, config FROM drivers")
+        .fetch_all(pool)
+        .await?;
+    let mut rows: Vec<(String, String, String, bool)> = drivers.into_iter()
+        .map(|(id, enabled, config)| {
+            let config: Value = serde_json::from_str(&config).unwrap_or_default();
+            let mount_path = config["mount_path"].as_str().unwrap_or("").to_string();
+            let driver_type = config["driver_type"].as_str().unwrap_or("unknown").to_string();
+            (id, mount_path, driver_type, enabled)
+        })
+        .collect();
+    rows.sort_by(|a, b| a.1.cmp(&b.1));
+
+    println!("{:<6} {:<32} {:<16} STATUS", "ID", "MOUNT PATH", "DRIVER");
+    for (id, mount_path, driver_type, enabled) in rows {
+        println!("{:<6} {:<32} {:<16} {}", id, mount_path, driver_type, if enabled { "enabled" } else { "disabled" });
+    }
+    Ok(())
+}
+
+async fn apply_mounts(pool: &SqlitePool, app_config: &AppConfig, file: &PathBuf, server: &ServerArgs) -> Result<()> {
+    let text = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let spec: MountFile = serde_yaml::from_str(&text).with_context(|| format!("Invalid mount file {}", file.display()))?;
+
+    let mut changed = Vec::new();
+    let mut tx = pool.begin().await?;
+    for mount in &spec.mounts {
+        let (id, applied) = bootstrap::upsert_mount(&mut tx, mount).await?;
+        match applied {
+            Applied::Created => println!("Created mount {} (id {})", fix_and_clean_path(mount.mount_path.trim()), id),
+            Applied::Updated => println!("Updated mount {} (id {})", fix_and_clean_path(mount.mount_path.trim()), id),
+        }
+        changed.push(id);
+    }
+    tx.commit().await?;
+
+    // 服务正在运行时立即重新加载，否则在下次启动时加载
+    match ServerClient::connect(pool, app_config, server).await {
+        Ok(client) => {
+            for id in &changed {
+                match client.post(&format!("/api/drivers/{}/reload", id), json!({})).await {
+                    Ok(result) => println!("{}", message_of(&result)),
+                    Err(e) => eprintln!("Failed to reload mount {}: {}", id, e),
+                }
+            }
+            client.close().await;
+        }
+        Err(e) => eprintln!("Server not reachable ({}), mounts are loaded on next start", e),
+    }
+    Ok(())
+}
+
+fn message_of(result: &Value) -> String {
+    result.get("message")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| result.to_string())
+}
+
+/// Admin API client of the running server / 运行中服务的管理接口客户端
+struct ServerClient {
+    pool: SqlitePool,
+    http: reqwest::Client,
+    base_url: String,
+    session_id: String,
+}
+
+impl ServerClient {
+    /// Sign in with a short-lived admin session and check the server responds
+    /// 写入短期管理员会话并确认服务可访问
+    async fn connect(pool: &SqlitePool, app_config: &AppConfig, args: &ServerArgs) -> Result<Self> {
+        let (base_url, local) = match &args.server {
+            Some(url) => (url.trim_end_matches('/').to_string(), false),
+            None => (default_server_url(app_config), true),
+        };
+        let http = reqwest::Client::builder()
+            // 本机回环地址上的证书通常与 IP 不匹配
+            .danger_accept_invalid_certs(local)
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+        http.get(format!("{}/api/settings/public", base_url))
+            .send()
+            .await
+            .with_context(|| format!("Cannot reach {}", base_url))?;
+
+        let admin: Option<(String,)> = sqlx::query_as(
+            "SELECT id FROM users WHERE is_admin = 1 AND enabled = 1 AND workspace_id = ? ORDER BY CAST(id AS INTEGER) LIMIT 1"
+        )
+        .bind(yaolist_backend::workspace::DEFAULT_WORKSPACE_ID)
+        .fetch_optional(pool)
+        .await?;
+        let (admin_id,) = admin.ok_or_else(|| anyhow!("No enabled administrator found"))?;
+
+        let mut session = create_session(&admin_id);
+        session.expires_at = Utc::now() + Duration::minutes(CLI_SESSION_MINUTES);
+        sqlx::query("INSERT INTO sessions (id, user_id, expires_at, created_at, workspace_id) VALUES (?, ?, ?, ?, ?)")
+            .bind(&session.id)
+            .bind(&session.user_id)
+            .bind(session.expires_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .bind(yaolist_backend::workspace::DEFAULT_WORKSPACE_ID)
+            .execute(pool)
+            .await?;
+
+        Ok(Self { pool: pool.clone(), http, base_url, session_id: session.id })
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        self.send(self.http.get(format!("{}{}", self.base_url, path))).await
+    }
+
+    async fn post(&self, path: &str, body: Value) -> Result<Value> {
+        self.send(self.http.post(format!("{}{}", self.base_url, path)).json(&body)).await
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value> {
+        let response = request
+            .header(reqwest::header::COOKIE, format!("{}={}", SESSION_COOKIE_NAME, self.session_id))
+            .header(reqwest::header::ACCEPT_LANGUAGE, "en")
+            .send()
+            .await?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        let code = body.get("code").and_then(Value::as_u64).unwrap_or(200);
+        if !status.is_success() || code >= 400 {
+            let message = body.get("message").or_else(|| body.get("error")).and_then(Value::as_str).unwrap_or("");
+            bail!("{} {}", status.as_u16().max(code as u16), message);
+        }
+        Ok(body)
+    }
+
+    /// Delete the session / 删除会话
+    async fn close(self) {
+        let _ = sqlx::query("DELETE FROM sessions WHERE id = ?")
+            .bind(&self.session_id)
+            .execute(&self.pool)
+            .await;
+    }
+}
+
+/// Loopback URL of the configured listener / 配置的监听地址对应的本机 URL
+fn default_server_url(app_config: &AppConfig) -> String {
+    let host = match app_config.server.host.as_str() {
+        "" | "0.0.0.0" => "127.0.0.1".to_string(),
+        "::" => "[::1]".to_string(),
+        h if h.contains(':') => format!("[{}]", h),
+        h => h.to_string(),
+    };
+    let scheme = if app_config.tls.enabled { "https" } else { "http" };
+    format!("{}://{}:{}", scheme, host, app_config.server.port)
+}
@@ -0,0 +1,217 @@
+//! Client IP behind reverse proxies / 反向代理下的客户端 IP
+//!
+//! Forwarding headers (`X-Forwarded-For`, `X-Real-IP`, `CF-Connecting-IP`) are only honoured
+//! when the connecting peer is listed in `server.trusted_proxies`; otherwise anyone could spoof
+//! their address past login protection. `X-Forwarded-For` is walked from the right, skipping
+//! trusted hops, so the first untrusted address is the client.
+//! 仅当连接方位于 `server.trusted_proxies` 中时才信任转发头，否则任何人都能伪造 IP 绕过登录保护。
+//! `X-Forwarded-For` 从右向左跳过可信代理，第一个不可信地址即为客户端。
+//!
+//! `client_ip_middleware` resolves the address once per request; handlers take it with the
+//! `ClientIp` extractor so login security, webhooks and load balancing agree on the same IP.
+//! 中间件每个请求解析一次，处理函数通过 `ClientIp` 提取，登录保护、Webhook 与负载均衡使用同一 IP。
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::config;
+
+/// One trusted address or CIDR range / 单个可信地址或网段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IpRange {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    /// Parse `10.0.0.0/8`, `::1` or `2001:db8::/32` / 解析地址或 CIDR
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (s.parse::<IpAddr>().ok()?, None),
+        };
+        let addr = addr.to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parsed `server.trusted_proxies` / 解析后的可信代理列表
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<IpRange>,
+}
+
+impl TrustedProxies {
+    /// Parse the configured list, failing on the first invalid entry / 解析配置列表，遇到无效项时报错
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let ranges = entries.iter()
+            .filter(|e| !e.trim().is_empty())
+            .map(|e| IpRange::parse(e).ok_or_else(|| format!("invalid trusted proxy '{}'", e.trim())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { ranges })
+    }
+
+    /// Current list from the global config / 读取全局配置中的列表
+    pub fn current() -> Self {
+        let config = config::get_config();
+        let config = config.read();
+        Self::parse(&config.server.trusted_proxies).unwrap_or_default()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.ranges.iter().any(|r| r.contains(ip))
+    }
+}
+
+/// Parse one forwarded hop, which may carry a port / 解析单个转发地址（可能带端口）
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim();
+    hop.parse::<IpAddr>()
+        .or_else(|_| hop.parse::<SocketAddr>().map(|a| a.ip()))
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+fn header_ip(headers: &HeaderMap, name: &str) -> Option<IpAddr> {
+    headers.get(name).and_then(|v| v.to_str().ok()).and_then(parse_hop)
+}
+
+/// Resolve the client address of a request from `peer` / 根据连接方地址解析客户端 IP
+///
+/// `peer_trusted` forces trust in the peer, used for the Unix socket listener whose peer is
+/// always a local proxy. / `peer_trusted` 强制信任连接方，用于 Unix 套接字（对端总是本机代理）。
+pub fn resolve_client_ip(headers: &HeaderMap, peer: IpAddr, trusted: &TrustedProxies, peer_trusted: bool) -> IpAddr {
+    let peer = peer.to_canonical();
+    if !peer_trusted && !trusted.contains(peer) {
+        return peer;
+    }
+
+    let hops: Vec<&str> = headers.get_all("X-Forwarded-For").iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter(|h| !h.trim().is_empty())
+        .collect();
+    if !hops.is_empty() {
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            match parse_hop(hop) {
+                Some(ip) if trusted.contains(ip) => client = ip,
+                Some(ip) => return ip,
+                // 无法解析的地址之后的内容不可信
+                None => return client,
+            }
+        }
+        // 全部为可信代理时取最左侧地址
+        return client;
+    }
+
+    header_ip(headers, "X-Real-IP")
+        .or_else(|| header_ip(headers, "CF-Connecting-IP"))
+        .unwrap_or(peer)
+}
+
+/// Real client address of the request / 请求的真实客户端地址
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Resolve the client IP once and store it in the request extensions / 解析客户端 IP 并存入请求扩展
+pub async fn client_ip_middleware(mut req: Request, next: Next) -> Response {
+    if req.extensions().get::<ClientIp>().is_none() {
+        let peer = req.extensions().get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let ip = resolve_client_ip(req.headers(), peer, &TrustedProxies::current(), false);
+        req.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(req).await
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(ip) = parts.extensions.get::<ClientIp>() {
+            return Ok(*ip);
+        }
+        let ConnectInfo(addr) = parts.extensions.get::<ConnectInfo<SocketAddr>>()
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Missing connection info"))?;
+        Ok(ClientIp(resolve_client_ip(&parts.headers, addr.ip(), &TrustedProxies::current(), false)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trusted(list: &[&str]) -> TrustedProxies {
+        TrustedProxies::parse(&list.iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let t = trusted(&["127.0.0.1", "10.0.0.0/8", "2001:db8::/32", ""]);
+        assert!(t.contains(ip("127.0.0.1")));
+        assert!(t.contains(ip("::ffff:127.0.0.1")));
+        assert!(t.contains(ip("10.200.1.1")));
+        assert!(!t.contains(ip("11.0.0.1")));
+        assert!(t.contains(ip("2001:db8:ffff::1")));
+        assert!(!t.contains(ip("2001:db9::1")));
+        assert!(trusted(&["0.0.0.0/0"]).contains(ip("8.8.8.8")));
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".to_string()]).is_err());
+        assert!(TrustedProxies::parse(&["proxy.local".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_client_ip() {
+        let t = trusted(&["127.0.0.1", "10.0.0.0/8"]);
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", "1.1.1.1, 203.0.113.7, 10.0.0.2".parse().unwrap());
+        headers.insert("X-Real-IP", "9.9.9.9".parse().unwrap());
+
+        // 不可信的连接方：忽略转发头
+        assert_eq!(resolve_client_ip(&headers, ip("198.51.100.1"), &t, false), ip("198.51.100.1"));
+        // 从右向左跳过可信代理，左侧伪造的地址被忽略
+        assert_eq!(resolve_client_ip(&headers, ip("127.0.0.1"), &t, false), ip("203.0.113.7"));
+        assert_eq!(resolve_client_ip(&headers, ip("198.51.100.1"), &t, true), ip("203.0.113.7"));
+
+        headers.insert("X-Forwarded-For", "10.0.0.3, 10.0.0.2".parse().unwrap());
+        assert_eq!(resolve_client_ip(&headers, ip("127.0.0.1"), &t, false), ip("10.0.0.3"));
+
+        headers.insert("X-Forwarded-For", "garbage, 10.0.0.2".parse().unwrap());
+        assert_eq!(resolve_client_ip(&headers, ip("127.0.0.1"), &t, false), ip("10.0.0.2"));
+
+        headers.remove("X-Forwarded-For");
+        assert_eq!(resolve_client_ip(&headers, ip("127.0.0.1"), &t, false), ip("9.9.9.9"));
+        headers.clear();
+        headers.insert("CF-Connecting-IP", "[2001:db8::1]:443".parse().unwrap());
+        assert_eq!(resolve_client_ip(&headers, ip("127.0.0.1"), &t, false), ip("2001:db8::1"));
+    }
+}
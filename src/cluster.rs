@@ -0,0 +1,552 @@
+//! Shared state for multiple replicas / 多实例共享状态
+//!
+//! Replicas behind one load balancer share short-lived keys (captchas, reset codes, locks) and
+//! broadcast events (setting changes, task control) through a `SharedStore`. The memory backend
+//! keeps everything in process for a single instance; the database backend uses two tables in the
+//! shared database; the Redis backend (`redis` feature) uses keys and a pub/sub channel.
+//! 同一负载均衡后的多个实例通过 `SharedStore` 共享短期键值（验证码、重置码、锁）并广播事件
+//! （设置变更、任务控制）。内存后端仅用于单实例；数据库后端使用共享数据库中的两张表；
+//! Redis 后端（`redis` 特性）使用键值与发布订阅频道。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::broadcast;
+
+use crate::config::{ClusterBackend, ClusterConfig};
+
+/// Settings changed on another replica, payload is the request path / 其他实例修改了设置，载荷为请求路径
+pub const EVENT_SETTINGS: &str = "settings";
+/// Mounts changed on another replica / 其他实例修改了挂载
+pub const EVENT_DRIVERS: &str = "drivers";
+/// Control a task owned by another replica, payload is `TaskControlEvent` JSON / 控制其他实例的任务
+pub const EVENT_TASK_CONTROL: &str = "task_control";
+/// Task event of another replica (Redis only), payload is `TaskEvent` JSON / 其他实例的任务事件（仅 Redis）
+pub const EVENT_TASK_EVENT: &str = "task_event";
+
+/// Heartbeat interval and lifetime / 心跳间隔与有效期
+const HEARTBEAT_SECS: u64 = 10;
+const HEARTBEAT_TTL_SECS: u64 = 30;
+/// Database backend: event poll interval and retention / 数据库后端：事件轮询间隔与保留时间
+const EVENT_POLL_MS: u64 = 1000;
+const EVENT_RETENTION_SECS: i64 = 600;
+
+/// Event sent to the other replicas / 发往其他实例的事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterEvent {
+    /// Sending instance / 发送实例
+    pub origin: String,
+    pub topic: String,
+    pub payload: String,
+}
+
+/// Control request for a task owned by another replica / 发给任务所属实例的控制请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskControlEvent {
+    pub task_id: String,
+    /// cancel / pause / resume / remove
+    pub action: String,
+}
+
+/// Shared key-value store and event bus / 共享键值存储与事件总线
+#[async_trait]
+pub trait SharedStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, String>;
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), String>;
+    /// Store only if absent or expired; true if this call stored it / 仅在不存在或已过期时写入，写入成功返回 true
+    async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, String>;
+    /// Get and delete atomically / 原子地读取并删除
+    async fn take(&self, key: &str) -> Result<Option<String>, String>;
+    async fn delete(&self, key: &str) -> Result<(), String>;
+    async fn publish(&self, event: &ClusterEvent) -> Result<(), String>;
+    /// Deliver events of other instances into `sender` / 将其他实例的事件投递到 `sender`
+    fn listen(&self, instance_id: String, sender: broadcast::Sender<ClusterEvent>);
+}
+
+/// In-process store for a single instance / 单实例进程内存储
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SharedStore for MemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let now = Instant::now();
+        Ok(self.entries.lock().get(key).filter(|(_, exp)| *exp > now).map(|(v, _)| v.clone()))
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), String> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        entries.retain(|_, (_, exp)| *exp > now);
+        entries.insert(key.to_string(), (value.to_string(), now + ttl));
+        Ok(())
+    }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, String> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        if entries.get(key).is_some_and(|(_, exp)| *exp > now) {
+            return Ok(false);
+        }
+        entries.insert(key.to_string(), (value.to_string(), now + ttl));
+        Ok(true)
+    }
+
+    async fn take(&self, key: &str) -> Result<Option<String>, String> {
+        let now = Instant::now();
+        Ok(self.entries.lock().remove(key).filter(|(_, exp)| *exp > now).map(|(v, _)| v))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        self.entries.lock().remove(key);
+        Ok(())
+    }
+
+    async fn publish(&self, _event: &ClusterEvent) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn listen(&self, _instance_id: String, _sender: broadcast::Sender<ClusterEvent>) {}
+}
+
+/// Store in the shared database / 共享数据库存储
+pub struct DbStore {
+    db: SqlitePool,
+}
+
+impl DbStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Create the tables / 创建数据表
+    pub async fn init(&self) -> Result<(), String> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS cluster_kv (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                expires_at INTEGER NOT NULL
+            )"
+        )
+        .execute(&self.db)
+        .await
+        .map_err(|e| e.to_string())?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS cluster_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                origin TEXT NOT NULL,
+                topic TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )"
+        )
+        .execute(&self.db)
+        .await
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn now_ms() -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+}
+
+#[async_trait]
+impl SharedStore for DbStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        sqlx::query_scalar("SELECT value FROM cluster_kv WHERE key = ? AND expires_at > ?")
+            .bind(key)
+            .bind(Self::now_ms())
+            .fetch_optional(&self.db)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), String> {
+        sqlx::query("INSERT OR REPLACE INTO cluster_kv (key, value, expires_at) VALUES (?, ?, ?)")
+            .bind(key)
+            .bind(value)
+            .bind(Self::now_ms() + ttl.as_millis() as i64)
+            .execute(&self.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, String> {
+        let now = Self::now_ms();
+        // 单条语句完成，多个实例同时写入时只有一个成功
+        let result = sqlx::query(
+            "INSERT INTO cluster_kv (key, value, expires_at) VALUES (?, ?, ?)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at
+             WHERE cluster_kv.expires_at <= ?"
+        )
+        .bind(key)
+        .bind(value)
+        .bind(now + ttl.as_millis() as i64)
+        .bind(now)
+        .execute(&self.db)
+        .await
+        .map_err(|e| e.to_string())?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn take(&self, key: &str) -> Result<Option<String>, String> {
+        let row: Option<(String, i64)> = sqlx::query_as("DELETE FROM cluster_kv WHERE key = ? RETURNING value, expires_at")
+            .bind(key)
+            .fetch_optional(&self.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        let now = Self::now_ms();
+        Ok(row.filter(|(_, exp)| *exp > now).map(|(v, _)| v))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        sqlx::query("DELETE FROM cluster_kv WHERE key = ?")
+            .bind(key)
+            .execute(&self.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    async fn publish(&self, event: &ClusterEvent) -> Result<(), String> {
+        sqlx::query("INSERT INTO cluster_events (origin, topic, payload, created_at) VALUES (?, ?, ?, ?)")
+            .bind(&event.origin)
+            .bind(&event.topic)
+            .bind(&event.payload)
+            .bind(Self::now_ms())
+            .execute(&self.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn listen(&self, instance_id: String, sender: broadcast::Sender<ClusterEvent>) {
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            // 只投递启动之后的事件
+            let mut last_id: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM cluster_events")
+                .fetch_one(&db)
+                .await
+                .unwrap_or(0);
+            let mut interval = tokio::time::interval(Duration::from_millis(EVENT_POLL_MS));
+            let mut ticks: u64 = 0;
+            loop {
+                interval.tick().await;
+                let rows: Vec<(i64, String, String, String)> = match sqlx::query_as(
+                    "SELECT id, origin, topic, payload FROM cluster_events WHERE id > ? ORDER BY id"
+                )
+                .bind(last_id)
+                .fetch_all(&db)
+                .await
+                {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        tracing::warn!("Failed to poll cluster events: {}", e);
+                        continue;
+                    }
+                };
+                for (id, origin, topic, payload) in rows {
+                    last_id = id;
+                    if origin != instance_id {
+                        let _ = sender.send(ClusterEvent { origin, topic, payload });
+                    }
+                }
+
+                ticks += 1;
+                if ticks.is_multiple_of(60) {
+                    let now = Self::now_ms();
+                    let _ = sqlx::query("DELETE FROM cluster_events WHERE created_at < ?")
+                        .bind(now - EVENT_RETENTION_SECS * 1000)
+                        .execute(&db)
+                        .await;
+                    let _ = sqlx::query("DELETE FROM cluster_kv WHERE expires_at <= ?")
+                        .bind(now)
+                        .execute(&db)
+                        .await;
+                }
+            }
+        });
+    }
+}
+
+/// Store on a Redis server / Redis 存储
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    client: redis::Client,
+    conn: redis::aio::ConnectionManager,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    const PREFIX: &'static str = "yaolist:";
+    const CHANNEL: &'static str = "yaolist:events";
+
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| e.to_string())?;
+        let conn = redis::aio::ConnectionManager::new(client.clone()).await.map_err(|e| e.to_string())?;
+        Ok(Self { client, conn })
+    }
+
+    fn key(key: &str) -> String {
+        format!("{}{}", Self::PREFIX, key)
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl SharedStore for RedisStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        redis::cmd("GET").arg(Self::key(key))
+            .query_async(&mut self.conn.clone()).await.map_err(|e| e.to_string())
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), String> {
+        redis::cmd("SET").arg(Self::key(key)).arg(value).arg("PX").arg(ttl.as_millis() as u64)
+            .query_async(&mut self.conn.clone()).await.map_err(|e| e.to_string())
+    }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, String> {
+        let stored: Option<String> = redis::cmd("SET").arg(Self::key(key)).arg(value).arg("NX").arg("PX").arg(ttl.as_millis() as u64)
+            .query_async(&mut self.conn.clone()).await.map_err(|e| e.to_string())?;
+        Ok(stored.is_some())
+    }
+
+    async fn take(&self, key: &str) -> Result<Option<String>, String> {
+        redis::cmd("GETDEL").arg(Self::key(key))
+            .query_async(&mut self.conn.clone()).await.map_err(|e| e.to_string())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        redis::cmd("DEL").arg(Self::key(key))
+            .query_async(&mut self.conn.clone()).await.map_err(|e| e.to_string())
+    }
+
+    async fn publish(&self, event: &ClusterEvent) -> Result<(), String> {
+        let payload = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        redis::cmd("PUBLISH").arg(Self::CHANNEL).arg(payload)
+            .query_async(&mut self.conn.clone()).await.map_err(|e| e.to_string())
+    }
+
+    fn listen(&self, instance_id: String, sender: broadcast::Sender<ClusterEvent>) {
+        use futures::StreamExt;
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            loop {
+                let mut pubsub = match client.get_async_pubsub().await {
+                    Ok(pubsub) => pubsub,
+                    Err(e) => {
+                        tracing::warn!("Redis pub/sub connection failed: {}", e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        continue;
+                    }
+                };
+                if let Err(e) = pubsub.subscribe(Self::CHANNEL).await {
+                    tracing::warn!("Redis subscribe failed: {}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+                let mut messages = pubsub.on_message();
+                while let Some(message) = messages.next().await {
+                    let Ok(payload) = message.get_payload::<String>() else { continue };
+                    if let Ok(event) = serde_json::from_str::<ClusterEvent>(&payload) {
+                        if event.origin != instance_id {
+                            let _ = sender.send(event);
+                        }
+                    }
+                }
+                tracing::warn!("Redis pub/sub connection lost, reconnecting");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        });
+    }
+}
+
+/// This replica's view of the cluster / 本实例的集群视图
+pub struct Cluster {
+    instance_id: String,
+    backend: ClusterBackend,
+    store: Arc<dyn SharedStore>,
+    events: broadcast::Sender<ClusterEvent>,
+}
+
+impl Cluster {
+    /// Single instance with in-process state / 进程内状态的单实例
+    pub fn local() -> Self {
+        Self::with_store(resolve_instance_id(""), ClusterBackend::Memory, Arc::new(MemoryStore::new()))
+    }
+
+    /// Connect the configured backend / 连接配置的后端
+    pub async fn connect(config: &ClusterConfig, db: &SqlitePool) -> Result<Self, String> {
+        let instance_id = resolve_instance_id(&config.instance_id);
+        let store: Arc<dyn SharedStore> = match config.backend {
+            ClusterBackend::Memory => Arc::new(MemoryStore::new()),
+            ClusterBackend::Database => {
+                let store = DbStore::new(db.clone());
+                store.init().await?;
+                Arc::new(store)
+            }
+            #[cfg(feature = "redis")]
+            ClusterBackend::Redis => Arc::new(RedisStore::connect(&config.redis_url).await?),
+            #[cfg(not(feature = "redis"))]
+            ClusterBackend::Redis => return Err("Redis backend requires building with --features redis".to_string()),
+        };
+        let cluster = Self::with_store(instance_id, config.backend, store);
+        if cluster.is_shared() {
+            cluster.store.listen(cluster.instance_id.clone(), cluster.events.clone());
+        }
+        Ok(cluster)
+    }
+
+    fn with_store(instance_id: String, backend: ClusterBackend, store: Arc<dyn SharedStore>) -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self { instance_id, backend, store, events }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// Whether other replicas share this state / 是否与其他实例共享状态
+    pub fn is_shared(&self) -> bool {
+        self.backend != ClusterBackend::Memory
+    }
+
+    pub fn backend(&self) -> ClusterBackend {
+        self.backend
+    }
+
+    /// Whether frequent events (task progress) can be fanned out; polling the database for them
+    /// would only add write contention / 是否可以扇出高频事件（任务进度），数据库轮询只会增加写入竞争
+    pub fn has_fast_events(&self) -> bool {
+        self.backend == ClusterBackend::Redis
+    }
+
+    pub fn store(&self) -> &Arc<dyn SharedStore> {
+        &self.store
+    }
+
+    /// Send an event to the other replicas / 向其他实例发送事件
+    pub async fn publish(&self, topic: &str, payload: &str) {
+        if !self.is_shared() {
+            return;
+        }
+        let event = ClusterEvent {
+            origin: self.instance_id.clone(),
+            topic: topic.to_string(),
+            payload: payload.to_string(),
+        };
+        if let Err(e) = self.store.publish(&event).await {
+            tracing::warn!("Failed to publish cluster event {}: {}", topic, e);
+        }
+    }
+
+    /// Events of the other replicas / 其他实例的事件
+    pub fn subscribe(&self) -> broadcast::Receiver<ClusterEvent> {
+        self.events.subscribe()
+    }
+
+    /// Keep announcing this replica as alive / 持续上报本实例存活
+    pub fn start_heartbeat(self: &Arc<Self>) {
+        if !self.is_shared() {
+            return;
+        }
+        let cluster = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(HEARTBEAT_SECS));
+            loop {
+                interval.tick().await;
+                let key = format!("instance:{}", cluster.instance_id);
+                let now = chrono::Utc::now().to_rfc3339();
+                if let Err(e) = cluster.store.set(&key, &now, Duration::from_secs(HEARTBEAT_TTL_SECS)).await {
+                    tracing::warn!("Cluster heartbeat failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Whether a replica sent a heartbeat recently / 实例最近是否上报过心跳
+    pub async fn is_alive(&self, instance_id: &str) -> bool {
+        if instance_id == self.instance_id {
+            return true;
+        }
+        // 存储不可用时按存活处理，避免误判其他实例的任务中断
+        self.store.get(&format!("instance:{}", instance_id)).await
+            .map(|v| v.is_some())
+            .unwrap_or(true)
+    }
+
+    /// Lock held by at most one replica until `ttl` passes / 在 `ttl` 内至多由一个实例持有的锁
+    pub async fn try_lock(&self, name: &str, ttl: Duration) -> bool {
+        match self.store.set_nx(&format!("lock:{}", name), &self.instance_id, ttl).await {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                tracing::warn!("Failed to acquire cluster lock {}: {}", name, e);
+                false
+            }
+        }
+    }
+}
+
+/// Configured id, else the host name, else a random id / 配置的ID，否则主机名，否则随机ID
+fn resolve_instance_id(configured: &str) -> String {
+    let configured = configured.trim();
+    if !configured.is_empty() {
+        return configured.to_string();
+    }
+    std::env::var("HOSTNAME").ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string()[..12].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn check_store(store: &dyn SharedStore) {
+        let ttl = Duration::from_secs(60);
+        store.set("a", "1", ttl).await.unwrap();
+        assert_eq!(store.get("a").await.unwrap().as_deref(), Some("1"));
+        assert_eq!(store.take("a").await.unwrap().as_deref(), Some("1"));
+        assert_eq!(store.take("a").await.unwrap(), None);
+
+        assert!(store.set_nx("lock", "x", ttl).await.unwrap());
+        assert!(!store.set_nx("lock", "y", ttl).await.unwrap());
+        store.set("expired", "1", Duration::ZERO).await.unwrap();
+        assert_eq!(store.get("expired").await.unwrap(), None);
+        assert!(store.set_nx("expired", "2", ttl).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        check_store(&MemoryStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_db_store() {
+        // 内存数据库每个连接独立，只用一个连接
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let store = DbStore::new(pool);
+        store.init().await.unwrap();
+        check_store(&store).await;
+    }
+}
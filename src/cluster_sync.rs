@@ -0,0 +1,190 @@
+//! Keep replicas in sync / 多实例同步
+//!
+//! Settings, mounts and load balance groups are cached in memory by every replica. When an admin
+//! request changes them on one replica, it publishes an event and the others reload from the
+//! shared database. Control requests for tasks owned by another replica are forwarded the same way.
+//! 设置、挂载与负载均衡组由每个实例缓存在内存中。某个实例上的管理请求修改它们后会发布事件，
+//! 其他实例从共享数据库重新加载。对其他实例所属任务的控制请求也通过事件转发。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
+use yaolist_backend::cluster::{TaskControlEvent, EVENT_DRIVERS, EVENT_SETTINGS, EVENT_TASK_CONTROL, EVENT_TASK_EVENT};
+use yaolist_backend::load_balance::BalanceGroupConfig;
+
+use crate::state::AppState;
+use crate::task::TaskEvent;
+
+/// Writes under these paths change mounts / 这些路径下的写请求会修改挂载
+const DRIVER_PATHS: &[&str] = &["/api/drivers", "/api/mounts", "/api/load_balance", "/api/admin/restore"];
+/// Writes under these paths change cached settings / 这些路径下的写请求会修改缓存的设置
+const SETTINGS_PATHS: &[&str] = &["/api/settings", "/api/admin/", "/api/notifications/", "/api/tasks/"];
+
+/// Publish successful admin writes to the other replicas / 将成功的管理写请求通知其他实例
+pub async fn publish_changes(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.cluster.is_shared() || matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    if DRIVER_PATHS.iter().any(|p| path.starts_with(p)) {
+        state.cluster.publish(EVENT_DRIVERS, &path).await;
+    }
+    if SETTINGS_PATHS.iter().any(|p| path.starts_with(p)) {
+        state.cluster.publish(EVENT_SETTINGS, &path).await;
+    }
+    response
+}
+
+/// Apply events of the other replicas / 处理其他实例的事件
+pub fn start(state: Arc<AppState>) {
+    if !state.cluster.is_shared() {
+        return;
+    }
+    let mut events = state.cluster.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(n)) => {
+                    // 丢失事件时全部重新加载
+                    tracing::warn!("Missed {} cluster events, reloading everything", n);
+                    reload_settings(&state).await;
+                    sync_drivers(&state, None).await;
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if event.topic == EVENT_TASK_EVENT {
+                if let Ok(task_event) = serde_json::from_str::<TaskEvent>(&event.payload) {
+                    state.task_manager.apply_remote_event(task_event).await;
+                }
+                continue;
+            }
+            tracing::debug!("Cluster event from {}: {} {}", event.origin, event.topic, event.payload);
+            match event.topic.as_str() {
+                EVENT_SETTINGS => reload_settings(&state).await,
+                EVENT_DRIVERS => sync_drivers(&state, reload_target(&event.payload)).await,
+                EVENT_TASK_CONTROL => {
+                    if let Ok(control) = serde_json::from_str::<TaskControlEvent>(&event.payload) {
+                        state.task_manager.apply_remote_control(&control).await;
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+/// Driver id of `/api/drivers/{id}/reload` / 从重新加载请求路径中取驱动ID
+fn reload_target(path: &str) -> Option<String> {
+    path.strip_prefix("/api/drivers/")
+        .and_then(|rest| rest.strip_suffix("/reload"))
+        .map(str::to_string)
+}
+
+/// Reload settings cached in memory / 重新加载内存中的设置
+async fn reload_settings(state: &AppState) {
+    let db = &state.db;
+    let results = [
+        ("download settings", state.download_settings.load_from_db(db).await),
+        ("transfer settings", state.transfer_settings.load_from_db(db).await),
+        ("HTTP security settings", state.http_security.load_from_db(db).await),
+        ("response compression settings", state.compression.load_from_db(db).await),
+        ("login lockout", state.login_lockout.load_from_db(db).await),
+        ("captcha settings", state.captcha.load_from_db(db).await),
+        ("guest policy", state.guest.load_from_db(db).await),
+        ("file type categories", state.file_types.load_from_db(db).await),
+        ("webhooks", state.webhooks.load_from_db(db).await),
+        ("email templates", state.email_templates.load_from_db(db).await),
+        ("file hooks", state.file_hooks.load_from_db(db).await),
+        ("STRM exports", state.strm.load_from_db(db).await),
+        ("tiering policies", state.tiering.load_from_db(db).await),
+        ("workspaces", state.workspaces.load_from_db(db).await),
+        ("announcements", state.announcements.load_from_db(db).await),
+        ("archive passwords", state.archive_passwords.load_from_db(db).await),
+        ("feeds", state.feeds.load_from_db(db).await),
+        ("listing cache settings", state.storage_manager.list_cache().load_from_db(db).await),
+        ("maintenance mode", state.storage_manager.maintenance().load_from_db(db).await),
+    ];
+    for (name, result) in results {
+        if let Err(e) = result {
+            tracing::warn!("Failed to reload {}: {}", name, e);
+        }
+    }
+    state.task_manager.load_retention_from_db().await;
+}
+
+/// Bring mounts and load balance groups in line with the database / 使挂载与负载均衡组与数据库一致
+async fn sync_drivers(state: &AppState, force_reload: Option<String>) {
+    state.paths.invalidate_mounts();
+    let rows: Vec<(String, bool, Option<String>)> = match sqlx::query_as("SELECT name, enabled, config FROM drivers")
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Failed to read drivers for sync: {}", e);
+            return;
+        }
+    };
+
+    let manager = &state.storage_manager;
+    let mut known = HashSet::new();
+    for (id, enabled, config) in rows {
+        known.insert(id.clone());
+        let spec = manager.get_driver_spec(&id).await;
+        if !enabled {
+            if spec.is_some() || manager.get_driver(&id).await.is_some() {
+                let _ = manager.remove_driver(&id).await;
+            }
+            continue;
+        }
+        let Some(Ok(config)) = config.map(|c| serde_json::from_str::<Value>(&c)) else { continue };
+        let (Some(driver_type), Some(driver_config)) = (config["driver_type"].as_str(), config.get("config")) else {
+            continue;
+        };
+        if force_reload.as_deref() == Some(id.as_str()) {
+            let _ = manager.remove_driver(&id).await;
+        } else if spec.is_some_and(|s| s.driver_type == driver_type && &s.config == driver_config) {
+            continue;
+        }
+        if let Err(e) = manager.update_driver_config(&id, driver_type, driver_config.clone()).await {
+            tracing::warn!("Failed to sync driver {}: {}", id, e);
+        }
+    }
+    for id in manager.list_drivers().await {
+        if !known.contains(&id) {
+            let _ = manager.remove_driver(&id).await;
+        }
+    }
+
+    let groups: Vec<(String,)> = sqlx::query_as("SELECT config FROM load_balance_groups")
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+    let mut names = HashSet::new();
+    for (config,) in groups {
+        if let Ok(config) = serde_json::from_str::<BalanceGroupConfig>(&config) {
+            names.insert(config.name.clone());
+            state.load_balance.update_group(config).await;
+        }
+    }
+    for group in state.load_balance.get_all_groups().await {
+        if !names.contains(&group.name) {
+            state.load_balance.delete_group(&group.name).await;
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Response compression / 响应压缩
+//!
+//! Responses are compressed with Brotli or gzip, whichever the client prefers in
+//! `Accept-Encoding`. Only text-like content types (JSON, HTML, CSS, JS, XML, SVG, plain text
+//! downloads) are compressed; images, video, audio and archives are already compressed and are
+//! passed through. Range responses and bodies smaller than `min_size` are never compressed.
+//! Settings are stored as JSON in `site_settings` (`response_compression`) and apply to the next
+//! request after saving.
+//! 按客户端 `Accept-Encoding` 的偏好使用 Brotli 或 gzip 压缩响应。仅压缩文本类内容（JSON、HTML、CSS、JS、
+//! XML、SVG、文本文件下载），图片、视频、音频和压缩包本身已压缩，原样返回。分段（Range）响应和小于
+//! `min_size` 的响应不压缩。设置以 JSON 保存在 `site_settings`（`response_compression`），保存后立即生效。
+
+use std::sync::Arc;
+use axum::body::HttpBody;
+use axum::http::{header, Response, StatusCode};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_http::compression::{CompressionLayer, Predicate};
+
+/// Settings key / 设置键
+pub const RESPONSE_COMPRESSION_KEY: &str = "response_compression";
+
+/// Response compression settings / 响应压缩设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Smallest body in bytes worth compressing; unknown sizes (streams) are compressed
+    /// 值得压缩的最小字节数；大小未知的流式响应会压缩
+    pub min_size: u64,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: 1024,
+        }
+    }
+}
+
+/// Whether a content type benefits from compression / 内容类型是否值得压缩
+pub fn is_compressible(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    let Some((kind, subtype)) = essence.split_once('/') else {
+        return false;
+    };
+    match kind {
+        // 事件流需要逐条推送，不能缓冲压缩
+        "text" => subtype != "event-stream",
+        "image" => subtype == "svg+xml",
+        "application" => {
+            subtype.ends_with("+json")
+                || subtype.ends_with("+xml")
+                || matches!(
+                    subtype,
+                    "json" | "x-ndjson" | "javascript" | "x-javascript" | "ecmascript"
+                        | "xml" | "wasm" | "x-sh" | "x-yaml" | "yaml" | "toml" | "sql"
+                )
+        }
+        _ => false,
+    }
+}
+
+/// Settings cache shared with the compression layer / 设置缓存（供压缩层读取）
+pub struct ResponseCompression {
+    config: RwLock<CompressionConfig>,
+}
+
+impl ResponseCompression {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(CompressionConfig::default()),
+        }
+    }
+
+    /// Load settings from database / 从数据库加载设置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let value: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = ?"
+        )
+        .bind(RESPONSE_COMPRESSION_KEY)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((json,)) = value {
+            let config: CompressionConfig = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            self.set(config);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self) -> CompressionConfig {
+        self.config.read().clone()
+    }
+
+    pub fn set(&self, config: CompressionConfig) {
+        *self.config.write() = config;
+    }
+
+    /// Gzip and Brotli layer that follows these settings / 按此设置工作的 gzip 与 Brotli 压缩层
+    pub fn layer(self: &Arc<Self>) -> CompressionLayer<CompressionPredicate> {
+        CompressionLayer::new()
+            .gzip(true)
+            .br(true)
+            .compress_when(CompressionPredicate(self.clone()))
+    }
+}
+
+impl Default for ResponseCompression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decides per response whether to compress / 按响应决定是否压缩
+#[derive(Clone)]
+pub struct CompressionPredicate(Arc<ResponseCompression>);
+
+impl Predicate for CompressionPredicate {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: HttpBody,
+    {
+        should_compress(&self.0.get(), response)
+    }
+}
+
+fn should_compress<B: HttpBody>(config: &CompressionConfig, response: &Response<B>) -> bool {
+    if !config.enabled {
+        return false;
+    }
+    let headers = response.headers();
+    // 分段响应压缩后字节偏移不再对应原文件
+    if response.status() == StatusCode::PARTIAL_CONTENT || headers.contains_key(header::CONTENT_RANGE) {
+        return false;
+    }
+    if headers.contains_key(header::CONTENT_ENCODING) {
+        return false;
+    }
+    let compressible = headers.get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(is_compressible);
+    if !compressible {
+        return false;
+    }
+    let size = response.body().size_hint().exact().or_else(|| {
+        headers.get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    });
+    size.is_none_or(|size| size >= config.min_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn response(content_type: &str, body: &'static str) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_should_compress() {
+        assert!(is_compressible("application/json; charset=utf-8"));
+        assert!(is_compressible("text/plain"));
+        assert!(is_compressible("image/svg+xml"));
+        assert!(is_compressible("application/manifest+json"));
+        assert!(!is_compressible("text/event-stream"));
+        assert!(!is_compressible("image/png"));
+        assert!(!is_compressible("application/zip"));
+        assert!(!is_compressible("application/octet-stream"));
+
+        let config = CompressionConfig { enabled: true, min_size: 4 };
+        assert!(should_compress(&config, &response("application/json", "{\"a\":1}")));
+        assert!(!should_compress(&config, &response("application/json", "{}")));
+        assert!(!should_compress(&config, &response("video/mp4", "0123456789")));
+
+        let mut partial = response("text/plain", "0123456789");
+        *partial.status_mut() = StatusCode::PARTIAL_CONTENT;
+        assert!(!should_compress(&config, &partial));
+
+        let disabled = CompressionConfig { enabled: false, ..config };
+        assert!(!should_compress(&disabled, &response("application/json", "{\"a\":1}")));
+    }
+}
@@ -6,6 +6,7 @@
 use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -23,6 +24,21 @@ pub struct AppConfig {
     pub search: SearchConfig,
     /// GeoIP configuration / GeoIP配置
     pub geoip: GeoIpConfig,
+    /// Logging configuration (live) / 日志配置（即时生效）
+    #[serde(default)]
+    pub log: LogConfig,
+    /// Request limits (live) / 请求限制（即时生效）
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// Cache sizes (live) / 缓存配置（即时生效）
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// HTTPS listener / HTTPS 配置
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// State shared between replicas / 多实例共享状态
+    #[serde(default)]
+    pub cluster: ClusterConfig,
 }
 
 /// Server configuration / 服务器配置
@@ -32,6 +48,37 @@ pub struct ServerConfig {
     pub host: String,
     /// Server port / 服务器端口
     pub port: u16,
+    /// Seconds to drain requests and checkpoint tasks on shutdown / 停机时等待请求结束、保存任务断点的秒数
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// Negotiate HTTP/2 on the TLS listener / TLS 监听协商 HTTP/2
+    #[serde(default = "default_http2")]
+    pub http2: bool,
+    /// Also serve on this Unix socket (e.g. behind nginx), empty = off / 额外监听的 Unix 套接字（如供 nginx 反代），为空不启用
+    #[serde(default)]
+    pub unix_socket: String,
+    /// Octal permissions of the socket file / 套接字文件权限（八进制）
+    #[serde(default = "default_unix_socket_mode")]
+    pub unix_socket_mode: String,
+    /// Proxies whose forwarding headers are trusted, IPs or CIDRs (live) / 信任其转发头的代理地址或网段（即时生效）
+    #[serde(default = "default_trusted_proxies")]
+    pub trusted_proxies: Vec<String>,
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
+fn default_http2() -> bool {
+    true
+}
+
+fn default_unix_socket_mode() -> String {
+    "660".to_string()
+}
+
+fn default_trusted_proxies() -> Vec<String> {
+    vec!["127.0.0.1".to_string(), "::1".to_string()]
 }
 
 /// Database configuration / 数据库配置
@@ -41,6 +88,34 @@ pub struct DatabaseConfig {
     pub data_dir: String,
     /// Main database file path (relative to data_dir) / 主数据库文件路径
     pub db_file: String,
+    /// Max pooled connections / 连接池最大连接数
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    /// Milliseconds a connection waits for a locked database before failing / 数据库被锁时连接的等待毫秒数
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+    /// Write-ahead log, readers no longer block the writer / 预写日志模式，读操作不再阻塞写操作
+    #[serde(default = "default_wal")]
+    pub wal: bool,
+    /// Seconds between batched task progress writes / 任务进度批量写入的间隔秒数
+    #[serde(default = "default_progress_flush_secs")]
+    pub progress_flush_secs: u64,
+}
+
+fn default_max_connections() -> u32 {
+    10
+}
+
+fn default_busy_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_wal() -> bool {
+    true
+}
+
+fn default_progress_flush_secs() -> u64 {
+    5
 }
 
 /// Search configuration / 搜索配置
@@ -59,6 +134,117 @@ pub struct GeoIpConfig {
     pub db_dir: String,
 }
 
+/// Logging configuration / 日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// Filter in `RUST_LOG` syntax, the env var wins at startup / `RUST_LOG` 语法的过滤规则，启动时环境变量优先
+    pub level: String,
+}
+
+/// Request limits / 请求限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// Max entries per page of directory listings / 目录列表每页最大条目数
+    pub max_page_size: u32,
+    /// Max request body of API routes in bytes, 0 = unlimited / API 请求体上限（字节），0 表示不限
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: u64,
+    /// Max request body of upload routes (`/api/fs/upload`, `/api/fs/write`, WebDAV), 0 = unlimited
+    /// 上传接口（`/api/fs/upload`、`/api/fs/write`、WebDAV）的请求体上限，0 表示不限
+    #[serde(default)]
+    pub max_upload_size: u64,
+    /// Per-route overrides: path prefix to max bytes (0 = unlimited), the longest prefix wins
+    /// 按路由覆盖：路径前缀到字节上限（0 表示不限），最长前缀优先
+    #[serde(default)]
+    pub route_body_limits: BTreeMap<String, u64>,
+}
+
+fn default_max_body_size() -> u64 {
+    64 * 1024 * 1024
+}
+
+/// Cache configuration / 缓存配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Archive listing cache lifetime in seconds / 压缩包目录缓存时长（秒）
+    pub archive_ttl_secs: u64,
+    /// Max archives kept in the listing cache / 压缩包目录缓存的最大条目数
+    pub archive_max_entries: usize,
+}
+
+/// HTTPS configuration / HTTPS 配置
+///
+/// When enabled the main listener (`server.host:server.port`) serves HTTPS.
+/// 启用后主监听地址改为提供 HTTPS。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// PEM certificate chain, unused in ACME mode / PEM 证书链（ACME 模式下不使用）
+    #[serde(default)]
+    pub cert_path: String,
+    /// PEM private key, unused in ACME mode / PEM 私钥（ACME 模式下不使用）
+    #[serde(default)]
+    pub key_path: String,
+    #[serde(default)]
+    pub acme: AcmeConfig,
+}
+
+/// Automatic certificates via ACME HTTP-01 (Let's Encrypt) / ACME 自动证书（HTTP-01 验证）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcmeConfig {
+    pub enabled: bool,
+    /// Domains on the certificate, all must resolve to this server / 证书包含的域名，需解析到本服务器
+    pub domains: Vec<String>,
+    /// Contact email for expiry notices / 联系邮箱
+    pub email: String,
+    pub directory_url: String,
+    /// Plain HTTP port answering challenges and redirecting to HTTPS / 响应验证并重定向到 HTTPS 的 HTTP 端口
+    pub http_port: u16,
+    /// Account and certificate directory (relative to data_dir) / 账号与证书目录（相对data_dir）
+    pub dir: String,
+}
+
+impl Default for AcmeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            domains: Vec::new(),
+            email: String::new(),
+            directory_url: "https://acme-v02.api.letsencrypt.org/directory".to_string(),
+            http_port: 80,
+            dir: "acme".to_string(),
+        }
+    }
+}
+
+/// Shared state backend / 共享状态后端
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterBackend {
+    /// Single instance, state kept in process / 单实例，状态保存在进程内
+    #[default]
+    Memory,
+    /// Replicas share the database / 多个实例共享数据库
+    Database,
+    /// Replicas share a Redis server (`redis` feature) / 多个实例共享 Redis（需 `redis` 特性）
+    Redis,
+}
+
+/// Multi-instance configuration / 多实例配置
+///
+/// Replicas behind one load balancer must use the same database and a shared backend.
+/// 同一负载均衡后的多个实例需使用同一数据库与共享后端。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    pub backend: ClusterBackend,
+    /// e.g. redis://127.0.0.1:6379/0 / 如 redis://127.0.0.1:6379/0
+    #[serde(default)]
+    pub redis_url: String,
+    /// Stable name of this replica, defaults to the host name / 实例的固定名称，默认为主机名
+    #[serde(default)]
+    pub instance_id: String,
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -66,6 +252,11 @@ impl Default for AppConfig {
             database: DatabaseConfig::default(),
             search: SearchConfig::default(),
             geoip: GeoIpConfig::default(),
+            log: LogConfig::default(),
+            limits: LimitsConfig::default(),
+            cache: CacheConfig::default(),
+            tls: TlsConfig::default(),
+            cluster: ClusterConfig::default(),
         }
     }
 }
@@ -75,15 +266,31 @@ impl Default for ServerConfig {
         Self {
             host: "0.0.0.0".to_string(),
             port: 8180,
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            http2: default_http2(),
+            unix_socket: String::new(),
+            unix_socket_mode: default_unix_socket_mode(),
+            trusted_proxies: default_trusted_proxies(),
         }
     }
 }
 
+impl ServerConfig {
+    /// Parsed socket file mode / 解析后的套接字文件权限
+    pub fn unix_socket_mode(&self) -> Option<u32> {
+        u32::from_str_radix(self.unix_socket_mode.trim(), 8).ok().filter(|m| *m <= 0o777)
+    }
+}
+
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
             data_dir: "data".to_string(),
             db_file: "yaolist.db".to_string(),
+            max_connections: default_max_connections(),
+            busy_timeout_ms: default_busy_timeout_ms(),
+            wal: default_wal(),
+            progress_flush_secs: default_progress_flush_secs(),
         }
     }
 }
@@ -105,7 +312,111 @@ impl Default for GeoIpConfig {
     }
 }
 
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LOG_LEVEL.to_string(),
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_page_size: 100,
+            max_body_size: default_max_body_size(),
+            max_upload_size: 0,
+            route_body_limits: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            archive_ttl_secs: 300,
+            archive_max_entries: 256,
+        }
+    }
+}
+
+/// Default log filter / 默认日志过滤规则
+pub const DEFAULT_LOG_LEVEL: &str = "yaolist_backend=debug,tower_http=debug";
+
 impl AppConfig {
+    /// Check values before they are applied / 应用前校验配置
+    pub fn validate(&self) -> Result<(), String> {
+        if self.server.host.trim().is_empty() {
+            return Err("server.host cannot be empty".to_string());
+        }
+        if self.server.port == 0 {
+            return Err("server.port cannot be 0".to_string());
+        }
+        if self.server.unix_socket_mode().is_none() {
+            return Err("server.unix_socket_mode must be an octal mode like 660".to_string());
+        }
+        crate::client_ip::TrustedProxies::parse(&self.server.trusted_proxies)
+            .map_err(|e| format!("server.trusted_proxies: {}", e))?;
+        if self.database.data_dir.trim().is_empty() || self.database.db_file.trim().is_empty() {
+            return Err("database.data_dir and database.db_file cannot be empty".to_string());
+        }
+        if self.log.level.trim().is_empty() {
+            return Err("log.level cannot be empty".to_string());
+        }
+        if self.limits.max_page_size == 0 {
+            return Err("limits.max_page_size must be at least 1".to_string());
+        }
+        if self.limits.route_body_limits.keys().any(|prefix| !prefix.starts_with('/')) {
+            return Err("limits.route_body_limits keys must be paths starting with /".to_string());
+        }
+        if self.tls.enabled {
+            if self.tls.acme.enabled {
+                if self.tls.acme.domains.iter().all(|d| d.trim().is_empty()) {
+                    return Err("tls.acme.domains cannot be empty".to_string());
+                }
+                if self.tls.acme.http_port == 0 || self.tls.acme.http_port == self.server.port {
+                    return Err("tls.acme.http_port must differ from server.port".to_string());
+                }
+            } else if self.tls.cert_path.trim().is_empty() || self.tls.key_path.trim().is_empty() {
+                return Err("tls.cert_path and tls.key_path are required".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Changed options that only take effect after a restart / 修改后需要重启才能生效的配置项
+    pub fn restart_required(&self, new: &AppConfig) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.server.host != new.server.host {
+            fields.push("server.host");
+        }
+        if self.server.port != new.server.port {
+            fields.push("server.port");
+        }
+        if self.server.http2 != new.server.http2 {
+            fields.push("server.http2");
+        }
+        if self.server.unix_socket != new.server.unix_socket || self.server.unix_socket_mode != new.server.unix_socket_mode {
+            fields.push("server.unix_socket");
+        }
+        if self.database.data_dir != new.database.data_dir {
+            fields.push("database.data_dir");
+        }
+        if self.database.db_file != new.database.db_file {
+            fields.push("database.db_file");
+        }
+        if self.search.db_dir != new.search.db_dir || self.search.db_file != new.search.db_file {
+            fields.push("search");
+        }
+        if self.geoip.db_dir != new.geoip.db_dir {
+            fields.push("geoip.db_dir");
+        }
+        if serde_json::to_value(&self.tls).ok() != serde_json::to_value(&new.tls).ok() {
+            fields.push("tls");
+        }
+        fields
+    }
+
     /// Get the full database URL / 获取完整的数据库URL
     pub fn get_database_url(&self) -> String {
         let db_path = Path::new(&self.database.data_dir).join(&self.database.db_file);
@@ -143,6 +454,21 @@ impl AppConfig {
         search_dir.join(format!("index_{}.db", driver_id))
     }
 
+    /// Get the driver plugins directory / 获取驱动插件目录
+    pub fn get_plugins_dir(&self) -> PathBuf {
+        self.get_data_dir().join("plugins")
+    }
+
+    /// Get the plugin private data directory / 获取插件私有数据目录
+    pub fn get_plugin_data_dir(&self) -> PathBuf {
+        self.get_data_dir().join("plugin_data")
+    }
+
+    /// Get the ACME account and certificate directory / 获取ACME账号与证书目录
+    pub fn get_acme_dir(&self) -> PathBuf {
+        self.get_data_dir().join(&self.tls.acme.dir)
+    }
+
     /// Get the GeoIP database directory / 获取GeoIP数据库目录
     pub fn get_geoip_dir(&self) -> PathBuf {
         let data_dir = self.get_data_dir();
@@ -160,7 +486,7 @@ impl AppConfig {
 }
 
 /// Get the config file path / 获取配置文件路径
-fn get_config_path() -> PathBuf {
+pub fn get_config_path() -> PathBuf {
     std::env::current_dir()
         .unwrap_or_else(|_| PathBuf::from("."))
         .join("config.json")
@@ -226,3 +552,99 @@ pub fn get_config() -> Arc<RwLock<AppConfig>> {
 pub fn config() -> AppConfig {
     get_config().read().clone()
 }
+
+/// Applies live options (log level) when config changes / 配置变更时应用即时生效的选项（日志级别）
+pub type LiveApplier = Box<dyn Fn(&AppConfig) -> Result<(), String> + Send + Sync>;
+
+static LIVE_APPLIER: OnceCell<LiveApplier> = OnceCell::new();
+
+/// Register the live applier, set once by the binary / 注册即时生效回调（由主程序设置一次）
+pub fn set_live_applier(applier: LiveApplier) {
+    let _ = LIVE_APPLIER.set(applier);
+}
+
+/// Validate, apply and persist a new config / 校验、应用并保存新配置
+///
+/// Returns the changed options that need a restart. Live options are applied first, so an
+/// invalid log filter is rejected before anything is written.
+/// 返回需要重启才能生效的配置项；先应用即时选项，无效的日志规则不会被写入文件。
+pub fn update_config(new: AppConfig) -> Result<Vec<&'static str>, String> {
+    new.validate()?;
+    let global = get_config();
+    let restart = global.read().restart_required(&new);
+    if let Some(apply) = LIVE_APPLIER.get() {
+        apply(&new)?;
+    }
+    save_config(&new)?;
+    *global.write() = new;
+    Ok(restart)
+}
+
+/// Re-read the config file (SIGHUP or admin request) / 重新读取配置文件（SIGHUP 或管理员请求）
+pub fn reload_config() -> Result<Vec<&'static str>, String> {
+    let content = std::fs::read_to_string(get_config_path())
+        .map_err(|e| format!("Failed to read config file: {}", e))?;
+    let new: AppConfig = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    new.validate()?;
+    let global = get_config();
+    let restart = global.read().restart_required(&new);
+    if let Some(apply) = LIVE_APPLIER.get() {
+        apply(&new)?;
+    }
+    *global.write() = new;
+    Ok(restart)
+}
+
+/// Deep-merge a JSON patch into a config value (objects merge, other values replace)
+/// 将 JSON 补丁深度合并到配置（对象合并，其他值替换）
+pub fn merge_json(base: &mut serde_json::Value, patch: serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge_json(base.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_and_restart_required() {
+        let old = AppConfig::default();
+        let mut value = serde_json::to_value(&old).unwrap();
+        merge_json(&mut value, serde_json::json!({
+            "server": { "port": 9000 },
+            "cache": { "archive_ttl_secs": 60 }
+        }));
+        let new: AppConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(new.server.port, 9000);
+        assert_eq!(new.server.host, old.server.host);
+        assert_eq!(new.cache.archive_ttl_secs, 60);
+        assert_eq!(new.cache.archive_max_entries, old.cache.archive_max_entries);
+        assert_eq!(old.restart_required(&new), vec!["server.port"]);
+    }
+
+    #[test]
+    fn test_old_config_file_parses() {
+        let config: AppConfig = serde_json::from_str(r#"{
+            "server": { "host": "0.0.0.0", "port": 8180 },
+            "database": { "data_dir": "data", "db_file": "yaolist.db" },
+            "search": { "db_dir": "search", "db_file": "search.db" },
+            "geoip": { "db_dir": "" }
+        }"#).unwrap();
+        assert_eq!(config.log.level, DEFAULT_LOG_LEVEL);
+        assert_eq!(config.limits.max_page_size, 100);
+        assert_eq!(config.limits.max_body_size, 64 * 1024 * 1024);
+        assert!(config.validate().is_ok());
+
+        // limits without the body size fields / 缺少请求体上限字段的 limits
+        let limits: LimitsConfig = serde_json::from_str(r#"{ "max_page_size": 50 }"#).unwrap();
+        assert_eq!(limits.max_upload_size, 0);
+        assert!(limits.route_body_limits.is_empty());
+    }
+}
@@ -1,11 +1,15 @@
+use std::str::FromStr;
+use std::time::Duration;
 use sqlx::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
 use anyhow::Result;
+use yaolist_backend::config::DatabaseConfig;
 use uuid::Uuid;
 use chrono::Utc;
 use rand::Rng;
 
 /// Generate random password / 生成随机密码
-fn generate_random_password(length: usize) -> String {
+pub fn generate_random_password(length: usize) -> String {
     const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghjkmnpqrstuvwxyz23456789!@#$%^&*";
     let mut rng = rand::thread_rng();
     (0..length)
@@ -16,6 +20,27 @@ fn generate_random_password(length: usize) -> String {
         .collect()
 }
 
+/// Open the main database pool / 打开主数据库连接池
+///
+/// `busy_timeout` is set on every connection so concurrent writers wait for the lock instead of
+/// failing with "database is locked"; WAL lets reads run while a write is in progress.
+/// 每个连接都设置 busy_timeout，并发写入时等待锁而不是直接报错；WAL 模式下读写可同时进行。
+pub async fn connect(database_url: &str, config: &DatabaseConfig) -> Result<SqlitePool> {
+    let mut options = SqliteConnectOptions::from_str(database_url)?
+        .busy_timeout(Duration::from_millis(config.busy_timeout_ms));
+    if config.wal {
+        // NORMAL is safe with WAL and avoids an fsync per commit / WAL 下 NORMAL 是安全的，且每次提交无需 fsync
+        options = options
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal);
+    }
+    let pool = SqlitePoolOptions::new()
+        .max_connections(config.max_connections.max(1))
+        .connect_with(options)
+        .await?;
+    Ok(pool)
+}
+
 /// Run database migrations / 运行数据库迁移
 pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     sqlx::query(
@@ -177,6 +202,38 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS login_failures (
+            scope TEXT NOT NULL,
+            key TEXT NOT NULL,
+            fail_count INTEGER NOT NULL DEFAULT 0,
+            last_failure TEXT NOT NULL,
+            locked_until TEXT,
+            PRIMARY KEY (scope, key)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS workspaces (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            slug TEXT NOT NULL UNIQUE,
+            domains TEXT NOT NULL DEFAULT '[]',
+            root_path TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS direct_links (
@@ -219,6 +276,37 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    // 用户、会话与分享所属的工作区
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN workspace_id TEXT NOT NULL DEFAULT 'default'").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE sessions ADD COLUMN workspace_id TEXT NOT NULL DEFAULT 'default'").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE shares ADD COLUMN workspace_id TEXT NOT NULL DEFAULT 'default'").execute(pool).await;
+
+    // 账号过期时间与首次登录强制修改密码
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN expires_at TEXT").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN must_change_password INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+
+    // 管理员模拟登录的会话：发起模拟的管理员及其原会话
+    let _ = sqlx::query("ALTER TABLE sessions ADD COLUMN impersonator_id TEXT").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE sessions ADD COLUMN parent_session_id TEXT").execute(pool).await;
+
+    // 用户组默认设置（流量配额、下载限速、可见挂载点），用户上的同名字段为空时继承用户组
+    let _ = sqlx::query("ALTER TABLE user_groups ADD COLUMN traffic_quota INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE user_groups ADD COLUMN download_speed_limit INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE user_groups ADD COLUMN visible_mounts TEXT").execute(pool).await;
+    // 用户组单文件上传上限（字节），0 表示不限
+    let _ = sqlx::query("ALTER TABLE user_groups ADD COLUMN max_upload_size INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+    // 用户组上传扩展名允许/拒绝列表（逗号分隔），为空表示不限
+    let _ = sqlx::query("ALTER TABLE user_groups ADD COLUMN upload_allow_ext TEXT").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE user_groups ADD COLUMN upload_deny_ext TEXT").execute(pool).await;
+    // 用户组要求成员启用两步验证
+    let _ = sqlx::query("ALTER TABLE user_groups ADD COLUMN require_2fa INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN allow_share INTEGER").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN traffic_quota INTEGER").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN download_speed_limit INTEGER").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN visible_mounts TEXT").execute(pool).await;
+    // 已发送流量配额提醒时的配额，配额变更后可再次提醒
+    let _ = sqlx::query("ALTER TABLE users ADD COLUMN quota_warned_for INTEGER").execute(pool).await;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS tasks (
@@ -253,6 +341,338 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN files TEXT").execute(pool).await;
     let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN items TEXT").execute(pool).await;
     let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN conflict_strategy TEXT").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN verification TEXT").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN buffer_size INTEGER").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN group_id TEXT").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN fsck TEXT").execute(pool).await;
+    // 多实例部署时任务所属实例
+    let _ = sqlx::query("ALTER TABLE tasks ADD COLUMN instance_id TEXT").execute(pool).await;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS webhooks (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            events TEXT NOT NULL DEFAULT '[]',
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS file_hooks (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            events TEXT NOT NULL DEFAULT '[]',
+            path_prefix TEXT,
+            action TEXT NOT NULL,
+            timeout_secs INTEGER NOT NULL DEFAULT 30,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS strm_exports (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            source_paths TEXT NOT NULL DEFAULT '[]',
+            target TEXT NOT NULL,
+            extensions TEXT NOT NULL DEFAULT '[]',
+            base_url TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 创建归档分层表（策略、已分层文件映射、热目录文件访问时间）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS tiering_policies (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            hot_path TEXT NOT NULL,
+            cold_path TEXT NOT NULL,
+            idle_days INTEGER NOT NULL DEFAULT 30,
+            min_size INTEGER NOT NULL DEFAULT 0,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS tiered_files (
+            path TEXT PRIMARY KEY,
+            policy_id TEXT NOT NULL,
+            cold_path TEXT NOT NULL,
+            size INTEGER NOT NULL DEFAULT 0,
+            modified TEXT,
+            tiered_at TEXT NOT NULL,
+            recalled_at TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS file_access (
+            path TEXT PRIMARY KEY,
+            accessed_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 创建文件哈希缓存表（重复文件扫描使用，大小或修改时间变化后失效）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS file_hashes (
+            path TEXT PRIMARY KEY,
+            size INTEGER NOT NULL,
+            modified TEXT,
+            hash TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 创建图片信息缓存表（相册使用，大小或修改时间变化后失效）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS image_meta (
+            path TEXT PRIMARY KEY,
+            size INTEGER NOT NULL,
+            modified TEXT,
+            taken_at TEXT,
+            width INTEGER,
+            height INTEGER,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 创建音频标签缓存表（播放列表使用，大小或修改时间变化后失效）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS audio_meta (
+            path TEXT PRIMARY KEY,
+            size INTEGER NOT NULL,
+            modified TEXT,
+            tags TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS reading_positions (
+            user_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            position TEXT NOT NULL,
+            progress REAL,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (user_id, path),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS playback_progress (
+            user_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            position REAL NOT NULL,
+            duration REAL,
+            finished INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (user_id, path),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_playback_progress_recent ON playback_progress(user_id, updated_at)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS announcements (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL DEFAULT '',
+            content TEXT NOT NULL DEFAULT '',
+            severity TEXT NOT NULL DEFAULT 'info',
+            starts_at TEXT,
+            ends_at TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS archive_passwords (
+            id TEXT PRIMARY KEY,
+            pattern TEXT NOT NULL,
+            password TEXT NOT NULL,
+            note TEXT NOT NULL DEFAULT '',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 目录订阅源：公开列出目录中最近的文件（RSS / Atom / JSON Feed）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS feeds (
+            id TEXT PRIMARY KEY,
+            path TEXT NOT NULL,
+            title TEXT NOT NULL DEFAULT '',
+            description TEXT NOT NULL DEFAULT '',
+            max_items INTEGER NOT NULL DEFAULT 50,
+            recursive INTEGER NOT NULL DEFAULT 0,
+            link_days INTEGER NOT NULL DEFAULT 7,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 账号登录过的国家（GeoIP），用于新国家登录告警
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS user_login_countries (
+            user_id TEXT NOT NULL,
+            country TEXT NOT NULL,
+            first_seen TEXT NOT NULL,
+            last_seen TEXT NOT NULL,
+            PRIMARY KEY (user_id, country)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 站内分享：分享给指定用户或用户组，接收者在“/Shared with me”下访问
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS internal_shares (
+            id TEXT PRIMARY KEY,
+            owner_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            name TEXT NOT NULL,
+            is_dir INTEGER NOT NULL DEFAULT 1,
+            target_type TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            access TEXT NOT NULL DEFAULT 'read',
+            workspace_id TEXT NOT NULL DEFAULT 'default',
+            created_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_internal_shares_target ON internal_shares(target_type, target_id)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS scheduled_jobs (
+            id TEXT PRIMARY KEY,
+            cron TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            jitter_secs INTEGER NOT NULL DEFAULT 0,
+            last_run_at TEXT,
+            last_status TEXT,
+            last_message TEXT,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS scheduled_job_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id TEXT NOT NULL,
+            trigger_type TEXT NOT NULL,
+            status TEXT NOT NULL,
+            message TEXT,
+            started_at TEXT NOT NULL,
+            finished_at TEXT,
+            duration_ms INTEGER
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_scheduled_job_runs_job ON scheduled_job_runs(job_id, id)")
+        .execute(pool)
+        .await?;
+    let _ = sqlx::query("ALTER TABLE scheduled_job_runs ADD COLUMN instance_id TEXT").execute(pool).await;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS task_groups (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            user_id TEXT,
+            created_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
 
     sqlx::query(
         r#"
@@ -269,6 +689,10 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
             r_sub INTEGER NOT NULL DEFAULT 0,
             header TEXT,
             header_sub INTEGER NOT NULL DEFAULT 0,
+            preview_only INTEGER NOT NULL DEFAULT 0,
+            po_sub INTEGER NOT NULL DEFAULT 0,
+            watermark INTEGER NOT NULL DEFAULT 0,
+            wm_sub INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL
         )
@@ -276,6 +700,12 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     )
     .execute(pool)
     .await?;
+    // 仅预览：允许浏览和在线预览，禁止下载原文件与直链
+    let _ = sqlx::query("ALTER TABLE metas ADD COLUMN preview_only INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE metas ADD COLUMN po_sub INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+    // 预览水印：图片与 PDF 预览叠加用户名和时间
+    let _ = sqlx::query("ALTER TABLE metas ADD COLUMN watermark INTEGER NOT NULL DEFAULT 0").execute(pool).await;
+    let _ = sqlx::query("ALTER TABLE metas ADD COLUMN wm_sub INTEGER NOT NULL DEFAULT 0").execute(pool).await;
 
     sqlx::query(
         r#"
@@ -429,6 +859,122 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    // 创建上传放置记录表（负载均衡组按空间选择的成员，读取时优先使用）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS upload_placements (
+            path TEXT PRIMARY KEY,
+            driver_id TEXT NOT NULL,
+            group_name TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 创建存储用量历史表（定时采集各挂载点空间）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS storage_usage_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            driver_id TEXT NOT NULL,
+            mount_path TEXT NOT NULL,
+            used INTEGER NOT NULL,
+            total INTEGER NOT NULL,
+            free INTEGER NOT NULL,
+            recorded_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_storage_usage_driver ON storage_usage_history(driver_id, recorded_at)")
+        .execute(pool)
+        .await?;
+
+    // 创建用户组路径授权表（在某路径之下覆盖用户组的文件权限）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS group_path_grants (
+            group_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            read_files INTEGER NOT NULL DEFAULT 0,
+            create_upload INTEGER NOT NULL DEFAULT 0,
+            rename_files INTEGER NOT NULL DEFAULT 0,
+            move_files INTEGER NOT NULL DEFAULT 0,
+            copy_files INTEGER NOT NULL DEFAULT 0,
+            delete_files INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (group_id, path)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 创建存储可见性表（设置了映射的存储只对所列用户组可见）
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS mount_group_visibility (
+            driver_id TEXT NOT NULL,
+            group_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (driver_id, group_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // 创建审计日志表
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_logs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            actor_id TEXT NOT NULL,
+            actor_name TEXT NOT NULL,
+            action TEXT NOT NULL,
+            target_id TEXT,
+            target_name TEXT,
+            detail TEXT,
+            ip TEXT,
+            workspace_id TEXT NOT NULL DEFAULT 'default',
+            created_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_logs_action ON audit_logs(action, id)")
+        .execute(pool)
+        .await?;
+
+    // 创建上传病毒扫描结果表
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS virus_scans (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            user_id TEXT,
+            size INTEGER,
+            status TEXT NOT NULL,
+            signature TEXT,
+            detail TEXT,
+            quarantine_file TEXT,
+            created_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_virus_scans_status ON virus_scans(status, id)")
+        .execute(pool)
+        .await?;
+
     tracing::info!("Database migration completed");
     
     initialize_default_data(pool).await?;
@@ -0,0 +1,244 @@
+//! Duplicate file report / 重复文件报告
+//!
+//! This module handles:
+//! - Grouping scanned files by content hash / 按内容哈希对扫描到的文件分组
+//! - The last scan report and its progress / 最近一次扫描报告及进度
+//! - Dropping resolved duplicates from the report / 从报告中移除已处理的重复文件
+//!
+//! Only files sharing their size with another file are hashed. Hashes are keyed as `md5:<hex>`
+//! so hashes reported by drivers and hashes computed from the content can be compared.
+//! 只对大小与其他文件相同的文件计算哈希。哈希统一为 `md5:<hex>`，驱动提供的与读取内容计算的哈希可以直接比较。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::HashType;
+
+/// Errors kept in the report / 报告中保留的错误数
+const MAX_REPORT_ERRORS: usize = 50;
+
+/// Hash key used for grouping / 分组使用的哈希键
+pub fn hash_key(hash_type: HashType, hash: &str) -> String {
+    let name = match hash_type {
+        HashType::Md5 => "md5",
+        HashType::Sha1 => "sha1",
+    };
+    format!("{}:{}", name, hash.trim().to_lowercase())
+}
+
+/// A file in a duplicate group / 重复分组中的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<String>,
+    /// On a local mount, can be replaced with a hard link / 位于本地存储，可替换为硬链接
+    pub local: bool,
+}
+
+/// Files with the same content / 内容相同的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub files: Vec<DuplicateFile>,
+    /// Space taken by all copies but one / 除保留的一份外其余副本占用的空间
+    pub wasted_bytes: u64,
+}
+
+impl DuplicateGroup {
+    fn new(hash: String, mut files: Vec<DuplicateFile>) -> Self {
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let size = files.first().map(|f| f.size).unwrap_or(0);
+        let wasted_bytes = size * (files.len().saturating_sub(1)) as u64;
+        Self { hash, size, files, wasted_bytes }
+    }
+}
+
+/// Group hashed files, keeping groups with at least two files, most wasted space first
+/// 按哈希分组，只保留至少两个文件的分组，浪费空间多的在前
+pub fn group_duplicates(files: Vec<(String, DuplicateFile)>) -> Vec<DuplicateGroup> {
+    let mut by_hash: HashMap<String, Vec<DuplicateFile>> = HashMap::new();
+    for (hash, file) in files {
+        by_hash.entry(hash).or_default().push(file);
+    }
+    let mut groups: Vec<DuplicateGroup> = by_hash.into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(hash, files)| DuplicateGroup::new(hash, files))
+        .collect();
+    groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.hash.cmp(&b.hash)));
+    groups
+}
+
+/// How duplicates are resolved / 重复文件的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupeAction {
+    /// Delete the duplicates / 删除重复文件
+    Delete,
+    /// Replace the duplicates with hard links to the kept file (local mounts) / 替换为指向保留文件的硬链接（本地存储）
+    Link,
+}
+
+/// Scan report / 扫描报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DedupeReport {
+    pub running: bool,
+    pub success: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Scanned folders / 扫描的目录
+    pub paths: Vec<String>,
+    /// Smaller files are ignored / 忽略小于该大小的文件
+    pub min_size: u64,
+    /// Files listed / 列出的文件数
+    pub scanned: u64,
+    /// Files whose hash was read from the driver or computed / 从驱动获取或计算哈希的文件数
+    pub hashed: u64,
+    /// Files whose hash came from the cache / 使用缓存哈希的文件数
+    pub cached: u64,
+    /// Hard links to a file already scanned, not counted as duplicates / 已扫描文件的硬链接，不计为重复
+    pub linked: u64,
+    pub groups: Vec<DuplicateGroup>,
+    pub wasted_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+impl DedupeReport {
+    pub fn started(paths: Vec<String>, min_size: u64) -> Self {
+        Self {
+            running: true,
+            started_at: Some(Utc::now()),
+            paths,
+            min_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn error(&mut self, message: String) {
+        if self.errors.len() < MAX_REPORT_ERRORS {
+            self.errors.push(message);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.running = false;
+        self.success = self.errors.is_empty();
+        self.finished_at = Some(Utc::now());
+        self.wasted_bytes = self.groups.iter().map(|g| g.wasted_bytes).sum();
+    }
+}
+
+/// Dedupe manager holding the last report / 保存最近报告的去重管理器
+pub struct DedupeManager {
+    report: RwLock<Option<DedupeReport>>,
+    running: AtomicBool,
+}
+
+impl DedupeManager {
+    pub fn new() -> Self {
+        Self {
+            report: RwLock::new(None),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// Last (or current) report / 最近一次（或正在进行的）报告
+    pub fn report(&self) -> Option<DedupeReport> {
+        self.report.read().clone()
+    }
+
+    /// Start a scan, `false` if one is running / 开始扫描，已在运行时返回 `false`
+    pub fn try_begin(&self, paths: Vec<String>, min_size: u64) -> bool {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        *self.report.write() = Some(DedupeReport::started(paths, min_size));
+        true
+    }
+
+    /// Publish scan progress / 更新扫描进度
+    pub fn progress(&self, report: &DedupeReport) {
+        *self.report.write() = Some(report.clone());
+    }
+
+    /// Store the report and clear the running flag / 保存报告并清除运行标记
+    pub fn finish(&self, mut report: DedupeReport) -> DedupeReport {
+        report.finish();
+        *self.report.write() = Some(report.clone());
+        self.running.store(false, Ordering::SeqCst);
+        report
+    }
+
+    /// Group of a hash in the last report / 最近报告中某个哈希的分组
+    pub fn group(&self, hash: &str) -> Option<DuplicateGroup> {
+        self.report.read().as_ref()?.groups.iter().find(|g| g.hash == hash).cloned()
+    }
+
+    /// Drop resolved files; groups left with one file are removed / 移除已处理的文件，只剩一个文件的分组一并移除
+    pub fn remove_files(&self, hash: &str, paths: &[String]) {
+        let mut report = self.report.write();
+        let Some(report) = report.as_mut() else {
+            return;
+        };
+        if let Some(group) = report.groups.iter_mut().find(|g| g.hash == hash) {
+            let files = std::mem::take(&mut group.files).into_iter()
+                .filter(|f| !paths.contains(&f.path))
+                .collect();
+            *group = DuplicateGroup::new(group.hash.clone(), files);
+        }
+        report.groups.retain(|g| g.files.len() > 1);
+        report.wasted_bytes = report.groups.iter().map(|g| g.wasted_bytes).sum();
+    }
+}
+
+impl Default for DedupeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64) -> DuplicateFile {
+        DuplicateFile { path: path.to_string(), size, modified: None, local: true }
+    }
+
+    #[test]
+    fn test_group_and_remove() {
+        let a = hash_key(HashType::Md5, " ABC ");
+        assert_eq!(a, "md5:abc");
+        let groups = group_duplicates(vec![
+            (a.clone(), file("/b/x.bin", 10)),
+            (a.clone(), file("/a/x.bin", 10)),
+            (a.clone(), file("/c/x.bin", 10)),
+            ("md5:def".to_string(), file("/a/big.bin", 100)),
+            ("md5:def".to_string(), file("/b/big.bin", 100)),
+            ("md5:123".to_string(), file("/a/single.bin", 5)),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].hash, "md5:def");
+        assert_eq!(groups[0].wasted_bytes, 100);
+        assert_eq!(groups[1].wasted_bytes, 20);
+        assert_eq!(groups[1].files[0].path, "/a/x.bin");
+
+        let manager = DedupeManager::new();
+        assert!(manager.try_begin(vec!["/".to_string()], 0));
+        assert!(!manager.try_begin(vec!["/".to_string()], 0));
+        let mut report = DedupeReport::started(vec!["/".to_string()], 0);
+        report.groups = groups;
+        let report = manager.finish(report);
+        assert_eq!(report.wasted_bytes, 120);
+
+        manager.remove_files("md5:abc", &["/b/x.bin".to_string()]);
+        assert_eq!(manager.group("md5:abc").unwrap().wasted_bytes, 10);
+        manager.remove_files("md5:def", &["/b/big.bin".to_string()]);
+        assert!(manager.group("md5:def").is_none());
+        assert_eq!(manager.report().unwrap().wasted_bytes, 10);
+    }
+}
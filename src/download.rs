@@ -2,8 +2,11 @@
 //! 
 //! This module handles:
 //! - Download domain validation / 下载域名验证
+//! - Per-mount direct link rewriting (CDN / worker) / 按挂载点改写直链(CDN/Worker)
+//! - Direct link vs proxy policy (see `link_policy`) / 直链与代理决策策略
 //! - Proxy bandwidth limiting (for local proxy streams) / 代理带宽限制(用于本地代理流)
 //! - Concurrent connection limiting / 并发连接限制
+//! - Per-token transfer stats (requests, parallel connections, bytes) / 按下载令牌的传输统计
 //!
 //! Note: This is Core layer logic, not Driver layer.
 //! 注意: 这是 Core 层逻辑，不是 Driver 层。
@@ -12,11 +15,73 @@
 //! using async stream wrappers, NOT by loading files into memory.
 //! 带宽限制应用于流式代理下载(FTP、天翼云盘等)，使用异步流包装器，而不是将文件加载到内存。
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicI64, AtomicI32, Ordering};
+use chrono::{DateTime, Utc};
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
+use crate::link_policy::{self, LinkDecision, LinkPolicyConfig, LinkRequest};
+
+/// Direct link rewrite rule for a mount / 挂载点直链改写规则
+///
+/// `target` is either a bare host/base URL (only scheme and host of the direct link are
+/// replaced) or a template with variables:
+/// `target` 可以是域名/基础URL（仅替换直链的协议和域名），也可以是带变量的模板：
+/// - `{url}`: original direct link, URL-encoded / 原始直链(URL编码)
+/// - `{host}`: host of the original link / 原始直链域名
+/// - `{uri}`: path and query of the original link / 原始直链路径和参数
+/// - `{path}`: file path under the mount, URL-encoded / 挂载点内文件路径(URL编码)
+/// - `{name}`: file name, URL-encoded / 文件名(URL编码)
+/// - `{expires}`: unix timestamp the signature expires at / 签名过期时间戳
+/// - `{sign}`: hex HMAC-SHA256 of `{path}:{expires}` with `secret` / 以secret签名的HMAC
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LinkRewriteRule {
+    /// Mount path this rule applies to (longest match wins) / 适用的挂载路径(最长匹配)
+    pub mount_path: String,
+    /// Target host or URL template / 目标域名或URL模板
+    pub target: String,
+    /// Secret for `{sign}` / 用于{sign}的密钥
+    #[serde(default)]
+    pub secret: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl LinkRewriteRule {
+    /// Rule as shown to clients, without the secret / 对外展示的规则（不含密钥）
+    pub fn redacted(&self) -> serde_json::Value {
+        serde_json::json!({
+            "mount_path": self.mount_path,
+            "target": self.target,
+            "secret_set": !self.secret.is_empty(),
+            "enabled": self.enabled,
+        })
+    }
+}
+
+/// Keep saved secrets for rules submitted without one (matched by mount path)
+/// 请求中密钥为空的规则保留已保存的密钥（按挂载路径匹配）
+pub fn merge_rewrite_secrets(rules: Vec<LinkRewriteRule>, saved: &[LinkRewriteRule]) -> Vec<LinkRewriteRule> {
+    rules.into_iter()
+        .map(|mut rule| {
+            if rule.secret.is_empty() {
+                let mount_path = normalize_mount_path(&rule.mount_path);
+                if let Some(old) = saved.iter().find(|s| s.mount_path == mount_path) {
+                    rule.secret = old.secret.clone();
+                }
+            }
+            rule
+        })
+        .collect()
+}
+
 /// Download settings cache / 下载设置缓存
 /// 
 /// Cached in memory for performance, updated when settings change.
@@ -34,6 +99,10 @@ pub struct DownloadSettings {
     global_limiter: Arc<BandwidthLimiter>,
     /// Download link expiry in minutes (default 15) / 下载链接有效期（分钟，默认15）
     link_expiry_minutes: AtomicI32,
+    /// Per-mount direct link rewrite rules / 按挂载点的直链改写规则
+    link_rewrites: RwLock<Vec<LinkRewriteRule>>,
+    /// Direct link vs proxy policy / 直链与代理决策策略
+    link_policy: RwLock<LinkPolicyConfig>,
 }
 
 impl DownloadSettings {
@@ -45,6 +114,8 @@ impl DownloadSettings {
             current_concurrent: AtomicI32::new(0),
             global_limiter: Arc::new(BandwidthLimiter::new(0)),
             link_expiry_minutes: AtomicI32::new(15),  // Default 15 minutes / 默认15分钟
+            link_rewrites: RwLock::new(Vec::new()),
+            link_policy: RwLock::new(LinkPolicyConfig::default()),
         }
     }
 
@@ -103,6 +174,36 @@ impl DownloadSettings {
             self.link_expiry_minutes.store(if minutes > 0 { minutes } else { 15 }, Ordering::SeqCst);
         }
 
+        // Load link rewrite rules / 加载直链改写规则
+        let rewrites: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'download_link_rewrites'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((r,)) = rewrites {
+            match serde_json::from_str::<Vec<LinkRewriteRule>>(&r) {
+                Ok(rules) => self.set_link_rewrites(rules),
+                Err(e) => tracing::warn!("load_from_db: invalid download_link_rewrites: {}", e),
+            }
+        }
+
+        // Load direct link policy / 加载直链策略
+        let policy: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'download_link_policy'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((p,)) = policy {
+            match serde_json::from_str::<LinkPolicyConfig>(&p) {
+                Ok(policy) => self.set_link_policy(policy),
+                Err(e) => tracing::warn!("load_from_db: invalid download_link_policy: {}", e),
+            }
+        }
+
         Ok(())
     }
 
@@ -130,6 +231,22 @@ impl DownloadSettings {
         self.download_domain.read().clone()
     }
 
+    /// Absolute origin of the configured download domain, `None` when unset.
+    /// Used for links leaving the site (emails, feeds) instead of request headers.
+    /// 配置的下载域名对应的完整地址，未配置时为 `None`；用于邮件、订阅等站外链接，而非取自请求头。
+    pub fn site_origin(&self) -> Option<String> {
+        let configured = self.download_domain.read();
+        let configured = configured.trim().trim_end_matches('/');
+        if configured.is_empty() {
+            return None;
+        }
+        if configured.starts_with("http://") || configured.starts_with("https://") {
+            Some(configured.to_string())
+        } else {
+            Some(format!("http://{}", configured))
+        }
+    }
+
     /// Get max speed in bytes/sec / 获取最大速度(字节/秒)
     pub fn get_max_speed(&self) -> i64 {
         self.max_speed.load(Ordering::SeqCst)
@@ -150,6 +267,97 @@ impl DownloadSettings {
         self.link_expiry_minutes.store(if minutes > 0 { minutes } else { 15 }, Ordering::SeqCst);
     }
 
+    /// Get link rewrite rules / 获取直链改写规则
+    pub fn get_link_rewrites(&self) -> Vec<LinkRewriteRule> {
+        self.link_rewrites.read().clone()
+    }
+
+    /// Set link rewrite rules / 设置直链改写规则
+    pub fn set_link_rewrites(&self, rules: Vec<LinkRewriteRule>) {
+        let rules = rules.into_iter()
+            .filter(|r| !r.target.trim().is_empty())
+            .map(|mut r| {
+                r.mount_path = normalize_mount_path(&r.mount_path);
+                r
+            })
+            .collect();
+        *self.link_rewrites.write() = rules;
+    }
+
+    /// Get direct link policy / 获取直链策略
+    pub fn get_link_policy(&self) -> LinkPolicyConfig {
+        self.link_policy.read().clone()
+    }
+
+    /// Set direct link policy / 设置直链策略
+    pub fn set_link_policy(&self, mut policy: LinkPolicyConfig) {
+        policy.normalize();
+        *self.link_policy.write() = policy;
+    }
+
+    /// Decide whether a download is redirected or proxied / 决定下载走302还是代理
+    pub fn decide_link(&self, request: &LinkRequest) -> LinkDecision {
+        let policy = self.link_policy.read();
+        link_policy::decide(&policy, request)
+    }
+
+    /// Rewrite a direct link of `internal_path` (path inside the mount) served by `mount_path`
+    /// 按挂载点规则改写直链，无匹配规则时原样返回
+    pub fn rewrite_direct_link(&self, mount_path: &str, internal_path: &str, direct_url: &str) -> String {
+        let mount_path = normalize_mount_path(mount_path);
+        let rules = self.link_rewrites.read();
+        let rule = rules.iter()
+            .filter(|r| r.enabled && mount_matches(&r.mount_path, &mount_path))
+            .max_by_key(|r| r.mount_path.len());
+        let Some(rule) = rule else {
+            return direct_url.to_string();
+        };
+
+        let Ok(original) = url::Url::parse(direct_url) else {
+            return direct_url.to_string();
+        };
+        let host = original.host_str().unwrap_or("");
+        let uri = match original.query() {
+            Some(q) => format!("{}?{}", original.path(), q),
+            None => original.path().to_string(),
+        };
+        let target = rule.target.trim();
+
+        if !target.contains('{') {
+            // Host replacement only / 仅替换域名
+            let base = if target.starts_with("http://") || target.starts_with("https://") {
+                target.trim_end_matches('/').to_string()
+            } else {
+                format!("{}://{}", original.scheme(), target.trim_end_matches('/'))
+            };
+            return format!("{}{}", base, uri);
+        }
+
+        let relative = if internal_path.starts_with('/') {
+            internal_path.to_string()
+        } else {
+            format!("/{}", internal_path)
+        };
+        let encoded_path = relative.split('/')
+            .map(|seg| urlencoding::encode(seg).into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        let name = relative.rsplit('/').next().unwrap_or("");
+        let expires = chrono::Utc::now().timestamp() + self.get_link_expiry_minutes() as i64 * 60;
+
+        let mut result = target
+            .replace("{url}", &urlencoding::encode(direct_url))
+            .replace("{host}", host)
+            .replace("{uri}", &uri)
+            .replace("{path}", &encoded_path)
+            .replace("{name}", &urlencoding::encode(name))
+            .replace("{expires}", &expires.to_string());
+        if result.contains("{sign}") {
+            result = result.replace("{sign}", &sign_path(&rule.secret, &relative, expires));
+        }
+        result
+    }
+
     /// Get global bandwidth limiter for proxy downloads (shared) / 获取代理下载的全局带宽限制器（共享）
     pub fn get_limiter(&self) -> Arc<BandwidthLimiter> {
         self.global_limiter.clone()
@@ -281,6 +489,36 @@ impl DownloadSettings {
     }
 }
 
+/// Normalize mount path: leading slash, no trailing slash / 规范化挂载路径
+fn normalize_mount_path(path: &str) -> String {
+    let trimmed = path.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else if trimmed.starts_with('/') {
+        trimmed.to_string()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// Check whether rule mount covers the given mount / 检查规则挂载点是否覆盖该挂载点
+fn mount_matches(rule_mount: &str, mount: &str) -> bool {
+    rule_mount == "/" || mount == rule_mount || mount.starts_with(&format!("{}/", rule_mount))
+}
+
+/// HMAC-SHA256 signature of `path:expires` / 计算 `path:expires` 的HMAC-SHA256签名
+fn sign_path(secret: &str, path: &str, expires: i64) -> String {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(m) => m,
+        Err(_) => return String::new(),
+    };
+    mac.update(format!("{}:{}", path, expires).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
 impl Default for DownloadSettings {
     fn default() -> Self {
         Self::new()
@@ -548,6 +786,7 @@ pub struct TrafficCountingStream<S> {
     bytes_transferred: Arc<AtomicU64>,
     user_id: Option<String>,
     db: SqlitePool,
+    transfer: Option<TransferGuard>,
 }
 
 impl<S> TrafficCountingStream<S> {
@@ -557,9 +796,16 @@ impl<S> TrafficCountingStream<S> {
             bytes_transferred: Arc::new(AtomicU64::new(0)),
             user_id,
             db,
+            transfer: None,
         }
     }
     
+    /// Also count bytes into a download token's stats / 同时计入下载令牌的传输统计
+    pub fn with_transfer(mut self, transfer: TransferGuard) -> Self {
+        self.transfer = Some(transfer);
+        self
+    }
+    
     /// 获取已传输的字节数
     pub fn get_bytes_transferred(&self) -> u64 {
         self.bytes_transferred.load(Ordering::SeqCst)
@@ -577,6 +823,9 @@ where
             Poll::Ready(Some(Ok(bytes))) => {
                 // 统计传输的字节数
                 self.bytes_transferred.fetch_add(bytes.len() as u64, Ordering::SeqCst);
+                if let Some(ref transfer) = self.transfer {
+                    transfer.add_bytes(bytes.len() as u64);
+                }
                 Poll::Ready(Some(Ok(bytes)))
             }
             other => other,
@@ -611,6 +860,140 @@ impl<S> Drop for TrafficCountingStream<S> {
     }
 }
 
+/// Transfer stats of one download token / 单个下载令牌的传输统计
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenTransferStats {
+    /// Download requests including redirects / 下载请求数（含302跳转）
+    pub requests: u64,
+    /// Requests with a Range header / 带 Range 头的请求数
+    pub range_requests: u64,
+    /// Requests answered with a 302 redirect / 302跳转次数
+    pub redirects: u64,
+    /// Proxy connections currently open / 当前中转连接数
+    pub active_connections: u32,
+    /// Most proxy connections open at once / 同时打开的最大中转连接数
+    pub peak_connections: u32,
+    /// Bytes proxied by this server / 本服务器中转的字节数
+    pub bytes_sent: u64,
+    pub first_access: DateTime<Utc>,
+    pub last_access: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+struct TokenTransfer {
+    bytes_sent: AtomicU64,
+    stats: parking_lot::Mutex<TokenTransferStats>,
+}
+
+impl TokenTransfer {
+    fn snapshot(&self) -> TokenTransferStats {
+        let mut stats = self.stats.lock().clone();
+        stats.bytes_sent = self.bytes_sent.load(Ordering::Relaxed);
+        stats
+    }
+}
+
+/// Per-token transfer tracking / 按下载令牌跟踪传输
+///
+/// A token can be redeemed any number of times until it expires, including parallel Range
+/// requests from download managers; each request is counted here. Stats are per instance.
+/// 令牌在有效期内可重复使用，下载工具可用同一令牌并发发起多个 Range 请求，每次请求都在此统计。统计按实例保存。
+#[derive(Default)]
+pub struct DownloadTransfers {
+    tokens: RwLock<HashMap<String, Arc<TokenTransfer>>>,
+}
+
+impl DownloadTransfers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request for `token` / 记录一次令牌请求
+    fn touch(&self, token: &str, expires_at: DateTime<Utc>, range: bool) -> Arc<TokenTransfer> {
+        let now = Utc::now();
+        let transfer = {
+            let mut tokens = self.tokens.write();
+            // 过期且没有进行中的连接时清理
+            tokens.retain(|_, t| {
+                let stats = t.stats.lock();
+                stats.expires_at > now || stats.active_connections > 0
+            });
+            tokens.entry(token.to_string())
+                .or_insert_with(|| Arc::new(TokenTransfer {
+                    bytes_sent: AtomicU64::new(0),
+                    stats: parking_lot::Mutex::new(TokenTransferStats {
+                        requests: 0,
+                        range_requests: 0,
+                        redirects: 0,
+                        active_connections: 0,
+                        peak_connections: 0,
+                        bytes_sent: 0,
+                        first_access: now,
+                        last_access: now,
+                        expires_at,
+                    }),
+                }))
+                .clone()
+        };
+        {
+            let mut stats = transfer.stats.lock();
+            stats.requests += 1;
+            if range {
+                stats.range_requests += 1;
+            }
+            stats.last_access = now;
+        }
+        transfer
+    }
+
+    /// Record a request answered with a redirect / 记录一次302跳转
+    pub fn record_redirect(&self, token: &str, expires_at: DateTime<Utc>, range: bool) {
+        self.touch(token, expires_at, range).stats.lock().redirects += 1;
+    }
+
+    /// Open a proxy connection, closed when the guard drops / 打开一个中转连接，守卫丢弃时关闭
+    pub fn begin(&self, token: &str, expires_at: DateTime<Utc>, range: bool) -> TransferGuard {
+        let transfer = self.touch(token, expires_at, range);
+        {
+            let mut stats = transfer.stats.lock();
+            stats.active_connections += 1;
+            stats.peak_connections = stats.peak_connections.max(stats.active_connections);
+        }
+        TransferGuard { transfer }
+    }
+
+    pub fn get(&self, token: &str) -> Option<TokenTransferStats> {
+        self.tokens.read().get(token).map(|t| t.snapshot())
+    }
+
+    /// All tracked tokens, most recently used first / 所有令牌的统计，最近使用的在前
+    pub fn list(&self) -> Vec<(String, TokenTransferStats)> {
+        let mut list: Vec<_> = self.tokens.read().iter()
+            .map(|(token, t)| (token.clone(), t.snapshot()))
+            .collect();
+        list.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.last_access));
+        list
+    }
+}
+
+/// RAII guard of one proxy connection / 单个中转连接的RAII守卫
+pub struct TransferGuard {
+    transfer: Arc<TokenTransfer>,
+}
+
+impl TransferGuard {
+    pub fn add_bytes(&self, bytes: u64) {
+        self.transfer.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        let mut stats = self.transfer.stats.lock();
+        stats.active_connections = stats.active_connections.saturating_sub(1);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -640,6 +1023,72 @@ mod tests {
         assert!(!settings.validate_domain("example.com"));
     }
 
+    #[test]
+    fn test_site_origin() {
+        let settings = DownloadSettings::new();
+        assert_eq!(settings.site_origin(), None);
+        settings.set_download_domain("dl.example.com/".to_string());
+        assert_eq!(settings.site_origin().as_deref(), Some("http://dl.example.com"));
+        settings.set_download_domain("https://dl.example.com".to_string());
+        assert_eq!(settings.site_origin().as_deref(), Some("https://dl.example.com"));
+    }
+
+    #[test]
+    fn test_rewrite_secret_hidden_and_kept() {
+        let saved = vec![LinkRewriteRule {
+            mount_path: "/od".to_string(),
+            target: "https://w.example.com{path}?s={sign}".to_string(),
+            secret: "k".to_string(),
+            enabled: true,
+        }];
+        let shown = saved[0].redacted();
+        assert!(shown.get("secret").is_none());
+        assert_eq!(shown["secret_set"], true);
+
+        let submitted = LinkRewriteRule { mount_path: "/od/".to_string(), secret: String::new(), ..saved[0].clone() };
+        assert_eq!(merge_rewrite_secrets(vec![submitted], &saved)[0].secret, "k");
+        let replaced = LinkRewriteRule { secret: "new".to_string(), ..saved[0].clone() };
+        assert_eq!(merge_rewrite_secrets(vec![replaced], &saved)[0].secret, "new");
+    }
+
+    #[test]
+    fn test_rewrite_direct_link() {
+        let settings = DownloadSettings::new();
+        let url = "https://public.sn.files.1drv.com/abc/file.zip?download=1";
+
+        // No rule = unchanged / 无规则时不变
+        assert_eq!(settings.rewrite_direct_link("/od", "/a/file.zip", url), url);
+
+        settings.set_link_rewrites(vec![
+            LinkRewriteRule {
+                mount_path: "/od/".to_string(),
+                target: "dl.example.com".to_string(),
+                secret: String::new(),
+                enabled: true,
+            },
+            LinkRewriteRule {
+                mount_path: "/od/worker".to_string(),
+                target: "https://w.example.com{path}?src={url}&e={expires}&s={sign}".to_string(),
+                secret: "k".to_string(),
+                enabled: true,
+            },
+        ]);
+
+        // Host replacement / 域名替换
+        assert_eq!(
+            settings.rewrite_direct_link("/od", "/a/file.zip", url),
+            "https://dl.example.com/abc/file.zip?download=1"
+        );
+        // Other mount unaffected / 其他挂载点不受影响
+        assert_eq!(settings.rewrite_direct_link("/other", "/file.zip", url), url);
+
+        // Template with longest match / 最长匹配的模板规则
+        let rewritten = settings.rewrite_direct_link("/od/worker", "/a b.zip", url);
+        assert!(rewritten.starts_with("https://w.example.com/a%20b.zip?src=https%3A%2F%2Fpublic"));
+        let expires: i64 = rewritten.split("&e=").nth(1).unwrap().split('&').next().unwrap().parse().unwrap();
+        assert!(rewritten.ends_with(&format!("&s={}", sign_path("k", "/a b.zip", expires))));
+    }
+
     #[test]
     fn test_concurrent_guard() {
         let settings = DownloadSettings::new();
@@ -665,4 +1114,30 @@ mod tests {
         let g4 = ConcurrentGuard::try_new(&settings);
         assert!(g4.is_some());
     }
+
+    #[test]
+    fn test_download_transfers() {
+        let transfers = DownloadTransfers::new();
+        let expires_at = Utc::now() + chrono::Duration::minutes(5);
+
+        transfers.record_redirect("t", expires_at, false);
+        let g1 = transfers.begin("t", expires_at, true);
+        let g2 = transfers.begin("t", expires_at, true);
+        g1.add_bytes(100);
+        g2.add_bytes(50);
+        let stats = transfers.get("t").unwrap();
+        assert_eq!((stats.requests, stats.range_requests, stats.redirects), (3, 2, 1));
+        assert_eq!((stats.active_connections, stats.peak_connections, stats.bytes_sent), (2, 2, 150));
+
+        drop(g1);
+        drop(g2);
+        let stats = transfers.get("t").unwrap();
+        assert_eq!((stats.active_connections, stats.peak_connections), (0, 2));
+
+        // 过期令牌在下次请求时清理
+        transfers.begin("old", Utc::now() - chrono::Duration::seconds(1), false);
+        transfers.begin("t", expires_at, false);
+        assert!(transfers.get("old").is_none());
+        assert_eq!(transfers.list().len(), 1);
+    }
 }
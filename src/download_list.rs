@@ -0,0 +1,140 @@
+//! Download lists for external download managers / 供外部下载工具使用的下载列表
+//!
+//! A selection of files is exported as a list of signed direct links that download managers can
+//! import in one go:
+//! 将选中的文件导出为签名直链列表，下载工具可一次性导入：
+//! - Metalink 4 (RFC 5854), understood by aria2, Motrix, FDM, DownThemAll and others
+//!   Metalink 4（RFC 5854），aria2、Motrix、FDM、DownThemAll 等均支持
+//! - aria2 input files (`aria2c -i`) / aria2 输入文件（`aria2c -i`）
+//!
+//! Entries keep their path relative to the selection so folders are recreated on disk.
+//! 条目保留相对于所选位置的路径，下载后按原目录结构保存。
+
+use serde::Deserialize;
+
+/// One file in a download list / 下载列表中的一个文件
+#[derive(Debug, Clone)]
+pub struct DownloadItem {
+    /// Path relative to the selection, `/`-separated / 相对于所选位置的路径，以 `/` 分隔
+    pub name: String,
+    pub url: String,
+    pub size: u64,
+}
+
+/// Download list format / 下载列表格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListFormat {
+    #[default]
+    Metalink,
+    Aria2,
+}
+
+impl ListFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ListFormat::Metalink => "meta4",
+            ListFormat::Aria2 => "txt",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ListFormat::Metalink => "application/metalink4+xml; charset=utf-8",
+            ListFormat::Aria2 => "text/plain; charset=utf-8",
+        }
+    }
+
+    /// Render the list / 生成下载列表
+    pub fn render(self, items: &[DownloadItem]) -> String {
+        match self {
+            ListFormat::Metalink => to_metalink(items),
+            ListFormat::Aria2 => to_aria2_input(items),
+        }
+    }
+}
+
+/// Make a relative path safe for download managers: no empty, `.` or `..` segments, no control characters
+/// 使相对路径对下载工具安全：去掉空段、`.` 与 `..`，去掉控制字符
+pub fn safe_name(name: &str) -> String {
+    name.split(['/', '\\'])
+        .map(|s| s.chars().filter(|c| !c.is_control()).collect::<String>())
+        .filter(|s| !s.is_empty() && s != "." && s != "..")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(crate) fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Metalink 4 document / Metalink 4 文档
+pub fn to_metalink(items: &[DownloadItem]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<metalink xmlns=\"urn:ietf:params:xml:ns:metalink\">\n");
+    for item in items {
+        out.push_str(&format!(
+            "  <file name=\"{}\">\n    <size>{}</size>\n    <url>{}</url>\n  </file>\n",
+            escape_xml(&safe_name(&item.name)), item.size, escape_xml(&item.url),
+        ));
+    }
+    out.push_str("</metalink>\n");
+    out
+}
+
+/// aria2 input file: each URL followed by an indented `out=` option
+/// aria2 输入文件：每个链接后跟一行缩进的 `out=` 选项
+pub fn to_aria2_input(items: &[DownloadItem]) -> String {
+    let mut out = String::new();
+    for item in items {
+        // 换行会破坏格式（safe_name 已去掉控制字符）
+        let url = item.url.replace(['\r', '\n'], "");
+        out.push_str(&format!("{}\n  out={}\n", url, safe_name(&item.name)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<DownloadItem> {
+        vec![
+            DownloadItem { name: "a & b.txt".to_string(), url: "https://x/download/1?a=1&b=2".to_string(), size: 3 },
+            DownloadItem { name: "dir/../sub/./c\n.bin".to_string(), url: "https://x/download/2".to_string(), size: 0 },
+        ]
+    }
+
+    #[test]
+    fn test_safe_name() {
+        assert_eq!(safe_name("/a//b/../c"), "a/b/c");
+        assert_eq!(safe_name("..\\..\\etc\\passwd"), "etc/passwd");
+        assert_eq!(safe_name("x\ty"), "xy");
+    }
+
+    #[test]
+    fn test_metalink() {
+        let xml = to_metalink(&items());
+        assert!(xml.contains("<file name=\"a &amp; b.txt\">\n    <size>3</size>\n    <url>https://x/download/1?a=1&amp;b=2</url>"));
+        assert!(xml.contains("<file name=\"dir/sub/c.bin\">"));
+        assert!(xml.ends_with("</metalink>\n"));
+    }
+
+    #[test]
+    fn test_aria2_input() {
+        assert_eq!(
+            to_aria2_input(&items()),
+            "https://x/download/1?a=1&b=2\n  out=a & b.txt\nhttps://x/download/2\n  out=dir/sub/c.bin\n",
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! Email templates / 邮件模板
+//!
+//! This module handles:
+//! - Built-in localized templates for each email event / 各邮件事件的内置多语言模板
+//! - Admin overrides, per-event enable/disable and BCC routing / 管理员自定义模板、按事件启用与抄送
+//! - Handlebars-style rendering / Handlebars 风格渲染
+//!
+//! Supported syntax / 支持的语法:
+//! - `{{name}}` HTML-escaped value, dotted paths allowed / HTML转义输出，支持点路径
+//! - `{{{name}}}` raw value / 原样输出
+//! - `{{#if name}}...{{else}}...{{/if}}` conditional (nestable) / 条件块（可嵌套）
+
+use std::collections::HashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+/// Fallback locale / 默认语言
+pub const DEFAULT_EMAIL_LOCALE: &str = "zh-CN";
+
+/// Email event / 邮件事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailEvent {
+    /// Verification / password reset code / 验证码（含密码重置）
+    VerificationCode,
+    ShareInvitation,
+    TaskCompleted,
+    QuotaWarning,
+    /// Mount usage alert sent to administrators / 发送给管理员的挂载点空间告警
+    StorageAlert,
+    /// Security alert sent to administrators / 发送给管理员的安全告警
+    SecurityAlert,
+}
+
+impl EmailEvent {
+    pub fn all() -> &'static [EmailEvent] {
+        &[
+            EmailEvent::VerificationCode,
+            EmailEvent::ShareInvitation,
+            EmailEvent::TaskCompleted,
+            EmailEvent::QuotaWarning,
+            EmailEvent::StorageAlert,
+            EmailEvent::SecurityAlert,
+        ]
+    }
+
+    /// Enabled unless the admin turns it off / 默认是否启用
+    fn enabled_by_default(&self) -> bool {
+        !matches!(self, EmailEvent::TaskCompleted)
+    }
+
+    /// Template variables available to this event / 可用的模板变量
+    pub fn variables(&self) -> &'static [&'static str] {
+        match self {
+            EmailEvent::VerificationCode => &["site_title", "code", "minutes", "reset"],
+            EmailEvent::ShareInvitation => &["site_title", "inviter", "share_name", "share_url", "password", "expires_at", "message"],
+            EmailEvent::TaskCompleted => &["site_title", "username", "task_name", "task_type", "total_files", "total_size", "finished_at"],
+            EmailEvent::QuotaWarning => &["site_title", "username", "used", "total", "percent"],
+            EmailEvent::StorageAlert => &["site_title", "mount_path", "used", "total", "percent", "threshold"],
+            EmailEvent::SecurityAlert => &["site_title", "title", "details", "time"],
+        }
+    }
+}
+
+/// Subject + HTML body / 主题和HTML正文
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Per-event settings / 单个事件的设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailEventConfig {
+    pub enabled: bool,
+    /// Extra recipients copied on every mail of this event / 该事件邮件的抄送地址
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    /// Overrides keyed by locale (e.g. "en-US") / 按语言覆盖的模板
+    #[serde(default)]
+    pub templates: HashMap<String, EmailTemplate>,
+}
+
+/// All email template settings / 邮件模板设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailTemplateConfig {
+    #[serde(default = "default_locale")]
+    pub default_locale: String,
+    #[serde(default)]
+    pub events: HashMap<EmailEvent, EmailEventConfig>,
+}
+
+fn default_locale() -> String {
+    DEFAULT_EMAIL_LOCALE.to_string()
+}
+
+impl Default for EmailTemplateConfig {
+    fn default() -> Self {
+        Self {
+            default_locale: default_locale(),
+            events: HashMap::new(),
+        }
+    }
+}
+
+/// Rendered email ready to send / 渲染后的邮件
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub body: String,
+    pub bcc: Vec<String>,
+}
+
+/// Email template settings cache / 邮件模板设置缓存
+pub struct EmailTemplates {
+    config: RwLock<EmailTemplateConfig>,
+}
+
+impl EmailTemplates {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(EmailTemplateConfig::default()),
+        }
+    }
+
+    /// Load settings from database / 从数据库加载设置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'email_templates'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((json,)) = row {
+            let config = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            self.set_config(config);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> EmailTemplateConfig {
+        self.config.read().clone()
+    }
+
+    pub fn set_config(&self, config: EmailTemplateConfig) {
+        *self.config.write() = config;
+    }
+
+    /// Whether the event sends mail / 事件是否启用
+    pub fn is_enabled(&self, event: EmailEvent) -> bool {
+        self.config.read().events.get(&event)
+            .map(|c| c.enabled)
+            .unwrap_or_else(|| event.enabled_by_default())
+    }
+
+    /// Resolve template: override for locale, override for default locale, built-in
+    /// 选择模板：指定语言的自定义模板 > 默认语言的自定义模板 > 内置模板
+    pub fn resolve(&self, event: EmailEvent, locale: Option<&str>) -> EmailTemplate {
+        let config = self.config.read();
+        let locale = locale.filter(|l| !l.is_empty()).unwrap_or(&config.default_locale);
+        let overrides = config.events.get(&event).map(|c| &c.templates);
+
+        overrides.and_then(|t| t.get(locale))
+            .or_else(|| overrides.and_then(|t| t.get(&config.default_locale)))
+            .cloned()
+            .unwrap_or_else(|| builtin_template(event, locale))
+    }
+
+    /// Render an event email, None if the event is disabled / 渲染邮件，事件禁用时返回None
+    pub fn render(&self, event: EmailEvent, locale: Option<&str>, vars: &Value) -> Option<RenderedEmail> {
+        if !self.is_enabled(event) {
+            return None;
+        }
+        let template = self.resolve(event, locale);
+        let bcc = self.config.read().events.get(&event)
+            .map(|c| c.bcc.clone())
+            .unwrap_or_default();
+        Some(RenderedEmail {
+            subject: render_template(&template.subject, vars),
+            body: render_template(&template.body, vars),
+            bcc,
+        })
+    }
+}
+
+impl Default for EmailTemplates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Built-in template (zh-CN, otherwise en-US for any "en*" locale) / 内置模板
+pub fn builtin_template(event: EmailEvent, locale: &str) -> EmailTemplate {
+    let en = locale.to_ascii_lowercase().starts_with("en");
+    let (subject, content) = match (event, en) {
+        (EmailEvent::VerificationCode, false) => (
+            "{{site_title}} {{#if reset}}密码重置{{else}}验证码{{/if}}",
+            r#"<h2 style="color: #333;">{{#if reset}}密码重置{{else}}验证码{{/if}}</h2>
+            <p>{{#if reset}}您正在重置密码，验证码是：{{else}}您的验证码是：{{/if}}</p>
+            <div style="background: #f5f5f5; padding: 20px; border-radius: 8px; text-align: center; margin: 20px 0;">
+                <span style="font-size: 32px; font-weight: bold; letter-spacing: 8px;">{{code}}</span>
+            </div>
+            <p style="color: #999;">验证码有效期为{{minutes}}分钟，请勿泄露给他人。</p>"#,
+        ),
+        (EmailEvent::VerificationCode, true) => (
+            "{{site_title}} {{#if reset}}password reset{{else}}verification code{{/if}}",
+            r#"<h2 style="color: #333;">{{#if reset}}Password reset{{else}}Verification code{{/if}}</h2>
+            <p>{{#if reset}}You are resetting your password. Your code is:{{else}}Your verification code is:{{/if}}</p>
+            <div style="background: #f5f5f5; padding: 20px; border-radius: 8px; text-align: center; margin: 20px 0;">
+                <span style="font-size: 32px; font-weight: bold; letter-spacing: 8px;">{{code}}</span>
+            </div>
+            <p style="color: #999;">The code expires in {{minutes}} minutes. Do not share it with anyone.</p>"#,
+        ),
+        (EmailEvent::ShareInvitation, false) => (
+            "{{inviter}} 向您分享了「{{share_name}}」",
+            r#"<h2 style="color: #333;">文件分享</h2>
+            <p>{{inviter}} 在 {{site_title}} 向您分享了「{{share_name}}」。</p>
+            {{#if message}}<p style="background: #f5f5f5; padding: 12px; border-radius: 8px;">{{message}}</p>{{/if}}
+            <p><a href="{{share_url}}">{{share_url}}</a></p>
+            {{#if password}}<p>提取码：<b>{{password}}</b></p>{{/if}}
+            {{#if expires_at}}<p style="color: #999;">有效期至 {{expires_at}}</p>{{/if}}"#,
+        ),
+        (EmailEvent::ShareInvitation, true) => (
+            "{{inviter}} shared \"{{share_name}}\" with you",
+            r#"<h2 style="color: #333;">File share</h2>
+            <p>{{inviter}} shared "{{share_name}}" with you on {{site_title}}.</p>
+            {{#if message}}<p style="background: #f5f5f5; padding: 12px; border-radius: 8px;">{{message}}</p>{{/if}}
+            <p><a href="{{share_url}}">{{share_url}}</a></p>
+            {{#if password}}<p>Password: <b>{{password}}</b></p>{{/if}}
+            {{#if expires_at}}<p style="color: #999;">Expires at {{expires_at}}</p>{{/if}}"#,
+        ),
+        (EmailEvent::TaskCompleted, false) => (
+            "{{site_title}} 任务已完成：{{task_name}}",
+            r#"<h2 style="color: #333;">任务已完成</h2>
+            <p>{{username}}，您的任务「{{task_name}}」已于 {{finished_at}} 完成。</p>
+            <p>文件数：{{total_files}}，大小：{{total_size}}</p>"#,
+        ),
+        (EmailEvent::TaskCompleted, true) => (
+            "{{site_title}} task completed: {{task_name}}",
+            r#"<h2 style="color: #333;">Task completed</h2>
+            <p>{{username}}, your task "{{task_name}}" completed at {{finished_at}}.</p>
+            <p>Files: {{total_files}}, size: {{total_size}}</p>"#,
+        ),
+        (EmailEvent::QuotaWarning, false) => (
+            "{{site_title}} 下载流量即将用尽",
+            r#"<h2 style="color: #333;">下载流量提醒</h2>
+            <p>{{username}}，您已使用下载流量 {{used}} / {{total}}（{{percent}}%）。</p>
+            <p>流量用尽后将无法继续下载文件，如需更多流量请联系管理员。</p>"#,
+        ),
+        (EmailEvent::QuotaWarning, true) => (
+            "{{site_title}} download traffic almost used up",
+            r#"<h2 style="color: #333;">Traffic warning</h2>
+            <p>{{username}}, you have used {{used}} of your {{total}} download traffic ({{percent}}%).</p>
+            <p>Downloads stop once the quota is used up; contact an administrator for more.</p>"#,
+        ),
+        (EmailEvent::StorageAlert, false) => (
+            "{{site_title}} 挂载点 {{mount_path}} 空间告警",
+            r#"<h2 style="color: #333;">存储空间告警</h2>
+            <p>挂载点 {{mount_path}} 已使用 {{used}} / {{total}}（{{percent}}%），超过告警阈值 {{threshold}}%。</p>
+            <p>请清理文件或扩容，以免影响上传。</p>"#,
+        ),
+        (EmailEvent::StorageAlert, true) => (
+            "{{site_title}} storage alert for {{mount_path}}",
+            r#"<h2 style="color: #333;">Storage alert</h2>
+            <p>Mount {{mount_path}} has used {{used}} of {{total}} ({{percent}}%), above the {{threshold}}% alert threshold.</p>
+            <p>Free up space or add capacity to keep uploading.</p>"#,
+        ),
+        (EmailEvent::SecurityAlert, false) => (
+            "{{site_title}} 安全告警：{{title}}",
+            r#"<h2 style="color: #333;">安全告警</h2>
+            <p>{{title}}</p>
+            <p>{{details}}</p>
+            <p style="color: #999;">时间：{{time}}。如非本人或其他管理员操作，请检查账号安全。</p>"#,
+        ),
+        (EmailEvent::SecurityAlert, true) => (
+            "{{site_title}} security alert: {{title}}",
+            r#"<h2 style="color: #333;">Security alert</h2>
+            <p>{{title}}</p>
+            <p>{{details}}</p>
+            <p style="color: #999;">Time: {{time}}. If this wasn't you or another administrator, review your accounts.</p>"#,
+        ),
+    };
+    EmailTemplate {
+        subject: subject.to_string(),
+        body: format!(
+            r#"<div style="font-family: Arial, sans-serif; max-width: 600px; margin: 0 auto; padding: 20px;">
+            {}
+        </div>"#,
+            content
+        ),
+    }
+}
+
+/// Render a Handlebars-style template / 渲染 Handlebars 风格模板
+pub fn render_template(template: &str, vars: &Value) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let tag = &rest[start..];
+
+        // {{{raw}}}
+        if let Some(inner) = tag.strip_prefix("{{{") {
+            if let Some(end) = inner.find("}}}") {
+                out.push_str(&value_to_string(lookup(vars, inner[..end].trim())));
+                rest = &inner[end + 3..];
+                continue;
+            }
+        }
+
+        let Some(end) = tag.find("}}") else {
+            out.push_str(tag);
+            return out;
+        };
+        let expr = tag[2..end].trim();
+        let after = &tag[end + 2..];
+
+        if let Some(name) = expr.strip_prefix("#if ") {
+            let (then_part, else_part, remaining) = split_if_block(after);
+            let branch = if is_truthy(lookup(vars, name.trim())) { then_part } else { else_part };
+            out.push_str(&render_template(branch, vars));
+            rest = remaining;
+        } else if expr == "else" || expr == "/if" {
+            // Stray block tag, drop it / 孤立的块标签，忽略
+            rest = after;
+        } else {
+            out.push_str(&html_escape(&value_to_string(lookup(vars, expr))));
+            rest = after;
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Split `then{{else}}else{{/if}}rest` honoring nesting / 拆分条件块（支持嵌套）
+fn split_if_block(input: &str) -> (&str, &str, &str) {
+    let mut depth = 0;
+    let mut else_pos: Option<(usize, usize)> = None;
+    let mut pos = 0;
+
+    while let Some(offset) = input[pos..].find("{{") {
+        let start = pos + offset;
+        let Some(len) = input[start..].find("}}") else { break };
+        let end = start + len + 2;
+        let expr = input[start + 2..start + len].trim();
+
+        if expr.starts_with("#if ") {
+            depth += 1;
+        } else if expr == "/if" {
+            if depth == 0 {
+                return match else_pos {
+                    Some((es, ee)) => (&input[..es], &input[ee..start], &input[end..]),
+                    None => (&input[..start], "", &input[end..]),
+                };
+            }
+            depth -= 1;
+        } else if expr == "else" && depth == 0 && else_pos.is_none() {
+            else_pos = Some((start, end));
+        }
+        pos = end;
+    }
+
+    // Unclosed block: treat the rest as body / 未闭合：剩余部分作为内容
+    (input, "", "")
+}
+
+fn lookup<'a>(vars: &'a Value, path: &str) -> &'a Value {
+    path.split('.').fold(vars, |v, key| v.get(key).unwrap_or(&Value::Null))
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().map(|f| f != 0.0).unwrap_or(false),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_template() {
+        let vars = json!({
+            "name": "<b>Yao</b>",
+            "task": { "files": 3 },
+            "reset": true,
+            "empty": ""
+        });
+        assert_eq!(render_template("Hi {{name}}!", &vars), "Hi &lt;b&gt;Yao&lt;/b&gt;!");
+        assert_eq!(render_template("Hi {{{name}}}!", &vars), "Hi <b>Yao</b>!");
+        assert_eq!(render_template("{{task.files}} files{{missing}}", &vars), "3 files");
+        assert_eq!(render_template("{{#if reset}}A{{else}}B{{/if}}", &vars), "A");
+        assert_eq!(render_template("{{#if empty}}A{{else}}B{{/if}}.", &vars), "B.");
+        assert_eq!(
+            render_template("{{#if reset}}[{{#if empty}}x{{else}}y{{/if}}]{{else}}z{{/if}}", &vars),
+            "[y]"
+        );
+        assert_eq!(render_template("broken {{name", &vars), "broken {{name");
+    }
+
+    #[test]
+    fn test_resolve_and_enable() {
+        let templates = EmailTemplates::new();
+        assert!(templates.is_enabled(EmailEvent::VerificationCode));
+        assert!(!templates.is_enabled(EmailEvent::TaskCompleted));
+        assert!(templates.render(EmailEvent::TaskCompleted, None, &json!({})).is_none());
+
+        let mut config = EmailTemplateConfig::default();
+        let custom = EmailTemplate { subject: "Code {{code}}".to_string(), body: "{{code}}".to_string() };
+        config.events.insert(EmailEvent::VerificationCode, EmailEventConfig {
+            enabled: true,
+            bcc: vec!["audit@example.com".to_string()],
+            templates: HashMap::from([("en-US".to_string(), custom.clone())]),
+        });
+        templates.set_config(config);
+
+        assert_eq!(templates.resolve(EmailEvent::VerificationCode, Some("en-US")), custom);
+        // No override for default locale: built-in / 默认语言无覆盖时使用内置模板
+        assert_eq!(
+            templates.resolve(EmailEvent::VerificationCode, None),
+            builtin_template(EmailEvent::VerificationCode, DEFAULT_EMAIL_LOCALE)
+        );
+
+        let mail = templates.render(EmailEvent::VerificationCode, Some("en-US"), &json!({"code": "123456"})).unwrap();
+        assert_eq!(mail.subject, "Code 123456");
+        assert_eq!(mail.bcc, vec!["audit@example.com".to_string()]);
+    }
+}
@@ -0,0 +1,249 @@
+//! API error type / API 错误类型
+//!
+//! `ApiError` carries the HTTP status of a failed request and serializes as the usual
+//! `{"code", "message", "data", "error_type"}` body, with `code` equal to the HTTP status.
+//! Storage driver failures are categorized from their message so clients can tell an expired
+//! driver login, a full quota or upstream rate limiting apart from a generic outage.
+//! `ApiError` 携带失败请求的 HTTP 状态码，序列化为统一的 `{"code", "message", "data", "error_type"}`
+//! 响应体，`code` 与 HTTP 状态码一致。存储驱动错误按消息归类，便于客户端区分驱动登录过期、
+//! 空间不足、上游限流与一般故障。
+
+use std::fmt;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+/// Storage driver failure category / 存储驱动错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverErrorKind {
+    /// Driver login or token expired / 驱动登录或令牌已过期
+    AuthExpired,
+    /// Storage quota exceeded / 存储空间不足
+    QuotaExceeded,
+    /// Object missing on the storage / 存储上不存在该对象
+    NotFound,
+    /// Upstream rate limiting / 上游限流
+    RateLimited,
+    /// Mount is read-only or the site is in maintenance / 挂载只读或站点维护中
+    ReadOnly,
+    /// Any other failure / 其他故障
+    Unavailable,
+}
+
+impl DriverErrorKind {
+    /// Categorize a driver error message / 按消息归类驱动错误
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let has = |keys: &[&str]| keys.iter().any(|k| lower.contains(k));
+        if has(&["只读", "read-only", "维护中", "maintenance"]) {
+            DriverErrorKind::ReadOnly
+        } else if has(&["429", "too many requests", "rate limit", "ratelimit", "throttl", "请求过于频繁", "频率"]) {
+            DriverErrorKind::RateLimited
+        } else if has(&["quota", "insufficient storage", "507", "no space", "storage full", "空间不足", "容量不足"]) {
+            DriverErrorKind::QuotaExceeded
+        } else if has(&["401", "unauthorized", "token expired", "expired token", "invalid token", "invalid_grant",
+            "access token", "access_token", "refresh token", "refresh_token", "login expired", "登录已过期", "登录失效", "授权"]) {
+            DriverErrorKind::AuthExpired
+        } else if has(&["404", "not found", "no such file", "does not exist", "不存在"]) {
+            DriverErrorKind::NotFound
+        } else {
+            DriverErrorKind::Unavailable
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DriverErrorKind::AuthExpired => StatusCode::BAD_GATEWAY,
+            DriverErrorKind::QuotaExceeded => StatusCode::INSUFFICIENT_STORAGE,
+            DriverErrorKind::NotFound => StatusCode::NOT_FOUND,
+            DriverErrorKind::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            DriverErrorKind::ReadOnly => StatusCode::FORBIDDEN,
+            DriverErrorKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DriverErrorKind::AuthExpired => "DRIVER_AUTH_EXPIRED",
+            DriverErrorKind::QuotaExceeded => "DRIVER_QUOTA_EXCEEDED",
+            DriverErrorKind::NotFound => "DRIVER_NOT_FOUND",
+            DriverErrorKind::RateLimited => "DRIVER_RATE_LIMITED",
+            DriverErrorKind::ReadOnly => "DRIVER_READ_ONLY",
+            DriverErrorKind::Unavailable => "DRIVER_ERROR",
+        }
+    }
+}
+
+/// API error / API 错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    TooManyRequests(String),
+    PayloadTooLarge(String),
+    Driver { kind: DriverErrorKind, message: String },
+    /// Internal failure, details are logged and not returned / 内部错误，详情只记录日志不返回
+    Internal(String),
+}
+
+impl ApiError {
+    /// Driver error categorized from its message / 按消息归类的驱动错误
+    pub fn driver(message: impl Into<String>) -> Self {
+        let message = message.into();
+        ApiError::Driver { kind: DriverErrorKind::classify(&message), message }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Driver { kind, .. } => kind.status(),
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable error category / 机器可读的错误类别
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::TooManyRequests(_) => "RATE_LIMITED",
+            ApiError::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            ApiError::Driver { kind, .. } => kind.as_str(),
+            ApiError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Message returned to the client / 返回给客户端的消息
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BadRequest(m)
+            | ApiError::Unauthorized(m)
+            | ApiError::Forbidden(m)
+            | ApiError::NotFound(m)
+            | ApiError::Conflict(m)
+            | ApiError::TooManyRequests(m)
+            | ApiError::PayloadTooLarge(m)
+            | ApiError::Driver { message: m, .. } => m,
+            ApiError::Internal(_) => "服务器错误",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Internal(detail) => write!(f, "{}: {}", self.error_type(), detail),
+            _ => write!(f, "{}: {}", self.error_type(), self.message()),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        ApiError::Internal(e.to_string())
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::Internal(format!("{:#}", e))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if let ApiError::Internal(detail) = &self {
+            tracing::error!("Internal error: {}", detail);
+        }
+        (self.status(), Json(ApiResponse::<()>::from_error(&self))).into_response()
+    }
+}
+
+/// Unified API response body / 统一 API 响应体
+#[derive(Debug, Serialize)]
+pub struct ApiResponse<T> {
+    pub code: u16,
+    pub message: String,
+    pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<&'static str>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn success(data: T) -> Self {
+        Self {
+            code: 200,
+            message: "success".to_string(),
+            data: Some(data),
+            error_type: None,
+        }
+    }
+
+    /// Generic server-side failure / 一般服务端错误
+    pub fn error(message: &str) -> Self {
+        Self {
+            code: 500,
+            message: message.to_string(),
+            data: None,
+            error_type: None,
+        }
+    }
+
+    pub fn from_error(error: &ApiError) -> Self {
+        Self {
+            code: error.status().as_u16(),
+            message: error.message().to_string(),
+            data: None,
+            error_type: Some(error.error_type()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_driver_error() {
+        assert_eq!(DriverErrorKind::classify("HTTP 401 Unauthorized: token expired"), DriverErrorKind::AuthExpired);
+        assert_eq!(DriverErrorKind::classify("refresh_token is invalid"), DriverErrorKind::AuthExpired);
+        assert_eq!(DriverErrorKind::classify("Quota exceeded"), DriverErrorKind::QuotaExceeded);
+        assert_eq!(DriverErrorKind::classify("文件不存在"), DriverErrorKind::NotFound);
+        assert_eq!(DriverErrorKind::classify("status 429 Too Many Requests"), DriverErrorKind::RateLimited);
+        assert_eq!(DriverErrorKind::classify("存储为只读"), DriverErrorKind::ReadOnly);
+        assert_eq!(DriverErrorKind::classify("connection reset"), DriverErrorKind::Unavailable);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let err = ApiError::driver("上传失败: quota exceeded");
+        assert_eq!(err.status(), StatusCode::INSUFFICIENT_STORAGE);
+        let body = serde_json::to_value(ApiResponse::<()>::from_error(&err)).unwrap();
+        assert_eq!(body, serde_json::json!({
+            "code": 507,
+            "message": "上传失败: quota exceeded",
+            "data": null,
+            "error_type": "DRIVER_QUOTA_EXCEEDED"
+        }));
+
+        let err = ApiError::Internal("database is locked".to_string());
+        assert_eq!(err.message(), "服务器错误");
+        let body = serde_json::to_value(ApiResponse::success(1)).unwrap();
+        assert!(body.get("error_type").is_none());
+    }
+}
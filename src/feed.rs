@@ -0,0 +1,426 @@
+//! Directory feeds / 目录订阅源
+//!
+//! Admins opt a folder in to a public feed listing its most recent files as RSS 2.0, Atom or
+//! JSON Feed 1.1, so subscribers notice new releases without polling the web UI. Items link to
+//! `/feed/:id/file`, signed per feed and valid for a number of days; the signature ends on a day
+//! boundary so the links stay the same between polls and readers don't treat items as new.
+//! Items are identified by path and modification time, a replaced file shows up again.
+//! 管理员为目录开启公开订阅源，以 RSS 2.0、Atom 或 JSON Feed 1.1 列出最近的文件，订阅者无需反复打开网页即可
+//! 得知新版本。条目链接指向 `/feed/:id/file`，按订阅源签名并在若干天内有效；签名在整天结束时过期，
+//! 两次拉取之间链接不变，阅读器不会把条目当作新条目。条目以路径与修改时间标识，被替换的文件会重新出现。
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+
+use crate::download_list::escape_xml;
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Max title and description length in characters / 标题与描述最大长度（字符）
+pub const MAX_TITLE_LEN: usize = 200;
+
+/// Items listed when not configured / 未配置时列出的条目数
+pub const DEFAULT_MAX_ITEMS: u32 = 50;
+
+/// Upper bound of items per feed / 每个订阅源的条目数上限
+pub const MAX_ITEMS: u32 = 500;
+
+/// Days links stay valid when not configured / 未配置时链接的有效天数
+pub const DEFAULT_LINK_DAYS: u32 = 7;
+
+/// Upper bound of link validity in days / 链接有效天数上限
+pub const MAX_LINK_DAYS: u32 = 365;
+
+const DAY: i64 = 24 * 3600;
+
+/// Output format / 输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    #[default]
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "rss",
+            FeedFormat::Atom => "atom",
+            FeedFormat::Json => "json",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Json => "application/feed+json; charset=utf-8",
+        }
+    }
+
+    /// Render the feed / 生成订阅源
+    pub fn render(self, channel: &Channel, items: &[FeedItem]) -> String {
+        match self {
+            FeedFormat::Rss => to_rss(channel, items),
+            FeedFormat::Atom => to_atom(channel, items),
+            FeedFormat::Json => to_json_feed(channel, items),
+        }
+    }
+}
+
+/// A feed published for a folder / 为目录发布的订阅源
+#[derive(Debug, Clone, Serialize)]
+pub struct Feed {
+    pub id: String,
+    /// Folder the feed lists / 订阅源列出的目录
+    pub path: String,
+    pub title: String,
+    pub description: String,
+    pub max_items: u32,
+    /// Include files in subfolders / 包含子目录中的文件
+    pub recursive: bool,
+    /// Days a signed download link stays valid / 签名下载链接的有效天数
+    pub link_days: u32,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Feed {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.path.starts_with('/') {
+            return Err("path must be absolute".to_string());
+        }
+        if self.title.chars().count() > MAX_TITLE_LEN || self.description.chars().count() > MAX_TITLE_LEN {
+            return Err(format!("title and description must be at most {} characters", MAX_TITLE_LEN));
+        }
+        if !(1..=MAX_ITEMS).contains(&self.max_items) {
+            return Err(format!("max_items must be between 1 and {}", MAX_ITEMS));
+        }
+        if !(1..=MAX_LINK_DAYS).contains(&self.link_days) {
+            return Err(format!("link_days must be between 1 and {}", MAX_LINK_DAYS));
+        }
+        Ok(())
+    }
+
+    /// Whether a file path lies in the feed / 文件路径是否属于订阅源
+    pub fn contains(&self, path: &str) -> bool {
+        let path = fix_and_clean_path(path);
+        path != fix_and_clean_path(&self.path) && is_sub_path(&self.path, &path)
+    }
+
+    /// Expiry of links signed at `now`: `link_days` after the end of the current day
+    /// 在 `now` 签名的链接的过期时间：当天结束后再加 `link_days` 天
+    pub fn link_expiry(&self, now: i64) -> i64 {
+        (now / DAY + 1 + self.link_days as i64) * DAY
+    }
+
+    /// Signed subject of a file link, so signatures can't be reused across feeds or for thumbnails
+    /// 文件链接的签名内容，使签名不能用于其他订阅源或缩略图
+    pub fn link_subject(&self, path: &str) -> String {
+        format!("feed:{}:{}", self.id, path)
+    }
+
+    /// Title shown to readers, the folder name when empty / 向阅读器显示的标题，为空时使用目录名
+    pub fn display_title(&self) -> &str {
+        match self.title.trim() {
+            "" => self.path.rsplit('/').find(|s| !s.is_empty()).unwrap_or("/"),
+            title => title,
+        }
+    }
+}
+
+/// Feed-level fields of a rendered document / 生成文档时订阅源级别的字段
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Web page of the folder / 目录的网页地址
+    pub home_url: String,
+    /// URL of the feed itself / 订阅源自身的地址
+    pub feed_url: String,
+}
+
+/// One file in a feed / 订阅源中的一个文件
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub name: String,
+    /// Path relative to the feed folder / 相对于订阅源目录的路径
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub url: String,
+}
+
+impl FeedItem {
+    /// Stable identity across polls / 多次拉取之间不变的标识
+    pub fn guid(&self) -> String {
+        match self.modified {
+            Some(modified) => format!("{}@{}", self.path, modified.timestamp()),
+            None => self.path.clone(),
+        }
+    }
+
+    fn mime_type(&self) -> String {
+        mime_guess::from_path(&self.name).first_or_octet_stream().to_string()
+    }
+}
+
+/// Parse a modification time as reported by drivers / 解析驱动返回的修改时间
+pub fn parse_modified(modified: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(modified) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(modified, format).ok())
+        .map(|dt| dt.and_utc())
+}
+
+/// Keep the `max` most recent items, newest first; items without a time go last
+/// 保留最近的 `max` 个条目（新的在前），没有时间的条目排在最后
+pub fn most_recent(mut items: Vec<FeedItem>, max: usize) -> Vec<FeedItem> {
+    items.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.path.cmp(&b.path)));
+    items.truncate(max);
+    items
+}
+
+fn updated(items: &[FeedItem]) -> DateTime<Utc> {
+    items.iter().filter_map(|i| i.modified).max().unwrap_or(DateTime::UNIX_EPOCH)
+}
+
+/// RSS 2.0 document, files are also attached as enclosures / RSS 2.0 文档，文件同时作为附件
+pub fn to_rss(channel: &Channel, items: &[FeedItem]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+    out.push_str(&format!(
+        "  <title>{}</title>\n  <link>{}</link>\n  <description>{}</description>\n  <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n  <lastBuildDate>{}</lastBuildDate>\n",
+        escape_xml(&channel.title), escape_xml(&channel.home_url), escape_xml(&channel.description),
+        escape_xml(&channel.feed_url), updated(items).to_rfc2822(),
+    ));
+    for item in items {
+        out.push_str(&format!(
+            "  <item>\n    <title>{}</title>\n    <link>{}</link>\n    <guid isPermaLink=\"false\">{}</guid>\n",
+            escape_xml(&item.path), escape_xml(&item.url), escape_xml(&item.guid()),
+        ));
+        if let Some(modified) = item.modified {
+            out.push_str(&format!("    <pubDate>{}</pubDate>\n", modified.to_rfc2822()));
+        }
+        out.push_str(&format!(
+            "    <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>\n  </item>\n",
+            escape_xml(&item.url), item.size, escape_xml(&item.mime_type()),
+        ));
+    }
+    out.push_str("</channel>\n</rss>\n");
+    out
+}
+
+/// Atom (RFC 4287) document / Atom（RFC 4287）文档
+pub fn to_atom(channel: &Channel, items: &[FeedItem]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!(
+        "  <id>urn:yaolist:feed:{}</id>\n  <title>{}</title>\n  <subtitle>{}</subtitle>\n  <updated>{}</updated>\n  <link rel=\"self\" href=\"{}\"/>\n  <link href=\"{}\"/>\n",
+        escape_xml(&channel.id), escape_xml(&channel.title), escape_xml(&channel.description),
+        updated(items).to_rfc3339(), escape_xml(&channel.feed_url), escape_xml(&channel.home_url),
+    ));
+    for item in items {
+        out.push_str(&format!(
+            "  <entry>\n    <id>urn:yaolist:feed:{}:{}</id>\n    <title>{}</title>\n    <updated>{}</updated>\n    <link href=\"{}\"/>\n    <link rel=\"enclosure\" href=\"{}\" length=\"{}\" type=\"{}\"/>\n  </entry>\n",
+            escape_xml(&channel.id), escape_xml(&urlencoding::encode(&item.guid())), escape_xml(&item.path),
+            item.modified.unwrap_or(DateTime::UNIX_EPOCH).to_rfc3339(), escape_xml(&item.url),
+            escape_xml(&item.url), item.size, escape_xml(&item.mime_type()),
+        ));
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+/// JSON Feed 1.1 document / JSON Feed 1.1 文档
+pub fn to_json_feed(channel: &Channel, items: &[FeedItem]) -> String {
+    let items: Vec<_> = items.iter()
+        .map(|item| json!({
+            "id": item.guid(),
+            "title": item.path,
+            "url": item.url,
+            "date_published": item.modified.map(|m| m.to_rfc3339()),
+            "attachments": [{
+                "url": item.url,
+                "mime_type": item.mime_type(),
+                "size_in_bytes": item.size
+            }]
+        }))
+        .collect();
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": channel.title,
+        "description": channel.description,
+        "home_page_url": channel.home_url,
+        "feed_url": channel.feed_url,
+        "items": items
+    }).to_string()
+}
+
+/// `feeds` row: id, path, title, description, max_items, recursive, link_days, enabled, created_at
+type FeedRow = (String, String, String, String, i64, bool, i64, bool, String);
+
+/// Feed cache / 订阅源缓存
+pub struct FeedManager {
+    feeds: RwLock<Vec<Feed>>,
+}
+
+impl FeedManager {
+    pub fn new() -> Self {
+        Self {
+            feeds: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Load feeds from database / 从数据库加载订阅源
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<FeedRow> = sqlx::query_as(
+            "SELECT id, path, title, description, max_items, recursive, link_days, enabled, created_at FROM feeds"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let feeds = rows.into_iter()
+            .map(|(id, path, title, description, max_items, recursive, link_days, enabled, created_at)| Feed {
+                id,
+                path,
+                title,
+                description,
+                max_items: max_items.clamp(1, MAX_ITEMS as i64) as u32,
+                recursive,
+                link_days: link_days.clamp(1, MAX_LINK_DAYS as i64) as u32,
+                enabled,
+                created_at: DateTime::parse_from_rfc3339(&created_at)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+            })
+            .collect();
+        *self.feeds.write() = feeds;
+
+        Ok(())
+    }
+
+    /// List all feeds, newest first / 列出全部订阅源（新的在前）
+    pub fn list(&self) -> Vec<Feed> {
+        let mut list = self.feeds.read().clone();
+        list.sort_by_key(|f| std::cmp::Reverse(f.created_at));
+        list
+    }
+
+    pub fn get(&self, id: &str) -> Option<Feed> {
+        self.feeds.read().iter().find(|f| f.id == id).cloned()
+    }
+
+    /// Insert or replace a feed in the cache / 新增或替换缓存中的订阅源
+    pub fn upsert(&self, feed: Feed) -> Result<(), String> {
+        feed.validate()?;
+        let mut feeds = self.feeds.write();
+        match feeds.iter_mut().find(|f| f.id == feed.id) {
+            Some(existing) => *existing = feed,
+            None => feeds.push(feed),
+        }
+        Ok(())
+    }
+
+    /// Remove a feed from the cache / 从缓存移除订阅源
+    pub fn remove(&self, id: &str) -> bool {
+        let mut feeds = self.feeds.write();
+        let before = feeds.len();
+        feeds.retain(|f| f.id != id);
+        feeds.len() != before
+    }
+}
+
+impl Default for FeedManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed() -> Feed {
+        Feed {
+            id: "f1".to_string(),
+            path: "/releases".to_string(),
+            title: String::new(),
+            description: String::new(),
+            max_items: DEFAULT_MAX_ITEMS,
+            recursive: false,
+            link_days: DEFAULT_LINK_DAYS,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn item(path: &str, modified: Option<&str>) -> FeedItem {
+        FeedItem {
+            name: path.rsplit('/').next().unwrap().to_string(),
+            path: path.to_string(),
+            size: 10,
+            modified: modified.and_then(parse_modified),
+            url: format!("https://x/feed/f1/file?path={}&a=1", path),
+        }
+    }
+
+    #[test]
+    fn test_feed() {
+        let mut f = feed();
+        assert!(f.validate().is_ok());
+        assert_eq!(f.display_title(), "releases");
+        assert!(f.contains("/releases/v1.zip"));
+        assert!(f.contains("/releases/../releases/a/b"));
+        assert!(!f.contains("/releases"));
+        assert!(!f.contains("/releases2/x"));
+        assert_eq!(f.link_expiry(DAY + 5), (2 + DEFAULT_LINK_DAYS as i64) * DAY);
+        assert_eq!(f.link_expiry(DAY + 5), f.link_expiry(2 * DAY - 1));
+        f.max_items = 0;
+        assert!(f.validate().is_err());
+    }
+
+    #[test]
+    fn test_most_recent() {
+        let items = vec![
+            item("a.zip", Some("2024-01-01T00:00:00Z")),
+            item("b.zip", None),
+            item("c.zip", Some("2024-03-01 08:00:00")),
+            item("d.zip", Some("2024-02-01T00:00:00+08:00")),
+        ];
+        let names: Vec<String> = most_recent(items, 3).into_iter().map(|i| i.name).collect();
+        assert_eq!(names, ["c.zip", "d.zip", "a.zip"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let channel = Channel {
+            id: "f1".to_string(),
+            title: "R & D".to_string(),
+            description: String::new(),
+            home_url: "https://x/releases".to_string(),
+            feed_url: "https://x/feed/f1".to_string(),
+        };
+        let items = vec![item("v1 <beta>.zip", Some("2024-01-01T00:00:00Z"))];
+
+        let rss = to_rss(&channel, &items);
+        assert!(rss.contains("<title>R &amp; D</title>"));
+        assert!(rss.contains("<title>v1 &lt;beta&gt;.zip</title>"));
+        assert!(rss.contains("&amp;a=1"));
+        assert!(rss.contains("type=\"application/zip\""));
+        assert!(rss.contains("<pubDate>Mon, 1 Jan 2024 00:00:00 +0000</pubDate>"));
+
+        let atom = to_atom(&channel, &items);
+        assert!(atom.contains("<updated>2024-01-01T00:00:00+00:00</updated>"));
+
+        let json: serde_json::Value = serde_json::from_str(&to_json_feed(&channel, &items)).unwrap();
+        assert_eq!(json["items"][0]["id"], "v1 <beta>.zip@1704067200");
+        assert_eq!(json["items"][0]["attachments"][0]["size_in_bytes"], 10);
+    }
+}
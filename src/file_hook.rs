@@ -0,0 +1,544 @@
+//! File event hooks / 文件事件钩子
+//!
+//! This module handles:
+//! - Hook registry cache (events, path filter, action) / 钩子配置缓存
+//! - Shell command and HTTP call actions / Shell 命令与 HTTP 调用
+//! - Last run status per hook / 每个钩子的最近执行结果
+//!
+//! Commands never get context substituted into the command line (no shell injection through file
+//! names); they read it from `YAOLIST_*` environment variables and a JSON document on stdin.
+//! HTTP actions may use `{{path}}`, `{{name}}`, `{{dir}}`, `{{event}}`, `{{username}}`, `{{user_id}}`
+//! placeholders (URL-encoded in the URL, JSON-escaped in the body).
+//! 命令不会把上下文拼接进命令行（防止文件名注入），而是通过 `YAOLIST_*` 环境变量和 stdin 的 JSON 获取；
+//! HTTP 调用支持占位符（URL 中做 URL 编码，请求体中做 JSON 转义）。
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Default action timeout (seconds) / 默认超时（秒）
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+/// Max action timeout (seconds) / 最大超时（秒）
+pub const MAX_HOOK_TIMEOUT_SECS: u64 = 600;
+/// Output kept in the run status (bytes) / 执行结果中保留的输出长度（字节）
+const MAX_OUTPUT_BYTES: usize = 4096;
+
+/// File event / 文件事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileHookEvent {
+    UploadComplete,
+    Delete,
+    ShareCreate,
+    /// Manual test from the admin panel / 管理面板手动测试
+    Test,
+}
+
+impl FileHookEvent {
+    /// Events that can be subscribed / 可订阅的事件
+    pub fn all() -> &'static [FileHookEvent] {
+        &[FileHookEvent::UploadComplete, FileHookEvent::Delete, FileHookEvent::ShareCreate]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileHookEvent::UploadComplete => "upload_complete",
+            FileHookEvent::Delete => "delete",
+            FileHookEvent::ShareCreate => "share_create",
+            FileHookEvent::Test => "test",
+        }
+    }
+}
+
+/// What a hook does / 钩子动作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FileHookAction {
+    /// Run through `sh -c` (`cmd /C` on Windows) / 通过 shell 执行命令
+    Command { command: String },
+    /// HTTP request; empty body sends the context as JSON / HTTP 请求，body 为空时发送上下文 JSON
+    Http {
+        url: String,
+        #[serde(default = "default_method")]
+        method: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(default)]
+        body: Option<String>,
+    },
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+/// Hook config / 钩子配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHook {
+    pub id: String,
+    pub name: String,
+    /// Subscribed events, empty = all / 订阅的事件，为空表示全部
+    #[serde(default)]
+    pub events: Vec<FileHookEvent>,
+    /// Only fire for paths under this prefix / 仅对该路径下的文件触发
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    pub action: FileHookAction,
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_HOOK_TIMEOUT_SECS
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl FileHook {
+    /// Whether this hook wants the event for `path` / 是否对该事件与路径触发
+    pub fn accepts(&self, event: FileHookEvent, path: &str) -> bool {
+        if event == FileHookEvent::Test {
+            return true;
+        }
+        self.enabled
+            && (self.events.is_empty() || self.events.contains(&event))
+            && self.path_prefix.as_deref().map(|p| path_under(path, p)).unwrap_or(true)
+    }
+}
+
+/// Path-boundary aware prefix check (`/a` matches `/a/b`, not `/ab`) / 按路径边界判断前缀
+fn path_under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    prefix.is_empty() || path == prefix || path.strip_prefix(prefix).map(|r| r.starts_with('/')).unwrap_or(false)
+}
+
+/// Event context passed to the action / 传给动作的事件上下文
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHookContext {
+    pub event: FileHookEvent,
+    pub path: String,
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    /// Event-specific fields (task id, share id, ...) / 事件相关字段
+    #[serde(default)]
+    pub extra: Value,
+}
+
+impl FileHookContext {
+    pub fn new(event: FileHookEvent, path: &str, user_id: Option<String>, username: Option<String>, extra: Value) -> Self {
+        Self {
+            event,
+            path: path.to_string(),
+            user_id,
+            username,
+            timestamp: Utc::now(),
+            extra,
+        }
+    }
+
+    /// File name (last path segment) / 文件名
+    pub fn name(&self) -> &str {
+        self.path.trim_end_matches('/').rsplit('/').next().unwrap_or("")
+    }
+
+    /// Parent directory / 父目录
+    pub fn dir(&self) -> &str {
+        let trimmed = self.path.trim_end_matches('/');
+        match trimmed.rfind('/') {
+            Some(0) | None => "/",
+            Some(i) => &trimmed[..i],
+        }
+    }
+
+    fn vars(&self) -> [(&'static str, &str); 6] {
+        [
+            ("path", self.path.as_str()),
+            ("name", self.name()),
+            ("dir", self.dir()),
+            ("event", self.event.as_str()),
+            ("username", self.username.as_deref().unwrap_or("")),
+            ("user_id", self.user_id.as_deref().unwrap_or("")),
+        ]
+    }
+
+    /// Replace `{{var}}` placeholders, escaping values with `escape` / 替换占位符
+    ///
+    /// Single pass, so values containing `{{...}}` are never expanded again / 单次扫描，值中的占位符不会被再次展开
+    pub fn render(&self, template: &str, escape: impl Fn(&str) -> String) -> String {
+        let vars = self.vars();
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            match after.find("}}").and_then(|end| {
+                let key = after[..end].trim();
+                vars.iter().find(|(k, _)| *k == key).map(|(_, v)| (end, *v))
+            }) {
+                Some((end, value)) => {
+                    out.push_str(&escape(value));
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    out.push_str("{{");
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// JSON-escape a string without the surrounding quotes / JSON 转义（不含两侧引号）
+fn json_escape(s: &str) -> String {
+    let quoted = serde_json::to_string(s).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// Last run result of a hook / 最近一次执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHookRunStatus {
+    pub event: FileHookEvent,
+    pub path: String,
+    pub success: bool,
+    /// Exit code (command) or HTTP status / 退出码或 HTTP 状态码
+    pub code: Option<i32>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub ran_at: DateTime<Utc>,
+}
+
+/// `file_hooks` row: id, name, events, path_prefix, action, timeout_secs, enabled, created_at
+type HookRow = (String, String, String, Option<String>, String, i64, bool, String);
+
+/// File hook manager / 文件钩子管理器
+pub struct FileHookManager {
+    hooks: RwLock<Vec<FileHook>>,
+    status: RwLock<HashMap<String, FileHookRunStatus>>,
+    client: reqwest::Client,
+}
+
+impl FileHookManager {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent("YaoList-Hook")
+            .build()
+            .unwrap_or_default();
+        Self {
+            hooks: RwLock::new(Vec::new()),
+            status: RwLock::new(HashMap::new()),
+            client,
+        }
+    }
+
+    /// Load hooks from database / 从数据库加载钩子
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<HookRow> = sqlx::query_as(
+            "SELECT id, name, events, path_prefix, action, timeout_secs, enabled, created_at FROM file_hooks"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let mut hooks = Vec::new();
+        for (id, name, events, path_prefix, action, timeout_secs, enabled, created_at) in rows {
+            let action = match serde_json::from_str(&action) {
+                Ok(a) => a,
+                Err(e) => {
+                    tracing::warn!("Invalid action for file hook {}: {}", id, e);
+                    continue;
+                }
+            };
+            hooks.push(FileHook {
+                id,
+                name,
+                events: serde_json::from_str(&events).unwrap_or_default(),
+                path_prefix,
+                action,
+                timeout_secs: (timeout_secs.max(1) as u64).min(MAX_HOOK_TIMEOUT_SECS),
+                enabled,
+                created_at: DateTime::parse_from_rfc3339(&created_at)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+            });
+        }
+        *self.hooks.write() = hooks;
+
+        Ok(())
+    }
+
+    /// List hooks / 列出钩子
+    pub fn list(&self) -> Vec<FileHook> {
+        self.hooks.read().clone()
+    }
+
+    /// Get hook by id / 获取钩子
+    pub fn get(&self, id: &str) -> Option<FileHook> {
+        self.hooks.read().iter().find(|h| h.id == id).cloned()
+    }
+
+    /// Insert or replace a hook in the cache / 新增或替换缓存中的钩子
+    pub fn upsert(&self, hook: FileHook) {
+        let mut hooks = self.hooks.write();
+        match hooks.iter_mut().find(|h| h.id == hook.id) {
+            Some(existing) => *existing = hook,
+            None => hooks.push(hook),
+        }
+    }
+
+    /// Remove a hook from the cache / 从缓存移除钩子
+    pub fn remove(&self, id: &str) -> bool {
+        self.status.write().remove(id);
+        let mut hooks = self.hooks.write();
+        let before = hooks.len();
+        hooks.retain(|h| h.id != id);
+        hooks.len() != before
+    }
+
+    /// Last run status of a hook / 最近一次执行结果
+    pub fn last_status(&self, id: &str) -> Option<FileHookRunStatus> {
+        self.status.read().get(id).cloned()
+    }
+
+    /// Run all matching hooks in background / 后台执行所有匹配的钩子
+    pub fn fire(self: &Arc<Self>, ctx: FileHookContext) {
+        if ctx.event == FileHookEvent::Test {
+            return;
+        }
+        let targets: Vec<FileHook> = self.hooks.read()
+            .iter()
+            .filter(|h| h.accepts(ctx.event, &ctx.path))
+            .cloned()
+            .collect();
+        for hook in targets {
+            let manager = self.clone();
+            let ctx = ctx.clone();
+            crate::request_id::spawn(async move {
+                manager.run(&hook, &ctx).await;
+            });
+        }
+    }
+
+    /// Run one hook and wait for the result / 执行钩子并等待结果
+    pub async fn run(&self, hook: &FileHook, ctx: &FileHookContext) -> FileHookRunStatus {
+        let started = Instant::now();
+        let timeout = Duration::from_secs(hook.timeout_secs.clamp(1, MAX_HOOK_TIMEOUT_SECS));
+        let result = match &hook.action {
+            FileHookAction::Command { command } => run_command(command, ctx, timeout).await,
+            FileHookAction::Http { url, method, headers, body } => {
+                self.run_http(url, method, headers, body.as_deref(), ctx, timeout).await
+            }
+        };
+
+        let (success, code, output, error) = match result {
+            Ok((code, output, ok)) => (ok, code, output, if ok { None } else { Some(format!("Exit code / status: {:?}", code)) }),
+            Err(e) => (false, None, None, Some(e)),
+        };
+        if let Some(ref e) = error {
+            tracing::warn!("File hook {} ({}) failed for {}: {}", hook.id, ctx.event.as_str(), ctx.path, e);
+        }
+
+        let status = FileHookRunStatus {
+            event: ctx.event,
+            path: ctx.path.clone(),
+            success,
+            code,
+            output,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+            ran_at: Utc::now(),
+        };
+        self.status.write().insert(hook.id.clone(), status.clone());
+        status
+    }
+
+    /// Returns (status, body, success) / 返回（状态码，响应体，是否成功）
+    async fn run_http(
+        &self,
+        url: &str,
+        method: &str,
+        headers: &BTreeMap<String, String>,
+        body: Option<&str>,
+        ctx: &FileHookContext,
+        timeout: Duration,
+    ) -> Result<(Option<i32>, Option<String>, bool), String> {
+        let url = ctx.render(url, |v| urlencoding::encode(v).into_owned());
+        let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|_| format!("Invalid HTTP method: {}", method))?;
+
+        let mut req = self.client.request(method.clone(), &url).timeout(timeout);
+        for (k, v) in headers {
+            req = req.header(k.as_str(), ctx.render(v, |s| s.to_string()));
+        }
+        if method != reqwest::Method::GET && method != reqwest::Method::HEAD {
+            req = match body.filter(|b| !b.is_empty()) {
+                Some(b) => req.body(ctx.render(b, json_escape)),
+                None => req.json(ctx),
+            };
+        }
+
+        let resp = req.send().await.map_err(|e| e.to_string())?;
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        Ok((Some(status.as_u16() as i32), Some(truncate(&text)), status.is_success()))
+    }
+}
+
+impl Default for FileHookManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run a shell command with the context in env + stdin / 执行命令（上下文通过环境变量与stdin传入）
+async fn run_command(command: &str, ctx: &FileHookContext, timeout: Duration) -> Result<(Option<i32>, Option<String>, bool), String> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut c = tokio::process::Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut c = tokio::process::Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    };
+
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .env("YAOLIST_EVENT", ctx.event.as_str())
+        .env("YAOLIST_PATH", &ctx.path)
+        .env("YAOLIST_NAME", ctx.name())
+        .env("YAOLIST_DIR", ctx.dir())
+        .env("YAOLIST_USER", ctx.username.as_deref().unwrap_or(""))
+        .env("YAOLIST_USER_ID", ctx.user_id.as_deref().unwrap_or(""))
+        .env("YAOLIST_EXTRA", ctx.extra.to_string());
+
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to start command: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = serde_json::to_vec(ctx).unwrap_or_default();
+        // The command may ignore stdin / 命令可能不读取stdin
+        let _ = stdin.write_all(&input).await;
+    }
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let run = async {
+        // Read both pipes together so neither can fill up and block the command / 同时读取，避免管道写满阻塞
+        let (out, err) = tokio::join!(read_capped(stdout), read_capped(stderr));
+        let status = child.wait().await;
+        (status, out, err)
+    };
+
+    match tokio::time::timeout(timeout, run).await {
+        Ok((Ok(status), out, err)) => {
+            let mut output = String::from_utf8_lossy(&out).into_owned();
+            if !err.is_empty() {
+                output.push_str(&String::from_utf8_lossy(&err));
+            }
+            Ok((status.code(), Some(truncate(&output)), status.success()))
+        }
+        Ok((Err(e), _, _)) => Err(e.to_string()),
+        Err(_) => Err(format!("Timed out after {}s", timeout.as_secs())),
+    }
+}
+
+/// Read a pipe to the end, keeping only the first `MAX_OUTPUT_BYTES` / 读完管道，只保留前 MAX_OUTPUT_BYTES 字节
+async fn read_capped<R: tokio::io::AsyncRead + Unpin>(pipe: Option<R>) -> Vec<u8> {
+    let mut kept = Vec::new();
+    let Some(mut pipe) = pipe else {
+        return kept;
+    };
+    let mut buf = [0u8; 8192];
+    while let Ok(n) = pipe.read(&mut buf).await {
+        if n == 0 {
+            break;
+        }
+        let room = (MAX_OUTPUT_BYTES + 1).saturating_sub(kept.len());
+        kept.extend_from_slice(&buf[..n.min(room)]);
+    }
+    kept
+}
+
+fn truncate(s: &str) -> String {
+    if s.len() <= MAX_OUTPUT_BYTES {
+        return s.to_string();
+    }
+    let mut end = MAX_OUTPUT_BYTES;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &s[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(path: &str) -> FileHookContext {
+        FileHookContext::new(FileHookEvent::UploadComplete, path, Some("u1".to_string()), Some("alice".to_string()), Value::Null)
+    }
+
+    #[test]
+    fn test_accepts() {
+        let mut hook = FileHook {
+            id: "1".to_string(),
+            name: "scan".to_string(),
+            events: vec![FileHookEvent::UploadComplete],
+            path_prefix: Some("/media/".to_string()),
+            action: FileHookAction::Command { command: "true".to_string() },
+            timeout_secs: 5,
+            enabled: true,
+            created_at: Utc::now(),
+        };
+        assert!(hook.accepts(FileHookEvent::UploadComplete, "/media/movie.mkv"));
+        assert!(hook.accepts(FileHookEvent::UploadComplete, "/media"));
+        assert!(!hook.accepts(FileHookEvent::UploadComplete, "/mediax/movie.mkv"));
+        assert!(!hook.accepts(FileHookEvent::Delete, "/media/movie.mkv"));
+        hook.enabled = false;
+        assert!(!hook.accepts(FileHookEvent::UploadComplete, "/media/movie.mkv"));
+        assert!(hook.accepts(FileHookEvent::Test, "/"));
+    }
+
+    #[test]
+    fn test_render() {
+        let c = ctx("/media/a \"b\".mkv");
+        assert_eq!(c.name(), "a \"b\".mkv");
+        assert_eq!(c.dir(), "/media");
+        assert_eq!(ctx("/a.txt").dir(), "/");
+        assert_eq!(
+            c.render("http://x/scan?p={{path}}&u={{username}}", |v| urlencoding::encode(v).into_owned()),
+            "http://x/scan?p=%2Fmedia%2Fa%20%22b%22.mkv&u=alice"
+        );
+        assert_eq!(c.render(r#"{"p":"{{name}}"}"#, json_escape), r#"{"p":"a \"b\".mkv"}"#);
+        assert_eq!(ctx("/{{user_id}}").render("{{ path }} {{unknown}}", |v| v.to_string()), "/{{user_id}} {{unknown}}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_env() {
+        let c = ctx("/media/$(touch /tmp/x).mkv");
+        let (code, output, ok) = run_command("printf '%s' \"$YAOLIST_PATH\"", &c, Duration::from_secs(5)).await.unwrap();
+        assert!(ok);
+        assert_eq!(code, Some(0));
+        assert_eq!(output.as_deref(), Some("/media/$(touch /tmp/x).mkv"));
+    }
+}
@@ -0,0 +1,246 @@
+//! File type categories / 文件类型分类
+//!
+//! Maps extensions to coarse categories (video, audio, image, doc, archive) that are returned with
+//! listings and search results, so clients filter and pick icons from one map instead of keeping
+//! their own. The map is stored as JSON in `site_settings` (`file_types`) and applies as soon as
+//! it is saved; directories and unknown extensions are `other`.
+//! 按扩展名将文件归为视频、音频、图片、文档、压缩包等类别，随列表与搜索结果返回，
+//! 客户端据此筛选和选择图标，无需各自维护一份映射。映射以 JSON 保存在 `site_settings`（`file_types`），
+//! 保存后立即生效；目录与未知扩展名为 `other`。
+
+use std::collections::HashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Site setting holding the category map / 保存分类映射的站点设置
+const SETTING_KEY: &str = "file_types";
+
+const DOC_EXTENSIONS: &[&str] = &[
+    "txt", "md", "pdf", "doc", "docx", "xls", "xlsx", "csv", "ppt", "pptx", "odt", "ods", "odp",
+    "rtf", "epub", "mobi", "azw3",
+];
+
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    "zip", "rar", "7z", "tar", "gz", "tgz", "bz2", "xz", "zst", "iso", "dmg",
+];
+
+/// Category of a file / 文件类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    Video,
+    Audio,
+    Image,
+    Doc,
+    Archive,
+    Other,
+}
+
+impl FileType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "video" => Some(Self::Video),
+            "audio" => Some(Self::Audio),
+            "image" => Some(Self::Image),
+            "doc" => Some(Self::Doc),
+            "archive" => Some(Self::Archive),
+            "other" => Some(Self::Other),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Image => "image",
+            Self::Doc => "doc",
+            Self::Archive => "archive",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Extensions of each category / 各类别的扩展名
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileTypeMap {
+    pub video: Vec<String>,
+    pub audio: Vec<String>,
+    pub image: Vec<String>,
+    pub doc: Vec<String>,
+    pub archive: Vec<String>,
+}
+
+fn owned(list: &[&str]) -> Vec<String> {
+    list.iter().map(|e| e.to_string()).collect()
+}
+
+impl Default for FileTypeMap {
+    fn default() -> Self {
+        // ISO 镜像在 STRM 导出中按视频处理，分类时归为压缩包
+        let mut video = owned(crate::strm::DEFAULT_VIDEO_EXTENSIONS);
+        video.retain(|e| e != "iso");
+        let mut image = owned(crate::gallery::IMAGE_EXTENSIONS);
+        image.extend(owned(&["svg", "ico"]));
+        Self {
+            video,
+            audio: owned(crate::audio::AUDIO_EXTENSIONS),
+            image,
+            doc: owned(DOC_EXTENSIONS),
+            archive: owned(ARCHIVE_EXTENSIONS),
+        }
+    }
+}
+
+impl FileTypeMap {
+    fn categories(&self) -> [(FileType, &Vec<String>); 5] {
+        [
+            (FileType::Video, &self.video),
+            (FileType::Audio, &self.audio),
+            (FileType::Image, &self.image),
+            (FileType::Doc, &self.doc),
+            (FileType::Archive, &self.archive),
+        ]
+    }
+
+    /// Lowercase, strip leading dots and deduplicate / 转小写、去掉开头的点并去重
+    pub fn normalize(&mut self) {
+        for list in [&mut self.video, &mut self.audio, &mut self.image, &mut self.doc, &mut self.archive] {
+            let mut exts: Vec<String> = list.iter()
+                .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect();
+            exts.sort();
+            exts.dedup();
+            *list = exts;
+        }
+    }
+
+    /// An extension may belong to one category only / 每个扩展名只能属于一个类别
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen: HashMap<&str, FileType> = HashMap::new();
+        for (file_type, exts) in self.categories() {
+            for ext in exts {
+                if let Some(other) = seen.insert(ext, file_type) {
+                    if other != file_type {
+                        return Err(format!("extension {} is both {} and {}", ext, other.name(), file_type.name()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup(&self) -> HashMap<String, FileType> {
+        self.categories()
+            .into_iter()
+            .flat_map(|(file_type, exts)| exts.iter().map(move |e| (e.clone(), file_type)))
+            .collect()
+    }
+}
+
+/// Category map cache / 分类映射缓存
+pub struct FileTypeSettings {
+    map: RwLock<FileTypeMap>,
+    lookup: RwLock<HashMap<String, FileType>>,
+}
+
+impl FileTypeSettings {
+    pub fn new() -> Self {
+        let map = FileTypeMap::default();
+        Self {
+            lookup: RwLock::new(map.lookup()),
+            map: RwLock::new(map),
+        }
+    }
+
+    /// Load map from database / 从数据库加载映射
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let value: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = ?"
+        )
+        .bind(SETTING_KEY)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((json,)) = value {
+            let map: FileTypeMap = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            self.set(map)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self) -> FileTypeMap {
+        self.map.read().clone()
+    }
+
+    /// Validate and apply a new map / 校验并应用新映射
+    pub fn set(&self, mut map: FileTypeMap) -> Result<(), String> {
+        map.normalize();
+        map.validate()?;
+        *self.lookup.write() = map.lookup();
+        *self.map.write() = map;
+        Ok(())
+    }
+
+    /// Category of a listed entry / 列表条目的类别
+    pub fn classify(&self, name: &str, is_dir: bool) -> FileType {
+        if is_dir {
+            return FileType::Other;
+        }
+        match name.rsplit_once('.') {
+            Some((_, ext)) => self.lookup.read().get(&ext.to_lowercase()).copied().unwrap_or(FileType::Other),
+            None => FileType::Other,
+        }
+    }
+}
+
+impl Default for FileTypeSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let settings = FileTypeSettings::new();
+        assert_eq!(settings.classify("Movie.MKV", false), FileType::Video);
+        assert_eq!(settings.classify("song.flac", false), FileType::Audio);
+        assert_eq!(settings.classify("photo.jpeg", false), FileType::Image);
+        assert_eq!(settings.classify("report.pdf", false), FileType::Doc);
+        assert_eq!(settings.classify("backup.tar.gz", false), FileType::Archive);
+        assert_eq!(settings.classify("disk.iso", false), FileType::Archive);
+        assert_eq!(settings.classify("Makefile", false), FileType::Other);
+        assert_eq!(settings.classify("clips.mp4", true), FileType::Other);
+    }
+
+    #[test]
+    fn test_custom_map() {
+        let settings = FileTypeSettings::new();
+        let map = FileTypeMap {
+            video: vec![".MP4".to_string(), "mp4".to_string(), " ".to_string()],
+            doc: vec!["mp4".to_string()],
+            ..Default::default()
+        };
+        assert!(settings.set(map).is_err());
+        // 校验失败时保留原映射
+        assert_eq!(settings.classify("a.mkv", false), FileType::Video);
+
+        let map = FileTypeMap {
+            video: vec![".MP4".to_string(), "mp4".to_string(), " ".to_string()],
+            doc: vec!["log".to_string()],
+            ..Default::default()
+        };
+        settings.set(map).unwrap();
+        assert_eq!(settings.get().video, vec!["mp4"]);
+        assert_eq!(settings.classify("a.mkv", false), FileType::Other);
+        assert_eq!(settings.classify("server.log", false), FileType::Doc);
+    }
+}
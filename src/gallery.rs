@@ -0,0 +1,334 @@
+//! Image gallery / 相册
+//!
+//! This module handles:
+//! - Telling images apart by extension / 按扩展名识别图片
+//! - EXIF capture date and pixel size from the head of a file / 从文件开头读取 EXIF 拍摄时间与像素尺寸
+//! - Pre-sized JPEG thumbnails, signed thumbnail URLs and their disk cache / 预设尺寸的 JPEG 缩略图、签名缩略图链接及磁盘缓存
+//!
+//! Thumbnail URLs are signed by the gallery listing, so `<img>` tags can load them without
+//! repeating password or permission checks. Signatures expire on hour boundaries so the same
+//! URL is handed out for a while and browsers can cache it.
+//! 缩略图链接由相册列表签名，`<img>` 加载时无需再次校验密码与权限。签名按整点过期，
+//! 一段时间内返回相同的链接，浏览器可以缓存。
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, ImageDecoder, ImageReader, RgbImage};
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// Image extensions listed by the gallery / 相册列出的图片扩展名
+pub const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "bmp", "tif", "tiff", "heic", "heif", "avif",
+];
+
+/// Extensions a thumbnail can be rendered from / 可以生成缩略图的扩展名
+const THUMBNAIL_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
+
+/// Extensions that may carry EXIF / 可能带有 EXIF 的扩展名
+const EXIF_EXTENSIONS: &[&str] = &["jpg", "jpeg", "tif", "tiff", "heic", "heif", "png", "webp"];
+
+/// Bytes read from the start of a file to find EXIF and the image header / 读取文件开头的字节数，用于查找 EXIF 与图片头
+pub const PROBE_BYTES: u64 = 256 * 1024;
+
+/// Largest source file a thumbnail is rendered from / 生成缩略图的源文件大小上限
+pub const MAX_THUMBNAIL_SOURCE: u64 = 64 * 1024 * 1024;
+
+/// JPEG quality of thumbnails / 缩略图的 JPEG 质量
+const THUMBNAIL_QUALITY: u8 = 80;
+
+/// Site setting holding the thumbnail URL signing key / 保存缩略图链接签名密钥的站点设置
+const SECRET_SETTING: &str = "gallery_thumbnail_secret";
+
+fn extension(name: &str) -> Option<String> {
+    let (_, ext) = name.rsplit_once('.')?;
+    Some(ext.to_ascii_lowercase())
+}
+
+/// Whether a file name is an image / 文件名是否为图片
+pub fn is_image(name: &str) -> bool {
+    extension(name).is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Whether a thumbnail can be rendered from the file / 能否从该文件生成缩略图
+pub fn can_thumbnail(name: &str) -> bool {
+    extension(name).is_some_and(|ext| THUMBNAIL_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Whether the file may carry EXIF / 文件是否可能带有 EXIF
+pub fn may_have_exif(name: &str) -> bool {
+    extension(name).is_some_and(|ext| EXIF_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Pre-sized thumbnail variant / 预设尺寸的缩略图
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    /// Grid tiles / 网格缩略图
+    Small,
+    /// Timeline and large grid / 时间线与大网格
+    Medium,
+    /// Lightbox preview / 灯箱预览
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 3] = [ThumbnailSize::Small, ThumbnailSize::Medium, ThumbnailSize::Large];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "small" => Some(Self::Small),
+            "medium" => Some(Self::Medium),
+            "large" => Some(Self::Large),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+
+    /// Longest edge in pixels / 最长边像素
+    pub fn max_edge(&self) -> u32 {
+        match self {
+            Self::Small => 256,
+            Self::Medium => 720,
+            Self::Large => 1600,
+        }
+    }
+}
+
+/// What the head of an image file tells / 从图片文件开头读到的信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImageInfo {
+    /// EXIF capture time as `YYYY-MM-DDTHH:MM:SS` in the camera's local time / EXIF 拍摄时间（相机本地时间）
+    pub taken_at: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Read capture date and pixel size from the first bytes of an image / 从图片开头的字节读取拍摄时间与像素尺寸
+pub fn probe(name: &str, head: &[u8]) -> ImageInfo {
+    let taken_at = if may_have_exif(name) { capture_date(head) } else { None };
+    let (width, height) = ImageReader::new(Cursor::new(head))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok())
+        .map_or((None, None), |(w, h)| (Some(w), Some(h)));
+    ImageInfo { taken_at, width, height }
+}
+
+/// EXIF capture date, falling back to the digitized and modification dates / EXIF 拍摄时间，缺少时依次使用数字化时间与修改时间
+pub fn capture_date(head: &[u8]) -> Option<String> {
+    use exif::{In, Tag, Value};
+
+    let exif = exif::Reader::new().read_from_container(&mut Cursor::new(head)).ok()?;
+    [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime].iter().find_map(|tag| {
+        let field = exif.get_field(*tag, In::PRIMARY)?;
+        let Value::Ascii(ref values) = field.value else {
+            return None;
+        };
+        let dt = exif::DateTime::from_ascii(values.first()?).ok()?;
+        // 未设置时间的相机写入全零
+        (dt.year > 0 && dt.month > 0 && dt.day > 0).then(|| format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+        ))
+    })
+}
+
+/// Render a JPEG thumbnail no larger than the variant, honoring EXIF orientation
+/// 生成不超过指定尺寸的 JPEG 缩略图，按 EXIF 方向旋转
+pub fn render_thumbnail(data: &[u8], size: ThumbnailSize) -> Result<Vec<u8>, String> {
+    let mut decoder = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    image.apply_orientation(orientation);
+
+    let edge = size.max_edge();
+    if image.width() > edge || image.height() > edge {
+        image = image.thumbnail(edge, edge);
+    }
+
+    let rgb = flatten_on_white(&image);
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, THUMBNAIL_QUALITY)
+        .encode_image(&rgb)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+/// JPEG has no alpha, draw transparent images on white / JPEG 没有透明通道，透明图片绘制在白底上
+fn flatten_on_white(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Expiry of thumbnail URLs signed at `now`: the end of the next hour
+/// 在 `now` 签名的缩略图链接的过期时间：下一个整点之后一小时
+pub fn link_expiry(now: i64) -> i64 {
+    (now / 3600 + 2) * 3600
+}
+
+/// Thumbnail disk cache and URL signing, also signs audio cover links / 缩略图磁盘缓存与链接签名（音频封面链接也使用此签名）
+pub struct ThumbnailStore {
+    root: PathBuf,
+    secret: String,
+    /// Limits thumbnails decoded at once / 限制同时解码的缩略图数
+    render_permits: tokio::sync::Semaphore,
+}
+
+impl ThumbnailStore {
+    pub fn new(data_dir: &Path, secret: String) -> Self {
+        Self {
+            root: data_dir.join("thumbnails"),
+            secret,
+            render_permits: tokio::sync::Semaphore::new(num_cpus::get().max(1)),
+        }
+    }
+
+    /// Load the signing key from site settings, creating it on first start / 从站点设置加载签名密钥，首次启动时创建
+    pub async fn load_secret(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+        let existing: Option<String> = sqlx::query_scalar("SELECT value FROM site_settings WHERE key = ?")
+            .bind(SECRET_SETTING)
+            .fetch_optional(pool)
+            .await?;
+        if let Some(secret) = existing.filter(|s| !s.is_empty()) {
+            return Ok(secret);
+        }
+        let secret = hex::encode(rand::random::<[u8; 32]>());
+        // 多个实例同时启动时以先写入的为准
+        sqlx::query("INSERT OR IGNORE INTO site_settings (key, value, updated_at) VALUES (?, ?, ?)")
+            .bind(SECRET_SETTING)
+            .bind(&secret)
+            .bind(chrono::Utc::now().to_rfc3339())
+            .execute(pool)
+            .await?;
+        sqlx::query_scalar("SELECT value FROM site_settings WHERE key = ?")
+            .bind(SECRET_SETTING)
+            .fetch_one(pool)
+            .await
+    }
+
+    fn mac(&self, path: &str, expires: i64) -> hmac::Hmac<sha2::Sha256> {
+        use hmac::Mac;
+
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("{}:{}", path, expires).as_bytes());
+        mac
+    }
+
+    /// HMAC-SHA256 of `path:expires` / 计算 `path:expires` 的 HMAC-SHA256
+    pub fn sign(&self, path: &str, expires: i64) -> String {
+        use hmac::Mac;
+
+        hex::encode(self.mac(path, expires).finalize().into_bytes())
+    }
+
+    /// Check a signature made by [`Self::sign`] that has not expired / 校验未过期的签名
+    pub fn verify(&self, path: &str, expires: i64, sign: &str, now: i64) -> bool {
+        use hmac::Mac;
+
+        let Ok(sign) = hex::decode(sign) else {
+            return false;
+        };
+        expires >= now && self.mac(path, expires).verify_slice(&sign).is_ok()
+    }
+
+    /// Render a thumbnail on the blocking pool, at most one per CPU at a time / 在阻塞线程池中生成缩略图，同时最多每个 CPU 一个
+    pub async fn render(&self, data: Vec<u8>, size: ThumbnailSize) -> Result<Vec<u8>, String> {
+        let _permit = self.render_permits.acquire().await.map_err(|e| e.to_string())?;
+        tokio::task::spawn_blocking(move || render_thumbnail(&data, size))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    /// Cache file of a thumbnail, a changed size or modification time gives a new file
+    /// 缩略图的缓存文件，文件大小或修改时间变化后对应新的缓存文件
+    fn cache_path(&self, path: &str, file_size: u64, modified: Option<&str>, size: ThumbnailSize) -> PathBuf {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}\0{}\0{}", path, file_size, modified.unwrap_or("")).as_bytes());
+        let key = hex::encode(hasher.finalize());
+        self.root.join(&key[..2]).join(format!("{}-{}.jpg", key, size.name()))
+    }
+
+    /// Cached thumbnail / 读取缓存的缩略图
+    pub async fn get(&self, path: &str, file_size: u64, modified: Option<&str>, size: ThumbnailSize) -> Option<Vec<u8>> {
+        tokio::fs::read(self.cache_path(path, file_size, modified, size)).await.ok()
+    }
+
+    /// Save a thumbnail through a temporary file / 经临时文件保存缩略图
+    pub async fn put(&self, path: &str, file_size: u64, modified: Option<&str>, size: ThumbnailSize, data: &[u8]) -> Result<(), String> {
+        let target = self.cache_path(path, file_size, modified, size);
+        let dir = target.parent().ok_or("invalid thumbnail path")?;
+        tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+        let tmp = target.with_extension(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+        tokio::fs::write(&tmp, data).await.map_err(|e| e.to_string())?;
+        tokio::fs::rename(&tmp, &target).await.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extensions() {
+        assert!(is_image("IMG_0001.JPG"));
+        assert!(is_image("scan.tiff"));
+        assert!(!is_image("notes.txt"));
+        assert!(!is_image("jpg"));
+        assert!(can_thumbnail("a.webp"));
+        assert!(!can_thumbnail("a.heic"));
+        assert!(may_have_exif("a.heic"));
+        assert!(!may_have_exif("a.gif"));
+    }
+
+    #[test]
+    fn test_sign_and_expiry() {
+        let store = ThumbnailStore::new(Path::new("/tmp"), "k".to_string());
+        let expires = link_expiry(7200 + 10);
+        assert_eq!(expires, 4 * 3600);
+        assert_eq!(link_expiry(7200 + 3599), expires);
+        let sign = store.sign("/photos/a.jpg", expires);
+        assert!(store.verify("/photos/a.jpg", expires, &sign, 7300));
+        assert!(!store.verify("/photos/b.jpg", expires, &sign, 7300));
+        assert!(!store.verify("/photos/a.jpg", expires, &sign, expires + 1));
+    }
+
+    #[test]
+    fn test_render_and_probe() {
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(600, 300, image::Rgba([0, 0, 0, 0])));
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+
+        let info = probe("a.png", &png);
+        assert_eq!((info.width, info.height, info.taken_at), (Some(600), Some(300), None));
+
+        let thumb = render_thumbnail(&png, ThumbnailSize::Small).unwrap();
+        let decoded = image::load_from_memory(&thumb).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (256, 128));
+        // 透明像素绘制在白底上
+        assert!(decoded.to_rgb8().get_pixel(10, 10).0.iter().all(|c| *c > 240));
+    }
+}
@@ -168,30 +168,6 @@ pub fn is_private_ip(ip: &IpAddr) -> bool {
     }
 }
 
-pub fn extract_client_ip(headers: &axum::http::HeaderMap, connect_ip: Option<IpAddr>) -> Option<IpAddr> {
-    for header in ["CF-Connecting-IP", "X-Real-IP"] {
-        if let Some(val) = headers.get(header) {
-            if let Ok(ip_str) = val.to_str() {
-                if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                    return Some(ip);
-                }
-            }
-        }
-    }
-    
-    if let Some(forwarded) = headers.get("X-Forwarded-For") {
-        if let Ok(ip_str) = forwarded.to_str() {
-            if let Some(first_ip) = ip_str.split(',').next() {
-                if let Ok(ip) = first_ip.trim().parse::<IpAddr>() {
-                    return Some(ip);
-                }
-            }
-        }
-    }
-    
-    connect_ip
-}
-
 pub fn hash_ip(ip: &IpAddr) -> u64 {
     use std::hash::{Hash, Hasher};
     use std::collections::hash_map::DefaultHasher;
@@ -0,0 +1,347 @@
+//! Group defaults with per-user overrides / 用户组默认设置与用户级覆盖
+//!
+//! Besides file permissions, a user group carries defaults for its members: the root path,
+//! whether members may create shares, a download traffic quota, a download speed limit, a maximum
+//! upload size, the upload extension lists and the mounts members can see. A user inherits them
+//! from their groups and may override each one except the upload limits; an override left empty
+//! (`NULL`) falls back to the groups. For several groups the most
+//! permissive value wins: any group allowing shares allows them, an unlimited quota or speed
+//! (0) beats a limited one, otherwise the largest applies, extension lists merge as described in
+//! [`crate::upload_policy`], and visible mounts are merged (a group without a list sees every
+//! mount). Visible mounts are full mount paths, one per line.
+//! 除文件权限外，用户组还为成员提供默认设置：根路径、是否允许创建分享、下载流量配额、下载限速、单文件上传上限、上传扩展名列表以及可见的挂载点。
+//! 用户从所属用户组继承这些设置，并可逐项覆盖（上传限制除外）；覆盖为空（`NULL`）时沿用用户组的设置。属于多个用户组时取最宽松的值：
+//! 任一用户组允许分享即可分享，不限（0）优先于有限的配额或速度，否则取最大值，扩展名列表按 [`crate::upload_policy`] 中的规则合并，
+//! 可见挂载点取并集（未设置列表的用户组可见全部挂载点）。
+//! 可见挂载点为完整的挂载路径，每行一个。
+
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::SqlitePool;
+
+use crate::upload_policy::{self, UploadPolicy};
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Share of the traffic quota at which the user is warned by email / 达到流量配额的该比例时邮件提醒用户
+pub const QUOTA_WARNING_PERCENT: i64 = 90;
+
+/// Defaults stored on a group / 用户组上的默认设置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupDefaults {
+    pub root_path: Option<String>,
+    pub allow_share: bool,
+    /// Bytes, 0 for unlimited / 字节，0 表示不限
+    pub traffic_quota: i64,
+    /// Bytes per second, 0 for unlimited / 字节每秒，0 表示不限
+    pub download_speed_limit: i64,
+    /// Bytes per uploaded file, 0 for unlimited / 单个上传文件的字节数，0 表示不限
+    pub max_upload_size: i64,
+    /// Comma separated, none for any / 逗号分隔，为空表示不限
+    pub upload_allow_ext: Option<String>,
+    pub upload_deny_ext: Option<String>,
+    pub visible_mounts: Option<String>,
+}
+
+/// Per-user overrides, `None` inherits from the groups / 用户级覆盖，`None` 表示继承用户组
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserOverrides {
+    pub root_path: Option<String>,
+    pub allow_share: Option<bool>,
+    pub traffic_quota: Option<i64>,
+    pub download_speed_limit: Option<i64>,
+    pub visible_mounts: Option<String>,
+}
+
+/// Settings in effect for a user / 用户实际生效的设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemberSettings {
+    pub root_path: String,
+    pub allow_share: bool,
+    pub traffic_quota: i64,
+    pub download_speed_limit: i64,
+    pub max_upload_size: i64,
+    /// Empty for any extension / 为空表示不限扩展名
+    pub upload_allow_ext: Vec<String>,
+    pub upload_deny_ext: Vec<String>,
+    /// Empty for every mount / 为空表示全部挂载点
+    pub visible_mounts: Vec<String>,
+}
+
+impl Default for MemberSettings {
+    fn default() -> Self {
+        Self {
+            root_path: "/".to_string(),
+            allow_share: false,
+            traffic_quota: 0,
+            download_speed_limit: 0,
+            max_upload_size: 0,
+            upload_allow_ext: Vec::new(),
+            upload_deny_ext: Vec::new(),
+            visible_mounts: Vec::new(),
+        }
+    }
+}
+
+impl MemberSettings {
+    /// Whether a mount is shown to the user / 挂载点是否对用户可见
+    pub fn mount_visible(&self, mount_path: &str) -> bool {
+        self.visible_mounts.is_empty() || self.visible_mounts.iter().any(|m| is_sub_path(m, mount_path))
+    }
+
+    /// Whether `path` lies in a visible mount; with `browse`, parents of visible mounts also pass
+    /// so they can be listed
+    /// `path` 是否位于可见挂载点中；`browse` 为 true 时可见挂载点的上级目录也可通过，以便浏览
+    pub fn path_visible(&self, path: &str, browse: bool) -> bool {
+        self.mount_visible(path) || (browse && self.visible_mounts.iter().any(|m| is_sub_path(path, m)))
+    }
+
+    /// Whether `used` bytes exhaust the quota / 已用流量是否超出配额
+    pub fn quota_exceeded(&self, used: i64) -> bool {
+        self.traffic_quota > 0 && used >= self.traffic_quota
+    }
+
+    /// Whether `used` bytes reached [`QUOTA_WARNING_PERCENT`] of the quota / 已用流量是否达到配额的告警比例
+    pub fn quota_warning_due(&self, used: i64) -> bool {
+        self.traffic_quota > 0 && used as i128 * 100 >= self.traffic_quota as i128 * QUOTA_WARNING_PERCENT as i128
+    }
+
+    /// Whether a file of `size` bytes exceeds the upload limit / `size` 字节的文件是否超过上传上限
+    pub fn upload_too_large(&self, size: u64) -> bool {
+        self.max_upload_size > 0 && size > self.max_upload_size as u64
+    }
+
+    /// Upload policy of the user / 用户的上传策略
+    pub fn upload_policy(&self) -> UploadPolicy {
+        UploadPolicy {
+            allow: self.upload_allow_ext.clone(),
+            deny: self.upload_deny_ext.clone(),
+            max_size: self.max_upload_size.max(0) as u64,
+        }
+    }
+}
+
+/// Split a mount list into clean paths / 将挂载点列表拆分为规范化的路径
+pub fn parse_mounts(text: &str) -> Vec<String> {
+    let mut mounts: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(fix_and_clean_path)
+        .collect();
+    mounts.sort();
+    mounts.dedup();
+    mounts
+}
+
+/// Normalize a mount list for storage, `None` when empty / 规范化待保存的挂载点列表，为空时返回 `None`
+pub fn normalize_mounts(text: &str) -> Option<String> {
+    Some(parse_mounts(text).join("\n")).filter(|m| !m.is_empty())
+}
+
+/// Largest limit where 0 means unlimited / 取最大限制，0 表示不限
+fn widest_limit(limits: impl Iterator<Item = i64>) -> i64 {
+    limits
+        .map(|l| l.max(0))
+        .try_fold(0, |max, l| if l == 0 { None } else { Some(max.max(l)) })
+        .unwrap_or(0)
+}
+
+/// Merge the defaults of all groups, then apply the user's overrides
+/// 合并所有用户组的默认设置，再应用用户的覆盖
+pub fn resolve(groups: &[GroupDefaults], user: &UserOverrides) -> MemberSettings {
+    let group_root = groups
+        .iter()
+        .filter_map(|g| g.root_path.clone())
+        .filter(|p| !p.is_empty())
+        .max();
+    // 用户根路径为空或为 / 时使用用户组根路径
+    let root_path = user
+        .root_path
+        .clone()
+        .filter(|p| !p.is_empty() && p != "/")
+        .or(group_root)
+        .unwrap_or_else(|| "/".to_string());
+
+    let group_mounts = if groups.iter().any(|g| g.visible_mounts.as_deref().is_none_or(|m| m.trim().is_empty())) {
+        Vec::new()
+    } else {
+        parse_mounts(&groups.iter().filter_map(|g| g.visible_mounts.as_deref()).collect::<Vec<_>>().join("\n"))
+    };
+
+    let group_policies: Vec<UploadPolicy> = groups
+        .iter()
+        .map(|g| UploadPolicy::from_lists(g.upload_allow_ext.as_deref(), g.upload_deny_ext.as_deref(), 0))
+        .collect();
+
+    MemberSettings {
+        root_path,
+        allow_share: user.allow_share.unwrap_or_else(|| groups.iter().any(|g| g.allow_share)),
+        traffic_quota: user.traffic_quota.map(|q| q.max(0)).unwrap_or_else(|| widest_limit(groups.iter().map(|g| g.traffic_quota))),
+        download_speed_limit: user
+            .download_speed_limit
+            .map(|s| s.max(0))
+            .unwrap_or_else(|| widest_limit(groups.iter().map(|g| g.download_speed_limit))),
+        max_upload_size: widest_limit(groups.iter().map(|g| g.max_upload_size)),
+        upload_allow_ext: upload_policy::widest_allow(group_policies.iter().map(|p| p.allow.as_slice())),
+        upload_deny_ext: upload_policy::common_deny(group_policies.iter().map(|p| p.deny.as_slice())),
+        visible_mounts: user.visible_mounts.as_deref().map(parse_mounts).unwrap_or(group_mounts),
+    }
+}
+
+/// Deserialize a field where a missing value keeps the current setting and `null` clears it
+/// 反序列化可清除的字段：缺省表示保持不变，`null` 表示清除
+pub fn nullable<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<T>>, D::Error> {
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+type GroupRow = (Option<String>, bool, i64, i64, i64, Option<String>, Option<String>, Option<String>);
+type UserRow = (Option<String>, Option<bool>, Option<i64>, Option<i64>, Option<String>);
+
+fn group_from_row(
+    (root_path, allow_share, traffic_quota, download_speed_limit, max_upload_size, upload_allow_ext, upload_deny_ext, visible_mounts): GroupRow,
+) -> GroupDefaults {
+    GroupDefaults {
+        root_path,
+        allow_share,
+        traffic_quota,
+        download_speed_limit,
+        max_upload_size,
+        upload_allow_ext,
+        upload_deny_ext,
+        visible_mounts,
+    }
+}
+
+/// Settings of a user from their groups and overrides / 由用户组与用户覆盖得出用户的设置
+pub async fn load_user(db: &SqlitePool, user_id: &str) -> Result<MemberSettings, sqlx::Error> {
+    let groups: Vec<GroupRow> = sqlx::query_as(
+        "SELECT g.root_path, g.allow_share, g.traffic_quota, g.download_speed_limit, g.max_upload_size,
+                g.upload_allow_ext, g.upload_deny_ext, g.visible_mounts
+         FROM user_groups g
+         INNER JOIN user_group_members m ON CAST(g.id AS TEXT) = m.group_id
+         WHERE m.user_id = ?"
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    let user: Option<UserRow> = sqlx::query_as(
+        "SELECT root_path, allow_share, traffic_quota, download_speed_limit, visible_mounts FROM users WHERE id = ?"
+    )
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+
+    let groups: Vec<GroupDefaults> = groups.into_iter().map(group_from_row).collect();
+    let overrides = user
+        .map(|(root_path, allow_share, traffic_quota, download_speed_limit, visible_mounts)| UserOverrides {
+            root_path,
+            allow_share,
+            traffic_quota,
+            download_speed_limit,
+            visible_mounts,
+        })
+        .unwrap_or_default();
+    Ok(resolve(&groups, &overrides))
+}
+
+/// Settings of guests, taken from the guest group / 游客的设置，取自游客组
+pub async fn load_guest(db: &SqlitePool) -> Result<MemberSettings, sqlx::Error> {
+    let group: Option<GroupRow> = sqlx::query_as(
+        "SELECT root_path, allow_share, traffic_quota, download_speed_limit, max_upload_size,
+                upload_allow_ext, upload_deny_ext, visible_mounts
+         FROM user_groups WHERE name = '游客组'"
+    )
+    .fetch_optional(db)
+    .await?;
+    let groups: Vec<GroupDefaults> = group.into_iter().map(group_from_row).collect();
+    Ok(resolve(&groups, &UserOverrides::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(quota: i64, speed: i64, mounts: Option<&str>) -> GroupDefaults {
+        GroupDefaults {
+            root_path: Some("/".to_string()),
+            traffic_quota: quota,
+            download_speed_limit: speed,
+            visible_mounts: mounts.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_groups_merge_most_permissive() {
+        let groups = [group(100, 0, Some("/b\n/a/")), group(300, 50, Some("/a"))];
+        let settings = resolve(&groups, &UserOverrides::default());
+        assert_eq!(settings.traffic_quota, 300);
+        assert_eq!(settings.download_speed_limit, 0);
+        assert_eq!(settings.visible_mounts, vec!["/a", "/b"]);
+        assert!(!settings.allow_share);
+        assert_eq!(settings.root_path, "/");
+
+        // 未限制挂载点的用户组可见全部挂载点
+        let groups = [group(100, 10, Some("/a")), group(0, 20, None)];
+        let settings = resolve(&groups, &UserOverrides::default());
+        assert_eq!(settings.traffic_quota, 0);
+        assert_eq!(settings.download_speed_limit, 20);
+        assert!(settings.visible_mounts.is_empty());
+
+        // 上传上限同样取最宽松的值
+        let mut small = group(0, 0, None);
+        small.max_upload_size = 100;
+        let mut large = group(0, 0, None);
+        large.max_upload_size = 1000;
+        let settings = resolve(&[small.clone(), large], &UserOverrides::default());
+        assert!(!settings.upload_too_large(1000));
+        assert!(settings.upload_too_large(1001));
+        let settings = resolve(&[small, group(0, 0, None)], &UserOverrides::default());
+        assert!(!settings.upload_too_large(u64::MAX));
+
+        // 扩展名列表：任一用户组不限制即不限制，只拒绝所有用户组都拒绝的扩展名
+        let mut images = group(0, 0, None);
+        images.upload_allow_ext = Some("jpg,png".to_string());
+        images.upload_deny_ext = Some("exe,bat".to_string());
+        let mut docs = group(0, 0, None);
+        docs.upload_allow_ext = Some("pdf".to_string());
+        docs.upload_deny_ext = Some("exe".to_string());
+        let settings = resolve(&[images.clone(), docs], &UserOverrides::default());
+        assert_eq!(settings.upload_allow_ext, vec!["jpg", "pdf", "png"]);
+        assert_eq!(settings.upload_deny_ext, vec!["exe"]);
+        assert!(settings.upload_policy().check("a.bat", None).is_err());
+        let settings = resolve(&[images, group(0, 0, None)], &UserOverrides::default());
+        assert!(settings.upload_allow_ext.is_empty() && settings.upload_deny_ext.is_empty());
+    }
+
+    #[test]
+    fn test_user_overrides() {
+        let mut shared = group(100, 10, Some("/a"));
+        shared.allow_share = true;
+        shared.root_path = Some("/home".to_string());
+        let user = UserOverrides {
+            root_path: Some("/".to_string()),
+            allow_share: Some(false),
+            traffic_quota: Some(0),
+            visible_mounts: Some("/b".to_string()),
+            ..Default::default()
+        };
+        let settings = resolve(&[shared], &user);
+        assert_eq!(settings.root_path, "/home");
+        assert!(!settings.allow_share);
+        assert_eq!(settings.traffic_quota, 0);
+        assert_eq!(settings.download_speed_limit, 10);
+        assert_eq!(settings.visible_mounts, vec!["/b"]);
+
+        assert!(settings.mount_visible("/b/sub"));
+        assert!(!settings.mount_visible("/bb"));
+        assert!(!settings.mount_visible("/"));
+        assert!(settings.path_visible("/", true));
+        assert!(!settings.path_visible("/", false));
+        assert!(!settings.quota_exceeded(1 << 40));
+        assert!(!settings.quota_warning_due(1 << 40));
+
+        let settings = resolve(&[group(100, 0, None)], &UserOverrides::default());
+        assert!(!settings.quota_warning_due(89));
+        assert!(settings.quota_warning_due(90));
+    }
+}
@@ -0,0 +1,183 @@
+//! Guest access policy / 游客访问策略
+//!
+//! Decides what an anonymous visitor may do on top of the guest group's permissions: whether the
+//! file API answers at all, which paths (relative to the guest root) can be browsed, whether files
+//! can be downloaded and at what speed, whether search is available, and whether WebDAV accepts
+//! anonymous reads.
+//! 在游客组权限之上决定匿名访问者可执行的操作：文件接口是否开放、可浏览的路径（相对游客根路径）、
+//! 是否允许下载及限速、是否允许搜索、WebDAV 是否接受匿名只读访问。
+
+use std::sync::Arc;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::download::BandwidthLimiter;
+use crate::utils::fix_and_clean_path;
+
+/// Guest access policy / 游客访问策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuestPolicy {
+    /// Anonymous reads through the file API / 是否允许匿名访问文件接口
+    pub allow_browse: bool,
+    /// Browsable paths relative to the guest root, empty means everything
+    /// 可浏览路径（相对游客根路径），为空表示不限制
+    pub browse_paths: Vec<String>,
+    pub allow_download: bool,
+    /// Speed cap shared by all guest downloads in bytes/sec, 0 means unlimited
+    /// 所有游客下载共享的限速（字节/秒），0 表示不限速
+    pub download_speed_limit: i64,
+    pub allow_search: bool,
+    /// Anonymous read-only WebDAV access / 是否允许匿名只读访问 WebDAV
+    pub allow_webdav: bool,
+}
+
+impl Default for GuestPolicy {
+    fn default() -> Self {
+        Self {
+            allow_browse: true,
+            browse_paths: Vec::new(),
+            allow_download: true,
+            download_speed_limit: 0,
+            allow_search: true,
+            allow_webdav: false,
+        }
+    }
+}
+
+impl GuestPolicy {
+    /// Clean and deduplicate browse paths / 清理并去重可浏览路径
+    pub fn normalize(&mut self) {
+        let mut paths: Vec<String> = self.browse_paths.iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(fix_and_clean_path)
+            .collect();
+        paths.sort();
+        paths.dedup();
+        self.browse_paths = paths;
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.download_speed_limit < 0 {
+            return Err("download_speed_limit must not be negative".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn path_browsable(&self, path: &str) -> bool {
+        path_browsable(&self.browse_paths, path)
+    }
+}
+
+/// Whether a path lies inside one of the allowed paths, or is a parent directory leading to one
+/// 路径位于某个允许路径之内，或是通往允许路径的上级目录
+pub fn path_browsable(allowed: &[String], path: &str) -> bool {
+    if allowed.is_empty() {
+        return true;
+    }
+    let path = fix_and_clean_path(path);
+    allowed.iter().any(|p| {
+        p == "/"
+            || path == "/"
+            || *p == path
+            || path.starts_with(&format!("{}/", p))
+            || p.starts_with(&format!("{}/", path))
+    })
+}
+
+/// Guest policy cache / 游客策略缓存
+pub struct GuestSettings {
+    policy: RwLock<GuestPolicy>,
+    limiter: Arc<BandwidthLimiter>,
+}
+
+impl GuestSettings {
+    pub fn new() -> Self {
+        Self {
+            policy: RwLock::new(GuestPolicy::default()),
+            limiter: Arc::new(BandwidthLimiter::new(0)),
+        }
+    }
+
+    /// Load policy from database / 从数据库加载策略
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let value: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'guest_policy'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((json,)) = value {
+            let policy: GuestPolicy = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            self.set(policy)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self) -> GuestPolicy {
+        self.policy.read().clone()
+    }
+
+    /// Validate and apply a new policy / 校验并应用新策略
+    pub fn set(&self, mut policy: GuestPolicy) -> Result<(), String> {
+        policy.normalize();
+        policy.validate()?;
+        self.limiter.set_rate(policy.download_speed_limit);
+        *self.policy.write() = policy;
+        Ok(())
+    }
+
+    /// Limiter shared by all proxied guest downloads / 所有游客中转下载共享的限速器
+    pub fn get_limiter(&self) -> Arc<BandwidthLimiter> {
+        self.limiter.clone()
+    }
+}
+
+impl Default for GuestSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_browsable() {
+        let mut policy = GuestPolicy {
+            browse_paths: vec!["public/".to_string(), " ".to_string(), "/docs/manual".to_string()],
+            ..Default::default()
+        };
+        policy.normalize();
+        assert_eq!(policy.browse_paths, vec!["/docs/manual", "/public"]);
+
+        assert!(policy.path_browsable("/"));
+        assert!(policy.path_browsable("/public"));
+        assert!(policy.path_browsable("/public/a/b.txt"));
+        assert!(policy.path_browsable("/docs"));
+        assert!(policy.path_browsable("/docs/manual/index.md"));
+        assert!(!policy.path_browsable("/docs/private"));
+        assert!(!policy.path_browsable("/publicity"));
+        assert!(!policy.path_browsable("/private"));
+
+        assert!(GuestPolicy::default().path_browsable("/anything"));
+    }
+
+    #[test]
+    fn test_settings() {
+        let settings = GuestSettings::new();
+        let policy: GuestPolicy = serde_json::from_str(r#"{"allow_download":false,"download_speed_limit":1024}"#).unwrap();
+        assert!(policy.allow_browse);
+        settings.set(policy).unwrap();
+        assert!(!settings.get().allow_download);
+        assert_eq!(settings.get_limiter().get_rate(), 1024);
+
+        let invalid = GuestPolicy { download_speed_limit: -1, ..Default::default() };
+        assert!(settings.set(invalid).is_err());
+        assert_eq!(settings.get().download_speed_limit, 1024);
+    }
+}
@@ -0,0 +1,240 @@
+//! Hidden-file rules / 隐藏文件规则
+//!
+//! The `hide` field of a meta holds one rule per line. A rule is a regular expression by default
+//! (a bare pattern that is not a valid regex is read as a glob), or names its syntax with a
+//! `regex:` or `glob:` prefix. Rules apply to the meta's directory; in its subfolders they follow
+//! the meta's "apply to subfolders" switch unless the rule carries its own `sub:` or `nosub:`
+//! prefix. Prefixes combine, e.g. `sub:glob:*.tmp`. Globs match the whole name and support `*`,
+//! `**`, `?`, `[abc]`, `[!abc]` and `{a,b}`; regexes match anywhere in the name.
+//! 元信息的 `hide` 字段每行一条规则。规则默认为正则表达式（不是合法正则的普通模式按通配符处理），也可用 `regex:` 或
+//! `glob:` 前缀指定语法。规则作用于元信息所在目录；在子目录中沿用元信息的“应用到子文件夹”开关，除非规则自带
+//! `sub:` 或 `nosub:` 前缀。前缀可组合，例如 `sub:glob:*.tmp`。通配符匹配完整文件名，支持 `*`、`**`、`?`、
+//! `[abc]`、`[!abc]` 和 `{a,b}`；正则表达式匹配文件名的任意部分。
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Rule syntax / 规则语法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSyntax {
+    Regex,
+    Glob,
+}
+
+/// One parsed rule / 解析后的单条规则
+#[derive(Debug, Clone)]
+pub struct HideRule {
+    /// The rule line as written / 原始规则行
+    pub rule: String,
+    pub syntax: RuleSyntax,
+    /// Own subfolder switch, `None` follows the meta / 规则自身的子文件夹开关，`None` 表示沿用元信息
+    pub sub: Option<bool>,
+    matcher: Regex,
+}
+
+impl HideRule {
+    /// Parse one line, `Ok(None)` for blank lines / 解析一行规则，空行返回 `Ok(None)`
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let rule = line.trim();
+        let mut pattern = rule;
+        let mut syntax = None;
+        let mut sub = None;
+        loop {
+            if let Some(rest) = pattern.strip_prefix("regex:").filter(|_| syntax.is_none()) {
+                syntax = Some(RuleSyntax::Regex);
+                pattern = rest;
+            } else if let Some(rest) = pattern.strip_prefix("glob:").filter(|_| syntax.is_none()) {
+                syntax = Some(RuleSyntax::Glob);
+                pattern = rest;
+            } else if let Some(rest) = pattern.strip_prefix("sub:").filter(|_| sub.is_none()) {
+                sub = Some(true);
+                pattern = rest;
+            } else if let Some(rest) = pattern.strip_prefix("nosub:").filter(|_| sub.is_none()) {
+                sub = Some(false);
+                pattern = rest;
+            } else {
+                break;
+            }
+        }
+        if pattern.is_empty() {
+            return if rule.is_empty() { Ok(None) } else { Err("规则为空".to_string()) };
+        }
+
+        let (syntax, matcher) = match syntax {
+            Some(RuleSyntax::Regex) => (RuleSyntax::Regex, Regex::new(pattern).map_err(|e| e.to_string())?),
+            Some(RuleSyntax::Glob) => (RuleSyntax::Glob, Regex::new(&glob_to_regex(pattern)).map_err(|e| e.to_string())?),
+            // 兼容旧规则：默认按正则处理，不合法时按通配符处理
+            None => match Regex::new(pattern) {
+                Ok(re) => (RuleSyntax::Regex, re),
+                Err(e) => match Regex::new(&glob_to_regex(pattern)) {
+                    Ok(re) => (RuleSyntax::Glob, re),
+                    Err(_) => return Err(e.to_string()),
+                },
+            },
+        };
+        Ok(Some(Self { rule: rule.to_string(), syntax, sub, matcher }))
+    }
+
+    /// Whether the rule matches a file name / 规则是否匹配文件名
+    pub fn matches(&self, name: &str) -> bool {
+        self.matcher.is_match(name)
+    }
+
+    /// Whether the rule applies in a subfolder of the meta's directory
+    /// 规则是否应用到元信息目录的子文件夹
+    pub fn applies_to_subfolders(&self, h_sub: bool) -> bool {
+        self.sub.unwrap_or(h_sub)
+    }
+}
+
+/// Rules in effect for one directory / 某个目录生效的规则
+#[derive(Debug, Clone, Default)]
+pub struct HideRules {
+    rules: Vec<HideRule>,
+}
+
+impl HideRules {
+    /// Parse every line, skipping invalid rules / 解析所有规则行，跳过不合法的规则
+    pub fn parse(text: &str) -> Self {
+        Self { rules: text.lines().filter_map(|l| HideRule::parse(l).ok().flatten()).collect() }
+    }
+
+    /// Rules of a meta at `meta_path` that apply to the files directly in `dir`
+    /// 位于 `meta_path` 的元信息中应用到 `dir` 目录下直接文件的规则
+    pub fn at(meta_path: &str, text: &str, h_sub: bool, dir: &str) -> Self {
+        match scope(meta_path, dir) {
+            Some(in_sub) => {
+                let mut rules = Self::parse(text);
+                rules.rules.retain(|r| !in_sub || r.applies_to_subfolders(h_sub));
+                rules
+            }
+            None => Self::default(),
+        }
+    }
+
+    /// The first rule hiding `name` / 隐藏 `name` 的第一条规则
+    pub fn matching(&self, name: &str) -> Option<&HideRule> {
+        self.rules.iter().find(|r| r.matches(name))
+    }
+
+    /// Like [`HideRules::at`] on rules already parsed, for checking many directories
+    /// 与 [`HideRules::at`] 相同，但作用于已解析的规则，用于检查多个目录
+    pub fn hides_at(&self, meta_path: &str, h_sub: bool, dir: &str, name: &str) -> bool {
+        match scope(meta_path, dir) {
+            Some(in_sub) => self.rules.iter().any(|r| (!in_sub || r.applies_to_subfolders(h_sub)) && r.matches(name)),
+            None => false,
+        }
+    }
+
+    /// Whether `name` is hidden / `name` 是否被隐藏
+    pub fn hides(&self, name: &str) -> bool {
+        self.matching(name).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Whether `dir` is in a subfolder of `meta_path`, `None` when outside it
+/// `dir` 是否位于 `meta_path` 的子文件夹中，不在其范围内时返回 `None`
+fn scope(meta_path: &str, dir: &str) -> Option<bool> {
+    let meta_path = fix_and_clean_path(meta_path);
+    let dir = fix_and_clean_path(dir);
+    if dir == meta_path {
+        Some(false)
+    } else if is_sub_path(&meta_path, &dir) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Translate a glob into an anchored regex / 将通配符转换为完整匹配的正则表达式
+pub fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut re = String::from("^");
+    let mut braces = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                re.push_str(".*");
+                i += 1;
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            // 字符集 [..]，[!..] 取反；没有闭合的 [ 按字面处理
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']').filter(|&len| len > 0) {
+                Some(len) => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let (negate, body) = match class.strip_prefix('!') {
+                        Some(body) => ("^", body),
+                        None => ("", class.as_str()),
+                    };
+                    re.push_str(&format!("[{}{}]", negate, body.replace('\\', "\\\\").replace('[', "\\[")));
+                    i += len + 1;
+                }
+                None => re.push_str("\\["),
+            },
+            '{' => {
+                braces += 1;
+                re.push_str("(?:");
+            }
+            ',' if braces > 0 => re.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                re.push(')');
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    re.push('$');
+    re
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_syntaxes() {
+        let rules = HideRules::parse("^\\.\nglob:*.{tmp,bak}\n*.log\nregex:[\nglob:file[!0-9].txt");
+        assert!(rules.hides(".env"));
+        assert!(rules.hides("a.tmp"));
+        assert!(rules.hides("b.bak"));
+        assert!(!rules.hides("a.tmp.txt"));
+        // 不合法的正则按通配符处理
+        assert!(rules.hides("x.log"));
+        assert_eq!(rules.matching("x.log").map(|r| r.syntax), Some(RuleSyntax::Glob));
+        assert!(rules.hides("filea.txt"));
+        assert!(!rules.hides("file1.txt"));
+        assert!(HideRule::parse("regex:[").is_err());
+        assert!(HideRule::parse("  ").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rules_at_subfolders() {
+        let text = "sub:glob:*.tmp\nnosub:glob:*.bak\nglob:*.log";
+        let here = HideRules::at("/a", text, false, "/a/");
+        assert!(here.hides("x.tmp") && here.hides("x.bak") && here.hides("x.log"));
+
+        let below = HideRules::at("/a", text, false, "/a/b/c");
+        assert!(below.hides("x.tmp"));
+        assert!(!below.hides("x.bak"));
+        assert!(!below.hides("x.log"));
+
+        let below = HideRules::at("/a", text, true, "/a/b");
+        assert!(below.hides("x.tmp") && !below.hides("x.bak") && below.hides("x.log"));
+
+        assert!(HideRules::at("/a", text, true, "/ab").is_empty());
+        let parsed = HideRules::parse(text);
+        assert!(parsed.hides_at("/a", false, "/a/b", "x.tmp"));
+        assert!(!parsed.hides_at("/a", false, "/a/b", "x.log"));
+        assert!(!parsed.hides_at("/a", true, "/", "x.log"));
+    }
+}
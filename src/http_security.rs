@@ -0,0 +1,311 @@
+//! CORS and security headers / 跨域与安全响应头
+//!
+//! This module handles:
+//! - CORS preflight answers and `Access-Control-*` response headers / 跨域预检与响应头
+//! - Security headers (CSP, X-Frame-Options, HSTS, nosniff, Referrer-Policy) / 安全响应头
+//!
+//! Settings are stored as JSON in `site_settings` (`http_security`) and apply to the next
+//! request after saving. The defaults keep the previous behaviour: any origin, method and
+//! header is allowed, and only `X-Content-Type-Options: nosniff` is added.
+//! 设置以 JSON 保存在 `site_settings`（`http_security`），保存后立即生效。
+//! 默认值与原行为一致：允许任意来源、方法和请求头，仅额外添加 nosniff。
+
+use std::sync::Arc;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// CORS and security header settings / 跨域与安全头设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSecurityConfig {
+    /// Allowed origins such as `https://a.example.com`, `*` = any / 允许的来源，`*` 表示任意
+    pub cors_allowed_origins: Vec<String>,
+    /// Allowed methods, `*` = any / 允许的方法
+    pub cors_allowed_methods: Vec<String>,
+    /// Allowed request headers, `*` = any / 允许的请求头
+    pub cors_allowed_headers: Vec<String>,
+    /// Allow cookies on cross-origin requests / 跨域请求携带 Cookie
+    pub cors_allow_credentials: bool,
+    /// Preflight cache seconds, 0 = browser default / 预检缓存秒数，0 使用浏览器默认值
+    pub cors_max_age_secs: u64,
+    /// Content-Security-Policy, empty = not sent / 内容安全策略，为空不发送
+    pub content_security_policy: String,
+    /// X-Frame-Options: `DENY`, `SAMEORIGIN` or empty / 为空不发送
+    pub frame_options: String,
+    /// Strict-Transport-Security max-age, 0 = not sent / HSTS 有效期，0 不发送
+    pub hsts_max_age_secs: u64,
+    pub hsts_include_subdomains: bool,
+    /// X-Content-Type-Options: nosniff
+    pub content_type_nosniff: bool,
+    /// Referrer-Policy, empty = not sent / 为空不发送
+    pub referrer_policy: String,
+}
+
+impl Default for HttpSecurityConfig {
+    fn default() -> Self {
+        Self {
+            cors_allowed_origins: vec!["*".to_string()],
+            cors_allowed_methods: vec!["*".to_string()],
+            cors_allowed_headers: vec!["*".to_string()],
+            cors_allow_credentials: false,
+            cors_max_age_secs: 0,
+            content_security_policy: String::new(),
+            frame_options: String::new(),
+            hsts_max_age_secs: 0,
+            hsts_include_subdomains: false,
+            content_type_nosniff: true,
+            referrer_policy: String::new(),
+        }
+    }
+}
+
+fn is_any(list: &[String]) -> bool {
+    list.iter().any(|v| v.trim() == "*")
+}
+
+impl HttpSecurityConfig {
+    /// Check values before they are saved / 保存前校验
+    pub fn validate(&self) -> Result<(), String> {
+        for origin in &self.cors_allowed_origins {
+            let origin = origin.trim();
+            if origin != "*"
+                && (!(origin.starts_with("http://") || origin.starts_with("https://"))
+                    || origin.ends_with('/')
+                    || HeaderValue::from_str(origin).is_err())
+            {
+                return Err(format!("invalid origin '{}', expected scheme://host[:port]", origin));
+            }
+        }
+        for method in &self.cors_allowed_methods {
+            if method.trim() != "*" && Method::from_bytes(method.trim().as_bytes()).is_err() {
+                return Err(format!("invalid method '{}'", method));
+            }
+        }
+        for name in &self.cors_allowed_headers {
+            if name.trim() != "*" && HeaderName::from_bytes(name.trim().as_bytes()).is_err() {
+                return Err(format!("invalid header name '{}'", name));
+            }
+        }
+        // 任意来源携带凭证等于允许所有网站以用户身份调用接口
+        if self.cors_allow_credentials && is_any(&self.cors_allowed_origins) {
+            return Err("cors_allow_credentials requires explicit cors_allowed_origins, not *".to_string());
+        }
+        if !matches!(self.frame_options.trim().to_ascii_uppercase().as_str(), "" | "DENY" | "SAMEORIGIN") {
+            return Err("frame_options must be DENY, SAMEORIGIN or empty".to_string());
+        }
+        for (name, value) in [("content_security_policy", &self.content_security_policy), ("referrer_policy", &self.referrer_policy)] {
+            if HeaderValue::from_str(value.trim()).is_err() {
+                return Err(format!("{} contains invalid characters", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Value for `Access-Control-Allow-Origin`, or None if the origin is not allowed
+    /// 返回允许的来源头，不允许时返回 None
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        if is_any(&self.cors_allowed_origins) {
+            // 携带凭证时不能使用 *（配置校验已拒绝该组合），也不回显任意来源
+            return (!self.cors_allow_credentials).then(|| HeaderValue::from_static("*"));
+        }
+        let origin_str = origin.to_str().ok()?;
+        self.cors_allowed_origins.iter()
+            .any(|o| o.trim().eq_ignore_ascii_case(origin_str))
+            .then(|| origin.clone())
+    }
+
+    /// Value of an allow-list header; `*` mirrors what the preflight asked for
+    /// 生成允许列表头，`*` 时回显预检请求的内容
+    fn allow_list(list: &[String], requested: Option<&HeaderValue>) -> Option<HeaderValue> {
+        if is_any(list) {
+            return requested.cloned();
+        }
+        let joined = list.iter().map(|v| v.trim()).filter(|v| !v.is_empty()).collect::<Vec<_>>().join(", ");
+        HeaderValue::from_str(&joined).ok().filter(|_| !joined.is_empty())
+    }
+
+    /// Add the configured security headers, keeping ones set by handlers / 添加安全头，不覆盖处理函数已设置的值
+    fn apply_security_headers(&self, headers: &mut HeaderMap) {
+        let mut set = |name: HeaderName, value: &str| {
+            if value.is_empty() || headers.contains_key(&name) {
+                return;
+            }
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.insert(name, value);
+            }
+        };
+        set(header::CONTENT_SECURITY_POLICY, self.content_security_policy.trim());
+        set(header::X_FRAME_OPTIONS, &self.frame_options.trim().to_ascii_uppercase());
+        set(header::REFERRER_POLICY, self.referrer_policy.trim());
+        if self.content_type_nosniff {
+            set(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+        }
+        if self.hsts_max_age_secs > 0 {
+            let mut hsts = format!("max-age={}", self.hsts_max_age_secs);
+            if self.hsts_include_subdomains {
+                hsts.push_str("; includeSubDomains");
+            }
+            set(header::STRICT_TRANSPORT_SECURITY, &hsts);
+        }
+    }
+}
+
+/// Settings cache shared with the middleware / 设置缓存（供中间件读取）
+pub struct HttpSecurity {
+    config: RwLock<Arc<HttpSecurityConfig>>,
+}
+
+impl HttpSecurity {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(Arc::new(HttpSecurityConfig::default())),
+        }
+    }
+
+    /// Load settings from database / 从数据库加载设置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let value: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'http_security'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((json,)) = value {
+            let config: HttpSecurityConfig = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            self.set(config)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self) -> Arc<HttpSecurityConfig> {
+        self.config.read().clone()
+    }
+
+    /// Validate and apply new settings / 校验并应用新设置
+    pub fn set(&self, config: HttpSecurityConfig) -> Result<(), String> {
+        config.validate()?;
+        *self.config.write() = Arc::new(config);
+        Ok(())
+    }
+}
+
+impl Default for HttpSecurity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Answer CORS preflights and add CORS / security headers to responses
+/// 处理跨域预检请求，并为响应添加跨域与安全头
+pub async fn http_security_middleware(State(security): State<Arc<HttpSecurity>>, req: Request, next: Next) -> Response {
+    let config = security.get();
+    let origin = req.headers().get(header::ORIGIN).cloned();
+    let allowed_origin = origin.as_ref().and_then(|o| config.allow_origin(o));
+    let is_preflight = req.method() == Method::OPTIONS
+        && origin.is_some()
+        && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+    let mut response = if is_preflight {
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        if allowed_origin.is_some() {
+            let headers = response.headers_mut();
+            let requested_method = req.headers().get(header::ACCESS_CONTROL_REQUEST_METHOD);
+            let requested_headers = req.headers().get(header::ACCESS_CONTROL_REQUEST_HEADERS);
+            if let Some(v) = HttpSecurityConfig::allow_list(&config.cors_allowed_methods, requested_method) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, v);
+            }
+            if let Some(v) = HttpSecurityConfig::allow_list(&config.cors_allowed_headers, requested_headers) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, v);
+            }
+            if config.cors_max_age_secs > 0 {
+                headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(config.cors_max_age_secs));
+            }
+        }
+        response
+    } else {
+        next.run(req).await
+    };
+
+    let headers = response.headers_mut();
+    if let Some(allowed_origin) = allowed_origin {
+        let mirrored = allowed_origin != "*";
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin);
+        if config.cors_allow_credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        } else if !is_preflight {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static("*"));
+        }
+        if mirrored {
+            headers.append(header::VARY, HeaderValue::from_static("origin"));
+        }
+    }
+    config.apply_security_headers(headers);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(HttpSecurityConfig::default().validate().is_ok());
+        let mut config = HttpSecurityConfig {
+            cors_allowed_origins: vec!["https://a.example.com".to_string()],
+            cors_allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            frame_options: "sameorigin".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        config.cors_allowed_origins = vec!["a.example.com".to_string()];
+        assert!(config.validate().is_err());
+        config.cors_allowed_origins = vec!["https://a.example.com/".to_string()];
+        assert!(config.validate().is_err());
+        config.cors_allowed_origins = vec!["*".to_string()];
+        config.cors_allow_credentials = true;
+        assert!(config.validate().is_err());
+        config.cors_allow_credentials = false;
+        config.frame_options = "ALLOW".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_allow_origin() {
+        let origin = HeaderValue::from_static("https://a.example.com");
+        let mut config = HttpSecurityConfig::default();
+        assert_eq!(config.allow_origin(&origin).unwrap(), "*");
+        config.cors_allow_credentials = true;
+        assert!(config.allow_origin(&origin).is_none());
+        config.cors_allowed_origins = vec!["https://b.example.com".to_string()];
+        assert!(config.allow_origin(&origin).is_none());
+        config.cors_allowed_origins.push("https://a.example.com".to_string());
+        assert_eq!(config.allow_origin(&origin).unwrap(), "https://a.example.com");
+
+        let requested = HeaderValue::from_static("x-custom");
+        assert_eq!(HttpSecurityConfig::allow_list(&["*".to_string()], Some(&requested)).unwrap(), "x-custom");
+        assert_eq!(HttpSecurityConfig::allow_list(&["GET".to_string(), "PUT".to_string()], None).unwrap(), "GET, PUT");
+    }
+
+    #[test]
+    fn test_security_headers() {
+        let config = HttpSecurityConfig {
+            frame_options: "deny".to_string(),
+            hsts_max_age_secs: 31536000,
+            hsts_include_subdomains: true,
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("custom"));
+        config.apply_security_headers(&mut headers);
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[header::STRICT_TRANSPORT_SECURITY], "max-age=31536000; includeSubDomains");
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "custom");
+        assert!(!headers.contains_key(header::CONTENT_SECURITY_POLICY));
+    }
+}
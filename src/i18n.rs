@@ -0,0 +1,356 @@
+//! API error codes and localized messages / API 错误码与本地化消息
+//!
+//! Handlers keep returning their (mostly Chinese) messages; `i18n_middleware` looks the message
+//! up in the catalog, adds a stable `error_code` to error responses and rewrites the message in
+//! the language negotiated from `Accept-Language`. Messages built as `"<text>: <detail>"` are
+//! matched on the text and keep the detail as-is. Unknown messages pass through unchanged.
+//! 处理器仍返回原有消息；`i18n_middleware` 在消息目录中查找该消息，为错误响应添加稳定的
+//! `error_code`，并按 `Accept-Language` 协商的语言改写消息。`"<文本>: <详情>"` 形式的消息按文本匹配，
+//! 详情保持不变；目录中没有的消息原样返回。
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+
+/// Largest JSON body that is localized / 参与本地化的最大 JSON 响应体
+const MAX_LOCALIZE_BODY: u64 = 64 * 1024;
+
+/// Response language / 响应语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// Simplified Chinese, the language the messages are written in / 简体中文（消息原文）
+    #[default]
+    ZhCn,
+    En,
+}
+
+impl Locale {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next().unwrap_or("").trim().to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" => Some(Locale::ZhCn),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// Pick the supported language with the highest `q` from an `Accept-Language` value
+    /// 从 `Accept-Language` 中选出权重最高的受支持语言
+    pub fn negotiate(accept_language: &str) -> Self {
+        let mut best: Option<(Locale, f32)> = None;
+        for item in accept_language.split(',') {
+            let mut parts = item.split(';');
+            let Some(locale) = parts.next().and_then(Locale::from_tag) else {
+                continue;
+            };
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+                best = Some((locale, q));
+            }
+        }
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers.get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(Locale::negotiate)
+            .unwrap_or_default()
+    }
+}
+
+/// Catalog entry / 消息目录条目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    /// Stable error code / 稳定错误码
+    pub code: &'static str,
+    pub zh: &'static str,
+    pub en: &'static str,
+}
+
+impl Message {
+    pub fn text(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::ZhCn => self.zh,
+            Locale::En => self.en,
+        }
+    }
+}
+
+macro_rules! catalog {
+    ($($code:literal => $zh:literal, $en:literal;)*) => {
+        /// Message catalog / 消息目录
+        pub const CATALOG: &[Message] = &[$(Message { code: $code, zh: $zh, en: $en }),*];
+    };
+}
+
+catalog! {
+    // Common / 通用
+    "internal_error" => "服务器错误", "Internal server error";
+    "not_logged_in" => "未登录", "Not logged in";
+    "admin_required" => "需要管理员权限", "Administrator permission required";
+    "permission_denied" => "没有权限", "Permission denied";
+    "session_expired" => "会话已过期", "Session expired";
+    "session_invalid" => "会话无效", "Invalid session";
+    "save_failed" => "保存失败", "Save failed";
+    "invalid_config" => "配置格式错误", "Invalid configuration format";
+    "config_not_object" => "配置必须是JSON对象", "Configuration must be a JSON object";
+    "invalid_url_scheme" => "URL必须以http://或https://开头", "URL must start with http:// or https://";
+    "record_not_found" => "记录不存在", "Record not found";
+    "request_failed" => "请求失败", "Request failed";
+    // Auth / 认证
+    "invalid_credentials" => "账号或密码错误", "Invalid username or password";
+    "captcha_invalid" => "验证码错误", "Invalid captcha";
+    "captcha_invalid_or_expired" => "验证码错误或已过期", "Invalid or expired verification code";
+    "captcha_not_found" => "验证码不存在或已过期", "Verification code not found or expired";
+    "captcha_used" => "验证码已使用", "Verification code already used";
+    "verification_failed" => "验证失败", "Verification failed";
+    "totp_required" => "请输入两步验证码", "Two-factor code required";
+    "totp_not_setup" => "请先设置2FA", "Set up two-factor authentication first";
+    "totp_config_error" => "2FA配置错误", "Two-factor configuration error";
+    "totp_create_failed" => "创建TOTP失败", "Failed to create TOTP";
+    "totp_disable_failed" => "禁用2FA失败", "Failed to disable two-factor authentication";
+    "totp_secret_invalid" => "密钥解析失败", "Failed to parse secret";
+    "totp_secret_failed" => "生成密钥失败", "Failed to generate secret";
+    "qrcode_failed" => "生成二维码失败", "Failed to generate QR code";
+    "totp_setup_required" => "所属用户组要求启用两步验证", "Your group requires two-factor authentication";
+    "totp_disable_denied" => "所属用户组要求两步验证，不能关闭", "Your group requires two-factor authentication, it cannot be disabled";
+    "registration_closed" => "注册功能已关闭", "Registration is closed";
+    "username_taken" => "用户名已存在", "Username already exists";
+    "email_taken" => "邮箱已被注册", "Email already registered";
+    "email_in_use" => "该邮箱已被其他账号使用", "Email is used by another account";
+    "email_in_use_as_phone" => "该邮箱已被其他账号作为手机号使用", "Email is used as a phone number by another account";
+    "phone_in_use" => "该手机号已被其他账号使用", "Phone number is used by another account";
+    "phone_in_use_as_email" => "该手机号已被其他账号作为邮箱使用", "Phone number is used as an email by another account";
+    "email_required" => "请提供邮箱地址", "Email address required";
+    "phone_required" => "请提供手机号码", "Phone number required";
+    "phone_password_required" => "请先填写手机号和密码", "Enter phone number and password first";
+    "email_code_disabled" => "邮件验证码已禁用", "Email verification codes are disabled";
+    "email_notify_disabled" => "邮箱通知未启用", "Email notifications are not enabled";
+    "sms_notify_disabled" => "短信通知未启用", "SMS notifications are not enabled";
+    "send_code_failed" => "发送验证码失败", "Failed to send verification code";
+    "password_update_failed" => "更新密码失败", "Failed to update password";
+    // Users and groups / 用户与用户组
+    "user_not_found" => "用户不存在", "User not found";
+    "user_create_failed" => "创建用户失败", "Failed to create user";
+    "group_not_found" => "用户组不存在", "User group not found";
+    "group_fetch_failed" => "获取用户组失败", "Failed to get user groups";
+    // Files / 文件
+    "guest_disabled" => "guest_disabled", "guest_disabled";
+    "password_required" => "password is incorrect or you have no permission", "password is incorrect or you have no permission";
+    "path_not_found" => "路径不存在", "Path not found";
+    "path_invalid" => "路径不合法", "Invalid path";
+    "path_forbidden" => "路径越权", "Path outside of your root";
+    "path_required" => "路径不能为空", "Path is required";
+    "file_not_found" => "文件不存在", "File not found";
+    "source_not_found" => "源文件不存在", "Source file not found";
+    "read_denied" => "没有读取权限", "No permission to read files";
+    "download_denied" => "没有下载文件的权限", "No permission to download files";
+    "download_file_denied" => "无权下载此文件", "No permission to download this file";
+    "direct_link_denied" => "没有创建直链的权限", "No permission to create direct links";
+    "move_denied" => "没有移动文件的权限", "No permission to move files";
+    "copy_denied" => "没有复制文件的权限", "No permission to copy files";
+    "rename_denied" => "没有重命名文件的权限", "No permission to rename files";
+    "delete_denied" => "没有删除文件的权限", "No permission to delete files";
+    "extract_denied" => "没有解压缩权限", "No permission to extract archives";
+    "guest_path_denied" => "游客无权访问该路径", "Guests cannot access this path";
+    "guest_download_denied" => "游客不允许下载该文件", "Guests cannot download this file";
+    "guest_direct_link_denied" => "游客不允许创建该文件的直链", "Guests cannot create a direct link for this file";
+    "guest_search_denied" => "游客不允许搜索", "Guests cannot search";
+    "driver_error" => "存储驱动故障", "Storage driver error";
+    "driver_error_contact_admin" => "存储驱动故障，请联系管理员", "Storage driver error, please contact the administrator";
+    "driver_not_found" => "驱动不存在", "Driver not found";
+    "driver_not_loaded" => "驱动未找到", "Driver not loaded";
+    "source_driver_not_found" => "源存储驱动不存在", "Source storage driver not found";
+    "target_driver_not_found" => "目标存储驱动不存在", "Target storage driver not found";
+    "mount_not_found" => "挂载点不存在", "Mount point not found";
+    "meta_not_found" => "Meta not found", "Meta not found";
+    "upload_failed" => "上传失败", "Upload failed";
+    "rename_failed" => "重命名失败", "Rename failed";
+    "file_info_failed" => "获取文件信息失败", "Failed to get file info";
+    "extract_failed" => "解压失败", "Extraction failed";
+    "mount_read_only" => "存储为只读", "Storage is read-only";
+    "maintenance_mode" => "站点维护中，暂时无法修改文件", "The site is under maintenance, files cannot be changed";
+    "upload_infected" => "上传的文件中检测到病毒，已拒绝", "A virus was detected in the uploaded file, the upload was rejected";
+    "virus_scan_failed" => "病毒扫描失败，上传已拒绝", "Virus scan failed, the upload was rejected";
+    // Shares and links / 分享与直链
+    "share_not_found" => "分享不存在", "Share not found";
+    "share_disabled" => "分享已被禁用", "Share is disabled";
+    "share_expired" => "分享已过期", "Share has expired";
+    "share_access_limit" => "分享访问次数已达上限", "Share access limit reached";
+    "share_forbidden" => "无权操作此分享", "No permission to manage this share";
+    "direct_link_not_found" => "直链不存在", "Direct link not found";
+    // Tasks / 任务
+    "task_not_found" => "任务不存在", "Task not found";
+    "task_group_not_found" => "任务组不存在", "Task group not found";
+    "task_paused" => "任务已暂停", "Task is paused";
+    "task_cancelled" => "任务已取消", "Task was cancelled";
+    // Admin features / 管理功能
+    "workspace_not_found" => "工作区不存在", "Workspace not found";
+    "workspace_default_only" => "该功能仅能在默认工作区中管理", "Only available in the default workspace";
+    "workspace_default_readonly" => "默认工作区不可修改", "The default workspace cannot be modified";
+    "workspace_default_undeletable" => "默认工作区不可删除", "The default workspace cannot be deleted";
+    "workspace_has_users" => "请先删除该工作区中的用户", "Delete the users of this workspace first";
+    "instance_settings_default_only" => "实例级设置仅能在默认工作区中修改", "Instance settings can only be changed in the default workspace";
+    "announcement_not_found" => "公告不存在", "Announcement not found";
+    "announcement_invalid" => "公告无效", "Invalid announcement";
+    "hook_not_found" => "钩子不存在", "Hook not found";
+    "webhook_not_found" => "Webhook不存在", "Webhook not found";
+    "export_not_found" => "导出不存在", "Export not found";
+    "security_settings_invalid" => "安全设置无效", "Invalid security settings";
+    "lockout_policy_invalid" => "登录锁定策略无效", "Invalid login lockout policy";
+    "captcha_settings_invalid" => "验证码设置无效", "Invalid captcha settings";
+    "guest_policy_invalid" => "游客访问策略无效", "Invalid guest access policy";
+    "link_rewrite_invalid" => "直链改写规则格式错误", "Invalid direct link rewrite rules";
+}
+
+/// Look up a catalog entry by error code / 按错误码查找
+pub fn by_code(code: &str) -> Option<&'static Message> {
+    CATALOG.iter().find(|m| m.code == code)
+}
+
+/// Find the entry for a message in either language / 按任一语言的消息文本查找
+fn by_text(text: &str) -> Option<&'static Message> {
+    CATALOG.iter().find(|m| m.zh == text || m.en == text)
+}
+
+/// Match a message against the catalog, returning the entry and a trailing detail
+/// 匹配消息，返回条目及附带的详情（`"<文本>: <详情>"`）
+pub fn lookup(message: &str) -> Option<(&'static Message, Option<&str>)> {
+    if let Some(m) = by_text(message) {
+        return Some((m, None));
+    }
+    let (text, detail) = message.split_once(": ").or_else(|| message.split_once('：'))?;
+    by_text(text).map(|m| (m, Some(detail)))
+}
+
+/// Localize a message, `None` when it is not in the catalog / 本地化消息，不在目录中时返回 `None`
+pub fn localize(message: &str, locale: Locale) -> Option<(&'static str, String)> {
+    let (entry, detail) = lookup(message)?;
+    let text = entry.text(locale);
+    let localized = match detail {
+        Some(detail) => format!("{}: {}", text, detail),
+        None => text.to_string(),
+    };
+    Some((entry.code, localized))
+}
+
+/// Add `error_code` and localize `message` / `error` of an error body; `false` if nothing changed
+/// 为错误响应体添加 `error_code` 并本地化 `message` / `error`，未改动时返回 `false`
+pub fn localize_body(body: &mut Value, is_error: bool, locale: Locale) -> bool {
+    let Some(object) = body.as_object_mut() else {
+        return false;
+    };
+    let is_error = is_error || object.get("code").and_then(Value::as_i64).is_some_and(|c| c >= 400);
+    if !is_error {
+        return false;
+    }
+    let mut code = None;
+    for key in ["message", "error"] {
+        let Some(text) = object.get(key).and_then(Value::as_str) else {
+            continue;
+        };
+        if let Some((entry_code, localized)) = localize(text, locale) {
+            code.get_or_insert(entry_code);
+            object.insert(key.to_string(), Value::String(localized));
+        }
+    }
+    match code {
+        Some(code) => {
+            object.entry("error_code").or_insert_with(|| Value::String(code.to_string()));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Localize JSON error responses according to `Accept-Language`
+/// 按 `Accept-Language` 本地化 JSON 错误响应
+pub async fn i18n_middleware(req: Request, next: Next) -> Response {
+    let locale = Locale::from_headers(req.headers());
+    let response = next.run(req).await;
+
+    let is_json = response.headers().get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let small = response.body().size_hint().upper().is_some_and(|n| n <= MAX_LOCALIZE_BODY);
+    if !is_json || !small {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_LOCALIZE_BODY as usize).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+    let is_error = parts.status.is_client_error() || parts.status.is_server_error();
+    let mut body = bytes;
+    if let Ok(mut value) = serde_json::from_slice::<Value>(&body) {
+        if localize_body(&mut value, is_error, locale) {
+            if let Ok(localized) = serde_json::to_vec(&value) {
+                body = Bytes::from(localized);
+                parts.headers.remove(header::CONTENT_LENGTH);
+                parts.headers.insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(locale.tag()));
+                parts.headers.append(header::VARY, HeaderValue::from_static("accept-language"));
+            }
+        }
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Locale::negotiate("en-US,en;q=0.9,zh-CN;q=0.8"), Locale::En);
+        assert_eq!(Locale::negotiate("zh-TW,en;q=0.5"), Locale::ZhCn);
+        assert_eq!(Locale::negotiate("fr-FR, en;q=0.3, zh;q=0.7"), Locale::ZhCn);
+        assert_eq!(Locale::negotiate("en;q=0, fr"), Locale::ZhCn);
+        assert_eq!(Locale::negotiate(""), Locale::ZhCn);
+    }
+
+    #[test]
+    fn test_catalog_unique() {
+        for (i, m) in CATALOG.iter().enumerate() {
+            assert!(CATALOG[i + 1..].iter().all(|o| o.code != m.code && o.zh != m.zh), "duplicate entry {}", m.code);
+        }
+        assert_eq!(by_code("move_denied").unwrap().en, "No permission to move files");
+    }
+
+    #[test]
+    fn test_localize_body() {
+        let mut body = json!({"code": 403, "message": "没有移动文件的权限"});
+        assert!(localize_body(&mut body, false, Locale::En));
+        assert_eq!(body, json!({"code": 403, "message": "No permission to move files", "error_code": "move_denied"}));
+
+        let mut body = json!({"error": "路径不存在: /a/b"});
+        assert!(localize_body(&mut body, true, Locale::En));
+        assert_eq!(body["error"], "Path not found: /a/b");
+        assert_eq!(body["error_code"], "path_not_found");
+
+        let mut body = json!({"error": "Path not found"});
+        assert!(localize_body(&mut body, true, Locale::ZhCn));
+        assert_eq!(body["error"], "路径不存在");
+
+        let mut body = json!({"code": 200, "message": "服务器错误"});
+        assert!(!localize_body(&mut body, false, Locale::En));
+        let mut body = json!({"code": 500, "message": "unknown"});
+        assert!(!localize_body(&mut body, false, Locale::En));
+    }
+}
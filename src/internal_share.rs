@@ -0,0 +1,267 @@
+//! Internal shares / 站内分享
+//!
+//! A user shares a file or folder with specific users or user groups instead of a public link.
+//! Recipients see every item shared with them under the virtual folder [`SHARED_ROOT`] in file
+//! listings and WebDAV, regardless of their own root path, and get read-only or read-write access
+//! to it. Nothing is reachable without logging in.
+//! 用户将文件或目录分享给指定的用户或用户组，而不是生成公开链接。接收者在文件列表与 WebDAV 中的虚拟目录
+//! [`SHARED_ROOT`] 下看到分享给自己的全部项目（不受其根路径限制），并获得只读或读写权限。未登录时无法访问。
+//!
+//! Paths below `SHARED_ROOT/<name>` map to the shared storage path; read-write access covers the
+//! contents of a shared folder but never renaming, moving or deleting the shared item itself.
+//! `SHARED_ROOT/<名称>` 之下的路径映射到被分享的存储路径；读写权限作用于分享目录中的内容，但不能重命名、移动或删除被分享的项目本身。
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::access::Capability;
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Virtual folder holding the items shared with a user / 存放分享给用户的项目的虚拟目录
+pub const SHARED_ROOT: &str = "/Shared with me";
+
+/// Who an internal share is for / 站内分享的接收者类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetType {
+    User,
+    Group,
+}
+
+impl TargetType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TargetType::User => "user",
+            TargetType::Group => "group",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "user" => Some(TargetType::User),
+            "group" => Some(TargetType::Group),
+            _ => None,
+        }
+    }
+}
+
+/// Access granted to recipients / 授予接收者的权限
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareAccess {
+    /// List, view and download / 列出、查看与下载
+    #[default]
+    Read,
+    /// Also upload, rename, move, copy and delete inside / 还可在其中上传、重命名、移动、复制与删除
+    ReadWrite,
+}
+
+impl ShareAccess {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShareAccess::Read => "read",
+            ShareAccess::ReadWrite => "read_write",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(ShareAccess::Read),
+            "read_write" => Some(ShareAccess::ReadWrite),
+            _ => None,
+        }
+    }
+}
+
+/// An item as a recipient sees it / 接收者看到的分享项目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SharedItem {
+    /// Folder name below [`SHARED_ROOT`], unique per recipient / [`SHARED_ROOT`] 下的名称，对每个接收者唯一
+    pub name: String,
+    /// Full storage path / 完整存储路径
+    pub path: String,
+    pub is_dir: bool,
+    pub access: ShareAccess,
+}
+
+impl SharedItem {
+    /// Whether `capability` is allowed at the full storage path `path` through this share
+    /// 通过该分享能否在完整存储路径 `path` 上执行 `capability`
+    pub fn allows(&self, capability: Capability, path: &str) -> bool {
+        let path = fix_and_clean_path(path);
+        if !is_sub_path(&self.path, &path) {
+            return false;
+        }
+        match capability {
+            Capability::Read => true,
+            // 分享目录本身只能在其中上传，不能被重命名、移动或删除
+            Capability::Upload => self.access == ShareAccess::ReadWrite && self.is_dir,
+            _ => self.access == ShareAccess::ReadWrite && path != self.path,
+        }
+    }
+}
+
+/// Where a user path below [`SHARED_ROOT`] points / [`SHARED_ROOT`] 下的用户路径指向的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolved {
+    /// Not below [`SHARED_ROOT`] / 不在 [`SHARED_ROOT`] 下
+    NotShared,
+    /// [`SHARED_ROOT`] itself / [`SHARED_ROOT`] 本身
+    Root,
+    /// Full storage path / 完整存储路径
+    Path(String),
+    /// No item with that name / 没有该名称的项目
+    Missing,
+}
+
+/// Resolve a user path against the items shared with the user / 按分享给用户的项目解析用户路径
+pub fn resolve(items: &[SharedItem], req_path: &str) -> Resolved {
+    let req_path = fix_and_clean_path(req_path);
+    if items.is_empty() || !is_sub_path(SHARED_ROOT, &req_path) {
+        return Resolved::NotShared;
+    }
+    let rest = req_path[SHARED_ROOT.len()..].trim_start_matches('/');
+    if rest.is_empty() {
+        return Resolved::Root;
+    }
+    let (name, sub) = rest.split_once('/').unwrap_or((rest, ""));
+    match items.iter().find(|i| i.name == name) {
+        Some(item) if sub.is_empty() => Resolved::Path(item.path.clone()),
+        Some(item) if item.is_dir => Resolved::Path(fix_and_clean_path(&format!("{}/{}", item.path, sub))),
+        _ => Resolved::Missing,
+    }
+}
+
+/// Whether any share allows `capability` at `path` / 是否有分享允许在 `path` 上执行 `capability`
+pub fn allows(items: &[SharedItem], capability: Capability, path: &str) -> bool {
+    items.iter().any(|i| i.allows(capability, path))
+}
+
+/// Merge shares of the same path (highest access wins) and make names unique with a ` (2)` suffix
+/// 合并同一路径的分享（取最高权限），重名时添加 ` (2)` 后缀
+pub fn merge_items(shares: Vec<SharedItem>) -> Vec<SharedItem> {
+    let mut merged: Vec<SharedItem> = Vec::new();
+    for mut share in shares {
+        share.path = fix_and_clean_path(&share.path);
+        if let Some(existing) = merged.iter_mut().find(|i| i.path == share.path) {
+            existing.access = existing.access.max(share.access);
+            continue;
+        }
+        let base = share.name.clone();
+        let mut n = 2;
+        while merged.iter().any(|i| i.name == share.name) {
+            share.name = format!("{} ({})", base, n);
+            n += 1;
+        }
+        merged.push(share);
+    }
+    merged
+}
+
+/// Display name of a shared item: no slashes, not empty / 分享项目的显示名称：不含斜杠且非空
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return Err("name is required".to_string());
+    }
+    if name.contains(['/', '\\']) || name.chars().any(char::is_control) {
+        return Err("name must not contain slashes".to_string());
+    }
+    if name.chars().count() > 255 {
+        return Err("name must be at most 255 characters".to_string());
+    }
+    Ok(())
+}
+
+/// Items shared with a user directly or through one of their groups, in the current workspace;
+/// the user's own shares are left out
+/// 当前工作区中直接或通过用户组分享给用户的项目，不含用户自己创建的分享
+pub async fn load_for_user(db: &SqlitePool, user_id: &str) -> Result<Vec<SharedItem>, sqlx::Error> {
+    let rows: Vec<(String, String, bool, String)> = sqlx::query_as(
+        "SELECT s.name, s.path, s.is_dir, s.access FROM internal_shares s
+         WHERE s.workspace_id = ? AND s.owner_id != ? AND (
+             (s.target_type = 'user' AND s.target_id = ?)
+             OR (s.target_type = 'group' AND s.target_id IN (SELECT group_id FROM user_group_members WHERE user_id = ?))
+         )
+         ORDER BY s.created_at"
+    )
+    .bind(crate::workspace::current().id())
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    Ok(merge_items(rows.into_iter().map(|(name, path, is_dir, access)| SharedItem {
+        name,
+        path,
+        is_dir,
+        access: ShareAccess::parse(&access).unwrap_or_default(),
+    }).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, path: &str, is_dir: bool, access: ShareAccess) -> SharedItem {
+        SharedItem { name: name.to_string(), path: path.to_string(), is_dir, access }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let items = vec![
+            item("Docs", "/team/docs", true, ShareAccess::Read),
+            item("a.txt", "/team/a.txt", false, ShareAccess::Read),
+        ];
+        assert_eq!(resolve(&items, "/docs"), Resolved::NotShared);
+        assert_eq!(resolve(&items, "/Shared with me2"), Resolved::NotShared);
+        assert_eq!(resolve(&[], "/Shared with me/Docs"), Resolved::NotShared);
+        assert_eq!(resolve(&items, "/Shared with me/"), Resolved::Root);
+        assert_eq!(resolve(&items, "/Shared with me/Docs"), Resolved::Path("/team/docs".to_string()));
+        assert_eq!(resolve(&items, "/Shared with me/Docs/x/y.md"), Resolved::Path("/team/docs/x/y.md".to_string()));
+        assert_eq!(resolve(&items, "/Shared with me/a.txt"), Resolved::Path("/team/a.txt".to_string()));
+        assert_eq!(resolve(&items, "/Shared with me/a.txt/x"), Resolved::Missing);
+        assert_eq!(resolve(&items, "/Shared with me/Other"), Resolved::Missing);
+        // 先规范化路径，`..` 无法越出被分享的目录
+        assert_eq!(resolve(&items, "/Shared with me/Docs/x/../../a.txt"), Resolved::Path("/team/a.txt".to_string()));
+        assert_eq!(resolve(&items, "/Shared with me/Docs/../../etc"), Resolved::NotShared);
+    }
+
+    #[test]
+    fn test_allows() {
+        let items = vec![
+            item("Docs", "/team/docs", true, ShareAccess::Read),
+            item("Inbox", "/team/inbox", true, ShareAccess::ReadWrite),
+        ];
+        assert!(allows(&items, Capability::Read, "/team/docs/a"));
+        assert!(!allows(&items, Capability::Upload, "/team/docs"));
+        assert!(!allows(&items, Capability::Read, "/team/docs2"));
+        assert!(allows(&items, Capability::Upload, "/team/inbox"));
+        assert!(allows(&items, Capability::Delete, "/team/inbox/a"));
+        // 分享目录本身不能被删除或重命名
+        assert!(!allows(&items, Capability::Delete, "/team/inbox"));
+        assert!(!allows(&items, Capability::Rename, "/team/inbox/"));
+    }
+
+    #[test]
+    fn test_merge_items() {
+        let merged = merge_items(vec![
+            item("Docs", "/a/docs", true, ShareAccess::Read),
+            item("Docs", "/b/docs", true, ShareAccess::Read),
+            item("Docs", "/a/docs/", true, ShareAccess::ReadWrite),
+            item("Docs", "/c/docs", true, ShareAccess::Read),
+        ]);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].access, ShareAccess::ReadWrite);
+        assert_eq!(merged[1].name, "Docs (2)");
+        assert_eq!(merged[2].name, "Docs (3)");
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("Docs").is_ok());
+        assert!(validate_name(" ").is_err());
+        assert!(validate_name("..").is_err());
+        assert!(validate_name("a/b").is_err());
+    }
+}
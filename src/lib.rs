@@ -11,6 +11,56 @@ pub mod load_balance;
 pub mod geoip;
 pub mod server;
 pub mod download;
+pub mod link_policy;
+pub mod transfer;
+pub mod webhook;
+pub mod email_template;
+pub mod scheduler;
+pub mod file_hook;
+pub mod strm;
+pub mod tiering;
+pub mod dedupe;
+pub mod gallery;
+pub mod watermark;
+pub mod audio;
+pub mod book;
+pub mod archive;
+pub mod archive_password;
+pub mod feed;
+pub mod static_site;
+pub mod service;
+pub mod security_alert;
+pub mod virus_scan;
+pub mod upload_policy;
+pub mod download_list;
+pub mod share_link;
+pub mod internal_share;
+pub mod playback;
+pub mod file_type;
+pub mod tls;
+pub mod client_ip;
+pub mod request_id;
+pub mod body_stream;
+pub mod body_limit;
+pub mod http_security;
+pub mod compression;
+pub mod lockout;
+pub mod captcha;
+pub mod workspace;
+pub mod guest;
+pub mod access;
+pub mod audit;
+pub mod secrets;
+pub mod accounts;
+pub mod group_defaults;
+pub mod mount_visibility;
+pub mod path_resolver;
+pub mod hide_rules;
+pub mod announcement;
+pub mod branding;
+pub mod i18n;
+pub mod error;
+pub mod cluster;
 
 // Driver modules (point to project root drivers via path attribute) / 驱动模块
 #[path = "../drivers/mod.rs"]
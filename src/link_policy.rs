@@ -0,0 +1,200 @@
+//! Direct link vs proxy decision / 直链与代理决策
+//!
+//! Decides per download whether the client is redirected (302) to the provider's direct link or
+//! the file is streamed through this server. Inputs are the driver's `can_direct_link`, the mount's
+//! `web_proxy` / `hide_user_agent` options, the client's country (GeoIP), the file size and
+//! whether the download is throttled locally.
+//! 按每次下载决定客户端是302跳转到存储商直链，还是由本服务器中转。依据包括驱动的直链能力、
+//! 挂载点的 web_proxy / hide_user_agent 选项、客户端所在国家/地区(GeoIP)、文件大小以及是否需要本地限速。
+
+use std::net::IpAddr;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Site-wide link policy / 全站直链策略
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkPolicyConfig {
+    /// Clients from these countries are always proxied (ISO codes, `LOCAL` for private networks)
+    /// 这些国家/地区的客户端始终走代理（ISO代码，内网为 LOCAL）
+    pub proxy_countries: Vec<String>,
+    /// When not empty, only clients from these countries get direct links
+    /// 非空时仅这些国家/地区的客户端使用直链
+    pub direct_countries: Vec<String>,
+    /// Proxy files up to this size in bytes, 0 means off / 不超过此大小（字节）的文件走代理，0 表示关闭
+    pub proxy_max_size: u64,
+}
+
+impl LinkPolicyConfig {
+    /// Uppercase and deduplicate country codes / 国家代码转大写并去重
+    pub fn normalize(&mut self) {
+        for list in [&mut self.proxy_countries, &mut self.direct_countries] {
+            let mut codes: Vec<String> = list.iter()
+                .map(|c| c.trim().to_uppercase())
+                .filter(|c| !c.is_empty())
+                .collect();
+            codes.sort();
+            codes.dedup();
+            *list = codes;
+        }
+    }
+
+    fn has_region_rules(&self) -> bool {
+        !self.proxy_countries.is_empty() || !self.direct_countries.is_empty()
+    }
+}
+
+/// Per-mount link options read from the driver config / 从驱动配置读取的挂载点选项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MountLinkOptions {
+    /// Always proxy this mount / 该挂载点始终走代理
+    pub web_proxy: bool,
+    /// The provider link needs the server's User-Agent or headers, so clients must not see it
+    /// 直链需要服务器的 User-Agent 或请求头，不能交给客户端
+    pub hide_user_agent: bool,
+}
+
+impl MountLinkOptions {
+    /// Read from a mount's driver config; `proxy_download` is the older per-driver name of `web_proxy`
+    /// 从挂载点驱动配置读取；`proxy_download` 是部分驱动旧的 `web_proxy` 写法
+    pub fn from_config(config: &Value) -> Self {
+        Self {
+            web_proxy: config_flag(config, "web_proxy") || config_flag(config, "proxy_download"),
+            hide_user_agent: config_flag(config, "hide_user_agent"),
+        }
+    }
+}
+
+fn config_flag(config: &Value, key: &str) -> bool {
+    match config.get(key) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => s == "true" || s == "1",
+        Some(Value::Number(n)) => n.as_u64().is_some_and(|n| n != 0),
+        _ => false,
+    }
+}
+
+/// One download to decide / 待决策的一次下载
+#[derive(Debug, Clone, Default)]
+pub struct LinkRequest {
+    /// Driver can produce a direct link / 驱动可生成直链
+    pub can_direct_link: bool,
+    pub mount: MountLinkOptions,
+    pub client_ip: Option<IpAddr>,
+    pub file_size: Option<u64>,
+    /// A local speed limit applies, which only works when proxying / 需要本地限速（只能在中转时生效）
+    pub throttled: bool,
+}
+
+/// Why a download is proxied / 走代理的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyReason {
+    NoDirectLink,
+    Throttled,
+    HideUserAgent,
+    MountProxy,
+    Region,
+    SmallFile,
+}
+
+/// Decision for one download / 单次下载的决策
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkDecision {
+    Redirect,
+    Proxy(ProxyReason),
+}
+
+impl LinkDecision {
+    pub fn is_redirect(&self) -> bool {
+        matches!(self, LinkDecision::Redirect)
+    }
+}
+
+/// Decide between redirect and proxy; the first matching reason to proxy wins
+/// 决定跳转还是代理，按顺序命中第一个需要代理的原因
+pub fn decide(config: &LinkPolicyConfig, request: &LinkRequest) -> LinkDecision {
+    decide_with_country(config, request, crate::geoip::lookup_country)
+}
+
+fn decide_with_country(
+    config: &LinkPolicyConfig,
+    request: &LinkRequest,
+    lookup_country: impl Fn(IpAddr) -> Option<String>,
+) -> LinkDecision {
+    if !request.can_direct_link {
+        return LinkDecision::Proxy(ProxyReason::NoDirectLink);
+    }
+    if request.throttled {
+        return LinkDecision::Proxy(ProxyReason::Throttled);
+    }
+    if request.mount.hide_user_agent {
+        return LinkDecision::Proxy(ProxyReason::HideUserAgent);
+    }
+    if request.mount.web_proxy {
+        return LinkDecision::Proxy(ProxyReason::MountProxy);
+    }
+    if config.has_region_rules() {
+        // 查不到国家时只有白名单会拒绝直链
+        let country = request.client_ip.and_then(lookup_country).map(|c| c.to_uppercase());
+        let denied = country.as_ref().is_some_and(|c| config.proxy_countries.contains(c));
+        let not_allowed = !config.direct_countries.is_empty()
+            && !country.as_ref().is_some_and(|c| config.direct_countries.contains(c));
+        if denied || not_allowed {
+            return LinkDecision::Proxy(ProxyReason::Region);
+        }
+    }
+    if config.proxy_max_size > 0 && request.file_size.is_some_and(|s| s <= config.proxy_max_size) {
+        return LinkDecision::Proxy(ProxyReason::SmallFile);
+    }
+    LinkDecision::Redirect
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_decide() {
+        let lookup = |ip: IpAddr| match ip.to_string().as_str() {
+            "1.1.1.1" => Some("us".to_string()),
+            "2.2.2.2" => Some("CN".to_string()),
+            _ => None,
+        };
+        let request = LinkRequest {
+            can_direct_link: true,
+            client_ip: Some("1.1.1.1".parse().unwrap()),
+            file_size: Some(4096),
+            ..Default::default()
+        };
+        let mut config = LinkPolicyConfig::default();
+        assert_eq!(decide_with_country(&config, &request, lookup), LinkDecision::Redirect);
+
+        let no_link = LinkRequest { can_direct_link: false, ..request.clone() };
+        assert_eq!(decide_with_country(&config, &no_link, lookup), LinkDecision::Proxy(ProxyReason::NoDirectLink));
+
+        let mount = MountLinkOptions::from_config(&json!({"proxy_download": "true"}));
+        assert!(mount.web_proxy && !mount.hide_user_agent);
+        let proxied = LinkRequest { mount, ..request.clone() };
+        assert_eq!(decide_with_country(&config, &proxied, lookup), LinkDecision::Proxy(ProxyReason::MountProxy));
+
+        config.direct_countries = vec![" us ".to_string()];
+        config.normalize();
+        assert_eq!(decide_with_country(&config, &request, lookup), LinkDecision::Redirect);
+        let china = LinkRequest { client_ip: Some("2.2.2.2".parse().unwrap()), ..request.clone() };
+        assert_eq!(decide_with_country(&config, &china, lookup), LinkDecision::Proxy(ProxyReason::Region));
+        let unknown = LinkRequest { client_ip: None, ..request.clone() };
+        assert_eq!(decide_with_country(&config, &unknown, lookup), LinkDecision::Proxy(ProxyReason::Region));
+
+        config.direct_countries.clear();
+        config.proxy_countries = vec!["CN".to_string()];
+        assert_eq!(decide_with_country(&config, &china, lookup), LinkDecision::Proxy(ProxyReason::Region));
+        assert_eq!(decide_with_country(&config, &unknown, lookup), LinkDecision::Redirect);
+
+        config.proxy_max_size = 4096;
+        assert_eq!(decide_with_country(&config, &request, lookup), LinkDecision::Proxy(ProxyReason::SmallFile));
+        let large = LinkRequest { file_size: Some(4097), ..request };
+        assert_eq!(decide_with_country(&config, &large, lookup), LinkDecision::Redirect);
+    }
+}
@@ -5,12 +5,23 @@
 //! - Load balancing logic completely in Core layer, drivers unaware of these concepts / 负载均衡逻辑
 //! - Use Capability to declare driver capabilities (can_redirect, etc.) / 使用Capability声明
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::RwLock;
 use std::net::IpAddr;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::geoip;
+use crate::storage::StorageManager;
+use crate::storage::aggregate::{choose_write_target, WriteCandidate, WritePolicy};
+
+/// Consecutive failures before a member is excluded / 连续失败多少次后剔除成员
+pub const MEMBER_FAILURE_THRESHOLD: u32 = 2;
+/// Timeout of a member probe (seconds) / 成员探测超时（秒）
+const MEMBER_PROBE_TIMEOUT_SECS: u64 = 15;
+/// LowestLatency sends one in this many requests to another member so it gets (fresh) samples
+/// 最低延迟模式每隔多少次请求分给其他成员一次，使其获得（新的）延迟样本
+const LATENCY_EXPLORE_EVERY: usize = 10;
 
 /// Load balancing mode / 负载均衡模式
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -22,6 +33,10 @@ pub enum LoadBalanceMode {
     IpHash,
     /// Geographic region distribution (domestic/foreign) / 按地区分流
     GeoRegion,
+    /// Primary/backup: always use the first healthy member by order / 主备故障转移
+    Failover,
+    /// Lowest recorded response time / 最低延迟优先
+    LowestLatency,
 }
 
 impl From<&str> for LoadBalanceMode {
@@ -30,6 +45,8 @@ impl From<&str> for LoadBalanceMode {
             "weighted_round_robin" | "weightedroundrobin" | "weighted" | "round_robin" => LoadBalanceMode::WeightedRoundRobin,
             "ip_hash" | "iphash" => LoadBalanceMode::IpHash,
             "geo_region" | "georegion" => LoadBalanceMode::GeoRegion,
+            "failover" => LoadBalanceMode::Failover,
+            "lowest_latency" | "lowestlatency" | "latency" => LoadBalanceMode::LowestLatency,
             _ => LoadBalanceMode::WeightedRoundRobin,
         }
     }
@@ -41,6 +58,65 @@ impl ToString for LoadBalanceMode {
             LoadBalanceMode::WeightedRoundRobin => "weighted_round_robin".to_string(),
             LoadBalanceMode::IpHash => "ip_hash".to_string(),
             LoadBalanceMode::GeoRegion => "geo_region".to_string(),
+            LoadBalanceMode::Failover => "failover".to_string(),
+            LoadBalanceMode::LowestLatency => "lowest_latency".to_string(),
+        }
+    }
+}
+
+/// Where uploads go inside a group / 组内上传文件的放置策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadPolicy {
+    /// Use the mounts' own write policy (default) / 使用挂载点自身的写入策略（默认）
+    #[default]
+    None,
+    /// Member with the most free space / 剩余空间最多的成员
+    MostFreeSpace,
+    /// First member (by order) whose usage is below the threshold / 第一个使用率低于阈值的成员（按排序）
+    UsageThreshold,
+}
+
+impl From<&str> for UploadPolicy {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "most_free_space" | "mostfreespace" | "free_space" => UploadPolicy::MostFreeSpace,
+            "usage_threshold" | "usagethreshold" | "threshold" => UploadPolicy::UsageThreshold,
+            _ => UploadPolicy::None,
+        }
+    }
+}
+
+/// Default usage threshold of `UsageThreshold` (percent) / 使用率阈值默认值（百分比）
+pub const DEFAULT_USAGE_THRESHOLD: u8 = 90;
+
+fn default_usage_threshold() -> u8 {
+    DEFAULT_USAGE_THRESHOLD
+}
+
+/// Pick the member to upload to; candidates are in member order, None when the policy is off
+/// 选择上传的成员下标，候选按成员排序；未启用上传策略时返回 None
+///
+/// `UsageThreshold` falls back to the member with the most free space when every member is over
+/// the threshold, members that cannot report space never count as below it.
+/// 所有成员都超过阈值时回退到剩余空间最多的成员，无法查询空间的成员不视为低于阈值。
+pub fn choose_upload_member(
+    policy: UploadPolicy,
+    usage_threshold: u8,
+    candidates: &[WriteCandidate],
+    size: Option<u64>,
+) -> Option<usize> {
+    match policy {
+        UploadPolicy::None => None,
+        UploadPolicy::MostFreeSpace => choose_write_target(WritePolicy::MostFreeSpace, candidates, size),
+        UploadPolicy::UsageThreshold => {
+            let below = candidates.iter().position(|c| {
+                let Some(space) = c.space.as_ref().filter(|s| s.total > 0) else { return false };
+                let used = space.used.saturating_add(size.unwrap_or(0));
+                used.saturating_mul(100) < space.total.saturating_mul(usage_threshold as u64)
+                    && c.max_file_size.is_none_or(|max| size.is_none_or(|s| s <= max))
+            });
+            below.or_else(|| choose_write_target(WritePolicy::MostFreeSpace, candidates, size))
         }
     }
 }
@@ -78,6 +154,29 @@ pub struct BalanceDriver {
     pub is_china_node: bool,
 }
 
+/// Member health (shared across groups, keyed by driver id) / 成员健康状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberHealth {
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Smoothed response time of successful probes/requests (ms) / 平滑后的响应时间
+    pub latency_ms: Option<u64>,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+impl Default for MemberHealth {
+    fn default() -> Self {
+        Self {
+            healthy: true,
+            consecutive_failures: 0,
+            last_error: None,
+            latency_ms: None,
+            checked_at: None,
+        }
+    }
+}
+
 /// Load balancing group configuration (for serialization) / 负载均衡组配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BalanceGroupConfig {
@@ -85,6 +184,12 @@ pub struct BalanceGroupConfig {
     pub mode: LoadBalanceMode,
     pub drivers: Vec<BalanceDriver>,
     pub enabled: bool,
+    /// Upload placement among members / 成员间的上传放置策略
+    #[serde(default)]
+    pub upload_policy: UploadPolicy,
+    /// Usage percent limit of `UsageThreshold` / `UsageThreshold` 的使用率上限（百分比）
+    #[serde(default = "default_usage_threshold")]
+    pub usage_threshold: u8,
 }
 
 /// Load balancing group (runtime) / 负载均衡组
@@ -94,6 +199,8 @@ pub struct BalanceGroup {
     pub mode: LoadBalanceMode,
     pub drivers: Vec<BalanceDriver>,
     pub enabled: bool,
+    pub upload_policy: UploadPolicy,
+    pub usage_threshold: u8,
     /// Round robin counter / 轮询计数器
     counter: AtomicUsize,
 }
@@ -105,6 +212,8 @@ impl BalanceGroup {
             mode,
             drivers: Vec::new(),
             enabled: true,
+            upload_policy: UploadPolicy::None,
+            usage_threshold: DEFAULT_USAGE_THRESHOLD,
             counter: AtomicUsize::new(0),
         }
     }
@@ -112,6 +221,8 @@ impl BalanceGroup {
     pub fn from_config(config: BalanceGroupConfig) -> Self {
         let mut group = Self::new(config.name, config.mode);
         group.enabled = config.enabled;
+        group.upload_policy = config.upload_policy;
+        group.usage_threshold = config.usage_threshold;
         group.drivers = config.drivers;
         group.drivers.sort_by_key(|d| d.order);
         group
@@ -123,6 +234,8 @@ impl BalanceGroup {
             mode: self.mode.clone(),
             drivers: self.drivers.clone(),
             enabled: self.enabled,
+            upload_policy: self.upload_policy,
+            usage_threshold: self.usage_threshold,
         }
     }
 
@@ -139,43 +252,101 @@ impl BalanceGroup {
     /// - WeightedRoundRobin：按权重比例轮询分配
     /// - IpHash：根据IP哈希选择
     /// - GeoRegion：按地区分流
-    pub fn select_driver(&self, client_ip: Option<IpAddr>, _file_name: &str) -> Option<&BalanceDriver> {
-        if self.drivers.is_empty() {
+    /// - Failover：按order选择第一个可用成员
+    /// - LowestLatency：选择记录响应时间最低的成员
+    pub fn select_driver(&self, client_ip: Option<IpAddr>, file_name: &str) -> Option<&BalanceDriver> {
+        self.select_driver_excluding(client_ip, file_name, &HashSet::new())
+    }
+
+    /// 选择驱动，跳过excluded中的成员（不健康或本次请求已失败的成员）
+    pub fn select_driver_excluding(
+        &self,
+        client_ip: Option<IpAddr>,
+        file_name: &str,
+        excluded: &HashSet<String>,
+    ) -> Option<&BalanceDriver> {
+        self.select_driver_with_latency(client_ip, file_name, excluded, &HashMap::new())
+    }
+
+    /// 选择驱动（带成员响应时间，driver_id -> ms，供LowestLatency使用）
+    pub fn select_driver_with_latency(
+        &self,
+        client_ip: Option<IpAddr>,
+        _file_name: &str,
+        excluded: &HashSet<String>,
+        latencies: &HashMap<String, u64>,
+    ) -> Option<&BalanceDriver> {
+        let candidates: Vec<&BalanceDriver> = self.drivers.iter()
+            .filter(|d| !excluded.contains(&d.driver_id))
+            .collect();
+        if candidates.is_empty() {
             return None;
         }
 
         match self.mode {
             LoadBalanceMode::WeightedRoundRobin => {
                 // 加权轮询
-                self.select_by_weight()
+                self.select_by_weight(&candidates)
             }
             LoadBalanceMode::IpHash => {
                 // IP哈希分流
                 if let Some(ip) = client_ip {
                     let hash = Self::hash_ip(&ip);
-                    let idx = hash % self.drivers.len();
-                    Some(&self.drivers[idx])
+                    let idx = hash % candidates.len();
+                    Some(candidates[idx])
                 } else {
                     // 没有IP，回退到加权轮询
-                    self.select_by_weight()
+                    self.select_by_weight(&candidates)
                 }
             }
             LoadBalanceMode::GeoRegion => {
                 // 按地区分流
-                self.select_by_geo(client_ip)
+                self.select_by_geo(client_ip, &candidates)
+            }
+            LoadBalanceMode::Failover => {
+                // 成员已按order排序，取第一个
+                candidates.first().copied()
+            }
+            LoadBalanceMode::LowestLatency => {
+                // 选择响应时间最低的成员；尚无记录时回退到加权轮询
+                let best = candidates.iter()
+                    .filter_map(|d| latencies.get(&d.driver_id).map(|ms| (*ms, *d)))
+                    .min_by_key(|(ms, d)| (*ms, d.order))
+                    .map(|(_, d)| d);
+                let Some(best) = best else {
+                    return self.select_by_weight(&candidates);
+                };
+                // 定期探索其他成员（优先没有记录的），否则它们永远得不到样本
+                let tick = self.counter.fetch_add(1, Ordering::Relaxed);
+                if tick % LATENCY_EXPLORE_EVERY == LATENCY_EXPLORE_EVERY - 1 {
+                    let unsampled: Vec<&BalanceDriver> = candidates.iter()
+                        .filter(|d| !latencies.contains_key(&d.driver_id))
+                        .copied()
+                        .collect();
+                    let others = if unsampled.is_empty() {
+                        candidates.iter().filter(|d| d.driver_id != best.driver_id).copied().collect()
+                    } else {
+                        unsampled
+                    };
+                    if !others.is_empty() {
+                        return Some(others[(tick / LATENCY_EXPLORE_EVERY) % others.len()]);
+                    }
+                }
+                Some(best)
             }
         }
     }
     
     /// 按地区选择（国内/国外）
-    fn select_by_geo(&self, client_ip: Option<IpAddr>) -> Option<&BalanceDriver> {
+    fn select_by_geo<'a>(&self, client_ip: Option<IpAddr>, candidates: &[&'a BalanceDriver]) -> Option<&'a BalanceDriver> {
         let is_china = client_ip
             .map(|ip| geoip::is_china_ip(ip))
             .unwrap_or(false);
         
         // 根据地区过滤驱动
-        let region_drivers: Vec<_> = self.drivers.iter()
+        let region_drivers: Vec<_> = candidates.iter()
             .filter(|d| d.is_china_node == is_china)
+            .copied()
             .collect();
         
         if !region_drivers.is_empty() {
@@ -185,9 +356,9 @@ impl BalanceGroup {
         }
         
         // 没有匹配地区的驱动，回退到所有驱动轮询
-        if !self.drivers.is_empty() {
-            let idx = self.counter.fetch_add(1, Ordering::Relaxed) % self.drivers.len();
-            return Some(&self.drivers[idx]);
+        if !candidates.is_empty() {
+            let idx = self.counter.fetch_add(1, Ordering::Relaxed) % candidates.len();
+            return Some(candidates[idx]);
         }
         
         None
@@ -203,24 +374,24 @@ impl BalanceGroup {
     }
 
     /// 按权重选择
-    fn select_by_weight(&self) -> Option<&BalanceDriver> {
-        let total_weight: u32 = self.drivers.iter().map(|d| d.weight).sum();
+    fn select_by_weight<'a>(&self, candidates: &[&'a BalanceDriver]) -> Option<&'a BalanceDriver> {
+        let total_weight: u32 = candidates.iter().map(|d| d.weight).sum();
         if total_weight == 0 {
-            return self.drivers.first();
+            return candidates.first().copied();
         }
 
         let counter = self.counter.fetch_add(1, Ordering::Relaxed);
         let target = (counter as u32) % total_weight;
         
         let mut accumulated = 0u32;
-        for driver in &self.drivers {
+        for driver in candidates {
             accumulated += driver.weight;
             if target < accumulated {
                 return Some(driver);
             }
         }
         
-        self.drivers.last()
+        candidates.last().copied()
     }
 }
 
@@ -230,6 +401,8 @@ pub struct LoadBalanceManager {
     mount_groups: RwLock<HashMap<String, BalanceGroup>>,
     /// 按负载均衡组名分组
     named_groups: RwLock<HashMap<String, BalanceGroup>>,
+    /// 成员健康状态（driver_id -> health）
+    member_health: RwLock<HashMap<String, MemberHealth>>,
 }
 
 impl LoadBalanceManager {
@@ -237,7 +410,85 @@ impl LoadBalanceManager {
         Self {
             mount_groups: RwLock::new(HashMap::new()),
             named_groups: RwLock::new(HashMap::new()),
+            member_health: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 记录成员成功（探测或实际请求）
+    pub async fn report_success(&self, driver_id: &str, latency_ms: Option<u64>) {
+        let mut health = self.member_health.write().await;
+        let entry = health.entry(driver_id.to_string()).or_default();
+        if !entry.healthy {
+            tracing::info!("Load balance member recovered: {}", driver_id);
         }
+        entry.healthy = true;
+        entry.consecutive_failures = 0;
+        entry.last_error = None;
+        if let Some(sample) = latency_ms {
+            // 指数加权平均，避免单次抖动影响选择
+            entry.latency_ms = Some(match entry.latency_ms {
+                Some(prev) => (prev * 7 + sample * 3) / 10,
+                None => sample,
+            });
+        }
+        entry.checked_at = Some(Utc::now());
+    }
+
+    /// 记录成员失败，连续失败达到阈值后剔除
+    pub async fn report_failure(&self, driver_id: &str, error: &str) {
+        let mut health = self.member_health.write().await;
+        let entry = health.entry(driver_id.to_string()).or_default();
+        entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+        entry.last_error = Some(error.to_string());
+        entry.checked_at = Some(Utc::now());
+        if entry.healthy && entry.consecutive_failures >= MEMBER_FAILURE_THRESHOLD {
+            entry.healthy = false;
+            tracing::warn!("Load balance member excluded: {} - {}", driver_id, error);
+        }
+    }
+
+    /// 获取所有成员健康状态
+    pub async fn get_member_health(&self) -> HashMap<String, MemberHealth> {
+        self.member_health.read().await.clone()
+    }
+
+    /// 当前不健康的成员集合
+    async fn unhealthy_members(&self) -> HashSet<String> {
+        self.member_health.read().await
+            .iter()
+            .filter(|(_, h)| !h.healthy)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// 探测所有启用组的成员（列根目录），更新健康状态
+    pub async fn probe_members(&self, storage: &StorageManager) {
+        let member_ids: HashSet<String> = self.named_groups.read().await
+            .values()
+            .filter(|g| g.enabled)
+            .flat_map(|g| g.drivers.iter().map(|d| d.driver_id.clone()))
+            .collect();
+
+        let probes = member_ids.into_iter().map(|id| async move {
+            let driver = match storage.get_driver(&id).await {
+                Some(d) => d,
+                None => {
+                    self.report_failure(&id, "Driver not loaded").await;
+                    return;
+                }
+            };
+            let started = std::time::Instant::now();
+            let result = tokio::time::timeout(
+                std::time::Duration::from_secs(MEMBER_PROBE_TIMEOUT_SECS),
+                driver.list("/"),
+            ).await;
+            match result {
+                Ok(Ok(_)) => self.report_success(&id, Some(started.elapsed().as_millis() as u64)).await,
+                Ok(Err(e)) => self.report_failure(&id, &e.to_string()).await,
+                Err(_) => self.report_failure(&id, "Probe timeout").await,
+            }
+        });
+        futures::future::join_all(probes).await;
     }
 
     /// 注册驱动到负载均衡管理器
@@ -314,22 +565,55 @@ impl LoadBalanceManager {
         self.named_groups.write().await.insert(name, group);
     }
     
-    /// 从命名组选择驱动
+    /// 从命名组选择驱动（自动跳过不健康成员）
     pub async fn select_from_group(
         &self,
         group_name: &str,
         client_ip: Option<IpAddr>,
         file_name: &str,
     ) -> Option<BalanceDriver> {
+        self.select_from_group_excluding(group_name, client_ip, file_name, &HashSet::new()).await
+    }
+
+    /// 从命名组选择驱动，额外跳过tried中的成员（故障转移重试用）
+    ///
+    /// 所有候选成员都不健康时，仍在未尝试的成员中选择，避免整组不可用
+    pub async fn select_from_group_excluding(
+        &self,
+        group_name: &str,
+        client_ip: Option<IpAddr>,
+        file_name: &str,
+        tried: &HashSet<String>,
+    ) -> Option<BalanceDriver> {
+        let mut excluded = self.unhealthy_members().await;
+        excluded.extend(tried.iter().cloned());
+        let latencies: HashMap<String, u64> = self.member_health.read().await
+            .iter()
+            .filter_map(|(id, h)| h.latency_ms.map(|ms| (id.clone(), ms)))
+            .collect();
         let groups = self.named_groups.read().await;
         if let Some(group) = groups.get(group_name) {
             if group.enabled {
-                return group.select_driver(client_ip, file_name).cloned();
+                return group.select_driver_with_latency(client_ip, file_name, &excluded, &latencies)
+                    .or_else(|| group.select_driver_with_latency(client_ip, file_name, tried, &latencies))
+                    .cloned();
             }
         }
         None
     }
 
+    /// 查找对这些驱动启用了上传放置策略的组（第一个包含其中任一驱动的启用组）
+    pub async fn upload_group_for(&self, driver_ids: &[&str]) -> Option<BalanceGroupConfig> {
+        let groups = self.named_groups.read().await;
+        let mut matched: Vec<&BalanceGroup> = groups.values()
+            .filter(|g| g.enabled && g.upload_policy != UploadPolicy::None)
+            .filter(|g| g.drivers.iter().any(|d| driver_ids.contains(&d.driver_id.as_str())))
+            .collect();
+        // HashMap无序，按组名排序保证结果稳定
+        matched.sort_by(|a, b| a.name.cmp(&b.name));
+        matched.first().map(|g| g.to_config())
+    }
+
     /// 清除所有注册
     pub async fn clear(&self) {
         self.mount_groups.write().await.clear();
@@ -387,6 +671,8 @@ mod tests {
         assert_eq!(LoadBalanceMode::from("weighted_round_robin"), LoadBalanceMode::WeightedRoundRobin);
         assert_eq!(LoadBalanceMode::from("ip_hash"), LoadBalanceMode::IpHash);
         assert_eq!(LoadBalanceMode::from("geo_region"), LoadBalanceMode::GeoRegion);
+        assert_eq!(LoadBalanceMode::from("failover"), LoadBalanceMode::Failover);
+        assert_eq!(LoadBalanceMode::from("lowest_latency"), LoadBalanceMode::LowestLatency);
         assert_eq!(LoadBalanceMode::from("unknown"), LoadBalanceMode::WeightedRoundRobin);
     }
 
@@ -416,8 +702,8 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_balance_group_redirect_priority() {
-        let mut group = BalanceGroup::new("test".to_string(), LoadBalanceMode::None);
+    async fn test_balance_group_failover() {
+        let mut group = BalanceGroup::new("test".to_string(), LoadBalanceMode::Failover);
         
         group.add_driver(BalanceDriver {
             driver_id: "local".to_string(),
@@ -439,7 +725,86 @@ mod tests {
             is_china_node: false,
         });
 
-        let selected = group.select_driver(None, "test.txt");
+        // 主节点可用时总是选择order最小的成员
+        for _ in 0..3 {
+            assert_eq!(group.select_driver(None, "test.txt").unwrap().driver_id, "local");
+        }
+
+        // 主节点被剔除后转移到下一个成员
+        let excluded: HashSet<String> = ["local".to_string()].into_iter().collect();
+        let selected = group.select_driver_excluding(None, "test.txt", &excluded);
         assert_eq!(selected.unwrap().driver_id, "cloud");
     }
+
+    #[test]
+    fn test_balance_group_lowest_latency() {
+        let mut group = BalanceGroup::new("test".to_string(), LoadBalanceMode::LowestLatency);
+        for (i, id) in ["slow", "fast"].iter().enumerate() {
+            group.add_driver(BalanceDriver {
+                driver_id: id.to_string(),
+                driver_name: id.to_string(),
+                mount_path: "/test".to_string(),
+                weight: 1,
+                capability: DriverCapability::default(),
+                order: i as i32,
+                is_china_node: false,
+            });
+        }
+
+        let latencies: HashMap<String, u64> = [("slow".to_string(), 800), ("fast".to_string(), 120)]
+            .into_iter()
+            .collect();
+        for _ in 0..3 {
+            let selected = group.select_driver_with_latency(None, "test.txt", &HashSet::new(), &latencies);
+            assert_eq!(selected.unwrap().driver_id, "fast");
+        }
+
+        // 没有记录的成员也会定期被选中以获得样本
+        let sampled: HashMap<String, u64> = [("fast".to_string(), 120)].into_iter().collect();
+        let picks: Vec<String> = (0..LATENCY_EXPLORE_EVERY * 2)
+            .map(|_| group.select_driver_with_latency(None, "test.txt", &HashSet::new(), &sampled).unwrap().driver_id.clone())
+            .collect();
+        assert!(picks.iter().any(|id| id == "slow"));
+        assert!(picks.iter().filter(|id| *id == "fast").count() > LATENCY_EXPLORE_EVERY);
+
+        // 没有延迟记录时回退到加权轮询
+        let first = group.select_driver(None, "test.txt").unwrap().driver_id.clone();
+        let second = group.select_driver(None, "test.txt").unwrap().driver_id.clone();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_choose_upload_member() {
+        use crate::storage::SpaceInfo;
+        let candidate = |used: u64, total: u64| WriteCandidate {
+            max_file_size: None,
+            space: Some(SpaceInfo { used, total, free: total - used }),
+        };
+        let candidates = [candidate(95, 100), candidate(50, 1000), candidate(100, 200)];
+        assert_eq!(choose_upload_member(UploadPolicy::None, 90, &candidates, None), None);
+        assert_eq!(choose_upload_member(UploadPolicy::MostFreeSpace, 90, &candidates, None), Some(1));
+        assert_eq!(choose_upload_member(UploadPolicy::UsageThreshold, 90, &candidates, None), Some(1));
+        assert_eq!(choose_upload_member(UploadPolicy::UsageThreshold, 60, &candidates[..1], None), Some(0));
+        // 上传后超过阈值的成员跳过
+        assert_eq!(choose_upload_member(UploadPolicy::UsageThreshold, 60, &[candidate(100, 200), candidate(10, 1000)], Some(30)), Some(1));
+        let unknown = [WriteCandidate::default(), candidate(10, 100)];
+        assert_eq!(choose_upload_member(UploadPolicy::UsageThreshold, 90, &unknown, None), Some(1));
+
+        let config: BalanceGroupConfig = serde_json::from_str(
+            r#"{"name":"g","mode":"failover","drivers":[],"enabled":true}"#
+        ).unwrap();
+        assert_eq!(config.upload_policy, UploadPolicy::None);
+        assert_eq!(config.usage_threshold, DEFAULT_USAGE_THRESHOLD);
+    }
+
+    #[tokio::test]
+    async fn test_member_exclusion_threshold() {
+        let manager = LoadBalanceManager::new();
+        manager.report_failure("a", "error").await;
+        assert!(manager.unhealthy_members().await.is_empty());
+        manager.report_failure("a", "error").await;
+        assert!(manager.unhealthy_members().await.contains("a"));
+        manager.report_success("a", Some(10)).await;
+        assert!(manager.unhealthy_members().await.is_empty());
+    }
 }
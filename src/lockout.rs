@@ -0,0 +1,413 @@
+//! Login brute-force protection / 登录暴力破解防护
+//!
+//! This module handles:
+//! - Configurable lockout policy (attempts, window, lock duration) / 可配置的锁定策略
+//! - Failure counters per client IP and per account / 按 IP 与按账号的失败计数
+//! - Persisting counters in `login_failures` so restarts don't reset them / 计数持久化，重启后不丢失
+//!
+//! A counter resets when no failure happened within the window or when its lock has expired.
+//! 窗口期内无新的失败，或锁定到期后，计数清零。
+
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Lockout policy / 锁定策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockoutPolicy {
+    /// Failures from one IP before it is locked, 0 = never / 同一 IP 失败多少次后锁定，0 不锁定
+    pub ip_max_failures: u32,
+    /// Failures on one account before it is locked, 0 = never / 同一账号失败多少次后锁定，0 不锁定
+    pub account_max_failures: u32,
+    /// Failures older than this are forgotten / 失败计数窗口（分钟）
+    pub window_minutes: u32,
+    /// How long a lock lasts / 锁定时长（分钟）
+    pub lock_minutes: u32,
+    /// Failures before a captcha is required, 0 = always / 失败多少次后需要验证码，0 表示始终需要
+    pub captcha_after_failures: u32,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            ip_max_failures: 5,
+            account_max_failures: 10,
+            window_minutes: 30,
+            lock_minutes: 30,
+            captcha_after_failures: 1,
+        }
+    }
+}
+
+impl LockoutPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_minutes == 0 || self.lock_minutes == 0 {
+            return Err("window_minutes and lock_minutes must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What a counter is keyed by / 计数维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockoutScope {
+    Ip,
+    Account,
+}
+
+impl LockoutScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockoutScope::Ip => "ip",
+            LockoutScope::Account => "account",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ip" => Some(LockoutScope::Ip),
+            "account" => Some(LockoutScope::Account),
+            _ => None,
+        }
+    }
+}
+
+/// Failure counter / 失败计数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureRecord {
+    pub fail_count: u32,
+    pub last_failure: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl FailureRecord {
+    /// Whether the counter still matters at `now` / 计数在 `now` 时是否仍有效
+    fn is_active(&self, policy: &LockoutPolicy, now: DateTime<Utc>) -> bool {
+        match self.locked_until {
+            Some(until) => until > now,
+            None => now - self.last_failure < Duration::minutes(policy.window_minutes as i64),
+        }
+    }
+
+    fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.is_some_and(|until| until > now)
+    }
+
+    /// Count one more failure, locking once `max_failures` is reached / 记一次失败，达到上限时锁定
+    fn next(record: Option<&FailureRecord>, policy: &LockoutPolicy, max_failures: u32, now: DateTime<Utc>) -> FailureRecord {
+        let mut record = record
+            .filter(|r| r.is_active(policy, now))
+            .cloned()
+            .unwrap_or(FailureRecord { fail_count: 0, last_failure: now, locked_until: None });
+        record.fail_count += 1;
+        record.last_failure = now;
+        if max_failures > 0 && record.fail_count >= max_failures && !record.is_locked(now) {
+            record.locked_until = Some(now + Duration::minutes(policy.lock_minutes as i64));
+        }
+        record
+    }
+}
+
+/// Counter as shown to admins / 管理界面展示的计数
+#[derive(Debug, Clone, Serialize)]
+pub struct LockoutEntry {
+    pub scope: LockoutScope,
+    pub key: String,
+    pub fail_count: u32,
+    pub last_failure: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Account counters ignore case and surrounding spaces / 账号计数忽略大小写与首尾空格
+pub fn account_key(identifier: &str) -> String {
+    identifier.trim().to_lowercase()
+}
+
+/// Lockout state / 锁定状态
+pub struct LoginLockout {
+    policy: RwLock<LockoutPolicy>,
+    records: RwLock<HashMap<(LockoutScope, String), FailureRecord>>,
+}
+
+impl LoginLockout {
+    pub fn new() -> Self {
+        Self {
+            policy: RwLock::new(LockoutPolicy::default()),
+            records: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Load the policy and the counters still in effect / 加载策略与仍有效的计数
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let policy: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'login_lockout_policy'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+        if let Some((json,)) = policy {
+            let policy: LockoutPolicy = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            self.set_policy(policy)?;
+        }
+
+        let rows: Vec<(String, String, i64, String, Option<String>)> = sqlx::query_as(
+            "SELECT scope, key, fail_count, last_failure, locked_until FROM login_failures"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let policy = self.get_policy();
+        let now = Utc::now();
+        let parse = |s: &str| DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc));
+        {
+            let mut records = self.records.write();
+            for (scope, key, fail_count, last_failure, locked_until) in rows {
+                let (Some(scope), Some(last_failure)) = (LockoutScope::parse(&scope), parse(&last_failure)) else {
+                    continue;
+                };
+                let record = FailureRecord {
+                    fail_count: fail_count.max(0) as u32,
+                    last_failure,
+                    locked_until: locked_until.as_deref().and_then(parse),
+                };
+                if record.is_active(&policy, now) {
+                    records.insert((scope, key), record);
+                }
+            }
+        }
+        self.prune_db(db).await;
+        Ok(())
+    }
+
+    pub fn get_policy(&self) -> LockoutPolicy {
+        self.policy.read().clone()
+    }
+
+    pub fn set_policy(&self, policy: LockoutPolicy) -> Result<(), String> {
+        policy.validate()?;
+        *self.policy.write() = policy;
+        Ok(())
+    }
+
+    fn active_record(&self, scope: LockoutScope, key: &str, now: DateTime<Utc>) -> Option<FailureRecord> {
+        let policy = self.policy.read().clone();
+        self.records.read()
+            .get(&(scope, key.to_string()))
+            .filter(|r| r.is_active(&policy, now))
+            .cloned()
+    }
+
+    /// End of the lock on `key`, if locked / 返回锁定截止时间
+    pub fn locked_until(&self, scope: LockoutScope, key: &str) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        self.active_record(scope, key, now)
+            .and_then(|r| r.locked_until)
+            .filter(|until| *until > now)
+    }
+
+    /// Failures counted on `key` within the window / 时间窗口内 `key` 的失败次数
+    pub fn fail_count(&self, scope: LockoutScope, key: &str) -> u32 {
+        self.active_record(scope, key, Utc::now()).map(|r| r.fail_count).unwrap_or(0)
+    }
+
+    /// Whether the next login attempt must pass a captcha / 下次登录是否需要验证码
+    pub fn needs_captcha(&self, ip: &str, account: Option<&str>) -> bool {
+        let threshold = self.policy.read().captcha_after_failures;
+        if threshold == 0 {
+            return true;
+        }
+        let now = Utc::now();
+        let count = |scope, key: &str| self.active_record(scope, key, now).map(|r| r.fail_count).unwrap_or(0);
+        count(LockoutScope::Ip, ip) >= threshold
+            || account.is_some_and(|a| count(LockoutScope::Account, &account_key(a)) >= threshold)
+    }
+
+    /// Record a failed login; returns the lock end if this failure caused a lock
+    /// 记录一次登录失败，若因此触发锁定则返回锁定截止时间
+    pub async fn record_failure(&self, db: &SqlitePool, ip: &str, account: &str) -> Option<DateTime<Utc>> {
+        let policy = self.get_policy();
+        let now = Utc::now();
+        let mut new_lock = None;
+        let updates = [
+            (LockoutScope::Ip, ip.to_string(), policy.ip_max_failures),
+            (LockoutScope::Account, account_key(account), policy.account_max_failures),
+        ];
+        let mut changed = Vec::new();
+        {
+            let mut records = self.records.write();
+            records.retain(|_, r| r.is_active(&policy, now));
+            for (scope, key, max_failures) in updates {
+                let previous = records.get(&(scope, key.clone()));
+                let was_locked = previous.is_some_and(|r| r.is_locked(now));
+                let record = FailureRecord::next(previous, &policy, max_failures, now);
+                if !was_locked && record.is_locked(now) {
+                    tracing::warn!("Login locked for {} {} until {:?}", scope.as_str(), key, record.locked_until);
+                    new_lock = new_lock.max(record.locked_until);
+                }
+                records.insert((scope, key.clone()), record.clone());
+                changed.push((scope, key, record));
+            }
+        }
+
+        for (scope, key, record) in changed {
+            let result = sqlx::query(
+                "INSERT OR REPLACE INTO login_failures (scope, key, fail_count, last_failure, locked_until) VALUES (?, ?, ?, ?, ?)"
+            )
+            .bind(scope.as_str())
+            .bind(&key)
+            .bind(record.fail_count as i64)
+            .bind(record.last_failure.to_rfc3339())
+            .bind(record.locked_until.map(|t| t.to_rfc3339()))
+            .execute(db)
+            .await;
+            if let Err(e) = result {
+                tracing::warn!("Failed to persist login failure: {}", e);
+            }
+        }
+        self.prune_db(db).await;
+        new_lock
+    }
+
+    /// Re-read one counter from the database, written by another replica / 从数据库重新读取计数（可能由其他实例写入）
+    pub async fn refresh(&self, db: &SqlitePool, scope: LockoutScope, key: &str) {
+        let row: Option<(i64, String, Option<String>)> = match sqlx::query_as(
+            "SELECT fail_count, last_failure, locked_until FROM login_failures WHERE scope = ? AND key = ?"
+        )
+        .bind(scope.as_str())
+        .bind(key)
+        .fetch_optional(db)
+        .await
+        {
+            Ok(row) => row,
+            Err(e) => {
+                tracing::warn!("Failed to refresh login failures: {}", e);
+                return;
+            }
+        };
+        let parse = |s: &str| DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc));
+        let record = row.and_then(|(fail_count, last_failure, locked_until)| Some(FailureRecord {
+            fail_count: fail_count.max(0) as u32,
+            last_failure: parse(&last_failure)?,
+            locked_until: locked_until.as_deref().and_then(parse),
+        }));
+        let mut records = self.records.write();
+        match record {
+            Some(record) => records.insert((scope, key.to_string()), record),
+            None => records.remove(&(scope, key.to_string())),
+        };
+    }
+
+    /// Successful login clears both counters / 登录成功后清除计数
+    pub async fn clear(&self, db: &SqlitePool, ip: &str, account: &str) {
+        self.unlock(db, LockoutScope::Ip, ip).await;
+        self.unlock(db, LockoutScope::Account, &account_key(account)).await;
+    }
+
+    /// Drop one counter (admin unlock) / 删除计数（管理员解锁）
+    pub async fn unlock(&self, db: &SqlitePool, scope: LockoutScope, key: &str) -> bool {
+        let removed = self.records.write().remove(&(scope, key.to_string())).is_some();
+        let _ = sqlx::query("DELETE FROM login_failures WHERE scope = ? AND key = ?")
+            .bind(scope.as_str())
+            .bind(key)
+            .execute(db)
+            .await;
+        removed
+    }
+
+    /// Counters still in effect, locked ones first / 仍有效的计数，已锁定的排在前面
+    pub fn list(&self) -> Vec<LockoutEntry> {
+        let policy = self.get_policy();
+        let now = Utc::now();
+        let mut entries: Vec<LockoutEntry> = self.records.read().iter()
+            .filter(|(_, r)| r.is_active(&policy, now))
+            .map(|((scope, key), r)| LockoutEntry {
+                scope: *scope,
+                key: key.clone(),
+                fail_count: r.fail_count,
+                last_failure: r.last_failure,
+                locked_until: r.locked_until,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.locked_until.cmp(&a.locked_until).then(b.last_failure.cmp(&a.last_failure)));
+        entries
+    }
+
+    /// Delete rows that no longer matter / 删除已失效的记录
+    async fn prune_db(&self, db: &SqlitePool) {
+        let policy = self.get_policy();
+        let now = Utc::now();
+        let cutoff = now - Duration::minutes(policy.window_minutes as i64);
+        let _ = sqlx::query(
+            "DELETE FROM login_failures WHERE (locked_until IS NULL AND last_failure < ?) OR locked_until < ?"
+        )
+        .bind(cutoff.to_rfc3339())
+        .bind(now.to_rfc3339())
+        .execute(db)
+        .await;
+    }
+}
+
+impl Default for LoginLockout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_counting() {
+        let policy = LockoutPolicy { ip_max_failures: 3, window_minutes: 10, lock_minutes: 5, ..Default::default() };
+        let t0 = Utc::now();
+
+        let mut record = None;
+        for i in 0..2 {
+            record = Some(FailureRecord::next(record.as_ref(), &policy, 3, t0 + Duration::minutes(i)));
+        }
+        assert_eq!(record.as_ref().unwrap().fail_count, 2);
+        assert!(record.as_ref().unwrap().locked_until.is_none());
+
+        // 第三次失败触发锁定
+        let locked = FailureRecord::next(record.as_ref(), &policy, 3, t0 + Duration::minutes(2));
+        assert_eq!(locked.locked_until, Some(t0 + Duration::minutes(7)));
+        assert!(locked.is_active(&policy, t0 + Duration::minutes(6)));
+
+        // 锁定到期后重新计数
+        let after = FailureRecord::next(Some(&locked), &policy, 3, t0 + Duration::minutes(8));
+        assert_eq!(after.fail_count, 1);
+        assert!(after.locked_until.is_none());
+
+        // 超出窗口期后重新计数
+        let stale = FailureRecord::next(record.as_ref(), &policy, 3, t0 + Duration::minutes(12));
+        assert_eq!(stale.fail_count, 1);
+
+        // 上限为 0 时从不锁定
+        let mut unlimited = None;
+        for _ in 0..20 {
+            unlimited = Some(FailureRecord::next(unlimited.as_ref(), &policy, 0, t0));
+        }
+        assert!(unlimited.unwrap().locked_until.is_none());
+    }
+
+    #[test]
+    fn test_needs_captcha() {
+        let lockout = LoginLockout::new();
+        assert!(!lockout.needs_captcha("1.2.3.4", Some("Admin")));
+        lockout.records.write().insert(
+            (LockoutScope::Account, "admin".to_string()),
+            FailureRecord { fail_count: 1, last_failure: Utc::now(), locked_until: None },
+        );
+        assert!(lockout.needs_captcha("1.2.3.4", Some(" Admin ")));
+        assert!(!lockout.needs_captcha("1.2.3.4", None));
+        lockout.set_policy(LockoutPolicy { captcha_after_failures: 0, ..Default::default() }).unwrap();
+        assert!(lockout.needs_captcha("5.6.7.8", None));
+        assert!(lockout.set_policy(LockoutPolicy { lock_minutes: 0, ..Default::default() }).is_err());
+    }
+}
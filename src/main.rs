@@ -5,10 +5,10 @@ use axum::{
     response::{Response, IntoResponse},
     http::{header, StatusCode, Uri},
     body::Body,
+    extract::State,
 };
 use sqlx::sqlite::SqlitePool;
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
 use tower_cookies::CookieManagerLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use rust_embed::RustEmbed;
@@ -22,12 +22,16 @@ struct FrontendAssets;
 
 mod api;
 mod auth;
+mod bootstrap;
+mod cli;
+mod cluster_sync;
 mod db;
 mod state;
 mod task;
 
 use yaolist_backend::models;
 use yaolist_backend::config;
+use yaolist_backend::secrets;
 use state::AppState;
 use chrono::Utc;
 
@@ -63,9 +67,21 @@ async fn save_driver_config_to_db(db: &SqlitePool, id: &str, updated_config: ser
 }
 
 /// Handle embedded static file requests / 处理嵌入的静态文件请求
-async fn serve_embedded_file(uri: Uri) -> impl IntoResponse {
+async fn serve_embedded_file(State(state): State<Arc<AppState>>, uri: Uri) -> impl IntoResponse {
     let path = uri.path().trim_start_matches('/');
     
+    // Custom favicon replaces the embedded one / 自定义网站图标替换内置图标
+    if path == "favicon.ico" {
+        let workspace_id = yaolist_backend::workspace::current().id().to_string();
+        if let Some((data, meta)) = state.branding.read(&workspace_id, yaolist_backend::branding::BrandingAsset::Favicon).await {
+            return Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, meta.content_type)
+                .body(Body::from(data))
+                .unwrap();
+        }
+    }
+    
     // Try to get requested file / 尝试获取请求的文件
     if let Some(content) = FrontendAssets::get(path) {
         let mime = mime_guess::from_path(path).first_or_octet_stream();
@@ -78,10 +94,17 @@ async fn serve_embedded_file(uri: Uri) -> impl IntoResponse {
     
     // If directory or file not found, try return index.html (SPA routing support) / 目录或文件不存在时返回index.html
     if let Some(content) = FrontendAssets::get("index.html") {
+        // Add the site name, favicon and custom CSS / JS / 注入站点名称、网站图标与自定义 CSS / JS
+        let branding = state.branding.effective(yaolist_backend::workspace::current().id()).await;
+        let html = yaolist_backend::branding::inject_into_index(
+            &String::from_utf8_lossy(&content.data),
+            &branding,
+            &api::branding::workspace_base(),
+        );
         return Response::builder()
             .status(StatusCode::OK)
             .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
-            .body(Body::from(content.data.into_owned()))
+            .body(Body::from(html))
             .unwrap();
     }
     
@@ -91,18 +114,388 @@ async fn serve_embedded_file(uri: Uri) -> impl IntoResponse {
         .unwrap()
 }
 
+/// Answer ACME HTTP-01 challenges / 应答 ACME HTTP-01 验证
+async fn acme_challenge(
+    axum::extract::State(challenges): axum::extract::State<Arc<yaolist_backend::tls::AcmeChallenges>>,
+    axum::extract::Path(token): axum::extract::Path<String>,
+) -> Response {
+    match challenges.get(&token) {
+        Some(key_authorization) => key_authorization.into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Redirect plain HTTP to the HTTPS listener / 将 HTTP 请求重定向到 HTTPS
+async fn redirect_to_https(headers: axum::http::HeaderMap, uri: Uri) -> Response {
+    let host = headers.get(header::HOST).and_then(|h| h.to_str().ok()).unwrap_or_default();
+    // 去掉端口（兼容IPv6字面量）
+    let host = match host.rsplit_once(':') {
+        Some((h, port)) if !port.contains(']') => h,
+        _ => host,
+    };
+    if host.is_empty() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let port = config::config().server.port;
+    let authority = if port == 443 { host.to_string() } else { format!("{}:{}", host, port) };
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    axum::response::Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
+
+/// Set the ALPN protocols of the TLS listener / 设置 TLS 监听协商的应用层协议
+fn apply_alpn(rustls_config: &axum_server::tls_rustls::RustlsConfig, http2: bool) {
+    let mut server_config = (*rustls_config.get_inner()).clone();
+    server_config.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    rustls_config.reload_from_config(Arc::new(server_config));
+}
+
+/// Prepare HTTPS: load the certificate, or run the ACME HTTP port and obtain one
+/// 准备 HTTPS：加载证书，ACME 模式下启动 HTTP 端口并申请证书
+async fn setup_tls(
+    state: &Arc<AppState>,
+    app_config: &config::AppConfig,
+    mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
+) -> anyhow::Result<axum_server::tls_rustls::RustlsConfig> {
+    use anyhow::Context;
+    use axum_server::tls_rustls::RustlsConfig;
+    use yaolist_backend::scheduler::{JobHandler, JobSpec};
+    use yaolist_backend::tls::{acme_cert_paths, issue_certificate, needs_renewal, AcmeChallenges};
+
+    let tls = &app_config.tls;
+    let http2 = app_config.server.http2;
+    if !tls.acme.enabled {
+        let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+            .await
+            .with_context(|| format!("Failed to load certificate {} / key {}", tls.cert_path, tls.key_path))?;
+        apply_alpn(&rustls_config, http2);
+        return Ok(rustls_config);
+    }
+
+    // HTTP port: challenges + redirect / HTTP端口：验证与重定向
+    let challenges = Arc::new(AcmeChallenges::new());
+    let http_addr = format!("{}:{}", app_config.server.host, tls.acme.http_port);
+    let http_listener = tokio::net::TcpListener::bind(&http_addr)
+        .await
+        .with_context(|| format!("Failed to bind ACME HTTP port {}", http_addr))?;
+    let http_app = Router::new()
+        .route("/.well-known/acme-challenge/:token", get(acme_challenge))
+        .fallback(redirect_to_https)
+        .with_state(challenges.clone());
+    tokio::spawn(async move {
+        let result = axum::serve(http_listener, http_app)
+            .with_graceful_shutdown(async move {
+                let _ = shutdown_rx.changed().await;
+            })
+            .await;
+        if let Err(e) = result {
+            tracing::error!("ACME HTTP listener failed: {}", e);
+        }
+    });
+    tracing::info!("ACME HTTP listener running at http://{}", http_addr);
+
+    let dir = app_config.get_acme_dir();
+    let (cert_path, key_path) = acme_cert_paths(&dir);
+    if needs_renewal(&cert_path, Utc::now()) {
+        tracing::info!("Requesting certificate for {:?}", tls.acme.domains);
+        if let Err(e) = issue_certificate(&tls.acme, &dir, &challenges).await {
+            if !cert_path.exists() {
+                return Err(e.context("Failed to obtain certificate"));
+            }
+            tracing::error!("Certificate renewal failed, keeping the current one: {}", e);
+        }
+    }
+    let rustls_config = RustlsConfig::from_pem_file(&cert_path, &key_path)
+        .await
+        .context("Failed to load ACME certificate")?;
+    apply_alpn(&rustls_config, http2);
+
+    // Daily renewal check, hot-swaps the certificate / 每日检查续期，证书热更新
+    let handler: JobHandler = {
+        let acme = tls.acme.clone();
+        let rustls_config = rustls_config.clone();
+        Arc::new(move || {
+            let acme = acme.clone();
+            let dir = dir.clone();
+            let challenges = challenges.clone();
+            let rustls_config = rustls_config.clone();
+            Box::pin(async move {
+                let (cert_path, key_path) = acme_cert_paths(&dir);
+                if !needs_renewal(&cert_path, Utc::now()) {
+                    return Ok("Certificate is not due for renewal".to_string());
+                }
+                issue_certificate(&acme, &dir, &challenges).await.map_err(|e| e.to_string())?;
+                rustls_config.reload_from_pem_file(&cert_path, &key_path).await.map_err(|e| e.to_string())?;
+                apply_alpn(&rustls_config, http2);
+                Ok("Certificate renewed".to_string())
+            })
+        })
+    };
+    let spec = JobSpec {
+        id: "acme_renew",
+        name: "HTTPS certificate renewal / HTTPS 证书续期",
+        description: "Renew the ACME certificate when it expires within 30 days / 证书30天内过期时自动续期",
+        default_cron: "0 4 * * *",
+        default_enabled: true,
+        default_jitter_secs: 3600,
+    };
+    if let Err(e) = state.scheduler.register(spec, handler).await {
+        tracing::error!("Failed to register scheduled job acme_renew: {}", e);
+    }
+
+    Ok(rustls_config)
+}
+
+/// Register built-in scheduled jobs / 注册内置定时任务
+async fn register_scheduled_jobs(state: &Arc<AppState>) {
+    use yaolist_backend::scheduler::{JobHandler, JobSpec};
+
+    let jobs: Vec<(JobSpec, JobHandler)> = vec![
+        (
+            JobSpec {
+                id: "task_cleanup",
+                name: "Task cleanup / 任务清理",
+                description: "Remove finished tasks past the retention period / 清理超过保留期的已结束任务",
+                default_cron: "0 * * * *",
+                default_enabled: true,
+                default_jitter_secs: 60,
+            },
+            {
+                let state = state.clone();
+                Arc::new(move || {
+                    let state = state.clone();
+                    Box::pin(async move {
+                        let (memory, db) = state.task_manager.cleanup_expired().await;
+                        Ok(format!("Removed {} tasks from memory, {} from database", memory, db))
+                    })
+                })
+            },
+        ),
+        (
+            JobSpec {
+                id: "session_cleanup",
+                name: "Session cleanup / 会话清理",
+                description: "Delete expired sessions, sessions of expired accounts and verification codes / 删除过期会话、过期账号的会话与验证码",
+                default_cron: "30 * * * *",
+                default_enabled: true,
+                default_jitter_secs: 60,
+            },
+            {
+                let state = state.clone();
+                Arc::new(move || {
+                    let state = state.clone();
+                    Box::pin(async move {
+                        let now = Utc::now().to_rfc3339();
+                        // 过期账号的会话一并删除
+                        let sessions = sqlx::query(
+                            "DELETE FROM sessions WHERE expires_at < ?
+                             OR user_id IN (SELECT id FROM users WHERE expires_at IS NOT NULL AND expires_at <= ?)"
+                        )
+                            .bind(&now)
+                            .bind(&now)
+                            .execute(&state.db)
+                            .await
+                            .map_err(|e| e.to_string())?
+                            .rows_affected();
+                        let codes = sqlx::query("DELETE FROM verification_codes WHERE expires_at < ?")
+                            .bind(&now)
+                            .execute(&state.db)
+                            .await
+                            .map_err(|e| e.to_string())?
+                            .rows_affected();
+                        Ok(format!("Removed {} sessions, {} verification codes", sessions, codes))
+                    })
+                })
+            },
+        ),
+        (
+            JobSpec {
+                id: "index_rebuild",
+                name: "Search index rebuild / 重建搜索索引",
+                description: "Rebuild the search index from all enabled drivers / 从所有启用的驱动重建搜索索引",
+                default_cron: "0 3 * * *",
+                default_enabled: false,
+                default_jitter_secs: 600,
+            },
+            {
+                let state = state.clone();
+                Arc::new(move || {
+                    let state = state.clone();
+                    Box::pin(async move {
+                        api::search::trigger_rebuild_index(state).await?;
+                        Ok("Index rebuild started".to_string())
+                    })
+                })
+            },
+        ),
+        (
+            JobSpec {
+                id: "strm_export",
+                name: "STRM export / STRM 导出",
+                description: "Refresh .strm files of all enabled STRM exports / 刷新所有启用的 STRM 导出",
+                default_cron: "0 */6 * * *",
+                default_enabled: true,
+                default_jitter_secs: 300,
+            },
+            {
+                let state = state.clone();
+                Arc::new(move || {
+                    let state = state.clone();
+                    Box::pin(api::strm::run_all_exports(state))
+                })
+            },
+        ),
+        (
+            JobSpec {
+                id: "tiering",
+                name: "Archival tiering / 归档分层",
+                description: "Move files idle for too long from hot to cold mounts / 将长期未访问的文件从热存储移到冷存储",
+                default_cron: "0 4 * * *",
+                default_enabled: true,
+                default_jitter_secs: 600,
+            },
+            {
+                let state = state.clone();
+                Arc::new(move || {
+                    let state = state.clone();
+                    Box::pin(api::tiering::run_all_policies(state))
+                })
+            },
+        ),
+        (
+            JobSpec {
+                id: "storage_usage",
+                name: "Storage usage / 存储用量采集",
+                description: "Record the space of all mounts and alert when usage crosses the threshold / 记录所有挂载点空间，使用率超过阈值时告警",
+                default_cron: "*/30 * * * *",
+                default_enabled: true,
+                default_jitter_secs: 60,
+            },
+            {
+                let state = state.clone();
+                Arc::new(move || {
+                    let state = state.clone();
+                    Box::pin(api::storage_usage::collect_storage_usage(state))
+                })
+            },
+        ),
+        (
+            JobSpec {
+                id: "upload_cleanup",
+                name: "Upload cleanup / 中断上传清理",
+                description: "Purge stale upload temp files and abort unfinished multipart sessions / 清理过期的上传临时文件并中止未完成的分片上传会话",
+                default_cron: "15 * * * *",
+                default_enabled: true,
+                default_jitter_secs: 120,
+            },
+            {
+                let state = state.clone();
+                Arc::new(move || {
+                    let state = state.clone();
+                    Box::pin(api::upload_cleanup::cleanup_stale_uploads(state))
+                })
+            },
+        ),
+    ];
+
+    for (spec, handler) in jobs {
+        let id = spec.id;
+        if let Err(e) = state.scheduler.register(spec, handler).await {
+            tracing::error!("Failed to register scheduled job {}: {}", id, e);
+        }
+    }
+}
+
+/// Wait for Ctrl+C or SIGTERM / 等待 Ctrl+C 或 SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Checkpoint tasks and flush state before exit / 停机前保存任务断点并刷新缓存
+async fn checkpoint_on_shutdown(state: &AppState) {
+    state.scheduler.stop();
+
+    let checkpointed = state.task_manager.checkpoint_for_shutdown().await;
+    tracing::info!("Checkpointed {} running task(s)", checkpointed);
+
+    // Persist refreshed driver tokens / 保存驱动刷新后的配置（如token）
+    for id in state.storage_manager.list_drivers().await {
+        if let Some(updated_config) = state.storage_manager.get_driver_updated_config(&id).await {
+            if let Err(e) = save_driver_config_to_db(&state.db, &id, updated_config).await {
+                tracing::warn!("Failed to save driver config: {} - {}", id, e);
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = <cli::Cli as clap::Parser>::parse();
+    let command = cli.command.filter(|c| !matches!(c, cli::Command::Serve));
+
+    // Log filter can be changed at runtime via config / 日志过滤规则可通过配置即时修改
+    // Subcommands only log warnings unless RUST_LOG is set / 子命令默认只输出警告
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env();
+    let log_from_env = env_filter.is_ok();
+    let default_level = if command.is_some() { "warn" } else { config::DEFAULT_LOG_LEVEL };
+    let (log_filter, log_handle) = tracing_subscriber::reload::Layer::new(
+        env_filter.unwrap_or_else(|_| default_level.into()),
+    );
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "yaolist_backend=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(log_filter)
+        // Mask tokens and cookies drivers may log / 隐藏驱动日志中可能出现的令牌与 Cookie
+        .with(tracing_subscriber::fmt::layer().with_writer(secrets::ScrubbingMakeWriter(std::io::stdout)))
         .init();
 
     // Load configuration / 加载配置
-    let app_config = config::load_config().expect("Failed to load configuration");
+    let app_config = config::init_config().expect("Failed to load configuration").read().clone();
+    if let Some(command) = command {
+        if let Err(e) = cli::run(command, &app_config).await {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let apply_log_level = move |c: &config::AppConfig| -> Result<(), String> {
+        let filter = tracing_subscriber::EnvFilter::try_new(c.log.level.trim())
+            .map_err(|e| format!("Invalid log.level: {}", e))?;
+        log_handle.reload(filter).map_err(|e| e.to_string())
+    };
+    if !log_from_env {
+        if let Err(e) = apply_log_level(&app_config) {
+            tracing::warn!("{}", e);
+        }
+    }
+    config::set_live_applier(Box::new(apply_log_level));
     tracing::info!("Server will listen on {}:{}", app_config.server.host, app_config.server.port);
 
     // Create data directory if not exists / 创建数据目录
@@ -115,15 +508,54 @@ async fn main() -> anyhow::Result<()> {
     let database_url = std::env::var("DATABASE_URL")
         .unwrap_or_else(|_| app_config.get_database_url());
 
-    let pool = SqlitePool::connect(&database_url).await?;
+    let pool = db::connect(&database_url, &app_config.database).await?;
     
     db::run_migrations(&pool).await?;
 
+    // Reconcile bootstrap.yaml before anything is loaded / 在加载数据前同步 bootstrap.yaml
+    if let Err(e) = bootstrap::apply_if_present(&pool).await {
+        tracing::error!("{:#}", e);
+        std::process::exit(1);
+    }
+
+    // Shared state backend for multiple replicas / 多实例共享状态后端
+    let cluster = match yaolist_backend::cluster::Cluster::connect(&app_config.cluster, &pool).await {
+        Ok(cluster) => Arc::new(cluster),
+        Err(e) => {
+            tracing::error!("Failed to connect cluster backend: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if cluster.is_shared() {
+        cluster.start_heartbeat();
+        tracing::info!("Cluster mode: {:?}, instance {}", app_config.cluster.backend, cluster.instance_id());
+    }
+
     let storage_manager = yaolist_backend::storage::StorageManager::new();
     
+    // Listing cache, kept in Redis when replicas share one / 列表缓存，多实例共享 Redis 时保存在 Redis
+    if let Err(e) = storage_manager.list_cache().load_from_db(&pool).await {
+        tracing::warn!("Failed to load listing cache settings: {}", e);
+    }
+    if cluster.backend() == yaolist_backend::config::ClusterBackend::Redis {
+        storage_manager.list_cache().set_shared_store(cluster.store().clone());
+    }
+    
+    // Maintenance mode / 维护模式
+    if let Err(e) = storage_manager.maintenance().load_from_db(&pool).await {
+        tracing::warn!("Failed to load maintenance mode: {}", e);
+    }
+    
     // Register all storage driver factories / 注册所有存储驱动工厂
     yaolist_backend::register_storage_drivers(&storage_manager).await?;
     
+    // Load external driver plugins / 加载外部驱动插件
+    yaolist_backend::storage::plugin::load_plugins(
+        &storage_manager,
+        &app_config.get_plugins_dir(),
+        &app_config.get_plugin_data_dir(),
+    ).await;
+    
     // Load saved driver configs from database / 从数据库加载已保存的驱动配置
     let saved_drivers: Vec<(String, String)> = sqlx::query_as(
         "SELECT name, config FROM drivers WHERE enabled = 1"
@@ -132,6 +564,7 @@ async fn main() -> anyhow::Result<()> {
     .await?;
     
     // Load drivers in background with retry mechanism / 后台异步加载驱动，支持重试
+    let drivers_loading = Arc::new(std::sync::atomic::AtomicUsize::new(0));
     for (name, config_str) in saved_drivers {
         if let Ok(config) = serde_json::from_str::<serde_json::Value>(&config_str) {
             if let Some(driver_type) = config.get("driver_type").and_then(|v| v.as_str()) {
@@ -142,6 +575,8 @@ async fn main() -> anyhow::Result<()> {
                     let n = name.clone();
                     
                     let db = pool.clone();
+                    let loading = drivers_loading.clone();
+                    loading.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
                     // Async load with retry (max 3 attempts, 30s timeout each) / 异步加载支持重试
                     tokio::spawn(async move {
                         let max_retries = 3;
@@ -188,6 +623,7 @@ async fn main() -> anyhow::Result<()> {
                                 }
                             }
                         }
+                        loading.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
                     });
                 }
             }
@@ -197,6 +633,7 @@ async fn main() -> anyhow::Result<()> {
 
     let mut task_manager = task::TaskManager::new();
     task_manager.set_db(pool.clone());
+    task_manager.set_cluster(cluster.clone());
     
     // Load tasks from database / 从数据库加载任务
     task_manager.load_tasks_from_db().await;
@@ -204,10 +641,26 @@ async fn main() -> anyhow::Result<()> {
     // Mark all running tasks as interrupted on startup / 服务器启动时标记运行中任务为中断
     task_manager.interrupt_all_running_tasks().await;
     
+    // Task retention (cleanup runs as a scheduled job) / 任务保留设置（清理由定时任务执行）
+    task_manager.load_retention_from_db().await;
+    
+    // Batched task progress writes / 任务进度批量写入
+    task_manager.start_progress_writer(app_config.database.progress_flush_secs);
+    
     let index_state = Arc::new(state::IndexState::new());
     
     // Initialize load balance manager / 初始化负载均衡管理器
     let load_balance = Arc::new(yaolist_backend::load_balance::LoadBalanceManager::new());
+    let saved_groups: Vec<(String,)> = sqlx::query_as("SELECT config FROM load_balance_groups")
+        .fetch_all(&pool)
+        .await
+        .unwrap_or_default();
+    for (config_str,) in saved_groups {
+        match serde_json::from_str::<yaolist_backend::load_balance::BalanceGroupConfig>(&config_str) {
+            Ok(config) => load_balance.create_group(config).await,
+            Err(e) => tracing::warn!("Failed to parse load balance group config: {}", e),
+        }
+    }
     
     // Initialize download settings / 初始化下载设置
     let download_settings = Arc::new(yaolist_backend::download::DownloadSettings::new());
@@ -216,7 +669,116 @@ async fn main() -> anyhow::Result<()> {
     }
     tracing::info!("Download settings loaded: domain={}", download_settings.get_download_domain());
     
+    // Initialize transfer settings / 初始化传输设置
+    let transfer_settings = Arc::new(yaolist_backend::transfer::TransferSettings::new());
+    if let Err(e) = transfer_settings.load_from_db(&pool).await {
+        tracing::warn!("Failed to load transfer settings: {}", e);
+    }
+    
+    // Initialize CORS / security header settings / 初始化跨域与安全头设置
+    let http_security = Arc::new(yaolist_backend::http_security::HttpSecurity::new());
+    if let Err(e) = http_security.load_from_db(&pool).await {
+        tracing::warn!("Failed to load HTTP security settings: {}", e);
+    }
+    
+    // Initialize response compression settings / 初始化响应压缩设置
+    let compression = Arc::new(yaolist_backend::compression::ResponseCompression::new());
+    if let Err(e) = compression.load_from_db(&pool).await {
+        tracing::warn!("Failed to load response compression settings: {}", e);
+    }
+    
+    // Initialize login lockout and captcha settings / 初始化登录锁定与验证码设置
+    let login_lockout = Arc::new(yaolist_backend::lockout::LoginLockout::new());
+    if let Err(e) = login_lockout.load_from_db(&pool).await {
+        tracing::warn!("Failed to load login lockout state: {}", e);
+    }
+    let captcha = Arc::new(yaolist_backend::captcha::CaptchaSettings::new());
+    if let Err(e) = captcha.load_from_db(&pool).await {
+        tracing::warn!("Failed to load captcha settings: {}", e);
+    }
+    
+    // Initialize guest access policy / 初始化游客访问策略
+    let guest = Arc::new(yaolist_backend::guest::GuestSettings::new());
+    if let Err(e) = guest.load_from_db(&pool).await {
+        tracing::warn!("Failed to load guest policy: {}", e);
+    }
+    
+    // Initialize file type categories / 初始化文件类型分类
+    let file_types = Arc::new(yaolist_backend::file_type::FileTypeSettings::new());
+    if let Err(e) = file_types.load_from_db(&pool).await {
+        tracing::warn!("Failed to load file type categories: {}", e);
+    }
+    
+    // Initialize webhooks / 初始化 Webhook
+    let webhooks = Arc::new(yaolist_backend::webhook::WebhookManager::new());
+    if let Err(e) = webhooks.load_from_db(&pool).await {
+        tracing::warn!("Failed to load webhooks: {}", e);
+    }
+    
+    // Initialize email templates / 初始化邮件模板
+    let email_templates = Arc::new(yaolist_backend::email_template::EmailTemplates::new());
+    if let Err(e) = email_templates.load_from_db(&pool).await {
+        tracing::warn!("Failed to load email templates: {}", e);
+    }
+    
+    // Initialize file hooks / 初始化文件事件钩子
+    let file_hooks = Arc::new(yaolist_backend::file_hook::FileHookManager::new());
+    if let Err(e) = file_hooks.load_from_db(&pool).await {
+        tracing::warn!("Failed to load file hooks: {}", e);
+    }
+    
+    // Initialize STRM exports / 初始化 STRM 导出
+    let strm = Arc::new(yaolist_backend::strm::StrmManager::new());
+    if let Err(e) = strm.load_from_db(&pool).await {
+        tracing::warn!("Failed to load STRM exports: {}", e);
+    }
+    
+    // Initialize archival tiering / 初始化归档分层
+    let tiering = Arc::new(yaolist_backend::tiering::TieringManager::new());
+    if let Err(e) = tiering.load_from_db(&pool).await {
+        tracing::warn!("Failed to load tiering policies: {}", e);
+    }
+    
+    // Duplicate file report / 重复文件报告
+    let dedupe = Arc::new(yaolist_backend::dedupe::DedupeManager::new());
+    
+    // Initialize workspaces / 初始化工作区
+    let workspaces = Arc::new(yaolist_backend::workspace::WorkspaceRegistry::new());
+    if let Err(e) = workspaces.load_from_db(&pool).await {
+        tracing::warn!("Failed to load workspaces: {}", e);
+    }
+    
+    // Initialize announcements / 初始化站点公告
+    let announcements = Arc::new(yaolist_backend::announcement::AnnouncementManager::new());
+    if let Err(e) = announcements.load_from_db(&pool).await {
+        tracing::warn!("Failed to load announcements: {}", e);
+    }
+    
+    // Initialize stored archive passwords / 初始化压缩包密码库
+    let archive_passwords = Arc::new(yaolist_backend::archive_password::ArchivePasswordManager::new());
+    if let Err(e) = archive_passwords.load_from_db(&pool).await {
+        tracing::warn!("Failed to load archive passwords: {}", e);
+    }
+    
+    // Initialize directory feeds / 初始化目录订阅源
+    let feeds = Arc::new(yaolist_backend::feed::FeedManager::new());
+    if let Err(e) = feeds.load_from_db(&pool).await {
+        tracing::warn!("Failed to load feeds: {}", e);
+    }
+    
+    // Branding assets below the data directory / 数据目录下的品牌资源
+    let branding = Arc::new(yaolist_backend::branding::BrandingStore::new(&data_dir));
+    
+    // Gallery thumbnails below the data directory / 数据目录下的相册缩略图
+    let thumbnail_secret = yaolist_backend::gallery::ThumbnailStore::load_secret(&pool).await?;
+    let thumbnails = Arc::new(yaolist_backend::gallery::ThumbnailStore::new(&data_dir, thumbnail_secret));
+    
+    let mut scheduler = yaolist_backend::scheduler::Scheduler::new(pool.clone());
+    scheduler.set_cluster(cluster.clone());
+    let scheduler = Arc::new(scheduler);
+    
     let state = Arc::new(AppState {
+        paths: yaolist_backend::path_resolver::PathResolver::new(pool.clone()),
         db: pool,
         storage_manager,
         task_manager,
@@ -224,15 +786,152 @@ async fn main() -> anyhow::Result<()> {
         index_state,
         load_balance,
         webdav_config: tokio::sync::RwLock::new(yaolist_backend::server::WebDavConfig::default()),
-        login_security: state::LoginSecurity::new(),
+        login_security: state::LoginSecurity::new(cluster.store().clone()),
+        login_lockout,
+        captcha,
+        guest,
+        file_types,
         download_settings,
+        download_transfers: Arc::new(yaolist_backend::download::DownloadTransfers::new()),
+        transfer_settings,
+        http_security,
+        compression,
+        webhooks,
+        email_templates,
+        scheduler,
+        file_hooks,
+        strm,
+        tiering,
+        dedupe,
+        workspaces,
+        announcements,
+        archive_passwords,
+        feeds,
+        branding,
+        thumbnails,
+        drivers_loading,
+        cluster,
     });
+    
+    // Apply changes made by other replicas / 应用其他实例的变更
+    cluster_sync::start(state.clone());
+    
+    // Register and start scheduled jobs / 注册并启动定时任务
+    register_scheduled_jobs(&state).await;
+    state.scheduler.start();
+    
+    // Fetch accessed tiered files back to their hot mount / 将访问过的已分层文件取回热存储
+    api::tiering::start_recall_worker(state.clone());
+    
+    // Forward task events to webhooks / 任务事件推送到 Webhook
+    {
+        let mut events = state.task_manager.subscribe();
+        let webhooks = state.webhooks.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            use tokio::sync::broadcast::error::RecvError;
+            use yaolist_backend::webhook::WebhookEvent;
+            loop {
+                match events.recv().await {
+                    Ok(task::TaskEvent::TaskCompleted { task }) => {
+                        webhooks.emit(WebhookEvent::TaskCompleted, serde_json::json!(task));
+                        tokio::spawn(api::hooks::fire_upload_hooks(state.clone(), task.clone()));
+                        tokio::spawn(api::notification::notify_task_completed(state.clone(), task));
+                    }
+                    Ok(task::TaskEvent::TaskFailed { task }) => {
+                        webhooks.emit(WebhookEvent::TaskFailed, serde_json::json!(task));
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    // Reload config file on SIGHUP / 收到 SIGHUP 时重新加载配置文件
+    #[cfg(unix)]
+    tokio::spawn(async {
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(sig) => sig,
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            match config::reload_config() {
+                Ok(restart) if restart.is_empty() => tracing::info!("Configuration reloaded"),
+                Ok(restart) => tracing::warn!("Configuration reloaded, restart required for: {}", restart.join(", ")),
+                Err(e) => tracing::error!("Failed to reload configuration: {}", e),
+            }
+        }
+    });
+
+    // Listing cache warmup of hot directories / 热门目录列表缓存预热
+    {
+        let sm = state.storage_manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+                yaolist_backend::storage::list_cache::WARM_TICK_SECS,
+            ));
+            loop {
+                interval.tick().await;
+                let warmed = sm.warm_list_cache().await;
+                if warmed > 0 {
+                    tracing::debug!("Listing cache warmed {} paths", warmed);
+                }
+            }
+        });
+    }
+
+    // Driver health monitor: probe drivers and auto reload failed ones / 驱动健康监测与自动重载
+    {
+        let sm = state.storage_manager.clone();
+        let db = state.db.clone();
+        let lb = state.load_balance.clone();
+        let webhooks = state.webhooks.clone();
+        tokio::spawn(async move {
+            use yaolist_backend::storage::health::HealthStatus;
+            let interval = std::time::Duration::from_secs(yaolist_backend::storage::health::HEALTH_CHECK_INTERVAL_SECS);
+            let mut last_status: std::collections::HashMap<String, HealthStatus> = std::collections::HashMap::new();
+            loop {
+                tokio::time::sleep(interval).await;
+                for id in sm.run_health_checks().await {
+                    if let Some(updated_config) = sm.get_driver_updated_config(&id).await {
+                        if let Err(e) = save_driver_config_to_db(&db, &id, updated_config).await {
+                            tracing::warn!("Failed to save driver config: {} - {}", id, e);
+                        }
+                    }
+                }
+                // Notify drivers that just went into error / 驱动刚进入错误状态时推送通知
+                let health = sm.get_all_driver_health().await;
+                for (id, h) in &health {
+                    if h.status == HealthStatus::Error && last_status.get(id) != Some(&HealthStatus::Error) {
+                        webhooks.emit(yaolist_backend::webhook::WebhookEvent::DriverError, serde_json::json!({
+                            "driver_id": id,
+                            "error": h.last_error,
+                            "consecutive_failures": h.consecutive_failures
+                        }));
+                    }
+                }
+                last_status = health.into_iter().map(|(id, h)| (id, h.status)).collect();
+                // Load balance member probes / 负载均衡成员探测
+                lb.probe_members(&sm).await;
+            }
+        });
+    }
 
     let app = Router::new()
         .route("/api/health", get(api::server::health_check))
+        .route("/api/health/ready", get(api::server::readiness_check))
         .route("/api/settings/public", get(api::settings::get_public_settings))
         .route("/api/settings", post(api::settings::update_settings))
         .route("/api/settings/geoip/status", get(api::settings::get_geoip_status))
+        // Branding: logo, favicon, site name, custom CSS / JS / 品牌：Logo、网站图标、站点名称、自定义 CSS / JS
+        .route("/api/branding", get(api::branding::get_branding).post(api::branding::update_branding))
+        .route("/api/branding/:asset", get(api::branding::get_branding_asset)
+            .put(api::branding::upload_branding_asset)
+            .delete(api::branding::delete_branding_asset))
         .route("/api/settings/geoip/download", post(api::settings::download_geoip_db))
         .route("/api/settings/geoip/reload", post(api::settings::reload_geoip_db))
         .route("/api/settings/geoip/config", get(api::settings::get_geoip_config))
@@ -246,6 +945,10 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/auth/permissions", get(api::auth::permissions))
         .route("/api/auth/captcha", get(api::auth::generate_captcha))
         .route("/api/auth/check-captcha", get(api::auth::check_need_captcha))
+        .route("/api/admin/login-lockouts", get(api::auth::list_login_lockouts))
+        .route("/api/admin/login-lockouts/:scope/:key/unlock", post(api::auth::unlock_login_lockout))
+        .route("/api/admin/audit-logs", get(api::auth::list_audit_logs))
+        .route("/api/auth/impersonate/stop", post(api::auth::stop_impersonation))
         .route("/api/auth/forgot-password", post(api::auth::forgot_password))
         .route("/api/auth/reset-password", post(api::auth::reset_password))
         .route("/api/auth/me", get(api::auth::get_current_user))
@@ -257,24 +960,31 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/auth/2fa/disable", post(api::auth::disable_2fa))
         .route("/api/users", get(api::users::list_users))
         .route("/api/users", post(api::users::create_user))
+        .route("/api/users/import", post(api::users::import_users))
         .route("/api/users/:id", get(api::users::get_user))
         .route("/api/users/:id", post(api::users::update_user))
         .route("/api/users/:id/delete", post(api::users::delete_user))
+        .route("/api/users/:id/impersonate", post(api::auth::start_impersonation))
         .route("/api/groups", get(api::groups::list_groups))
         .route("/api/groups", post(api::groups::create_group))
         .route("/api/groups/:id", get(api::groups::get_group))
         .route("/api/groups/:id", post(api::groups::update_group))
         .route("/api/groups/:id/delete", post(api::groups::delete_group))
+        .route("/api/groups/:id/grants", get(api::groups::get_group_grants).post(api::groups::update_group_grants))
         .route("/api/permissions", get(api::groups::list_permissions))
         .route("/api/drivers", get(api::drivers::list_drivers))
         .route("/api/drivers", post(api::drivers::create_driver))
         .route("/api/drivers/available", get(api::drivers::list_available_drivers))
+        .route("/api/admin/plugins", get(api::drivers::list_plugins))
         .route("/api/drivers/:id", post(api::drivers::update_driver))
         .route("/api/drivers/:id/enable", post(api::drivers::enable_driver))
         .route("/api/drivers/:id/disable", post(api::drivers::disable_driver))
         .route("/api/drivers/:id/delete", post(api::drivers::delete_driver))
         .route("/api/drivers/:id/reload", post(api::drivers::reload_driver))
         .route("/api/drivers/:id/space", get(api::drivers::get_driver_space))
+        .route("/api/drivers/:id/capabilities", get(api::drivers::get_driver_capabilities))
+        .route("/api/drivers/:id/visibility", get(api::drivers::get_driver_visibility))
+        .route("/api/drivers/:id/visibility", post(api::drivers::update_driver_visibility))
         .route("/api/driver/thunder/send_sms", post(api::drivers::thunder_send_sms))
         .route("/api/mounts", get(api::mounts::list_mounts))
         .route("/api/mounts", post(api::mounts::create_mount))
@@ -283,6 +993,7 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/mounts/:id/delete", post(api::mounts::delete_mount))
         .route("/api/metas", get(api::meta::list_metas))
         .route("/api/metas", post(api::meta::create_meta))
+        .route("/api/metas/test_hide", post(api::meta::test_hide))
         .route("/api/metas/:id", get(api::meta::get_meta))
         .route("/api/metas/:id", post(api::meta::update_meta))
         .route("/api/metas/:id/delete", post(api::meta::delete_meta))
@@ -299,12 +1010,18 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/shares/:id", post(api::shares::update_share))
         .route("/api/shares/:id/delete", post(api::shares::delete_share))
         .route("/api/shares/:id/toggle", post(api::shares::toggle_share))
+        .route("/api/shares/:id/invite", post(api::shares::invite_share))
+        .route("/api/shares/:id/link", get(api::shares::get_share_link))
+        .route("/api/internal-shares", get(api::shares::list_internal_shares))
+        .route("/api/internal-shares", post(api::shares::create_internal_share))
+        .route("/api/internal-shares/received", get(api::shares::list_received_shares))
+        .route("/api/internal-shares/:id/delete", post(api::shares::delete_internal_share))
         // 分享访问API（公开，无需认证）
         .route("/api/share/:short_id/info", get(api::shares::get_share_info))
         .route("/api/share/:short_id/verify", post(api::shares::verify_share))
         .route("/api/share/:short_id/files", post(api::shares::get_share_files))
         .route("/api/share/:short_id/download/:filename", get(api::shares::get_share_download))
-        .route("/api/fs/list", post(api::files::fs_list))
+        .route("/api/fs/list", post(api::files::fs_list_with_etag))
         .route("/api/fs/get", post(api::files::fs_get))
         .route("/api/fs/mkdir", post(api::files::fs_mkdir))
         .route("/api/fs/write", post(api::files::fs_write))
@@ -313,16 +1030,37 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/fs/move", post(api::files::fs_move))
         .route("/api/fs/copy", post(api::files::fs_copy))
         .route("/api/fs/get_download_url", post(api::files::fs_get_download_url))
+        .route("/api/fs/export_links", post(api::files::fs_export_links))
         .route("/api/fs/get_direct_link", post(api::files::fs_get_direct_link))
         .route("/api/fs/upload", post(api::files::fs_upload))
         .route("/api/fs/upload/status", post(api::files::fs_upload_status))
         .route("/api/fs/upload/batch", post(api::files::fs_create_batch_upload))
         .route("/api/fs/upload/progress", post(api::files::fs_update_upload_progress))
         .route("/api/fs/upload/complete_file", post(api::files::fs_complete_file))
+        .route("/api/fs/exists", post(api::files::fs_exists))
+        .route("/api/fs/clipboard/get", post(api::files::fs_clipboard_get))
+        .route("/api/fs/clipboard/copy", post(api::files::fs_clipboard_copy))
+        .route("/api/fs/clipboard/cut", post(api::files::fs_clipboard_cut))
+        .route("/api/fs/clipboard/paste", post(api::files::fs_clipboard_paste))
+        .route("/api/fs/clipboard/clear", post(api::files::fs_clipboard_clear))
         .route("/api/fs/properties", post(api::files::fs_properties))
+        .route("/api/fs/gallery", post(api::files::fs_gallery))
+        .route("/api/fs/thumbnail", get(api::files::fs_thumbnail))
+        .route("/api/fs/audio/metadata", post(api::files::fs_audio_metadata))
+        .route("/api/fs/audio/cover", get(api::files::fs_audio_cover))
+        .route("/api/fs/audio/playlist", post(api::files::fs_audio_playlist))
+        .route("/api/fs/book/open", post(api::files::fs_book_open))
+        .route("/api/fs/book/epub", get(api::files::fs_book_epub_member))
+        .route("/api/fs/book/position", post(api::files::fs_book_position))
+        .route("/api/fs/progress", get(api::files::fs_get_progress).post(api::files::fs_set_progress))
+        .route("/api/fs/progress/delete", post(api::files::fs_delete_progress))
+        .route("/api/fs/progress/recent", get(api::files::fs_recent_progress))
         .route("/api/admin/fs/list", post(api::files::admin_fs_list))
+        .route("/api/admin/fs/download_tokens", post(api::files::admin_download_tokens))
         .route("/api/tasks/list", post(api::tasks::list_tasks))
+        .route("/api/tasks/history", post(api::tasks::task_history))
         .route("/api/tasks/get", post(api::tasks::get_task))
+        .route("/api/tasks/directories", post(api::tasks::get_task_directories))
         .route("/api/tasks/cancel", post(api::tasks::cancel_task))
         .route("/api/tasks/pause", post(api::tasks::pause_task))
         .route("/api/tasks/resume", post(api::tasks::resume_task))
@@ -331,12 +1069,67 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/tasks/remove", post(api::tasks::remove_task))
         .route("/api/tasks/retry", post(api::tasks::retry_task))
         .route("/api/tasks/restart", post(api::tasks::restart_task))
+        .route("/api/tasks/group/create", post(api::tasks::create_task_group))
+        .route("/api/tasks/group/add", post(api::tasks::add_to_task_group))
+        .route("/api/tasks/group/list", post(api::tasks::list_task_groups))
+        .route("/api/tasks/group/get", post(api::tasks::get_task_group))
+        .route("/api/tasks/group/pause", post(api::tasks::pause_task_group))
+        .route("/api/tasks/group/resume", post(api::tasks::resume_task_group))
+        .route("/api/tasks/group/cancel", post(api::tasks::cancel_task_group))
+        .route("/api/tasks/group/retry", post(api::tasks::retry_task_group))
+        .route("/api/tasks/group/remove", post(api::tasks::remove_task_group))
         .route("/api/fs/archive/list", post(api::archive::archive_list))
+        .route("/api/fs/archive/member", get(api::files::fs_archive_member))
         .route("/api/fs/extract", post(api::extract::extract_archive))
         .route("/api/tasks", get(api::tasks::get_tasks))
         // 备份/恢复API
         .route("/api/admin/backup", get(api::backup::export_backup))
         .route("/api/admin/restore", post(api::backup::import_backup))
+        // 定时任务API
+        .route("/api/admin/jobs", get(api::scheduler::list_jobs))
+        .route("/api/admin/jobs/update", post(api::scheduler::update_job))
+        .route("/api/admin/jobs/trigger", post(api::scheduler::trigger_job))
+        .route("/api/admin/jobs/history", post(api::scheduler::job_history))
+        .route("/api/admin/storage/usage", get(api::storage_usage::admin_storage_usage))
+        .route("/api/admin/config", get(api::server_config::get_config))
+        .route("/api/admin/config", post(api::server_config::update_config))
+        .route("/api/admin/config/reload", post(api::server_config::reload_config))
+        .route("/api/admin/hooks", get(api::hooks::list_hooks))
+        .route("/api/admin/hooks", post(api::hooks::save_hook))
+        .route("/api/admin/hooks/delete", post(api::hooks::delete_hook))
+        .route("/api/admin/hooks/test", post(api::hooks::test_hook))
+        .route("/api/admin/virus-scans", get(api::virus_scan::list_scan_results))
+        .route("/api/admin/virus-scans/test", post(api::virus_scan::test_scanner))
+        .route("/api/admin/strm", get(api::strm::list_exports))
+        .route("/api/admin/strm", post(api::strm::save_export))
+        .route("/api/admin/strm/delete", post(api::strm::delete_export))
+        .route("/api/admin/strm/run", post(api::strm::run_export_now))
+        .route("/api/admin/tiering", get(api::tiering::list_policies))
+        .route("/api/admin/tiering", post(api::tiering::save_policy))
+        .route("/api/admin/tiering/delete", post(api::tiering::delete_policy))
+        .route("/api/admin/tiering/run", post(api::tiering::run_policy_now))
+        .route("/api/admin/tiering/files", get(api::tiering::list_tiered_files))
+        .route("/api/admin/tiering/recall", post(api::tiering::recall_now))
+        .route("/api/admin/dedupe", get(api::dedupe::get_report))
+        .route("/api/admin/dedupe/scan", post(api::dedupe::start_scan))
+        .route("/api/admin/dedupe/resolve", post(api::dedupe::resolve_group))
+        .route("/api/admin/fsck", get(api::fsck::get_fsck_report))
+        .route("/api/admin/fsck", post(api::fsck::create_fsck_task))
+        .route("/api/admin/static-site", post(api::static_site::create_static_site_task))
+        // Workspaces / 工作区
+        .route("/api/admin/workspaces", get(api::workspaces::list_workspaces))
+        .route("/api/admin/workspaces", post(api::workspaces::save_workspace))
+        .route("/api/admin/workspaces/delete", post(api::workspaces::delete_workspace))
+        // Announcements / 站点公告
+        .route("/api/admin/announcements", get(api::announcements::list_announcements))
+        .route("/api/admin/announcements", post(api::announcements::save_announcement))
+        .route("/api/admin/announcements/delete", post(api::announcements::delete_announcement))
+        .route("/api/admin/archive-passwords", get(api::archive_passwords::list_archive_passwords))
+        .route("/api/admin/archive-passwords", post(api::archive_passwords::save_archive_password))
+        .route("/api/admin/archive-passwords/delete", post(api::archive_passwords::delete_archive_password))
+        .route("/api/admin/feeds", get(api::feeds::list_feeds))
+        .route("/api/admin/feeds", post(api::feeds::save_feed))
+        .route("/api/admin/feeds/delete", post(api::feeds::delete_feed))
         // 搜索管理API
         .route("/api/admin/search/settings", get(api::search::get_search_settings))
         .route("/api/admin/search/settings", post(api::search::update_search_settings))
@@ -353,6 +1146,13 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/notifications/test/sms", post(api::notification::test_sms))
         .route("/api/notifications/send-code", post(api::notification::send_verification_code))
         .route("/api/notifications/verify-code", post(api::notification::verify_code))
+        .route("/api/notifications/email-templates", get(api::notification::get_email_templates))
+        .route("/api/notifications/email-templates", post(api::notification::save_email_templates))
+        .route("/api/notifications/email-templates/preview", post(api::notification::preview_email_template))
+        .route("/api/notifications/webhooks", get(api::notification::list_webhooks))
+        .route("/api/notifications/webhooks", post(api::notification::save_webhook))
+        .route("/api/notifications/webhooks/delete", post(api::notification::delete_webhook))
+        .route("/api/notifications/webhooks/test", post(api::notification::test_webhook))
         // 负载均衡API
         .route("/api/load_balance/groups", get(api::load_balance::list_groups))
         .route("/api/load_balance/groups", post(api::load_balance::create_group))
@@ -365,24 +1165,137 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/oauth/google/exchange", post(api::oauth::exchange_token))
         .route("/download/:token", get(api::files::fs_download))
         .route("/dlink/*path", get(api::files::direct_link_download))
+        .route("/feed/:id", get(api::files::get_feed))
+        .route("/feed/:id/file", get(api::files::get_feed_file))
         // WebDAV routes
         .route("/dav", axum::routing::any(api::webdav::webdav_handler))
         .route("/dav/", axum::routing::any(api::webdav::webdav_handler))
         .route("/dav/*path", axum::routing::any(api::webdav::webdav_handler))
+        // GraphQL API (graphql feature)
+        .merge(api::graphql_routes())
         // Embedded frontend static files
         .fallback(serve_embedded_file)
-        .layer(DefaultBodyLimit::disable()) // No size limit
+        // Tell other replicas about admin changes / 通知其他实例管理变更
+        .layer(axum::middleware::from_fn_with_state(state.clone(), cluster_sync::publish_changes))
+        // Reject file changes during maintenance / 维护期间拒绝修改文件
+        .layer(axum::middleware::from_fn_with_state(
+            state.storage_manager.maintenance().clone(),
+            yaolist_backend::storage::read_only::maintenance_middleware,
+        ))
+        // Members of groups requiring 2FA must set it up first / 用户组要求两步验证的成员须先完成设置
+        .layer(axum::middleware::from_fn_with_state(state.clone(), api::auth::two_factor_setup_middleware))
+        .layer(axum::middleware::from_fn(yaolist_backend::client_ip::client_ip_middleware))
+        // Body size limits per route and for uploads / 按路由与上传接口限制请求体大小
+        .layer(axum::middleware::from_fn(yaolist_backend::body_limit::body_limit_middleware))
+        // Limits come from body_limit_middleware / 请求体上限由 body_limit_middleware 控制
+        .layer(DefaultBodyLimit::disable())
+        // Error codes and localized error messages / 错误码与本地化错误消息
+        .layer(axum::middleware::from_fn(yaolist_backend::i18n::i18n_middleware))
         .layer(CookieManagerLayer::new())
-        .layer(CorsLayer::permissive())
+        .layer(axum::middleware::from_fn_with_state(
+            state.http_security.clone(),
+            yaolist_backend::http_security::http_security_middleware,
+        ))
+        // Gzip / Brotli for JSON and text responses / 压缩 JSON 与文本响应
+        .layer(state.compression.layer())
         .with_state(state.clone());
+    // Pick the workspace before routing so /w/<slug> prefixes can be stripped / 路由前确定工作区，以便去掉 /w/<slug> 前缀
+    let app = Router::new()
+        .fallback_service(app)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), api::workspaces::workspace_middleware))
+        // Request ID on every log line and response / 每条日志与响应都带请求 ID
+        .layer(axum::middleware::from_fn(yaolist_backend::request_id::request_id_middleware));
 
     let bind_addr = app_config.get_bind_address();
     let listener = tokio::net::TcpListener::bind(&bind_addr)
         .await?;
     
-    tracing::info!("Server running at http://{}", bind_addr);
-    
-    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
+    // Stop accepting connections on signal, then drain within the grace period / 收到信号后停止接受连接，在宽限期内排空请求
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+
+    // Optional Unix socket listener for a local reverse proxy / 可选的 Unix 套接字监听（供本机反向代理）
+    let mut unix_server = if app_config.server.unix_socket.trim().is_empty() {
+        None
+    } else {
+        #[cfg(unix)]
+        {
+            let path = std::path::PathBuf::from(app_config.server.unix_socket.trim());
+            let mode = app_config.server.unix_socket_mode().unwrap_or(0o660);
+            let listener = yaolist_backend::server::unix::bind_unix(&path, mode)
+                .map_err(|e| anyhow::anyhow!("Failed to bind Unix socket {:?}: {}", path, e))?;
+            tracing::info!("Server running at unix:{}", path.display());
+            Some(tokio::spawn(yaolist_backend::server::unix::serve_unix(listener, app.clone(), shutdown_rx.clone())))
+        }
+        #[cfg(not(unix))]
+        {
+            tracing::warn!("Unix sockets are not supported on this platform, ignoring server.unix_socket");
+            None
+        }
+    };
+    let mut server = if app_config.tls.enabled {
+        let rustls_config = setup_tls(&state, &app_config, shutdown_rx.clone()).await?;
+        tracing::info!("Server running at https://{}", bind_addr);
+        let handle = axum_server::Handle::new();
+        {
+            let handle = handle.clone();
+            let mut shutdown_rx = shutdown_rx;
+            tokio::spawn(async move {
+                let _ = shutdown_rx.changed().await;
+                handle.graceful_shutdown(None);
+            });
+        }
+        let listener = listener.into_std()?;
+        tokio::spawn(async move {
+            axum_server::from_tcp_rustls(listener, rustls_config)
+                .handle(handle)
+                .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+                .await
+        })
+    } else {
+        tracing::info!("Server running at http://{}", bind_addr);
+        let mut shutdown_rx = shutdown_rx;
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+                .with_graceful_shutdown(async move {
+                    let _ = shutdown_rx.changed().await;
+                })
+                .await
+        })
+    };
+
+    tokio::select! {
+        result = &mut server => {
+            result??;
+            return Ok(());
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    let grace = std::time::Duration::from_secs(config::config().server.shutdown_grace_secs);
+    tracing::info!("Shutting down, waiting up to {:?} for requests and tasks", grace);
+    let _ = shutdown_tx.send(true);
+
+    checkpoint_on_shutdown(&state).await;
+    let drain = async {
+        let _ = (&mut server).await;
+        if let Some(unix_server) = &mut unix_server {
+            let _ = unix_server.await;
+        }
+    };
+    if tokio::time::timeout(grace, drain).await.is_err() {
+        tracing::warn!("Grace period elapsed, closing remaining connections");
+        server.abort();
+        if let Some(unix_server) = &unix_server {
+            unix_server.abort();
+        }
+    }
+
+    // Close databases cleanly / 关闭数据库连接
+    if let Some(index) = state.db_index.read().await.as_ref() {
+        index.close().await;
+    }
+    state.db.close().await;
+    tracing::info!("Shutdown complete");
 
     Ok(())
 }
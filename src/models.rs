@@ -63,6 +63,24 @@ pub struct User {
     pub last_login: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// Workspace the user belongs to / 用户所属工作区
+    #[sqlx(default)]
+    pub workspace_id: String,
+    /// Account expiry (RFC 3339), none for never / 账号过期时间（RFC 3339），为空表示不过期
+    #[sqlx(default)]
+    pub expires_at: Option<String>,
+    /// Next login must set a new password / 下次登录必须修改密码
+    #[sqlx(default)]
+    pub must_change_password: bool,
+    /// Overrides of the group defaults, none to inherit / 覆盖用户组默认设置，为空表示继承
+    #[sqlx(default)]
+    pub allow_share: Option<bool>,
+    #[sqlx(default)]
+    pub traffic_quota: Option<i64>,
+    #[sqlx(default)]
+    pub download_speed_limit: Option<i64>,
+    #[sqlx(default)]
+    pub visible_mounts: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -89,6 +107,27 @@ pub struct UserGroup {
     pub root_path: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// Download traffic quota in bytes, 0 for unlimited / 下载流量配额（字节），0 表示不限
+    #[sqlx(default)]
+    pub traffic_quota: i64,
+    /// Download speed limit in bytes per second, 0 for unlimited / 下载限速（字节每秒），0 表示不限
+    #[sqlx(default)]
+    pub download_speed_limit: i64,
+    /// Max size of one uploaded file in bytes, 0 for unlimited / 单个上传文件的最大字节数，0 表示不限
+    #[sqlx(default)]
+    pub max_upload_size: i64,
+    /// Upload extensions accepted, comma separated, none for any / 允许上传的扩展名，逗号分隔，为空表示不限
+    #[sqlx(default)]
+    pub upload_allow_ext: Option<String>,
+    /// Upload extensions refused, comma separated / 拒绝上传的扩展名，逗号分隔
+    #[sqlx(default)]
+    pub upload_deny_ext: Option<String>,
+    /// Mount paths visible to members, one per line, none for all / 成员可见的挂载路径，每行一个，为空表示全部
+    #[sqlx(default)]
+    pub visible_mounts: Option<String>,
+    /// Members must enable two-factor authentication / 成员必须启用两步验证
+    #[sqlx(default)]
+    pub require_2fa: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -121,6 +160,23 @@ pub struct CreateUserRequest {
     pub email: Option<String>,
     pub phone: Option<String>,
     pub group_ids: Vec<String>,
+    /// Target workspace, only honoured in the default workspace / 目标工作区（仅默认工作区中有效）
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+    /// Account expiry, RFC 3339 or YYYY-MM-DD / 账号过期时间（RFC 3339 或 YYYY-MM-DD）
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub must_change_password: bool,
+    /// Overrides of the group defaults, none to inherit / 覆盖用户组默认设置，为空表示继承
+    #[serde(default)]
+    pub allow_share: Option<bool>,
+    #[serde(default)]
+    pub traffic_quota: Option<i64>,
+    #[serde(default)]
+    pub download_speed_limit: Option<i64>,
+    #[serde(default)]
+    pub visible_mounts: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -141,6 +197,20 @@ pub struct UpdateUserRequest {
     pub two_factor_enabled: Option<bool>,
     #[serde(default)]
     pub group_ids: Option<Vec<String>>,
+    /// Empty string clears the expiry / 空字符串表示取消过期时间
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub must_change_password: Option<bool>,
+    /// Overrides of the group defaults, `null` to inherit again / 覆盖用户组默认设置，`null` 表示恢复继承
+    #[serde(default, deserialize_with = "crate::group_defaults::nullable")]
+    pub allow_share: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "crate::group_defaults::nullable")]
+    pub traffic_quota: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "crate::group_defaults::nullable")]
+    pub download_speed_limit: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "crate::group_defaults::nullable")]
+    pub visible_mounts: Option<Option<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -179,6 +249,12 @@ pub struct Meta {
     pub r_sub: bool,
     pub header: Option<String>,
     pub header_sub: bool,
+    /// Browse and preview only, no raw downloads or direct links / 仅允许浏览与预览，禁止下载原文件和直链
+    pub preview_only: bool,
+    pub po_sub: bool,
+    /// Watermark image and PDF previews with the viewer and time / 图片与 PDF 预览叠加查看者和时间水印
+    pub watermark: bool,
+    pub wm_sub: bool,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -196,6 +272,10 @@ pub struct CreateMetaRequest {
     pub r_sub: Option<bool>,
     pub header: Option<String>,
     pub header_sub: Option<bool>,
+    pub preview_only: Option<bool>,
+    pub po_sub: Option<bool>,
+    pub watermark: Option<bool>,
+    pub wm_sub: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -211,4 +291,8 @@ pub struct UpdateMetaRequest {
     pub r_sub: Option<bool>,
     pub header: Option<String>,
     pub header_sub: Option<bool>,
+    pub preview_only: Option<bool>,
+    pub po_sub: Option<bool>,
+    pub watermark: Option<bool>,
+    pub wm_sub: Option<bool>,
 }
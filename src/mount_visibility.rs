@@ -0,0 +1,163 @@
+//! Per-mount visibility by user group / 按用户组控制挂载点可见性
+//!
+//! A storage (driver) can be limited to selected user groups. Storages without a mapping are
+//! visible to everyone; a mapped storage only exists for members of the mapped groups, so the
+//! mount resolver leaves it out of listings, search, downloads and WebDAV for everyone else.
+//! Administrators see every storage. When several storages share a mount path, the path stays
+//! visible while any of them is.
+//! 存储（驱动）可以限定为仅对所选用户组可见。未设置映射的存储对所有人可见；设置了映射的存储只对这些用户组的成员存在，
+//! 挂载解析对其他用户在列表、搜索、下载和 WebDAV 中都会跳过它。管理员可见全部存储。多个存储挂载在同一路径时，
+//! 只要其中任一可见，该路径即可见。
+
+use std::collections::HashMap;
+
+use sqlx::SqlitePool;
+
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Groups each restricted storage is visible to / 受限存储可见的用户组
+pub type VisibilityRules = HashMap<String, Vec<String>>;
+
+/// Whether a storage is visible to members of `groups` / 存储是否对 `groups` 的成员可见
+pub fn driver_visible(rules: &VisibilityRules, driver_id: &str, groups: &[String]) -> bool {
+    rules
+        .get(driver_id)
+        .is_none_or(|allowed| allowed.iter().any(|g| groups.contains(g)))
+}
+
+/// Storages hidden from members of `groups` / 对 `groups` 的成员隐藏的存储
+pub fn hidden_drivers(rules: &VisibilityRules, groups: &[String]) -> Vec<String> {
+    let mut hidden: Vec<String> = rules
+        .keys()
+        .filter(|id| !driver_visible(rules, id, groups))
+        .cloned()
+        .collect();
+    hidden.sort();
+    hidden
+}
+
+/// Mount paths and whether the user can see them / 挂载路径及其对用户是否可见
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MountVisibility {
+    mounts: Vec<(String, bool)>,
+}
+
+impl MountVisibility {
+    /// Build from `(driver_id, mount_path)` pairs / 由 `(驱动ID, 挂载路径)` 构建
+    pub fn new<'a>(mounts: impl IntoIterator<Item = (&'a str, &'a str)>, hidden: &[String]) -> Self {
+        let mut paths: Vec<(String, bool)> = Vec::new();
+        for (id, mount_path) in mounts {
+            let mount_path = fix_and_clean_path(mount_path);
+            let visible = !hidden.iter().any(|h| h == id);
+            match paths.iter_mut().find(|(p, _)| *p == mount_path) {
+                Some((_, v)) => *v |= visible,
+                None => paths.push((mount_path, visible)),
+            }
+        }
+        Self { mounts: paths }
+    }
+
+    /// Whether the storage serving `path` (the longest matching mount) is visible; paths outside
+    /// every mount are virtual directories and pass
+    /// 提供 `path` 的存储（最长匹配的挂载点）是否可见；不在任何挂载点中的路径是虚拟目录，允许通过
+    pub fn path_visible(&self, path: &str) -> bool {
+        self.mounts
+            .iter()
+            .filter(|(m, _)| is_sub_path(m, path))
+            .max_by_key(|(m, _)| m.len())
+            .is_none_or(|(_, visible)| *visible)
+    }
+}
+
+/// All mappings / 全部映射
+pub async fn load_rules(db: &SqlitePool) -> Result<VisibilityRules, sqlx::Error> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT driver_id, group_id FROM mount_group_visibility ORDER BY driver_id, group_id"
+    )
+    .fetch_all(db)
+    .await?;
+    let mut rules = VisibilityRules::new();
+    for (driver_id, group_id) in rows {
+        rules.entry(driver_id).or_default().push(group_id);
+    }
+    Ok(rules)
+}
+
+/// Groups of one storage, empty when unrestricted / 单个存储的可见用户组，为空表示不限制
+pub async fn load_driver_groups(db: &SqlitePool, driver_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT group_id FROM mount_group_visibility WHERE driver_id = ? ORDER BY group_id")
+        .bind(driver_id)
+        .fetch_all(db)
+        .await
+}
+
+/// Replace the groups of a storage, empty to make it visible to everyone
+/// 替换存储的可见用户组，为空表示对所有人可见
+pub async fn save_driver_groups(db: &SqlitePool, driver_id: &str, groups: &[String]) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut tx = db.begin().await?;
+    sqlx::query("DELETE FROM mount_group_visibility WHERE driver_id = ?")
+        .bind(driver_id)
+        .execute(&mut *tx)
+        .await?;
+    for group_id in groups {
+        sqlx::query("INSERT OR IGNORE INTO mount_group_visibility (driver_id, group_id, created_at) VALUES (?, ?, ?)")
+            .bind(driver_id)
+            .bind(group_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// Group ids of a user / 用户所属的用户组ID
+pub async fn user_groups(db: &SqlitePool, user_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT group_id FROM user_group_members WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+}
+
+/// Group id of the guest group / 游客组的ID
+pub async fn guest_groups(db: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT CAST(id AS TEXT) FROM user_groups WHERE name = '游客组'")
+        .fetch_all(db)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_visibility() {
+        let rules: VisibilityRules = [
+            ("secret".to_string(), vec!["3".to_string()]),
+            ("alias-b".to_string(), vec!["4".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+        let groups = vec!["2".to_string()];
+        assert!(driver_visible(&rules, "public", &groups));
+        assert!(!driver_visible(&rules, "secret", &groups));
+        assert!(driver_visible(&rules, "secret", &["3".to_string()]));
+
+        let hidden = hidden_drivers(&rules, &groups);
+        assert_eq!(hidden, vec!["alias-b", "secret"]);
+        let mounts = [
+            ("public", "/"),
+            ("secret", "/secret"),
+            ("alias-a", "/shared"),
+            ("alias-b", "/shared/"),
+        ];
+        let visibility = MountVisibility::new(mounts, &hidden);
+        assert!(visibility.path_visible("/docs"));
+        assert!(!visibility.path_visible("/secret"));
+        assert!(!visibility.path_visible("/secret/a.txt"));
+        assert!(visibility.path_visible("/secret2"));
+        // 别名挂载中有一个可见即可见
+        assert!(visibility.path_visible("/shared/a"));
+        assert!(MountVisibility::default().path_visible("/anything"));
+    }
+}
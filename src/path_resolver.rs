@@ -0,0 +1,449 @@
+//! Path resolution shared by the file API, WebDAV, shares and search / 文件接口、WebDAV、分享与搜索共用的路径解析
+//!
+//! Every entry point turns a request path into a storage path the same way: join it with the
+//! user's root (staying inside the root and the current workspace), pick the mounts with the
+//! longest matching mount path, and compute the path inside the driver. Paths above the mounts
+//! list them as virtual directories. Meta rules (password, hide, readme, write, preview only,
+//! watermark) come from the nearest meta above a path, looked up with a single query instead of
+//! one per parent.
+//! The enabled mounts are kept in memory; endpoints that change the `drivers` table call
+//! [`PathResolver::invalidate_mounts`], and a short TTL picks up changes made outside the process
+//! (CLI).
+//! 所有入口以同样的方式把请求路径转换为存储路径：与用户根路径结合（不超出根路径与当前工作区），
+//! 选出挂载路径最长匹配的挂载点，并计算驱动内路径。挂载点之上的路径以虚拟目录列出挂载点。
+//! 元信息规则（密码、隐藏、说明、写入、仅预览、水印）取自路径之上最近的元信息，一次查询完成而不是逐级查询父目录。
+//! 已启用的挂载点缓存在内存中：修改 `drivers` 表的接口调用 [`PathResolver::invalidate_mounts`]，
+//! 较短的过期时间用于感知进程外（CLI）的修改。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use crate::models::Meta;
+use crate::storage::display::MountDisplay;
+use crate::upload_policy::UploadPolicy;
+use crate::utils::fix_and_clean_path;
+use crate::workspace;
+
+/// Cached mounts are reloaded after this long even without an invalidation / 即使未失效，缓存的挂载点在此时长后也会重新加载
+pub const MOUNT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Columns of the `metas` table / `metas` 表的列
+pub const META_COLUMNS: &str =
+    "id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, preview_only, po_sub, watermark, wm_sub, created_at, updated_at";
+
+/// A mounted storage / 挂载点信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    /// Driver ID / 驱动ID
+    pub id: String,
+    pub mount_path: String,
+    pub order: i32,
+    /// Display defaults from the driver config / 驱动配置中的显示默认值
+    pub display: MountDisplay,
+    /// Upload policy from the driver config / 驱动配置中的上传策略
+    pub upload: UploadPolicy,
+}
+
+impl MountInfo {
+    /// Read from a `drivers` row, `None` without a mount path / 从 `drivers` 表的行读取，没有挂载路径时为 `None`
+    pub fn from_row(id: &str, config: &str) -> Option<Self> {
+        let config: Value = serde_json::from_str(config).ok()?;
+        let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
+        Some(Self {
+            id: id.to_string(),
+            mount_path: mount_path.to_string(),
+            order: config.get("order").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+            display: config.get("config").map(MountDisplay::from_config).unwrap_or_default(),
+            upload: config.get("config").map(UploadPolicy::from_config).unwrap_or_default(),
+        })
+    }
+}
+
+/// Directory shown above mounts / 挂载点之上显示的虚拟目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualDir {
+    pub name: String,
+    /// Set when the directory is a mount point itself / 目录本身是挂载点时为其驱动ID
+    pub driver_id: Option<String>,
+}
+
+/// Whether `child` is `parent` or below it / `child` 是否为 `parent` 或其子路径
+pub fn is_sub_path(parent: &str, child: &str) -> bool {
+    let parent = fix_and_clean_path(parent);
+    let child = fix_and_clean_path(child);
+
+    if parent == "/" {
+        return true;
+    }
+
+    child == parent || child.starts_with(&format!("{}/", parent))
+}
+
+/// Join a request path with the user's root path (no traversal out of the root or the workspace)
+/// 将用户请求路径与用户根路径结合（防止穿越出根路径或工作区）
+pub fn join_user_path(base_path: &str, req_path: &str) -> Result<String, String> {
+    // `..` is resolved while cleaning, so the result always stays below the base
+    // 清理时已解析 `..`，结果始终位于根路径之下
+    let clean_req = fix_and_clean_path(req_path);
+    let clean_base = fix_and_clean_path(base_path);
+    let full_path = if clean_base == "/" {
+        clean_req
+    } else if clean_req == "/" {
+        clean_base
+    } else {
+        fix_and_clean_path(&format!("{}{}", clean_base, clean_req))
+    };
+
+    // 其他工作区的目录不可访问
+    if !workspace::current().path_visible(&full_path) {
+        return Err("路径越权".to_string());
+    }
+
+    Ok(full_path)
+}
+
+/// Mounts with the longest mount path containing `path`, by order (aliases / load balancing)
+/// 挂载路径最长匹配 `path` 的所有挂载点，按 order 排序（别名/负载均衡）
+pub fn get_matching_mounts<'a>(path: &str, mounts: &'a [MountInfo]) -> Vec<&'a MountInfo> {
+    let path = fix_and_clean_path(path);
+    let best_len = mounts.iter()
+        .map(|mount| fix_and_clean_path(&mount.mount_path))
+        .filter(|mount_path| is_sub_path(mount_path, &path))
+        .map(|mount_path| mount_path.len())
+        .max();
+    let Some(best_len) = best_len else {
+        return Vec::new();
+    };
+
+    let mut result: Vec<&MountInfo> = mounts.iter()
+        .filter(|mount| {
+            let mount_path = fix_and_clean_path(&mount.mount_path);
+            is_sub_path(&mount_path, &path) && mount_path.len() == best_len
+        })
+        .collect();
+    result.sort_by_key(|m| m.order);
+    result
+}
+
+/// First matching mount (writes) / 第一个匹配的挂载点（写操作用）
+pub fn get_first_mount<'a>(path: &str, mounts: &'a [MountInfo]) -> Option<&'a MountInfo> {
+    get_matching_mounts(path, mounts).into_iter().next()
+}
+
+/// Path inside the driver mounted at `mount_path` / 挂载于 `mount_path` 的驱动内的实际路径
+pub fn calculate_internal_path(mount_path: &str, full_path: &str) -> String {
+    let mount_path = fix_and_clean_path(mount_path);
+    let full_path = fix_and_clean_path(full_path);
+
+    if mount_path == "/" {
+        return full_path;
+    }
+
+    if full_path.len() > mount_path.len() {
+        fix_and_clean_path(&full_path[mount_path.len()..])
+    } else {
+        "/".to_string()
+    }
+}
+
+/// Directories leading to mounts below `path`, sorted by name / `path` 之下通往挂载点的目录，按名称排序
+pub fn virtual_dirs(path: &str, mounts: &[MountInfo]) -> Vec<VirtualDir> {
+    let path = fix_and_clean_path(path);
+    let mut dirs: Vec<VirtualDir> = Vec::new();
+
+    for mount in mounts {
+        let mount_path = fix_and_clean_path(&mount.mount_path);
+        if mount_path == path || !is_sub_path(&path, &mount_path) {
+            continue;
+        }
+        let relative = mount_path[path.len()..].trim_start_matches('/');
+        let name = relative.split('/').next().unwrap_or(relative);
+        if name.is_empty() {
+            continue;
+        }
+        // 目录本身是挂载点时记录驱动ID
+        let driver_id = (relative == name).then(|| mount.id.clone());
+        match dirs.iter_mut().find(|d| d.name == name) {
+            Some(dir) => {
+                if dir.driver_id.is_none() {
+                    dir.driver_id = driver_id;
+                }
+            }
+            None => dirs.push(VirtualDir { name: name.to_string(), driver_id }),
+        }
+    }
+
+    dirs.sort_by(|a, b| a.name.cmp(&b.name));
+    dirs
+}
+
+/// `path` and all its parents, deepest first / `path` 及其所有父目录，由深到浅
+fn ancestors(path: &str) -> Vec<String> {
+    let path = fix_and_clean_path(path);
+    let mut result = vec![path.clone()];
+    let mut current = path.as_str();
+    while current != "/" {
+        current = match current.rfind('/') {
+            Some(0) | None => "/",
+            Some(pos) => &current[..pos],
+        };
+        result.push(current.to_string());
+    }
+    result
+}
+
+/// Mounts loaded from the database / 从数据库加载的挂载点
+struct CachedMounts {
+    mounts: Arc<Vec<MountInfo>>,
+    loaded: Instant,
+}
+
+/// Mount and meta lookups backed by the database / 基于数据库的挂载点与元信息查询
+#[derive(Clone)]
+pub struct PathResolver {
+    db: SqlitePool,
+    mounts: Arc<RwLock<Option<CachedMounts>>>,
+    /// Bumped on every invalidation so a load racing with a change is not cached
+    /// 每次失效时递增，避免与修改并发的加载结果被缓存
+    generation: Arc<AtomicU64>,
+}
+
+impl PathResolver {
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            db,
+            mounts: Arc::new(RwLock::new(None)),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn db(&self) -> &SqlitePool {
+        &self.db
+    }
+
+    /// Drop the cached mounts after the `drivers` table changed / `drivers` 表变更后丢弃缓存的挂载点
+    pub fn invalidate_mounts(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *self.mounts.write() = None;
+    }
+
+    /// Enabled mounts visible in the current workspace / 当前工作区可见的已启用挂载点
+    pub async fn mounts(&self) -> Result<Vec<MountInfo>, sqlx::Error> {
+        let workspace = workspace::current();
+        Ok(self.all_mounts().await?
+            .into_iter()
+            .filter(|m| workspace.mount_visible(&m.mount_path))
+            .collect())
+    }
+
+    /// All enabled mounts regardless of workspace (background jobs) / 所有已启用的挂载点，不区分工作区（后台任务用）
+    pub async fn all_mounts(&self) -> Result<Vec<MountInfo>, sqlx::Error> {
+        if let Some(cached) = self.mounts.read().as_ref() {
+            if cached.loaded.elapsed() < MOUNT_CACHE_TTL {
+                return Ok(cached.mounts.as_ref().clone());
+            }
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let rows: Vec<(String, String)> = sqlx::query_as("SELECT name, config FROM drivers WHERE enabled = 1")
+            .fetch_all(&self.db)
+            .await?;
+        let mounts: Vec<MountInfo> = rows.iter().filter_map(|(id, config)| MountInfo::from_row(id, config)).collect();
+
+        let mut cache = self.mounts.write();
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *cache = Some(CachedMounts { mounts: Arc::new(mounts.clone()), loaded: Instant::now() });
+        }
+        Ok(mounts)
+    }
+
+    /// Mount path of a driver (disabled ones included) / 驱动的挂载路径（包括已禁用的驱动）
+    pub async fn mount_path(&self, driver_id: &str) -> Option<String> {
+        if let Ok(mounts) = self.all_mounts().await {
+            if let Some(mount) = mounts.into_iter().find(|m| m.id == driver_id) {
+                return Some(mount.mount_path);
+            }
+        }
+        let config: Option<String> = sqlx::query_scalar("SELECT config FROM drivers WHERE name = ?")
+            .bind(driver_id)
+            .fetch_optional(&self.db)
+            .await
+            .ok()?;
+        MountInfo::from_row(driver_id, &config?).map(|m| m.mount_path)
+    }
+
+    /// Nearest meta at or above `path` / `path` 处或其上最近的元信息
+    pub async fn nearest_meta(&self, path: &str) -> Option<Meta> {
+        self.nearest_meta_where(path, "").await
+    }
+
+    /// Nearest meta with a password; only the nearest password applies
+    /// 最近的设置了密码的元信息，只需验证最近的密码
+    pub async fn nearest_password_meta(&self, path: &str) -> Option<Meta> {
+        self.nearest_meta_where(path, " AND password IS NOT NULL AND password != ''").await
+    }
+
+    /// Nearest meta marked preview only, so metas below it without the flag don't lift it
+    /// 最近的设置了仅预览的元信息，其下未设置该标记的元信息不会解除限制
+    pub async fn nearest_preview_only_meta(&self, path: &str) -> Option<Meta> {
+        self.nearest_meta_where(path, " AND preview_only = 1").await
+    }
+
+    /// Whether the path is preview only: the flag is set on it, or on a parent with `po_sub`
+    /// 路径是否为仅预览：自身设置了该标记，或上级设置且应用到子目录
+    pub async fn is_preview_only(&self, path: &str) -> bool {
+        let path = fix_and_clean_path(path);
+        self.nearest_preview_only_meta(&path).await
+            .is_some_and(|m| fix_and_clean_path(&m.path) == path || (m.po_sub && is_sub_path(&m.path, &path)))
+    }
+
+    /// Whether copying or moving `from` to `to` would take preview-only content (the item
+    /// itself or a flagged path below it) out of the flag; lookup errors count as leaking
+    /// 将 `from` 复制或移动到 `to` 是否会把仅预览的内容（自身或其下设置了标记的路径）带出限制范围；查询失败时视为会带出
+    pub async fn leaks_preview_only(&self, from: &str, to: &str) -> bool {
+        let from = fix_and_clean_path(from);
+        let protected = self.is_preview_only(&from).await || match self.metas().await {
+            Ok(metas) => metas.iter().any(|m| m.preview_only && is_sub_path(&from, &m.path)),
+            Err(_) => true,
+        };
+        protected && !self.is_preview_only(to).await
+    }
+
+    /// Nearest meta asking for watermarked previews / 最近的要求预览加水印的元信息
+    pub async fn nearest_watermark_meta(&self, path: &str) -> Option<Meta> {
+        self.nearest_meta_where(path, " AND watermark = 1").await
+    }
+
+    async fn nearest_meta_where(&self, path: &str, condition: &str) -> Option<Meta> {
+        let paths = ancestors(path);
+        let placeholders = vec!["?"; paths.len()].join(", ");
+        let sql = format!(
+            "SELECT {} FROM metas WHERE path IN ({}){} ORDER BY length(path) DESC LIMIT 1",
+            META_COLUMNS, placeholders, condition
+        );
+        let mut query = sqlx::query_as::<_, Meta>(&sql);
+        for p in &paths {
+            query = query.bind(p);
+        }
+        match query.fetch_optional(&self.db).await {
+            Ok(meta) => meta,
+            Err(e) => {
+                tracing::warn!(path = %path, error = %e, "Failed to look up meta");
+                None
+            }
+        }
+    }
+
+    /// All metas / 所有元信息
+    pub async fn metas(&self) -> Result<Vec<Meta>, sqlx::Error> {
+        sqlx::query_as(&format!("SELECT {} FROM metas", META_COLUMNS))
+            .fetch_all(&self.db)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mount(id: &str, mount_path: &str, order: i32) -> MountInfo {
+        MountInfo { id: id.to_string(), mount_path: mount_path.to_string(), order, display: MountDisplay::default(), upload: UploadPolicy::default() }
+    }
+
+    #[test]
+    fn test_join_user_path() {
+        assert_eq!(join_user_path("/", "/a/b").unwrap(), "/a/b");
+        assert_eq!(join_user_path("/home/alice", "/").unwrap(), "/home/alice");
+        assert_eq!(join_user_path("/home/alice/", "docs//a.txt").unwrap(), "/home/alice/docs/a.txt");
+        assert_eq!(join_user_path("/home/alice", "/../../etc/passwd").unwrap(), "/home/alice/etc/passwd");
+        assert_eq!(join_user_path("/home/alice", "/a..b").unwrap(), "/home/alice/a..b");
+    }
+
+    #[test]
+    fn test_matching_mounts() {
+        let mounts = vec![mount("1", "/", 0), mount("2", "/media", 2), mount("3", "/media/", 1), mount("4", "/mediax", 0)];
+        let ids: Vec<&str> = get_matching_mounts("/media/a.mp4", &mounts).iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["3", "2"]);
+        assert_eq!(get_first_mount("/docs", &mounts).unwrap().id, "1");
+        assert!(get_matching_mounts("/a", &mounts[1..]).is_empty());
+        assert_eq!(calculate_internal_path("/media", "/media/a/b.mp4"), "/a/b.mp4");
+        assert_eq!(calculate_internal_path("/media", "/media"), "/");
+        assert_eq!(calculate_internal_path("/", "/a"), "/a");
+    }
+
+    #[test]
+    fn test_virtual_dirs() {
+        let mounts = vec![mount("1", "/media/movies", 0), mount("2", "/media", 0), mount("3", "/docs", 0), mount("4", "/media/music/x", 0)];
+        assert_eq!(virtual_dirs("/", &mounts), [
+            VirtualDir { name: "docs".to_string(), driver_id: Some("3".to_string()) },
+            VirtualDir { name: "media".to_string(), driver_id: Some("2".to_string()) },
+        ]);
+        assert_eq!(virtual_dirs("/media", &mounts), [
+            VirtualDir { name: "movies".to_string(), driver_id: Some("1".to_string()) },
+            VirtualDir { name: "music".to_string(), driver_id: None },
+        ]);
+        assert!(virtual_dirs("/med", &mounts).is_empty());
+    }
+
+    #[test]
+    fn test_ancestors() {
+        assert_eq!(ancestors("/a/b/"), ["/a/b", "/a", "/"]);
+        assert_eq!(ancestors("/"), ["/"]);
+    }
+
+    #[tokio::test]
+    async fn test_nearest_meta() {
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query(
+            "CREATE TABLE metas (id INTEGER PRIMARY KEY, path TEXT, password TEXT, p_sub BOOLEAN DEFAULT 0,
+             write BOOLEAN DEFAULT 0, w_sub BOOLEAN DEFAULT 0, hide TEXT, h_sub BOOLEAN DEFAULT 0, readme TEXT,
+             r_sub BOOLEAN DEFAULT 0, header TEXT, header_sub BOOLEAN DEFAULT 0, preview_only BOOLEAN DEFAULT 0,
+             po_sub BOOLEAN DEFAULT 0, watermark BOOLEAN DEFAULT 0, wm_sub BOOLEAN DEFAULT 0, created_at TEXT DEFAULT '', updated_at TEXT DEFAULT '')"
+        ).execute(&db).await.unwrap();
+        sqlx::query("INSERT INTO metas (path, password) VALUES ('/', 'root'), ('/a', NULL), ('/a/b/c', 'deep'), ('/ab', NULL)")
+            .execute(&db).await.unwrap();
+        sqlx::query("UPDATE metas SET preview_only = 1, po_sub = 1 WHERE path = '/a'").execute(&db).await.unwrap();
+        sqlx::query("UPDATE metas SET watermark = 1 WHERE path = '/a/b/c'").execute(&db).await.unwrap();
+        let resolver = PathResolver::new(db);
+
+        assert_eq!(resolver.nearest_meta("/a/b/x").await.unwrap().path, "/a");
+        assert_eq!(resolver.nearest_meta("/a/b/c/d").await.unwrap().path, "/a/b/c");
+        assert_eq!(resolver.nearest_meta("/zzz").await.unwrap().path, "/");
+        assert_eq!(resolver.nearest_password_meta("/a/b/x").await.unwrap().path, "/");
+        assert_eq!(resolver.nearest_password_meta("/a/b/c").await.unwrap().password.as_deref(), Some("deep"));
+        assert_eq!(resolver.nearest_preview_only_meta("/a/b/c/d").await.unwrap().path, "/a");
+        assert!(resolver.nearest_preview_only_meta("/ab").await.is_none());
+        assert!(resolver.is_preview_only("/a/b/c/d").await);
+        assert!(!resolver.is_preview_only("/ab/x").await);
+        assert!(resolver.leaks_preview_only("/a/b", "/ab/b").await);
+        assert!(resolver.leaks_preview_only("/", "/ab/all").await);
+        assert!(!resolver.leaks_preview_only("/a/b", "/a/c").await);
+        assert!(!resolver.leaks_preview_only("/ab", "/zzz").await);
+        assert!(resolver.nearest_watermark_meta("/a/b").await.is_none());
+        assert_eq!(resolver.nearest_watermark_meta("/a/b/c/d").await.unwrap().path, "/a/b/c");
+        assert_eq!(resolver.metas().await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_mount_cache() {
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE drivers (name TEXT PRIMARY KEY, enabled BOOLEAN, config TEXT)")
+            .execute(&db).await.unwrap();
+        sqlx::query(r#"INSERT INTO drivers VALUES ('1', 1, '{"mount_path": "/a"}'), ('2', 0, '{"mount_path": "/b"}')"#)
+            .execute(&db).await.unwrap();
+        let resolver = PathResolver::new(db.clone());
+        assert_eq!(resolver.all_mounts().await.unwrap().len(), 1);
+
+        sqlx::query("UPDATE drivers SET enabled = 1 WHERE name = '2'").execute(&db).await.unwrap();
+        assert_eq!(resolver.all_mounts().await.unwrap().len(), 1);
+        assert_eq!(resolver.mount_path("2").await.as_deref(), Some("/b"));
+
+        resolver.clone().invalidate_mounts();
+        assert_eq!(resolver.all_mounts().await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,80 @@
+//! Playback progress / 播放进度
+//!
+//! This module handles:
+//! - Validating positions reported by players / 校验播放器上报的播放位置
+//! - Deciding when a video counts as watched, so it drops out of "continue watching" / 判断视频是否已看完（看完后不再出现在继续观看中）
+
+/// Progress records kept per user, the oldest are dropped / 每个用户保留的进度记录数（超出时删除最旧的）
+pub const MAX_ENTRIES_PER_USER: i64 = 500;
+
+/// Recently watched items shown on the dashboard / 仪表盘显示的最近观看条数
+pub const DASHBOARD_RECENT: i64 = 10;
+
+/// Largest page of recently watched items / 最近观看列表每页上限
+pub const MAX_RECENT: i64 = 100;
+
+/// Watched share after which a video is finished / 播放比例达到该值视为看完
+const FINISHED_RATIO: f64 = 0.95;
+
+/// Seconds left (end credits) after which a video is finished / 剩余秒数不超过该值视为看完（片尾）
+const FINISHED_REMAINING_SECS: f64 = 60.0;
+
+/// A position reported by a player, in seconds / 播放器上报的位置（秒）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub position: f64,
+    pub duration: Option<f64>,
+}
+
+impl Progress {
+    /// Validate a report, the position is clamped to the duration / 校验上报值，位置不超过总时长
+    pub fn new(position: f64, duration: Option<f64>) -> Option<Self> {
+        if !position.is_finite() || position < 0.0 {
+            return None;
+        }
+        let duration = duration.filter(|d| d.is_finite() && *d > 0.0);
+        let position = duration.map_or(position, |d| position.min(d));
+        Some(Self { position, duration })
+    }
+
+    /// Watched share from 0 to 1, unknown without a duration / 播放比例（0 到 1），未知总时长时为 None
+    pub fn ratio(&self) -> Option<f64> {
+        self.duration.map(|d| self.position / d)
+    }
+
+    /// Whether the video counts as watched / 是否视为已看完
+    pub fn is_finished(&self) -> bool {
+        match self.duration {
+            // 很短的视频只按比例判断，避免刚开始就算看完
+            Some(d) => self.position >= d * FINISHED_RATIO
+                || (d > FINISHED_REMAINING_SECS * 4.0 && d - self.position <= FINISHED_REMAINING_SECS),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_validation() {
+        assert!(Progress::new(f64::NAN, None).is_none());
+        assert!(Progress::new(-1.0, Some(100.0)).is_none());
+        let p = Progress::new(150.0, Some(100.0)).unwrap();
+        assert_eq!(p.position, 100.0);
+        assert_eq!(p.ratio(), Some(1.0));
+        assert_eq!(Progress::new(10.0, Some(0.0)).unwrap().duration, None);
+    }
+
+    #[test]
+    fn test_finished() {
+        // 两小时电影进入片尾
+        assert!(Progress::new(7150.0, Some(7200.0)).unwrap().is_finished());
+        assert!(!Progress::new(3600.0, Some(7200.0)).unwrap().is_finished());
+        // 两分钟短片剩 50 秒不算看完
+        assert!(!Progress::new(70.0, Some(120.0)).unwrap().is_finished());
+        assert!(Progress::new(115.0, Some(120.0)).unwrap().is_finished());
+        assert!(!Progress::new(99999.0, None).unwrap().is_finished());
+    }
+}
@@ -0,0 +1,110 @@
+//! Request IDs and correlated logging / 请求 ID 与关联日志
+//!
+//! `request_id_middleware` gives every request an ID (reusing a well-formed incoming
+//! `X-Request-ID` from a reverse proxy), runs the request inside a `request` span carrying it
+//! and echoes it in the response header. Background work started by a request is launched with
+//! `spawn`, which keeps the span and the ID, so a failed upload can be followed from the API
+//! through the task manager down to the driver (whose own spans nest inside).
+//! `request_id_middleware` 为每个请求分配 ID（反向代理传入的合法 `X-Request-ID` 会被沿用），在携带该 ID 的
+//! `request` span 中处理请求，并在响应头中返回。请求触发的后台任务通过 `spawn` 启动，保留 span 与 ID，
+//! 因此可以从 API 经任务管理器一直追踪到驱动（驱动自身的 span 嵌套在其中）。
+
+use std::future::Future;
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// Header carrying the request ID / 携带请求 ID 的请求头
+pub const HEADER: &str = "x-request-id";
+
+/// Longest incoming ID that is reused / 沿用的传入 ID 最大长度
+const MAX_LEN: usize = 64;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// Request ID stored in request extensions / 保存在请求扩展中的请求 ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Whether an incoming ID is safe to reuse in logs and headers / 传入的 ID 是否可安全用于日志与响应头
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// Reuse a well-formed incoming ID, or generate one / 沿用合法的传入 ID，否则生成新 ID
+pub fn resolve(incoming: Option<&str>) -> String {
+    match incoming.map(str::trim) {
+        Some(id) if is_valid(id) => id.to_string(),
+        _ => uuid::Uuid::new_v4().simple().to_string(),
+    }
+}
+
+/// ID of the current request, if any / 当前请求的 ID
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.clone()).ok()
+}
+
+/// Spawn a task that keeps the current request ID and log span
+/// 启动保留当前请求 ID 与日志 span 的任务
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let future = future.in_current_span();
+    match current() {
+        Some(id) => tokio::spawn(CURRENT.scope(id, future)),
+        None => tokio::spawn(future),
+    }
+}
+
+/// Assign the request ID, log the request inside its span and return the ID in the response
+/// 分配请求 ID，在其 span 中处理请求并在响应中返回该 ID
+pub async fn request_id_middleware(mut req: Request, next: Next) -> Response {
+    let id = resolve(req.headers().get(HEADER).and_then(|v| v.to_str().ok()));
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let span = tracing::info_span!("request", id = %id, method = %req.method(), path = %req.uri().path());
+
+    let started = std::time::Instant::now();
+    let mut response = CURRENT.scope(id.clone(), next.run(req).instrument(span.clone())).await;
+    span.in_scope(|| {
+        tracing::debug!(status = response.status().as_u16(), elapsed_ms = started.elapsed().as_millis() as u64, "request finished");
+    });
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve(Some("abc-123_x.y")), "abc-123_x.y");
+        assert_eq!(resolve(Some(" abc ")), "abc");
+        for bad in [None, Some(""), Some("a b"), Some("x\ny"), Some(&"a".repeat(65)[..])] {
+            let id = resolve(bad);
+            assert_eq!(id.len(), 32);
+            assert!(id.bytes().all(|b| b.is_ascii_hexdigit()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_keeps_id() {
+        assert_eq!(current(), None);
+        let id = CURRENT.scope("req-1".to_string(), async {
+            spawn(async { current() }).await.unwrap()
+        }).await;
+        assert_eq!(id.as_deref(), Some("req-1"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Cron expression / Cron 表达式
+//!
+//! Standard 5-field syntax: `minute hour day-of-month month day-of-week`.
+//! 标准5段格式：分 时 日 月 周
+//!
+//! - `*`, `5`, `1-5`, `*/15`, `10-50/10`, `1,15,30`
+//! - Month / weekday names: `JAN`..`DEC`, `SUN`..`SAT` (0 and 7 = Sunday) / 月份与星期名称
+//! - Macros: `@hourly` `@daily` `@weekly` `@monthly` `@yearly` / 宏
+//! - When both day-of-month and day-of-week are restricted, either may match (Vixie cron).
+//!   日与周都有限制时，满足其一即可（与 Vixie cron 一致）
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+/// Give up searching after this many years (e.g. `0 0 30 2 *`) / 搜索上限（年）
+const MAX_SEARCH_YEARS: i32 = 5;
+
+const MONTH_NAMES: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// Parsed cron schedule / 解析后的 Cron 计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    /// Parse expression / 解析表达式
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim();
+        let expanded = match expr.to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ => expr,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Cron expression needs 5 fields, got {}: {}", fields.len(), expr));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7, Some(&DAY_NAMES))?;
+        // 7 is Sunday as well / 7 也表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expr: expr.to_string(),
+            minutes: parse_field(fields[0], 0, 59, None)?,
+            hours: parse_field(fields[1], 0, 23, None)?,
+            days: parse_field(fields[2], 1, 31, None)?,
+            months: parse_field(fields[3], 1, 12, Some(&MONTH_NAMES))?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Original expression / 原始表达式
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    /// First matching time strictly after `after` (minute precision) / 严格晚于指定时间的下一次触发时间
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let local = after.naive_local();
+        let mut t = local.date().and_hms_opt(local.hour(), local.minute(), 0)? + Duration::minutes(1);
+        let limit_year = local.year() + MAX_SEARCH_YEARS;
+
+        while t.year() <= limit_year {
+            if !bit(self.months, t.month()) {
+                t = first_of_next_month(t)?;
+                continue;
+            }
+            if !self.day_matches(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !bit(self.hours, t.hour()) {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if !bit(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+                continue;
+            }
+            // Skipped local times (DST gap) move on / 夏令时跳过的时间继续向后找
+            match tz.from_local_datetime(&t).earliest() {
+                Some(dt) => return Some(dt),
+                None => t += Duration::minutes(1),
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = bit(self.days, date.day());
+        let dow = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+fn bit(mask: u64, n: u32) -> bool {
+    mask & (1u64 << n) != 0
+}
+
+fn first_of_next_month(t: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (year, month) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+/// Parse one field into a bitmask / 解析单个字段为位掩码
+fn parse_field(field: &str, min: u32, max: u32, names: Option<&[&str]>) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => {
+                let step: u32 = s.parse().map_err(|_| format!("Invalid step: {}", part))?;
+                if step == 0 {
+                    return Err(format!("Step must be positive: {}", part));
+                }
+                (r, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (parse_value(a, min, names)?, parse_value(b, min, names)?)
+        } else {
+            let v = parse_value(range, min, names)?;
+            // `5/10` means from 5 to max / `5/10` 表示从5到最大值
+            (v, if step > 1 { max } else { v })
+        };
+
+        if start < min || end > max || start > end {
+            return Err(format!("Value out of range {}-{}: {}", min, max, part));
+        }
+        let mut v = start;
+        while v <= end {
+            mask |= 1u64 << v;
+            v += step;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(s: &str, min: u32, names: Option<&[&str]>) -> Result<u32, String> {
+    if let Ok(v) = s.parse::<u32>() {
+        return Ok(v);
+    }
+    names
+        .and_then(|names| names.iter().position(|n| n.eq_ignore_ascii_case(s)))
+        .map(|i| i as u32 + min)
+        .ok_or_else(|| format!("Invalid value: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(CronSchedule::parse("* * * * *").is_ok());
+        assert!(CronSchedule::parse("*/15 0-6,22 1 JAN-mar mon-fri").is_ok());
+        assert!(CronSchedule::parse("@daily").is_ok());
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("* * * * FOO").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        let hourly = CronSchedule::parse("@hourly").unwrap();
+        assert_eq!(hourly.next_after(&at("2024-01-01T10:00:00Z")), Some(at("2024-01-01T11:00:00Z")));
+        assert_eq!(hourly.next_after(&at("2024-01-01T10:59:59Z")), Some(at("2024-01-01T11:00:00Z")));
+
+        let quarter = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(quarter.next_after(&at("2024-01-01T10:07:00Z")), Some(at("2024-01-01T10:15:00Z")));
+
+        // Year rollover / 跨年
+        let daily = CronSchedule::parse("30 3 * * *").unwrap();
+        assert_eq!(daily.next_after(&at("2024-12-31T04:00:00Z")), Some(at("2025-01-01T03:30:00Z")));
+
+        // 2024-01-01 is Monday, next Sunday (7) is the 7th / 周日可以写7
+        let sunday = CronSchedule::parse("0 0 * * 7").unwrap();
+        assert_eq!(sunday.next_after(&at("2024-01-01T00:00:00Z")), Some(at("2024-01-07T00:00:00Z")));
+
+        // Day-of-month OR day-of-week / 日或周满足其一
+        let either = CronSchedule::parse("0 0 15 * MON").unwrap();
+        assert_eq!(either.next_after(&at("2024-01-01T00:00:00Z")), Some(at("2024-01-08T00:00:00Z")));
+        assert_eq!(either.next_after(&at("2024-01-13T00:00:00Z")), Some(at("2024-01-15T00:00:00Z")));
+
+        let leap = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(leap.next_after(&at("2024-03-01T00:00:00Z")), Some(at("2028-02-29T00:00:00Z")));
+
+        let never = CronSchedule::parse("0 0 30 2 *").unwrap();
+        assert_eq!(never.next_after(&at("2024-01-01T00:00:00Z")), None);
+    }
+}
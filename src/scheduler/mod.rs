@@ -0,0 +1,521 @@
+//! Scheduled job engine / 定时任务引擎
+//!
+//! This module handles:
+//! - Job registry with persisted cron / enabled / jitter overrides / 任务注册与持久化配置
+//! - Background loop running due jobs (one instance per job at a time) / 后台循环执行到期任务（同一任务不并发）
+//! - Run history and manual trigger / 运行历史与手动触发
+//! - With a shared cluster backend, each scheduled run happens on one replica only / 共享集群后端下每次定时运行只在一个实例执行
+//!
+//! Jobs are registered by the application with a handler closure; the engine only knows
+//! ids and schedules.
+//! 任务由应用层注册处理函数，引擎只负责调度。
+
+pub mod cron;
+
+pub use cron::CronSchedule;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Local, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::cluster::Cluster;
+
+/// How often the loop checks for due jobs (seconds) / 检查到期任务的间隔（秒）
+pub const SCHEDULER_TICK_SECS: u64 = 15;
+/// Run records kept per job / 每个任务保留的运行记录数
+pub const MAX_RUN_HISTORY: i64 = 100;
+/// Max jitter (seconds) / 最大随机延迟（秒）
+pub const MAX_JITTER_SECS: u64 = 3600;
+
+/// Job result: Ok(summary) or Err(error) / 任务结果
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+/// Job handler / 任务处理函数
+pub type JobHandler = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+/// Static description of a job / 任务定义
+#[derive(Debug, Clone)]
+pub struct JobSpec {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default_cron: &'static str,
+    pub default_enabled: bool,
+    pub default_jitter_secs: u64,
+}
+
+/// What started a run / 触发方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunTrigger {
+    Schedule,
+    Manual,
+}
+
+/// Run status / 运行状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Success,
+    Failed,
+}
+
+impl RunStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Success => "success",
+            RunStatus::Failed => "failed",
+        }
+    }
+
+    fn from_str(s: &str) -> Self {
+        match s {
+            "success" => RunStatus::Success,
+            "failed" => RunStatus::Failed,
+            _ => RunStatus::Running,
+        }
+    }
+}
+
+/// Job state for the admin API / 任务信息（管理接口用）
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub cron: String,
+    pub default_cron: String,
+    pub enabled: bool,
+    pub jitter_secs: u64,
+    pub running: bool,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_status: Option<RunStatus>,
+    pub last_message: Option<String>,
+}
+
+/// One run record / 单次运行记录
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub id: i64,
+    pub job_id: String,
+    pub trigger: RunTrigger,
+    pub status: RunStatus,
+    pub message: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<u64>,
+}
+
+/// scheduled_jobs row: cron, enabled, jitter_secs, last_run_at, last_status, last_message
+type JobRow = (String, bool, i64, Option<String>, Option<String>, Option<String>);
+/// scheduled_job_runs row / 运行记录行
+type RunRow = (i64, String, String, String, Option<String>, String, Option<String>, Option<i64>);
+
+struct JobEntry {
+    spec: JobSpec,
+    handler: JobHandler,
+    schedule: CronSchedule,
+    enabled: bool,
+    jitter_secs: u64,
+    running: bool,
+    next_run_at: Option<DateTime<Utc>>,
+    /// Cron time of the next run before jitter, same on every replica / 下次运行的 cron 时间（不含随机延迟），各实例相同
+    next_slot: Option<DateTime<Utc>>,
+    last_run_at: Option<DateTime<Utc>>,
+    last_status: Option<RunStatus>,
+    last_message: Option<String>,
+}
+
+impl JobEntry {
+    fn info(&self) -> JobInfo {
+        JobInfo {
+            id: self.spec.id.to_string(),
+            name: self.spec.name.to_string(),
+            description: self.spec.description.to_string(),
+            cron: self.schedule.expr().to_string(),
+            default_cron: self.spec.default_cron.to_string(),
+            enabled: self.enabled,
+            jitter_secs: self.jitter_secs,
+            running: self.running,
+            next_run_at: self.next_run_at,
+            last_run_at: self.last_run_at,
+            last_status: self.last_status,
+            last_message: self.last_message.clone(),
+        }
+    }
+
+    /// Compute next run (local time cron + random jitter) / 计算下次运行时间（本地时区 + 随机延迟）
+    fn schedule_next(&mut self, after: DateTime<Utc>) {
+        self.next_slot = if self.enabled {
+            self.schedule.next_after(&after.with_timezone(&Local)).map(|t| t.with_timezone(&Utc))
+        } else {
+            None
+        };
+        self.next_run_at = self.next_slot.map(|t| {
+            let jitter = if self.jitter_secs > 0 { rand::random::<u64>() % (self.jitter_secs + 1) } else { 0 };
+            t + chrono::Duration::seconds(jitter as i64)
+        });
+    }
+}
+
+/// Scheduler / 调度器
+pub struct Scheduler {
+    db: SqlitePool,
+    jobs: RwLock<HashMap<String, JobEntry>>,
+    /// No new runs after shutdown began / 停机后不再启动新的运行
+    stopped: AtomicBool,
+    /// Shared backend electing the replica of each run / 选出每次运行所在实例的共享后端
+    cluster: Option<Arc<Cluster>>,
+}
+
+impl Scheduler {
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            db,
+            jobs: RwLock::new(HashMap::new()),
+            stopped: AtomicBool::new(false),
+            cluster: None,
+        }
+    }
+
+    /// Share scheduled runs with other replicas / 与其他实例共享定时运行
+    pub fn set_cluster(&mut self, cluster: Arc<Cluster>) {
+        self.cluster = Some(cluster);
+    }
+
+    /// Instance id when a shared backend is used / 使用共享后端时的实例ID
+    fn shared_instance(&self) -> Option<&str> {
+        self.cluster.as_ref().filter(|c| c.is_shared()).map(|c| c.instance_id())
+    }
+
+    /// Claim a scheduled run for this replica / 为本实例认领一次定时运行
+    async fn claim(&self, id: &str) -> bool {
+        let Some(cluster) = self.cluster.as_ref().filter(|c| c.is_shared()) else {
+            return true;
+        };
+        let Some(slot) = self.jobs.read().get(id).and_then(|j| j.next_slot) else {
+            return false;
+        };
+        cluster.try_lock(&format!("job:{}:{}", id, slot.timestamp()), Duration::from_secs(3600)).await
+    }
+
+    /// Register a job, applying persisted overrides / 注册任务（应用已保存的配置）
+    pub async fn register(&self, spec: JobSpec, handler: JobHandler) -> Result<(), String> {
+        let default_schedule = CronSchedule::parse(spec.default_cron)?;
+
+        let row: Option<JobRow> = sqlx::query_as(
+            "SELECT cron, enabled, jitter_secs, last_run_at, last_status, last_message FROM scheduled_jobs WHERE id = ?"
+        )
+        .bind(spec.id)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let mut entry = JobEntry {
+            spec: spec.clone(),
+            handler,
+            schedule: default_schedule.clone(),
+            enabled: spec.default_enabled,
+            jitter_secs: spec.default_jitter_secs,
+            running: false,
+            next_run_at: None,
+            next_slot: None,
+            last_run_at: None,
+            last_status: None,
+            last_message: None,
+        };
+
+        match row {
+            Some((cron, enabled, jitter, last_run_at, last_status, last_message)) => {
+                entry.schedule = CronSchedule::parse(&cron).unwrap_or_else(|e| {
+                    tracing::warn!("Invalid saved cron for job {}: {}, using default", spec.id, e);
+                    default_schedule
+                });
+                entry.enabled = enabled;
+                entry.jitter_secs = (jitter.max(0) as u64).min(MAX_JITTER_SECS);
+                entry.last_run_at = last_run_at.and_then(|s| parse_time(&s));
+                entry.last_status = last_status.map(|s| RunStatus::from_str(&s));
+                entry.last_message = last_message;
+            }
+            None => {
+                sqlx::query(
+                    "INSERT INTO scheduled_jobs (id, cron, enabled, jitter_secs, updated_at) VALUES (?, ?, ?, ?, ?)"
+                )
+                .bind(spec.id)
+                .bind(spec.default_cron)
+                .bind(spec.default_enabled)
+                .bind(spec.default_jitter_secs as i64)
+                .bind(Utc::now().to_rfc3339())
+                .execute(&self.db)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        entry.schedule_next(Utc::now());
+        tracing::info!("Scheduled job registered: {} ({}), next run: {:?}", spec.id, entry.schedule.expr(), entry.next_run_at);
+        self.jobs.write().insert(spec.id.to_string(), entry);
+        Ok(())
+    }
+
+    /// List jobs / 列出任务
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.jobs.read().values().map(|j| j.info()).collect();
+        jobs.sort_by(|a, b| a.id.cmp(&b.id));
+        jobs
+    }
+
+    /// Get one job / 获取任务
+    pub fn get(&self, id: &str) -> Option<JobInfo> {
+        self.jobs.read().get(id).map(|j| j.info())
+    }
+
+    /// Update cron / enabled / jitter and persist / 更新并保存任务配置
+    pub async fn update(
+        &self,
+        id: &str,
+        cron: Option<&str>,
+        enabled: Option<bool>,
+        jitter_secs: Option<u64>,
+    ) -> Result<JobInfo, String> {
+        let schedule = cron.map(CronSchedule::parse).transpose()?;
+
+        let info = {
+            let mut jobs = self.jobs.write();
+            let entry = jobs.get_mut(id).ok_or_else(|| format!("Job not found: {}", id))?;
+            if let Some(schedule) = schedule {
+                entry.schedule = schedule;
+            }
+            if let Some(enabled) = enabled {
+                entry.enabled = enabled;
+            }
+            if let Some(jitter) = jitter_secs {
+                entry.jitter_secs = jitter.min(MAX_JITTER_SECS);
+            }
+            entry.schedule_next(Utc::now());
+            entry.info()
+        };
+
+        sqlx::query("UPDATE scheduled_jobs SET cron = ?, enabled = ?, jitter_secs = ?, updated_at = ? WHERE id = ?")
+            .bind(&info.cron)
+            .bind(info.enabled)
+            .bind(info.jitter_secs as i64)
+            .bind(Utc::now().to_rfc3339())
+            .bind(id)
+            .execute(&self.db)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(info)
+    }
+
+    /// Run a job now in background / 立即在后台运行任务
+    pub fn trigger(self: &Arc<Self>, id: &str) -> Result<(), String> {
+        self.start_run(id, RunTrigger::Manual)
+    }
+
+    /// Query run history (newest first) / 查询运行历史（最新在前）
+    pub async fn history(&self, job_id: Option<&str>, page: u32, page_size: u32) -> (Vec<JobRun>, u64) {
+        let page = page.max(1);
+        let page_size = page_size.clamp(1, 100);
+        let where_clause = if job_id.is_some() { " WHERE job_id = ?" } else { "" };
+
+        let count_sql = format!("SELECT COUNT(*) FROM scheduled_job_runs{}", where_clause);
+        let mut count_query = sqlx::query_scalar::<_, i64>(&count_sql);
+        if let Some(id) = job_id {
+            count_query = count_query.bind(id);
+        }
+        let total = count_query.fetch_one(&self.db).await.unwrap_or(0) as u64;
+
+        let list_sql = format!(
+            "SELECT id, job_id, trigger_type, status, message, started_at, finished_at, duration_ms \
+             FROM scheduled_job_runs{} ORDER BY id DESC LIMIT ? OFFSET ?",
+            where_clause
+        );
+        let mut list_query = sqlx::query_as::<_, RunRow>(&list_sql);
+        if let Some(id) = job_id {
+            list_query = list_query.bind(id);
+        }
+        let rows = list_query
+            .bind(page_size as i64)
+            .bind(((page - 1) * page_size) as i64)
+            .fetch_all(&self.db)
+            .await
+            .unwrap_or_default();
+
+        let runs = rows.into_iter().map(|(id, job_id, trigger, status, message, started_at, finished_at, duration_ms)| JobRun {
+            id,
+            job_id,
+            trigger: if trigger == "manual" { RunTrigger::Manual } else { RunTrigger::Schedule },
+            status: RunStatus::from_str(&status),
+            message,
+            started_at: parse_time(&started_at).unwrap_or_else(Utc::now),
+            finished_at: finished_at.and_then(|s| parse_time(&s)),
+            duration_ms: duration_ms.map(|d| d.max(0) as u64),
+        }).collect();
+
+        (runs, total)
+    }
+
+    /// Start the background loop / 启动后台调度循环
+    pub fn start(self: &Arc<Self>) {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            // Runs left "running" by a previous process were interrupted / 上次进程遗留的运行记录标记为失败
+            let _ = sqlx::query(
+                "UPDATE scheduled_job_runs SET status = 'failed', message = 'Interrupted by restart' \
+                 WHERE status = 'running' AND (instance_id IS NULL OR instance_id = ? OR ? IS NULL)"
+            )
+            .bind(scheduler.shared_instance())
+            .bind(scheduler.shared_instance())
+            .execute(&scheduler.db)
+            .await;
+
+            let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULER_TICK_SECS));
+            loop {
+                interval.tick().await;
+                if scheduler.stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                let now = Utc::now();
+                let due: Vec<String> = scheduler.jobs.read()
+                    .iter()
+                    .filter(|(_, j)| j.enabled && !j.running && j.next_run_at.map(|t| t <= now).unwrap_or(false))
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                for id in due {
+                    if !scheduler.claim(&id).await {
+                        // Another replica runs this one / 由其他实例运行
+                        if let Some(job) = scheduler.jobs.write().get_mut(&id) {
+                            job.schedule_next(now);
+                        }
+                        continue;
+                    }
+                    if let Err(e) = scheduler.start_run(&id, RunTrigger::Schedule) {
+                        tracing::debug!("Scheduled job {} skipped: {}", id, e);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Stop starting runs (shutdown); runs in progress are left to finish / 停止调度（停机），运行中的任务不受影响
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Mark job running and spawn it / 标记运行中并启动任务
+    fn start_run(self: &Arc<Self>, id: &str, trigger: RunTrigger) -> Result<(), String> {
+        if self.stopped.load(Ordering::Relaxed) {
+            return Err("Scheduler is stopped".to_string());
+        }
+        let handler = {
+            let mut jobs = self.jobs.write();
+            let entry = jobs.get_mut(id).ok_or_else(|| format!("Job not found: {}", id))?;
+            if entry.running {
+                return Err(format!("Job is already running: {}", id));
+            }
+            entry.running = true;
+            // Schedule next run from now so a long run is not repeated immediately / 从现在起计算下次运行
+            entry.schedule_next(Utc::now());
+            entry.handler.clone()
+        };
+
+        let scheduler = self.clone();
+        let id = id.to_string();
+        tokio::spawn(async move {
+            scheduler.run(&id, trigger, handler).await;
+        });
+        Ok(())
+    }
+
+    async fn run(&self, id: &str, trigger: RunTrigger, handler: JobHandler) {
+        let started_at = Utc::now();
+        let trigger_str = match trigger {
+            RunTrigger::Schedule => "schedule",
+            RunTrigger::Manual => "manual",
+        };
+        let run_id = sqlx::query(
+            "INSERT INTO scheduled_job_runs (job_id, trigger_type, status, started_at, instance_id) VALUES (?, ?, 'running', ?, ?)"
+        )
+        .bind(id)
+        .bind(trigger_str)
+        .bind(started_at.to_rfc3339())
+        .bind(self.shared_instance())
+        .execute(&self.db)
+        .await
+        .map(|r| r.last_insert_rowid())
+        .ok();
+
+        tracing::info!("Scheduled job started: {} ({})", id, trigger_str);
+        // A panicking job must not leave the job marked running / 任务 panic 时不能一直处于运行中
+        let result = match tokio::spawn(handler()).await {
+            Ok(result) => result,
+            Err(e) => Err(format!("Job panicked: {}", e)),
+        };
+        let finished_at = Utc::now();
+        let duration_ms = (finished_at - started_at).num_milliseconds().max(0);
+
+        let (status, message) = match result {
+            Ok(msg) => (RunStatus::Success, msg),
+            Err(e) => {
+                tracing::warn!("Scheduled job failed: {} - {}", id, e);
+                (RunStatus::Failed, e)
+            }
+        };
+        tracing::info!("Scheduled job finished: {} ({}, {}ms)", id, status.as_str(), duration_ms);
+
+        {
+            let mut jobs = self.jobs.write();
+            if let Some(entry) = jobs.get_mut(id) {
+                entry.running = false;
+                entry.last_run_at = Some(started_at);
+                entry.last_status = Some(status);
+                entry.last_message = Some(message.clone());
+            }
+        }
+
+        if let Some(run_id) = run_id {
+            let _ = sqlx::query(
+                "UPDATE scheduled_job_runs SET status = ?, message = ?, finished_at = ?, duration_ms = ? WHERE id = ?"
+            )
+            .bind(status.as_str())
+            .bind(&message)
+            .bind(finished_at.to_rfc3339())
+            .bind(duration_ms)
+            .bind(run_id)
+            .execute(&self.db)
+            .await;
+        }
+        let _ = sqlx::query("UPDATE scheduled_jobs SET last_run_at = ?, last_status = ?, last_message = ? WHERE id = ?")
+            .bind(started_at.to_rfc3339())
+            .bind(status.as_str())
+            .bind(&message)
+            .bind(id)
+            .execute(&self.db)
+            .await;
+        // Keep the latest MAX_RUN_HISTORY records / 只保留最近的运行记录
+        let _ = sqlx::query(
+            "DELETE FROM scheduled_job_runs WHERE job_id = ? AND id NOT IN \
+             (SELECT id FROM scheduled_job_runs WHERE job_id = ? ORDER BY id DESC LIMIT ?)"
+        )
+        .bind(id)
+        .bind(id)
+        .bind(MAX_RUN_HISTORY)
+        .execute(&self.db)
+        .await;
+    }
+}
+
+fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.with_timezone(&Utc))
+}
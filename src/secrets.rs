@@ -0,0 +1,163 @@
+//! Secrets redaction / 敏感信息脱敏
+//!
+//! Driver config fields declared with `format: password` are write-only: the admin API returns
+//! them blank, and a blank value on update keeps the stored one. Log output is scrubbed of
+//! tokens, cookies and passwords that drivers may include in URLs or error messages.
+//! 驱动配置中声明为 `format: password` 的字段只写不读：管理接口返回空值，更新时留空则保留原值。
+//! 日志输出会去除驱动可能写入 URL 或错误信息中的令牌、Cookie 与密码。
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use regex::Regex;
+use serde_json::Value;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::storage::ConfigItem;
+
+lazy_static::lazy_static! {
+    /// Secret query parameters in URLs / URL 中的敏感查询参数
+    static ref QUERY_SECRET: Regex = Regex::new(
+        r"(?i)([?&](?:access_token|refresh_token|token|stoken|auth_token|sign|signature|x-amz-signature|x-amz-credential|x-amz-security-token|password|pwd|bduss|session_id|sessionid)=)[^&\s]+"
+    ).unwrap();
+    /// Bearer / Basic credentials in Authorization headers / Authorization 头中的凭据
+    static ref AUTH_SCHEME: Regex = Regex::new(r"(?i)((?:bearer|basic)\s+)[A-Za-z0-9\-._~+/]+=*").unwrap();
+    /// Cookie headers and fields, masked up to the end of the value / Cookie 头与字段，整段隐藏
+    static ref COOKIE: Regex = Regex::new(
+        r#"(?i)((?:set-)?cookies?"?\s*[:=]\s*"?)[^"\r\n]+"#
+    ).unwrap();
+    /// `key: value` / `"key": "value"` / `key=value` pairs with a secret key / 敏感键值对
+    static ref KEY_VALUE: Regex = Regex::new(
+        r#"(?i)("?(?:password|passwd|passphrase|refresh_token|access_token|client_secret|secret_access_key|session_token|private_key|bduss)"?\s*[:=]\s*"?)([^"\s,&}]+)"#
+    ).unwrap();
+}
+
+/// Names of the secret fields in a driver's config schema / 驱动配置中的敏感字段名
+pub fn secret_fields(items: &[ConfigItem]) -> Vec<String> {
+    items.iter().filter(|i| i.is_secret()).map(|i| i.name.clone()).collect()
+}
+
+fn is_blank(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(s)) => s.is_empty(),
+        _ => false,
+    }
+}
+
+/// Blank out secret fields, returning the names of those that had a value
+/// 清空敏感字段，返回原本有值的字段名
+pub fn redact_config(config: &mut Value, secrets: &[String]) -> Vec<String> {
+    let Some(obj) = config.as_object_mut() else {
+        return Vec::new();
+    };
+    let mut set = Vec::new();
+    for name in secrets {
+        if let Some(value) = obj.get_mut(name) {
+            if !is_blank(Some(value)) {
+                *value = Value::String(String::new());
+                set.push(name.clone());
+            }
+        }
+    }
+    set
+}
+
+/// Fill secret fields left blank in an update with the stored values
+/// 更新时留空的敏感字段沿用已保存的值
+pub fn keep_blank_secrets(config: &mut Value, stored: &Value, secrets: &[String]) {
+    let Some(obj) = config.as_object_mut() else {
+        return;
+    };
+    for name in secrets {
+        if !is_blank(obj.get(name)) {
+            continue;
+        }
+        if let Some(old) = stored.get(name).filter(|v| !is_blank(Some(v))) {
+            obj.insert(name.clone(), old.clone());
+        }
+    }
+}
+
+/// Mask tokens, cookies and passwords in free text / 隐藏文本中的令牌、Cookie 与密码
+pub fn scrub(text: &str) -> Cow<'_, str> {
+    let mut out = Cow::Borrowed(text);
+    for (re, rep) in [
+        (&*QUERY_SECRET, "${1}***"),
+        (&*AUTH_SCHEME, "${1}***"),
+        (&*COOKIE, "${1}***"),
+        (&*KEY_VALUE, "${1}***"),
+    ] {
+        if let Cow::Owned(s) = re.replace_all(&out, rep) {
+            out = Cow::Owned(s);
+        }
+    }
+    out
+}
+
+/// Log writer factory that scrubs each formatted event / 对每条日志脱敏的写入器工厂
+pub struct ScrubbingMakeWriter<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for ScrubbingMakeWriter<M> {
+    type Writer = ScrubbingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ScrubbingWriter(self.0.make_writer())
+    }
+}
+
+/// Writer that scrubs secrets before passing output on / 脱敏后再输出的写入器
+pub struct ScrubbingWriter<W>(W);
+
+impl<W: Write> Write for ScrubbingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The fmt layer writes one whole event per call / fmt 层每次写入一整条日志
+        match std::str::from_utf8(buf) {
+            Ok(text) => self.0.write_all(scrub(text).as_bytes())?,
+            Err(_) => self.0.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_and_keep() {
+        let items = vec![
+            ConfigItem::new("username", "string"),
+            ConfigItem::new("password", "password"),
+            ConfigItem::new("cookie", "string").password(),
+        ];
+        let secrets = secret_fields(&items);
+        assert_eq!(secrets, vec!["password", "cookie"]);
+
+        let stored = json!({"username": "a", "password": "p", "cookie": ""});
+        let mut shown = stored.clone();
+        assert_eq!(redact_config(&mut shown, &secrets), vec!["password"]);
+        assert_eq!(shown, json!({"username": "a", "password": "", "cookie": ""}));
+
+        let mut update = json!({"username": "b", "password": "", "cookie": "c=1"});
+        keep_blank_secrets(&mut update, &stored, &secrets);
+        assert_eq!(update, json!({"username": "b", "password": "p", "cookie": "c=1"}));
+    }
+
+    #[test]
+    fn test_scrub() {
+        assert_eq!(
+            scrub("GET https://x.com/file?id=1&access_token=abc.def&n=2"),
+            "GET https://x.com/file?id=1&access_token=***&n=2"
+        );
+        assert_eq!(scrub("Authorization: Bearer eyJhbGc.x-y"), "Authorization: Bearer ***");
+        assert_eq!(scrub("cookie: BDUSS=xyz; STOKEN=1\nnext"), "cookie: ***\nnext");
+        assert_eq!(scrub(r#"{"refresh_token":"r1","name":"n"}"#), r#"{"refresh_token":"***","name":"n"}"#);
+        assert!(matches!(scrub("listing /a/b"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,235 @@
+//! Security alerts / 安全告警
+//!
+//! This module handles:
+//! - Alert settings: failed login threshold, new country logins, admin account changes / 告警设置
+//! - Alert events and their plain text form / 告警事件及其纯文本内容
+//! - Telegram delivery through the Bot API / 通过 Bot API 推送到 Telegram
+//!
+//! Failed logins are counted by `LoginLockout` within its window; an alert fires once when a
+//! counter reaches the threshold. A login from a country the account has never used before is
+//! reported once the account has at least one known country, so the first login only records it.
+//! Every alert is also emitted as the `security_alert` webhook event and, if enabled, mailed to
+//! the administrators.
+//! 登录失败次数沿用 `LoginLockout` 在其时间窗口内的计数，计数达到阈值时告警一次。账号已有登录国家记录后，
+//! 从未出现过的国家登录时告警，首次登录只记录国家。所有告警同时作为 `security_alert` Webhook 事件推送，
+//! 并可按设置发送邮件给管理员。
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::webhook::WEBHOOK_TIMEOUT_SECS;
+
+/// Settings key / 设置项键名
+pub const SECURITY_ALERT_KEY: &str = "security_alert";
+/// Telegram Bot API base / Telegram Bot API 地址
+pub const TELEGRAM_API_BASE: &str = "https://api.telegram.org";
+
+/// Security alert settings / 安全告警设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityAlertConfig {
+    /// Alert when an account or IP reaches this many failures, 0 disables
+    /// 账号或IP失败次数达到该值时告警，0 表示关闭
+    pub failed_login_threshold: u32,
+    /// Alert on logins from a country new to the account (needs GeoIP) / 账号从新国家登录时告警（需要 GeoIP）
+    pub new_country: bool,
+    /// Alert when administrator accounts are created, changed or deleted / 管理员账号被创建、修改或删除时告警
+    pub admin_changes: bool,
+    /// Also email the administrators / 同时发送邮件给管理员
+    pub email_admins: bool,
+    /// Telegram bot token, empty disables Telegram / Telegram 机器人令牌，为空时不推送
+    pub telegram_bot_token: String,
+    /// Telegram chat ID / Telegram 会话ID
+    pub telegram_chat_id: String,
+}
+
+impl Default for SecurityAlertConfig {
+    fn default() -> Self {
+        Self {
+            failed_login_threshold: 5,
+            new_country: true,
+            admin_changes: true,
+            email_admins: true,
+            telegram_bot_token: String::new(),
+            telegram_chat_id: String::new(),
+        }
+    }
+}
+
+impl SecurityAlertConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.telegram_bot_token.is_empty() && self.telegram_chat_id.trim().is_empty() {
+            return Err("telegram_chat_id is required with a bot token".to_string());
+        }
+        if self.telegram_bot_token.contains(['/', '?', '#']) {
+            return Err("Invalid telegram_bot_token".to_string());
+        }
+        Ok(())
+    }
+
+    /// Keep the saved bot token when the request leaves it empty / 请求中令牌为空时保留已保存的令牌
+    pub fn merge_secret(mut self, saved: &SecurityAlertConfig) -> Self {
+        if self.telegram_bot_token.is_empty() && !self.telegram_chat_id.trim().is_empty() {
+            self.telegram_bot_token = saved.telegram_bot_token.clone();
+        }
+        self
+    }
+
+    /// Settings as shown to admins, without the bot token / 管理界面展示的设置（不含令牌）
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(obj) = value.as_object_mut() {
+            obj.remove("telegram_bot_token");
+            obj.insert("telegram_token_set".to_string(), json!(!self.telegram_bot_token.is_empty()));
+        }
+        value
+    }
+
+    pub fn telegram_enabled(&self) -> bool {
+        !self.telegram_bot_token.is_empty() && !self.telegram_chat_id.trim().is_empty()
+    }
+}
+
+/// A security event worth an alert / 需要告警的安全事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityAlert {
+    /// An account or IP reached the failed login threshold / 账号或IP的登录失败次数达到阈值
+    FailedLogins { username: String, ip: String, failures: u32, blocked: bool },
+    /// Successful login from a country new to the account / 账号从新的国家登录成功
+    NewCountry { username: String, ip: String, country: String, known: Vec<String> },
+    /// An administrator account was created, changed or deleted / 管理员账号被创建、修改或删除
+    AdminChanged { actor: String, username: String, action: String },
+}
+
+impl SecurityAlert {
+    /// Machine readable kind / 事件类型
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SecurityAlert::FailedLogins { .. } => "failed_logins",
+            SecurityAlert::NewCountry { .. } => "new_country",
+            SecurityAlert::AdminChanged { .. } => "admin_changed",
+        }
+    }
+
+    /// One line title / 单行标题
+    pub fn title(&self) -> String {
+        match self {
+            SecurityAlert::FailedLogins { username, failures, .. } => {
+                format!("{} failed logins for {}", failures, username)
+            }
+            SecurityAlert::NewCountry { username, country, .. } => {
+                format!("{} signed in from a new country ({})", username, country)
+            }
+            SecurityAlert::AdminChanged { username, action, .. } => {
+                format!("Admin account {} {}", username, action)
+            }
+        }
+    }
+
+    /// Detail line / 详情
+    pub fn details(&self) -> String {
+        match self {
+            SecurityAlert::FailedLogins { ip, blocked, .. } => {
+                format!("Last attempt from {}{}", ip, if *blocked { ", now locked out" } else { "" })
+            }
+            SecurityAlert::NewCountry { ip, known, .. } => {
+                format!("IP {}, previously seen from {}", ip, known.join(", "))
+            }
+            SecurityAlert::AdminChanged { actor, .. } => format!("Changed by {}", actor),
+        }
+    }
+
+    /// Webhook payload data / Webhook 数据
+    pub fn to_json(&self) -> Value {
+        let mut data = match self {
+            SecurityAlert::FailedLogins { username, ip, failures, blocked } => json!({
+                "username": username, "ip": ip, "failures": failures, "blocked": blocked
+            }),
+            SecurityAlert::NewCountry { username, ip, country, known } => json!({
+                "username": username, "ip": ip, "country": country, "known_countries": known
+            }),
+            SecurityAlert::AdminChanged { actor, username, action } => json!({
+                "actor": actor, "username": username, "action": action
+            }),
+        };
+        data["kind"] = json!(self.kind());
+        data["title"] = json!(self.title());
+        data
+    }
+}
+
+/// Whether `country` is new to an account seen from `known`; the first recorded country is not
+/// 对已记录 `known` 国家的账号，`country` 是否为新国家；首次记录不算
+pub fn is_new_country(known: &[String], country: &str) -> bool {
+    !known.is_empty() && !known.iter().any(|k| k.eq_ignore_ascii_case(country))
+}
+
+/// Whether a failure counter just reached the threshold / 失败计数是否刚好达到阈值
+pub fn reached_threshold(failures: u32, threshold: u32) -> bool {
+    threshold > 0 && failures == threshold
+}
+
+/// Send a message through the Telegram Bot API / 通过 Telegram Bot API 发送消息
+pub async fn send_telegram(config: &SecurityAlertConfig, text: &str) -> Result<(), String> {
+    if !config.telegram_enabled() {
+        return Ok(());
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .post(format!("{}/bot{}/sendMessage", TELEGRAM_API_BASE, config.telegram_bot_token))
+        .json(&json!({
+            "chat_id": config.telegram_chat_id.trim(),
+            "text": text,
+            "disable_web_page_preview": true
+        }))
+        .send()
+        .await
+        .map_err(|e| e.without_url().to_string())?;
+    if !response.status().is_success() {
+        let body: Value = response.json().await.unwrap_or_default();
+        return Err(body["description"].as_str().unwrap_or("Telegram request failed").to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let saved = SecurityAlertConfig {
+            telegram_bot_token: "123:abc".to_string(),
+            telegram_chat_id: "42".to_string(),
+            ..Default::default()
+        };
+        let update = SecurityAlertConfig { telegram_chat_id: "42".to_string(), ..Default::default() };
+        assert_eq!(update.merge_secret(&saved), saved);
+        assert!(saved.telegram_enabled());
+        assert!(!SecurityAlertConfig::default().merge_secret(&saved).telegram_enabled());
+
+        let shown = saved.redacted();
+        assert!(shown.get("telegram_bot_token").is_none());
+        assert_eq!(shown["telegram_token_set"], true);
+        assert!(SecurityAlertConfig { telegram_chat_id: String::new(), ..saved }.validate().is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(!is_new_country(&[], "US"));
+        assert!(!is_new_country(&["CN".to_string()], "cn"));
+        assert!(is_new_country(&["CN".to_string()], "US"));
+
+        assert!(reached_threshold(5, 5));
+        assert!(!reached_threshold(6, 5));
+        assert!(!reached_threshold(1, 0));
+
+        let alert = SecurityAlert::FailedLogins { username: "admin".into(), ip: "1.2.3.4".into(), failures: 5, blocked: true };
+        assert_eq!(alert.to_json()["kind"], "failed_logins");
+        assert_eq!(alert.details(), "Last attempt from 1.2.3.4, now locked out");
+    }
+}
@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use crate::access::{self, Capabilities, Capability, PathGrant};
+use crate::group_defaults::{self, MemberSettings};
+use crate::internal_share::{self, SharedItem};
+use crate::mount_visibility;
 use crate::models::UserGroup;
+use crate::storage::ListSort;
+use crate::utils::is_sub_path;
 
 /// FTP服务器配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +61,9 @@ pub struct WebDavConfig {
     pub tls_cert_path: Option<String>,
     /// TLS私钥路径
     pub tls_key_path: Option<String>,
+    /// 目录列表排序方式
+    #[serde(default)]
+    pub sort: ListSort,
 }
 
 impl Default for WebDavConfig {
@@ -66,6 +75,7 @@ impl Default for WebDavConfig {
             tls_enabled: false,
             tls_cert_path: None,
             tls_key_path: None,
+            sort: ListSort::default(),
         }
     }
 }
@@ -90,6 +100,16 @@ pub struct UserPermissions {
     pub webdav_enabled: bool,
     pub is_admin: bool,
     pub root_path: Option<String>,
+    /// 可浏览路径（相对根路径，匿名访问时来自游客策略），为空表示不限制
+    pub browse_paths: Vec<String>,
+    /// 用户组的路径授权
+    pub grants: Vec<PathGrant>,
+    /// 继承自用户组并应用用户覆盖的设置
+    pub settings: MemberSettings,
+    /// 对该用户隐藏的存储（驱动ID）
+    pub hidden_drivers: Vec<String>,
+    /// 站内分享给该用户的项目（显示在“/Shared with me”下）
+    pub shared: Vec<SharedItem>,
 }
 
 impl UserPermissions {
@@ -122,6 +142,29 @@ impl UserPermissions {
         
         perms
     }
+
+    /// 在完整存储路径上是否允许该操作（管理员不受限制）
+    pub fn allows(&self, capability: Capability, path: &str) -> bool {
+        let base = Capabilities {
+            read_files: self.can_read,
+            create_upload: self.can_write,
+            rename_files: self.can_rename,
+            move_files: self.can_move,
+            copy_files: self.can_copy,
+            delete_files: self.can_delete,
+        };
+        self.is_admin
+            || internal_share::allows(&self.shared, capability, path)
+            || (self.settings.path_visible(path, capability == Capability::Read)
+                && access::allows(&base, &self.grants, capability, path))
+    }
+
+    /// 存储是否可见：挂载路径可见且存储未限定给其他用户组（管理员可见全部存储），提供站内分享项目的存储对接收者可见
+    pub fn mount_allowed(&self, driver_id: &str, mount_path: &str) -> bool {
+        self.is_admin
+            || (self.settings.mount_visible(mount_path) && !self.hidden_drivers.iter().any(|d| d == driver_id))
+            || self.shared.iter().any(|s| is_sub_path(mount_path, &s.path) || is_sub_path(&s.path, mount_path))
+    }
 }
 
 /// 认证用户信息
@@ -160,6 +203,11 @@ impl UserAuthenticator {
             return None;
         }
 
+        // 过期账号与需要先修改密码的账号不能使用FTP/WebDAV
+        if crate::accounts::is_expired(user.expires_at.as_deref(), chrono::Utc::now()) || user.must_change_password {
+            return None;
+        }
+
         // 查询用户的所有组
         let groups: Vec<UserGroup> = sqlx::query_as(
             r#"
@@ -173,7 +221,19 @@ impl UserAuthenticator {
         .await
         .ok()?;
 
-        let permissions = UserPermissions::from_groups(&groups, user.root_path.clone());
+        // 用户组要求两步验证而尚未启用的账号需先在网页端完成设置
+        if !user.two_factor_enabled && groups.iter().any(|g| g.require_2fa) {
+            return None;
+        }
+
+        let mut permissions = UserPermissions::from_groups(&groups, user.root_path.clone());
+        permissions.grants = access::load_user_grants(&self.db, &user.id).await.ok()?;
+        permissions.settings = group_defaults::load_user(&self.db, &user.id).await.ok()?;
+        permissions.root_path = Some(permissions.settings.root_path.clone());
+        let rules = mount_visibility::load_rules(&self.db).await.ok()?;
+        let group_ids: Vec<String> = groups.iter().map(|g| g.id.to_string()).collect();
+        permissions.hidden_drivers = mount_visibility::hidden_drivers(&rules, &group_ids);
+        permissions.shared = internal_share::load_for_user(&self.db, &user.id).await.ok()?;
 
         Some(AuthenticatedUser {
             id: user.id,
@@ -182,6 +242,43 @@ impl UserAuthenticator {
         })
     }
 
+    /// 匿名访问使用的游客身份（只读），游客用户被禁用时返回None
+    pub async fn guest(&self, browse_paths: Vec<String>) -> Option<AuthenticatedUser> {
+        let user: crate::models::User = sqlx::query_as(
+            "SELECT * FROM users WHERE username = 'guest' AND enabled = 1"
+        )
+        .fetch_optional(&self.db)
+        .await
+        .ok()??;
+
+        let group: UserGroup = sqlx::query_as(
+            "SELECT * FROM user_groups WHERE name = '游客组'"
+        )
+        .fetch_optional(&self.db)
+        .await
+        .ok()??;
+
+        let user_root = user.root_path.clone().filter(|p| !p.is_empty() && p != "/");
+        let root_path = UserPermissions::from_groups(std::slice::from_ref(&group), user_root).root_path;
+        let grants = access::load_group_grants(&self.db, &group.id.to_string()).await.ok()?;
+        let settings = group_defaults::load_guest(&self.db).await.ok()?;
+        let rules = mount_visibility::load_rules(&self.db).await.ok()?;
+        let hidden_drivers = mount_visibility::hidden_drivers(&rules, &[group.id.to_string()]);
+        Some(AuthenticatedUser {
+            id: user.id,
+            username: user.username,
+            permissions: UserPermissions {
+                can_read: group.read_files,
+                root_path,
+                browse_paths,
+                grants,
+                settings,
+                hidden_drivers,
+                ..Default::default()
+            },
+        })
+    }
+
     /// 验证用户是否有FTP权限
     pub async fn authenticate_ftp(&self, username: &str, password: &str) -> Option<AuthenticatedUser> {
         let user = self.authenticate(username, password).await?;
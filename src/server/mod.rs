@@ -1,5 +1,7 @@
 pub mod webdav;
 pub mod config;
+#[cfg(unix)]
+pub mod unix;
 
 pub use config::{ServerConfig, WebDavConfig, AuthenticatedUser, UserPermissions, UserAuthenticator};
 pub use webdav::{WebDavServer, WebDavFs, create_webdav_server};
@@ -0,0 +1,84 @@
+//! Unix domain socket listener / Unix 套接字监听
+//!
+//! Serves the same router as the TCP listener, meant for a reverse proxy on the same host
+//! (e.g. nginx `proxy_pass http://unix:/run/yaolist.sock`). Connections speak HTTP/1.1 or
+//! prior-knowledge HTTP/2 and may upgrade to WebSocket.
+//! 与 TCP 监听提供相同的路由，供同机反向代理使用（如 nginx），支持 HTTP/1.1、HTTP/2 与 WebSocket 升级。
+//!
+//! Unix sockets carry no peer address; the peer is always a local proxy, so its forwarding
+//! headers are trusted regardless of `server.trusted_proxies`.
+//! Unix 套接字没有对端地址，对端总是本机代理，因此始终信任其转发头。
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use tokio::net::UnixListener;
+use tokio::sync::watch;
+use tower::Service;
+
+use crate::client_ip::{resolve_client_ip, ClientIp, TrustedProxies};
+
+/// Bind `path` (replacing a stale socket) and set its permissions / 绑定套接字（替换遗留的套接字文件）并设置权限
+pub fn bind_unix(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} exists and is not a socket", path)));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+/// Serve `app` on `listener` until `shutdown` flips, then wait for open connections
+/// 在套接字上提供服务，收到停机信号后等待已有连接结束
+pub async fn serve_unix(listener: UnixListener, app: Router, mut shutdown: watch::Receiver<bool>) -> io::Result<()> {
+    let graceful = GracefulShutdown::new();
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Unix socket accept failed: {}", e);
+                    continue;
+                }
+            },
+            _ = shutdown.changed() => break,
+        };
+
+        let tower_service = app.clone();
+        let service = hyper::service::service_fn(move |mut req: Request<Incoming>| {
+            let local = IpAddr::V4(Ipv4Addr::LOCALHOST);
+            let ip = resolve_client_ip(req.headers(), local, &TrustedProxies::current(), true);
+            req.extensions_mut().insert(ConnectInfo(SocketAddr::new(local, 0)));
+            req.extensions_mut().insert(ClientIp(ip));
+            tower_service.clone().call(req)
+        });
+        let conn = Builder::new(TokioExecutor::new())
+            .serve_connection_with_upgrades(TokioIo::new(stream), service)
+            .into_owned();
+        let conn = graceful.watch(conn);
+        tokio::spawn(async move {
+            if let Err(e) = conn.await {
+                tracing::debug!("Unix socket connection error: {}", e);
+            }
+        });
+    }
+
+    let path = listener.local_addr().ok().and_then(|a| a.as_pathname().map(|p| p.to_path_buf()));
+    drop(listener);
+    graceful.shutdown().await;
+    if let Some(path) = path {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
@@ -23,121 +23,19 @@ use tokio::net::TcpListener;
 use tokio::sync::RwLock;
 
 use super::config::{AuthenticatedUser, UserAuthenticator, WebDavConfig};
-use crate::storage::{Entry, StorageManager};
-use crate::utils::should_hide_file;
-
-/// 元信息结构
-#[derive(Debug, Clone, sqlx::FromRow)]
-pub struct Meta {
-    pub id: String,
-    pub path: String,
-    pub password: Option<String>,
-    pub p_sub: bool,
-    pub write: bool,
-    pub w_sub: bool,
-    pub hide: Option<String>,
-    pub h_sub: bool,
-    pub readme: Option<String>,
-    pub r_sub: bool,
-    pub header: Option<String>,
-    pub header_sub: bool,
-    pub created_at: String,
-    pub updated_at: String,
-}
-
-/// 检查隐藏规则是否应用到指定路径
-fn is_hide_apply(meta_path: &str, req_path: &str, h_sub: bool) -> bool {
-    let meta_path = fix_and_clean_path(meta_path);
-    let req_path = fix_and_clean_path(req_path);
-    
-    if meta_path == req_path {
-        return true;
-    }
-    
-    if !is_sub_path(&meta_path, &req_path) {
-        return false;
-    }
-    
-    let relative = req_path.strip_prefix(&meta_path).unwrap_or(&req_path);
-    let relative = relative.trim_start_matches('/');
-    let depth = relative.matches('/').count();
-    
-    depth == 0 || h_sub
-}
-
-/// 挂载点信息
-#[derive(Debug, Clone)]
-pub struct MountInfo {
-    pub id: String,
-    pub mount_path: String,
-    pub order: i32,
-}
-
-/// 清理路径
-fn fix_and_clean_path(path: &str) -> String {
-    let path = path.replace('\\', "/");
-    let path = if path.is_empty() || !path.starts_with('/') {
-        format!("/{}", path)
-    } else {
-        path
-    };
-    
-    // 移除连续斜杠
-    let mut result = String::new();
-    let mut prev_slash = false;
-    for c in path.chars() {
-        if c == '/' {
-            if !prev_slash {
-                result.push(c);
-            }
-            prev_slash = true;
-        } else {
-            result.push(c);
-            prev_slash = false;
-        }
-    }
-    
-    // 移除末尾斜杠（根目录除外）
-    if result.len() > 1 && result.ends_with('/') {
-        result.pop();
-    }
-    
-    result
-}
-
-/// 检查路径是否是子路径
-fn is_sub_path(parent: &str, child: &str) -> bool {
-    let parent = fix_and_clean_path(parent);
-    let child = fix_and_clean_path(child);
-    
-    if parent == "/" {
-        return true;
-    }
-    
-    child == parent || child.starts_with(&format!("{}/", parent))
-}
+use crate::access::Capability;
+use crate::storage::{DriverBox, Entry, ListSort, SpaceInfo, StorageManager};
+use crate::guest::path_browsable;
+use crate::hide_rules::HideRules;
+use crate::internal_share::{self, Resolved, SharedItem, SHARED_ROOT};
+use crate::path_resolver::{
+    calculate_internal_path, get_matching_mounts, join_user_path, virtual_dirs, MountInfo, PathResolver,
+};
+use crate::utils::{fix_and_clean_path, is_sub_path};
+use crate::virus_scan;
 
-/// 将用户请求路径与用户根路径结合
-fn join_user_path(base_path: &str, req_path: &str) -> Result<String, String> {
-    if req_path.contains("..") {
-        return Err("路径不合法".to_string());
-    }
-    
-    let clean_req = fix_and_clean_path(req_path);
-    let clean_base = fix_and_clean_path(base_path);
-    
-    if clean_base == "/" {
-        return Ok(clean_req);
-    }
-    
-    let full_path = if clean_req == "/" {
-        clean_base.clone()
-    } else {
-        format!("{}{}", clean_base.trim_end_matches('/'), clean_req)
-    };
-    
-    Ok(fix_and_clean_path(&full_path))
-}
+/// 查询驱动空间信息的超时（秒），超时的挂载不计入配额
+const QUOTA_TIMEOUT_SECS: u64 = 10;
 
 /// WebDAV文件系统适配器
 /// 将StorageDriver包装成dav-server的DavFileSystem
@@ -145,8 +43,11 @@ fn join_user_path(base_path: &str, req_path: &str) -> Result<String, String> {
 #[derive(Clone)]
 pub struct WebDavFs {
     storage_manager: StorageManager,
-    db: SqlitePool,
+    paths: PathResolver,
     user: Arc<RwLock<Option<AuthenticatedUser>>>,
+    sort: ListSort,
+    /// 本次请求的路径（不含前缀），用于按路径计算配额属性
+    request_path: Option<String>,
 }
 
 impl Debug for WebDavFs {
@@ -156,22 +57,45 @@ impl Debug for WebDavFs {
 }
 
 impl WebDavFs {
-    pub fn new(storage_manager: StorageManager, db: SqlitePool) -> Self {
+    pub fn new(storage_manager: StorageManager, paths: PathResolver) -> Self {
         Self {
             storage_manager,
-            db,
+            paths,
             user: Arc::new(RwLock::new(None)),
+            sort: ListSort::default(),
+            request_path: None,
         }
     }
 
-    pub fn with_user(storage_manager: StorageManager, db: SqlitePool, user: AuthenticatedUser) -> Self {
+    pub fn with_user(storage_manager: StorageManager, paths: PathResolver, user: AuthenticatedUser) -> Self {
         Self {
             storage_manager,
-            db,
+            paths,
             user: Arc::new(RwLock::new(Some(user))),
+            sort: ListSort::default(),
+            request_path: None,
         }
     }
 
+    /// 设置目录列表排序方式（默认按名称自然排序）
+    pub fn with_sort(mut self, sort: ListSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// 设置本次请求的URL路径（URL编码，含前缀），PROPFIND的配额属性按该路径所在的挂载计算
+    pub fn with_request_path(mut self, uri_path: &str, prefix: &str) -> Self {
+        let mut path = match DavPath::new(uri_path) {
+            Ok(path) => path,
+            Err(_) => return self,
+        };
+        if !prefix.is_empty() && path.set_prefix(prefix).is_err() {
+            return self;
+        }
+        self.request_path = Some(fix_and_clean_path(&path.as_pathbuf().to_string_lossy()));
+        self
+    }
+
     /// 设置用户
     pub async fn set_user(&self, user: AuthenticatedUser) {
         let mut guard = self.user.write().await;
@@ -186,120 +110,33 @@ impl WebDavFs {
             .unwrap_or_else(|| "/".to_string())
     }
 
-    /// 从数据库获取所有启用的挂载点
+    /// 获取对用户可见的已启用挂载点
     async fn get_all_mounts(&self) -> Vec<MountInfo> {
-        let db_drivers: Vec<(String, String)> = sqlx::query_as(
-            "SELECT name, config FROM drivers WHERE enabled = 1"
-        )
-        .fetch_all(&self.db)
-        .await
-        .unwrap_or_default();
-        
-        db_drivers.iter().filter_map(|(id, config_str)| {
-            let config: Value = serde_json::from_str(config_str).ok()?;
-            let mount_path = config.get("mount_path").and_then(|v| v.as_str())?;
-            let order = config.get("order").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            Some(MountInfo {
-                id: id.clone(),
-                mount_path: mount_path.to_string(),
-                order,
-            })
-        }).collect()
-    }
-
-    /// 获取匹配路径的驱动（最长匹配）
-    fn get_matching_mounts<'a>(&self, path: &str, mounts: &'a [MountInfo]) -> Vec<&'a MountInfo> {
-        let path = fix_and_clean_path(path);
-        let mut best_len = 0;
-        
-        for mount in mounts {
-            let mount_path = fix_and_clean_path(&mount.mount_path);
-            if is_sub_path(&mount_path, &path) && mount_path.len() > best_len {
-                best_len = mount_path.len();
-            }
+        let mut mounts = self.paths.mounts().await.unwrap_or_default();
+        let user = self.user.read().await;
+        if let Some(u) = user.as_ref() {
+            mounts.retain(|m| u.permissions.mount_allowed(&m.id, &m.mount_path));
         }
-        
-        let mut result: Vec<&MountInfo> = mounts.iter()
-            .filter(|mount| {
-                let mount_path = fix_and_clean_path(&mount.mount_path);
-                is_sub_path(&mount_path, &path) && mount_path.len() == best_len
-            })
-            .collect();
-        
-        result.sort_by_key(|m| m.order);
-        result
+        mounts
     }
 
     /// 获取虚拟目录列表（没有匹配驱动时显示子挂载点）
     fn get_virtual_dirs(&self, prefix: &str, mounts: &[MountInfo]) -> Vec<WebDavDirEntry> {
-        let prefix = fix_and_clean_path(prefix);
-        let mut seen: HashMap<String, bool> = HashMap::new();
-        let mut dirs = Vec::new();
-        
-        for mount in mounts {
-            let mount_path = fix_and_clean_path(&mount.mount_path);
-            
-            if prefix.len() >= mount_path.len() || !is_sub_path(&prefix, &mount_path) {
-                continue;
-            }
-            
-            let relative = mount_path.strip_prefix(&prefix).unwrap_or(&mount_path);
-            let relative = relative.trim_start_matches('/');
-            let name = relative.split('/').next().unwrap_or(relative);
-            
-            if name.is_empty() || seen.contains_key(name) {
-                continue;
-            }
-            
-            seen.insert(name.to_string(), true);
-            dirs.push(WebDavDirEntry {
-                name: name.to_string(),
-                metadata: WebDavMetaData {
-                    is_dir: true,
-                    size: 0,
-                    modified: Some(SystemTime::now()),
-                    created: None,
-                },
-            });
-        }
-        
-        dirs
-    }
-
-    /// 获取最近的元信息（向上查找父目录）
-    async fn get_nearest_meta(&self, path: &str) -> Option<Meta> {
-        let path = fix_and_clean_path(path);
-        
-        // 先尝试精确匹配
-        if let Ok(Some(meta)) = sqlx::query_as::<_, Meta>(
-            "SELECT id, path, password, p_sub, write, w_sub, hide, h_sub, readme, r_sub, header, header_sub, created_at, updated_at FROM metas WHERE path = ?"
-        )
-        .bind(&path)
-        .fetch_optional(&self.db)
-        .await {
-            return Some(meta);
-        }
-        
-        // 递归向上查找父目录
-        if path == "/" {
-            return None;
-        }
-        
-        let parent = std::path::Path::new(&path)
-            .parent()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|| "/".to_string());
-        let parent = if parent.is_empty() { "/".to_string() } else { parent };
-        
-        Box::pin(self.get_nearest_meta(&parent)).await
+        virtual_dirs(prefix, mounts).into_iter().map(|dir| WebDavDirEntry {
+            name: dir.name,
+            metadata: WebDavMetaData {
+                is_dir: true,
+                size: 0,
+                modified: Some(SystemTime::now()),
+                created: None,
+            },
+        }).collect()
     }
 
-    /// 获取隐藏规则
-    async fn get_hide_patterns(&self, path: &str) -> String {
-        let meta = self.get_nearest_meta(path).await;
-        meta.as_ref()
-            .filter(|m| is_hide_apply(&m.path, path, m.h_sub))
-            .and_then(|m| m.hide.clone())
+    /// 获取应用到目录下直接文件的隐藏规则
+    async fn get_hide_rules(&self, path: &str) -> HideRules {
+        let meta = self.paths.nearest_meta(path).await;
+        meta.map(|m| HideRules::at(&m.path, m.hide.as_deref().unwrap_or(""), m.h_sub, path))
             .unwrap_or_default()
     }
 
@@ -311,35 +148,101 @@ impl WebDavFs {
             .unwrap_or(false)
     }
 
-    /// 检查读取权限
-    async fn check_read(&self) -> FsResult<()> {
+    /// 获取可浏览路径限制（为空表示不限制）
+    async fn browse_paths(&self) -> Vec<String> {
         let user = self.user.read().await;
-        match user.as_ref() {
-            Some(u) if u.permissions.can_read => Ok(()),
-            Some(_) => Err(FsError::Forbidden),
-            None => Err(FsError::Forbidden),
-        }
+        user.as_ref()
+            .map(|u| u.permissions.browse_paths.clone())
+            .unwrap_or_default()
     }
 
-    /// 检查写入权限
-    async fn check_write(&self) -> FsResult<()> {
+    /// 站内分享给用户的项目
+    async fn shared(&self) -> Vec<SharedItem> {
         let user = self.user.read().await;
-        match user.as_ref() {
-            Some(u) if u.permissions.can_write => Ok(()),
-            Some(_) => Err(FsError::Forbidden),
-            None => Err(FsError::Forbidden),
+        user.as_ref().map(|u| u.permissions.shared.clone()).unwrap_or_default()
+    }
+
+    /// 将请求路径解析为完整存储路径：“/Shared with me/<名称>”下的路径映射到被分享的路径，其他路径与用户根路径结合
+    async fn join_path(&self, root: &str, req_path: &str) -> FsResult<String> {
+        match internal_share::resolve(&self.shared().await, req_path) {
+            Resolved::NotShared => join_user_path(root, req_path).map_err(|_| FsError::Forbidden),
+            Resolved::Path(path) => Ok(path),
+            Resolved::Root => Err(FsError::Forbidden),
+            Resolved::Missing => Err(FsError::NotFound),
+        }
+    }
+
+    /// 存储路径所在挂载（虚拟目录时为其下所有挂载）的空间合计，没有驱动提供空间信息时返回 None
+    async fn space_info(&self, storage_path: &str) -> Option<SpaceInfo> {
+        let mounts = self.get_all_mounts().await;
+        let matching = get_matching_mounts(storage_path, &mounts);
+        let scoped: Vec<&MountInfo> = if matching.is_empty() {
+            mounts.iter().filter(|m| is_sub_path(storage_path, &m.mount_path)).collect()
+        } else {
+            matching
+        };
+
+        let timeout = std::time::Duration::from_secs(QUOTA_TIMEOUT_SECS);
+        let mut total: Option<SpaceInfo> = None;
+        for mount in scoped {
+            let Some(driver) = self.storage_manager.get_driver(&mount.id).await else {
+                continue;
+            };
+            if let Ok(Ok(Some(space))) = tokio::time::timeout(timeout, driver.get_space_info()).await {
+                total = Some(match total {
+                    Some(sum) => SpaceInfo {
+                        used: sum.used.saturating_add(space.used),
+                        total: sum.total.saturating_add(space.total),
+                        free: sum.free.saturating_add(space.free),
+                    },
+                    None => space,
+                });
+            }
         }
+        total
     }
 
-    /// 检查删除权限
-    async fn check_delete(&self) -> FsResult<()> {
+    /// 检查在完整存储路径上的操作权限（按路径授权）
+    async fn check(&self, capability: Capability, storage_path: &str) -> FsResult<()> {
         let user = self.user.read().await;
         match user.as_ref() {
-            Some(u) if u.permissions.can_delete => Ok(()),
+            Some(u) if u.permissions.allows(capability, storage_path) => Ok(()),
             Some(_) => Err(FsError::Forbidden),
             None => Err(FsError::Forbidden),
         }
     }
+
+    /// WebDAV 没有在线预览，仅预览的文件不能读取；复制或移动时不能带出仅预览的范围（管理员不受限制）
+    async fn check_preview_only(&self, from: &str, to: Option<&str>) -> FsResult<()> {
+        let is_admin = self.user.read().await.as_ref().is_some_and(|u| u.permissions.is_admin);
+        let blocked = match to {
+            _ if is_admin => false,
+            Some(to) => self.paths.leaks_preview_only(from, to).await,
+            None => self.paths.is_preview_only(from).await,
+        };
+        if blocked { Err(FsError::Forbidden) } else { Ok(()) }
+    }
+
+    /// 检查挂载点与用户组的上传策略（扩展名、单文件上限），返回写入时的大小上限
+    async fn check_upload(&self, mount: &MountInfo, storage_path: &str, size: Option<u64>) -> FsResult<Option<u64>> {
+        let user = self.user.read().await;
+        let user_policy = user.as_ref().map(|u| u.permissions.settings.upload_policy()).unwrap_or_default();
+        mount.upload.check(storage_path, size)
+            .and_then(|_| user_policy.check(storage_path, size))
+            .map_err(|_| FsError::Forbidden)?;
+        Ok(mount.upload.size_limit().into_iter().chain(user_policy.size_limit()).min())
+    }
+}
+
+/// 将空间信息转换为dav-server的 (已用, 总量)：用户在该路径没有上传权限时可用空间为 0。
+/// dav-server 在根路径以外把条目自身大小当作已用并从总量中扣除，所以此时总量直接给出可用空间
+fn dav_quota(space: &SpaceInfo, writable: bool, at_root: bool) -> (u64, Option<u64>) {
+    let free = if writable { space.free } else { 0 };
+    if at_root {
+        (space.used, Some(space.used.saturating_add(free)))
+    } else {
+        (space.used, Some(free))
+    }
 }
 
 /// WebDAV文件元数据
@@ -417,6 +320,54 @@ pub struct WebDavFile {
     position: Arc<tokio::sync::Mutex<u64>>,
     /// 文件大小
     size: u64,
+    /// 以写入方式打开（PUT）时的上传缓存
+    upload: Option<UploadSpool>,
+}
+
+/// PUT 写入的内容先缓存在临时文件中，flush 时扫描病毒后再写入驱动
+struct UploadSpool {
+    file: tokio::fs::File,
+    len: u64,
+    /// 挂载点与用户组的单文件上限
+    limit: Option<u64>,
+    /// 完整存储路径（用于扫描记录）
+    storage_path: String,
+    user_id: Option<String>,
+    db: SqlitePool,
+}
+
+impl UploadSpool {
+    async fn write(&mut self, buf: &[u8]) -> FsResult<()> {
+        use tokio::io::AsyncWriteExt;
+        self.len += buf.len() as u64;
+        if self.limit.is_some_and(|limit| self.len > limit) {
+            return Err(FsError::TooLarge);
+        }
+        self.file.write_all(buf).await.map_err(|_| FsError::GeneralFailure)
+    }
+
+    /// 扫描缓存的内容，通过后写入驱动；被拒绝的上传不会到达挂载点
+    async fn commit(mut self, driver: &DriverBox, driver_path: &str) -> FsResult<()> {
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+        let path = self.storage_path.clone();
+        let failed = |e: String| {
+            tracing::error!("WebDAV failed to write {}: {}", path, e);
+            FsError::GeneralFailure
+        };
+        self.file.flush().await.map_err(|e| failed(e.to_string()))?;
+        self.file.rewind().await.map_err(|e| failed(e.to_string()))?;
+        if let Err(message) = virus_scan::scan_before_commit(
+            &self.db, &mut self.file, &self.storage_path, self.user_id.as_deref(), self.len,
+        ).await {
+            tracing::warn!("WebDAV upload {} rejected: {}", self.storage_path, message);
+            return Err(FsError::Forbidden);
+        }
+        self.file.rewind().await.map_err(|e| failed(e.to_string()))?;
+        let mut writer = driver.open_writer(driver_path, Some(self.len), None).await
+            .map_err(|e| failed(e.to_string()))?;
+        tokio::io::copy(&mut self.file, &mut writer).await.map_err(|e| failed(e.to_string()))?;
+        writer.shutdown().await.map_err(|e| failed(e.to_string()))
+    }
 }
 
 impl Debug for WebDavFile {
@@ -442,17 +393,15 @@ impl DavFile for WebDavFile {
         })
     }
 
-    fn write_buf(&mut self, _buf: Box<dyn bytes::Buf + Send>) -> FsFuture<'_, ()> {
-        Box::pin(async move {
-            // TODO: 实现写入
-            Err(FsError::NotImplemented)
-        })
+    fn write_buf(&mut self, mut buf: Box<dyn bytes::Buf + Send>) -> FsFuture<'_, ()> {
+        let bytes = buf.copy_to_bytes(buf.remaining());
+        self.write_bytes(bytes)
     }
 
-    fn write_bytes(&mut self, _buf: Bytes) -> FsFuture<'_, ()> {
+    fn write_bytes(&mut self, buf: Bytes) -> FsFuture<'_, ()> {
         Box::pin(async move {
-            // TODO: 实现写入
-            Err(FsError::NotImplemented)
+            let spool = self.upload.as_mut().ok_or(FsError::Forbidden)?;
+            spool.write(&buf).await
         })
     }
 
@@ -504,7 +453,12 @@ impl DavFile for WebDavFile {
     fn seek(&mut self, pos: SeekFrom) -> FsFuture<'_, u64> {
         let position = self.position.clone();
         let size = self.size;
+        // 写入只支持整个文件，不支持带 Content-Range 的部分写入
+        let partial_write = self.upload.is_some() && pos != SeekFrom::Start(0);
         Box::pin(async move {
+            if partial_write {
+                return Err(FsError::NotImplemented);
+            }
             let mut current_pos = position.lock().await;
             let new_pos = match pos {
                 SeekFrom::Start(n) => n,
@@ -517,19 +471,23 @@ impl DavFile for WebDavFile {
     }
 
     fn flush(&mut self) -> FsFuture<'_, ()> {
-        Box::pin(async move { Ok(()) })
+        Box::pin(async move {
+            let Some(spool) = self.upload.take() else {
+                return Ok(());
+            };
+            let driver = self.storage_manager.get_driver(&self.driver_id).await
+                .ok_or(FsError::NotFound)?;
+            spool.commit(&driver, &self.driver_path).await
+        })
     }
 }
 
 impl DavFileSystem for WebDavFs {
-    fn open<'a>(&'a self, path: &'a DavPath, _options: OpenOptions) -> FsFuture<'a, Box<dyn DavFile>> {
+    fn open<'a>(&'a self, path: &'a DavPath, options: OpenOptions) -> FsFuture<'a, Box<dyn DavFile>> {
         let path_clone = path.clone();
         let fs = self.clone();
 
         Box::pin(async move {
-            // 检查读取权限
-            fs.check_read().await?;
-            
             // 获取用户根路径
             let root = fs.get_root_path().await;
             
@@ -537,16 +495,17 @@ impl DavFileSystem for WebDavFs {
             // 使用as_pathbuf()获取解码后的路径
             let req_path = path_clone.as_pathbuf().to_string_lossy().to_string();
             let req_path = fix_and_clean_path(&req_path);
-            let storage_path = match join_user_path(&root, &req_path) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            if !path_browsable(&fs.browse_paths().await, &req_path) {
+                return Err(FsError::Forbidden);
+            }
+            let storage_path = fs.join_path(&root, &req_path).await?;
+            fs.check(Capability::Read, &storage_path).await?;
 
             // 获取所有挂载点
             let mounts = fs.get_all_mounts().await;
             
             // 查找匹配的驱动
-            let matching_mounts = fs.get_matching_mounts(&storage_path, &mounts);
+            let matching_mounts = get_matching_mounts(&storage_path, &mounts);
             
             if matching_mounts.is_empty() {
                 return Err(FsError::NotFound);
@@ -554,14 +513,29 @@ impl DavFileSystem for WebDavFs {
             
             // 使用第一个匹配的挂载点
             let mount = &matching_mounts[0];
-            let mount_path = fix_and_clean_path(&mount.mount_path);
             
-            // 计算相对于驱动的路径
-            let driver_path = if storage_path.len() > mount_path.len() {
-                fix_and_clean_path(&storage_path[mount_path.len()..])
+            // PUT 写入前检查上传权限与上传策略，读取时检查仅预览
+            let upload = if options.write || options.create || options.create_new {
+                fs.check(Capability::Upload, &storage_path).await?;
+                let limit = fs.check_upload(mount, &storage_path, options.size).await?;
+                let file = tempfile::tempfile().map_err(|_| FsError::GeneralFailure)?;
+                let user_id = fs.user.read().await.as_ref().map(|u| u.id.clone());
+                Some(UploadSpool {
+                    file: tokio::fs::File::from_std(file),
+                    len: 0,
+                    limit,
+                    storage_path: storage_path.clone(),
+                    user_id,
+                    db: fs.paths.db().clone(),
+                })
             } else {
-                "/".to_string()
+                fs.check_preview_only(&storage_path, None).await?;
+                None
             };
+            let mount_path = fix_and_clean_path(&mount.mount_path);
+            
+            // 计算相对于驱动的路径
+            let driver_path = calculate_internal_path(&mount_path, &storage_path);
             
             // 获取文件元信息以获取大小
             let mut size = 0u64;
@@ -592,6 +566,7 @@ impl DavFileSystem for WebDavFs {
                 storage_manager: fs.storage_manager.clone(),
                 position: Arc::new(tokio::sync::Mutex::new(0)),
                 size,
+                upload,
             };
 
             Ok(Box::new(file) as Box<dyn DavFile>)
@@ -607,9 +582,6 @@ impl DavFileSystem for WebDavFs {
         let fs = self.clone();
 
         Box::pin(async move {
-            // 检查读取权限
-            fs.check_read().await?;
-
             // 获取用户根路径
             let root = fs.get_root_path().await;
             
@@ -618,33 +590,50 @@ impl DavFileSystem for WebDavFs {
             let req_path = path_clone.as_pathbuf().to_string_lossy().to_string();
             let req_path = fix_and_clean_path(&req_path);
             tracing::debug!("WebDAV read_dir: root={}, req_path={}", root, req_path);
-            
-            let storage_path = match join_user_path(&root, &req_path) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
+            let browse_paths = fs.browse_paths().await;
+            if !path_browsable(&browse_paths, &req_path) {
+                return Err(FsError::Forbidden);
+            }
+            let child_browsable = |name: &str| {
+                path_browsable(&browse_paths, &format!("{}/{}", req_path.trim_end_matches('/'), name))
             };
+            
+            // “/Shared with me”列出站内分享给用户的项目
+            let shared = fs.shared().await;
+            if internal_share::resolve(&shared, &req_path) == Resolved::Root {
+                let mut entries: Vec<Entry> = shared.iter()
+                    .map(|item| Entry { name: item.name.clone(), path: String::new(), is_dir: item.is_dir, size: 0, modified: None })
+                    .collect();
+                fs.sort.sort(&mut entries);
+                let dir_entries: Vec<Box<dyn DavDirEntry>> = entries.iter()
+                    .map(|e| Box::new(WebDavDirEntry { name: e.name.clone(), metadata: WebDavMetaData::from(e) }) as Box<dyn DavDirEntry>)
+                    .collect();
+                return Ok(Box::pin(stream::iter(dir_entries.into_iter().map(Ok)))
+                    as FsStream<Box<dyn DavDirEntry>>);
+            }
+            
+            let storage_path = fs.join_path(&root, &req_path).await?;
+            fs.check(Capability::Read, &storage_path).await?;
             tracing::debug!("WebDAV read_dir: storage_path={}", storage_path);
 
             // 获取隐藏规则（元信息隐藏在WebDAV生效，密码不生效）
-            let hide_patterns = fs.get_hide_patterns(&storage_path).await;
+            let hide_rules = fs.get_hide_rules(&storage_path).await;
             let can_show_hidden = fs.can_show_hidden().await;
 
             // 获取所有挂载点
             let mounts = fs.get_all_mounts().await;
             
             // 查找匹配的驱动
-            let matching_mounts = fs.get_matching_mounts(&storage_path, &mounts);
+            let matching_mounts = get_matching_mounts(&storage_path, &mounts);
             
+            // 按名称去重，排序用的条目与返回的条目分开保存
             let mut all_entries: HashMap<String, WebDavDirEntry> = HashMap::new();
+            let mut sort_entries: Vec<Entry> = Vec::new();
             
             if !matching_mounts.is_empty() {
                 // 计算相对于挂载点的实际路径
                 let mount_path = fix_and_clean_path(&matching_mounts[0].mount_path);
-                let actual_path = if storage_path.len() > mount_path.len() {
-                    fix_and_clean_path(&storage_path[mount_path.len()..])
-                } else {
-                    "/".to_string()
-                };
+                let actual_path = calculate_internal_path(&mount_path, &storage_path);
                 
                 // 从所有匹配的驱动获取文件列表
                 for mount in &matching_mounts {
@@ -652,13 +641,20 @@ impl DavFileSystem for WebDavFs {
                         if let Ok(entries) = driver.list(&actual_path).await {
                             for e in entries {
                                 // 应用隐藏规则过滤
-                                if !can_show_hidden && should_hide_file(&e.name, &hide_patterns) {
+                                if !can_show_hidden && hide_rules.hides(&e.name) {
+                                    continue;
+                                }
+                                if !child_browsable(&e.name) {
                                     continue;
                                 }
-                                all_entries.entry(e.name.clone()).or_insert(WebDavDirEntry {
+                                if all_entries.contains_key(&e.name) {
+                                    continue;
+                                }
+                                all_entries.insert(e.name.clone(), WebDavDirEntry {
                                     name: e.name.clone(),
                                     metadata: WebDavMetaData::from(&e),
                                 });
+                                sort_entries.push(e);
                             }
                         }
                     }
@@ -668,10 +664,28 @@ impl DavFileSystem for WebDavFs {
             // 合并虚拟目录（子挂载点），同样应用隐藏规则
             let virtual_dirs = fs.get_virtual_dirs(&storage_path, &mounts);
             for vd in virtual_dirs {
-                if !can_show_hidden && should_hide_file(&vd.name, &hide_patterns) {
+                if (!can_show_hidden && hide_rules.hides(&vd.name)) || !child_browsable(&vd.name) {
+                    continue;
+                }
+                if all_entries.contains_key(&vd.name) {
                     continue;
                 }
-                all_entries.entry(vd.name.clone()).or_insert(vd);
+                sort_entries.push(Entry {
+                    name: vd.name.clone(),
+                    path: String::new(),
+                    is_dir: true,
+                    size: 0,
+                    modified: None,
+                });
+                all_entries.insert(vd.name.clone(), vd);
+            }
+            
+            // 根目录中显示“/Shared with me”
+            let shared_root = SHARED_ROOT.trim_start_matches('/');
+            if req_path == "/" && !shared.is_empty() && !all_entries.contains_key(shared_root) {
+                let entry = Entry { name: shared_root.to_string(), path: String::new(), is_dir: true, size: 0, modified: None };
+                all_entries.insert(entry.name.clone(), WebDavDirEntry { name: entry.name.clone(), metadata: WebDavMetaData::from(&entry) });
+                sort_entries.push(entry);
             }
             
             // 如果没有任何内容且不是根目录，返回404
@@ -679,8 +693,11 @@ impl DavFileSystem for WebDavFs {
                 return Err(FsError::NotFound);
             }
             
-            let dir_entries: Vec<Box<dyn DavDirEntry>> = all_entries
-                .into_values()
+            // 与网页端相同的排序，客户端按返回顺序显示时也有序
+            fs.sort.sort(&mut sort_entries);
+            let dir_entries: Vec<Box<dyn DavDirEntry>> = sort_entries
+                .iter()
+                .filter_map(|e| all_entries.remove(&e.name))
                 .map(|e| Box::new(e) as Box<dyn DavDirEntry>)
                 .collect();
             
@@ -701,13 +718,14 @@ impl DavFileSystem for WebDavFs {
             // 使用as_pathbuf()获取解码后的路径
             let req_path = path_clone.as_pathbuf().to_string_lossy().to_string();
             let req_path = fix_and_clean_path(&req_path);
-            let storage_path = match join_user_path(&root, &req_path) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            if !path_browsable(&fs.browse_paths().await, &req_path) {
+                return Err(FsError::Forbidden);
+            }
+            let shared_root = internal_share::resolve(&fs.shared().await, &req_path) == Resolved::Root;
+            let storage_path = if shared_root { root.clone() } else { fs.join_path(&root, &req_path).await? };
 
-            // 根路径返回目录元数据
-            if storage_path == "/" || storage_path == root {
+            // 根路径与“/Shared with me”返回目录元数据
+            if shared_root || storage_path == "/" || storage_path == root {
                 return Ok(Box::new(WebDavMetaData {
                     is_dir: true,
                     size: 0,
@@ -718,7 +736,7 @@ impl DavFileSystem for WebDavFs {
 
             // 获取挂载点
             let mounts = fs.get_all_mounts().await;
-            let _matching_mounts = fs.get_matching_mounts(&storage_path, &mounts);
+            let _matching_mounts = get_matching_mounts(&storage_path, &mounts);
             
             // 检查是否是虚拟目录
             let virtual_dirs = fs.get_virtual_dirs(&storage_path, &mounts);
@@ -745,7 +763,7 @@ impl DavFileSystem for WebDavFs {
                 .map(|n| n.to_string_lossy().to_string())
                 .unwrap_or_default();
 
-            let parent_mounts = fs.get_matching_mounts(&parent, &mounts);
+            let parent_mounts = get_matching_mounts(&parent, &mounts);
             
             if parent_mounts.is_empty() {
                 // 检查是否是虚拟目录中的项
@@ -760,11 +778,7 @@ impl DavFileSystem for WebDavFs {
 
             // 计算相对于挂载点的路径
             let mount_path = fix_and_clean_path(&parent_mounts[0].mount_path);
-            let actual_parent = if parent.len() > mount_path.len() {
-                fix_and_clean_path(&parent[mount_path.len()..])
-            } else {
-                "/".to_string()
-            };
+            let actual_parent = calculate_internal_path(&mount_path, &parent);
 
             // 从驱动获取文件列表查找目标文件
             for mount in &parent_mounts {
@@ -789,23 +803,18 @@ impl DavFileSystem for WebDavFs {
         let fs = self.clone();
 
         Box::pin(async move {
-            // 检查写入权限
-            fs.check_write().await?;
-            
             // 获取用户根路径
             let root = fs.get_root_path().await;
             
             // 将请求路径与用户根路径结合
             let req_path = path_clone.as_pathbuf().to_string_lossy().to_string();
             let req_path = fix_and_clean_path(&req_path);
-            let storage_path = match join_user_path(&root, &req_path) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            let storage_path = fs.join_path(&root, &req_path).await?;
+            fs.check(Capability::Upload, &storage_path).await?;
 
             // 获取挂载点
             let mounts = fs.get_all_mounts().await;
-            let matching_mounts = fs.get_matching_mounts(&storage_path, &mounts);
+            let matching_mounts = get_matching_mounts(&storage_path, &mounts);
             
             if matching_mounts.is_empty() {
                 return Err(FsError::NotFound);
@@ -813,11 +822,7 @@ impl DavFileSystem for WebDavFs {
             
             // 计算相对于挂载点的路径
             let mount_path = fix_and_clean_path(&matching_mounts[0].mount_path);
-            let actual_path = if storage_path.len() > mount_path.len() {
-                fix_and_clean_path(&storage_path[mount_path.len()..])
-            } else {
-                "/".to_string()
-            };
+            let actual_path = calculate_internal_path(&mount_path, &storage_path);
 
             // 使用第一个匹配的驱动创建目录
             if let Some(driver) = fs.storage_manager.get_driver(&matching_mounts[0].id).await {
@@ -833,23 +838,18 @@ impl DavFileSystem for WebDavFs {
         let fs = self.clone();
 
         Box::pin(async move {
-            // 检查删除权限
-            fs.check_delete().await?;
-            
             // 获取用户根路径
             let root = fs.get_root_path().await;
             
             // 将请求路径与用户根路径结合
             let req_path = path_clone.as_pathbuf().to_string_lossy().to_string();
             let req_path = fix_and_clean_path(&req_path);
-            let storage_path = match join_user_path(&root, &req_path) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            let storage_path = fs.join_path(&root, &req_path).await?;
+            fs.check(Capability::Delete, &storage_path).await?;
 
             // 获取挂载点
             let mounts = fs.get_all_mounts().await;
-            let matching_mounts = fs.get_matching_mounts(&storage_path, &mounts);
+            let matching_mounts = get_matching_mounts(&storage_path, &mounts);
             
             if matching_mounts.is_empty() {
                 return Err(FsError::NotFound);
@@ -857,11 +857,7 @@ impl DavFileSystem for WebDavFs {
             
             // 计算相对于挂载点的路径
             let mount_path = fix_and_clean_path(&matching_mounts[0].mount_path);
-            let actual_path = if storage_path.len() > mount_path.len() {
-                fix_and_clean_path(&storage_path[mount_path.len()..])
-            } else {
-                "/".to_string()
-            };
+            let actual_path = calculate_internal_path(&mount_path, &storage_path);
 
             // 使用第一个匹配的驱动删除
             if let Some(driver) = fs.storage_manager.get_driver(&matching_mounts[0].id).await {
@@ -882,37 +878,34 @@ impl DavFileSystem for WebDavFs {
         let fs = self.clone();
 
         Box::pin(async move {
-            // 检查权限
-            {
-                let user = fs.user.read().await;
-                let can_op = user.as_ref()
-                    .map(|u| u.permissions.can_rename || u.permissions.can_move)
-                    .unwrap_or(false);
-                if !can_op {
-                    return Err(FsError::Forbidden);
-                }
-            }
-            
             // 获取用户根路径
             let root = fs.get_root_path().await;
             
             // 将请求路径与用户根路径结合
             let from_req = from_clone.as_pathbuf().to_string_lossy().to_string();
             let from_req = fix_and_clean_path(&from_req);
-            let from_path = match join_user_path(&root, &from_req) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            let from_path = fs.join_path(&root, &from_req).await?;
             let to_req = to_clone.as_pathbuf().to_string_lossy().to_string();
             let to_req = fix_and_clean_path(&to_req);
-            let to_path = match join_user_path(&root, &to_req) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            let to_path = fs.join_path(&root, &to_req).await?;
+
+            // 检查权限：源路径与目标路径都需要重命名或移动权限
+            {
+                let user = fs.user.read().await;
+                let can_op = user.as_ref()
+                    .map(|u| [&from_path, &to_path].iter().all(|p| {
+                        u.permissions.allows(Capability::Rename, p) || u.permissions.allows(Capability::Move, p)
+                    }))
+                    .unwrap_or(false);
+                if !can_op {
+                    return Err(FsError::Forbidden);
+                }
+            }
+            fs.check_preview_only(&from_path, Some(&to_path)).await?;
 
             // 获取挂载点
             let mounts = fs.get_all_mounts().await;
-            let from_mounts = fs.get_matching_mounts(&from_path, &mounts);
+            let from_mounts = get_matching_mounts(&from_path, &mounts);
             
             if from_mounts.is_empty() {
                 return Err(FsError::NotFound);
@@ -920,11 +913,7 @@ impl DavFileSystem for WebDavFs {
             
             // 计算相对于挂载点的路径
             let mount_path = fix_and_clean_path(&from_mounts[0].mount_path);
-            let actual_from = if from_path.len() > mount_path.len() {
-                fix_and_clean_path(&from_path[mount_path.len()..])
-            } else {
-                "/".to_string()
-            };
+            let actual_from = calculate_internal_path(&mount_path, &from_path);
 
             // 检查是重命名还是移动
             let from_parent = fix_and_clean_path(
@@ -952,11 +941,7 @@ impl DavFileSystem for WebDavFs {
                     driver.rename(&actual_from, &new_name).await.map_err(|_| FsError::GeneralFailure)
                 } else {
                     // 移动
-                    let actual_to = if to_path.len() > mount_path.len() {
-                        fix_and_clean_path(&to_path[mount_path.len()..])
-                    } else {
-                        "/".to_string()
-                    };
+                    let actual_to = calculate_internal_path(&mount_path, &to_path);
                     driver.move_item(&actual_from, &actual_to).await.map_err(|_| FsError::GeneralFailure)
                 }
             } else {
@@ -971,37 +956,25 @@ impl DavFileSystem for WebDavFs {
         let fs = self.clone();
 
         Box::pin(async move {
-            // 检查复制权限
-            {
-                let user = fs.user.read().await;
-                let can_copy = user.as_ref()
-                    .map(|u| u.permissions.can_copy)
-                    .unwrap_or(false);
-                if !can_copy {
-                    return Err(FsError::Forbidden);
-                }
-            }
-            
             // 获取用户根路径
             let root = fs.get_root_path().await;
             
             // 将请求路径与用户根路径结合
             let from_req = from_clone.as_pathbuf().to_string_lossy().to_string();
             let from_req = fix_and_clean_path(&from_req);
-            let from_path = match join_user_path(&root, &from_req) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            let from_path = fs.join_path(&root, &from_req).await?;
             let to_req = to_clone.as_pathbuf().to_string_lossy().to_string();
             let to_req = fix_and_clean_path(&to_req);
-            let to_path = match join_user_path(&root, &to_req) {
-                Ok(p) => p,
-                Err(_) => return Err(FsError::Forbidden),
-            };
+            let to_path = fs.join_path(&root, &to_req).await?;
+
+            // 检查复制权限（源路径与目标路径）
+            fs.check(Capability::Copy, &from_path).await?;
+            fs.check(Capability::Copy, &to_path).await?;
+            fs.check_preview_only(&from_path, Some(&to_path)).await?;
 
             // 获取挂载点
             let mounts = fs.get_all_mounts().await;
-            let from_mounts = fs.get_matching_mounts(&from_path, &mounts);
+            let from_mounts = get_matching_mounts(&from_path, &mounts);
             
             if from_mounts.is_empty() {
                 return Err(FsError::NotFound);
@@ -1009,16 +982,8 @@ impl DavFileSystem for WebDavFs {
             
             // 计算相对于挂载点的路径
             let mount_path = fix_and_clean_path(&from_mounts[0].mount_path);
-            let actual_from = if from_path.len() > mount_path.len() {
-                fix_and_clean_path(&from_path[mount_path.len()..])
-            } else {
-                "/".to_string()
-            };
-            let actual_to = if to_path.len() > mount_path.len() {
-                fix_and_clean_path(&to_path[mount_path.len()..])
-            } else {
-                "/".to_string()
-            };
+            let actual_from = calculate_internal_path(&mount_path, &from_path);
+            let actual_to = calculate_internal_path(&mount_path, &to_path);
 
             if let Some(driver) = fs.storage_manager.get_driver(&from_mounts[0].id).await {
                 driver.copy_item(&actual_from, &actual_to).await.map_err(|_| FsError::GeneralFailure)
@@ -1027,6 +992,20 @@ impl DavFileSystem for WebDavFs {
             }
         })
     }
+
+    fn get_quota(&self) -> FsFuture<'_, (u64, Option<u64>)> {
+        Box::pin(async move {
+            let req_path = self.request_path.clone().ok_or(FsError::NotImplemented)?;
+            if !path_browsable(&self.browse_paths().await, &req_path) {
+                return Err(FsError::Forbidden);
+            }
+            let root = self.get_root_path().await;
+            let storage_path = self.join_path(&root, &req_path).await?;
+            let space = self.space_info(&storage_path).await.ok_or(FsError::NotImplemented)?;
+            let writable = self.check(Capability::Upload, &storage_path).await.is_ok();
+            Ok(dav_quota(&space, writable, req_path == "/"))
+        })
+    }
 }
 
 /// WebDAV服务器
@@ -1064,18 +1043,22 @@ impl WebDavServer {
 
         let storage_manager = self.storage_manager.clone();
         let prefix = self.config.prefix.clone();
+        let sort = self.config.sort;
+        let paths = PathResolver::new(db.clone());
 
         loop {
             let (stream, remote_addr) = listener.accept().await?;
             let io = TokioIo::new(stream);
             let storage = storage_manager.clone();
             let db_clone = db.clone();
+            let paths_clone = paths.clone();
             let prefix_clone = prefix.clone();
 
             tokio::spawn(async move {
                 let service = service_fn(move |req: Request<hyper::body::Incoming>| {
                     let storage = storage.clone();
                     let db = db_clone.clone();
+                    let paths = paths_clone.clone();
                     let prefix = prefix_clone.clone();
                     
                     async move {
@@ -1127,7 +1110,9 @@ impl WebDavServer {
                         };
 
                         // 创建带用户的文件系统（使用数据库查询挂载点，支持所有驱动）
-                        let fs = WebDavFs::with_user(storage, db, user);
+                        let fs = WebDavFs::with_user(storage, paths, user)
+                            .with_sort(sort)
+                            .with_request_path(req.uri().path(), &prefix);
                         let handler = dav_server::DavHandler::builder()
                             .filesystem(Box::new(fs))
                             .locksystem(dav_server::fakels::FakeLs::new())
@@ -1154,3 +1139,31 @@ impl WebDavServer {
 pub fn create_webdav_server(config: WebDavConfig, storage_manager: StorageManager) -> WebDavServer {
     WebDavServer::new(config, storage_manager)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dav_quota() {
+        let space = SpaceInfo { used: 30, total: 100, free: 70 };
+        assert_eq!(dav_quota(&space, true, true), (30, Some(100)));
+        assert_eq!(dav_quota(&space, true, false), (30, Some(70)));
+        assert_eq!(dav_quota(&space, false, true), (30, Some(30)));
+        assert_eq!(dav_quota(&space, false, false), (30, Some(0)));
+    }
+
+    #[tokio::test]
+    async fn test_upload_spool_limit() {
+        let mut spool = UploadSpool {
+            file: tokio::fs::File::from_std(tempfile::tempfile().unwrap()),
+            len: 0,
+            limit: Some(4),
+            storage_path: "/a.txt".to_string(),
+            user_id: None,
+            db: SqlitePool::connect_lazy("sqlite::memory:").unwrap(),
+        };
+        assert!(spool.write(b"abc").await.is_ok());
+        assert!(matches!(spool.write(b"de").await, Err(FsError::TooLarge)));
+    }
+}
@@ -0,0 +1,220 @@
+//! System service installation and data directory migration / 系统服务安装与数据目录迁移
+//!
+//! `install-service` registers the binary to start with the machine, running from the directory
+//! that holds `config.json`: a systemd unit on Linux, a startup task under the SYSTEM account on
+//! Windows (the binary doesn't talk to the service control manager, so a plain `sc create` service
+//! would be stopped after the start timeout). `migrate-data` copies an existing data directory into
+//! a new one so upgrades and relocations don't need manual file copying.
+//! `install-service` 将程序注册为开机启动，工作目录为 `config.json` 所在目录：Linux 上生成 systemd
+//! 单元，Windows 上创建以 SYSTEM 账户运行的开机任务（程序不与服务控制管理器通信，直接用 `sc create`
+//! 注册的服务会在启动超时后被停止）。`migrate-data` 将已有数据目录复制到新目录，升级或迁移时无需手动复制文件。
+
+use std::path::{Component, Path, PathBuf};
+use anyhow::{bail, Context, Result};
+
+/// Default service name / 默认服务名
+pub const DEFAULT_SERVICE_NAME: &str = "yaolist";
+
+/// How the service is started / 服务的启动方式
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    pub name: String,
+    /// Binary to run / 要运行的程序
+    pub exe: PathBuf,
+    /// Directory holding `config.json` / `config.json` 所在目录
+    pub working_dir: PathBuf,
+    /// Account to run as (Linux), root when `None` / 运行账户（Linux），为 `None` 时为 root
+    pub user: Option<String>,
+}
+
+impl ServiceSpec {
+    /// The running binary started from the current directory / 以当前目录运行当前程序
+    pub fn current(name: &str, user: Option<String>) -> Result<Self> {
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+            bail!("Invalid service name {:?}", name);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            exe: std::env::current_exe().context("Cannot locate the running binary")?,
+            working_dir: std::env::current_dir().context("Cannot read the current directory")?,
+            user: user.map(|u| u.trim().to_string()).filter(|u| !u.is_empty()),
+        })
+    }
+
+    /// systemd unit file / systemd 单元文件
+    pub fn systemd_unit(&self) -> String {
+        let mut unit = String::from("[Unit]\nDescription=YaoList File Manager\nAfter=network-online.target\nWants=network-online.target\n\n[Service]\nType=simple\n");
+        if let Some(user) = &self.user {
+            unit.push_str(&format!("User={}\n", user));
+        }
+        unit.push_str(&format!("WorkingDirectory={}\n", self.working_dir.display()));
+        unit.push_str(&format!("ExecStart=\"{}\"\n", self.exe.display()));
+        unit.push_str("Restart=on-failure\nRestartSec=5\n\n[Install]\nWantedBy=multi-user.target\n");
+        unit
+    }
+
+    /// Path of the systemd unit / systemd 单元文件路径
+    pub fn systemd_unit_path(&self) -> PathBuf {
+        PathBuf::from("/etc/systemd/system").join(format!("{}.service", self.name))
+    }
+
+    /// `schtasks` arguments creating the startup task / 创建开机任务的 `schtasks` 参数
+    pub fn schtasks_args(&self) -> Vec<String> {
+        // 任务没有工作目录设置，通过 cmd 切换到 config.json 所在目录
+        let command = format!("cmd /c cd /d \"{}\" && \"{}\"", self.working_dir.display(), self.exe.display());
+        ["/Create", "/TN", &self.name, "/TR", &command, "/SC", "ONSTART", "/RU", "SYSTEM", "/RL", "HIGHEST", "/F"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    /// Register and enable the service, returns a summary / 注册并启用服务，返回说明
+    #[cfg(target_os = "linux")]
+    pub fn install(&self) -> Result<String> {
+        let path = self.systemd_unit_path();
+        std::fs::write(&path, self.systemd_unit())
+            .with_context(|| format!("Failed to write {} (run as root)", path.display()))?;
+        run_tool("systemctl", &["daemon-reload".to_string()])?;
+        run_tool("systemctl", &["enable".to_string(), self.name.clone()])?;
+        Ok(format!("Installed {}; start it with: systemctl start {}", path.display(), self.name))
+    }
+
+    /// Register and enable the service, returns a summary / 注册并启用服务，返回说明
+    #[cfg(windows)]
+    pub fn install(&self) -> Result<String> {
+        run_tool("schtasks", &self.schtasks_args())?;
+        Ok(format!("Registered startup task {}; start it now with: schtasks /Run /TN {}", self.name, self.name))
+    }
+
+    /// Register and enable the service, returns a summary / 注册并启用服务，返回说明
+    #[cfg(not(any(target_os = "linux", windows)))]
+    pub fn install(&self) -> Result<String> {
+        bail!("Service installation is supported on Linux (systemd) and Windows")
+    }
+}
+
+/// Run a system tool and fail with its output / 执行系统工具，失败时返回其输出
+#[cfg(any(target_os = "linux", windows))]
+fn run_tool(program: &str, args: &[String]) -> Result<()> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!("{} {} failed: {}", program, args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Files and bytes copied by a migration / 迁移复制的文件数与字节数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Absolute path with `.` and `..` resolved lexically / 词法解析 `.` 与 `..` 后的绝对路径
+fn absolute(path: &Path) -> Result<PathBuf> {
+    let path = if path.is_absolute() { path.to_path_buf() } else { std::env::current_dir()?.join(path) };
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    Ok(out)
+}
+
+/// Check `from` is a data directory that can be copied to `to`; an existing database in `to` is
+/// only overwritten with `force`
+/// 检查 `from` 是可复制到 `to` 的数据目录；`to` 中已有数据库时仅在 `force` 时覆盖
+pub fn check_migration(from: &Path, to: &Path, db_file: &str, force: bool) -> Result<()> {
+    if !from.join(db_file).is_file() {
+        bail!("{} is not a data directory: {} not found", from.display(), db_file);
+    }
+    let (abs_from, abs_to) = (absolute(from)?, absolute(to)?);
+    if abs_from == abs_to {
+        bail!("Source and target are the same directory");
+    }
+    if abs_to.starts_with(&abs_from) || abs_from.starts_with(&abs_to) {
+        bail!("Source and target must not contain each other");
+    }
+    if to.join(db_file).exists() && !force {
+        bail!("{} already has a database, pass --force to overwrite it", to.display());
+    }
+    Ok(())
+}
+
+/// Copy a directory tree, database files (with their `-wal`/`-shm` companions) included
+/// 复制整个目录树，包括数据库文件及其 `-wal`/`-shm` 文件
+pub fn copy_dir(from: &Path, to: &Path) -> Result<MigrationReport> {
+    let mut report = MigrationReport::default();
+    let mut stack = vec![(from.to_path_buf(), to.to_path_buf())];
+    while let Some((src, dst)) = stack.pop() {
+        std::fs::create_dir_all(&dst).with_context(|| format!("Failed to create {}", dst.display()))?;
+        for entry in std::fs::read_dir(&src).with_context(|| format!("Failed to read {}", src.display()))? {
+            let entry = entry?;
+            let target = dst.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                stack.push((entry.path(), target));
+            } else {
+                report.bytes += std::fs::copy(entry.path(), &target)
+                    .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+                report.files += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            name: "yaolist".to_string(),
+            exe: PathBuf::from("/opt/yao list/yaolist"),
+            working_dir: PathBuf::from("/opt/yao list"),
+            user: Some("yaolist".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_service_files() {
+        let unit = spec().systemd_unit();
+        assert!(unit.contains("User=yaolist\n"));
+        assert!(unit.contains("WorkingDirectory=/opt/yao list\n"));
+        assert!(unit.contains("ExecStart=\"/opt/yao list/yaolist\"\n"));
+        assert!(!ServiceSpec { user: None, ..spec() }.systemd_unit().contains("User="));
+        assert_eq!(spec().systemd_unit_path(), PathBuf::from("/etc/systemd/system/yaolist.service"));
+
+        let args = spec().schtasks_args();
+        assert_eq!(args[2], "yaolist");
+        assert_eq!(args[4], "cmd /c cd /d \"/opt/yao list\" && \"/opt/yao list/yaolist\"");
+    }
+
+    #[test]
+    fn test_migrate() {
+        let root = tempfile::tempdir().unwrap();
+        let old = root.path().join("old");
+        std::fs::create_dir_all(old.join("search")).unwrap();
+        std::fs::write(old.join("yaolist.db"), b"db").unwrap();
+        std::fs::write(old.join("search").join("search.db"), b"index").unwrap();
+
+        let new = root.path().join("new");
+        assert!(check_migration(&new, &old, "yaolist.db", false).is_err());
+        assert!(check_migration(&old, &old.join("sub"), "yaolist.db", false).is_err());
+        check_migration(&old, &new, "yaolist.db", false).unwrap();
+
+        assert_eq!(copy_dir(&old, &new).unwrap(), MigrationReport { files: 2, bytes: 7 });
+        assert_eq!(std::fs::read(new.join("search").join("search.db")).unwrap(), b"index");
+        assert!(check_migration(&old, &new, "yaolist.db", false).is_err());
+        assert!(check_migration(&old, &new, "yaolist.db", true).is_ok());
+    }
+}
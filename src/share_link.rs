@@ -0,0 +1,112 @@
+//! Share links / 分享链接
+//!
+//! Custom short IDs (vanity slugs) chosen when creating a share, QR codes of a share's public
+//! URL for the share management UI, and the paths visitors browse inside a folder share.
+//! 创建分享时自定义的短链接（个性化短 ID）、供分享管理界面展示的分享公开地址二维码，以及访客在目录分享中浏览的路径。
+
+use serde::Serialize;
+
+use crate::utils::fix_and_clean_path;
+
+/// Min custom short ID length / 自定义短 ID 最小长度
+pub const MIN_SLUG_LEN: usize = 3;
+
+/// Max custom short ID length / 自定义短 ID 最大长度
+pub const MAX_SLUG_LEN: usize = 64;
+
+/// Check a custom short ID: letters, digits, `-` and `_`, not starting or ending with a separator
+/// 检查自定义短 ID：字母、数字、`-` 与 `_`，不能以分隔符开头或结尾
+pub fn validate_slug(slug: &str) -> Result<(), String> {
+    let len = slug.chars().count();
+    if !(MIN_SLUG_LEN..=MAX_SLUG_LEN).contains(&len) {
+        return Err(format!("short ID must be {} to {} characters", MIN_SLUG_LEN, MAX_SLUG_LEN));
+    }
+    if !slug.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("short ID may only contain letters, digits, '-' and '_'".to_string());
+    }
+    if slug.starts_with(['-', '_']) || slug.ends_with(['-', '_']) {
+        return Err("short ID must not start or end with '-' or '_'".to_string());
+    }
+    Ok(())
+}
+
+/// Public URL of a share / 分享的公开地址
+pub fn share_url(base: &str, short_id: &str) -> String {
+    format!("{}/share/{}", base.trim_end_matches('/'), short_id)
+}
+
+/// QR code of `text` as PNG / 将 `text` 生成为 PNG 二维码
+pub fn qr_png(text: &str) -> Result<Vec<u8>, String> {
+    qrcodegen_image::draw_png(text)
+}
+
+/// Path inside a folder share, relative to the shared folder: `""` for the folder itself,
+/// otherwise `/a/b`. `.` and `..` are resolved against the shared folder, so the result never
+/// leaves it.
+/// 目录分享内相对于分享目录的路径：分享目录本身为 `""`，否则为 `/a/b`。`.` 与 `..` 相对分享目录解析，结果不会越出分享目录。
+pub fn normalize_sub_path(sub_path: &str) -> String {
+    match fix_and_clean_path(sub_path).as_str() {
+        "/" => String::new(),
+        p => p.to_string(),
+    }
+}
+
+/// One level of the breadcrumb shown while browsing a share / 浏览分享时面包屑中的一级
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Breadcrumb {
+    pub name: String,
+    /// Value to send back as `sub_path` / 作为 `sub_path` 回传的值
+    pub path: String,
+}
+
+/// Breadcrumb from the shared folder (named `root_name`) down to a normalized `sub_path`
+/// 从分享目录（名为 `root_name`）到规范化后的 `sub_path` 的面包屑
+pub fn breadcrumbs(root_name: &str, sub_path: &str) -> Vec<Breadcrumb> {
+    let mut crumbs = vec![Breadcrumb { name: root_name.to_string(), path: String::new() }];
+    let mut path = String::new();
+    for segment in sub_path.split('/').filter(|s| !s.is_empty()) {
+        path = format!("{}/{}", path, segment);
+        crumbs.push(Breadcrumb { name: segment.to_string(), path: path.clone() });
+    }
+    crumbs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_slug() {
+        assert!(validate_slug("team-docs_2024").is_ok());
+        assert!(validate_slug("abc").is_ok());
+        assert!(validate_slug("ab").is_err());
+        assert!(validate_slug(&"a".repeat(MAX_SLUG_LEN + 1)).is_err());
+        assert!(validate_slug("a/b").is_err());
+        assert!(validate_slug("文档分享").is_err());
+        assert!(validate_slug("-docs").is_err());
+        assert!(validate_slug("docs_").is_err());
+    }
+
+    #[test]
+    fn test_share_url_and_qr() {
+        assert_eq!(share_url("https://pan.example.com/", "docs"), "https://pan.example.com/share/docs");
+        let png = qr_png(&share_url("https://pan.example.com", "docs")).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn test_sub_path_and_breadcrumbs() {
+        assert_eq!(normalize_sub_path(""), "");
+        assert_eq!(normalize_sub_path("/"), "");
+        assert_eq!(normalize_sub_path("a//b/"), "/a/b");
+        assert_eq!(normalize_sub_path("a/./c/../b"), "/a/b");
+        assert_eq!(normalize_sub_path("../../etc"), "/etc");
+        assert_eq!(normalize_sub_path("a\\b"), "/a/b");
+
+        let crumbs = breadcrumbs("Docs", "/a/b");
+        assert_eq!(crumbs.len(), 3);
+        assert_eq!(crumbs[0], Breadcrumb { name: "Docs".to_string(), path: String::new() });
+        assert_eq!(crumbs[2], Breadcrumb { name: "b".to_string(), path: "/a/b".to_string() });
+        assert_eq!(breadcrumbs("Docs", "").len(), 1);
+    }
+}
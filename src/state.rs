@@ -1,15 +1,37 @@
 use sqlx::SqlitePool;
 use yaolist_backend::storage::StorageManager;
+use yaolist_backend::path_resolver::PathResolver;
 use yaolist_backend::search::DbIndex;
 use yaolist_backend::load_balance::LoadBalanceManager;
 use yaolist_backend::server::WebDavConfig;
-use yaolist_backend::download::DownloadSettings;
+use yaolist_backend::download::{DownloadSettings, DownloadTransfers};
+use yaolist_backend::transfer::TransferSettings;
+use yaolist_backend::http_security::HttpSecurity;
+use yaolist_backend::compression::ResponseCompression;
+use yaolist_backend::lockout::LoginLockout;
+use yaolist_backend::captcha::CaptchaSettings;
+use yaolist_backend::guest::GuestSettings;
+use yaolist_backend::file_type::FileTypeSettings;
+use yaolist_backend::workspace::WorkspaceRegistry;
+use yaolist_backend::webhook::WebhookManager;
+use yaolist_backend::email_template::EmailTemplates;
+use yaolist_backend::scheduler::Scheduler;
+use yaolist_backend::file_hook::FileHookManager;
+use yaolist_backend::strm::StrmManager;
+use yaolist_backend::tiering::TieringManager;
+use yaolist_backend::dedupe::DedupeManager;
+use yaolist_backend::announcement::AnnouncementManager;
+use yaolist_backend::archive_password::ArchivePasswordManager;
+use yaolist_backend::feed::FeedManager;
+use yaolist_backend::branding::BrandingStore;
+use yaolist_backend::gallery::ThumbnailStore;
+use yaolist_backend::cluster::{Cluster, SharedStore};
 use crate::task::TaskManager;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
 use parking_lot::RwLock;
-use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 /// Index building progress / 索引构建进度
 #[derive(Debug, Clone)]
@@ -105,172 +127,134 @@ impl Default for IndexState {
     }
 }
 
-/// Login failure records / 登录失败记录
-#[derive(Debug, Clone)]
-pub struct LoginAttempt {
-    pub fail_count: u32,
-    pub last_attempt: DateTime<Utc>,
-}
-
-/// Captcha records / 验证码记录
-#[derive(Debug, Clone)]
-pub struct CaptchaRecord {
-    pub code: String,
-    pub created_at: DateTime<Utc>,
-}
+/// Captcha lifetime / 验证码有效期
+const CAPTCHA_TTL_SECS: u64 = 5 * 60;
+/// Reset code lifetime / 重置码有效期
+const RESET_CODE_TTL_SECS: u64 = 10 * 60;
 
 /// Reset code records / 重置码记录
-#[derive(Debug, Clone)]
-pub struct ResetCodeRecord {
-    pub user_id: String,
-    pub code: String,
-    pub created_at: DateTime<Utc>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ResetCodeRecord {
+    user_id: String,
+    code: String,
 }
 
-/// Login security state / 登录安全状态
+/// Captcha and reset code state (failure counters live in `LoginLockout`) / 验证码与重置码状态（失败计数见 `LoginLockout`）
+///
+/// Kept in the cluster store, so a code issued by one replica can be checked by another.
+/// 保存在集群共享存储中，一个实例签发的验证码可由另一个实例校验。
 pub struct LoginSecurity {
-    /// IP login failure records: IP -> LoginAttempt / IP登录失败记录
-    pub ip_attempts: RwLock<HashMap<String, LoginAttempt>>,
-    /// User login failure records: username -> fail_count / 用户登录失败记录
-    pub user_attempts: RwLock<HashMap<String, u32>>,
-    /// Captcha storage: captcha_id -> CaptchaRecord / 验证码存储
-    pub captchas: RwLock<HashMap<String, CaptchaRecord>>,
-    /// Reset code storage: target -> ResetCodeRecord / 重置码存储
-    pub reset_codes: RwLock<HashMap<String, ResetCodeRecord>>,
+    store: Arc<dyn SharedStore>,
 }
 
 impl LoginSecurity {
-    pub fn new() -> Self {
-        Self {
-            ip_attempts: RwLock::new(HashMap::new()),
-            user_attempts: RwLock::new(HashMap::new()),
-            captchas: RwLock::new(HashMap::new()),
-            reset_codes: RwLock::new(HashMap::new()),
-        }
-    }
-
-    /// Check if IP is blocked (more than 5 failures within 30 minutes) / 检查IP是否被封禁
-    pub fn is_ip_blocked(&self, ip: &str) -> bool {
-        let attempts = self.ip_attempts.read();
-        if let Some(attempt) = attempts.get(ip) {
-            if attempt.fail_count >= 5 {
-                let elapsed = Utc::now().signed_duration_since(attempt.last_attempt);
-                return elapsed.num_minutes() < 30;
-            }
-        }
-        false
-    }
-
-    /// Check if user needs captcha (more than 1 failure) / 检查用户是否需要验证码
-    pub fn needs_captcha(&self, username: &str) -> bool {
-        let attempts = self.user_attempts.read();
-        attempts.get(username).map(|&c| c >= 1).unwrap_or(false)
-    }
-
-    /// Check if IP needs captcha (has failure record within 30 minutes) / 检查IP是否需要验证码
-    pub fn needs_captcha_by_ip(&self, ip: &str) -> bool {
-        let attempts = self.ip_attempts.read();
-        if let Some(attempt) = attempts.get(ip) {
-            if attempt.fail_count >= 1 {
-                let elapsed = Utc::now().signed_duration_since(attempt.last_attempt);
-                return elapsed.num_minutes() < 30;
-            }
-        }
-        false
-    }
-
-    /// Record login failure / 记录登录失败
-    pub fn record_failure(&self, ip: &str, username: &str) {
-        let now = Utc::now();
-        
-        // Update IP failure records / 更新IP失败记录
-        {
-            let mut attempts = self.ip_attempts.write();
-            let entry = attempts.entry(ip.to_string()).or_insert(LoginAttempt {
-                fail_count: 0,
-                last_attempt: now,
-            });
-            // If over 30 minutes, reset count / 如果超过30分钟
-            if now.signed_duration_since(entry.last_attempt).num_minutes() >= 30 {
-                entry.fail_count = 0;
-            }
-            entry.fail_count += 1;
-            entry.last_attempt = now;
-        }
-        
-        // Update user failure records / 更新用户失败记录
-        {
-            let mut attempts = self.user_attempts.write();
-            *attempts.entry(username.to_string()).or_insert(0) += 1;
-        }
-    }
-
-    /// Login successful, clear failure records / 登录成功
-    pub fn clear_failure(&self, ip: &str, username: &str) {
-        self.ip_attempts.write().remove(ip);
-        self.user_attempts.write().remove(username);
+    pub fn new(store: Arc<dyn SharedStore>) -> Self {
+        Self { store }
     }
 
     /// Store captcha / 存储验证码
-    pub fn store_captcha(&self, id: String, code: String) {
-        let mut captchas = self.captchas.write();
-        // Clean expired captchas (5 minutes) / 清理过期验证码
-        let now = Utc::now();
-        captchas.retain(|_, v| now.signed_duration_since(v.created_at).num_minutes() < 5);
-        captchas.insert(id, CaptchaRecord { code, created_at: now });
+    pub async fn store_captcha(&self, id: String, code: String) {
+        let key = format!("captcha:{}", id);
+        if let Err(e) = self.store.set(&key, &code, Duration::from_secs(CAPTCHA_TTL_SECS)).await {
+            tracing::warn!("Failed to store captcha: {}", e);
+        }
     }
 
     /// Verify and consume captcha / 验证并消费验证码
-    pub fn verify_captcha(&self, id: &str, code: &str) -> bool {
-        let mut captchas = self.captchas.write();
-        if let Some(record) = captchas.remove(id) {
-            let elapsed = Utc::now().signed_duration_since(record.created_at);
-            if elapsed.num_minutes() < 5 {
-                return record.code.eq_ignore_ascii_case(code);
+    pub async fn verify_captcha(&self, id: &str, code: &str) -> bool {
+        match self.store.take(&format!("captcha:{}", id)).await {
+            Ok(Some(stored)) => stored.eq_ignore_ascii_case(code),
+            Ok(None) => false,
+            Err(e) => {
+                tracing::warn!("Failed to read captcha: {}", e);
+                false
             }
         }
-        false
     }
 
     /// Store reset code / 存储重置码
-    pub fn store_reset_code(&self, target: String, user_id: String, code: String) {
-        let mut reset_codes = self.reset_codes.write();
-        let now = Utc::now();
-        // Clean expired reset codes (10 minutes) / 清理过期重置码
-        reset_codes.retain(|_, v| now.signed_duration_since(v.created_at).num_minutes() < 10);
-        reset_codes.insert(target, ResetCodeRecord { user_id, code, created_at: now });
+    pub async fn store_reset_code(&self, target: String, user_id: String, code: String) {
+        let key = format!("reset_code:{}", target);
+        let record = serde_json::to_string(&ResetCodeRecord { user_id, code }).unwrap_or_default();
+        if let Err(e) = self.store.set(&key, &record, Duration::from_secs(RESET_CODE_TTL_SECS)).await {
+            tracing::warn!("Failed to store reset code: {}", e);
+        }
     }
 
     /// Verify and consume reset code, return user_id / 验证并消费重置码
-    pub fn verify_reset_code(&self, target: &str, code: &str) -> Option<String> {
-        let mut reset_codes = self.reset_codes.write();
-        if let Some(record) = reset_codes.remove(target) {
-            let elapsed = Utc::now().signed_duration_since(record.created_at);
-            if elapsed.num_minutes() < 10 && record.code == code {
-                return Some(record.user_id);
+    pub async fn verify_reset_code(&self, target: &str, code: &str) -> Option<String> {
+        let stored = match self.store.take(&format!("reset_code:{}", target)).await {
+            Ok(stored) => stored?,
+            Err(e) => {
+                tracing::warn!("Failed to read reset code: {}", e);
+                return None;
             }
-        }
-        None
-    }
-}
-
-impl Default for LoginSecurity {
-    fn default() -> Self {
-        Self::new()
+        };
+        serde_json::from_str::<ResetCodeRecord>(&stored).ok()
+            .filter(|record| record.code == code)
+            .map(|record| record.user_id)
     }
 }
 
 pub struct AppState {
     pub db: SqlitePool,
     pub storage_manager: StorageManager,
+    /// Mount matching and meta lookups shared by all entry points / 各入口共用的挂载点匹配与元信息查询
+    pub paths: PathResolver,
     pub task_manager: TaskManager,
     pub db_index: tokio::sync::RwLock<Option<Arc<DbIndex>>>,
     pub index_state: Arc<IndexState>,
     pub load_balance: Arc<LoadBalanceManager>,
     pub webdav_config: tokio::sync::RwLock<WebDavConfig>,
     pub login_security: LoginSecurity,
+    /// Login lockout policy and failure counters / 登录锁定策略与失败计数
+    pub login_lockout: Arc<LoginLockout>,
+    /// Captcha provider settings / 验证码提供方设置
+    pub captcha: Arc<CaptchaSettings>,
+    /// Guest access policy / 游客访问策略
+    pub guest: Arc<GuestSettings>,
+    /// File type categories by extension / 按扩展名的文件类型分类
+    pub file_types: Arc<FileTypeSettings>,
     /// Download settings (domain validation, proxy limits) / 下载设置(域名验证、代理限制)
     pub download_settings: Arc<DownloadSettings>,
+    /// Per-token download transfer stats / 按下载令牌的传输统计
+    pub download_transfers: Arc<DownloadTransfers>,
+    /// Transfer settings (copy buffer size) / 传输设置(复制缓冲区大小)
+    pub transfer_settings: Arc<TransferSettings>,
+    /// CORS and security headers / 跨域与安全响应头
+    pub http_security: Arc<HttpSecurity>,
+    /// Gzip / Brotli response compression / 响应压缩
+    pub compression: Arc<ResponseCompression>,
+    /// Webhook notifications / Webhook 通知
+    pub webhooks: Arc<WebhookManager>,
+    /// Email templates and per-event settings / 邮件模板与事件设置
+    pub email_templates: Arc<EmailTemplates>,
+    /// Scheduled jobs / 定时任务
+    pub scheduler: Arc<Scheduler>,
+    /// File event hooks (commands / HTTP calls) / 文件事件钩子
+    pub file_hooks: Arc<FileHookManager>,
+    /// STRM exports for media servers / 媒体服务器 STRM 导出
+    pub strm: Arc<StrmManager>,
+    /// Archival tiering between mounts / 挂载间的归档分层
+    pub tiering: Arc<TieringManager>,
+    /// Duplicate file report / 重复文件报告
+    pub dedupe: Arc<DedupeManager>,
+    /// Workspaces (multi-tenancy) / 工作区（多租户）
+    pub workspaces: Arc<WorkspaceRegistry>,
+    /// Site announcements / 站点公告
+    pub announcements: Arc<AnnouncementManager>,
+    /// Stored passwords for encrypted archives / 加密压缩包的已保存密码
+    pub archive_passwords: Arc<ArchivePasswordManager>,
+    /// Public feeds of recent files in a folder / 目录最近文件的公开订阅源
+    pub feeds: Arc<FeedManager>,
+    /// Logo, favicon, site name and custom CSS / JS on disk / 磁盘上的 Logo、网站图标、站点名称与自定义 CSS / JS
+    pub branding: Arc<BrandingStore>,
+    /// Gallery thumbnail cache and URL signing / 相册缩略图缓存与链接签名
+    pub thumbnails: Arc<ThumbnailStore>,
+    /// Saved drivers still loading after startup / 启动后仍在加载的驱动数
+    pub drivers_loading: Arc<AtomicUsize>,
+    /// State shared with other replicas / 与其他实例共享的状态
+    pub cluster: Arc<Cluster>,
 }
 
 impl AppState {
@@ -0,0 +1,129 @@
+//! Static site export / 静态站点导出
+//!
+//! Renders a directory tree as plain `index.html` pages, one per folder, whose file links are
+//! permanent direct links. The pages are written onto a mount, typically object storage behind a
+//! CDN, so a mirror can be browsed and downloaded without YaoList serving the listings.
+//! Folders link to `<name>/index.html` explicitly because plain buckets don't resolve index files.
+//! 将目录树渲染为纯 `index.html` 页面（每个目录一页），文件链接为永久直链。页面写入某个挂载（通常是
+//! CDN 后的对象存储），镜像站无需 YaoList 提供列表即可浏览与下载。目录链接显式指向 `<名称>/index.html`，
+//! 因为普通存储桶不会自动解析索引文件。
+
+use crate::download_list::escape_xml;
+
+/// File name of generated pages / 生成页面的文件名
+pub const INDEX_FILE: &str = "index.html";
+
+/// A file listed on a page / 页面中列出的文件
+#[derive(Debug, Clone)]
+pub struct SiteFile {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<String>,
+    /// Direct link / 直链
+    pub url: String,
+}
+
+/// One folder of the exported tree / 导出目录树中的一个目录
+#[derive(Debug, Clone, Default)]
+pub struct SiteDir {
+    /// Path relative to the export root, empty for the root / 相对于导出根目录的路径，根目录为空
+    pub rel: String,
+    pub dirs: Vec<String>,
+    pub files: Vec<SiteFile>,
+}
+
+impl SiteDir {
+    pub fn new(rel: &str) -> Self {
+        Self { rel: rel.to_string(), ..Default::default() }
+    }
+
+    /// Path of the page relative to the target folder / 页面相对于目标目录的路径
+    pub fn page_path(&self) -> String {
+        if self.rel.is_empty() {
+            INDEX_FILE.to_string()
+        } else {
+            format!("{}/{}", self.rel, INDEX_FILE)
+        }
+    }
+}
+
+/// Human readable size / 可读的文件大小
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Render the page of a folder; `site_title` heads every page / 渲染目录页面，`site_title` 显示在每页顶部
+pub fn render_index(site_title: &str, dir: &SiteDir) -> String {
+    let heading = if dir.rel.is_empty() { "/".to_string() } else { format!("/{}/", dir.rel) };
+    let mut dirs = dir.dirs.clone();
+    dirs.sort_by(|a, b| natord::compare_ignore_case(a, b));
+    let mut files: Vec<&SiteFile> = dir.files.iter().collect();
+    files.sort_by(|a, b| natord::compare_ignore_case(&a.name, &b.name));
+
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    out.push_str(&format!("<title>{} - {}</title>\n", escape_xml(&heading), escape_xml(site_title)));
+    out.push_str("<style>body{font-family:sans-serif;max-width:960px;margin:2em auto;padding:0 1em}table{width:100%;border-collapse:collapse}td{padding:.3em .5em;border-bottom:1px solid #eee}td.n{text-align:right;white-space:nowrap}a{text-decoration:none}</style>\n</head>\n<body>\n");
+    out.push_str(&format!("<h1>{}</h1>\n<h2>{}</h2>\n<table>\n", escape_xml(site_title), escape_xml(&heading)));
+    if !dir.rel.is_empty() {
+        out.push_str(&format!("<tr><td><a href=\"../{}\">../</a></td><td></td><td></td></tr>\n", INDEX_FILE));
+    }
+    for name in &dirs {
+        out.push_str(&format!(
+            "<tr><td><a href=\"{}/{}\">{}/</a></td><td></td><td></td></tr>\n",
+            escape_xml(&urlencoding::encode(name)), INDEX_FILE, escape_xml(name),
+        ));
+    }
+    for file in files {
+        out.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td class=\"n\">{}</td><td class=\"n\">{}</td></tr>\n",
+            escape_xml(&file.url), escape_xml(&file.name), format_size(file.size),
+            escape_xml(file.modified.as_deref().unwrap_or("")),
+        ));
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+    }
+
+    #[test]
+    fn test_render_index() {
+        let mut dir = SiteDir::new("a b");
+        assert_eq!(dir.page_path(), "a b/index.html");
+        assert_eq!(SiteDir::new("").page_path(), "index.html");
+
+        dir.dirs = vec!["v10".to_string(), "v2 <rc>".to_string()];
+        dir.files.push(SiteFile {
+            name: "x&y.zip".to_string(),
+            size: 2048,
+            modified: None,
+            url: "https://cdn/dlink/s/x%26y.zip".to_string(),
+        });
+        let html = render_index("Mirror", &dir);
+        assert!(html.contains("<a href=\"../index.html\">"));
+        assert!(html.contains("<a href=\"v2%20%3Crc%3E/index.html\">v2 &lt;rc&gt;/</a>"));
+        assert!(html.find("v2%20").unwrap() < html.find("v10/").unwrap());
+        assert!(html.contains("<a href=\"https://cdn/dlink/s/x%26y.zip\">x&amp;y.zip</a>"));
+        assert!(html.contains("2.0 KB"));
+        assert!(!render_index("Mirror", &SiteDir::new("")).contains("../"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Aggregate mounts / 聚合挂载
+//!
+//! Several enabled mounts may share one `mount_path`. Listings are merged, reads go to a mount
+//! that has the file, and new files or folders are placed by the write policy of the group, so
+//! several small accounts can be used as one large folder. The policy is read from the first
+//! mount of the group (by order).
+//! 多个启用的挂载点可以使用同一个挂载路径：列表合并，读取时使用包含该文件的挂载点，新建文件/目录按组的写入策略放置，
+//! 从而把多个小容量账号合并为一个大目录。写入策略取自组内第一个挂载点（按排序）。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::SpaceInfo;
+
+/// Driver config key of the write policy / 写入策略的配置键
+pub const WRITE_POLICY_KEY: &str = "write_policy";
+
+/// Where new files go when several mounts share a path / 多个挂载点共用路径时新文件的写入位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WritePolicy {
+    /// First mount (by order) that can take the file / 第一个能容纳该文件的挂载点（按排序）
+    #[default]
+    First,
+    /// Mount with the most free space / 剩余空间最多的挂载点
+    MostFreeSpace,
+}
+
+impl WritePolicy {
+    pub fn from_config(config: &Value) -> Self {
+        config.get(WRITE_POLICY_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// One mount a new file could be written to / 可写入新文件的候选挂载点
+#[derive(Debug, Clone, Default)]
+pub struct WriteCandidate {
+    /// Largest file the driver accepts / 驱动允许的最大文件大小
+    pub max_file_size: Option<u64>,
+    /// None when the driver cannot report space / 驱动不支持时为 None
+    pub space: Option<SpaceInfo>,
+}
+
+impl WriteCandidate {
+    /// Whether a file of `size` fits; unknown sizes and space always fit
+    /// 能否容纳 `size` 大小的文件；大小或空间未知时视为可以
+    fn fits(&self, size: Option<u64>) -> bool {
+        let Some(size) = size else { return true };
+        self.max_file_size.is_none_or(|max| size <= max)
+            && self.space.as_ref().is_none_or(|s| size <= s.free)
+    }
+}
+
+/// Pick the index of the candidate to write to; candidates are in mount order
+/// 选择写入的候选挂载点下标，候选按挂载点排序
+pub fn choose_write_target(policy: WritePolicy, candidates: &[WriteCandidate], size: Option<u64>) -> Option<usize> {
+    let mut fitting = candidates.iter().enumerate().filter(|(_, c)| c.fits(size));
+    let chosen = match policy {
+        WritePolicy::First => fitting.next().map(|(i, _)| i),
+        // 不支持查询空间的挂载点排在最后，同等空间时取靠前的
+        WritePolicy::MostFreeSpace => fitting
+            .min_by_key(|(i, c)| (std::cmp::Reverse(c.space.as_ref().map(|s| s.free)), *i))
+            .map(|(i, _)| i),
+    };
+    // 都放不下时仍写入第一个，由驱动返回具体错误
+    chosen.or_else(|| (!candidates.is_empty()).then_some(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn candidate(free: Option<u64>) -> WriteCandidate {
+        WriteCandidate {
+            max_file_size: None,
+            space: free.map(|free| SpaceInfo { used: 0, total: free, free }),
+        }
+    }
+
+    #[test]
+    fn test_choose_write_target() {
+        assert_eq!(WritePolicy::from_config(&json!({"write_policy": "most_free_space"})), WritePolicy::MostFreeSpace);
+        assert_eq!(WritePolicy::from_config(&json!({"write_policy": "bogus"})), WritePolicy::First);
+
+        let candidates = [candidate(Some(10)), candidate(None), candidate(Some(50)), candidate(Some(50))];
+        assert_eq!(choose_write_target(WritePolicy::First, &candidates, None), Some(0));
+        assert_eq!(choose_write_target(WritePolicy::First, &candidates, Some(20)), Some(1));
+        assert_eq!(choose_write_target(WritePolicy::MostFreeSpace, &candidates, None), Some(2));
+        assert_eq!(choose_write_target(WritePolicy::MostFreeSpace, &candidates[..2], None), Some(0));
+        assert_eq!(choose_write_target(WritePolicy::MostFreeSpace, &[candidate(None)], Some(1)), Some(0));
+
+        let full = [candidate(Some(1)), WriteCandidate { max_file_size: Some(5), space: None }];
+        assert_eq!(choose_write_target(WritePolicy::First, &full, Some(10)), Some(0));
+        assert_eq!(choose_write_target(WritePolicy::First, &[], None), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! Driver capability introspection / 驱动能力查询
+//!
+//! Combines what a driver declares (`Capability`, the `DriverConfig` flags of its type) with the
+//! common options of one mount, so the frontend can hide actions a mount doesn't support (upload
+//! on share links, seeking on drivers without range reads) instead of failing when they are used.
+//! 合并驱动声明的能力（`Capability`、驱动类型的 `DriverConfig` 标志）与挂载的通用选项，
+//! 前端据此隐藏挂载不支持的操作（分享链接上传、不支持范围读取时的拖动），而不是在使用时才报错。
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{read_only, Capability, ConfigItem, DriverConfig};
+
+/// Common items stored on the mount record instead of the driver config / 保存在挂载记录而非驱动配置中的通用项
+const MOUNT_RECORD_ITEMS: &[&str] = &["mount_path", "order", "remark"];
+
+/// Actions the frontend may offer on a mount / 前端可在挂载上提供的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MountActions {
+    /// Upload new files / 上传新文件
+    pub upload: bool,
+    /// Create, rename, move and delete / 新建、重命名、移动与删除
+    pub modify: bool,
+    /// Seek within files (video scrubbing, resumed downloads) / 在文件内定位（视频拖动、断点续传）
+    pub range_read: bool,
+    /// Hand out direct links instead of proxying / 下发直链而不是代理
+    pub direct_link: bool,
+}
+
+/// Capabilities of one mount / 单个挂载的能力
+#[derive(Debug, Clone, Serialize)]
+pub struct DriverCapabilities {
+    pub driver_type: String,
+    /// Declared by the driver instance / 驱动实例声明的能力
+    pub capability: Capability,
+    /// Flags of the driver type / 驱动类型的标志
+    pub config: DriverConfig,
+    /// Common options of the mount with defaults filled in / 填入默认值后的挂载通用选项
+    pub options: Map<String, Value>,
+    pub actions: MountActions,
+}
+
+impl DriverCapabilities {
+    /// `maintenance` blocks all changes like a read-only mount / 维护模式与只读挂载一样禁止修改
+    pub fn resolve(
+        driver_type: &str,
+        capability: Capability,
+        config: DriverConfig,
+        common: &[ConfigItem],
+        mount_config: &Value,
+        maintenance: bool,
+    ) -> Self {
+        let options = resolve_common_options(common, mount_config);
+        let flag = |key: &str| options.get(key).and_then(Value::as_bool).unwrap_or(false);
+        let modify = !maintenance && !read_only::is_read_only(mount_config);
+        let proxied = config.only_proxy || flag("web_proxy") || flag("hide_user_agent");
+        let actions = MountActions {
+            upload: modify && !config.no_upload,
+            modify,
+            range_read: capability.can_range_read,
+            direct_link: capability.can_direct_link && !proxied,
+        };
+        Self {
+            driver_type: driver_type.to_string(),
+            capability,
+            config,
+            options,
+            actions,
+        }
+    }
+}
+
+/// Value of each common item: the mount's own value, else the default, typed by the item
+/// 各通用项的取值：挂载自身的值，否则为默认值，按配置项类型转换
+pub fn resolve_common_options(common: &[ConfigItem], mount_config: &Value) -> Map<String, Value> {
+    common.iter()
+        .filter(|item| !MOUNT_RECORD_ITEMS.contains(&item.name.as_str()))
+        .map(|item| {
+            let value = match mount_config.get(&item.name) {
+                Some(Value::Null) | None => item.default.clone().map(Value::String).unwrap_or(Value::Null),
+                Some(Value::String(s)) if s.trim().is_empty() => item.default.clone().map(Value::String).unwrap_or(Value::Null),
+                Some(v) => v.clone(),
+            };
+            (item.name.clone(), typed(&item.item_type, value))
+        })
+        .collect()
+}
+
+/// Forms send booleans and numbers as strings / 表单中的布尔与数字可能是字符串
+fn typed(item_type: &str, value: Value) -> Value {
+    match (item_type, &value) {
+        ("bool", Value::String(s)) => Value::Bool(matches!(s.trim(), "true" | "1")),
+        ("bool", Value::Number(n)) => Value::Bool(n.as_i64().is_some_and(|n| n != 0)),
+        ("number", Value::String(s)) => match s.trim().parse::<i64>() {
+            Ok(n) => Value::from(n),
+            Err(_) => s.trim().parse::<f64>().map(Value::from).unwrap_or(Value::Null),
+        },
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::get_common_items;
+    use serde_json::json;
+
+    fn driver_config(no_upload: bool) -> DriverConfig {
+        DriverConfig {
+            name: "test".to_string(),
+            local_sort: false,
+            only_proxy: false,
+            no_cache: false,
+            no_upload,
+            default_root: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_common_options() {
+        let common = get_common_items(&driver_config(false), "test");
+        let options = resolve_common_options(&common, &json!({"web_proxy": "true", "cache_expiration": "60", "read_only": ""}));
+        assert_eq!(options["web_proxy"], json!(true));
+        assert_eq!(options["cache_expiration"], json!(60));
+        assert_eq!(options["read_only"], json!(false));
+        assert_eq!(options["write_policy"], json!("first"));
+        assert!(!options.contains_key("mount_path"));
+    }
+
+    #[test]
+    fn test_mount_actions() {
+        let capability = Capability { can_range_read: true, can_direct_link: true, ..Default::default() };
+        let common = get_common_items(&driver_config(true), "test");
+
+        let share = DriverCapabilities::resolve("test", capability.clone(), driver_config(true), &common, &json!({}), false);
+        assert_eq!(share.actions, MountActions { upload: false, modify: true, range_read: true, direct_link: true });
+
+        let common = get_common_items(&driver_config(false), "test");
+        let proxied = DriverCapabilities::resolve("test", capability.clone(), driver_config(false), &common, &json!({"web_proxy": true}), false);
+        assert!(proxied.actions.upload && !proxied.actions.direct_link);
+
+        let read_only = DriverCapabilities::resolve("test", capability.clone(), driver_config(false), &common, &json!({"read_only": true}), false);
+        assert!(!read_only.actions.upload && !read_only.actions.modify);
+
+        let maintenance = DriverCapabilities::resolve("test", Capability::default(), driver_config(false), &common, &json!({}), true);
+        assert_eq!(maintenance.actions, MountActions { upload: false, modify: false, range_read: false, direct_link: false });
+    }
+}
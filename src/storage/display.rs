@@ -0,0 +1,134 @@
+//! Per-mount display defaults / 挂载级显示默认值
+//!
+//! A photo library reads best as a gallery sorted newest first, a software mirror as a list by
+//! name. These common items let each mount choose its default order, view and how many folder
+//! levels the automatic index under the listing covers; `fs_list` returns them and uses the
+//! default order when the request doesn't pick one.
+//! 相册适合按时间倒序的图库视图，软件镜像适合按名称排序的列表。这些通用配置项让每个挂载选择默认排序、
+//! 视图以及列表下方自动索引覆盖的目录层数；`fs_list` 返回这些值，并在请求未指定排序时使用默认排序。
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::sort::ListSort;
+use super::ConfigItem;
+
+/// Config key of the default sort field / 默认排序字段的配置项
+pub const DEFAULT_SORT_KEY: &str = "default_sort";
+/// Config key of the default sort direction / 默认排序方向的配置项
+pub const DEFAULT_SORT_ORDER_KEY: &str = "default_sort_order";
+/// Config key of the default view / 默认视图的配置项
+pub const DEFAULT_VIEW_KEY: &str = "default_view";
+/// Config key of the index depth / 索引层数的配置项
+pub const LIST_DEPTH_KEY: &str = "list_depth";
+/// Deepest index the frontend is asked to build / 前端构建索引的最大层数
+pub const MAX_LIST_DEPTH: u32 = 5;
+
+/// How the frontend lays out a listing / 前端展示列表的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum View {
+    #[default]
+    List,
+    Grid,
+    Gallery,
+}
+
+/// Display defaults of one mount / 单个挂载的显示默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MountDisplay {
+    /// `sort_by` used when the request has none, `None` for name order
+    /// 请求未指定时使用的 `sort_by`，为 `None` 时按名称排序
+    pub sort_by: Option<String>,
+    pub sort_order: Option<String>,
+    pub view: View,
+    /// Folder levels of the automatic index, 0 = no index / 自动索引的目录层数，0 表示不显示索引
+    pub list_depth: u32,
+}
+
+impl MountDisplay {
+    /// Read from a driver config; empty or unknown values keep the defaults
+    /// 从驱动配置读取，空值或未知值保持默认
+    pub fn from_config(config: &Value) -> Self {
+        let text = |key: &str| config.get(key).and_then(Value::as_str).map(str::trim).filter(|s| !s.is_empty());
+        let sort_by = text(DEFAULT_SORT_KEY)
+            .filter(|s| matches!(*s, "name" | "pinyin" | "modified" | "size"))
+            .map(str::to_string);
+        let sort_order = text(DEFAULT_SORT_ORDER_KEY)
+            .filter(|s| matches!(*s, "asc" | "desc"))
+            .map(str::to_string);
+        let view = match text(DEFAULT_VIEW_KEY) {
+            Some("grid") => View::Grid,
+            Some("gallery") => View::Gallery,
+            _ => View::List,
+        };
+        let list_depth = match config.get(LIST_DEPTH_KEY) {
+            Some(Value::Number(n)) => n.as_u64(),
+            Some(Value::String(s)) => s.trim().parse().ok(),
+            _ => None,
+        }.unwrap_or(0).min(MAX_LIST_DEPTH as u64) as u32;
+        Self { sort_by, sort_order, view, list_depth }
+    }
+
+    /// Order of a listing: the request's own fields win over the mount defaults
+    /// 列表排序：请求自身的字段优先于挂载默认值
+    pub fn sort(&self, sort_by: Option<&str>, sort_order: Option<&str>) -> ListSort {
+        match sort_by.filter(|s| !s.is_empty()) {
+            Some(sort_by) => ListSort::parse(Some(sort_by), sort_order),
+            None => ListSort::parse(
+                self.sort_by.as_deref(),
+                sort_order.filter(|s| !s.is_empty()).or(self.sort_order.as_deref()),
+            ),
+        }
+    }
+}
+
+/// Common config items of the display defaults / 显示默认值的通用配置项
+pub fn config_items() -> Vec<ConfigItem> {
+    vec![
+        ConfigItem::new(DEFAULT_SORT_KEY, "select")
+            .options("name:Name,pinyin:Pinyin,modified:Modified,size:Size")
+            .default("name")
+            .help("Default sort order of listings, used when the visitor hasn't picked one"),
+        ConfigItem::new(DEFAULT_SORT_ORDER_KEY, "select")
+            .options("asc:Ascending,desc:Descending")
+            .default("asc")
+            .help("Default sort direction"),
+        ConfigItem::new(DEFAULT_VIEW_KEY, "select")
+            .options("list:List,grid:Grid,gallery:Gallery")
+            .default("list")
+            .help("Default view of this storage"),
+        ConfigItem::new(LIST_DEPTH_KEY, "number")
+            .default("0")
+            .help("Folder levels covered by the automatic index below listings, 0 = no index"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::sort::SortField;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_config() {
+        let display = MountDisplay::from_config(&json!({
+            "default_sort": "modified", "default_sort_order": "desc", "default_view": "gallery", "list_depth": "9"
+        }));
+        assert_eq!(display.sort_by.as_deref(), Some("modified"));
+        assert_eq!(display.view, View::Gallery);
+        assert_eq!(display.list_depth, MAX_LIST_DEPTH);
+
+        let display = MountDisplay::from_config(&json!({"default_sort": "bogus", "default_view": "", "list_depth": 2}));
+        assert_eq!(display, MountDisplay { list_depth: 2, ..Default::default() });
+    }
+
+    #[test]
+    fn test_sort() {
+        let display = MountDisplay::from_config(&json!({"default_sort": "modified", "default_sort_order": "desc"}));
+        assert_eq!(display.sort(None, None), ListSort { field: SortField::Modified, desc: true });
+        assert_eq!(display.sort(Some(""), Some("asc")), ListSort { field: SortField::Modified, desc: false });
+        assert_eq!(display.sort(Some("size"), None), ListSort { field: SortField::Size, desc: false });
+        assert_eq!(MountDisplay::default().sort(None, None), ListSort::default());
+    }
+}
@@ -0,0 +1,126 @@
+//! Driver health monitoring / 驱动健康监测
+//!
+//! Core periodically lists the root of every loaded driver (cheap primitive call),
+//! records latency and errors, and reloads failed drivers with exponential backoff.
+//! 定期对每个驱动列根目录，记录延迟与错误，并以指数退避自动重载出错的驱动
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Interval between two health check rounds (seconds) / 健康检查间隔（秒）
+pub const HEALTH_CHECK_INTERVAL_SECS: u64 = 60;
+/// Timeout of a single probe (seconds) / 单次探测超时（秒）
+pub const HEALTH_PROBE_TIMEOUT_SECS: u64 = 30;
+/// Latency above which a working driver is reported as degraded (ms) / 超过该延迟视为降级
+pub const DEGRADED_LATENCY_MS: u64 = 5000;
+/// First reload backoff (seconds) / 首次重载退避
+const RELOAD_BACKOFF_BASE_SECS: i64 = 30;
+/// Maximum reload backoff (seconds) / 最大重载退避
+const RELOAD_BACKOFF_MAX_SECS: i64 = 3600;
+
+/// Health status / 健康状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Not checked yet / 尚未检查
+    Unknown,
+    Ok,
+    /// Reachable but slow / 可用但响应慢
+    Degraded,
+    Error,
+}
+
+/// Health record of one driver / 单个驱动的健康记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriverHealth {
+    pub status: HealthStatus,
+    pub last_error: Option<String>,
+    /// Latency of the last successful probe (ms) / 最近一次成功探测的延迟
+    pub latency_ms: Option<u64>,
+    pub checked_at: Option<DateTime<Utc>>,
+    /// Consecutive failed probes / reloads / 连续失败次数
+    pub consecutive_failures: u32,
+    /// Earliest time of the next automatic reload / 下次自动重载时间
+    pub next_reload_at: Option<DateTime<Utc>>,
+}
+
+impl Default for DriverHealth {
+    fn default() -> Self {
+        Self {
+            status: HealthStatus::Unknown,
+            last_error: None,
+            latency_ms: None,
+            checked_at: None,
+            consecutive_failures: 0,
+            next_reload_at: None,
+        }
+    }
+}
+
+impl DriverHealth {
+    /// Record a successful probe / 记录成功探测
+    pub fn record_success(&mut self, latency_ms: u64) {
+        self.status = if latency_ms > DEGRADED_LATENCY_MS {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Ok
+        };
+        self.last_error = None;
+        self.latency_ms = Some(latency_ms);
+        self.checked_at = Some(Utc::now());
+        self.consecutive_failures = 0;
+        self.next_reload_at = None;
+    }
+
+    /// Record a failed probe or reload and schedule the next reload / 记录失败并安排下次重载
+    pub fn record_failure(&mut self, error: String) {
+        let now = Utc::now();
+        self.status = HealthStatus::Error;
+        self.last_error = Some(error);
+        self.checked_at = Some(now);
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.next_reload_at = Some(now + reload_backoff(self.consecutive_failures));
+    }
+
+    /// Whether an automatic reload is due / 是否到了自动重载时间
+    pub fn reload_due(&self, now: DateTime<Utc>) -> bool {
+        self.status == HealthStatus::Error
+            && self.next_reload_at.map(|t| now >= t).unwrap_or(true)
+    }
+}
+
+/// Exponential backoff: 30s, 60s, 120s ... capped at 1h / 指数退避
+pub fn reload_backoff(failures: u32) -> Duration {
+    let exp = failures.saturating_sub(1).min(16);
+    let secs = RELOAD_BACKOFF_BASE_SECS.saturating_mul(1i64 << exp);
+    Duration::seconds(secs.min(RELOAD_BACKOFF_MAX_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_backoff() {
+        assert_eq!(reload_backoff(1), Duration::seconds(30));
+        assert_eq!(reload_backoff(2), Duration::seconds(60));
+        assert_eq!(reload_backoff(4), Duration::seconds(240));
+        assert_eq!(reload_backoff(100), Duration::seconds(3600));
+    }
+
+    #[test]
+    fn test_health_transitions() {
+        let mut health = DriverHealth::default();
+        health.record_failure("timeout".to_string());
+        assert_eq!(health.status, HealthStatus::Error);
+        assert!(!health.reload_due(Utc::now()));
+        assert!(health.reload_due(Utc::now() + Duration::seconds(31)));
+
+        health.record_success(DEGRADED_LATENCY_MS + 1);
+        assert_eq!(health.status, HealthStatus::Degraded);
+        assert_eq!(health.consecutive_failures, 0);
+
+        health.record_success(10);
+        assert_eq!(health.status, HealthStatus::Ok);
+    }
+}
@@ -0,0 +1,84 @@
+//! Streaming HTTP download readers / 流式 HTTP 下载读取器
+//!
+//! Drivers that download through a provider URL forward the requested range as a `Range` header
+//! and wrap `bytes_stream()` into an `AsyncRead`, so proxied downloads never hold the whole file
+//! in memory. Some providers ignore `Range` and answer `200` with the full body; the requested
+//! slice is then cut out of the stream, so callers always get exactly the bytes they asked for.
+//! 通过存储商 URL 下载的驱动把请求范围作为 `Range` 头转发，并将 `bytes_stream()` 包装为 `AsyncRead`，
+//! 中转下载不会把整个文件读进内存。部分存储商忽略 `Range` 并返回 `200` 完整内容，此时从流中截取所需部分，
+//! 保证调用方拿到的正是请求的字节。
+
+use std::ops::Range;
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::StreamReader;
+
+/// Add a `Range` header for the half-open `range` / 为左闭右开的范围添加 `Range` 头
+pub fn with_range(request: RequestBuilder, range: Option<&Range<u64>>) -> RequestBuilder {
+    match range {
+        Some(r) if r.end > r.start => request.header("Range", format!("bytes={}-{}", r.start, r.end - 1)),
+        _ => request,
+    }
+}
+
+/// Turn a download response into a streaming reader / 将下载响应转为流式读取器
+pub fn response_reader(response: Response, range: Option<Range<u64>>) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(anyhow!("下载失败: HTTP {}", status));
+    }
+    // 请求了范围却返回200说明上游忽略了Range
+    let range = range.filter(|_| status != StatusCode::PARTIAL_CONTENT);
+    if range.is_some() {
+        tracing::debug!("Upstream ignored Range, slicing the full response");
+    }
+    Ok(stream_reader(response.bytes_stream(), range))
+}
+
+/// Wrap a byte stream, keeping only `range` when given / 包装字节流，指定范围时只保留该部分
+fn stream_reader<S, E>(stream: S, range: Option<Range<u64>>) -> Box<dyn AsyncRead + Unpin + Send>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut skip = range.as_ref().map(|r| r.start).unwrap_or(0);
+    let stream = stream.map(move |chunk| {
+        let mut chunk = chunk.map_err(std::io::Error::other)?;
+        if skip > 0 {
+            let n = skip.min(chunk.len() as u64) as usize;
+            chunk = chunk.slice(n..);
+            skip -= n as u64;
+        }
+        Ok::<_, std::io::Error>(chunk)
+    });
+    let reader = StreamReader::new(Box::pin(stream));
+    match range {
+        Some(r) if r.end > r.start => Box::new(reader.take(r.end - r.start)),
+        _ => Box::new(reader),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    async fn read_all(chunks: &[&'static str], range: Option<Range<u64>>) -> String {
+        let stream = stream::iter(chunks.iter().map(|c| Ok::<_, std::io::Error>(Bytes::from_static(c.as_bytes()))).collect::<Vec<_>>());
+        let mut out = String::new();
+        stream_reader(stream, range).read_to_string(&mut out).await.unwrap();
+        out
+    }
+
+    #[tokio::test]
+    async fn test_stream_reader_slices_full_body() {
+        let chunks = ["0123", "4567", "89"];
+        assert_eq!(read_all(&chunks, None).await, "0123456789");
+        assert_eq!(read_all(&chunks, Some(2..7)).await, "23456");
+        assert_eq!(read_all(&chunks, Some(4..8)).await, "4567");
+        assert_eq!(read_all(&chunks, Some(8..20)).await, "89");
+    }
+}
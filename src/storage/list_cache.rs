@@ -0,0 +1,608 @@
+//! Directory listing cache / 目录列表缓存
+//!
+//! Every driver instance is wrapped in `CachedDriver`, which keeps `list` results for the mount's
+//! `cache_expiration` seconds (`0` or a driver without the option = not cached). Any write through
+//! the wrapper drops the whole mount's listings by bumping a per-mount generation, so a replica
+//! never serves its own stale writes. With the Redis cluster backend the entries and generations
+//! live in Redis and are shared by all replicas; otherwise each process keeps its own.
+//! 每个驱动实例包装为 `CachedDriver`，按挂载的 `cache_expiration` 秒缓存 `list` 结果（`0` 或
+//! 无该配置项的驱动不缓存）。经包装器的任何写操作都会递增挂载的代数，使该挂载的全部列表失效。
+//! 使用 Redis 集群后端时条目与代数保存在 Redis 中由所有实例共享，否则每个进程各自缓存。
+//!
+//! Sorted views: for mounts with a lifetime, each order a path is listed in keeps a sorted copy
+//! in this process next to the cached listing, so paging through a huge directory sorts it once.
+//! Views belong to the listing's generation and are dropped with it.
+//! 排序视图：有缓存时间的挂载，路径按每种排序方式列出时会在本进程中保留一份排好序的副本，
+//! 翻阅超大目录时只排序一次。视图属于列表的代数，随列表一起失效。
+//!
+//! Warming: every cached `list` call counts towards the path's (decaying) hit score. Shortly
+//! before the cached listing of one of the `warm_paths` hottest paths expires it is fetched again
+//! in the background, while readers keep getting the old entry, so popular folders stay warm.
+//! 预热：每次缓存的 `list` 调用都会累计路径的（随时间衰减的）访问分数。最热的 `warm_paths` 个路径在
+//! 缓存即将过期前于后台重新获取，期间读取仍返回旧条目，因此热门目录始终命中缓存。
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::cluster::{MemoryStore, SharedStore};
+use super::rate_limit::config_number;
+use super::sort::ListSort;
+use super::{Capability, Entry, HashType, ProgressCallback, SpaceInfo, StorageDriver};
+
+/// Config key of the per-mount lifetime (seconds) / 挂载级缓存时间配置项（秒）
+pub const CACHE_EXPIRATION_KEY: &str = "cache_expiration";
+/// Lifetime of a mount's generation key / 挂载代数键的有效期
+const GENERATION_TTL_SECS: u64 = 86400;
+/// How often due paths are warmed (seconds) / 检查待预热路径的间隔（秒）
+pub const WARM_TICK_SECS: u64 = 5;
+/// Hit scores halve after this long (seconds) / 访问分数的半衰期（秒）
+const HIT_HALF_LIFE_SECS: f64 = 600.0;
+/// Paths scoring less are never warmed / 分数低于此值的路径不预热
+const MIN_WARM_SCORE: f64 = 2.0;
+/// Paths scoring less are forgotten / 分数低于此值的路径不再跟踪
+const FORGET_SCORE: f64 = 0.05;
+/// Sorted views kept, the ones expiring first are dropped / 保留的排序视图数（超出时淘汰最早过期的）
+const MAX_SORTED_VIEWS: usize = 64;
+
+tokio::task_local! {
+    /// Set while warming: skip the cached entry and do not count a hit / 预热中：跳过缓存条目且不计访问
+    static WARMING: ();
+}
+
+/// Listing cache settings (site setting `list_cache`) / 列表缓存设置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ListCacheConfig {
+    /// Cache listings at all / 是否缓存列表
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Keep entries in Redis when the cluster backend is Redis / 集群后端为 Redis 时将条目保存在 Redis
+    #[serde(default = "default_true")]
+    pub shared: bool,
+    /// Refresh the N hottest paths before they expire, 0 disables / 在过期前刷新最热的 N 个路径，0 表示关闭
+    #[serde(default = "default_warm_paths")]
+    pub warm_paths: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_warm_paths() -> usize {
+    20
+}
+
+impl Default for ListCacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, shared: true, warm_paths: default_warm_paths() }
+    }
+}
+
+/// Access tracking of one cached path / 单个缓存路径的访问跟踪
+struct HotPath {
+    score: f64,
+    scored_at: Instant,
+    /// When this replica's cached listing expires, None while not cached or being warmed
+    /// 本实例缓存的列表过期时间，未缓存或正在预热时为 None
+    expires_at: Option<Instant>,
+}
+
+impl HotPath {
+    fn score_at(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.scored_at).as_secs_f64();
+        self.score * 0.5f64.powf(elapsed / HIT_HALF_LIFE_SECS)
+    }
+}
+
+/// Sorted copy of a cached listing / 缓存列表的排序副本
+struct SortedView {
+    entries: Arc<Vec<Entry>>,
+    expires_at: Instant,
+}
+
+/// Hot path for the admin panel / 热门路径（管理面板展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct HotPathInfo {
+    pub mount_id: String,
+    pub path: String,
+    pub score: f64,
+}
+
+/// Listing cache shared by all mounts / 所有挂载共用的列表缓存
+pub struct ListCache {
+    config: RwLock<ListCacheConfig>,
+    local: Arc<MemoryStore>,
+    shared: RwLock<Option<Arc<dyn SharedStore>>>,
+    /// Lifetime per mount (id -> seconds) / 每个挂载的缓存时间
+    ttls: RwLock<HashMap<String, u64>>,
+    /// Access scores ((mount id, path) -> hot path) / 访问分数
+    hot: RwLock<HashMap<(String, String), HotPath>>,
+    /// Sorted views ((entry key, order) -> view) / 排序视图
+    views: RwLock<HashMap<(String, ListSort), SortedView>>,
+}
+
+impl Default for ListCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ListCache {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(ListCacheConfig::default()),
+            local: Arc::new(MemoryStore::new()),
+            shared: RwLock::new(None),
+            ttls: RwLock::new(HashMap::new()),
+            hot: RwLock::new(HashMap::new()),
+            views: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Store shared with other replicas (Redis) / 与其他实例共享的存储（Redis）
+    pub fn set_shared_store(&self, store: Arc<dyn SharedStore>) {
+        *self.shared.write() = Some(store);
+    }
+
+    /// Whether entries currently go to the shared store / 条目当前是否写入共享存储
+    pub fn is_shared(&self) -> bool {
+        self.config.read().shared && self.shared.read().is_some()
+    }
+
+    pub fn get_config(&self) -> ListCacheConfig {
+        *self.config.read()
+    }
+
+    pub fn set_config(&self, config: ListCacheConfig) {
+        *self.config.write() = config;
+    }
+
+    /// Load settings from database / 从数据库加载设置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'list_cache'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((value,)) = row {
+            let config: ListCacheConfig = serde_json::from_str(&value).map_err(|e| e.to_string())?;
+            self.set_config(config);
+        }
+        Ok(())
+    }
+
+    /// Take the lifetime from a mount's config / 从挂载配置读取缓存时间
+    pub fn set_ttl(&self, id: &str, config: &Value) {
+        let secs = config_number(config, CACHE_EXPIRATION_KEY).filter(|s| *s > 0.0).unwrap_or(0.0) as u64;
+        self.ttls.write().insert(id.to_string(), secs);
+    }
+
+    pub fn remove_ttl(&self, id: &str) {
+        self.ttls.write().remove(id);
+        self.hot.write().retain(|(mount, _), _| mount != id);
+    }
+
+    /// Lifetime of a mount, `None` when not cached / 挂载的缓存时间，不缓存时为 `None`
+    fn ttl(&self, id: &str) -> Option<Duration> {
+        if !self.config.read().enabled {
+            return None;
+        }
+        self.ttls.read().get(id).copied().filter(|s| *s > 0).map(Duration::from_secs)
+    }
+
+    fn store(&self) -> Arc<dyn SharedStore> {
+        if self.config.read().shared {
+            if let Some(store) = self.shared.read().clone() {
+                return store;
+            }
+        }
+        self.local.clone()
+    }
+
+    async fn entry_key(&self, store: &Arc<dyn SharedStore>, id: &str, path: &str) -> String {
+        let generation = store.get(&format!("list_gen:{}", id)).await.ok().flatten().unwrap_or_default();
+        format!("list:{}:{}:{}", id, generation, path)
+    }
+
+    /// Cached listing of a path / 读取路径的缓存列表
+    pub async fn get(&self, id: &str, path: &str) -> Option<Vec<Entry>> {
+        self.ttl(id)?;
+        if WARMING.try_with(|_| ()).is_ok() {
+            return None;
+        }
+        self.record_hit(id, path);
+        let store = self.store();
+        let key = self.entry_key(&store, id, path).await;
+        let value = store.get(&key).await.ok().flatten()?;
+        serde_json::from_str(&value).ok()
+    }
+
+    /// Remember a listing / 缓存列表
+    pub async fn put(&self, id: &str, path: &str, entries: &[Entry]) {
+        let Some(ttl) = self.ttl(id) else { return };
+        let Ok(value) = serde_json::to_string(entries) else { return };
+        let store = self.store();
+        let key = self.entry_key(&store, id, path).await;
+        self.views.write().retain(|(k, _), _| *k != key);
+        if let Err(e) = store.set(&key, &value, ttl).await {
+            tracing::debug!("Failed to cache listing {}:{}: {}", id, path, e);
+            return;
+        }
+        if let Some(hot) = self.hot.write().get_mut(&(id.to_string(), path.to_string())) {
+            hot.expires_at = Some(Instant::now() + ttl);
+        }
+    }
+
+    /// Listing of a path in `order`, sorted once per cached listing / 按 `order` 排序的路径列表，每份缓存列表只排序一次
+    ///
+    /// `driver` should be the mount's cached driver, a miss lists through it.
+    /// `driver` 应为挂载的缓存驱动，未命中时通过它获取列表。
+    pub async fn sorted_list(&self, id: &str, driver: &dyn StorageDriver, path: &str, order: ListSort) -> Result<Arc<Vec<Entry>>> {
+        let Some(ttl) = self.ttl(id) else {
+            let mut entries = driver.list(path).await?;
+            order.sort(&mut entries);
+            return Ok(Arc::new(entries));
+        };
+        let store = self.store();
+        let view_key = (self.entry_key(&store, id, path).await, order);
+        let now = Instant::now();
+        if WARMING.try_with(|_| ()).is_err() {
+            let cached = self.views.read().get(&view_key)
+                .filter(|v| v.expires_at > now)
+                .map(|v| v.entries.clone());
+            if let Some(entries) = cached {
+                self.record_hit(id, path);
+                return Ok(entries);
+            }
+        }
+
+        let mut entries = driver.list(path).await?;
+        order.sort(&mut entries);
+        let entries = Arc::new(entries);
+        let mut views = self.views.write();
+        views.retain(|_, v| v.expires_at > now);
+        while views.len() >= MAX_SORTED_VIEWS {
+            let Some(first) = views.iter().min_by_key(|(_, v)| v.expires_at).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            views.remove(&first);
+        }
+        views.insert(view_key, SortedView { entries: entries.clone(), expires_at: now + ttl });
+        Ok(entries)
+    }
+
+    /// Count one access of a cached path / 记录一次缓存路径的访问
+    fn record_hit(&self, id: &str, path: &str) {
+        let now = Instant::now();
+        let mut hot = self.hot.write();
+        let entry = hot.entry((id.to_string(), path.to_string())).or_insert(HotPath {
+            score: 0.0,
+            scored_at: now,
+            expires_at: None,
+        });
+        entry.score = entry.score_at(now) + 1.0;
+        entry.scored_at = now;
+    }
+
+    /// Hottest paths due for warming, marked as being warmed; forgets cold paths
+    /// 取出即将过期的最热路径并标记为预热中，同时清理已冷却的路径
+    ///
+    /// A path is due when its listing expires within a fifth of the mount's lifetime, but at
+    /// least two warm ticks so a tick is never missed.
+    /// 列表在挂载缓存时间的五分之一内过期即需预热，至少提前两个检查间隔，避免错过。
+    pub fn take_due_warmups(&self) -> Vec<(String, String)> {
+        let config = self.get_config();
+        let now = Instant::now();
+        let ttls = self.ttls.read().clone();
+        let mut hot = self.hot.write();
+        hot.retain(|(id, _), h| ttls.contains_key(id) && h.score_at(now) >= FORGET_SCORE);
+        if config.warm_paths == 0 || !config.enabled {
+            return Vec::new();
+        }
+
+        let mut ranked: Vec<(&(String, String), f64)> = hot.iter()
+            .map(|(key, h)| (key, h.score_at(now)))
+            .filter(|(_, score)| *score >= MIN_WARM_SCORE)
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let due: Vec<(String, String)> = ranked.into_iter()
+            .take(config.warm_paths)
+            .map(|(key, _)| key.clone())
+            .filter(|key| {
+                let lifetime = ttls.get(&key.0).copied().unwrap_or(0);
+                let lead = Duration::from_secs((lifetime / 5).max(WARM_TICK_SECS * 2));
+                lifetime > 0 && hot[key].expires_at.is_some_and(|at| at <= now + lead)
+            })
+            .collect();
+        for key in &due {
+            if let Some(h) = hot.get_mut(key) {
+                h.expires_at = None;
+            }
+        }
+        due
+    }
+
+    /// Hottest tracked paths / 当前最热的路径
+    pub fn hot_paths(&self, limit: usize) -> Vec<HotPathInfo> {
+        let now = Instant::now();
+        let mut paths: Vec<HotPathInfo> = self.hot.read().iter()
+            .map(|((id, path), h)| HotPathInfo { mount_id: id.clone(), path: path.clone(), score: h.score_at(now) })
+            .collect();
+        paths.sort_by(|a, b| b.score.total_cmp(&a.score));
+        paths.truncate(limit);
+        paths
+    }
+
+    /// Drop all listings of a mount / 使挂载的全部列表失效
+    pub async fn invalidate(&self, id: &str) {
+        let prefix = format!("list:{}:", id);
+        self.views.write().retain(|(k, _), _| !k.starts_with(&prefix));
+        let generation = uuid::Uuid::new_v4().simple().to_string();
+        let key = format!("list_gen:{}", id);
+        let shared = self.shared.read().clone();
+        for store in [Some(self.local.clone() as Arc<dyn SharedStore>), shared].into_iter().flatten() {
+            if let Err(e) = store.set(&key, &generation, Duration::from_secs(GENERATION_TTL_SECS)).await {
+                tracing::warn!("Failed to invalidate listing cache of {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Drop the listing of one path / 使单个路径的列表失效
+    pub async fn invalidate_path(&self, id: &str, path: &str) {
+        let store = self.store();
+        let key = self.entry_key(&store, id, path).await;
+        self.views.write().retain(|(k, _), _| *k != key);
+        let _ = store.delete(&key).await;
+    }
+}
+
+/// Fetch a listing again, ignoring the cached entry; the result replaces it
+/// 忽略缓存条目重新获取列表，结果替换缓存
+pub async fn refresh_listing(driver: &dyn StorageDriver, path: &str) -> Result<Vec<Entry>> {
+    WARMING.scope((), driver.list(path)).await
+}
+
+/// Driver wrapper serving listings from the cache / 从缓存提供列表的驱动包装
+pub struct CachedDriver {
+    id: String,
+    inner: Box<dyn StorageDriver>,
+    cache: Arc<ListCache>,
+}
+
+impl CachedDriver {
+    pub fn new(id: String, inner: Box<dyn StorageDriver>, cache: Arc<ListCache>) -> Self {
+        Self { id, inner, cache }
+    }
+
+    async fn after_write<T>(&self, result: Result<T>) -> Result<T> {
+        self.cache.invalidate(&self.id).await;
+        result
+    }
+}
+
+/// Writer dropping the mount's listings once the upload is finished / 上传结束后使挂载列表失效的写入器
+struct InvalidatingWriter {
+    inner: Box<dyn AsyncWrite + Unpin + Send>,
+    id: String,
+    cache: Arc<ListCache>,
+}
+
+impl AsyncWrite for InvalidatingWriter {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let result = Pin::new(&mut self.inner).poll_shutdown(cx);
+        if result.is_ready() {
+            let (id, cache) = (self.id.clone(), self.cache.clone());
+            tokio::spawn(async move { cache.invalidate(&id).await });
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl StorageDriver for CachedDriver {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn version(&self) -> &str {
+        self.inner.version()
+    }
+
+    fn capabilities(&self) -> Capability {
+        self.inner.capabilities()
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        if let Some(entries) = self.cache.get(&self.id, path).await {
+            return Ok(entries);
+        }
+        let entries = self.inner.list(path).await?;
+        self.cache.put(&self.id, path, &entries).await;
+        Ok(entries)
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.open_reader(path, range).await
+    }
+
+    async fn open_writer(
+        &self,
+        path: &str,
+        size_hint: Option<u64>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        let writer = self.after_write(self.inner.open_writer(path, size_hint, progress).await).await?;
+        Ok(Box::new(InvalidatingWriter { inner: writer, id: self.id.clone(), cache: self.cache.clone() }))
+    }
+
+    async fn open_resume_writer(
+        &self,
+        path: &str,
+        offset: u64,
+        size_hint: Option<u64>,
+    ) -> Result<Option<Box<dyn AsyncWrite + Unpin + Send>>> {
+        let writer = self.after_write(self.inner.open_resume_writer(path, offset, size_hint).await).await?;
+        Ok(writer.map(|inner| {
+            Box::new(InvalidatingWriter { inner, id: self.id.clone(), cache: self.cache.clone() })
+                as Box<dyn AsyncWrite + Unpin + Send>
+        }))
+    }
+
+    async fn put(&self, path: &str, data: bytes::Bytes, progress: Option<ProgressCallback>) -> Result<()> {
+        self.after_write(self.inner.put(path, data, progress).await).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.after_write(self.inner.delete(path).await).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.after_write(self.inner.create_dir(path).await).await
+    }
+
+    async fn rename(&self, old_path: &str, new_name: &str) -> Result<()> {
+        self.after_write(self.inner.rename(old_path, new_name).await).await
+    }
+
+    async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.after_write(self.inner.move_item(old_path, new_path).await).await
+    }
+
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.after_write(self.inner.copy_item(old_path, new_path).await).await
+    }
+
+    fn hash_types(&self) -> Vec<HashType> {
+        self.inner.hash_types()
+    }
+
+    async fn get_hash(&self, path: &str, hash_type: HashType) -> Result<Option<String>> {
+        self.inner.get_hash(path, hash_type).await
+    }
+
+    async fn account_key(&self) -> Option<String> {
+        self.inner.account_key().await
+    }
+
+    fn account_path(&self, path: &str) -> Option<String> {
+        self.inner.account_path(path)
+    }
+
+    async fn transfer_within_account(&self, src_path: &str, dst_account_path: &str, copy: bool) -> Result<bool> {
+        let result = self.inner.transfer_within_account(src_path, dst_account_path, copy).await;
+        if matches!(result, Ok(true)) {
+            self.cache.invalidate(&self.id).await;
+        }
+        result
+    }
+
+    async fn get_direct_link(&self, path: &str) -> Result<Option<String>> {
+        self.inner.get_direct_link(path).await
+    }
+
+    fn get_local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.get_local_path(path)
+    }
+
+    fn is_local(&self) -> bool {
+        self.inner.is_local()
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        self.inner.get_space_info().await
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        self.inner.show_space_in_frontend()
+    }
+
+    async fn abort_stale_uploads(&self, older_than: std::time::Duration) -> Result<usize> {
+        self.inner.abort_stale_uploads(older_than).await
+    }
+
+    fn get_updated_config(&self) -> Option<Value> {
+        self.inner.get_updated_config()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(name: &str) -> Entry {
+        Entry { name: name.to_string(), path: format!("/{}", name), is_dir: false, size: 1, modified: None }
+    }
+
+    #[tokio::test]
+    async fn test_list_cache() {
+        let cache = ListCache::new();
+        cache.set_ttl("m", &json!({"cache_expiration": "30"}));
+        cache.set_ttl("nocache", &json!({}));
+
+        cache.put("m", "/", &[entry("a")]).await;
+        cache.put("nocache", "/", &[entry("a")]).await;
+        assert_eq!(cache.get("m", "/").await.map(|e| e.len()), Some(1));
+        assert!(cache.get("nocache", "/").await.is_none());
+
+        cache.invalidate("m").await;
+        assert!(cache.get("m", "/").await.is_none());
+
+        cache.put("m", "/", &[entry("a"), entry("b")]).await;
+        cache.set_config(ListCacheConfig { enabled: false, shared: true, warm_paths: 0 });
+        assert!(cache.get("m", "/").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_warmup_selection() {
+        let cache = ListCache::new();
+        cache.set_config(ListCacheConfig { warm_paths: 1, ..Default::default() });
+        cache.set_ttl("m", &json!({"cache_expiration": "30"}));
+        for path in ["/hot", "/warm"] {
+            cache.put("m", path, &[entry("a")]).await;
+        }
+        for _ in 0..3 {
+            cache.get("m", "/hot").await;
+            cache.get("m", "/warm").await;
+        }
+        cache.get("m", "/hot").await;
+        // 刚缓存的条目还未到预热时间
+        cache.put("m", "/hot", &[entry("a")]).await;
+        assert!(cache.take_due_warmups().is_empty());
+
+        cache.hot.write().get_mut(&("m".to_string(), "/hot".to_string())).unwrap().expires_at = Some(Instant::now());
+        assert_eq!(cache.take_due_warmups(), vec![("m".to_string(), "/hot".to_string())]);
+        // 预热中的路径不会重复取出
+        assert!(cache.take_due_warmups().is_empty());
+        assert_eq!(cache.hot_paths(1)[0].path, "/hot");
+
+        // 预热请求不计访问，且跳过缓存条目
+        let hits = cache.hot_paths(2)[1].score;
+        assert!(WARMING.scope((), cache.get("m", "/warm")).await.is_none());
+        assert!(cache.hot_paths(2)[1].score <= hits);
+    }
+}
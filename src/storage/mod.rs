@@ -28,6 +28,9 @@ pub struct ConfigItem {
     /// Link URL for "link" type items (opens in new tab) / link类型的URL
     #[serde(skip_serializing_if = "Option::is_none")]
     pub link: Option<String>,
+    /// Value format hint; "password" marks a write-only secret / 值格式提示，"password" 表示只写的敏感字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
 }
 
 impl ConfigItem {
@@ -41,6 +44,7 @@ impl ConfigItem {
             required: false,
             help: None,
             link: None,
+            format: None,
         }
     }
     
@@ -73,6 +77,18 @@ impl ConfigItem {
         self.link = Some(val.to_string());
         self
     }
+
+    /// Mark as a secret: redacted in API responses, blank on update keeps the stored value
+    /// 标记为敏感字段：接口返回时隐藏，更新时留空则保留原值
+    pub fn password(mut self) -> Self {
+        self.format = Some("password".to_string());
+        self
+    }
+
+    /// Whether this item holds a secret / 是否为敏感字段
+    pub fn is_secret(&self) -> bool {
+        self.format.as_deref() == Some("password") || self.item_type == "password"
+    }
 }
 
 /// Driver configuration information / 驱动配置信息
@@ -103,17 +119,18 @@ pub struct DriverInfo {
 }
 
 /// Generate common configuration items (defined in Core, shared by all drivers) / 生成通用配置项
-pub fn get_common_items(config: &DriverConfig) -> Vec<ConfigItem> {
+pub fn get_common_items(config: &DriverConfig, driver_type: &str) -> Vec<ConfigItem> {
     let mut items = vec![
         ConfigItem::new("mount_path", "string")
             .required()
-            .help("Mount path, must be unique"),
+            .help("Mount path, mounts sharing a path are merged into one folder"),
         ConfigItem::new("order", "number")
             .default("0")
             .help("Sort order"),
         ConfigItem::new("remark", "text")
             .help("Remark/Notes"),
     ];
+    items.extend(display::config_items());
     
     if !config.no_cache {
         items.push(
@@ -130,8 +147,38 @@ pub fn get_common_items(config: &DriverConfig) -> Vec<ConfigItem> {
                 .default("false")
                 .help("Enable Web proxy")
         );
+        items.push(
+            ConfigItem::new("hide_user_agent", "bool")
+                .default("false")
+                .help("Always proxy downloads, for direct links that need the server's User-Agent or headers")
+        );
     }
     
+    items.push(
+        ConfigItem::new(aggregate::WRITE_POLICY_KEY, "select")
+            .options("first:First writable,most_free_space:Most free space")
+            .default("first")
+            .help("Where new files go when several mounts share this path, taken from the first mount")
+    );
+    items.push(
+        ConfigItem::new(read_only::READ_ONLY_KEY, "bool")
+            .default("false")
+            .help("Read-only (WORM): reject uploads, deletes, renames and moves on this mount")
+    );
+    items.extend(crate::upload_policy::config_items());
+    
+    let default_limit = rate_limit::RateLimit::default_for(driver_type);
+    let mut rps = ConfigItem::new(rate_limit::RATE_LIMIT_RPS_KEY, "number")
+        .help("Max API requests per second to the provider, empty = driver default, 0 = unlimited");
+    let mut burst = ConfigItem::new(rate_limit::RATE_LIMIT_BURST_KEY, "number")
+        .help("Requests allowed at once before throttling");
+    if let Some(limit) = default_limit {
+        rps = rps.default(&limit.requests_per_sec.to_string());
+        burst = burst.default(&limit.burst.to_string());
+    }
+    items.push(rps);
+    items.push(burst);
+    
     items
 }
 
@@ -156,6 +203,14 @@ pub struct SpaceInfo {
     pub free: u64,
 }
 
+/// File checksum algorithm / 文件校验算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashType {
+    Md5,
+    Sha1,
+}
+
 /// Driver capability declaration / 驱动能力声明
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Capability {
@@ -232,6 +287,17 @@ pub trait StorageDriver: Send + Sync {
         progress: Option<ProgressCallback>,
     ) -> Result<Box<dyn AsyncWrite + Unpin + Send>>;
     
+    /// Open writer continuing a partially written file at `offset` (resumable copy)
+    /// Returns None if the driver cannot resume writes / 从offset处继续写入已部分写入的文件
+    async fn open_resume_writer(
+        &self,
+        _path: &str,
+        _offset: u64,
+        _size_hint: Option<u64>,
+    ) -> Result<Option<Box<dyn AsyncWrite + Unpin + Send>>> {
+        Ok(None)
+    }
+    
     /// Put complete file data - 上传完整文件
     /// 云盘驱动应重写此方法，自己处理分片上传、秒传等
     /// 默认实现使用open_writer，适合本地存储等流式驱动
@@ -273,6 +339,35 @@ pub trait StorageDriver: Send + Sync {
         Ok(())
     }
     
+    /// Checksum algorithms this driver can report via `get_hash` / 驱动可通过get_hash提供的校验算法
+    fn hash_types(&self) -> Vec<HashType> {
+        Vec::new()
+    }
+    
+    /// Get file checksum as lowercase hex (primitive operation)
+    /// Returns None if the driver cannot provide it / 获取文件校验值（小写十六进制）
+    async fn get_hash(&self, _path: &str, _hash_type: HashType) -> Result<Option<String>> {
+        Ok(None)
+    }
+    
+    /// Identity of the underlying cloud account/drive (may query the provider)
+    /// Mounts returning the same key can move/copy server-side between each other / 账号标识
+    async fn account_key(&self) -> Option<String> {
+        None
+    }
+    
+    /// Convert a path inside this mount to an account-level path (root folder applied)
+    /// 将挂载点内路径转换为账号级路径（包含根目录）
+    fn account_path(&self, _path: &str) -> Option<String> {
+        None
+    }
+    
+    /// Server-side move/copy to an account-level destination on the same account
+    /// Returns Ok(false) if not supported / 同账号服务端移动/复制，不支持时返回Ok(false)
+    async fn transfer_within_account(&self, _src_path: &str, _dst_account_path: &str, _copy: bool) -> Result<bool> {
+        Ok(false)
+    }
+    
     /// Get direct link URL (if supported) / 获取直链 URL
     async fn get_direct_link(&self, _path: &str) -> Result<Option<String>> {
         Ok(None)
@@ -301,6 +396,12 @@ pub trait StorageDriver: Send + Sync {
         false
     }
     
+    /// Abort unfinished multipart/resumable upload sessions older than `older_than`
+    /// Returns how many were aborted, 0 if the driver has none / 中止超过指定时长的未完成分片上传会话，返回中止数量
+    async fn abort_stale_uploads(&self, _older_than: std::time::Duration) -> Result<usize> {
+        Ok(0)
+    }
+    
     /// Get updated config (for saving tokens etc.) / 获取更新后的配置
     /// Returns None if config hasn't changed / 如果配置未变更则返回None
     fn get_updated_config(&self) -> Option<serde_json::Value> {
@@ -310,6 +411,26 @@ pub trait StorageDriver: Send + Sync {
 
 pub mod manager;
 pub mod local_factory;
+pub mod health;
+pub mod plugin;
+pub mod rate_limit;
+pub mod list_cache;
+pub mod retry;
+pub mod http_stream;
+pub mod aggregate;
+pub mod capabilities;
+pub mod display;
+pub mod usage;
+pub mod read_only;
+pub mod sort;
+pub mod traced;
+pub mod upload_cleanup;
 
-pub use manager::{StorageManager, DriverFactory, DriverBox};
+pub use manager::{StorageManager, DriverFactory, DriverBox, DriverSpec, ConfigUpdate, HttpClientKey, shared_http_client, shared_http_client_or_fallback};
+pub use health::{DriverHealth, HealthStatus};
+pub use rate_limit::{RateLimit, RateLimitStats};
+pub use list_cache::{ListCache, ListCacheConfig};
+pub use read_only::{MaintenanceConfig, MaintenanceMode};
+pub use sort::{Cursor, ListSort, SortField};
+pub use retry::{RetryPolicy, SendRetry};
 pub use local_factory::LocalDriverFactory;
@@ -0,0 +1,315 @@
+//! StorageDriver implemented over plugin RPC / 基于插件 RPC 的 StorageDriver 实现
+
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::PollSender;
+
+use super::rpc::PluginClient;
+use super::PluginManifest;
+use crate::storage::{Capability, Entry, HashType, ProgressCallback, SpaceInfo, StorageDriver};
+
+/// Bytes per read / write RPC call / 每次读写调用传输的字节数
+pub const PLUGIN_CHUNK_SIZE: usize = 1024 * 1024;
+/// Chunks queued between Core and the plugin / 排队中的数据块数
+const CHUNK_QUEUE: usize = 2;
+
+fn b64() -> base64::engine::GeneralPurpose {
+    base64::engine::general_purpose::STANDARD
+}
+
+/// Driver instance backed by a plugin process / 由插件进程提供的驱动实例
+pub struct PluginDriver {
+    manifest: Arc<PluginManifest>,
+    client: Arc<PluginClient>,
+}
+
+impl PluginDriver {
+    pub fn new(manifest: Arc<PluginManifest>, client: PluginClient) -> Self {
+        Self { manifest, client: Arc::new(client) }
+    }
+
+    async fn call_unit(&self, method: &str, params: Value) -> Result<()> {
+        self.client.call(method, params).await.map(|_| ())
+    }
+}
+
+#[async_trait]
+impl StorageDriver for PluginDriver {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn version(&self) -> &str {
+        &self.manifest.version
+    }
+
+    fn capabilities(&self) -> Capability {
+        self.manifest.capabilities.clone()
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        let result = self.client.call("list", json!({ "path": path })).await?;
+        serde_json::from_value(result).map_err(|e| anyhow!("Invalid list result from plugin: {}", e))
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let (tx, rx) = mpsc::channel::<io::Result<bytes::Bytes>>(CHUNK_QUEUE);
+        let client = self.client.clone();
+        let path = path.to_string();
+        let (mut offset, end) = match range {
+            Some(r) => (r.start, Some(r.end)),
+            None => (0, None),
+        };
+
+        tokio::spawn(async move {
+            loop {
+                let length = match end {
+                    Some(end) if offset >= end => break,
+                    Some(end) => ((end - offset) as usize).min(PLUGIN_CHUNK_SIZE),
+                    None => PLUGIN_CHUNK_SIZE,
+                };
+                let chunk = client.call("read", json!({ "path": path, "offset": offset, "length": length })).await
+                    .and_then(|v| {
+                        let data = v.get("data").and_then(|d| d.as_str()).unwrap_or("");
+                        b64().decode(data).map_err(|e| anyhow!("Invalid base64 from plugin: {}", e))
+                    });
+                match chunk {
+                    Ok(data) if data.is_empty() => break,
+                    Ok(data) => {
+                        offset += data.len() as u64;
+                        if tx.send(Ok(bytes::Bytes::from(data))).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(io::Error::other(e.to_string()))).await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+        Ok(Box::new(tokio_util::io::StreamReader::new(stream)))
+    }
+
+    async fn open_writer(
+        &self,
+        path: &str,
+        size_hint: Option<u64>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        let begin = self.client.call("write_begin", json!({ "path": path, "size": size_hint })).await?;
+        let handle = begin.get("handle").cloned().unwrap_or(Value::Null);
+
+        let (tx, mut rx) = mpsc::channel::<WriteMsg>(CHUNK_QUEUE);
+        let client = self.client.clone();
+        let task = tokio::spawn(async move {
+            let mut written = 0u64;
+            let total = size_hint.unwrap_or(0);
+            loop {
+                match rx.recv().await {
+                    Some(WriteMsg::Data(data)) => {
+                        let len = data.len() as u64;
+                        let params = json!({ "handle": handle, "data": b64().encode(&data) });
+                        if let Err(e) = client.call("write_chunk", params).await {
+                            let _ = client.call("write_abort", json!({ "handle": handle })).await;
+                            return Err(io::Error::other(e.to_string()));
+                        }
+                        written += len;
+                        if let Some(ref cb) = progress {
+                            cb(written, total.max(written));
+                        }
+                    }
+                    Some(WriteMsg::Finish) => {
+                        return client.call("write_end", json!({ "handle": handle })).await
+                            .map(|_| ())
+                            .map_err(|e| io::Error::other(e.to_string()));
+                    }
+                    // Writer dropped without shutdown: discard partial file / 未调用shutdown即被丢弃，放弃写入
+                    None => {
+                        let _ = client.call("write_abort", json!({ "handle": handle })).await;
+                        return Err(io::Error::new(io::ErrorKind::Interrupted, "Plugin write aborted"));
+                    }
+                }
+            }
+        });
+
+        Ok(Box::new(PluginWriter {
+            tx: PollSender::new(tx),
+            buf: Vec::with_capacity(PLUGIN_CHUNK_SIZE),
+            finished: false,
+            task: Some(task),
+        }))
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.call_unit("delete", json!({ "path": path })).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.call_unit("create_dir", json!({ "path": path })).await
+    }
+
+    async fn rename(&self, old_path: &str, new_name: &str) -> Result<()> {
+        self.call_unit("rename", json!({ "path": old_path, "new_name": new_name })).await
+    }
+
+    async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.call_unit("move", json!({ "from": old_path, "to": new_path })).await
+    }
+
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        if self.manifest.capabilities.can_server_side_copy
+            && self.client.call_optional("copy", json!({ "from": old_path, "to": new_path })).await?.is_some()
+        {
+            return Ok(());
+        }
+        // Fallback: stream through Core / 回退：经由Core读写
+        use tokio::io::AsyncWriteExt;
+        let mut reader = self.open_reader(old_path, None).await?;
+        let mut writer = self.open_writer(new_path, None, None).await?;
+        tokio::io::copy(&mut reader, &mut writer).await?;
+        writer.shutdown().await?;
+        Ok(())
+    }
+
+    fn hash_types(&self) -> Vec<HashType> {
+        self.manifest.hash_types.clone()
+    }
+
+    async fn get_hash(&self, path: &str, hash_type: HashType) -> Result<Option<String>> {
+        if !self.manifest.hash_types.contains(&hash_type) {
+            return Ok(None);
+        }
+        let result = self.client.call_optional("get_hash", json!({ "path": path, "hash_type": hash_type })).await?;
+        Ok(result
+            .and_then(|v| v.get("hash").and_then(|h| h.as_str()).map(|h| h.to_lowercase())))
+    }
+
+    async fn get_direct_link(&self, path: &str) -> Result<Option<String>> {
+        if !self.manifest.capabilities.can_direct_link {
+            return Ok(None);
+        }
+        let result = self.client.call_optional("get_direct_link", json!({ "path": path })).await?;
+        Ok(result.and_then(|v| v.get("url").and_then(|u| u.as_str()).map(|u| u.to_string())))
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        match self.client.call_optional("get_space_info", json!({})).await? {
+            Some(v) if !v.is_null() => Ok(serde_json::from_value(v).ok()),
+            _ => Ok(None),
+        }
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        self.manifest.show_space_in_frontend
+    }
+
+    fn get_updated_config(&self) -> Option<Value> {
+        self.client.take_updated_config()
+    }
+}
+
+enum WriteMsg {
+    Data(Vec<u8>),
+    Finish,
+}
+
+/// Buffers writes into chunks sent to the plugin / 将写入缓冲为数据块发送给插件
+struct PluginWriter {
+    tx: PollSender<WriteMsg>,
+    buf: Vec<u8>,
+    finished: bool,
+    task: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl PluginWriter {
+    fn closed() -> io::Error {
+        io::Error::new(io::ErrorKind::BrokenPipe, "Plugin upload channel closed")
+    }
+
+    /// Send one message once the queue has room / 队列有空位时发送消息
+    fn poll_send(&mut self, cx: &mut Context<'_>, msg: impl FnOnce(&mut Self) -> WriteMsg) -> Poll<io::Result<()>> {
+        match self.tx.poll_reserve(cx) {
+            Poll::Ready(Ok(())) => {
+                let msg = msg(self);
+                Poll::Ready(self.tx.send_item(msg).map_err(|_| Self::closed()))
+            }
+            Poll::Ready(Err(_)) => Poll::Ready(Err(Self::closed())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_send_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.buf.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        self.poll_send(cx, |w| WriteMsg::Data(std::mem::replace(&mut w.buf, Vec::with_capacity(PLUGIN_CHUNK_SIZE))))
+    }
+}
+
+impl AsyncWrite for PluginWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(Err(Self::closed()));
+        }
+        if this.buf.len() >= PLUGIN_CHUNK_SIZE {
+            match this.poll_send_buf(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let n = data.len().min(PLUGIN_CHUNK_SIZE - this.buf.len());
+        this.buf.extend_from_slice(&data[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.finished {
+            match this.poll_send_buf(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+            match this.poll_send(cx, |_| WriteMsg::Finish) {
+                Poll::Ready(Ok(())) => this.finished = true,
+                Poll::Ready(Err(_)) => this.finished = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        // Wait for the plugin to commit the file / 等待插件完成写入
+        match this.task.as_mut() {
+            Some(task) => match Pin::new(task).poll(cx) {
+                Poll::Ready(result) => {
+                    this.task = None;
+                    Poll::Ready(result.unwrap_or_else(|e| Err(io::Error::other(e.to_string()))))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
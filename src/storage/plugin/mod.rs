@@ -0,0 +1,351 @@
+//! External driver plugins / 外部驱动插件
+//!
+//! This module handles:
+//! - Discovery of `<plugins_dir>/<id>/plugin.json` manifests / 扫描插件目录中的清单
+//! - Subprocess sandbox (clean env, own data dir, rlimits, call timeout) / 子进程沙箱
+//! - `DriverFactory` for each plugin, config schema passed through to the admin UI
+//!   每个插件注册为驱动工厂，配置项直接透传给管理界面
+//!
+//! Protocol: JSON-RPC 2.0 over stdio, see `drivers/PLUGIN_PROTOCOL.md`.
+//! 协议：基于 stdio 的 JSON-RPC 2.0，详见 `drivers/PLUGIN_PROTOCOL.md`。
+
+pub mod rpc;
+pub mod driver;
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Capability, ConfigItem, DriverConfig, DriverFactory, HashType, StorageDriver, StorageManager};
+pub use driver::PluginDriver;
+pub use rpc::PluginClient;
+
+/// Protocol version sent in `initialize` / 协议版本
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Manifest file name / 清单文件名
+pub const MANIFEST_FILE: &str = "plugin.json";
+
+/// Sandbox limits / 沙箱限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginSandbox {
+    /// Per-call timeout (seconds) / 单次调用超时（秒）
+    pub timeout_secs: u64,
+    /// Address space limit (MB, 0 = unlimited, Unix only) / 内存上限（MB，仅Unix）
+    pub max_memory_mb: u64,
+    /// Open file limit (0 = unlimited, Unix only) / 打开文件数上限（仅Unix）
+    pub max_open_files: u64,
+    /// Extra environment variables inherited from YaoList / 额外继承的环境变量
+    pub env: Vec<String>,
+}
+
+impl Default for PluginSandbox {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 60,
+            max_memory_mb: 1024,
+            max_open_files: 256,
+            env: Vec::new(),
+        }
+    }
+}
+
+/// Raw manifest as written by the plugin author / 插件清单原始格式
+#[derive(Debug, Clone, Deserialize)]
+struct RawManifest {
+    id: String,
+    name: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    description: String,
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    config: Option<DriverConfig>,
+    #[serde(default)]
+    additional: Vec<ConfigItem>,
+    /// Partial capability object, missing fields use defaults / 部分能力声明
+    #[serde(default)]
+    capabilities: Value,
+    #[serde(default)]
+    hash_types: Vec<HashType>,
+    #[serde(default)]
+    show_space_in_frontend: bool,
+    #[serde(default)]
+    sandbox: PluginSandbox,
+}
+
+/// Validated plugin manifest / 校验后的插件清单
+#[derive(Debug, Clone)]
+pub struct PluginManifest {
+    /// Driver type name / 驱动类型名
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    /// Plugin directory / 插件目录
+    pub dir: PathBuf,
+    pub command: String,
+    pub args: Vec<String>,
+    pub config: DriverConfig,
+    /// Driver-specific config items (shown in admin UI) / 驱动配置项（透传给管理界面）
+    pub additional: Vec<ConfigItem>,
+    pub capabilities: Capability,
+    pub hash_types: Vec<HashType>,
+    pub show_space_in_frontend: bool,
+    pub sandbox: PluginSandbox,
+}
+
+impl PluginManifest {
+    /// Parse and validate `plugin.json` content / 解析并校验清单
+    pub fn parse(content: &str, dir: &Path) -> Result<Self> {
+        let raw: RawManifest = serde_json::from_str(content)
+            .map_err(|e| anyhow!("Invalid {}: {}", MANIFEST_FILE, e))?;
+
+        if raw.id.is_empty() || raw.id.len() > 64
+            || !raw.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        {
+            return Err(anyhow!("Invalid plugin id '{}': use 1-64 chars of a-z, 0-9, _ and -", raw.id));
+        }
+        if raw.command.trim().is_empty() {
+            return Err(anyhow!("Plugin {} has no command", raw.id));
+        }
+        check_relative(&raw.command).map_err(|e| anyhow!("Plugin {} command: {}", raw.id, e))?;
+
+        // Overlay declared capabilities on the defaults / 在默认能力上覆盖声明的字段
+        let mut capabilities = serde_json::to_value(Capability::default())?;
+        if let (Some(base), Some(declared)) = (capabilities.as_object_mut(), raw.capabilities.as_object()) {
+            for (k, v) in declared {
+                base.insert(k.clone(), v.clone());
+            }
+        }
+        let capabilities: Capability = serde_json::from_value(capabilities)
+            .map_err(|e| anyhow!("Plugin {} capabilities: {}", raw.id, e))?;
+
+        let config = raw.config.unwrap_or_else(|| DriverConfig {
+            name: raw.name.clone(),
+            local_sort: false,
+            only_proxy: false,
+            no_cache: false,
+            no_upload: false,
+            default_root: None,
+        });
+
+        Ok(Self {
+            id: raw.id,
+            name: raw.name,
+            version: if raw.version.is_empty() { "0.0.0".to_string() } else { raw.version },
+            description: raw.description,
+            dir: dir.to_path_buf(),
+            command: raw.command,
+            args: raw.args,
+            config,
+            additional: raw.additional,
+            capabilities,
+            hash_types: raw.hash_types,
+            show_space_in_frontend: raw.show_space_in_frontend,
+            sandbox: raw.sandbox,
+        })
+    }
+
+    /// Program to execute: file inside the plugin dir, otherwise a bare name looked up in PATH
+    /// 可执行程序：优先插件目录内的文件，否则为 PATH 中的命令名（如 python3、node）
+    pub fn resolve_command(&self) -> Result<PathBuf> {
+        let local = self.dir.join(&self.command);
+        if local.is_file() {
+            return Ok(local);
+        }
+        if Path::new(&self.command).components().count() == 1 {
+            return Ok(PathBuf::from(&self.command));
+        }
+        Err(anyhow!("Plugin command not found: {:?}", local))
+    }
+}
+
+/// Reject absolute paths and `..` so commands stay inside the plugin dir / 禁止绝对路径与上级目录
+fn check_relative(path: &str) -> Result<()> {
+    let p = Path::new(path);
+    if p.is_absolute() || p.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(anyhow!("must be a relative path inside the plugin directory: {}", path));
+    }
+    Ok(())
+}
+
+/// Driver factory backed by a plugin / 插件驱动工厂
+pub struct PluginDriverFactory {
+    manifest: Arc<PluginManifest>,
+    /// Plugin id with 'static lifetime (plugins load once per process) / 静态生命周期的插件id
+    driver_type: &'static str,
+    data_root: PathBuf,
+}
+
+impl PluginDriverFactory {
+    pub fn new(manifest: PluginManifest, data_root: PathBuf) -> Self {
+        let driver_type: &'static str = Box::leak(manifest.id.clone().into_boxed_str());
+        Self {
+            manifest: Arc::new(manifest),
+            driver_type,
+            data_root,
+        }
+    }
+}
+
+impl DriverFactory for PluginDriverFactory {
+    fn driver_type(&self) -> &'static str {
+        self.driver_type
+    }
+
+    fn create_driver(&self, config: Value) -> Result<Box<dyn StorageDriver>> {
+        let data_dir = self.data_root.join(&self.manifest.id);
+        let client = PluginClient::new(self.manifest.clone(), config, data_dir);
+        Ok(Box::new(PluginDriver::new(self.manifest.clone(), client)))
+    }
+
+    fn driver_config(&self) -> DriverConfig {
+        self.manifest.config.clone()
+    }
+
+    fn additional_items(&self) -> Vec<ConfigItem> {
+        self.manifest.additional.clone()
+    }
+}
+
+/// Discovered plugin status / 插件发现结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginStatus {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub path: String,
+    pub loaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of the last discovery / 最近一次插件发现的结果
+static PLUGINS: Lazy<RwLock<Vec<PluginStatus>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Get discovered plugins / 获取已发现的插件
+pub fn loaded_plugins() -> Vec<PluginStatus> {
+    PLUGINS.read().clone()
+}
+
+/// Check whether a driver type comes from a plugin / 判断驱动类型是否来自插件
+pub fn is_plugin(driver_type: &str) -> bool {
+    PLUGINS.read().iter().any(|p| p.loaded && p.id.as_deref() == Some(driver_type))
+}
+
+/// Scan `plugins_dir` and register every valid plugin / 扫描插件目录并注册有效插件
+///
+/// Must run after built-in drivers are registered: a plugin never replaces a built-in driver type.
+/// 需在内置驱动注册后调用：插件不能覆盖内置驱动类型
+pub async fn load_plugins(manager: &StorageManager, plugins_dir: &Path, data_root: &Path) -> Vec<PluginStatus> {
+    let mut statuses = Vec::new();
+    let mut dirs: Vec<PathBuf> = match std::fs::read_dir(plugins_dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect(),
+        Err(_) => {
+            tracing::debug!("Plugin directory not found: {:?}", plugins_dir);
+            Vec::new()
+        }
+    };
+    dirs.sort();
+
+    let mut known = manager.list_driver_types().await;
+    for dir in dirs {
+        let path = dir.display().to_string();
+        let manifest = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+            .map_err(|e| anyhow!("Cannot read {}: {}", MANIFEST_FILE, e))
+            .and_then(|content| PluginManifest::parse(&content, &dir))
+            .and_then(|m| {
+                if known.contains(&m.id) {
+                    Err(anyhow!("Driver type '{}' already exists", m.id))
+                } else {
+                    m.resolve_command().map(|_| m)
+                }
+            });
+
+        let status = match manifest {
+            Ok(manifest) => {
+                let mut status = PluginStatus {
+                    id: Some(manifest.id.clone()),
+                    name: Some(manifest.name.clone()),
+                    version: Some(manifest.version.clone()),
+                    description: Some(manifest.description.clone()),
+                    path,
+                    loaded: false,
+                    error: None,
+                };
+                let id = manifest.id.clone();
+                match manager.register_factory(Box::new(PluginDriverFactory::new(manifest, data_root.to_path_buf()))).await {
+                    Ok(()) => {
+                        tracing::info!("Driver plugin loaded: {} ({})", id, status.path);
+                        status.loaded = true;
+                        known.push(id);
+                    }
+                    Err(e) => status.error = Some(e.to_string()),
+                }
+                status
+            }
+            Err(e) => {
+                tracing::warn!("Driver plugin skipped: {} - {}", path, e);
+                PluginStatus {
+                    id: None,
+                    name: None,
+                    version: None,
+                    description: None,
+                    path,
+                    loaded: false,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        statuses.push(status);
+    }
+
+    *PLUGINS.write() = statuses.clone();
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_parse() {
+        let dir = Path::new("/tmp/plugins/demo");
+        let m = PluginManifest::parse(r#"{
+            "id": "demo",
+            "name": "Demo",
+            "command": "python3",
+            "args": ["main.py"],
+            "additional": [{"name": "token", "type": "string", "required": true}],
+            "capabilities": {"can_range_read": true},
+            "hash_types": ["md5"],
+            "sandbox": {"timeout_secs": 5}
+        }"#, dir).unwrap();
+        assert_eq!(m.config.name, "Demo");
+        assert!(m.capabilities.can_range_read);
+        assert!(!m.capabilities.can_direct_link);
+        assert_eq!(m.hash_types, vec![HashType::Md5]);
+        assert_eq!(m.sandbox.timeout_secs, 5);
+        assert_eq!(m.sandbox.max_open_files, PluginSandbox::default().max_open_files);
+        assert_eq!(m.additional[0].name, "token");
+        assert_eq!(m.resolve_command().unwrap(), PathBuf::from("python3"));
+    }
+
+    #[test]
+    fn test_manifest_rejects_unsafe() {
+        let dir = Path::new("/tmp/plugins/demo");
+        assert!(PluginManifest::parse(r#"{"id": "Demo!", "name": "x", "command": "run"}"#, dir).is_err());
+        assert!(PluginManifest::parse(r#"{"id": "demo", "name": "x", "command": "/bin/sh"}"#, dir).is_err());
+        assert!(PluginManifest::parse(r#"{"id": "demo", "name": "x", "command": "../other/run"}"#, dir).is_err());
+        assert!(PluginManifest::parse(r#"{"id": "demo", "name": "x", "command": ""}"#, dir).is_err());
+    }
+}
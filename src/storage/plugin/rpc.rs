@@ -0,0 +1,336 @@
+//! Plugin process and JSON-RPC transport / 插件进程与 JSON-RPC 通信
+//!
+//! One JSON-RPC 2.0 message per line over stdin/stdout; stderr is forwarded to the log.
+//! Requests are multiplexed by id, so plugins may answer out of order.
+//! 每行一条 JSON-RPC 2.0 消息（stdin/stdout），stderr 写入日志；按 id 复用，插件可乱序应答。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+use super::PluginManifest;
+
+/// Max size of one message line (base64 chunks included) / 单条消息最大长度
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+/// JSON-RPC "method not found" / 方法不存在错误码
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Wait at least this long before restarting a crashed plugin / 插件崩溃后的最短重启间隔
+const RESTART_BACKOFF: Duration = Duration::from_secs(5);
+/// Environment variables always passed to plugins / 始终传给插件的环境变量
+const BASE_ENV: &[&str] = &["PATH", "LANG", "TZ", "SYSTEMROOT"];
+
+/// Error returned by the plugin / 插件返回的错误
+#[derive(Debug, Clone)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+type Pending = Mutex<HashMap<u64, oneshot::Sender<std::result::Result<Value, RpcError>>>>;
+
+/// A running plugin process / 运行中的插件进程
+struct PluginProcess {
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    pending: Arc<Pending>,
+    alive: Arc<AtomicBool>,
+    next_id: AtomicU64,
+    _child: Child,
+}
+
+impl PluginProcess {
+    /// Spawn the plugin inside its sandbox / 在沙箱中启动插件
+    fn spawn(manifest: &PluginManifest, data_dir: &Path, updated_config: Arc<Mutex<Option<Value>>>) -> Result<Self> {
+        let program = manifest.resolve_command()?;
+        let mut cmd = Command::new(&program);
+        cmd.args(&manifest.args)
+            .current_dir(&manifest.dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .env_clear();
+
+        // Only whitelisted environment is inherited / 只继承白名单中的环境变量
+        for key in BASE_ENV.iter().copied().chain(manifest.sandbox.env.iter().map(|s| s.as_str())) {
+            if let Ok(val) = std::env::var(key) {
+                cmd.env(key, val);
+            }
+        }
+        std::fs::create_dir_all(data_dir)?;
+        cmd.env("HOME", data_dir)
+            .env("TMPDIR", data_dir)
+            .env("YAOLIST_PLUGIN_ID", &manifest.id)
+            .env("YAOLIST_PLUGIN_DATA", data_dir);
+
+        #[cfg(unix)]
+        apply_rlimits(&mut cmd, manifest.sandbox.max_memory_mb, manifest.sandbox.max_open_files);
+
+        let mut child = cmd.spawn()
+            .map_err(|e| anyhow!("Failed to start plugin {} ({:?}): {}", manifest.id, program, e))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("Plugin stdin unavailable"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("Plugin stdout unavailable"))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow!("Plugin stderr unavailable"))?;
+
+        let pending: Arc<Pending> = Arc::new(Mutex::new(HashMap::new()));
+        let alive = Arc::new(AtomicBool::new(true));
+
+        // stdout: responses and notifications / 响应与通知
+        {
+            let id = manifest.id.clone();
+            let pending = pending.clone();
+            let alive = alive.clone();
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(stdout);
+                loop {
+                    let mut line = Vec::new();
+                    let n = match (&mut reader).take(MAX_MESSAGE_BYTES as u64 + 1).read_until(b'\n', &mut line).await {
+                        Ok(n) => n,
+                        Err(e) => {
+                            tracing::warn!("Plugin {} stdout error: {}", id, e);
+                            break;
+                        }
+                    };
+                    if n == 0 {
+                        break;
+                    }
+                    if line.len() > MAX_MESSAGE_BYTES {
+                        tracing::error!("Plugin {} sent a message over {} bytes, stopping it", id, MAX_MESSAGE_BYTES);
+                        break;
+                    }
+                    match serde_json::from_slice::<Value>(&line) {
+                        Ok(msg) => dispatch(&id, msg, &pending, &updated_config),
+                        Err(e) => tracing::warn!("Plugin {} sent invalid JSON: {}", id, e),
+                    }
+                }
+                alive.store(false, Ordering::SeqCst);
+                // Fail everything still waiting / 进程结束，所有等待中的请求失败
+                for (_, tx) in pending.lock().drain() {
+                    let _ = tx.send(Err(RpcError { code: -32000, message: "Plugin process exited".to_string() }));
+                }
+                tracing::warn!("Plugin {} process exited", id);
+            });
+        }
+
+        // stderr: plugin logs / 插件日志
+        {
+            let id = manifest.id.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::info!("[plugin:{}] {}", id, line);
+                }
+            });
+        }
+
+        Ok(Self {
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending,
+            alive,
+            next_id: AtomicU64::new(1),
+            _child: child,
+        })
+    }
+
+    fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    async fn call(&self, method: &str, params: Value, timeout: Duration) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+
+        let mut line = serde_json::to_vec(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }))?;
+        line.push(b'\n');
+        {
+            let mut stdin = self.stdin.lock().await;
+            if let Err(e) = async {
+                stdin.write_all(&line).await?;
+                stdin.flush().await
+            }.await {
+                self.pending.lock().remove(&id);
+                return Err(anyhow!("Failed to write to plugin: {}", e));
+            }
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(e))) => Err(e.into()),
+            Ok(Err(_)) => Err(anyhow!("Plugin process exited")),
+            Err(_) => {
+                self.pending.lock().remove(&id);
+                Err(anyhow!("Plugin call timed out after {}s: {}", timeout.as_secs(), method))
+            }
+        }
+    }
+}
+
+/// Route one incoming message / 分发收到的消息
+fn dispatch(id: &str, msg: Value, pending: &Pending, updated_config: &Mutex<Option<Value>>) {
+    // Notification from plugin / 插件发来的通知
+    if let Some(method) = msg.get("method").and_then(|m| m.as_str()) {
+        match method {
+            "config_updated" => {
+                if let Some(Value::Object(pushed)) = msg.get("params").and_then(|p| p.get("config")) {
+                    // Merge with keys pushed since the last save / 与上次保存后推送的配置合并
+                    let mut guard = updated_config.lock();
+                    match guard.as_mut().and_then(|v| v.as_object_mut()) {
+                        Some(existing) => existing.extend(pushed.clone()),
+                        None => *guard = Some(Value::Object(pushed.clone())),
+                    }
+                }
+            }
+            _ => tracing::debug!("Plugin {} sent unknown notification: {}", id, method),
+        }
+        return;
+    }
+
+    let Some(req_id) = msg.get("id").and_then(|v| v.as_u64()) else {
+        tracing::warn!("Plugin {} sent a response without id", id);
+        return;
+    };
+    let Some(tx) = pending.lock().remove(&req_id) else {
+        return;
+    };
+    let result = match msg.get("error") {
+        Some(err) if !err.is_null() => Err(RpcError {
+            code: err.get("code").and_then(|c| c.as_i64()).unwrap_or(-32000),
+            message: err.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown plugin error").to_string(),
+        }),
+        _ => Ok(msg.get("result").cloned().unwrap_or(Value::Null)),
+    };
+    let _ = tx.send(result);
+}
+
+/// Limit memory / open files / core dumps of the child / 限制子进程内存、文件数、core dump
+#[cfg(unix)]
+fn apply_rlimits(cmd: &mut Command, max_memory_mb: u64, max_open_files: u64) {
+    unsafe {
+        cmd.pre_exec(move || {
+            let set = |resource, value: u64| {
+                let limit = libc::rlimit { rlim_cur: value as libc::rlim_t, rlim_max: value as libc::rlim_t };
+                libc::setrlimit(resource, &limit);
+            };
+            if max_memory_mb > 0 {
+                set(libc::RLIMIT_AS, max_memory_mb * 1024 * 1024);
+            }
+            if max_open_files > 0 {
+                set(libc::RLIMIT_NOFILE, max_open_files);
+            }
+            set(libc::RLIMIT_CORE, 0);
+            Ok(())
+        });
+    }
+}
+
+/// Lazily started, auto-restarting connection to one plugin instance
+/// 插件实例连接（首次调用时启动，崩溃后自动重启）
+pub struct PluginClient {
+    manifest: Arc<PluginManifest>,
+    config: Mutex<Value>,
+    data_dir: PathBuf,
+    process: tokio::sync::Mutex<Option<Arc<PluginProcess>>>,
+    last_spawn: Mutex<Option<Instant>>,
+    /// Config keys pushed by the plugin (e.g. refreshed tokens) / 插件推送的配置项（如刷新后的令牌）
+    updated_config: Arc<Mutex<Option<Value>>>,
+}
+
+impl PluginClient {
+    pub fn new(manifest: Arc<PluginManifest>, config: Value, data_dir: PathBuf) -> Self {
+        Self {
+            manifest,
+            config: Mutex::new(config),
+            data_dir,
+            process: tokio::sync::Mutex::new(None),
+            last_spawn: Mutex::new(None),
+            updated_config: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Call a method (starts / restarts the process when needed) / 调用方法（必要时启动进程）
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let process = self.process().await?;
+        process.call(method, params, self.timeout()).await
+    }
+
+    /// Call an optional method, `None` if the plugin does not implement it / 调用可选方法，未实现时返回None
+    pub async fn call_optional(&self, method: &str, params: Value) -> Result<Option<Value>> {
+        match self.call(method, params).await {
+            Ok(v) => Ok(Some(v)),
+            Err(e) => match e.downcast_ref::<RpcError>() {
+                Some(rpc) if rpc.code == METHOD_NOT_FOUND => Ok(None),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Full config with pushed keys merged in, `None` if nothing changed
+    /// 合并插件推送后的完整配置，无变化时返回None
+    pub fn take_updated_config(&self) -> Option<Value> {
+        let pushed = self.updated_config.lock().take()?;
+        let mut config = self.config.lock();
+        match (config.as_object_mut(), pushed) {
+            (Some(base), Value::Object(pushed)) => base.extend(pushed),
+            (_, pushed) => *config = pushed,
+        }
+        Some(config.clone())
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.manifest.sandbox.timeout_secs.max(1))
+    }
+
+    async fn process(&self) -> Result<Arc<PluginProcess>> {
+        let mut guard = self.process.lock().await;
+        if let Some(ref p) = *guard {
+            if p.is_alive() {
+                return Ok(p.clone());
+            }
+            tracing::warn!("Plugin {} is not running, restarting", self.manifest.id);
+        }
+        *guard = None;
+
+        {
+            let mut last = self.last_spawn.lock();
+            if let Some(t) = *last {
+                if t.elapsed() < RESTART_BACKOFF {
+                    return Err(anyhow!("Plugin {} crashed recently, retry later", self.manifest.id));
+                }
+            }
+            *last = Some(Instant::now());
+        }
+
+        let process = Arc::new(PluginProcess::spawn(&self.manifest, &self.data_dir, self.updated_config.clone())?);
+        process.call("initialize", json!({
+            "config": self.config.lock().clone(),
+            "data_dir": self.data_dir,
+            "protocol_version": super::PROTOCOL_VERSION,
+        }), self.timeout()).await
+            .map_err(|e| anyhow!("Plugin {} initialize failed: {}", self.manifest.id, e))?;
+
+        *guard = Some(process.clone());
+        Ok(process)
+    }
+}
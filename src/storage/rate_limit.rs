@@ -0,0 +1,347 @@
+//! Driver API rate limiting / 驱动 API 限流
+//!
+//! Every driver instance is wrapped in `RateLimitedDriver`, which takes a token from the
+//! mount's bucket before each primitive call (list, read/write open, delete, direct link, ...).
+//! Strict providers get a default budget per driver type; any mount can override it with the
+//! `rate_limit_rps` / `rate_limit_burst` config items (`0` = unlimited).
+//! 每个驱动实例包装为 `RateLimitedDriver`，每次原语调用前从挂载的令牌桶取令牌；
+//! 严格的网盘按驱动类型提供默认额度，挂载可通过配置项覆盖（`0` 表示不限制）。
+//!
+//! Calls made internally by a driver (e.g. chunk uploads inside a writer) are not counted.
+//! 驱动内部发起的调用（如写入器中的分片上传）不计入。
+
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{Capability, Entry, HashType, ProgressCallback, SpaceInfo, StorageDriver};
+
+/// Request budget: sustained rate and burst size / 请求额度：持续速率与突发数
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub requests_per_sec: f64,
+    pub burst: u32,
+}
+
+/// Default budgets of providers known to ban aggressive clients / 对频繁请求敏感的网盘的默认额度
+///
+/// `BaiduNetdisk` applies to a driver or plugin registered under that type.
+/// `BaiduNetdisk` 适用于以该类型注册的驱动或插件。
+const DEFAULT_LIMITS: &[(&str, RateLimit)] = &[
+    ("lanzou", RateLimit { requests_per_sec: 2.0, burst: 5 }),
+    ("BaiduNetdisk", RateLimit { requests_per_sec: 5.0, burst: 10 }),
+    ("pan115", RateLimit { requests_per_sec: 2.0, burst: 4 }),
+    ("115Share", RateLimit { requests_per_sec: 2.0, burst: 4 }),
+];
+
+/// Config keys of the per-mount override / 挂载级覆盖的配置项
+pub const RATE_LIMIT_RPS_KEY: &str = "rate_limit_rps";
+pub const RATE_LIMIT_BURST_KEY: &str = "rate_limit_burst";
+
+impl RateLimit {
+    /// Built-in default for a driver type / 驱动类型的内置默认额度
+    pub fn default_for(driver_type: &str) -> Option<RateLimit> {
+        DEFAULT_LIMITS.iter().find(|(t, _)| *t == driver_type).map(|(_, l)| *l)
+    }
+
+    /// Effective limit of a mount: override from config, else the type default / 挂载的生效额度
+    ///
+    /// Returns `None` when unlimited. / 不限制时返回 `None`。
+    pub fn resolve(driver_type: &str, config: &Value) -> Option<RateLimit> {
+        let default = Self::default_for(driver_type);
+        let rps = config_number(config, RATE_LIMIT_RPS_KEY);
+        let burst = config_number(config, RATE_LIMIT_BURST_KEY);
+        let requests_per_sec = match rps {
+            Some(r) if r <= 0.0 => return None,
+            Some(r) => r,
+            None => default?.requests_per_sec,
+        };
+        let burst = burst
+            .filter(|b| *b >= 1.0)
+            .map(|b| b as u32)
+            .or(default.filter(|_| rps.is_none()).map(|d| d.burst))
+            .unwrap_or_else(|| requests_per_sec.ceil().max(1.0) as u32);
+        Some(RateLimit { requests_per_sec, burst })
+    }
+}
+
+/// Config values come from forms as numbers or strings; empty = not set / 配置值可能是数字或字符串，空表示未设置
+pub(crate) fn config_number(config: &Value, key: &str) -> Option<f64> {
+    match config.get(key)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) if !s.trim().is_empty() => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Usage counters of a limiter / 限流器统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitStats {
+    /// Active limit, `None` = unlimited / 当前额度
+    pub limit: Option<RateLimit>,
+    pub requests: u64,
+    /// Requests that had to wait for a token / 需要等待的请求数
+    pub throttled: u64,
+    pub total_wait_ms: u64,
+}
+
+struct Bucket {
+    limit: Option<RateLimit>,
+    tokens: f64,
+    refilled_at: Instant,
+    stats: RateLimitStats,
+}
+
+/// Token bucket shared by all calls to one mount / 单个挂载的令牌桶
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                limit,
+                tokens: limit.map(|l| l.burst as f64).unwrap_or(0.0),
+                refilled_at: Instant::now(),
+                stats: RateLimitStats { limit, ..Default::default() },
+            }),
+        }
+    }
+
+    /// Change the limit in place (config update) / 原地修改额度
+    pub fn set_limit(&self, limit: Option<RateLimit>) {
+        let mut bucket = self.bucket.lock();
+        if bucket.limit == limit {
+            return;
+        }
+        bucket.limit = limit;
+        bucket.tokens = limit.map(|l| l.burst as f64).unwrap_or(0.0);
+        bucket.refilled_at = Instant::now();
+        bucket.stats.limit = limit;
+    }
+
+    pub fn stats(&self) -> RateLimitStats {
+        self.bucket.lock().stats.clone()
+    }
+
+    /// Reserve one token, returning how long the caller must wait / 预占一个令牌，返回需要等待的时间
+    ///
+    /// Tokens may go negative, so concurrent callers queue up instead of all waking at once.
+    /// 令牌数可为负，并发调用会依次排队而不是同时唤醒。
+    fn reserve(&self) -> Duration {
+        let mut bucket = self.bucket.lock();
+        bucket.stats.requests += 1;
+        let Some(limit) = bucket.limit else {
+            return Duration::ZERO;
+        };
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.requests_per_sec).min(limit.burst as f64);
+        bucket.refilled_at = now;
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        let wait = Duration::from_secs_f64(-bucket.tokens / limit.requests_per_sec);
+        bucket.stats.throttled += 1;
+        bucket.stats.total_wait_ms += wait.as_millis() as u64;
+        wait
+    }
+
+    /// Wait until a request is allowed / 等待直到允许发出请求
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Driver wrapper enforcing the mount's request budget / 按挂载额度限流的驱动包装
+pub struct RateLimitedDriver {
+    inner: Box<dyn StorageDriver>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedDriver {
+    pub fn new(inner: Box<dyn StorageDriver>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl StorageDriver for RateLimitedDriver {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn version(&self) -> &str {
+        self.inner.version()
+    }
+
+    fn capabilities(&self) -> Capability {
+        self.inner.capabilities()
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        self.limiter.acquire().await;
+        self.inner.list(path).await
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.limiter.acquire().await;
+        self.inner.open_reader(path, range).await
+    }
+
+    async fn open_writer(
+        &self,
+        path: &str,
+        size_hint: Option<u64>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.limiter.acquire().await;
+        self.inner.open_writer(path, size_hint, progress).await
+    }
+
+    async fn open_resume_writer(
+        &self,
+        path: &str,
+        offset: u64,
+        size_hint: Option<u64>,
+    ) -> Result<Option<Box<dyn AsyncWrite + Unpin + Send>>> {
+        self.limiter.acquire().await;
+        self.inner.open_resume_writer(path, offset, size_hint).await
+    }
+
+    async fn put(&self, path: &str, data: bytes::Bytes, progress: Option<ProgressCallback>) -> Result<()> {
+        self.limiter.acquire().await;
+        self.inner.put(path, data, progress).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.limiter.acquire().await;
+        self.inner.delete(path).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.limiter.acquire().await;
+        self.inner.create_dir(path).await
+    }
+
+    async fn rename(&self, old_path: &str, new_name: &str) -> Result<()> {
+        self.limiter.acquire().await;
+        self.inner.rename(old_path, new_name).await
+    }
+
+    async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.limiter.acquire().await;
+        self.inner.move_item(old_path, new_path).await
+    }
+
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.limiter.acquire().await;
+        self.inner.copy_item(old_path, new_path).await
+    }
+
+    fn hash_types(&self) -> Vec<HashType> {
+        self.inner.hash_types()
+    }
+
+    async fn get_hash(&self, path: &str, hash_type: HashType) -> Result<Option<String>> {
+        self.limiter.acquire().await;
+        self.inner.get_hash(path, hash_type).await
+    }
+
+    async fn account_key(&self) -> Option<String> {
+        self.limiter.acquire().await;
+        self.inner.account_key().await
+    }
+
+    fn account_path(&self, path: &str) -> Option<String> {
+        self.inner.account_path(path)
+    }
+
+    async fn transfer_within_account(&self, src_path: &str, dst_account_path: &str, copy: bool) -> Result<bool> {
+        self.limiter.acquire().await;
+        self.inner.transfer_within_account(src_path, dst_account_path, copy).await
+    }
+
+    async fn get_direct_link(&self, path: &str) -> Result<Option<String>> {
+        self.limiter.acquire().await;
+        self.inner.get_direct_link(path).await
+    }
+
+    fn get_local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.get_local_path(path)
+    }
+
+    fn is_local(&self) -> bool {
+        self.inner.is_local()
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        self.limiter.acquire().await;
+        self.inner.get_space_info().await
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        self.inner.show_space_in_frontend()
+    }
+
+    async fn abort_stale_uploads(&self, older_than: std::time::Duration) -> Result<usize> {
+        self.limiter.acquire().await;
+        self.inner.abort_stale_uploads(older_than).await
+    }
+
+    fn get_updated_config(&self) -> Option<Value> {
+        self.inner.get_updated_config()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(RateLimit::resolve("local", &json!({})), None);
+        assert_eq!(RateLimit::resolve("lanzou", &json!({})), RateLimit::default_for("lanzou"));
+        assert_eq!(RateLimit::resolve("lanzou", &json!({"rate_limit_rps": 0})), None);
+        assert_eq!(
+            RateLimit::resolve("local", &json!({"rate_limit_rps": "2.5", "rate_limit_burst": ""})),
+            Some(RateLimit { requests_per_sec: 2.5, burst: 3 })
+        );
+        assert_eq!(
+            RateLimit::resolve("pan115", &json!({"rate_limit_burst": 10})),
+            Some(RateLimit { requests_per_sec: 2.0, burst: 10 })
+        );
+    }
+
+    #[test]
+    fn test_bucket_reservation() {
+        let limiter = RateLimiter::new(Some(RateLimit { requests_per_sec: 10.0, burst: 2 }));
+        assert!(limiter.reserve().is_zero());
+        assert!(limiter.reserve().is_zero());
+        let third = limiter.reserve();
+        let fourth = limiter.reserve();
+        assert!(third > Duration::from_millis(80) && third <= Duration::from_millis(100));
+        assert!(fourth > Duration::from_millis(180) && fourth <= Duration::from_millis(200));
+        assert_eq!(limiter.stats().throttled, 2);
+
+        limiter.set_limit(None);
+        assert!(limiter.reserve().is_zero());
+        assert_eq!(limiter.stats().limit, None);
+    }
+}
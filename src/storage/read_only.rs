@@ -0,0 +1,333 @@
+//! Read-only mounts and maintenance mode / 只读挂载与维护模式
+//!
+//! A mount with the `read_only` config item keeps serving listings and downloads but refuses
+//! every write (upload, mkdir, delete, rename, move, copy), e.g. for WORM archives. The global
+//! maintenance switch does the same for all mounts while data is migrated or backed up, and
+//! `maintenance_middleware` rejects the mutating API endpoints up front with a clear message.
+//! Every driver instance is wrapped in `ReadOnlyDriver`, so WebDAV, FTP, SFTP and background
+//! tasks are covered as well.
+//! 设置了 `read_only` 配置项的挂载仍可列出与下载，但拒绝所有写操作（上传、新建目录、删除、重命名、移动、复制），
+//! 可用于 WORM 归档。全局维护模式在迁移或备份数据期间对所有挂载生效，`maintenance_middleware` 会直接以明确的消息
+//! 拒绝修改类 API。每个驱动实例都包装为 `ReadOnlyDriver`，因此 WebDAV、FTP、SFTP 与后台任务同样受限。
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use axum::extract::{Request, State};
+use axum::http::{Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{Capability, Entry, HashType, ProgressCallback, SpaceInfo, StorageDriver};
+
+/// Config key of the per-mount switch / 挂载级开关的配置项
+pub const READ_ONLY_KEY: &str = "read_only";
+/// Site setting holding the maintenance switch / 保存维护模式的站点设置
+pub const MAINTENANCE_KEY: &str = "maintenance_mode";
+/// Error of writes to a read-only mount / 写入只读挂载时的错误
+pub const READ_ONLY_MESSAGE: &str = "存储为只读";
+/// Default error while in maintenance / 维护模式的默认错误
+pub const MAINTENANCE_MESSAGE: &str = "站点维护中，暂时无法修改文件";
+
+/// Mutating endpoints rejected during maintenance / 维护期间拒绝的修改类接口
+const MUTATING_ENDPOINTS: &[&str] = &[
+    "/api/fs/mkdir", "/api/fs/write", "/api/fs/remove", "/api/fs/rename", "/api/fs/move", "/api/fs/copy",
+    "/api/fs/upload", "/api/fs/upload/batch", "/api/fs/upload/progress", "/api/fs/upload/complete_file",
+    "/api/fs/clipboard/paste", "/api/fs/extract",
+    "/api/tasks/resume", "/api/tasks/retry", "/api/tasks/restart",
+    "/api/tasks/group/resume", "/api/tasks/group/retry",
+    "/api/admin/restore", "/api/admin/strm/run", "/api/admin/tiering/run", "/api/admin/tiering/recall",
+    "/api/admin/dedupe/resolve",
+];
+/// WebDAV methods that change files / 修改文件的 WebDAV 方法
+const MUTATING_DAV_METHODS: &[&str] = &["PUT", "DELETE", "MKCOL", "MOVE", "COPY", "PROPPATCH"];
+
+/// Whether a mount config enables read-only; forms send booleans or strings
+/// 挂载配置是否启用只读，表单值可能是布尔或字符串
+pub fn is_read_only(config: &Value) -> bool {
+    match config.get(READ_ONLY_KEY) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => matches!(s.trim(), "true" | "1"),
+        Some(Value::Number(n)) => n.as_i64() == Some(1),
+        _ => false,
+    }
+}
+
+/// Maintenance settings / 维护模式设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    /// Shown to users, empty for the default message / 向用户显示的消息，为空时使用默认消息
+    pub message: String,
+}
+
+impl MaintenanceConfig {
+    /// Error message while enabled / 启用时的错误消息
+    pub fn error_message(&self) -> &str {
+        match self.message.trim() {
+            "" => MAINTENANCE_MESSAGE,
+            message => message,
+        }
+    }
+}
+
+/// Maintenance switch shared by the drivers and the middleware / 驱动与中间件共享的维护开关
+pub struct MaintenanceMode {
+    enabled: AtomicBool,
+    config: RwLock<MaintenanceConfig>,
+}
+
+impl MaintenanceMode {
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            config: RwLock::new(MaintenanceConfig::default()),
+        }
+    }
+
+    /// Load settings from database / 从数据库加载设置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let value: Option<(String,)> = sqlx::query_as("SELECT value FROM site_settings WHERE key = ?")
+            .bind(MAINTENANCE_KEY)
+            .fetch_optional(db)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Some((json,)) = value {
+            self.set(serde_json::from_str(&json).map_err(|e| e.to_string())?);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self) -> MaintenanceConfig {
+        self.config.read().clone()
+    }
+
+    pub fn set(&self, config: MaintenanceConfig) {
+        self.enabled.store(config.enabled, Ordering::SeqCst);
+        *self.config.write() = config;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for MaintenanceMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a request changes files / 请求是否会修改文件
+fn is_mutating(method: &Method, path: &str) -> bool {
+    if path == "/dav" || path.starts_with("/dav/") {
+        return MUTATING_DAV_METHODS.contains(&method.as_str());
+    }
+    *method != Method::GET && MUTATING_ENDPOINTS.contains(&path.trim_end_matches('/'))
+}
+
+/// Reject mutating requests during maintenance / 维护期间拒绝修改类请求
+pub async fn maintenance_middleware(
+    State(maintenance): State<Arc<MaintenanceMode>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if maintenance.is_enabled() && is_mutating(request.method(), request.uri().path()) {
+        let config = maintenance.get();
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "code": 503,
+                "message": config.error_message(),
+                "error_code": "maintenance_mode"
+            })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Driver wrapper refusing writes on read-only mounts and during maintenance
+/// 只读挂载与维护期间拒绝写操作的驱动包装
+pub struct ReadOnlyDriver {
+    inner: Box<dyn StorageDriver>,
+    read_only: Arc<AtomicBool>,
+    maintenance: Arc<MaintenanceMode>,
+}
+
+impl ReadOnlyDriver {
+    pub fn new(inner: Box<dyn StorageDriver>, read_only: Arc<AtomicBool>, maintenance: Arc<MaintenanceMode>) -> Self {
+        Self { inner, read_only, maintenance }
+    }
+
+    fn check_write(&self) -> Result<()> {
+        if self.maintenance.is_enabled() {
+            return Err(anyhow!("{}", self.maintenance.get().error_message()));
+        }
+        if self.read_only.load(Ordering::SeqCst) {
+            return Err(anyhow!(READ_ONLY_MESSAGE));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageDriver for ReadOnlyDriver {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn version(&self) -> &str {
+        self.inner.version()
+    }
+
+    fn capabilities(&self) -> Capability {
+        self.inner.capabilities()
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        self.inner.list(path).await
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.open_reader(path, range).await
+    }
+
+    async fn open_writer(
+        &self,
+        path: &str,
+        size_hint: Option<u64>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.check_write()?;
+        self.inner.open_writer(path, size_hint, progress).await
+    }
+
+    async fn open_resume_writer(
+        &self,
+        path: &str,
+        offset: u64,
+        size_hint: Option<u64>,
+    ) -> Result<Option<Box<dyn AsyncWrite + Unpin + Send>>> {
+        self.check_write()?;
+        self.inner.open_resume_writer(path, offset, size_hint).await
+    }
+
+    async fn put(&self, path: &str, data: bytes::Bytes, progress: Option<ProgressCallback>) -> Result<()> {
+        self.check_write()?;
+        self.inner.put(path, data, progress).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.check_write()?;
+        self.inner.delete(path).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.check_write()?;
+        self.inner.create_dir(path).await
+    }
+
+    async fn rename(&self, old_path: &str, new_name: &str) -> Result<()> {
+        self.check_write()?;
+        self.inner.rename(old_path, new_name).await
+    }
+
+    async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.check_write()?;
+        self.inner.move_item(old_path, new_path).await
+    }
+
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.check_write()?;
+        self.inner.copy_item(old_path, new_path).await
+    }
+
+    fn hash_types(&self) -> Vec<HashType> {
+        self.inner.hash_types()
+    }
+
+    async fn get_hash(&self, path: &str, hash_type: HashType) -> Result<Option<String>> {
+        self.inner.get_hash(path, hash_type).await
+    }
+
+    async fn account_key(&self) -> Option<String> {
+        self.inner.account_key().await
+    }
+
+    fn account_path(&self, path: &str) -> Option<String> {
+        self.inner.account_path(path)
+    }
+
+    async fn transfer_within_account(&self, src_path: &str, dst_account_path: &str, copy: bool) -> Result<bool> {
+        // 目标可能是同账号的其他挂载，源为只读时一律交给常规复制/移动判断
+        self.check_write()?;
+        self.inner.transfer_within_account(src_path, dst_account_path, copy).await
+    }
+
+    async fn get_direct_link(&self, path: &str) -> Result<Option<String>> {
+        self.inner.get_direct_link(path).await
+    }
+
+    fn get_local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.get_local_path(path)
+    }
+
+    fn is_local(&self) -> bool {
+        self.inner.is_local()
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        self.inner.get_space_info().await
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        self.inner.show_space_in_frontend()
+    }
+
+    async fn abort_stale_uploads(&self, older_than: std::time::Duration) -> Result<usize> {
+        self.inner.abort_stale_uploads(older_than).await
+    }
+
+    fn get_updated_config(&self) -> Option<Value> {
+        self.inner.get_updated_config()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_and_maintenance() {
+        assert!(is_read_only(&json!({"read_only": true})));
+        assert!(is_read_only(&json!({"read_only": "true"})));
+        assert!(!is_read_only(&json!({"read_only": "false"})));
+        assert!(!is_read_only(&json!({})));
+
+        assert!(is_mutating(&Method::POST, "/api/fs/remove"));
+        assert!(is_mutating(&Method::POST, "/api/fs/upload"));
+        assert!(!is_mutating(&Method::POST, "/api/fs/upload/status"));
+        assert!(!is_mutating(&Method::POST, "/api/fs/list"));
+        assert!(!is_mutating(&Method::POST, "/api/settings"));
+        assert!(is_mutating(&Method::from_bytes(b"MKCOL").unwrap(), "/dav/a"));
+        assert!(!is_mutating(&Method::from_bytes(b"PROPFIND").unwrap(), "/dav/a"));
+
+        let config = MaintenanceConfig { enabled: true, message: " ".to_string() };
+        assert_eq!(config.error_message(), MAINTENANCE_MESSAGE);
+    }
+}
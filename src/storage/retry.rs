@@ -0,0 +1,161 @@
+//! HTTP retry for driver clients / 驱动 HTTP 请求重试
+//!
+//! Cloud providers answer bursts with `429` and occasionally fail with `5xx`; these are retried
+//! with exponential backoff (plus jitter) instead of surfacing as hard failures. A `Retry-After`
+//! header from the provider takes precedence over the computed delay.
+//! 网盘在突发请求时返回 `429`、偶尔返回 `5xx`，这些请求按指数退避（带抖动）重试，
+//! 服务端返回的 `Retry-After` 优先于计算出的等待时间。
+//!
+//! Drivers opt in by calling `.send_retry()` instead of `.send()` on a `RequestBuilder`.
+//! Requests with a streaming body cannot be replayed and are sent once.
+//! 驱动将 `.send()` 替换为 `.send_retry()` 即可；流式请求体无法重放，只发送一次。
+
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+
+/// Retry settings / 重试参数
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one / 总尝试次数（含首次）
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled each time / 首次重试前的等待，之后逐次翻倍
+    pub base_delay: Duration,
+    /// Upper bound of the computed backoff / 退避时间上限
+    pub max_delay: Duration,
+    /// Longest `Retry-After` worth waiting for; longer ones return the response as is
+    /// 愿意等待的最长 `Retry-After`，超过时直接返回响应
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            max_retry_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `retry` (1-based), with up to 25% jitter / 第 `retry` 次重试前的退避时间（含最多25%抖动）
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(1u32 << retry.saturating_sub(1).min(16));
+        let delay = exp.min(self.max_delay);
+        let jitter = rand::thread_rng().gen_range(0.0..=0.25);
+        delay.mul_f64(1.0 + jitter).min(self.max_delay)
+    }
+
+    /// Send `req`, retrying transient failures / 发送请求并重试临时性失败
+    pub async fn send(&self, req: RequestBuilder) -> reqwest::Result<Response> {
+        let (client, request) = req.build_split();
+        let request = request?;
+        let method = request.method().clone();
+        let url = request.url().clone();
+        let mut attempt = 1;
+        loop {
+            // 流式请求体无法克隆时只发送一次
+            let Some(next) = request.try_clone().filter(|_| attempt < self.max_attempts) else {
+                return client.execute(request).await;
+            };
+            let delay = match client.execute(next).await {
+                Ok(resp) if is_retryable_status(resp.status()) => {
+                    let delay = match retry_after(resp.headers()) {
+                        Some(d) if d > self.max_retry_after => return Ok(resp),
+                        Some(d) => d,
+                        None => self.backoff(attempt),
+                    };
+                    tracing::warn!("{} {} returned {}, retry {} in {:?}", method, url, resp.status(), attempt, delay);
+                    delay
+                }
+                Ok(resp) => return Ok(resp),
+                Err(e) if is_retryable_error(&e, &method) => {
+                    let delay = self.backoff(attempt);
+                    tracing::warn!("{} {} failed: {}, retry {} in {:?}", method, url, e, attempt, delay);
+                    delay
+                }
+                Err(e) => return Err(e),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// `429` and gateway/server errors that usually go away / 通常会自行恢复的状态码
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::REQUEST_TIMEOUT
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Connection failures are always safe to retry; timeouts only for idempotent methods,
+/// since the provider may already have applied a timed-out POST.
+/// 连接失败总可重试；超时仅对幂等方法重试，超时的 POST 可能已被服务端执行。
+fn is_retryable_error(e: &reqwest::Error, method: &Method) -> bool {
+    e.is_connect()
+        || (e.is_timeout()
+            && matches!(*method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS))
+}
+
+/// Parse `Retry-After` as delay seconds or an HTTP date / 解析 `Retry-After`（秒数或HTTP日期）
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    parse_retry_after(headers.get(RETRY_AFTER)?.to_str().ok()?, Utc::now())
+}
+
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// `.send_retry()` for `RequestBuilder` with the default policy / 使用默认策略的 `.send_retry()`
+#[async_trait]
+pub trait SendRetry {
+    async fn send_retry(self) -> reqwest::Result<Response>;
+}
+
+#[async_trait]
+impl SendRetry for RequestBuilder {
+    async fn send_retry(self) -> reqwest::Result<Response> {
+        RetryPolicy::default().send(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        let first = policy.backoff(1);
+        assert!(first >= Duration::from_millis(500) && first <= Duration::from_millis(625));
+        let third = policy.backoff(3);
+        assert!(third >= Duration::from_secs(2) && third <= Duration::from_millis(2500));
+        assert_eq!(policy.backoff(10), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT").unwrap().with_timezone(&Utc);
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
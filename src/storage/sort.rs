@@ -0,0 +1,222 @@
+//! Listing order and cursors / 列表排序与游标
+//!
+//! Every listing is sorted here, folders first, so the listing cache can keep a sorted view per
+//! order and a page is just a slice of it. Ties are broken by name, which makes the order total:
+//! a cursor remembers the last entry of a page and the next page starts right after it, so
+//! entries added or removed in between never shift or repeat the rest of a large directory.
+//! 所有列表都在此排序（目录在前），列表缓存可按排序方式保存排好序的视图，分页只是从中取一段。
+//! 排序键相同时按名称区分，使顺序全序：游标记录上一页最后一项，下一页从其后开始，
+//! 期间新增或删除的条目不会使大目录的后续内容错位或重复。
+//!
+//! Names sort naturally (`file2` before `file10`); the pinyin order transliterates Chinese
+//! characters first, so `北京` sits among the B's instead of after every Latin name.
+//! 名称按自然顺序排序（`file2` 在 `file10` 之前）；拼音排序先将汉字转写为拼音，
+//! 使 `北京` 排在 B 开头的名称之间，而不是排在所有拉丁字母名称之后。
+
+use std::cmp::Ordering;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use super::Entry;
+
+/// Field a listing is sorted by / 排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortField {
+    /// Natural name order / 名称自然排序
+    Name,
+    /// Natural order of the pinyin of the name / 名称拼音的自然排序
+    Pinyin,
+    Modified,
+    Size,
+}
+
+/// Listing order / 列表排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ListSort {
+    pub field: SortField,
+    pub desc: bool,
+}
+
+impl Default for ListSort {
+    fn default() -> Self {
+        Self { field: SortField::Name, desc: false }
+    }
+}
+
+impl ListSort {
+    /// From the `sort_by` / `sort_order` request fields, unknown values fall back to name ascending
+    /// 从请求的 `sort_by` / `sort_order` 解析，未知值按名称升序
+    ///
+    /// `sort_by`: `name` or `natural`, `pinyin`, `modified`, `size`
+    pub fn parse(sort_by: Option<&str>, sort_order: Option<&str>) -> Self {
+        let field = match sort_by.unwrap_or("name") {
+            "pinyin" => SortField::Pinyin,
+            "modified" => SortField::Modified,
+            "size" => SortField::Size,
+            _ => SortField::Name,
+        };
+        Self { field, desc: sort_order == Some("desc") }
+    }
+
+    /// Compare two entries, folders always first / 比较两个条目（目录始终在前）
+    pub fn compare(&self, a: &Entry, b: &Entry) -> Ordering {
+        b.is_dir.cmp(&a.is_dir).then_with(|| {
+            let cmp = match self.field {
+                SortField::Name => Ordering::Equal,
+                SortField::Pinyin => natord::compare_ignore_case(&pinyin_key(&a.name), &pinyin_key(&b.name)),
+                SortField::Modified => a.modified.as_deref().unwrap_or("").cmp(b.modified.as_deref().unwrap_or("")),
+                SortField::Size => a.size.cmp(&b.size),
+            }
+            // 自然排序（让 "2" 排在 "10" 前面），最后按原始名称区分大小写不同的同名项
+            .then_with(|| natord::compare_ignore_case(&a.name, &b.name))
+            .then_with(|| a.name.cmp(&b.name));
+            if self.desc { cmp.reverse() } else { cmp }
+        })
+    }
+
+    /// Sort entries in place / 原地排序
+    pub fn sort(&self, entries: &mut [Entry]) {
+        if self.field != SortField::Pinyin {
+            entries.sort_by(|a, b| self.compare(a, b));
+            return;
+        }
+        // 转写较慢，每个名称只转写一次
+        let keys: Vec<String> = entries.iter().map(|e| pinyin_key(&e.name)).collect();
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by(|&i, &j| {
+            let (a, b) = (&entries[i], &entries[j]);
+            b.is_dir.cmp(&a.is_dir).then_with(|| {
+                let cmp = natord::compare_ignore_case(&keys[i], &keys[j])
+                    .then_with(|| natord::compare_ignore_case(&a.name, &b.name))
+                    .then_with(|| a.name.cmp(&b.name));
+                if self.desc { cmp.reverse() } else { cmp }
+            })
+        });
+        let sorted: Vec<Entry> = order.iter().map(|&i| entries[i].clone()).collect();
+        entries.clone_from_slice(&sorted);
+    }
+}
+
+/// Sort key of a name in pinyin order: Chinese characters become toneless pinyin
+/// 拼音排序键：汉字转写为不带声调的拼音
+pub fn pinyin_key(name: &str) -> String {
+    deunicode::deunicode(name).to_lowercase()
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorData {
+    sort: ListSort,
+    name: String,
+    is_dir: bool,
+    size: u64,
+    #[serde(default)]
+    modified: Option<String>,
+}
+
+/// Position after an entry of a sorted listing / 排序列表中某一项之后的位置
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    sort: ListSort,
+    last: Entry,
+}
+
+impl Cursor {
+    /// Cursor pointing right after `entry` / 指向 `entry` 之后的游标
+    pub fn after(sort: ListSort, entry: &Entry) -> Self {
+        Self { sort, last: entry.clone() }
+    }
+
+    /// Opaque string handed to clients / 返回给客户端的不透明字符串
+    pub fn encode(&self) -> String {
+        let data = CursorData {
+            sort: self.sort,
+            name: self.last.name.clone(),
+            is_dir: self.last.is_dir,
+            size: self.last.size,
+            modified: self.last.modified.clone(),
+        };
+        let json = serde_json::to_vec(&data).unwrap_or_default();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Parse a cursor made for `sort`, `None` when invalid or made for another order
+    /// 解析为 `sort` 生成的游标，无效或排序方式不同时返回 `None`
+    pub fn decode(value: &str, sort: ListSort) -> Option<Self> {
+        let json = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(value).ok()?;
+        let data: CursorData = serde_json::from_slice(&json).ok()?;
+        if data.sort != sort {
+            return None;
+        }
+        Some(Self {
+            sort,
+            last: Entry { name: data.name, path: String::new(), is_dir: data.is_dir, size: data.size, modified: data.modified },
+        })
+    }
+
+    /// Index of the first entry after the cursor in a listing sorted by the same order
+    /// 在同样排序的列表中，游标之后第一项的下标
+    pub fn position<T>(&self, sorted: &[T], entry: impl Fn(&T) -> &Entry) -> usize {
+        sorted.partition_point(|e| self.sort.compare(entry(e), &self.last) != Ordering::Greater)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, is_dir: bool, size: u64) -> Entry {
+        Entry { name: name.to_string(), path: format!("/{}", name), is_dir, size, modified: None }
+    }
+
+    #[test]
+    fn test_sort_order() {
+        let mut entries = vec![
+            entry("file10", false, 1),
+            entry("b", true, 0),
+            entry("file2", false, 3),
+            entry("File2", false, 2),
+            entry("a", true, 0),
+        ];
+        ListSort::default().sort(&mut entries);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "File2", "file2", "file10"]);
+
+        ListSort::parse(Some("size"), Some("desc")).sort(&mut entries);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["b", "a", "file2", "File2", "file10"]);
+    }
+
+    #[test]
+    fn test_pinyin_order() {
+        let mut entries: Vec<Entry> = ["张三", "Zoo", "北京", "apple", "目录2", "目录10"]
+            .iter()
+            .map(|n| entry(n, false, 0))
+            .collect();
+        let sort = ListSort::parse(Some("pinyin"), None);
+        sort.sort(&mut entries);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["apple", "北京", "目录2", "目录10", "张三", "Zoo"]);
+        // 排序结果与逐个比较一致（游标定位依赖这一点）
+        assert!(entries.windows(2).all(|w| sort.compare(&w[0], &w[1]) == Ordering::Less));
+    }
+
+    #[test]
+    fn test_cursor_is_stable() {
+        let sort = ListSort::default();
+        let mut entries: Vec<Entry> = (0..10).map(|i| entry(&format!("f{}", i), false, 0)).collect();
+        sort.sort(&mut entries);
+        let cursor = Cursor::after(sort, &entries[3]).encode();
+
+        // 翻页前删除了游标所在项并在前面插入了新项，下一页仍从 f4 开始
+        entries.remove(3);
+        entries.push(entry("f0a", false, 0));
+        sort.sort(&mut entries);
+        let cursor = Cursor::decode(&cursor, sort).unwrap();
+        assert_eq!(entries[cursor.position(&entries, |e| e)].name, "f4");
+
+        // 换了排序方式的游标无效
+        assert!(Cursor::decode(&cursor.encode(), ListSort::parse(Some("size"), None)).is_none());
+        assert!(Cursor::decode("not a cursor", sort).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+//! Driver operation spans / 驱动操作的日志上下文
+//!
+//! Every driver instance is wrapped in `TracedDriver`, which runs each operation inside a
+//! `driver` span carrying the mount id, driver type, operation and path. Log lines emitted by
+//! the driver itself (and by the HTTP helpers it calls) inherit these fields, so operators can
+//! filter by mount or correlate a failure with the request that caused it. Readers and writers
+//! returned by the driver keep the span, so upload progress logged while polling is covered too.
+//! 每个驱动实例都包装为 `TracedDriver`，每个操作在带有挂载 ID、驱动类型、操作名与路径的 `driver` span 中执行。
+//! 驱动自身（及其调用的 HTTP 工具）输出的日志都会带上这些字段，便于按挂载过滤或关联出错的请求。
+//! 驱动返回的读写流同样保留该 span，轮询时记录的上传进度也带有这些字段。
+
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tracing::{Instrument, Span};
+
+use super::{Capability, Entry, HashType, ProgressCallback, SpaceInfo, StorageDriver};
+
+/// Driver wrapper attaching mount/driver/path fields to its logs / 为驱动日志附加挂载、驱动与路径字段的包装
+pub struct TracedDriver {
+    inner: Box<dyn StorageDriver>,
+    mount: String,
+    driver_type: String,
+}
+
+impl TracedDriver {
+    pub fn new(inner: Box<dyn StorageDriver>, mount: &str, driver_type: &str) -> Self {
+        Self { inner, mount: mount.to_string(), driver_type: driver_type.to_string() }
+    }
+
+    fn span(&self, op: &'static str, path: &str) -> Span {
+        tracing::info_span!("driver", mount = %self.mount, driver = %self.driver_type, op, path)
+    }
+
+    /// Run `fut` inside the operation span, logging failures at debug level
+    /// 在操作 span 中执行 `fut`，失败时以 debug 级别记录
+    async fn traced<T>(&self, op: &'static str, path: &str, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        async move {
+            let result = fut.await;
+            if let Err(e) = &result {
+                tracing::debug!(error = %e, "driver operation failed");
+            }
+            result
+        }
+        .instrument(self.span(op, path))
+        .await
+    }
+}
+
+/// Reader/writer that enters its span while polled / 轮询时进入 span 的读写流
+struct TracedStream<S> {
+    inner: S,
+    span: Span,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for TracedStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let _enter = this.span.enter();
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for TracedStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let _enter = this.span.enter();
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let _enter = this.span.enter();
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let _enter = this.span.enter();
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[async_trait]
+impl StorageDriver for TracedDriver {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn version(&self) -> &str {
+        self.inner.version()
+    }
+
+    fn capabilities(&self) -> Capability {
+        self.inner.capabilities()
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<Entry>> {
+        self.traced("list", path, self.inner.list(path)).await
+    }
+
+    async fn open_reader(
+        &self,
+        path: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self.traced("open_reader", path, self.inner.open_reader(path, range)).await?;
+        Ok(Box::new(TracedStream { inner: reader, span: self.span("read", path) }))
+    }
+
+    async fn open_writer(
+        &self,
+        path: &str,
+        size_hint: Option<u64>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        let writer = self.traced("open_writer", path, self.inner.open_writer(path, size_hint, progress)).await?;
+        Ok(Box::new(TracedStream { inner: writer, span: self.span("write", path) }))
+    }
+
+    async fn open_resume_writer(
+        &self,
+        path: &str,
+        offset: u64,
+        size_hint: Option<u64>,
+    ) -> Result<Option<Box<dyn AsyncWrite + Unpin + Send>>> {
+        let writer = self.traced("open_resume_writer", path, self.inner.open_resume_writer(path, offset, size_hint)).await?;
+        Ok(writer.map(|w| Box::new(TracedStream { inner: w, span: self.span("write", path) }) as Box<dyn AsyncWrite + Unpin + Send>))
+    }
+
+    async fn put(&self, path: &str, data: bytes::Bytes, progress: Option<ProgressCallback>) -> Result<()> {
+        self.traced("put", path, self.inner.put(path, data, progress)).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.traced("delete", path, self.inner.delete(path)).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.traced("create_dir", path, self.inner.create_dir(path)).await
+    }
+
+    async fn rename(&self, old_path: &str, new_name: &str) -> Result<()> {
+        self.traced("rename", old_path, self.inner.rename(old_path, new_name)).await
+    }
+
+    async fn move_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.traced("move", old_path, self.inner.move_item(old_path, new_path)).await
+    }
+
+    async fn copy_item(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.traced("copy", old_path, self.inner.copy_item(old_path, new_path)).await
+    }
+
+    fn hash_types(&self) -> Vec<HashType> {
+        self.inner.hash_types()
+    }
+
+    async fn get_hash(&self, path: &str, hash_type: HashType) -> Result<Option<String>> {
+        self.traced("get_hash", path, self.inner.get_hash(path, hash_type)).await
+    }
+
+    async fn account_key(&self) -> Option<String> {
+        self.inner.account_key().instrument(self.span("account_key", "")).await
+    }
+
+    fn account_path(&self, path: &str) -> Option<String> {
+        self.inner.account_path(path)
+    }
+
+    async fn transfer_within_account(&self, src_path: &str, dst_account_path: &str, copy: bool) -> Result<bool> {
+        self.traced("transfer", src_path, self.inner.transfer_within_account(src_path, dst_account_path, copy)).await
+    }
+
+    async fn get_direct_link(&self, path: &str) -> Result<Option<String>> {
+        self.traced("direct_link", path, self.inner.get_direct_link(path)).await
+    }
+
+    fn get_local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.get_local_path(path)
+    }
+
+    fn is_local(&self) -> bool {
+        self.inner.is_local()
+    }
+
+    async fn get_space_info(&self) -> Result<Option<SpaceInfo>> {
+        self.traced("space_info", "", self.inner.get_space_info()).await
+    }
+
+    fn show_space_in_frontend(&self) -> bool {
+        self.inner.show_space_in_frontend()
+    }
+
+    async fn abort_stale_uploads(&self, older_than: std::time::Duration) -> Result<usize> {
+        self.traced("abort_stale_uploads", "", self.inner.abort_stale_uploads(older_than)).await
+    }
+
+    fn get_updated_config(&self) -> Option<Value> {
+        self.inner.get_updated_config()
+    }
+}
@@ -0,0 +1,140 @@
+//! Cleanup of interrupted uploads / 中断上传的清理
+//!
+//! Chunked uploads that never finish leave parts in the local temp dir and unfinished multipart
+//! sessions on the provider. A scheduled job purges temp files older than the configured age and
+//! asks every driver to abort its stale sessions via `abort_stale_uploads`. Providers that can't
+//! list their sessions (OneDrive) record them in an [`UploadSessions`] registry instead.
+//! 未完成的分片上传会在本地临时目录留下分片，并在服务商处留下未完成的分片上传会话。
+//! 定时任务清理超过设定时长的临时文件，并通过 `abort_stale_uploads` 让各驱动中止过期会话。
+//! 无法列出会话的服务商（OneDrive）改用 [`UploadSessions`] 记录会话。
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Settings key of the cleanup config / 清理配置的设置键
+pub const UPLOAD_CLEANUP_KEY: &str = "upload_cleanup";
+
+/// Where chunked uploads keep their parts / 分片上传保存分片的临时目录
+pub const TEMP_DIR: &str = "data/temps";
+
+/// Upload cleanup settings / 上传清理设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadCleanupConfig {
+    /// Temp files and sessions untouched for this many hours are removed / 超过此小时数未更新的临时文件与会话会被清理
+    pub max_age_hours: u32,
+}
+
+impl Default for UploadCleanupConfig {
+    fn default() -> Self {
+        Self { max_age_hours: 24 }
+    }
+}
+
+impl UploadCleanupConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_age_hours == 0 {
+            return Err("max_age_hours must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_hours as u64 * 3600)
+    }
+}
+
+/// Upload sessions opened by a driver and not finished yet / 驱动已创建但尚未完成的上传会话
+#[derive(Clone, Default)]
+pub struct UploadSessions(Arc<Mutex<HashMap<String, Instant>>>);
+
+impl UploadSessions {
+    /// Record a new session by its upload URL / 按上传地址记录新会话
+    pub fn register(&self, url: &str) {
+        self.0.lock().insert(url.to_string(), Instant::now());
+    }
+
+    /// Forget a session that completed / 移除已完成的会话
+    pub fn finish(&self, url: &str) {
+        self.0.lock().remove(url);
+    }
+
+    /// Remove and return sessions opened more than `older_than` ago / 取出创建时间超过 `older_than` 的会话
+    pub fn take_stale(&self, older_than: Duration) -> Vec<String> {
+        let mut sessions = self.0.lock();
+        let stale: Vec<String> = sessions.iter()
+            .filter(|(_, opened)| opened.elapsed() >= older_than)
+            .map(|(url, _)| url.clone())
+            .collect();
+        for url in &stale {
+            sessions.remove(url);
+        }
+        stale
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Delete files in `dir` last modified more than `older_than` ago, returns how many were removed
+/// 删除 `dir` 中最后修改时间早于 `older_than` 的文件，返回删除数量
+pub async fn purge_temp_files(dir: &Path, older_than: Duration) -> std::io::Result<usize> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let cutoff = SystemTime::now().checked_sub(older_than).unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut removed = 0;
+    while let Some(entry) = entries.next_entry().await? {
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        if !metadata.is_file() || metadata.modified().is_ok_and(|m| m > cutoff) {
+            continue;
+        }
+        match tokio::fs::remove_file(entry.path()).await {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!(file = %entry.path().display(), error = %e, "Failed to remove stale upload part"),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_stale_sessions() {
+        let sessions = UploadSessions::default();
+        sessions.register("https://upload/a");
+        sessions.register("https://upload/b");
+        sessions.finish("https://upload/b");
+        assert!(sessions.take_stale(Duration::from_secs(3600)).is_empty());
+        assert_eq!(sessions.take_stale(Duration::ZERO), ["https://upload/a"]);
+        assert!(sessions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_purge_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("task_a.bin.part0"), b"part").unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+
+        assert_eq!(purge_temp_files(dir.path(), Duration::from_secs(3600)).await.unwrap(), 0);
+        assert_eq!(purge_temp_files(dir.path(), Duration::ZERO).await.unwrap(), 1);
+        assert!(dir.path().join("nested").exists());
+        assert_eq!(purge_temp_files(&dir.path().join("missing"), Duration::ZERO).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,139 @@
+//! Storage usage history and space alerts / 存储用量历史与空间告警
+//!
+//! A scheduled job samples `get_space_info` of every mount and keeps the samples for a few
+//! weeks. The admin report shows the history with a growth trend, and an alert is sent once when
+//! a mount's usage rises past the configured percentage (not again until it drops back below).
+//! 定时任务采集所有挂载点的 `get_space_info` 并保留一段时间。管理报表展示历史与增长趋势，
+//! 挂载点使用率升过设定百分比时发送一次告警（回落到阈值以下后才会再次告警）。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::SpaceInfo;
+
+/// Settings key of the alert config / 告警配置的设置键
+pub const STORAGE_ALERT_KEY: &str = "storage_alert";
+
+/// Space alert settings / 空间告警设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageAlertConfig {
+    /// Alert when usage reaches this percent, 0 disables alerts / 使用率达到此百分比时告警，0 表示关闭
+    pub threshold_percent: u8,
+    /// Days of samples to keep / 采样保留天数
+    pub retention_days: u32,
+    /// Also email the administrators / 同时发送邮件给管理员
+    pub email_admins: bool,
+}
+
+impl Default for StorageAlertConfig {
+    fn default() -> Self {
+        Self {
+            threshold_percent: 90,
+            retention_days: 30,
+            email_admins: true,
+        }
+    }
+}
+
+impl StorageAlertConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threshold_percent > 100 {
+            return Err("threshold_percent must be 0-100".to_string());
+        }
+        if self.retention_days == 0 {
+            return Err("retention_days must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Used percent of a mount, None when the total is unknown / 使用率百分比，总空间未知时为 None
+pub fn usage_percent(space: &SpaceInfo) -> Option<f64> {
+    (space.total > 0).then(|| space.used as f64 * 100.0 / space.total as f64)
+}
+
+/// Whether usage just rose past the threshold / 使用率是否刚刚超过阈值
+pub fn crossed_threshold(previous: Option<f64>, current: f64, threshold_percent: u8) -> bool {
+    let threshold = threshold_percent as f64;
+    threshold_percent > 0 && current >= threshold && previous.is_none_or(|p| p < threshold)
+}
+
+/// One recorded sample / 一次采样记录
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageSample {
+    pub used: u64,
+    pub total: u64,
+    pub free: u64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Growth of used space over the samples / 采样期间已用空间的增长趋势
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageTrend {
+    /// Least-squares growth, negative when space is freed / 最小二乘拟合的增长量，释放空间时为负
+    pub bytes_per_day: i64,
+    /// Days until the free space runs out at this rate / 按此速度剩余空间可用天数
+    pub days_until_full: Option<f64>,
+}
+
+/// Trend of samples in time order; needs two samples at different times
+/// 按时间排序的采样的趋势，至少需要两个不同时间的采样
+pub fn usage_trend(samples: &[UsageSample]) -> Option<UsageTrend> {
+    let first = samples.first()?;
+    let last = samples.last()?;
+    let points: Vec<(f64, f64)> = samples.iter()
+        .map(|s| ((s.recorded_at - first.recorded_at).num_seconds() as f64 / 86400.0, s.used as f64))
+        .collect();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let var_x: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if var_x <= 0.0 {
+        return None;
+    }
+    let cov: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let per_day = cov / var_x;
+    Some(UsageTrend {
+        bytes_per_day: per_day.round() as i64,
+        days_until_full: (per_day >= 1.0).then(|| last.free as f64 / per_day),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(day: i64, used: u64) -> UsageSample {
+        UsageSample {
+            used,
+            total: 1000,
+            free: 1000 - used,
+            recorded_at: DateTime::from_timestamp(day * 86400, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_crossed_threshold() {
+        assert!(crossed_threshold(None, 95.0, 90));
+        assert!(crossed_threshold(Some(89.9), 90.0, 90));
+        assert!(!crossed_threshold(Some(91.0), 95.0, 90));
+        assert!(!crossed_threshold(Some(50.0), 60.0, 90));
+        assert!(!crossed_threshold(None, 100.0, 0));
+        assert_eq!(usage_percent(&SpaceInfo { used: 1, total: 4, free: 3 }), Some(25.0));
+        assert_eq!(usage_percent(&SpaceInfo { used: 1, total: 0, free: 0 }), None);
+    }
+
+    #[test]
+    fn test_usage_trend() {
+        let samples = [sample(0, 100), sample(1, 200), sample(2, 300)];
+        let trend = usage_trend(&samples).unwrap();
+        assert_eq!(trend.bytes_per_day, 100);
+        assert_eq!(trend.days_until_full, Some(7.0));
+
+        let shrinking = [sample(0, 300), sample(2, 100)];
+        assert_eq!(usage_trend(&shrinking).unwrap().days_until_full, None);
+        assert_eq!(usage_trend(&samples[..1]), None);
+        assert_eq!(usage_trend(&[]), None);
+    }
+}
@@ -0,0 +1,519 @@
+//! STRM export for media servers / 媒体服务器 STRM 导出
+//!
+//! This module handles:
+//! - Export config cache (source folders, target, extensions) / 导出配置缓存
+//! - `.strm` sync into a local directory or a storage mount / 将 `.strm` 同步到本地目录或存储挂载
+//! - Last run status per export / 每个导出的最近运行结果
+//!
+//! Each video `<source>/a/b.mkv` becomes `<target>/<source name>/a/b.strm` containing one URL.
+//! Jellyfin, Emby and Plex play the URL, so the cloud content never has to be mounted.
+//! The target is owned by the export: `.strm` files no longer produced are removed.
+//! 每个视频生成一个只含 URL 的 `.strm` 文件；目标目录归导出所有，不再生成的 `.strm` 会被删除。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::storage::DriverBox;
+
+/// Video extensions used when an export lists none / 未配置时使用的视频扩展名
+pub const DEFAULT_VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v", "ts", "m2ts", "mpg", "mpeg", "rmvb", "iso", "3gp",
+];
+/// Errors kept in the run status / 运行结果中保留的错误数
+const MAX_STATUS_ERRORS: usize = 50;
+
+/// Where `.strm` files are written / `.strm` 文件的输出位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StrmTarget {
+    /// Directory on the server running YaoList / 服务器本地目录
+    Local { dir: String },
+    /// Virtual path inside a storage mount / 存储挂载内的虚拟路径
+    Mount { path: String },
+}
+
+/// Export config / 导出配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrmExport {
+    pub id: String,
+    pub name: String,
+    /// Virtual folders to walk / 要遍历的虚拟目录
+    pub source_paths: Vec<String>,
+    pub target: StrmTarget,
+    /// Video extensions, empty = defaults / 视频扩展名，为空使用默认
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// External URL of YaoList, falls back to the download domain / 外部访问地址，为空时使用下载域名
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl StrmExport {
+    /// Whether a file name has one of the export's video extensions / 是否为视频文件
+    pub fn is_video(&self, name: &str) -> bool {
+        let ext = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+            _ => return false,
+        };
+        if self.extensions.is_empty() {
+            DEFAULT_VIDEO_EXTENSIONS.contains(&ext.as_str())
+        } else {
+            self.extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+        }
+    }
+}
+
+/// `.strm` path relative to the target for a video under `source_root` / 计算视频对应的 `.strm` 相对路径
+///
+/// Prefixed with the source folder name so several sources don't collide; `None` for paths outside
+/// the source or with `.`/`..` segments.
+/// 以源目录名作为第一级目录，避免多个源冲突；路径不在源目录下或包含 `.`/`..` 时返回 `None`。
+pub fn strm_relative_path(source_root: &str, file_path: &str) -> Option<String> {
+    let root = source_root.trim_end_matches('/');
+    let rest = file_path.strip_prefix(root)?.strip_prefix('/')?;
+    let root_name = root.rsplit('/').next().unwrap_or("");
+    let rel = if root_name.is_empty() { rest.to_string() } else { format!("{}/{}", root_name, rest) };
+    if rel.split('/').any(|s| s.is_empty() || s == "." || s == "..") {
+        return None;
+    }
+    let stem = match rel.rsplit_once('.') {
+        Some((stem, _)) if !stem.ends_with('/') => stem,
+        _ => rel.as_str(),
+    };
+    Some(format!("{}.strm", stem))
+}
+
+/// Result of one export run / 单次导出结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StrmExportStatus {
+    pub running: bool,
+    pub success: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// `.strm` files created or updated / 新建或更新的文件数
+    pub written: u64,
+    pub unchanged: u64,
+    /// Stale `.strm` files deleted / 删除的过期文件数
+    pub removed: u64,
+    pub errors: Vec<String>,
+}
+
+impl StrmExportStatus {
+    pub fn started() -> Self {
+        Self {
+            running: true,
+            started_at: Some(Utc::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(&mut self, message: String) {
+        if self.errors.len() < MAX_STATUS_ERRORS {
+            self.errors.push(message);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.running = false;
+        self.success = self.errors.is_empty();
+        self.finished_at = Some(Utc::now());
+    }
+}
+
+/// Destination of `.strm` files, paths are relative and `/`-separated / `.strm` 输出端（相对路径，以 `/` 分隔）
+#[async_trait]
+pub trait StrmSink: Send + Sync {
+    /// All `.strm` files currently under the target / 目标下现有的全部 `.strm` 文件
+    async fn existing(&self) -> Result<Vec<String>>;
+    async fn read(&self, rel: &str) -> Option<String>;
+    async fn write(&self, rel: &str, content: &str) -> Result<()>;
+    async fn remove(&self, rel: &str) -> Result<()>;
+}
+
+/// Write `files` (relative path → URL) into `sink`, deleting other `.strm` files when `prune` is set
+/// 将 `files` 写入输出端，`prune` 为真时删除多余的 `.strm` 文件
+///
+/// Callers pass `prune = false` when the walk was incomplete, so a driver hiccup never wipes the library.
+/// 遍历不完整时应传 `prune = false`，避免驱动短暂故障导致媒体库被清空。
+pub async fn sync_strm(sink: &dyn StrmSink, files: &BTreeMap<String, String>, prune: bool, status: &mut StrmExportStatus) {
+    let existing: HashSet<String> = match sink.existing().await {
+        Ok(list) => list.into_iter().collect(),
+        Err(e) => {
+            status.error(format!("List target failed: {}", e));
+            HashSet::new()
+        }
+    };
+
+    for (rel, content) in files {
+        if existing.contains(rel) && sink.read(rel).await.as_deref() == Some(content.as_str()) {
+            status.unchanged += 1;
+            continue;
+        }
+        match sink.write(rel, content).await {
+            Ok(()) => status.written += 1,
+            Err(e) => status.error(format!("Write {} failed: {}", rel, e)),
+        }
+    }
+
+    if !prune {
+        return;
+    }
+    let mut stale: Vec<&String> = existing.iter().filter(|rel| !files.contains_key(*rel)).collect();
+    stale.sort();
+    for rel in stale {
+        match sink.remove(rel).await {
+            Ok(()) => status.removed += 1,
+            Err(e) => status.error(format!("Remove {} failed: {}", rel, e)),
+        }
+    }
+}
+
+/// Local directory sink / 本地目录输出端
+pub struct LocalStrmSink {
+    root: PathBuf,
+}
+
+impl LocalStrmSink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn full_path(&self, rel: &str) -> PathBuf {
+        rel.split('/').fold(self.root.clone(), |p, s| p.join(s))
+    }
+}
+
+#[async_trait]
+impl StrmSink for LocalStrmSink {
+    async fn existing(&self) -> Result<Vec<String>> {
+        let mut found = Vec::new();
+        if !self.root.exists() {
+            return Ok(found);
+        }
+        let mut stack = vec![(self.root.clone(), String::new())];
+        while let Some((dir, prefix)) = stack.pop() {
+            let mut entries = tokio::fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let rel = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+                let file_type = entry.file_type().await?;
+                if file_type.is_dir() {
+                    stack.push((entry.path(), rel));
+                } else if file_type.is_file() && name.ends_with(".strm") {
+                    found.push(rel);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    async fn read(&self, rel: &str) -> Option<String> {
+        tokio::fs::read_to_string(self.full_path(rel)).await.ok()
+    }
+
+    async fn write(&self, rel: &str, content: &str) -> Result<()> {
+        let path = self.full_path(rel);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, content).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, rel: &str) -> Result<()> {
+        let path = self.full_path(rel);
+        tokio::fs::remove_file(&path).await?;
+        // Drop directories left empty / 清理变空的目录
+        let mut dir = path.parent().map(Path::to_path_buf);
+        while let Some(d) = dir {
+            if d == self.root || tokio::fs::remove_dir(&d).await.is_err() {
+                break;
+            }
+            dir = d.parent().map(Path::to_path_buf);
+        }
+        Ok(())
+    }
+}
+
+/// Storage driver sink (a folder inside a mount) / 存储驱动输出端（挂载内的目录）
+pub struct DriverStrmSink {
+    driver: DriverBox,
+    /// Driver-internal path of the target folder / 目标目录在驱动内的路径
+    root: String,
+}
+
+impl DriverStrmSink {
+    pub fn new(driver: DriverBox, root: &str) -> Self {
+        let root = root.trim_end_matches('/');
+        Self {
+            driver,
+            root: if root.is_empty() { "/".to_string() } else { root.to_string() },
+        }
+    }
+
+    fn full_path(&self, rel: &str) -> String {
+        format!("{}/{}", self.root.trim_end_matches('/'), rel)
+    }
+}
+
+#[async_trait]
+impl StrmSink for DriverStrmSink {
+    async fn existing(&self) -> Result<Vec<String>> {
+        let mut found = Vec::new();
+        let mut stack = vec![(self.root.clone(), String::new())];
+        while let Some((dir, prefix)) = stack.pop() {
+            let entries = match self.driver.list(&dir).await {
+                Ok(entries) => entries,
+                // Target not created yet / 目标目录尚未创建
+                Err(_) if prefix.is_empty() => return Ok(found),
+                Err(e) => return Err(e),
+            };
+            for entry in entries {
+                let rel = if prefix.is_empty() { entry.name.clone() } else { format!("{}/{}", prefix, entry.name) };
+                if entry.is_dir {
+                    stack.push((format!("{}/{}", dir.trim_end_matches('/'), entry.name), rel));
+                } else if entry.name.ends_with(".strm") {
+                    found.push(rel);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    async fn read(&self, rel: &str) -> Option<String> {
+        let mut reader = self.driver.open_reader(&self.full_path(rel), None).await.ok()?;
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).await.ok()?;
+        Some(buf)
+    }
+
+    async fn write(&self, rel: &str, content: &str) -> Result<()> {
+        // Create missing parents; errors for existing folders are expected / 逐级创建父目录，已存在时的错误可忽略
+        let mut dir = self.root.trim_end_matches('/').to_string();
+        if let Some((parents, _)) = rel.rsplit_once('/') {
+            for segment in parents.split('/') {
+                dir = format!("{}/{}", dir, segment);
+                let _ = self.driver.create_dir(&dir).await;
+            }
+        }
+        let mut writer = self.driver
+            .open_writer(&self.full_path(rel), Some(content.len() as u64), None)
+            .await?;
+        writer.write_all(content.as_bytes()).await?;
+        writer.shutdown().await?;
+        Ok(())
+    }
+
+    async fn remove(&self, rel: &str) -> Result<()> {
+        self.driver.delete(&self.full_path(rel)).await
+    }
+}
+
+/// STRM export manager / STRM 导出管理器
+pub struct StrmManager {
+    exports: RwLock<Vec<StrmExport>>,
+    status: RwLock<HashMap<String, StrmExportStatus>>,
+    running: Mutex<HashSet<String>>,
+}
+
+/// `strm_exports` row: id, name, source_paths, target, extensions, base_url, enabled, created_at
+type ExportRow = (String, String, String, String, String, Option<String>, bool, String);
+
+impl StrmManager {
+    pub fn new() -> Self {
+        Self {
+            exports: RwLock::new(Vec::new()),
+            status: RwLock::new(HashMap::new()),
+            running: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Load exports from database / 从数据库加载导出配置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<ExportRow> = sqlx::query_as(
+            "SELECT id, name, source_paths, target, extensions, base_url, enabled, created_at FROM strm_exports"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let mut exports = Vec::new();
+        for (id, name, source_paths, target, extensions, base_url, enabled, created_at) in rows {
+            let target = match serde_json::from_str(&target) {
+                Ok(t) => t,
+                Err(e) => {
+                    tracing::warn!("Invalid target for STRM export {}: {}", id, e);
+                    continue;
+                }
+            };
+            exports.push(StrmExport {
+                id,
+                name,
+                source_paths: serde_json::from_str(&source_paths).unwrap_or_default(),
+                target,
+                extensions: serde_json::from_str(&extensions).unwrap_or_default(),
+                base_url,
+                enabled,
+                created_at: DateTime::parse_from_rfc3339(&created_at)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+            });
+        }
+        *self.exports.write() = exports;
+
+        Ok(())
+    }
+
+    /// List exports / 列出导出配置
+    pub fn list(&self) -> Vec<StrmExport> {
+        self.exports.read().clone()
+    }
+
+    /// Get export by id / 获取导出配置
+    pub fn get(&self, id: &str) -> Option<StrmExport> {
+        self.exports.read().iter().find(|e| e.id == id).cloned()
+    }
+
+    /// Insert or replace an export in the cache / 新增或替换缓存中的导出配置
+    pub fn upsert(&self, export: StrmExport) {
+        let mut exports = self.exports.write();
+        match exports.iter_mut().find(|e| e.id == export.id) {
+            Some(existing) => *existing = export,
+            None => exports.push(export),
+        }
+    }
+
+    /// Remove an export from the cache / 从缓存移除导出配置
+    pub fn remove(&self, id: &str) -> bool {
+        self.status.write().remove(id);
+        let mut exports = self.exports.write();
+        let before = exports.len();
+        exports.retain(|e| e.id != id);
+        exports.len() != before
+    }
+
+    /// Last (or current) run status / 最近一次（或正在进行的）运行结果
+    pub fn last_status(&self, id: &str) -> Option<StrmExportStatus> {
+        self.status.read().get(id).cloned()
+    }
+
+    /// Mark an export as running, `false` if it already is / 标记为运行中，已在运行时返回 `false`
+    pub fn try_begin(&self, id: &str) -> bool {
+        if !self.running.lock().insert(id.to_string()) {
+            return false;
+        }
+        self.status.write().insert(id.to_string(), StrmExportStatus::started());
+        true
+    }
+
+    /// Store the run result and clear the running flag / 保存运行结果并清除运行标记
+    pub fn finish(&self, id: &str, mut status: StrmExportStatus) -> StrmExportStatus {
+        status.finish();
+        self.status.write().insert(id.to_string(), status.clone());
+        self.running.lock().remove(id);
+        status
+    }
+}
+
+impl Default for StrmManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Normalize a base URL, `None` if it is not http(s) / 规范化外部地址
+pub fn normalize_base_url(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Some(url.to_string())
+    } else {
+        None
+    }
+}
+
+/// Build the `.strm` content for a direct link sign / 生成直链的 `.strm` 内容
+pub fn strm_content(base_url: &str, sign: &str, filename: &str) -> String {
+    format!("{}/dlink/{}/{}", base_url, sign, urlencoding::encode(filename))
+}
+
+/// Validate a local target directory / 校验本地目标目录
+pub fn validate_local_dir(dir: &str) -> Result<()> {
+    let path = Path::new(dir.trim());
+    if dir.trim().is_empty() || path.parent().is_none() {
+        return Err(anyhow!("Target directory must not be empty or a filesystem root"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(extensions: Vec<String>) -> StrmExport {
+        StrmExport {
+            id: "e1".to_string(),
+            name: "Movies".to_string(),
+            source_paths: vec!["/cloud/Movies".to_string()],
+            target: StrmTarget::Local { dir: "/srv/strm".to_string() },
+            extensions,
+            base_url: None,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_relative_path_and_filter() {
+        assert_eq!(strm_relative_path("/cloud/Movies", "/cloud/Movies/A (2020)/a.mkv").as_deref(), Some("Movies/A (2020)/a.strm"));
+        assert_eq!(strm_relative_path("/cloud/Movies/", "/cloud/Movies/b.x.mp4").as_deref(), Some("Movies/b.x.strm"));
+        assert_eq!(strm_relative_path("/", "/a/b.mkv").as_deref(), Some("a/b.strm"));
+        assert_eq!(strm_relative_path("/cloud/Movies", "/cloud/MoviesX/a.mkv"), None);
+        assert_eq!(strm_relative_path("/cloud", "/cloud/../a.mkv"), None);
+
+        let e = export(Vec::new());
+        assert!(e.is_video("a.MKV"));
+        assert!(!e.is_video("a.srt"));
+        assert!(!e.is_video(".mkv"));
+        let e = export(vec![".strm2".to_string(), "MP4".to_string()]);
+        assert!(e.is_video("a.mp4"));
+        assert!(!e.is_video("a.mkv"));
+    }
+
+    #[tokio::test]
+    async fn test_local_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = LocalStrmSink::new(dir.path());
+        std::fs::create_dir_all(dir.path().join("Movies/Old")).unwrap();
+        std::fs::write(dir.path().join("Movies/Old/gone.strm"), "x").unwrap();
+        std::fs::write(dir.path().join("Movies/keep.nfo"), "nfo").unwrap();
+
+        let mut files = BTreeMap::new();
+        files.insert("Movies/A/a.strm".to_string(), "http://h/dlink/s1/a.mkv".to_string());
+        let mut status = StrmExportStatus::started();
+        sync_strm(&sink, &files, false, &mut status).await;
+        assert_eq!((status.written, status.removed), (1, 0));
+        assert!(dir.path().join("Movies/Old/gone.strm").exists());
+
+        let mut status = StrmExportStatus::started();
+        sync_strm(&sink, &files, true, &mut status).await;
+        assert_eq!((status.written, status.unchanged, status.removed), (0, 1, 1));
+        assert!(!dir.path().join("Movies/Old").exists());
+        assert!(dir.path().join("Movies/keep.nfo").exists());
+        assert_eq!(std::fs::read_to_string(dir.path().join("Movies/A/a.strm")).unwrap(), "http://h/dlink/s1/a.mkv");
+    }
+}
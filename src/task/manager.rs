@@ -1,18 +1,63 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use tokio::sync::{RwLock, broadcast};
 use chrono::Utc;
+use yaolist_backend::cluster::{Cluster, TaskControlEvent, EVENT_TASK_CONTROL, EVENT_TASK_EVENT};
 
 use super::types::{TaskType, TaskStatus, TaskEvent};
-use super::models::{Task, TaskSummary, TaskControl, UploadFileInfo};
+use super::models::{Task, TaskSummary, TaskControl, UploadFileInfo, TransferVerification, VerifyResult, FsckReport, TaskGroup, TaskGroupSummary, DirectoryProgress};
 
 /// 任务管理器（按用户隔离，支持WebSocket广播）
+/// 已结束任务在数据库中保留的默认天数（0表示永久保留）
+pub const DEFAULT_TASK_RETENTION_DAYS: u32 = 30;
+/// 已结束任务在任务列表（内存）中保留的默认小时数
+pub const DEFAULT_TASK_MEMORY_RETENTION_HOURS: u32 = 24;
+/// 停机时暂停任务后等待正在写入的分块落盘的时间（毫秒）
+const SHUTDOWN_SETTLE_MS: u64 = 500;
+/// 进度批量写入：只更新仍在进行中的任务，避免覆盖已写入的结束/暂停状态
+const PROGRESS_UPDATE_SQL: &str = "UPDATE tasks SET status = ?, total_size = ?, processed_size = ?, total_files = ?, processed_files = ?, \
+    progress = ?, speed = ?, eta_seconds = ?, current_file = ?, files = ?, items = ?, verification = ? \
+    WHERE id = ? AND status IN ('pending', 'running')";
+/// 同一任务的进度事件发往其他实例的最小间隔（毫秒）
+const FANOUT_INTERVAL_MS: u128 = 1000;
+
+/// 任务表查询列
+const TASK_COLUMNS: &str = "id, task_type, status, name, source_path, target_path, \
+    total_size, processed_size, total_files, processed_files, \
+    progress, speed, eta_seconds, created_at, started_at, \
+    finished_at, error, user_id, current_file, files, items, conflict_strategy, verification, buffer_size, group_id, fsck";
+
+/// 任务历史查询条件
+#[derive(Debug, Clone, Default)]
+pub struct TaskHistoryFilter {
+    /// None表示所有用户（管理员）
+    pub user_id: Option<String>,
+    pub task_type: Option<String>,
+    pub status: Option<String>,
+    pub page: u32,
+    pub page_size: u32,
+}
+
 #[derive(Clone)]
 pub struct TaskManager {
     tasks: Arc<RwLock<HashMap<String, Task>>>,
     controls: Arc<RwLock<HashMap<String, Arc<TaskControl>>>>,
+    groups: Arc<RwLock<HashMap<String, TaskGroup>>>,
     event_sender: broadcast::Sender<TaskEvent>,
     db: Option<sqlx::SqlitePool>,
+    retention_days: Arc<AtomicU32>,
+    memory_retention_hours: Arc<AtomicU32>,
+    /// 服务器正在停机，不再启动新任务
+    shutting_down: Arc<AtomicBool>,
+    /// 多实例共享状态，其他实例的任务从数据库读取
+    cluster: Option<Arc<Cluster>>,
+    /// 其他实例推送的最新任务进度（Redis 后端）
+    remote_progress: Arc<RwLock<HashMap<String, TaskSummary>>>,
+    /// 等待批量写入的任务进度（每个任务只保留最新一份）
+    pending_progress: Arc<parking_lot::Mutex<HashMap<String, Task>>>,
+    /// 每个任务最近一次向其他实例发送进度的时间
+    fanout_sent: Arc<parking_lot::Mutex<HashMap<String, std::time::Instant>>>,
 }
 
 impl TaskManager {
@@ -21,8 +66,16 @@ impl TaskManager {
         Self {
             tasks: Arc::new(RwLock::new(HashMap::new())),
             controls: Arc::new(RwLock::new(HashMap::new())),
+            groups: Arc::new(RwLock::new(HashMap::new())),
             event_sender,
             db: None,
+            retention_days: Arc::new(AtomicU32::new(DEFAULT_TASK_RETENTION_DAYS)),
+            memory_retention_hours: Arc::new(AtomicU32::new(DEFAULT_TASK_MEMORY_RETENTION_HOURS)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            cluster: None,
+            remote_progress: Arc::new(RwLock::new(HashMap::new())),
+            pending_progress: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            fanout_sent: Arc::new(parking_lot::Mutex::new(HashMap::new())),
         }
     }
 
@@ -31,102 +84,159 @@ impl TaskManager {
         self.db = Some(db);
     }
 
-    /// 从数据库加载任务到内存
+    /// 设置集群（多实例时任务归属于创建它的实例）
+    pub fn set_cluster(&mut self, cluster: Arc<Cluster>) {
+        self.cluster = Some(cluster);
+    }
+
+    /// 多实例共享时返回本实例ID
+    fn shared_instance(&self) -> Option<&str> {
+        self.cluster.as_ref()
+            .filter(|c| c.is_shared())
+            .map(|c| c.instance_id())
+    }
+
+    /// 从数据库加载任务到内存（多实例时只加载本实例的任务）
     pub async fn load_tasks_from_db(&self) {
         if let Some(db) = &self.db {
-            use sqlx::Row;
-            
-            let rows = sqlx::query(
-                r#"SELECT id, task_type, status, name, source_path, target_path,
-                   total_size, processed_size, total_files, processed_files,
-                   progress, speed, eta_seconds, created_at, started_at,
-                   finished_at, error, user_id, current_file, files, items, conflict_strategy FROM tasks"#
-            )
-            .fetch_all(db)
-            .await
+            let rows = match self.shared_instance() {
+                Some(instance_id) => sqlx::query(
+                    &format!("SELECT {} FROM tasks WHERE instance_id IS NULL OR instance_id = ?", TASK_COLUMNS)
+                )
+                .bind(instance_id)
+                .fetch_all(db)
+                .await,
+                None => sqlx::query(
+                    &format!("SELECT {} FROM tasks", TASK_COLUMNS)
+                )
+                .fetch_all(db)
+                .await,
+            }
             .unwrap_or_default();
 
             let mut tasks = self.tasks.write().await;
             for row in rows {
-                let id: String = row.get("id");
-                let task_type_str: String = row.get("task_type");
-                let status_str: String = row.get("status");
-                
-                let task_type = match task_type_str.as_str() {
-                    "upload" => TaskType::Upload,
-                    "download" => TaskType::Download,
-                    "copy" => TaskType::Copy,
-                    "move" => TaskType::Move,
-                    "delete" => TaskType::Delete,
-                    "extract" => TaskType::Extract,
-                    _ => TaskType::Upload,
-                };
-                
-                // 运行中的任务重启后标记为中断
-                let status = match status_str.as_str() {
-                    "pending" => TaskStatus::Pending,
-                    "running" => TaskStatus::Interrupted, // 重启后运行中的任务变为中断
-                    "paused" => TaskStatus::Interrupted,  // 暂停的也变为中断
-                    "completed" => TaskStatus::Completed,
-                    "failed" => TaskStatus::Failed,
-                    "cancelled" => TaskStatus::Cancelled,
-                    "interrupted" => TaskStatus::Interrupted,
-                    _ => TaskStatus::Pending,
-                };
-                
-                let created_at_str: String = row.get("created_at");
-                let started_at_str: Option<String> = row.get("started_at");
-                let finished_at_str: Option<String> = row.get("finished_at");
-                let current_file: Option<String> = row.try_get("current_file").ok().flatten();
-                let files_json: Option<String> = row.try_get("files").ok().flatten();
-                let items_json: Option<String> = row.try_get("items").ok().flatten();
-                let conflict_strategy: Option<String> = row.try_get("conflict_strategy").ok().flatten();
-                
-                // 解析files和items字段
-                let files: Option<Vec<UploadFileInfo>> = files_json
-                    .and_then(|json| serde_json::from_str(&json).ok());
-                let items: Option<Vec<String>> = items_json
-                    .and_then(|json| serde_json::from_str(&json).ok());
-                
-                let task = Task {
-                    id: id.clone(),
-                    task_type,
-                    status,
-                    name: row.get("name"),
-                    source_path: row.get("source_path"),
-                    target_path: row.get("target_path"),
-                    total_size: row.get::<i64, _>("total_size") as u64,
-                    processed_size: row.get::<i64, _>("processed_size") as u64,
-                    total_files: row.get::<i64, _>("total_files") as u64,
-                    processed_files: row.get::<i64, _>("processed_files") as u64,
-                    progress: row.get::<f64, _>("progress") as f32,
-                    speed: 0.0, // 重置速度
-                    eta_seconds: None, // 重置ETA
-                    created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    started_at: started_at_str.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                        .map(|dt| dt.with_timezone(&Utc)),
-                    finished_at: finished_at_str.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                        .map(|dt| dt.with_timezone(&Utc)),
-                    error: row.get("error"),
-                    // 兼容 INTEGER 和 TEXT 类型的 user_id
-                    user_id: row.try_get::<Option<String>, _>("user_id")
-                        .unwrap_or_else(|_| row.try_get::<Option<i64>, _>("user_id")
-                            .ok()
-                            .flatten()
-                            .map(|id| id.to_string())),
-                    current_file,
-                    files,
-                    items,
-                    conflict_strategy,
-                    last_saved: None,
-                    last_speed_update_time: None,
-                    last_speed_processed_size: 0,
-                };
-                tasks.insert(id, task);
+                let task = Self::task_from_row(&row, true);
+                tasks.insert(task.id.clone(), task);
             }
             tracing::info!("Loaded {} tasks from database", tasks.len());
+            
+            let group_rows: Vec<(String, String, Option<String>, String)> = sqlx::query_as(
+                "SELECT id, name, user_id, created_at FROM task_groups"
+            )
+            .fetch_all(db)
+            .await
+            .unwrap_or_default();
+            let mut groups = self.groups.write().await;
+            for (id, name, user_id, created_at) in group_rows {
+                let created_at = chrono::DateTime::parse_from_rfc3339(&created_at)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now());
+                groups.insert(id.clone(), TaskGroup { id, name, user_id, created_at });
+            }
+        }
+    }
+
+    /// 从数据库行解析任务
+    /// on_startup为true时，运行中/暂停的任务标记为中断
+    fn task_from_row(row: &sqlx::sqlite::SqliteRow, on_startup: bool) -> Task {
+        use sqlx::Row;
+        
+        let id: String = row.get("id");
+        let task_type_str: String = row.get("task_type");
+        let status_str: String = row.get("status");
+        
+        let task_type = match task_type_str.as_str() {
+            "upload" => TaskType::Upload,
+            "download" => TaskType::Download,
+            "copy" => TaskType::Copy,
+            "move" => TaskType::Move,
+            "delete" => TaskType::Delete,
+            "extract" => TaskType::Extract,
+            "fsck" => TaskType::Fsck,
+            "staticsite" => TaskType::StaticSite,
+            _ => TaskType::Upload,
+        };
+        
+        // 运行中的任务重启后标记为中断
+        let status = match status_str.as_str() {
+            "pending" => TaskStatus::Pending,
+            "running" if !on_startup => TaskStatus::Running,
+            "paused" if !on_startup => TaskStatus::Paused,
+            "running" => TaskStatus::Interrupted, // 重启后运行中的任务变为中断
+            "paused" => TaskStatus::Interrupted,  // 暂停的也变为中断
+            "completed" => TaskStatus::Completed,
+            "failed" => TaskStatus::Failed,
+            "cancelled" => TaskStatus::Cancelled,
+            "interrupted" => TaskStatus::Interrupted,
+            _ => TaskStatus::Pending,
+        };
+        
+        let created_at_str: String = row.get("created_at");
+        let started_at_str: Option<String> = row.get("started_at");
+        let finished_at_str: Option<String> = row.get("finished_at");
+        let current_file: Option<String> = row.try_get("current_file").ok().flatten();
+        let files_json: Option<String> = row.try_get("files").ok().flatten();
+        let items_json: Option<String> = row.try_get("items").ok().flatten();
+        let conflict_strategy: Option<String> = row.try_get("conflict_strategy").ok().flatten();
+        let verification: Option<TransferVerification> = row.try_get::<Option<String>, _>("verification")
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok());
+        
+        // 解析files和items字段
+        let files: Option<Vec<UploadFileInfo>> = files_json
+            .and_then(|json| serde_json::from_str(&json).ok());
+        let items: Option<Vec<String>> = items_json
+            .and_then(|json| serde_json::from_str(&json).ok());
+        let buffer_size: Option<u64> = row.try_get::<Option<i64>, _>("buffer_size")
+            .ok()
+            .flatten()
+            .map(|s| s as u64);
+        let group_id: Option<String> = row.try_get("group_id").ok().flatten();
+        let fsck: Option<FsckReport> = row.try_get::<Option<String>, _>("fsck")
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok());
+        
+        Task {
+            id,
+            task_type,
+            status,
+            name: row.get("name"),
+            source_path: row.get("source_path"),
+            target_path: row.get("target_path"),
+            total_size: row.get::<i64, _>("total_size") as u64,
+            processed_size: row.get::<i64, _>("processed_size") as u64,
+            total_files: row.get::<i64, _>("total_files") as u64,
+            processed_files: row.get::<i64, _>("processed_files") as u64,
+            progress: row.get::<f64, _>("progress") as f32,
+            speed: 0.0, // 重置速度
+            eta_seconds: None, // 重置ETA
+            created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            started_at: started_at_str.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+            finished_at: finished_at_str.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+            error: row.get("error"),
+            // 兼容 INTEGER 和 TEXT 类型的 user_id
+            user_id: row.try_get::<Option<String>, _>("user_id")
+                .unwrap_or_else(|_| row.try_get::<Option<i64>, _>("user_id")
+                    .ok()
+                    .flatten()
+                    .map(|id| id.to_string())),
+            current_file,
+            files,
+            items,
+            conflict_strategy,
+            verification,
+            fsck,
+            buffer_size,
+            group_id,
+            last_speed_update_time: None,
+            last_speed_processed_size: 0,
         }
     }
 
@@ -135,10 +245,49 @@ impl TaskManager {
         self.event_sender.subscribe()
     }
 
-    /// 广播事件
+    /// 广播事件（Redis 后端时同时推送给其他实例）
     pub fn broadcast(&self, event: TaskEvent) {
+        self.fan_out(&event);
         let _ = self.event_sender.send(event);
     }
+
+    /// 将任务事件推送给其他实例，进度更新按任务限流
+    fn fan_out(&self, event: &TaskEvent) {
+        let Some(cluster) = self.cluster.as_ref().filter(|c| c.has_fast_events()) else {
+            return;
+        };
+        if let TaskEvent::TaskUpdated { task } = event {
+            let mut sent = self.fanout_sent.lock();
+            let now = std::time::Instant::now();
+            if sent.get(&task.id).is_some_and(|t| now.duration_since(*t).as_millis() < FANOUT_INTERVAL_MS) {
+                return;
+            }
+            sent.insert(task.id.clone(), now);
+        } else {
+            let (TaskEvent::TaskCreated { task } | TaskEvent::TaskCompleted { task }
+                | TaskEvent::TaskFailed { task } | TaskEvent::TaskCancelled { task }) = event else { return };
+            self.fanout_sent.lock().remove(&task.id);
+        }
+        let Ok(payload) = serde_json::to_string(event) else { return };
+        let cluster = cluster.clone();
+        tokio::spawn(async move {
+            cluster.publish(EVENT_TASK_EVENT, &payload).await;
+        });
+    }
+
+    /// 记录其他实例推送的任务事件，列出其他实例的任务时用于显示实时进度
+    pub async fn apply_remote_event(&self, event: TaskEvent) {
+        let mut progress = self.remote_progress.write().await;
+        match event {
+            TaskEvent::TaskCreated { task } | TaskEvent::TaskUpdated { task } => {
+                progress.insert(task.id.clone(), task);
+            }
+            // 结束状态已写入数据库
+            TaskEvent::TaskCompleted { task } | TaskEvent::TaskFailed { task } | TaskEvent::TaskCancelled { task } => {
+                progress.remove(&task.id);
+            }
+        }
+    }
     
     /// 添加任务
     pub async fn add_task(&self, task: Task) {
@@ -340,21 +489,11 @@ impl TaskManager {
                 task.eta_seconds = Some((remaining as f64 / task.speed) as u64);
             }
             
-            // 定期保存到数据库（每5秒一次）
-            let should_save = task.last_saved.map(|t| {
-                Utc::now().signed_duration_since(t).num_seconds() >= 5
-            }).unwrap_or(true);
-            
-            if should_save {
-                task.last_saved = Some(Utc::now());
-            }
-            
             let task_clone = task.clone();
             drop(tasks);
             
-            if should_save {
-                self.save_task_to_db(&task_clone).await;
-            }
+            // 进度由批量写入器定期保存
+            self.queue_progress(&task_clone);
             self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(&task_clone) });
         }
     }
@@ -430,41 +569,174 @@ impl TaskManager {
                 task.eta_seconds = Some((remaining as f64 / task.speed) as u64);
             }
             
-            // 定期保存到数据库（每5秒一次）
-            let should_save = task.last_saved.map(|t| {
-                Utc::now().signed_duration_since(t).num_seconds() >= 5
-            }).unwrap_or(true);
+            let task_clone = task.clone();
+            drop(tasks);
             
-            if should_save {
-                task.last_saved = Some(Utc::now());
+            // 进度由批量写入器定期保存
+            self.queue_progress(&task_clone);
+            self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(&task_clone) });
+        }
+    }
+
+    /// 获取跨驱动复制中某个文件的断点（path为源驱动内路径）
+    pub async fn get_copy_checkpoint(&self, task_id: &str, path: &str) -> Option<UploadFileInfo> {
+        let tasks = self.tasks.read().await;
+        tasks.get(task_id)?
+            .files.as_ref()?
+            .iter()
+            .find(|f| f.path == path)
+            .cloned()
+    }
+
+    /// 更新跨驱动复制中某个文件的已写入字节数（仅更新内存，随进度定期保存）
+    pub async fn update_copy_checkpoint(&self, task_id: &str, path: &str, target_path: &str, size: u64, offset: u64) {
+        let mut tasks = self.tasks.write().await;
+        if let Some(task) = tasks.get_mut(task_id) {
+            let files = task.files.get_or_insert_with(Vec::new);
+            match files.iter_mut().find(|f| f.path == path) {
+                Some(file) => {
+                    file.target_path = Some(target_path.to_string());
+                    file.size = size;
+                    file.uploaded_size = offset;
+                }
+                None => files.push(UploadFileInfo {
+                    path: path.to_string(),
+                    size,
+                    uploaded_size: offset,
+                    uploaded_chunks: Vec::new(),
+                    status: TaskStatus::Running,
+                    target_path: Some(target_path.to_string()),
+                }),
+            }
+        }
+    }
+
+    /// 文件复制完成后移除断点
+    pub async fn clear_copy_checkpoint(&self, task_id: &str, path: &str) {
+        let mut tasks = self.tasks.write().await;
+        if let Some(task) = tasks.get_mut(task_id) {
+            if let Some(ref mut files) = task.files {
+                files.retain(|f| f.path != path);
+                if files.is_empty() {
+                    task.files = None;
+                }
+            }
+        }
+    }
+
+    /// 记录跨驱动复制中单个文件的校验结果
+    /// result为None表示重试后仍校验失败
+    pub async fn record_verification(&self, task_id: &str, file: &str, result: Option<VerifyResult>, retries: u64) {
+        let mut tasks = self.tasks.write().await;
+        if let Some(task) = tasks.get_mut(task_id) {
+            let verification = task.verification.get_or_insert_with(TransferVerification::default);
+            verification.retries += retries;
+            match result {
+                Some(VerifyResult::Checksum) => verification.checksum_verified += 1,
+                Some(VerifyResult::SizeOnly) => verification.size_verified += 1,
+                None => verification.failed.push(file.to_string()),
             }
-            
             let task_clone = task.clone();
             drop(tasks);
             
-            if should_save {
+            // 校验失败或发生重传时立即保存，便于排查
+            if result.is_none() || retries > 0 {
                 self.save_task_to_db(&task_clone).await;
             }
-            self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(&task_clone) });
+        }
+    }
+
+    /// 保存存储校验任务的报告
+    pub async fn set_fsck_report(&self, task_id: &str, report: FsckReport) {
+        let mut tasks = self.tasks.write().await;
+        if let Some(task) = tasks.get_mut(task_id) {
+            task.fsck = Some(report);
+        }
+    }
+
+    /// 排队等待批量写入进度
+    fn queue_progress(&self, task: &Task) {
+        if self.db.is_some() {
+            self.pending_progress.lock().insert(task.id.clone(), task.clone());
+        }
+    }
+
+    /// 启动进度批量写入器：几十个任务的进度合并为一个事务，不再逐条争用数据库写锁
+    pub fn start_progress_writer(&self, interval_secs: u64) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1)));
+            loop {
+                interval.tick().await;
+                manager.flush_progress().await;
+            }
+        });
+    }
+
+    /// 将排队的进度写入数据库
+    pub async fn flush_progress(&self) {
+        let Some(db) = &self.db else { return };
+        let batch: Vec<Task> = std::mem::take(&mut *self.pending_progress.lock()).into_values().collect();
+        if batch.is_empty() {
+            return;
+        }
+        let result: Result<(), sqlx::Error> = async {
+            let mut tx = db.begin().await?;
+            for task in &batch {
+                sqlx::query(PROGRESS_UPDATE_SQL)
+                    .bind(format!("{:?}", task.status).to_lowercase())
+                    .bind(task.total_size as i64)
+                    .bind(task.processed_size as i64)
+                    .bind(task.total_files as i64)
+                    .bind(task.processed_files as i64)
+                    .bind(task.progress as f64)
+                    .bind(task.speed)
+                    .bind(task.eta_seconds.map(|s| s as i64))
+                    .bind(&task.current_file)
+                    .bind(task.files.as_ref().map(|f| serde_json::to_string(f).unwrap_or_default()))
+                    .bind(task.items.as_ref().map(|i| serde_json::to_string(i).unwrap_or_default()))
+                    .bind(task.verification.as_ref().map(|v| serde_json::to_string(v).unwrap_or_default()))
+                    .bind(&task.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await
+        }.await;
+        match result {
+            Ok(()) => tracing::debug!("Saved progress of {} tasks", batch.len()),
+            Err(e) => {
+                tracing::warn!("Failed to save progress of {} tasks: {}", batch.len(), e);
+                // 放回队列下次重试，已有更新的进度时保留新的
+                let mut pending = self.pending_progress.lock();
+                for task in batch {
+                    pending.entry(task.id.clone()).or_insert(task);
+                }
+            }
         }
     }
 
     /// 保存任务到数据库
     async fn save_task_to_db(&self, task: &Task) {
+        // 完整保存后排队中的旧进度不再需要
+        self.pending_progress.lock().remove(&task.id);
         if let Some(db) = &self.db {
             // 序列化files和items字段为JSON（用于断点续传）
             let files_json = task.files.as_ref()
                 .map(|f| serde_json::to_string(f).unwrap_or_default());
             let items_json = task.items.as_ref()
                 .map(|i| serde_json::to_string(i).unwrap_or_default());
+            let verification_json = task.verification.as_ref()
+                .map(|v| serde_json::to_string(v).unwrap_or_default());
+            let fsck_json = task.fsck.as_ref()
+                .map(|r| serde_json::to_string(r).unwrap_or_default());
             
             let _ = sqlx::query(
                 r#"INSERT OR REPLACE INTO tasks 
                    (id, task_type, status, name, source_path, target_path, 
                     total_size, processed_size, total_files, processed_files, 
                     progress, speed, eta_seconds, created_at, started_at, 
-                    finished_at, error, user_id, current_file, files, items, conflict_strategy) 
-                   VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+                    finished_at, error, user_id, current_file, files, items, conflict_strategy, verification, buffer_size, group_id, instance_id, fsck) 
+                   VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
             )
             .bind(&task.id)
             .bind(format!("{:?}", task.task_type).to_lowercase())
@@ -488,6 +760,11 @@ impl TaskManager {
             .bind(files_json)
             .bind(items_json)
             .bind(&task.conflict_strategy)
+            .bind(verification_json)
+            .bind(task.buffer_size.map(|s| s as i64))
+            .bind(&task.group_id)
+            .bind(self.shared_instance())
+            .bind(fsck_json)
             .execute(db)
             .await;
         }
@@ -519,11 +796,11 @@ impl TaskManager {
         task_id
     }
 
-    /// 开始任务
+    /// 开始任务（停机期间直接标记为中断，下次启动后可续传）
     pub async fn start_task(&self, task_id: &str) -> bool {
         let mut tasks = self.tasks.write().await;
         if let Some(task) = tasks.get_mut(task_id) {
-            task.status = TaskStatus::Running;
+            task.status = if self.is_shutting_down() { TaskStatus::Interrupted } else { TaskStatus::Running };
             task.started_at = Some(Utc::now());
             let task_clone = task.clone();
             drop(tasks);
@@ -604,7 +881,8 @@ impl TaskManager {
             }
             let task_clone = task.clone();
             drop(tasks);
-            self.save_task_to_db(&task_clone).await;
+            // 每个分片都会调用，交给批量写入器合并
+            self.queue_progress(&task_clone);
             self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(&task_clone) });
         }
     }
@@ -661,7 +939,9 @@ impl TaskManager {
                 return true;
             }
         }
-        false
+        let local = tasks.contains_key(task_id);
+        drop(tasks);
+        !local && self.forward_control(task_id, "cancel").await
     }
     
     /// 暂停任务
@@ -685,7 +965,9 @@ impl TaskManager {
                 return true;
             }
         }
-        false
+        let local = tasks.contains_key(task_id);
+        drop(tasks);
+        !local && self.forward_control(task_id, "pause").await
     }
     
     /// 继续任务
@@ -709,7 +991,9 @@ impl TaskManager {
                 return true;
             }
         }
-        false
+        let local = tasks.contains_key(task_id);
+        drop(tasks);
+        !local && self.forward_control(task_id, "resume").await
     }
     
     /// 重新启动任务（完全重置）
@@ -738,13 +1022,17 @@ impl TaskManager {
                     task.processed_size = 0;
                     task.processed_files = 0;
                     task.current_file = None;
+                    task.verification = None;
                     
-                    // 重置文件状态
+                    // 重置文件状态（复制/移动任务的字节级断点保留，用于中断文件续传）
+                    let keep_checkpoints = matches!(task.task_type, TaskType::Copy | TaskType::Move);
                     if let Some(ref mut files) = task.files {
-                        for file in files.iter_mut() {
-                            file.status = TaskStatus::Pending;
-                            file.uploaded_size = 0;
-                            file.uploaded_chunks.clear();
+                        if !keep_checkpoints {
+                            for file in files.iter_mut() {
+                                file.status = TaskStatus::Pending;
+                                file.uploaded_size = 0;
+                                file.uploaded_chunks.clear();
+                            }
                         }
                     }
                 }
@@ -770,9 +1058,12 @@ impl TaskManager {
         controls.get(task_id).cloned()
     }
     
-    /// 创建任务控制标志
+    /// 创建任务控制标志（停机期间创建的任务保持暂停，不再写入）
     pub async fn create_control(&self, task_id: &str) -> Arc<TaskControl> {
         let ctrl = Arc::new(TaskControl::new());
+        if self.is_shutting_down() {
+            ctrl.pause();
+        }
         let mut controls = self.controls.write().await;
         controls.insert(task_id.to_string(), ctrl.clone());
         ctrl
@@ -820,25 +1111,180 @@ impl TaskManager {
         }
     }
 
-    /// 获取任务
+    /// 是否正在停机
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// 停机前保存断点：暂停所有运行中任务，等待分块写完后标记为中断并写入数据库
+    /// 每个文件的已写入偏移随files字段保存，下次启动后可从断点续传
+    /// 返回保存断点的任务数
+    pub async fn checkpoint_for_shutdown(&self) -> usize {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        self.flush_progress().await;
+        for ctrl in self.controls.read().await.values() {
+            ctrl.pause();
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(SHUTDOWN_SETTLE_MS)).await;
+
+        let mut tasks = self.tasks.write().await;
+        let mut checkpointed = Vec::new();
+        for task in tasks.values_mut() {
+            if matches!(task.status, TaskStatus::Running | TaskStatus::Paused) {
+                task.status = TaskStatus::Interrupted;
+                task.speed = 0.0;
+                task.eta_seconds = None;
+                checkpointed.push(task.clone());
+            }
+        }
+        drop(tasks);
+
+        for task in &checkpointed {
+            self.save_task_to_db(task).await;
+            self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(task) });
+        }
+        checkpointed.len()
+    }
+
+    /// 获取任务（包括其他实例的任务）
     pub async fn get_task(&self, task_id: &str) -> Option<Task> {
-        let tasks = self.tasks.read().await;
-        tasks.get(task_id).cloned()
+        let local = self.tasks.read().await.get(task_id).cloned();
+        match local {
+            Some(task) => Some(task),
+            None => self.remote_tasks(Some(task_id)).await.into_iter().next(),
+        }
     }
 
     /// 获取用户的所有任务
     pub async fn get_user_tasks(&self, user_id: Option<String>) -> Vec<Task> {
-        let tasks = self.tasks.read().await;
-        tasks.values()
-            .filter(|t| t.user_id == user_id)
-            .cloned()
-            .collect()
+        let mut result: Vec<Task> = {
+            let tasks = self.tasks.read().await;
+            tasks.values()
+                .filter(|t| t.user_id == user_id)
+                .cloned()
+                .collect()
+        };
+        result.extend(self.remote_tasks(None).await.into_iter().filter(|t| t.user_id == user_id));
+        result
     }
 
     /// 获取所有任务
     pub async fn get_all_tasks(&self) -> Vec<Task> {
-        let tasks = self.tasks.read().await;
-        tasks.values().cloned().collect()
+        let mut result: Vec<Task> = self.tasks.read().await.values().cloned().collect();
+        result.extend(self.remote_tasks(None).await);
+        result
+    }
+
+    /// 其他实例的任务（从数据库读取，所属实例已停止时显示为中断）
+    async fn remote_tasks(&self, task_id: Option<&str>) -> Vec<Task> {
+        use sqlx::Row;
+        let (Some(db), Some(cluster)) = (&self.db, self.cluster.as_ref().filter(|c| c.is_shared())) else {
+            return Vec::new();
+        };
+        let cutoff = Utc::now() - chrono::Duration::hours(self.memory_retention_hours.load(Ordering::Relaxed) as i64);
+        let rows = sqlx::query(&format!(
+            "SELECT {}, instance_id FROM tasks WHERE instance_id IS NOT NULL AND instance_id != ? \
+             AND (finished_at IS NULL OR finished_at > ?) AND (? IS NULL OR id = ?)",
+            TASK_COLUMNS
+        ))
+        .bind(cluster.instance_id())
+        .bind(cutoff.to_rfc3339())
+        .bind(task_id)
+        .bind(task_id)
+        .fetch_all(db)
+        .await
+        .unwrap_or_default();
+
+        let mut alive: HashMap<String, bool> = HashMap::new();
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let owner: String = row.get("instance_id");
+            let owner_alive = match alive.get(&owner) {
+                Some(a) => *a,
+                None => {
+                    let a = cluster.is_alive(&owner).await;
+                    alive.insert(owner.clone(), a);
+                    a
+                }
+            };
+            let mut task = Self::task_from_row(&row, !owner_alive);
+            if owner_alive {
+                if let Some(live) = self.remote_progress.read().await.get(&task.id) {
+                    Self::apply_progress(&mut task, live);
+                }
+            } else {
+                self.remote_progress.write().await.remove(&task.id);
+            }
+            if !owner_alive && task.status == TaskStatus::Interrupted {
+                let _ = sqlx::query("UPDATE tasks SET status = 'interrupted' WHERE id = ? AND status IN ('running', 'paused')")
+                    .bind(&task.id)
+                    .execute(db)
+                    .await;
+            }
+            result.push(task);
+        }
+        result
+    }
+
+    /// 用其他实例推送的进度覆盖数据库中的进度
+    fn apply_progress(task: &mut Task, live: &TaskSummary) {
+        task.status = live.status.clone();
+        task.total_size = live.total_size;
+        task.processed_size = live.processed_size;
+        task.total_files = live.total_files;
+        task.processed_files = live.processed_files;
+        task.progress = live.progress;
+        task.speed = live.speed;
+        task.eta_seconds = live.eta_seconds;
+        task.started_at = live.started_at;
+        task.current_file = live.current_file.clone();
+        task.error = live.error.clone();
+    }
+
+    /// 将控制请求转发给任务所属实例；所属实例已停止时直接在数据库中取消或删除
+    async fn forward_control(&self, task_id: &str, action: &str) -> bool {
+        let Some(task) = self.remote_tasks(Some(task_id)).await.into_iter().next() else {
+            return false;
+        };
+        let (Some(db), Some(cluster)) = (&self.db, &self.cluster) else {
+            return false;
+        };
+        if matches!(task.status, TaskStatus::Interrupted | TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled) {
+            return match action {
+                "remove" => sqlx::query("DELETE FROM tasks WHERE id = ?")
+                    .bind(task_id)
+                    .execute(db)
+                    .await
+                    .is_ok_and(|r| r.rows_affected() > 0),
+                "cancel" if task.status == TaskStatus::Interrupted => sqlx::query(
+                    "UPDATE tasks SET status = 'cancelled', finished_at = ? WHERE id = ?"
+                )
+                .bind(Utc::now().to_rfc3339())
+                .bind(task_id)
+                .execute(db)
+                .await
+                .is_ok(),
+                _ => false,
+            };
+        }
+        let event = TaskControlEvent { task_id: task_id.to_string(), action: action.to_string() };
+        cluster.publish(EVENT_TASK_CONTROL, &serde_json::to_string(&event).unwrap_or_default()).await;
+        true
+    }
+
+    /// 执行其他实例转发来的控制请求
+    pub async fn apply_remote_control(&self, event: &TaskControlEvent) {
+        if !self.tasks.read().await.contains_key(&event.task_id) {
+            return;
+        }
+        let done = match event.action.as_str() {
+            "cancel" => self.cancel_task(&event.task_id).await,
+            "pause" => self.pause_task(&event.task_id).await,
+            "resume" => self.resume_task(&event.task_id).await,
+            "remove" => self.remove_task(&event.task_id).await,
+            _ => false,
+        };
+        tracing::debug!("Remote task control {} {}: {}", event.action, event.task_id, done);
     }
 
     /// 删除已完成的任务（同时从数据库删除）
@@ -858,6 +1304,8 @@ impl TaskManager {
         });
         
         let count = removed_ids.len();
+        drop(tasks);
+        self.prune_empty_groups().await;
         
         // 从数据库删除
         if let Some(db) = &self.db {
@@ -876,8 +1324,13 @@ impl TaskManager {
     pub async fn remove_task(&self, task_id: &str) -> bool {
         let mut tasks = self.tasks.write().await;
         let removed = tasks.remove(task_id).is_some();
+        drop(tasks);
+        if !removed {
+            return self.forward_control(task_id, "remove").await;
+        }
         
         if removed {
+            self.prune_empty_groups().await;
             if let Some(db) = &self.db {
                 let _ = sqlx::query("DELETE FROM tasks WHERE id = ?")
                     .bind(task_id)
@@ -889,18 +1342,339 @@ impl TaskManager {
         removed
     }
 
-    /// 清理过期任务（超过1小时的已完成/失败/取消任务）
-    pub async fn cleanup_expired(&self) {
-        let mut tasks = self.tasks.write().await;
+    /// 从数据库加载任务保留设置
+    pub async fn load_retention_from_db(&self) {
+        if let Some(db) = &self.db {
+            let rows: Vec<(String, String)> = sqlx::query_as(
+                "SELECT key, value FROM site_settings WHERE key IN ('task_retention_days', 'task_memory_retention_hours')"
+            )
+            .fetch_all(db)
+            .await
+            .unwrap_or_default();
+            
+            for (key, value) in rows {
+                match key.as_str() {
+                    "task_retention_days" => self.set_retention(value.parse().ok(), None),
+                    "task_memory_retention_hours" => self.set_retention(None, value.parse().ok()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// 设置任务保留策略（数据库保留天数、任务列表保留小时数）
+    pub fn set_retention(&self, days: Option<u32>, memory_hours: Option<u32>) {
+        if let Some(days) = days {
+            self.retention_days.store(days, Ordering::SeqCst);
+        }
+        if let Some(hours) = memory_hours {
+            self.memory_retention_hours.store(hours.max(1), Ordering::SeqCst);
+        }
+    }
+
+    /// 获取任务保留策略 (数据库保留天数, 任务列表保留小时数)
+    pub fn get_retention(&self) -> (u32, u32) {
+        (
+            self.retention_days.load(Ordering::SeqCst),
+            self.memory_retention_hours.load(Ordering::SeqCst),
+        )
+    }
+
+    /// 清理过期任务
+    /// - 结束超过memory_hours的任务从任务列表（内存）移除，数据库中保留作为历史
+    /// - 结束超过retention_days的任务从数据库删除（0表示永久保留）
+    /// 返回 (内存移除数, 数据库删除数)
+    pub async fn cleanup_expired(&self) -> (usize, u64) {
+        let (days, memory_hours) = self.get_retention();
         let now = Utc::now();
-        tasks.retain(|_, t| {
-            if let Some(finished) = t.finished_at {
-                let duration = now.signed_duration_since(finished);
-                duration.num_hours() < 1
-            } else {
-                true
+        let memory_cutoff = now - chrono::Duration::hours(memory_hours as i64);
+        
+        let mut removed_ids = Vec::new();
+        {
+            let mut tasks = self.tasks.write().await;
+            tasks.retain(|id, t| {
+                let finished = matches!(t.status, TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled);
+                let expired = finished && t.finished_at.map(|f| f < memory_cutoff).unwrap_or(false);
+                if expired {
+                    removed_ids.push(id.clone());
+                }
+                !expired
+            });
+        }
+        if !removed_ids.is_empty() {
+            let mut controls = self.controls.write().await;
+            for id in &removed_ids {
+                controls.remove(id);
             }
-        });
+        }
+        
+        self.prune_empty_groups().await;
+        
+        let mut deleted = 0;
+        if days > 0 {
+            if let Some(db) = &self.db {
+                let cutoff = now - chrono::Duration::days(days as i64);
+                deleted = sqlx::query(
+                    "DELETE FROM tasks WHERE status IN ('completed', 'failed', 'cancelled') \
+                     AND finished_at IS NOT NULL AND finished_at < ?"
+                )
+                .bind(cutoff.to_rfc3339())
+                .execute(db)
+                .await
+                .map(|r| r.rows_affected())
+                .unwrap_or(0);
+            }
+        }
+        
+        if !removed_ids.is_empty() || deleted > 0 {
+            tracing::info!("Task cleanup: {} removed from list, {} deleted from history", removed_ids.len(), deleted);
+        }
+        (removed_ids.len(), deleted)
+    }
+
+    /// 分页查询数据库中的任务历史（按创建时间倒序），返回 (任务, 总数)
+    pub async fn query_history(&self, filter: &TaskHistoryFilter) -> (Vec<Task>, u64) {
+        let Some(db) = &self.db else {
+            return (Vec::new(), 0);
+        };
+        
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut params: Vec<String> = Vec::new();
+        if let Some(ref uid) = filter.user_id {
+            conditions.push("user_id = ?");
+            params.push(uid.clone());
+        }
+        if let Some(ref task_type) = filter.task_type {
+            conditions.push("task_type = ?");
+            params.push(task_type.to_lowercase());
+        }
+        if let Some(ref status) = filter.status {
+            conditions.push("status = ?");
+            params.push(status.to_lowercase());
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+        
+        let count_sql = format!("SELECT COUNT(*) FROM tasks{}", where_clause);
+        let mut count_query = sqlx::query_scalar::<_, i64>(&count_sql);
+        for p in &params {
+            count_query = count_query.bind(p);
+        }
+        let total = count_query.fetch_one(db).await.unwrap_or(0) as u64;
+        
+        let page = filter.page.max(1);
+        let page_size = filter.page_size.clamp(1, 100);
+        let list_sql = format!(
+            "SELECT {} FROM tasks{} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+            TASK_COLUMNS, where_clause
+        );
+        let mut list_query = sqlx::query(&list_sql);
+        for p in &params {
+            list_query = list_query.bind(p);
+        }
+        let rows = list_query
+            .bind(page_size as i64)
+            .bind(((page - 1) * page_size) as i64)
+            .fetch_all(db)
+            .await
+            .unwrap_or_default();
+        
+        let tasks = rows.iter().map(|row| Self::task_from_row(row, false)).collect();
+        (tasks, total)
+    }
+    
+    /// 创建任务组（父任务）
+    pub async fn create_group(&self, name: String, user_id: Option<String>) -> TaskGroup {
+        let group = TaskGroup::new(name, user_id);
+        self.groups.write().await.insert(group.id.clone(), group.clone());
+        
+        if let Some(db) = &self.db {
+            let _ = sqlx::query(
+                "INSERT OR REPLACE INTO task_groups (id, name, user_id, created_at) VALUES (?, ?, ?, ?)"
+            )
+            .bind(&group.id)
+            .bind(&group.name)
+            .bind(&group.user_id)
+            .bind(group.created_at.to_rfc3339())
+            .execute(db)
+            .await;
+        }
+        group
+    }
+
+    /// 获取任务组
+    pub async fn get_group(&self, group_id: &str) -> Option<TaskGroup> {
+        self.groups.read().await.get(group_id).cloned()
+    }
+
+    /// 将任务加入任务组（任务与组必须属于同一登录用户），返回加入的任务数
+    pub async fn add_to_group(&self, group_id: &str, task_ids: &[String]) -> usize {
+        // 游客的组由所有游客共用，不再接受任务
+        let Some(group) = self.get_group(group_id).await.filter(|g| g.user_id.is_some()) else {
+            return 0;
+        };
+        
+        let mut updated = Vec::new();
+        {
+            let mut tasks = self.tasks.write().await;
+            for id in task_ids {
+                if let Some(task) = tasks.get_mut(id) {
+                    if task.user_id == group.user_id {
+                        task.group_id = Some(group.id.clone());
+                        updated.push(task.clone());
+                    }
+                }
+            }
+        }
+        for task in &updated {
+            self.save_task_to_db(task).await;
+            self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(task) });
+        }
+        updated.len()
+    }
+
+    /// 获取任务组内的子任务
+    pub async fn get_group_tasks(&self, group_id: &str) -> Vec<Task> {
+        let tasks = self.tasks.read().await;
+        tasks.values()
+            .filter(|t| t.group_id.as_deref() == Some(group_id))
+            .cloned()
+            .collect()
+    }
+
+    /// 获取任务组聚合信息
+    pub async fn get_group_summary(&self, group_id: &str) -> Option<TaskGroupSummary> {
+        let group = self.get_group(group_id).await?;
+        let tasks = self.get_group_tasks(group_id).await;
+        let refs: Vec<&Task> = tasks.iter().collect();
+        Some(TaskGroupSummary::aggregate(&group, &refs))
+    }
+
+    /// 列出任务组聚合信息（user_id为None时返回所有组，按创建时间倒序）
+    pub async fn list_groups(&self, user_id: Option<Option<String>>) -> Vec<TaskGroupSummary> {
+        let groups = self.groups.read().await;
+        let tasks = self.tasks.read().await;
+        
+        let mut members: HashMap<&str, Vec<&Task>> = HashMap::new();
+        for task in tasks.values() {
+            if let Some(ref gid) = task.group_id {
+                members.entry(gid.as_str()).or_default().push(task);
+            }
+        }
+        
+        let mut summaries: Vec<TaskGroupSummary> = groups.values()
+            .filter(|g| user_id.as_ref().map(|uid| &g.user_id == uid).unwrap_or(true))
+            .map(|g| {
+                let children = members.get(g.id.as_str()).map(|v| v.as_slice()).unwrap_or(&[]);
+                TaskGroupSummary::aggregate(g, children)
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        summaries
+    }
+
+    /// 暂停组内所有运行中的任务，返回暂停的任务数
+    pub async fn pause_group(&self, group_id: &str) -> usize {
+        let mut count = 0;
+        for task in self.get_group_tasks(group_id).await {
+            if self.pause_task(&task.id).await {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// 继续组内所有暂停的任务，返回继续的任务数
+    pub async fn resume_group(&self, group_id: &str) -> usize {
+        let mut count = 0;
+        for task in self.get_group_tasks(group_id).await {
+            if self.resume_task(&task.id).await {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// 取消组内所有未结束的任务，返回取消的任务数
+    pub async fn cancel_group(&self, group_id: &str) -> usize {
+        let mut count = 0;
+        for task in self.get_group_tasks(group_id).await {
+            if self.cancel_task(&task.id).await {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// 解散任务组（子任务保留，只移除分组关系）
+    pub async fn remove_group(&self, group_id: &str) -> bool {
+        let removed = self.groups.write().await.remove(group_id).is_some();
+        if !removed {
+            return false;
+        }
+        
+        let mut updated = Vec::new();
+        {
+            let mut tasks = self.tasks.write().await;
+            for task in tasks.values_mut() {
+                if task.group_id.as_deref() == Some(group_id) {
+                    task.group_id = None;
+                    updated.push(task.clone());
+                }
+            }
+        }
+        for task in &updated {
+            self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(task) });
+        }
+        
+        if let Some(db) = &self.db {
+            let _ = sqlx::query("UPDATE tasks SET group_id = NULL WHERE group_id = ?")
+                .bind(group_id)
+                .execute(db)
+                .await;
+            let _ = sqlx::query("DELETE FROM task_groups WHERE id = ?")
+                .bind(group_id)
+                .execute(db)
+                .await;
+        }
+        true
+    }
+
+    /// 移除没有子任务的任务组（刚创建的组保留一段时间等待任务加入）
+    async fn prune_empty_groups(&self) {
+        let cutoff = Utc::now() - chrono::Duration::hours(1);
+        let removed: Vec<String> = {
+            let tasks = self.tasks.read().await;
+            let mut groups = self.groups.write().await;
+            let mut removed = Vec::new();
+            groups.retain(|id, g| {
+                let keep = g.created_at > cutoff
+                    || tasks.values().any(|t| t.group_id.as_deref() == Some(id.as_str()));
+                if !keep {
+                    removed.push(id.clone());
+                }
+                keep
+            });
+            removed
+        };
+        
+        if let Some(db) = &self.db {
+            for id in &removed {
+                let _ = sqlx::query("DELETE FROM task_groups WHERE id = ?")
+                    .bind(id)
+                    .execute(db)
+                    .await;
+            }
+        }
+    }
+    
+    /// 获取文件夹上传任务各目录的进度
+    pub async fn get_directory_progress(&self, task_id: &str) -> Option<Vec<DirectoryProgress>> {
+        let tasks = self.tasks.read().await;
+        tasks.get(task_id).map(DirectoryProgress::aggregate)
     }
     
     /// 创建批次上传任务
@@ -931,26 +1705,7 @@ impl TaskManager {
             task.current_file = Some(file_path.to_string());
             
             if let Some(ref mut files) = task.files {
-                // 尝试多种方式匹配文件路径
-                let file_name = file_path.split('/').last().unwrap_or(file_path);
-                let file_opt = files.iter_mut().find(|f| {
-                    // 精确匹配
-                    if f.path == file_path {
-                        return true;
-                    }
-                    // 文件名匹配
-                    let f_name = f.path.split('/').last().unwrap_or(&f.path);
-                    if f_name == file_name {
-                        return true;
-                    }
-                    // 路径后缀匹配
-                    if f.path.ends_with(&format!("/{}", file_name)) || file_path.ends_with(&format!("/{}", f_name)) {
-                        return true;
-                    }
-                    false
-                });
-                
-                if let Some(file) = file_opt {
+                if let Some(file) = find_upload_file(files, file_path) {
                     // 确保进度只增不减，避免进度倒退
                     if uploaded_size > file.uploaded_size {
                         file.uploaded_size = uploaded_size;
@@ -1035,22 +1790,11 @@ impl TaskManager {
                 task.progress = (task.processed_size as f32 / task.total_size as f32) * 100.0;
             }
             
-            // 定期保存到数据库（每5秒一次，避免频繁IO）
-            let should_save = task.last_saved.map(|t| {
-                Utc::now().signed_duration_since(t).num_seconds() >= 5
-            }).unwrap_or(true);
-            
-            if should_save {
-                task.last_saved = Some(Utc::now());
-            }
-            
             let task_clone = task.clone();
             drop(tasks);
             
-            // 定期保存到数据库
-            if should_save {
-                self.save_task_to_db(&task_clone).await;
-            }
+            // 进度由批量写入器定期保存
+            self.queue_progress(&task_clone);
             
             self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(&task_clone) });
         }
@@ -1064,23 +1808,7 @@ impl TaskManager {
             let mut file_found = false;
             
             if let Some(ref mut files) = task.files {
-                // 尝试多种方式匹配文件路径
-                let file_name = file_path.split('/').last().unwrap_or(file_path);
-                let file_opt = files.iter_mut().find(|f| {
-                    if f.path == file_path {
-                        return true;
-                    }
-                    let f_name = f.path.split('/').last().unwrap_or(&f.path);
-                    if f_name == file_name {
-                        return true;
-                    }
-                    if f.path.ends_with(&format!("/{}", file_name)) || file_path.ends_with(&format!("/{}", f_name)) {
-                        return true;
-                    }
-                    false
-                });
-                
-                if let Some(file) = file_opt {
+                if let Some(file) = find_upload_file(files, file_path) {
                     file_found = true;
                     // 只有未完成的文件才增加计数
                     if file.status != TaskStatus::Completed {
@@ -1129,6 +1857,7 @@ impl TaskManager {
             } else {
                 let task_clone = task.clone();
                 drop(tasks);
+                self.queue_progress(&task_clone);
                 self.broadcast(TaskEvent::TaskUpdated { task: TaskSummary::from(&task_clone) });
             }
         }
@@ -1153,3 +1882,18 @@ impl Default for TaskManager {
         Self::new()
     }
 }
+
+/// 查找批次任务中的文件：优先精确匹配路径，其次按文件名或路径后缀匹配
+/// 文件夹上传中不同目录可能有同名文件，因此不能只按文件名匹配
+fn find_upload_file<'a>(files: &'a mut [UploadFileInfo], file_path: &str) -> Option<&'a mut UploadFileInfo> {
+    if let Some(pos) = files.iter().position(|f| f.path == file_path) {
+        return files.get_mut(pos);
+    }
+    let file_name = file_path.split('/').next_back().unwrap_or(file_path);
+    files.iter_mut().find(|f| {
+        let f_name = f.path.split('/').next_back().unwrap_or(&f.path);
+        f_name == file_name
+            || f.path.ends_with(&format!("/{}", file_name))
+            || file_path.ends_with(&format!("/{}", f_name))
+    })
+}
@@ -62,6 +62,8 @@ pub struct TaskSummary {
     pub error: Option<String>,
     pub user_id: Option<String>,
     pub current_file: Option<String>,
+    pub verification: Option<TransferVerification>,
+    pub group_id: Option<String>,
 }
 
 impl From<&Task> for TaskSummary {
@@ -86,6 +88,8 @@ impl From<&Task> for TaskSummary {
             error: task.error.clone(),
             user_id: task.user_id.clone(),
             current_file: task.current_file.clone(),
+            verification: task.verification.clone(),
+            group_id: task.group_id.clone(),
         }
     }
 }
@@ -98,6 +102,82 @@ pub struct UploadFileInfo {
     pub uploaded_size: u64,
     pub uploaded_chunks: Vec<u32>,  // 已上传的分片索引（用于断点续传）
     pub status: TaskStatus,
+    #[serde(default)]
+    pub target_path: Option<String>,  // 跨驱动复制的目标路径（复制任务断点续传用）
+}
+
+/// 复制后单个文件的校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyResult {
+    /// 大小和校验值均一致
+    Checksum,
+    /// 目标驱动不提供校验值，仅校验了大小
+    SizeOnly,
+}
+
+/// 跨驱动复制的传输校验汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferVerification {
+    /// 校验值一致的文件数
+    pub checksum_verified: u64,
+    /// 仅校验大小的文件数
+    pub size_verified: u64,
+    /// 因校验不一致而重传的次数
+    pub retries: u64,
+    /// 重试后仍校验失败的文件
+    pub failed: Vec<String>,
+}
+
+/// 存储校验任务中发现的问题类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsckIssueKind {
+    /// 文件不存在
+    Missing,
+    /// 文件大小不一致
+    SizeMismatch,
+    /// 校验值不一致
+    HashMismatch,
+    /// 无法读取文件内容
+    Unreadable,
+}
+
+/// 存储校验任务中的问题文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsckIssue {
+    pub path: String,
+    pub kind: FsckIssueKind,
+    pub detail: String,
+}
+
+/// 存储校验任务的报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FsckReport {
+    /// 校验值一致的文件数
+    pub checksum_verified: u64,
+    /// 仅校验大小的文件数（驱动不提供校验值且不是本地存储）
+    pub size_verified: u64,
+    /// 首次记录校验值的文件数
+    pub baselined: u64,
+    /// 内容已正常修改、更新了记录的文件数
+    pub changed: u64,
+    pub issues: Vec<FsckIssue>,
+    /// 超出上限未列出的问题数
+    pub issues_truncated: u64,
+}
+
+impl FsckReport {
+    /// 报告中最多列出的问题数
+    const MAX_ISSUES: usize = 1000;
+
+    pub fn issue(&mut self, path: &str, kind: FsckIssueKind, detail: String) {
+        if self.issues.len() < Self::MAX_ISSUES {
+            self.issues.push(FsckIssue { path: path.to_string(), kind, detail });
+        } else {
+            self.issues_truncated += 1;
+        }
+    }
 }
 
 /// 任务信息
@@ -125,8 +205,14 @@ pub struct Task {
     pub files: Option<Vec<UploadFileInfo>>, // 批次上传的文件列表（用于断点续传）
     pub items: Option<Vec<String>>,         // 待处理的项目列表（复制/移动用）
     pub conflict_strategy: Option<String>,  // 冲突策略
-    #[serde(skip)]
-    pub last_saved: Option<DateTime<Utc>>,  // 上次保存时间（不序列化）
+    #[serde(default)]
+    pub verification: Option<TransferVerification>, // 跨驱动复制的传输校验结果
+    #[serde(default)]
+    pub fsck: Option<FsckReport>,           // 存储校验任务的报告
+    #[serde(default)]
+    pub buffer_size: Option<u64>,           // 跨驱动复制的缓冲区大小（字节）
+    #[serde(default)]
+    pub group_id: Option<String>,           // 所属任务组（父任务）
     #[serde(skip)]
     pub last_speed_update_time: Option<DateTime<Utc>>,  // 上次速度更新时间（不序列化）
     #[serde(skip)]
@@ -166,7 +252,10 @@ impl Task {
             files: None,
             items: None,
             conflict_strategy: None,
-            last_saved: None,
+            verification: None,
+            fsck: None,
+            buffer_size: None,
+            group_id: None,
             last_speed_update_time: None,
             last_speed_processed_size: 0,
         }
@@ -205,7 +294,10 @@ impl Task {
             files: Some(files),
             items: None,
             conflict_strategy: None,
-            last_saved: None,
+            verification: None,
+            fsck: None,
+            buffer_size: None,
+            group_id: None,
             last_speed_update_time: None,
             last_speed_processed_size: 0,
         }
@@ -245,9 +337,184 @@ impl Task {
             files: None,
             items: Some(items),
             conflict_strategy: Some(conflict_strategy),
-            last_saved: None,
+            verification: None,
+            fsck: None,
+            buffer_size: None,
+            group_id: None,
             last_speed_update_time: None,
             last_speed_processed_size: 0,
         }
     }
 }
+
+/// 任务组（父任务），组内的子任务可以整体暂停/取消/重试
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskGroup {
+    pub id: String,
+    pub name: String,
+    pub user_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TaskGroup {
+    pub fn new(name: String, user_id: Option<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            user_id,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// 文件夹上传中单个目录的进度（包含所有子目录中的文件）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryProgress {
+    pub path: String,
+    pub status: TaskStatus,
+    pub total_files: u64,
+    pub completed_files: u64,
+    pub total_size: u64,
+    pub uploaded_size: u64,
+    pub progress: f32,
+}
+
+impl DirectoryProgress {
+    /// 按目标目录下的相对目录汇总批次上传任务的文件进度，父目录排在子目录之前
+    pub fn aggregate(task: &Task) -> Vec<Self> {
+        let Some(files) = task.files.as_ref() else {
+            return Vec::new();
+        };
+        let root = task.target_path.as_deref().unwrap_or("/").trim_end_matches('/');
+        let mut dirs: std::collections::BTreeMap<String, Vec<&UploadFileInfo>> = std::collections::BTreeMap::new();
+        for file in files {
+            let Some(relative) = file.path.strip_prefix(root).map(|p| p.trim_start_matches('/')) else {
+                continue;
+            };
+            // 文件计入其所在目录及所有上级目录
+            let mut end = 0;
+            while let Some(pos) = relative[end..].find('/') {
+                end += pos;
+                dirs.entry(format!("{}/{}", root, &relative[..end])).or_default().push(file);
+                end += 1;
+            }
+        }
+        
+        let mut result: Vec<Self> = dirs.into_iter().map(|(path, files)| {
+            let total_size: u64 = files.iter().map(|f| f.size).sum();
+            let uploaded_size: u64 = files.iter().map(|f| f.uploaded_size.min(f.size)).sum();
+            let completed_files = files.iter().filter(|f| f.status == TaskStatus::Completed).count() as u64;
+            let status = if completed_files == files.len() as u64 {
+                TaskStatus::Completed
+            } else if files.iter().any(|f| f.status == TaskStatus::Failed) {
+                TaskStatus::Failed
+            } else if uploaded_size > 0 || completed_files > 0 {
+                TaskStatus::Running
+            } else {
+                TaskStatus::Pending
+            };
+            let progress = if total_size > 0 {
+                (uploaded_size as f64 / total_size as f64 * 100.0) as f32
+            } else {
+                completed_files as f32 / files.len() as f32 * 100.0
+            };
+            Self {
+                path,
+                status,
+                total_files: files.len() as u64,
+                completed_files,
+                total_size,
+                uploaded_size,
+                progress: progress.min(100.0),
+            }
+        }).collect();
+        result.sort_by(|a, b| a.path.matches('/').count().cmp(&b.path.matches('/').count()).then_with(|| a.path.cmp(&b.path)));
+        result
+    }
+}
+
+/// 任务组聚合信息（用于显示整体进度）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskGroupSummary {
+    pub id: String,
+    pub name: String,
+    pub user_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub status: TaskStatus,
+    pub total_tasks: u64,
+    pub completed_tasks: u64,
+    pub failed_tasks: u64,
+    pub total_size: u64,
+    pub processed_size: u64,
+    pub total_files: u64,
+    pub processed_files: u64,
+    pub progress: f32,
+    pub speed: f64,
+    pub eta_seconds: Option<u64>,
+}
+
+impl TaskGroupSummary {
+    /// 根据子任务计算聚合进度和状态
+    pub fn aggregate(group: &TaskGroup, tasks: &[&Task]) -> Self {
+        let total_size: u64 = tasks.iter().map(|t| t.total_size).sum();
+        let processed_size: u64 = tasks.iter().map(|t| t.processed_size).sum();
+        let speed: f64 = tasks.iter()
+            .filter(|t| t.status == TaskStatus::Running)
+            .map(|t| t.speed)
+            .sum();
+        
+        // 有大小信息时按字节计算，否则按子任务进度平均
+        let progress = if total_size > 0 {
+            (processed_size as f64 / total_size as f64 * 100.0) as f32
+        } else if !tasks.is_empty() {
+            tasks.iter().map(|t| t.progress).sum::<f32>() / tasks.len() as f32
+        } else {
+            0.0
+        };
+        let eta_seconds = if speed > 0.0 && total_size > processed_size {
+            Some(((total_size - processed_size) as f64 / speed) as u64)
+        } else {
+            None
+        };
+        
+        Self {
+            id: group.id.clone(),
+            name: group.name.clone(),
+            user_id: group.user_id.clone(),
+            created_at: group.created_at,
+            status: Self::group_status(tasks),
+            total_tasks: tasks.len() as u64,
+            completed_tasks: tasks.iter().filter(|t| t.status == TaskStatus::Completed).count() as u64,
+            failed_tasks: tasks.iter().filter(|t| t.status == TaskStatus::Failed).count() as u64,
+            total_size,
+            processed_size,
+            total_files: tasks.iter().map(|t| t.total_files).sum(),
+            processed_files: tasks.iter().map(|t| t.processed_files).sum(),
+            progress: progress.min(100.0),
+            speed,
+            eta_seconds,
+        }
+    }
+    
+    /// 组状态：有运行中的为运行中，其次等待、暂停；全部完成为完成；否则失败/中断/取消
+    fn group_status(tasks: &[&Task]) -> TaskStatus {
+        let any = |s: TaskStatus| tasks.iter().any(|t| t.status == s);
+        if tasks.is_empty() {
+            TaskStatus::Pending
+        } else if any(TaskStatus::Running) {
+            TaskStatus::Running
+        } else if any(TaskStatus::Pending) {
+            TaskStatus::Pending
+        } else if any(TaskStatus::Paused) {
+            TaskStatus::Paused
+        } else if tasks.iter().all(|t| t.status == TaskStatus::Completed) {
+            TaskStatus::Completed
+        } else if any(TaskStatus::Failed) {
+            TaskStatus::Failed
+        } else if any(TaskStatus::Interrupted) {
+            TaskStatus::Interrupted
+        } else {
+            TaskStatus::Cancelled
+        }
+    }
+}
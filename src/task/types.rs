@@ -12,6 +12,8 @@ pub enum TaskType {
     Move,
     Delete,
     Extract,
+    Fsck,
+    StaticSite,
 }
 
 /// 任务状态
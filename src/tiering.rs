@@ -0,0 +1,419 @@
+//! Archival tiering between mounts / 挂载间的归档分层
+//!
+//! This module handles:
+//! - Tiering policy cache (hot folder, cold folder, idle days) / 分层策略缓存（热目录、冷目录、闲置天数）
+//! - Mapping of tiered files to their cold copy / 已分层文件到冷存储副本的映射
+//! - Access times of files under hot folders / 热目录下文件的访问时间
+//! - Queue of files to fetch back to the hot mount / 待取回热存储的文件队列
+//!
+//! A scheduled job moves files not accessed for `idle_days` from `<hot>/a/b.bin` to `<cold>/a/b.bin`
+//! and records the mapping, so the file stays listed at its hot path. Accessing a tiered file serves
+//! the cold copy and queues a recall that copies it back; the cold copy is kept until links handed out
+//! for it have expired, then removed by the job.
+//! 定时任务将 `idle_days` 天未访问的文件从 `<hot>/a/b.bin` 移到 `<cold>/a/b.bin` 并记录映射，文件仍显示在原路径；
+//! 访问已分层的文件时从冷存储读取，并排队将其复制回热存储；冷存储副本保留到已发出的链接过期，之后由定时任务删除。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::Notify;
+
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Errors kept in the run status / 运行结果中保留的错误数
+const MAX_STATUS_ERRORS: usize = 50;
+
+/// Tiering policy / 分层策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieringPolicy {
+    pub id: String,
+    pub name: String,
+    /// Virtual folder files are moved from / 文件移出的虚拟目录
+    pub hot_path: String,
+    /// Virtual folder files are moved to / 文件移入的虚拟目录
+    pub cold_path: String,
+    /// Days without access before a file is tiered / 文件多少天未访问后分层
+    pub idle_days: u32,
+    /// Smaller files stay hot, 0 = any size / 小于该大小的文件不分层，0 表示不限
+    #[serde(default)]
+    pub min_size: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl TieringPolicy {
+    /// Cold path of a file under the hot folder, `None` outside it / 热目录下文件对应的冷存储路径
+    pub fn cold_target(&self, hot_file: &str) -> Option<String> {
+        let hot = fix_and_clean_path(&self.hot_path);
+        let file = fix_and_clean_path(hot_file);
+        if !is_sub_path(&hot, &file) || file == hot {
+            return None;
+        }
+        let rel = file[hot.len()..].trim_start_matches('/');
+        Some(format!("{}/{}", fix_and_clean_path(&self.cold_path).trim_end_matches('/'), rel))
+    }
+
+    /// Whether a file is idle long enough to be tiered / 文件是否闲置足够久
+    ///
+    /// The last access falls back to the modification time; files with neither stay hot.
+    /// 没有访问记录时使用修改时间，两者都没有时不分层。
+    pub fn is_idle(&self, last_access: Option<DateTime<Utc>>, modified: Option<&str>, now: DateTime<Utc>) -> bool {
+        let modified = modified.and_then(parse_time);
+        let last = match (last_access, modified) {
+            (Some(a), Some(m)) => a.max(m),
+            (a, m) => match a.or(m) {
+                Some(t) => t,
+                None => return false,
+            },
+        };
+        now - last >= Duration::days(self.idle_days as i64)
+    }
+}
+
+/// Check that hot and cold folders are usable / 校验热目录与冷目录
+pub fn validate_paths(hot_path: &str, cold_path: &str) -> Result<(), String> {
+    let hot = fix_and_clean_path(hot_path);
+    let cold = fix_and_clean_path(cold_path);
+    if hot == "/" || cold == "/" {
+        return Err("热目录和冷目录不能为根目录".to_string());
+    }
+    if is_sub_path(&hot, &cold) || is_sub_path(&cold, &hot) {
+        return Err("热目录和冷目录不能相互包含".to_string());
+    }
+    Ok(())
+}
+
+/// Parse a driver modification time (RFC 3339 or `YYYY-MM-DD HH:MM:SS`) / 解析驱动返回的修改时间
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).map(|t| t.with_timezone(&Utc)).ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok().map(|t| t.and_utc()))
+}
+
+/// A file moved to the cold folder / 已移到冷目录的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieredFile {
+    /// Virtual path the file is listed at / 文件显示的虚拟路径
+    pub path: String,
+    pub policy_id: String,
+    /// Virtual path of the cold copy / 冷存储副本的虚拟路径
+    pub cold_path: String,
+    pub size: u64,
+    pub modified: Option<String>,
+    pub tiered_at: DateTime<Utc>,
+}
+
+/// Result of one policy run / 单次策略运行结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TieringStatus {
+    pub running: bool,
+    pub success: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Files moved to the cold folder / 移到冷目录的文件数
+    pub tiered: u64,
+    pub tiered_bytes: u64,
+    /// Files checked and kept hot / 检查后保留的文件数
+    pub kept: u64,
+    pub errors: Vec<String>,
+}
+
+impl TieringStatus {
+    pub fn started() -> Self {
+        Self {
+            running: true,
+            started_at: Some(Utc::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(&mut self, message: String) {
+        if self.errors.len() < MAX_STATUS_ERRORS {
+            self.errors.push(message);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.running = false;
+        self.success = self.errors.is_empty();
+        self.finished_at = Some(Utc::now());
+    }
+}
+
+/// Tiering manager / 分层管理器
+pub struct TieringManager {
+    policies: RwLock<Vec<TieringPolicy>>,
+    /// Tiered files by virtual path / 按虚拟路径索引的已分层文件
+    files: RwLock<BTreeMap<String, TieredFile>>,
+    /// Accesses not yet written to the database / 尚未写入数据库的访问时间
+    accessed: Mutex<HashMap<String, DateTime<Utc>>>,
+    status: RwLock<HashMap<String, TieringStatus>>,
+    running: Mutex<HashSet<String>>,
+    /// Files waiting to be fetched back / 等待取回的文件
+    recalls: Mutex<Vec<String>>,
+    recall_notify: Notify,
+}
+
+/// `tiering_policies` row: id, name, hot_path, cold_path, idle_days, min_size, enabled, created_at
+type PolicyRow = (String, String, String, String, i64, i64, bool, String);
+/// `tiered_files` row: path, policy_id, cold_path, size, modified, tiered_at
+type FileRow = (String, String, String, i64, Option<String>, String);
+
+fn parse_rfc3339(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+impl TieringManager {
+    pub fn new() -> Self {
+        Self {
+            policies: RwLock::new(Vec::new()),
+            files: RwLock::new(BTreeMap::new()),
+            accessed: Mutex::new(HashMap::new()),
+            status: RwLock::new(HashMap::new()),
+            running: Mutex::new(HashSet::new()),
+            recalls: Mutex::new(Vec::new()),
+            recall_notify: Notify::new(),
+        }
+    }
+
+    /// Load policies and tiered files from database / 从数据库加载策略与已分层文件
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<PolicyRow> = sqlx::query_as(
+            "SELECT id, name, hot_path, cold_path, idle_days, min_size, enabled, created_at FROM tiering_policies"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+        let policies = rows.into_iter()
+            .map(|(id, name, hot_path, cold_path, idle_days, min_size, enabled, created_at)| TieringPolicy {
+                id,
+                name,
+                hot_path,
+                cold_path,
+                idle_days: idle_days.max(0) as u32,
+                min_size: min_size.max(0) as u64,
+                enabled,
+                created_at: parse_rfc3339(&created_at),
+            })
+            .collect();
+
+        let rows: Vec<FileRow> = sqlx::query_as(
+            "SELECT path, policy_id, cold_path, size, modified, tiered_at FROM tiered_files WHERE recalled_at IS NULL"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+        let files = rows.into_iter()
+            .map(|(path, policy_id, cold_path, size, modified, tiered_at)| {
+                (path.clone(), TieredFile {
+                    path,
+                    policy_id,
+                    cold_path,
+                    size: size.max(0) as u64,
+                    modified,
+                    tiered_at: parse_rfc3339(&tiered_at),
+                })
+            })
+            .collect();
+
+        *self.policies.write() = policies;
+        *self.files.write() = files;
+        Ok(())
+    }
+
+    /// List policies / 列出策略
+    pub fn list(&self) -> Vec<TieringPolicy> {
+        self.policies.read().clone()
+    }
+
+    /// Get policy by id / 获取策略
+    pub fn get(&self, id: &str) -> Option<TieringPolicy> {
+        self.policies.read().iter().find(|p| p.id == id).cloned()
+    }
+
+    /// Insert or replace a policy in the cache / 新增或替换缓存中的策略
+    pub fn upsert(&self, policy: TieringPolicy) {
+        let mut policies = self.policies.write();
+        match policies.iter_mut().find(|p| p.id == policy.id) {
+            Some(existing) => *existing = policy,
+            None => policies.push(policy),
+        }
+    }
+
+    /// Remove a policy from the cache, its tiered files stay mapped / 从缓存移除策略，已分层文件的映射保留
+    pub fn remove(&self, id: &str) -> bool {
+        self.status.write().remove(id);
+        let mut policies = self.policies.write();
+        let before = policies.len();
+        policies.retain(|p| p.id != id);
+        policies.len() != before
+    }
+
+    /// Tiered file at a virtual path / 虚拟路径处的已分层文件
+    pub fn tiered(&self, path: &str) -> Option<TieredFile> {
+        self.files.read().get(&fix_and_clean_path(path)).cloned()
+    }
+
+    /// Tiered files directly in a folder / 目录下直接包含的已分层文件
+    pub fn children(&self, dir: &str) -> Vec<TieredFile> {
+        let prefix = format!("{}/", fix_and_clean_path(dir).trim_end_matches('/'));
+        self.files.read()
+            .range(prefix.clone()..)
+            .take_while(|(path, _)| path.starts_with(&prefix))
+            .filter(|(path, _)| !path[prefix.len()..].contains('/'))
+            .map(|(_, file)| file.clone())
+            .collect()
+    }
+
+    /// Tiered files at or below a path / 路径本身及其下的已分层文件
+    pub fn under(&self, path: &str) -> Vec<TieredFile> {
+        let path = fix_and_clean_path(path);
+        self.files.read()
+            .values()
+            .filter(|f| is_sub_path(&path, &f.path))
+            .cloned()
+            .collect()
+    }
+
+    /// All tiered files / 全部已分层文件
+    pub fn files(&self) -> Vec<TieredFile> {
+        self.files.read().values().cloned().collect()
+    }
+
+    pub fn insert_file(&self, file: TieredFile) {
+        self.files.write().insert(file.path.clone(), file);
+    }
+
+    pub fn remove_file(&self, path: &str) -> Option<TieredFile> {
+        self.files.write().remove(&fix_and_clean_path(path))
+    }
+
+    /// Remember an access to a file under a hot folder / 记录热目录下文件的访问
+    pub fn record_access(&self, path: &str) {
+        let path = fix_and_clean_path(path);
+        let watched = self.policies.read().iter()
+            .any(|p| p.enabled && is_sub_path(&fix_and_clean_path(&p.hot_path), &path));
+        if watched {
+            self.accessed.lock().insert(path, Utc::now());
+        }
+    }
+
+    /// Take accesses not yet written to the database / 取出尚未写入数据库的访问时间
+    pub fn take_accesses(&self) -> HashMap<String, DateTime<Utc>> {
+        std::mem::take(&mut *self.accessed.lock())
+    }
+
+    /// Queue a tiered file to be fetched back / 将已分层文件加入取回队列
+    pub fn queue_recall(&self, path: &str) {
+        let path = fix_and_clean_path(path);
+        let mut recalls = self.recalls.lock();
+        if !recalls.contains(&path) {
+            recalls.push(path);
+        }
+        drop(recalls);
+        self.recall_notify.notify_one();
+    }
+
+    /// Wait for queued recalls and take them / 等待并取出排队的取回请求
+    pub async fn next_recalls(&self) -> Vec<String> {
+        loop {
+            let recalls = std::mem::take(&mut *self.recalls.lock());
+            if !recalls.is_empty() {
+                return recalls;
+            }
+            self.recall_notify.notified().await;
+        }
+    }
+
+    /// Last (or current) run status / 最近一次（或正在进行的）运行结果
+    pub fn last_status(&self, id: &str) -> Option<TieringStatus> {
+        self.status.read().get(id).cloned()
+    }
+
+    /// Mark a policy as running, `false` if it already is / 标记为运行中，已在运行时返回 `false`
+    pub fn try_begin(&self, id: &str) -> bool {
+        if !self.running.lock().insert(id.to_string()) {
+            return false;
+        }
+        self.status.write().insert(id.to_string(), TieringStatus::started());
+        true
+    }
+
+    /// Store the run result and clear the running flag / 保存运行结果并清除运行标记
+    pub fn finish(&self, id: &str, mut status: TieringStatus) -> TieringStatus {
+        status.finish();
+        self.status.write().insert(id.to_string(), status.clone());
+        self.running.lock().remove(id);
+        status
+    }
+}
+
+impl Default for TieringManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> TieringPolicy {
+        TieringPolicy {
+            id: "p1".to_string(),
+            name: "Archive".to_string(),
+            hot_path: "/ssd/data".to_string(),
+            cold_path: "/cloud/archive/".to_string(),
+            idle_days: 30,
+            min_size: 0,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_cold_target_and_idle() {
+        let p = policy();
+        assert_eq!(p.cold_target("/ssd/data/a/b.bin").as_deref(), Some("/cloud/archive/a/b.bin"));
+        assert_eq!(p.cold_target("/ssd/data"), None);
+        assert_eq!(p.cold_target("/ssd/database/x"), None);
+
+        let now = Utc::now();
+        let old = (now - Duration::days(40)).to_rfc3339();
+        assert!(p.is_idle(None, Some(&old), now));
+        assert!(!p.is_idle(Some(now - Duration::days(2)), Some(&old), now));
+        assert!(p.is_idle(None, Some("2000-01-01 00:00:00"), now));
+        assert!(!p.is_idle(None, Some("unknown"), now));
+
+        assert!(validate_paths("/ssd/data", "/cloud/archive").is_ok());
+        assert!(validate_paths("/ssd", "/ssd/archive").is_err());
+        assert!(validate_paths("/", "/cloud").is_err());
+    }
+
+    #[test]
+    fn test_tiered_children() {
+        let manager = TieringManager::new();
+        for path in ["/ssd/data/a.bin", "/ssd/data/sub/b.bin", "/ssd/data2/c.bin"] {
+            manager.insert_file(TieredFile {
+                path: path.to_string(),
+                policy_id: "p1".to_string(),
+                cold_path: format!("/cloud{}", path),
+                size: 1,
+                modified: None,
+                tiered_at: Utc::now(),
+            });
+        }
+        let names: Vec<String> = manager.children("/ssd/data/").into_iter().map(|f| f.path).collect();
+        assert_eq!(names, vec!["/ssd/data/a.bin".to_string()]);
+        assert_eq!(manager.under("/ssd/data").len(), 2);
+        assert!(manager.tiered("/ssd/data/sub/b.bin").is_some());
+    }
+}
@@ -0,0 +1,200 @@
+//! Native HTTPS and ACME certificates / 原生 HTTPS 与 ACME 自动证书
+//!
+//! This module handles:
+//! - HTTP-01 challenge tokens served on the plain HTTP port / 在 HTTP 端口上提供的验证令牌
+//! - Certificate issuance and renewal with an ACME CA (Let's Encrypt) / 向 ACME CA 申请与续期证书
+//! - Certificate expiry checks / 证书过期检查
+//!
+//! The account key, certificate chain and private key live in the ACME directory
+//! (`account.json`, `cert.pem`, `key.pem`); the HTTPS listener reloads them after renewal.
+//! 账号、证书链和私钥保存在 ACME 目录中，续期后 HTTPS 监听会重新加载。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, NewAccount, NewOrder,
+    OrderStatus, RetryPolicy,
+};
+use parking_lot::RwLock;
+
+use crate::config::AcmeConfig;
+
+/// Renew when the certificate expires within this many days / 证书剩余有效期少于该天数时续期
+pub const RENEW_BEFORE_DAYS: i64 = 30;
+/// Path prefix of HTTP-01 challenges / HTTP-01 验证路径前缀
+pub const CHALLENGE_PATH_PREFIX: &str = "/.well-known/acme-challenge/";
+
+/// Pending HTTP-01 challenges (token → key authorization) / 待验证的 HTTP-01 令牌
+#[derive(Default)]
+pub struct AcmeChallenges {
+    tokens: RwLock<HashMap<String, String>>,
+}
+
+impl AcmeChallenges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key authorization to answer for `token` / 令牌对应的应答内容
+    pub fn get(&self, token: &str) -> Option<String> {
+        self.tokens.read().get(token).cloned()
+    }
+
+    fn insert(&self, token: String, key_authorization: String) {
+        self.tokens.write().insert(token, key_authorization);
+    }
+
+    fn clear(&self) {
+        self.tokens.write().clear();
+    }
+}
+
+/// Certificate and key paths inside the ACME directory / ACME 目录中的证书与私钥路径
+pub fn acme_cert_paths(dir: &Path) -> (PathBuf, PathBuf) {
+    (dir.join("cert.pem"), dir.join("key.pem"))
+}
+
+/// `notAfter` of the first certificate in a PEM chain / PEM 证书链中第一张证书的过期时间
+pub fn cert_expiry(pem: &[u8]) -> Result<DateTime<Utc>> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(pem).map_err(|e| anyhow!("Invalid PEM: {}", e))?;
+    let cert = pem.parse_x509().map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+    DateTime::from_timestamp(cert.validity().not_after.timestamp(), 0)
+        .ok_or_else(|| anyhow!("Invalid certificate expiry"))
+}
+
+/// Whether the certificate at `cert_path` is missing, unreadable or close to expiry
+/// 证书不存在、无法解析或即将过期时返回 true
+pub fn needs_renewal(cert_path: &Path, now: DateTime<Utc>) -> bool {
+    match std::fs::read(cert_path).map_err(anyhow::Error::from).and_then(|pem| cert_expiry(&pem)) {
+        Ok(expiry) => expiry - now < chrono::Duration::days(RENEW_BEFORE_DAYS),
+        Err(_) => true,
+    }
+}
+
+/// Write a file via a temp file so a crash never leaves half a key / 通过临时文件写入，避免崩溃时留下不完整的文件
+fn write_atomic(path: &Path, content: &[u8], private: bool) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Load the saved ACME account or register a new one / 加载已保存的 ACME 账号，不存在时注册
+async fn load_account(config: &AcmeConfig, dir: &Path) -> Result<Account> {
+    let path = dir.join("account.json");
+    if let Ok(content) = std::fs::read_to_string(&path) {
+        let credentials: AccountCredentials = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid ACME account file {:?}", path))?;
+        return Ok(Account::builder()?.from_credentials(credentials).await?);
+    }
+
+    let contact = format!("mailto:{}", config.email.trim());
+    let contacts: Vec<&str> = if config.email.trim().is_empty() { Vec::new() } else { vec![contact.as_str()] };
+    let (account, credentials) = Account::builder()?
+        .create(
+            &NewAccount {
+                contact: &contacts,
+                terms_of_service_agreed: true,
+                only_return_existing: false,
+            },
+            config.directory_url.clone(),
+            None,
+        )
+        .await?;
+    write_atomic(&path, serde_json::to_string_pretty(&credentials)?.as_bytes(), true)?;
+    tracing::info!("Registered ACME account at {}", config.directory_url);
+    Ok(account)
+}
+
+/// Obtain a certificate for the configured domains, answering HTTP-01 challenges via `challenges`
+/// 为配置的域名申请证书，通过 `challenges` 应答 HTTP-01 验证
+///
+/// The HTTP port listener must already be running. / 调用前 HTTP 端口监听必须已启动。
+pub async fn issue_certificate(config: &AcmeConfig, dir: &Path, challenges: &AcmeChallenges) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let account = load_account(config, dir).await?;
+
+    let identifiers: Vec<Identifier> = config.domains.iter()
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+        .map(|d| Identifier::Dns(d.to_string()))
+        .collect();
+    let mut order = account.new_order(&NewOrder::new(&identifiers)).await?;
+
+    let result = async {
+        let mut authorizations = order.authorizations();
+        while let Some(authz) = authorizations.next().await {
+            let mut authz = authz?;
+            match authz.status {
+                AuthorizationStatus::Pending => {}
+                AuthorizationStatus::Valid => continue,
+                status => return Err(anyhow!("Authorization is {:?}", status)),
+            }
+            let mut challenge = authz.challenge(ChallengeType::Http01)
+                .ok_or_else(|| anyhow!("CA offered no http-01 challenge"))?;
+            challenges.insert(challenge.token.clone(), challenge.key_authorization().as_str().to_string());
+            challenge.set_ready().await?;
+        }
+
+        let retry = RetryPolicy::new().timeout(Duration::from_secs(120));
+        let status = order.poll_ready(&retry).await?;
+        if status != OrderStatus::Ready {
+            let error = order.state().error.as_ref().map(|e| e.to_string()).unwrap_or_default();
+            return Err(anyhow!("Order is {:?} {}", status, error));
+        }
+        let key_pem = order.finalize().await?;
+        let cert_pem = order.poll_certificate(&retry).await?;
+        Ok((cert_pem, key_pem))
+    }.await;
+    challenges.clear();
+    let (cert_pem, key_pem) = result?;
+
+    let (cert_path, key_path) = acme_cert_paths(dir);
+    write_atomic(&key_path, key_pem.as_bytes(), true)?;
+    write_atomic(&cert_path, cert_pem.as_bytes(), false)?;
+    tracing::info!("Issued certificate for {:?}, expires {:?}", config.domains, cert_expiry(cert_pem.as_bytes()).ok());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed, valid 2024-01-01 .. 2034-01-01 / 自签名证书
+    const TEST_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBdDCCARmgAwIBAgIUMUkpeXMzmxOtGr5m+d0zVGxQbUowCgYIKoZIzj0EAwIw
+DzENMAsGA1UEAwwEdGVzdDAeFw0yNDAxMDEwMDAwMDBaFw0zNDAxMDEwMDAwMDBa
+MA8xDTALBgNVBAMMBHRlc3QwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAR1O8dK
+OCxiAlusYdFP4U/ZR0zKt8rGBwQW/f3rmsZz+X07lrRhnOmPmoF2lxkg4YO0n8Pi
+8DScU87cdVEuBL3wo1MwUTAdBgNVHQ4EFgQU7YvrSB+EjlWiZW4ownTSBNTTZIgw
+HwYDVR0jBBgwFoAU7YvrSB+EjlWiZW4ownTSBNTTZIgwDwYDVR0TAQH/BAUwAwEB
+/zAKBggqhkjOPQQDAgNJADBGAiEA400s+2cPYv/N9RVQ+7375lJtetqEfY0O/B36
+kCpftHwCIQDuYBLwU2YTrevF1nFeNy0u9SpkTTtSjiybTl6nEhih5Q==
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_challenges() {
+        let challenges = AcmeChallenges::new();
+        challenges.insert("token".to_string(), "token.thumb".to_string());
+        assert_eq!(challenges.get("token").as_deref(), Some("token.thumb"));
+        challenges.clear();
+        assert!(challenges.get("token").is_none());
+    }
+
+    #[test]
+    fn test_cert_expiry() {
+        let expiry = cert_expiry(TEST_CERT.as_bytes()).unwrap();
+        assert_eq!(expiry.to_rfc3339(), "2034-01-01T00:00:00+00:00");
+        assert!(cert_expiry(b"not a certificate").is_err());
+        assert!(needs_renewal(Path::new("/nonexistent/cert.pem"), Utc::now()));
+    }
+}
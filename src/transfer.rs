@@ -0,0 +1,264 @@
+//! Transfer control module / 传输控制模块
+//!
+//! This module handles:
+//! - Copy buffer size settings / 复制缓冲区大小设置
+//! - Shared buffer pool for cross-driver copies / 跨驱动复制共享的缓冲区池
+//! - Bounded read-ahead streaming (backpressure) / 有界预读流（背压）
+//!
+//! Each copy holds at most `COPY_CHANNEL_CAPACITY + 2` buffers (queued + being read + being written),
+//! so memory stays bounded no matter how fast the source is compared to the destination.
+//! 每个复制最多占用 `COPY_CHANNEL_CAPACITY + 2` 个缓冲区（排队中 + 读取中 + 写入中），
+//! 源端比目标端快时不会无限占用内存。
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Default copy buffer size (4MB) / 默认复制缓冲区大小
+pub const DEFAULT_COPY_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+/// Minimum copy buffer size (64KB) / 最小复制缓冲区大小
+pub const MIN_COPY_BUFFER_SIZE: usize = 64 * 1024;
+/// Maximum copy buffer size (64MB) / 最大复制缓冲区大小
+pub const MAX_COPY_BUFFER_SIZE: usize = 64 * 1024 * 1024;
+/// Chunks queued between reader and writer / 读写之间排队的数据块数
+pub const COPY_CHANNEL_CAPACITY: usize = 2;
+/// Max bytes kept idle in the pool / 池中最多保留的空闲字节数
+const MAX_IDLE_POOL_BYTES: usize = 128 * 1024 * 1024;
+
+/// Clamp buffer size into the allowed range / 将缓冲区大小限制在允许范围内
+pub fn clamp_buffer_size(size: usize) -> usize {
+    size.clamp(MIN_COPY_BUFFER_SIZE, MAX_COPY_BUFFER_SIZE)
+}
+
+/// Transfer settings cache / 传输设置缓存
+pub struct TransferSettings {
+    /// Default copy buffer size in bytes / 默认复制缓冲区大小(字节)
+    buffer_size: AtomicUsize,
+}
+
+impl TransferSettings {
+    pub fn new() -> Self {
+        Self {
+            buffer_size: AtomicUsize::new(DEFAULT_COPY_BUFFER_SIZE),
+        }
+    }
+
+    /// Load settings from database / 从数据库加载设置
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let size: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM site_settings WHERE key = 'copy_buffer_size'"
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some((s,)) = size {
+            self.set_buffer_size(s.parse().unwrap_or(DEFAULT_COPY_BUFFER_SIZE));
+        }
+
+        Ok(())
+    }
+
+    /// Get default copy buffer size / 获取默认复制缓冲区大小
+    pub fn get_buffer_size(&self) -> usize {
+        self.buffer_size.load(Ordering::SeqCst)
+    }
+
+    /// Set default copy buffer size, 0 = default / 设置默认复制缓冲区大小，0表示默认值
+    pub fn set_buffer_size(&self, size: usize) {
+        let size = if size == 0 { DEFAULT_COPY_BUFFER_SIZE } else { clamp_buffer_size(size) };
+        self.buffer_size.store(size, Ordering::SeqCst);
+    }
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reusable buffer pool keyed by buffer size / 按大小复用的缓冲区池
+pub struct BufferPool {
+    idle: Mutex<HashMap<usize, Vec<Vec<u8>>>>,
+    idle_bytes: AtomicUsize,
+    max_idle_bytes: usize,
+}
+
+/// Process-wide pool shared by all copy tasks / 所有复制任务共享的缓冲区池
+static BUFFER_POOL: Lazy<Arc<BufferPool>> = Lazy::new(|| Arc::new(BufferPool::new(MAX_IDLE_POOL_BYTES)));
+
+/// Get the shared buffer pool / 获取共享缓冲区池
+pub fn buffer_pool() -> Arc<BufferPool> {
+    BUFFER_POOL.clone()
+}
+
+impl BufferPool {
+    pub fn new(max_idle_bytes: usize) -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            idle_bytes: AtomicUsize::new(0),
+            max_idle_bytes,
+        }
+    }
+
+    /// Take a buffer of `size` bytes (reused if available) / 获取指定大小的缓冲区
+    pub fn get(self: &Arc<Self>, size: usize) -> PooledBuffer {
+        let reused = self.idle.lock().get_mut(&size).and_then(|list| list.pop());
+        let buf = match reused {
+            Some(buf) => {
+                self.idle_bytes.fetch_sub(size, Ordering::SeqCst);
+                buf
+            }
+            None => vec![0u8; size],
+        };
+        PooledBuffer { buf: Some(buf), len: 0, pool: self.clone() }
+    }
+
+    /// Bytes currently idle in the pool / 池中空闲字节数
+    pub fn idle_bytes(&self) -> usize {
+        self.idle_bytes.load(Ordering::SeqCst)
+    }
+
+    fn put(&self, buf: Vec<u8>) {
+        let size = buf.len();
+        if self.idle_bytes.load(Ordering::SeqCst) + size > self.max_idle_bytes {
+            return;
+        }
+        self.idle_bytes.fetch_add(size, Ordering::SeqCst);
+        self.idle.lock().entry(size).or_default().push(buf);
+    }
+}
+
+/// Buffer returned to its pool on drop / 丢弃时归还到池的缓冲区
+pub struct PooledBuffer {
+    buf: Option<Vec<u8>>,
+    len: usize,
+    pool: Arc<BufferPool>,
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    /// Filled part of the buffer / 已填充的数据
+    fn deref(&self) -> &[u8] {
+        &self.buf.as_ref().expect("buffer present until drop")[..self.len]
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            self.pool.put(buf);
+        }
+    }
+}
+
+/// Read `reader` in a background task, sending filled buffers through a bounded channel
+/// 在后台任务中读取数据，通过有界通道发送已填充的缓冲区
+///
+/// The reader stops when the receiver is dropped (cancel) and waits while the channel
+/// is full (backpressure). Empty channel end = EOF.
+/// 接收端被丢弃（取消）时读取停止；通道满时等待（背压）。通道结束表示EOF。
+pub fn spawn_buffered_reader(
+    mut reader: Box<dyn AsyncRead + Unpin + Send>,
+    buffer_size: usize,
+) -> mpsc::Receiver<std::io::Result<PooledBuffer>> {
+    let (tx, rx) = mpsc::channel(COPY_CHANNEL_CAPACITY);
+    let pool = buffer_pool();
+    let buffer_size = clamp_buffer_size(buffer_size);
+
+    tokio::spawn(async move {
+        loop {
+            let mut chunk = pool.get(buffer_size);
+            let read = {
+                let buf = chunk.buf.as_mut().expect("buffer present until drop");
+                fill_buffer(&mut reader, buf).await
+            };
+            match read {
+                Ok(0) => break,
+                Ok(n) => {
+                    chunk.len = n;
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    break;
+                }
+            }
+        }
+    });
+
+    rx
+}
+
+/// Fill the buffer as far as possible (fewer, larger writes) / 尽量填满缓冲区（减少写入次数）
+async fn fill_buffer(reader: &mut (dyn AsyncRead + Unpin + Send), buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = reader.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_pool_reuse() {
+        let pool = Arc::new(BufferPool::new(MIN_COPY_BUFFER_SIZE * 2));
+        let a = pool.get(MIN_COPY_BUFFER_SIZE);
+        let b = pool.get(MIN_COPY_BUFFER_SIZE);
+        let c = pool.get(MIN_COPY_BUFFER_SIZE);
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle_bytes(), MIN_COPY_BUFFER_SIZE * 2);
+
+        // Over idle limit, buffer is freed / 超过空闲上限时直接释放
+        drop(c);
+        assert_eq!(pool.idle_bytes(), MIN_COPY_BUFFER_SIZE * 2);
+
+        let _d = pool.get(MIN_COPY_BUFFER_SIZE);
+        assert_eq!(pool.idle_bytes(), MIN_COPY_BUFFER_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_reader() {
+        let data: Vec<u8> = (0..MIN_COPY_BUFFER_SIZE * 3 + 17).map(|i| (i % 251) as u8).collect();
+        let reader: Box<dyn AsyncRead + Unpin + Send> = Box::new(std::io::Cursor::new(data.clone()));
+        let mut rx = spawn_buffered_reader(reader, MIN_COPY_BUFFER_SIZE);
+
+        let mut out = Vec::new();
+        let mut chunks = 0;
+        while let Some(chunk) = rx.recv().await {
+            out.extend_from_slice(&chunk.unwrap());
+            chunks += 1;
+        }
+        assert_eq!(out, data);
+        assert_eq!(chunks, 4);
+    }
+
+    #[test]
+    fn test_settings_clamp() {
+        let settings = TransferSettings::new();
+        assert_eq!(settings.get_buffer_size(), DEFAULT_COPY_BUFFER_SIZE);
+        settings.set_buffer_size(1);
+        assert_eq!(settings.get_buffer_size(), MIN_COPY_BUFFER_SIZE);
+        settings.set_buffer_size(usize::MAX);
+        assert_eq!(settings.get_buffer_size(), MAX_COPY_BUFFER_SIZE);
+        settings.set_buffer_size(0);
+        assert_eq!(settings.get_buffer_size(), DEFAULT_COPY_BUFFER_SIZE);
+    }
+}
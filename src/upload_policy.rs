@@ -0,0 +1,218 @@
+//! Upload policy by file extension and size / 按扩展名与大小限制上传
+//!
+//! A mount can accept only some extensions (allow list), refuse others (deny list) and cap the
+//! size of one file; the same lists are also set on user groups next to their upload size
+//! limit. An upload must pass both the mount's and the user's policy. Extensions are compared
+//! case-insensitively against the end of the file name, so `tar.gz` works as well as `gz`; a
+//! non-empty allow list refuses files without a listed extension, and the deny list is checked
+//! after it. For several groups the most permissive lists win: a group without an allow list
+//! lifts it, otherwise the allow lists are merged, and an extension stays denied only while
+//! every group denies it.
+//! 挂载点可以只接受部分扩展名（允许列表）、拒绝某些扩展名（拒绝列表）并限制单个文件的大小；用户组在上传上限之外
+//! 也可以设置同样的列表。上传必须同时通过挂载点与用户的策略。扩展名不区分大小写地与文件名结尾比较，因此 `tar.gz`
+//! 与 `gz` 均可使用；允许列表不为空时拒绝没有列出扩展名的文件，拒绝列表在其后检查。属于多个用户组时取最宽松的列表：
+//! 任一用户组没有允许列表即不限制，否则合并允许列表；只有所有用户组都拒绝的扩展名才会被拒绝。
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::storage::ConfigItem;
+
+/// Config key of the mount's allowed extensions / 挂载点允许的扩展名配置项
+pub const ALLOW_EXTENSIONS_KEY: &str = "upload_allow_ext";
+/// Config key of the mount's denied extensions / 挂载点拒绝的扩展名配置项
+pub const DENY_EXTENSIONS_KEY: &str = "upload_deny_ext";
+/// Config key of the mount's max file size / 挂载点单文件上限配置项
+pub const MAX_SIZE_KEY: &str = "upload_max_size";
+
+/// Extension lists and size limit / 扩展名列表与大小上限
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UploadPolicy {
+    /// Empty accepts every extension / 为空表示接受所有扩展名
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    /// Bytes per file, 0 for unlimited / 单个文件的字节数，0 表示不限
+    pub max_size: u64,
+}
+
+/// Why an upload was refused / 上传被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// Extension missing from the allow list / 扩展名不在允许列表中
+    NotAllowed,
+    /// Extension on the deny list / 扩展名在拒绝列表中
+    Denied(String),
+    /// Larger than the limit in bytes / 超过字节上限
+    TooLarge(u64),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::NotAllowed => write!(f, "file type is not allowed"),
+            Violation::Denied(ext) => write!(f, "files of type .{} are not allowed", ext),
+            Violation::TooLarge(limit) => write!(f, "file exceeds the limit of {} bytes", limit),
+        }
+    }
+}
+
+/// Split an extension list (commas, spaces or new lines) into clean lowercase extensions
+/// 将扩展名列表（逗号、空格或换行分隔）拆分为规范化的小写扩展名
+pub fn parse_extensions(text: &str) -> Vec<String> {
+    let mut extensions: Vec<String> = text
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .map(|e| e.trim_start_matches(['*', '.']).trim_end_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect();
+    extensions.sort();
+    extensions.dedup();
+    extensions
+}
+
+/// Normalize an extension list for storage, `None` when empty / 规范化待保存的扩展名列表，为空时返回 `None`
+pub fn normalize_extensions(text: &str) -> Option<String> {
+    Some(parse_extensions(text).join(",")).filter(|e| !e.is_empty())
+}
+
+/// Longest listed extension the name ends with / 文件名结尾匹配的最长扩展名
+fn matching<'a>(extensions: &'a [String], name: &str) -> Option<&'a str> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name).to_lowercase();
+    extensions
+        .iter()
+        .filter(|ext| name.len() > ext.len() + 1 && name.ends_with(ext.as_str()) && name[..name.len() - ext.len()].ends_with('.'))
+        .map(String::as_str)
+        .max_by_key(|ext| ext.len())
+}
+
+impl UploadPolicy {
+    /// Read from a driver config; empty or invalid values lift the limit
+    /// 从驱动配置读取，空值或无效值表示不限制
+    pub fn from_config(config: &Value) -> Self {
+        let text = |key: &str| config.get(key).and_then(Value::as_str).map(parse_extensions).unwrap_or_default();
+        let max_size = match config.get(MAX_SIZE_KEY) {
+            Some(Value::Number(n)) => n.as_u64(),
+            Some(Value::String(s)) => s.trim().parse().ok(),
+            _ => None,
+        }.unwrap_or(0);
+        Self { allow: text(ALLOW_EXTENSIONS_KEY), deny: text(DENY_EXTENSIONS_KEY), max_size }
+    }
+
+    /// Policy with stored lists (see [`normalize_extensions`]) / 由已保存的列表构造策略
+    pub fn from_lists(allow: Option<&str>, deny: Option<&str>, max_size: u64) -> Self {
+        Self {
+            allow: allow.map(parse_extensions).unwrap_or_default(),
+            deny: deny.map(parse_extensions).unwrap_or_default(),
+            max_size,
+        }
+    }
+
+    /// Check the file name alone / 仅检查文件名
+    pub fn check_name(&self, name: &str) -> Result<(), Violation> {
+        if !self.allow.is_empty() && matching(&self.allow, name).is_none() {
+            return Err(Violation::NotAllowed);
+        }
+        match matching(&self.deny, name) {
+            Some(ext) => Err(Violation::Denied(ext.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Check name and size, an unknown size only checks the name / 检查文件名与大小，大小未知时只检查文件名
+    pub fn check(&self, name: &str, size: Option<u64>) -> Result<(), Violation> {
+        self.check_name(name)?;
+        match (self.size_limit(), size) {
+            (Some(limit), Some(size)) if size > limit => Err(Violation::TooLarge(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Size limit, `None` for unlimited / 大小上限，`None` 表示不限
+    pub fn size_limit(&self) -> Option<u64> {
+        (self.max_size > 0).then_some(self.max_size)
+    }
+}
+
+/// Most permissive allow list: empty when any list is / 最宽松的允许列表：任一列表为空时为空
+pub fn widest_allow<'a>(lists: impl IntoIterator<Item = &'a [String]>) -> Vec<String> {
+    let mut merged = Vec::new();
+    for list in lists {
+        if list.is_empty() {
+            return Vec::new();
+        }
+        merged.extend_from_slice(list);
+    }
+    merged.sort();
+    merged.dedup();
+    merged
+}
+
+/// Extensions denied by every list / 所有列表都拒绝的扩展名
+pub fn common_deny<'a>(lists: impl IntoIterator<Item = &'a [String]>) -> Vec<String> {
+    let mut lists = lists.into_iter();
+    let Some(first) = lists.next() else {
+        return Vec::new();
+    };
+    let mut common = first.to_vec();
+    for list in lists {
+        common.retain(|ext| list.contains(ext));
+    }
+    common
+}
+
+/// Common config items of the mount policy / 挂载点上传策略的通用配置项
+pub fn config_items() -> Vec<ConfigItem> {
+    vec![
+        ConfigItem::new(ALLOW_EXTENSIONS_KEY, "string")
+            .help("Only accept uploads with these extensions, comma separated, empty = any"),
+        ConfigItem::new(DENY_EXTENSIONS_KEY, "string")
+            .help("Refuse uploads with these extensions, comma separated"),
+        ConfigItem::new(MAX_SIZE_KEY, "number")
+            .default("0")
+            .help("Max size of one uploaded file in bytes, 0 = unlimited"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_extensions() {
+        assert_eq!(parse_extensions(" .JPG, png;*.tar.gz\nexe jpg "), vec!["exe", "jpg", "png", "tar.gz"]);
+        assert_eq!(normalize_extensions("Exe , .bat"), Some("bat,exe".to_string()));
+        assert_eq!(normalize_extensions(" , "), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = UploadPolicy::from_config(&json!({
+            "upload_allow_ext": "jpg, tar.gz", "upload_deny_ext": "", "upload_max_size": "100"
+        }));
+        assert_eq!(policy.check("a/Photo.JPG", Some(100)), Ok(()));
+        assert_eq!(policy.check("backup.tar.gz", None), Ok(()));
+        assert_eq!(policy.check("backup.gz", None), Err(Violation::NotAllowed));
+        assert_eq!(policy.check("jpg", None), Err(Violation::NotAllowed));
+        assert_eq!(policy.check("README", None), Err(Violation::NotAllowed));
+        assert_eq!(policy.check("b.jpg", Some(101)), Err(Violation::TooLarge(100)));
+
+        let policy = UploadPolicy::from_lists(None, Some("exe,gz"), 0);
+        assert_eq!(policy.check("setup.EXE", Some(u64::MAX)), Err(Violation::Denied("exe".to_string())));
+        assert_eq!(policy.check("a.tar.gz", None), Err(Violation::Denied("gz".to_string())));
+        assert_eq!(policy.check("notes.exe.txt", None), Ok(()));
+        assert_eq!(UploadPolicy::from_config(&json!({})), UploadPolicy::default());
+    }
+
+    #[test]
+    fn test_merge_lists() {
+        let images = parse_extensions("jpg,png");
+        let docs = parse_extensions("pdf,png");
+        assert_eq!(widest_allow([images.as_slice(), docs.as_slice()]), vec!["jpg", "pdf", "png"]);
+        assert!(widest_allow([images.as_slice(), &[]]).is_empty());
+        assert_eq!(common_deny([images.as_slice(), docs.as_slice()]), vec!["png"]);
+        assert!(common_deny([images.as_slice(), &[]]).is_empty());
+        assert!(common_deny(std::iter::empty()).is_empty());
+    }
+}
@@ -150,10 +150,57 @@ pub fn get_actual_path(mount_path: &str, raw_path: &str) -> String {
     fix_and_clean_path(actual)
 }
 
+/// Strong ETag of a response body (quoted) / 响应体的强 ETag（带引号）
+pub fn content_etag(body: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(body);
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// Whether an `If-None-Match` header matches the ETag (weak comparison, `*` matches any)
+/// `If-None-Match` 是否与 ETag 匹配（弱比较，`*` 匹配任意值）
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Timestamp in SQLite's `datetime()` format, so it compares correctly with `datetime('now')`
+/// SQLite `datetime()` 格式的时间，可与 `datetime('now')` 正确比较
+pub fn sql_datetime(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    #[tokio::test]
+    async fn test_expired_session_rejected() {
+        let db = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE sessions (id TEXT PRIMARY KEY, expires_at TEXT)").execute(&db).await.unwrap();
+        let now = chrono::Utc::now();
+        for (id, expires_at) in [("expired", now - chrono::Duration::seconds(5)), ("valid", now + chrono::Duration::minutes(5))] {
+            sqlx::query("INSERT INTO sessions (id, expires_at) VALUES (?, ?)")
+                .bind(id).bind(sql_datetime(expires_at)).execute(&db).await.unwrap();
+        }
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM sessions WHERE expires_at > datetime('now')")
+            .fetch_all(&db).await.unwrap();
+        assert_eq!(ids, ["valid"]);
+    }
+    
+    #[test]
+    fn test_etag_matches() {
+        let etag = content_etag(b"{}");
+        assert_eq!(etag, content_etag(b"{}"));
+        assert_ne!(etag, content_etag(b"[]"));
+        assert!(etag_matches(&etag, &etag));
+        assert!(etag_matches(&format!("\"x\", W/{}", etag), &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"x\"", &etag));
+    }
+    
     #[test]
     fn test_fix_and_clean_path() {
         assert_eq!(fix_and_clean_path(""), "/");
@@ -181,19 +228,6 @@ pub fn should_hide_file(filename: &str, hide_patterns: &str) -> bool {
         return false;
     }
     
-    for pattern in hide_patterns.lines() {
-        let pattern = pattern.trim();
-        if pattern.is_empty() {
-            continue;
-        }
-        
-        // Try to match as regex / 尝试作为正则表达式匹配
-        if let Ok(re) = regex::Regex::new(pattern) {
-            if re.is_match(filename) {
-                return true;
-            }
-        }
-    }
-    
-    false
+    // Rule syntax see hide_rules / 规则语法见 hide_rules
+    crate::hide_rules::HideRules::parse(hide_patterns).hides(filename)
 }
@@ -0,0 +1,520 @@
+//! Upload virus scanning / 上传病毒扫描
+//!
+//! This module handles:
+//! - Scanner settings stored in site settings / 保存在站点设置中的扫描设置
+//! - Streaming a file to clamd over TCP (`INSTREAM`) and reading the verdict / 通过 TCP 将文件流式发送给 clamd（`INSTREAM`）并读取结果
+//! - Keeping a copy of infected files in the quarantine directory / 在隔离目录中保留被感染文件的副本
+//! - Recording scan results in `virus_scans` / 在 `virus_scans` 中记录扫描结果
+//!
+//! Uploads that are buffered on the server before reaching the driver are scanned before they
+//! are committed. Streamed uploads are scanned right after they are written by reading them back,
+//! and an infected file is removed from the mount. Either way the upload is reported as rejected.
+//! 在到达驱动之前会在服务器上缓存的上传在提交前扫描；流式上传在写入后立即读回扫描，
+//! 被感染的文件从挂载点中删除。两种情况下上传都会被拒绝。
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::storage::DriverBox;
+
+/// Site setting key of the scanner settings / 扫描设置在站点设置中的键
+pub const VIRUS_SCAN_KEY: &str = "virus_scan";
+
+/// clamd listens here by default / clamd 默认监听地址
+pub const DEFAULT_CLAMD_ADDRESS: &str = "127.0.0.1:3310";
+
+/// Bytes sent to clamd per `INSTREAM` chunk / 每个 `INSTREAM` 分块发送给 clamd 的字节数
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Longest clamd reply read / 读取 clamd 回复的最大长度
+const MAX_REPLY_BYTES: usize = 4096;
+
+/// Shown to the uploader when a virus was found / 检测到病毒时返回给上传者的消息
+pub const INFECTED_MESSAGE: &str = "上传的文件中检测到病毒，已拒绝";
+/// Shown to the uploader when the scan failed and failures reject / 扫描失败且设置为拒绝时返回给上传者的消息
+pub const SCAN_FAILED_MESSAGE: &str = "病毒扫描失败，上传已拒绝";
+
+/// Directory under the data directory holding quarantined files / 数据目录下保存隔离文件的目录
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// The file had no known signature / 文件未命中特征
+pub const STATUS_CLEAN: &str = "clean";
+/// The scanner found a signature / 扫描器命中特征
+pub const STATUS_INFECTED: &str = "infected";
+/// The scanner could not be reached or failed / 无法连接扫描器或扫描失败
+pub const STATUS_ERROR: &str = "error";
+/// Larger than the scan size limit, not scanned / 超过扫描大小上限，未扫描
+pub const STATUS_SKIPPED: &str = "skipped";
+
+/// Virus scanning settings / 病毒扫描设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VirusScanConfig {
+    /// Scan uploads / 是否扫描上传
+    pub enabled: bool,
+    /// clamd TCP address (`host:port`) / clamd TCP 地址（`host:port`）
+    pub address: String,
+    /// Timeout of one scan in seconds / 单次扫描超时（秒）
+    pub timeout_secs: u64,
+    /// Files larger than this are not scanned (bytes), 0 leaves the limit to clamd's StreamMaxLength
+    /// 超过该大小的文件不扫描（字节），0 表示由 clamd 的 StreamMaxLength 决定
+    pub max_size: u64,
+    /// Reject the upload when the scanner is unreachable or fails / 扫描器不可用或扫描失败时拒绝上传
+    pub reject_on_error: bool,
+    /// Keep a copy of infected files in the quarantine directory / 在隔离目录中保留被感染文件的副本
+    pub quarantine: bool,
+}
+
+impl Default for VirusScanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: DEFAULT_CLAMD_ADDRESS.to_string(),
+            timeout_secs: 120,
+            max_size: 0,
+            reject_on_error: true,
+            quarantine: true,
+        }
+    }
+}
+
+impl VirusScanConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let (host, port) = self.address.rsplit_once(':').ok_or("address must be host:port")?;
+        if host.trim().is_empty() || port.parse::<u16>().is_err() {
+            return Err("address must be host:port".to_string());
+        }
+        if !(1..=3600).contains(&self.timeout_secs) {
+            return Err("timeout_secs must be between 1 and 3600".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether a file of this size is scanned / 该大小的文件是否需要扫描
+    pub fn should_scan(&self, size: u64) -> bool {
+        self.max_size == 0 || size <= self.max_size
+    }
+}
+
+/// Scanner verdict / 扫描结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// Name of the matched signature / 命中的特征名
+    Infected(String),
+}
+
+/// Parse a clamd reply such as `stream: OK` or `stream: Eicar-Signature FOUND`
+/// 解析 clamd 回复，如 `stream: OK` 或 `stream: Eicar-Signature FOUND`
+pub fn parse_reply(reply: &str) -> Result<ScanVerdict, String> {
+    let reply = reply.trim_end_matches(['\0', '\n', '\r']).trim();
+    let result = reply.split_once(": ").map_or(reply, |(_, r)| r);
+    if result == "OK" {
+        return Ok(ScanVerdict::Clean);
+    }
+    if let Some(signature) = result.strip_suffix(" FOUND") {
+        return Ok(ScanVerdict::Infected(signature.trim().to_string()));
+    }
+    Err(format!("clamd: {}", reply))
+}
+
+async fn connect(config: &VirusScanConfig) -> Result<TcpStream, String> {
+    TcpStream::connect(&config.address)
+        .await
+        .map_err(|e| format!("Failed to connect to clamd at {}: {}", config.address, e))
+}
+
+async fn read_reply(stream: &mut TcpStream) -> Result<String, String> {
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 256];
+    while reply.len() < MAX_REPLY_BYTES && !reply.ends_with(b"\0") {
+        let n = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        reply.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Check that clamd answers `PING` / 检查 clamd 是否响应 `PING`
+pub async fn ping(config: &VirusScanConfig) -> Result<(), String> {
+    let task = async {
+        let mut stream = connect(config).await?;
+        stream.write_all(b"zPING\0").await.map_err(|e| e.to_string())?;
+        let reply = read_reply(&mut stream).await?;
+        match reply.trim_end_matches('\0').trim() {
+            "PONG" => Ok(()),
+            other => Err(format!("Unexpected clamd reply: {}", other)),
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(config.timeout_secs), task)
+        .await
+        .map_err(|_| "clamd did not answer in time".to_string())?
+}
+
+/// Stream `reader` to clamd; when `quarantine` is set the data is also copied there and the copy
+/// is kept only if the file is infected
+/// 将 `reader` 流式发送给 clamd；设置 `quarantine` 时同时复制到该文件，仅在文件被感染时保留副本
+pub async fn scan<R: AsyncRead + Unpin>(
+    config: &VirusScanConfig,
+    reader: R,
+    quarantine: Option<&Path>,
+) -> Result<ScanVerdict, String> {
+    let result = tokio::time::timeout(
+        Duration::from_secs(config.timeout_secs),
+        scan_stream(config, reader, quarantine),
+    )
+    .await
+    .unwrap_or_else(|_| Err("clamd did not answer in time".to_string()));
+    if let Some(path) = quarantine {
+        if !matches!(result, Ok(ScanVerdict::Infected(_))) {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+    }
+    result
+}
+
+async fn scan_stream<R: AsyncRead + Unpin>(
+    config: &VirusScanConfig,
+    mut reader: R,
+    quarantine: Option<&Path>,
+) -> Result<ScanVerdict, String> {
+    let mut copy = match quarantine {
+        Some(path) => Some(tokio::fs::File::create(path).await.map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let mut stream = connect(config).await?;
+    stream.write_all(b"zINSTREAM\0").await.map_err(|e| e.to_string())?;
+
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buf).await.map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        if let Some(file) = copy.as_mut() {
+            file.write_all(&buf[..n]).await.map_err(|e| e.to_string())?;
+        }
+        // clamd 超过 StreamMaxLength 时会提前回复并关闭连接，此时读取其回复
+        let sent = async {
+            stream.write_all(&(n as u32).to_be_bytes()).await?;
+            stream.write_all(&buf[..n]).await
+        };
+        if sent.await.is_err() {
+            return parse_reply(&read_reply(&mut stream).await?);
+        }
+    }
+    if let Some(mut file) = copy {
+        file.flush().await.map_err(|e| e.to_string())?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await.map_err(|e| e.to_string())?;
+    parse_reply(&read_reply(&mut stream).await?)
+}
+
+/// Quarantine directory / 隔离目录
+pub fn quarantine_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(QUARANTINE_DIR)
+}
+
+/// Load the scanner settings, defaults when unset or invalid / 加载扫描设置，未设置或无效时使用默认值
+pub async fn load_config(db: &SqlitePool) -> VirusScanConfig {
+    sqlx::query_scalar::<_, String>("SELECT value FROM site_settings WHERE key = ?")
+        .bind(VIRUS_SCAN_KEY)
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+/// Scan and record the result, returning the message for the uploader when rejected
+/// 扫描内容并记录结果，拒绝上传时返回给上传者的消息
+async fn scan_and_record<R: AsyncRead + Unpin>(
+    db: &SqlitePool,
+    config: &VirusScanConfig,
+    reader: R,
+    path: &str,
+    user_id: Option<&str>,
+    size: Option<u64>,
+) -> Result<(), &'static str> {
+    let mut result = ScanRecord { path, user_id, size, ..Default::default() };
+
+    // 隔离副本按时间与随机后缀命名，避免同名上传互相覆盖
+    let quarantine_name = config.quarantine.then(|| {
+        let name = path.rsplit('/').next().unwrap_or("file");
+        format!("{}_{}_{}", chrono::Utc::now().format("%Y%m%d%H%M%S"), &uuid::Uuid::new_v4().simple().to_string()[..8], name)
+    });
+    let quarantine_path = match &quarantine_name {
+        Some(name) => {
+            let dir = quarantine_dir(&crate::config::config().get_data_dir());
+            match tokio::fs::create_dir_all(&dir).await {
+                Ok(()) => Some(dir.join(name)),
+                Err(e) => {
+                    tracing::warn!("Failed to create quarantine directory {:?}: {}", dir, e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let verdict = scan(config, reader, quarantine_path.as_deref()).await;
+    let outcome = match &verdict {
+        Ok(ScanVerdict::Clean) => {
+            result.status = STATUS_CLEAN;
+            Ok(())
+        }
+        Ok(ScanVerdict::Infected(signature)) => {
+            tracing::warn!("Virus scan: {} infected with {}", path, signature);
+            result.status = STATUS_INFECTED;
+            result.signature = Some(signature);
+            result.quarantine_file = quarantine_path.as_ref().and(quarantine_name.as_deref());
+            Err(INFECTED_MESSAGE)
+        }
+        Err(e) => {
+            tracing::warn!("Virus scan failed for {}: {}", path, e);
+            result.status = STATUS_ERROR;
+            result.detail = Some(e);
+            if config.reject_on_error { Err(SCAN_FAILED_MESSAGE) } else { Ok(()) }
+        }
+    };
+    if let Err(e) = record(db, result).await {
+        tracing::error!("Failed to record virus scan result: {}", e);
+    }
+    outcome
+}
+
+/// Scan an upload buffered on the server before it is committed to the driver
+/// 缓存在服务器上、尚未提交到驱动的上传：提交前扫描
+pub async fn scan_before_commit<R: AsyncRead + Unpin>(
+    db: &SqlitePool,
+    reader: R,
+    path: &str,
+    user_id: Option<&str>,
+    size: u64,
+) -> Result<(), &'static str> {
+    let config = load_config(db).await;
+    if !config.enabled {
+        return Ok(());
+    }
+    if !config.should_scan(size) {
+        skip(db, path, user_id, size).await;
+        return Ok(());
+    }
+    scan_and_record(db, &config, reader, path, user_id, Some(size)).await
+}
+
+/// Scan a streamed upload by reading it back; rejected files are removed from the mount
+/// 已写入驱动的流式上传：读回扫描，被感染或按设置拒绝时从挂载点删除
+pub async fn scan_after_write(
+    db: &SqlitePool,
+    driver: &DriverBox,
+    actual_path: &str,
+    path: &str,
+    user_id: Option<&str>,
+    size: Option<u64>,
+) -> Result<(), &'static str> {
+    let config = load_config(db).await;
+    if !config.enabled {
+        return Ok(());
+    }
+    if let Some(size) = size.filter(|s| !config.should_scan(*s)) {
+        skip(db, path, user_id, size).await;
+        return Ok(());
+    }
+    let outcome = match driver.open_reader(actual_path, None).await {
+        Ok(reader) => scan_and_record(db, &config, reader, path, user_id, size).await,
+        Err(e) => {
+            let detail = format!("Failed to read uploaded file: {}", e);
+            tracing::warn!("Virus scan failed for {}: {}", path, detail);
+            let result = ScanRecord { path, user_id, size, status: STATUS_ERROR, detail: Some(&detail), ..Default::default() };
+            if let Err(e) = record(db, result).await {
+                tracing::error!("Failed to record virus scan result: {}", e);
+            }
+            if config.reject_on_error { Err(SCAN_FAILED_MESSAGE) } else { Ok(()) }
+        }
+    };
+    if outcome.is_err() {
+        if let Err(e) = driver.delete(actual_path).await {
+            tracing::error!("Failed to remove rejected upload {}: {}", path, e);
+        }
+    }
+    outcome
+}
+
+async fn skip(db: &SqlitePool, path: &str, user_id: Option<&str>, size: u64) {
+    let result = ScanRecord { path, user_id, size: Some(size), status: STATUS_SKIPPED, ..Default::default() };
+    if let Err(e) = record(db, result).await {
+        tracing::error!("Failed to record virus scan result: {}", e);
+    }
+}
+
+/// Stored scan result / 已记录的扫描结果
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ScanEntry {
+    pub id: i64,
+    pub path: String,
+    pub user_id: Option<String>,
+    pub size: Option<i64>,
+    pub status: String,
+    pub signature: Option<String>,
+    pub detail: Option<String>,
+    pub quarantine_file: Option<String>,
+    pub created_at: String,
+}
+
+/// Scan result to record / 待记录的扫描结果
+#[derive(Debug, Clone, Default)]
+pub struct ScanRecord<'a> {
+    pub path: &'a str,
+    pub user_id: Option<&'a str>,
+    pub size: Option<u64>,
+    pub status: &'a str,
+    pub signature: Option<&'a str>,
+    pub detail: Option<&'a str>,
+    pub quarantine_file: Option<&'a str>,
+}
+
+/// Append a scan result / 追加一条扫描结果
+pub async fn record(db: &SqlitePool, result: ScanRecord<'_>) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO virus_scans (path, user_id, size, status, signature, detail, quarantine_file, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(result.path)
+    .bind(result.user_id)
+    .bind(result.size.map(|s| s as i64))
+    .bind(result.status)
+    .bind(result.signature)
+    .bind(result.detail)
+    .bind(result.quarantine_file)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Newest results first, optionally filtered by status, with the total count
+/// 按时间倒序列出扫描结果（可按状态过滤），并返回总数
+pub async fn list(
+    db: &SqlitePool,
+    status: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<ScanEntry>, i64), sqlx::Error> {
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM virus_scans WHERE (? IS NULL OR status = ?)"
+    )
+    .bind(status)
+    .bind(status)
+    .fetch_one(db)
+    .await?;
+    let entries = sqlx::query_as::<_, ScanEntry>(
+        "SELECT * FROM virus_scans WHERE (? IS NULL OR status = ?)
+         ORDER BY id DESC LIMIT ? OFFSET ?"
+    )
+    .bind(status)
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(db)
+    .await?;
+    Ok((entries, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Minimal clamd answering one `INSTREAM` request, FOUND when the data contains "EICAR"
+    /// 只处理一次 `INSTREAM` 请求的简易 clamd，数据中包含 "EICAR" 时返回 FOUND
+    async fn fake_clamd() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut command = [0u8; 10];
+            socket.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            let mut data = Vec::new();
+            loop {
+                let len = socket.read_u32().await.unwrap() as usize;
+                if len == 0 {
+                    break;
+                }
+                let mut chunk = vec![0u8; len];
+                socket.read_exact(&mut chunk).await.unwrap();
+                data.extend(chunk);
+            }
+            let reply: &[u8] = if data.windows(5).any(|w| w == b"EICAR") {
+                b"stream: Eicar-Test-Signature FOUND\0"
+            } else {
+                b"stream: OK\0"
+            };
+            socket.write_all(reply).await.unwrap();
+        });
+        address
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("stream: OK\0"), Ok(ScanVerdict::Clean));
+        assert_eq!(
+            parse_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0"),
+            Ok(ScanVerdict::Infected("Win.Test.EICAR_HDB-1".to_string()))
+        );
+        assert!(parse_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+        assert!(parse_reply("").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = VirusScanConfig::default();
+        assert!(config.validate().is_ok());
+        config.address = "clamd".to_string();
+        assert!(config.validate().is_err());
+        config.address = "[::1]:3310".to_string();
+        assert!(config.validate().is_ok());
+        config.timeout_secs = 0;
+        assert!(config.validate().is_err());
+
+        config.max_size = 10;
+        assert!(config.should_scan(10));
+        assert!(!config.should_scan(11));
+    }
+
+    #[tokio::test]
+    async fn test_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = VirusScanConfig { address: fake_clamd().await, ..Default::default() };
+        let copy = dir.path().join("clean");
+        let verdict = scan(&config, &b"hello world"[..], Some(&copy)).await.unwrap();
+        assert_eq!(verdict, ScanVerdict::Clean);
+        // 干净的文件不保留副本
+        assert!(!copy.exists());
+
+        let config = VirusScanConfig { address: fake_clamd().await, ..Default::default() };
+        let copy = dir.path().join("infected");
+        let data = vec![b'x'; CHUNK_SIZE * 2].into_iter().chain(*b"EICAR").collect::<Vec<u8>>();
+        let verdict = scan(&config, &data[..], Some(&copy)).await.unwrap();
+        assert_eq!(verdict, ScanVerdict::Infected("Eicar-Test-Signature".to_string()));
+        assert_eq!(std::fs::read(&copy).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let config = VirusScanConfig { address, timeout_secs: 5, ..Default::default() };
+        assert!(scan(&config, &b"x"[..], None).await.is_err());
+        assert!(ping(&config).await.is_err());
+    }
+}
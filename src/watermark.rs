@@ -0,0 +1,789 @@
+//! Preview watermarks / 预览水印
+//!
+//! This module handles:
+//! - The label drawn on a preview: viewer name and time / 叠加到预览上的文字：查看者与时间
+//! - Tiling the label over JPEG, PNG, GIF and WebP images / 在 JPEG、PNG、GIF、WebP 图片上平铺文字
+//! - Stamping the label onto every page of a PDF / 在 PDF 的每一页盖上文字
+//!
+//! Text is drawn with a built-in 5x7 pixel font, so no font files are needed. Letters are drawn
+//! in upper case and characters outside the font (including CJK) show as `?`.
+//! PDFs get an incremental update: a new version of each page object whose content list is
+//! wrapped in `q`/`Q` and followed by a stream of filled rectangles. The original bytes are kept,
+//! so pages are not re-rendered and the update works without parsing page content. Encrypted
+//! PDFs are refused.
+//! 文字使用内置的 5x7 点阵字体绘制，无需字体文件。字母以大写绘制，字体中没有的字符（包括中文）显示为 `?`。
+//! PDF 采用增量更新：为每个页面对象写入新版本，原内容列表包裹在 `q`/`Q` 中，其后追加一段由填充矩形组成的内容流。
+//! 原文件字节保持不变，无需重新渲染页面，也不需要解析页面内容。加密的 PDF 不予处理。
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, RgbaImage};
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+
+/// Extensions a watermark can be applied to / 可以添加水印的扩展名
+pub const WATERMARK_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "pdf"];
+
+/// Largest source file that is watermarked / 添加水印的源文件大小上限
+pub const MAX_WATERMARK_SOURCE: u64 = 64 * 1024 * 1024;
+
+/// JPEG quality of watermarked images / 加水印后图片的 JPEG 质量
+const JPEG_QUALITY: u8 = 90;
+
+/// Strength of the watermark over image pixels, out of 255 / 水印与图片像素的混合强度（满值 255）
+const IMAGE_ALPHA: u32 = 80;
+
+/// Gray level of the watermark on PDF pages / PDF 页面上水印的灰度
+const PDF_GRAY: &str = "0.6";
+
+/// Page size used when a PDF page has no readable MediaBox (US Letter) / 页面没有可读的 MediaBox 时使用的尺寸（Letter）
+const DEFAULT_MEDIA_BOX: [f64; 4] = [0.0, 0.0, 612.0, 792.0];
+
+/// A watermarked file and its content type / 加水印后的文件及其内容类型
+pub struct Watermarked {
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+}
+
+fn extension(name: &str) -> Option<String> {
+    let (_, ext) = name.rsplit_once('.')?;
+    Some(ext.to_ascii_lowercase())
+}
+
+/// Whether a watermark can be applied to the file / 能否为该文件添加水印
+pub fn can_watermark(name: &str) -> bool {
+    extension(name).is_some_and(|ext| WATERMARK_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Text drawn on previews: viewer and time / 预览上绘制的文字：查看者与时间
+pub fn label<Tz: chrono::TimeZone>(viewer: &str, at: &chrono::DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    format!("{} {}", viewer, at.format("%Y-%m-%d %H:%M"))
+}
+
+/// Watermark an image or PDF by file name / 按文件名为图片或 PDF 添加水印
+pub fn apply(name: &str, data: &[u8], text: &str) -> Result<Watermarked, String> {
+    if extension(name).as_deref() == Some("pdf") {
+        return Ok(Watermarked { data: watermark_pdf(data, text)?, content_type: "application/pdf" });
+    }
+    watermark_image(data, text)
+}
+
+/// 5x7 glyph rows, top first, bit 4 is the leftmost column / 5x7 字形，自上而下，第 4 位为最左列
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        ' ' => [0; 7],
+        '-' => [0, 0, 0, 0x1F, 0, 0, 0],
+        ':' => [0, 0x0C, 0x0C, 0, 0x0C, 0x0C, 0],
+        '.' => [0, 0, 0, 0, 0, 0x0C, 0x0C],
+        '_' => [0, 0, 0, 0, 0, 0, 0x1F],
+        '@' => [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E],
+        '/' => [0, 0x01, 0x02, 0x04, 0x08, 0x10, 0],
+        '+' => [0, 0x04, 0x04, 0x1F, 0x04, 0x04, 0],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0, 0x04],
+    }
+}
+
+/// Lit runs of the text in font pixels: (x, row from top, width) / 文字点亮的像素段（字体像素）：(x, 自上而下的行, 宽度)
+fn text_runs(text: &str) -> Vec<(u32, u32, u32)> {
+    let mut runs = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let left = i as u32 * 6;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let mut col = 0;
+            while col < 5 {
+                if bits & (0x10 >> col) == 0 {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < 5 && bits & (0x10 >> col) != 0 {
+                    col += 1;
+                }
+                runs.push((left + start, row as u32, col - start));
+            }
+        }
+    }
+    runs
+}
+
+/// Width of the text in font pixels / 文字宽度（字体像素）
+fn text_width(text: &str) -> u32 {
+    (text.chars().count() as u32 * 6).saturating_sub(1).max(1)
+}
+
+/// Tile the text over an image, JPEGs stay JPEG and other formats become PNG
+/// 在图片上平铺文字，JPEG 仍输出 JPEG，其他格式输出 PNG
+fn watermark_image(data: &[u8], text: &str) -> Result<Watermarked, String> {
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let format = reader.format();
+    let mut decoder = reader.into_decoder().map_err(|e| e.to_string())?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    image.apply_orientation(orientation);
+
+    let mut rgba = image.to_rgba8();
+    draw_tiled(&mut rgba, text);
+
+    let mut out = Vec::new();
+    if format == Some(ImageFormat::Jpeg) {
+        let rgb = DynamicImage::ImageRgba8(rgba).to_rgb8();
+        JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+            .encode_image(&rgb)
+            .map_err(|e| e.to_string())?;
+        return Ok(Watermarked { data: out, content_type: "image/jpeg" });
+    }
+    DynamicImage::ImageRgba8(rgba)
+        .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(Watermarked { data: out, content_type: "image/png" })
+}
+
+/// Draw staggered rows of text, each pixel pushed toward black or white for contrast
+/// 绘制交错排列的文字行，每个像素向黑或白偏移以保证对比度
+fn draw_tiled(image: &mut RgbaImage, text: &str) {
+    let (width, height) = image.dimensions();
+    let scale = (width.min(height) / 240).max(1);
+    let runs = text_runs(text);
+    let step_x = (text_width(text) + 12) * scale;
+    let step_y = 7 * scale * 6;
+
+    let mut y0 = step_y / 2;
+    let mut row = 0;
+    while y0 < height {
+        let offset = if row % 2 == 0 { 0 } else { step_x / 2 };
+        let mut x0 = offset as i64 - step_x as i64 / 2;
+        while x0 < width as i64 {
+            for &(x, y, w) in &runs {
+                for py in (y0 + y * scale)..(y0 + (y + 1) * scale) {
+                    for px in (x0 + (x * scale) as i64)..(x0 + ((x + w) * scale) as i64) {
+                        if px >= 0 && (px as u32) < width && py < height {
+                            blend(image.get_pixel_mut(px as u32, py));
+                        }
+                    }
+                }
+            }
+            x0 += step_x as i64;
+        }
+        y0 += step_y;
+        row += 1;
+    }
+}
+
+fn blend(pixel: &mut image::Rgba<u8>) {
+    let [r, g, b, _] = pixel.0;
+    let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    let target = if luma > 127 { 0 } else { 255 };
+    for c in pixel.0.iter_mut().take(3) {
+        *c = ((*c as u32 * (255 - IMAGE_ALPHA) + target * IMAGE_ALPHA) / 255) as u8;
+    }
+    // 透明区域也要显示水印
+    pixel.0[3] = pixel.0[3].max(IMAGE_ALPHA as u8 * 2);
+}
+
+/// An object body as Latin-1 text, streams keep only their dictionary
+/// 以 Latin-1 文本表示的对象内容，流对象只保留字典
+struct PdfObject {
+    generation: u32,
+    body: String,
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn latin1_bytes(text: &str) -> Vec<u8> {
+    text.chars().map(|c| c as u32 as u8).collect()
+}
+
+fn is_delimiter(b: u8) -> bool {
+    b.is_ascii_whitespace() || b"()<>[]{}/%".contains(&b)
+}
+
+fn skip_whitespace(data: &[u8], mut i: usize) -> usize {
+    while i < data.len() && (data[i].is_ascii_whitespace() || data[i] == 0) {
+        i += 1;
+    }
+    i
+}
+
+/// End (exclusive) of the value starting at `start`: dictionary, array, string or token
+/// 从 `start` 开始的值的结束位置（不含）：字典、数组、字符串或单个记号
+fn value_end(data: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    let mut depth = 0usize;
+    loop {
+        let b = *data.get(i)?;
+        match b {
+            b'<' if data.get(i + 1) == Some(&b'<') => {
+                depth += 1;
+                i += 2;
+                continue;
+            }
+            b'>' if data.get(i + 1) == Some(&b'>') => {
+                depth = depth.checked_sub(1)?;
+                i += 2;
+            }
+            b'[' => {
+                depth += 1;
+                i += 1;
+            }
+            b']' => {
+                depth = depth.checked_sub(1)?;
+                i += 1;
+            }
+            b'(' => {
+                let mut nesting = 0usize;
+                loop {
+                    match *data.get(i)? {
+                        b'\\' => i += 1,
+                        b'(' => nesting += 1,
+                        b')' => {
+                            nesting -= 1;
+                            if nesting == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'<' => {
+                while *data.get(i)? != b'>' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'%' => {
+                while i < data.len() && data[i] != b'\n' && data[i] != b'\r' {
+                    i += 1;
+                }
+                continue;
+            }
+            _ if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'/' => {
+                i += 1;
+                while i < data.len() && !is_delimiter(data[i]) {
+                    i += 1;
+                }
+            }
+            _ => {
+                i += 1;
+                while i < data.len() && !is_delimiter(data[i]) {
+                    i += 1;
+                }
+            }
+        }
+        if depth == 0 {
+            return Some(i);
+        }
+    }
+}
+
+/// Raw value of a key in a dictionary, `None` when missing / 字典中某个键的原始值，不存在时为 `None`
+fn dict_value<'a>(dict: &'a str, key: &str) -> Option<(usize, &'a str)> {
+    let bytes = dict.as_bytes();
+    // 只匹配顶层键，跳过嵌套字典中的同名键
+    let mut i = 2;
+    while i < bytes.len() {
+        i = skip_whitespace(bytes, i);
+        if bytes.get(i) != Some(&b'/') {
+            return None;
+        }
+        let name_end = value_end(bytes, i)?;
+        let value_start = skip_whitespace(bytes, name_end);
+        let mut value_end_at = value_end(bytes, value_start)?;
+        // 间接引用 "N G R" 作为一个值
+        let rest = &dict[value_end_at..];
+        if let Some(reference) = parse_reference_tail(&dict[value_start..value_end_at], rest) {
+            value_end_at += reference;
+        }
+        if &dict[i..name_end] == key {
+            return Some((value_start, &dict[value_start..value_end_at]));
+        }
+        i = value_end_at;
+    }
+    None
+}
+
+/// Length of " G R" after an object number, making the number an indirect reference
+/// 对象号之后 " G R" 的长度，存在时该数字是间接引用
+fn parse_reference_tail(number: &str, rest: &str) -> Option<usize> {
+    number.parse::<u32>().ok()?;
+    let trimmed = rest.trim_start();
+    let generation_len = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+    if generation_len == 0 {
+        return None;
+    }
+    let after = trimmed[generation_len..].trim_start();
+    let consumed = rest.len() - after.len();
+    (after.starts_with('R') && after[1..].bytes().next().is_none_or(is_delimiter)).then_some(consumed + 1)
+}
+
+fn parse_reference(value: &str) -> Option<u32> {
+    let mut parts = value.split_ascii_whitespace();
+    let number = parts.next()?.parse().ok()?;
+    parts.next()?.parse::<u32>().ok()?;
+    (parts.next() == Some("R") && parts.next().is_none()).then_some(number)
+}
+
+fn is_name(value: Option<(usize, &str)>, name: &str) -> bool {
+    value.is_some_and(|(_, v)| v == name)
+}
+
+/// Raw data of the stream following a dictionary that ends at `dict_end`, `None` for plain objects
+/// 结束于 `dict_end` 的字典之后的流的原始数据范围，普通对象返回 `None`
+fn stream_span(data: &[u8], dict: &str, dict_end: usize) -> Option<std::ops::Range<usize>> {
+    let mut i = skip_whitespace(data, dict_end);
+    if !data[i..].starts_with(b"stream") {
+        return None;
+    }
+    i += b"stream".len();
+    if data.get(i) == Some(&b'\r') {
+        i += 1;
+    }
+    if data.get(i) == Some(&b'\n') {
+        i += 1;
+    }
+    match dict_value(dict, "/Length").and_then(|(_, v)| v.parse::<usize>().ok()) {
+        Some(len) if i + len <= data.len() => Some(i..i + len),
+        // 长度为间接引用时以 endstream 为界
+        _ => {
+            let end = i + find(&data[i..], b"endstream")?;
+            let raw = &data[i..end];
+            let trimmed = raw.strip_suffix(b"\n").unwrap_or(raw);
+            Some(i..i + trimmed.strip_suffix(b"\r").unwrap_or(trimmed).len())
+        }
+    }
+}
+
+/// Decoded data of a stream, only unfiltered and plain Flate streams are supported
+/// 流解码后的数据，仅支持未压缩与不带参数的 Flate 流
+fn stream_data(data: &[u8], dict: &str, dict_end: usize) -> Option<Vec<u8>> {
+    let raw = &data[stream_span(data, dict, dict_end)?];
+    match dict_value(dict, "/Filter").map(|(_, v)| v) {
+        None => Some(raw.to_vec()),
+        Some("/FlateDecode") | Some("[/FlateDecode]") if dict_value(dict, "/DecodeParms").is_none() => {
+            let mut out = Vec::new();
+            flate2::read::ZlibDecoder::new(raw).read_to_end(&mut out).ok()?;
+            Some(out)
+        }
+        _ => None,
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+/// Number and generation written before the `obj` keyword at `at` / 位于 `at` 的 `obj` 关键字之前的对象号与代号
+fn object_header(data: &[u8], at: usize) -> Option<(u32, u32)> {
+    let mut end = at;
+    let mut numbers = [0u32; 2];
+    for slot in numbers.iter_mut().rev() {
+        let digits_end = end - data[..end].iter().rev().take_while(|b| b.is_ascii_whitespace()).count();
+        let digits = data[..digits_end].iter().rev().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 || digits_end == end {
+            return None;
+        }
+        *slot = std::str::from_utf8(&data[digits_end - digits..digits_end]).ok()?.parse().ok()?;
+        end = digits_end - digits;
+    }
+    Some((numbers[0], numbers[1]))
+}
+
+/// Latest version of every object, including objects packed in object streams
+/// 每个对象的最新版本，包括打包在对象流中的对象
+fn collect_objects(data: &[u8]) -> HashMap<u32, PdfObject> {
+    let mut objects = HashMap::new();
+    let mut from = 0;
+    while let Some(pos) = find(&data[from..], b"obj") {
+        let at = from + pos;
+        from = at + 3;
+        if data.get(at + 3).is_some_and(|b| !is_delimiter(*b)) {
+            continue;
+        }
+        let Some((number, generation)) = object_header(data, at) else { continue };
+        let start = skip_whitespace(data, at + 3);
+        let Some(end) = value_end(data, start) else { continue };
+        let body = latin1(&data[start..end]);
+        if body.starts_with("<<") && is_name(dict_value(&body, "/Type"), "/ObjStm") {
+            if let Some(decoded) = stream_data(data, &body, end) {
+                unpack_object_stream(&body, &decoded, &mut objects);
+            }
+        }
+        // 跳过流数据，压缩数据中可能恰好出现 "obj"
+        from = match body.starts_with("<<").then(|| stream_span(data, &body, end)).flatten() {
+            Some(span) => span.end,
+            None => end,
+        };
+        objects.insert(number, PdfObject { generation, body });
+    }
+    objects
+}
+
+/// Objects packed in an object stream / 对象流中打包的对象
+fn unpack_object_stream(dict: &str, decoded: &[u8], objects: &mut HashMap<u32, PdfObject>) {
+    let count = dict_value(dict, "/N").and_then(|(_, v)| v.parse::<usize>().ok()).unwrap_or(0);
+    let Some(first) = dict_value(dict, "/First").and_then(|(_, v)| v.parse::<usize>().ok()) else { return };
+    let Some(header) = decoded.get(..first) else { return };
+    let numbers: Vec<usize> = latin1(header).split_ascii_whitespace().filter_map(|n| n.parse().ok()).collect();
+    for pair in numbers.chunks(2).take(count) {
+        let [number, offset] = pair else { break };
+        let start = skip_whitespace(decoded, first + offset);
+        let Some(end) = value_end(decoded, start) else { continue };
+        objects.insert(*number as u32, PdfObject { generation: 0, body: latin1(&decoded[start..end]) });
+    }
+}
+
+/// Trailer dictionary of the last revision and the offset of its cross-reference section
+/// 最后一个版本的 trailer 字典及其交叉引用表的偏移
+fn last_trailer(data: &[u8]) -> Result<(String, usize), String> {
+    let at = rfind(data, b"startxref").ok_or("missing startxref")?;
+    let offset: usize = latin1(&data[at + 9..]).split_ascii_whitespace().next()
+        .and_then(|v| v.parse().ok())
+        .ok_or("bad startxref")?;
+    let section = data.get(offset..).ok_or("bad startxref")?;
+    let dict_start = if section.starts_with(b"xref") {
+        offset + find(section, b"trailer").ok_or("missing trailer")? + 7
+    } else {
+        // 交叉引用流：字典就是 trailer
+        offset + find(section, b"<<").ok_or("missing trailer")?
+    };
+    let dict_start = skip_whitespace(data, dict_start);
+    let dict_end = value_end(data, dict_start).ok_or("bad trailer")?;
+    Ok((latin1(&data[dict_start..dict_end]), offset))
+}
+
+/// MediaBox of a page, inherited from the page tree when missing / 页面的 MediaBox，缺失时从页面树继承
+fn media_box(objects: &HashMap<u32, PdfObject>, page: &str) -> [f64; 4] {
+    let mut dict = page;
+    for _ in 0..32 {
+        if let Some((_, value)) = dict_value(dict, "/MediaBox") {
+            let numbers: Vec<f64> = value.trim_matches(['[', ']']).split_ascii_whitespace()
+                .filter_map(|n| n.parse().ok())
+                .collect();
+            if let [x0, y0, x1, y1] = numbers[..] {
+                return [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)];
+            }
+            break;
+        }
+        match dict_value(dict, "/Parent").and_then(|(_, v)| parse_reference(v)).and_then(|n| objects.get(&n)) {
+            Some(parent) => dict = &parent.body,
+            None => break,
+        }
+    }
+    DEFAULT_MEDIA_BOX
+}
+
+/// Content stream drawing the text three times diagonally across the page
+/// 在页面上沿对角线绘制三次文字的内容流
+fn page_stamp(text: &str, [x0, y0, x1, y1]: [f64; 4]) -> String {
+    let (width, height) = (x1 - x0, y1 - y0);
+    let text_w = text_width(text) as f64;
+    let scale = (width * 0.7 / text_w).min(width.min(height) / 40.0).max(0.5);
+    let (sin, cos) = (30f64.to_radians().sin(), 30f64.to_radians().cos());
+
+    // 以字体像素为单位的矩形，y 轴向上
+    let mut glyphs = String::new();
+    for (x, row, w) in text_runs(text) {
+        glyphs.push_str(&format!("{} {} {} 1 re\n", x, 6 - row, w));
+    }
+
+    let mut out = format!("Q\nq {} g\n", PDF_GRAY);
+    for i in 0..3 {
+        let cx = x0 + width / 2.0;
+        let cy = y0 + height * (1.0 + 2.0 * i as f64) / 6.0;
+        let (half_w, half_h) = (text_w * scale / 2.0, 3.5 * scale);
+        let tx = cx - half_w * cos + half_h * sin;
+        let ty = cy - half_w * sin - half_h * cos;
+        out.push_str(&format!(
+            "q {:.4} {:.4} {:.4} {:.4} {:.2} {:.2} cm\n{}f\nQ\n",
+            scale * cos, scale * sin, -scale * sin, scale * cos, tx, ty, glyphs
+        ));
+    }
+    out.push_str("Q\n");
+    out
+}
+
+fn stream_object(number: u32, content: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(content).map_err(|e| e.to_string())?;
+    let compressed = encoder.finish().map_err(|e| e.to_string())?;
+    let mut out = format!("{} 0 obj\n<< /Length {} /Filter /FlateDecode >>\nstream\n", number, compressed.len()).into_bytes();
+    out.extend_from_slice(&compressed);
+    out.extend_from_slice(b"\nendstream\nendobj\n");
+    Ok(out)
+}
+
+/// Page dictionary whose content list is wrapped in `q`/`Q` and followed by the stamp
+/// 内容列表包裹在 `q`/`Q` 中并追加水印流的页面字典
+fn stamped_page(objects: &HashMap<u32, PdfObject>, page: &str, open: u32, stamp: u32) -> String {
+    let existing = match dict_value(page, "/Contents") {
+        Some((start, value)) => {
+            let listed = match parse_reference(value).and_then(|n| objects.get(&n)) {
+                // 内容是指向数组对象的引用时展开数组
+                Some(target) if target.body.starts_with('[') => target.body.as_str(),
+                _ => value,
+            };
+            let listed = listed.strip_prefix('[').and_then(|v| v.strip_suffix(']')).unwrap_or(listed);
+            Some((start, value.len(), listed.trim().to_string()))
+        }
+        None => None,
+    };
+    let contents = format!(
+        "[{} 0 R {} {} 0 R]",
+        open,
+        existing.as_ref().map(|(_, _, listed)| listed.as_str()).unwrap_or(""),
+        stamp
+    );
+    match existing {
+        Some((start, len, _)) => format!("{}{}{}", &page[..start], contents, &page[start + len..]),
+        None => format!("{} /Contents {} >>", page[..page.len() - 2].trim_end(), contents),
+    }
+}
+
+/// Stamp the text on every page with an incremental update / 以增量更新在每一页盖上文字
+fn watermark_pdf(data: &[u8], text: &str) -> Result<Vec<u8>, String> {
+    if find(&data[..data.len().min(1024)], b"%PDF-").is_none() {
+        return Err("not a PDF file".to_string());
+    }
+    let (trailer, prev) = last_trailer(data)?;
+    if dict_value(&trailer, "/Encrypt").is_some() {
+        return Err("encrypted PDF".to_string());
+    }
+    let root = dict_value(&trailer, "/Root").ok_or("missing /Root")?.1;
+
+    let objects = collect_objects(data);
+    let mut pages: Vec<(&u32, &PdfObject)> = objects.iter()
+        .filter(|(_, o)| o.body.starts_with("<<") && is_name(dict_value(&o.body, "/Type"), "/Page"))
+        .collect();
+    if pages.is_empty() {
+        return Err("no pages found".to_string());
+    }
+    pages.sort_by_key(|(n, _)| **n);
+
+    let size = dict_value(&trailer, "/Size").and_then(|(_, v)| v.parse::<u32>().ok()).unwrap_or(0);
+    let mut next = objects.keys().max().map_or(size, |max| size.max(max + 1));
+    let mut out = data.to_vec();
+    if !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+    let mut offsets: Vec<(u32, u32, usize)> = Vec::new();
+
+    let open = next;
+    next += 1;
+    offsets.push((open, 0, out.len()));
+    out.extend(stream_object(open, b"q\n")?);
+
+    // 相同尺寸的页面共用一个水印流
+    let mut stamps: HashMap<String, u32> = HashMap::new();
+    for (number, page) in pages {
+        let bbox = media_box(&objects, &page.body);
+        let key = format!("{:?}", bbox);
+        let stamp = match stamps.get(&key) {
+            Some(stamp) => *stamp,
+            None => {
+                let stamp = next;
+                next += 1;
+                offsets.push((stamp, 0, out.len()));
+                out.extend(stream_object(stamp, page_stamp(text, bbox).as_bytes())?);
+                stamps.insert(key, stamp);
+                stamp
+            }
+        };
+        offsets.push((*number, page.generation, out.len()));
+        let body = stamped_page(&objects, &page.body, open, stamp);
+        out.extend(format!("{} {} obj\n", number, page.generation).into_bytes());
+        out.extend(latin1_bytes(&body));
+        out.extend_from_slice(b"\nendobj\n");
+    }
+
+    offsets.sort_by_key(|(number, _, _)| *number);
+    let xref = out.len();
+    out.extend_from_slice(b"xref\n");
+    for (number, generation, offset) in &offsets {
+        out.extend(format!("{} 1\n{:010} {:05} n \n", number, offset, generation).into_bytes());
+    }
+    let mut new_trailer = format!("<< /Size {} /Root {} /Prev {}", next, root, prev);
+    for key in ["/Info", "/ID"] {
+        if let Some((_, value)) = dict_value(&trailer, key) {
+            new_trailer.push_str(&format!(" {} {}", key, value));
+        }
+    }
+    out.extend(format!("trailer\n{} >>\nstartxref\n{}\n%%EOF\n", new_trailer, xref).into_bytes());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two-page PDF with a classic cross-reference table / 带传统交叉引用表的两页 PDF
+    fn sample_pdf() -> Vec<u8> {
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 /MediaBox [0 0 595 842] >>",
+            "<< /Type /Page /Parent 2 0 R /Contents 5 0 R >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 842 595] /Contents [5 0 R] /Annots [<< /T (a>>) >>] >>",
+            "<< /Length 8 >>\nstream\n0 0 m S\n\nendstream",
+        ];
+        let mut out = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, body) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, body).into_bytes());
+        }
+        let xref = out.len();
+        out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+        for offset in offsets {
+            out.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+        }
+        out.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).into_bytes());
+        out
+    }
+
+    #[test]
+    fn test_can_watermark_and_label() {
+        assert!(can_watermark("Report.PDF"));
+        assert!(can_watermark("a.jpeg"));
+        assert!(!can_watermark("a.heic"));
+        assert!(!can_watermark("pdf"));
+        let at = chrono::DateTime::parse_from_rfc3339("2024-05-06T07:08:09+08:00").unwrap();
+        assert_eq!(label("alice", &at), "alice 2024-05-06 07:08");
+    }
+
+    #[test]
+    fn test_text_runs() {
+        // "-" 是第 3 行的一整段
+        assert_eq!(text_runs("-"), vec![(0, 3, 5)]);
+        assert_eq!(text_runs(" -"), vec![(6, 3, 5)]);
+        assert_eq!(text_width("ab"), 11);
+        // 不在字体中的字符显示为问号
+        assert_eq!(glyph('中'), glyph('?'));
+        assert_eq!(glyph('a'), glyph('A'));
+    }
+
+    #[test]
+    fn test_dict_value() {
+        let dict = "<< /Type /Page /Kids [1 0 R 2 0 R] /Res << /Type /Font >> /Parent 12 0 R /N 3 >>";
+        assert_eq!(dict_value(dict, "/Type").map(|v| v.1), Some("/Page"));
+        assert_eq!(dict_value(dict, "/Kids").map(|v| v.1), Some("[1 0 R 2 0 R]"));
+        assert_eq!(dict_value(dict, "/Parent").map(|v| v.1), Some("12 0 R"));
+        assert_eq!(dict_value(dict, "/N").map(|v| v.1), Some("3"));
+        assert_eq!(dict_value(dict, "/Font"), None);
+        assert_eq!(parse_reference("12 0 R"), Some(12));
+        assert_eq!(parse_reference("12"), None);
+    }
+
+    #[test]
+    fn test_watermark_pdf() {
+        let pdf = sample_pdf();
+        let out = watermark_pdf(&pdf, "alice").unwrap();
+        assert!(out.starts_with(&pdf));
+        let objects = collect_objects(&out);
+        assert_eq!(objects[&3].body, "<< /Type /Page /Parent 2 0 R /Contents [6 0 R 5 0 R 7 0 R] >>");
+        // 横向页面使用另一个水印流，嵌套字典中的字符串不影响解析
+        assert!(objects[&4].body.contains("/Contents [6 0 R 5 0 R 8 0 R]"));
+        assert!(objects[&4].body.ends_with("/Annots [<< /T (a>>) >>] >>"));
+
+        let (trailer, xref) = last_trailer(&out).unwrap();
+        assert_eq!(xref, rfind(&out, b"\nxref\n").unwrap() + 1);
+        let prev = (rfind(&pdf, b"\nxref\n").unwrap() + 1).to_string();
+        assert_eq!(dict_value(&trailer, "/Prev").map(|v| v.1), Some(prev.as_str()));
+        assert_eq!(dict_value(&trailer, "/Size").map(|v| v.1), Some("9"));
+        assert_eq!(dict_value(&trailer, "/Root").map(|v| v.1), Some("1 0 R"));
+
+        // 交叉引用表中的偏移指向对象
+        let table = latin1(&out[xref..]);
+        let offset: usize = table.lines().nth(2).unwrap()[..10].parse().unwrap();
+        assert!(out[offset..].starts_with(b"3 0 obj"));
+
+        assert!(watermark_pdf(b"hello", "x").is_err());
+        let encrypted = String::from_utf8(pdf).unwrap().replace("/Root 1 0 R", "/Root 1 0 R /Encrypt 9 0 R");
+        assert!(watermark_pdf(encrypted.as_bytes(), "x").is_err());
+    }
+
+    #[test]
+    fn test_object_streams() {
+        let packed = b"3 0 4 33 << /Type /Page /Parent 2 0 R >>  << /Type /Pages /Count 1 >>";
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(packed).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut data = format!("1 0 obj\n<< /Type /ObjStm /N 2 /First 9 /Length {} /Filter /FlateDecode >>\nstream\n", compressed.len()).into_bytes();
+        data.extend(compressed);
+        data.extend_from_slice(b"\nendstream\nendobj\n");
+
+        let objects = collect_objects(&data);
+        assert_eq!(objects[&3].body, "<< /Type /Page /Parent 2 0 R >>");
+        assert!(objects[&4].body.starts_with("<< /Type /Pages"));
+    }
+
+    #[test]
+    fn test_watermark_image() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(400, 300, image::Rgb([255, 255, 255])));
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+
+        let out = apply("a.png", &png, "alice 2024-05-06 07:08").unwrap();
+        assert_eq!(out.content_type, "image/png");
+        let decoded = image::load_from_memory(&out.data).unwrap().to_rgb8();
+        assert_eq!(decoded.dimensions(), (400, 300));
+        // 白底上的水印像素变暗
+        assert!(decoded.pixels().any(|p| p.0[0] < 220));
+        assert!(decoded.pixels().filter(|p| p.0[0] == 255).count() > 100_000);
+
+        let mut jpeg = Vec::new();
+        image.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg).unwrap();
+        assert_eq!(apply("a.jpg", &jpeg, "bob").unwrap().content_type, "image/jpeg");
+    }
+}
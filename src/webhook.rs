@@ -0,0 +1,376 @@
+//! Webhook notifications / Webhook 通知
+//!
+//! This module handles:
+//! - Webhook registry cache (URL, secret, event filter) / Webhook 配置缓存
+//! - Signed JSON delivery with retries / 带签名的 JSON 推送与重试
+//! - Last delivery status per webhook / 每个 Webhook 的最近推送状态
+//!
+//! Signature: `X-YaoList-Signature: sha256=<hex>` where the HMAC-SHA256 key is the webhook
+//! secret and the message is `<X-YaoList-Timestamp>.<body>`.
+//! 签名：以 Webhook 密钥对 `<时间戳>.<请求体>` 做 HMAC-SHA256，放在 `X-YaoList-Signature` 头中。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+/// Delivery attempts per event (first try + retries) / 每个事件的推送次数（含重试）
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 4;
+/// Request timeout (seconds) / 请求超时（秒）
+pub const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+/// First retry delay (seconds), multiplied by 5 each retry / 首次重试延迟，之后每次乘以5
+const WEBHOOK_RETRY_BASE_SECS: u64 = 2;
+
+pub const SIGNATURE_HEADER: &str = "X-YaoList-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-YaoList-Timestamp";
+pub const EVENT_HEADER: &str = "X-YaoList-Event";
+pub const DELIVERY_HEADER: &str = "X-YaoList-Delivery";
+
+/// Webhook event type / Webhook 事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    TaskCompleted,
+    TaskFailed,
+    DriverError,
+    LoginFailure,
+    ShareAccessed,
+    /// A mount's usage rose past the alert threshold / 挂载点使用率超过告警阈值
+    StorageAlert,
+    /// Repeated failed logins, a login from a new country or an admin account change
+    /// 多次登录失败、从新国家登录或管理员账号变更
+    SecurityAlert,
+    /// Test delivery from the admin panel / 管理面板发送的测试事件
+    Ping,
+}
+
+impl WebhookEvent {
+    /// Events that can be subscribed / 可订阅的事件
+    pub fn all() -> &'static [WebhookEvent] {
+        &[
+            WebhookEvent::TaskCompleted,
+            WebhookEvent::TaskFailed,
+            WebhookEvent::DriverError,
+            WebhookEvent::LoginFailure,
+            WebhookEvent::ShareAccessed,
+            WebhookEvent::StorageAlert,
+            WebhookEvent::SecurityAlert,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::TaskCompleted => "task_completed",
+            WebhookEvent::TaskFailed => "task_failed",
+            WebhookEvent::DriverError => "driver_error",
+            WebhookEvent::LoginFailure => "login_failure",
+            WebhookEvent::ShareAccessed => "share_accessed",
+            WebhookEvent::StorageAlert => "storage_alert",
+            WebhookEvent::SecurityAlert => "security_alert",
+            WebhookEvent::Ping => "ping",
+        }
+    }
+}
+
+/// Webhook config / Webhook 配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    /// Subscribed events, empty = all / 订阅的事件，为空表示全部
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Webhook {
+    /// Whether this webhook wants the event / 是否订阅该事件
+    pub fn accepts(&self, event: WebhookEvent) -> bool {
+        if event == WebhookEvent::Ping {
+            return true;
+        }
+        self.enabled && (self.events.is_empty() || self.events.contains(&event))
+    }
+}
+
+/// Payload sent to webhook URLs / 推送的请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Delivery id (same across retries) / 推送ID（重试时不变）
+    pub id: String,
+    pub event: WebhookEvent,
+    pub timestamp: DateTime<Utc>,
+    pub data: Value,
+}
+
+/// Last delivery result of a webhook / 最近一次推送结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDeliveryStatus {
+    pub event: WebhookEvent,
+    pub success: bool,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub delivered_at: DateTime<Utc>,
+}
+
+/// Compute `sha256=<hex>` signature / 计算签名
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Retry delay before attempt `attempt` (1-based) / 第N次推送前的等待时间
+pub fn retry_delay(attempt: u32) -> Duration {
+    let exp = attempt.saturating_sub(2).min(8);
+    Duration::from_secs(WEBHOOK_RETRY_BASE_SECS * 5u64.pow(exp))
+}
+
+/// Webhook manager / Webhook 管理器
+pub struct WebhookManager {
+    hooks: RwLock<Vec<Webhook>>,
+    status: RwLock<HashMap<String, WebhookDeliveryStatus>>,
+    client: reqwest::Client,
+}
+
+impl WebhookManager {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .user_agent("YaoList-Webhook")
+            .build()
+            .unwrap_or_default();
+        Self {
+            hooks: RwLock::new(Vec::new()),
+            status: RwLock::new(HashMap::new()),
+            client,
+        }
+    }
+
+    /// Load webhooks from database / 从数据库加载 Webhook
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<(String, String, String, String, String, bool, String)> = sqlx::query_as(
+            "SELECT id, name, url, secret, events, enabled, created_at FROM webhooks"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let hooks = rows.into_iter().map(|(id, name, url, secret, events, enabled, created_at)| Webhook {
+            id,
+            name,
+            url,
+            secret,
+            events: serde_json::from_str(&events).unwrap_or_default(),
+            enabled,
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+        }).collect();
+        *self.hooks.write() = hooks;
+
+        Ok(())
+    }
+
+    /// List webhooks / 列出 Webhook
+    pub fn list(&self) -> Vec<Webhook> {
+        self.hooks.read().clone()
+    }
+
+    /// Get webhook by id / 获取 Webhook
+    pub fn get(&self, id: &str) -> Option<Webhook> {
+        self.hooks.read().iter().find(|h| h.id == id).cloned()
+    }
+
+    /// Insert or replace a webhook in the cache / 新增或替换缓存中的 Webhook
+    pub fn upsert(&self, hook: Webhook) {
+        let mut hooks = self.hooks.write();
+        match hooks.iter_mut().find(|h| h.id == hook.id) {
+            Some(existing) => *existing = hook,
+            None => hooks.push(hook),
+        }
+    }
+
+    /// Remove a webhook from the cache / 从缓存移除 Webhook
+    pub fn remove(&self, id: &str) -> bool {
+        self.status.write().remove(id);
+        let mut hooks = self.hooks.write();
+        let before = hooks.len();
+        hooks.retain(|h| h.id != id);
+        hooks.len() != before
+    }
+
+    /// Last delivery status of a webhook / 最近一次推送结果
+    pub fn last_status(&self, id: &str) -> Option<WebhookDeliveryStatus> {
+        self.status.read().get(id).cloned()
+    }
+
+    /// Send event to all subscribed webhooks in background / 后台推送事件到所有订阅的 Webhook
+    pub fn emit(self: &Arc<Self>, event: WebhookEvent, data: Value) {
+        let targets: Vec<Webhook> = self.hooks.read()
+            .iter()
+            .filter(|h| h.accepts(event) && event != WebhookEvent::Ping)
+            .cloned()
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let payload = WebhookPayload {
+            id: uuid::Uuid::new_v4().to_string(),
+            event,
+            timestamp: Utc::now(),
+            data,
+        };
+        for hook in targets {
+            let manager = self.clone();
+            let payload = payload.clone();
+            crate::request_id::spawn(async move {
+                manager.deliver(&hook, &payload, WEBHOOK_MAX_ATTEMPTS).await;
+            });
+        }
+    }
+
+    /// Send a ping event once and wait for the result / 发送一次测试事件并等待结果
+    pub async fn ping(&self, hook: &Webhook) -> WebhookDeliveryStatus {
+        let payload = WebhookPayload {
+            id: uuid::Uuid::new_v4().to_string(),
+            event: WebhookEvent::Ping,
+            timestamp: Utc::now(),
+            data: serde_json::json!({ "webhook_id": hook.id }),
+        };
+        self.deliver(hook, &payload, 1).await
+    }
+
+    /// Deliver payload with retries / 推送（含重试）
+    async fn deliver(&self, hook: &Webhook, payload: &WebhookPayload, max_attempts: u32) -> WebhookDeliveryStatus {
+        let body = serde_json::to_string(payload).unwrap_or_default();
+        let mut status_code = None;
+        let mut error = None;
+        let mut attempts = 0;
+
+        for attempt in 1..=max_attempts.max(1) {
+            if attempt > 1 {
+                tokio::time::sleep(retry_delay(attempt)).await;
+            }
+            attempts = attempt;
+
+            let timestamp = Utc::now().timestamp();
+            let result = self.client
+                .post(&hook.url)
+                .header("Content-Type", "application/json")
+                .header(EVENT_HEADER, payload.event.as_str())
+                .header(DELIVERY_HEADER, &payload.id)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, sign_payload(&hook.secret, timestamp, &body))
+                .body(body.clone())
+                .send()
+                .await;
+
+            match result {
+                Ok(resp) if resp.status().is_success() => {
+                    status_code = Some(resp.status().as_u16());
+                    error = None;
+                    break;
+                }
+                Ok(resp) => {
+                    status_code = Some(resp.status().as_u16());
+                    error = Some(format!("HTTP {}", resp.status()));
+                    // 4xx other than 408/429 will not succeed on retry / 客户端错误不重试
+                    if resp.status().is_client_error() && resp.status() != 408 && resp.status() != 429 {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    status_code = None;
+                    error = Some(e.to_string());
+                }
+            }
+        }
+
+        if let Some(ref e) = error {
+            tracing::warn!("Webhook {} delivery of {} failed after {} attempts: {}",
+                hook.id, payload.event.as_str(), attempts, e);
+        }
+
+        let status = WebhookDeliveryStatus {
+            event: payload.event,
+            success: error.is_none(),
+            status_code,
+            error,
+            attempts,
+            delivered_at: Utc::now(),
+        };
+        self.status.write().insert(hook.id.clone(), status.clone());
+        status
+    }
+}
+
+impl Default for WebhookManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        let sig = sign_payload("secret", 1700000000, "{}");
+        assert!(sig.starts_with("sha256="));
+        assert_eq!(sig.len(), "sha256=".len() + 64);
+        assert_eq!(sig, sign_payload("secret", 1700000000, "{}"));
+        assert_ne!(sig, sign_payload("secret", 1700000001, "{}"));
+        assert_ne!(sig, sign_payload("other", 1700000000, "{}"));
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let mut hook = Webhook {
+            id: "1".to_string(),
+            name: "test".to_string(),
+            url: "http://localhost".to_string(),
+            secret: String::new(),
+            events: vec![],
+            enabled: true,
+            created_at: Utc::now(),
+        };
+        assert!(hook.accepts(WebhookEvent::LoginFailure));
+
+        hook.events = vec![WebhookEvent::TaskFailed];
+        assert!(hook.accepts(WebhookEvent::TaskFailed));
+        assert!(!hook.accepts(WebhookEvent::TaskCompleted));
+
+        hook.enabled = false;
+        assert!(!hook.accepts(WebhookEvent::TaskFailed));
+        assert!(hook.accepts(WebhookEvent::Ping));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(10));
+        assert_eq!(retry_delay(4), Duration::from_secs(50));
+    }
+}
@@ -0,0 +1,468 @@
+//! Workspaces (multi-tenancy) / 工作区（多租户）
+//!
+//! One instance can serve several independent teams. Each workspace has its own users, sessions,
+//! shares and site appearance settings, and sees the storage tree below its `root_path` only.
+//! A request is assigned to a workspace by its `Host` (one of the workspace's `domains`) or by a
+//! `/w/<slug>` path prefix; everything else belongs to the built-in `default` workspace.
+//! 一个实例可服务多个互相独立的团队。每个工作区拥有独立的用户、会话、分享与站点外观设置，
+//! 且只能看到 `root_path` 之下的存储。请求按 `Host`（工作区的 `domains`）或 `/w/<slug>` 路径前缀
+//! 归属工作区，其余请求属于内置的 `default` 工作区。
+//!
+//! Storages, user groups and instance settings stay global and are managed from the default
+//! workspace. The default workspace cannot see inside other workspaces' roots.
+//! 存储、用户组与实例级设置保持全局，仅能在默认工作区管理。默认工作区看不到其他工作区根目录内的内容。
+
+use std::future::Future;
+use std::sync::Arc;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::utils::{fix_and_clean_path, is_sub_path};
+
+/// Id of the built-in workspace / 内置工作区 ID
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+
+/// Path prefix selecting a workspace by slug / 按标识选择工作区的路径前缀
+pub const PATH_PREFIX: &str = "/w/";
+
+/// Settings each workspace may override / 各工作区可单独设置的站点设置
+pub const SCOPED_SETTING_KEYS: &[&str] = &[
+    "site_title",
+    "site_description",
+    "site_icon",
+    "site_announcement",
+    "robots_txt",
+    "background_image",
+    "glass_effect",
+    "glass_blur",
+    "glass_opacity",
+    "allow_registration",
+    "default_user_group",
+];
+
+/// Routes that manage the whole instance, only reachable from the default workspace
+/// 管理整个实例的路由，仅能从默认工作区访问
+const INSTANCE_ROUTES: &[&str] = &[
+    "/api/admin/",
+    "/api/drivers",
+    "/api/driver/",
+    "/api/mounts",
+    "/api/groups",
+    "/api/metas",
+    "/api/load_balance/groups",
+    "/api/settings/geoip",
+    "/api/notifications/settings",
+    "/api/notifications/webhooks",
+    "/api/notifications/email-templates",
+    "/api/notifications/test",
+    "/dav",
+];
+
+/// Workspace / 工作区
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    /// Used in the `/w/<slug>` prefix / 用于 `/w/<slug>` 前缀
+    pub slug: String,
+    /// Hosts served by this workspace, e.g. `team-a.example.com` / 该工作区对应的域名
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Storage subtree visible to the workspace / 工作区可见的存储子树
+    pub root_path: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Workspace {
+    pub fn default_workspace() -> Self {
+        Self {
+            id: DEFAULT_WORKSPACE_ID.to_string(),
+            name: "Default".to_string(),
+            slug: DEFAULT_WORKSPACE_ID.to_string(),
+            domains: Vec::new(),
+            root_path: "/".to_string(),
+            enabled: true,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_WORKSPACE_ID
+    }
+
+    /// Normalize slug, domains and root path / 规范化标识、域名与根路径
+    pub fn normalize(&mut self) {
+        self.slug = self.slug.trim().to_lowercase();
+        self.name = self.name.trim().to_string();
+        self.domains = self.domains.iter()
+            .map(|d| d.trim().trim_end_matches('.').to_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect();
+        self.domains.dedup();
+        self.root_path = fix_and_clean_path(&self.root_path);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("name is required".to_string());
+        }
+        let slug_ok = (1..=32).contains(&self.slug.len())
+            && self.slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !self.slug.starts_with('-');
+        if !slug_ok {
+            return Err("slug must be 1-32 characters of a-z, 0-9 and '-'".to_string());
+        }
+        if !self.is_default() && (self.slug == DEFAULT_WORKSPACE_ID || self.root_path == "/") {
+            return Err("workspaces other than default need their own slug and a root_path below /".to_string());
+        }
+        for domain in &self.domains {
+            let valid = domain.split('.').all(|label| {
+                !label.is_empty() && label.len() <= 63
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+            if !valid {
+                return Err(format!("invalid domain '{}'", domain));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Workspace a request runs in / 请求所属的工作区
+#[derive(Debug, Clone)]
+pub struct WorkspaceContext {
+    pub workspace: Workspace,
+    /// Selected through `/w/<slug>` rather than a domain / 通过 `/w/<slug>` 前缀选中
+    pub via_prefix: bool,
+    /// Roots of other workspaces nested in this one, hidden from it / 嵌套在本工作区内的其他工作区根目录（对本工作区隐藏）
+    pub excluded_roots: Vec<String>,
+}
+
+impl WorkspaceContext {
+    pub fn id(&self) -> &str {
+        &self.workspace.id
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.workspace.is_default()
+    }
+
+    /// Whether a storage path belongs to this workspace / 存储路径是否属于本工作区
+    pub fn path_visible(&self, path: &str) -> bool {
+        is_sub_path(&self.workspace.root_path, path)
+            && !self.excluded_roots.iter().any(|root| is_sub_path(root, path))
+    }
+
+    /// Whether a mount serves part of this workspace; mounts above the root stay usable
+    /// 挂载点是否服务于本工作区；根目录之上的挂载点仍可使用
+    pub fn mount_visible(&self, mount_path: &str) -> bool {
+        self.path_visible(mount_path) || is_sub_path(mount_path, &self.workspace.root_path)
+    }
+
+    /// Place a user's root path inside the workspace root / 将用户根路径放入工作区根目录下
+    pub fn scope_root(&self, user_root: &str) -> String {
+        let user_root = fix_and_clean_path(user_root);
+        if self.workspace.root_path == "/" {
+            return user_root;
+        }
+        fix_and_clean_path(&format!("{}{}", self.workspace.root_path, user_root))
+    }
+
+    /// Path for the session cookie, so prefixed workspaces on one host keep separate logins
+    /// 会话 Cookie 的路径，同一域名下按前缀区分的工作区各自保持登录
+    pub fn cookie_path(&self) -> String {
+        if self.via_prefix {
+            format!("{}{}", PATH_PREFIX, self.workspace.slug)
+        } else {
+            "/".to_string()
+        }
+    }
+
+    /// Site settings key in this workspace / 本工作区中的站点设置键
+    pub fn settings_key(&self, key: &str) -> String {
+        if self.is_default() || !SCOPED_SETTING_KEYS.contains(&key) {
+            key.to_string()
+        } else {
+            format!("workspace:{}:{}", self.workspace.id, key)
+        }
+    }
+}
+
+static DEFAULT_CONTEXT: Lazy<Arc<WorkspaceContext>> = Lazy::new(|| Arc::new(WorkspaceContext {
+    workspace: Workspace::default_workspace(),
+    via_prefix: false,
+    excluded_roots: Vec::new(),
+}));
+
+tokio::task_local! {
+    static CURRENT: Arc<WorkspaceContext>;
+}
+
+/// Run `f` inside a workspace / 在指定工作区内运行 `f`
+pub async fn scope<F: Future>(context: Arc<WorkspaceContext>, f: F) -> F::Output {
+    CURRENT.scope(context, f).await
+}
+
+/// Workspace of the current request, the default workspace outside requests
+/// 当前请求的工作区，请求之外为默认工作区
+///
+/// Excluded roots are only known inside a request, so background jobs must not rely on
+/// `path_visible` of the fallback. / 排除目录仅在请求内已知，后台任务不应依赖回退上下文的 `path_visible`。
+pub fn current() -> Arc<WorkspaceContext> {
+    CURRENT.try_with(|c| c.clone()).unwrap_or_else(|_| DEFAULT_CONTEXT.clone())
+}
+
+/// Site settings key in the current workspace / 当前工作区中的站点设置键
+pub fn settings_key(key: &str) -> String {
+    current().settings_key(key)
+}
+
+/// Read a site setting, preferring the current workspace's own value
+/// 读取站点设置，优先使用当前工作区自己的值
+pub async fn read_setting(db: &SqlitePool, key: &str) -> Result<Option<(String,)>, sqlx::Error> {
+    let scoped = settings_key(key);
+    if scoped != key {
+        let value = sqlx::query_as("SELECT value FROM site_settings WHERE key = ?")
+            .bind(&scoped)
+            .fetch_optional(db)
+            .await?;
+        if value.is_some() {
+            return Ok(value);
+        }
+    }
+    sqlx::query_as("SELECT value FROM site_settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(db)
+        .await
+}
+
+/// Whether a route manages the whole instance / 路由是否管理整个实例
+pub fn is_instance_route(path: &str) -> bool {
+    INSTANCE_ROUTES.iter().any(|prefix| {
+        path.starts_with(prefix)
+            && (prefix.ends_with('/') || path.len() == prefix.len() || path[prefix.len()..].starts_with('/'))
+    })
+}
+
+/// Split `/w/<slug>/rest` into slug and the remaining path / 拆分出工作区标识与剩余路径
+pub fn split_prefix(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix(PATH_PREFIX)?;
+    let (slug, rest) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    (!slug.is_empty()).then_some((slug, rest))
+}
+
+/// Host without port, lowercase / 去掉端口并转小写的主机名
+fn normalize_host(host: &str) -> String {
+    let host = match host.rsplit_once(':') {
+        Some((h, port)) if !port.contains(']') => h,
+        _ => host,
+    };
+    host.trim_end_matches('.').to_lowercase()
+}
+
+/// All workspaces other than default / 默认工作区以外的全部工作区
+pub struct WorkspaceRegistry {
+    workspaces: RwLock<Vec<Workspace>>,
+}
+
+impl WorkspaceRegistry {
+    pub fn new() -> Self {
+        Self {
+            workspaces: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Load workspaces from database / 从数据库加载工作区
+    pub async fn load_from_db(&self, db: &SqlitePool) -> Result<(), String> {
+        let rows: Vec<(String, String, String, String, String, bool)> = sqlx::query_as(
+            "SELECT id, name, slug, domains, root_path, enabled FROM workspaces ORDER BY created_at"
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let workspaces = rows.into_iter()
+            .map(|(id, name, slug, domains, root_path, enabled)| Workspace {
+                id,
+                name,
+                slug,
+                domains: serde_json::from_str(&domains).unwrap_or_default(),
+                root_path,
+                enabled,
+            })
+            .collect();
+        *self.workspaces.write() = workspaces;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<Workspace> {
+        self.workspaces.read().clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Workspace> {
+        if id == DEFAULT_WORKSPACE_ID {
+            return Some(Workspace::default_workspace());
+        }
+        self.workspaces.read().iter().find(|w| w.id == id).cloned()
+    }
+
+    /// Check a workspace against the others before saving / 保存前检查与其他工作区是否冲突
+    pub fn check_conflicts(&self, workspace: &Workspace) -> Result<(), String> {
+        for other in self.workspaces.read().iter().filter(|w| w.id != workspace.id) {
+            if other.slug == workspace.slug {
+                return Err(format!("slug '{}' is already used", workspace.slug));
+            }
+            if let Some(domain) = workspace.domains.iter().find(|d| other.domains.contains(d)) {
+                return Err(format!("domain '{}' is already used by '{}'", domain, other.name));
+            }
+            if other.root_path == workspace.root_path {
+                return Err(format!("root path '{}' is already used by '{}'", workspace.root_path, other.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert or replace a workspace in memory / 在内存中新增或替换工作区
+    pub fn upsert(&self, workspace: Workspace) {
+        let mut workspaces = self.workspaces.write();
+        match workspaces.iter_mut().find(|w| w.id == workspace.id) {
+            Some(existing) => *existing = workspace,
+            None => workspaces.push(workspace),
+        }
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.workspaces.write().retain(|w| w.id != id);
+    }
+
+    /// Build the request context of a workspace / 构建工作区的请求上下文
+    fn context(&self, workspace: Workspace, via_prefix: bool) -> WorkspaceContext {
+        let excluded_roots = self.workspaces.read().iter()
+            .filter(|w| w.id != workspace.id && w.root_path != workspace.root_path)
+            .filter(|w| is_sub_path(&workspace.root_path, &w.root_path))
+            .map(|w| w.root_path.clone())
+            .collect();
+        WorkspaceContext { workspace, via_prefix, excluded_roots }
+    }
+
+    /// Pick the workspace of a request; `None` for an unknown or disabled `/w/<slug>` prefix
+    /// 确定请求所属的工作区；`/w/<slug>` 前缀不存在或已禁用时返回 `None`
+    pub fn resolve(&self, host: Option<&str>, path: &str) -> Option<WorkspaceContext> {
+        if let Some((slug, _)) = split_prefix(path) {
+            let workspace = self.workspaces.read().iter()
+                .find(|w| w.enabled && w.slug == slug)
+                .cloned()?;
+            return Some(self.context(workspace, true));
+        }
+
+        if let Some(host) = host.map(normalize_host).filter(|h| !h.is_empty()) {
+            let workspace = self.workspaces.read().iter()
+                .find(|w| w.enabled && w.domains.contains(&host))
+                .cloned();
+            if let Some(workspace) = workspace {
+                return Some(self.context(workspace, false));
+            }
+        }
+        Some(self.context(Workspace::default_workspace(), false))
+    }
+}
+
+impl Default for WorkspaceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(id: &str, root: &str, domains: &[&str]) -> Workspace {
+        Workspace {
+            id: id.to_string(),
+            name: id.to_string(),
+            slug: id.to_string(),
+            domains: domains.iter().map(|d| d.to_string()).collect(),
+            root_path: root.to_string(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Workspace::default_workspace().validate().is_ok());
+        assert!(workspace("team-a", "/teams/a", &["a.example.com"]).validate().is_ok());
+        assert!(workspace("team-a", "/", &[]).validate().is_err());
+        assert!(workspace("Team A", "/teams/a", &[]).validate().is_err());
+        assert!(workspace("team-a", "/teams/a", &["a..example.com"]).validate().is_err());
+
+        let mut ws = workspace(" Team-A ", "teams//a/", &["A.Example.com."]);
+        ws.normalize();
+        assert_eq!(ws.slug, "team-a");
+        assert_eq!(ws.root_path, "/teams/a");
+        assert_eq!(ws.domains, vec!["a.example.com"]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let registry = WorkspaceRegistry::new();
+        registry.upsert(workspace("a", "/teams/a", &["a.example.com"]));
+        registry.upsert(workspace("b", "/teams/a/b", &[]));
+
+        let ctx = registry.resolve(Some("A.example.com:8180"), "/api/fs/list").unwrap();
+        assert_eq!(ctx.id(), "a");
+        assert!(!ctx.via_prefix);
+        assert_eq!(ctx.excluded_roots, vec!["/teams/a/b"]);
+
+        let ctx = registry.resolve(Some("files.example.com"), "/w/b/api/fs/list").unwrap();
+        assert_eq!(ctx.id(), "b");
+        assert_eq!(ctx.cookie_path(), "/w/b");
+        assert!(registry.resolve(None, "/w/missing").is_none());
+
+        let ctx = registry.resolve(Some("files.example.com"), "/").unwrap();
+        assert!(ctx.is_default());
+        assert_eq!(ctx.excluded_roots, vec!["/teams/a", "/teams/a/b"]);
+        assert_eq!(ctx.settings_key("site_title"), "site_title");
+    }
+
+    #[test]
+    fn test_visibility() {
+        let registry = WorkspaceRegistry::new();
+        registry.upsert(workspace("a", "/teams/a", &[]));
+        registry.upsert(workspace("b", "/teams/a/b", &[]));
+        let a = registry.resolve(None, "/w/a").unwrap();
+
+        assert!(a.path_visible("/teams/a/docs"));
+        assert!(!a.path_visible("/teams/ab"));
+        assert!(!a.path_visible("/teams/a/b/secret"));
+        assert!(a.mount_visible("/"));
+        assert!(a.mount_visible("/teams/a/docs"));
+        assert!(!a.mount_visible("/public"));
+        assert!(!a.mount_visible("/teams/a/b"));
+        assert_eq!(a.scope_root("/../docs"), "/teams/a/docs");
+        assert_eq!(a.settings_key("site_title"), "workspace:a:site_title");
+        assert_eq!(a.settings_key("proxy_max_speed"), "proxy_max_speed");
+    }
+
+    #[test]
+    fn test_instance_routes() {
+        assert!(is_instance_route("/api/admin/config"));
+        assert!(is_instance_route("/api/drivers"));
+        assert!(is_instance_route("/api/drivers/1/delete"));
+        assert!(is_instance_route("/dav/file.txt"));
+        assert!(!is_instance_route("/api/driversx"));
+        assert!(!is_instance_route("/api/fs/list"));
+        assert_eq!(split_prefix("/w/team-a/api/fs/list"), Some(("team-a", "/api/fs/list")));
+        assert_eq!(split_prefix("/w/team-a"), Some(("team-a", "/")));
+        assert_eq!(split_prefix("/w/"), None);
+    }
+}
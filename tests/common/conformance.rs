@@ -0,0 +1,90 @@
+//! StorageDriver conformance suite / StorageDriver 一致性测试
+//!
+//! `run` exercises list/read/write/rename/move/copy/delete on an empty driver root and panics
+//! with the failing step. Range reads are only checked when the driver declares
+//! `can_range_read`.
+//! `run` 在空的驱动根目录上依次验证列出、读取、写入、重命名、移动、复制与删除，失败时指出出错的步骤。
+//! 仅当驱动声明 `can_range_read` 时检查范围读取。
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use yaolist_backend::storage::{Entry, StorageDriver};
+
+/// Sorted entries of a directory / 目录内容（按名称排序）
+pub async fn list(driver: &dyn StorageDriver, path: &str) -> Vec<Entry> {
+    let mut entries = driver.list(path).await
+        .unwrap_or_else(|e| panic!("list {} failed: {}", path, e));
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+/// Names in a directory / 目录中的名称
+pub async fn names(driver: &dyn StorageDriver, path: &str) -> Vec<String> {
+    list(driver, path).await.into_iter().map(|e| e.name).collect()
+}
+
+/// Read a file, or a byte range of it / 读取文件或其中一段
+pub async fn read(driver: &dyn StorageDriver, path: &str, range: Option<std::ops::Range<u64>>) -> Vec<u8> {
+    let mut reader = driver.open_reader(path, range).await
+        .unwrap_or_else(|e| panic!("open_reader {} failed: {}", path, e));
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await
+        .unwrap_or_else(|e| panic!("read {} failed: {}", path, e));
+    buf
+}
+
+/// Write a file through `open_writer` / 通过 `open_writer` 写入文件
+pub async fn write(driver: &dyn StorageDriver, path: &str, data: &[u8]) {
+    let mut writer = driver.open_writer(path, Some(data.len() as u64), None).await
+        .unwrap_or_else(|e| panic!("open_writer {} failed: {}", path, e));
+    writer.write_all(data).await.unwrap_or_else(|e| panic!("write {} failed: {}", path, e));
+    writer.shutdown().await.unwrap_or_else(|e| panic!("shutdown {} failed: {}", path, e));
+}
+
+/// Run the whole suite / 运行完整测试
+pub async fn run(driver: &dyn StorageDriver) {
+    assert!(names(driver, "/").await.is_empty(), "root must start empty");
+
+    // Directories / 目录
+    driver.create_dir("/docs").await.expect("create_dir /docs");
+    driver.create_dir("/moved").await.expect("create_dir /moved");
+    let root = list(driver, "/").await;
+    assert_eq!(root.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), ["docs", "moved"]);
+    assert!(root.iter().all(|e| e.is_dir), "created entries must be directories");
+    assert_eq!(root[0].path, "/docs", "entry path must be absolute within the driver");
+
+    // Files via put / 通过 put 写入
+    driver.put("/docs/a.txt", bytes::Bytes::from_static(b"hello world"), None).await.expect("put");
+    let docs = list(driver, "/docs").await;
+    assert_eq!(docs.len(), 1);
+    assert_eq!((docs[0].name.as_str(), docs[0].is_dir, docs[0].size), ("a.txt", false, 11));
+    assert_eq!(read(driver, "/docs/a.txt", None).await, b"hello world");
+    if driver.capabilities().can_range_read {
+        assert_eq!(read(driver, "/docs/a.txt", Some(6..11)).await, b"world", "range read");
+    }
+
+    // Files via streaming writer, overwriting an existing one / 流式写入，并覆盖已有文件
+    write(driver, "/docs/b.txt", b"first").await;
+    write(driver, "/docs/b.txt", b"streamed").await;
+    assert_eq!(read(driver, "/docs/b.txt", None).await, b"streamed", "overwrite");
+
+    // Rename keeps the parent / 重命名保留父目录
+    driver.rename("/docs/a.txt", "c.txt").await.expect("rename");
+    assert_eq!(names(driver, "/docs").await, ["b.txt", "c.txt"]);
+    assert_eq!(read(driver, "/docs/c.txt", None).await, b"hello world");
+
+    // Move and copy take full destination paths / 移动与复制使用完整目标路径
+    driver.move_item("/docs/c.txt", "/moved/c.txt").await.expect("move_item");
+    driver.copy_item("/docs/b.txt", "/moved/b.txt").await.expect("copy_item");
+    assert_eq!(names(driver, "/docs").await, ["b.txt"]);
+    assert_eq!(names(driver, "/moved").await, ["b.txt", "c.txt"]);
+    assert_eq!(read(driver, "/moved/b.txt", None).await, b"streamed");
+
+    // Delete files and non-empty directories / 删除文件与非空目录
+    driver.delete("/docs/b.txt").await.expect("delete file");
+    assert!(names(driver, "/docs").await.is_empty());
+    driver.delete("/moved").await.expect("delete directory");
+    assert_eq!(names(driver, "/").await, ["docs"]);
+
+    // Missing files are errors, not empty reads / 不存在的文件应报错
+    assert!(driver.open_reader("/docs/missing.txt", None).await.is_err(), "missing file must error");
+}
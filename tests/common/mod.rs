@@ -0,0 +1,10 @@
+//! Shared driver test harness / 驱动测试公共工具
+//!
+//! `conformance` is the behaviour every `StorageDriver` must have; `webdav` mocks a WebDAV
+//! server so the HTTP driver can be tested without network access.
+//! `conformance` 是所有 `StorageDriver` 必须满足的行为；`webdav` 模拟 WebDAV 服务器，无需联网即可测试驱动。
+
+#![allow(dead_code)]
+
+pub mod conformance;
+pub mod webdav;
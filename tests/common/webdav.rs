@@ -0,0 +1,131 @@
+//! WebDAV server fixtures / WebDAV 服务器模拟
+//!
+//! Requests are only answered with the expected Basic credentials; anything else gets `401`,
+//! like a real server rejecting a bad login.
+//! 只有携带正确 Basic 凭据的请求才会得到响应，其余返回 `401`，与真实服务器拒绝错误登录一致。
+
+use base64::Engine;
+use serde_json::json;
+use wiremock::matchers::{body_bytes, header, method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use yaolist_backend::drivers::webdav::WebDavDriverFactory;
+use yaolist_backend::storage::{DriverFactory, StorageDriver};
+
+pub const USERNAME: &str = "alice";
+pub const PASSWORD: &str = "secret";
+/// Server path the driver is rooted at / 驱动根目录对应的服务器路径
+pub const ROOT: &str = "/dav";
+
+/// Mocked WebDAV server / 模拟的 WebDAV 服务器
+pub struct WebDavMock {
+    pub server: MockServer,
+}
+
+/// One PROPFIND result / 一条 PROPFIND 结果
+pub struct DavEntry<'a> {
+    pub name: &'a str,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+fn auth() -> String {
+    let credentials = format!("{}:{}", USERNAME, PASSWORD);
+    format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+}
+
+/// Server path of a driver path / 驱动路径对应的服务器路径
+fn server_path(driver_path: &str) -> String {
+    format!("{}/{}", ROOT, driver_path.trim_start_matches('/'))
+}
+
+fn response_xml(href: &str, is_dir: bool, size: u64) -> String {
+    let resource_type = if is_dir { "<D:collection/>" } else { "" };
+    format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>\
+         <D:resourcetype>{}</D:resourcetype><D:getcontentlength>{}</D:getcontentlength>\
+         <D:getlastmodified>Mon, 01 Jan 2024 00:00:00 GMT</D:getlastmodified>\
+         </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        href, resource_type, size
+    )
+}
+
+impl WebDavMock {
+    /// Start a server that rejects unauthenticated requests / 启动拒绝未认证请求的服务器
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        // Lowest priority: every request without valid credentials / 最低优先级：凭据无效的请求
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(401))
+            .with_priority(u8::MAX)
+            .mount(&server)
+            .await;
+        Self { server }
+    }
+
+    /// Driver pointed at the mock server / 指向模拟服务器的驱动
+    pub fn driver(&self, password: &str) -> Box<dyn StorageDriver> {
+        WebDavDriverFactory
+            .create_driver(json!({
+                "address": self.server.uri(),
+                "username": USERNAME,
+                "password": password,
+                "root_path": ROOT,
+            }))
+            .expect("create webdav driver")
+    }
+
+    /// Answer PROPFIND on a directory, including the directory itself like real servers do
+    /// 响应目录的 PROPFIND，与真实服务器一样包含目录自身
+    pub async fn mount_listing(&self, dir: &str, entries: &[DavEntry<'_>]) {
+        let dir_href = format!("{}/", server_path(dir).trim_end_matches('/'));
+        let mut body = String::from(r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#);
+        body.push_str(&response_xml(&dir_href, true, 0));
+        for entry in entries {
+            let href = format!("{}{}{}", dir_href, entry.name, if entry.is_dir { "/" } else { "" });
+            body.push_str(&response_xml(&href, entry.is_dir, entry.size));
+        }
+        body.push_str("</D:multistatus>");
+
+        // The driver may request the directory with or without the trailing slash / 驱动请求目录时可能不带结尾斜杠
+        Mock::given(method("PROPFIND"))
+            .and(path_regex(format!("^{}/?$", regex::escape(dir_href.trim_end_matches('/')))))
+            .and(header("Authorization", auth().as_str()))
+            .and(header("Depth", "1"))
+            .respond_with(ResponseTemplate::new(207).set_body_raw(body, "application/xml"))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Serve a file, honouring the single range the driver sends / 提供文件下载，支持驱动发送的单个范围
+    pub async fn mount_file(&self, file: &str, content: &'static [u8]) {
+        let file_path = server_path(file);
+        Mock::given(method("GET"))
+            .and(path(file_path.as_str()))
+            .and(header("Authorization", auth().as_str()))
+            .respond_with(move |req: &wiremock::Request| {
+                let range = req.headers.get("Range")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.strip_prefix("bytes="))
+                    .and_then(|v| v.split_once('-'))
+                    .and_then(|(s, e)| Some((s.parse::<usize>().ok()?, e.parse::<usize>().ok()?)));
+                match range {
+                    Some((start, end)) => ResponseTemplate::new(206).set_body_bytes(&content[start..=end]),
+                    None => ResponseTemplate::new(200).set_body_bytes(content),
+                }
+            })
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Expect exactly one upload of `content` / 期望恰好一次上传 `content`
+    pub async fn expect_upload(&self, file: &str, content: &'static [u8]) {
+        Mock::given(method("PUT"))
+            .and(path(server_path(file).as_str()))
+            .and(header("Authorization", auth().as_str()))
+            .and(body_bytes(content))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&self.server)
+            .await;
+    }
+}
@@ -0,0 +1,39 @@
+//! Drivers that can run the full conformance suite locally / 可在本地运行完整一致性测试的驱动
+
+mod common;
+
+use serde_json::json;
+use yaolist_backend::drivers::memory::MemoryDriverFactory;
+use yaolist_backend::storage::{DriverFactory, LocalDriverFactory};
+
+#[tokio::test]
+async fn local_driver_conforms() {
+    let root = tempfile::tempdir().unwrap();
+    let driver = LocalDriverFactory
+        .create_driver(json!({"root": root.path().to_string_lossy()}))
+        .unwrap();
+    common::conformance::run(driver.as_ref()).await;
+}
+
+#[tokio::test]
+async fn memory_driver_conforms() {
+    let driver = MemoryDriverFactory.create_driver(json!({"mode": "memory"})).unwrap();
+    common::conformance::run(driver.as_ref()).await;
+}
+
+#[tokio::test]
+async fn temp_dir_driver_conforms() {
+    let driver = MemoryDriverFactory.create_driver(json!({"mode": "temp_dir"})).unwrap();
+    common::conformance::run(driver.as_ref()).await;
+}
+
+#[tokio::test]
+async fn memory_driver_enforces_max_size() {
+    let driver = MemoryDriverFactory.create_driver(json!({"mode": "memory", "max_size": "1"})).unwrap();
+    let mb = bytes::Bytes::from(vec![0u8; 600 * 1024]);
+    driver.put("/a.bin", mb.clone(), None).await.unwrap();
+    assert!(driver.put("/b.bin", mb.clone(), None).await.is_err());
+    // Overwriting only counts the difference / 覆盖时只计算差值
+    driver.put("/a.bin", mb, None).await.unwrap();
+    assert_eq!(driver.get_space_info().await.unwrap().unwrap().used, 600 * 1024);
+}
@@ -0,0 +1,52 @@
+//! WebDAV driver against a mocked server / 针对模拟服务器的 WebDAV 驱动测试
+
+mod common;
+
+use common::conformance;
+use common::webdav::{DavEntry, WebDavMock, PASSWORD};
+
+#[tokio::test]
+async fn list_skips_the_directory_itself() {
+    let mock = WebDavMock::start().await;
+    mock.mount_listing("/", &[
+        DavEntry { name: "docs", is_dir: true, size: 0 },
+        DavEntry { name: "a b.txt", is_dir: false, size: 5 },
+    ]).await;
+    mock.mount_listing("/docs", &[DavEntry { name: "c.txt", is_dir: false, size: 3 }]).await;
+    let driver = mock.driver(PASSWORD);
+
+    let root = conformance::list(driver.as_ref(), "/").await;
+    let root: Vec<_> = root.iter().map(|e| (e.name.as_str(), e.path.as_str(), e.is_dir, e.size)).collect();
+    assert_eq!(root, [("a b.txt", "/a b.txt", false, 5), ("docs", "/docs", true, 0)]);
+    assert_eq!(conformance::names(driver.as_ref(), "/docs").await, ["c.txt"]);
+}
+
+#[tokio::test]
+async fn wrong_password_is_an_error() {
+    let mock = WebDavMock::start().await;
+    mock.mount_listing("/", &[]).await;
+    let driver = mock.driver("wrong");
+    let err = driver.list("/").await.unwrap_err();
+    assert!(err.to_string().contains("401"), "{}", err);
+}
+
+#[tokio::test]
+async fn download_full_and_range() {
+    let mock = WebDavMock::start().await;
+    mock.mount_file("/docs/a.txt", b"hello world").await;
+    let driver = mock.driver(PASSWORD);
+
+    assert_eq!(conformance::read(driver.as_ref(), "/docs/a.txt", None).await, b"hello world");
+    assert_eq!(conformance::read(driver.as_ref(), "/docs/a.txt", Some(6..11)).await, b"world");
+    assert!(driver.open_reader("/docs/missing.txt", None).await.is_err());
+}
+
+#[tokio::test]
+async fn upload_sends_the_body() {
+    let mock = WebDavMock::start().await;
+    mock.expect_upload("/docs/up.txt", b"uploaded").await;
+    let driver = mock.driver(PASSWORD);
+
+    driver.put("/docs/up.txt", bytes::Bytes::from_static(b"uploaded"), None).await.unwrap();
+    mock.server.verify().await;
+}